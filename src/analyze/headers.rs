@@ -0,0 +1,101 @@
+//! Signatures of encrypted volume headers
+//!
+//! - **BitLocker**: the volume boot sector carries the OEM id `-FVE-FS-` at
+//!   byte 3 (Windows Vista and later).
+//! - **LUKS**: the header starts with `LUKS\xba\xbe` followed by a big-endian
+//!   version (1 or 2).
+//! - **VeraCrypt / TrueCrypt**: the header is encrypted and has no magic,
+//!   so a volume is only suspected when the 64 KiB header area at the start
+//!   of the image or a partition is indistinguishable from random data and
+//!   has no boot signature.
+
+use serde::{Deserialize, Serialize};
+
+use super::{random_threshold, shannon_entropy};
+
+const SECTOR: usize = 512;
+const BITLOCKER_OEM_ID: &[u8] = b"-FVE-FS-";
+const LUKS_MAGIC: &[u8] = b"LUKS\xba\xbe";
+
+/// Size of the VeraCrypt/TrueCrypt header area (header plus reserved space)
+pub const VERACRYPT_HEADER_AREA: usize = 64 * 1024;
+
+/// Kind of encrypted volume a header belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VolumeHeaderKind {
+    BitLocker,
+    Luks,
+    /// VeraCrypt or TrueCrypt (suspected, see module docs)
+    VeraCrypt,
+}
+
+impl VolumeHeaderKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::BitLocker => "BitLocker",
+            Self::Luks => "LUKS",
+            Self::VeraCrypt => "VeraCrypt/TrueCrypt (suspected)",
+        }
+    }
+}
+
+/// An encrypted volume header found in the image
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VolumeHeader {
+    /// Byte offset of the header in the image
+    pub offset: u64,
+    pub kind: VolumeHeaderKind,
+    pub detail: String,
+}
+
+/// Find BitLocker and LUKS headers in the sectors of `data`, which starts
+/// at `offset` in the image
+pub fn detect_headers(data: &[u8], offset: u64) -> Vec<VolumeHeader> {
+    let mut headers = Vec::new();
+    for (i, sector) in data.chunks_exact(SECTOR).enumerate() {
+        let sector_offset = offset + (i * SECTOR) as u64;
+        if &sector[3..11] == BITLOCKER_OEM_ID {
+            headers.push(VolumeHeader {
+                offset: sector_offset,
+                kind: VolumeHeaderKind::BitLocker,
+                detail: "volume boot sector (-FVE-FS-)".to_string(),
+            });
+        } else if sector.starts_with(LUKS_MAGIC) {
+            let version = u16::from_be_bytes([sector[6], sector[7]]);
+            // LUKS1 names the cipher in the header; LUKS2 keeps it in JSON
+            let detail = match version {
+                1 => {
+                    let cipher = ascii_field(&sector[8..40]);
+                    let mode = ascii_field(&sector[40..72]);
+                    format!("LUKS1, {}-{}", cipher, mode)
+                }
+                _ => format!("LUKS{}", version),
+            };
+            headers.push(VolumeHeader {
+                offset: sector_offset,
+                kind: VolumeHeaderKind::Luks,
+                detail,
+            });
+        }
+    }
+    headers
+}
+
+/// Whether the header area at the start of a volume looks like a
+/// VeraCrypt/TrueCrypt header: random-looking with no boot signature
+pub fn looks_like_veracrypt(header_area: &[u8]) -> bool {
+    header_area.len() >= VERACRYPT_HEADER_AREA
+        && header_area[510..512] != [0x55, 0xAA]
+        && shannon_entropy(&header_area[..VERACRYPT_HEADER_AREA])
+            >= random_threshold(VERACRYPT_HEADER_AREA)
+}
+
+/// NUL-padded ASCII header field
+fn ascii_field(field: &[u8]) -> String {
+    field
+        .iter()
+        .take_while(|&&b| b != 0)
+        .map(|&b| b as char)
+        .collect()
+}
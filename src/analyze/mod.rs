@@ -0,0 +1,457 @@
+//! Entropy analysis - where an image is blank, plain data, compressed or
+//! encrypted
+//!
+//! `diamond-drill analyze` measures the Shannon entropy of every block of an
+//! image (or an even sample of blocks), merges neighbouring blocks of the
+//! same class into regions and looks for encrypted volume headers. Carving
+//! finds nothing inside an encrypted region, so the report tells the user
+//! why a range came back empty instead of leaving them to guess.
+
+pub mod headers;
+
+pub use headers::{detect_headers, looks_like_veracrypt, VolumeHeader, VolumeHeaderKind};
+
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::diskimage::{self, ImageFormat};
+use crate::throttle::Throttle;
+
+/// Default analysis block size (1 MiB)
+pub const DEFAULT_BLOCK_SIZE: usize = 1024 * 1024;
+
+/// Smallest block size that still gives a meaningful entropy estimate
+pub const MIN_BLOCK_SIZE: usize = 4096;
+
+/// Blocks below this entropy are treated as blank (zero or fill patterns)
+const BLANK_BELOW: f64 = 0.5;
+
+/// Blocks at or above this entropy are compressed or encrypted
+const HIGH_ENTROPY: f64 = 7.5;
+
+/// Shannon entropy of `data` in bits per byte (0.0 - 8.0)
+pub fn shannon_entropy(data: &[u8]) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+    let mut counts = [0u64; 256];
+    for &b in data {
+        counts[b as usize] += 1;
+    }
+    let len = data.len() as f64;
+    counts
+        .iter()
+        .filter(|&&c| c > 0)
+        .map(|&c| {
+            let p = c as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Entropy above which a block of `len` bytes is indistinguishable from
+/// random data
+///
+/// A finite sample of random bytes measures slightly below 8 bits (by about
+/// `255 / (2 * len * ln 2)`), so the cut-off allows twice that shortfall.
+/// Compressed data sits just under it because of headers and block
+/// structure; ciphertext does not.
+pub fn random_threshold(len: usize) -> f64 {
+    8.0 - 2.0 * 255.0 / (2.0 * len.max(1) as f64 * std::f64::consts::LN_2)
+}
+
+/// What a block most likely contains, judged by its entropy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RegionClass {
+    /// Zeroed or wiped space
+    Blank,
+    /// File system structures, text, uncompressed media
+    Data,
+    /// Compressed files or archives
+    Compressed,
+    /// Encrypted volume or random wipe
+    Encrypted,
+}
+
+impl RegionClass {
+    /// Classify a block of `len` bytes with the given entropy
+    pub fn classify(entropy: f64, len: usize) -> Self {
+        if entropy < BLANK_BELOW {
+            Self::Blank
+        } else if entropy < HIGH_ENTROPY {
+            Self::Data
+        } else if entropy < random_threshold(len) {
+            Self::Compressed
+        } else {
+            Self::Encrypted
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Blank => "blank",
+            Self::Data => "data",
+            Self::Compressed => "compressed",
+            Self::Encrypted => "encrypted",
+        }
+    }
+
+    /// Character used for this class in a text heat strip
+    pub fn glyph(&self) -> char {
+        match self {
+            Self::Blank => ' ',
+            Self::Data => '░',
+            Self::Compressed => '▒',
+            Self::Encrypted => '█',
+        }
+    }
+
+    /// Every class, from lowest to highest entropy
+    pub const ALL: [Self; 4] = [Self::Blank, Self::Data, Self::Compressed, Self::Encrypted];
+}
+
+/// Options for [`analyze_image`]
+#[derive(Debug, Clone)]
+pub struct EntropyOptions {
+    /// Bytes per entropy block
+    pub block_size: usize,
+    /// Only read this many evenly spaced blocks (`None` = every block)
+    pub max_blocks: Option<u64>,
+    /// Limit read speed (bytes per second)
+    pub max_bytes_per_sec: Option<u64>,
+}
+
+impl Default for EntropyOptions {
+    fn default() -> Self {
+        Self {
+            block_size: DEFAULT_BLOCK_SIZE,
+            max_blocks: None,
+            max_bytes_per_sec: None,
+        }
+    }
+}
+
+/// Run of neighbouring blocks with the same class
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EntropyRegion {
+    /// Byte offset of the first block
+    pub start: u64,
+    /// Byte offset just past the last block
+    pub end: u64,
+    pub class: RegionClass,
+    /// Mean entropy of the blocks in the region
+    pub mean_entropy: f64,
+}
+
+impl EntropyRegion {
+    pub fn len(&self) -> u64 {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+/// One cell of a heat strip
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HeatCell {
+    /// Most common class among the blocks in the cell
+    pub class: RegionClass,
+    /// Mean entropy of the blocks in the cell
+    pub entropy: f64,
+}
+
+/// Result of [`analyze_image`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntropyReport {
+    pub source: PathBuf,
+    pub format: ImageFormat,
+    pub image_size: u64,
+    pub block_size: usize,
+    /// Bytes of the image each entry in `blocks` stands for (larger than
+    /// `block_size` when the image was sampled)
+    pub stride: u64,
+    /// Whether only a sample of blocks was read
+    pub sampled: bool,
+    /// Entropy of each analysed block, in image order
+    #[serde(skip)]
+    pub blocks: Vec<f32>,
+    pub regions: Vec<EntropyRegion>,
+    pub headers: Vec<VolumeHeader>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl EntropyReport {
+    /// Bytes of the image judged to be of `class`
+    pub fn class_bytes(&self, class: RegionClass) -> u64 {
+        self.regions
+            .iter()
+            .filter(|r| r.class == class)
+            .map(EntropyRegion::len)
+            .sum()
+    }
+
+    /// Whether any part of the image looks encrypted
+    pub fn has_encrypted(&self) -> bool {
+        !self.headers.is_empty() || self.class_bytes(RegionClass::Encrypted) > 0
+    }
+
+    /// Squeeze the blocks into `width` cells for display
+    pub fn heat_strip(&self, width: usize) -> Vec<HeatCell> {
+        if self.blocks.is_empty() || width == 0 {
+            return Vec::new();
+        }
+        let n = self.blocks.len();
+        let cells = width.min(n);
+        let len = self.block_size.min(self.image_size as usize);
+        (0..cells)
+            .map(|cell| {
+                let from = cell * n / cells;
+                let to = ((cell + 1) * n / cells).max(from + 1);
+                let slice = &self.blocks[from..to];
+                let mut counts = [0usize; 4];
+                for &e in slice {
+                    let class = RegionClass::classify(e as f64, len);
+                    counts[class as usize] += 1;
+                }
+                let class = RegionClass::ALL
+                    .into_iter()
+                    .max_by_key(|&c| (counts[c as usize], c as usize))
+                    .unwrap_or(RegionClass::Data);
+                let entropy = slice.iter().map(|&e| e as f64).sum::<f64>() / slice.len() as f64;
+                HeatCell { class, entropy }
+            })
+            .collect()
+    }
+
+    /// Heat strip as text, one glyph per cell
+    pub fn strip_chars(&self, width: usize) -> String {
+        self.heat_strip(width)
+            .iter()
+            .map(|cell| cell.class.glyph())
+            .collect()
+    }
+}
+
+/// Measure the entropy of an image block by block
+///
+/// `progress` is called with (blocks done, blocks total) after every block.
+pub fn analyze_image(
+    path: &Path,
+    options: &EntropyOptions,
+    mut progress: impl FnMut(u64, u64),
+) -> Result<EntropyReport> {
+    let (mut reader, format, image_size) = diskimage::open_image(path)?;
+    let block_size = options.block_size.max(MIN_BLOCK_SIZE);
+    let total_blocks = image_size.div_ceil(block_size as u64);
+    let wanted = options
+        .max_blocks
+        .filter(|&m| m > 0 && m < total_blocks)
+        .unwrap_or(total_blocks);
+    let sampled = wanted < total_blocks;
+    let stride = if wanted == 0 {
+        block_size as u64
+    } else {
+        total_blocks.div_ceil(wanted) * block_size as u64
+    };
+
+    let throttle = Throttle::new(options.max_bytes_per_sec);
+    let mut buf = vec![0u8; block_size];
+    let mut blocks = Vec::with_capacity(wanted as usize);
+    let mut headers = Vec::new();
+
+    for i in 0..wanted {
+        let offset = if sampled {
+            (i * total_blocks / wanted) * block_size as u64
+        } else {
+            i * block_size as u64
+        };
+        let len = read_block(&mut reader, offset, &mut buf)
+            .with_context(|| format!("Failed to read {} at offset {}", path.display(), offset))?;
+        throttle.consume(len as u64);
+        blocks.push(shannon_entropy(&buf[..len]) as f32);
+        headers.extend(detect_headers(&buf[..len], offset));
+        progress(i + 1, wanted);
+    }
+
+    // VeraCrypt has no magic: check the header area of the image and of
+    // every partition that isn't already a known volume
+    let mut volume_starts = vec![0u64];
+    volume_starts.extend(
+        diskimage::read_partitions(&mut reader)
+            .unwrap_or_default()
+            .iter()
+            .map(|p| p.start),
+    );
+    let mut area = vec![0u8; headers::VERACRYPT_HEADER_AREA];
+    for start in volume_starts {
+        if headers.iter().any(|h| h.offset == start) {
+            continue;
+        }
+        let len = read_block(&mut reader, start, &mut area)?;
+        if looks_like_veracrypt(&area[..len]) {
+            headers.push(VolumeHeader {
+                offset: start,
+                kind: VolumeHeaderKind::VeraCrypt,
+                detail: "random header area, no boot signature".to_string(),
+            });
+        }
+    }
+    headers.sort_by_key(|h| h.offset);
+
+    let regions = merge_regions(&blocks, stride, block_size, image_size);
+    Ok(EntropyReport {
+        source: path.to_path_buf(),
+        format,
+        image_size,
+        block_size,
+        stride,
+        sampled,
+        blocks,
+        regions,
+        headers,
+        created_at: Utc::now(),
+    })
+}
+
+/// Read up to `buf.len()` bytes at `offset`, stopping early at the end of
+/// the image
+fn read_block<R: Read + Seek + ?Sized>(
+    reader: &mut R,
+    offset: u64,
+    buf: &mut [u8],
+) -> Result<usize> {
+    reader.seek(SeekFrom::Start(offset))?;
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+/// Merge neighbouring blocks of the same class into regions
+fn merge_regions(
+    blocks: &[f32],
+    stride: u64,
+    block_size: usize,
+    image_size: u64,
+) -> Vec<EntropyRegion> {
+    let mut regions: Vec<EntropyRegion> = Vec::new();
+    let mut count = 0usize;
+    for (i, &e) in blocks.iter().enumerate() {
+        let start = i as u64 * stride;
+        let end = (start + stride).min(image_size);
+        let len = (end - start).min(block_size as u64) as usize;
+        let class = RegionClass::classify(e as f64, len);
+        match regions.last_mut() {
+            Some(last) if last.class == class => {
+                last.end = end;
+                last.mean_entropy += (e as f64 - last.mean_entropy) / (count + 1) as f64;
+                count += 1;
+            }
+            _ => {
+                regions.push(EntropyRegion {
+                    start,
+                    end,
+                    class,
+                    mean_entropy: e as f64,
+                });
+                count = 1;
+            }
+        }
+    }
+    regions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    /// xorshift stream standing in for ciphertext
+    fn random_bytes(len: usize, mut state: u64) -> Vec<u8> {
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                (state >> 24) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_entropy_regions_and_headers() {
+        assert_eq!(shannon_entropy(&[0u8; 4096]), 0.0);
+        assert!((shannon_entropy(&(0..=255u8).collect::<Vec<_>>()) - 8.0).abs() < 1e-9);
+
+        let block = MIN_BLOCK_SIZE;
+        let mut image = vec![0u8; block * 2];
+        image.extend((0..block * 2).map(|i| b"plain text log line\n"[i % 20]));
+        let mut luks = random_bytes(block * 4, 0x9E37_79B9_7F4A_7C15);
+        luks[..6].copy_from_slice(b"LUKS\xba\xbe");
+        luks[6..8].copy_from_slice(&2u16.to_be_bytes());
+        image.extend(&luks);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("disk.img");
+        std::fs::File::create(&path)
+            .unwrap()
+            .write_all(&image)
+            .unwrap();
+
+        let options = EntropyOptions {
+            block_size: block,
+            ..Default::default()
+        };
+        let mut calls = 0;
+        let report = analyze_image(&path, &options, |_, _| calls += 1).unwrap();
+        assert_eq!(calls, 8);
+        assert!(!report.sampled);
+
+        let classes: Vec<_> = report.regions.iter().map(|r| r.class).collect();
+        assert_eq!(
+            classes,
+            [
+                RegionClass::Blank,
+                RegionClass::Data,
+                RegionClass::Encrypted
+            ]
+        );
+        assert_eq!(report.regions[2].start, (block * 4) as u64);
+        assert_eq!(
+            report.class_bytes(RegionClass::Encrypted),
+            (block * 4) as u64
+        );
+
+        assert_eq!(report.headers.len(), 1);
+        assert_eq!(report.headers[0].kind, VolumeHeaderKind::Luks);
+        assert_eq!(report.headers[0].offset, (block * 4) as u64);
+        assert!(report.has_encrypted());
+
+        assert_eq!(report.strip_chars(4), " ░██");
+
+        // Sampling every other block still sees all three classes
+        let sampled = analyze_image(
+            &path,
+            &EntropyOptions {
+                max_blocks: Some(4),
+                ..options
+            },
+            |_, _| {},
+        )
+        .unwrap();
+        assert!(sampled.sampled);
+        assert_eq!(sampled.blocks.len(), 4);
+        assert_eq!(sampled.regions.len(), 3);
+    }
+}
@@ -0,0 +1,272 @@
+//! Reading Chromium and Firefox databases
+
+use anyhow::Result;
+
+use super::{ArtifactEvent, BrowserDatabase};
+
+/// Read the timeline events out of one database
+#[cfg(feature = "sqlite")]
+pub(super) fn read_events(db: &BrowserDatabase) -> Result<Vec<ArtifactEvent>> {
+    use anyhow::Context;
+
+    use super::{Browser, DatabaseKind, EventKind};
+
+    // Work on a copy: opening the original would create journal files next
+    // to it, and the WAL beside it holds writes not yet in the main file
+    let scratch = Scratch::new()?;
+    let copy = scratch.0.join("db.sqlite");
+    std::fs::copy(&db.path, &copy)
+        .with_context(|| format!("Failed to copy {}", db.path.display()))?;
+    let mut wal = db.path.as_os_str().to_owned();
+    wal.push("-wal");
+    if std::path::Path::new(&wal).exists() {
+        std::fs::copy(&wal, scratch.0.join("db.sqlite-wal"))
+            .with_context(|| format!("Failed to copy {}", wal.to_string_lossy()))?;
+    }
+    let conn = rusqlite::Connection::open(&copy)
+        .with_context(|| format!("Failed to open {}", db.path.display()))?;
+
+    let event = |kind, time, url: String, detail: String| ArtifactEvent {
+        time,
+        browser: db.browser,
+        profile: db.profile.clone(),
+        kind,
+        url,
+        detail,
+        database: db.path.clone(),
+    };
+    let mut events = Vec::new();
+    match (db.browser, db.kind) {
+        (Browser::Chrome | Browser::Edge, DatabaseKind::History) => {
+            query(
+                &conn,
+                "SELECT u.url, u.title, v.visit_time FROM visits v JOIN urls u ON u.id = v.url",
+                |r| {
+                    events.push(event(
+                        EventKind::Visit,
+                        webkit_to_utc(r.get(2)?),
+                        r.get(0)?,
+                        r.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                    ));
+                    Ok(())
+                },
+            )?;
+            query(
+                &conn,
+                "SELECT (SELECT c.url FROM downloads_url_chains c WHERE c.id = d.id \
+                 ORDER BY c.chain_index DESC LIMIT 1), d.target_path, d.start_time \
+                 FROM downloads d",
+                |r| {
+                    events.push(event(
+                        EventKind::Download,
+                        webkit_to_utc(r.get(2)?),
+                        r.get::<_, Option<String>>(0)?.unwrap_or_default(),
+                        r.get(1)?,
+                    ));
+                    Ok(())
+                },
+            )?;
+        }
+        (Browser::Chrome | Browser::Edge, DatabaseKind::Cookies) => {
+            query(
+                &conn,
+                "SELECT host_key, name, creation_utc FROM cookies",
+                |r| {
+                    events.push(event(
+                        EventKind::Cookie,
+                        webkit_to_utc(r.get(2)?),
+                        r.get(0)?,
+                        r.get(1)?,
+                    ));
+                    Ok(())
+                },
+            )?;
+        }
+        (Browser::Firefox, DatabaseKind::History) => {
+            query(
+                &conn,
+                "SELECT p.url, p.title, v.visit_date FROM moz_historyvisits v \
+                 JOIN moz_places p ON p.id = v.place_id",
+                |r| {
+                    events.push(event(
+                        EventKind::Visit,
+                        prtime_to_utc(r.get(2)?),
+                        r.get(0)?,
+                        r.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                    ));
+                    Ok(())
+                },
+            )?;
+            // Downloads are annotations on the page they came from
+            query(
+                &conn,
+                "SELECT p.url, a.content, a.dateAdded FROM moz_annos a \
+                 JOIN moz_anno_attributes n ON n.id = a.anno_attribute_id \
+                 JOIN moz_places p ON p.id = a.place_id \
+                 WHERE n.name = 'downloads/destinationFileURI'",
+                |r| {
+                    let target: String = r.get(1)?;
+                    events.push(event(
+                        EventKind::Download,
+                        prtime_to_utc(r.get(2)?),
+                        r.get(0)?,
+                        target
+                            .strip_prefix("file://")
+                            .unwrap_or(&target)
+                            .to_string(),
+                    ));
+                    Ok(())
+                },
+            )?;
+        }
+        (Browser::Firefox, DatabaseKind::Cookies) => {
+            query(
+                &conn,
+                "SELECT host, name, creationTime FROM moz_cookies",
+                |r| {
+                    events.push(event(
+                        EventKind::Cookie,
+                        prtime_to_utc(r.get(2)?),
+                        r.get(0)?,
+                        r.get(1)?,
+                    ));
+                    Ok(())
+                },
+            )?;
+        }
+    }
+    Ok(events)
+}
+
+/// Scratch directory removed when dropped
+#[cfg(feature = "sqlite")]
+struct Scratch(std::path::PathBuf);
+
+#[cfg(feature = "sqlite")]
+impl Scratch {
+    fn new() -> Result<Self> {
+        use anyhow::Context;
+        let dir = std::env::temp_dir().join(format!("diamond-drill-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+        Ok(Self(dir))
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Databases can't be read without SQLite support
+#[cfg(not(feature = "sqlite"))]
+pub(super) fn read_events(_db: &BrowserDatabase) -> Result<Vec<ArtifactEvent>> {
+    anyhow::bail!("SQLite support not compiled in; rebuild with --features sqlite")
+}
+
+/// Run `sql` and hand each row to `row`. Tables missing from older or
+/// newer schema versions are skipped rather than failing the database.
+#[cfg(feature = "sqlite")]
+fn query(
+    conn: &rusqlite::Connection,
+    sql: &str,
+    mut row: impl FnMut(&rusqlite::Row<'_>) -> rusqlite::Result<()>,
+) -> Result<()> {
+    let mut stmt = match conn.prepare(sql) {
+        Ok(stmt) => stmt,
+        Err(e) if e.to_string().contains("no such") => {
+            tracing::debug!("Skipping query ({}): {}", e, sql);
+            return Ok(());
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let mut rows = stmt.query([])?;
+    while let Some(r) = rows.next()? {
+        row(r)?;
+    }
+    Ok(())
+}
+
+/// Microseconds since 1601-01-01 (Chromium's WebKit timestamps)
+#[cfg(feature = "sqlite")]
+fn webkit_to_utc(micros: i64) -> Option<chrono::DateTime<chrono::Utc>> {
+    crate::core::filetime_to_utc(u64::try_from(micros).ok()?.checked_mul(10)?)
+}
+
+/// Microseconds since the Unix epoch (Firefox's PRTime)
+#[cfg(feature = "sqlite")]
+fn prtime_to_utc(micros: i64) -> Option<chrono::DateTime<chrono::Utc>> {
+    (micros > 0)
+        .then(|| chrono::DateTime::from_timestamp_micros(micros))
+        .flatten()
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::super::{extract, locate_in_dir, EventKind};
+
+    #[test]
+    fn test_chrome_and_firefox_timeline() {
+        let dir = tempfile::tempdir().unwrap();
+        let chrome = dir.path().join("Chrome/User Data/Default");
+        let firefox = dir.path().join("Firefox/Profiles/x1.default");
+        std::fs::create_dir_all(&chrome).unwrap();
+        std::fs::create_dir_all(&firefox).unwrap();
+
+        let c = rusqlite::Connection::open(chrome.join("History")).unwrap();
+        c.execute_batch(
+            "CREATE TABLE urls (id INTEGER PRIMARY KEY, url TEXT, title TEXT);
+             CREATE TABLE visits (id INTEGER PRIMARY KEY, url INTEGER, visit_time INTEGER);
+             CREATE TABLE downloads (id INTEGER PRIMARY KEY, target_path TEXT, start_time INTEGER);
+             CREATE TABLE downloads_url_chains (id INTEGER, chain_index INTEGER, url TEXT);
+             INSERT INTO urls VALUES (1, 'https://example.com/', 'Example');
+             INSERT INTO visits VALUES (1, 1, 13350211200000000);
+             INSERT INTO downloads VALUES (1, 'C:\\Users\\ana\\report.pdf', 13350211260000000);
+             INSERT INTO downloads_url_chains VALUES (1, 0, 'https://example.com/report.pdf');",
+        )
+        .unwrap();
+        drop(c);
+
+        let f = rusqlite::Connection::open(firefox.join("places.sqlite")).unwrap();
+        f.execute_batch(
+            "CREATE TABLE moz_places (id INTEGER PRIMARY KEY, url TEXT, title TEXT);
+             CREATE TABLE moz_historyvisits (id INTEGER PRIMARY KEY, place_id INTEGER, visit_date INTEGER);
+             INSERT INTO moz_places VALUES (1, 'https://mozilla.org/', NULL);
+             INSERT INTO moz_historyvisits VALUES (1, 1, 1705737000000000);",
+        )
+        .unwrap();
+        drop(f);
+
+        let report = extract(dir.path(), locate_in_dir(dir.path()));
+        assert_eq!(report.databases.len(), 2);
+        assert!(report.databases.iter().all(|d| d.error.is_none()));
+        let rows: Vec<(EventKind, &str, String)> = report
+            .events
+            .iter()
+            .map(|e| (e.kind, e.url.as_str(), e.time.unwrap().to_rfc3339()))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                (
+                    EventKind::Visit,
+                    "https://mozilla.org/",
+                    "2024-01-20T07:50:00+00:00".to_string()
+                ),
+                (
+                    EventKind::Visit,
+                    "https://example.com/",
+                    "2024-01-20T08:00:00+00:00".to_string()
+                ),
+                (
+                    EventKind::Download,
+                    "https://example.com/report.pdf",
+                    "2024-01-20T08:01:00+00:00".to_string()
+                ),
+            ]
+        );
+        assert_eq!(report.events[2].detail, "C:\\Users\\ana\\report.pdf");
+    }
+}
@@ -0,0 +1,264 @@
+//! Browser artifacts - history, downloads and cookies
+//!
+//! Finds the SQLite databases Chrome, Edge and Firefox keep in each
+//! profile, reads visits, downloads and cookies out of them and merges
+//! everything into one timeline. Databases are copied (with their `-wal`
+//! journal) to a scratch directory before opening, so the source is never
+//! touched and uncheckpointed writes are still seen.
+//!
+//! Parsing needs the `sqlite` feature; without it databases are located
+//! and listed but not read.
+
+mod browser;
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::core::FileEntry;
+
+/// Every SQLite 3 database starts with this
+const SQLITE_MAGIC: &[u8; 16] = b"SQLite format 3\0";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Browser {
+    Chrome,
+    Edge,
+    Firefox,
+}
+
+impl std::fmt::Display for Browser {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(match self {
+            Self::Chrome => "Chrome",
+            Self::Edge => "Edge",
+            Self::Firefox => "Firefox",
+        })
+    }
+}
+
+/// What a database holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DatabaseKind {
+    /// Visits and downloads
+    History,
+    Cookies,
+}
+
+/// A browser database found in the source
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BrowserDatabase {
+    pub path: PathBuf,
+    pub browser: Browser,
+    pub kind: DatabaseKind,
+    /// Profile directory name (`Default`, `Profile 1`, `abcd1234.default-release`)
+    pub profile: String,
+    /// Timeline events read from it
+    pub events: usize,
+    /// Why it could not be read, if it couldn't
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventKind {
+    Visit,
+    Download,
+    Cookie,
+}
+
+impl std::fmt::Display for EventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(match self {
+            Self::Visit => "visit",
+            Self::Download => "download",
+            Self::Cookie => "cookie",
+        })
+    }
+}
+
+/// One row of the timeline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArtifactEvent {
+    pub time: Option<DateTime<Utc>>,
+    pub browser: Browser,
+    pub profile: String,
+    pub kind: EventKind,
+    /// Page visited, file downloaded from, or cookie host
+    pub url: String,
+    /// Page title, download target path, or cookie name
+    pub detail: String,
+    /// Database the event came from
+    pub database: PathBuf,
+}
+
+/// Everything found in a source
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArtifactReport {
+    pub source: PathBuf,
+    pub generated_at: DateTime<Utc>,
+    pub databases: Vec<BrowserDatabase>,
+    /// Oldest first; events without a time come last
+    pub events: Vec<ArtifactEvent>,
+}
+
+impl ArtifactReport {
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        use anyhow::Context;
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json)
+            .with_context(|| format!("Failed to write report {}", path.display()))
+    }
+}
+
+/// Pick out the browser databases among indexed files
+pub fn locate_in_entries(entries: &[FileEntry]) -> Vec<BrowserDatabase> {
+    locate(entries.iter().map(|e| e.path.as_path()))
+}
+
+/// Walk `root` for browser databases (for sources that haven't been indexed)
+pub fn locate_in_dir(root: &Path) -> Vec<BrowserDatabase> {
+    let files: Vec<PathBuf> = walkdir::WalkDir::new(root)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .collect();
+    locate(files.iter().map(PathBuf::as_path))
+}
+
+fn locate<'a>(paths: impl Iterator<Item = &'a Path>) -> Vec<BrowserDatabase> {
+    let mut found: Vec<BrowserDatabase> = paths
+        .filter_map(|path| {
+            let (browser, kind, profile) = classify(path)?;
+            is_sqlite(path).then(|| BrowserDatabase {
+                path: path.to_path_buf(),
+                browser,
+                kind,
+                profile,
+                events: 0,
+                error: None,
+            })
+        })
+        .collect();
+    found.sort_by(|a, b| a.path.cmp(&b.path));
+    found
+}
+
+/// Browser, kind and profile of a database, judged by its name and where
+/// it sits
+fn classify(path: &Path) -> Option<(Browser, DatabaseKind, String)> {
+    let name = path.file_name()?.to_str()?;
+    let mut parent = path.parent()?;
+    let (browser, kind) = match name {
+        "places.sqlite" => (Browser::Firefox, DatabaseKind::History),
+        "cookies.sqlite" => (Browser::Firefox, DatabaseKind::Cookies),
+        "History" | "Cookies" => {
+            // Newer Chromium keeps cookies in <profile>/Network/
+            if parent.file_name().is_some_and(|n| n == "Network") {
+                parent = parent.parent()?;
+            }
+            let lower = path.to_string_lossy().to_lowercase();
+            if !lower.contains("user data") {
+                return None;
+            }
+            let browser = if lower.contains("edge") {
+                Browser::Edge
+            } else {
+                Browser::Chrome
+            };
+            let kind = if name == "History" {
+                DatabaseKind::History
+            } else {
+                DatabaseKind::Cookies
+            };
+            (browser, kind)
+        }
+        _ => return None,
+    };
+    let profile = parent.file_name()?.to_string_lossy().into_owned();
+    Some((browser, kind, profile))
+}
+
+fn is_sqlite(path: &Path) -> bool {
+    let mut magic = [0u8; 16];
+    std::fs::File::open(path)
+        .and_then(|mut f| f.read_exact(&mut magic))
+        .is_ok()
+        && &magic == SQLITE_MAGIC
+}
+
+/// Read every database into a timeline. Databases that fail keep their
+/// error and contribute no events.
+pub fn extract(source: &Path, mut databases: Vec<BrowserDatabase>) -> ArtifactReport {
+    let mut events = Vec::new();
+    for db in &mut databases {
+        match browser::read_events(db) {
+            Ok(mut found) => {
+                db.events = found.len();
+                events.append(&mut found);
+            }
+            Err(e) => {
+                tracing::warn!("Could not read {}: {:#}", db.path.display(), e);
+                db.error = Some(format!("{:#}", e));
+            }
+        }
+    }
+    // None sorts first, so key on (missing, time)
+    events.sort_by_key(|e| (e.time.is_none(), e.time));
+    ArtifactReport {
+        source: source.to_path_buf(),
+        generated_at: Utc::now(),
+        databases,
+        events,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locate_browser_databases() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = [
+            "Users/ana/AppData/Local/Google/Chrome/User Data/Default/History",
+            "Users/ana/AppData/Local/Google/Chrome/User Data/Default/Network/Cookies",
+            "Users/ana/AppData/Local/Microsoft/Edge/User Data/Profile 1/History",
+            "Users/ana/AppData/Roaming/Mozilla/Firefox/Profiles/x1.default/places.sqlite",
+            "Users/ana/Documents/History",
+        ];
+        for f in files {
+            let path = dir.path().join(f);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            let mut data = SQLITE_MAGIC.to_vec();
+            data.resize(512, 0);
+            std::fs::write(path, data).unwrap();
+        }
+        // Right name, not a database
+        let fake = dir
+            .path()
+            .join("Users/ana/AppData/Roaming/Mozilla/Firefox/Profiles/x1.default/cookies.sqlite");
+        std::fs::write(fake, b"not sqlite").unwrap();
+
+        let found = locate_in_dir(dir.path());
+        let summary: Vec<(Browser, DatabaseKind, &str)> = found
+            .iter()
+            .map(|d| (d.browser, d.kind, d.profile.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (Browser::Chrome, DatabaseKind::History, "Default"),
+                (Browser::Chrome, DatabaseKind::Cookies, "Default"),
+                (Browser::Edge, DatabaseKind::History, "Profile 1"),
+                (Browser::Firefox, DatabaseKind::History, "x1.default"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,486 @@
+//! Audit - a structured operation log for every run
+//!
+//! Each command writes one JSON Lines file under `<data dir>/logs`, so what
+//! was done to a drive can be reconstructed after the fact:
+//!
+//! - a `start` record with the command, its full argument list, the version
+//!   and the working directory
+//! - an `event` record for each per-file outcome published on the
+//!   [`events`](crate::events) bus: exports finished or skipped, hashes,
+//!   bad sectors, heals, the final swarm counts (progress ticks are left out)
+//! - an `end` record with the duration, the outcome and any error
+//!
+//! A run killed before it finished has no `end` record and is listed as
+//! incomplete. `diamond-drill logs` lists runs and pretty-prints one.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::broadcast::error::TryRecvError;
+
+use crate::carve::CarveProgress;
+use crate::events::{self, Event};
+use crate::export::ExportFileEvent;
+use crate::maintenance::{data_dir, LOGS_DIR};
+
+/// How often the recorder checks the event bus when it is idle
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// One line of an operation log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "record", rename_all = "snake_case")]
+pub enum Record {
+    /// The run began
+    Start {
+        run_id: String,
+        command: String,
+        args: Vec<String>,
+        version: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cwd: Option<PathBuf>,
+        at: DateTime<Utc>,
+    },
+    /// Something happened to one file or region; `detail` holds the
+    /// [`Event`] as published, tagged by `event`
+    Event {
+        at: DateTime<Utc>,
+        #[serde(flatten)]
+        detail: Value,
+    },
+    /// The run finished
+    End {
+        at: DateTime<Utc>,
+        duration_ms: u64,
+        ok: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+        counts: OutcomeCounts,
+    },
+}
+
+/// Per-file outcomes seen during a run
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutcomeCounts {
+    #[serde(default)]
+    pub exported: usize,
+    #[serde(default)]
+    pub export_failed: usize,
+    #[serde(default)]
+    pub skipped: usize,
+    #[serde(default)]
+    pub hashed: usize,
+    #[serde(default)]
+    pub bad_sectors: usize,
+    #[serde(default)]
+    pub heals: usize,
+}
+
+impl OutcomeCounts {
+    fn count(&mut self, event: &Event) {
+        match event {
+            Event::Export(ExportFileEvent::Finished { result: Ok(_), .. }) => self.exported += 1,
+            Event::Export(ExportFileEvent::Finished { result: Err(_), .. }) => {
+                self.export_failed += 1
+            }
+            Event::Export(ExportFileEvent::Skipped { .. }) => self.skipped += 1,
+            Event::Hash { .. } => self.hashed += 1,
+            Event::BadSector(_) => self.bad_sectors += 1,
+            Event::Heal(_) => self.heals += 1,
+            _ => {}
+        }
+    }
+}
+
+/// Outcomes are logged; progress ticks would swamp the file
+fn worth_logging(event: &Event) -> bool {
+    match event {
+        Event::Scan { .. } => false,
+        Event::Export(export) => matches!(
+            export,
+            ExportFileEvent::Skipped { .. } | ExportFileEvent::Finished { .. }
+        ),
+        Event::Carve(carve) => matches!(
+            carve,
+            CarveProgress::ScanComplete { .. } | CarveProgress::Done
+        ),
+        Event::Swarm(progress) => progress.finished,
+        Event::Hash { .. } | Event::BadSector(_) | Event::Heal(_) => true,
+    }
+}
+
+type Writer = Arc<Mutex<BufWriter<File>>>;
+
+fn write_record(writer: &Writer, record: &Record) {
+    let mut writer = writer.lock();
+    if let Ok(line) = serde_json::to_string(record) {
+        let _ = writeln!(writer, "{}", line);
+    }
+}
+
+/// The operation log of the current run
+pub struct RunLog {
+    path: PathBuf,
+    writer: Writer,
+    started: Instant,
+    counts: Arc<Mutex<OutcomeCounts>>,
+    stop: Arc<AtomicBool>,
+    recorder: Option<JoinHandle<()>>,
+}
+
+impl RunLog {
+    /// Start logging `command`, run with this process's arguments, in the
+    /// data directory
+    pub fn start(command: &str) -> Result<Self> {
+        let args: Vec<String> = std::env::args().collect();
+        Self::start_in(&data_dir().join(LOGS_DIR), command, args)
+    }
+
+    /// Start logging `command` into a new file in `dir`
+    pub fn start_in(dir: &Path, command: &str, args: Vec<String>) -> Result<Self> {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create log directory {}", dir.display()))?;
+
+        let now = Utc::now();
+        let run_id = format!(
+            "{}-{}-{}",
+            now.format("%Y%m%d-%H%M%S"),
+            command,
+            std::process::id()
+        );
+        let path = dir.join(format!("{}.jsonl", run_id));
+        let file = OpenOptions::new()
+            .create_new(true)
+            .write(true)
+            .open(&path)
+            .with_context(|| format!("Failed to create operation log {}", path.display()))?;
+        let writer: Writer = Arc::new(Mutex::new(BufWriter::new(file)));
+
+        write_record(
+            &writer,
+            &Record::Start {
+                run_id,
+                command: command.to_string(),
+                args,
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                cwd: std::env::current_dir().ok(),
+                at: now,
+            },
+        );
+        writer.lock().flush()?;
+
+        let counts = Arc::new(Mutex::new(OutcomeCounts::default()));
+        let stop = Arc::new(AtomicBool::new(false));
+        let recorder = {
+            let mut events = events::subscribe();
+            let (writer, counts, stop) = (writer.clone(), counts.clone(), stop.clone());
+            thread::Builder::new()
+                .name("audit".to_string())
+                .spawn(move || loop {
+                    match events.try_recv() {
+                        Ok(event) => {
+                            counts.lock().count(&event);
+                            if !worth_logging(&event) {
+                                continue;
+                            }
+                            if let Ok(detail) = serde_json::to_value(&event) {
+                                write_record(
+                                    &writer,
+                                    &Record::Event {
+                                        at: Utc::now(),
+                                        detail,
+                                    },
+                                );
+                            }
+                        }
+                        Err(TryRecvError::Lagged(missed)) => {
+                            tracing::warn!("Operation log missed {} events", missed);
+                        }
+                        Err(TryRecvError::Empty) => {
+                            // Stop only once everything sent so far is written
+                            if stop.load(Ordering::Relaxed) {
+                                break;
+                            }
+                            let _ = writer.lock().flush();
+                            thread::sleep(POLL_INTERVAL);
+                        }
+                        Err(TryRecvError::Closed) => break,
+                    }
+                })
+                .context("Failed to start the operation log recorder")?
+        };
+
+        Ok(Self {
+            path,
+            writer,
+            started: Instant::now(),
+            counts,
+            stop,
+            recorder: Some(recorder),
+        })
+    }
+
+    /// File the run is logged to
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Write the `end` record for the run's result
+    pub fn finish<T>(mut self, result: &Result<T>) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(recorder) = self.recorder.take() {
+            let _ = recorder.join();
+        }
+
+        write_record(
+            &self.writer,
+            &Record::End {
+                at: Utc::now(),
+                duration_ms: self.started.elapsed().as_millis() as u64,
+                ok: result.is_ok(),
+                error: result.as_ref().err().map(|e| format!("{:#}", e)),
+                counts: self.counts.lock().clone(),
+            },
+        );
+        let _ = self.writer.lock().flush();
+    }
+}
+
+/// A past run, from the first and last records of its log
+#[derive(Debug, Clone, Serialize)]
+pub struct RunSummary {
+    pub run_id: String,
+    pub path: PathBuf,
+    pub command: String,
+    pub args: Vec<String>,
+    pub started: DateTime<Utc>,
+    /// None if the run has no `end` record (still running, or killed)
+    pub finished: Option<RunEnd>,
+    pub events: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RunEnd {
+    pub duration_ms: u64,
+    pub ok: bool,
+    pub error: Option<String>,
+    pub counts: OutcomeCounts,
+}
+
+/// Read every record of an operation log, skipping lines that do not parse
+/// (such as a last line cut short by a crash)
+pub fn read_run(path: &Path) -> Result<Vec<Record>> {
+    let file = File::open(path)
+        .with_context(|| format!("Failed to open operation log {}", path.display()))?;
+    let mut records = Vec::new();
+    for line in BufReader::new(file).lines() {
+        if let Ok(record) = serde_json::from_str(&line?) {
+            records.push(record);
+        }
+    }
+    Ok(records)
+}
+
+/// Summarize one operation log
+pub fn summarize(path: &Path) -> Result<RunSummary> {
+    let records = read_run(path)?;
+    let Some(Record::Start {
+        run_id,
+        command,
+        args,
+        at,
+        ..
+    }) = records.first().cloned()
+    else {
+        anyhow::bail!("{} is not an operation log", path.display());
+    };
+
+    let finished = match records.last() {
+        Some(Record::End {
+            duration_ms,
+            ok,
+            error,
+            counts,
+            ..
+        }) => Some(RunEnd {
+            duration_ms: *duration_ms,
+            ok: *ok,
+            error: error.clone(),
+            counts: counts.clone(),
+        }),
+        _ => None,
+    };
+    let events = records
+        .iter()
+        .filter(|r| matches!(r, Record::Event { .. }))
+        .count();
+
+    Ok(RunSummary {
+        run_id,
+        path: path.to_path_buf(),
+        command,
+        args,
+        started: at,
+        finished,
+        events,
+    })
+}
+
+/// Runs logged in `dir`, newest first
+pub fn list_runs(dir: &Path) -> Result<Vec<RunSummary>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut runs: Vec<RunSummary> = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read {}", dir.display()))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|e| e == "jsonl"))
+        .filter_map(|path| summarize(&path).ok())
+        .collect();
+    runs.sort_by(|a, b| b.started.cmp(&a.started).then(b.run_id.cmp(&a.run_id)));
+    Ok(runs)
+}
+
+/// Log file for `run`: a path, `last`, a run ID, or a unique prefix of one
+pub fn find_run(dir: &Path, run: &str) -> Result<PathBuf> {
+    let path = Path::new(run);
+    if path.is_file() {
+        return Ok(path.to_path_buf());
+    }
+
+    let runs = list_runs(dir)?;
+    if run == "last" {
+        return runs
+            .into_iter()
+            .next()
+            .map(|r| r.path)
+            .with_context(|| format!("No runs logged in {}", dir.display()));
+    }
+    if let Some(exact) = runs.iter().find(|r| r.run_id == run) {
+        return Ok(exact.path.clone());
+    }
+    let matches: Vec<&RunSummary> = runs.iter().filter(|r| r.run_id.starts_with(run)).collect();
+    match matches.as_slice() {
+        [only] => Ok(only.path.clone()),
+        [] => anyhow::bail!("No run {} in {}", run, dir.display()),
+        _ => anyhow::bail!(
+            "{} runs start with {}; give more of the ID",
+            matches.len(),
+            run
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::BadSector;
+
+    fn bad_sector(path: &Path) -> Event {
+        Event::BadSector(BadSector {
+            file_path: path.to_path_buf(),
+            offset: 4096,
+            length: 512,
+            error: "Input/output error".to_string(),
+            detected_at: Utc::now(),
+            retry_count: 2,
+            block_size: 512,
+        })
+    }
+
+    #[test]
+    fn test_run_log_records_start_events_and_end() {
+        let dir = tempfile::tempdir().unwrap();
+        let log =
+            RunLog::start_in(dir.path(), "export", vec!["dd".into(), "export".into()]).unwrap();
+        let path = log.path().to_path_buf();
+
+        let unreadable = dir.path().join("audit-test.img");
+        events::emit(Event::Scan {
+            source: unreadable.clone(),
+            files: 1,
+            bytes: 512,
+            path: unreadable.clone(),
+        });
+        events::emit(bad_sector(&unreadable));
+        log.finish::<()>(&Err(anyhow::anyhow!("disk went away")));
+
+        let records = read_run(&path).unwrap();
+        assert!(matches!(&records[0], Record::Start { command, .. } if command == "export"));
+
+        // Other tests publish on the same bus, so look for ours only
+        let ours: Vec<&Value> = records
+            .iter()
+            .filter_map(|r| match r {
+                Record::Event { detail, .. } => Some(detail),
+                _ => None,
+            })
+            .filter(|d| d.to_string().contains("audit-test.img"))
+            .collect();
+        assert_eq!(ours.len(), 1, "scan progress is not logged");
+        assert_eq!(ours[0]["event"], "bad_sector");
+        assert_eq!(ours[0]["offset"], 4096);
+
+        match records.last().unwrap() {
+            Record::End {
+                ok, error, counts, ..
+            } => {
+                assert!(!ok);
+                assert_eq!(error.as_deref(), Some("disk went away"));
+                assert!(counts.bad_sectors >= 1);
+            }
+            other => panic!("expected an end record, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_list_and_find_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(list_runs(&dir.path().join("none")).unwrap().is_empty());
+
+        let first = RunLog::start_in(dir.path(), "index", vec![]).unwrap();
+        let first_path = first.path().to_path_buf();
+        first.finish(&Ok(()));
+
+        // A run that was killed: start record only, and a torn last line
+        let killed = dir.path().join("20990101-000000-carve-1.jsonl");
+        let start = Record::Start {
+            run_id: "20990101-000000-carve-1".to_string(),
+            command: "carve".to_string(),
+            args: vec![],
+            version: "0".to_string(),
+            cwd: None,
+            at: Utc::now() + chrono::Duration::days(1),
+        };
+        fs::write(
+            &killed,
+            format!(
+                "{}\n{{\"record\":\"ev",
+                serde_json::to_string(&start).unwrap()
+            ),
+        )
+        .unwrap();
+        fs::write(dir.path().join("notes.txt"), "not a log").unwrap();
+
+        let runs = list_runs(dir.path()).unwrap();
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0].command, "carve");
+        assert!(runs[0].finished.is_none());
+        assert!(runs[1].finished.as_ref().unwrap().ok);
+
+        assert_eq!(find_run(dir.path(), "last").unwrap(), killed);
+        assert_eq!(find_run(dir.path(), "20990101").unwrap(), killed);
+        assert_eq!(find_run(dir.path(), &runs[1].run_id).unwrap(), first_path);
+        assert!(find_run(dir.path(), "1999").is_err());
+    }
+}
@@ -0,0 +1,441 @@
+//! Device imaging with skip-on-error, in the manner of GNU ddrescue
+//!
+//! `diamond-drill image` clones a (possibly failing) device into an image
+//! file without ever writing to the source:
+//!
+//! 1. **Copy**: the device is streamed through the configured
+//!    [`crate::iobackend`] in large blocks. A block that fails is written as
+//!    zeros and marked non-trimmed (`*`); copying moves straight on to the
+//!    next block instead of grinding on the damaged area.
+//! 2. **Scrape**: each failed block is re-read one sector at a time, with
+//!    backoff on transient errors. Sectors that read are written into the
+//!    image; the rest stay zero-filled and are marked bad (`-`). With
+//!    `reverse`, failed blocks are scraped last sector first, approaching
+//!    the damage from the far side.
+//! 3. **Retry**: bad sectors are tried again for the configured number of
+//!    passes, alternating direction.
+//!
+//! The outcome is written as a ddrescue mapfile (so `carve --mapfile`,
+//! `report` and ddrescue itself know which bytes are placeholders) and as a
+//! proof manifest whose custody log records the bad regions.
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use super::{MapRegion, RegionStatus, RescueMap, SectorReader};
+use crate::core::BadSector;
+use crate::events::{self, Event};
+use crate::iobackend::{self, IoOptions};
+use crate::proof::{self, ChainOfCustody, CustodyEventKind, ProofEntry};
+
+/// Default bytes per read in the copy pass (64KB)
+pub const DEFAULT_COPY_BLOCK_SIZE: usize = 64 * 1024;
+
+/// Default sector size failed blocks are scraped in
+pub const DEFAULT_SECTOR_SIZE: usize = 512;
+
+/// Options for [`image_device`]
+#[derive(Debug, Clone)]
+pub struct ImagingOptions {
+    pub source: PathBuf,
+    pub output: PathBuf,
+    /// Bytes per read in the copy pass
+    pub block_size: usize,
+    /// Bytes per read when scraping failed blocks
+    pub sector_size: usize,
+    /// Passes over the bad sectors after scraping
+    pub retry_passes: u32,
+    /// Scrape failed blocks last sector first
+    pub reverse: bool,
+    /// Mapfile to write (default: `<output>.map`)
+    pub mapfile: Option<PathBuf>,
+    pub io: IoOptions,
+}
+
+impl Default for ImagingOptions {
+    fn default() -> Self {
+        Self {
+            source: PathBuf::new(),
+            output: PathBuf::new(),
+            block_size: DEFAULT_COPY_BLOCK_SIZE,
+            sector_size: DEFAULT_SECTOR_SIZE,
+            retry_passes: 1,
+            reverse: false,
+            mapfile: None,
+            io: IoOptions::default(),
+        }
+    }
+}
+
+/// Outcome of [`image_device`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImagingSummary {
+    pub source: PathBuf,
+    pub output: PathBuf,
+    pub mapfile: PathBuf,
+    pub proof_manifest: PathBuf,
+    /// Device size, and so image size
+    pub size: u64,
+    /// Bytes read from the device
+    pub rescued_bytes: u64,
+    /// Bytes zero-filled in the image
+    pub bad_bytes: u64,
+    /// Untrusted regions in the mapfile
+    pub bad_regions: Vec<MapRegion>,
+    /// BLAKE3 of the image as written, zero-filled regions included
+    pub blake3: String,
+    pub duration_ms: u64,
+}
+
+/// Path of the mapfile written for `output` unless one is given
+pub fn mapfile_path_for(output: &Path) -> PathBuf {
+    let mut name = output.as_os_str().to_owned();
+    name.push(".map");
+    PathBuf::from(name)
+}
+
+/// Path of the proof manifest written for `output`
+pub fn proof_path_for(output: &Path) -> PathBuf {
+    let mut name = output.as_os_str().to_owned();
+    name.push(".proof.json");
+    PathBuf::from(name)
+}
+
+/// Image `opts.source` into `opts.output`, writing the mapfile and proof
+/// manifest next to it. `progress` receives the bytes covered by the copy
+/// pass so far and the device size.
+pub fn image_device(opts: &ImagingOptions, progress: impl Fn(u64, u64)) -> Result<ImagingSummary> {
+    let started = Instant::now();
+    let started_at = Utc::now();
+    let block_size = opts.block_size.max(1);
+    let sector_size = opts.sector_size.clamp(1, block_size);
+
+    let mut source = File::open(&opts.source)
+        .with_context(|| format!("Failed to open {}", opts.source.display()))?;
+    // Block devices report a length of 0 in their metadata
+    let size = source
+        .seek(SeekFrom::End(0))
+        .with_context(|| format!("Failed to determine size of {}", opts.source.display()))?;
+
+    if let Some(parent) = opts.output.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let image = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&opts.output)
+        .with_context(|| format!("Failed to create {}", opts.output.display()))?;
+
+    // Copy pass
+    let mut regions = Vec::new();
+    let mut writer = BufWriter::with_capacity(block_size, image);
+    let zeros = vec![0u8; block_size];
+    iobackend::stream(
+        &opts.source,
+        0..size,
+        block_size,
+        &opts.io,
+        |offset, block| {
+            let len = (size - offset).min(block_size as u64);
+            match block {
+                Ok(data) if data.len() as u64 == len => {
+                    writer.write_all(data)?;
+                    push_region(&mut regions, offset, len, RegionStatus::Finished);
+                }
+                // A short read mid-device counts as a failed block; the stream
+                // ends after it
+                _ => {
+                    writer.write_all(&zeros[..len as usize])?;
+                    push_region(&mut regions, offset, len, RegionStatus::NonTrimmed);
+                }
+            }
+            progress(offset + len, size);
+            Ok(())
+        },
+    )
+    .with_context(|| format!("Failed to read {}", opts.source.display()))?;
+    let mut image = writer.into_inner().map_err(|e| e.into_error())?;
+    let copied: u64 = regions.iter().map(|r| r.size).sum();
+    if copied < size {
+        // The device ended early: the rest could not be read at all
+        push_region(
+            &mut regions,
+            copied,
+            size - copied,
+            RegionStatus::NonTrimmed,
+        );
+        image.set_len(size)?;
+    }
+
+    // Scrape pass, then retry passes over what is still bad
+    let reader = SectorReader::with_block_size(sector_size);
+    let mut buffer = vec![0u8; sector_size];
+    let mut rescue = |regions: Vec<MapRegion>, from: RegionStatus, reverse: bool| -> Result<_> {
+        let mut out = Vec::with_capacity(regions.len());
+        for region in regions {
+            if region.status != from {
+                out.push(region);
+                continue;
+            }
+            let mut sectors: Vec<u64> = (region.pos..region.end()).step_by(sector_size).collect();
+            if reverse {
+                sectors.reverse();
+            }
+            for pos in sectors {
+                let len = (region.end() - pos).min(sector_size as u64);
+                let buf = &mut buffer[..len as usize];
+                let status = match reader.read_block_with_retry(&mut source, pos, buf) {
+                    Ok(()) => {
+                        image.seek(SeekFrom::Start(pos))?;
+                        image.write_all(buf)?;
+                        RegionStatus::Finished
+                    }
+                    Err(_) => RegionStatus::BadSector,
+                };
+                out.push(MapRegion {
+                    pos,
+                    size: len,
+                    status,
+                });
+            }
+        }
+        Ok(merge_regions(out))
+    };
+    regions = rescue(regions, RegionStatus::NonTrimmed, opts.reverse)?;
+    for pass in 0..opts.retry_passes {
+        if !regions.iter().any(|r| r.status == RegionStatus::BadSector) {
+            break;
+        }
+        regions = rescue(
+            regions,
+            RegionStatus::BadSector,
+            opts.reverse ^ (pass % 2 == 0),
+        )?;
+    }
+    image.flush()?;
+    drop(image);
+
+    let map = RescueMap {
+        regions: regions
+            .into_iter()
+            .filter(|r| !r.status.is_trusted())
+            .collect(),
+    };
+    let mapfile = opts
+        .mapfile
+        .clone()
+        .unwrap_or_else(|| mapfile_path_for(&opts.output));
+    map.save(&mapfile, size)?;
+
+    for region in &map.regions {
+        events::emit(Event::BadSector(BadSector {
+            file_path: opts.source.clone(),
+            offset: region.pos,
+            length: region.size,
+            error: format!("unreadable after imaging ({})", region.status.label()),
+            detected_at: Utc::now(),
+            retry_count: opts.retry_passes.min(u8::MAX as u32) as u8,
+            block_size: sector_size as u64,
+        }));
+    }
+
+    let mut hasher = blake3::Hasher::new();
+    let image = File::open(&opts.output)
+        .with_context(|| format!("Failed to open {}", opts.output.display()))?;
+    hasher
+        .update_reader(image)
+        .with_context(|| format!("Failed to hash {}", opts.output.display()))?;
+    let blake3 = hasher.finalize().to_hex().to_string();
+    events::emit(Event::Hash {
+        path: opts.output.clone(),
+        blake3: blake3.clone(),
+        bytes: size,
+    });
+
+    let bad_bytes = map.untrusted_bytes();
+    let proof_manifest = proof_path_for(&opts.output);
+    write_proof(
+        opts,
+        &map,
+        size,
+        &blake3,
+        &mapfile,
+        started_at,
+        &proof_manifest,
+    )?;
+
+    Ok(ImagingSummary {
+        source: opts.source.clone(),
+        output: opts.output.clone(),
+        mapfile,
+        proof_manifest,
+        size,
+        rescued_bytes: size - bad_bytes,
+        bad_bytes,
+        bad_regions: map.regions,
+        blake3,
+        duration_ms: started.elapsed().as_millis() as u64,
+    })
+}
+
+/// Append a region, extending the last one if it has the same status and
+/// ends where this one starts
+fn push_region(regions: &mut Vec<MapRegion>, pos: u64, size: u64, status: RegionStatus) {
+    match regions.last_mut() {
+        Some(last) if last.status == status && last.end() == pos => last.size += size,
+        _ => regions.push(MapRegion { pos, size, status }),
+    }
+}
+
+/// Sort regions by offset and join neighbours with the same status
+fn merge_regions(mut regions: Vec<MapRegion>) -> Vec<MapRegion> {
+    regions.sort_by_key(|r| r.pos);
+    let mut merged = Vec::with_capacity(regions.len());
+    for region in regions {
+        push_region(&mut merged, region.pos, region.size, region.status);
+    }
+    merged
+}
+
+/// Save a proof manifest for the image, with the bad regions in its
+/// custody log
+fn write_proof(
+    opts: &ImagingOptions,
+    map: &RescueMap,
+    size: u64,
+    blake3: &str,
+    mapfile: &Path,
+    started_at: chrono::DateTime<Utc>,
+    path: &Path,
+) -> Result<()> {
+    let bad_bytes = map.untrusted_bytes();
+    let entry = ProofEntry {
+        source_path: opts.source.display().to_string(),
+        dest_path: opts.output.display().to_string(),
+        size,
+        blake3_hash: blake3.to_string(),
+        digests: Default::default(),
+        exported_at: Utc::now(),
+        bad_sector_notes: (bad_bytes > 0).then(|| {
+            format!(
+                "{} bytes in {} regions zero-filled, see {}",
+                bad_bytes,
+                map.regions.len(),
+                mapfile.display()
+            )
+        }),
+        verified: false,
+    };
+
+    let mut custody = ChainOfCustody::from_environment();
+    custody.started_at = started_at;
+    custody.completed_at = Some(Utc::now());
+    custody.record_event_at(
+        started_at,
+        CustodyEventKind::ImagingStarted,
+        format!("{} -> {}", opts.source.display(), opts.output.display()),
+    );
+    for region in &map.regions {
+        custody.record_event(
+            CustodyEventKind::BadSector,
+            format!(
+                "{}: {} bytes at offset {} ({})",
+                opts.source.display(),
+                region.size,
+                region.pos,
+                region.status.label()
+            ),
+        );
+    }
+    custody.record_event(
+        CustodyEventKind::ImagingCompleted,
+        format!("{} bytes, {} unreadable", size, bad_bytes),
+    );
+    let options_used = &mut custody.options_used;
+    options_used.insert("block_size".into(), opts.block_size.to_string());
+    options_used.insert("sector_size".into(), opts.sector_size.to_string());
+    options_used.insert("retry_passes".into(), opts.retry_passes.to_string());
+    options_used.insert("reverse".into(), opts.reverse.to_string());
+    options_used.insert("mapfile".into(), mapfile.display().to_string());
+    options_used.insert(
+        "io_backend".into(),
+        format!("{:?}", opts.io.backend.resolve()).to_lowercase(),
+    );
+
+    let dest_root = opts.output.parent().unwrap_or(Path::new(""));
+    let proof = proof::build_manifest(&opts.source, dest_root, vec![entry], custody);
+    proof::save_manifest(&proof, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_regions() {
+        let region = |pos, size, status| MapRegion { pos, size, status };
+        let merged = merge_regions(vec![
+            region(1024, 512, RegionStatus::BadSector),
+            region(0, 512, RegionStatus::Finished),
+            region(512, 512, RegionStatus::Finished),
+            region(1536, 512, RegionStatus::BadSector),
+            region(2048, 512, RegionStatus::Finished),
+        ]);
+        assert_eq!(
+            merged,
+            vec![
+                region(0, 1024, RegionStatus::Finished),
+                region(1024, 1024, RegionStatus::BadSector),
+                region(2048, 512, RegionStatus::Finished),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_image_readable_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("disk.bin");
+        let data: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&source, &data).unwrap();
+
+        let opts = ImagingOptions {
+            source: source.clone(),
+            output: dir.path().join("out/disk.img"),
+            block_size: 4096,
+            ..Default::default()
+        };
+        let summary = image_device(&opts, |_, _| {}).unwrap();
+
+        assert_eq!(std::fs::read(&opts.output).unwrap(), data);
+        assert_eq!(summary.bad_bytes, 0);
+        assert_eq!(summary.blake3, blake3::hash(&data).to_hex().to_string());
+
+        let map = RescueMap::load(&summary.mapfile).unwrap();
+        assert!(map.regions.is_empty());
+
+        let proof = proof::load_manifest(&summary.proof_manifest).unwrap();
+        assert_eq!(proof.entries[0].blake3_hash, summary.blake3);
+        let kinds: Vec<_> = proof
+            .chain_of_custody
+            .events
+            .iter()
+            .map(|e| e.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                CustodyEventKind::ImagingStarted,
+                CustodyEventKind::ImagingCompleted
+            ]
+        );
+    }
+}
@@ -0,0 +1,333 @@
+//! GNU ddrescue mapfile support
+//!
+//! A ddrescue mapfile records which byte ranges of an imaged device were
+//! read cleanly and which were not. `carve --mapfile` uses it to skip
+//! regions that are zero-filled placeholders rather than real data, and to
+//! flag carved files whose bytes cannot be trusted; `report` draws it as a
+//! heatmap. Bad-sector scans ([`super::SectorReader`]) do not consult it:
+//! they read every block of the files they are given.
+//!
+//! Format (comment lines start with `#`):
+//!
+//! ```text
+//! # current_pos  current_status  current_pass
+//! 0x00120000     ?               1
+//! #      pos        size  status
+//! 0x00000000  0x00117000  +
+//! 0x00117000  0x00000200  -
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::{BlockInfo, SectorMap};
+use crate::error::DrillError;
+
+/// Status of a block in a ddrescue mapfile
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RegionStatus {
+    /// `?` - not read yet
+    NonTried,
+    /// `*` - failed block, not trimmed
+    NonTrimmed,
+    /// `/` - failed block, not scraped
+    NonScraped,
+    /// `-` - failed block, confirmed bad sectors
+    BadSector,
+    /// `+` - read successfully
+    Finished,
+}
+
+impl RegionStatus {
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            '?' => Some(Self::NonTried),
+            '*' => Some(Self::NonTrimmed),
+            '/' => Some(Self::NonScraped),
+            '-' => Some(Self::BadSector),
+            '+' => Some(Self::Finished),
+            _ => None,
+        }
+    }
+
+    /// Mapfile status character
+    pub fn symbol(self) -> char {
+        match self {
+            Self::NonTried => '?',
+            Self::NonTrimmed => '*',
+            Self::NonScraped => '/',
+            Self::BadSector => '-',
+            Self::Finished => '+',
+        }
+    }
+
+    /// Whether bytes in this region hold data actually read from the device
+    pub fn is_trusted(self) -> bool {
+        self == Self::Finished
+    }
+
+    /// Short human-readable label
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::NonTried => "non-tried",
+            Self::NonTrimmed => "non-trimmed",
+            Self::NonScraped => "non-scraped",
+            Self::BadSector => "bad-sector",
+            Self::Finished => "finished",
+        }
+    }
+}
+
+/// A contiguous block from the mapfile
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MapRegion {
+    /// Byte offset of the region start
+    pub pos: u64,
+    /// Region length in bytes
+    pub size: u64,
+    /// Rescue status
+    pub status: RegionStatus,
+}
+
+impl MapRegion {
+    /// Exclusive end offset
+    pub fn end(&self) -> u64 {
+        self.pos.saturating_add(self.size)
+    }
+}
+
+/// Untrusted regions of an image, as recorded by ddrescue
+///
+/// Only regions whose status is not `+` are kept, sorted by offset.
+/// Ranges the mapfile does not mention are treated as readable.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RescueMap {
+    /// Untrusted regions, sorted by `pos`
+    pub regions: Vec<MapRegion>,
+}
+
+impl RescueMap {
+    /// Load and parse a mapfile from disk
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read mapfile: {}", path.display()))?;
+        Self::parse(&text).map_err(|e| {
+            DrillError::FormatParse {
+                path: path.to_path_buf(),
+                format: "ddrescue mapfile",
+                message: format!("{:#}", e),
+            }
+            .into()
+        })
+    }
+
+    /// Parse mapfile text
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(i, l)| (i + 1, l.trim()))
+            .filter(|(_, l)| !l.is_empty() && !l.starts_with('#'));
+
+        // First data line is the status line (current_pos current_status [pass])
+        if lines.next().is_none() {
+            bail!("missing status line");
+        }
+
+        let mut regions = Vec::new();
+        for (lineno, line) in lines {
+            let mut fields = line.split_whitespace();
+            let (Some(pos), Some(size), Some(status)) = (fields.next(), fields.next(), fields.next())
+            else {
+                bail!("line {}: expected `pos size status`", lineno);
+            };
+
+            let pos = parse_number(pos).with_context(|| format!("line {}: bad pos", lineno))?;
+            let size = parse_number(size).with_context(|| format!("line {}: bad size", lineno))?;
+            let status = status
+                .chars()
+                .next()
+                .and_then(RegionStatus::from_char)
+                .with_context(|| format!("line {}: unknown status '{}'", lineno, status))?;
+
+            if !status.is_trusted() && size > 0 {
+                regions.push(MapRegion { pos, size, status });
+            }
+        }
+
+        regions.sort_by_key(|r| r.pos);
+        Ok(Self { regions })
+    }
+
+    /// Mapfile text for a finished rescue of `size` bytes: every region,
+    /// with the gaps between untrusted ones written as `+`
+    pub fn to_mapfile(&self, size: u64) -> String {
+        let mut out = format!(
+            "# Mapfile. Created by {} {}\n\
+             # current_pos  current_status  current_pass\n\
+             0x{:08X}     +               1\n\
+             #      pos        size  status\n",
+            crate::proof::TOOL_NAME,
+            env!("CARGO_PKG_VERSION"),
+            size
+        );
+        let mut line = |pos: u64, size: u64, status: RegionStatus| {
+            out.push_str(&format!(
+                "0x{:08X}  0x{:08X}  {}\n",
+                pos,
+                size,
+                status.symbol()
+            ));
+        };
+        let mut pos = 0;
+        for region in self.regions.iter().filter(|r| r.pos < size) {
+            if region.pos > pos {
+                line(pos, region.pos - pos, RegionStatus::Finished);
+            }
+            let end = region.end().min(size);
+            line(region.pos, end - region.pos, region.status);
+            pos = end;
+        }
+        if pos < size {
+            line(pos, size - pos, RegionStatus::Finished);
+        }
+        out
+    }
+
+    /// Write the mapfile for a finished rescue of `size` bytes
+    pub fn save(&self, path: &Path, size: u64) -> Result<()> {
+        std::fs::write(path, self.to_mapfile(size))
+            .with_context(|| format!("Failed to write mapfile: {}", path.display()))
+    }
+
+    /// Total untrusted bytes
+    pub fn untrusted_bytes(&self) -> u64 {
+        self.regions.iter().map(|r| r.size).sum()
+    }
+
+    /// Untrusted regions that end after `offset`, in order
+    pub fn regions_from(&self, offset: u64) -> &[MapRegion] {
+        let idx = self.regions.partition_point(|r| r.end() <= offset);
+        &self.regions[idx..]
+    }
+
+    /// Whether the byte at `offset` lies in an untrusted region
+    pub fn is_untrusted(&self, offset: u64) -> bool {
+        self.regions_from(offset)
+            .first()
+            .is_some_and(|r| r.pos <= offset)
+    }
+
+    /// Number of bytes in `[offset, offset + len)` that are untrusted
+    pub fn overlap(&self, offset: u64, len: u64) -> u64 {
+        let end = offset.saturating_add(len);
+        self.regions_from(offset)
+            .iter()
+            .take_while(|r| r.pos < end)
+            .map(|r| r.end().min(end) - r.pos.max(offset))
+            .sum()
+    }
+
+    /// Convert to a [`SectorMap`] for the image so it can be reported and
+    /// visualized like any other bad-sector scan.
+    pub fn to_sector_map(&self, image: &Path, image_size: u64, block_size: usize) -> SectorMap {
+        let block = block_size.max(1) as u64;
+        let bad_blocks: Vec<BlockInfo> = self
+            .regions
+            .iter()
+            .filter(|r| r.pos < image_size)
+            .map(|r| BlockInfo {
+                offset: r.pos,
+                length: r.end().min(image_size) - r.pos,
+                error: format!("ddrescue: {}", r.status.label()),
+                retry_count: 0,
+            })
+            .collect();
+        let bad_bytes: u64 = bad_blocks.iter().map(|b| b.length).sum();
+
+        SectorMap {
+            path: PathBuf::from(image),
+            total_blocks: image_size.div_ceil(block),
+            bad_blocks,
+            good_bytes: image_size - bad_bytes,
+            bad_bytes,
+            file_size: image_size,
+            block_size,
+            passes: Vec::new(),
+        }
+    }
+}
+
+fn parse_number(s: &str) -> Result<u64> {
+    let parsed = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    parsed.with_context(|| format!("not a number: '{}'", s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "\
+# Mapfile. Created by GNU ddrescue version 1.27
+# current_pos  current_status  current_pass
+0x00003000     +               1
+#      pos        size  status
+0x00000000  0x00001000  +
+0x00001000  0x00000200  -
+0x00001200  0x00000E00  +
+0x00002000  0x00000400  /
+0x00002400  0x00000C00  +
+";
+
+    #[test]
+    fn test_parse_keeps_untrusted_only() {
+        let map = RescueMap::parse(SAMPLE).unwrap();
+        assert_eq!(map.regions.len(), 2);
+        assert_eq!(map.regions[0].pos, 0x1000);
+        assert_eq!(map.regions[0].status, RegionStatus::BadSector);
+        assert_eq!(map.regions[1].status, RegionStatus::NonScraped);
+        assert_eq!(map.untrusted_bytes(), 0x600);
+    }
+
+    #[test]
+    fn test_queries() {
+        let map = RescueMap::parse(SAMPLE).unwrap();
+        assert!(!map.is_untrusted(0xFFF));
+        assert!(map.is_untrusted(0x1000));
+        assert!(map.is_untrusted(0x11FF));
+        assert!(!map.is_untrusted(0x1200));
+
+        assert_eq!(map.overlap(0, 0x1000), 0);
+        assert_eq!(map.overlap(0x0F00, 0x200), 0x100);
+        assert_eq!(map.overlap(0, 0x3000), 0x600);
+    }
+
+    #[test]
+    fn test_to_sector_map() {
+        let map = RescueMap::parse(SAMPLE).unwrap();
+        let sm = map.to_sector_map(Path::new("disk.img"), 0x3000, 4096);
+        assert!(sm.has_bad_sectors());
+        assert_eq!(sm.total_blocks, 3);
+        assert_eq!(sm.bad_bytes, 0x600);
+    }
+
+    #[test]
+    fn test_mapfile_round_trip() {
+        let map = RescueMap::parse(SAMPLE).unwrap();
+        let text = map.to_mapfile(0x3000);
+        assert!(text.contains("0x00001000  0x00000200  -\n"));
+        assert!(text.contains("0x00002400  0x00000C00  +\n"));
+        assert_eq!(RescueMap::parse(&text).unwrap().regions, map.regions);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(RescueMap::parse("# only comments\n").is_err());
+        assert!(RescueMap::parse("0 +\n0x0 0x10 X\n").is_err());
+    }
+}
@@ -1,7 +1,18 @@
 //! Bad Sector module - Enhanced sector-level error detection and reporting
 //!
 //! Provides block-level file reading with retry logic, exponential backoff,
-//! and detailed error tracking for disk recovery operations.
+//! and detailed error tracking for disk recovery operations. The first pass
+//! over a file goes through the configured [`crate::iobackend`]; only blocks
+//! that fail there are retried one at a time. [`imaging`] applies the same
+//! reads to cloning a whole device, and [`parallel`] scans many files at
+//! once.
+
+pub mod imaging;
+pub mod mapfile;
+pub mod parallel;
+
+pub use mapfile::{MapRegion, RegionStatus, RescueMap};
+pub use parallel::{ParallelScanner, ScanProgress};
 
 use std::io::{Read, Seek, SeekFrom};
 use std::path::{Path, PathBuf};
@@ -11,9 +22,18 @@ use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::core::BadSector;
+use crate::error::{is_transient_io, DrillResult};
+use crate::events::{self, Event};
+use crate::iobackend::{self, IoOptions};
+
 /// Default block size for sector reads (4KB)
 pub const DEFAULT_BLOCK_SIZE: usize = 4096;
 
+/// Block sizes of [`SectorReader::multi_pass`]: 1MB for speed, then
+/// 64KB, 4KB and single sectors over what failed
+pub const MULTI_PASS_BLOCK_SIZES: [usize; 4] = [1024 * 1024, 64 * 1024, 4096, 512];
+
 /// Maximum retry attempts for transient I/O errors
 pub const MAX_RETRIES: u8 = 3;
 
@@ -59,8 +79,26 @@ pub struct SectorMap {
     pub bad_bytes: u64,
     /// File size
     pub file_size: u64,
-    /// Block size used for scanning
+    /// Block size used for scanning (of the last pass, for multi-pass reads)
     pub block_size: usize,
+    /// What each pass read and recovered, in order
+    #[serde(default)]
+    pub passes: Vec<PassResult>,
+}
+
+/// Outcome of one pass of a [`SectorReader`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PassResult {
+    /// Block size the pass read in
+    pub block_size: usize,
+    /// Bytes the pass tried to read (what the pass before failed on)
+    pub attempted_bytes: u64,
+    /// Bytes read successfully
+    pub recovered_bytes: u64,
+    /// Bytes left for the next pass, or bad after the last
+    pub failed_bytes: u64,
+    /// Blocks that failed
+    pub failed_blocks: u64,
 }
 
 impl SectorMap {
@@ -185,6 +223,18 @@ impl BadSectorReport {
                     humansize::format_size(map.bad_bytes, humansize::BINARY),
                 ));
 
+                if map.passes.len() > 1 {
+                    for (n, pass) in map.passes.iter().enumerate() {
+                        out.push_str(&format!(
+                            "    Pass {} ({} blocks): {} of {} recovered\n",
+                            n + 1,
+                            humansize::format_size(pass.block_size as u64, humansize::BINARY),
+                            humansize::format_size(pass.recovered_bytes, humansize::BINARY),
+                            humansize::format_size(pass.attempted_bytes, humansize::BINARY),
+                        ));
+                    }
+                }
+
                 for block in &map.bad_blocks {
                     out.push_str(&format!(
                         "    [offset 0x{:08X}, {} bytes, {} retries] {}\n",
@@ -204,37 +254,79 @@ impl BadSectorReport {
 }
 
 /// Reads a file block-by-block with retry logic for bad sector detection
+///
+/// With several passes, the first reads the whole file in large blocks for
+/// speed and each later pass re-reads only the blocks the pass before it
+/// failed on, in smaller blocks. Only the last pass retries with backoff;
+/// blocks that fail it are the bad blocks of the [`SectorMap`].
 pub struct SectorReader {
-    block_size: usize,
+    /// Block size of each pass, largest first
+    pass_sizes: Vec<usize>,
     max_retries: u8,
+    io: IoOptions,
 }
 
 impl SectorReader {
     /// Create a new sector reader with default settings
     pub fn new() -> Self {
-        Self {
-            block_size: DEFAULT_BLOCK_SIZE,
-            max_retries: MAX_RETRIES,
-        }
+        Self::with_block_size(DEFAULT_BLOCK_SIZE)
     }
 
     /// Create with custom block size
     pub fn with_block_size(block_size: usize) -> Self {
         Self {
-            block_size: block_size.max(512), // minimum 512 bytes
+            pass_sizes: vec![block_size.max(512)], // minimum 512 bytes
             max_retries: MAX_RETRIES,
+            io: IoOptions::default(),
+        }
+    }
+
+    /// Create with one pass per block size in [`MULTI_PASS_BLOCK_SIZES`]
+    pub fn multi_pass() -> Self {
+        Self::with_passes(&MULTI_PASS_BLOCK_SIZES)
+    }
+
+    /// Create with one pass per block size. Sizes below 512 bytes are
+    /// raised to 512, and sizes not smaller than the pass before are
+    /// dropped.
+    pub fn with_passes(block_sizes: &[usize]) -> Self {
+        let mut pass_sizes: Vec<usize> = Vec::with_capacity(block_sizes.len());
+        for size in block_sizes.iter().map(|&s| s.max(512)) {
+            if pass_sizes.last().is_none_or(|&last| size < last) {
+                pass_sizes.push(size);
+            }
+        }
+        if pass_sizes.is_empty() {
+            pass_sizes.push(DEFAULT_BLOCK_SIZE);
+        }
+        Self {
+            pass_sizes,
+            ..Self::new()
         }
     }
 
+    /// Read through this backend and queue depth
+    pub fn with_io(mut self, io: IoOptions) -> Self {
+        self.io = io;
+        self
+    }
+
+    /// Block size of the last pass, which bad blocks are reported in
+    pub fn block_size(&self) -> usize {
+        self.pass_sizes[self.pass_sizes.len() - 1]
+    }
+
     /// Read a file with sector-level tracking
     ///
     /// Returns a SectorMap with all bad block locations.
     /// For files that are entirely readable, the bad_blocks vec will be empty.
-    pub fn read_with_sector_tracking(&self, path: &Path) -> Result<SectorMap> {
+    /// Each bad block is also published as [`Event::BadSector`] when found.
+    pub fn read_with_sector_tracking(&self, path: &Path) -> DrillResult<SectorMap> {
         let metadata = std::fs::metadata(path)
             .with_context(|| format!("Failed to get metadata for {}", path.display()))?;
 
         let file_size = metadata.len();
+        let block_size = self.block_size();
 
         if file_size == 0 {
             return Ok(SectorMap {
@@ -244,49 +336,91 @@ impl SectorReader {
                 good_bytes: 0,
                 bad_bytes: 0,
                 file_size: 0,
-                block_size: self.block_size,
+                block_size,
+                passes: Vec::new(),
             });
         }
 
-        let total_blocks = file_size.div_ceil(self.block_size as u64);
-        let mut bad_blocks = Vec::new();
-        let mut good_bytes = 0u64;
-        let mut bad_bytes = 0u64;
-
         let mut file = std::fs::File::open(path)
             .with_context(|| format!("Failed to open {}", path.display()))?;
 
-        let mut buffer = vec![0u8; self.block_size];
+        // Regions still to read: the whole file, then what each pass failed on
+        let mut pending: Vec<_> = std::iter::once(0..file_size).collect();
+        let mut passes = Vec::with_capacity(self.pass_sizes.len());
+        let mut bad_blocks = Vec::new();
 
-        for block_idx in 0..total_blocks {
-            let offset = block_idx * self.block_size as u64;
-            let remaining = file_size - offset;
-            let read_size = remaining.min(self.block_size as u64) as usize;
+        for (pass, &pass_size) in self.pass_sizes.iter().enumerate() {
+            let last_pass = pass + 1 == self.pass_sizes.len();
+            let mut buffer = vec![0u8; pass_size];
+            let mut failed: Vec<std::ops::Range<u64>> = Vec::new();
+            let mut result = PassResult {
+                block_size: pass_size,
+                ..Default::default()
+            };
+
+            for region in std::mem::take(&mut pending) {
+                let region_end = region.end;
+                iobackend::stream(path, region, pass_size, &self.io, |offset, block| {
+                    let read_size = (region_end - offset).min(pass_size as u64) as usize;
+                    result.attempted_bytes += read_size as u64;
+
+                    let outcome = match block {
+                        Ok(_) => Ok(()),
+                        Err(e) if !last_pass => Err((e.to_string(), 0)),
+                        Err(_) => {
+                            self.read_block_with_retry(&mut file, offset, &mut buffer[..read_size])
+                        }
+                    };
+                    match outcome {
+                        Ok(()) => result.recovered_bytes += read_size as u64,
+                        Err((error, retry_count)) => {
+                            result.failed_bytes += read_size as u64;
+                            result.failed_blocks += 1;
+                            let end = offset + read_size as u64;
+                            match failed.last_mut() {
+                                Some(prev) if prev.end == offset => prev.end = end,
+                                _ => failed.push(offset..end),
+                            }
+                            if last_pass {
+                                events::emit(Event::BadSector(BadSector {
+                                    file_path: path.to_path_buf(),
+                                    offset,
+                                    length: read_size as u64,
+                                    error: error.clone(),
+                                    detected_at: Utc::now(),
+                                    retry_count,
+                                    block_size: pass_size as u64,
+                                }));
+                                bad_blocks.push(BlockInfo {
+                                    offset,
+                                    length: read_size as u64,
+                                    error,
+                                    retry_count,
+                                });
+                            }
+                        }
+                    }
+                    Ok(())
+                })?;
+            }
 
-            match self.read_block_with_retry(&mut file, offset, &mut buffer[..read_size]) {
-                Ok(()) => {
-                    good_bytes += read_size as u64;
-                }
-                Err((error, retry_count)) => {
-                    bad_bytes += read_size as u64;
-                    bad_blocks.push(BlockInfo {
-                        offset,
-                        length: read_size as u64,
-                        error,
-                        retry_count,
-                    });
-                }
+            passes.push(result);
+            pending = failed;
+            if pending.is_empty() {
+                break;
             }
         }
 
+        let bad_bytes: u64 = bad_blocks.iter().map(|b| b.length).sum();
         Ok(SectorMap {
             path: path.to_path_buf(),
-            total_blocks,
+            total_blocks: file_size.div_ceil(block_size as u64),
             bad_blocks,
-            good_bytes,
+            good_bytes: file_size - bad_bytes,
             bad_bytes,
             file_size,
-            block_size: self.block_size,
+            block_size,
+            passes,
         })
     }
 
@@ -338,12 +472,7 @@ impl SectorReader {
 
     /// Check if an I/O error is transient (worth retrying)
     fn is_transient_error(e: &std::io::Error) -> bool {
-        matches!(
-            e.kind(),
-            std::io::ErrorKind::Interrupted
-                | std::io::ErrorKind::TimedOut
-                | std::io::ErrorKind::WouldBlock
-        )
+        is_transient_io(e.kind())
     }
 }
 
@@ -358,6 +487,7 @@ pub fn export_with_bad_sector_handling(
     source: &Path,
     dest: &Path,
     sector_map: &SectorMap,
+    io: &IoOptions,
 ) -> Result<ExportBadSectorResult> {
     use std::io::Write;
 
@@ -366,13 +496,9 @@ pub fn export_with_bad_sector_handling(
         std::fs::create_dir_all(parent)?;
     }
 
-    let mut src_file = std::fs::File::open(source)
-        .with_context(|| format!("Failed to open source: {}", source.display()))?;
-
     let mut dst_file = std::fs::File::create(dest)
         .with_context(|| format!("Failed to create dest: {}", dest.display()))?;
 
-    let mut buffer = vec![0u8; sector_map.block_size];
     let zero_buffer = vec![0u8; sector_map.block_size];
     let mut bytes_copied = 0u64;
     let mut bytes_zeroed = 0u64;
@@ -382,33 +508,43 @@ pub fn export_with_bad_sector_handling(
     let bad_offsets: std::collections::HashSet<u64> =
         sector_map.bad_blocks.iter().map(|b| b.offset).collect();
 
-    for block_idx in 0..sector_map.total_blocks {
-        let offset = block_idx * sector_map.block_size as u64;
-        let remaining = sector_map.file_size - offset;
-        let read_size = remaining.min(sector_map.block_size as u64) as usize;
-
-        if bad_offsets.contains(&offset) {
-            // Zero-fill this block
-            dst_file.write_all(&zero_buffer[..read_size])?;
-            hasher.update(&zero_buffer[..read_size]);
-            bytes_zeroed += read_size as u64;
-        } else {
-            // Copy the block
-            src_file.seek(SeekFrom::Start(offset))?;
-            match src_file.read_exact(&mut buffer[..read_size]) {
-                Ok(()) => {
-                    dst_file.write_all(&buffer[..read_size])?;
-                    hasher.update(&buffer[..read_size]);
-                    bytes_copied += read_size as u64;
+    iobackend::stream(
+        source,
+        0..sector_map.file_size,
+        sector_map.block_size,
+        io,
+        |offset, block| {
+            let remaining = sector_map.file_size - offset;
+            let read_size = remaining.min(sector_map.block_size as u64) as usize;
+
+            match block {
+                Ok(data) if !bad_offsets.contains(&offset) => {
+                    // Copy the block
+                    dst_file.write_all(data)?;
+                    hasher.update(data);
+                    bytes_copied += data.len() as u64;
                 }
-                Err(_) => {
-                    // Unexpected error on previously-good block — zero-fill
+                // Bad in the map, or unexpected error on a previously-good
+                // block — zero-fill
+                _ => {
                     dst_file.write_all(&zero_buffer[..read_size])?;
                     hasher.update(&zero_buffer[..read_size]);
                     bytes_zeroed += read_size as u64;
                 }
             }
-        }
+            Ok(())
+        },
+    )
+    .with_context(|| format!("Failed to read source: {}", source.display()))?;
+
+    // Source shorter than mapped — zero-fill the rest
+    let mut written = bytes_copied + bytes_zeroed;
+    while written < sector_map.file_size {
+        let len = (sector_map.file_size - written).min(zero_buffer.len() as u64) as usize;
+        dst_file.write_all(&zero_buffer[..len])?;
+        hasher.update(&zero_buffer[..len]);
+        bytes_zeroed += len as u64;
+        written += len as u64;
     }
 
     dst_file.flush()?;
@@ -496,6 +632,34 @@ mod tests {
         assert_eq!(map.readable_percent(), 100.0);
     }
 
+    #[test]
+    fn test_sector_reader_multi_pass() {
+        let passes = SectorReader::with_passes(&[4096, 65536, 100, 512]);
+        assert_eq!(passes.pass_sizes, [4096, 512]);
+        assert_eq!(passes.block_size(), 512);
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("clean.bin");
+        std::fs::write(&path, vec![0x5A; 3 * 1024 * 1024 + 100]).unwrap();
+
+        // A clean first pass leaves nothing for the smaller ones
+        let map = SectorReader::multi_pass()
+            .read_with_sector_tracking(&path)
+            .unwrap();
+        assert!(!map.has_bad_sectors());
+        assert_eq!(map.block_size, 512);
+        assert_eq!(
+            map.passes,
+            [PassResult {
+                block_size: 1024 * 1024,
+                attempted_bytes: map.file_size,
+                recovered_bytes: map.file_size,
+                failed_bytes: 0,
+                failed_blocks: 0,
+            }]
+        );
+    }
+
     #[test]
     fn test_sector_reader_empty_file() {
         let dir = tempdir().unwrap();
@@ -533,6 +697,7 @@ mod tests {
             bad_bytes: 2 * 4096,
             file_size: 10 * 4096,
             block_size: 4096,
+            passes: Vec::new(),
         };
 
         let heatmap = map.heatmap();
@@ -583,9 +748,11 @@ mod tests {
             bad_bytes: 4096,
             file_size: 8192,
             block_size: 4096,
+            passes: Vec::new(),
         };
 
-        let result = export_with_bad_sector_handling(&source, &dest, &map).unwrap();
+        let result =
+            export_with_bad_sector_handling(&source, &dest, &map, &IoOptions::default()).unwrap();
 
         assert_eq!(result.bytes_copied, 4096); // First block copied
         assert_eq!(result.bytes_zeroed, 4096); // Second block zero-filled
@@ -621,6 +788,7 @@ mod tests {
                 bad_bytes: 4096,
                 file_size: 40960,
                 block_size: 4096,
+                passes: Vec::new(),
             }],
         };
 
@@ -659,6 +827,7 @@ mod tests {
                 bad_bytes: 0,
                 file_size: 5 * 4096,
                 block_size: 4096,
+                passes: Vec::new(),
             },
             SectorMap {
                 path: PathBuf::from("/bad.txt"),
@@ -673,6 +842,7 @@ mod tests {
                 bad_bytes: 4096,
                 file_size: 10 * 4096,
                 block_size: 4096,
+                passes: Vec::new(),
             },
         ];
 
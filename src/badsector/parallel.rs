@@ -0,0 +1,170 @@
+//! Parallel sector scanning across files
+//!
+//! A [`SectorReader`] reads one file at a time. Scanning every file of a
+//! source serially leaves an SSD or a set of disks mostly idle, but running
+//! one reader per core against a single spinning disk turns sequential reads
+//! into seeks. [`ParallelScanner`] groups files by the physical device they
+//! live on and runs at most `per_device` readers on each device, with the
+//! devices themselves scanned side by side on the rayon pool.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use parking_lot::Mutex;
+use rayon::prelude::*;
+
+use super::{SectorMap, SectorReader};
+use crate::error::DrillResult;
+
+/// Readers run at once on one device unless configured otherwise
+pub const DEFAULT_PER_DEVICE: usize = 2;
+
+/// Progress of a [`ParallelScanner::scan`], across all files
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScanProgress {
+    pub files_done: usize,
+    pub files_total: usize,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    /// Bad blocks found so far
+    pub bad_blocks: u64,
+}
+
+/// Scans many files with a [`SectorReader`], bounded per physical device
+pub struct ParallelScanner {
+    reader: SectorReader,
+    per_device: usize,
+}
+
+impl ParallelScanner {
+    pub fn new(reader: SectorReader) -> Self {
+        Self {
+            reader,
+            per_device: DEFAULT_PER_DEVICE,
+        }
+    }
+
+    /// Run at most `per_device` readers on each device (at least one)
+    pub fn with_per_device(mut self, per_device: usize) -> Self {
+        self.per_device = per_device.max(1);
+        self
+    }
+
+    /// Scan `paths`, returning each file's sector map (or why it could not
+    /// be read) in the order given. `progress` is called after every file,
+    /// from whichever thread finished it.
+    pub fn scan<F>(&self, paths: &[PathBuf], progress: F) -> Vec<DrillResult<SectorMap>>
+    where
+        F: Fn(ScanProgress) + Sync,
+    {
+        let mut devices: BTreeMap<u64, Vec<usize>> = BTreeMap::new();
+        let mut bytes_total = 0;
+        for (i, path) in paths.iter().enumerate() {
+            let (device, size) = device_and_size(path);
+            devices.entry(device).or_default().push(i);
+            bytes_total += size;
+        }
+
+        let files_done = AtomicUsize::new(0);
+        let bytes_done = AtomicU64::new(0);
+        let bad_blocks = AtomicU64::new(0);
+        let results: Mutex<Vec<Option<DrillResult<SectorMap>>>> =
+            Mutex::new(paths.iter().map(|_| None).collect());
+
+        // `per_device` workers per device, each taking the device's next file
+        let queues: Vec<(Vec<usize>, AtomicUsize)> = devices
+            .into_values()
+            .map(|files| (files, AtomicUsize::new(0)))
+            .collect();
+        let workers: Vec<&(Vec<usize>, AtomicUsize)> = queues
+            .iter()
+            .flat_map(|queue| std::iter::repeat_n(queue, self.per_device.min(queue.0.len())))
+            .collect();
+
+        workers.into_par_iter().for_each(|(files, next)| {
+            while let Some(&i) = files.get(next.fetch_add(1, Ordering::Relaxed)) {
+                let result = self.reader.read_with_sector_tracking(&paths[i]);
+                if let Ok(map) = &result {
+                    bytes_done.fetch_add(map.file_size, Ordering::Relaxed);
+                    bad_blocks.fetch_add(map.bad_blocks.len() as u64, Ordering::Relaxed);
+                }
+                results.lock()[i] = Some(result);
+                progress(ScanProgress {
+                    files_done: files_done.fetch_add(1, Ordering::Relaxed) + 1,
+                    files_total: paths.len(),
+                    bytes_done: bytes_done.load(Ordering::Relaxed),
+                    bytes_total,
+                    bad_blocks: bad_blocks.load(Ordering::Relaxed),
+                });
+            }
+        });
+
+        results
+            .into_inner()
+            .into_iter()
+            .map(|r| r.expect("every file is scanned by its device's workers"))
+            .collect()
+    }
+}
+
+/// Device the file lives on and its size. Files whose metadata can't be
+/// read share device 0 and fail when scanned.
+fn device_and_size(path: &Path) -> (u64, u64) {
+    let Ok(metadata) = std::fs::metadata(path) else {
+        return (0, 0);
+    };
+    #[cfg(unix)]
+    let device = std::os::unix::fs::MetadataExt::dev(&metadata);
+    // No stable volume id on other platforms: files under the same root
+    // (drive letter or share) count as one device
+    #[cfg(not(unix))]
+    let device = {
+        use std::hash::{Hash, Hasher};
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        path.components().next().hash(&mut hasher);
+        hasher.finish()
+    };
+    (device, metadata.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_keeps_order_and_reports_progress() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut paths: Vec<PathBuf> = (0..12)
+            .map(|i| {
+                let path = dir.path().join(format!("file{i}.bin"));
+                std::fs::write(&path, vec![i as u8; 1000 * (i + 1)]).unwrap();
+                path
+            })
+            .collect();
+        paths.insert(5, dir.path().join("missing.bin"));
+
+        let calls = AtomicUsize::new(0);
+        let last = Mutex::new(ScanProgress::default());
+        let results = ParallelScanner::new(SectorReader::new())
+            .with_per_device(3)
+            .scan(&paths, |p| {
+                calls.fetch_add(1, Ordering::Relaxed);
+                let mut last = last.lock();
+                if p.files_done > last.files_done {
+                    *last = p;
+                }
+            });
+
+        assert_eq!(results.len(), paths.len());
+        assert!(results[5].is_err());
+        for (path, result) in paths.iter().zip(&results).filter(|(_, r)| r.is_ok()) {
+            assert_eq!(&result.as_ref().unwrap().path, path);
+        }
+        assert_eq!(calls.into_inner(), paths.len());
+        let last = last.into_inner();
+        assert_eq!(last.files_done, paths.len());
+        assert_eq!(last.bytes_done, last.bytes_total);
+        assert_eq!(last.bytes_total, (1..=12).map(|i| 1000 * i).sum::<u64>());
+    }
+}
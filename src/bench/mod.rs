@@ -0,0 +1,804 @@
+//! Bench - source and destination throughput
+//!
+//! `diamond-drill bench <source> [--dest DIR]` times reads of the source
+//! and writes to the destination, so carving and exporting can be sized to
+//! the hardware instead of the CPU count:
+//!
+//! - sequential and random reads of the source at each block size
+//! - sequential reads by 1, 2, 4, ... workers at the best block size, which
+//!   is where a spinning disk falls apart
+//! - writes to the destination at each block size, then by more workers
+//!
+//! From these it recommends a [`Tuning`]: the smallest block size and
+//! worker counts within [`GOOD_ENOUGH`] of the best throughput seen. The
+//! report is kept in the data directory ([`Calibrations`]); later commands
+//! on a path under the benched source use its tuning unless told otherwise.
+//! Sources never benched are tuned by a guess from the kind of storage
+//! holding them ([`auto_tune`], [`StorageKind`]).
+//!
+//! Each measurement stops after `sample_bytes` or `time_limit`, whichever
+//! comes first, so a USB2 drive is not read for an hour. Reads of one
+//! measurement start where the last one stopped and the page cache is
+//! dropped in between where the OS allows, so cached data does not inflate
+//! the numbers. Read errors are counted, not fatal.
+
+use std::fs::{self, File};
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+mod storage;
+
+pub use storage::*;
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::config::TuningConfig;
+use crate::export::DEFAULT_EXPORT_WORKERS;
+use crate::iobackend::read_full_at;
+use crate::maintenance;
+
+/// File in the data directory holding the saved calibrations
+pub const CALIBRATIONS_FILE: &str = "calibrations.json";
+
+/// Block sizes measured unless configured otherwise
+pub const DEFAULT_BLOCK_SIZES: [usize; 4] = [4 * 1024, 64 * 1024, 1024 * 1024, 4 * 1024 * 1024];
+
+/// Most bytes one measurement reads or writes
+pub const DEFAULT_SAMPLE_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Longest one measurement runs
+pub const DEFAULT_TIME_LIMIT: Duration = Duration::from_secs(5);
+
+/// Throughput within this share of the best counts as just as good, and
+/// the smaller block size or worker count wins
+pub const GOOD_ENOUGH: f64 = 0.9;
+
+/// Files of a directory source read from, largest first
+const MAX_SAMPLE_FILES: usize = 256;
+
+/// Block size recommended when no sequential reads were measured
+const FALLBACK_BLOCK_SIZE: usize = 1024 * 1024;
+
+/// What to measure
+#[derive(Debug, Clone)]
+pub struct BenchOptions {
+    /// File, device or directory to read
+    pub source: PathBuf,
+    /// Directory to write to (None = reads only)
+    pub dest: Option<PathBuf>,
+    pub block_sizes: Vec<usize>,
+    pub sample_bytes: u64,
+    pub time_limit: Duration,
+    /// Most workers tried at once
+    pub max_workers: usize,
+}
+
+impl Default for BenchOptions {
+    fn default() -> Self {
+        Self {
+            source: PathBuf::new(),
+            dest: None,
+            block_sizes: DEFAULT_BLOCK_SIZES.to_vec(),
+            sample_bytes: DEFAULT_SAMPLE_BYTES,
+            time_limit: DEFAULT_TIME_LIMIT,
+            max_workers: num_cpus::get().min(16),
+        }
+    }
+}
+
+/// Access pattern of a measurement
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Pattern {
+    SequentialRead,
+    RandomRead,
+    Write,
+}
+
+impl Pattern {
+    pub fn label(self) -> &'static str {
+        match self {
+            Pattern::SequentialRead => "sequential read",
+            Pattern::RandomRead => "random read",
+            Pattern::Write => "write",
+        }
+    }
+}
+
+/// One timed run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Measurement {
+    pub pattern: Pattern,
+    pub block_size: usize,
+    pub workers: usize,
+    pub bytes: u64,
+    /// Reads or writes completed
+    pub ops: u64,
+    /// Reads or writes that failed
+    pub errors: u64,
+    pub duration_ms: u64,
+}
+
+impl Measurement {
+    pub fn bytes_per_sec(&self) -> f64 {
+        self.bytes as f64 / (self.duration_ms.max(1) as f64 / 1000.0)
+    }
+
+    pub fn iops(&self) -> f64 {
+        self.ops as f64 / (self.duration_ms.max(1) as f64 / 1000.0)
+    }
+}
+
+/// Settings recommended from a benchmark
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tuning {
+    /// Workers reading the source at once (indexing, carving)
+    pub read_workers: usize,
+    /// Files exported at once
+    pub export_workers: usize,
+    /// Bytes per source read (None = each command's own default)
+    pub block_size: Option<usize>,
+}
+
+/// What commands do untuned: one reader per CPU
+impl Default for Tuning {
+    fn default() -> Self {
+        Self {
+            read_workers: num_cpus::get(),
+            export_workers: DEFAULT_EXPORT_WORKERS,
+            block_size: None,
+        }
+    }
+}
+
+impl Tuning {
+    /// The smallest block size and worker counts within [`GOOD_ENOUGH`] of
+    /// the best throughput measured
+    pub fn recommend(measurements: &[Measurement]) -> Self {
+        let of = |pattern: Pattern| measurements.iter().filter(move |m| m.pattern == pattern);
+
+        let block_size = good_enough(of(Pattern::SequentialRead).filter(|m| m.workers == 1))
+            .map_or(FALLBACK_BLOCK_SIZE, |m| m.block_size);
+        let read_workers =
+            good_enough(of(Pattern::SequentialRead).filter(|m| m.block_size == block_size))
+                .map_or(1, |m| m.workers);
+
+        let write_block =
+            good_enough(of(Pattern::Write).filter(|m| m.workers == 1)).map(|m| m.block_size);
+        let write_workers = write_block.and_then(|block_size| {
+            good_enough(of(Pattern::Write).filter(|m| m.block_size == block_size))
+                .map(|m| m.workers)
+        });
+
+        Self {
+            read_workers,
+            // An export goes at the pace of the slower side
+            export_workers: write_workers.map_or(read_workers, |w| w.min(read_workers)),
+            block_size: Some(block_size),
+        }
+    }
+}
+
+/// The cheapest of `measurements` (fewest workers, then smallest blocks)
+/// within [`GOOD_ENOUGH`] of the fastest
+fn good_enough<'a>(measurements: impl Iterator<Item = &'a Measurement>) -> Option<&'a Measurement> {
+    let measurements: Vec<_> = measurements.filter(|m| m.bytes > 0).collect();
+    let best = measurements
+        .iter()
+        .map(|m| m.bytes_per_sec())
+        .fold(0.0, f64::max);
+    measurements
+        .into_iter()
+        .filter(|m| m.bytes_per_sec() >= best * GOOD_ENOUGH)
+        .min_by_key(|m| (m.workers, m.block_size))
+}
+
+/// Result of `bench`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchReport {
+    /// Benched source (canonical)
+    pub source: PathBuf,
+    /// Benched destination (canonical)
+    pub dest: Option<PathBuf>,
+    pub measured_at: DateTime<Utc>,
+    /// Bytes available to read from the source
+    pub source_bytes: u64,
+    pub measurements: Vec<Measurement>,
+    pub tuning: Tuning,
+}
+
+/// Measure `options.source` (and `options.dest`), passing each measurement
+/// to `on_measurement` as it completes
+pub fn run<F>(options: &BenchOptions, mut on_measurement: F) -> Result<BenchReport>
+where
+    F: FnMut(&Measurement),
+{
+    if options.block_sizes.is_empty() {
+        bail!("No block sizes to measure");
+    }
+    let source = options
+        .source
+        .canonicalize()
+        .with_context(|| format!("Source not found: {}", options.source.display()))?;
+    let dest = options
+        .dest
+        .as_ref()
+        .map(|dest| {
+            dest.canonicalize()
+                .with_context(|| format!("Destination not found: {}", dest.display()))
+        })
+        .transpose()?;
+    if let Some(dest) = dest.as_ref().filter(|d| !d.is_dir()) {
+        bail!("Destination is not a directory: {}", dest.display());
+    }
+
+    let sample = Sample::open(&source)?;
+    let mut measurements = Vec::new();
+    let mut record = |measurements: &mut Vec<Measurement>, m: Measurement| {
+        on_measurement(&m);
+        measurements.push(m);
+    };
+
+    // Each sequential measurement starts where the previous one stopped
+    let mut cursor = 0u64;
+    for &block_size in &options.block_sizes {
+        sample.drop_cache();
+        let m = measure(Pattern::SequentialRead, block_size, 1, options, |_| {
+            Ok(SequentialReader::new(&sample, cursor))
+        })?;
+        cursor += m.bytes;
+        record(&mut measurements, m);
+    }
+    for &block_size in &options.block_sizes {
+        sample.drop_cache();
+        let m = measure(Pattern::RandomRead, block_size, 1, options, |worker| {
+            Ok(RandomReader::new(&sample, worker))
+        })?;
+        record(&mut measurements, m);
+    }
+
+    let block_size = Tuning::recommend(&measurements)
+        .block_size
+        .unwrap_or(FALLBACK_BLOCK_SIZE);
+    for workers in worker_counts(options.max_workers).skip(1) {
+        sample.drop_cache();
+        // Workers spread over the source, like carve workers over an image
+        let stride = sample.size / workers as u64;
+        let m = measure(
+            Pattern::SequentialRead,
+            block_size,
+            workers,
+            options,
+            |worker| {
+                Ok(SequentialReader::new(
+                    &sample,
+                    cursor + worker as u64 * stride,
+                ))
+            },
+        )?;
+        cursor += m.bytes;
+        record(&mut measurements, m);
+    }
+
+    if let Some(dest) = &dest {
+        for &block_size in &options.block_sizes {
+            let m = measure(Pattern::Write, block_size, 1, options, |worker| {
+                Writer::create(dest, worker)
+            })?;
+            record(&mut measurements, m);
+        }
+        let write_block = good_enough(
+            measurements
+                .iter()
+                .filter(|m| m.pattern == Pattern::Write && m.workers == 1),
+        )
+        .map_or(FALLBACK_BLOCK_SIZE, |m| m.block_size);
+        for workers in worker_counts(options.max_workers).skip(1) {
+            let m = measure(Pattern::Write, write_block, workers, options, |worker| {
+                Writer::create(dest, worker)
+            })?;
+            record(&mut measurements, m);
+        }
+    }
+
+    let tuning = Tuning::recommend(&measurements);
+    Ok(BenchReport {
+        source,
+        dest,
+        measured_at: Utc::now(),
+        source_bytes: sample.size,
+        measurements,
+        tuning,
+    })
+}
+
+/// 1, 2, 4, ... up to `max`
+fn worker_counts(max: usize) -> impl Iterator<Item = usize> {
+    std::iter::successors(Some(1usize), |&n| n.checked_mul(2)).take_while(move |&n| n <= max.max(1))
+}
+
+/// One thread's share of a measurement
+trait Worker: Send {
+    /// Read or write one block through `buf`, returning the bytes moved
+    fn step(&mut self, buf: &mut [u8]) -> io::Result<usize>;
+
+    /// Called once the measurement is over, inside the timing
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Time `workers` threads stepping through `block_size` blocks until the
+/// sample size or time limit is reached
+fn measure<W, M>(
+    pattern: Pattern,
+    block_size: usize,
+    workers: usize,
+    options: &BenchOptions,
+    make: M,
+) -> Result<Measurement>
+where
+    W: Worker,
+    M: Fn(usize) -> Result<W>,
+{
+    let block_size = block_size.max(1);
+    let workers_state = (0..workers.max(1)).map(&make).collect::<Result<Vec<_>>>()?;
+    let bytes = AtomicU64::new(0);
+    let ops = AtomicU64::new(0);
+    let errors = AtomicU64::new(0);
+
+    let started = Instant::now();
+    thread::scope(|scope| {
+        for mut worker in workers_state {
+            let (bytes, ops, errors) = (&bytes, &ops, &errors);
+            scope.spawn(move || {
+                let mut buf = vec![0u8; block_size];
+                if pattern == Pattern::Write {
+                    fill_incompressible(&mut buf);
+                }
+                while bytes.load(Ordering::Relaxed) < options.sample_bytes
+                    && started.elapsed() < options.time_limit
+                {
+                    match worker.step(&mut buf) {
+                        Ok(n) => {
+                            bytes.fetch_add(n as u64, Ordering::Relaxed);
+                            ops.fetch_add(1, Ordering::Relaxed);
+                        }
+                        Err(_) => {
+                            errors.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                }
+                if worker.finish().is_err() {
+                    errors.fetch_add(1, Ordering::Relaxed);
+                }
+            });
+        }
+    });
+
+    Ok(Measurement {
+        pattern,
+        block_size,
+        workers: workers.max(1),
+        bytes: bytes.into_inner(),
+        ops: ops.into_inner(),
+        errors: errors.into_inner(),
+        duration_ms: started.elapsed().as_millis() as u64,
+    })
+}
+
+/// Pseudo-random bytes, so compressing drives and file systems can't cheat
+fn fill_incompressible(buf: &mut [u8]) {
+    let mut state = 0x9E37_79B9_7F4A_7C15u64;
+    for chunk in buf.chunks_mut(8) {
+        state = splitmix64(state);
+        chunk.copy_from_slice(&state.to_le_bytes()[..chunk.len()]);
+    }
+}
+
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// The bytes a source offers to read: one file or device, or the largest
+/// files of a directory laid end to end
+struct Sample {
+    /// Open files and the offset each starts at
+    files: Vec<(File, u64)>,
+    size: u64,
+}
+
+impl Sample {
+    fn open(source: &Path) -> Result<Self> {
+        let paths = if source.is_dir() {
+            let mut files: Vec<(PathBuf, u64)> = walkdir::WalkDir::new(source)
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_file())
+                .filter_map(|e| Some((e.path().to_path_buf(), e.metadata().ok()?.len())))
+                .filter(|(_, len)| *len > 0)
+                .collect();
+            files.sort_by_key(|f| std::cmp::Reverse(f.1));
+            files.truncate(MAX_SAMPLE_FILES);
+            files.into_iter().map(|(path, _)| path).collect()
+        } else {
+            vec![source.to_path_buf()]
+        };
+
+        let mut files = Vec::new();
+        let mut size = 0;
+        for path in paths {
+            let mut file = match File::open(&path) {
+                Ok(file) => file,
+                Err(e) if source.is_dir() => {
+                    tracing::debug!("Not reading {}: {}", path.display(), e);
+                    continue;
+                }
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Failed to open {}", path.display()))
+                }
+            };
+            // Devices report a zero length; their end is where seeking goes
+            let len = match file.metadata()?.len() {
+                0 => file.seek(SeekFrom::End(0)).unwrap_or(0),
+                len => len,
+            };
+            if len > 0 {
+                files.push((file, size));
+                size += len;
+            }
+        }
+        if size == 0 {
+            bail!("Nothing to read in {}", source.display());
+        }
+        Ok(Self { files, size })
+    }
+
+    /// Read into `buf` from `offset` (modulo the size), stopping at the end
+    /// of the file it falls in
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        let offset = offset % self.size;
+        let i = self
+            .files
+            .partition_point(|(_, start)| *start <= offset)
+            .saturating_sub(1);
+        let (file, start) = &self.files[i];
+        let end = self.files.get(i + 1).map_or(self.size, |(_, s)| *s);
+        let len = buf.len().min((end - offset) as usize);
+        read_full_at(file, &mut buf[..len], offset - start)
+    }
+
+    /// Ask the OS to forget what it cached of the sample
+    fn drop_cache(&self) {
+        #[cfg(target_os = "linux")]
+        for (file, _) in &self.files {
+            use std::os::unix::io::AsRawFd;
+            // Advisory only: a failure leaves numbers that may be cached
+            unsafe {
+                libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED);
+            }
+        }
+    }
+}
+
+struct SequentialReader<'a> {
+    sample: &'a Sample,
+    offset: u64,
+}
+
+impl<'a> SequentialReader<'a> {
+    fn new(sample: &'a Sample, offset: u64) -> Self {
+        Self {
+            sample,
+            offset: offset % sample.size,
+        }
+    }
+}
+
+impl Worker for SequentialReader<'_> {
+    fn step(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let result = self.sample.read_at(buf, self.offset);
+        // Past an error or the end of a file either way
+        let advance = match result {
+            Ok(n) if n > 0 => n as u64,
+            _ => buf.len() as u64,
+        };
+        self.offset = (self.offset + advance) % self.sample.size;
+        result
+    }
+}
+
+struct RandomReader<'a> {
+    sample: &'a Sample,
+    state: u64,
+}
+
+impl<'a> RandomReader<'a> {
+    fn new(sample: &'a Sample, worker: usize) -> Self {
+        Self {
+            sample,
+            state: splitmix64(worker as u64 ^ sample.size),
+        }
+    }
+}
+
+impl Worker for RandomReader<'_> {
+    fn step(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.state = splitmix64(self.state);
+        let blocks = (self.sample.size / buf.len() as u64).max(1);
+        self.sample
+            .read_at(buf, self.state % blocks * buf.len() as u64)
+    }
+}
+
+/// Writes a scratch file in the destination, removed when dropped
+struct Writer {
+    file: File,
+    path: PathBuf,
+}
+
+impl Writer {
+    fn create(dir: &Path, worker: usize) -> Result<Self> {
+        let path = dir.join(format!(
+            ".diamond-drill-bench-{}-{}.tmp",
+            std::process::id(),
+            worker
+        ));
+        let file =
+            File::create(&path).with_context(|| format!("Failed to write to {}", dir.display()))?;
+        Ok(Self { file, path })
+    }
+}
+
+impl Worker for Writer {
+    fn step(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    /// Writes count once they are on the disk, not in the page cache
+    fn finish(&mut self) -> io::Result<()> {
+        self.file.sync_all()
+    }
+}
+
+impl Drop for Writer {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Benchmark reports kept for later commands
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Calibrations {
+    pub reports: Vec<BenchReport>,
+}
+
+impl Calibrations {
+    /// Where calibrations are kept
+    pub fn default_path() -> PathBuf {
+        maintenance::data_dir().join(CALIBRATIONS_FILE)
+    }
+
+    /// Calibrations saved at `path` (none if it does not exist yet)
+    pub fn load(path: &Path) -> Result<Self> {
+        match fs::read(path) {
+            Ok(data) => serde_json::from_slice(&data)
+                .with_context(|| format!("Failed to parse calibrations: {}", path.display())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => {
+                Err(e).with_context(|| format!("Failed to read calibrations: {}", path.display()))
+            }
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        fs::write(path, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("Failed to write calibrations: {}", path.display()))
+    }
+
+    /// Keep `report`, replacing any earlier one of the same source and
+    /// destination
+    pub fn record(&mut self, report: BenchReport) {
+        self.reports
+            .retain(|r| r.source != report.source || r.dest != report.dest);
+        self.reports.push(report);
+    }
+
+    /// Tuning for reading `source` (and exporting to `dest`): from the
+    /// report of the closest benched ancestor of `source`, preferring one
+    /// that also benched an ancestor of `dest`, then the newest
+    pub fn tuning(&self, source: &Path, dest: Option<&Path>) -> Option<Tuning> {
+        let source = canonical(source);
+        let dest = dest.map(canonical);
+        self.reports
+            .iter()
+            .filter(|r| source.starts_with(&r.source))
+            .max_by_key(|r| {
+                let same_dest = match (&dest, &r.dest) {
+                    (Some(dest), Some(benched)) => dest.starts_with(benched),
+                    _ => false,
+                };
+                (same_dest, r.source.components().count(), r.measured_at)
+            })
+            .map(|r| r.tuning)
+    }
+}
+
+/// Where a [`Tuning`] came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TuningOrigin {
+    /// A `bench` calibration
+    Bench,
+    /// A guess from the kinds of storage involved
+    Storage {
+        source: StorageKind,
+        dest: Option<StorageKind>,
+    },
+}
+
+/// Settings for a command reading `source` (and exporting to `dest`), for
+/// the options its user left unset: the `bench` calibration of `source` if
+/// there is one, else a guess from the storage holding `source` and `dest`.
+/// None when tuning is off or the defaults are the best guess.
+pub fn auto_tune(
+    source: &Path,
+    dest: Option<&Path>,
+    config: &TuningConfig,
+    calibrations: &Calibrations,
+) -> Option<(Tuning, TuningOrigin)> {
+    if !config.auto {
+        return None;
+    }
+    if let Some(tuning) = calibrations.tuning(source, dest) {
+        return Some((tuning, TuningOrigin::Bench));
+    }
+
+    let source_kind = storage::detect(source);
+    let dest_kind = dest.map(storage::detect);
+    let mut tuning = Tuning::for_storage(source_kind, config);
+    // An export goes no faster than its destination takes the files
+    if let Some(limit) = dest_kind.and_then(|kind| Tuning::for_storage(kind, config)) {
+        let tuning = tuning.get_or_insert_with(Tuning::default);
+        tuning.export_workers = tuning.export_workers.min(limit.export_workers);
+    }
+    tuning.map(|tuning| {
+        let origin = TuningOrigin::Storage {
+            source: source_kind,
+            dest: dest_kind,
+        };
+        (tuning, origin)
+    })
+}
+
+/// `path` resolved as far as it exists (a destination may not yet)
+fn canonical(path: &Path) -> PathBuf {
+    let mut missing = Vec::new();
+    let mut existing = path;
+    loop {
+        if let Ok(resolved) = existing.canonicalize() {
+            return missing
+                .iter()
+                .rev()
+                .fold(resolved, |path: PathBuf, part| path.join(part));
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                missing.push(name.to_os_string());
+                existing = parent;
+            }
+            _ => return path.to_path_buf(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn measurement(pattern: Pattern, block_size: usize, workers: usize, mb: u64) -> Measurement {
+        Measurement {
+            pattern,
+            block_size,
+            workers,
+            bytes: mb * 1024 * 1024,
+            ops: 1,
+            errors: 0,
+            duration_ms: 1000,
+        }
+    }
+
+    #[test]
+    fn test_recommend_prefers_cheapest_near_best() {
+        use Pattern::*;
+        // A spinning disk: 1 MiB blocks nearly as good as 4 MiB, and more
+        // workers only add seeks
+        let measurements = vec![
+            measurement(SequentialRead, 4096, 1, 20),
+            measurement(SequentialRead, 1 << 20, 1, 118),
+            measurement(SequentialRead, 4 << 20, 1, 120),
+            measurement(SequentialRead, 1 << 20, 2, 70),
+            measurement(SequentialRead, 1 << 20, 4, 40),
+            measurement(Write, 1 << 20, 1, 300),
+            measurement(Write, 1 << 20, 2, 600),
+        ];
+        let tuning = Tuning::recommend(&measurements);
+        assert_eq!(tuning.block_size, Some(1 << 20));
+        assert_eq!(tuning.read_workers, 1);
+        assert_eq!(tuning.export_workers, 1);
+
+        // An SSD scaling to 4 readers, writing to a destination that
+        // scales to 2
+        let measurements = vec![
+            measurement(SequentialRead, 1 << 20, 1, 500),
+            measurement(SequentialRead, 1 << 20, 2, 1000),
+            measurement(SequentialRead, 1 << 20, 4, 2000),
+            measurement(SequentialRead, 1 << 20, 8, 2050),
+            measurement(Write, 1 << 20, 1, 300),
+            measurement(Write, 1 << 20, 2, 600),
+            measurement(Write, 1 << 20, 4, 610),
+        ];
+        let tuning = Tuning::recommend(&measurements);
+        assert_eq!(tuning.read_workers, 4);
+        assert_eq!(tuning.export_workers, 2);
+    }
+
+    #[test]
+    fn test_run_and_calibrations() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source");
+        let dest = dir.path().join("dest");
+        fs::create_dir_all(source.join("sub")).unwrap();
+        fs::create_dir_all(&dest).unwrap();
+        fs::write(source.join("a.bin"), vec![1u8; 300_000]).unwrap();
+        fs::write(source.join("sub/b.bin"), vec![2u8; 100_000]).unwrap();
+
+        let options = BenchOptions {
+            source: source.clone(),
+            dest: Some(dest.clone()),
+            block_sizes: vec![4096, 65536],
+            sample_bytes: 1 << 20,
+            time_limit: Duration::from_millis(200),
+            max_workers: 2,
+        };
+        let mut seen = 0;
+        let report = run(&options, |_| seen += 1).unwrap();
+        // 2 sequential, 2 random, 1 more reader, 2 writes, 1 more writer
+        assert_eq!(seen, 8);
+        assert_eq!(report.measurements.len(), 8);
+        assert_eq!(report.source_bytes, 400_000);
+        assert!(report
+            .measurements
+            .iter()
+            .all(|m| m.bytes > 0 && m.errors == 0));
+        // Scratch files are gone
+        assert_eq!(fs::read_dir(&dest).unwrap().count(), 0);
+
+        let path = dir.path().join(CALIBRATIONS_FILE);
+        let mut calibrations = Calibrations::load(&path).unwrap();
+        assert!(calibrations.reports.is_empty());
+        calibrations.record(report.clone());
+        calibrations.record(report.clone());
+        calibrations.save(&path).unwrap();
+
+        let calibrations = Calibrations::load(&path).unwrap();
+        assert_eq!(calibrations.reports.len(), 1);
+        assert_eq!(
+            calibrations.tuning(&source.join("sub/b.bin"), Some(&dest.join("new"))),
+            Some(report.tuning)
+        );
+        assert_eq!(calibrations.tuning(&dest, None), None);
+    }
+}
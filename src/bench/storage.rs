@@ -0,0 +1,261 @@
+//! The kind of storage a path lives on
+//!
+//! Without a `bench` calibration, worker counts come from what holds the
+//! source: a single spinning disk read by one worker per CPU spends its
+//! time seeking. On Linux the kind comes from the mount table and sysfs
+//! (`queue/rotational`, a USB parent, the disks under a device-mapper or md
+//! device); elsewhere only UNC paths are recognized, as network shares.
+
+use std::path::{Component, Path, Prefix};
+
+use serde::{Deserialize, Serialize};
+
+use super::Tuning;
+use crate::config::TuningConfig;
+
+/// Block size for storage that does best with long sequential reads
+const LARGE_BLOCK: usize = 1024 * 1024;
+
+/// File systems reached over the network
+#[cfg(any(target_os = "linux", test))]
+const NETWORK_FILESYSTEMS: &[&str] = &[
+    "nfs",
+    "nfs4",
+    "cifs",
+    "smb3",
+    "smbfs",
+    "9p",
+    "afs",
+    "ceph",
+    "glusterfs",
+    "fuse.sshfs",
+    "fuse.rclone",
+    "fuse.davfs2",
+];
+
+/// What holds a source or destination
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageKind {
+    /// Spinning disk
+    Rotational,
+    Ssd,
+    /// Flash or SSD behind a USB bridge
+    Usb,
+    /// NFS, SMB and the like
+    Network,
+    Unknown,
+}
+
+impl StorageKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            StorageKind::Rotational => "spinning disk",
+            StorageKind::Ssd => "SSD",
+            StorageKind::Usb => "USB drive",
+            StorageKind::Network => "network share",
+            StorageKind::Unknown => "unknown storage",
+        }
+    }
+
+    /// Built-in settings (None = the defaults, one worker per CPU)
+    fn builtin(self) -> Option<Tuning> {
+        let tuning = |read_workers, export_workers| Tuning {
+            read_workers,
+            export_workers,
+            block_size: Some(LARGE_BLOCK),
+        };
+        match self {
+            StorageKind::Rotational => Some(tuning(1, 2)),
+            StorageKind::Usb => Some(tuning(2, 2)),
+            // Latency, not seeks: a few requests in flight hide it
+            StorageKind::Network => Some(tuning(4, 4)),
+            StorageKind::Ssd | StorageKind::Unknown => None,
+        }
+    }
+}
+
+impl Tuning {
+    /// Settings for storage of `kind`: the built-in guess with the config's
+    /// table for the kind on top
+    pub fn for_storage(kind: StorageKind, config: &TuningConfig) -> Option<Tuning> {
+        let custom = match kind {
+            StorageKind::Rotational => config.rotational.as_ref(),
+            StorageKind::Ssd => config.ssd.as_ref(),
+            StorageKind::Usb => config.usb.as_ref(),
+            StorageKind::Network => config.network.as_ref(),
+            StorageKind::Unknown => None,
+        };
+        let builtin = kind.builtin();
+        let Some(custom) = custom else {
+            return builtin;
+        };
+        let base = builtin.unwrap_or_default();
+        Some(Tuning {
+            read_workers: custom.read_workers.unwrap_or(base.read_workers).max(1),
+            export_workers: custom.export_workers.unwrap_or(base.export_workers).max(1),
+            block_size: custom.block_size.or(base.block_size),
+        })
+    }
+}
+
+/// Kind of storage holding `path` (which need not exist yet)
+pub fn detect(path: &Path) -> StorageKind {
+    let unc = matches!(
+        path.components().next(),
+        Some(Component::Prefix(p)) if matches!(p.kind(), Prefix::UNC(..) | Prefix::VerbatimUNC(..))
+    );
+    if unc {
+        return StorageKind::Network;
+    }
+    detect_local(&super::canonical(path))
+}
+
+#[cfg(target_os = "linux")]
+fn detect_local(path: &Path) -> StorageKind {
+    use std::os::unix::fs::FileTypeExt;
+
+    let is_device = std::fs::metadata(path).is_ok_and(|m| m.file_type().is_block_device());
+    let device = if is_device {
+        path.to_path_buf()
+    } else {
+        let Ok(mountinfo) = std::fs::read_to_string("/proc/self/mountinfo") else {
+            return StorageKind::Unknown;
+        };
+        match crate::health::mount_of(&mountinfo, path) {
+            Some((fstype, _)) if NETWORK_FILESYSTEMS.contains(&fstype) => {
+                return StorageKind::Network
+            }
+            Some((_, source)) if source.starts_with("/dev/") => source.into(),
+            _ => return StorageKind::Unknown,
+        }
+    };
+    // /dev/mapper/* and /dev/disk/by-*/* are links to the kernel's name
+    let name = device
+        .canonicalize()
+        .ok()
+        .and_then(|d| d.file_name().map(|n| n.to_string_lossy().into_owned()));
+    name.map_or(StorageKind::Unknown, |name| {
+        block_kind(Path::new("/sys/class/block"), &name)
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn detect_local(_path: &Path) -> StorageKind {
+    StorageKind::Unknown
+}
+
+/// Kind of block device `name` (`sda1`, `nvme0n1p2`, `dm-0`) as described
+/// under the sysfs directory `class_block`
+#[cfg(any(target_os = "linux", test))]
+fn block_kind(class_block: &Path, name: &str) -> StorageKind {
+    use std::fs;
+
+    let Ok(device) = class_block.join(name).canonicalize() else {
+        return StorageKind::Unknown;
+    };
+
+    // Device-mapper and md devices go at the pace of the disks under them
+    let members: Vec<StorageKind> = fs::read_dir(device.join("slaves"))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| block_kind(class_block, &e.file_name().to_string_lossy()))
+        .collect();
+    if !members.is_empty() {
+        return [StorageKind::Rotational, StorageKind::Usb, StorageKind::Ssd]
+            .into_iter()
+            .find(|kind| members.contains(kind))
+            .unwrap_or(StorageKind::Unknown);
+    }
+
+    // A partition's queue is its disk's
+    let disk = match device.parent() {
+        Some(parent) if device.join("partition").exists() => parent.to_path_buf(),
+        _ => device,
+    };
+    let usb = disk
+        .components()
+        .any(|c| c.as_os_str().to_string_lossy().starts_with("usb"));
+    match fs::read_to_string(disk.join("queue/rotational")) {
+        Ok(flag) if flag.trim() == "1" => StorageKind::Rotational,
+        Ok(_) if usb => StorageKind::Usb,
+        Ok(_) => StorageKind::Ssd,
+        Err(_) => StorageKind::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::StorageTuning;
+
+    #[test]
+    fn test_for_storage_applies_config() {
+        let mut config = TuningConfig::default();
+        assert_eq!(
+            Tuning::for_storage(StorageKind::Rotational, &config).map(|t| t.read_workers),
+            Some(1)
+        );
+        assert_eq!(Tuning::for_storage(StorageKind::Ssd, &config), None);
+
+        config.rotational = Some(StorageTuning {
+            read_workers: Some(2),
+            ..Default::default()
+        });
+        config.ssd = Some(StorageTuning {
+            export_workers: Some(16),
+            ..Default::default()
+        });
+        let rotational = Tuning::for_storage(StorageKind::Rotational, &config).unwrap();
+        assert_eq!((rotational.read_workers, rotational.export_workers), (2, 2));
+        let ssd = Tuning::for_storage(StorageKind::Ssd, &config).unwrap();
+        assert_eq!(ssd.export_workers, 16);
+        assert_eq!(ssd.read_workers, num_cpus::get());
+        assert_eq!(ssd.block_size, None);
+    }
+
+    #[test]
+    fn test_network_mounts() {
+        let mountinfo = "\
+22 1 8:1 / / rw,relatime shared:1 - ext4 /dev/sda1 rw
+40 22 0:45 / /mnt/nas rw,relatime shared:30 - cifs //nas/photos rw,vers=3.0";
+        let fstype = |p: &str| crate::health::mount_of(mountinfo, Path::new(p)).map(|m| m.0);
+        assert!(NETWORK_FILESYSTEMS.contains(&fstype("/mnt/nas/2019").unwrap()));
+        assert!(!NETWORK_FILESYSTEMS.contains(&fstype("/home").unwrap()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_block_kind_from_sysfs() {
+        use std::fs;
+        use std::os::unix::fs::symlink;
+
+        let sys = tempfile::tempdir().unwrap();
+        let devices = sys.path().join("devices");
+        let class = sys.path().join("class");
+        fs::create_dir_all(&class).unwrap();
+        let disk = |path: &str, rotational: &str| {
+            let dir = devices.join(path);
+            fs::create_dir_all(dir.join("queue")).unwrap();
+            fs::write(dir.join("queue/rotational"), rotational).unwrap();
+            dir
+        };
+
+        let sda = disk("pci0/ata1/sda", "1\n");
+        fs::create_dir_all(sda.join("sda1")).unwrap();
+        fs::write(sda.join("sda1/partition"), "1").unwrap();
+        symlink(sda.join("sda1"), class.join("sda1")).unwrap();
+        symlink(disk("pci0/nvme0n1", "0\n"), class.join("nvme0n1")).unwrap();
+        symlink(disk("pci0/usb2/2-1/sdb", "0\n"), class.join("sdb")).unwrap();
+        let dm = devices.join("virtual/dm-0");
+        fs::create_dir_all(dm.join("slaves/sda1")).unwrap();
+        symlink(&dm, class.join("dm-0")).unwrap();
+
+        assert_eq!(block_kind(&class, "sda1"), StorageKind::Rotational);
+        assert_eq!(block_kind(&class, "nvme0n1"), StorageKind::Ssd);
+        assert_eq!(block_kind(&class, "sdb"), StorageKind::Usb);
+        assert_eq!(block_kind(&class, "dm-0"), StorageKind::Rotational);
+        assert_eq!(block_kind(&class, "sdz"), StorageKind::Unknown);
+    }
+}
@@ -0,0 +1,120 @@
+//! diamond-verify - Standalone proof manifest verifier
+//!
+//! A dependency-light binary for recipients of an export who only need to
+//! check the files against their proof manifest. Build with:
+//!
+//! ```text
+//! cargo build --release --no-default-features --features verify-only
+//! ```
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use diamond_drill::proof;
+
+const USAGE: &str = "\
+Usage: diamond-verify <MANIFEST> [--json] [--self-contained] [--pubkey <FILE>] [--single <FILE>]
+
+Options:
+  --json            Print the verification result as JSON
+  --self-contained  Write a b3sum checklist, shell verifier and schema next to the manifest
+  --pubkey <FILE>   Require a valid signature by this Ed25519 public key (PEM)
+  --single <FILE>   Check only this exported file, via its Merkle inclusion proof
+  -h, --help        Print this help";
+
+fn main() -> ExitCode {
+    let mut manifest_path: Option<PathBuf> = None;
+    let mut json = false;
+    let mut self_contained = false;
+    let mut pubkey: Option<PathBuf> = None;
+    let mut single: Option<PathBuf> = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--self-contained" => self_contained = true,
+            "--pubkey" | "--single" => match args.next() {
+                Some(path) if arg == "--pubkey" => pubkey = Some(PathBuf::from(path)),
+                Some(path) => single = Some(PathBuf::from(path)),
+                None => {
+                    eprintln!("{} needs a file\n\n{}", arg, USAGE);
+                    return ExitCode::from(2);
+                }
+            },
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return ExitCode::SUCCESS;
+            }
+            _ if arg.starts_with('-') || manifest_path.is_some() => {
+                eprintln!("Unexpected argument: {}\n\n{}", arg, USAGE);
+                return ExitCode::from(2);
+            }
+            _ => manifest_path = Some(PathBuf::from(arg)),
+        }
+    }
+
+    let Some(manifest_path) = manifest_path else {
+        eprintln!("{}", USAGE);
+        return ExitCode::from(2);
+    };
+
+    let result = match &single {
+        Some(file) => run_single(&manifest_path, file, json, pubkey.as_deref()),
+        None => run(&manifest_path, json, self_contained, pubkey.as_deref()),
+    };
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(1),
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn run(
+    manifest_path: &Path,
+    json: bool,
+    self_contained: bool,
+    pubkey: Option<&Path>,
+) -> anyhow::Result<bool> {
+    let manifest = proof::load_manifest(manifest_path)?;
+    let trusted = pubkey.map(proof::load_verifying_key).transpose()?;
+    let result = proof::verify_manifest_with_key(&manifest, trusted.as_ref())?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&result)?);
+    } else {
+        println!(
+            "Manifest: {} files, {} bytes, operator {}",
+            manifest.total_files, manifest.total_bytes, manifest.chain_of_custody.operator
+        );
+        print!("{}", proof::format_verify_result(&result));
+    }
+
+    if self_contained {
+        let kit = proof::write_verifier_kit(&manifest, manifest_path)?;
+        eprintln!("Verifier kit written: {}", kit.script.display());
+    }
+
+    Ok(result.is_clean())
+}
+
+fn run_single(
+    manifest_path: &Path,
+    file: &Path,
+    json: bool,
+    pubkey: Option<&Path>,
+) -> anyhow::Result<bool> {
+    let manifest = proof::load_manifest(manifest_path)?;
+    let trusted = pubkey.map(proof::load_verifying_key).transpose()?;
+    let result = proof::verify_single_file(&manifest, file, trusted.as_ref())?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&result)?);
+    } else {
+        print!("{}", proof::format_single_file_result(&result));
+    }
+    Ok(result.is_clean())
+}
@@ -0,0 +1,176 @@
+//! AVI size parsing for carving
+//!
+//! The RIFF size field only covers the first RIFF chunk. OpenDML (AVI 2.0)
+//! files past 1 GB carry the rest of their frames in `AVIX` RIFF chunks
+//! that follow it, and a recorder that crashed leaves the size at zero.
+//! The chunks of each RIFF are walked instead: `hdrl`, the `movi` frames
+//! and the legacy `idx1` index, whose furthest entry has to land inside
+//! `movi` for the declared size to be trusted.
+
+const RIFF: &[u8] = b"RIFF";
+const LIST: &[u8] = b"LIST";
+const IDX1: &[u8] = b"idx1";
+
+/// Bytes in an `idx1` entry: chunk ID, flags, offset, size
+const INDEX_ENTRY_LEN: usize = 16;
+
+/// What walking one RIFF chunk found
+struct Riff {
+    /// End of the RIFF, or of its last whole chunk when the size can't be
+    /// trusted
+    end: usize,
+    /// Whether the chunks reached the declared size exactly
+    consistent: bool,
+    saw_movi: bool,
+}
+
+/// Length of the AVI file `data` starts with, OpenDML extensions included
+pub fn parse_size(data: &[u8]) -> Option<u64> {
+    let first = walk_riff(data, 0, b"AVI ")?;
+    if !first.saw_movi {
+        return None;
+    }
+    let mut end = first.end;
+    if first.consistent {
+        while let Some(extension) = walk_riff(data, end, b"AVIX") {
+            if !(extension.consistent && extension.saw_movi) {
+                break;
+            }
+            end = extension.end;
+        }
+    }
+    Some(end as u64)
+}
+
+fn walk_riff(data: &[u8], start: usize, form: &[u8]) -> Option<Riff> {
+    let header = data.get(start..start + 12)?;
+    if !header.starts_with(RIFF) || &header[8..12] != form {
+        return None;
+    }
+    let declared = u32_at(header, 4) as usize;
+    let declared_end = start + 8 + declared;
+
+    let mut pos = start + 12;
+    let mut movi = None;
+    let mut index_fits = true;
+    while pos != declared_end {
+        let Some(chunk) = data.get(pos..pos + 8) else {
+            break;
+        };
+        let id = &chunk[..4];
+        // Another file's RIFF can't be a chunk of this one
+        if !is_fourcc(id) || id == RIFF {
+            break;
+        }
+        let size = u32_at(chunk, 4) as usize;
+        let body = pos + 8;
+        let next = body + size + (size & 1);
+        if body + size > data.len() {
+            break;
+        }
+        if id == LIST && data.get(body..body + 4) == Some(b"movi") {
+            movi = Some((body, size));
+        }
+        if id == IDX1 {
+            index_fits = movi.is_some_and(|movi| index_within(&data[body..body + size], movi));
+        }
+        pos = next.min(data.len());
+    }
+    Some(Riff {
+        end: pos,
+        consistent: declared > 4 && pos == declared_end && index_fits,
+        saw_movi: movi.is_some(),
+    })
+}
+
+/// Whether every `idx1` entry points inside the `movi` list at `(start,
+/// len)`. Offsets are from the `movi` ID, or from the file start in files
+/// from some muxers.
+fn index_within(index: &[u8], (movi_start, movi_len): (usize, usize)) -> bool {
+    let furthest = index
+        .chunks_exact(INDEX_ENTRY_LEN)
+        .map(|entry| u32_at(entry, 8) as usize + 8 + u32_at(entry, 12) as usize)
+        .max()
+        .unwrap_or(0);
+    furthest <= movi_len || (furthest >= movi_start && furthest <= movi_start + movi_len)
+}
+
+/// Chunk IDs are four printable ASCII characters
+fn is_fourcc(id: &[u8]) -> bool {
+    id.iter().all(|&b| (0x20..0x7F).contains(&b))
+}
+
+fn u32_at(data: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(id: &[u8], body: &[u8]) -> Vec<u8> {
+        let mut out = id.to_vec();
+        out.extend((body.len() as u32).to_le_bytes());
+        out.extend(body);
+        if body.len() % 2 == 1 {
+            out.push(0);
+        }
+        out
+    }
+
+    fn riff(form: &[u8], frames: usize, index: bool) -> Vec<u8> {
+        let mut movi = b"movi".to_vec();
+        let mut entries = Vec::new();
+        for _ in 0..frames {
+            let offset = movi.len() as u32;
+            movi.extend(chunk(b"00dc", &[0xAB; 301]));
+            entries.extend(*b"00dc");
+            entries.extend(0x10u32.to_le_bytes());
+            entries.extend(offset.to_le_bytes());
+            entries.extend(301u32.to_le_bytes());
+        }
+        let mut body = form.to_vec();
+        body.extend(chunk(b"LIST", b"hdrlavih...................."));
+        body.extend(chunk(b"LIST", &movi));
+        if index {
+            body.extend(chunk(b"idx1", &entries));
+        }
+        chunk(b"RIFF", &body)
+    }
+
+    #[test]
+    fn test_opendml_extensions_are_included() {
+        let mut avi = riff(b"AVI ", 4, true);
+        let first = avi.len() as u64;
+        avi.extend(riff(b"AVIX", 10, false));
+        avi.extend(riff(b"AVIX", 10, false));
+        let mut carved = avi.clone();
+        carved.extend(b"RIFF\x10\x00\x00\x00WAVEfmt ");
+
+        assert_eq!(parse_size(&carved), Some(avi.len() as u64));
+        // A legacy parser stops at the first RIFF
+        assert_eq!(
+            crate::carve::signatures::parse_riff_size(&carved),
+            Some(first)
+        );
+    }
+
+    #[test]
+    fn test_crashed_recording_ends_after_frames() {
+        let mut avi = riff(b"AVI ", 6, false);
+        avi[4..8].copy_from_slice(&0u32.to_le_bytes());
+        let mut carved = avi.clone();
+        carved.extend([0xFF; 64]);
+        assert_eq!(parse_size(&carved), Some(avi.len() as u64));
+
+        // An index pointing past the frames: the declared size is not
+        // trusted, so no extensions are followed
+        let mut spliced = riff(b"AVI ", 2, true);
+        let len = spliced.len();
+        spliced[len - 8..len - 4].copy_from_slice(&9000u32.to_le_bytes());
+        spliced.extend(riff(b"AVIX", 3, false));
+        assert_eq!(parse_size(&spliced), Some(len as u64));
+
+        assert_eq!(parse_size(&riff(b"WAVE", 2, false)), None);
+    }
+}
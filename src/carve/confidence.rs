@@ -0,0 +1,90 @@
+//! Confidence scoring for carved files
+//!
+//! Each carve gets a score from 0 to 100 for how likely it is a whole,
+//! real file. The score starts from how its end was found (a length field
+//! beats a footer, which beats running into the next header or the size
+//! cap), then moves with structural validation (OOXML packages, SQLite
+//! pages), content-type verification, and overlap with unreadable regions.
+//! Heuristic text carves keep their own language score.
+
+use super::{BoundaryMethod, CarvedFile, Integrity};
+
+/// Confidence in `file`, 0-100. `content_match` is whether content-type
+/// detection agreed with the signature (None when it found nothing or
+/// didn't run).
+pub fn score(file: &CarvedFile, content_match: Option<bool>) -> u8 {
+    let mut score: i32 = match file.boundary_method {
+        BoundaryMethod::InternalSize => 60,
+        BoundaryMethod::FooterScan => 50,
+        BoundaryMethod::NextHeader => 30,
+        BoundaryMethod::MaxSizeCap => 10,
+        BoundaryMethod::TextRun => return file.confidence.unwrap_or(0),
+    };
+    score += match file.integrity {
+        Some(Integrity::Intact) => 30,
+        Some(Integrity::Repaired) => 15,
+        Some(Integrity::Corrupt) => -30,
+        None => 0,
+    };
+    if let Some(check) = &file.page_check {
+        score += if check.is_intact() { 30 } else { -30 };
+    }
+    score += match content_match {
+        Some(true) => 20,
+        Some(false) => -20,
+        None => 0,
+    };
+    if file.bad_region_bytes > 0 {
+        score -= 25;
+    }
+    score.clamp(0, 100) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::FileType;
+
+    fn carved(boundary_method: BoundaryMethod) -> CarvedFile {
+        CarvedFile {
+            offset: 0,
+            size: 4096,
+            signature_name: "JPEG".to_string(),
+            extension: "jpg".to_string(),
+            file_type: FileType::Image,
+            boundary_method,
+            hash: None,
+            bad_region_bytes: 0,
+            path: None,
+            page_check: None,
+            integrity: None,
+            confidence: None,
+        }
+    }
+
+    #[test]
+    fn test_score_ranks_evidence() {
+        let sized = carved(BoundaryMethod::InternalSize);
+        assert_eq!(score(&sized, Some(true)), 80);
+        assert_eq!(score(&carved(BoundaryMethod::FooterScan), Some(true)), 70);
+        assert_eq!(score(&carved(BoundaryMethod::MaxSizeCap), None), 10);
+        assert_eq!(score(&carved(BoundaryMethod::NextHeader), Some(false)), 10);
+
+        let document = CarvedFile {
+            integrity: Some(Integrity::Intact),
+            ..sized.clone()
+        };
+        assert_eq!(score(&document, Some(true)), 100);
+        let damaged = CarvedFile {
+            bad_region_bytes: 512,
+            ..sized
+        };
+        assert_eq!(score(&damaged, None), 35);
+
+        let text = CarvedFile {
+            confidence: Some(64),
+            ..carved(BoundaryMethod::TextRun)
+        };
+        assert_eq!(score(&text, None), 64);
+    }
+}
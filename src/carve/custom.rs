@@ -0,0 +1,281 @@
+//! User-defined carve signatures loaded at runtime
+//!
+//! `carve --signatures my_sigs.toml` adds formats the built-in database
+//! doesn't know, without recompiling:
+//!
+//! ```toml
+//! [[signature]]
+//! name = "Acme Project"
+//! extension = "acp"
+//! file_type = "document"      # optional, guessed from the extension
+//! header = "41 43 4D 45 01"   # hex, spaces optional
+//! header_offset = 0           # optional
+//! footer = "45 4E 44 21"      # optional
+//! max_size = "50MB"           # or a number of bytes
+//! ```
+//!
+//! A signature can also name a WASM module that computes the file's length
+//! (see [`super::plugin`]). A signature named like a built-in one replaces it. Custom signatures are
+//! tried before the built-in ones, so a longer magic can refine a shorter
+//! built-in match.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+
+use super::plugin::{SizePlugin, DEFAULT_PARSER_WINDOW};
+use super::signatures::FileSignature;
+use crate::core::{parse_size_str, FileType};
+
+/// Largest header offset accepted (the scan overlaps chunks by this much)
+const MAX_HEADER_OFFSET: usize = 64 * 1024;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SignatureFile {
+    #[serde(default)]
+    signature: Vec<SignatureSpec>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SignatureSpec {
+    name: String,
+    extension: String,
+    file_type: Option<String>,
+    header: String,
+    #[serde(default)]
+    header_offset: usize,
+    footer: Option<String>,
+    max_size: SizeSpec,
+    size_parser: Option<PathBuf>,
+    parser_window: Option<SizeSpec>,
+}
+
+/// A signature from a signatures file, with its size parser plugin if any
+#[derive(Debug, Clone)]
+pub struct CustomSignature {
+    pub signature: FileSignature,
+    pub size_plugin: Option<SizePlugin>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum SizeSpec {
+    Bytes(u64),
+    Text(String),
+}
+
+/// Load the signatures defined in `path`
+pub fn load_signatures(path: &Path) -> Result<Vec<CustomSignature>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read signatures: {}", path.display()))?;
+    let base = path.parent().unwrap_or(Path::new("."));
+    parse_signatures(&text, base)
+        .with_context(|| format!("Invalid signatures file: {}", path.display()))
+}
+
+/// Parse a signatures file (see the module docs for the format); plugin
+/// paths are relative to `base`
+pub fn parse_signatures(text: &str, base: &Path) -> Result<Vec<CustomSignature>> {
+    let file: SignatureFile = toml::from_str(text)?;
+    file.signature
+        .into_iter()
+        .map(|spec| {
+            let name = spec.name.clone();
+            spec.into_custom(base)
+                .with_context(|| format!("Signature \"{}\"", name))
+        })
+        .collect()
+}
+
+/// `custom` signatures first, then the built-in ones they don't replace
+pub fn merge_signatures(
+    builtin: Vec<FileSignature>,
+    custom: &[CustomSignature],
+) -> Vec<FileSignature> {
+    let mut merged: Vec<FileSignature> = custom.iter().map(|c| c.signature.clone()).collect();
+    merged.extend(builtin.into_iter().filter(|b| {
+        !custom
+            .iter()
+            .any(|c| c.signature.name.eq_ignore_ascii_case(b.name))
+    }));
+    merged
+}
+
+impl SignatureSpec {
+    fn into_custom(mut self, base: &Path) -> Result<CustomSignature> {
+        let size_plugin = match self.size_parser.take() {
+            Some(module) => {
+                let window = match &self.parser_window {
+                    Some(spec) => spec.bytes().context("Invalid parser_window")?,
+                    None => DEFAULT_PARSER_WINDOW as u64,
+                };
+                Some(SizePlugin::load(&base.join(module), window as usize)?)
+            }
+            None => None,
+        };
+        Ok(CustomSignature {
+            signature: self.into_signature()?,
+            size_plugin,
+        })
+    }
+
+    fn into_signature(self) -> Result<FileSignature> {
+        let header = parse_hex(&self.header).context("Invalid header")?;
+        if header.is_empty() {
+            bail!("Header is empty");
+        }
+        if self.header_offset > MAX_HEADER_OFFSET {
+            bail!(
+                "Header offset {} is beyond the {} bytes supported",
+                self.header_offset,
+                MAX_HEADER_OFFSET
+            );
+        }
+        let footer = match &self.footer {
+            Some(hex) => Some(parse_hex(hex).context("Invalid footer")?),
+            None => None,
+        };
+        if footer.as_ref().is_some_and(|f| f.is_empty()) {
+            bail!("Footer is empty");
+        }
+        let max_size = self.max_size.bytes().context("Invalid max_size")?;
+        let extension = self.extension.trim_start_matches('.').to_lowercase();
+        if extension.is_empty() {
+            bail!("Extension is empty");
+        }
+        let file_type = match &self.file_type {
+            Some(name) => parse_file_type(name)?,
+            None => FileType::from_extension(&extension),
+        };
+
+        // Loaded once per run and kept for the rest of it, so leaking
+        // gives the 'static data the signature table is built from
+        Ok(FileSignature {
+            name: Box::leak(self.name.into_boxed_str()),
+            extension: Box::leak(extension.into_boxed_str()),
+            file_type,
+            header: Box::leak(header.into_boxed_slice()),
+            header_offset: self.header_offset,
+            footer: footer.map(|f| &*Box::leak(f.into_boxed_slice())),
+            max_size,
+            size_parser: None,
+        })
+    }
+}
+
+impl SizeSpec {
+    fn bytes(&self) -> Result<u64> {
+        let bytes = match self {
+            SizeSpec::Bytes(n) => Some(*n),
+            SizeSpec::Text(s) => parse_size_str(s),
+        };
+        match bytes {
+            Some(n) if n > 0 => Ok(n),
+            _ => bail!("Expected a size like 4096 or \"50MB\""),
+        }
+    }
+}
+
+fn parse_hex(text: &str) -> Result<Vec<u8>> {
+    let digits: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    let digits = digits.strip_prefix("0x").unwrap_or(&digits);
+    Ok(hex::decode(digits)?)
+}
+
+fn parse_file_type(name: &str) -> Result<FileType> {
+    Ok(match name.to_lowercase().as_str() {
+        "image" => FileType::Image,
+        "video" => FileType::Video,
+        "audio" => FileType::Audio,
+        "document" => FileType::Document,
+        "archive" => FileType::Archive,
+        "code" => FileType::Code,
+        "executable" => FileType::Executable,
+        "database" => FileType::Database,
+        "other" => FileType::Other,
+        _ => bail!("Unknown file_type \"{}\"", name),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::carve::signatures::all_signatures;
+    use crate::carve::{CarveOptions, Carver};
+
+    #[test]
+    fn test_custom_signature_is_carved() {
+        let custom = parse_signatures(
+            r#"
+[[signature]]
+name = "Acme Project"
+extension = ".ACP"
+header = "41 43 4D 45 01"
+footer = "45 4E 44 21"
+max_size = "1MB"
+
+[[signature]]
+name = "jpeg"
+extension = "jpg"
+file_type = "image"
+header = "0xFFD8FFE1"
+max_size = 4096
+"#,
+            Path::new("."),
+        )
+        .unwrap();
+        assert_eq!(custom[0].signature.extension, "acp");
+        assert_eq!(custom[0].signature.file_type, FileType::Other);
+        assert_eq!(custom[1].signature.max_size, 4096);
+        assert!(custom[0].size_plugin.is_none());
+
+        let merged = merge_signatures(all_signatures(), &custom);
+        assert_eq!(merged.len(), all_signatures().len() + 1);
+        assert_eq!(merged.iter().filter(|s| s.extension == "jpg").count(), 1);
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut image = vec![0u8; 4096];
+        let file = b"ACME\x01 project data END!";
+        image[1024..1024 + file.len()].copy_from_slice(file);
+        let source = dir.path().join("disk.img");
+        std::fs::write(&source, &image).unwrap();
+
+        let carver = Carver::new(CarveOptions {
+            source,
+            output_dir: dir.path().join("out"),
+            min_size: 1,
+            extra_signatures: custom,
+            ..Default::default()
+        });
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let (files, _) = rt.block_on(carver.carve()).unwrap();
+        let acp = files.iter().find(|f| f.extension == "acp").unwrap();
+        assert_eq!(acp.offset, 1024);
+        assert_eq!(acp.size, file.len() as u64);
+    }
+
+    #[test]
+    fn test_invalid_signatures_are_reported() {
+        let error = parse_signatures(
+            r#"
+[[signature]]
+name = "Broken"
+extension = "brk"
+header = "4G"
+max_size = 10
+"#,
+            Path::new("."),
+        )
+        .unwrap_err();
+        assert_eq!(
+            format!("{:#}", error).split(':').next(),
+            Some("Signature \"Broken\"")
+        );
+
+        let typo = "[[signature]]\nname = \"x\"\nheadr = \"00\"";
+        assert!(parse_signatures(typo, Path::new(".")).is_err());
+    }
+}
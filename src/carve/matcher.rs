@@ -0,0 +1,128 @@
+//! Multi-pattern header matching for byte-level scans
+//!
+//! Probing every byte against the first-byte index costs a lookup and a
+//! comparison per offset, and common first bytes (0x00, 0xFF) send most
+//! offsets into the comparison. All signature headers instead go into one
+//! Aho-Corasick automaton, built once per carver, whose SIMD prefilter
+//! (memchr, Teddy) skips bytes no header can start at. Offset signatures
+//! (MP4 `ftyp` at +4, TAR `ustar` at +257) are matched at their magic and
+//! mapped back to where the file would start.
+
+use aho_corasick::AhoCorasick;
+
+use super::signatures::FileSignature;
+
+/// Every signature header in one automaton
+pub struct HeaderMatcher {
+    automaton: AhoCorasick,
+    /// Signature index and header offset of each pattern
+    patterns: Vec<(usize, usize)>,
+    /// How far past a file start the furthest header ends
+    reach: usize,
+}
+
+impl HeaderMatcher {
+    pub fn new(sigs: &[FileSignature]) -> Self {
+        let automaton = AhoCorasick::new(sigs.iter().map(|s| s.header))
+            .expect("signature headers fit in an automaton");
+        let patterns = sigs
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (i, s.header_offset))
+            .collect();
+        let reach = sigs
+            .iter()
+            .map(|s| s.header_offset + s.header.len())
+            .max()
+            .unwrap_or(0);
+        Self {
+            automaton,
+            patterns,
+            reach,
+        }
+    }
+
+    /// File starts in `start..end` as `(offset, signature index)`, in
+    /// offset order. Headers may extend past `end`. Where several
+    /// signatures match one start, a header at the start beats one at an
+    /// offset, then the earlier signature wins, as in a per-byte probe.
+    pub fn find(&self, data: &[u8], start: usize, end: usize) -> Vec<(usize, usize)> {
+        let end = end.min(data.len());
+        if start >= end {
+            return Vec::new();
+        }
+        let haystack = &data[start..(end + self.reach).min(data.len())];
+
+        let mut found: Vec<(usize, bool, usize)> = self
+            .automaton
+            .find_overlapping_iter(haystack)
+            .filter_map(|m| {
+                let (sig_idx, header_offset) = self.patterns[m.pattern().as_usize()];
+                let file_start = (start + m.start()).checked_sub(header_offset)?;
+                (file_start >= start && file_start < end).then_some((
+                    file_start,
+                    header_offset > 0,
+                    sig_idx,
+                ))
+            })
+            .collect();
+        found.sort_unstable();
+        found.dedup_by_key(|f| f.0);
+        found
+            .into_iter()
+            .map(|(offset, _, sig_idx)| (offset, sig_idx))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::carve::signatures::all_signatures;
+
+    /// The per-byte probe the automaton replaces
+    fn probe(sigs: &[FileSignature], data: &[u8], pos: usize) -> Option<usize> {
+        let at = |sig: &FileSignature| {
+            data.get(pos + sig.header_offset..pos + sig.header_offset + sig.header.len())
+                == Some(sig.header)
+        };
+        let first = sigs.iter().position(|s| s.header_offset == 0 && at(s));
+        first.or_else(|| sigs.iter().position(|s| s.header_offset > 0 && at(s)))
+    }
+
+    #[test]
+    fn test_matches_per_byte_probe() {
+        let sigs = all_signatures();
+        let matcher = HeaderMatcher::new(&sigs);
+
+        let mut data = vec![0u8; 8192];
+        let mut rng = 0x2545_F491_4F6C_DD1Du64;
+        for byte in data.iter_mut() {
+            rng ^= rng << 13;
+            rng ^= rng >> 7;
+            rng ^= rng << 17;
+            *byte = rng as u8;
+        }
+        // Plant every header at an odd offset, plus MP4 and TAR at offsets
+        for (i, sig) in sigs
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.header_offset < 512)
+        {
+            let at = 101 + i * 13 + sig.header_offset;
+            data[at..at + sig.header.len()].copy_from_slice(sig.header);
+        }
+
+        let expected: Vec<(usize, usize)> = (0..data.len())
+            .filter_map(|pos| Some((pos, probe(&sigs, &data, pos)?)))
+            .collect();
+        assert!(expected.len() > sigs.len() / 2);
+        assert_eq!(matcher.find(&data, 0, data.len()), expected);
+
+        // Split at a boundary a header straddles: nothing lost or doubled
+        let split = expected[3].0 + 1;
+        let mut halves = matcher.find(&data, 0, split);
+        halves.extend(matcher.find(&data, split, data.len()));
+        assert_eq!(halves, expected);
+    }
+}
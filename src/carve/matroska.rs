@@ -0,0 +1,238 @@
+//! Matroska (MKV) and WebM size parsing for carving
+//!
+//! A Matroska file is an EBML header followed by one Segment element whose
+//! size field covers the rest of the file. The Segment's top-level children
+//! (SeekHead, Info, Tracks, Clusters, Cues, ...) are walked up to that
+//! size, so a file cut short or partly overwritten ends after its last
+//! whole element instead of at the next header. Live recorders write the
+//! Segment, and often each Cluster, with an unknown size; those are walked
+//! too, stopping at the first element that can't belong to them.
+
+/// EBML header element ID, the first four bytes of every file
+pub const MAGIC: &[u8] = &[0x1A, 0x45, 0xDF, 0xA3];
+
+const EBML: u32 = 0x1A45_DFA3;
+const DOC_TYPE: u32 = 0x4282;
+const SEGMENT: u32 = 0x1853_8067;
+const CLUSTER: u32 = 0x1F43_B675;
+
+const VOID: u32 = 0xEC;
+const CRC32: u32 = 0xBF;
+
+/// Elements allowed directly inside the Segment
+const SEGMENT_CHILDREN: &[u32] = &[
+    0x114D_9B74, // SeekHead
+    0x1549_A966, // Info
+    0x1654_AE6B, // Tracks
+    CLUSTER,
+    0x1C53_BB6B, // Cues
+    0x1941_A469, // Attachments
+    0x1043_A770, // Chapters
+    0x1254_C367, // Tags
+    VOID,
+    CRC32,
+];
+
+/// Elements allowed directly inside a Cluster
+const CLUSTER_CHILDREN: &[u32] = &[
+    0xE7,   // Timestamp
+    0x5854, // SilentTracks
+    0xA7,   // Position
+    0xAB,   // PrevSize
+    0xA3,   // SimpleBlock
+    0xA0,   // BlockGroup
+    0xAF,   // EncryptedBlock
+    VOID, CRC32,
+];
+
+/// An element's header
+struct Element {
+    id: u32,
+    /// Start of the element's data
+    data: usize,
+    /// Data length, None when written as unknown
+    size: Option<usize>,
+}
+
+/// Length of the Matroska file `data` starts with
+pub fn parse_size(data: &[u8]) -> Option<u64> {
+    let header = element(data, 0)?;
+    if header.id != EBML {
+        return None;
+    }
+    let segment = element(data, header.data + header.size?)?;
+    if segment.id != SEGMENT {
+        return None;
+    }
+    let declared_end = segment.size.map(|size| segment.data.saturating_add(size));
+
+    let mut pos = segment.data;
+    let mut saw_cluster = false;
+    while Some(pos) != declared_end {
+        let Some(child) = element(data, pos) else {
+            break;
+        };
+        if !SEGMENT_CHILDREN.contains(&child.id) {
+            break;
+        }
+        let end = match child.size {
+            Some(size) => child.data.saturating_add(size),
+            None if child.id == CLUSTER => walk(data, child.data, CLUSTER_CHILDREN),
+            None => break,
+        };
+        if end > data.len() {
+            break;
+        }
+        saw_cluster |= child.id == CLUSTER;
+        pos = end;
+    }
+    // Short of the declared end, what was walked is still playable
+    (saw_cluster || Some(pos) == declared_end).then_some(pos as u64)
+}
+
+/// The document type from the EBML header: "matroska" or "webm"
+pub fn doc_type(data: &[u8]) -> Option<&[u8]> {
+    let header = element(data, 0)?;
+    if header.id != EBML {
+        return None;
+    }
+    let end = header.data + header.size?;
+    let mut pos = header.data;
+    while pos < end {
+        let child = element(data, pos)?;
+        let child_end = child.data + child.size?;
+        if child.id == DOC_TYPE {
+            return data.get(child.data..child_end);
+        }
+        pos = child_end;
+    }
+    None
+}
+
+/// End of the run of elements with known sizes and IDs in `allowed`
+/// starting at `pos`
+fn walk(data: &[u8], mut pos: usize, allowed: &[u32]) -> usize {
+    while let Some(child) = element(data, pos) {
+        if !allowed.contains(&child.id) {
+            break;
+        }
+        match child.size {
+            Some(size) if child.data.saturating_add(size) <= data.len() => {
+                pos = child.data + size;
+            }
+            _ => break,
+        }
+    }
+    pos
+}
+
+fn element(data: &[u8], pos: usize) -> Option<Element> {
+    let (id, id_len) = read_id(data, pos)?;
+    let (size, size_len) = read_size(data, pos + id_len)?;
+    Some(Element {
+        id,
+        data: pos + id_len + size_len,
+        size: size.map(|s| usize::try_from(s).unwrap_or(usize::MAX)),
+    })
+}
+
+/// An element ID: 1 to 4 bytes, length marker kept
+fn read_id(data: &[u8], pos: usize) -> Option<(u32, usize)> {
+    let len = data.get(pos)?.leading_zeros() as usize + 1;
+    if len > 4 {
+        return None;
+    }
+    let bytes = data.get(pos..pos + len)?;
+    Some((bytes.iter().fold(0, |id, &b| id << 8 | b as u32), len))
+}
+
+/// A data size: 1 to 8 bytes, length marker dropped; all ones means unknown
+fn read_size(data: &[u8], pos: usize) -> Option<(Option<u64>, usize)> {
+    let first = *data.get(pos)?;
+    let len = first.leading_zeros() as usize + 1;
+    if len > 8 {
+        return None;
+    }
+    let bytes = data.get(pos..pos + len)?;
+    let marker_free = (first as u16 & (0xFF >> len)) as u64;
+    let value = bytes[1..]
+        .iter()
+        .fold(marker_free, |v, &b| v << 8 | b as u64);
+    let unknown = value == (1u64 << (7 * len)) - 1;
+    Some(((!unknown).then_some(value), len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An element with an 8-byte size, or unknown size when `payload` is None
+    fn element(id: u32, payload: Option<&[u8]>) -> Vec<u8> {
+        let mut out: Vec<u8> = id
+            .to_be_bytes()
+            .into_iter()
+            .skip_while(|&b| b == 0)
+            .collect();
+        match payload {
+            Some(payload) => {
+                out.push(0x01);
+                out.extend_from_slice(&(payload.len() as u64).to_be_bytes()[1..]);
+                out.extend_from_slice(payload);
+            }
+            None => out.push(0xFF),
+        }
+        out
+    }
+
+    fn cluster() -> Vec<u8> {
+        let mut cluster = element(0xE7, Some(&[0]));
+        cluster.extend(element(0xA3, Some(&[0x55; 500])));
+        cluster
+    }
+
+    fn file(doc_type: &str, sized: bool) -> Vec<u8> {
+        let mut out = element(EBML, Some(&element(DOC_TYPE, Some(doc_type.as_bytes()))));
+        let mut body = element(0x1549_A966, Some(&[0u8; 20]));
+        body.extend(element(0x1654_AE6B, Some(&[0u8; 40])));
+        for _ in 0..3 {
+            if sized {
+                body.extend(element(CLUSTER, Some(&cluster())));
+            } else {
+                body.extend(element(CLUSTER, None));
+                body.extend(cluster());
+            }
+        }
+        if sized {
+            out.extend(element(SEGMENT, Some(&body)));
+        } else {
+            out.extend(element(SEGMENT, None));
+            out.extend(body);
+        }
+        out
+    }
+
+    #[test]
+    fn test_segment_size_bounds_file() {
+        let mkv = file("matroska", true);
+        let mut carved = mkv.clone();
+        carved.extend([0u8; 300]);
+        assert_eq!(parse_size(&carved), Some(mkv.len() as u64));
+        assert_eq!(doc_type(&carved), Some(&b"matroska"[..]));
+
+        // Cut inside the last cluster: the first two still play
+        let cut = &mkv[..mkv.len() - 100];
+        let two_clusters = mkv.len() - element(CLUSTER, Some(&cluster())).len();
+        assert_eq!(parse_size(cut), Some(two_clusters as u64));
+    }
+
+    #[test]
+    fn test_live_recording_is_walked() {
+        let webm = file("webm", false);
+        let mut carved = webm.clone();
+        carved.extend(b"\x89PNG\r\n\x1a\n");
+        assert_eq!(parse_size(&carved), Some(webm.len() as u64));
+        assert_eq!(doc_type(&carved), Some(&b"webm"[..]));
+
+        assert_eq!(parse_size(b"\x1A\x45\xDF\xA3\x80\x00"), None);
+    }
+}
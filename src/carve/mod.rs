@@ -10,15 +10,61 @@
 //! - **Parallel chunks**: Image split into N chunks (one per CPU core),
 //!   each scanned independently with rayon, overlapping by `max_header_size`
 //!   to catch headers that straddle chunk boundaries
-//! - **Signature dispatch**: First-byte index for O(1) candidate lookup,
-//!   then full header match
+//! - **Signature dispatch**: Byte-level scans run every header through one
+//!   Aho-Corasick automaton with a SIMD prefilter (see [`matcher`]);
+//!   sector-aligned scans use a first-byte index, then a full header match
 //! - **Smart sizing**: Per-format size parsers read internal length fields
-//!   (PNG chunks, RIFF sizes, BMP headers, ZIP EOCD) before falling back
-//!   to footer scanning
+//!   (PNG chunks, RIFF sizes, BMP headers, ZIP central directories) before
+//!   falling back to footer scanning
+//! - **ZIP archives**: Walked entry by entry to the end record, ZIP64 and
+//!   split-archive first volumes included (see [`zip`])
+//! - **Video containers**: MKV/WebM element trees and AVI chunk lists
+//!   (with OpenDML extensions past the first RIFF) are walked to their
+//!   real end (see [`matroska`], [`avi`])
+//! - **MP4 faststart**: Optionally, a `moov` written after the media is
+//!   moved to the front so carved videos play before fully read (see
+//!   [`mp4`])
+//! - **Office documents**: Carved docx/xlsx/pptx packages are validated
+//!   entry by entry, trimmed to their end record or given a rebuilt
+//!   central directory when truncated (see [`ooxml`])
+//! - **SQLite databases**: Sized from the database header, then every page
+//!   is validated (checksums where present) to flag fragmented carves
+//! - **Text and logs**: Optionally, runs of printable text in the bytes no
+//!   carved file covers are carved as `.txt` with a confidence score (see
+//!   [`text`])
+//! - **Read-ahead**: Optionally, each scan worker's range is read through
+//!   an I/O backend (io_uring, overlapped I/O) a window ahead of the scan,
+//!   so the memory map hits the page cache (see [`crate::iobackend`])
 //! - **Sector alignment**: Optional 512-byte alignment for true disk images
-
+//! - **ddrescue mapfiles**: Unreadable regions are skipped during the scan
+//!   and carved files overlapping them are flagged
+//! - **Encrypted volumes**: With an unlock key, a BitLocker or LUKS volume
+//!   is decrypted into anonymous memory and carved there; offsets are then
+//!   relative to the plaintext volume
+//! - **Custom signatures**: Formats defined in a TOML file at runtime are
+//!   merged with the built-in database (see [`custom`]), optionally with a
+//!   sandboxed WASM size parser (see [`plugin`])
+//! - **Results listing**: `carve` saves what it found as `carve-results.json`
+//!   so files can be browsed and re-extracted with another boundary later
+
+pub mod avi;
+pub mod confidence;
+pub mod custom;
+pub mod matcher;
+pub mod matroska;
+pub mod mp4;
+pub mod ooxml;
+pub mod plugin;
+mod results;
 pub mod signatures;
+pub mod sqlite;
+pub mod text;
+pub mod tiff;
+pub mod zip;
+
+pub use results::{CarveListing, ReextractBoundary, CARVE_LISTING_FILE};
 
+use std::io::{self, Read};
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
@@ -29,9 +75,18 @@ use chrono::Utc;
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 
+use crate::badsector::RescueMap;
 use crate::core::{FileEntry, FileType};
+use crate::diskimage::unlock::{self, EncryptedVolume, UnlockKey};
+use crate::error::{DrillError, DrillResult};
+use crate::events::{self, Event};
+use crate::iobackend::{IoOptions, ReadAhead};
+use crate::throttle::Throttle;
 use signatures::*;
 
+/// Scanned bytes charged to the throttle at a time
+const THROTTLE_BLOCK: usize = 1024 * 1024;
+
 /// A carved file found in a raw image
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CarvedFile {
@@ -49,6 +104,22 @@ pub struct CarvedFile {
     pub boundary_method: BoundaryMethod,
     /// Blake3 hash of extracted content
     pub hash: Option<String>,
+    /// Bytes of this file lying in regions the mapfile marks unreadable
+    #[serde(default)]
+    pub bad_region_bytes: u64,
+    /// Where the file was extracted (None in dry runs)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    /// Page validation of a carved SQLite database
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_check: Option<sqlite::PageCheck>,
+    /// Outcome of structural validation, for formats that get one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub integrity: Option<Integrity>,
+    /// How likely the carve is a whole, real file, 0-100 (see
+    /// [`confidence`]); None in listings from before scoring
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<u8>,
 }
 
 /// How the end of a carved file was determined
@@ -62,6 +133,20 @@ pub enum BoundaryMethod {
     NextHeader,
     /// Hit the max_size cap
     MaxSizeCap,
+    /// End of a run of printable text (heuristic text carving)
+    TextRun,
+}
+
+/// State of a carved file after structural validation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Integrity {
+    /// Structure checked and whole
+    Intact,
+    /// Damaged, but rebuilt into a file that opens
+    Repaired,
+    /// Damaged beyond repair
+    Corrupt,
 }
 
 /// Options for a carve operation
@@ -83,6 +168,25 @@ pub struct CarveOptions {
     pub dry_run: bool,
     /// Verify extracted files with infer crate
     pub verify: bool,
+    /// GNU ddrescue mapfile describing unreadable regions of the image
+    pub mapfile: Option<PathBuf>,
+    /// Cap on image read rate in bytes per second (None = unlimited)
+    pub max_bytes_per_sec: Option<u64>,
+    /// Unlock the BitLocker/LUKS volume in the image and carve its plaintext
+    pub unlock: Option<UnlockKey>,
+    /// User-defined signatures, tried before (and replacing same-named)
+    /// built-in ones
+    pub extra_signatures: Vec<custom::CustomSignature>,
+    /// Move a trailing `moov` to the front of carved MP4/MOV files
+    pub faststart: bool,
+    /// Also carve runs of text at least this long from the bytes no
+    /// carved file covers (None = off)
+    pub text_min_len: Option<usize>,
+    /// Skip extracting carves scored below this confidence (0-100)
+    pub min_confidence: u8,
+    /// Read the image ahead of the scan through this backend (None = leave
+    /// it to the memory map's page faults)
+    pub io: Option<IoOptions>,
 }
 
 impl Default for CarveOptions {
@@ -96,6 +200,14 @@ impl Default for CarveOptions {
             workers: num_cpus::get(),
             dry_run: false,
             verify: true,
+            mapfile: None,
+            max_bytes_per_sec: None,
+            unlock: None,
+            extra_signatures: Vec::new(),
+            faststart: false,
+            text_min_len: None,
+            min_confidence: 0,
+            io: None,
         }
     }
 }
@@ -111,10 +223,32 @@ pub struct CarveResult {
     pub image_size: u64,
     pub duration_ms: u64,
     pub by_type: std::collections::HashMap<String, usize>,
+    /// Image bytes skipped because the mapfile marks them unreadable
+    #[serde(default)]
+    pub bytes_skipped: u64,
+    /// Carved files that overlap an unreadable region
+    #[serde(default)]
+    pub files_in_bad_regions: usize,
+    /// Damaged files rebuilt into ones that open
+    #[serde(default)]
+    pub files_repaired: usize,
+    /// Videos rewritten with `moov` in front
+    #[serde(default)]
+    pub files_faststarted: usize,
+    /// Carves not extracted for scoring below the minimum confidence
+    #[serde(default)]
+    pub files_below_confidence: usize,
+    /// BitLocker/LUKS volume found in the image
+    #[serde(default)]
+    pub encrypted_volume: Option<EncryptedVolume>,
+    /// Whether the plaintext of `encrypted_volume` was carved
+    #[serde(default)]
+    pub unlocked: bool,
 }
 
 /// Progress updates emitted during carving
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "stage", rename_all = "snake_case")]
 pub enum CarveProgress {
     /// Scanning phase: bytes_scanned out of total
     Scanning { bytes_scanned: u64, total_bytes: u64 },
@@ -132,11 +266,15 @@ pub struct Carver {
     signatures: Vec<FileSignature>,
     first_byte_index: [Vec<usize>; 256],
     offset_sigs: Vec<(usize, usize)>,
+    matcher: matcher::HeaderMatcher,
+    /// WASM size parsers of custom signatures, by signature name
+    size_plugins: std::collections::HashMap<&'static str, plugin::SizePlugin>,
+    throttle: Throttle,
 }
 
 impl Carver {
     pub fn new(options: CarveOptions) -> Self {
-        let mut sigs = all_signatures();
+        let mut sigs = custom::merge_signatures(all_signatures(), &options.extra_signatures);
 
         if let Some(ref types) = options.file_types {
             sigs.retain(|s| types.contains(&s.file_type));
@@ -144,35 +282,122 @@ impl Carver {
 
         let first_byte_index = build_first_byte_index(&sigs);
         let offset_sigs = build_offset_signatures(&sigs);
+        let matcher = matcher::HeaderMatcher::new(&sigs);
+        let throttle = Throttle::new(options.max_bytes_per_sec);
+        let size_plugins = options
+            .extra_signatures
+            .iter()
+            .filter_map(|c| Some((c.signature.name, c.size_plugin.clone()?)))
+            .collect();
 
         Self {
             options,
             signatures: sigs,
             first_byte_index,
             offset_sigs,
+            matcher,
+            size_plugins,
+            throttle,
+        }
+    }
+
+    /// Pace image reads with a shared throttle instead of one built from
+    /// `max_bytes_per_sec`, so the rate can be changed while carving
+    pub fn with_throttle(mut self, throttle: Throttle) -> Self {
+        self.throttle = throttle;
+        self
+    }
+
+    /// Map the source for scanning: the image itself, or with an unlock key
+    /// the decrypted volume in anonymous memory (never written to disk)
+    fn map_source(&self) -> Result<(memmap2::Mmap, u64, Option<EncryptedVolume>)> {
+        let source = &self.options.source;
+
+        let Some(ref key) = self.options.unlock else {
+            let file = std::fs::File::open(source)
+                .with_context(|| format!("Failed to open image: {}", source.display()))?;
+            let image_size = file.metadata()?.len();
+            let mmap = unsafe {
+                memmap2::Mmap::map(&file)
+                    .with_context(|| format!("Failed to mmap image: {}", source.display()))?
+            };
+            let volume =
+                unlock::find_encrypted_volume(&mut std::io::Cursor::new(&mmap[..]), image_size)
+                    .ok()
+                    .flatten();
+            if let Some(ref volume) = volume {
+                tracing::warn!(%volume, "Image contains an encrypted volume; carve with --unlock to see its contents");
+            }
+            return Ok((mmap, image_size, volume));
+        };
+
+        anyhow::ensure!(
+            self.options.mapfile.is_none(),
+            "A ddrescue mapfile can't be combined with unlocking: its offsets refer to the encrypted image"
+        );
+        let mut reader = unlock::open_unlocked(source, key)?;
+        let size = reader.size();
+        anyhow::ensure!(size > 0, "Unlocked volume is empty");
+        tracing::info!(volume = %reader.volume(), plaintext_size = size, "Unlocked encrypted volume");
+
+        let mut plain = memmap2::MmapMut::map_anon(size as usize)
+            .context("Failed to allocate memory for the decrypted volume")?;
+        for chunk in plain.chunks_mut(THROTTLE_BLOCK) {
+            self.throttle.consume(chunk.len() as u64);
+            reader
+                .read_exact(chunk)
+                .context("Failed to read the decrypted volume")?;
         }
+        let volume = reader.volume().clone();
+        Ok((plain.make_read_only()?, size, Some(volume)))
     }
 
     /// Carve with a progress callback. The callback is called from the
     /// extraction (sequential) phase and after the scan phase completes.
+    /// The same progress is published as [`Event::Carve`].
     pub async fn carve_with_progress<F>(
         &self,
         on_progress: F,
-    ) -> Result<(Vec<CarvedFile>, CarveResult)>
+    ) -> DrillResult<(Vec<CarvedFile>, CarveResult)>
     where
         F: Fn(CarveProgress) + Send + Sync,
     {
+        self.run(on_progress).await.map_err(DrillError::from)
+    }
+
+    async fn run<F>(&self, on_progress: F) -> Result<(Vec<CarvedFile>, CarveResult)>
+    where
+        F: Fn(CarveProgress) + Send + Sync,
+    {
+        let on_progress = |progress: CarveProgress| {
+            events::emit(Event::Carve(progress.clone()));
+            on_progress(progress);
+        };
         let start = Instant::now();
         let source = &self.options.source;
 
-        anyhow::ensure!(source.exists(), "Source image not found: {}", source.display());
+        if !source.exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Source image not found: {}", source.display()),
+            )
+            .into());
+        }
 
-        let file = std::fs::File::open(source)
-            .with_context(|| format!("Failed to open image: {}", source.display()))?;
-        let metadata = file.metadata()?;
-        let image_size = metadata.len();
+        let (mmap, image_size, encrypted_volume) = self.map_source()?;
 
-        anyhow::ensure!(image_size > 0, "Image file is empty");
+        if image_size == 0 {
+            return Err(DrillError::InvalidInput("Image file is empty".to_string()).into());
+        }
+
+        let rescue_map = self.options.mapfile.as_deref().map(RescueMap::load).transpose()?;
+        if let Some(ref map) = rescue_map {
+            tracing::info!(
+                regions = map.regions.len(),
+                untrusted_bytes = map.untrusted_bytes(),
+                "Loaded ddrescue mapfile"
+            );
+        }
 
         tracing::info!(
             source = %source.display(),
@@ -185,10 +410,7 @@ impl Carver {
             "Starting file carve"
         );
 
-        let mmap = Arc::new(unsafe {
-            memmap2::Mmap::map(&file)
-                .with_context(|| format!("Failed to mmap image: {}", source.display()))?
-        });
+        let mmap = Arc::new(mmap);
 
         if !self.options.dry_run {
             std::fs::create_dir_all(&self.options.output_dir)?;
@@ -215,7 +437,17 @@ impl Carver {
                 };
                 let chunk_end = chunk_end.min(image_size as usize);
 
-                let hits = self.scan_chunk(&mmap, chunk_start, chunk_end);
+                let hits = match self.read_ahead(chunk_start, chunk_end) {
+                    None => self.scan_chunk(&mmap, chunk_start, chunk_end, rescue_map.as_ref()),
+                    Some(read_ahead) => (chunk_start..chunk_end)
+                        .step_by(THROTTLE_BLOCK)
+                        .flat_map(|block| {
+                            read_ahead.advance(block as u64);
+                            let block_end = (block + THROTTLE_BLOCK).min(chunk_end);
+                            self.scan_chunk(&mmap, block, block_end, rescue_map.as_ref())
+                        })
+                        .collect(),
+                };
                 sp.fetch_add((chunk_end - chunk_start) as u64, Ordering::Relaxed);
                 hits
             })
@@ -237,11 +469,11 @@ impl Carver {
         on_progress(CarveProgress::ScanComplete { headers_found: hits.len() });
 
         // Phase 2: determine boundaries
-        let carved: Vec<CarvedFile> = hits
+        let mut carved: Vec<CarvedFile> = hits
             .par_iter()
             .enumerate()
             .filter_map(|(i, &(offset, sig_idx))| {
-                let sig = &self.signatures[sig_idx];
+                let sig = self.refine_signature(&mmap, offset, &self.signatures[sig_idx]);
                 let next_offset = hits.get(i + 1).map(|&(o, _)| o);
 
                 match self.determine_size(&mmap, offset, sig, next_offset) {
@@ -254,11 +486,22 @@ impl Carver {
                             file_type: sig.file_type,
                             boundary_method: BoundaryMethod::MaxSizeCap,
                             hash: None,
+                            bad_region_bytes: rescue_map
+                                .as_ref()
+                                .map_or(0, |m| m.overlap(offset, size)),
+                            path: None,
+                            page_check: None,
+                            integrity: None,
+                            confidence: None,
                         };
 
                         carved.boundary_method = self.classify_boundary(
                             &mmap, offset, size, sig, next_offset,
                         );
+                        if sig.header == sqlite::MAGIC {
+                            let end = (offset + size).min(mmap.len() as u64) as usize;
+                            carved.page_check = sqlite::check_pages(&mmap[offset as usize..end]);
+                        }
 
                         Some(carved)
                     }
@@ -267,11 +510,19 @@ impl Carver {
             })
             .collect();
 
+        if let Some(min_len) = self.options.text_min_len {
+            carved.extend(text::carve_gaps(&mmap, &carved, min_len));
+            carved.sort_by_key(|f| f.offset);
+        }
+
         // Phase 3: extract to disk with progress
         let total_to_extract = carved.len();
         let mut result = CarveResult {
             files_found: total_to_extract,
             image_size,
+            bytes_skipped: rescue_map.as_ref().map_or(0, |m| m.overlap(0, image_size)),
+            unlocked: self.options.unlock.is_some(),
+            encrypted_volume,
             ..Default::default()
         };
 
@@ -291,14 +542,51 @@ impl Carver {
             }
 
             let data = &mmap[cf.offset as usize..end];
+            self.throttle.consume(cf.size);
 
+            let mut content_match = None;
             if self.options.verify {
                 if let Some(kind) = infer::get(data) {
+                    content_match = Some(
+                        kind.extension() == cf.extension
+                            || FileType::from_extension(kind.extension()) == cf.file_type,
+                    );
                     cf.extension = kind.extension().to_string();
                     result.files_verified += 1;
                 }
             }
 
+            let mut rebuilt = None;
+            if let Some(validation) = ooxml::validate(data) {
+                cf.extension = validation.extension.to_string();
+                cf.integrity = Some(validation.integrity);
+                match validation.integrity {
+                    Integrity::Intact => cf.size = validation.len as u64,
+                    Integrity::Repaired => rebuilt = validation.rebuilt,
+                    // Left as carved for manual recovery
+                    Integrity::Corrupt => {}
+                }
+            }
+
+            let confidence = confidence::score(&cf, content_match);
+            cf.confidence = Some(confidence);
+            if confidence < self.options.min_confidence {
+                result.files_below_confidence += 1;
+                continue;
+            }
+            if cf.integrity == Some(Integrity::Repaired) {
+                result.files_repaired += 1;
+            }
+
+            if self.options.faststart && mp4::FASTSTART_EXTENSIONS.contains(&cf.extension.as_str())
+            {
+                rebuilt = mp4::faststart(&data[..cf.size as usize]);
+                if rebuilt.is_some() {
+                    result.files_faststarted += 1;
+                }
+            }
+            let data = rebuilt.as_deref().unwrap_or(&data[..cf.size as usize]);
+
             let hash = blake3::hash(data);
             cf.hash = Some(hex::encode(hash.as_bytes()));
 
@@ -319,11 +607,22 @@ impl Carver {
                     result.files_failed += 1;
                     continue;
                 }
+                cf.path = Some(out_path);
                 result.files_extracted += 1;
             } else {
                 result.files_extracted += 1;
             }
 
+            if cf.bad_region_bytes > 0 {
+                tracing::warn!(
+                    offset = cf.offset,
+                    size = cf.size,
+                    bad_bytes = cf.bad_region_bytes,
+                    "Carved file overlaps unreadable region"
+                );
+                result.files_in_bad_regions += 1;
+            }
+
             *result.by_type.entry(cf.extension.clone()).or_insert(0) += 1;
             result.total_bytes_extracted += cf.size;
             final_carved.push(cf);
@@ -346,27 +645,66 @@ impl Carver {
     }
 
     /// Convenience wrapper without progress (for tests and non-interactive use)
-    pub async fn carve(&self) -> Result<(Vec<CarvedFile>, CarveResult)> {
+    pub async fn carve(&self) -> DrillResult<(Vec<CarvedFile>, CarveResult)> {
         self.carve_with_progress(|_| {}).await
     }
 
+    /// Background read of `start..end` of the image ahead of a scan worker,
+    /// when configured and the scan runs over the image itself
+    fn read_ahead(&self, start: usize, end: usize) -> Option<ReadAhead> {
+        let io = self.options.io.filter(|_| self.options.unlock.is_none())?;
+        Some(ReadAhead::spawn(
+            &self.options.source,
+            start as u64..end as u64,
+            io.block_size.unwrap_or(THROTTLE_BLOCK),
+            io,
+        ))
+    }
+
     /// Scan a chunk of the mmap for file headers. Returns (offset, signature_index) pairs.
     ///
     /// When sector_aligned=true, the main loop steps by 512 bytes for offset-0
     /// signatures. For offset-based signatures (ftyp at +4, ustar at +257,
     /// CD001 at +32769), we probe at `pos + header_offset` from each sector
     /// boundary so files starting at sector boundaries are always found.
-    fn scan_chunk(&self, data: &[u8], start: usize, end: usize) -> Vec<(u64, usize)> {
+    ///
+    /// Byte-level scans go through the header automaton instead (see
+    /// [`Self::scan_bytes`]).
+    ///
+    /// Offsets inside regions the rescue map marks unreadable are skipped.
+    fn scan_chunk(
+        &self,
+        data: &[u8],
+        start: usize,
+        end: usize,
+        rescue_map: Option<&RescueMap>,
+    ) -> Vec<(u64, usize)> {
+        if !self.options.sector_aligned {
+            return self.scan_bytes(data, start, end, rescue_map);
+        }
         let mut hits = Vec::new();
-        let step = if self.options.sector_aligned { 512 } else { 1 };
         let end = end.min(data.len());
+        let mut pos = (start + 511) & !511;
 
-        let mut pos = start;
-        if self.options.sector_aligned {
-            pos = (pos + 511) & !511;
-        }
+        let mut bad_regions = rescue_map.map_or(&[][..], |m| m.regions_from(pos as u64));
+        // Start of the bytes scanned but not yet charged to the throttle
+        let mut paced = pos;
 
         while pos < end {
+            if pos - paced >= THROTTLE_BLOCK {
+                self.throttle.consume((pos - paced) as u64);
+                paced = pos;
+            }
+            while bad_regions.first().is_some_and(|r| r.end() <= pos as u64) {
+                bad_regions = &bad_regions[1..];
+            }
+            if let Some(region) = bad_regions.first().filter(|r| r.pos <= pos as u64) {
+                self.throttle.consume((pos - paced) as u64);
+                pos = (region.end() as usize + 511) & !511;
+                paced = pos;
+                continue;
+            }
+
             // Fast path: first-byte index lookup for signatures at offset 0
             let byte = data[pos];
             for &sig_idx in &self.first_byte_index[byte as usize] {
@@ -397,12 +735,48 @@ impl Carver {
                 }
             }
 
-            pos += step;
+            pos += 512;
         }
+        self.throttle.consume(end.saturating_sub(paced) as u64);
 
         hits
     }
 
+    /// Scan every offset of a chunk with the header automaton, one
+    /// throttle block at a time
+    fn scan_bytes(
+        &self,
+        data: &[u8],
+        start: usize,
+        end: usize,
+        rescue_map: Option<&RescueMap>,
+    ) -> Vec<(u64, usize)> {
+        let mut hits = Vec::new();
+        let end = end.min(data.len());
+        let mut bad_regions = rescue_map.map_or(&[][..], |m| m.regions_from(start as u64));
+
+        for block in (start..end).step_by(THROTTLE_BLOCK) {
+            let block_end = (block + THROTTLE_BLOCK).min(end);
+            while bad_regions.first().is_some_and(|r| r.end() <= block as u64) {
+                bad_regions = &bad_regions[1..];
+            }
+            let in_block = || bad_regions.iter().take_while(|r| r.pos < block_end as u64);
+            let unreadable: u64 = in_block()
+                .map(|r| r.end().min(block_end as u64) - r.pos.max(block as u64))
+                .sum();
+            self.throttle
+                .consume((block_end - block) as u64 - unreadable);
+
+            for (offset, sig_idx) in self.matcher.find(data, block, block_end) {
+                let offset = offset as u64;
+                if !in_block().any(|r| r.pos <= offset && offset < r.end()) {
+                    hits.push((offset, sig_idx));
+                }
+            }
+        }
+        hits
+    }
+
     /// Determine the size of a carved file using (in order):
     /// 1. Internal size parser
     /// 2. Footer scan
@@ -423,11 +797,9 @@ impl Carver {
 
         // 1. Internal size parser (most precise, uses format-specific fields)
         let slice_full = &data[start..max_end];
-        if let Some(parser) = sig.size_parser {
-            if let Some(size) = parser(slice_full) {
-                if size >= self.options.min_size && (start + size as usize) <= data.len() {
-                    return Some(size);
-                }
+        if let Some(size) = self.internal_size(slice_full, sig) {
+            if size >= self.options.min_size && (start + size as usize) <= data.len() {
+                return Some(size);
             }
         }
 
@@ -458,6 +830,15 @@ impl Carver {
         None
     }
 
+    /// Length of the file `data` starts with, from the signature's size
+    /// parser or WASM plugin
+    fn internal_size(&self, data: &[u8], sig: &FileSignature) -> Option<u64> {
+        if let Some(parser) = sig.size_parser {
+            return parser(data);
+        }
+        self.size_plugins.get(sig.name)?.parse(data)
+    }
+
     fn classify_boundary(
         &self,
         data: &[u8],
@@ -473,12 +854,8 @@ impl Carver {
         let max_end = (start as u64 + sig.max_size).min(data.len() as u64) as usize;
         let slice_full = &data[start..max_end];
 
-        if let Some(parser) = sig.size_parser {
-            if let Some(parsed_size) = parser(slice_full) {
-                if parsed_size == size {
-                    return BoundaryMethod::InternalSize;
-                }
-            }
+        if self.internal_size(slice_full, sig) == Some(size) {
+            return BoundaryMethod::InternalSize;
         }
 
         if let Some(footer) = sig.footer {
@@ -503,12 +880,60 @@ impl Carver {
         BoundaryMethod::MaxSizeCap
     }
 
-    /// Resolve extension with sub-type discrimination (RIFF → wav/avi/webp, ftyp → mp4/m4a/mov)
+    /// Pick the specific signature for a hit on a shared container header:
+    /// TIFF → CR2/NEF/ARW/DNG, ftyp → HEIC/MP4/M4A, RIFF → AVI/WAV/WebP. The specific
+    /// signature's name, size cap and size parser then apply.
+    fn refine_signature<'a>(
+        &'a self,
+        data: &[u8],
+        offset: u64,
+        sig: &'a FileSignature,
+    ) -> &'a FileSignature {
+        let start = offset as usize;
+        let end = (offset + sig.max_size).min(data.len() as u64) as usize;
+        let slice = &data[start..end];
+        let wanted = if is_tiff_header(sig.header) {
+            discriminate_tiff(slice)
+        } else if sig.header == b"ftyp" && sig.header_offset == 4 {
+            discriminate_ftyp(slice).map(|ext| match ext {
+                "mov" | "3gp" => "mp4",
+                "heif" => "heic",
+                ext => ext,
+            })
+        } else if sig.header == b"RIFF" {
+            discriminate_riff(slice)
+        } else {
+            None
+        };
+        let Some(wanted) = wanted else {
+            return sig;
+        };
+        self.signatures
+            .iter()
+            .find(|s| {
+                s.extension == wanted
+                    && s.header_offset == sig.header_offset
+                    && slice
+                        .get(s.header_offset..)
+                        .is_some_and(|rest| rest.starts_with(s.header))
+            })
+            .unwrap_or(sig)
+    }
+
+    /// Resolve extension with sub-type discrimination (RIFF → wav/avi/webp, ftyp → mp4/m4a/mov,
+    /// TIFF → cr2/nef/arw/dng)
     fn resolve_extension(&self, data: &[u8], offset: u64, sig: &FileSignature) -> String {
         let start = offset as usize;
         let avail = data.len().saturating_sub(start);
         let slice = &data[start..start + avail.min(64)];
 
+        if is_tiff_header(sig.header) {
+            let full = &data[start..start + avail.min(sig.max_size as usize)];
+            if let Some(ext) = discriminate_tiff(full) {
+                return ext.to_string();
+            }
+        }
+
         if sig.header == b"RIFF" {
             if let Some(ext) = discriminate_riff(slice) {
                 return ext.to_string();
@@ -521,6 +946,12 @@ impl Carver {
             }
         }
 
+        if sig.header == matroska::MAGIC
+            && matroska::doc_type(&data[start..start + avail.min(4096)]) == Some(&b"webm"[..])
+        {
+            return "webm".to_string();
+        }
+
         sig.extension.to_string()
     }
 
@@ -544,8 +975,9 @@ impl Carver {
                     modified: None,
                     created: Some(Utc::now()),
                     hash: cf.hash.clone(),
-                    has_bad_sectors: false,
+                    has_bad_sectors: cf.bad_region_bytes > 0,
                     thumbnail: None,
+                    metadata: Default::default(),
                 }
             })
             .collect()
@@ -757,7 +1189,7 @@ mod tests {
         let c = carver_default();
         let mut data = vec![0u8; 2048];
         data[512] = 0xFF; data[513] = 0xD8; data[514] = 0xFF; // JPEG at sector 1
-        let hits = c.scan_chunk(&data, 0, data.len());
+        let hits = c.scan_chunk(&data, 0, data.len(), None);
         assert_eq!(hits.len(), 1);
         assert_eq!(hits[0].0, 512);
     }
@@ -767,7 +1199,7 @@ mod tests {
         let c = carver_default();
         let mut data = vec![0u8; 2048];
         data[100] = 0xFF; data[101] = 0xD8; data[102] = 0xFF; // JPEG at byte 100, not aligned
-        let hits = c.scan_chunk(&data, 0, data.len());
+        let hits = c.scan_chunk(&data, 0, data.len(), None);
         assert!(hits.is_empty(), "Sector-aligned scan should skip byte 100");
     }
 
@@ -780,7 +1212,7 @@ mod tests {
         data[0..4].copy_from_slice(&[0x00, 0x00, 0x00, 0x1C]); // box size 28
         data[4..8].copy_from_slice(b"ftyp");
         data[8..12].copy_from_slice(b"isom");
-        let hits = c.scan_chunk(&data, 0, data.len());
+        let hits = c.scan_chunk(&data, 0, data.len(), None);
         let mp4_hit = hits.iter().find(|&&(off, _)| off == 0);
         assert!(mp4_hit.is_some(), "Should find MP4 at sector 0 via ftyp probe at byte 4");
     }
@@ -791,7 +1223,7 @@ mod tests {
         let mut data = vec![0u8; 2048];
         // TAR file starting at sector 0: "ustar" at offset 257
         data[257..262].copy_from_slice(b"ustar");
-        let hits = c.scan_chunk(&data, 0, data.len());
+        let hits = c.scan_chunk(&data, 0, data.len(), None);
         let tar_hit = hits.iter().find(|&&(off, _)| off == 0);
         assert!(tar_hit.is_some(), "Should find TAR at sector 0 via ustar probe at byte 257");
     }
@@ -801,7 +1233,7 @@ mod tests {
         let c = carver_byte_level();
         let mut data = vec![0u8; 2048];
         data[100] = 0xFF; data[101] = 0xD8; data[102] = 0xFF;
-        let hits = c.scan_chunk(&data, 0, data.len());
+        let hits = c.scan_chunk(&data, 0, data.len(), None);
         assert!(!hits.is_empty());
         assert_eq!(hits[0].0, 100);
     }
@@ -814,7 +1246,7 @@ mod tests {
         data[0] = 0xFF; data[1] = 0xD8; data[2] = 0xFF;
         // PDF at 2048
         data[2048] = b'%'; data[2049] = b'P'; data[2050] = b'D'; data[2051] = b'F';
-        let hits = c.scan_chunk(&data, 0, data.len());
+        let hits = c.scan_chunk(&data, 0, data.len(), None);
         assert!(hits.len() >= 2, "Should find JPEG and PDF, found {}", hits.len());
     }
 
@@ -822,7 +1254,7 @@ mod tests {
     fn scenario_4_empty_range() {
         let c = carver_default();
         let data = vec![0u8; 1024];
-        let hits = c.scan_chunk(&data, 512, 512); // zero-length range
+        let hits = c.scan_chunk(&data, 512, 512, None); // zero-length range
         assert!(hits.is_empty());
     }
 
@@ -844,6 +1276,8 @@ mod tests {
         assert_eq!(discriminate_ftyp(b"\x00\x00\x00\x1CftypM4A "), Some("m4a"));
         assert_eq!(discriminate_ftyp(b"\x00\x00\x00\x1Cftypqt  "), Some("mov"));
         assert_eq!(discriminate_ftyp(b"\x00\x00\x00\x1Cftyp3gp5"), Some("3gp"));
+        assert_eq!(discriminate_ftyp(b"\x00\x00\x00\x18ftypheic"), Some("heic"));
+        assert_eq!(discriminate_ftyp(b"\x00\x00\x00\x18ftypmif1"), Some("heif"));
     }
 
     #[test]
@@ -912,6 +1346,62 @@ mod tests {
         assert_eq!(carved[0].boundary_method, BoundaryMethod::InternalSize);
     }
 
+    #[test]
+    fn scenario_7_raw_ifd_walk_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let jpeg = [0xFF, 0xD8, 0xFF, 0xE0, 0, 0, 0xFF, 0xD9];
+        let raw = tiff::tests::sample_raw("SONY", &jpeg);
+        let mut img = raw.clone();
+        img.resize(raw.len() + 8192, 0);
+        let path = write_img(dir.path(), "arw.img", &img);
+
+        let (carved, _) = run_carve(CarveOptions {
+            source: path,
+            output_dir: dir.path().join("out"),
+            sector_aligned: false,
+            min_size: 100,
+            dry_run: true,
+            verify: false,
+            ..Default::default()
+        });
+
+        assert_eq!(carved[0].signature_name, "Sony ARW");
+        assert_eq!(carved[0].extension, "arw");
+        assert_eq!(carved[0].size, raw.len() as u64);
+        assert_eq!(carved[0].boundary_method, BoundaryMethod::InternalSize);
+    }
+
+    #[test]
+    fn scenario_7_heic_box_walk_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut heic = Vec::new();
+        heic.extend_from_slice(&24u32.to_be_bytes());
+        heic.extend_from_slice(b"ftypheic\0\0\0\0mif1heic");
+        heic.extend_from_slice(&400u32.to_be_bytes());
+        heic.extend_from_slice(b"meta");
+        heic.resize(24 + 400, 0x11);
+        heic.extend_from_slice(&3000u32.to_be_bytes());
+        heic.extend_from_slice(b"mdat");
+        heic.resize(24 + 400 + 3000, 0x22);
+        let mut img = heic.clone();
+        img.resize(8192, 0);
+        let path = write_img(dir.path(), "heic.img", &img);
+
+        let (carved, _) = run_carve(CarveOptions {
+            source: path,
+            output_dir: dir.path().join("out"),
+            min_size: 100,
+            dry_run: true,
+            verify: false,
+            ..Default::default()
+        });
+
+        assert_eq!(carved[0].signature_name, "HEIF/HEIC");
+        assert_eq!(carved[0].extension, "heic");
+        assert_eq!(carved[0].size, heic.len() as u64);
+        assert_eq!(carved[0].boundary_method, BoundaryMethod::InternalSize);
+    }
+
     // =====================================================================
     // Scenario 8: Full carve — actual file extraction to disk
     // =====================================================================
@@ -1106,6 +1596,11 @@ mod tests {
                 file_type: FileType::Image,
                 boundary_method: BoundaryMethod::FooterScan,
                 hash: Some("abc123".to_string()),
+                bad_region_bytes: 0,
+                path: None,
+                page_check: None,
+                integrity: None,
+                confidence: None,
             },
             CarvedFile {
                 offset: 4096,
@@ -1115,6 +1610,11 @@ mod tests {
                 file_type: FileType::Image,
                 boundary_method: BoundaryMethod::InternalSize,
                 hash: Some("def456".to_string()),
+                bad_region_bytes: 0,
+                path: None,
+                page_check: None,
+                integrity: None,
+                confidence: None,
             },
         ];
 
@@ -1152,4 +1652,50 @@ mod tests {
 
         assert!(carved.is_empty(), "Zeroed image should produce no carved files");
     }
+
+    // =====================================================================
+    // Scenario 16: ddrescue mapfile — bad regions skipped and flagged
+    // =====================================================================
+
+    #[test]
+    fn scenario_16_mapfile_bad_regions() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut img = vec![0u8; 16384];
+        // JPEG at 0 spanning a bad region at 0x800..0xA00
+        img[0] = 0xFF; img[1] = 0xD8; img[2] = 0xFF; img[3] = 0xE0;
+        img[3000] = 0xFF; img[3001] = 0xD9;
+        // JPEG header sitting inside an unread region at 0x2000
+        img[0x2000] = 0xFF; img[0x2001] = 0xD8; img[0x2002] = 0xFF; img[0x2003] = 0xE0;
+        img[0x2400] = 0xFF; img[0x2401] = 0xD9;
+        let path = write_img(dir.path(), "disk.img", &img);
+
+        let mapfile = dir.path().join("disk.map");
+        std::fs::write(
+            &mapfile,
+            "# current_pos  current_status\n0x4000 +\n\
+             0x0000 0x0800 +\n0x0800 0x0200 -\n0x0A00 0x1600 +\n\
+             0x2000 0x0200 ?\n0x2200 0x1E00 +\n",
+        )
+        .unwrap();
+
+        let (carved, result) = run_carve(CarveOptions {
+            source: path,
+            output_dir: dir.path().join("out"),
+            sector_aligned: true,
+            min_size: 512,
+            dry_run: true,
+            verify: false,
+            mapfile: Some(mapfile),
+            ..Default::default()
+        });
+
+        assert_eq!(carved.len(), 1, "header inside unread region should be skipped");
+        assert_eq!(carved[0].offset, 0);
+        assert_eq!(carved[0].bad_region_bytes, 0x200);
+        assert_eq!(result.files_in_bad_regions, 1);
+        assert_eq!(result.bytes_skipped, 0x400);
+
+        let entries = carver_default().to_file_entries(&carved, std::path::Path::new("/out"));
+        assert!(entries[0].has_bad_sectors);
+    }
 }
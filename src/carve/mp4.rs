@@ -0,0 +1,226 @@
+//! MP4/MOV box handling for carving: sizing and faststart repair
+//!
+//! The top-level boxes are walked from `ftyp` (see
+//! [`super::signatures::parse_isobmff_size`]), and a file whose `moov`
+//! points at sample chunks past the walked end is rejected, since its
+//! media lies in another fragment.
+//!
+//! Cameras and phones often write `moov` (the sample tables) after the
+//! `mdat` media, so a player has to read the whole file before the first
+//! frame. Faststart repair moves `moov` to just after `ftyp` and adds its
+//! length to every chunk offset in the `stco`/`co64` tables.
+
+/// Extensions of the ftyp-based formats faststart applies to
+pub const FASTSTART_EXTENSIONS: &[&str] = &["mp4", "mov", "m4a", "3gp"];
+
+/// A box: its type and where its header, body and end are
+struct Mp4Box {
+    kind: [u8; 4],
+    start: usize,
+    body: usize,
+    end: usize,
+}
+
+/// Length of the MP4/MOV file `data` starts with
+pub fn parse_size(data: &[u8]) -> Option<u64> {
+    let end = super::signatures::parse_isobmff_size(data)? as usize;
+    if let Some(moov) = find(data, 0, end, b"moov") {
+        let furthest = chunk_offsets(data, &moov).into_iter().max();
+        if furthest.is_some_and(|offset| offset >= end as u64) {
+            return None;
+        }
+    }
+    Some(end as u64)
+}
+
+/// `data` with `moov` moved in front of the media, or None when it's
+/// already there (or the file can't be rewritten safely)
+pub fn faststart(data: &[u8]) -> Option<Vec<u8>> {
+    let top = children(data, 0, data.len());
+    if top.first()?.kind != *b"ftyp" || top.last()?.end != data.len() {
+        return None;
+    }
+    let moov = top.iter().position(|b| b.kind == *b"moov")?;
+    let mdat = top.iter().position(|b| b.kind == *b"mdat")?;
+    if moov < mdat {
+        return None;
+    }
+    let (ftyp, moov) = (&top[0], &top[moov]);
+
+    // Everything between ftyp and moov moves down by moov's length
+    let shift = (moov.end - moov.start) as u64;
+    let mut relocated = data[moov.start..moov.end].to_vec();
+    let tables = chunk_offset_tables(data, moov);
+    for table in &tables {
+        let (width, count) = table_layout(data, table)?;
+        for i in 0..count {
+            let at = table.body + 8 + i * width - moov.start;
+            let field = &mut relocated[at..at + width];
+            let offset = match width {
+                4 => u32::from_be_bytes(field.try_into().ok()?) as u64,
+                _ => u64::from_be_bytes(field.try_into().ok()?),
+            };
+            if offset < ftyp.end as u64 || offset >= moov.start as u64 {
+                return None;
+            }
+            let moved = offset + shift;
+            match width {
+                4 => field.copy_from_slice(&u32::try_from(moved).ok()?.to_be_bytes()),
+                _ => field.copy_from_slice(&moved.to_be_bytes()),
+            }
+        }
+    }
+
+    let mut out = Vec::with_capacity(data.len());
+    out.extend_from_slice(&data[..ftyp.end]);
+    out.extend_from_slice(&relocated);
+    out.extend_from_slice(&data[ftyp.end..moov.start]);
+    out.extend_from_slice(&data[moov.end..]);
+    Some(out)
+}
+
+/// Every chunk offset in `moov`'s sample tables
+fn chunk_offsets(data: &[u8], moov: &Mp4Box) -> Vec<u64> {
+    let mut offsets = Vec::new();
+    for table in chunk_offset_tables(data, moov) {
+        let Some((width, count)) = table_layout(data, &table) else {
+            continue;
+        };
+        let entries = &data[table.body + 8..table.body + 8 + count * width];
+        offsets.extend(entries.chunks_exact(width).map(|field| match width {
+            4 => u32::from_be_bytes([field[0], field[1], field[2], field[3]]) as u64,
+            _ => u64::from_be_bytes(field.try_into().unwrap_or_default()),
+        }));
+    }
+    offsets
+}
+
+/// The `stco` and `co64` boxes under `parent`
+fn chunk_offset_tables(data: &[u8], parent: &Mp4Box) -> Vec<Mp4Box> {
+    let mut tables = Vec::new();
+    for child in children(data, parent.body, parent.end) {
+        match &child.kind {
+            b"trak" | b"mdia" | b"minf" | b"stbl" => {
+                tables.extend(chunk_offset_tables(data, &child));
+            }
+            b"stco" | b"co64" => tables.push(child),
+            _ => {}
+        }
+    }
+    tables
+}
+
+/// Entry width and count of a chunk offset table whose entries all fit
+/// inside it
+fn table_layout(data: &[u8], table: &Mp4Box) -> Option<(usize, usize)> {
+    let width = if table.kind == *b"co64" { 8 } else { 4 };
+    let count = u32::from_be_bytes(data.get(table.body + 4..table.body + 8)?.try_into().ok()?);
+    let count = count as usize;
+    (table.body + 8 + count.checked_mul(width)? <= table.end).then_some((width, count))
+}
+
+fn find(data: &[u8], start: usize, end: usize, kind: &[u8; 4]) -> Option<Mp4Box> {
+    children(data, start, end)
+        .into_iter()
+        .find(|b| b.kind == *kind)
+}
+
+/// The boxes from `pos` up to `end`, stopping at the first malformed one
+fn children(data: &[u8], mut pos: usize, end: usize) -> Vec<Mp4Box> {
+    let mut boxes = Vec::new();
+    while let Some(b) = read_box(data, pos, end) {
+        pos = b.end;
+        boxes.push(b);
+    }
+    boxes
+}
+
+fn read_box(data: &[u8], pos: usize, end: usize) -> Option<Mp4Box> {
+    let header = data.get(pos..pos + 8)?;
+    let kind = header[4..8].try_into().ok()?;
+    let (size, body) = match u32::from_be_bytes(header[..4].try_into().ok()?) {
+        0 => (end.checked_sub(pos)?, pos + 8),
+        1 => {
+            let large = u64::from_be_bytes(data.get(pos + 8..pos + 16)?.try_into().ok()?);
+            (usize::try_from(large).ok()?, pos + 16)
+        }
+        n => (n as usize, pos + 8),
+    };
+    let box_end = pos.checked_add(size)?;
+    (box_end >= body && box_end <= end).then_some(Mp4Box {
+        kind,
+        start: pos,
+        body,
+        end: box_end,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mp4_box(kind: &[u8], body: &[u8]) -> Vec<u8> {
+        let mut out = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        out.extend(kind);
+        out.extend(body);
+        out
+    }
+
+    /// A moov with one track whose chunks are at `offsets`
+    fn moov(offsets: &[u32]) -> Vec<u8> {
+        let mut stco = vec![0u8; 4];
+        stco.extend((offsets.len() as u32).to_be_bytes());
+        for offset in offsets {
+            stco.extend(offset.to_be_bytes());
+        }
+        let stbl = mp4_box(b"stbl", &mp4_box(b"stco", &stco));
+        let minf = mp4_box(b"minf", &stbl);
+        let trak = mp4_box(b"trak", &mp4_box(b"mdia", &minf));
+        let mut body = mp4_box(b"mvhd", &[0u8; 100]);
+        body.extend(trak);
+        mp4_box(b"moov", &body)
+    }
+
+    /// ftyp, mdat with two chunks, then moov
+    fn recording() -> Vec<u8> {
+        let mut file = mp4_box(b"ftyp", b"isom\0\0\x02\0isomiso2");
+        let first = file.len() as u32 + 8;
+        let mut media = vec![0x11; 400];
+        media.extend([0x22; 400]);
+        file.extend(mp4_box(b"mdat", &media));
+        file.extend(moov(&[first, first + 400]));
+        file
+    }
+
+    #[test]
+    fn test_size_checks_chunk_offsets() {
+        let file = recording();
+        let mut carved = file.clone();
+        carved.extend([0u8; 100]);
+        assert_eq!(parse_size(&carved), Some(file.len() as u64));
+
+        // Chunks beyond the walked file: media is elsewhere
+        let mut file = mp4_box(b"ftyp", b"isom\0\0\x02\0isomiso2");
+        file.extend(mp4_box(b"mdat", &[0x11; 100]));
+        file.extend(moov(&[50_000]));
+        assert_eq!(parse_size(&file), None);
+    }
+
+    #[test]
+    fn test_faststart_moves_moov_and_offsets() {
+        let file = recording();
+        let fixed = faststart(&file).unwrap();
+        assert_eq!(fixed.len(), file.len());
+        assert_eq!(&fixed[28..32], b"moov");
+        assert_eq!(parse_size(&fixed), Some(fixed.len() as u64));
+
+        let moov = find(&fixed, 0, fixed.len(), b"moov").unwrap();
+        let offsets = chunk_offsets(&fixed, &moov);
+        assert_eq!(fixed[offsets[0] as usize], 0x11);
+        assert_eq!(fixed[offsets[1] as usize], 0x22);
+        assert_eq!(fixed[offsets[1] as usize - 1], 0x11);
+
+        // Already faststart
+        assert!(faststart(&fixed).is_none());
+    }
+}
@@ -0,0 +1,210 @@
+//! Validation and repair of carved Office Open XML files (docx/xlsx/pptx)
+//!
+//! Carved ZIP-based documents are often truncated, or run on into whatever
+//! follows them on disk. The local entries are walked from the start and
+//! each one's CRC checked; then:
+//!
+//! - a central directory and end record that match the entries make the
+//!   file **intact**, and anything after the end record is trimmed off
+//!   (still intact: the document itself is whole)
+//! - a missing or damaged central directory is rebuilt from the complete
+//!   entries, giving a **repaired** file that Office and LibreOffice open
+//! - without `[Content_Types].xml` and one more complete entry there is
+//!   nothing to open, and the file is **corrupt**
+//!
+//! Archives without `[Content_Types].xml` aren't Office files and are left
+//! alone.
+
+use super::zip::{
+    u16_at, u32_at, walk_entries, Entry, CENTRAL_HEADER, CENTRAL_HEADER_LEN, END_RECORD,
+    END_RECORD_LEN,
+};
+use super::Integrity;
+
+/// Part every OOXML package has
+const CONTENT_TYPES: &str = "[Content_Types].xml";
+
+/// Outcome of validating a carved OOXML file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Validation {
+    pub integrity: Integrity,
+    /// docx, xlsx or pptx, from the package's part names
+    pub extension: &'static str,
+    /// Bytes of the carved data the file really spans
+    pub len: usize,
+    /// The rebuilt file, to write instead of `data[..len]`
+    pub rebuilt: Option<Vec<u8>>,
+}
+
+/// Validate (and if needed rebuild) the OOXML package `data` starts with;
+/// None when it isn't one
+pub fn validate(data: &[u8]) -> Option<Validation> {
+    let (entries, walked) = walk_entries(data, 0);
+    let names: Vec<&[u8]> = entries.iter().map(|e| e.name).collect();
+    if !names.contains(&CONTENT_TYPES.as_bytes()) {
+        return None;
+    }
+    let extension = package_extension(&names);
+
+    if let Some(end) = central_directory_end(data, walked, entries.len()) {
+        return Some(Validation {
+            integrity: Integrity::Intact,
+            extension,
+            len: end,
+            rebuilt: None,
+        });
+    }
+    if entries.len() < 2 {
+        return Some(Validation {
+            integrity: Integrity::Corrupt,
+            extension,
+            len: walked,
+            rebuilt: None,
+        });
+    }
+    Some(Validation {
+        integrity: Integrity::Repaired,
+        extension,
+        len: walked,
+        rebuilt: Some(rebuild(data, &entries)),
+    })
+}
+
+/// End of the end record when a central directory for exactly `entries`
+/// entries starts at `start`
+fn central_directory_end(data: &[u8], start: usize, entries: usize) -> Option<usize> {
+    let mut pos = start;
+    for _ in 0..entries {
+        let header = data.get(pos..pos + CENTRAL_HEADER_LEN)?;
+        if !header.starts_with(CENTRAL_HEADER) {
+            return None;
+        }
+        let variable =
+            u16_at(header, 28) as usize + u16_at(header, 30) as usize + u16_at(header, 32) as usize;
+        pos += CENTRAL_HEADER_LEN + variable;
+    }
+    let record = data.get(pos..pos + END_RECORD_LEN)?;
+    let consistent = record.starts_with(END_RECORD)
+        && u16_at(record, 10) as usize == entries
+        && u32_at(record, 12) as usize == pos - start
+        && u32_at(record, 16) as usize == start;
+    if !consistent {
+        return None;
+    }
+    let end = pos + END_RECORD_LEN + u16_at(record, 20) as usize;
+    (end <= data.len()).then_some(end)
+}
+
+/// The entries followed by a new central directory and end record
+fn rebuild(data: &[u8], entries: &[Entry<'_>]) -> Vec<u8> {
+    let walked = entries.last().map_or(0, |e| e.end);
+    let mut out = data[..walked].to_vec();
+    let directory_start = out.len();
+    for entry in entries {
+        let f = &entry.fields;
+        out.extend_from_slice(CENTRAL_HEADER);
+        // Version made by: same as needed, MS-DOS
+        out.extend_from_slice(&f[0..2]);
+        // Version needed through name length
+        out.extend_from_slice(&f[0..24]);
+        // Extra, comment, disk, internal and external attributes
+        out.extend_from_slice(&[0u8; 12]);
+        out.extend_from_slice(&(entry.offset as u32).to_le_bytes());
+        out.extend_from_slice(entry.name);
+    }
+    let directory_len = out.len() - directory_start;
+    let count = (entries.len() as u16).to_le_bytes();
+    out.extend_from_slice(END_RECORD);
+    out.extend_from_slice(&[0u8; 4]);
+    out.extend_from_slice(&count);
+    out.extend_from_slice(&count);
+    out.extend_from_slice(&(directory_len as u32).to_le_bytes());
+    out.extend_from_slice(&(directory_start as u32).to_le_bytes());
+    out.extend_from_slice(&[0u8; 2]);
+    out
+}
+
+fn package_extension(names: &[&[u8]]) -> &'static str {
+    let has = |prefix: &[u8]| names.iter().any(|n| n.starts_with(prefix));
+    if has(b"xl/") {
+        "xlsx"
+    } else if has(b"ppt/") {
+        "pptx"
+    } else {
+        "docx"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn workbook() -> Vec<u8> {
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated);
+        for (name, body) in [
+            (CONTENT_TYPES, "<Types/>".repeat(20)),
+            ("_rels/.rels", "<Relationships/>".repeat(20)),
+            ("xl/workbook.xml", "<workbook/>".repeat(50)),
+            ("xl/worksheets/sheet1.xml", "<row>1</row>".repeat(500)),
+        ] {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(body.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    fn read_names(data: &[u8]) -> Vec<String> {
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(data)).unwrap();
+        (0..archive.len())
+            .map(|i| {
+                let mut file = archive.by_index(i).unwrap();
+                std::io::copy(&mut file, &mut std::io::sink()).unwrap();
+                file.name().to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_trailing_garbage_is_trimmed() {
+        let file = workbook();
+        let mut carved = file.clone();
+        carved.extend(vec![0xAB; 4096]);
+
+        let validation = validate(&carved).unwrap();
+        assert_eq!(validation.integrity, Integrity::Intact);
+        assert_eq!(validation.extension, "xlsx");
+        assert_eq!(validation.len, file.len());
+        assert!(validation.rebuilt.is_none());
+    }
+
+    #[test]
+    fn test_truncated_file_is_rebuilt() {
+        let file = workbook();
+        // Cut inside the last entry: the central directory is gone too
+        let sheet = memchr::memmem::find(&file, b"xl/worksheets").unwrap();
+        let carved = &file[..sheet + 40];
+
+        let validation = validate(carved).unwrap();
+        assert_eq!(validation.integrity, Integrity::Repaired);
+        let rebuilt = validation.rebuilt.unwrap();
+        assert_eq!(
+            read_names(&rebuilt),
+            vec![CONTENT_TYPES, "_rels/.rels", "xl/workbook.xml"]
+        );
+
+        // Only the content types survive: nothing to open
+        let second = memchr::memmem::find(&file, b"_rels/.rels").unwrap();
+        let validation = validate(&file[..second + 10]).unwrap();
+        assert_eq!(validation.integrity, Integrity::Corrupt);
+
+        // A plain archive isn't touched
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        zip.start_file("notes.txt", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(b"hello").unwrap();
+        assert!(validate(&zip.finish().unwrap().into_inner()).is_none());
+    }
+}
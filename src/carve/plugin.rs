@@ -0,0 +1,235 @@
+//! WASM size-parser plugins for custom carve signatures
+//!
+//! A custom signature (see [`super::custom`]) can name a WebAssembly module
+//! that computes the length of a candidate file, for container formats
+//! whose length lives in their own fields (dashcam and DVR formats, say):
+//!
+//! ```toml
+//! [[signature]]
+//! name = "Dashcam clip"
+//! extension = "dcm"
+//! header = "44 43 41 4D"
+//! max_size = "4GB"
+//! size_parser = "dashcam.wasm"   # relative to the signatures file
+//! parser_window = "4MB"          # bytes from the header passed in
+//! ```
+//!
+//! The module exports its `memory` and two functions:
+//!
+//! - `alloc(len: i32) -> i32`: where to write `len` input bytes (growing
+//!   the memory if needed)
+//! - `size(ptr: i32, len: i32) -> i64`: the file's length in bytes, or a
+//!   negative number when the data doesn't look like the format
+//!
+//! Each hit gets a fresh instance with no imports, so a plugin can't touch
+//! files, the network or the clock, and can't carry state between hits. Its
+//! memory and the instructions it may run per hit are capped; a plugin
+//! that traps or runs out of fuel leaves the boundary to the footer and
+//! next-header rules.
+
+use std::path::{Path, PathBuf};
+#[cfg(feature = "wasm-plugins")]
+use std::sync::Arc;
+
+use anyhow::Result;
+
+/// Input bytes passed to a plugin when the signature doesn't say
+pub const DEFAULT_PARSER_WINDOW: usize = 1024 * 1024;
+
+/// Memory a plugin may use beyond its input
+#[cfg(feature = "wasm-plugins")]
+const MEMORY_HEADROOM: usize = 16 * 1024 * 1024;
+
+/// Instructions (roughly) a plugin may run per hit
+#[cfg(feature = "wasm-plugins")]
+const FUEL_PER_CALL: u64 = 100_000_000;
+
+/// A loaded size-parser module
+#[derive(Clone)]
+pub struct SizePlugin {
+    path: PathBuf,
+    window: usize,
+    #[cfg(feature = "wasm-plugins")]
+    engine: wasmi::Engine,
+    #[cfg(feature = "wasm-plugins")]
+    module: Arc<wasmi::Module>,
+}
+
+impl std::fmt::Debug for SizePlugin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SizePlugin")
+            .field("path", &self.path)
+            .field("window", &self.window)
+            .finish()
+    }
+}
+
+impl SizePlugin {
+    /// The module the plugin was loaded from
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[cfg(feature = "wasm-plugins")]
+impl SizePlugin {
+    /// Load and check the module at `path`, passing it up to `window`
+    /// bytes per hit
+    pub fn load(path: &Path, window: usize) -> Result<Self> {
+        use anyhow::Context;
+
+        let wasm = std::fs::read(path)
+            .with_context(|| format!("Failed to read size parser: {}", path.display()))?;
+        Self::from_bytes(path, &wasm, window)
+            .with_context(|| format!("Invalid size parser: {}", path.display()))
+    }
+
+    fn from_bytes(path: &Path, wasm: &[u8], window: usize) -> Result<Self> {
+        let mut config = wasmi::Config::default();
+        config.consume_fuel(true);
+        let engine = wasmi::Engine::new(&config);
+        let module = wasmi::Module::new(&engine, wasm)?;
+        if let Some(import) = module.imports().next() {
+            anyhow::bail!(
+                "Plugins can't import anything (imports {}::{})",
+                import.module(),
+                import.name()
+            );
+        }
+        let plugin = Self {
+            path: path.to_path_buf(),
+            window,
+            engine,
+            module: Arc::new(module),
+        };
+        // Fail now rather than on every hit
+        let mut store = plugin.store(0)?;
+        plugin.exports(&mut store)?;
+        Ok(plugin)
+    }
+
+    /// Length of the file `data` starts with, per the plugin
+    pub fn parse(&self, data: &[u8]) -> Option<u64> {
+        let input = &data[..data.len().min(self.window)];
+        match self.call(input) {
+            Ok(size) => u64::try_from(size).ok(),
+            Err(e) => {
+                tracing::debug!("Size parser {} failed: {:#}", self.path.display(), e);
+                None
+            }
+        }
+    }
+
+    fn call(&self, input: &[u8]) -> Result<i64> {
+        let mut store = self.store(input.len())?;
+        let (memory, alloc, size) = self.exports(&mut store)?;
+        let len = i32::try_from(input.len())?;
+        let ptr = alloc.call(&mut store, len)?;
+        memory
+            .write(&mut store, usize::try_from(ptr)?, input)
+            .map_err(|e| anyhow::anyhow!("Input doesn't fit at {}: {}", ptr, e))?;
+        Ok(size.call(&mut store, (ptr, len))?)
+    }
+
+    fn store(&self, input_len: usize) -> Result<wasmi::Store<wasmi::StoreLimits>> {
+        let limits = wasmi::StoreLimitsBuilder::new()
+            .memory_size(input_len + MEMORY_HEADROOM)
+            .instances(1)
+            .build();
+        let mut store = wasmi::Store::new(&self.engine, limits);
+        store.limiter(|limits| limits);
+        store
+            .set_fuel(FUEL_PER_CALL)
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        Ok(store)
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn exports(
+        &self,
+        store: &mut wasmi::Store<wasmi::StoreLimits>,
+    ) -> Result<(
+        wasmi::Memory,
+        wasmi::TypedFunc<i32, i32>,
+        wasmi::TypedFunc<(i32, i32), i64>,
+    )> {
+        let linker = wasmi::Linker::new(&self.engine);
+        let instance = linker
+            .instantiate(&mut *store, &self.module)?
+            .start(&mut *store)?;
+        let memory = instance
+            .get_memory(&*store, "memory")
+            .ok_or_else(|| anyhow::anyhow!("Missing export: memory"))?;
+        let alloc = instance.get_typed_func::<i32, i32>(&*store, "alloc")?;
+        let size = instance.get_typed_func::<(i32, i32), i64>(&*store, "size")?;
+        Ok((memory, alloc, size))
+    }
+}
+
+#[cfg(not(feature = "wasm-plugins"))]
+impl SizePlugin {
+    /// Load and check the module at `path`
+    pub fn load(_path: &Path, _window: usize) -> Result<Self> {
+        anyhow::bail!("WASM size parsers not compiled in; rebuild with --features wasm-plugins")
+    }
+
+    /// Length of the file `data` starts with, per the plugin
+    pub fn parse(&self, _data: &[u8]) -> Option<u64> {
+        None
+    }
+}
+
+#[cfg(all(test, feature = "wasm-plugins"))]
+mod tests {
+    use super::*;
+
+    /// A module exporting two pages of memory, `alloc` returning 1024 and
+    /// `size` with the given body (no locals)
+    fn module(size_body: &[u8]) -> Vec<u8> {
+        let mut wasm = b"\0asm\x01\0\0\0".to_vec();
+        // Types: (i32) -> i32, (i32 i32) -> i64
+        wasm.extend([
+            1, 12, 2, 0x60, 1, 0x7f, 1, 0x7f, 0x60, 2, 0x7f, 0x7f, 1, 0x7e,
+        ]);
+        // Functions, memory
+        wasm.extend([3, 3, 2, 0, 1, 5, 3, 1, 0, 2]);
+        wasm.extend([7, 25, 3, 6]);
+        wasm.extend(b"memory\x02\0\x05alloc\0\0\x04size\0\x01");
+        let alloc = [0u8, 0x41, 0x80, 0x08, 0x0b];
+        let size_len = size_body.len() + 1;
+        wasm.extend([
+            10,
+            (2 + alloc.len() + 1 + size_len) as u8,
+            2,
+            alloc.len() as u8,
+        ]);
+        wasm.extend(alloc);
+        wasm.push(size_len as u8);
+        wasm.push(0);
+        wasm.extend(size_body);
+        wasm
+    }
+
+    #[test]
+    fn test_plugin_reads_length_field() {
+        // i64.load32_u offset=4 (local 0)
+        let wasm = module(&[0x20, 0, 0x35, 2, 4, 0x0b]);
+        let plugin = SizePlugin::from_bytes(Path::new("len.wasm"), &wasm, 64).unwrap();
+
+        let mut data = b"DCAM".to_vec();
+        data.extend(5000u32.to_le_bytes());
+        data.resize(200, 0xAA);
+        assert_eq!(plugin.parse(&data), Some(5000));
+    }
+
+    #[test]
+    fn test_runaway_plugin_is_stopped() {
+        // loop { br 0 }; i64.const 0
+        let wasm = module(&[0x03, 0x40, 0x0c, 0, 0x0b, 0x42, 0, 0x0b]);
+        let plugin = SizePlugin::from_bytes(Path::new("spin.wasm"), &wasm, 64).unwrap();
+        assert_eq!(plugin.parse(b"DCAM...."), None);
+
+        let error = SizePlugin::from_bytes(Path::new("bad.wasm"), b"\0asm", 64);
+        assert!(error.is_err());
+    }
+}
@@ -0,0 +1,233 @@
+//! Saved carve results and re-extraction
+//!
+//! `carve` writes a [`CarveListing`] next to the files it extracts so the
+//! results can be browsed later (TUI Carve tab) and chosen files pulled out
+//! of the image again with a different end boundary.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::ooxml::Validation;
+use super::{BoundaryMethod, CarveResult, CarvedFile, Carver, Integrity};
+use crate::error::{DrillError, DrillResult};
+
+/// Listing file written into the carve output directory
+pub const CARVE_LISTING_FILE: &str = "carve-results.json";
+
+/// Everything one carve run found
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CarveListing {
+    /// Image that was carved
+    pub source: PathBuf,
+    pub result: CarveResult,
+    pub files: Vec<CarvedFile>,
+}
+
+impl CarveListing {
+    /// Load a listing from its file or from the carve output directory
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = if path.is_dir() {
+            path.join(CARVE_LISTING_FILE)
+        } else {
+            path.to_path_buf()
+        };
+        let data = std::fs::read_to_string(&file)
+            .with_context(|| format!("Failed to read carve results: {}", file.display()))?;
+        serde_json::from_str(&data)
+            .with_context(|| format!("Failed to parse carve results: {}", file.display()))
+    }
+
+    /// Write the listing into `dir` as [`CARVE_LISTING_FILE`]
+    pub fn save(&self, dir: &Path) -> Result<PathBuf> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+        let file = dir.join(CARVE_LISTING_FILE);
+        let data =
+            serde_json::to_string_pretty(self).context("Failed to serialize carve results")?;
+        std::fs::write(&file, data)
+            .with_context(|| format!("Failed to write carve results: {}", file.display()))?;
+        Ok(file)
+    }
+}
+
+/// Where a re-extracted file ends
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReextractBoundary {
+    /// The same bytes as the original carve
+    #[default]
+    AsCarved,
+    /// Find the end again without stopping at the next file header, for
+    /// files cut short by a header embedded in them (e.g. a JPEG thumbnail)
+    IgnoreNextHeader,
+    /// Twice the carved size, for truncated files a viewer may still open
+    DoubleSize,
+}
+
+impl ReextractBoundary {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::AsCarved => "as carved",
+            Self::IgnoreNextHeader => "ignore next header",
+            Self::DoubleSize => "double size",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            Self::AsCarved => Self::IgnoreNextHeader,
+            Self::IgnoreNextHeader => Self::DoubleSize,
+            Self::DoubleSize => Self::AsCarved,
+        }
+    }
+}
+
+impl Carver {
+    /// Extract `files` from an earlier carve of this carver's image again,
+    /// into `output_dir` and ending at `boundary`
+    pub fn reextract(
+        &self,
+        files: &[CarvedFile],
+        boundary: ReextractBoundary,
+        output_dir: &Path,
+    ) -> DrillResult<Vec<CarvedFile>> {
+        let (mmap, image_size, _) = self.map_source()?;
+        std::fs::create_dir_all(output_dir)
+            .with_context(|| format!("Failed to create {}", output_dir.display()))?;
+
+        files
+            .iter()
+            .map(|cf| {
+                if cf.offset >= image_size {
+                    return Err(DrillError::InvalidInput(format!(
+                        "Offset {} is past the end of {}",
+                        cf.offset,
+                        self.options.source.display()
+                    )));
+                }
+                let (size, boundary_method) = match boundary {
+                    ReextractBoundary::AsCarved => (cf.size, cf.boundary_method),
+                    ReextractBoundary::IgnoreNextHeader => self.redetect_end(&mmap, cf),
+                    ReextractBoundary::DoubleSize => {
+                        (cf.size.saturating_mul(2), BoundaryMethod::MaxSizeCap)
+                    }
+                };
+                let end = cf.offset.saturating_add(size).min(image_size);
+                let data = &mmap[cf.offset as usize..end as usize];
+                self.throttle.consume(data.len() as u64);
+
+                let validation = super::ooxml::validate(data);
+                let integrity = validation.as_ref().map(|v| v.integrity);
+                let data = match &validation {
+                    Some(Validation {
+                        rebuilt: Some(bytes),
+                        ..
+                    }) => bytes.as_slice(),
+                    Some(v) if v.integrity == Integrity::Intact => &data[..v.len],
+                    _ => data,
+                };
+                let faststarted = (self.options.faststart
+                    && super::mp4::FASTSTART_EXTENSIONS.contains(&cf.extension.as_str()))
+                .then(|| super::mp4::faststart(data))
+                .flatten();
+                let data = faststarted.as_deref().unwrap_or(data);
+
+                let path = output_dir.join(format!("{:012x}.{}", cf.offset, cf.extension));
+                std::fs::write(&path, data)
+                    .with_context(|| format!("Failed to write {}", path.display()))?;
+                Ok(CarvedFile {
+                    size: data.len() as u64,
+                    boundary_method,
+                    hash: Some(hex::encode(blake3::hash(data).as_bytes())),
+                    path: Some(path),
+                    page_check: super::sqlite::check_pages(data),
+                    integrity,
+                    ..cf.clone()
+                })
+            })
+            .collect()
+    }
+
+    /// Size and boundary of `cf` found again with no next header to stop at
+    fn redetect_end(&self, data: &[u8], cf: &CarvedFile) -> (u64, BoundaryMethod) {
+        let Some(sig) = self.signatures.iter().find(|s| s.name == cf.signature_name) else {
+            return (cf.size, cf.boundary_method);
+        };
+        match self.determine_size(data, cf.offset, sig, None) {
+            Some(size) => (
+                size,
+                self.classify_boundary(data, cf.offset, size, sig, None),
+            ),
+            None => (sig.max_size, BoundaryMethod::MaxSizeCap),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::carve::CarveOptions;
+
+    #[tokio::test]
+    async fn test_listing_round_trip_and_reextract() {
+        let dir = tempfile::tempdir().unwrap();
+        // A JPEG whose embedded thumbnail header cuts the carve short
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0];
+        jpeg.extend(vec![0x11; 1020]);
+        jpeg.extend([0xFF, 0xD8, 0xFF, 0xE0]);
+        jpeg.extend(vec![0x22; 1020]);
+        jpeg.extend([0xFF, 0xD9]);
+        let mut image = vec![0u8; 512];
+        image.extend(&jpeg);
+        image.resize(8192, 0);
+        let source = dir.path().join("disk.img");
+        std::fs::write(&source, &image).unwrap();
+
+        let carver = Carver::new(CarveOptions {
+            source: source.clone(),
+            output_dir: dir.path().join("carved"),
+            sector_aligned: false,
+            min_size: 16,
+            verify: false,
+            ..Default::default()
+        });
+        let (files, result) = carver.carve().await.unwrap();
+        let first = files.iter().find(|f| f.offset == 512).unwrap();
+        assert_eq!(first.boundary_method, BoundaryMethod::NextHeader);
+        assert_eq!(first.size, 1024);
+
+        let listing = CarveListing {
+            source,
+            result,
+            files: files.clone(),
+        };
+        listing.save(&dir.path().join("carved")).unwrap();
+        let loaded = CarveListing::load(&dir.path().join("carved")).unwrap();
+        assert_eq!(loaded.files.len(), files.len());
+
+        let out = dir.path().join("again");
+        let redone = carver
+            .reextract(
+                std::slice::from_ref(first),
+                ReextractBoundary::IgnoreNextHeader,
+                &out,
+            )
+            .unwrap();
+        assert_eq!(redone[0].size, jpeg.len() as u64);
+        assert_eq!(redone[0].boundary_method, BoundaryMethod::FooterScan);
+        assert_eq!(
+            std::fs::read(redone[0].path.as_ref().unwrap()).unwrap(),
+            jpeg
+        );
+
+        let doubled = carver
+            .reextract(
+                std::slice::from_ref(first),
+                ReextractBoundary::DoubleSize,
+                &out,
+            )
+            .unwrap();
+        assert_eq!(doubled[0].size, 2048);
+    }
+}
@@ -64,20 +64,16 @@ pub(crate) fn parse_pdf_size(_data: &[u8]) -> Option<u64> {
     None // use footer scan
 }
 
-/// Parse ZIP: find end-of-central-directory record
+/// Parse ZIP: walk the entries and central directory to the end record
+/// (ZIP64 included)
 pub(crate) fn parse_zip_size(data: &[u8]) -> Option<u64> {
-    // EOCD signature: 50 4B 05 06, search backward from end
-    let search_len = data.len().min(65_536 + 22);
-    let start = if data.len() > search_len { data.len() - search_len } else { 0 };
-    for i in (start..data.len().saturating_sub(21)).rev() {
-        if data[i..].starts_with(&[0x50, 0x4B, 0x05, 0x06]) {
-            if i + 22 <= data.len() {
-                let comment_len = u16::from_le_bytes([data[i + 20], data[i + 21]]) as usize;
-                return Some((i + 22 + comment_len) as u64);
-            }
-        }
-    }
-    None
+    super::zip::parse_size(data)
+}
+
+/// Parse the first volume of a split ZIP: the entries after the spanning
+/// marker whose CRC checks out
+pub(crate) fn parse_zip_split_size(data: &[u8]) -> Option<u64> {
+    super::zip::parse_split_size(data)
 }
 
 /// Parse BMP: size at bytes 2-5 (little-endian u32)
@@ -107,28 +103,62 @@ pub(crate) fn parse_riff_size(data: &[u8]) -> Option<u64> {
     }
 }
 
-/// Parse MP4/MOV: walk ftyp/moov/mdat boxes
+/// Parse MP4/MOV/M4A: walk the top-level boxes, then check that moov's
+/// chunk offsets lie inside them
 pub(crate) fn parse_mp4_size(data: &[u8]) -> Option<u64> {
-    let mut pos = 0u64;
+    super::mp4::parse_size(data)
+}
+
+/// Parse TIFF and TIFF-based RAW (CR2/NEF/ARW/DNG): walk every IFD and take
+/// the furthest byte referenced by a tag value, strip, tile or JPEG preview
+pub(crate) fn parse_tiff_size(data: &[u8]) -> Option<u64> {
+    let layout = super::tiff::parse(data)?;
+    (layout.end > 8 && layout.end <= data.len() as u64).then_some(layout.end)
+}
+
+/// Whether a signature header is a TIFF byte-order mark (TIFF and RAW formats)
+pub fn is_tiff_header(header: &[u8]) -> bool {
+    header.starts_with(&[0x49, 0x49, 0x2A, 0x00]) || header.starts_with(&[0x4D, 0x4D, 0x00, 0x2A])
+}
+
+/// TIFF sub-type discriminator: Canon CR2 marker, DNGVersion tag, or the
+/// camera maker in IFD0 (NIKON → nef, SONY → arw)
+pub fn discriminate_tiff(data: &[u8]) -> Option<&'static str> {
+    super::tiff::parse(data).map(|layout| layout.extension())
+}
+
+/// Top-level ISO-BMFF box types; anything else ends the walk
+const ISOBMFF_TOP_LEVEL: &[&[u8; 4]] = &[
+    b"ftyp", b"meta", b"moov", b"mdat", b"free", b"skip", b"wide", b"uuid", b"moof", b"mfra",
+    b"sidx", b"styp", b"pdin", b"udta",
+];
+
+/// Parse ISO-BMFF (HEIC/HEIF, MP4, M4A): walk top-level boxes from `ftyp`
+/// until a non-box, and require a media box (`mdat`) to have been seen.
+/// Boxes running past the available data, or to end-of-file (size 0), give
+/// no size since the real end is unknown.
+pub(crate) fn parse_isobmff_size(data: &[u8]) -> Option<u64> {
     let len = data.len() as u64;
-    loop {
-        if pos + 8 > len {
-            break;
-        }
+    let mut pos = 0u64;
+    let mut saw_media = false;
+    while pos + 8 <= len {
         let p = pos as usize;
-        let box_size = u32::from_be_bytes([data[p], data[p + 1], data[p + 2], data[p + 3]]) as u64;
-        if box_size == 0 {
-            return Some(len); // box extends to end
-        }
-        if box_size < 8 {
+        let kind: &[u8; 4] = data[p + 4..p + 8].try_into().ok()?;
+        if !ISOBMFF_TOP_LEVEL.contains(&kind) || (pos == 0 && kind != b"ftyp") {
             break;
         }
-        pos += box_size;
-        if pos >= len {
-            return Some(pos);
+        let size = match u32::from_be_bytes(data[p..p + 4].try_into().ok()?) {
+            0 => return None,
+            1 => u64::from_be_bytes(data.get(p + 8..p + 16)?.try_into().ok()?),
+            n => n as u64,
+        };
+        if size < 8 || pos + size > len {
+            return None;
         }
+        saw_media |= kind == b"mdat";
+        pos += size;
     }
-    if pos > 8 { Some(pos) } else { None }
+    (saw_media && pos > 0).then_some(pos)
 }
 
 /// Parse FLAC: 4-byte magic + walk metadata blocks to find total
@@ -136,6 +166,21 @@ pub(crate) fn parse_flac_size(_data: &[u8]) -> Option<u64> {
     None // FLAC has no simple total-size field, use max_size cap
 }
 
+/// Parse AVI: walk the RIFF chunks, then any OpenDML `AVIX` extensions
+pub(crate) fn parse_avi_size(data: &[u8]) -> Option<u64> {
+    super::avi::parse_size(data)
+}
+
+/// Parse Matroska/WebM: walk the Segment's top-level elements
+pub(crate) fn parse_mkv_size(data: &[u8]) -> Option<u64> {
+    super::matroska::parse_size(data)
+}
+
+/// Parse SQLite: page size times the page count from the database header
+pub(crate) fn parse_sqlite_size(data: &[u8]) -> Option<u64> {
+    super::sqlite::parse(data).map(|header| header.size())
+}
+
 /// All known signatures, ordered by frequency for faster matching
 pub fn all_signatures() -> Vec<FileSignature> {
     vec![
@@ -198,7 +243,7 @@ pub fn all_signatures() -> Vec<FileSignature> {
             header_offset: 0,
             footer: None,
             max_size: 500 * 1024 * 1024,
-            size_parser: None,
+            size_parser: Some(parse_tiff_size),
         },
         FileSignature {
             name: "TIFF-BE",
@@ -208,7 +253,7 @@ pub fn all_signatures() -> Vec<FileSignature> {
             header_offset: 0,
             footer: None,
             max_size: 500 * 1024 * 1024,
-            size_parser: None,
+            size_parser: Some(parse_tiff_size),
         },
         FileSignature {
             name: "WebP",
@@ -230,7 +275,7 @@ pub fn all_signatures() -> Vec<FileSignature> {
             header_offset: 4,
             footer: None,
             max_size: 100 * 1024 * 1024,
-            size_parser: None,
+            size_parser: Some(parse_isobmff_size),
         },
 
         // === Video ===
@@ -243,7 +288,7 @@ pub fn all_signatures() -> Vec<FileSignature> {
             header_offset: 4,
             footer: None,
             max_size: 4 * 1024 * 1024 * 1024, // 4 GB
-            size_parser: None, // handled by mp4 box walker at carver level
+            size_parser: Some(parse_mp4_size),
         },
         FileSignature {
             name: "AVI",
@@ -252,18 +297,19 @@ pub fn all_signatures() -> Vec<FileSignature> {
             header: b"RIFF",
             header_offset: 0,
             footer: None,
-            max_size: 4 * 1024 * 1024 * 1024,
-            size_parser: Some(parse_riff_size),
+            // OpenDML files run past the 4 GB of a single RIFF
+            max_size: 8u64 * 1024 * 1024 * 1024,
+            size_parser: Some(parse_avi_size),
         },
         FileSignature {
             name: "MKV",
             extension: "mkv",
             file_type: FileType::Video,
-            header: &[0x1A, 0x45, 0xDF, 0xA3],
+            header: super::matroska::MAGIC,
             header_offset: 0,
             footer: None,
             max_size: 8u64 * 1024 * 1024 * 1024,
-            size_parser: None,
+            size_parser: Some(parse_mkv_size),
         },
         FileSignature {
             name: "FLV",
@@ -335,7 +381,7 @@ pub fn all_signatures() -> Vec<FileSignature> {
             header_offset: 4,
             footer: None,
             max_size: 500 * 1024 * 1024,
-            size_parser: None,
+            size_parser: Some(parse_mp4_size),
         },
 
         // === Documents ===
@@ -388,9 +434,21 @@ pub fn all_signatures() -> Vec<FileSignature> {
             header: &[0x50, 0x4B, 0x03, 0x04],
             header_offset: 0,
             footer: None,
-            max_size: 4 * 1024 * 1024 * 1024,
+            // ZIP64 archives run past 4 GB
+            max_size: 64u64 * 1024 * 1024 * 1024,
             size_parser: Some(parse_zip_size),
         },
+        FileSignature {
+            name: "ZIP split volume",
+            extension: "z01",
+            file_type: FileType::Archive,
+            // Spanning marker, then the first local header
+            header: b"PK\x07\x08PK\x03\x04",
+            header_offset: 0,
+            footer: None,
+            max_size: 4 * 1024 * 1024 * 1024,
+            size_parser: Some(parse_zip_split_size),
+        },
         FileSignature {
             name: "RAR5",
             extension: "rar",
@@ -479,11 +537,11 @@ pub fn all_signatures() -> Vec<FileSignature> {
             name: "SQLite",
             extension: "sqlite",
             file_type: FileType::Database,
-            header: b"SQLite format 3\x00",
+            header: super::sqlite::MAGIC,
             header_offset: 0,
             footer: None,
             max_size: 2 * 1024 * 1024 * 1024,
-            size_parser: None,
+            size_parser: Some(parse_sqlite_size),
         },
 
         // ==================================================================
@@ -499,7 +557,7 @@ pub fn all_signatures() -> Vec<FileSignature> {
             header_offset: 0,
             footer: None,
             max_size: 100 * 1024 * 1024,
-            size_parser: None,
+            size_parser: Some(parse_tiff_size),
         },
         FileSignature {
             name: "Nikon NEF",
@@ -509,7 +567,7 @@ pub fn all_signatures() -> Vec<FileSignature> {
             header_offset: 0,
             footer: None,
             max_size: 100 * 1024 * 1024,
-            size_parser: None,
+            size_parser: Some(parse_tiff_size),
         },
         FileSignature {
             name: "Sony ARW",
@@ -519,7 +577,7 @@ pub fn all_signatures() -> Vec<FileSignature> {
             header_offset: 0,
             footer: None,
             max_size: 100 * 1024 * 1024,
-            size_parser: None,
+            size_parser: Some(parse_tiff_size),
         },
         FileSignature {
             name: "Adobe DNG",
@@ -529,7 +587,7 @@ pub fn all_signatures() -> Vec<FileSignature> {
             header_offset: 0,
             footer: None,
             max_size: 200 * 1024 * 1024,
-            size_parser: None,
+            size_parser: Some(parse_tiff_size),
         },
 
         // --- Design / Creative ---
@@ -895,7 +953,7 @@ pub fn discriminate_riff(data: &[u8]) -> Option<&'static str> {
     }
 }
 
-/// MP4/M4A discriminator: check ftyp brand
+/// MP4/M4A/HEIC discriminator: check ftyp brand
 pub fn discriminate_ftyp(data: &[u8]) -> Option<&'static str> {
     if data.len() < 12 {
         return None;
@@ -906,6 +964,8 @@ pub fn discriminate_ftyp(data: &[u8]) -> Option<&'static str> {
         b"mp41" | b"mp42" | b"isom" | b"MSNV" | b"avc1" | b"dash" => Some("mp4"),
         b"qt  " => Some("mov"),
         b"3gp4" | b"3gp5" | b"3gp6" => Some("3gp"),
+        b"heic" | b"heix" | b"heim" | b"heis" | b"hevc" | b"hevx" => Some("heic"),
+        b"mif1" | b"msf1" => Some("heif"),
         _ => Some("mp4"),
     }
 }
@@ -0,0 +1,279 @@
+//! SQLite database header parsing and page validation for carving
+//!
+//! The 100-byte header gives the page size and, when the change counter
+//! matches the version-valid-for number, a trustworthy page count, so the
+//! database length is known without a footer. Pages are then checked one
+//! by one: databases written through the cksumvfs extension (8 reserved
+//! bytes per page) carry a checksum on every page; otherwise b-tree pages
+//! are checked for a consistent header. A carved database with bad pages
+//! was likely fragmented on disk or partly overwritten.
+
+use serde::{Deserialize, Serialize};
+
+/// Magic string every SQLite 3 database starts with
+pub const MAGIC: &[u8] = b"SQLite format 3\x00";
+
+/// Size of the database header on page 1
+const HEADER_LEN: usize = 100;
+
+/// Reserved bytes per page the cksumvfs extension stores its checksum in
+const CKSUM_RESERVED: u8 = 8;
+
+/// Bad page numbers kept per database
+const MAX_BAD_PAGES: usize = 256;
+
+/// What the database header says about the file's layout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SqliteHeader {
+    pub page_size: u32,
+    pub page_count: u32,
+    /// Bytes at the end of each page not used by SQLite itself
+    pub reserved: u8,
+}
+
+impl SqliteHeader {
+    /// Length of the database file in bytes
+    pub fn size(&self) -> u64 {
+        self.page_size as u64 * self.page_count as u64
+    }
+}
+
+/// Result of validating every page of a carved database
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageCheck {
+    /// Pages in the database
+    pub pages: u32,
+    /// Whether pages carry cksumvfs checksums (otherwise only b-tree
+    /// headers could be checked)
+    pub checksummed: bool,
+    /// 1-based numbers of pages that failed validation (the first 256)
+    pub bad_pages: Vec<u32>,
+    /// Total pages that failed validation
+    pub bad_page_count: u32,
+}
+
+impl PageCheck {
+    pub fn is_intact(&self) -> bool {
+        self.bad_page_count == 0
+    }
+}
+
+/// Parse the header at the start of `data`, or None when it isn't a
+/// database header or its page count can't be trusted
+pub fn parse(data: &[u8]) -> Option<SqliteHeader> {
+    if data.len() < HEADER_LEN || !data.starts_with(MAGIC) {
+        return None;
+    }
+    let page_size = match u16::from_be_bytes([data[16], data[17]]) {
+        1 => 65_536,
+        n if n >= 512 && n.is_power_of_two() => n as u32,
+        _ => return None,
+    };
+    // File format versions (legacy or WAL) and the fixed payload fractions
+    if !matches!(data[18], 1 | 2) || !matches!(data[19], 1 | 2) || data[21..24] != [64, 32, 32] {
+        return None;
+    }
+    let reserved = data[20];
+    if page_size - (reserved as u32) < 480 {
+        return None;
+    }
+    // The in-header size is only valid when written by SQLite 3.7.0 or
+    // later, which also keeps these two counters equal
+    let change_counter = &data[24..28];
+    let version_valid_for = &data[92..96];
+    let page_count = u32::from_be_bytes([data[28], data[29], data[30], data[31]]);
+    if page_count == 0 || change_counter != version_valid_for {
+        return None;
+    }
+    Some(SqliteHeader {
+        page_size,
+        page_count,
+        reserved,
+    })
+}
+
+/// Validate every page of the database `data` holds
+pub fn check_pages(data: &[u8]) -> Option<PageCheck> {
+    let header = parse(data)?;
+    let page_size = header.page_size as usize;
+    let pages: Vec<&[u8]> = data.chunks_exact(page_size).collect();
+    let checksummed =
+        header.reserved == CKSUM_RESERVED && pages.first().is_some_and(|p| checksum_matches(p));
+
+    let mut check = PageCheck {
+        pages: header.page_count,
+        checksummed,
+        ..Default::default()
+    };
+    let present = header.page_count.min(pages.len() as u32);
+    for (number, page) in (1..=present).zip(&pages) {
+        let valid = if checksummed {
+            checksum_matches(page)
+        } else {
+            btree_header_valid(page, number == 1, header.reserved)
+        };
+        if !valid {
+            check.bad_page_count += 1;
+            if check.bad_pages.len() < MAX_BAD_PAGES {
+                check.bad_pages.push(number);
+            }
+        }
+    }
+    // Pages cut short by the end of the image are counted, not walked: the
+    // header's page count is untrusted and may be up to 2^32
+    check.bad_page_count += header.page_count - present;
+    let room = MAX_BAD_PAGES - check.bad_pages.len();
+    check
+        .bad_pages
+        .extend((present + 1..=header.page_count).take(room));
+    Some(check)
+}
+
+/// cksumvfs: Fletcher-like sums over the page's little-endian words,
+/// stored in its last 8 bytes
+fn checksum_matches(page: &[u8]) -> bool {
+    let (content, stored) = page.split_at(page.len() - 8);
+    let (mut s1, mut s2) = (0u32, 0u32);
+    for pair in content.chunks_exact(8) {
+        let a = u32::from_le_bytes([pair[0], pair[1], pair[2], pair[3]]);
+        let b = u32::from_le_bytes([pair[4], pair[5], pair[6], pair[7]]);
+        s1 = s1.wrapping_add(a).wrapping_add(s2);
+        s2 = s2.wrapping_add(b).wrapping_add(s1);
+    }
+    stored[..4] == s1.to_le_bytes() && stored[4..] == s2.to_le_bytes()
+}
+
+/// A page that claims to be a b-tree page must have its cell pointers and
+/// cell content inside the usable area; page 1 must be a b-tree page (the
+/// schema table). Overflow, freelist and pointer-map pages have no header
+/// to check.
+fn btree_header_valid(page: &[u8], first: bool, reserved: u8) -> bool {
+    let start = if first { HEADER_LEN } else { 0 };
+    let usable = page.len() - reserved as usize;
+    let header_len = match page[start] {
+        0x02 | 0x05 => 12,
+        0x0a | 0x0d => 8,
+        _ => return !first,
+    };
+    let cells = u16::from_be_bytes([page[start + 3], page[start + 4]]) as usize;
+    let content = match u16::from_be_bytes([page[start + 5], page[start + 6]]) {
+        0 => 65_536,
+        n => n as usize,
+    };
+    let pointers_end = start + header_len + 2 * cells;
+    pointers_end <= content && content <= usable
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A database of `pages` pages of 512 bytes: page 1 an empty schema
+    /// leaf, the rest empty table leaves
+    fn database(pages: u32, reserved: u8) -> Vec<u8> {
+        let mut db = vec![0u8; 512 * pages as usize];
+        db[..16].copy_from_slice(MAGIC);
+        db[16..18].copy_from_slice(&512u16.to_be_bytes());
+        db[18] = 1;
+        db[19] = 1;
+        db[20] = reserved;
+        db[21..24].copy_from_slice(&[64, 32, 32]);
+        db[24..28].copy_from_slice(&7u32.to_be_bytes());
+        db[28..32].copy_from_slice(&pages.to_be_bytes());
+        db[92..96].copy_from_slice(&7u32.to_be_bytes());
+        let usable = (512 - reserved as u16).to_be_bytes();
+        for (i, page) in db.chunks_exact_mut(512).enumerate() {
+            let start = if i == 0 { HEADER_LEN } else { 0 };
+            page[start] = 0x0d;
+            page[start + 5..start + 7].copy_from_slice(&usable);
+        }
+        db
+    }
+
+    fn seal(db: &mut [u8]) {
+        for page in db.chunks_exact_mut(512) {
+            let (content, stored) = page.split_at_mut(504);
+            let (mut s1, mut s2) = (0u32, 0u32);
+            for pair in content.chunks_exact(8) {
+                s1 = s1
+                    .wrapping_add(u32::from_le_bytes(pair[..4].try_into().unwrap()))
+                    .wrapping_add(s2);
+                s2 = s2
+                    .wrapping_add(u32::from_le_bytes(pair[4..].try_into().unwrap()))
+                    .wrapping_add(s1);
+            }
+            stored[..4].copy_from_slice(&s1.to_le_bytes());
+            stored[4..].copy_from_slice(&s2.to_le_bytes());
+        }
+    }
+
+    #[test]
+    fn test_header_gives_size_and_pages_validate() {
+        let mut db = database(4, 0);
+        let header = parse(&db).unwrap();
+        assert_eq!(header.size(), 2048);
+        assert!(check_pages(&db).unwrap().is_intact());
+
+        // Another file's data where page 3 should be
+        db[1024] = 0x0d;
+        db[1024 + 3..1024 + 5].copy_from_slice(&300u16.to_be_bytes());
+        let check = check_pages(&db).unwrap();
+        assert!(!check.checksummed);
+        assert_eq!(check.bad_pages, vec![3]);
+
+        // Page count not trustworthy: written by an old SQLite
+        db[92] = 9;
+        assert!(parse(&db).is_none());
+    }
+
+    #[test]
+    fn test_cksumvfs_checksums_are_verified() {
+        let mut db = database(3, CKSUM_RESERVED);
+        seal(&mut db);
+        let check = check_pages(&db).unwrap();
+        assert!(check.checksummed && check.is_intact());
+
+        db[512 + 200] ^= 0xFF;
+        let check = check_pages(&db).unwrap();
+        assert_eq!(check.bad_pages, vec![2]);
+
+        // Truncated by the end of the image
+        let check = check_pages(&db[..1024]).unwrap();
+        assert_eq!(check.bad_pages, vec![2, 3]);
+    }
+
+    #[test]
+    fn test_huge_page_count_is_not_walked() {
+        let mut db = database(2, 0);
+        db[28..32].copy_from_slice(&u32::MAX.to_be_bytes());
+        let check = check_pages(&db).unwrap();
+        assert_eq!(check.pages, u32::MAX);
+        assert_eq!(check.bad_page_count, u32::MAX - 2);
+        assert_eq!(check.bad_pages.len(), MAX_BAD_PAGES);
+        assert_eq!(check.bad_pages[..2], [3, 4]);
+    }
+
+    #[test]
+    fn test_carved_database_uses_header_size() {
+        use crate::carve::{BoundaryMethod, CarveOptions, Carver};
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut image = vec![0u8; 16 * 1024];
+        image[4096..4096 + 2048].copy_from_slice(&database(4, 0));
+        let source = dir.path().join("disk.img");
+        std::fs::write(&source, &image).unwrap();
+
+        let carver = Carver::new(CarveOptions {
+            source,
+            dry_run: true,
+            verify: false,
+            ..Default::default()
+        });
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let (files, _) = rt.block_on(carver.carve()).unwrap();
+        let db = files.iter().find(|f| f.extension == "sqlite").unwrap();
+        assert_eq!((db.offset, db.size), (4096, 2048));
+        assert_eq!(db.boundary_method, BoundaryMethod::InternalSize);
+        assert!(db.page_check.as_ref().unwrap().is_intact());
+    }
+}
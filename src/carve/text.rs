@@ -0,0 +1,194 @@
+//! Heuristic carving of plain text and logs
+//!
+//! Text has no magic bytes, so it can't be found by signature. Instead the
+//! bytes no carved file covers are searched for long runs of printable
+//! ASCII and valid UTF-8 (tabs and line breaks included). Each run long
+//! enough is scored for how much it reads like language or a log: letters
+//! and spacing in normal proportions, plausible word lengths, common words,
+//! and enough different characters that it isn't filler. The score becomes
+//! the carve's confidence.
+
+use super::{BoundaryMethod, CarvedFile};
+use crate::core::FileType;
+
+/// Words common in English prose and in logs
+const COMMON_WORDS: &[&str] = &[
+    "the", "and", "to", "of", "a", "in", "is", "that", "for", "it", "on", "with", "as", "was",
+    "be", "this", "are", "not", "you", "at", "from", "by", "or", "have", "error", "warn",
+    "warning", "info", "debug", "failed", "user", "file", "server", "request",
+];
+
+/// Name carved text is listed under
+pub const SIGNATURE_NAME: &str = "Text";
+
+/// Runs of text in `data` at least `min_len` bytes long, as `(start, end)`
+pub fn find_runs(data: &[u8], min_len: usize) -> Vec<(usize, usize)> {
+    let mut runs = Vec::new();
+    let mut start = 0;
+    let mut pos = 0;
+    while pos < data.len() {
+        match char_len(&data[pos..]) {
+            Some(len) => pos += len,
+            None => {
+                if pos - start >= min_len {
+                    runs.push((start, pos));
+                }
+                pos += 1;
+                start = pos;
+            }
+        }
+    }
+    if pos - start >= min_len {
+        runs.push((start, pos));
+    }
+    runs
+}
+
+/// Length of the text character `data` starts with, or None for a
+/// control byte or invalid UTF-8
+fn char_len(data: &[u8]) -> Option<usize> {
+    let len = match data[0] {
+        b'\t' | b'\n' | b'\r' | 0x0C | 0x20..=0x7E => return Some(1),
+        0xC2..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF4 => 4,
+        _ => return None,
+    };
+    let bytes = data.get(..len)?;
+    std::str::from_utf8(bytes).ok().map(|_| len)
+}
+
+/// How much `text` reads like language or a log, 0-100
+pub fn score(text: &str) -> u8 {
+    let chars = text.chars().count().max(1) as f32;
+    let letters = text.chars().filter(|c| c.is_alphabetic()).count() as f32 / chars;
+    let spaces = text.chars().filter(|c| c.is_whitespace()).count() as f32 / chars;
+
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let word_count = words.len().max(1) as f32;
+    let mean_word = words.iter().map(|w| w.chars().count()).sum::<usize>() as f32 / word_count;
+    let common = words
+        .iter()
+        .filter(|w| {
+            let word = w.trim_matches(|c: char| !c.is_alphanumeric());
+            COMMON_WORDS.iter().any(|c| c.eq_ignore_ascii_case(word))
+        })
+        .count() as f32
+        / word_count;
+
+    let letter_score = (letters / 0.55).min(1.0);
+    // Prose and logs are roughly 10-25% whitespace
+    let space_score = if (0.08..=0.3).contains(&spaces) {
+        1.0
+    } else {
+        (1.0 - (spaces - 0.19).abs() * 3.0).max(0.0)
+    };
+    let word_score = if (2.0..=10.0).contains(&mean_word) {
+        1.0
+    } else {
+        0.3
+    };
+    let common_score = (common * 5.0).min(1.0);
+
+    let mut distinct = [false; 128];
+    text.bytes()
+        .filter(u8::is_ascii)
+        .for_each(|b| distinct[b as usize] = true);
+    let variety = (distinct.iter().filter(|&&d| d).count() as f32 / 20.0).min(1.0);
+
+    let score = 0.3 * letter_score + 0.2 * space_score + 0.2 * word_score + 0.3 * common_score;
+    (score * variety * 100.0).round() as u8
+}
+
+/// Text runs in the parts of `data` no file in `carved` covers
+pub fn carve_gaps(data: &[u8], carved: &[CarvedFile], min_len: usize) -> Vec<CarvedFile> {
+    let mut covered: Vec<(u64, u64)> = carved
+        .iter()
+        .map(|f| (f.offset, f.offset + f.size))
+        .collect();
+    covered.sort_unstable();
+
+    let mut gaps = Vec::new();
+    let mut pos = 0u64;
+    for (start, end) in covered {
+        if start > pos {
+            gaps.push((pos, start));
+        }
+        pos = pos.max(end);
+    }
+    gaps.push((pos, data.len() as u64));
+
+    gaps.into_iter()
+        .filter(|&(start, end)| end > start && (end - start) as usize >= min_len)
+        .flat_map(|(start, end)| {
+            let gap = &data[start as usize..(end as usize).min(data.len())];
+            find_runs(gap, min_len)
+                .into_iter()
+                .map(move |(run_start, run_end)| {
+                    let text = std::str::from_utf8(&gap[run_start..run_end]).unwrap_or_default();
+                    CarvedFile {
+                        offset: start + run_start as u64,
+                        size: (run_end - run_start) as u64,
+                        signature_name: SIGNATURE_NAME.to_string(),
+                        extension: "txt".to_string(),
+                        file_type: FileType::Document,
+                        boundary_method: BoundaryMethod::TextRun,
+                        hash: None,
+                        bad_region_bytes: 0,
+                        path: None,
+                        page_check: None,
+                        integrity: None,
+                        confidence: Some(score(text)),
+                    }
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runs_are_found_and_scored() {
+        let prose = "The recovery of the file was not possible, so the user \
+                     tried again with a copy of the disk. It is in the archive now.\n"
+            .repeat(4);
+        let log = "2024-03-01 12:00:01 ERROR server: request from 10.0.0.4 failed\n".repeat(6);
+        let mut data = vec![0u8; 300];
+        data.extend(prose.as_bytes());
+        data.extend([0xFF, 0x00, 0x13]);
+        data.extend("żółw café ".repeat(3).as_bytes());
+        data.extend([0u8; 40]);
+        data.extend(log.as_bytes());
+        data.extend(vec![b'A'; 600]);
+
+        let runs = find_runs(&data, 256);
+        assert_eq!(runs[0], (300, 300 + prose.len()));
+        // Short UTF-8 run skipped; the log and the filler run together
+        assert_eq!(runs.len(), 2);
+
+        assert!(score(&prose) >= 80, "prose scored {}", score(&prose));
+        assert!(score(&log) >= 60, "log scored {}", score(&log));
+        assert!(score(&"A".repeat(600)) < 20);
+        assert!(score(&"x7#Qz!9@".repeat(80)) < 50);
+    }
+
+    #[test]
+    fn test_only_uncovered_bytes_are_carved() {
+        let line = "the quick brown fox jumps over the lazy dog and is not seen again\n";
+        let data = line.repeat(40).into_bytes();
+        let covering = CarvedFile {
+            offset: 0,
+            size: 1000,
+            ..carve_gaps(&data, &[], 256).remove(0)
+        };
+        let text = carve_gaps(&data, &[covering], 256);
+        assert_eq!(text.len(), 1);
+        assert_eq!(
+            (text[0].offset, text[0].size),
+            (1000, data.len() as u64 - 1000)
+        );
+        assert_eq!(text[0].boundary_method, BoundaryMethod::TextRun);
+    }
+}
@@ -0,0 +1,339 @@
+//! TIFF structure walker for TIFF-based camera RAW formats
+//!
+//! CR2, NEF, ARW and DNG files are TIFF containers: a chain of IFDs, with
+//! SubIFDs and the EXIF IFD hanging off IFD0. Walking every IFD gives the
+//! byte range the file occupies (for carving without a footer) and the
+//! embedded JPEG previews the camera stored alongside the sensor data.
+
+/// Stop following IFDs after this many (corrupt or looping chains)
+const MAX_IFDS: usize = 64;
+
+/// Entries per IFD beyond which the IFD is treated as garbage
+const MAX_ENTRIES: usize = 1024;
+
+/// Strips or tiles tracked per IFD
+const MAX_SEGMENTS: usize = 65_536;
+
+const TAG_COMPRESSION: u16 = 0x0103;
+const TAG_MAKE: u16 = 0x010F;
+const TAG_STRIP_OFFSETS: u16 = 0x0111;
+const TAG_STRIP_BYTE_COUNTS: u16 = 0x0117;
+const TAG_TILE_OFFSETS: u16 = 0x0144;
+const TAG_TILE_BYTE_COUNTS: u16 = 0x0145;
+const TAG_SUB_IFDS: u16 = 0x014A;
+const TAG_JPEG_OFFSET: u16 = 0x0201;
+const TAG_JPEG_LENGTH: u16 = 0x0202;
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_DNG_VERSION: u16 = 0xC612;
+
+/// Compression values whose strips hold a JPEG stream
+const JPEG_COMPRESSION: &[u64] = &[6, 7];
+
+/// An embedded JPEG stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JpegRef {
+    pub offset: u64,
+    pub length: u64,
+}
+
+/// What walking a TIFF container found
+#[derive(Debug, Clone, Default)]
+pub struct TiffLayout {
+    /// One past the last byte referenced by any IFD, tag value or image data
+    pub end: u64,
+    /// JPEG streams, largest first
+    pub jpegs: Vec<JpegRef>,
+    /// Camera maker from IFD0
+    pub make: Option<String>,
+    pub is_dng: bool,
+    /// Canon CR2 marker ("CR" after the TIFF header)
+    pub is_cr2: bool,
+}
+
+impl TiffLayout {
+    /// RAW flavour of the container, or "tiff" for a plain TIFF
+    pub fn extension(&self) -> &'static str {
+        if self.is_cr2 {
+            return "cr2";
+        }
+        if self.is_dng {
+            return "dng";
+        }
+        let make = self
+            .make
+            .as_deref()
+            .unwrap_or_default()
+            .to_ascii_uppercase();
+        if make.starts_with("NIKON") {
+            "nef"
+        } else if make.starts_with("SONY") {
+            "arw"
+        } else if make.starts_with("CANON") {
+            "cr2"
+        } else {
+            "tiff"
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Reader<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+}
+
+impl Reader<'_> {
+    fn u16(&self, pos: u64) -> Option<u16> {
+        let b: [u8; 2] = self
+            .data
+            .get(pos as usize..pos as usize + 2)?
+            .try_into()
+            .ok()?;
+        Some(if self.little_endian {
+            u16::from_le_bytes(b)
+        } else {
+            u16::from_be_bytes(b)
+        })
+    }
+
+    fn u32(&self, pos: u64) -> Option<u32> {
+        let b: [u8; 4] = self
+            .data
+            .get(pos as usize..pos as usize + 4)?
+            .try_into()
+            .ok()?;
+        Some(if self.little_endian {
+            u32::from_le_bytes(b)
+        } else {
+            u32::from_be_bytes(b)
+        })
+    }
+}
+
+/// One IFD entry with its values located
+struct Entry {
+    tag: u16,
+    field_type: u16,
+    count: u64,
+    /// Where the value bytes are (inline in the entry or out of line)
+    value_pos: u64,
+    value_len: u64,
+}
+
+impl Entry {
+    fn values(&self, r: Reader<'_>) -> Vec<u64> {
+        let count = (self.count as usize).min(MAX_SEGMENTS);
+        (0..count as u64)
+            .filter_map(|i| match self.field_type {
+                3 => r.u16(self.value_pos + i * 2).map(u64::from),
+                4 | 13 => r.u32(self.value_pos + i * 4).map(u64::from),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn first(&self, r: Reader<'_>) -> Option<u64> {
+        self.values(r).first().copied()
+    }
+}
+
+fn type_size(field_type: u16) -> Option<u64> {
+    match field_type {
+        1 | 2 | 6 | 7 => Some(1),
+        3 | 8 => Some(2),
+        4 | 9 | 11 | 13 => Some(4),
+        5 | 10 | 12 => Some(8),
+        _ => None,
+    }
+}
+
+/// Walk a TIFF container starting at `data[0]`
+pub fn parse(data: &[u8]) -> Option<TiffLayout> {
+    let little_endian = match data.get(..4)? {
+        [0x49, 0x49, 0x2A, 0x00] => true,
+        [0x4D, 0x4D, 0x00, 0x2A] => false,
+        _ => return None,
+    };
+    let r = Reader {
+        data,
+        little_endian,
+    };
+    let mut layout = TiffLayout {
+        end: 8,
+        is_cr2: data.get(8..10) == Some(b"CR"),
+        ..Default::default()
+    };
+
+    let mut queue = vec![u64::from(r.u32(4)?)];
+    let mut seen = Vec::new();
+    while let Some(ifd) = queue.pop() {
+        if ifd < 8 || seen.contains(&ifd) || seen.len() >= MAX_IFDS {
+            continue;
+        }
+        seen.push(ifd);
+        let Some(count) = r.u16(ifd) else {
+            continue;
+        };
+        let count = count as usize;
+        if count == 0 || count > MAX_ENTRIES {
+            continue;
+        }
+        let ifd_end = ifd + 2 + count as u64 * 12 + 4;
+        layout.end = layout.end.max(ifd_end);
+
+        let entries: Vec<Entry> = (0..count as u64)
+            .filter_map(|i| {
+                let pos = ifd + 2 + i * 12;
+                let field_type = r.u16(pos + 2)?;
+                let count = u64::from(r.u32(pos + 4)?);
+                let value_len = type_size(field_type)?.checked_mul(count)?;
+                let value_pos = if value_len <= 4 {
+                    pos + 8
+                } else {
+                    u64::from(r.u32(pos + 8)?)
+                };
+                Some(Entry {
+                    tag: r.u16(pos)?,
+                    field_type,
+                    count,
+                    value_pos,
+                    value_len,
+                })
+            })
+            .collect();
+        let find = |tag: u16| entries.iter().find(|e| e.tag == tag);
+
+        for e in &entries {
+            layout.end = layout.end.max(e.value_pos + e.value_len);
+        }
+        if layout.make.is_none() {
+            layout.make = find(TAG_MAKE).and_then(|e| {
+                let bytes = data.get(e.value_pos as usize..(e.value_pos + e.value_len) as usize)?;
+                let text = String::from_utf8_lossy(bytes);
+                Some(text.trim_end_matches('\0').trim().to_string())
+            });
+        }
+        layout.is_dng |= find(TAG_DNG_VERSION).is_some();
+
+        let compression = find(TAG_COMPRESSION).and_then(|e| e.first(r));
+        for (offsets_tag, counts_tag) in [
+            (TAG_STRIP_OFFSETS, TAG_STRIP_BYTE_COUNTS),
+            (TAG_TILE_OFFSETS, TAG_TILE_BYTE_COUNTS),
+        ] {
+            let (Some(offsets), Some(counts)) = (find(offsets_tag), find(counts_tag)) else {
+                continue;
+            };
+            let offsets = offsets.values(r);
+            let counts = counts.values(r);
+            for (&offset, &length) in offsets.iter().zip(&counts) {
+                layout.end = layout.end.max(offset + length);
+            }
+            if let ([offset], [length]) = (offsets.as_slice(), counts.as_slice()) {
+                if compression.is_some_and(|c| JPEG_COMPRESSION.contains(&c)) {
+                    push_jpeg(&mut layout, data, *offset, *length);
+                }
+            }
+        }
+
+        if let (Some(offset), Some(length)) = (
+            find(TAG_JPEG_OFFSET).and_then(|e| e.first(r)),
+            find(TAG_JPEG_LENGTH).and_then(|e| e.first(r)),
+        ) {
+            layout.end = layout.end.max(offset + length);
+            push_jpeg(&mut layout, data, offset, length);
+        }
+
+        if let Some(e) = find(TAG_SUB_IFDS) {
+            queue.extend(e.values(r));
+        }
+        if let Some(exif) = find(TAG_EXIF_IFD).and_then(|e| e.first(r)) {
+            queue.push(exif);
+        }
+        if let Some(next) = r.u32(ifd_end - 4).filter(|&n| n != 0) {
+            queue.push(u64::from(next));
+        }
+    }
+
+    layout.jpegs.sort_by_key(|j| std::cmp::Reverse(j.length));
+    Some(layout)
+}
+
+/// Record a JPEG stream if it starts with an SOI marker
+fn push_jpeg(layout: &mut TiffLayout, data: &[u8], offset: u64, length: u64) {
+    if length > 2 && data.get(offset as usize..offset as usize + 2) == Some(&[0xFF, 0xD8]) {
+        let jpeg = JpegRef { offset, length };
+        if !layout.jpegs.contains(&jpeg) {
+            layout.jpegs.push(jpeg);
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Minimal little-endian NEF-like file: IFD0 with Make and a SubIFD
+    /// holding a JPEG preview, followed by "sensor data" in a strip
+    pub(crate) fn sample_raw(make: &str, jpeg: &[u8]) -> Vec<u8> {
+        let mut out = b"II\x2A\x00".to_vec();
+        out.extend_from_slice(&8u32.to_le_bytes());
+
+        let entry = |out: &mut Vec<u8>, tag: u16, ty: u16, count: u32, value: u32| {
+            out.extend_from_slice(&tag.to_le_bytes());
+            out.extend_from_slice(&ty.to_le_bytes());
+            out.extend_from_slice(&count.to_le_bytes());
+            out.extend_from_slice(&value.to_le_bytes());
+        };
+
+        // IFD0 at 8: Make, StripOffsets, StripByteCounts, SubIFDs
+        let ifd0_len = 2 + 4 * 12 + 4;
+        let make_pos = 8 + ifd0_len;
+        let make_bytes = format!("{}\0", make);
+        let sub_ifd = make_pos + make_bytes.len() as u32;
+        let sub_len = 2 + 2 * 12 + 4;
+        let jpeg_pos = sub_ifd + sub_len;
+        let strip_pos = jpeg_pos + jpeg.len() as u32;
+        let strip_len = 4096u32;
+
+        out.extend_from_slice(&4u16.to_le_bytes());
+        entry(&mut out, TAG_MAKE, 2, make_bytes.len() as u32, make_pos);
+        entry(&mut out, TAG_STRIP_OFFSETS, 4, 1, strip_pos);
+        entry(&mut out, TAG_STRIP_BYTE_COUNTS, 4, 1, strip_len);
+        entry(&mut out, TAG_SUB_IFDS, 4, 1, sub_ifd);
+        out.extend_from_slice(&0u32.to_le_bytes());
+        out.extend_from_slice(make_bytes.as_bytes());
+
+        out.extend_from_slice(&2u16.to_le_bytes());
+        entry(&mut out, TAG_JPEG_OFFSET, 4, 1, jpeg_pos);
+        entry(&mut out, TAG_JPEG_LENGTH, 4, 1, jpeg.len() as u32);
+        out.extend_from_slice(&0u32.to_le_bytes());
+
+        out.extend_from_slice(jpeg);
+        out.resize(out.len() + strip_len as usize, 0xA5);
+        out
+    }
+
+    #[test]
+    fn test_parse_raw_layout() {
+        let jpeg = [0xFF, 0xD8, 0xFF, 0xE0, 1, 2, 3, 0xFF, 0xD9];
+        let mut data = sample_raw("NIKON CORPORATION", &jpeg);
+        let file_len = data.len() as u64;
+        data.extend_from_slice(&[0u8; 1000]); // unrelated trailing bytes
+
+        let layout = parse(&data).unwrap();
+        assert_eq!(layout.end, file_len);
+        assert_eq!(layout.extension(), "nef");
+        assert_eq!(layout.jpegs.len(), 1);
+        let j = layout.jpegs[0];
+        assert_eq!(
+            &data[j.offset as usize..(j.offset + j.length) as usize],
+            &jpeg
+        );
+
+        assert_eq!(
+            parse(&sample_raw("SONY", &jpeg)).unwrap().extension(),
+            "arw"
+        );
+        assert!(parse(b"not a tiff").is_none());
+    }
+}
@@ -0,0 +1,329 @@
+//! ZIP structure for carving: archive sizing, split volumes, and the local
+//! entry walk [`super::ooxml`] validates documents with
+//!
+//! An archive is sized by following its own structure: the local entries
+//! (skipping each one's data by its size, so an archive stored inside it is
+//! passed over whole), then the central directory, then the end records.
+//! The end record has to point back at the central directory just walked;
+//! a local header hit in the middle of a larger archive points elsewhere
+//! and gets no size. Archives past 4 GB or 65,535 entries keep their sizes
+//! in ZIP64 extra fields and a ZIP64 end record, found through its locator.
+//!
+//! The first volume of a split (spanned) archive starts with the spanning
+//! marker and holds no central directory: it ends after the last entry
+//! whose CRC checks out, the next one continuing on the following volume.
+
+use std::io::Read;
+
+use flate2::read::DeflateDecoder;
+
+pub(super) const LOCAL_HEADER: &[u8] = b"PK\x03\x04";
+pub(super) const CENTRAL_HEADER: &[u8] = b"PK\x01\x02";
+pub(super) const END_RECORD: &[u8] = b"PK\x05\x06";
+const DATA_DESCRIPTOR: &[u8] = b"PK\x07\x08";
+const ZIP64_END_RECORD: &[u8] = b"PK\x06\x06";
+const ZIP64_LOCATOR: &[u8] = b"PK\x06\x07";
+
+/// Marker the first volume of a split archive starts with
+pub const SPANNING_MARKER: &[u8] = DATA_DESCRIPTOR;
+
+const LOCAL_HEADER_LEN: usize = 30;
+pub(super) const CENTRAL_HEADER_LEN: usize = 46;
+pub(super) const END_RECORD_LEN: usize = 22;
+const ZIP64_END_RECORD_LEN: usize = 56;
+const ZIP64_LOCATOR_LEN: usize = 20;
+
+/// Entry flag: sizes and CRC follow the data in a data descriptor
+const FLAG_DATA_DESCRIPTOR: u16 = 1 << 3;
+
+/// Extra field holding an entry's 64-bit sizes
+const ZIP64_EXTRA: u16 = 0x0001;
+
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATED: u16 = 8;
+
+/// One complete local entry
+pub(super) struct Entry<'a> {
+    pub offset: usize,
+    /// Local header fields from version-needed up to the name length,
+    /// with the real CRC and sizes filled in
+    pub fields: [u8; 26],
+    pub name: &'a [u8],
+    /// End of the entry's data (and data descriptor, if any)
+    pub end: usize,
+}
+
+/// Length of the archive `data` starts with
+pub fn parse_size(data: &[u8]) -> Option<u64> {
+    let mut pos = 0;
+    while data.get(pos..)?.starts_with(LOCAL_HEADER) {
+        pos = entry_end(data, pos)?;
+    }
+    if pos == 0 {
+        return None;
+    }
+
+    let directory_start = pos;
+    let mut entries = 0u64;
+    while data.get(pos..)?.starts_with(CENTRAL_HEADER) {
+        let header = data.get(pos..pos + CENTRAL_HEADER_LEN)?;
+        pos += CENTRAL_HEADER_LEN
+            + u16_at(header, 28) as usize
+            + u16_at(header, 30) as usize
+            + u16_at(header, 32) as usize;
+        entries += 1;
+    }
+    let directory = (directory_start as u64, (pos - directory_start) as u64);
+
+    if data.get(pos..)?.starts_with(ZIP64_END_RECORD) {
+        let record = data.get(pos..pos + ZIP64_END_RECORD_LEN)?;
+        if (u64_at(record, 48), u64_at(record, 40)) != directory || u64_at(record, 32) != entries {
+            return None;
+        }
+        let record_pos = pos as u64;
+        pos = pos
+            .checked_add(12)?
+            .checked_add(usize::try_from(u64_at(record, 4)).ok()?)?;
+        let locator = data.get(pos..pos + ZIP64_LOCATOR_LEN)?;
+        if !locator.starts_with(ZIP64_LOCATOR) || u64_at(locator, 8) != record_pos {
+            return None;
+        }
+        pos += ZIP64_LOCATOR_LEN;
+        // The end record's own fields may just say "see ZIP64"
+        if !data.get(pos..pos + END_RECORD_LEN)?.starts_with(END_RECORD) {
+            return None;
+        }
+    } else {
+        let record = data.get(pos..pos + END_RECORD_LEN)?;
+        let consistent = record.starts_with(END_RECORD)
+            && (u32_at(record, 16) as u64, u32_at(record, 12) as u64) == directory
+            && u16_at(record, 10) as u64 == entries;
+        if !consistent {
+            return None;
+        }
+    }
+    let end = pos + END_RECORD_LEN + u16_at(&data[pos..], 20) as usize;
+    (end <= data.len()).then_some(end as u64)
+}
+
+/// Length of the split archive volume `data` starts with
+pub fn parse_split_size(data: &[u8]) -> Option<u64> {
+    if !data.starts_with(SPANNING_MARKER) {
+        return None;
+    }
+    let (entries, end) = walk_entries(data, SPANNING_MARKER.len());
+    (!entries.is_empty()).then_some(end as u64)
+}
+
+/// The complete entries from `start` whose CRC matches, and where the walk
+/// stopped
+pub(super) fn walk_entries(data: &[u8], start: usize) -> (Vec<Entry<'_>>, usize) {
+    let mut entries = Vec::new();
+    let mut pos = start;
+    while let Some(entry) = read_entry(data, pos) {
+        pos = entry.end;
+        entries.push(entry);
+    }
+    (entries, pos)
+}
+
+fn read_entry(data: &[u8], offset: usize) -> Option<Entry<'_>> {
+    let header = data.get(offset..offset + LOCAL_HEADER_LEN)?;
+    if !header.starts_with(LOCAL_HEADER) {
+        return None;
+    }
+    let flags = u16_at(header, 6);
+    let method = u16_at(header, 8);
+    let name_len = u16_at(header, 26) as usize;
+    let extra_len = u16_at(header, 28) as usize;
+    let data_start = offset + LOCAL_HEADER_LEN + name_len + extra_len;
+    let name = data.get(offset + LOCAL_HEADER_LEN..offset + LOCAL_HEADER_LEN + name_len)?;
+
+    let mut fields: [u8; 26] = header[4..30].try_into().ok()?;
+    let (crc, compressed, end) = if flags & FLAG_DATA_DESCRIPTOR != 0 {
+        let found = find_descriptor(data, data_start, false)?;
+        let descriptor = &data[found..found + 16];
+        fields[10..22].copy_from_slice(&descriptor[4..16]);
+        (u32_at(descriptor, 4), found - data_start, found + 16)
+    } else {
+        let compressed = u32_at(header, 18) as usize;
+        (u32_at(header, 14), compressed, data_start + compressed)
+    };
+    let content = data.get(data_start..data_start + compressed)?;
+    if !crc_matches(content, method, crc) {
+        return None;
+    }
+    Some(Entry {
+        offset,
+        fields,
+        name,
+        end,
+    })
+}
+
+/// End of the local entry at `pos`, going by its sizes alone
+fn entry_end(data: &[u8], pos: usize) -> Option<usize> {
+    let header = data.get(pos..pos + LOCAL_HEADER_LEN)?;
+    let name_len = u16_at(header, 26) as usize;
+    let extra_len = u16_at(header, 28) as usize;
+    let extra_start = pos + LOCAL_HEADER_LEN + name_len;
+    let zip64 = zip64_sizes(data.get(extra_start..extra_start + extra_len)?);
+    let data_start = extra_start + extra_len;
+
+    if u16_at(header, 6) & FLAG_DATA_DESCRIPTOR != 0 {
+        let found = find_descriptor(data, data_start, zip64.is_some())?;
+        return Some(found + if zip64.is_some() { 24 } else { 16 });
+    }
+    let compressed = match u32_at(header, 18) {
+        u32::MAX => zip64?.1,
+        n => n as u64,
+    };
+    let end = data_start.checked_add(usize::try_from(compressed).ok()?)?;
+    (end <= data.len()).then_some(end)
+}
+
+/// Where the data descriptor for data starting at `data_start` is: the
+/// first one whose compressed size agrees with its position
+fn find_descriptor(data: &[u8], data_start: usize, zip64: bool) -> Option<usize> {
+    let len = if zip64 { 24 } else { 16 };
+    let mut search = data_start;
+    loop {
+        let found = memchr::memmem::find(data.get(search..)?, DATA_DESCRIPTOR)? + search;
+        let descriptor = data.get(found..found + len)?;
+        let compressed = if zip64 {
+            u64_at(descriptor, 8)
+        } else {
+            u32_at(descriptor, 8) as u64
+        };
+        if compressed == (found - data_start) as u64 {
+            return Some(found);
+        }
+        search = found + 4;
+    }
+}
+
+/// (uncompressed, compressed) from a local header's ZIP64 extra field
+fn zip64_sizes(mut extra: &[u8]) -> Option<(u64, u64)> {
+    while extra.len() >= 4 {
+        let len = u16_at(extra, 2) as usize;
+        let body = extra.get(4..4 + len)?;
+        if u16_at(extra, 0) == ZIP64_EXTRA && len >= 16 {
+            return Some((u64_at(body, 0), u64_at(body, 8)));
+        }
+        extra = &extra[4 + len..];
+    }
+    None
+}
+
+fn crc_matches(content: &[u8], method: u16, crc: u32) -> bool {
+    let mut hasher = flate2::Crc::new();
+    match method {
+        METHOD_STORED => hasher.update(content),
+        METHOD_DEFLATED => {
+            let mut decoder = DeflateDecoder::new(content);
+            let mut buf = [0u8; 64 * 1024];
+            loop {
+                match decoder.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(n) => hasher.update(&buf[..n]),
+                    Err(_) => return false,
+                }
+            }
+        }
+        // Other methods can't be checked without their decoders
+        _ => return false,
+    }
+    hasher.sum() == crc
+}
+
+pub(super) fn u16_at(data: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([data[at], data[at + 1]])
+}
+
+pub(super) fn u32_at(data: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]])
+}
+
+fn u64_at(data: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(data[at..at + 8].try_into().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn archive(names: &[&str], large_file: bool) -> Vec<u8> {
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated)
+            .large_file(large_file);
+        for name in names {
+            zip.start_file(*name, options).unwrap();
+            zip.write_all(name.repeat(200).as_bytes()).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    /// `plain` with its directory's position in a ZIP64 end record, as
+    /// written for archives past 4 GB
+    fn with_zip64_end(plain: Vec<u8>) -> Vec<u8> {
+        let eocd = plain.len() - END_RECORD_LEN;
+        let entries = u16_at(&plain, eocd + 10) as u64;
+        let mut out = plain[..eocd].to_vec();
+        let record_pos = out.len() as u64;
+        out.extend(ZIP64_END_RECORD);
+        out.extend(44u64.to_le_bytes());
+        out.extend([45, 0, 45, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        out.extend(entries.to_le_bytes());
+        out.extend(entries.to_le_bytes());
+        out.extend((u32_at(&plain, eocd + 12) as u64).to_le_bytes());
+        out.extend((u32_at(&plain, eocd + 16) as u64).to_le_bytes());
+        out.extend(ZIP64_LOCATOR);
+        out.extend(0u32.to_le_bytes());
+        out.extend(record_pos.to_le_bytes());
+        out.extend(1u32.to_le_bytes());
+        out.extend(END_RECORD);
+        out.extend([0, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF]);
+        out.extend([0xFF; 8]);
+        out.extend([0, 0]);
+        out
+    }
+
+    #[test]
+    fn test_archive_sized_by_structure() {
+        let inner = archive(&["a.txt", "b.txt"], false);
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        zip.start_file("inner.zip", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(&inner).unwrap();
+        let outer = zip.finish().unwrap().into_inner();
+
+        let mut carved = outer.clone();
+        carved.extend(archive(&["next.txt"], false));
+        assert_eq!(parse_size(&carved), Some(outer.len() as u64));
+
+        // A hit on the second entry belongs to a larger archive
+        let second = memchr::memmem::find(&inner[4..], LOCAL_HEADER).unwrap() + 4;
+        assert_eq!(parse_size(&inner[second..]), None);
+        // Cut short: no end record
+        assert_eq!(parse_size(&outer[..outer.len() - 10]), None);
+    }
+
+    #[test]
+    fn test_zip64_and_split_volumes() {
+        let zip64 = with_zip64_end(archive(&["big.bin", "more.bin"], true));
+        let mut carved = zip64.clone();
+        carved.extend([0u8; 512]);
+        assert_eq!(parse_size(&carved), Some(zip64.len() as u64));
+
+        // First volume: marker, two whole entries, then the third cut off
+        let plain = archive(&["one.txt", "two.txt", "three.txt"], false);
+        let third = memchr::memmem::find(&plain, b"three.txt").unwrap() - LOCAL_HEADER_LEN;
+        let mut volume = SPANNING_MARKER.to_vec();
+        volume.extend(&plain[..third + 40]);
+        let mut carved = volume.clone();
+        carved.extend([0xEE; 256]);
+        assert_eq!(parse_split_size(&carved), Some(third as u64 + 4));
+    }
+}
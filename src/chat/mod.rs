@@ -0,0 +1,644 @@
+//! Chat app export grouping (WhatsApp, Telegram)
+//!
+//! Recovered phones and backups often contain thousands of chat media files
+//! with meaningless names like `IMG-20230115-WA0003.jpg`. This module
+//! recognizes the folder layouts and export files these apps leave behind,
+//! maps media back to the conversation it was sent in, and exports each
+//! conversation into its own folder with an `index.html` for review.
+//!
+//! Sources of conversation information, in order of preference:
+//! - WhatsApp "Export chat" text files (`WhatsApp Chat with X.txt`, `_chat.txt`)
+//! - Telegram Desktop JSON exports (`result.json`)
+//! - WhatsApp `msgstore.db` databases (with the `sqlite` feature; encrypted
+//!   `.crypt*` backups are only reported)
+//!
+//! Media found in app media folders that no source claims is grouped into an
+//! "unsorted" conversation per app so it still gets reviewed by date.
+
+pub mod telegram;
+pub mod whatsapp;
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::fmt::Write as FmtWrite;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::Serialize;
+use walkdir::WalkDir;
+
+use crate::core::{display_timezone, FileEntry, FileType};
+use crate::export::{ExportOptions, Exporter};
+
+/// Name of the per-conversation review page
+pub const INDEX_FILE: &str = "index.html";
+
+/// Chat application a conversation came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+pub enum ChatApp {
+    WhatsApp,
+    Telegram,
+}
+
+impl fmt::Display for ChatApp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChatApp::WhatsApp => write!(f, "WhatsApp"),
+            ChatApp::Telegram => write!(f, "Telegram"),
+        }
+    }
+}
+
+/// A media reference found in a chat export or database, before it is
+/// matched to a file on disk
+#[derive(Debug, Clone)]
+pub struct MediaRef {
+    /// File name or path relative to the export (as written by the app)
+    pub file: String,
+    pub sender: Option<String>,
+    /// Wall-clock time of the message as shown on the phone
+    pub sent: Option<NaiveDateTime>,
+}
+
+/// A conversation as described by one export file or database
+#[derive(Debug, Clone)]
+pub struct ChatLog {
+    pub app: ChatApp,
+    pub name: String,
+    /// Export file or database the conversation was read from
+    pub source: PathBuf,
+    pub media: Vec<MediaRef>,
+}
+
+/// A recovered media file attributed to a conversation
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatMedia {
+    pub path: PathBuf,
+    pub size: u64,
+    pub file_type: FileType,
+    pub sender: Option<String>,
+    pub sent: Option<NaiveDateTime>,
+    /// Day the media was sent (from the message, the file name, or mtime)
+    pub date: Option<NaiveDate>,
+}
+
+/// Media grouped into one conversation
+#[derive(Debug, Clone, Serialize)]
+pub struct Conversation {
+    pub app: ChatApp,
+    pub name: String,
+    /// Export file or database the grouping came from (`None` for unsorted media)
+    pub source: Option<PathBuf>,
+    pub media: Vec<ChatMedia>,
+    /// Attachments referenced by the chat that were not found on disk
+    pub missing: Vec<String>,
+}
+
+impl Conversation {
+    /// True for the catch-all group of media no export claimed
+    pub fn is_unsorted(&self) -> bool {
+        self.source.is_none()
+    }
+
+    /// First and last day with media
+    pub fn date_range(&self) -> Option<(NaiveDate, NaiveDate)> {
+        let mut dates = self.media.iter().filter_map(|m| m.date);
+        let first = dates.next()?;
+        Some(dates.fold((first, first), |(lo, hi), d| (lo.min(d), hi.max(d))))
+    }
+
+    /// Media grouped by day; undated media comes last under `None`
+    pub fn by_date(&self) -> Vec<(Option<NaiveDate>, Vec<&ChatMedia>)> {
+        let mut days: BTreeMap<NaiveDate, Vec<&ChatMedia>> = BTreeMap::new();
+        let mut undated = Vec::new();
+        for media in &self.media {
+            match media.date {
+                Some(day) => days.entry(day).or_default().push(media),
+                None => undated.push(media),
+            }
+        }
+        let mut groups: Vec<_> = days.into_iter().map(|(d, m)| (Some(d), m)).collect();
+        if !undated.is_empty() {
+            groups.push((None, undated));
+        }
+        groups
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.media.iter().map(|m| m.size).sum()
+    }
+
+    /// Folder name used when exporting this conversation
+    pub fn folder_name(&self) -> String {
+        let name: String = self
+            .name
+            .chars()
+            .map(|c| match c {
+                '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+                c if c.is_control() => '_',
+                c => c,
+            })
+            .collect();
+        let name = name.trim().trim_matches('.').to_string();
+        if name.is_empty() {
+            "unnamed".to_string()
+        } else {
+            name
+        }
+    }
+}
+
+/// Result of scanning a source for chat app data
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ChatScan {
+    pub conversations: Vec<Conversation>,
+    /// Encrypted WhatsApp backups (`msgstore.db.crypt14` etc.) that need a key
+    pub encrypted_databases: Vec<PathBuf>,
+    /// Export files or databases that could not be read
+    pub warnings: Vec<String>,
+}
+
+/// Scan `root` for WhatsApp/Telegram exports and media folders and group
+/// the media into conversations.
+pub fn scan(root: &Path) -> Result<ChatScan> {
+    if !root.exists() {
+        anyhow::bail!("Source does not exist: {}", root.display());
+    }
+
+    let mut files = Vec::new();
+    for entry in WalkDir::new(root).follow_links(false) {
+        let Ok(entry) = entry else { continue };
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        if let Ok(metadata) = entry.metadata() {
+            files.push(FileEntry::new(entry.into_path(), &metadata));
+        }
+    }
+
+    let mut scan = ChatScan::default();
+    let mut logs = Vec::new();
+    for file in &files {
+        let name = file.name();
+        let parsed = if whatsapp::is_chat_export(&file.path) {
+            whatsapp::parse_chat_export(&file.path).map(|log| vec![log])
+        } else if telegram::is_export(&file.path) {
+            telegram::parse_export(&file.path)
+        } else if whatsapp::is_encrypted_backup(&name) {
+            scan.encrypted_databases.push(file.path.clone());
+            continue;
+        } else if whatsapp::is_msgstore(&name) {
+            whatsapp::read_msgstore(&file.path)
+        } else {
+            continue;
+        };
+        match parsed {
+            Ok(parsed) => logs.extend(parsed),
+            Err(e) => scan
+                .warnings
+                .push(format!("{}: {:#}", file.path.display(), e)),
+        }
+    }
+
+    scan.conversations = group(&files, logs);
+    Ok(scan)
+}
+
+/// Match chat logs to files and collect leftover app media into unsorted groups
+pub fn group(files: &[FileEntry], logs: Vec<ChatLog>) -> Vec<Conversation> {
+    let by_path: HashMap<&Path, &FileEntry> = files.iter().map(|f| (f.path.as_path(), f)).collect();
+    let mut by_name: HashMap<String, Vec<&FileEntry>> = HashMap::new();
+    for file in files {
+        by_name
+            .entry(file.name().to_lowercase())
+            .or_default()
+            .push(file);
+    }
+
+    let mut claimed: HashSet<PathBuf> = logs.iter().map(|l| l.source.clone()).collect();
+    let mut conversations = Vec::new();
+
+    for log in logs {
+        let base = log.source.parent().unwrap_or(Path::new(""));
+        let mut conversation = Conversation {
+            app: log.app,
+            name: log.name,
+            source: Some(log.source.clone()),
+            media: Vec::new(),
+            missing: Vec::new(),
+        };
+        let mut seen = HashSet::new();
+
+        for media_ref in log.media {
+            let found = by_path
+                .get(base.join(&media_ref.file).as_path())
+                .copied()
+                .or_else(|| {
+                    let name = Path::new(&media_ref.file).file_name()?;
+                    let candidates = by_name.get(&name.to_string_lossy().to_lowercase())?;
+                    // Prefer a copy inside the app's media folders
+                    candidates
+                        .iter()
+                        .find(|f| app_for_media_folder(&f.path) == Some(log.app))
+                        .or_else(|| candidates.first())
+                        .copied()
+                });
+            let Some(file) = found else {
+                conversation.missing.push(media_ref.file);
+                continue;
+            };
+            if !seen.insert(file.path.clone()) {
+                continue;
+            }
+            claimed.insert(file.path.clone());
+            conversation
+                .media
+                .push(chat_media(file, media_ref.sender, media_ref.sent));
+        }
+
+        if !conversation.media.is_empty() || !conversation.missing.is_empty() {
+            conversations.push(conversation);
+        }
+    }
+
+    let mut unsorted: BTreeMap<ChatApp, Vec<ChatMedia>> = BTreeMap::new();
+    for file in files {
+        if claimed.contains(&file.path) {
+            continue;
+        }
+        if let Some(app) = app_for_media_folder(&file.path) {
+            unsorted
+                .entry(app)
+                .or_default()
+                .push(chat_media(file, None, None));
+        }
+    }
+    for (app, media) in unsorted {
+        conversations.push(Conversation {
+            app,
+            name: format!("Unsorted {} media", app),
+            source: None,
+            media,
+            missing: Vec::new(),
+        });
+    }
+
+    for conversation in &mut conversations {
+        conversation
+            .media
+            .sort_by(|a, b| (a.date, a.sent, &a.path).cmp(&(b.date, b.sent, &b.path)));
+    }
+    conversations.sort_by(|a, b| {
+        (a.app, a.is_unsorted(), a.name.to_lowercase()).cmp(&(
+            b.app,
+            b.is_unsorted(),
+            b.name.to_lowercase(),
+        ))
+    });
+    conversations
+}
+
+fn chat_media(file: &FileEntry, sender: Option<String>, sent: Option<NaiveDateTime>) -> ChatMedia {
+    let name = file.name();
+    let date = sent
+        .map(|s| s.date())
+        .or_else(|| whatsapp::date_from_file_name(&name))
+        .or_else(|| telegram::date_from_file_name(&name))
+        .or_else(|| {
+            file.modified
+                .map(|m| display_timezone().format(&m, "%Y-%m-%d"))
+                .and_then(|d| NaiveDate::parse_from_str(&d, "%Y-%m-%d").ok())
+        });
+    ChatMedia {
+        path: file.path.clone(),
+        size: file.size,
+        file_type: file.file_type,
+        sender,
+        sent,
+        date,
+    }
+}
+
+/// Which app's media folder `path` lives in, if any
+pub fn app_for_media_folder(path: &Path) -> Option<ChatApp> {
+    path.parent()?.components().rev().find_map(|c| {
+        let name = c.as_os_str().to_string_lossy();
+        if whatsapp::MEDIA_FOLDERS
+            .iter()
+            .any(|f| name.eq_ignore_ascii_case(f))
+        {
+            Some(ChatApp::WhatsApp)
+        } else if telegram::MEDIA_FOLDERS
+            .iter()
+            .any(|f| name.eq_ignore_ascii_case(f))
+        {
+            Some(ChatApp::Telegram)
+        } else {
+            None
+        }
+    })
+}
+
+/// Outcome of exporting one conversation
+#[derive(Debug, Clone, Serialize)]
+pub struct ConversationExport {
+    pub app: ChatApp,
+    pub name: String,
+    pub folder: PathBuf,
+    pub index: PathBuf,
+    pub exported: usize,
+    pub failed: usize,
+    pub bytes: u64,
+}
+
+/// Export each conversation into `<dest>/<app>/<conversation>/` with an
+/// `index.html` page, plus a top-level `index.html` linking all of them.
+pub async fn export_conversations(
+    conversations: &[Conversation],
+    dest: &Path,
+    verify_hash: bool,
+) -> Result<Vec<ConversationExport>> {
+    let mut results = Vec::new();
+    let mut used_folders = HashSet::new();
+
+    for conversation in conversations {
+        let mut folder_name = conversation.folder_name();
+        let app_dir = dest.join(conversation.app.to_string());
+        let mut suffix = 2;
+        while !used_folders.insert(app_dir.join(&folder_name)) {
+            folder_name = format!("{} ({})", conversation.folder_name(), suffix);
+            suffix += 1;
+        }
+        let folder = app_dir.join(&folder_name);
+
+        let entries: Vec<FileEntry> = conversation
+            .media
+            .iter()
+            .map(|m| FileEntry {
+                path: m.path.clone(),
+                size: m.size,
+                file_type: m.file_type,
+                extension: m
+                    .path
+                    .extension()
+                    .map(|e| e.to_string_lossy().to_lowercase())
+                    .unwrap_or_default(),
+                modified: None,
+                created: None,
+                hash: None,
+                has_bad_sectors: false,
+                thumbnail: None,
+                metadata: Default::default(),
+            })
+            .collect();
+
+        let exporter = Exporter::new(ExportOptions {
+            dest: folder.clone(),
+            verify_hash,
+            continue_on_error: true,
+            create_manifest: true,
+            ..Default::default()
+        });
+        let result = exporter
+            .export_batch(&entries, |_| {})
+            .await
+            .with_context(|| format!("Failed to export conversation '{}'", conversation.name))?;
+
+        let index = folder.join(INDEX_FILE);
+        std::fs::write(&index, conversation_html(conversation))
+            .with_context(|| format!("Failed to write {}", index.display()))?;
+
+        results.push(ConversationExport {
+            app: conversation.app,
+            name: conversation.name.clone(),
+            folder,
+            index,
+            exported: result.successful,
+            failed: result.failed,
+            bytes: result.total_bytes,
+        });
+    }
+
+    if !results.is_empty() {
+        let overview = dest.join(INDEX_FILE);
+        std::fs::write(&overview, overview_html(dest, &results))
+            .with_context(|| format!("Failed to write {}", overview.display()))?;
+    }
+
+    Ok(results)
+}
+
+const PAGE_STYLE: &str =
+    "body{font-family:system-ui,sans-serif;background:#0b1220;color:#e2e8f0;margin:2rem}\
+h1{color:#7dd3fc}h2{color:#93c5fd;border-bottom:1px solid #1e293b;padding-bottom:.3rem}\
+a{color:#7dd3fc}.meta{color:#94a3b8}.grid{display:flex;flex-wrap:wrap;gap:1rem}\
+.item{background:#111a2e;border-radius:8px;padding:.6rem;width:220px;word-break:break-all}\
+.item img,.item video{max-width:100%;max-height:200px;border-radius:4px}.item audio{width:100%}\
+.missing{color:#fca5a5}";
+
+/// Render the review page for one exported conversation. Media is linked by
+/// file name, relative to the conversation folder.
+pub fn conversation_html(conversation: &Conversation) -> String {
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{app} – {name}</title>\
+         <style>{style}</style></head><body>\n<h1>{app} – {name}</h1>\n<p class=\"meta\">{count} files, {size}",
+        app = conversation.app,
+        name = html_escape(&conversation.name),
+        style = PAGE_STYLE,
+        count = conversation.media.len(),
+        size = humansize::format_size(conversation.total_bytes(), humansize::BINARY),
+    );
+    if let Some((first, last)) = conversation.date_range() {
+        let _ = write!(html, ", {} to {}", first, last);
+    }
+    if let Some(source) = &conversation.source {
+        let _ = write!(
+            html,
+            "<br>Source: {}",
+            html_escape(&source.display().to_string())
+        );
+    }
+    html.push_str("</p>\n");
+
+    for (date, media) in conversation.by_date() {
+        let heading = date.map_or_else(
+            || "Undated".to_string(),
+            |d| d.format("%A, %Y-%m-%d").to_string(),
+        );
+        let _ = writeln!(html, "<h2>{}</h2>\n<div class=\"grid\">", heading);
+        for item in media {
+            let name = item
+                .path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let href = url_escape(&name);
+            let name = html_escape(&name);
+            html.push_str("<div class=\"item\">");
+            match item.file_type {
+                FileType::Image => {
+                    let _ = write!(html, "<a href=\"{href}\"><img src=\"{href}\" loading=\"lazy\" alt=\"{name}\"></a>");
+                }
+                FileType::Video => {
+                    let _ = write!(
+                        html,
+                        "<video src=\"{href}\" controls preload=\"none\"></video>"
+                    );
+                }
+                FileType::Audio => {
+                    let _ = write!(
+                        html,
+                        "<audio src=\"{href}\" controls preload=\"none\"></audio>"
+                    );
+                }
+                _ => {}
+            }
+            let _ = write!(
+                html,
+                "<div><a href=\"{href}\">{name}</a></div><div class=\"meta\">"
+            );
+            if let Some(sent) = item.sent {
+                let _ = write!(html, "{} ", sent.format("%H:%M"));
+            }
+            if let Some(sender) = &item.sender {
+                let _ = write!(html, "{} · ", html_escape(sender));
+            }
+            let _ = writeln!(
+                html,
+                "{}</div></div>",
+                humansize::format_size(item.size, humansize::BINARY)
+            );
+        }
+        html.push_str("</div>\n");
+    }
+
+    if !conversation.missing.is_empty() {
+        let _ = writeln!(
+            html,
+            "<h2>Not recovered</h2>\n<p class=\"meta\">Referenced in the chat but not found on the source:</p>\n<ul class=\"missing\">"
+        );
+        for name in &conversation.missing {
+            let _ = writeln!(html, "<li>{}</li>", html_escape(name));
+        }
+        html.push_str("</ul>\n");
+    }
+
+    html.push_str("</body></html>\n");
+    html
+}
+
+/// Render the top-level page linking every exported conversation
+pub fn overview_html(dest: &Path, exports: &[ConversationExport]) -> String {
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Chat conversations</title>\
+         <style>{}</style></head><body>\n<h1>Chat conversations</h1>\n<ul>\n",
+        PAGE_STYLE
+    );
+    for export in exports {
+        let href = export
+            .index
+            .strip_prefix(dest)
+            .unwrap_or(&export.index)
+            .components()
+            .map(|c| url_escape(&c.as_os_str().to_string_lossy()))
+            .collect::<Vec<_>>()
+            .join("/");
+        let _ = writeln!(
+            html,
+            "<li><a href=\"{}\">{} – {}</a> <span class=\"meta\">({} files)</span></li>",
+            href,
+            export.app,
+            html_escape(&export.name),
+            export.exported
+        );
+    }
+    html.push_str("</ul>\n</body></html>\n");
+    html
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Percent-encode a file name for use in a relative URL
+fn url_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(b as char)
+            }
+            _ => {
+                let _ = write!(out, "%{:02X}", b);
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_scan_and_export_whatsapp_conversation() {
+        let dir = tempdir().unwrap();
+        let export_dir = dir.path().join("Chat export");
+        let media_dir = dir.path().join("WhatsApp/Media/WhatsApp Images");
+        std::fs::create_dir_all(&export_dir).unwrap();
+        std::fs::create_dir_all(&media_dir).unwrap();
+
+        std::fs::write(
+            export_dir.join("WhatsApp Chat with Alice & Bob.txt"),
+            "15/01/2023, 12:34 - Alice: IMG-20230115-WA0003.jpg (file attached)\n\
+             15/01/2023, 12:35 - Bob: nice!\n\
+             16/01/2023, 09:00 - Bob: PTT-20230116-WA0001.opus (file attached)\n\
+             16/01/2023, 09:01 - Bob: DOC-20230116-WA0002.pdf (file attached)\n",
+        )
+        .unwrap();
+        std::fs::write(export_dir.join("PTT-20230116-WA0001.opus"), b"voice").unwrap();
+        std::fs::write(media_dir.join("IMG-20230115-WA0003.jpg"), b"jpeg").unwrap();
+        std::fs::write(media_dir.join("IMG-20221224-WA0010.jpg"), b"other").unwrap();
+
+        let scan = scan(dir.path()).unwrap();
+        assert_eq!(scan.conversations.len(), 2);
+
+        let chat = &scan.conversations[0];
+        assert_eq!(chat.name, "Alice & Bob");
+        assert_eq!(chat.media.len(), 2);
+        assert_eq!(chat.media[0].sender.as_deref(), Some("Alice"));
+        assert_eq!(chat.media[0].date, NaiveDate::from_ymd_opt(2023, 1, 15));
+        assert_eq!(chat.missing, vec!["DOC-20230116-WA0002.pdf".to_string()]);
+        assert_eq!(chat.by_date().len(), 2);
+
+        let unsorted = &scan.conversations[1];
+        assert!(unsorted.is_unsorted());
+        assert_eq!(unsorted.media.len(), 1);
+        assert_eq!(
+            unsorted.media[0].date,
+            NaiveDate::from_ymd_opt(2022, 12, 24)
+        );
+
+        let out = dir.path().join("out");
+        let exports = export_conversations(&scan.conversations, &out, true)
+            .await
+            .unwrap();
+        assert_eq!(exports[0].exported, 2);
+        let folder = out.join("WhatsApp").join("Alice & Bob");
+        assert!(folder.join("IMG-20230115-WA0003.jpg").exists());
+        let page = std::fs::read_to_string(folder.join(INDEX_FILE)).unwrap();
+        assert!(page.contains("<img src=\"IMG-20230115-WA0003.jpg\""));
+        assert!(page.contains("Alice &amp; Bob"));
+        assert!(page.contains("DOC-20230116-WA0002.pdf"));
+        let overview = std::fs::read_to_string(out.join(INDEX_FILE)).unwrap();
+        assert!(overview.contains("WhatsApp/Alice%20%26%20Bob/index.html"));
+    }
+}
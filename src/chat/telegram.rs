@@ -0,0 +1,178 @@
+//! Telegram Desktop JSON exports and Telegram media folders
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::{NaiveDate, NaiveDateTime};
+use serde_json::Value;
+
+use super::{ChatApp, ChatLog, MediaRef};
+
+/// Media folder names used by Telegram on Android (`Telegram/Telegram Images`
+/// or `Android/media/org.telegram.messenger/Telegram/...`)
+pub const MEDIA_FOLDERS: &[&str] = &[
+    "Telegram Images",
+    "Telegram Video",
+    "Telegram Audio",
+    "Telegram Documents",
+    "Telegram Stickers",
+];
+
+/// JSON exports larger than this are not parsed
+const MAX_EXPORT_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// Message fields that reference an exported file
+const MEDIA_FIELDS: &[&str] = &["photo", "file"];
+
+/// Whether `path` is a Telegram Desktop export (`result.json`)
+pub fn is_export(path: &Path) -> bool {
+    path.file_name().is_some_and(|n| n == "result.json")
+}
+
+/// Parse a Telegram Desktop export, either a single chat or a full export
+/// with `chats.list` / `left_chats.list`
+pub fn parse_export(path: &Path) -> Result<Vec<ChatLog>> {
+    let size = std::fs::metadata(path)
+        .with_context(|| format!("Failed to stat {}", path.display()))?
+        .len();
+    if size > MAX_EXPORT_BYTES {
+        anyhow::bail!("Telegram export is too large to parse ({} bytes)", size);
+    }
+    let file =
+        std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let root: Value = serde_json::from_reader(std::io::BufReader::new(file))
+        .with_context(|| format!("Invalid Telegram export: {}", path.display()))?;
+    Ok(parse_value(&root, path))
+}
+
+fn parse_value(root: &Value, source: &Path) -> Vec<ChatLog> {
+    let mut chats: Vec<&Value> = Vec::new();
+    if root.get("messages").is_some() {
+        chats.push(root);
+    }
+    for key in ["chats", "left_chats"] {
+        if let Some(list) = root
+            .pointer(&format!("/{}/list", key))
+            .and_then(Value::as_array)
+        {
+            chats.extend(list);
+        }
+    }
+
+    chats
+        .into_iter()
+        .filter_map(|chat| {
+            let media: Vec<MediaRef> = chat
+                .get("messages")?
+                .as_array()?
+                .iter()
+                .flat_map(|message| {
+                    MEDIA_FIELDS.iter().filter_map(move |field| {
+                        let file = message.get(*field)?.as_str()?;
+                        // Files skipped by the export settings are replaced by
+                        // "(File not included. Change data exporting settings to download.)"
+                        if file.starts_with('(') {
+                            return None;
+                        }
+                        Some(MediaRef {
+                            file: file.to_string(),
+                            sender: message
+                                .get("from")
+                                .and_then(Value::as_str)
+                                .map(str::to_string),
+                            sent: message.get("date").and_then(Value::as_str).and_then(|d| {
+                                NaiveDateTime::parse_from_str(d, "%Y-%m-%dT%H:%M:%S").ok()
+                            }),
+                        })
+                    })
+                })
+                .collect();
+            if media.is_empty() {
+                return None;
+            }
+            let name = chat
+                .get("name")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .unwrap_or_else(|| match chat.get("type").and_then(Value::as_str) {
+                    Some("saved_messages") => "Saved Messages".to_string(),
+                    _ => format!(
+                        "Chat {}",
+                        chat.get("id").map(|id| id.to_string()).unwrap_or_default()
+                    ),
+                });
+            Some(ChatLog {
+                app: ChatApp::Telegram,
+                name,
+                source: source.to_path_buf(),
+                media,
+            })
+        })
+        .collect()
+}
+
+/// Send date encoded in Telegram media names: `IMG_20230115_123456_123.jpg`
+/// (Android) or `photo_1@15-01-2023_12-34-56.jpg` (Desktop export)
+pub fn date_from_file_name(name: &str) -> Option<NaiveDate> {
+    if let Some((_, rest)) = name.split_once('@') {
+        return NaiveDate::parse_from_str(rest.get(..10)?, "%d-%m-%Y").ok();
+    }
+    let mut parts = name.split('_');
+    let prefix = parts.next()?;
+    if !matches!(prefix, "IMG" | "VID" | "AUD" | "PHOTO" | "VIDEO") {
+        return None;
+    }
+    let date = parts.next()?;
+    if date.len() != 8 {
+        return None;
+    }
+    NaiveDate::parse_from_str(date, "%Y%m%d").ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_full_export() {
+        let json = serde_json::json!({
+            "chats": { "list": [
+                {
+                    "name": "Family",
+                    "type": "private_group",
+                    "id": 42,
+                    "messages": [
+                        { "id": 1, "type": "message", "date": "2023-01-15T12:34:56",
+                          "from": "Mum", "photo": "photos/photo_1@15-01-2023_12-34-56.jpg" },
+                        { "id": 2, "type": "message", "date": "2023-01-15T12:35:00",
+                          "from": "Dad", "text": "hi" },
+                        { "id": 3, "type": "message", "date": "2023-01-16T08:00:00",
+                          "from": "Dad",
+                          "file": "(File not included. Change data exporting settings to download.)" }
+                    ]
+                },
+                { "type": "saved_messages", "id": 1, "messages": [
+                    { "id": 9, "type": "message", "date": "2023-02-01T10:00:00",
+                      "file": "files/report.pdf" }
+                ]}
+            ]}
+        });
+        let logs = parse_value(&json, Path::new("export/result.json"));
+        assert_eq!(logs.len(), 2);
+        assert_eq!(logs[0].name, "Family");
+        assert_eq!(logs[0].media.len(), 1);
+        assert_eq!(logs[0].media[0].sender.as_deref(), Some("Mum"));
+        assert_eq!(logs[1].name, "Saved Messages");
+        assert_eq!(logs[1].media[0].file, "files/report.pdf");
+
+        assert_eq!(
+            date_from_file_name("photo_1@15-01-2023_12-34-56.jpg"),
+            NaiveDate::from_ymd_opt(2023, 1, 15)
+        );
+        assert_eq!(
+            date_from_file_name("VID_20230116_080000_001.mp4"),
+            NaiveDate::from_ymd_opt(2023, 1, 16)
+        );
+        assert_eq!(date_from_file_name("holiday.jpg"), None);
+    }
+}
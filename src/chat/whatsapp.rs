@@ -0,0 +1,370 @@
+//! WhatsApp chat exports, media folders and message databases
+
+use std::path::Path;
+use std::sync::OnceLock;
+
+use anyhow::{Context, Result};
+use chrono::{NaiveDate, NaiveTime};
+use regex::Regex;
+
+use super::{ChatApp, ChatLog, MediaRef};
+
+/// Media folder names used by WhatsApp on Android (`WhatsApp/Media/...` or
+/// `Android/media/com.whatsapp/WhatsApp/Media/...`)
+pub const MEDIA_FOLDERS: &[&str] = &[
+    "WhatsApp Images",
+    "WhatsApp Video",
+    "WhatsApp Animated Gifs",
+    "WhatsApp Audio",
+    "WhatsApp Voice Notes",
+    "WhatsApp Documents",
+    "WhatsApp Stickers",
+];
+
+/// Chat export text files larger than this are not parsed
+const MAX_EXPORT_BYTES: u64 = 256 * 1024 * 1024;
+
+/// "Attached" markers in localized Android exports
+const ATTACHED_MARKERS: &[&str] = &[
+    "(file attached)",
+    "(Datei angehängt)",
+    "(archivo adjunto)",
+    "(fichier joint)",
+    "(arquivo anexado)",
+    "(file allegato)",
+    "(bestand bijgevoegd)",
+];
+
+/// Whether `path` is a WhatsApp "Export chat" text file
+pub fn is_chat_export(path: &Path) -> bool {
+    let Some(name) = path.file_name().map(|n| n.to_string_lossy()) else {
+        return false;
+    };
+    name == "_chat.txt"
+        || (name.starts_with("WhatsApp Chat") && name.to_lowercase().ends_with(".txt"))
+}
+
+/// Whether `name` is an encrypted WhatsApp backup (`msgstore.db.crypt14`)
+pub fn is_encrypted_backup(name: &str) -> bool {
+    name.to_lowercase()
+        .rsplit_once(".crypt")
+        .is_some_and(|(stem, version)| {
+            stem.starts_with("msgstore") && version.chars().all(|c| c.is_ascii_digit())
+        })
+}
+
+/// Whether `name` is a decrypted WhatsApp message database
+pub fn is_msgstore(name: &str) -> bool {
+    name.eq_ignore_ascii_case("msgstore.db")
+}
+
+/// Conversation name from the export file name: `WhatsApp Chat with Alice.txt`,
+/// `WhatsApp Chat - Alice.txt`, or the folder of an iOS `_chat.txt`
+fn conversation_name(path: &Path) -> String {
+    let stem = if path.file_name().is_some_and(|n| n == "_chat.txt") {
+        path.parent()
+            .and_then(|p| p.file_name())
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default()
+    } else {
+        path.file_stem()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default()
+    };
+    let name = ["WhatsApp Chat with ", "WhatsApp Chat - ", "WhatsApp Chat"]
+        .iter()
+        .find_map(|prefix| stem.strip_prefix(prefix))
+        .unwrap_or(&stem)
+        .trim();
+    if name.is_empty() {
+        "WhatsApp chat".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+fn message_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        // Android: `15/01/2023, 12:34 - Alice: text`
+        // iOS:     `[15/01/2023, 12:34:56] Alice: text`
+        Regex::new(
+            r"^[\u{200e}\u{feff}]?\[?(\d{1,4}[./-]\d{1,2}[./-]\d{2,4}),? (\d{1,2}[:.]\d{2}(?:[:.]\d{2})?(?:[\s\u{202f}]?[AaPp]\.?[Mm]\.?)?)\]?(?: -)? ([^:]+?): (.*)$",
+        )
+        .expect("valid message regex")
+    })
+}
+
+fn attachment(text: &str) -> Option<String> {
+    let text = text.trim().trim_start_matches('\u{200e}');
+    if let Some(rest) = text.strip_prefix("<attached: ") {
+        return Some(rest.trim_end_matches('>').trim().to_string());
+    }
+    ATTACHED_MARKERS.iter().find_map(|marker| {
+        text.strip_suffix(marker)
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+    })
+}
+
+/// Parse an exported chat and collect its attachment references
+pub fn parse_chat_export(path: &Path) -> Result<ChatLog> {
+    let size = std::fs::metadata(path)
+        .with_context(|| format!("Failed to stat {}", path.display()))?
+        .len();
+    if size > MAX_EXPORT_BYTES {
+        anyhow::bail!("chat export is too large to parse ({} bytes)", size);
+    }
+    let bytes =
+        std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let text = String::from_utf8_lossy(&bytes);
+    Ok(parse_chat_text(&text, conversation_name(path), path))
+}
+
+fn parse_chat_text(text: &str, name: String, source: &Path) -> ChatLog {
+    let re = message_regex();
+    let messages: Vec<(&str, &str, &str, &str)> = text
+        .lines()
+        .filter_map(|line| {
+            let caps = re.captures(line)?;
+            Some((
+                caps.get(1)?.as_str(),
+                caps.get(2)?.as_str(),
+                caps.get(3)?.as_str(),
+                caps.get(4)?.as_str(),
+            ))
+        })
+        .collect();
+
+    let order = DateOrder::detect(messages.iter().map(|m| m.0));
+    let media = messages
+        .into_iter()
+        .filter_map(|(date, time, sender, body)| {
+            Some(MediaRef {
+                file: attachment(body)?,
+                sender: Some(sender.trim_start_matches('\u{200e}').trim().to_string()),
+                sent: parse_date(date, order)
+                    .zip(parse_time(time))
+                    .map(|(d, t)| d.and_time(t)),
+            })
+        })
+        .collect();
+
+    ChatLog {
+        app: ChatApp::WhatsApp,
+        name,
+        source: source.to_path_buf(),
+        media,
+    }
+}
+
+/// Order of day and month in an export, which follows the phone's locale
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DateOrder {
+    Dmy,
+    Mdy,
+    Ymd,
+}
+
+impl DateOrder {
+    /// Pick the order that makes every date in the export valid, preferring
+    /// day-first when the export never disambiguates
+    fn detect<'a>(dates: impl Iterator<Item = &'a str>) -> Self {
+        let mut order = DateOrder::Dmy;
+        for date in dates {
+            let parts = split_date(date);
+            if parts.len() != 3 {
+                continue;
+            }
+            if parts[0].len() == 4 {
+                return DateOrder::Ymd;
+            }
+            let (a, b) = (parts[0].parse::<u32>(), parts[1].parse::<u32>());
+            match (a, b) {
+                (Ok(a), _) if a > 12 => return DateOrder::Dmy,
+                (_, Ok(b)) if b > 12 => order = DateOrder::Mdy,
+                _ => {}
+            }
+        }
+        order
+    }
+}
+
+fn split_date(date: &str) -> Vec<&str> {
+    date.split(['/', '.', '-']).collect()
+}
+
+fn parse_date(date: &str, order: DateOrder) -> Option<NaiveDate> {
+    let parts: Vec<u32> = split_date(date)
+        .iter()
+        .map(|p| p.parse().ok())
+        .collect::<Option<_>>()?;
+    let [a, b, c] = parts[..] else { return None };
+    let (year, month, day) = match order {
+        DateOrder::Ymd => (a, b, c),
+        DateOrder::Dmy => (c, b, a),
+        DateOrder::Mdy => (c, a, b),
+    };
+    let year = if year < 100 { year + 2000 } else { year };
+    NaiveDate::from_ymd_opt(year as i32, month, day)
+}
+
+fn parse_time(time: &str) -> Option<NaiveTime> {
+    let lower = time.to_lowercase().replace(['\u{202f}', ' '], "");
+    let (clock, pm) = if let Some(clock) = lower.strip_suffix("pm").or(lower.strip_suffix("p.m.")) {
+        (clock, Some(true))
+    } else if let Some(clock) = lower.strip_suffix("am").or(lower.strip_suffix("a.m.")) {
+        (clock, Some(false))
+    } else {
+        (lower.as_str(), None)
+    };
+    let mut parts = clock.split([':', '.']).map(|p| p.parse::<u32>().ok());
+    let mut hour = parts.next()??;
+    let minute = parts.next()??;
+    let second = parts.next().flatten().unwrap_or(0);
+    match pm {
+        Some(true) if hour < 12 => hour += 12,
+        Some(false) if hour == 12 => hour = 0,
+        _ => {}
+    }
+    NaiveTime::from_hms_opt(hour, minute, second)
+}
+
+/// Send date encoded in WhatsApp media names: `IMG-20230115-WA0003.jpg`
+/// (Android) or `00000012-PHOTO-2023-01-15-12-34-56.jpg` (iOS export)
+pub fn date_from_file_name(name: &str) -> Option<NaiveDate> {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| {
+        Regex::new(
+            r"(?:^(?:IMG|VID|AUD|PTT|DOC|STK)-(\d{8})-WA\d+)|(?:^\d+-(?:PHOTO|VIDEO|AUDIO|GIF|STICKER)-(\d{4}-\d{2}-\d{2})-)",
+        )
+        .expect("valid media name regex")
+    });
+    let caps = re.captures(name)?;
+    if let Some(compact) = caps.get(1) {
+        NaiveDate::parse_from_str(compact.as_str(), "%Y%m%d").ok()
+    } else {
+        NaiveDate::parse_from_str(caps.get(2)?.as_str(), "%Y-%m-%d").ok()
+    }
+}
+
+/// Read media references from a decrypted `msgstore.db`.
+///
+/// Handles the current schema (`message_media` + `chat` + `jid`) and the
+/// legacy single `messages` table.
+#[cfg(feature = "sqlite")]
+pub fn read_msgstore(path: &Path) -> Result<Vec<ChatLog>> {
+    use rusqlite::{Connection, OpenFlags};
+    use std::collections::BTreeMap;
+
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+
+    let has_table = |name: &str| -> bool {
+        conn.query_row(
+            "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+            [name],
+            |_| Ok(()),
+        )
+        .is_ok()
+    };
+
+    let query = if has_table("message_media") {
+        "SELECT mm.file_path, m.timestamp, COALESCE(c.subject, j.raw_string, 'unknown')
+         FROM message_media mm
+         JOIN message m ON m._id = mm.message_row_id
+         JOIN chat c ON c._id = mm.chat_row_id
+         LEFT JOIN jid j ON j._id = c.jid_row_id
+         WHERE mm.file_path IS NOT NULL"
+    } else if has_table("messages") {
+        "SELECT media_name, timestamp, key_remote_jid FROM messages
+         WHERE media_name IS NOT NULL AND media_name != ''"
+    } else {
+        anyhow::bail!("unrecognized msgstore schema");
+    };
+
+    let mut stmt = conn.prepare(query)?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, Option<i64>>(1)?,
+            row.get::<_, String>(2)?,
+        ))
+    })?;
+
+    let mut chats: BTreeMap<String, Vec<MediaRef>> = BTreeMap::new();
+    for row in rows {
+        let (file, timestamp_ms, chat) = row?;
+        let sent = timestamp_ms
+            .and_then(chrono::DateTime::from_timestamp_millis)
+            .map(|utc| {
+                let local = crate::core::format_timestamp(&utc, "%Y-%m-%d %H:%M:%S");
+                chrono::NaiveDateTime::parse_from_str(&local, "%Y-%m-%d %H:%M:%S")
+                    .unwrap_or_else(|_| utc.naive_utc())
+            });
+        chats.entry(chat).or_default().push(MediaRef {
+            file,
+            sender: None,
+            sent,
+        });
+    }
+
+    Ok(chats
+        .into_iter()
+        .map(|(jid, media)| ChatLog {
+            app: ChatApp::WhatsApp,
+            // Strip the server part of the JID (`4915112345678@s.whatsapp.net`)
+            name: jid
+                .split_once('@')
+                .map_or(jid.clone(), |(user, _)| user.to_string()),
+            source: path.to_path_buf(),
+            media,
+        })
+        .collect())
+}
+
+/// Without the `sqlite` feature, message databases are only reported
+#[cfg(not(feature = "sqlite"))]
+pub fn read_msgstore(_path: &Path) -> Result<Vec<ChatLog>> {
+    anyhow::bail!("reading msgstore.db requires building with --features sqlite")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_chat_formats() {
+        let android = "1/15/23, 9:05 PM - Alice: IMG-20230115-WA0003.jpg (file attached)\n\
+                       1/15/23, 9:06 PM - Bob: <Media omitted>\n\
+                       1/16/23, 7:00 AM - Bob: VID-20230116-WA0001.mp4 (file attached)\n\
+                       caption on a continuation line\n";
+        let log = parse_chat_text(android, "Alice".into(), Path::new("chat.txt"));
+        assert_eq!(log.media.len(), 2);
+        assert_eq!(log.media[0].file, "IMG-20230115-WA0003.jpg");
+        assert_eq!(
+            log.media[0].sent,
+            NaiveDate::from_ymd_opt(2023, 1, 15).and_then(|d| d.and_hms_opt(21, 5, 0))
+        );
+
+        let ios = "[15.01.23, 12:34:56] Alice: \u{200e}<attached: 00000012-PHOTO-2023-01-15-12-34-56.jpg>\n";
+        let log = parse_chat_text(ios, "Alice".into(), Path::new("_chat.txt"));
+        assert_eq!(log.media[0].file, "00000012-PHOTO-2023-01-15-12-34-56.jpg");
+        assert_eq!(log.media[0].sender.as_deref(), Some("Alice"));
+        assert_eq!(
+            log.media[0].sent,
+            NaiveDate::from_ymd_opt(2023, 1, 15).and_then(|d| d.and_hms_opt(12, 34, 56))
+        );
+
+        assert_eq!(
+            date_from_file_name("PTT-20240229-WA0007.opus"),
+            NaiveDate::from_ymd_opt(2024, 2, 29)
+        );
+        assert!(is_encrypted_backup("msgstore-2024-01-01.1.db.crypt14"));
+        assert!(!is_encrypted_backup("notes.crypt"));
+        assert_eq!(
+            conversation_name(Path::new("x/WhatsApp Chat - Family/_chat.txt")),
+            "Family"
+        );
+    }
+}
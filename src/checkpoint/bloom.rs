@@ -0,0 +1,128 @@
+//! Bloom filter used to summarise the processed-path set of compacted
+//! checkpoints
+//!
+//! A compacted checkpoint answers "was this path processed?" with a small
+//! false-positive rate instead of storing every path. Bits are stored as a
+//! hex string so the checkpoint stays plain JSON.
+
+use serde::{Deserialize, Serialize};
+
+/// Target false-positive rate for summaries built by [`BloomFilter::from_items`]
+pub const DEFAULT_FALSE_POSITIVE_RATE: f64 = 0.0001;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BloomFilter {
+    /// Number of bits in the filter
+    pub bit_count: u64,
+    /// Number of hash functions
+    pub hashes: u32,
+    /// Number of items inserted
+    pub items: usize,
+    #[serde(with = "hex_words")]
+    words: Vec<u64>,
+}
+
+impl BloomFilter {
+    /// Size a filter for `expected` items at `false_positive_rate`
+    pub fn with_capacity(expected: usize, false_positive_rate: f64) -> Self {
+        let n = expected.max(1) as f64;
+        let ln2 = std::f64::consts::LN_2;
+        let bits = (-(n * false_positive_rate.ln()) / (ln2 * ln2))
+            .ceil()
+            .max(64.0) as u64;
+        let hashes = ((bits as f64 / n) * ln2).round().clamp(1.0, 32.0) as u32;
+        Self {
+            bit_count: bits,
+            hashes,
+            items: 0,
+            words: vec![0; bits.div_ceil(64) as usize],
+        }
+    }
+
+    /// Build a filter containing every item
+    pub fn from_items<'a>(items: impl ExactSizeIterator<Item = &'a str>) -> Self {
+        let mut filter = Self::with_capacity(items.len(), DEFAULT_FALSE_POSITIVE_RATE);
+        for item in items {
+            filter.insert(item);
+        }
+        filter
+    }
+
+    /// Bit positions for an item (Kirsch–Mitzenmacher double hashing over
+    /// one BLAKE3 digest)
+    fn positions(&self, item: &str) -> impl Iterator<Item = u64> {
+        let digest = blake3::hash(item.as_bytes());
+        let bytes = digest.as_bytes();
+        let h1 = u64::from_le_bytes(bytes[..8].try_into().expect("8 bytes"));
+        let h2 = u64::from_le_bytes(bytes[8..16].try_into().expect("8 bytes")) | 1;
+        let bit_count = self.bit_count;
+        (0..self.hashes as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % bit_count)
+    }
+
+    pub fn insert(&mut self, item: &str) {
+        let positions: Vec<u64> = self.positions(item).collect();
+        for bit in positions {
+            self.words[(bit / 64) as usize] |= 1 << (bit % 64);
+        }
+        self.items += 1;
+    }
+
+    /// Whether `item` may have been inserted (never false for inserted items)
+    pub fn contains(&self, item: &str) -> bool {
+        self.positions(item)
+            .all(|bit| self.words[(bit / 64) as usize] & (1 << (bit % 64)) != 0)
+    }
+
+    /// Size of the bit array in bytes
+    pub fn byte_size(&self) -> usize {
+        self.words.len() * 8
+    }
+}
+
+mod hex_words {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(words: &[u64], serializer: S) -> Result<S::Ok, S::Error> {
+        let bytes: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+        serializer.serialize_str(&hex::encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u64>, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        let bytes = hex::decode(text).map_err(serde::de::Error::custom)?;
+        if !bytes.len().is_multiple_of(8) {
+            return Err(serde::de::Error::custom(
+                "bloom filter length is not a multiple of 8",
+            ));
+        }
+        Ok(bytes
+            .chunks_exact(8)
+            .map(|c| u64::from_le_bytes(c.try_into().expect("8 bytes")))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bloom_membership_and_round_trip() {
+        let paths: Vec<String> = (0..5000)
+            .map(|i| format!("/evidence/dir{}/file{}.jpg", i % 50, i))
+            .collect();
+        let filter = BloomFilter::from_items(paths.iter().map(String::as_str));
+        assert!(paths.iter().all(|p| filter.contains(p)));
+
+        let false_positives = (0..20_000)
+            .filter(|i| filter.contains(&format!("/other/{}.png", i)))
+            .count();
+        assert!(false_positives < 20, "{} false positives", false_positives);
+
+        let json = serde_json::to_string(&filter).unwrap();
+        let back: BloomFilter = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, filter);
+        // Far smaller than the paths themselves
+        assert!(filter.byte_size() * 10 < paths.iter().map(String::len).sum::<usize>());
+    }
+}
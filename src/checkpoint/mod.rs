@@ -4,6 +4,8 @@
 //! can be resumed after interruption. Uses serde_json (NOT bincode) for
 //! human-debuggable checkpoint files.
 
+pub mod bloom;
+
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 
@@ -11,7 +13,9 @@ use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-use crate::core::BadSector;
+use crate::core::{BadSector, SourceFingerprint};
+
+pub use bloom::BloomFilter;
 
 /// Which operation phase this checkpoint covers
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -45,8 +49,18 @@ pub struct Checkpoint {
     pub phase: CheckpointPhase,
     /// Paths already processed (skip on resume)
     pub processed_paths: HashSet<String>,
+    /// Bloom-filter summary of paths moved out of `processed_paths` by
+    /// [`Checkpoint::compact`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub processed_summary: Option<BloomFilter>,
+    /// Fingerprint of the source when the checkpoint was started
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_fingerprint: Option<SourceFingerprint>,
     /// Hashes already computed (reuse on resume)
     pub hashes_computed: HashMap<String, String>,
+    /// Where each processed item was written (export destinations)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub outputs: HashMap<String, String>,
     /// Bad sectors discovered so far
     pub bad_sectors_found: Vec<BadSector>,
     /// How often to auto-save (every N items)
@@ -75,7 +89,10 @@ impl Checkpoint {
             source_path: source.to_string_lossy().to_string(),
             phase,
             processed_paths: HashSet::new(),
+            processed_summary: None,
+            source_fingerprint: None,
             hashes_computed: HashMap::new(),
+            outputs: HashMap::new(),
             bad_sectors_found: Vec::new(),
             auto_save_interval,
             items_since_save: 0,
@@ -88,6 +105,10 @@ impl Checkpoint {
     /// Check if a path has already been processed
     pub fn is_already_processed(&self, path: &str) -> bool {
         self.processed_paths.contains(path)
+            || self
+                .processed_summary
+                .as_ref()
+                .is_some_and(|summary| summary.contains(path))
     }
 
     /// Mark a path as processed, optionally storing its hash
@@ -117,11 +138,45 @@ impl Checkpoint {
 
     /// Number of items processed so far
     pub fn processed_count(&self) -> usize {
-        self.processed_paths.len()
+        self.processed_paths.len() + self.processed_summary.as_ref().map_or(0, |s| s.items)
+    }
+
+    /// Whether `processed_paths` has been folded into a bloom-filter summary
+    pub fn is_compacted(&self) -> bool {
+        self.processed_summary.is_some()
+    }
+
+    /// Replace the processed-path set with a bloom-filter summary.
+    ///
+    /// Resuming from a compacted checkpoint may skip a small fraction of
+    /// unprocessed paths (false positives), so this is meant for old
+    /// checkpoints kept for reference rather than ones about to be resumed.
+    /// Returns false if there was nothing to compact.
+    pub fn compact(&mut self) -> bool {
+        if self.processed_paths.is_empty() {
+            return false;
+        }
+        match self.processed_summary {
+            // Paths added after an earlier compaction go into the existing
+            // summary; its false-positive rate creeps up slightly
+            Some(ref mut summary) => {
+                for path in &self.processed_paths {
+                    summary.insert(path);
+                }
+            }
+            None => {
+                self.processed_summary = Some(BloomFilter::from_items(
+                    self.processed_paths.iter().map(String::as_str),
+                ));
+            }
+        }
+        self.processed_paths.clear();
+        true
     }
 }
 
 /// Manages checkpoint persistence (load/save/clear)
+#[derive(Debug, Clone)]
 pub struct CheckpointManager {
     /// Directory where checkpoints are stored
     checkpoint_dir: PathBuf,
@@ -16,7 +16,7 @@ use dialoguer::{theme::ColorfulTheme, Confirm, FuzzySelect, Input, MultiSelect};
 use indicatif::{ProgressBar, ProgressStyle};
 
 use crate::core::DrillEngine;
-use crate::export::ExportOptions;
+use crate::export::{ExportOptions, DEFAULT_HASH_RETRIES};
 
 // ============================================================================
 // Detected Source Types
@@ -559,6 +559,18 @@ async fn step_index_source(
         checkpoint_interval: 1000,
         bad_sector_report: None,
         block_size: 4096,
+        sqlite: None,
+        incremental: false,
+        verify_hash: false,
+        metadata: false,
+        report_format: None,
+        report_file: None,
+        hashset: Vec::new(),
+        repair_names: None,
+        links: Default::default(),
+        all_hardlinks: false,
+        archives: false,
+        snapshots: false,
     };
 
     // Live progress counters
@@ -851,6 +863,8 @@ async fn step_export_files(
         verify_hash: true,
         continue_on_error: true,
         create_manifest: true,
+        hash_retries: DEFAULT_HASH_RETRIES,
+        retry_with_sector_reader: true,
         ..Default::default()
     };
 
@@ -905,7 +919,7 @@ async fn step_export_files(
         }
         Err(e) => {
             println!("\n{} Some errors occurred: {}", "⚠".yellow().bold(), e);
-            Err(e)
+            Err(e.into())
         }
     }
 }
@@ -12,8 +12,8 @@ use indicatif::{ProgressBar, ProgressStyle};
 
 use crate::carve::{CarveOptions, Carver};
 use crate::cli::InteractiveArgs;
-use crate::core::DrillEngine;
-use crate::export::ExportOptions;
+use crate::core::{DrillEngine, Selection, DEFAULT_SELECTION_FILE};
+use crate::export::{ExportOptions, DEFAULT_HASH_RETRIES};
 
 /// Run interactive session
 pub async fn run_interactive_session(args: &InteractiveArgs) -> Result<()> {
@@ -91,6 +91,7 @@ struct InteractiveSession {
     filter_pattern: String,
     current_directory: PathBuf,
     use_simple_theme: bool,
+    selection_path: PathBuf,
 }
 
 impl InteractiveSession {
@@ -103,13 +104,31 @@ impl InteractiveSession {
 
         let use_simple_theme = args.theme.to_lowercase() == "light";
 
+        let selection_path = args
+            .selection
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_SELECTION_FILE));
+        let selected_files = if selection_path.exists() {
+            let selection = Selection::load(&selection_path)?;
+            println!(
+                "{} Loaded {} selected files from {}",
+                "📋".bright_cyan(),
+                selection.len(),
+                selection_path.display()
+            );
+            selection.files.into_iter().collect()
+        } else {
+            Vec::new()
+        };
+
         Ok(Self {
             state: initial_state,
             engine: None,
-            selected_files: Vec::new(),
+            selected_files,
             filter_pattern: String::new(),
             current_directory: args.source.clone().unwrap_or_else(|| PathBuf::from(".")),
             use_simple_theme,
+            selection_path,
         })
     }
 
@@ -173,6 +192,18 @@ impl InteractiveSession {
             checkpoint_interval: 1000,
             bad_sector_report: None,
             block_size: 4096,
+            sqlite: None,
+            incremental: false,
+            verify_hash: false,
+            metadata: false,
+            report_format: None,
+            report_file: None,
+            hashset: Vec::new(),
+            repair_names: None,
+            links: Default::default(),
+            all_hardlinks: false,
+            archives: false,
+            snapshots: false,
         };
 
         engine.index_with_progress(&args).await?;
@@ -202,7 +233,11 @@ impl InteractiveSession {
         let files = if self.filter_pattern.is_empty() {
             engine.get_all_files().await?
         } else {
-            engine.search_fuzzy(&self.filter_pattern).await?
+            // A leading '~' turns on cross-script matching for this query
+            match self.filter_pattern.strip_prefix('~') {
+                Some(pattern) => engine.search_fuzzy_with(pattern, true).await?,
+                None => engine.search_fuzzy(&self.filter_pattern).await?,
+            }
         };
 
         if files.is_empty() {
@@ -247,6 +282,7 @@ impl InteractiveSession {
             "🔍 Search / Filter".to_string(),
             "📋 Select All".to_string(),
             "📋 Select None".to_string(),
+            format!("💾 Save Selection ({})", self.selection_path.display()),
             "📤 Export Selected".to_string(),
             "👁  Preview Selected".to_string(),
             "💎 Carve Raw Image".to_string(),
@@ -291,16 +327,17 @@ impl InteractiveSession {
             Some(1) => self.state = SessionState::Search,
             Some(2) => self.selected_files = files,
             Some(3) => self.selected_files.clear(),
-            Some(4) => {
+            Some(4) => self.save_selection()?,
+            Some(5) => {
                 if self.selected_files.is_empty() {
                     println!("{}", "No files selected!".yellow());
                 } else {
                     self.state = SessionState::Export;
                 }
             }
-            Some(5) => self.state = SessionState::Preview,
-            Some(6) => self.state = SessionState::Carve,
-            Some(7) => {
+            Some(6) => self.state = SessionState::Preview,
+            Some(7) => self.state = SessionState::Carve,
+            Some(8) => {
                 self.state = SessionState::Exit;
                 return Ok(false);
             }
@@ -321,9 +358,26 @@ impl InteractiveSession {
         Ok(true)
     }
 
+    /// Write the selected files to the selection file for a later session
+    /// or `export --selection`
+    fn save_selection(&self) -> Result<()> {
+        let mut selection =
+            Selection::load_or_new(&self.selection_path, Some(&self.current_directory))?;
+        selection.set_files(self.selected_files.iter().cloned());
+        selection.save(&self.selection_path)?;
+        println!(
+            "{} Saved {} selected files to {}",
+            "💾".bright_cyan(),
+            selection.len(),
+            self.selection_path.display()
+        );
+        Ok(())
+    }
+
     async fn search_files(&mut self) -> Result<()> {
         println!("\n{}", "Search/Filter Files".bright_yellow().bold());
-        println!("  Supports: glob (*.jpg), fuzzy (photo), extensions (.rs)\n");
+        println!("  Supports: glob (*.jpg), fuzzy (photo), extensions (.rs)");
+        println!("  Prefix with ~ to match other scripts (~dokument finds документ)\n");
 
         let pattern: String = Input::with_theme(&*self.theme())
             .with_prompt("Pattern")
@@ -365,7 +419,7 @@ impl InteractiveSession {
                 file.bright_white(),
                 humansize::format_size(info.size, humansize::BINARY),
                 info.modified
-                    .map(|d| d.format("%Y-%m-%d %H:%M").to_string())
+                    .map(|d| crate::core::format_timestamp(&d, "%Y-%m-%d %H:%M"))
                     .unwrap_or_else(|| "Unknown".to_string())
             );
         }
@@ -418,6 +472,8 @@ impl InteractiveSession {
             verify_hash: verify,
             continue_on_error: true,
             create_manifest: true,
+            hash_retries: DEFAULT_HASH_RETRIES,
+            retry_with_sector_reader: true,
             ..Default::default()
         };
 
@@ -521,6 +577,14 @@ impl InteractiveSession {
             workers: num_cpus::get(),
             dry_run,
             verify: !dry_run,
+            mapfile: None,
+            max_bytes_per_sec: None,
+            unlock: None,
+            extra_signatures: Vec::new(),
+            faststart: false,
+            text_min_len: None,
+            min_confidence: 0,
+            io: None,
         };
 
         let carver = Carver::new(opts);
@@ -602,6 +666,14 @@ impl InteractiveSession {
                     workers: num_cpus::get(),
                     dry_run: false,
                     verify: true,
+                    mapfile: None,
+                    max_bytes_per_sec: None,
+                    unlock: None,
+                    extra_signatures: Vec::new(),
+                    faststart: false,
+                    text_min_len: None,
+                    min_confidence: 0,
+                    io: None,
                 };
                 let extract_carver = Carver::new(extract_opts);
                 let (_, extract_result) = extract_carver.carve().await?;
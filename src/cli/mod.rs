@@ -2,11 +2,15 @@
 
 pub mod easy_mode;
 pub mod interactive;
+pub mod output;
+pub mod profile;
 
 use std::path::PathBuf;
 
 use clap::{Parser, Subcommand, ValueEnum};
 
+use crate::core::DisplayTz;
+
 /// Diamond Drill - Ultra-fast offline disk image recovery tool
 ///
 /// Indexes, previews, searches, selects and exports files from disk images
@@ -26,10 +30,39 @@ pub struct Cli {
     #[arg(long, short, global = true)]
     pub verbose: bool,
 
-    /// Output format for machine parsing
+    /// Output format for machine parsing (`search` prints JSON lines or CSV records)
     #[arg(long, value_enum, global = true)]
     pub output: Option<OutputFormat>,
 
+    /// Serve JSON-RPC 2.0 requests on stdin/stdout (one per line) for GUI
+    /// frontends; logs go to stderr
+    #[arg(long, global = true)]
+    pub rpc: bool,
+
+    /// Timezone for displayed timestamps and --after/--before dates
+    /// (local, UTC, +02:00, or an IANA name like Europe/Berlin)
+    #[arg(long, global = true, default_value = "local")]
+    pub timezone: DisplayTz,
+
+    /// Preset options from the config file (or built in: forensic,
+    /// photo-rescue); options given on the command line win
+    #[arg(long, global = true, value_name = "NAME")]
+    pub profile: Option<String>,
+
+    /// Don't write this run's operation log (see `logs`)
+    #[arg(long, global = true)]
+    pub no_log: bool,
+
+    /// Index, carve or export from a source drive even when SMART says it
+    /// is failing
+    #[arg(long, global = true)]
+    pub ignore_health: bool,
+
+    /// Serve Prometheus metrics on ADDR (`host:port`, or a port on
+    /// localhost) while the command runs; needs `--features metrics`
+    #[arg(long, global = true, value_name = "ADDR")]
+    pub metrics_addr: Option<String>,
+
     #[command(subcommand)]
     pub command: Option<Commands>,
 }
@@ -60,18 +93,106 @@ pub enum Commands {
     /// Verify a proof manifest against exported files
     Verify(VerifyArgs),
 
+    /// Sign proof manifests and manage signing keys
+    Proof(ProofArgs),
+
     /// Launch TUI mode (terminal UI with vim keybindings)
     Tui(TuiArgs),
 
     /// Run the 5-agent swarm pipeline for parallel document processing
     Swarm(SwarmArgs),
 
+    /// Find the passages of recovered documents that answer a question, and
+    /// with --answer have a local LLM answer it citing the files
+    Ask(AskArgs),
+
+    /// Triage a swarm heal log: failures by error class and by file
+    HealReport(HealReportArgs),
+
     /// Generate HTML/PDF recovery report from a manifest or export
     Report(ReportArgs),
 
+    /// Group WhatsApp/Telegram media into conversations and export them
+    Chats(ChatsArgs),
+
+    /// Convert between raw, split raw and E01 images, optionally trimming to a partition
+    Convert(ConvertArgs),
+
+    /// Entropy analysis: find encrypted and compressed regions in an image
+    Analyze(AnalyzeArgs),
+
+    /// Clone a failing device into an image, skipping and mapping bad regions
+    Image(ImageArgs),
+
+    /// Check the SMART health of the drive holding a source
+    Health(HealthArgs),
+
+    /// Measure source read and destination write throughput, and tune later
+    /// commands on them to it
+    Bench(BenchArgs),
+
+    /// Extract browser history, downloads and cookies into a timeline
+    /// (reading databases requires --features sqlite)
+    Artifacts(ArtifactsArgs),
+
+    /// Break an index down by type, extension, size and age
+    Stats(StatsArgs),
+
+    /// Index files as they land in a staging directory, with thumbnails and
+    /// duplicate checks, while an imaging tool is still writing to it
+    Watch(WatchArgs),
+
+    /// Housekeeping for the data directory (checkpoints, indexes, sessions)
+    Maintenance(MaintenanceArgs),
+
+    /// List past runs from their operation logs, or show one in full
+    Logs(LogsArgs),
+
     /// Launch GUI mode (requires --features gui)
     #[cfg(feature = "gui")]
     Gui(GuiArgs),
+
+    /// Serve a REST/WebSocket API and web UI for driving this machine from
+    /// a browser (requires --features serve)
+    #[cfg(feature = "serve")]
+    Serve(ServeArgs),
+}
+
+impl Commands {
+    /// Subcommand name as typed on the command line
+    pub fn name(&self) -> &'static str {
+        match self {
+            Commands::Index(_) => "index",
+            Commands::Search(_) => "search",
+            Commands::Preview(_) => "preview",
+            Commands::Export(_) => "export",
+            Commands::Interactive(_) => "interactive",
+            Commands::Carve(_) => "carve",
+            Commands::Dedup(_) => "dedup",
+            Commands::Verify(_) => "verify",
+            Commands::Proof(_) => "proof",
+            Commands::Tui(_) => "tui",
+            Commands::Swarm(_) => "swarm",
+            Commands::Ask(_) => "ask",
+            Commands::HealReport(_) => "heal-report",
+            Commands::Report(_) => "report",
+            Commands::Chats(_) => "chats",
+            Commands::Convert(_) => "convert",
+            Commands::Analyze(_) => "analyze",
+            Commands::Image(_) => "image",
+            Commands::Health(_) => "health",
+            Commands::Bench(_) => "bench",
+            Commands::Artifacts(_) => "artifacts",
+            Commands::Stats(_) => "stats",
+            Commands::Watch(_) => "watch",
+            Commands::Maintenance(_) => "maintenance",
+            Commands::Logs(_) => "logs",
+            #[cfg(feature = "gui")]
+            Commands::Gui(_) => "gui",
+            #[cfg(feature = "serve")]
+            Commands::Serve(_) => "serve",
+        }
+    }
 }
 
 #[derive(Debug, Clone, Parser)]
@@ -104,7 +225,8 @@ pub struct IndexArgs {
     #[arg(long, short)]
     pub thumbnails: bool,
 
-    /// Number of parallel workers (default: CPU count)
+    /// Number of parallel workers (default: tuned to the source's storage,
+    /// see `bench`, else CPU count)
     #[arg(long, short)]
     pub workers: Option<usize>,
 
@@ -119,6 +241,86 @@ pub struct IndexArgs {
     /// Block size for bad sector detection in bytes (default: 4096)
     #[arg(long, default_value = "4096")]
     pub block_size: usize,
+
+    /// Write the index to a SQLite database as files are scanned, instead of
+    /// keeping it in memory (requires the `sqlite` feature)
+    #[arg(long, conflicts_with_all = ["incremental", "bad_sector_report", "report_format"])]
+    pub sqlite: Option<PathBuf>,
+
+    /// Only rescan files that were added or changed (size/mtime) since the last index
+    #[arg(long)]
+    pub incremental: bool,
+
+    /// With --incremental, also re-hash unchanged files that have a stored hash
+    #[arg(long, requires = "incremental")]
+    pub verify_hash: bool,
+
+    /// Extract EXIF, ID3 and PDF metadata into the index (for `search --meta`)
+    #[arg(long)]
+    pub metadata: bool,
+
+    /// Also write the file list for other forensics tools
+    #[arg(long, value_enum)]
+    pub report_format: Option<ForensicReportFormat>,
+
+    /// Report path (default: diamond-drill-index.<dfxml|body> in the current directory)
+    #[arg(long, requires = "report_format")]
+    pub report_file: Option<PathBuf>,
+
+    /// Known-file hash set (NSRL RDS or md5sum/sha256sum/b3sum list);
+    /// prefix with bad: to flag matches instead of hiding them (repeatable)
+    #[arg(long, value_name = "[good:|bad:]FILE")]
+    pub hashset: Vec<String>,
+
+    /// Repair mis-encoded (mojibake) file names, trying these codepages
+    /// in order (e.g. windows-1251,shift_jis; default: common DOS and
+    /// Windows codepages)
+    #[arg(long, value_name = "CODEPAGES", num_args = 0..=1, require_equals = true, value_delimiter = ',')]
+    pub repair_names: Option<Vec<String>>,
+
+    /// What to do with symbolic links and junctions
+    #[arg(long, value_enum, default_value = "skip")]
+    pub links: LinkArg,
+
+    /// Index every name of a hard-linked file (default: the first one, with
+    /// the others in its metadata)
+    #[arg(long)]
+    pub all_hardlinks: bool,
+
+    /// Also index the files inside ZIP, tar and tar.gz archives (and 7z
+    /// with the `sevenz` feature), so search finds them and export can
+    /// extract them
+    #[arg(long)]
+    pub archives: bool,
+
+    /// Also index the files in the Volume Shadow Copies of an NTFS source
+    /// (an image, a device, or on Linux a mounted volume) under
+    /// `<source>/@vss/<n>/`, so earlier versions can be found and exported
+    #[arg(long)]
+    pub snapshots: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum LinkArg {
+    /// Leave links out of the index
+    #[default]
+    Skip,
+    /// Index what links point to, unless they loop or lead out of the source
+    Follow,
+    /// Index the links themselves with their targets
+    Record,
+}
+
+impl LinkArg {
+    /// Scanner policy for this choice
+    pub fn policy(self) -> crate::core::LinkPolicy {
+        use crate::core::LinkPolicy;
+        match self {
+            Self::Skip => LinkPolicy::Skip,
+            Self::Follow => LinkPolicy::Follow,
+            Self::Record => LinkPolicy::Record,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Parser)]
@@ -147,17 +349,40 @@ pub struct SearchArgs {
     #[arg(long)]
     pub max_size: Option<String>,
 
-    /// Modified after date (YYYY-MM-DD)
+    /// Modified after date (YYYY-MM-DD, in the --timezone zone)
     #[arg(long)]
     pub after: Option<String>,
 
-    /// Modified before date (YYYY-MM-DD)
+    /// Modified before date (YYYY-MM-DD, in the --timezone zone)
     #[arg(long)]
     pub before: Option<String>,
 
     /// Maximum results
     #[arg(long, short, default_value = "100")]
     pub limit: usize,
+
+    /// Match across scripts (e.g. "dokument" finds "документ"); fuzzy search only
+    #[arg(long)]
+    pub translit: bool,
+
+    /// Search inside text, PDF and DOCX files instead of file names
+    /// (prints file, byte offset and a snippet for each match)
+    #[arg(long)]
+    pub content: bool,
+
+    /// Only files whose metadata value contains VALUE, e.g. camera_model=iphone
+    /// (repeatable; needs an index built with --metadata)
+    #[arg(long = "meta", value_name = "KEY=VALUE", value_parser = crate::core::parse_meta_filter)]
+    pub meta: Vec<(String, String)>,
+
+    /// Add the matching files to a .ddsel selection file (created if missing)
+    #[arg(long, value_name = "FILE")]
+    pub select: Option<PathBuf>,
+
+    /// Known-file hash set (NSRL RDS or md5sum/sha256sum/b3sum list);
+    /// prefix with bad: to flag matches instead of hiding them (repeatable)
+    #[arg(long, value_name = "[good:|bad:]FILE")]
+    pub hashset: Vec<String>,
 }
 
 #[derive(Debug, Clone, Parser)]
@@ -176,6 +401,11 @@ pub struct PreviewArgs {
     /// Output directory for thumbnails
     #[arg(long, short)]
     pub output: Option<PathBuf>,
+
+    /// Show the size and first lines of .gz, .bz2, .xz and .zst files
+    /// decompressed
+    #[arg(long)]
+    pub decompress: bool,
 }
 
 #[derive(Debug, Clone, Parser, Default)]
@@ -186,6 +416,11 @@ pub struct InteractiveArgs {
     /// Color theme (dark, light, auto)
     #[arg(long, default_value = "auto")]
     pub theme: String,
+
+    /// Selection file to load at start and save to from the menu
+    /// (default: selection.ddsel)
+    #[arg(long, value_name = "FILE")]
+    pub selection: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone, Parser)]
@@ -194,7 +429,9 @@ pub struct ExportArgs {
     #[arg(required = true)]
     pub source: PathBuf,
 
-    /// Destination directory
+    /// Destination directory, or sftp://user@host/path or
+    /// smb://[domain;]user@host/share/path to upload to a server or NAS
+    /// (password in DIAMOND_DRILL_REMOTE_PASSWORD)
     #[arg(required = true)]
     pub dest: PathBuf,
 
@@ -217,9 +454,185 @@ pub struct ExportArgs {
     #[arg(long, short = 'n')]
     pub dry_run: bool,
 
+    /// Print the space the export needs on each destination (cluster
+    /// slack, file records and manifests included) against its free space,
+    /// without copying
+    #[arg(long)]
+    pub plan: bool,
+
+    /// Start even when the destination looks too small for the selection
+    #[arg(long)]
+    pub no_space_check: bool,
+
     /// Create manifest file with hashes
     #[arg(long, short)]
     pub manifest: bool,
+
+    /// Re-copy attempts after a hash mismatch before giving up
+    #[arg(long, default_value = "2")]
+    pub hash_retries: u32,
+
+    /// Re-read the source in 512-byte blocks when retrying a mismatch
+    #[arg(long)]
+    pub sector_retry: bool,
+
+    /// Sort exported files into folders (overrides --preserve-structure)
+    #[arg(long, value_enum)]
+    pub organize_by: Option<ExportOrganizeBy>,
+
+    /// What to do when a destination file already exists
+    #[arg(long, value_enum, default_value = "rename")]
+    pub on_conflict: ExportOnConflict,
+
+    /// Write DEST as a single .zip or .tar.gz archive (manifest included)
+    /// instead of a folder of loose files
+    #[arg(long)]
+    pub archive: bool,
+
+    /// Limit source reads to this rate per second (e.g., 20MB) to spare a
+    /// failing drive
+    #[arg(long)]
+    pub max_rate: Option<String>,
+
+    /// How to read sources: auto uses io_uring (Linux) or overlapped I/O
+    /// (Windows) when built with --features async-io
+    #[arg(long, value_enum, default_value = "auto")]
+    pub io_backend: IoBackendArg,
+
+    /// Source reads kept in flight
+    #[arg(long, default_value = "32", value_parser = clap::value_parser!(u16).range(1..=4096))]
+    pub queue_depth: u16,
+
+    /// Source read size, e.g. 1MB (default: tuned to the source's storage,
+    /// see `bench`, else 256KB)
+    #[arg(long)]
+    pub block_size: Option<String>,
+
+    /// Files copied at once (default: tuned to the source and destination
+    /// storage, see `bench`, else 8)
+    #[arg(long)]
+    pub workers: Option<usize>,
+
+    /// Export the files listed in a .ddsel selection file (plus any FILES)
+    #[arg(long, value_name = "FILE")]
+    pub selection: Option<PathBuf>,
+
+    /// Resume an interrupted export to DEST, skipping files it already copied
+    #[arg(long)]
+    pub resume: bool,
+
+    /// Also copy every file to this second destination, verified
+    /// independently, with a proof manifest covering both copies
+    #[arg(long, value_name = "DIR")]
+    pub mirror: Option<PathBuf>,
+
+    /// Also record these digests next to blake3 in the manifests
+    /// (e.g. --hash md5,sha256)
+    #[arg(long = "hash", value_enum, value_delimiter = ',')]
+    pub hash: Vec<ExtraHashAlgorithm>,
+
+    /// Known-file hash set (NSRL RDS or md5sum/sha256sum/b3sum list);
+    /// prefix with bad: to flag matches instead of hiding them (repeatable)
+    #[arg(long, value_name = "[good:|bad:]FILE")]
+    pub hashset: Vec<String>,
+
+    /// Write a report of the export (manifest, proof and bad sectors) into
+    /// the destination when done
+    #[arg(long, value_enum, requires = "manifest", conflicts_with_all = ["archive", "dry_run"])]
+    pub report: Option<ReportFormat>,
+
+    /// Logo (PNG or JPEG) for the top of the PDF report
+    #[arg(long, value_name = "FILE", requires = "report")]
+    pub logo: Option<PathBuf>,
+
+    /// Give mis-encoded (mojibake) file names their repaired names in the
+    /// destination, trying these codepages in order (default: common DOS
+    /// and Windows codepages)
+    #[arg(long, value_name = "CODEPAGES", num_args = 0..=1, require_equals = true, value_delimiter = ',')]
+    pub repair_names: Option<Vec<String>>,
+
+    /// Decompress .gz, .bz2, .xz and .zst files on the way out (bzip2, xz
+    /// and zstd need their command-line tools); the manifest keeps the
+    /// hash of both forms
+    #[arg(long, conflicts_with = "archive")]
+    pub decompress: bool,
+
+    /// Scan every file with ClamAV before it is written, through clamd's
+    /// socket (/run/clamav/clamd.ctl) or host:port (127.0.0.1:3310)
+    #[arg(long, value_name = "ADDR")]
+    pub clamd: Option<String>,
+
+    /// What to do with files the virus scan flags; the manifest records
+    /// the detection either way
+    #[arg(long, value_enum, default_value = "quarantine", requires = "clamd")]
+    pub on_detection: ExportOnDetection,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ExtraHashAlgorithm {
+    /// MD5 (required by many courts and older forensic tools)
+    Md5,
+    /// SHA-256
+    Sha256,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum IoBackendArg {
+    /// io_uring or overlapped I/O when compiled in, else sync
+    Auto,
+    /// Blocking reads with adaptive read-ahead
+    Sync,
+    /// io_uring (Linux, --features async-io)
+    Uring,
+    /// Overlapped I/O (Windows, --features async-io)
+    Overlapped,
+}
+
+impl IoBackendArg {
+    /// I/O options for this backend at `queue_depth`
+    pub fn options(self, queue_depth: u16) -> crate::iobackend::IoOptions {
+        use crate::iobackend::IoBackend;
+        crate::iobackend::IoOptions {
+            backend: match self {
+                Self::Auto => IoBackend::Auto,
+                Self::Sync => IoBackend::Sync,
+                Self::Uring => IoBackend::Uring,
+                Self::Overlapped => IoBackend::Overlapped,
+            },
+            queue_depth: queue_depth as usize,
+            block_size: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ExportOrganizeBy {
+    /// YYYY/MM folders from the EXIF capture date (falls back to mtime)
+    DateTaken,
+    /// YYYY/MM folders from the modification time
+    DateModified,
+    /// One folder per file type (image/, video/, ...)
+    FileType,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ExportOnConflict {
+    /// Add a numeric suffix (IMG_0001_1.jpg)
+    Rename,
+    /// Keep the existing file and skip this one
+    Skip,
+    /// Replace the existing file
+    Overwrite,
+    /// Report the file as failed
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ExportOnDetection {
+    /// Export it into the quarantine folder of the destination
+    Quarantine,
+    /// Export it as usual
+    Flag,
 }
 
 #[derive(Debug, Clone, Parser)]
@@ -244,7 +657,13 @@ pub struct CarveArgs {
     #[arg(long, short, value_enum, value_delimiter = ',')]
     pub file_type: Option<Vec<FileTypeFilter>>,
 
-    /// Number of parallel workers (default: CPU count)
+    /// TOML file of extra signatures (header, footer, max size, extension,
+    /// optional WASM size parser), merged with the built-in ones
+    #[arg(long, value_name = "FILE")]
+    pub signatures: Option<PathBuf>,
+
+    /// Number of parallel workers (default: tuned to the source's storage,
+    /// see `bench`, else CPU count)
     #[arg(long, short)]
     pub workers: Option<usize>,
 
@@ -256,9 +675,73 @@ pub struct CarveArgs {
     #[arg(long)]
     pub no_verify: bool,
 
+    /// Move the moov atom of carved MP4/MOV files in front of the media
+    /// (faststart), so they play without being read to the end
+    #[arg(long)]
+    pub faststart: bool,
+
+    /// Also carve plain text and logs: runs of printable text in the
+    /// space no carved file covers, scored for how much they read like text
+    #[arg(long)]
+    pub text: bool,
+
+    /// Shortest text run to carve, in bytes
+    #[arg(long, default_value = "256", requires = "text")]
+    pub text_min_len: usize,
+
+    /// Don't extract carves with a confidence score (0-100) below this
+    #[arg(long, default_value = "0", value_parser = clap::value_parser!(u8).range(0..=100))]
+    pub min_confidence: u8,
+
+    /// GNU ddrescue mapfile for the image: carving skips its unreadable
+    /// regions and flags carved files that overlap them
+    #[arg(long)]
+    pub mapfile: Option<PathBuf>,
+
+    /// Limit image reads to this rate per second (e.g., 20MB) to spare a
+    /// failing drive
+    #[arg(long)]
+    pub max_rate: Option<String>,
+
+    /// Read the image ahead of the scan through this backend (auto: io_uring
+    /// or overlapped I/O when built with --features async-io)
+    #[arg(long, value_enum)]
+    pub io_backend: Option<IoBackendArg>,
+
+    /// Image reads kept in flight by --io-backend
+    #[arg(long, default_value = "32", requires = "io_backend", value_parser = clap::value_parser!(u16).range(1..=4096))]
+    pub queue_depth: u16,
+
+    /// Unlock the BitLocker/LUKS volume in the image and carve its
+    /// plaintext; prompts for the passphrase or recovery password (leave
+    /// empty for a BitLocker volume with suspended protection)
+    #[arg(long, conflicts_with = "mapfile")]
+    pub unlock: bool,
+
+    /// Read the unlock passphrase or recovery password from the first line
+    /// of this file instead of prompting
+    #[arg(long, conflicts_with = "mapfile")]
+    pub unlock_key_file: Option<PathBuf>,
+
     /// Output format (human, json)
     #[arg(long, value_enum)]
     pub output_format: Option<OutputFormat>,
+
+    /// Also write the carved files for other forensics tools
+    #[arg(long, value_enum)]
+    pub report_format: Option<ForensicReportFormat>,
+
+    /// Report path (default: diamond-drill-carve.<dfxml|body> in the output directory)
+    #[arg(long, requires = "report_format")]
+    pub report_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ForensicReportFormat {
+    /// Digital Forensics XML
+    Dfxml,
+    /// Sleuth Kit body file (input for mactime)
+    Bodyfile,
 }
 
 #[cfg(feature = "gui")]
@@ -272,6 +755,22 @@ pub struct GuiArgs {
     pub size: String,
 }
 
+#[derive(Debug, Clone, Parser)]
+pub struct ServeArgs {
+    /// Source path - disk image, mounted volume, or directory
+    #[arg(required = true)]
+    pub source: PathBuf,
+
+    /// Address to listen on (e.g. 0.0.0.0:7878 to reach it from the LAN)
+    #[arg(long, default_value = "127.0.0.1:7878")]
+    pub bind: std::net::SocketAddr,
+
+    /// Access token clients must send; one is generated when listening
+    /// beyond loopback
+    #[arg(long)]
+    pub token: Option<String>,
+}
+
 #[derive(Debug, Clone, Copy, ValueEnum)]
 pub enum OutputFormat {
     /// Human readable (default)
@@ -316,6 +815,7 @@ pub enum FileTypeFilter {
 #[derive(Debug, Clone, Parser)]
 pub struct DedupArgs {
     /// Source path or index file to scan for duplicates
+    /// (add `diff <GROUP>` to compare near-duplicate documents)
     #[arg(required = true)]
     pub source: PathBuf,
 
@@ -331,6 +831,11 @@ pub struct DedupArgs {
     #[arg(long, default_value = "85")]
     pub threshold: u8,
 
+    /// Also group audio and video that sound or look the same across
+    /// formats and bitrates (video needs ffmpeg on the PATH)
+    #[arg(long)]
+    pub media: bool,
+
     /// Minimum file size to consider (bytes)
     #[arg(long, default_value = "1")]
     pub min_size: u64,
@@ -339,9 +844,62 @@ pub struct DedupArgs {
     #[arg(long)]
     pub purge: bool,
 
+    /// Instead of deleting, replace exact duplicates with links to the
+    /// kept file, so every path stays (e.g. in an export tree)
+    #[arg(long, value_enum, conflicts_with = "purge")]
+    pub merge: Option<DedupMergeMode>,
+
+    /// Another source or index file to compare against (repeatable):
+    /// reports which files of SOURCE it already has, by content
+    #[arg(
+        long = "source",
+        value_name = "PATH",
+        conflicts_with_all = ["purge", "merge", "fuzzy", "media"]
+    )]
+    pub sources: Vec<PathBuf>,
+
+    /// Export or proof manifest of files saved earlier, compared like
+    /// --source (repeatable)
+    #[arg(
+        long,
+        value_name = "MANIFEST",
+        conflicts_with_all = ["purge", "merge", "fuzzy", "media"]
+    )]
+    pub baseline: Vec<PathBuf>,
+
+    /// Save the files of SOURCE found in no --source or --baseline to a
+    /// .ddsel selection file for `export --selection`
+    #[arg(long, value_name = "FILE")]
+    pub missing: Option<PathBuf>,
+
     /// Output format for report
     #[arg(long, value_enum, default_value = "human")]
     pub report: DedupReportFormat,
+
+    /// Also report these digests for each exact duplicate group
+    /// (e.g. --hash md5,sha256)
+    #[arg(long = "hash", value_enum, value_delimiter = ',')]
+    pub hash: Vec<ExtraHashAlgorithm>,
+
+    #[command(subcommand)]
+    pub action: Option<DedupAction>,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum DedupAction {
+    /// Show text differences between a group's master and its candidates
+    Diff(DedupDiffArgs),
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct DedupDiffArgs {
+    /// Group number as shown in the dedup report (#1, #2, ...)
+    #[arg(required = true)]
+    pub group: usize,
+
+    /// Unchanged lines of context around each change
+    #[arg(long, short = 'C', default_value = "3")]
+    pub context: usize,
 }
 
 #[derive(Debug, Clone, Copy, ValueEnum)]
@@ -356,6 +914,14 @@ pub enum DedupKeepStrategy {
     Cleanest,
 }
 
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum DedupMergeMode {
+    /// Hardlink to the kept file (same filesystem only)
+    Hardlink,
+    /// Copy-on-write clone (btrfs, XFS, APFS)
+    Reflink,
+}
+
 #[derive(Debug, Clone, Copy, ValueEnum)]
 pub enum DedupReportFormat {
     /// Human-readable table
@@ -373,6 +939,123 @@ pub struct VerifyArgs {
     /// Output format for verification report
     #[arg(long, value_enum, default_value = "human")]
     pub report: VerifyReportFormat,
+
+    /// Write a standalone verifier (b3sum list, shell script, schema) next to the manifest
+    #[arg(long)]
+    pub self_contained: bool,
+
+    /// Require a valid signature by this Ed25519 public key (PEM)
+    #[arg(long, value_name = "FILE")]
+    pub pubkey: Option<PathBuf>,
+
+    /// Check only this exported file, via its Merkle inclusion proof
+    #[arg(long, value_name = "FILE", conflicts_with = "self_contained")]
+    pub single: Option<PathBuf>,
+
+    /// Append the outcome to the manifest's custody log
+    #[arg(long)]
+    pub record: bool,
+
+    /// Where `--report pdf` writes (default: diamond-drill-verify-report.pdf
+    /// next to the manifest)
+    #[arg(long, value_name = "FILE")]
+    pub report_path: Option<PathBuf>,
+
+    /// Logo (PNG or JPEG) for the top of the PDF report
+    #[arg(long, value_name = "FILE")]
+    pub logo: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct ProofArgs {
+    #[command(subcommand)]
+    pub action: ProofAction,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum ProofAction {
+    /// Generate an Ed25519 key pair for signing manifests
+    Keygen(ProofKeygenArgs),
+    /// Sign a proof manifest in place
+    Sign(ProofSignArgs),
+    /// Print the Merkle inclusion proof of one exported file
+    Prove(ProofProveArgs),
+    /// Add an RFC 3161 trusted timestamp of the root hash (before signing)
+    Timestamp(ProofTimestampArgs),
+    /// Show the custody log, or append an event to it
+    Log(ProofLogArgs),
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct ProofKeygenArgs {
+    /// Private key file to create (the public key goes next to it as .pub)
+    #[arg(required = true)]
+    pub key: PathBuf,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct ProofSignArgs {
+    /// Proof manifest to sign
+    #[arg(required = true)]
+    pub manifest: PathBuf,
+
+    /// Ed25519 private key (PKCS#8 PEM, e.g. from `proof keygen` or
+    /// `openssl genpkey -algorithm ed25519`)
+    #[arg(long, required = true)]
+    pub key: PathBuf,
+
+    /// Signer identity recorded in the manifest (default: the manifest's operator)
+    #[arg(long)]
+    pub signer: Option<String>,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct ProofProveArgs {
+    /// Proof manifest the file belongs to
+    #[arg(required = true)]
+    pub manifest: PathBuf,
+
+    /// Exported file (destination path as listed in the manifest)
+    #[arg(required = true)]
+    pub file: PathBuf,
+
+    /// Write the proof here instead of stdout
+    #[arg(long, short)]
+    pub out: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct ProofTimestampArgs {
+    /// Proof manifest to timestamp
+    #[arg(required = true)]
+    pub manifest: PathBuf,
+
+    /// RFC 3161 timestamp authority URL
+    #[arg(long, default_value = crate::proof::DEFAULT_TSA_URL)]
+    pub tsa: String,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct ProofLogArgs {
+    /// Proof manifest whose custody log to show or extend
+    #[arg(required = true)]
+    pub manifest: PathBuf,
+
+    /// Append an event of this kind instead of showing the log
+    #[arg(long, value_enum, requires = "detail")]
+    pub add: Option<CustodyEventArg>,
+
+    /// What happened (e.g. "Handed to Det. Smith, evidence bag 7")
+    #[arg(long)]
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum CustodyEventArg {
+    /// The manifest (and export) changed hands
+    Transferred,
+    /// Free-form note
+    Note,
 }
 
 #[derive(Debug, Clone, Copy, ValueEnum)]
@@ -381,19 +1064,59 @@ pub enum VerifyReportFormat {
     Human,
     /// JSON output
     Json,
+    /// Human-readable report, plus a PDF deliverable with the proof
+    /// manifest, the outcome and a signature block
+    Pdf,
 }
 
 #[derive(Debug, Clone, Parser)]
 pub struct TuiArgs {
     /// Source path to index and browse
     pub source: Option<PathBuf>,
+
+    /// Selection file to load at start and save to with 'w'
+    /// (default: selection.ddsel)
+    #[arg(long, value_name = "FILE")]
+    pub selection: Option<PathBuf>,
+
+    /// Directory that carving ('c' on the Carve tab) and export ('x' on the
+    /// Export tab) write into, under carved/ and export/
+    #[arg(long, value_name = "DIR", default_value = "diamond-drill-out")]
+    pub out_dir: PathBuf,
+
+    /// Open the results of an earlier `carve` (its output directory or
+    /// carve-results.json) on the Carve tab
+    #[arg(long, value_name = "PATH")]
+    pub carve_results: Option<PathBuf>,
+
+    /// How image thumbnails are drawn in the preview pane
+    #[arg(long, value_enum, default_value = "auto")]
+    pub graphics: GraphicsMode,
+}
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum GraphicsMode {
+    /// Detect the terminal's graphics protocol, falling back to blocks
+    #[default]
+    Auto,
+    /// Kitty graphics protocol (kitty, Ghostty)
+    Kitty,
+    /// iTerm2 inline images (iTerm2, WezTerm)
+    Iterm2,
+    /// Sixel (foot, mlterm)
+    Sixel,
+    /// Coloured half-block characters
+    Blocks,
+    /// No thumbnails
+    Off,
 }
 
 #[derive(Debug, Clone, Parser)]
 pub struct SwarmArgs {
-    /// Source path - directory to process with swarm
-    #[arg(required = true)]
-    pub source: PathBuf,
+    /// Source path - directory to process with swarm (default with
+    /// --resume: the session's source)
+    #[arg(required_unless_present = "resume")]
+    pub source: Option<PathBuf>,
 
     /// Output path for export manifest
     #[arg(long, short)]
@@ -411,6 +1134,42 @@ pub struct SwarmArgs {
     #[arg(long, default_value = "128")]
     pub chunk_overlap: usize,
 
+    /// Threads queueing scanned files (default: CPU count)
+    #[arg(long, value_name = "N")]
+    pub scan_workers: Option<usize>,
+
+    /// Files read and chunked at once (default: CPU count)
+    #[arg(long, value_name = "N")]
+    pub chunk_workers: Option<usize>,
+
+    /// Chunks of an embedding batch embedded at once (default: CPU count)
+    #[arg(long, value_name = "N")]
+    pub embed_workers: Option<usize>,
+
+    /// Chunks per embedding batch
+    #[arg(long, value_name = "N", default_value = "32")]
+    pub embed_batch: usize,
+
+    /// Messages buffered between pipeline stages; a full queue pauses the
+    /// stage feeding it, which bounds memory use
+    #[arg(long, value_name = "N", default_value = "1000")]
+    pub queue_depth: usize,
+
+    /// Reuse embeddings of chunks seen in earlier runs, caching new ones on
+    /// disk (in the data directory's embeddings folder unless
+    /// --embed-cache-dir is given)
+    #[arg(long)]
+    pub embed_cache: bool,
+
+    /// Directory for the embedding cache (implies --embed-cache)
+    #[arg(long, value_name = "DIR")]
+    pub embed_cache_dir: Option<PathBuf>,
+
+    /// Disk space the embedding cache may use, in MiB; least recently used
+    /// vectors are deleted past it
+    #[arg(long, value_name = "MIB", default_value = "4096")]
+    pub embed_cache_size: u64,
+
     /// Maximum retry attempts for failed operations
     #[arg(long, default_value = "3")]
     pub max_retries: u32,
@@ -427,15 +1186,123 @@ pub struct SwarmArgs {
     #[arg(long)]
     pub heal_log: Option<PathBuf>,
 
+    /// Reprocess only the files that failed in a heal log from an earlier
+    /// run, instead of the whole source
+    #[arg(long, value_name = "LOG")]
+    pub retry_failed: Option<PathBuf>,
+
     /// Enable GPU to CPU fallback on compute failures
     #[arg(long)]
     pub gpu_fallback: bool,
 
+    /// Tag chunks containing emails, phone, social security or card numbers
+    /// and write a per-file sensitivity report (JSON) here
+    #[arg(long, value_name = "PATH")]
+    pub sensitivity_report: Option<PathBuf>,
+
+    /// Also tag medical, financial, legal and credential content using this
+    /// Ollama embedding model
+    #[arg(long, value_name = "MODEL", requires = "sensitivity_report")]
+    pub classify_model: Option<String>,
+
+    /// Transcribe audio and video with Whisper so speech is embedded and
+    /// searchable, writing the transcripts (JSON) here (needs --features gpu)
+    #[arg(long, value_name = "PATH")]
+    pub transcripts: Option<PathBuf>,
+
+    /// Whisper model on HuggingFace Hub to transcribe with
+    #[arg(
+        long,
+        value_name = "MODEL",
+        default_value = "openai/whisper-base",
+        requires = "transcripts"
+    )]
+    pub whisper_model: String,
+
+    /// Language spoken in the recordings (ISO 639-1, e.g. en, de)
+    #[arg(long, value_name = "LANG", default_value = "en", requires = "transcripts")]
+    pub language: String,
+
+    /// Continue an interrupted run from its session file, skipping files
+    /// it already finished
+    #[arg(long, value_name = "SESSION")]
+    pub resume: Option<PathBuf>,
+
+    /// Directory to save the session in (default: the data directory's
+    /// sessions folder)
+    #[arg(long, value_name = "DIR", conflicts_with = "resume")]
+    pub session_dir: Option<PathBuf>,
+
+    /// Show a live dashboard of per-agent progress and ETA while the
+    /// swarm runs (human report only)
+    #[arg(long)]
+    pub dashboard: bool,
+
     /// Output format for report
     #[arg(long, value_enum, default_value = "human")]
     pub report: SwarmReportFormat,
 }
 
+#[derive(Debug, Clone, Parser)]
+pub struct HealReportArgs {
+    /// Heal log written by `swarm --heal-log`
+    #[arg(required = true)]
+    pub log: PathBuf,
+
+    /// Number of failed files to list
+    #[arg(long, default_value = "20")]
+    pub top: usize,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct AskArgs {
+    /// Directory or file of recovered documents to search
+    #[arg(required = true)]
+    pub source: PathBuf,
+
+    /// Question to answer
+    #[arg(required = true)]
+    pub question: String,
+
+    /// Have a local chat model answer from the passages found
+    #[arg(long)]
+    pub answer: bool,
+
+    /// Number of passages to retrieve
+    #[arg(long, short = 'k', default_value = "5")]
+    pub top: usize,
+
+    /// Local chat server to answer with
+    #[arg(long, value_enum, default_value = "lm-studio")]
+    pub llm: LlmBackendArg,
+
+    /// Chat model (default: LM Studio's loaded model, or llama3.2 on Ollama)
+    #[arg(long)]
+    pub chat_model: Option<String>,
+
+    /// Chat server URL (default: http://localhost:1234/v1 for LM Studio,
+    /// http://localhost:11434 for Ollama)
+    #[arg(long)]
+    pub llm_endpoint: Option<String>,
+
+    /// Embedding model served by LM Studio or Ollama (without one, passages
+    /// are ranked by keywords only)
+    #[arg(long, default_value = "nomic-embed-text")]
+    pub embed_model: String,
+
+    /// Only search files with these extensions (comma-separated)
+    #[arg(long, short, value_delimiter = ',')]
+    pub extensions: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum LlmBackendArg {
+    /// LM Studio (OpenAI-compatible API)
+    LmStudio,
+    /// Ollama
+    Ollama,
+}
+
 #[derive(Debug, Clone, Copy, ValueEnum)]
 pub enum SwarmReportFormat {
     /// Human-readable output
@@ -446,12 +1313,14 @@ pub enum SwarmReportFormat {
 
 #[derive(Debug, Clone, Parser)]
 pub struct ReportArgs {
-    /// Path to an export manifest (diamond-drill-manifest.json)
+    /// Run to report on: an export manifest or destination, a carve output
+    /// directory or carve-results.json, or an indexed source or image
     #[arg(required = true)]
-    pub manifest: PathBuf,
+    pub input: PathBuf,
 
-    /// Output path for the report (default: report.html next to manifest)
-    #[arg(long, short)]
+    /// Output path for the report (default: next to the manifest or carve
+    /// output, or the current directory for an indexed source)
+    #[arg(id = "report_output", long = "out", short = 'o', value_name = "FILE")]
     pub output: Option<PathBuf>,
 
     /// Report format
@@ -462,16 +1331,359 @@ pub struct ReportArgs {
     #[arg(long)]
     pub title: Option<String>,
 
+    /// Bad sector report (`index --bad-sector-report`) or ddrescue mapfile
+    /// to draw as heatmaps (default: the image's .map file, if any)
+    #[arg(long, value_name = "FILE")]
+    pub bad_sectors: Option<PathBuf>,
+
+    /// Logo (PNG or JPEG) for the top of the PDF report
+    #[arg(long, value_name = "FILE")]
+    pub logo: Option<PathBuf>,
+
+    /// Recovered images to embed in the thumbnail gallery (0 leaves it out)
+    #[arg(long, value_name = "N", default_value_t = crate::report::DEFAULT_GALLERY_SIZE)]
+    pub thumbnails: usize,
+
     /// Open report in browser after generation
     #[arg(long, default_value = "true")]
     pub open: bool,
 }
 
+#[derive(Debug, Clone, Parser)]
+pub struct ChatsArgs {
+    /// Source directory (recovered phone storage, backup, or chat exports)
+    #[arg(required = true)]
+    pub source: PathBuf,
+
+    /// Export each conversation into DIR/<app>/<conversation>/ with an index.html
+    #[arg(long, short)]
+    pub export: Option<PathBuf>,
+
+    /// Only include conversations whose name contains this text (repeatable)
+    #[arg(long, short)]
+    pub conversation: Vec<String>,
+
+    /// Only include conversations from this app
+    #[arg(long, value_enum)]
+    pub app: Option<ChatAppFilter>,
+
+    /// Skip hash verification when exporting
+    #[arg(long)]
+    pub no_verify: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ChatAppFilter {
+    Whatsapp,
+    Telegram,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct ConvertArgs {
+    /// Source image (raw/dd, first split segment .001, or .E01)
+    #[arg(required = true)]
+    pub input: PathBuf,
+
+    /// Output image (.E01 for EWF, .001 for split raw, anything else for raw)
+    #[arg(
+        id = "output_image",
+        value_name = "OUTPUT",
+        required_unless_present_any = ["list_partitions", "list_snapshots"]
+    )]
+    pub output: Option<PathBuf>,
+
+    /// Output format (default: inferred from the output extension)
+    #[arg(long, short, value_enum)]
+    pub format: Option<ImageFormatArg>,
+
+    /// Maximum segment size for split and E01 output (e.g., 650MB, 2GB)
+    #[arg(long, default_value = "2GB")]
+    pub segment_size: String,
+
+    /// Only copy this partition (see --list-partitions)
+    #[arg(long, short)]
+    pub partition: Option<usize>,
+
+    /// List the partitions in the source image and exit
+    #[arg(long)]
+    pub list_partitions: bool,
+
+    /// Copy this Volume Shadow Copy snapshot of an NTFS volume instead of
+    /// its current contents (see --list-snapshots)
+    #[arg(long)]
+    pub snapshot: Option<usize>,
+
+    /// List the Volume Shadow Copy snapshots in the source (or --partition)
+    /// and exit
+    #[arg(long)]
+    pub list_snapshots: bool,
+
+    /// Re-read the output after conversion and compare hashes
+    #[arg(long)]
+    pub verify: bool,
+
+    /// Case number recorded in the E01 header
+    #[arg(long)]
+    pub case_number: Option<String>,
+
+    /// Examiner name recorded in the E01 header
+    #[arg(long)]
+    pub examiner: Option<String>,
+
+    /// Evidence description recorded in the E01 header
+    #[arg(long)]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct ImageArgs {
+    /// Device or file to clone (opened read-only)
+    #[arg(required = true)]
+    pub device: PathBuf,
+
+    /// Image file to write
+    #[arg(required = true)]
+    pub image: PathBuf,
+
+    /// Bytes per read in the copy pass (e.g., 64KB, 1MB)
+    #[arg(long, default_value = "64KB")]
+    pub block_size: String,
+
+    /// Bytes per read when scraping failed blocks
+    #[arg(long, default_value = "512")]
+    pub sector_size: String,
+
+    /// Passes over the bad sectors after scraping
+    #[arg(long, default_value = "1")]
+    pub retries: u32,
+
+    /// Scrape failed blocks last sector first
+    #[arg(long)]
+    pub reverse: bool,
+
+    /// ddrescue mapfile to write (default: <IMAGE>.map)
+    #[arg(long, value_name = "FILE")]
+    pub mapfile: Option<PathBuf>,
+
+    /// How the device is read in the copy pass: io_uring (Linux) or
+    /// overlapped I/O (Windows) when built with --features async-io
+    #[arg(long, value_enum, default_value = "auto")]
+    pub io_backend: IoBackendArg,
+
+    /// Device reads kept in flight
+    #[arg(long, default_value = "32", value_parser = clap::value_parser!(u16).range(1..=4096))]
+    pub queue_depth: u16,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct HealthArgs {
+    /// Device, or a path on the filesystem to check the drive of
+    #[arg(required = true)]
+    pub source: PathBuf,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct BenchArgs {
+    /// Image, device or directory to read
+    #[arg(required = true)]
+    pub source: PathBuf,
+
+    /// Directory to measure writes to, such as an export destination
+    #[arg(long, short)]
+    pub dest: Option<PathBuf>,
+
+    /// Block sizes to measure
+    #[arg(long, value_delimiter = ',', default_value = "4KB,64KB,1MB,4MB")]
+    pub block_sizes: Vec<String>,
+
+    /// Most data read or written by one measurement
+    #[arg(long, default_value = "256MB")]
+    pub sample: String,
+
+    /// Longest one measurement runs, in seconds
+    #[arg(long, default_value = "5")]
+    pub seconds: u64,
+
+    /// Most workers to try at once (default: CPU count, up to 16)
+    #[arg(long)]
+    pub max_workers: Option<usize>,
+
+    /// Show the results without keeping them for later commands
+    #[arg(long)]
+    pub no_save: bool,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct ArtifactsArgs {
+    /// Source to search (its index is used if it has one)
+    #[arg(required = true)]
+    pub source: PathBuf,
+
+    /// Also save the full report (databases and timeline) as JSON
+    #[arg(long)]
+    pub report: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct StatsArgs {
+    /// Indexed source or index file to summarize
+    #[arg(required = true)]
+    pub source: PathBuf,
+
+    /// Number of largest files to list
+    #[arg(long, default_value_t = crate::report::DEFAULT_TOP_FILES)]
+    pub top: usize,
+
+    /// Also write the report as an HTML page
+    #[arg(long, value_name = "FILE")]
+    pub html: Option<PathBuf>,
+
+    /// Open the HTML page in the default browser
+    #[arg(long, requires = "html")]
+    pub open: bool,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct WatchArgs {
+    /// Staging directory to watch (recursively)
+    #[arg(required = true)]
+    pub dir: PathBuf,
+
+    /// Seconds a file must go unchanged before it is indexed
+    #[arg(long, value_name = "SECS", default_value_t = crate::watch::DEFAULT_SETTLE.as_secs())]
+    pub settle: u64,
+
+    /// Don't generate thumbnails for new images
+    #[arg(long)]
+    pub no_thumbnails: bool,
+
+    /// Don't check new files against the index for identical content
+    #[arg(long)]
+    pub no_dedup: bool,
+
+    /// Ignore hidden files and directories
+    #[arg(long)]
+    pub skip_hidden: bool,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct AnalyzeArgs {
+    /// Source image (raw/dd, first split segment .001, or .E01)
+    #[arg(required = true)]
+    pub source: PathBuf,
+
+    /// Bytes per entropy block (e.g., 64KB, 1MB)
+    #[arg(long, default_value = "1MB")]
+    pub block_size: String,
+
+    /// Only read this many evenly spaced blocks (quick overview of a large image)
+    #[arg(long, value_name = "BLOCKS")]
+    pub sample: Option<u64>,
+
+    /// Limit reads to this rate per second (e.g., 20MB) to spare a failing drive
+    #[arg(long)]
+    pub max_rate: Option<String>,
+
+    /// Width of the entropy heat strip in characters
+    #[arg(long, default_value = "64")]
+    pub width: usize,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct MaintenanceArgs {
+    #[command(subcommand)]
+    pub action: MaintenanceAction,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum MaintenanceAction {
+    /// Remove old checkpoints, indexes, sessions and heal logs, and compact
+    /// large old checkpoints
+    Gc(GcArgs),
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct GcArgs {
+    /// Data directory to clean (default: the platform data directory)
+    #[arg(long)]
+    pub data_dir: Option<PathBuf>,
+
+    /// Remove artifacts older than this many days (0 = no age limit)
+    #[arg(long, default_value = "90")]
+    pub max_age_days: u32,
+
+    /// Keep at most this many artifacts of each kind per source (0 = no limit)
+    #[arg(long, default_value = "5")]
+    pub keep_per_source: usize,
+
+    /// Compact checkpoints older than this many days into bloom-filter summaries
+    #[arg(long, default_value = "7")]
+    pub compact_after_days: u32,
+
+    /// Only compact checkpoints with at least this many processed paths
+    #[arg(long, default_value = "10000")]
+    pub compact_min_paths: usize,
+
+    /// Never compact checkpoints
+    #[arg(long)]
+    pub no_compact: bool,
+
+    /// List what would be removed or compacted without changing anything
+    #[arg(long, short = 'n')]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct LogsArgs {
+    #[command(subcommand)]
+    pub action: LogsAction,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum LogsAction {
+    /// List past runs, newest first
+    List(LogsListArgs),
+    /// Pretty-print the full log of one run
+    Show(LogsShowArgs),
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct LogsListArgs {
+    /// Directory of operation logs (default: logs in the data directory)
+    #[arg(long)]
+    pub dir: Option<PathBuf>,
+
+    /// Number of runs to list
+    #[arg(long, short = 'n', default_value = "20")]
+    pub limit: usize,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct LogsShowArgs {
+    /// Run ID (or a unique prefix of it), log file, or `last`
+    #[arg(default_value = "last")]
+    pub run: String,
+
+    /// Directory of operation logs (default: logs in the data directory)
+    #[arg(long)]
+    pub dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ImageFormatArg {
+    /// Single raw (dd) file
+    Raw,
+    /// Raw image split into .001, .002, ... segments
+    Split,
+    /// Expert Witness Format (EnCase), uncompressed
+    E01,
+}
+
 #[derive(Debug, Clone, Copy, ValueEnum)]
 pub enum ReportFormat {
     /// Self-contained HTML report with dark glassmorphic theme
     Html,
-    /// PDF report with summary and chain of custody
+    /// PDF report with summary, chain of custody and a signature block
     Pdf,
     /// Generate both HTML and PDF
     Both,
@@ -0,0 +1,268 @@
+//! Machine-readable output for scripting
+//!
+//! Prints index entries and content matches as JSON lines or CSV so command
+//! output can be piped into jq, xargs, spreadsheets, etc. Timestamps are always RFC 3339 UTC here,
+//! independent of `--timezone`.
+
+use std::io::Write;
+
+use anyhow::Result;
+
+use super::OutputFormat;
+use crate::artifacts::ArtifactEvent;
+use crate::core::{ContentMatch, FileEntry};
+
+/// Column order for CSV output
+pub const CSV_HEADER: &str = "path,size,file_type,extension,modified,created,hash,has_bad_sectors";
+
+/// Column order for `search --content` CSV output
+pub const MATCH_CSV_HEADER: &str = "path,offset,line,extracted,snippet";
+
+/// Column order for `artifacts` CSV output
+pub const ARTIFACT_CSV_HEADER: &str = "time,browser,profile,kind,url,detail,database";
+
+/// Write `entries` to `out` in the given format.
+///
+/// `Human` writes one path per line, which is what `xargs` expects.
+pub fn write_entries<W: Write>(
+    out: &mut W,
+    entries: &[FileEntry],
+    format: OutputFormat,
+) -> Result<()> {
+    match format {
+        OutputFormat::Human => {
+            for entry in entries {
+                writeln!(out, "{}", entry.path.display())?;
+            }
+        }
+        OutputFormat::Json => {
+            for entry in entries {
+                serde_json::to_writer(&mut *out, entry)?;
+                writeln!(out)?;
+            }
+        }
+        OutputFormat::Csv => {
+            writeln!(out, "{}", CSV_HEADER)?;
+            for entry in entries {
+                let fields = [
+                    entry.path.to_string_lossy().to_string(),
+                    entry.size.to_string(),
+                    format!("{:?}", entry.file_type),
+                    entry.extension.clone(),
+                    entry.modified.map(|d| d.to_rfc3339()).unwrap_or_default(),
+                    entry.created.map(|d| d.to_rfc3339()).unwrap_or_default(),
+                    entry.hash.clone().unwrap_or_default(),
+                    entry.has_bad_sectors.to_string(),
+                ];
+                let row: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+                writeln!(out, "{}", row.join(","))?;
+            }
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Print `entries` to stdout in the given format
+pub fn print_entries(entries: &[FileEntry], format: OutputFormat) -> Result<()> {
+    let stdout = std::io::stdout();
+    let mut out = std::io::BufWriter::new(stdout.lock());
+    write_entries(&mut out, entries, format)
+}
+
+/// Write `search --content` matches to `out` in the given format.
+///
+/// `Human` writes `path:line:offset: snippet`, like `grep -nb`.
+pub fn write_matches<W: Write>(
+    out: &mut W,
+    matches: &[ContentMatch],
+    format: OutputFormat,
+) -> Result<()> {
+    match format {
+        OutputFormat::Human => {
+            for m in matches {
+                writeln!(
+                    out,
+                    "{}:{}:{}: {}",
+                    m.path.display(),
+                    m.line,
+                    m.offset,
+                    m.snippet
+                )?;
+            }
+        }
+        OutputFormat::Json => {
+            for m in matches {
+                serde_json::to_writer(&mut *out, m)?;
+                writeln!(out)?;
+            }
+        }
+        OutputFormat::Csv => {
+            writeln!(out, "{}", MATCH_CSV_HEADER)?;
+            for m in matches {
+                let fields = [
+                    m.path.to_string_lossy().to_string(),
+                    m.offset.to_string(),
+                    m.line.to_string(),
+                    m.extracted.to_string(),
+                    m.snippet.clone(),
+                ];
+                let row: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+                writeln!(out, "{}", row.join(","))?;
+            }
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Print `search --content` matches to stdout in the given format
+pub fn print_matches(matches: &[ContentMatch], format: OutputFormat) -> Result<()> {
+    let stdout = std::io::stdout();
+    let mut out = std::io::BufWriter::new(stdout.lock());
+    write_matches(&mut out, matches, format)
+}
+
+/// Write a browser artifact timeline to `out` in the given format.
+///
+/// `Human` writes `time  browser  kind  url  detail`, one event per line.
+pub fn write_artifacts<W: Write>(
+    out: &mut W,
+    events: &[ArtifactEvent],
+    format: OutputFormat,
+) -> Result<()> {
+    match format {
+        OutputFormat::Human => {
+            for e in events {
+                writeln!(
+                    out,
+                    "{}  {:<7}  {:<8}  {}  {}",
+                    e.time
+                        .map(|t| crate::core::format_timestamp(&t, "%Y-%m-%d %H:%M:%S"))
+                        .unwrap_or_else(|| "-".repeat(19)),
+                    e.browser,
+                    e.kind,
+                    e.url,
+                    e.detail
+                )?;
+            }
+        }
+        OutputFormat::Json => {
+            for e in events {
+                serde_json::to_writer(&mut *out, e)?;
+                writeln!(out)?;
+            }
+        }
+        OutputFormat::Csv => {
+            writeln!(out, "{}", ARTIFACT_CSV_HEADER)?;
+            for e in events {
+                let fields = [
+                    e.time.map(|d| d.to_rfc3339()).unwrap_or_default(),
+                    e.browser.to_string(),
+                    e.profile.clone(),
+                    e.kind.to_string(),
+                    e.url.clone(),
+                    e.detail.clone(),
+                    e.database.to_string_lossy().to_string(),
+                ];
+                let row: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+                writeln!(out, "{}", row.join(","))?;
+            }
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Print a browser artifact timeline to stdout in the given format
+pub fn print_artifacts(events: &[ArtifactEvent], format: OutputFormat) -> Result<()> {
+    let stdout = std::io::stdout();
+    let mut out = std::io::BufWriter::new(stdout.lock());
+    write_artifacts(&mut out, events, format)
+}
+
+/// Quote a CSV field per RFC 4180 when it contains a delimiter, quote or newline
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::FileType;
+    use chrono::{TimeZone, Utc};
+    use std::path::PathBuf;
+
+    fn entry(path: &str) -> FileEntry {
+        FileEntry {
+            path: PathBuf::from(path),
+            size: 42,
+            file_type: FileType::Document,
+            extension: "txt".to_string(),
+            modified: Some(Utc.with_ymd_and_hms(2024, 5, 6, 7, 8, 9).unwrap()),
+            created: None,
+            hash: None,
+            has_bad_sectors: false,
+            thumbnail: None,
+            metadata: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_write_entries_formats() {
+        let entries = vec![entry("/data/notes.txt"), entry("/data/a, \"quoted\".txt")];
+
+        let mut csv = Vec::new();
+        write_entries(&mut csv, &entries, OutputFormat::Csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(
+            lines[1],
+            "/data/notes.txt,42,Document,txt,2024-05-06T07:08:09+00:00,,,false"
+        );
+        assert!(lines[2].starts_with("\"/data/a, \"\"quoted\"\".txt\",42,"));
+
+        let mut json = Vec::new();
+        write_entries(&mut json, &entries, OutputFormat::Json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert_eq!(json.lines().count(), 2);
+        let first: serde_json::Value = serde_json::from_str(json.lines().next().unwrap()).unwrap();
+        assert_eq!(first["path"], "/data/notes.txt");
+        assert_eq!(first["size"], 42);
+
+        let mut human = Vec::new();
+        write_entries(&mut human, &entries[..1], OutputFormat::Human).unwrap();
+        assert_eq!(String::from_utf8(human).unwrap(), "/data/notes.txt\n");
+    }
+
+    #[test]
+    fn test_write_matches_formats() {
+        let matches = vec![ContentMatch {
+            path: PathBuf::from("/data/lease.pdf"),
+            offset: 120,
+            line: 4,
+            snippet: "the tenancy agreement, signed".to_string(),
+            extracted: true,
+        }];
+
+        let mut human = Vec::new();
+        write_matches(&mut human, &matches, OutputFormat::Human).unwrap();
+        assert_eq!(
+            String::from_utf8(human).unwrap(),
+            "/data/lease.pdf:4:120: the tenancy agreement, signed\n"
+        );
+
+        let mut csv = Vec::new();
+        write_matches(&mut csv, &matches, OutputFormat::Csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(
+            csv.lines().nth(1).unwrap(),
+            "/data/lease.pdf,120,4,true,\"the tenancy agreement, signed\""
+        );
+    }
+}
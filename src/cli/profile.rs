@@ -0,0 +1,224 @@
+//! Config file profiles - preset options merged into the command line
+//!
+//! A [`Profile`] names options the way the command line does. Every option
+//! of the profile that was not given explicitly is appended to the
+//! arguments, which are then parsed again, so clap validates preset values
+//! exactly like typed ones.
+
+use std::ffi::OsString;
+
+use anyhow::{bail, Context, Result};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches};
+
+use super::Cli;
+use crate::config::{Config, Profile};
+
+/// Parse the process arguments with the config file's defaults and the
+/// `--profile` applied
+pub fn parse_args() -> Result<Cli> {
+    let args: Vec<OsString> = std::env::args_os().collect();
+    let matches = Cli::command().get_matches_from(&args);
+    // A broken config file only matters when a profile was asked for
+    let config = if matches.get_one::<String>("profile").is_some() && Config::exists() {
+        Config::load_from(&Config::default_path())?
+    } else {
+        Config::load()
+    };
+    parse_with_config(args, &matches, &config)
+}
+
+/// Re-parse `args` (already parsed into `matches`) with `config`'s presets
+pub fn parse_with_config(
+    mut args: Vec<OsString>,
+    matches: &ArgMatches,
+    config: &Config,
+) -> Result<Cli> {
+    let name = matches.get_one::<String>("profile").map(String::as_str);
+    let profile = config.profile(name)?;
+    let preset = preset_args(&profile, matches)?;
+    if preset.is_empty() {
+        return Ok(Cli::from_arg_matches(matches)?);
+    }
+
+    // After `--` everything is positional
+    let at = args.iter().position(|a| a == "--").unwrap_or(args.len());
+    args.splice(at..at, preset.into_iter().map(OsString::from));
+    let origin = match name {
+        Some(name) => format!("profile `{}`", name),
+        None => "[defaults]".to_string(),
+    };
+    let matches = Cli::command()
+        .try_get_matches_from(args)
+        .with_context(|| format!("Invalid option in config {}", origin))?;
+    Ok(Cli::from_arg_matches(&matches)?)
+}
+
+/// Arguments for the profile's global options and those of the command
+/// being run, skipping options given on the command line
+fn preset_args(profile: &Profile, matches: &ArgMatches) -> Result<Vec<String>> {
+    let command = Cli::command();
+    let mut args = Vec::new();
+    for (key, value) in profile.global_options() {
+        push_option(&mut args, &command, matches, None, key, value)?;
+    }
+    if let Some((name, sub_matches)) = matches.subcommand() {
+        if let Some(options) = profile.command_options(name) {
+            let sub = command
+                .find_subcommand(name)
+                .expect("clap matched a known subcommand");
+            for (key, value) in options {
+                push_option(&mut args, sub, sub_matches, Some(name), key, value)?;
+            }
+        }
+    }
+    Ok(args)
+}
+
+fn push_option(
+    args: &mut Vec<String>,
+    command: &clap::Command,
+    matches: &ArgMatches,
+    command_name: Option<&str>,
+    key: &str,
+    value: &toml::Value,
+) -> Result<()> {
+    let id = key.replace('-', "_");
+    let long = command
+        .get_arguments()
+        .find(|a| a.get_id() == id.as_str())
+        .and_then(|a| a.get_long());
+    let Some(long) = long else {
+        match command_name {
+            Some(name) => bail!("Profile option `{}` is not an option of `{}`", key, name),
+            None => bail!("Profile option `{}` is not a global option", key),
+        }
+    };
+    if matches!(
+        matches.value_source(&id),
+        Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+    ) {
+        return Ok(());
+    }
+
+    let values = match value {
+        toml::Value::Array(items) => items.as_slice(),
+        single => std::slice::from_ref(single),
+    };
+    for value in values {
+        match value {
+            toml::Value::Boolean(true) => args.push(format!("--{}", long)),
+            // Flags are off unless given
+            toml::Value::Boolean(false) => {}
+            toml::Value::String(s) => args.push(format!("--{}={}", long, s)),
+            toml::Value::Integer(_) | toml::Value::Float(_) | toml::Value::Datetime(_) => {
+                args.push(format!("--{}={}", long, value))
+            }
+            toml::Value::Array(_) | toml::Value::Table(_) => {
+                bail!(
+                    "Profile option `{}` must be a value or a list of values",
+                    key
+                )
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{Commands, ExportOrganizeBy};
+
+    fn parse(args: &[&str], config: &Config) -> Result<Cli> {
+        let args: Vec<OsString> = args.iter().map(OsString::from).collect();
+        let matches = Cli::command().try_get_matches_from(&args)?;
+        parse_with_config(args, &matches, config)
+    }
+
+    #[test]
+    fn test_profile_fills_options_not_given() {
+        let config: Config = toml::from_str(
+            r#"
+[defaults.export]
+manifest = true
+
+[profiles.slow.export]
+hash-retries = 5
+max_rate = "10MB"
+hash = ["md5", "sha256"]
+"#,
+        )
+        .unwrap();
+
+        let cli = parse(
+            &[
+                "diamond-drill",
+                "--profile",
+                "slow",
+                "export",
+                "/src",
+                "/dst",
+                "--hash-retries",
+                "1",
+            ],
+            &config,
+        )
+        .unwrap();
+        let Some(Commands::Export(args)) = cli.command else {
+            panic!("expected export");
+        };
+        assert!(args.manifest);
+        assert_eq!(args.hash_retries, 1);
+        assert_eq!(args.max_rate.as_deref(), Some("10MB"));
+        assert_eq!(args.hash.len(), 2);
+
+        // Built-in profile, options after `--` stay positional
+        let cli = parse(
+            &[
+                "diamond-drill",
+                "export",
+                "--profile=photo-rescue",
+                "/src",
+                "/dst",
+                "--",
+                "--odd-name",
+            ],
+            &config,
+        )
+        .unwrap();
+        let Some(Commands::Export(args)) = cli.command else {
+            panic!("expected export");
+        };
+        assert!(args.manifest && args.sector_retry);
+        assert!(matches!(
+            args.organize_by,
+            Some(ExportOrganizeBy::DateTaken)
+        ));
+        assert_eq!(args.files, vec!["--odd-name".to_string()]);
+    }
+
+    #[test]
+    fn test_profile_errors_name_the_option() {
+        let config: Config = toml::from_str(
+            r#"
+[profiles.typo.export]
+manifests = true
+
+[profiles.bad.export]
+organize_by = "colour"
+"#,
+        )
+        .unwrap();
+        let args = ["diamond-drill", "--profile", "typo", "export", "/s", "/d"];
+        let error = parse(&args, &config).unwrap_err().to_string();
+        assert_eq!(
+            error,
+            "Profile option `manifests` is not an option of `export`"
+        );
+
+        let args = ["diamond-drill", "--profile", "bad", "export", "/s", "/d"];
+        let error = parse(&args, &config).unwrap_err().to_string();
+        assert_eq!(error, "Invalid option in config profile `bad`");
+    }
+}
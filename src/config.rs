@@ -5,12 +5,15 @@
 //! - Theme preferences (dark/light/auto)
 //! - Keyboard shortcuts customization
 //! - Read-only enforcement settings
+//! - Worker counts per kind of storage (spinning disk, SSD, USB, network)
+//! - Named profiles of command-line options (`--profile forensic`) and
+//!   per-command defaults applied to every run
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::{Path, PathBuf};
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use serde::{Deserialize, Serialize};
 
 /// Diamond Drill Configuration
@@ -25,11 +28,100 @@ pub struct Config {
     pub tui: TuiConfig,
     /// Scan settings
     pub scan: ScanConfig,
+    /// Worker counts for the kind of storage being read
+    pub tuning: TuningConfig,
     /// Custom keyboard shortcuts
     #[serde(default)]
     pub keys: HashMap<String, String>,
+    /// Command-line options applied to every run
+    pub defaults: Profile,
+    /// Named sets of command-line options, selected with `--profile`
+    pub profiles: BTreeMap<String, Profile>,
 }
 
+/// Preset command-line options
+///
+/// Scalar keys set global options (`output = "json"`), and each table the
+/// options of one command (`[profiles.forensic.export]`). Keys are long
+/// option names, with `-` or `_`; values are what the option takes on the
+/// command line, `true` for a flag and a list for an option that can be
+/// repeated. Options given on the command line always win.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Profile {
+    /// One-line summary, shown when listing profiles
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Global options and per-command tables
+    #[serde(flatten)]
+    pub options: toml::Table,
+}
+
+impl Profile {
+    /// These options with `other`'s on top, command by command
+    pub fn merged(mut self, other: &Profile) -> Profile {
+        for (key, value) in &other.options {
+            match (self.options.get_mut(key), value) {
+                (Some(toml::Value::Table(mine)), toml::Value::Table(theirs)) => {
+                    mine.extend(theirs.clone());
+                }
+                _ => {
+                    self.options.insert(key.clone(), value.clone());
+                }
+            }
+        }
+        if other.description.is_some() {
+            self.description = other.description.clone();
+        }
+        self
+    }
+
+    /// Global options (no table)
+    pub fn global_options(&self) -> impl Iterator<Item = (&String, &toml::Value)> {
+        self.options.iter().filter(|(_, v)| !v.is_table())
+    }
+
+    /// Options of `command`, if the profile sets any
+    pub fn command_options(&self, command: &str) -> Option<&toml::Table> {
+        self.options.get(command).and_then(toml::Value::as_table)
+    }
+}
+
+/// Profiles available without a config file; a profile of the same name in
+/// the config file replaces them
+const BUILTIN_PROFILES: &str = r#"
+[forensic]
+description = "Evidence handling: manifests, extra hashes, sector-level retries"
+
+[forensic.index]
+metadata = true
+
+[forensic.export]
+manifest = true
+hash = ["sha256", "md5"]
+hash_retries = 3
+sector_retry = true
+on_conflict = "error"
+
+[forensic.carve]
+min_size = "512"
+
+[photo-rescue]
+description = "Recover photos and videos, sorted by the date they were taken"
+
+[photo-rescue.index]
+metadata = true
+skip_hidden = true
+
+[photo-rescue.export]
+continue_on_error = true
+sector_retry = true
+organize_by = "date-taken"
+
+[photo-rescue.carve]
+file_type = ["image", "video"]
+min_size = "16KB"
+"#;
+
 /// General application settings
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
@@ -148,6 +240,57 @@ impl Default for ScanConfig {
     }
 }
 
+/// Auto-tuning of worker counts and block sizes
+///
+/// Without `--workers`, index, carve and export size themselves to the
+/// storage they read: what `bench` measured for it, or else a guess from its
+/// kind. The tables replace the built-in guess for one kind:
+///
+/// ```toml
+/// [tuning.rotational]
+/// read_workers = 2
+/// export_workers = 2
+/// block_size = 4194304
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TuningConfig {
+    /// Tune to the storage at all (off = one worker per CPU)
+    pub auto: bool,
+    /// Spinning disks
+    pub rotational: Option<StorageTuning>,
+    /// Internal SSDs and NVMe drives
+    pub ssd: Option<StorageTuning>,
+    /// USB-attached flash drives and SSDs
+    pub usb: Option<StorageTuning>,
+    /// NFS, SMB and other network file systems
+    pub network: Option<StorageTuning>,
+}
+
+impl Default for TuningConfig {
+    fn default() -> Self {
+        Self {
+            auto: true,
+            rotational: None,
+            ssd: None,
+            usb: None,
+            network: None,
+        }
+    }
+}
+
+/// Settings for one kind of storage; unset ones keep the built-in value
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StorageTuning {
+    /// Workers reading at once when indexing and carving
+    pub read_workers: Option<usize>,
+    /// Files exported at once
+    pub export_workers: Option<usize>,
+    /// Bytes per read
+    pub block_size: Option<usize>,
+}
+
 impl Config {
     /// Load config from default path or return defaults
     pub fn load() -> Self {
@@ -212,6 +355,34 @@ impl Config {
         Ok(())
     }
 
+    /// The defaults, with profile `name` on top when one is given
+    pub fn profile(&self, name: Option<&str>) -> Result<Profile> {
+        let Some(name) = name else {
+            return Ok(self.defaults.clone());
+        };
+        let profile = match self.profiles.get(name) {
+            Some(profile) => profile.clone(),
+            None => match builtin_profiles().remove(name) {
+                Some(profile) => profile,
+                None => bail!(
+                    "Unknown profile `{}` (available: {})",
+                    name,
+                    self.profile_names().join(", ")
+                ),
+            },
+        };
+        Ok(self.defaults.clone().merged(&profile))
+    }
+
+    /// Names of the built-in and configured profiles, sorted
+    pub fn profile_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = builtin_profiles().into_keys().collect();
+        names.extend(self.profiles.keys().cloned());
+        names.sort();
+        names.dedup();
+        names
+    }
+
     /// Get keybinding or default
     pub fn get_key(&self, action: &str, default: &str) -> String {
         self.keys
@@ -221,6 +392,11 @@ impl Config {
     }
 }
 
+/// Profiles every installation has
+pub fn builtin_profiles() -> BTreeMap<String, Profile> {
+    toml::from_str(BUILTIN_PROFILES).expect("built-in profiles are valid TOML")
+}
+
 /// Generate a sample config file with comments
 pub fn generate_sample_config() -> String {
     r#"# Diamond Drill Configuration
@@ -294,6 +470,17 @@ default_extensions = []
 # Maximum scan depth (0 = unlimited)
 max_depth = 0
 
+[tuning]
+# Without --workers, size index, carve and export to the storage they read:
+# what `diamond-drill bench` measured for it, or else a guess from its kind
+auto = true
+
+# Replace the guess for one kind of storage: rotational, ssd, usb or network
+# [tuning.rotational]
+# read_workers = 1
+# export_workers = 2
+# block_size = 1048576
+
 [keys]
 # Custom keybindings (action = key)
 # Available actions: quit, nav_up, nav_down, select, select_all, search, help
@@ -301,6 +488,25 @@ max_depth = 0
 # nav_up = "k"
 # nav_down = "j"
 # select = "space"
+
+# Command-line options applied to every run (options given on the command
+# line win). Keys are long option names; use true for flags and a list for
+# options that can be repeated.
+[defaults]
+# timezone = "UTC"
+
+[defaults.export]
+# manifest = true
+
+# Named profiles, selected with --profile NAME. Built in: "forensic" and
+# "photo-rescue"; a profile defined here with the same name replaces them.
+# [profiles.triage]
+# description = "Quick look at documents"
+# output = "json"
+#
+# [profiles.triage.index]
+# extensions = ["pdf", "docx", "xlsx"]
+# skip_hidden = true
 "#
     .to_string()
 }
@@ -337,7 +543,48 @@ mod tests {
     #[test]
     fn test_parse_sample_config() {
         let sample = generate_sample_config();
-        let _config: Config = toml::from_str(&sample).unwrap();
+        let config: Config = toml::from_str(&sample).unwrap();
+        assert!(config.tuning.auto);
+    }
+
+    #[test]
+    fn test_profiles_layer_over_defaults() {
+        let config: Config = toml::from_str(
+            r#"
+[defaults]
+timezone = "UTC"
+[defaults.export]
+manifest = true
+hash_retries = 2
+
+[profiles.forensic]
+output = "json"
+[profiles.forensic.export]
+hash_retries = 5
+"#,
+        )
+        .unwrap();
+
+        // The config file's profile replaces the built-in one
+        let profile = config.profile(Some("forensic")).unwrap();
+        assert!(profile.description.is_none());
+        let export = profile.command_options("export").unwrap();
+        assert_eq!(export["manifest"].as_bool(), Some(true));
+        assert_eq!(export["hash_retries"].as_integer(), Some(5));
+        assert!(export.get("sector_retry").is_none());
+        assert_eq!(profile.global_options().count(), 2);
+
+        let builtin = config.profile(Some("photo-rescue")).unwrap();
+        assert!(builtin.command_options("carve").is_some());
+        assert_eq!(
+            builtin.command_options("export").unwrap()["manifest"].as_bool(),
+            Some(true)
+        );
+
+        let defaults = config.profile(None).unwrap();
+        assert!(defaults.command_options("carve").is_none());
+        let error = config.profile(Some("nope")).unwrap_err().to_string();
+        assert!(error.contains("forensic, photo-rescue"), "{}", error);
     }
 
     #[test]
@@ -0,0 +1,341 @@
+//! Archive-aware indexing
+//!
+//! Backups often end up as a `backup.zip` or `home.tar.gz`, and the file
+//! someone is after is inside. With archive indexing, each file in a ZIP,
+//! tar (plain or gzipped) or, with the `sevenz` feature, 7z archive is
+//! indexed as a virtual [`FileEntry`] at `<archive path>/<member path>`,
+//! carrying the archive in `archive` metadata and the member's name in
+//! `archive_member`. Search finds members by name like any other file, and
+//! export extracts just that member. Archives inside archives are listed
+//! as members but not opened.
+
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+
+use super::index::FileEntry;
+use super::{display_timezone, fat_to_utc, FileType};
+
+/// Metadata key holding the path of the archive a member is in
+pub const ARCHIVE_KEY: &str = "archive";
+
+/// Metadata key holding a member's path inside its archive
+pub const ARCHIVE_MEMBER_KEY: &str = "archive_member";
+
+/// Archive formats that can be looked into
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+    #[cfg(feature = "sevenz")]
+    SevenZ,
+}
+
+impl ArchiveKind {
+    /// Format of `path`, from its name
+    pub fn detect(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+        if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            return Some(Self::TarGz);
+        }
+        match name.rsplit_once('.')?.1 {
+            "zip" => Some(Self::Zip),
+            "tar" => Some(Self::Tar),
+            #[cfg(feature = "sevenz")]
+            "7z" => Some(Self::SevenZ),
+            _ => None,
+        }
+    }
+}
+
+/// A file inside an archive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveMember {
+    pub archive: PathBuf,
+    /// Path inside the archive, as stored
+    pub name: String,
+}
+
+impl ArchiveMember {
+    /// The member an index entry stands for, if it is one
+    pub fn of(entry: &FileEntry) -> Option<Self> {
+        Some(Self {
+            archive: PathBuf::from(entry.metadata.get(ARCHIVE_KEY)?),
+            name: entry.metadata.get(ARCHIVE_MEMBER_KEY)?.clone(),
+        })
+    }
+
+    /// Run `f` on a reader of the member's contents
+    pub fn read<T>(&self, f: impl FnOnce(&mut dyn Read) -> Result<T>) -> Result<T> {
+        let kind = ArchiveKind::detect(&self.archive)
+            .with_context(|| format!("{} is not a known archive", self.archive.display()))?;
+        let file = File::open(&self.archive)
+            .with_context(|| format!("Failed to open {}", self.archive.display()))?;
+        let missing = || anyhow::anyhow!("{} has no member {}", self.archive.display(), self.name);
+        match kind {
+            ArchiveKind::Zip => {
+                let mut zip = zip::ZipArchive::new(BufReader::new(file))?;
+                let mut member = zip.by_name(&self.name).map_err(|_| missing())?;
+                f(&mut member)
+            }
+            ArchiveKind::Tar => {
+                read_tar_member(tar::Archive::new(BufReader::new(file)), &self.name, f)?
+                    .ok_or_else(missing)
+            }
+            ArchiveKind::TarGz => read_tar_member(
+                tar::Archive::new(flate2::read::GzDecoder::new(BufReader::new(file))),
+                &self.name,
+                f,
+            )?
+            .ok_or_else(missing),
+            #[cfg(feature = "sevenz")]
+            ArchiveKind::SevenZ => {
+                read_7z_member(&self.archive, &self.name, f)?.ok_or_else(missing)
+            }
+        }
+    }
+}
+
+/// Whether `entry` is a file inside an archive rather than on the source
+pub fn is_archive_member(entry: &FileEntry) -> bool {
+    entry.metadata.contains_key(ARCHIVE_MEMBER_KEY)
+}
+
+/// Virtual entries for the files inside `archive`, or an empty list when it
+/// is not an archive that can be looked into
+pub fn member_entries(archive: &FileEntry) -> Result<Vec<FileEntry>> {
+    let Some(kind) = ArchiveKind::detect(&archive.path) else {
+        return Ok(Vec::new());
+    };
+    let members = list_members(&archive.path, kind)
+        .with_context(|| format!("Failed to list {}", archive.path.display()))?;
+    Ok(members
+        .into_iter()
+        .map(|member| {
+            // Names like "../x" must not climb out of the archive's path
+            let inside: PathBuf = Path::new(&member.name)
+                .components()
+                .filter(|c| matches!(c, Component::Normal(_)))
+                .collect();
+            let path = archive.path.join(inside);
+            let extension = path
+                .extension()
+                .map(|e| e.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            let mut entry = FileEntry {
+                file_type: FileType::from_extension(&extension),
+                extension,
+                path,
+                size: member.size,
+                modified: member.modified,
+                created: None,
+                hash: None,
+                has_bad_sectors: false,
+                thumbnail: None,
+                metadata: Default::default(),
+            };
+            entry
+                .metadata
+                .insert(ARCHIVE_KEY.into(), archive.path.display().to_string());
+            entry
+                .metadata
+                .insert(ARCHIVE_MEMBER_KEY.into(), member.name);
+            entry
+        })
+        .collect())
+}
+
+struct Listed {
+    name: String,
+    size: u64,
+    modified: Option<DateTime<Utc>>,
+}
+
+fn list_members(path: &Path, kind: ArchiveKind) -> Result<Vec<Listed>> {
+    let file = BufReader::new(File::open(path)?);
+    match kind {
+        ArchiveKind::Zip => {
+            let mut zip = zip::ZipArchive::new(file)?;
+            let mut members = Vec::with_capacity(zip.len());
+            for i in 0..zip.len() {
+                // Raw access lists encrypted members too
+                let member = zip.by_index_raw(i)?;
+                if member.is_dir() {
+                    continue;
+                }
+                let modified = member.last_modified();
+                members.push(Listed {
+                    name: member.name().to_string(),
+                    size: member.size(),
+                    modified: fat_to_utc(
+                        modified.datepart(),
+                        modified.timepart(),
+                        0,
+                        &display_timezone(),
+                    ),
+                });
+            }
+            Ok(members)
+        }
+        ArchiveKind::Tar => list_tar(tar::Archive::new(file)),
+        ArchiveKind::TarGz => list_tar(tar::Archive::new(flate2::read::GzDecoder::new(file))),
+        #[cfg(feature = "sevenz")]
+        ArchiveKind::SevenZ => {
+            let archive = sevenz_rust::Archive::open(path)?;
+            Ok(archive
+                .files
+                .into_iter()
+                .filter(|f| !f.is_directory() && !f.is_anti_item())
+                .map(|f| Listed {
+                    name: f.name().to_string(),
+                    size: f.size(),
+                    modified: f
+                        .has_last_modified_date
+                        .then(|| super::filetime_to_utc(f.last_modified_date().to_raw()))
+                        .flatten(),
+                })
+                .collect())
+        }
+    }
+}
+
+fn list_tar<R: Read>(mut archive: tar::Archive<R>) -> Result<Vec<Listed>> {
+    let mut members = Vec::new();
+    for member in archive.entries()? {
+        let member = member?;
+        let header = member.header();
+        if !header.entry_type().is_file() {
+            continue;
+        }
+        members.push(Listed {
+            name: member.path()?.to_string_lossy().into_owned(),
+            size: header.size()?,
+            modified: header
+                .mtime()
+                .ok()
+                .and_then(|secs| DateTime::from_timestamp(secs as i64, 0)),
+        });
+    }
+    Ok(members)
+}
+
+fn read_tar_member<R: Read, T>(
+    mut archive: tar::Archive<R>,
+    name: &str,
+    f: impl FnOnce(&mut dyn Read) -> Result<T>,
+) -> Result<Option<T>> {
+    for member in archive.entries()? {
+        let mut member = member?;
+        if member.path()?.to_string_lossy() == name {
+            return f(&mut member).map(Some);
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(feature = "sevenz")]
+fn read_7z_member<T>(
+    archive: &Path,
+    name: &str,
+    f: impl FnOnce(&mut dyn Read) -> Result<T>,
+) -> Result<Option<T>> {
+    let mut reader = sevenz_rust::SevenZReader::open(archive, sevenz_rust::Password::empty())?;
+    let mut f = Some(f);
+    let mut result = None;
+    // Solid blocks decompress in order, so earlier members are read through
+    reader.for_each_entries(|entry, data| {
+        if entry.name() != name {
+            return Ok(true);
+        }
+        if let Some(f) = f.take() {
+            result = Some(f(data));
+        }
+        Ok(false)
+    })?;
+    result.transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn archive_entry(path: &Path) -> FileEntry {
+        FileEntry::new(path.to_path_buf(), &std::fs::metadata(path).unwrap())
+    }
+
+    #[test]
+    fn test_zip_members() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("backup.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        zip.add_directory("docs/", options).unwrap();
+        zip.start_file("docs/taxes_2020.pdf", options).unwrap();
+        zip.write_all(b"%PDF-1.4 taxes").unwrap();
+        zip.start_file("../escape.txt", options).unwrap();
+        zip.write_all(b"climbing").unwrap();
+        zip.finish().unwrap();
+
+        let members = member_entries(&archive_entry(&path)).unwrap();
+        assert_eq!(members.len(), 2);
+        let taxes = &members[0];
+        assert_eq!(taxes.path, path.join("docs/taxes_2020.pdf"));
+        assert_eq!(taxes.name(), "taxes_2020.pdf");
+        assert_eq!(taxes.file_type, FileType::Document);
+        assert_eq!(taxes.size, 14);
+        assert!(is_archive_member(taxes));
+        assert_eq!(members[1].path, path.join("escape.txt"));
+
+        let member = ArchiveMember::of(taxes).unwrap();
+        let mut contents = Vec::new();
+        member.read(|r| Ok(r.read_to_end(&mut contents)?)).unwrap();
+        assert_eq!(contents, b"%PDF-1.4 taxes");
+    }
+
+    #[test]
+    fn test_tar_gz_members() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("home.tar.gz");
+        let gz = flate2::write::GzEncoder::new(
+            File::create(&path).unwrap(),
+            flate2::Compression::default(),
+        );
+        let mut tar = tar::Builder::new(gz);
+        let mut header = tar::Header::new_gnu();
+        header.set_size(5);
+        header.set_mode(0o644);
+        header.set_mtime(1_600_000_000);
+        tar.append_data(&mut header, "photos/IMG_0001.JPG", &b"jpeg!"[..])
+            .unwrap();
+        tar.into_inner().unwrap().finish().unwrap();
+
+        let members = member_entries(&archive_entry(&path)).unwrap();
+        assert_eq!(members.len(), 1);
+        assert_eq!(members[0].file_type, FileType::Image);
+        assert_eq!(
+            members[0].modified,
+            DateTime::from_timestamp(1_600_000_000, 0)
+        );
+
+        let member = ArchiveMember::of(&members[0]).unwrap();
+        let contents = member
+            .read(|r| {
+                let mut s = String::new();
+                r.read_to_string(&mut s)?;
+                Ok(s)
+            })
+            .unwrap();
+        assert_eq!(contents, "jpeg!");
+
+        let missing = ArchiveMember {
+            name: "nope.txt".into(),
+            ..member
+        };
+        assert!(missing.read(|_| Ok(())).is_err());
+    }
+}
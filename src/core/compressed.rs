@@ -0,0 +1,203 @@
+//! Single-file compression
+//!
+//! Rotated logs and database dumps turn up as `syslog.2.gz`,
+//! `access.log.xz` or `dump.sql.zst`. [`CompressedFormat`] recognizes gzip,
+//! bzip2, xz and zstd files and decompresses them as a stream, so export
+//! and preview can hand out the file inside. Gzip is decoded in-process;
+//! bzip2, xz and zstd go through the `bzip2`, `xz` and `zstd` tools, which
+//! must be on PATH.
+
+use std::ffi::{OsStr, OsString};
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+use std::process::{Child, ChildStdout, Command, Stdio};
+
+use anyhow::{Context, Result};
+
+/// Compression formats that can be decompressed on the fly
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressedFormat {
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+}
+
+impl CompressedFormat {
+    /// Format of `path`, from its extension, if its first bytes agree
+    pub fn detect(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        let format = match extension.as_str() {
+            "gz" | "tgz" => Self::Gzip,
+            "bz2" | "tbz2" | "tbz" => Self::Bzip2,
+            "xz" | "txz" => Self::Xz,
+            "zst" | "tzst" => Self::Zstd,
+            _ => return None,
+        };
+        // A renamed file must not be fed to a decompressor
+        let mut magic = [0u8; 6];
+        File::open(path).ok()?.read_exact(&mut magic).ok()?;
+        magic.starts_with(format.magic()).then_some(format)
+    }
+
+    /// Name used in manifests and previews
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Bzip2 => "bzip2",
+            Self::Xz => "xz",
+            Self::Zstd => "zstd",
+        }
+    }
+
+    fn magic(self) -> &'static [u8] {
+        match self {
+            Self::Gzip => &[0x1f, 0x8b],
+            Self::Bzip2 => b"BZh",
+            Self::Xz => &[0xfd, b'7', b'z', b'X', b'Z', 0x00],
+            Self::Zstd => &[0x28, 0xb5, 0x2f, 0xfd],
+        }
+    }
+
+    /// Reader of the decompressed contents of `path`. Corrupt or truncated
+    /// data is a read error, not a short read.
+    pub fn open(self, path: &Path) -> Result<Box<dyn Read + Send>> {
+        if self == Self::Gzip {
+            let file =
+                File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+            // Concatenated members (`cat a.gz b.gz`) are one stream
+            return Ok(Box::new(flate2::read::MultiGzDecoder::new(BufReader::new(
+                file,
+            ))));
+        }
+        let tool = self.name();
+        let mut child = Command::new(tool)
+            .arg("-dc")
+            .arg(path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .with_context(|| {
+                format!(
+                    "The {} tool is needed to decompress {}",
+                    tool,
+                    path.display()
+                )
+            })?;
+        let stdout = child.stdout.take().expect("stdout is piped");
+        Ok(Box::new(ToolReader {
+            tool,
+            child,
+            stdout,
+        }))
+    }
+}
+
+/// Name of the file inside a compressed file: `syslog.2.gz` is `syslog.2`,
+/// `backup.tgz` is `backup.tar`
+pub fn decompressed_name(name: &OsStr) -> OsString {
+    let path = Path::new(name);
+    let (Some(stem), Some(extension)) = (path.file_stem(), path.extension()) else {
+        return name.to_os_string();
+    };
+    let mut inner = stem.to_os_string();
+    if extension.to_string_lossy().to_lowercase().starts_with('t') {
+        inner.push(".tar");
+    }
+    inner
+}
+
+/// Output of a decompressor process; its exit status is checked at the end
+struct ToolReader {
+    tool: &'static str,
+    child: Child,
+    stdout: ChildStdout,
+}
+
+impl Read for ToolReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.stdout.read(buf)?;
+        if n == 0 && !buf.is_empty() {
+            let status = self.child.wait()?;
+            if !status.success() {
+                return Err(io::Error::other(format!(
+                    "{} could not decompress the file ({})",
+                    self.tool, status
+                )));
+            }
+        }
+        Ok(n)
+    }
+}
+
+impl Drop for ToolReader {
+    fn drop(&mut self) {
+        // A reader dropped part way must not leave the process behind
+        if let Ok(None) = self.child.try_wait() {
+            self.child.kill().ok();
+            self.child.wait().ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_gzip_stream() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("syslog.2.gz");
+        let mut gz = flate2::write::GzEncoder::new(
+            File::create(&path).unwrap(),
+            flate2::Compression::default(),
+        );
+        gz.write_all(b"Oct 18 03:14:07 host sshd[42]: Accepted\n")
+            .unwrap();
+        gz.finish().unwrap();
+
+        let format = CompressedFormat::detect(&path).unwrap();
+        assert_eq!(format, CompressedFormat::Gzip);
+        let mut contents = String::new();
+        format
+            .open(&path)
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "Oct 18 03:14:07 host sshd[42]: Accepted\n");
+
+        // Truncated data fails instead of reading short
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &bytes[..bytes.len() - 12]).unwrap();
+        let mut contents = Vec::new();
+        assert!(format
+            .open(&path)
+            .unwrap()
+            .read_to_end(&mut contents)
+            .is_err());
+    }
+
+    #[test]
+    fn test_detect_checks_magic() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.xz");
+        std::fs::write(&path, "not compressed at all").unwrap();
+        assert_eq!(CompressedFormat::detect(&path), None);
+        assert_eq!(
+            CompressedFormat::detect(&dir.path().join("missing.gz")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_decompressed_name() {
+        let name = |n: &str| decompressed_name(OsStr::new(n));
+        assert_eq!(name("syslog.2.gz"), "syslog.2");
+        assert_eq!(name("dump.sql.zst"), "dump.sql");
+        assert_eq!(name("backup.tgz"), "backup.tar");
+        assert_eq!(name("home.tar.xz"), "home.tar");
+    }
+}
@@ -0,0 +1,193 @@
+//! Content search - grep inside indexed documents
+//!
+//! `search --content` matches the pattern against file contents instead of
+//! names. Plain text and code are searched byte-for-byte, so reported offsets
+//! point into the file itself; PDFs and Office documents go through the swarm
+//! chunker's text extraction and offsets point into the extracted text.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+use regex::bytes::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use super::FileEntry;
+use crate::cli::SearchType;
+use crate::swarm::{extract_document_text, MediaType};
+
+/// Plain text files larger than this are skipped
+pub const MAX_TEXT_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Matches reported per file before moving on to the next one
+pub const MAX_MATCHES_PER_FILE: usize = 10;
+
+/// Bytes of context shown on each side of a match
+const SNIPPET_CONTEXT: usize = 60;
+
+/// Extensions searched besides what the chunker classifies as text or code
+const DOCUMENT_EXTENSIONS: &[&str] = &["rtf", "htm", "eml", "ini", "cfg"];
+
+/// A pattern match inside a file's content
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContentMatch {
+    pub path: PathBuf,
+    /// Byte offset of the match (into the extracted text when `extracted`)
+    pub offset: u64,
+    /// 1-based line number
+    pub line: usize,
+    /// The match with surrounding context, on a single line
+    pub snippet: String,
+    /// Whether the text was extracted from a PDF/Office document
+    pub extracted: bool,
+}
+
+/// Build the content matcher for a search pattern.
+///
+/// Regex searches use the pattern as written. Other search types match the
+/// pattern as a case-insensitive phrase (case-sensitive for `exact`) where
+/// any run of whitespace matches any other, since extracted PDF text breaks
+/// lines in arbitrary places.
+pub fn content_regex(pattern: &str, search_type: SearchType) -> Result<Regex> {
+    let (source, case_insensitive) = match search_type {
+        SearchType::Regex => (pattern.to_string(), false),
+        SearchType::Exact => (phrase_regex(pattern), false),
+        SearchType::Fuzzy | SearchType::Glob => (phrase_regex(pattern), true),
+    };
+    RegexBuilder::new(&source)
+        .case_insensitive(case_insensitive)
+        .build()
+        .with_context(|| format!("Invalid content search pattern: {}", pattern))
+}
+
+fn phrase_regex(pattern: &str) -> String {
+    pattern
+        .split_whitespace()
+        .map(regex::escape)
+        .collect::<Vec<_>>()
+        .join(r"\s+")
+}
+
+/// Whether an index entry is worth searching for text
+pub fn is_searchable(entry: &FileEntry) -> bool {
+    matches!(
+        MediaType::from_extension(&entry.extension),
+        MediaType::Text
+            | MediaType::Markdown
+            | MediaType::Code
+            | MediaType::Pdf
+            | MediaType::Document
+    ) || DOCUMENT_EXTENSIONS.contains(&entry.extension.as_str())
+}
+
+/// Search one file, returning at most `max_matches` matches
+pub fn search_file(path: &Path, regex: &Regex, max_matches: usize) -> Result<Vec<ContentMatch>> {
+    let (bytes, extracted) = match extract_document_text(path) {
+        Some(text) => (text?.into_bytes(), true),
+        None => {
+            let size = std::fs::metadata(path)
+                .with_context(|| format!("Failed to stat {}", path.display()))?
+                .len();
+            if size > MAX_TEXT_BYTES {
+                anyhow::bail!("{} is too large to search", path.display());
+            }
+            let bytes = std::fs::read(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            (bytes, false)
+        }
+    };
+
+    let mut matches = Vec::new();
+    let mut line = 1;
+    let mut counted_to = 0;
+    for m in regex.find_iter(&bytes).take(max_matches) {
+        line += memchr::memchr_iter(b'\n', &bytes[counted_to..m.start()]).count();
+        counted_to = m.start();
+        matches.push(ContentMatch {
+            path: path.to_path_buf(),
+            offset: m.start() as u64,
+            line,
+            snippet: snippet(&bytes, m.start(), m.end()),
+            extracted,
+        });
+    }
+    Ok(matches)
+}
+
+/// Search many files in parallel. Unreadable files are skipped; results are
+/// ordered by path and offset and capped at `limit`.
+pub fn search_entries(entries: &[FileEntry], regex: &Regex, limit: usize) -> Vec<ContentMatch> {
+    let mut matches: Vec<ContentMatch> = entries
+        .par_iter()
+        .filter(|entry| is_searchable(entry))
+        .flat_map_iter(|entry| {
+            search_file(&entry.path, regex, MAX_MATCHES_PER_FILE).unwrap_or_else(|e| {
+                tracing::debug!("Content search skipped {}: {:#}", entry.path.display(), e);
+                Vec::new()
+            })
+        })
+        .collect();
+    matches.sort_by(|a, b| (&a.path, a.offset).cmp(&(&b.path, b.offset)));
+    matches.truncate(limit);
+    matches
+}
+
+/// The match plus some context, with whitespace collapsed to single spaces
+fn snippet(bytes: &[u8], start: usize, end: usize) -> String {
+    let mut from = start.saturating_sub(SNIPPET_CONTEXT);
+    let mut to = (end + SNIPPET_CONTEXT).min(bytes.len());
+    // Don't cut a UTF-8 sequence in half
+    while from > 0 && (bytes[from] & 0xC0) == 0x80 {
+        from -= 1;
+    }
+    while to < bytes.len() && (bytes[to] & 0xC0) == 0x80 {
+        to += 1;
+    }
+    let text = String::from_utf8_lossy(&bytes[from..to]);
+    let collapsed = text
+        .split(|c: char| c.is_whitespace() || c.is_control())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!(
+        "{}{}{}",
+        if from > 0 { "…" } else { "" },
+        collapsed,
+        if to < bytes.len() { "…" } else { "" }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_search_file_offsets_and_snippets() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("lease.txt");
+        std::fs::write(
+            &path,
+            "Residential lease\nThis TENANCY\n  agreement is made between\nthe parties.\n",
+        )
+        .unwrap();
+
+        let regex = content_regex("tenancy agreement", SearchType::Fuzzy).unwrap();
+        let matches = search_file(&path, &regex, MAX_MATCHES_PER_FILE).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].offset, 23);
+        assert_eq!(matches[0].line, 2);
+        assert!(!matches[0].extracted);
+        assert_eq!(
+            matches[0].snippet,
+            "Residential lease This TENANCY agreement is made between the parties."
+        );
+
+        let exact = content_regex("tenancy agreement", SearchType::Exact).unwrap();
+        assert!(search_file(&path, &exact, 10).unwrap().is_empty());
+
+        let regex = content_regex(r"(?i)made\s+\w+", SearchType::Regex).unwrap();
+        let matches = search_file(&path, &regex, 10).unwrap();
+        assert_eq!(matches[0].line, 3);
+    }
+}
@@ -2,9 +2,11 @@
 //!
 //! Provides high-level API for indexing, searching, and exporting.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Instant;
 
 use anyhow::{Context, Result};
 use chrono::Utc;
@@ -12,14 +14,35 @@ use parking_lot::RwLock;
 use rayon::prelude::*;
 use tokio::sync::mpsc;
 
-use super::index::{FileEntry, FileIndex, IndexStats};
+use super::fingerprint::{FingerprintRegistry, SourceFingerprint};
+use super::hashset::{HashAlgorithm, HashSetCounts, HashSets};
+use super::index::{FileEntry, FileIndex, IndexChanges, IndexStats};
+use super::names::NameRepair;
 use super::scanner::{ScanOptions, Scanner};
-use super::{FileType, Progress};
+use super::selection::Selection;
+use super::{
+    decompressed_name, is_archive_member, is_snapshot_file, CompressedFormat, FileType, Progress,
+};
 use crate::checkpoint::{Checkpoint, CheckpointManager, CheckpointPhase};
-use crate::cli::IndexArgs;
-use crate::export::{ExportOptions, ExportResult, Exporter};
+use crate::cli::{IndexArgs, ReportFormat};
+use crate::diskimage::unlock;
+use crate::error::DrillResult;
+use crate::events::{self, Event};
+use crate::export::{
+    ArchiveFormat, ClamAv, CollisionPolicy, DetectionAction, ExportHook, ExportOptions, ExportPlan,
+    ExportResult, Exporter, OrganizeBy, RemoteTarget, SpaceStatus, PROOF_MANIFEST_FILE,
+    QUARANTINE_DIR,
+};
+use crate::iobackend::IoOptions;
 use crate::preview::ThumbnailGenerator;
 
+/// Files indexed between [`Event::Scan`] events
+const SCAN_EVENT_EVERY: usize = 64;
+
+/// Files buffered before they are written to a `--sqlite` database. A
+/// multiple of [`SCAN_EVENT_EVERY`], so a flush never drops the final scan event
+const SQLITE_BATCH: usize = SCAN_EVENT_EVERY * 128;
+
 /// The main Diamond Drill engine
 pub struct DrillEngine {
     /// Source path being indexed
@@ -32,11 +55,15 @@ pub struct DrillEngine {
     bad_sectors: Arc<RwLock<Vec<super::BadSector>>>,
     /// Index statistics
     stats: Arc<RwLock<IndexStats>>,
+    /// Changes found by the last incremental index run
+    last_changes: Arc<RwLock<Option<IndexChanges>>>,
+    /// Hash set matches of the last index/search/export run
+    last_hashset_counts: Arc<RwLock<Option<HashSetCounts>>>,
 }
 
 impl DrillEngine {
     /// Create a new engine for the given source path
-    pub async fn new(source: PathBuf) -> Result<Self> {
+    pub async fn new(source: PathBuf) -> DrillResult<Self> {
         let source = source
             .canonicalize()
             .with_context(|| format!("Failed to resolve path: {}", source.display()))?;
@@ -47,28 +74,56 @@ impl DrillEngine {
             thumbnail_gen: Arc::new(ThumbnailGenerator::new()),
             bad_sectors: Arc::new(RwLock::new(Vec::new())),
             stats: Arc::new(RwLock::new(IndexStats::default())),
+            last_changes: Arc::new(RwLock::new(None)),
+            last_hashset_counts: Arc::new(RwLock::new(None)),
         })
     }
 
     /// Load existing index or create new engine
-    pub async fn load_or_create(source: &Path) -> Result<Self> {
+    ///
+    /// When there is no index for this path, the source fingerprint is looked
+    /// up so an index written while the same disk was attached elsewhere is
+    /// reused. A warning is logged if the source changed since it was indexed.
+    pub async fn load_or_create(source: &Path) -> DrillResult<Self> {
+        let fingerprint = match SourceFingerprint::compute(source) {
+            Ok(fp) => Some(fp),
+            Err(e) => {
+                tracing::debug!("Could not fingerprint {}: {}", source.display(), e);
+                None
+            }
+        };
+
         // Try to load existing index
         let index_path = Self::get_index_path(source);
         if index_path.exists() {
             if let Ok(index) = FileIndex::load(&index_path).await {
-                // Reconstruct stats from loaded index
-                let stats = index.stats();
-
-                // Extract bad sectors from the loaded index
-                let bad_sectors = index.bad_sectors().to_vec();
-
-                return Ok(Self {
-                    source: source.to_path_buf(),
-                    index: Arc::new(RwLock::new(index)),
-                    thumbnail_gen: Arc::new(ThumbnailGenerator::new()),
-                    bad_sectors: Arc::new(RwLock::new(bad_sectors)),
-                    stats: Arc::new(RwLock::new(stats)),
-                });
+                if let (Some(stored), Some(current)) = (index.fingerprint(), &fingerprint) {
+                    warn_if_changed(source, stored, current);
+                }
+                return Ok(Self::from_index(source, index));
+            }
+        }
+
+        if let Some(current) = &fingerprint {
+            if let Some(mut index) = Self::load_by_fingerprint(current).await {
+                tracing::warn!(
+                    "{} was indexed before as {}; reusing that index",
+                    source.display(),
+                    index.source().display()
+                );
+                if let Some(stored) = index.fingerprint() {
+                    warn_if_changed(source, stored, current);
+                }
+                index.rebase(source);
+                if let Some(parent) = index_path.parent() {
+                    tokio::fs::create_dir_all(parent).await.ok();
+                }
+                if let Err(e) = index.save(&index_path).await {
+                    tracing::warn!("Failed to save rebased index: {}", e);
+                } else {
+                    record_fingerprint(current.clone(), source, &index_path);
+                }
+                return Ok(Self::from_index(source, index));
             }
         }
 
@@ -76,6 +131,44 @@ impl DrillEngine {
         Self::new(source.to_path_buf()).await
     }
 
+    fn from_index(source: &Path, index: FileIndex) -> Self {
+        // Reconstruct stats from loaded index
+        let stats = index.stats();
+
+        // Extract bad sectors from the loaded index
+        let bad_sectors = index.bad_sectors().to_vec();
+
+        Self {
+            source: source.to_path_buf(),
+            index: Arc::new(RwLock::new(index)),
+            thumbnail_gen: Arc::new(ThumbnailGenerator::new()),
+            bad_sectors: Arc::new(RwLock::new(bad_sectors)),
+            stats: Arc::new(RwLock::new(stats)),
+            last_changes: Arc::new(RwLock::new(None)),
+            last_hashset_counts: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    /// Load the most recent index recorded for a source with this fingerprint
+    async fn load_by_fingerprint(fingerprint: &SourceFingerprint) -> Option<FileIndex> {
+        let registry = FingerprintRegistry::load(&FingerprintRegistry::default_path())
+            .map_err(|e| tracing::warn!("{:#}", e))
+            .ok()?;
+        let record = registry.find(fingerprint)?;
+        match FileIndex::load(&record.index_path).await {
+            Ok(index) => Some(index),
+            Err(e) => {
+                tracing::warn!(
+                    "Could not load index {} for fingerprint {}: {}",
+                    record.index_path.display(),
+                    fingerprint.id,
+                    e
+                );
+                None
+            }
+        }
+    }
+
     /// Get the default index path for a source
     /// Get the default index path for a given source
     pub fn get_index_path(source: &Path) -> PathBuf {
@@ -88,7 +181,7 @@ impl DrillEngine {
     }
 
     /// Index with progress reporting
-    pub async fn index_with_progress(&self, args: &IndexArgs) -> Result<()> {
+    pub async fn index_with_progress(&self, args: &IndexArgs) -> DrillResult<()> {
         self.index_with_live_progress(args, |_, _| {}).await
     }
 
@@ -98,10 +191,16 @@ impl DrillEngine {
         &self,
         args: &IndexArgs,
         mut on_file: F,
-    ) -> Result<()>
+    ) -> DrillResult<()>
     where
         F: FnMut(usize, &FileEntry),
     {
+        let previous = if args.incremental {
+            self.load_previous_index(args).await
+        } else {
+            None
+        };
+
         let options = ScanOptions {
             source: args.source.clone(),
             skip_hidden: args.skip_hidden,
@@ -109,8 +208,24 @@ impl DrillEngine {
             extensions: args.extensions.clone(),
             workers: args.workers.unwrap_or_else(num_cpus::get),
             same_file_system: false,
+            previous: previous.as_ref().map(|(entries, _)| Arc::clone(entries)),
+            verify_hash: args.verify_hash,
+            extract_metadata: args.metadata,
+            repair_names: args
+                .repair_names
+                .as_deref()
+                .map(NameRepair::new)
+                .transpose()?,
+            links: args.links.policy(),
+            all_hardlinks: args.all_hardlinks,
+            archives: args.archives,
+            snapshots: args.snapshots,
         };
 
+        let fingerprint = SourceFingerprint::compute(&args.source)
+            .map_err(|e| tracing::debug!("Could not fingerprint {}: {}", args.source.display(), e))
+            .ok();
+
         // Load checkpoint if resuming
         let checkpoint_mgr = CheckpointManager::new();
         let mut checkpoint = if args.resume {
@@ -120,6 +235,15 @@ impl DrillEngine {
                         "Resuming from checkpoint: {} files already indexed",
                         cp.processed_count()
                     );
+                    if let (Some(stored), Some(current)) = (&cp.source_fingerprint, &fingerprint) {
+                        if stored.content_changed(current) {
+                            tracing::warn!(
+                                "{} has changed since the checkpoint was written; \
+                                 files indexed before the change are not rescanned",
+                                args.source.display()
+                            );
+                        }
+                    }
                     cp
                 }
                 None => Checkpoint::new(
@@ -136,6 +260,27 @@ impl DrillEngine {
             )
         };
 
+        checkpoint.source_fingerprint = fingerprint.clone();
+
+        let prep = EntryPrep {
+            hashsets: if args.hashset.is_empty() {
+                None
+            } else {
+                Some(Arc::new(HashSets::load(&args.hashset)?))
+            },
+            hashset_counts: Arc::clone(&self.last_hashset_counts),
+            thumbnails: args.thumbnails.then(|| Arc::clone(&self.thumbnail_gen)),
+        };
+        *self.last_hashset_counts.write() = None;
+
+        // With --sqlite, entries go to the database in batches instead of
+        // the in-memory index. A resumed run adds to what is already there.
+        let mut sink = args
+            .sqlite
+            .as_deref()
+            .map(|db| SqliteSink::open(db, &args.source, !args.resume, prep.clone()))
+            .transpose()?;
+
         let scanner = Scanner::new(options);
         let (tx, mut rx) = mpsc::channel::<FileEntry>(1000);
 
@@ -147,6 +292,8 @@ impl DrillEngine {
 
         // Collect results, skipping already-processed entries on resume
         let mut entries = Vec::new();
+        let mut files = 0;
+        let mut bytes = 0;
         while let Some(entry) = rx.recv().await {
             let path_str = entry.path.to_string_lossy().to_string();
             if checkpoint.is_already_processed(&path_str) {
@@ -160,9 +307,30 @@ impl DrillEngine {
             }
 
             // Fire live progress callback
-            on_file(entries.len() + 1, &entry);
+            files += 1;
+            bytes += entry.size;
+            on_file(files, &entry);
+            if files % SCAN_EVENT_EVERY == 0 {
+                events::emit(Event::Scan {
+                    source: args.source.clone(),
+                    files,
+                    bytes,
+                    path: entry.path.clone(),
+                });
+            }
 
             entries.push(entry);
+            if let Some(full) = sink.take_if(|_| entries.len() == SQLITE_BATCH) {
+                sink = Some(full.write(std::mem::take(&mut entries)).await?);
+            }
+        }
+        if let Some(last) = entries.last().filter(|_| files % SCAN_EVENT_EVERY != 0) {
+            events::emit(Event::Scan {
+                source: args.source.clone(),
+                files,
+                bytes,
+                path: last.path.clone(),
+            });
         }
 
         // Wait for scanner to complete
@@ -171,12 +339,54 @@ impl DrillEngine {
             .context("Scanner task panicked")?
             .context("Scanner failed")?;
 
+        // Carry over bad sectors recorded for files reused from the previous index
+        if let Some((ref prev_entries, ref prev_bad)) = previous {
+            let changes = IndexChanges::between(prev_entries, &entries);
+            tracing::info!(
+                "Incremental index: {} added, {} modified, {} removed, {} unchanged",
+                changes.added,
+                changes.modified,
+                changes.removed,
+                changes.unchanged
+            );
+
+            let reused: HashSet<&Path> = entries
+                .iter()
+                .filter(|e| {
+                    prev_entries
+                        .get(e.path.to_string_lossy().as_ref())
+                        .is_some_and(|prev| prev.same_version(e))
+                })
+                .map(|e| e.path.as_path())
+                .collect();
+            self.bad_sectors.write().extend(
+                prev_bad
+                    .iter()
+                    .filter(|b| reused.contains(b.file_path.as_path()))
+                    .cloned(),
+            );
+
+            *self.last_changes.write() = Some(changes);
+        }
+
         // Update index
-        {
-            let mut index = self.index.write();
-            for entry in entries {
-                index.add_entry(entry);
+        let sink = match sink {
+            Some(sink) => Some(sink.write(entries).await?),
+            None => {
+                let entries = prep.apply(entries);
+                let mut index = self.index.write();
+                for entry in entries {
+                    index.add_entry(entry);
+                }
+                None
             }
+        };
+        if let Some(counts) = *self.last_hashset_counts.read() {
+            tracing::info!(
+                "Hash sets: {} known-good filtered, {} known-bad flagged",
+                counts.known_good,
+                counts.known_bad
+            );
         }
 
         // Replace bad sectors in the index for persistence (not extend, to avoid duplicates)
@@ -184,47 +394,68 @@ impl DrillEngine {
             let mut index = self.index.write();
             let bad_sectors = self.bad_sectors.read().clone();
             index.set_bad_sectors(bad_sectors);
+            if let Some(fp) = &fingerprint {
+                index.set_fingerprint(fp.clone());
+            }
         }
 
         // Update stats
         {
             let mut stats = self.stats.write();
-            stats.total_files = self.index.read().len();
-            stats.total_bytes = self.index.read().total_bytes();
+            (stats.total_files, stats.total_bytes) = match &sink {
+                Some(sink) => (sink.files, sink.bytes),
+                None => (self.index.read().len(), self.index.read().total_bytes()),
+            };
             stats.indexed_at = Some(Utc::now());
             stats.scan_duration_ms = scan_stats.duration_ms;
             stats.bad_sector_count = self.bad_sectors.read().len();
+            stats.symlinks = scan_stats.symlinks;
+            stats.link_loops = scan_stats.link_loops;
+            stats.links_outside_source = scan_stats.links_outside_source;
+            stats.hardlinks_skipped = scan_stats.hardlinks_skipped;
+            stats.archive_members = scan_stats.archive_members;
+            stats.snapshot_files = scan_stats.snapshot_files;
         }
 
-        // Generate thumbnails if requested
-        if args.thumbnails {
-            self.generate_thumbnails_parallel().await?;
-        }
-
-        // Save index (now includes bad_sectors)
+        // Save index (now includes bad_sectors); the database is the index
+        // of a --sqlite run
         // Clone index data before await to avoid holding lock across await point
-        if let Some(ref index_path) = args.index_file {
-            let index_data = bincode::serialize(&*self.index.read())
-                .context("Failed to serialize index")?;
-            let path = index_path.clone();
-            tokio::task::spawn_blocking(move || std::fs::write(&path, index_data))
-                .await
-                .context("Index save task panicked")?
-                .with_context(|| format!("Failed to write index to {}", index_path.display()))?;
-        } else {
-            let default_path = Self::get_index_path(&args.source);
-            if let Some(parent) = default_path.parent() {
-                tokio::fs::create_dir_all(parent)
+        if sink.is_none() {
+            if let Some(ref index_path) = args.index_file {
+                let index_data =
+                    bincode::serialize(&*self.index.read()).context("Failed to serialize index")?;
+                let path = index_path.clone();
+                tokio::task::spawn_blocking(move || std::fs::write(&path, index_data))
+                    .await
+                    .context("Index save task panicked")?
+                    .with_context(|| {
+                        format!("Failed to write index to {}", index_path.display())
+                    })?;
+            } else {
+                let default_path = Self::get_index_path(&args.source);
+                if let Some(parent) = default_path.parent() {
+                    tokio::fs::create_dir_all(parent).await.with_context(|| {
+                        format!("Failed to create index directory: {}", parent.display())
+                    })?;
+                }
+                let index_data =
+                    bincode::serialize(&*self.index.read()).context("Failed to serialize index")?;
+                let path = default_path.clone();
+                tokio::task::spawn_blocking(move || std::fs::write(&path, index_data))
                     .await
-                    .with_context(|| format!("Failed to create index directory: {}", parent.display()))?;
+                    .context("Index save task panicked")?
+                    .with_context(|| {
+                        format!("Failed to write index to {}", default_path.display())
+                    })?;
+            }
+
+            if let Some(fp) = fingerprint {
+                let index_path = args
+                    .index_file
+                    .clone()
+                    .unwrap_or_else(|| Self::get_index_path(&args.source));
+                record_fingerprint(fp, &args.source, &index_path);
             }
-            let index_data = bincode::serialize(&*self.index.read())
-                .context("Failed to serialize index")?;
-            let path = default_path.clone();
-            tokio::task::spawn_blocking(move || std::fs::write(&path, index_data))
-                .await
-                .context("Index save task panicked")?
-                .with_context(|| format!("Failed to write index to {}", default_path.display()))?;
         }
 
         // Clear checkpoint on success
@@ -233,6 +464,157 @@ impl DrillEngine {
         Ok(())
     }
 
+    /// Load the entries and bad sectors of the index a previous run wrote for
+    /// `args`. Falls back to a full scan (None) if there is none or it can't be read.
+    async fn load_previous_index(
+        &self,
+        args: &IndexArgs,
+    ) -> Option<(Arc<HashMap<String, FileEntry>>, Vec<super::BadSector>)> {
+        let index_path = args
+            .index_file
+            .clone()
+            .unwrap_or_else(|| Self::get_index_path(&args.source));
+        if !index_path.exists() {
+            tracing::info!("No previous index at {}, doing a full scan", index_path.display());
+            return None;
+        }
+
+        match FileIndex::load(&index_path).await {
+            Ok(index) => {
+                let entries = index
+                    .entries()
+                    .map(|e| (e.path.to_string_lossy().to_string(), e.clone()))
+                    .collect();
+                Some((Arc::new(entries), index.bad_sectors().to_vec()))
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "Could not load previous index {}: {}; doing a full scan",
+                    index_path.display(),
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    /// Add (or refresh) single files in the index, hashing each in full and
+    /// generating thumbnails for images if asked, then save the index.
+    /// Files that disappear before they can be read are skipped; unreadable
+    /// ones are indexed and flagged like a scan would.
+    pub async fn index_files(&self, paths: &[PathBuf], thumbnails: bool) -> Result<Vec<FileEntry>> {
+        let thumbnail_gen = Arc::clone(&self.thumbnail_gen);
+        let paths = paths.to_vec();
+        let (entries, bad): (Vec<FileEntry>, Vec<super::BadSector>) =
+            tokio::task::spawn_blocking(move || {
+                let mut entries = Vec::new();
+                let mut bad = Vec::new();
+                for path in paths {
+                    let Ok(metadata) = std::fs::metadata(&path) else {
+                        continue;
+                    };
+                    if !metadata.is_file() {
+                        continue;
+                    }
+                    let mut entry = FileEntry::new(path, &metadata);
+                    match crate::dedup::hash_file(&entry.path) {
+                        Ok(hash) => entry.hash = Some(hash),
+                        Err(e) => {
+                            entry.has_bad_sectors = true;
+                            bad.push(super::BadSector {
+                                file_path: entry.path.clone(),
+                                offset: 0,
+                                length: entry.size,
+                                error: e.to_string(),
+                                detected_at: Utc::now(),
+                                retry_count: 0,
+                                block_size: 4096,
+                            });
+                        }
+                    }
+                    if thumbnails && entry.file_type == FileType::Image && !entry.has_bad_sectors {
+                        match thumbnail_gen.generate_progressive(&entry.path, 64, 512) {
+                            Ok(thumb) => entry.thumbnail = Some(thumb),
+                            Err(e) => tracing::warn!(
+                                "Failed to generate thumbnail for {}: {}",
+                                entry.path.display(),
+                                e
+                            ),
+                        }
+                    }
+                    entries.push(entry);
+                }
+                (entries, bad)
+            })
+            .await
+            .context("Indexing task panicked")?;
+
+        {
+            let mut index = self.index.write();
+            for entry in &entries {
+                index.add_entry(entry.clone());
+            }
+            self.bad_sectors.write().extend(bad);
+            index.set_bad_sectors(self.bad_sectors.read().clone());
+        }
+        {
+            let mut stats = self.stats.write();
+            stats.total_files = self.index.read().len();
+            stats.total_bytes = self.index.read().total_bytes();
+            stats.indexed_at = Some(Utc::now());
+            stats.bad_sector_count = self.bad_sectors.read().len();
+        }
+        self.save_index().await?;
+        Ok(entries)
+    }
+
+    /// Write the index to the default location for the source
+    pub async fn save_index(&self) -> Result<()> {
+        let path = Self::get_index_path(&self.source);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await.with_context(|| {
+                format!("Failed to create index directory: {}", parent.display())
+            })?;
+        }
+        let index_data =
+            bincode::serialize(&*self.index.read()).context("Failed to serialize index")?;
+        let target = path.clone();
+        tokio::task::spawn_blocking(move || std::fs::write(&target, index_data))
+            .await
+            .context("Index save task panicked")?
+            .with_context(|| format!("Failed to write index to {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Changes found by the last `--incremental` index run, if any
+    pub async fn last_changes(&self) -> Option<IndexChanges> {
+        self.last_changes.read().clone()
+    }
+
+    /// Hash set matches of the last run given `--hashset`, if any
+    pub async fn last_hashset_counts(&self) -> Option<HashSetCounts> {
+        *self.last_hashset_counts.read()
+    }
+
+    /// Drop known-good entries and flag known-bad ones using the `--hashset`
+    /// lists, remembering the match counts
+    fn apply_hashsets(&self, specs: &[String], entries: Vec<FileEntry>) -> Result<Vec<FileEntry>> {
+        if specs.is_empty() {
+            return Ok(entries);
+        }
+        let sets = HashSets::load(specs)?;
+        *self.last_hashset_counts.write() = None;
+        let entries = add_hashset_matches(&sets, &self.last_hashset_counts, entries);
+        if let Some(counts) = *self.last_hashset_counts.read() {
+            tracing::info!(
+                "Hash sets: {} known-good filtered, {} known-bad flagged",
+                counts.known_good,
+                counts.known_bad
+            );
+        }
+        Ok(entries)
+    }
+
     /// Get total file count
     pub async fn file_count(&self) -> usize {
         self.index.read().len()
@@ -248,6 +630,11 @@ impl DrillEngine {
         self.bad_sectors.read().len()
     }
 
+    /// Statistics of the index, with the scan counts of the last index run
+    pub async fn stats(&self) -> IndexStats {
+        self.stats.read().clone()
+    }
+
     /// Get all bad sectors
     pub async fn get_bad_sectors(&self) -> Vec<super::BadSector> {
         self.bad_sectors.read().clone()
@@ -286,11 +673,20 @@ impl DrillEngine {
 
     /// Fuzzy search files
     pub async fn search_fuzzy(&self, pattern: &str) -> Result<Vec<String>> {
+        self.search_fuzzy_with(pattern, false).await
+    }
+
+    /// Fuzzy search, optionally matching across scripts.
+    ///
+    /// With `translit` set, both the pattern and each filename are also
+    /// compared in transliterated form, so "dokument" finds "документ.pdf".
+    pub async fn search_fuzzy_with(&self, pattern: &str, translit: bool) -> Result<Vec<String>> {
         use fuzzy_matcher::skim::SkimMatcherV2;
         use fuzzy_matcher::FuzzyMatcher;
 
         let matcher = SkimMatcherV2::default();
         let pattern_lower = pattern.to_lowercase();
+        let pattern_latin = translit.then(|| super::transliterate(pattern));
 
         let mut matches: Vec<(i64, String)> = self
             .index
@@ -304,9 +700,12 @@ impl DrillEngine {
                     .map(|n| n.to_string_lossy().to_lowercase())
                     .unwrap_or_default();
 
-                matcher
-                    .fuzzy_match(&name, &pattern_lower)
-                    .map(|score| (score, path_str))
+                let direct = matcher.fuzzy_match(&name, &pattern_lower);
+                let latin = pattern_latin
+                    .as_ref()
+                    .and_then(|p| matcher.fuzzy_match(&super::transliterate(&name), p));
+
+                direct.max(latin).map(|score| (score, path_str))
             })
             .collect();
 
@@ -316,99 +715,81 @@ impl DrillEngine {
         Ok(matches.into_iter().map(|(_, path)| path).collect())
     }
 
-    /// Search with interactive filtering
-    pub async fn search_interactive(&self, args: &crate::cli::SearchArgs) -> Result<()> {
+    /// Search with interactive filtering; prints and returns the matches
+    pub async fn search_interactive(
+        &self,
+        args: &crate::cli::SearchArgs,
+    ) -> Result<Vec<FileEntry>> {
+        let filtered = self.search(args).await?;
+
+        for entry in &filtered {
+            match super::known_bad_set(entry) {
+                Some(set) => println!("{}  [known-bad: {}]", entry.path.display(), set),
+                None => println!("{}", entry.path.display()),
+            }
+        }
+
+        println!("\nFound {} matches", filtered.len());
+        if let Some(counts) = self.last_hashset_counts().await {
+            println!(
+                "Hash sets: {} known-good hidden, {} known-bad",
+                counts.known_good, counts.known_bad
+            );
+        }
+        Ok(filtered)
+    }
+
+    /// Run a search with all filters applied and return the matching entries
+    pub async fn search(&self, args: &crate::cli::SearchArgs) -> Result<Vec<FileEntry>> {
         let results = match args.search_type {
-            crate::cli::SearchType::Fuzzy => self.search_fuzzy(&args.pattern).await?,
+            crate::cli::SearchType::Fuzzy => {
+                self.search_fuzzy_with(&args.pattern, args.translit).await?
+            }
             crate::cli::SearchType::Glob => self.search_glob(&args.pattern).await?,
             crate::cli::SearchType::Regex => self.search_regex(&args.pattern).await?,
             crate::cli::SearchType::Exact => self.search_exact(&args.pattern).await?,
         };
 
-        // Parse size filters
-        let min_size = args.min_size.as_ref().and_then(|s| parse_size_str(s));
-        let max_size = args.max_size.as_ref().and_then(|s| parse_size_str(s));
+        let filter = SearchFilter::from_args(args);
 
-        // Parse date filters
-        let after_date = args.after.as_ref().and_then(|s| {
-            chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d")
-                .ok()
-                .and_then(|d| d.and_hms_opt(0, 0, 0))
-                .map(|dt| chrono::DateTime::<Utc>::from_naive_utc_and_offset(dt, Utc))
-        });
-        let before_date = args.before.as_ref().and_then(|s| {
-            chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d")
-                .ok()
-                .and_then(|d| d.and_hms_opt(23, 59, 59))
-                .map(|dt| chrono::DateTime::<Utc>::from_naive_utc_and_offset(dt, Utc))
-        });
+        // Apply filters against index entries
+        let filtered: Vec<FileEntry> = {
+            let index = self.index.read();
+            results
+                .into_iter()
+                .filter_map(|path| index.get_by_path(&path))
+                .filter(|entry| filter.matches(entry))
+                .cloned()
+                .collect()
+        };
+        let mut filtered = self.apply_hashsets(&args.hashset, filtered)?;
+        filtered.truncate(args.limit);
 
-        // Map CLI file type filter to core FileType
-        let type_filter = args.file_type.map(|ft| match ft {
-            crate::cli::FileTypeFilter::Image => FileType::Image,
-            crate::cli::FileTypeFilter::Video => FileType::Video,
-            crate::cli::FileTypeFilter::Audio => FileType::Audio,
-            crate::cli::FileTypeFilter::Document => FileType::Document,
-            crate::cli::FileTypeFilter::Archive => FileType::Archive,
-            crate::cli::FileTypeFilter::Code => FileType::Code,
-            crate::cli::FileTypeFilter::All => FileType::Other, // sentinel — won't filter
-        });
-        let filter_all = matches!(args.file_type, Some(crate::cli::FileTypeFilter::All) | None);
+        Ok(filtered)
+    }
 
-        // Apply filters against index entries
-        let index = self.index.read();
-        let filtered: Vec<_> = results
-            .into_iter()
-            .filter(|path| {
-                if let Some(entry) = index.get_by_path(path) {
-                    // File type filter
-                    if !filter_all {
-                        if let Some(ref ft) = type_filter {
-                            if entry.file_type != *ft {
-                                return false;
-                            }
-                        }
-                    }
-                    // Size filters
-                    if let Some(min) = min_size {
-                        if entry.size < min {
-                            return false;
-                        }
-                    }
-                    if let Some(max) = max_size {
-                        if entry.size > max {
-                            return false;
-                        }
-                    }
-                    // Date filters
-                    if let Some(ref after) = after_date {
-                        if let Some(ref modified) = entry.modified {
-                            if modified < after {
-                                return false;
-                            }
-                        }
-                    }
-                    if let Some(ref before) = before_date {
-                        if let Some(ref modified) = entry.modified {
-                            if modified > before {
-                                return false;
-                            }
-                        }
-                    }
-                    true
-                } else {
-                    true // path not in index, include anyway
-                }
-            })
-            .take(args.limit)
+    /// Search inside indexed documents (`search --content`).
+    ///
+    /// The size/date/type filters narrow the candidate files first; the
+    /// pattern is then matched against their text.
+    pub async fn search_content(
+        &self,
+        args: &crate::cli::SearchArgs,
+    ) -> Result<Vec<super::ContentMatch>> {
+        let regex = super::content_regex(&args.pattern, args.search_type)?;
+        let filter = SearchFilter::from_args(args);
+        let candidates: Vec<FileEntry> = self
+            .index
+            .read()
+            .entries()
+            .filter(|entry| filter.matches(entry) && super::is_searchable(entry))
+            .cloned()
             .collect();
+        let limit = args.limit;
 
-        for path in &filtered {
-            println!("{}", path);
-        }
-
-        println!("\nFound {} matches", filtered.len());
-        Ok(())
+        tokio::task::spawn_blocking(move || super::search_entries(&candidates, &regex, limit))
+            .await
+            .context("Content search task failed")
     }
 
     /// Glob pattern search
@@ -487,22 +868,43 @@ impl DrillEngine {
                     humansize::format_size(entry.size, humansize::BINARY),
                     entry
                         .modified
-                        .map(|d| d.format("%Y-%m-%d %H:%M:%S").to_string())
+                        .map(|d| super::format_timestamp(&d, "%Y-%m-%d %H:%M:%S %Z"))
                         .unwrap_or_else(|| "Unknown".to_string())
                 );
+                let mut metadata: Vec<_> = entry.metadata.iter().collect();
+                metadata.sort();
+                for (key, value) in metadata {
+                    println!("    {}: {}", key, value);
+                }
+                if args.decompress {
+                    if let Some(format) = CompressedFormat::detect(&entry.path) {
+                        if let Err(e) = preview_decompressed(&entry.path, format) {
+                            tracing::warn!("{:#}", e);
+                        }
+                    }
+                }
 
-                // Generate thumbnail if output dir specified and file is an image
+                // Generate thumbnail if output dir specified and file is an
+                // image, a document with a first-page preview, or audio with
+                // a waveform
                 if let Some(out_dir) = output_dir {
-                    if entry.file_type == FileType::Image {
+                    if entry.file_type == FileType::Image
+                        || crate::preview::is_document_previewable(&entry.path)
+                        || crate::preview::is_waveform_previewable(&entry.path)
+                    {
                         match self.thumbnail_gen.generate(&entry.path, thumb_size) {
                             Ok(thumb_path) => {
                                 // Copy thumbnail to output dir
                                 let dest_name = format!(
-                                    "thumb_{}_{}.jpg",
+                                    "thumb_{}_{}.{}",
                                     thumb_size,
                                     entry.path.file_stem()
                                         .map(|s| s.to_string_lossy().to_string())
-                                        .unwrap_or_else(|| "unknown".to_string())
+                                        .unwrap_or_else(|| "unknown".to_string()),
+                                    thumb_path
+                                        .extension()
+                                        .map(|e| e.to_string_lossy().to_string())
+                                        .unwrap_or_else(|| "jpg".to_string())
                                 );
                                 let dest = out_dir.join(&dest_name);
                                 std::fs::create_dir_all(out_dir).ok();
@@ -555,6 +957,72 @@ impl DrillEngine {
 
     /// Export selected files
     pub async fn export_selected(&self, args: &crate::cli::ExportArgs) -> Result<()> {
+        let archive = if args.archive {
+            Some(ArchiveFormat::from_path(&args.dest).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "--archive needs a destination ending in .zip, .tar.gz or .tgz: {}",
+                    args.dest.display()
+                )
+            })?)
+        } else {
+            None
+        };
+        let remote = RemoteTarget::parse(&args.dest)?;
+        if remote.is_some() {
+            anyhow::ensure!(
+                archive.is_none(),
+                "--archive is not supported with a remote destination"
+            );
+            anyhow::ensure!(
+                !args.resume,
+                "--resume is not supported with a remote destination"
+            );
+            anyhow::ensure!(
+                args.mirror.is_none(),
+                "--mirror is not supported with a remote destination"
+            );
+            anyhow::ensure!(
+                args.report.is_none(),
+                "--report needs a local destination (run `report` on the manifest instead)"
+            );
+        }
+        anyhow::ensure!(
+            !(args.resume && archive.is_some()),
+            "--resume is not supported with --archive (archives are rewritten from scratch)"
+        );
+        anyhow::ensure!(
+            !(args.mirror.is_some() && archive.is_some()),
+            "--mirror is not supported with --archive"
+        );
+        anyhow::ensure!(
+            args.mirror.as_deref() != Some(args.dest.as_path()),
+            "--mirror must differ from the destination"
+        );
+        let max_bytes_per_sec = args
+            .max_rate
+            .as_deref()
+            .map(|rate| {
+                parse_size_str(rate).ok_or_else(|| anyhow::anyhow!("Invalid --max-rate: {}", rate))
+            })
+            .transpose()?;
+        let block_size = args
+            .block_size
+            .as_deref()
+            .map(|size| {
+                parse_size_str(size)
+                    .filter(|&s| s > 0)
+                    .map(|s| s as usize)
+                    .ok_or_else(|| anyhow::anyhow!("Invalid --block-size: {}", size))
+            })
+            .transpose()?;
+        let mut hooks: Vec<Arc<dyn ExportHook>> = Vec::new();
+        if let Some(addr) = &args.clamd {
+            let clamav = ClamAv::new(addr);
+            clamav
+                .ping()
+                .with_context(|| format!("clamd at {} is not answering", addr))?;
+            hooks.push(Arc::new(clamav));
+        }
         let options = ExportOptions {
             dest: args.dest.clone(),
             preserve_structure: args.preserve_structure,
@@ -562,64 +1030,211 @@ impl DrillEngine {
             continue_on_error: args.continue_on_error,
             create_manifest: args.manifest,
             dry_run: args.dry_run,
+            hash_retries: args.hash_retries,
+            retry_with_sector_reader: args.sector_retry,
+            organize_by: args.organize_by.map(|o| match o {
+                crate::cli::ExportOrganizeBy::DateTaken => OrganizeBy::DateTaken,
+                crate::cli::ExportOrganizeBy::DateModified => OrganizeBy::DateModified,
+                crate::cli::ExportOrganizeBy::FileType => OrganizeBy::FileType,
+            }),
+            on_conflict: match args.on_conflict {
+                crate::cli::ExportOnConflict::Rename => CollisionPolicy::Rename,
+                crate::cli::ExportOnConflict::Skip => CollisionPolicy::Skip,
+                crate::cli::ExportOnConflict::Overwrite => CollisionPolicy::Overwrite,
+                crate::cli::ExportOnConflict::Error => CollisionPolicy::Error,
+            },
+            archive,
+            remote,
+            max_bytes_per_sec,
+            resume: args.resume,
+            mirror: args.mirror.clone(),
+            index_started: {
+                let index = self.index.read();
+                (!index.is_empty()).then(|| index.created_at())
+            },
+            hash_algorithms: extra_hash_algorithms(&args.hash),
+            io: IoOptions {
+                block_size,
+                ..args.io_backend.options(args.queue_depth)
+            },
+            workers: args.workers,
+            skip_space_check: args.no_space_check,
+            repair_names: args
+                .repair_names
+                .as_deref()
+                .map(NameRepair::new)
+                .transpose()?,
+            decompress: args.decompress,
+            hooks,
+            on_detection: match args.on_detection {
+                crate::cli::ExportOnDetection::Quarantine => DetectionAction::Quarantine,
+                crate::cli::ExportOnDetection::Flag => DetectionAction::Flag,
+            },
         };
 
-        let files: Vec<String> = if args.files.is_empty() {
+        let files: Vec<String> = if let Some(path) = &args.selection {
+            let selection = Selection::load(path)?;
+            if selection.is_from_other_source(&args.source) {
+                tracing::warn!(
+                    "Selection {} was built on {}, exporting from {}",
+                    path.display(),
+                    selection
+                        .source
+                        .as_deref()
+                        .unwrap_or(Path::new(""))
+                        .display(),
+                    args.source.display()
+                );
+            }
+            let (mut files, missing): (Vec<String>, Vec<String>) = {
+                let index = self.index.read();
+                selection
+                    .files
+                    .into_iter()
+                    .partition(|f| index.get_by_path(f).is_some())
+            };
+            if !missing.is_empty() {
+                println!(
+                    "Warning: {} selected files are not in the index and will be skipped",
+                    missing.len()
+                );
+                for file in &missing {
+                    tracing::warn!("Selected file not in index: {}", file);
+                }
+            }
+            for file in &args.files {
+                if !files.contains(file) {
+                    files.push(file.clone());
+                }
+            }
+            files
+        } else if args.files.is_empty() {
             // Export all
             self.get_all_files().await?
         } else {
             args.files.clone()
         };
-
-        // Load checkpoint for resume capability
-        let checkpoint_mgr = CheckpointManager::new();
-        let checkpoint = checkpoint_mgr.load(&args.source, CheckpointPhase::Exporting)?;
-
-        // Filter out already-exported files
-        let files_to_export: Vec<String> = if let Some(ref cp) = checkpoint {
-            let skipped = files
-                .iter()
-                .filter(|f| cp.is_already_processed(f))
-                .count();
-            if skipped > 0 {
-                tracing::info!(
-                    "Resuming export: skipping {} already-exported files",
-                    skipped
-                );
-            }
+        let files = if args.hashset.is_empty() {
             files
+        } else {
+            let entries: Vec<FileEntry> = {
+                let index = self.index.read();
+                files
+                    .iter()
+                    .filter_map(|path| index.get_by_path(path).cloned())
+                    .collect()
+            };
+            self.apply_hashsets(&args.hashset, entries)?
                 .into_iter()
-                .filter(|f| !cp.is_already_processed(f))
+                .map(|e| e.path.to_string_lossy().to_string())
                 .collect()
-        } else {
-            files
         };
 
+        if args.plan {
+            let plan = self.plan_export(&files, &options);
+            print!("{}", plan);
+            if plan.status() == SpaceStatus::Insufficient {
+                anyhow::bail!("The selection does not fit the destination");
+            }
+            return Ok(());
+        }
+
+        let started = Instant::now();
         let result = self
-            .export_files_with_progress(&files_to_export, &options, |_| {})
+            .export_files_with_progress(&files, &options, |_| {})
             .await?;
-
-        // Clear checkpoint on success
-        checkpoint_mgr.clear(&args.source, CheckpointPhase::Exporting)?;
+        let elapsed = started.elapsed();
 
         println!("\nExport complete:");
         println!("  Successful: {}", result.successful);
+        if result.resumed > 0 {
+            println!("  Already exported (resumed): {}", result.resumed);
+        }
         println!("  Failed: {}", result.failed);
+        if result.skipped > 0 {
+            println!("  Skipped (destination exists): {}", result.skipped);
+        }
+        if result.detections > 0 {
+            match options.on_detection {
+                DetectionAction::Quarantine => println!(
+                    "  Quarantined (virus scan): {} (in {})",
+                    result.detections,
+                    args.dest.join(QUARANTINE_DIR).display()
+                ),
+                DetectionAction::Flag => {
+                    println!("  Flagged by virus scan: {}", result.detections)
+                }
+            }
+        }
+        if let Some(counts) = self.last_hashset_counts().await {
+            println!("  Skipped (known-good hash): {}", counts.known_good);
+            println!("  Known-bad hash matches: {}", counts.known_bad);
+        }
         println!(
             "  Total size: {}",
             humansize::format_size(result.total_bytes, humansize::BINARY)
         );
+        if let (Some(target), false) = (&options.remote, args.dry_run) {
+            println!("  Uploaded to: {} (manifest included)", target);
+        }
+        if let Some(mirror) = &args.mirror {
+            if !args.dry_run {
+                println!("  Mirrored to: {}", mirror.display());
+                println!(
+                    "  Proof manifest: {}",
+                    args.dest.join(PROOF_MANIFEST_FILE).display()
+                );
+            }
+        }
+        if let (Some(format), Some(manifest_path)) = (args.report, &result.manifest_path) {
+            let mut data = crate::report::report_data_from_manifest(manifest_path)?;
+            data.duration_secs = elapsed.as_secs_f64();
+            data.files_failed = result.failed;
+            data.errors.extend(
+                result
+                    .errors
+                    .iter()
+                    .map(|e| format!("{}: {}", e.source_path.display(), e.error)),
+            );
+            let html = args.dest.join("diamond-drill-report.html");
+            let pdf = args.dest.join("diamond-drill-report.pdf");
+            let pdf_options = crate::report::PdfOptions {
+                logo: args.logo.clone(),
+            };
+            if matches!(format, ReportFormat::Html | ReportFormat::Both) {
+                crate::report::embed_thumbnails(&mut data, crate::report::DEFAULT_GALLERY_SIZE);
+                crate::report::save_html_report(&data, &html, false)?;
+                println!("  Report: {}", html.display());
+            }
+            if matches!(format, ReportFormat::Pdf | ReportFormat::Both) {
+                crate::report::generate_pdf_report_with(&data, &pdf, &pdf_options)?;
+                println!("  Report: {}", pdf.display());
+            }
+        }
 
         Ok(())
     }
 
+    /// Space the export of `files` needs against what is free on the
+    /// destination
+    pub fn plan_export(&self, files: &[String], options: &ExportOptions) -> ExportPlan {
+        let entries: Vec<_> = {
+            let index = self.index.read();
+            files
+                .iter()
+                .filter_map(|path| index.get_by_path(path).cloned())
+                .collect()
+        };
+        Exporter::new(options.clone()).plan(&entries)
+    }
+
     /// Export files with progress callback
     pub async fn export_files_with_progress<F>(
         &self,
         files: &[String],
         options: &ExportOptions,
         progress_callback: F,
-    ) -> Result<ExportResult>
+    ) -> DrillResult<ExportResult>
     where
         F: Fn(Progress) + Send + Sync,
     {
@@ -636,21 +1251,75 @@ impl DrillEngine {
         exporter.export_batch(&entries, progress_callback).await
     }
 
-    /// Generate thumbnails in parallel
-    async fn generate_thumbnails_parallel(&self) -> Result<()> {
-        let images: Vec<_> = self
-            .index
-            .read()
-            .entries()
-            .filter(|e| e.file_type == FileType::Image)
-            .cloned()
-            .collect();
+    /// Run deduplication analysis and optionally purge duplicates.
+    pub async fn run_dedup(&self, args: &crate::cli::DedupArgs) -> Result<()> {
+        println!("Diamond Drill Dedup Engine");
+        println!("Scanning {}...\n", self.source.display());
+
+        // If we have no index, do a quick scan first
+        if self.index.read().is_empty() {
+            self.index_with_progress(&quick_index_args(&self.source))
+                .await?;
+        }
 
-        let thumb_gen = Arc::clone(&self.thumbnail_gen);
+        let entries: Vec<FileEntry> = self.index.read().entries().cloned().collect();
+
+        println!(
+            "Indexed {} files. Running dedup analysis...\n",
+            entries.len()
+        );
 
-        // Process in parallel using rayon
-        images.par_iter().for_each(|entry| {
-            if let Err(e) = thumb_gen.generate_progressive(&entry.path, 64, 512) {
+        report_dedup(&entries, args)
+    }
+}
+
+/// What is done to scanned entries before they are indexed
+#[derive(Clone)]
+struct EntryPrep {
+    hashsets: Option<Arc<HashSets>>,
+    hashset_counts: Arc<RwLock<Option<HashSetCounts>>>,
+    /// Set when thumbnails were asked for
+    thumbnails: Option<Arc<ThumbnailGenerator>>,
+}
+
+impl EntryPrep {
+    /// Apply the hash sets, flag encrypted disk images and generate
+    /// thumbnails for the images left
+    fn apply(&self, entries: Vec<FileEntry>) -> Vec<FileEntry> {
+        let mut entries = match &self.hashsets {
+            Some(sets) => add_hashset_matches(sets, &self.hashset_counts, entries),
+            None => entries,
+        };
+        flag_encrypted_images(&mut entries);
+        if let Some(thumbnail_gen) = &self.thumbnails {
+            generate_thumbnails(thumbnail_gen, &entries);
+        }
+        entries
+    }
+}
+
+/// Filter `entries` through loaded hash sets, adding to the match `counts`
+fn add_hashset_matches(
+    sets: &HashSets,
+    counts: &RwLock<Option<HashSetCounts>>,
+    entries: Vec<FileEntry>,
+) -> Vec<FileEntry> {
+    let (entries, matched) = sets.filter_entries(entries);
+    let mut counts = counts.write();
+    let counts = counts.get_or_insert_with(HashSetCounts::default);
+    counts.known_good += matched.known_good;
+    counts.known_bad += matched.known_bad;
+    entries
+}
+
+/// Generate thumbnails for the images among `entries` in parallel
+fn generate_thumbnails(thumbnail_gen: &ThumbnailGenerator, entries: &[FileEntry]) {
+    entries
+        .par_iter()
+        // Members are only read out of their archive on export
+        .filter(|e| e.file_type == FileType::Image && !is_archive_member(e) && !is_snapshot_file(e))
+        .for_each(|entry| {
+            if let Err(e) = thumbnail_gen.generate_progressive(&entry.path, 64, 512) {
                 tracing::warn!(
                     "Failed to generate thumbnail for {}: {}",
                     entry.path.display(),
@@ -658,94 +1327,493 @@ impl DrillEngine {
                 );
             }
         });
+}
 
-        Ok(())
+/// The `--sqlite` database an index run writes its entries to as they are
+/// scanned, so a huge source is never held in memory
+struct SqliteSink {
+    #[cfg(feature = "sqlite")]
+    db: super::SqliteIndex,
+    prep: EntryPrep,
+    /// Batches are processed here while scanning: the global pool's
+    /// workers may all be blocked sending to the channel being drained
+    pool: rayon::ThreadPool,
+    files: usize,
+    bytes: u64,
+}
+
+impl SqliteSink {
+    /// Open the database, emptying it first when `fresh` so files deleted
+    /// since the last run, or from another source, don't linger
+    #[cfg(feature = "sqlite")]
+    fn open(db_path: &Path, source: &Path, fresh: bool, prep: EntryPrep) -> Result<Self> {
+        let db = super::SqliteIndex::open(db_path)?;
+        if fresh {
+            db.clear()?;
+        }
+        db.set_source(source)?;
+        let pool = rayon::ThreadPoolBuilder::new()
+            .build()
+            .context("Failed to start the index batch thread pool")?;
+        Ok(Self {
+            db,
+            prep,
+            pool,
+            files: 0,
+            bytes: 0,
+        })
     }
 
-    /// Run deduplication analysis and optionally purge duplicates.
-    pub async fn run_dedup(&self, args: &crate::cli::DedupArgs) -> Result<()> {
-        use crate::dedup;
+    #[cfg(not(feature = "sqlite"))]
+    fn open(_db_path: &Path, _source: &Path, _fresh: bool, _prep: EntryPrep) -> Result<Self> {
+        anyhow::bail!("SQLite index support not compiled in; rebuild with --features sqlite")
+    }
 
-        println!("Diamond Drill Dedup Engine");
-        println!("Scanning {}...\n", self.source.display());
+    /// Prepare and write a batch on a blocking thread, handing the sink back
+    async fn write(mut self, batch: Vec<FileEntry>) -> Result<Self> {
+        tokio::task::spawn_blocking(move || {
+            let batch = self.pool.install(|| self.prep.apply(batch));
+            self.files += self.insert(&batch)?;
+            self.bytes += batch.iter().map(|e| e.size).sum::<u64>();
+            Ok(self)
+        })
+        .await
+        .context("SQLite index task panicked")?
+    }
 
-        // If we have no index, do a quick scan first
-        if self.index.read().is_empty() {
-            let index_args = crate::cli::IndexArgs {
-                source: self.source.clone(),
-                resume: false,
-                index_file: None,
-                skip_hidden: true,
-                depth: None,
-                extensions: None,
-                thumbnails: false,
-                workers: None,
-                checkpoint_interval: 1000,
-                bad_sector_report: None,
-                block_size: 4096,
-            };
-            self.index_with_progress(&index_args).await?;
-        }
+    #[cfg(feature = "sqlite")]
+    fn insert(&mut self, entries: &[FileEntry]) -> Result<usize> {
+        self.db.insert_entries(entries)
+    }
 
-        let entries: Vec<FileEntry> = self.index.read().entries().cloned().collect();
+    #[cfg(not(feature = "sqlite"))]
+    fn insert(&mut self, _entries: &[FileEntry]) -> Result<usize> {
+        unreachable!("a SqliteSink can't be opened without the sqlite feature")
+    }
+}
+
+/// Index arguments for the quick scan `dedup` runs on unindexed sources
+fn quick_index_args(source: &Path) -> IndexArgs {
+    IndexArgs {
+        source: source.to_path_buf(),
+        resume: false,
+        index_file: None,
+        skip_hidden: true,
+        depth: None,
+        extensions: None,
+        thumbnails: false,
+        workers: None,
+        checkpoint_interval: 1000,
+        bad_sector_report: None,
+        block_size: 4096,
+        sqlite: None,
+        incremental: false,
+        verify_hash: false,
+        metadata: false,
+        report_format: None,
+        report_file: None,
+        hashset: Vec::new(),
+        repair_names: None,
+        links: Default::default(),
+        all_hardlinks: false,
+        archives: false,
+        snapshots: false,
+    }
+}
+
+/// Files of a dedup source: a SQLite index, or a directory or image (with
+/// its saved index, or scanned now if it has none)
+async fn load_dedup_entries(source: &Path) -> Result<Vec<FileEntry>> {
+    #[cfg(feature = "sqlite")]
+    if super::is_sqlite_index(source) {
+        return super::SqliteIndex::open(source)?.query(&super::IndexQuery::default());
+    }
+    let engine = DrillEngine::load_or_create(source).await?;
+    if engine.index.read().is_empty() {
+        engine
+            .index_with_progress(&quick_index_args(source))
+            .await?;
+    }
+    Ok(engine.get_all_entries().await)
+}
+
+/// Check `dedup SOURCE` against `--source` and `--baseline`: which of its
+/// files already exist there, and which are missing.
+pub async fn run_dedup_compare(args: &crate::cli::DedupArgs) -> Result<()> {
+    use crate::dedup;
+
+    anyhow::ensure!(
+        !args.sources.is_empty() || !args.baseline.is_empty(),
+        "--missing needs a --source or --baseline to compare against"
+    );
+    if args.action.is_some() {
+        anyhow::bail!("dedup diff works on a single source");
+    }
+
+    println!("Diamond Drill Dedup Engine");
+    println!("Scanning {}...", args.source.display());
+    let entries = load_dedup_entries(&args.source).await?;
+    let mut references = Vec::new();
+    for source in &args.sources {
+        println!("Scanning {}...", source.display());
+        references.push((source.clone(), load_dedup_entries(source).await?));
+    }
+    let baselines = args
+        .baseline
+        .iter()
+        .map(|path| dedup::Baseline::load(path))
+        .collect::<Result<Vec<_>>>()?;
+
+    println!(
+        "\nComparing {} files against {} sources and {} baseline files...\n",
+        entries.len(),
+        references.len(),
+        baselines.iter().map(|b| b.len()).sum::<usize>()
+    );
+    let comparison = dedup::compare_sources(
+        &args.source,
+        &entries,
+        &references,
+        &baselines,
+        args.min_size,
+    );
+
+    match args.report {
+        crate::cli::DedupReportFormat::Human => {
+            print!("{}", comparison.to_human_string());
+        }
+        crate::cli::DedupReportFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(&comparison)?);
+        }
+    }
 
+    if let Some(path) = &args.missing {
+        let mut selection = Selection::new(Some(&args.source));
+        selection.set_files(
+            comparison
+                .missing
+                .iter()
+                .map(|p| p.to_string_lossy().into_owned()),
+        );
+        selection.save(path)?;
         println!(
-            "Indexed {} files. Running dedup analysis...\n",
-            entries.len()
+            "Saved {} missing files to {} (export them with --selection)",
+            comparison.missing.len(),
+            path.display()
         );
+    } else if !comparison.missing.is_empty() {
+        println!("Run with --missing <FILE> to save the missing files for export --selection.");
+    }
 
-        // Map CLI strategy to dedup strategy
-        let strategy = match args.keep {
-            crate::cli::DedupKeepStrategy::Newest => dedup::KeepStrategy::Newest,
-            crate::cli::DedupKeepStrategy::Largest => dedup::KeepStrategy::Largest,
-            crate::cli::DedupKeepStrategy::Oldest => dedup::KeepStrategy::Oldest,
-            crate::cli::DedupKeepStrategy::Cleanest => dedup::KeepStrategy::Cleanest,
-        };
+    Ok(())
+}
+
+/// Analyze `entries` for duplicates, print the report and purge if asked
+pub(super) fn report_dedup(entries: &[FileEntry], args: &crate::cli::DedupArgs) -> Result<()> {
+    use crate::dedup;
+
+    // Map CLI strategy to dedup strategy
+    let strategy = match args.keep {
+        crate::cli::DedupKeepStrategy::Newest => dedup::KeepStrategy::Newest,
+        crate::cli::DedupKeepStrategy::Largest => dedup::KeepStrategy::Largest,
+        crate::cli::DedupKeepStrategy::Oldest => dedup::KeepStrategy::Oldest,
+        crate::cli::DedupKeepStrategy::Cleanest => dedup::KeepStrategy::Cleanest,
+    };
+
+    let options = dedup::DedupOptions {
+        strategy,
+        fuzzy: args.fuzzy,
+        fuzzy_threshold: args.threshold,
+        min_size: args.min_size,
+        hash_algorithms: extra_hash_algorithms(&args.hash),
+        media: args.media,
+    };
+
+    let report = dedup::analyze(entries, &options)?;
+
+    if let Some(crate::cli::DedupAction::Diff(ref diff_args)) = args.action {
+        return print_group_diff(&report, diff_args, args.report);
+    }
+
+    // Output report
+    match args.report {
+        crate::cli::DedupReportFormat::Human => {
+            print!("{}", report.to_human_string());
+        }
+        crate::cli::DedupReportFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
+    }
 
-        let options = dedup::DedupOptions {
-            strategy,
-            fuzzy: args.fuzzy,
-            fuzzy_threshold: args.threshold,
-            min_size: args.min_size,
+    // Purge if requested
+    if args.purge && !report.groups.is_empty() {
+        println!("Purging {} duplicate files...\n", report.total_duplicates);
+        let (deleted, freed, errors) = dedup::purge_duplicates(&report.groups, false);
+        println!(
+            "Purged {} files, freed {}",
+            deleted,
+            humansize::format_size(freed, humansize::BINARY)
+        );
+        if !errors.is_empty() {
+            eprintln!("\nErrors:");
+            for err in &errors {
+                eprintln!("  {}", err);
+            }
+        }
+    } else if let Some(mode) = args.merge.filter(|_| !report.groups.is_empty()) {
+        let mode = match mode {
+            crate::cli::DedupMergeMode::Hardlink => dedup::MergeMode::Hardlink,
+            crate::cli::DedupMergeMode::Reflink => dedup::MergeMode::Reflink,
         };
+        println!(
+            "Merging {} duplicate files ({:?})...\n",
+            report.total_duplicates, mode
+        );
+        let (merged, reclaimed, errors) = dedup::merge_duplicates(&report.groups, mode, false);
+        println!(
+            "Merged {} files, reclaimed {}",
+            merged,
+            humansize::format_size(reclaimed, humansize::BINARY)
+        );
+        if !errors.is_empty() {
+            eprintln!("\nErrors:");
+            for err in &errors {
+                eprintln!("  {}", err);
+            }
+        }
+    } else if !report.groups.is_empty() {
+        println!(
+            "Run with --purge to delete duplicate files, or --merge hardlink|reflink to link them."
+        );
+    }
+
+    Ok(())
+}
 
-        let report = dedup::analyze(&entries, &options)?;
+/// Map `--hash` values to the digests recorded next to blake3
+fn extra_hash_algorithms(hashes: &[crate::cli::ExtraHashAlgorithm]) -> Vec<HashAlgorithm> {
+    hashes
+        .iter()
+        .map(|h| match h {
+            crate::cli::ExtraHashAlgorithm::Md5 => HashAlgorithm::Md5,
+            crate::cli::ExtraHashAlgorithm::Sha256 => HashAlgorithm::Sha256,
+        })
+        .collect()
+}
+
+/// Print the decompressed name, size and first lines of a compressed file
+fn preview_decompressed(path: &Path, format: CompressedFormat) -> Result<()> {
+    const HEAD_BYTES: u64 = 4096;
+    const HEAD_LINES: usize = 10;
+
+    let failed = || format!("Failed to decompress {}", path.display());
+    let mut reader = format.open(path)?;
+    let mut head = Vec::new();
+    (&mut reader)
+        .take(HEAD_BYTES)
+        .read_to_end(&mut head)
+        .with_context(failed)?;
+    let size = head.len() as u64
+        + std::io::copy(&mut reader, &mut std::io::sink()).with_context(failed)?;
+    println!(
+        "    decompressed ({}): {} ({})",
+        format.name(),
+        decompressed_name(path.file_name().unwrap_or_default()).to_string_lossy(),
+        humansize::format_size(size, humansize::BINARY)
+    );
+    // Only text is shown
+    if !head.contains(&0) {
+        for line in String::from_utf8_lossy(&head).lines().take(HEAD_LINES) {
+            println!("    | {}", line);
+        }
+    }
+    Ok(())
+}
 
-        // Output report
-        match args.report {
-            crate::cli::DedupReportFormat::Human => {
-                print!("{}", report.to_human_string());
+/// Disk images smaller than this aren't probed for encrypted volumes
+const MIN_DISK_IMAGE_SIZE: u64 = 1024 * 1024;
+
+/// Record the BitLocker/LUKS volume of indexed disk images, so they can be
+/// carved with `--unlock`
+fn flag_encrypted_images(entries: &mut [FileEntry]) {
+    entries
+        .par_iter_mut()
+        .filter(|e| e.size >= MIN_DISK_IMAGE_SIZE)
+        .for_each(|entry| {
+            if let Some(volume) = unlock::probe_disk_image(&entry.path) {
+                entry.metadata.insert(
+                    unlock::ENCRYPTED_VOLUME_META_KEY.to_string(),
+                    volume.to_string(),
+                );
             }
-            crate::cli::DedupReportFormat::Json => {
-                println!("{}", serde_json::to_string_pretty(&report)?);
+        });
+}
+
+/// Warn when a source's contents differ from what was indexed
+fn warn_if_changed(source: &Path, stored: &SourceFingerprint, current: &SourceFingerprint) {
+    if stored.content_changed(current) {
+        tracing::warn!(
+            "{} has changed since it was last indexed; re-run `index --incremental` to refresh",
+            source.display()
+        );
+    }
+}
+
+/// Remember which index belongs to a fingerprinted source
+fn record_fingerprint(fingerprint: SourceFingerprint, source: &Path, index_path: &Path) {
+    let registry_path = FingerprintRegistry::default_path();
+    let result = FingerprintRegistry::load(&registry_path).and_then(|mut registry| {
+        registry.record(fingerprint, source, index_path);
+        registry.save(&registry_path)
+    });
+    if let Err(e) = result {
+        tracing::warn!("Failed to update fingerprint registry: {:#}", e);
+    }
+}
+
+/// Print text diffs for one group of a dedup report
+fn print_group_diff(
+    report: &crate::dedup::DedupReport,
+    diff_args: &crate::cli::DedupDiffArgs,
+    format: crate::cli::DedupReportFormat,
+) -> Result<()> {
+    use crate::dedup::diff::{diff_group, DiffLineKind};
+    use colored::Colorize;
+
+    let group = diff_args
+        .group
+        .checked_sub(1)
+        .and_then(|i| report.groups.get(i))
+        .with_context(|| {
+            format!(
+                "Group #{} does not exist; the report has {} group(s)",
+                diff_args.group,
+                report.groups.len()
+            )
+        })?;
+
+    let results = diff_group(group, diff_args.context);
+
+    if let crate::cli::DedupReportFormat::Json = format {
+        let json: Vec<serde_json::Value> = results
+            .iter()
+            .map(|(path, result)| match result {
+                Ok(diff) => serde_json::to_value(diff).unwrap_or_default(),
+                Err(e) => serde_json::json!({
+                    "candidate": path,
+                    "error": format!("{:#}", e),
+                }),
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&json)?);
+        return Ok(());
+    }
+
+    println!(
+        "Group #{} ({}% similar): KEEP {}\n",
+        diff_args.group,
+        group.similarity,
+        group.master.display()
+    );
+
+    for (path, result) in &results {
+        let diff = match result {
+            Ok(diff) => diff,
+            Err(e) => {
+                println!("{} {}: {:#}\n", "✗".bright_red(), path.display(), e);
+                continue;
             }
+        };
+
+        println!(
+            "{} {}  ({}% similar, {} {})",
+            "≠".bright_cyan(),
+            path.display().to_string().bright_white(),
+            diff.similarity,
+            format!("+{}", diff.added).bright_green(),
+            format!("-{}", diff.removed).bright_red()
+        );
+        if diff.is_identical() {
+            println!("  Text content is identical\n");
+            continue;
         }
 
-        // Purge if requested
-        if args.purge && !report.groups.is_empty() {
-            println!("Purging {} duplicate files...\n", report.total_duplicates);
-            let (deleted, freed, errors) = dedup::purge_duplicates(&report.groups, false);
-            println!(
-                "Purged {} files, freed {}",
-                deleted,
-                humansize::format_size(freed, humansize::BINARY)
-            );
-            if !errors.is_empty() {
-                eprintln!("\nErrors:");
-                for err in &errors {
-                    eprintln!("  {}", err);
+        println!("{}", format!("--- {}", diff.master.display()).bright_red());
+        println!(
+            "{}",
+            format!("+++ {}", diff.candidate.display()).bright_green()
+        );
+        for hunk in &diff.hunks {
+            println!("{}", hunk.header.bright_cyan());
+            for line in &hunk.lines {
+                match line.kind {
+                    DiffLineKind::Context => println!(" {}", line.text),
+                    DiffLineKind::Added => println!("{}", format!("+{}", line.text).green()),
+                    DiffLineKind::Removed => println!("{}", format!("-{}", line.text).red()),
                 }
             }
-        } else if !report.groups.is_empty() && !args.purge {
-            println!("Run with --purge to delete duplicate files.");
         }
+        println!();
+    }
 
-        Ok(())
+    Ok(())
+}
+
+/// Type, size and date filters of a `search` command
+struct SearchFilter {
+    file_type: Option<FileType>,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    after: Option<chrono::DateTime<Utc>>,
+    before: Option<chrono::DateTime<Utc>>,
+    meta: Vec<(String, String)>,
+}
+
+impl SearchFilter {
+    fn from_args(args: &crate::cli::SearchArgs) -> Self {
+        let tz = super::display_timezone();
+        Self {
+            // Map CLI file type filter to core FileType (`all` doesn't filter)
+            file_type: args.file_type.and_then(|ft| match ft {
+                crate::cli::FileTypeFilter::Image => Some(FileType::Image),
+                crate::cli::FileTypeFilter::Video => Some(FileType::Video),
+                crate::cli::FileTypeFilter::Audio => Some(FileType::Audio),
+                crate::cli::FileTypeFilter::Document => Some(FileType::Document),
+                crate::cli::FileTypeFilter::Archive => Some(FileType::Archive),
+                crate::cli::FileTypeFilter::Code => Some(FileType::Code),
+                crate::cli::FileTypeFilter::All => None,
+            }),
+            min_size: args.min_size.as_ref().and_then(|s| parse_size_str(s)),
+            max_size: args.max_size.as_ref().and_then(|s| parse_size_str(s)),
+            after: args.after.as_ref().and_then(|s| tz.parse_date(s, false)),
+            before: args.before.as_ref().and_then(|s| tz.parse_date(s, true)),
+            meta: args.meta.clone(),
+        }
+    }
+
+    /// Entries without a modification time pass the date filters; entries
+    /// lacking a `--meta` key do not pass that filter
+    fn matches(&self, entry: &FileEntry) -> bool {
+        self.file_type.is_none_or(|ft| entry.file_type == ft)
+            && self.min_size.is_none_or(|min| entry.size >= min)
+            && self.max_size.is_none_or(|max| entry.size <= max)
+            && self
+                .after
+                .is_none_or(|after| entry.modified.is_none_or(|m| m >= after))
+            && self
+                .before
+                .is_none_or(|before| entry.modified.is_none_or(|m| m <= before))
+            && self.meta.iter().all(|(key, wanted)| {
+                entry
+                    .metadata
+                    .get(key)
+                    .is_some_and(|value| super::meta_value_matches(value, wanted))
+            })
     }
 }
 
 /// Parse human-readable size string (e.g. "1KB", "10MB", "5GB") to bytes
-fn parse_size_str(s: &str) -> Option<u64> {
+pub(crate) fn parse_size_str(s: &str) -> Option<u64> {
     let s = s.trim().to_uppercase();
     let (num, unit) = if s.ends_with("GB") {
         (&s[..s.len() - 2], 1024u64 * 1024 * 1024)
@@ -0,0 +1,375 @@
+//! Source fingerprinting - recognise the same disk across sessions
+//!
+//! A fingerprint has two parts. The `id` identifies the medium and survives
+//! it being reattached under another path or drive letter; the
+//! `content_hash` is a cheap sample of its contents that changes when files
+//! are added or modified. Indexes store the fingerprint of their source and
+//! a small registry maps ids to index files, so prior work can be found for
+//! a source that moved.
+//!
+//! - Devices and image files: size, device serial (Linux) and the first
+//!   sectors (partition table and boot sector) form the id; sectors sampled
+//!   across the whole medium form the content hash.
+//! - Directories: the filesystem UUID (Linux) plus the path inside the
+//!   mount, or the top-level names when no UUID is available, form the id;
+//!   top-level names, sizes and modification times form the content hash.
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Bytes hashed at each sample point of a device or image
+const SAMPLE_SIZE: u64 = 64 * 1024;
+
+/// Offset of the second identity sample, where the first partition's boot
+/// sector usually is on 1 MiB-aligned disks
+const FIRST_PARTITION_OFFSET: u64 = 1024 * 1024;
+
+/// Evenly spaced content samples taken across a device or image
+const CONTENT_SAMPLES: u64 = 16;
+
+/// Top-level directory entries considered for a directory fingerprint
+const MAX_DIR_ENTRIES: usize = 4096;
+
+const REGISTRY_FILE: &str = "fingerprints.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceKind {
+    Directory,
+    Device,
+    Image,
+}
+
+/// Stable identity plus content sample of an indexed source
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceFingerprint {
+    /// Identity of the medium, independent of where it is mounted
+    pub id: String,
+    /// Sample of the contents; differs once the source has been modified
+    pub content_hash: String,
+    pub kind: SourceKind,
+    /// Size in bytes (devices and images)
+    pub size: Option<u64>,
+    /// Device serial or filesystem UUID, when the platform exposes one
+    pub serial: Option<String>,
+}
+
+impl SourceFingerprint {
+    /// Fingerprint a directory, block device or image file
+    pub fn compute(source: &Path) -> Result<Self> {
+        let meta = std::fs::metadata(source)
+            .with_context(|| format!("Failed to stat {}", source.display()))?;
+        if meta.is_dir() {
+            return fingerprint_directory(source);
+        }
+
+        let kind = if is_block_device(&meta) {
+            SourceKind::Device
+        } else {
+            SourceKind::Image
+        };
+        let mut file =
+            File::open(source).with_context(|| format!("Failed to open {}", source.display()))?;
+        let size = file
+            .seek(SeekFrom::End(0))
+            .with_context(|| format!("Failed to size {}", source.display()))?;
+        let serial = match kind {
+            SourceKind::Device => device_serial(source),
+            _ => None,
+        };
+
+        let mut id = blake3::Hasher::new();
+        id.update(&size.to_le_bytes());
+        if let Some(serial) = &serial {
+            id.update(serial.as_bytes());
+        }
+        for offset in [0, FIRST_PARTITION_OFFSET] {
+            hash_sample(&mut file, offset, size, &mut id)?;
+        }
+
+        let mut content = blake3::Hasher::new();
+        let stride = (size / CONTENT_SAMPLES).max(SAMPLE_SIZE);
+        let mut offset = 0;
+        while offset < size {
+            hash_sample(&mut file, offset, size, &mut content)?;
+            offset += stride;
+        }
+        hash_sample(
+            &mut file,
+            size.saturating_sub(SAMPLE_SIZE),
+            size,
+            &mut content,
+        )?;
+
+        Ok(Self {
+            id: short_hex(id.finalize()),
+            content_hash: short_hex(content.finalize()),
+            kind,
+            size: Some(size),
+            serial,
+        })
+    }
+
+    /// Whether both fingerprints describe the same medium
+    pub fn same_source(&self, other: &SourceFingerprint) -> bool {
+        self.id == other.id
+    }
+
+    /// Whether `current` is the same medium with different contents
+    pub fn content_changed(&self, current: &SourceFingerprint) -> bool {
+        self.same_source(current) && self.content_hash != current.content_hash
+    }
+}
+
+fn hash_sample(file: &mut File, offset: u64, size: u64, hasher: &mut blake3::Hasher) -> Result<()> {
+    if offset >= size {
+        return Ok(());
+    }
+    let mut buf = vec![0u8; SAMPLE_SIZE.min(size - offset) as usize];
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut buf)
+        .with_context(|| format!("Failed to read fingerprint sample at offset {}", offset))?;
+    hasher.update(&offset.to_le_bytes());
+    hasher.update(&buf);
+    Ok(())
+}
+
+fn fingerprint_directory(dir: &Path) -> Result<SourceFingerprint> {
+    let mut entries: Vec<(String, u64, i64)> = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read directory {}", dir.display()))?
+        .filter_map(|e| e.ok())
+        .take(MAX_DIR_ENTRIES)
+        .map(|e| {
+            let meta = e.metadata().ok();
+            (
+                e.file_name().to_string_lossy().to_string(),
+                meta.as_ref().map(|m| m.len()).unwrap_or(0),
+                meta.and_then(|m| m.modified().ok())
+                    .map(|t| DateTime::<Utc>::from(t).timestamp())
+                    .unwrap_or(0),
+            )
+        })
+        .collect();
+    entries.sort();
+
+    let mut id = blake3::Hasher::new();
+    let serial = filesystem_uuid(dir);
+    match (&serial, mount_relative_path(dir)) {
+        (Some(uuid), Some(relative)) => {
+            id.update(uuid.as_bytes());
+            id.update(relative.to_string_lossy().as_bytes());
+        }
+        _ => {
+            for (name, _, _) in &entries {
+                id.update(name.as_bytes());
+                id.update(b"\0");
+            }
+        }
+    }
+
+    let mut content = blake3::Hasher::new();
+    for (name, size, mtime) in &entries {
+        content.update(name.as_bytes());
+        content.update(&size.to_le_bytes());
+        content.update(&mtime.to_le_bytes());
+    }
+
+    Ok(SourceFingerprint {
+        id: short_hex(id.finalize()),
+        content_hash: short_hex(content.finalize()),
+        kind: SourceKind::Directory,
+        size: None,
+        serial,
+    })
+}
+
+fn short_hex(hash: blake3::Hash) -> String {
+    hex::encode(&hash.as_bytes()[..16])
+}
+
+#[cfg(unix)]
+fn is_block_device(meta: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    meta.file_type().is_block_device()
+}
+
+#[cfg(not(unix))]
+fn is_block_device(_meta: &std::fs::Metadata) -> bool {
+    false
+}
+
+/// Serial number of a block device from sysfs (whole disk or partition)
+#[cfg(target_os = "linux")]
+fn device_serial(device: &Path) -> Option<String> {
+    let name = std::fs::canonicalize(device).ok()?.file_name()?.to_owned();
+    let sys = std::fs::canonicalize(Path::new("/sys/class/block").join(name)).ok()?;
+    // Partitions live under their disk's directory
+    let disk = if sys.join("partition").exists() {
+        sys.parent()?.to_path_buf()
+    } else {
+        sys
+    };
+    ["device/serial", "serial", "device/wwid"]
+        .iter()
+        .filter_map(|f| std::fs::read_to_string(disk.join(f)).ok())
+        .map(|s| s.trim().to_string())
+        .find(|s| !s.is_empty())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn device_serial(_device: &Path) -> Option<String> {
+    None
+}
+
+/// UUID of the filesystem holding `dir`, matched through /dev/disk/by-uuid
+#[cfg(target_os = "linux")]
+fn filesystem_uuid(dir: &Path) -> Option<String> {
+    use std::os::unix::fs::MetadataExt;
+
+    let dev = std::fs::metadata(dir).ok()?.dev();
+    std::fs::read_dir("/dev/disk/by-uuid")
+        .ok()?
+        .filter_map(|e| e.ok())
+        .find(|e| std::fs::metadata(e.path()).is_ok_and(|m| m.rdev() == dev))
+        .map(|e| e.file_name().to_string_lossy().to_string())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn filesystem_uuid(_dir: &Path) -> Option<String> {
+    None
+}
+
+/// Path of `dir` relative to the root of the filesystem it is on
+#[cfg(unix)]
+fn mount_relative_path(dir: &Path) -> Option<PathBuf> {
+    use std::os::unix::fs::MetadataExt;
+
+    let dir = std::fs::canonicalize(dir).ok()?;
+    let dev = std::fs::metadata(&dir).ok()?.dev();
+    let mut root = dir.as_path();
+    while let Some(parent) = root.parent() {
+        if std::fs::metadata(parent).ok()?.dev() != dev {
+            break;
+        }
+        root = parent;
+    }
+    dir.strip_prefix(root).ok().map(Path::to_path_buf)
+}
+
+#[cfg(not(unix))]
+fn mount_relative_path(_dir: &Path) -> Option<PathBuf> {
+    None
+}
+
+/// One indexed source known to the registry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FingerprintRecord {
+    pub fingerprint: SourceFingerprint,
+    /// Path the source had when it was indexed
+    pub source: PathBuf,
+    /// Index file written for it
+    pub index_path: PathBuf,
+    pub indexed_at: DateTime<Utc>,
+}
+
+/// Maps source fingerprints to the indexes written for them
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct FingerprintRegistry {
+    pub records: Vec<FingerprintRecord>,
+}
+
+impl FingerprintRegistry {
+    /// Default registry location next to the indexes
+    pub fn default_path() -> PathBuf {
+        directories::ProjectDirs::from("com", "tunclon", "diamond-drill")
+            .map(|dirs| dirs.data_dir().join(REGISTRY_FILE))
+            .unwrap_or_else(|| PathBuf::from(format!(".diamond-drill-{}", REGISTRY_FILE)))
+    }
+
+    /// Load the registry, treating a missing file as empty
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let data = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read fingerprint registry {}", path.display()))?;
+        serde_json::from_str(&data)
+            .with_context(|| format!("Failed to parse fingerprint registry {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let data = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, data)
+            .with_context(|| format!("Failed to write fingerprint registry {}", path.display()))
+    }
+
+    /// Remember that `source` with `fingerprint` was indexed to `index_path`,
+    /// replacing any earlier record for the same index
+    pub fn record(&mut self, fingerprint: SourceFingerprint, source: &Path, index_path: &Path) {
+        self.records.retain(|r| r.index_path != index_path);
+        self.records.push(FingerprintRecord {
+            fingerprint,
+            source: source.to_path_buf(),
+            index_path: index_path.to_path_buf(),
+            indexed_at: Utc::now(),
+        });
+    }
+
+    /// Most recent record for the medium whose index file still exists
+    pub fn find(&self, fingerprint: &SourceFingerprint) -> Option<&FingerprintRecord> {
+        self.records
+            .iter()
+            .filter(|r| r.fingerprint.same_source(fingerprint) && r.index_path.exists())
+            .max_by_key(|r| r.indexed_at)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_fingerprint_identity_and_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("disk.img");
+        let mut data = vec![0u8; 4 * 1024 * 1024];
+        data[510] = 0x55;
+        data[511] = 0xAA;
+        std::fs::write(&first, &data).unwrap();
+        let original = SourceFingerprint::compute(&first).unwrap();
+        assert_eq!(original.kind, SourceKind::Image);
+        assert_eq!(original.size, Some(data.len() as u64));
+
+        // Same bytes under another name: same source, unchanged
+        let moved = dir.path().join("renamed.img");
+        std::fs::write(&moved, &data).unwrap();
+        let reattached = SourceFingerprint::compute(&moved).unwrap();
+        assert_eq!(reattached, original);
+
+        // Modified inside a sampled region: same source, changed content
+        data[2 * 1024 * 1024 + 10] = 0xFF;
+        std::fs::write(&moved, &data).unwrap();
+        let modified = SourceFingerprint::compute(&moved).unwrap();
+        assert!(original.same_source(&modified));
+        assert!(original.content_changed(&modified));
+
+        let mut registry = FingerprintRegistry::default();
+        let index = dir.path().join("disk.idx");
+        std::fs::write(&index, b"idx").unwrap();
+        registry.record(original.clone(), &first, &index);
+        registry.record(original.clone(), &first, &dir.path().join("gone.idx"));
+        let path = dir.path().join(REGISTRY_FILE);
+        registry.save(&path).unwrap();
+        let registry = FingerprintRegistry::load(&path).unwrap();
+        assert_eq!(registry.find(&modified).unwrap().index_path, index);
+    }
+}
@@ -0,0 +1,485 @@
+//! Known-file hash sets (NSRL RDS and custom hash lists)
+//!
+//! A hash set is either known-good (e.g. the NSRL Reference Data Set of
+//! stock OS and application files, which only clutter a review) or
+//! known-bad (hashes of contraband or malware). `--hashset` on index,
+//! search and export drops known-good files and flags known-bad ones.
+//!
+//! Accepted formats:
+//! - NSRL RDS 2.x `NSRLFile.txt` (quoted CSV with `"SHA-1","MD5",...`
+//!   header). RDS 3 ships as SQLite; export its `FILE` table to CSV first.
+//! - Plain lists with one hex digest per line, optionally followed by a
+//!   file name (`md5sum`, `sha1sum`, `sha256sum` and `b3sum` output).
+//!   The algorithm follows from the digest length; 64-digit digests are
+//!   SHA-256 unless the list is named `*.b3`/`*blake3*` or starts with a
+//!   `# blake3` comment.
+//!
+//! Flagged files carry `hashset=known-bad:<set>` in their metadata, so
+//! `search --meta hashset=known-bad` finds them later.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use md5::Md5;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+use super::FileEntry;
+
+/// Metadata key recording a known-bad match
+pub const HASHSET_META_KEY: &str = "hashset";
+
+/// Digest algorithms understood by hash sets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    Md5,
+    Sha1,
+    Sha256,
+    Blake3,
+}
+
+impl HashAlgorithm {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Md5 => "md5",
+            Self::Sha1 => "sha1",
+            Self::Sha256 => "sha256",
+            Self::Blake3 => "blake3",
+        }
+    }
+}
+
+/// Hex digests of one file keyed by algorithm
+pub type Digests = BTreeMap<HashAlgorithm, String>;
+
+/// Several digests of the same data, fed in one pass
+#[derive(Default)]
+pub struct MultiHasher {
+    md5: Option<Md5>,
+    sha1: Option<Sha1>,
+    sha256: Option<Sha256>,
+    blake3: Option<blake3::Hasher>,
+}
+
+impl MultiHasher {
+    pub fn new(algorithms: &[HashAlgorithm]) -> Self {
+        let mut hashers = Self::default();
+        for algorithm in algorithms {
+            match algorithm {
+                HashAlgorithm::Md5 => hashers.md5 = Some(Md5::new()),
+                HashAlgorithm::Sha1 => hashers.sha1 = Some(Sha1::new()),
+                HashAlgorithm::Sha256 => hashers.sha256 = Some(Sha256::new()),
+                HashAlgorithm::Blake3 => hashers.blake3 = Some(blake3::Hasher::new()),
+            }
+        }
+        hashers
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        if let Some(h) = &mut self.md5 {
+            h.update(data);
+        }
+        if let Some(h) = &mut self.sha1 {
+            h.update(data);
+        }
+        if let Some(h) = &mut self.sha256 {
+            h.update(data);
+        }
+        if let Some(h) = &mut self.blake3 {
+            h.update(data);
+        }
+    }
+
+    pub fn finish(self) -> Digests {
+        let mut digests = BTreeMap::new();
+        if let Some(h) = self.md5 {
+            digests.insert(HashAlgorithm::Md5, hex::encode(h.finalize()));
+        }
+        if let Some(h) = self.sha1 {
+            digests.insert(HashAlgorithm::Sha1, hex::encode(h.finalize()));
+        }
+        if let Some(h) = self.sha256 {
+            digests.insert(HashAlgorithm::Sha256, hex::encode(h.finalize()));
+        }
+        if let Some(h) = self.blake3 {
+            digests.insert(HashAlgorithm::Blake3, h.finalize().to_hex().to_string());
+        }
+        digests
+    }
+}
+
+/// Compute several digests of a file in one streaming pass
+pub fn compute_digests(path: &Path, algorithms: &[HashAlgorithm]) -> Result<Digests> {
+    let mut file =
+        std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut hashers = MultiHasher::new(algorithms);
+    let mut buffer = vec![0u8; 256 * 1024];
+    loop {
+        let n = file
+            .read(&mut buffer)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        if n == 0 {
+            break;
+        }
+        hashers.update(&buffer[..n]);
+    }
+    Ok(hashers.finish())
+}
+
+/// Whether a set lists files to ignore or files to flag
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HashSetKind {
+    KnownGood,
+    KnownBad,
+}
+
+impl HashSetKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::KnownGood => "known-good",
+            Self::KnownBad => "known-bad",
+        }
+    }
+}
+
+/// One loaded hash list
+#[derive(Debug, Clone)]
+pub struct KnownHashSet {
+    /// File name of the list
+    pub name: String,
+    pub kind: HashSetKind,
+    hashes: HashMap<HashAlgorithm, HashSet<String>>,
+}
+
+impl KnownHashSet {
+    /// Load an NSRL RDS file or a plain hash list
+    pub fn load(path: &Path, kind: HashSetKind) -> Result<Self> {
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("Failed to open hash set: {}", path.display()))?;
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.display().to_string());
+        let file_name = name.to_lowercase();
+        let mut wide = if file_name.ends_with(".b3") || file_name.contains("blake3") {
+            HashAlgorithm::Blake3
+        } else {
+            HashAlgorithm::Sha256
+        };
+
+        let mut set = Self {
+            name,
+            kind,
+            hashes: HashMap::new(),
+        };
+        let mut nsrl_columns: Option<(Option<usize>, Option<usize>)> = None;
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line =
+                line.with_context(|| format!("Failed to read hash set: {}", path.display()))?;
+            let line = line.trim();
+            if i == 0 && line.starts_with('"') {
+                let columns: Vec<String> = split_csv(line);
+                let position = |name: &str| columns.iter().position(|c| c == name);
+                nsrl_columns = Some((position("SHA-1"), position("MD5")));
+                continue;
+            }
+            if let Some(comment) = line.strip_prefix('#') {
+                if i == 0 && comment.trim().eq_ignore_ascii_case("blake3") {
+                    wide = HashAlgorithm::Blake3;
+                }
+                continue;
+            }
+            if line.is_empty() {
+                continue;
+            }
+            match nsrl_columns {
+                Some((sha1, md5)) => {
+                    let fields = split_csv(line);
+                    for (column, algorithm) in
+                        [(sha1, HashAlgorithm::Sha1), (md5, HashAlgorithm::Md5)]
+                    {
+                        if let Some(value) = column.and_then(|c| fields.get(c)) {
+                            set.insert(algorithm, value);
+                        }
+                    }
+                }
+                None => {
+                    let digest = line.split_whitespace().next().unwrap_or_default();
+                    // `b3sum`/`sha256sum --tag` style "ALGO (name) = digest"
+                    let digest = line.rsplit_once(" = ").map_or(digest, |(_, d)| d);
+                    let algorithm = match digest.len() {
+                        32 => HashAlgorithm::Md5,
+                        40 => HashAlgorithm::Sha1,
+                        64 => wide,
+                        _ => continue,
+                    };
+                    set.insert(algorithm, digest);
+                }
+            }
+        }
+        anyhow::ensure!(
+            !set.is_empty(),
+            "No hashes found in hash set: {}",
+            path.display()
+        );
+        Ok(set)
+    }
+
+    fn insert(&mut self, algorithm: HashAlgorithm, digest: &str) {
+        if digest.bytes().all(|b| b.is_ascii_hexdigit()) {
+            self.hashes
+                .entry(algorithm)
+                .or_default()
+                .insert(digest.to_ascii_lowercase());
+        }
+    }
+
+    /// Number of digests in the set
+    pub fn len(&self) -> usize {
+        self.hashes.values().map(HashSet::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn contains(&self, digests: &Digests) -> bool {
+        digests.iter().any(|(algorithm, digest)| {
+            self.hashes
+                .get(algorithm)
+                .is_some_and(|set| set.contains(digest))
+        })
+    }
+}
+
+/// Split one line of NSRL's quoted CSV (no embedded quotes in hash columns)
+fn split_csv(line: &str) -> Vec<String> {
+    line.split(',')
+        .map(|field| field.trim().trim_matches('"').to_string())
+        .collect()
+}
+
+/// Match of a file against a loaded set
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HashSetMatch {
+    pub set: String,
+    pub kind: HashSetKind,
+}
+
+/// How many files matched while filtering
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HashSetCounts {
+    /// Files dropped as known-good
+    pub known_good: usize,
+    /// Files flagged as known-bad
+    pub known_bad: usize,
+}
+
+/// All hash sets given with `--hashset`
+#[derive(Debug, Clone, Default)]
+pub struct HashSets {
+    sets: Vec<KnownHashSet>,
+}
+
+impl HashSets {
+    /// Load sets from `--hashset` values: `PATH`, `good:PATH` or `bad:PATH`
+    /// (known-good unless marked bad)
+    pub fn load(specs: &[String]) -> Result<Self> {
+        let sets = specs
+            .iter()
+            .map(|spec| {
+                let (kind, path) = match spec.split_once(':') {
+                    Some(("good", path)) => (HashSetKind::KnownGood, path),
+                    Some(("bad", path)) => (HashSetKind::KnownBad, path),
+                    _ => (HashSetKind::KnownGood, spec.as_str()),
+                };
+                let set = KnownHashSet::load(Path::new(path), kind)?;
+                tracing::info!(
+                    "Loaded {} hash set {} ({} hashes)",
+                    kind.label(),
+                    set.name,
+                    set.len()
+                );
+                Ok(set)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { sets })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sets.is_empty()
+    }
+
+    /// Algorithms that have to be computed to check a file
+    pub fn algorithms(&self) -> Vec<HashAlgorithm> {
+        let mut algorithms: Vec<HashAlgorithm> = self
+            .sets
+            .iter()
+            .flat_map(|s| s.hashes.keys().copied())
+            .collect();
+        algorithms.sort();
+        algorithms.dedup();
+        algorithms
+    }
+
+    /// Look up digests; a known-bad match wins over a known-good one
+    pub fn lookup(&self, digests: &Digests) -> Option<HashSetMatch> {
+        let matching = |kind| {
+            self.sets
+                .iter()
+                .find(|s| s.kind == kind && s.contains(digests))
+                .map(|s| HashSetMatch {
+                    set: s.name.clone(),
+                    kind,
+                })
+        };
+        matching(HashSetKind::KnownBad).or_else(|| matching(HashSetKind::KnownGood))
+    }
+
+    /// Hash a file (reusing its stored blake3) and look it up
+    pub fn check_entry(&self, entry: &FileEntry) -> Result<Option<HashSetMatch>> {
+        let mut algorithms = self.algorithms();
+        let known_blake3 = entry.hash.clone().filter(|_| {
+            let before = algorithms.len();
+            algorithms.retain(|a| *a != HashAlgorithm::Blake3);
+            algorithms.len() < before
+        });
+        let mut digests = if algorithms.is_empty() {
+            BTreeMap::new()
+        } else {
+            compute_digests(&entry.path, &algorithms)?
+        };
+        if let Some(blake3) = known_blake3 {
+            digests.insert(HashAlgorithm::Blake3, blake3);
+        }
+        Ok(self.lookup(&digests))
+    }
+
+    /// Drop known-good entries and flag known-bad ones (in parallel).
+    /// Files that cannot be read are kept unflagged.
+    pub fn filter_entries(&self, entries: Vec<FileEntry>) -> (Vec<FileEntry>, HashSetCounts) {
+        if self.is_empty() {
+            return (entries, HashSetCounts::default());
+        }
+        let checked: Vec<(FileEntry, Option<HashSetMatch>)> = entries
+            .into_par_iter()
+            .map(|entry| {
+                let matched = self.check_entry(&entry).unwrap_or_else(|e| {
+                    tracing::warn!(
+                        "Hash set check failed for {}: {:#}",
+                        entry.path.display(),
+                        e
+                    );
+                    None
+                });
+                (entry, matched)
+            })
+            .collect();
+
+        let mut counts = HashSetCounts::default();
+        let mut kept = Vec::with_capacity(checked.len());
+        for (mut entry, matched) in checked {
+            match matched {
+                Some(m) if m.kind == HashSetKind::KnownGood => counts.known_good += 1,
+                Some(m) => {
+                    counts.known_bad += 1;
+                    entry.metadata.insert(
+                        HASHSET_META_KEY.to_string(),
+                        format!("{}:{}", m.kind.label(), m.set),
+                    );
+                    kept.push(entry);
+                }
+                None => kept.push(entry),
+            }
+        }
+        (kept, counts)
+    }
+}
+
+/// Known-bad match recorded on an entry, if any
+pub fn known_bad_set(entry: &FileEntry) -> Option<&str> {
+    entry
+        .metadata
+        .get(HASHSET_META_KEY)
+        .and_then(|v| v.strip_prefix("known-bad:"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hashset_filtering() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, contents: &[u8]| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, contents).unwrap();
+            FileEntry::new(path.clone(), &std::fs::metadata(&path).unwrap())
+        };
+        let os_file = write("kernel32.dll", b"stock os file");
+        let contraband = write("bad.jpg", b"known bad image");
+        let photo = write("holiday.jpg", b"unknown photo");
+
+        let digests = compute_digests(
+            &os_file.path,
+            &[
+                HashAlgorithm::Md5,
+                HashAlgorithm::Sha1,
+                HashAlgorithm::Blake3,
+            ],
+        )
+        .unwrap();
+        assert_eq!(digests.len(), 3);
+        assert_eq!(digests[&HashAlgorithm::Md5].len(), 32);
+
+        // NSRL RDS 2.x layout, matched on SHA-1
+        let nsrl = dir.path().join("NSRLFile.txt");
+        std::fs::write(
+            &nsrl,
+            format!(
+                "\"SHA-1\",\"MD5\",\"CRC32\",\"FileName\",\"FileSize\",\"ProductCode\",\"OpSystemCode\",\"SpecialCode\"\n\
+                 \"{}\",\"{}\",\"00000000\",\"kernel32.dll\",13,1,\"WIN\",\"\"\n",
+                digests[&HashAlgorithm::Sha1].to_uppercase(),
+                "0".repeat(32)
+            ),
+        )
+        .unwrap();
+        // sha256sum output for the known-bad list
+        let bad_sha256 = hex::encode(Sha256::digest(b"known bad image"));
+        let bad = dir.path().join("bad.sha256");
+        std::fs::write(&bad, format!("{}  bad.jpg\n", bad_sha256)).unwrap();
+
+        let sets = HashSets::load(&[nsrl.display().to_string(), format!("bad:{}", bad.display())])
+            .unwrap();
+        assert_eq!(
+            sets.algorithms(),
+            [
+                HashAlgorithm::Md5,
+                HashAlgorithm::Sha1,
+                HashAlgorithm::Sha256
+            ]
+        );
+
+        let (kept, counts) = sets.filter_entries(vec![os_file, contraband, photo]);
+        assert_eq!(
+            counts,
+            HashSetCounts {
+                known_good: 1,
+                known_bad: 1
+            }
+        );
+        assert_eq!(kept.len(), 2);
+        let flagged: Vec<_> = kept.iter().filter_map(known_bad_set).collect();
+        assert_eq!(flagged, ["bad.sha256"]);
+
+        let empty = dir.path().join("empty.txt");
+        std::fs::write(&empty, "# nothing here\n").unwrap();
+        assert!(HashSets::load(&[empty.display().to_string()]).is_err());
+    }
+}
@@ -10,7 +10,8 @@ use anyhow::Result;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-use super::{BadSector, FileType};
+use super::{BadSector, FileType, SourceFingerprint};
+use crate::error::DrillError;
 
 /// A single file entry in the index
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,6 +34,9 @@ pub struct FileEntry {
     pub has_bad_sectors: bool,
     /// Thumbnail path (if generated)
     pub thumbnail: Option<PathBuf>,
+    /// Format metadata (EXIF, ID3, PDF info) when indexed with `--metadata`
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
 }
 
 impl FileEntry {
@@ -59,9 +63,15 @@ impl FileEntry {
             hash: None,
             has_bad_sectors: false,
             thumbnail: None,
+            metadata: HashMap::new(),
         }
     }
 
+    /// Whether `other` describes the same version of this file (size, mtime and hash)
+    pub fn same_version(&self, other: &FileEntry) -> bool {
+        self.size == other.size && self.modified == other.modified && self.hash == other.hash
+    }
+
     /// Get display name (filename only)
     pub fn name(&self) -> String {
         self.path
@@ -82,6 +92,58 @@ pub struct IndexStats {
     pub scan_duration_ms: u64,
     pub bad_sector_count: usize,
     pub error_count: usize,
+    /// Symbolic links and junctions met while scanning
+    pub symlinks: usize,
+    /// Followed links left alone because they loop back up the tree
+    pub link_loops: usize,
+    /// Followed links left alone because they lead out of the source
+    pub links_outside_source: usize,
+    /// Extra names of hard-linked files, indexed once
+    pub hardlinks_skipped: usize,
+    /// Files found inside archives
+    pub archive_members: usize,
+    /// Files found in shadow copies
+    #[serde(default)]
+    pub snapshot_files: usize,
+}
+
+/// What an incremental re-index found compared to the previous index
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IndexChanges {
+    pub added: usize,
+    pub modified: usize,
+    pub removed: usize,
+    pub unchanged: usize,
+}
+
+impl IndexChanges {
+    /// Compare freshly scanned entries against the previous index entries
+    pub fn between(previous: &HashMap<String, FileEntry>, current: &[FileEntry]) -> Self {
+        let mut changes = Self::default();
+        let mut matched = 0;
+
+        for entry in current {
+            match previous.get(entry.path.to_string_lossy().as_ref()) {
+                None => changes.added += 1,
+                Some(prev) => {
+                    matched += 1;
+                    if prev.same_version(entry) {
+                        changes.unchanged += 1;
+                    } else {
+                        changes.modified += 1;
+                    }
+                }
+            }
+        }
+
+        changes.removed = previous.len().saturating_sub(matched);
+        changes
+    }
+
+    /// Number of files that had to be (re)scanned or were dropped
+    pub fn changed(&self) -> usize {
+        self.added + self.modified + self.removed
+    }
 }
 
 /// The main file index
@@ -100,6 +162,9 @@ pub struct FileIndex {
     /// Bad sectors encountered during indexing
     #[serde(default)]
     bad_sectors: Vec<BadSector>,
+    /// Fingerprint of the source when it was indexed
+    #[serde(default)]
+    fingerprint: Option<SourceFingerprint>,
     /// Path to entry index for fast lookup
     #[serde(skip)]
     path_index: HashMap<String, usize>,
@@ -120,6 +185,7 @@ impl FileIndex {
             updated_at: Utc::now(),
             entries: Vec::new(),
             bad_sectors: Vec::new(),
+            fingerprint: None,
             path_index: HashMap::new(),
             total_bytes: AtomicU64::new(0),
         }
@@ -129,7 +195,11 @@ impl FileIndex {
     pub async fn load(path: &Path) -> Result<Self> {
         let owned_path = path.to_path_buf();
         let data = tokio::task::spawn_blocking(move || std::fs::read(&owned_path)).await??;
-        let mut index: Self = bincode::deserialize(&data)?;
+        let mut index: Self = bincode::deserialize(&data).map_err(|e| DrillError::FormatParse {
+            path: path.to_path_buf(),
+            format: "Diamond Drill index",
+            message: e.to_string(),
+        })?;
 
         // Rebuild path index
         index.path_index = index
@@ -232,11 +302,49 @@ impl FileIndex {
         self.entries.iter().filter(|e| predicate(e)).collect()
     }
 
+    /// When indexing of the source first started
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
     /// Get source path
     pub fn source(&self) -> &Path {
         &self.source
     }
 
+    /// Fingerprint of the source at indexing time, if recorded
+    pub fn fingerprint(&self) -> Option<&SourceFingerprint> {
+        self.fingerprint.as_ref()
+    }
+
+    pub fn set_fingerprint(&mut self, fingerprint: SourceFingerprint) {
+        self.fingerprint = Some(fingerprint);
+    }
+
+    /// Move the index to a new source location, e.g. after the same disk was
+    /// reattached under another drive letter. Paths under the old source are
+    /// rewritten to the same relative path under `new_source`.
+    pub fn rebase(&mut self, new_source: &Path) {
+        let old_source = std::mem::replace(&mut self.source, new_source.to_path_buf());
+        let rebase_path = |path: &mut PathBuf| {
+            if let Ok(relative) = path.strip_prefix(&old_source) {
+                *path = new_source.join(relative);
+            }
+        };
+        for entry in &mut self.entries {
+            rebase_path(&mut entry.path);
+        }
+        for bad in &mut self.bad_sectors {
+            rebase_path(&mut bad.file_path);
+        }
+        self.path_index = self
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(i, e)| (e.path.to_string_lossy().to_string(), i))
+            .collect();
+    }
+
     /// Add a bad sector record
     pub fn add_bad_sector(&mut self, bad_sector: BadSector) {
         self.bad_sectors.push(bad_sector);
@@ -303,6 +411,7 @@ mod tests {
             hash: None,
             has_bad_sectors: false,
             thumbnail: None,
+            metadata: Default::default(),
         };
 
         index.add_entry(entry);
@@ -317,6 +426,25 @@ mod tests {
         assert!(loaded.get_by_path("/test/photo.jpg").is_some());
     }
 
+    #[test]
+    fn test_rebase_to_new_source() {
+        let mut index = FileIndex::new(PathBuf::from("/media/old"));
+        let mut entry = FileEntry::new(
+            PathBuf::from("/media/old/dcim/photo.jpg"),
+            &std::fs::metadata(env!("CARGO_MANIFEST_DIR")).unwrap(),
+        );
+        entry.size = 10;
+        index.add_entry(entry);
+
+        index.rebase(Path::new("/media/new"));
+        assert_eq!(index.source(), Path::new("/media/new"));
+        assert!(index.get_by_path("/media/old/dcim/photo.jpg").is_none());
+        assert_eq!(
+            index.get_by_path("/media/new/dcim/photo.jpg").unwrap().size,
+            10
+        );
+    }
+
     #[tokio::test]
     async fn test_bad_sectors_persist() {
         let dir = tempdir().unwrap();
@@ -335,6 +463,7 @@ mod tests {
             hash: None,
             has_bad_sectors: true,
             thumbnail: None,
+            metadata: Default::default(),
         };
         index.add_entry(entry);
 
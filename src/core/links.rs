@@ -0,0 +1,216 @@
+//! Symbolic links, junctions and hard links
+//!
+//! A recovered volume can hold links that point back up the tree (and loop
+//! forever when followed), out of the source altogether (`/`, another
+//! user's profile), or many names for one file. [`LinkPolicy`] decides what
+//! the scanner does with symlinks and Windows junctions; followed links
+//! that loop or lead outside the source are left alone. Hard links are
+//! indexed once, under the first name found, with the other names in the
+//! entry's `hardlinks` metadata.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use walkdir::DirEntry;
+
+use super::index::FileEntry;
+
+/// What the scanner does with symbolic links and junctions
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LinkPolicy {
+    /// Leave links out of the index
+    #[default]
+    Skip,
+    /// Index what links point to, as long as it is inside the source
+    Follow,
+    /// Index each link itself, with its target in `link_target` metadata
+    Record,
+}
+
+/// Kind of link in `link_kind` metadata
+pub fn link_kind(path: &Path) -> &'static str {
+    if is_junction(path) {
+        "junction"
+    } else {
+        "symlink"
+    }
+}
+
+/// Whether the link at `path` resolves to somewhere outside `root` (which
+/// must be canonical). Dangling links lead nowhere.
+pub fn leads_outside(path: &Path, root: &Path) -> bool {
+    std::fs::canonicalize(path).is_ok_and(|target| !target.starts_with(root))
+}
+
+/// Entry for a link itself (`LinkPolicy::Record`), without touching its target
+pub fn link_entry(entry: &DirEntry) -> Result<FileEntry> {
+    let path = entry.path();
+    let metadata = std::fs::symlink_metadata(path)?;
+    let mut file_entry = FileEntry::new(path.to_path_buf(), &metadata);
+    let target = std::fs::read_link(path)?;
+    file_entry
+        .metadata
+        .insert("link_target".into(), target.display().to_string());
+    file_entry
+        .metadata
+        .insert("link_kind".into(), link_kind(path).into());
+    Ok(file_entry)
+}
+
+/// Drop every name of a hard-linked file but the first from `entries`.
+/// Returns the other names, keyed by the name that was kept.
+pub fn dedup_hardlinks(entries: &mut Vec<DirEntry>) -> HashMap<PathBuf, Vec<PathBuf>> {
+    // The inode from the directory listing narrows it down without a stat
+    // per file; only files sharing one get their device checked
+    let mut by_ino: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (i, entry) in entries.iter().enumerate() {
+        if let Some(ino) = dir_entry_ino(entry) {
+            by_ino.entry(ino).or_default().push(i);
+        }
+    }
+
+    let mut others: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+    let mut dropped = vec![false; entries.len()];
+    for indices in by_ino.into_values().filter(|i| i.len() > 1) {
+        let mut first_by_id: HashMap<(u64, u64), usize> = HashMap::new();
+        for i in indices {
+            let Some(id) = file_id(&entries[i]) else {
+                continue;
+            };
+            match first_by_id.get(&id) {
+                Some(&first) => {
+                    others
+                        .entry(entries[first].path().to_path_buf())
+                        .or_default()
+                        .push(entries[i].path().to_path_buf());
+                    dropped[i] = true;
+                }
+                None => {
+                    first_by_id.insert(id, i);
+                }
+            }
+        }
+    }
+
+    let mut i = 0;
+    entries.retain(|_| {
+        i += 1;
+        !dropped[i - 1]
+    });
+    others
+}
+
+#[cfg(unix)]
+fn dir_entry_ino(entry: &DirEntry) -> Option<u64> {
+    use walkdir::DirEntryExt;
+    Some(entry.ino())
+}
+
+#[cfg(unix)]
+fn file_id(entry: &DirEntry) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    let metadata = entry.metadata().ok()?;
+    (metadata.nlink() > 1).then(|| (metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn dir_entry_ino(_entry: &DirEntry) -> Option<u64> {
+    // File IDs need a handle per file on Windows; hard links there are rare
+    None
+}
+
+#[cfg(not(unix))]
+fn file_id(_entry: &DirEntry) -> Option<(u64, u64)> {
+    None
+}
+
+#[cfg(windows)]
+fn is_junction(path: &Path) -> bool {
+    use std::os::windows::fs::OpenOptionsExt;
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::Storage::FileSystem::{
+        FileAttributeTagInfo, GetFileInformationByHandleEx, FILE_ATTRIBUTE_TAG_INFO,
+        FILE_FLAG_BACKUP_SEMANTICS, FILE_FLAG_OPEN_REPARSE_POINT,
+    };
+
+    const IO_REPARSE_TAG_MOUNT_POINT: u32 = 0xA000_0003;
+
+    let Ok(file) = std::fs::OpenOptions::new()
+        .access_mode(0)
+        .custom_flags(FILE_FLAG_OPEN_REPARSE_POINT | FILE_FLAG_BACKUP_SEMANTICS)
+        .open(path)
+    else {
+        return false;
+    };
+    let mut info = FILE_ATTRIBUTE_TAG_INFO {
+        FileAttributes: 0,
+        ReparseTag: 0,
+    };
+    // SAFETY: the handle is open and `info` is the size we pass
+    let ok = unsafe {
+        GetFileInformationByHandleEx(
+            file.as_raw_handle() as _,
+            FileAttributeTagInfo,
+            &mut info as *mut _ as *mut _,
+            std::mem::size_of::<FILE_ATTRIBUTE_TAG_INFO>() as u32,
+        )
+    };
+    ok != 0 && info.ReparseTag == IO_REPARSE_TAG_MOUNT_POINT
+}
+
+#[cfg(not(windows))]
+fn is_junction(_path: &Path) -> bool {
+    false
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use walkdir::WalkDir;
+
+    #[test]
+    fn test_dedup_hardlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("a.txt"), "same file").unwrap();
+        std::fs::hard_link(root.join("a.txt"), root.join("b.txt")).unwrap();
+        std::fs::write(root.join("c.txt"), "other file").unwrap();
+
+        let mut entries: Vec<DirEntry> = WalkDir::new(root)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .collect();
+        let others = dedup_hardlinks(&mut entries);
+
+        let names: Vec<_> = entries.iter().map(|e| e.file_name().to_owned()).collect();
+        assert_eq!(names, ["a.txt", "c.txt"]);
+        assert_eq!(others[&root.join("a.txt")], [root.join("b.txt")]);
+    }
+
+    #[test]
+    fn test_link_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::os::unix::fs::symlink("/nowhere/target.bin", root.join("dangling.bin")).unwrap();
+
+        let entry = WalkDir::new(root)
+            .min_depth(1)
+            .into_iter()
+            .next()
+            .unwrap()
+            .unwrap();
+        let file_entry = link_entry(&entry).unwrap();
+        assert_eq!(file_entry.metadata["link_target"], "/nowhere/target.bin");
+        assert_eq!(file_entry.metadata["link_kind"], "symlink");
+        assert!(!leads_outside(&root.join("dangling.bin"), root));
+
+        std::os::unix::fs::symlink("/", root.join("escape")).unwrap();
+        assert!(leads_outside(
+            &root.join("escape"),
+            &root.canonicalize().unwrap()
+        ));
+    }
+}
@@ -0,0 +1,243 @@
+//! Rich metadata extraction for indexed files
+//!
+//! Pulls EXIF (capture date, camera, GPS) from photos, ID3/Vorbis tags from
+//! audio and the document information dictionary from PDFs into flat
+//! `key -> value` pairs stored on [`FileEntry`](super::FileEntry) and
+//! matched by `search --meta key=value`.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::FileType;
+
+/// PDFs larger than this are not parsed for their info dictionary
+const MAX_PDF_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Extract whatever metadata the file's format carries.
+///
+/// Unreadable or malformed files simply yield fewer (or no) keys.
+pub fn extract_metadata(path: &Path, file_type: FileType) -> HashMap<String, String> {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let mut meta = HashMap::new();
+    match (file_type, ext.as_str()) {
+        (FileType::Image, _) => exif_metadata(path, &mut meta),
+        (FileType::Audio, "mp3" | "flac" | "wav") => audio_metadata(path, &mut meta),
+        (_, "pdf") => pdf_metadata(path, &mut meta),
+        _ => {}
+    }
+    meta.retain(|_, v| !v.is_empty());
+    meta
+}
+
+/// Whether `entry_value` satisfies a `--meta` filter value
+/// (case-insensitive substring)
+pub fn meta_value_matches(entry_value: &str, wanted: &str) -> bool {
+    entry_value.to_lowercase().contains(&wanted.to_lowercase())
+}
+
+/// Parse a `--meta key=value` argument
+pub fn parse_meta_filter(arg: &str) -> Result<(String, String), String> {
+    match arg.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_lowercase(), value.trim().to_string()))
+        }
+        _ => Err(format!("expected key=value, got '{}'", arg)),
+    }
+}
+
+fn exif_metadata(path: &Path, meta: &mut HashMap<String, String>) {
+    let Ok(file) = std::fs::File::open(path) else {
+        return;
+    };
+    let mut reader = std::io::BufReader::new(file);
+    let Ok(exif) = exif::Reader::new().read_from_container(&mut reader) else {
+        return;
+    };
+
+    let ascii = |tag: exif::Tag| match exif.get_field(tag, exif::In::PRIMARY).map(|f| &f.value) {
+        Some(exif::Value::Ascii(parts)) => parts
+            .first()
+            .map(|s| String::from_utf8_lossy(s).trim().to_string()),
+        _ => None,
+    };
+    let date = ascii(exif::Tag::DateTimeOriginal).or_else(|| ascii(exif::Tag::DateTime));
+    if let Some(date) = date {
+        // "2023:07:14 18:02:11" -> "2023-07-14 18:02:11"
+        let date = match date.split_once(' ') {
+            Some((day, time)) => format!("{} {}", day.replace(':', "-"), time),
+            None => date.replace(':', "-"),
+        };
+        meta.insert("date_taken".into(), date);
+    }
+    if let Some(make) = ascii(exif::Tag::Make) {
+        meta.insert("camera_make".into(), make);
+    }
+    if let Some(model) = ascii(exif::Tag::Model) {
+        meta.insert("camera_model".into(), model);
+    }
+
+    let coordinate = |value: exif::Tag, reference: exif::Tag, negative: &str| {
+        let field = exif.get_field(value, exif::In::PRIMARY)?;
+        let exif::Value::Rational(parts) = &field.value else {
+            return None;
+        };
+        let degrees = parts
+            .iter()
+            .zip([1.0, 60.0, 3600.0])
+            .map(|(r, div)| r.to_f64() / div)
+            .sum::<f64>();
+        let sign = match ascii(reference) {
+            Some(r) if r.eq_ignore_ascii_case(negative) => -1.0,
+            _ => 1.0,
+        };
+        degrees
+            .is_finite()
+            .then(|| format!("{:.6}", sign * degrees))
+    };
+    if let (Some(lat), Some(lon)) = (
+        coordinate(exif::Tag::GPSLatitude, exif::Tag::GPSLatitudeRef, "S"),
+        coordinate(exif::Tag::GPSLongitude, exif::Tag::GPSLongitudeRef, "W"),
+    ) {
+        meta.insert("gps_latitude".into(), lat);
+        meta.insert("gps_longitude".into(), lon);
+    }
+}
+
+fn audio_metadata(path: &Path, meta: &mut HashMap<String, String>) {
+    use symphonia::core::formats::FormatOptions;
+    use symphonia::core::io::MediaSourceStream;
+    use symphonia::core::meta::{MetadataOptions, StandardTagKey};
+    use symphonia::core::probe::Hint;
+
+    let Ok(file) = std::fs::File::open(path) else {
+        return;
+    };
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+    let Ok(mut probed) = symphonia::default::get_probe().format(
+        &hint,
+        stream,
+        &FormatOptions::default(),
+        &MetadataOptions::default(),
+    ) else {
+        return;
+    };
+
+    // ID3v2 is read while probing; Vorbis comments and RIFF INFO belong to
+    // the container. Later (container) tags win.
+    let mut tags = Vec::new();
+    if let Some(rev) = probed.metadata.get().as_ref().and_then(|m| m.current()) {
+        tags.extend(rev.tags().iter().cloned());
+    }
+    if let Some(rev) = probed.format.metadata().current() {
+        tags.extend(rev.tags().iter().cloned());
+    }
+    for tag in tags {
+        let key = match tag.std_key {
+            Some(StandardTagKey::TrackTitle) => "title",
+            Some(StandardTagKey::Artist) => "artist",
+            Some(StandardTagKey::Album) => "album",
+            Some(StandardTagKey::Date | StandardTagKey::ReleaseDate) => "date",
+            Some(StandardTagKey::Genre) => "genre",
+            Some(StandardTagKey::TrackNumber) => "track",
+            _ => continue,
+        };
+        meta.insert(key.into(), tag.value.to_string().trim().to_string());
+    }
+
+    if let Some(params) = probed.format.default_track().map(|t| &t.codec_params) {
+        if let (Some(frames), Some(rate)) = (params.n_frames, params.sample_rate) {
+            if rate > 0 {
+                meta.insert("duration".into(), format!("{}", frames / u64::from(rate)));
+            }
+        }
+    }
+}
+
+fn pdf_metadata(path: &Path, meta: &mut HashMap<String, String>) {
+    if std::fs::metadata(path).map_or(true, |m| m.len() > MAX_PDF_SIZE) {
+        return;
+    }
+    // Page content is irrelevant here; dropping streams keeps memory low
+    let doc = match lopdf::Document::load_filtered(path, |id, obj| {
+        (!matches!(obj, lopdf::Object::Stream(_))).then(|| (id, obj.clone()))
+    }) {
+        Ok(doc) => doc,
+        Err(_) => return,
+    };
+    let Some(info) = doc.trailer.get(b"Info").ok().and_then(|obj| match obj {
+        lopdf::Object::Reference(id) => doc.get_dictionary(*id).ok(),
+        lopdf::Object::Dictionary(dict) => Some(dict),
+        _ => None,
+    }) else {
+        return;
+    };
+
+    for (pdf_key, key) in [
+        (&b"Title"[..], "title"),
+        (b"Author", "author"),
+        (b"Subject", "subject"),
+        (b"Creator", "creator"),
+        (b"Producer", "producer"),
+        (b"CreationDate", "created"),
+    ] {
+        if let Some(value) = info
+            .get(pdf_key)
+            .ok()
+            .and_then(|obj| lopdf::decode_text_string(obj).ok())
+        {
+            meta.insert(key.into(), value.trim().to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pdf_info_and_meta_filter() {
+        use lopdf::{dictionary, Document, Object};
+
+        let mut doc = Document::with_version("1.5");
+        let info = doc.add_object(dictionary! {
+            "Title" => Object::string_literal("Quarterly Report"),
+            "Author" => Object::string_literal("J. Doe"),
+        });
+        let pages = doc.add_object(dictionary! {
+            "Type" => "Pages",
+            "Kids" => Vec::<Object>::new(),
+            "Count" => 0,
+        });
+        let catalog = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages });
+        doc.trailer.set("Root", catalog);
+        doc.trailer.set("Info", info);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.pdf");
+        doc.save(&path).unwrap();
+
+        let meta = extract_metadata(&path, FileType::Document);
+        assert_eq!(meta.get("title").unwrap(), "Quarterly Report");
+        assert_eq!(meta.get("author").unwrap(), "J. Doe");
+
+        assert!(meta_value_matches(&meta["title"], "quarterly"));
+        assert!(!meta_value_matches(&meta["author"], "smith"));
+        assert_eq!(
+            parse_meta_filter("Camera_Model = iPhone").unwrap(),
+            ("camera_model".to_string(), "iPhone".to_string())
+        );
+        assert!(parse_meta_filter("no-equals").is_err());
+
+        // Files without metadata yield nothing
+        let txt = dir.path().join("notes.txt");
+        std::fs::write(&txt, "hello").unwrap();
+        assert!(extract_metadata(&txt, FileType::Document).is_empty());
+    }
+}
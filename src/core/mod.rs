@@ -2,13 +2,60 @@
 //!
 //! Contains the main engine, indexing, and file operations.
 
+mod archives;
+mod compressed;
+mod content;
 mod engine;
+mod fingerprint;
+mod hashset;
 mod index;
+mod links;
+mod metadata;
+mod names;
 mod scanner;
-
-pub use engine::DrillEngine;
-pub use index::{FileEntry, FileIndex, IndexStats};
+mod selection;
+mod snapshots;
+#[cfg(feature = "sqlite")]
+mod sqlite_index;
+mod timezone;
+mod translit;
+mod trash;
+
+pub use archives::{
+    is_archive_member, member_entries, ArchiveKind, ArchiveMember, ARCHIVE_KEY, ARCHIVE_MEMBER_KEY,
+};
+pub use compressed::{decompressed_name, CompressedFormat};
+pub use content::{
+    content_regex, is_searchable, search_entries, search_file, ContentMatch, MAX_MATCHES_PER_FILE,
+};
+pub use engine::{run_dedup_compare, DrillEngine};
+pub(crate) use engine::parse_size_str;
+pub use fingerprint::{FingerprintRecord, FingerprintRegistry, SourceFingerprint, SourceKind};
+pub use hashset::{
+    compute_digests, known_bad_set, Digests, HashAlgorithm, HashSetCounts, HashSetKind,
+    HashSetMatch, HashSets, KnownHashSet, MultiHasher, HASHSET_META_KEY,
+};
+pub use index::{FileEntry, FileIndex, IndexChanges, IndexStats};
+pub use links::LinkPolicy;
+pub use metadata::{extract_metadata, meta_value_matches, parse_meta_filter};
+pub use names::{NameRepair, RepairedName, DEFAULT_CODEPAGES};
 pub use scanner::{ScanOptions, Scanner};
+pub use selection::{Selection, DEFAULT_SELECTION_FILE, SELECTION_EXTENSION};
+pub use snapshots::{
+    is_snapshot_file, snapshot_entries, SnapshotFile, SNAPSHOTS_DIR, SNAPSHOT_CREATED_KEY,
+    SNAPSHOT_KEY,
+};
+#[cfg(feature = "sqlite")]
+pub use sqlite_index::{
+    dedup_sqlite_index, is_sqlite_index, search_sqlite_content, search_sqlite_entries,
+    search_sqlite_index, IndexQuery, SqliteIndex,
+};
+pub use timezone::{
+    display_timezone, exfat_to_utc, fat_to_utc, filetime_to_utc, format_timestamp,
+    set_display_timezone, DisplayTz,
+};
+pub use trash::{trash_info, TrashInfo};
+pub use translit::transliterate;
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
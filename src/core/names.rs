@@ -0,0 +1,294 @@
+//! Filename encoding repair
+//!
+//! Names recovered from old FAT and ext volumes are often in a legacy
+//! codepage, and come out as mojibake once mounted: CP1251 "ДОКУМЕНТ"
+//! read as Latin-1 is "ÄÎÊÓÌÅÍÒ", UTF-8 "документ" read as Windows-1252
+//! is "Ð´Ð¾ÐºÑƒÐ¼ÐµÐ½Ñ‚", and ext names that aren't UTF-8 at all are raw
+//! bytes. [`NameRepair`] takes the bytes the name was mis-read from, decodes
+//! them with each codepage hint and keeps the decoding that reads most like
+//! real text, if it beats the name as it is.
+//!
+//! Repaired entries carry `repaired_name`, `name_encoding` and `name_raw`
+//! (the on-disk name as hex) in [`FileEntry::metadata`](super::FileEntry).
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+
+use anyhow::Result;
+use encoding_rs::{Encoding, WINDOWS_1252};
+
+/// Codepages tried when no hints are given: double-encoded UTF-8, then the
+/// usual DOS/Windows codepages for Cyrillic, Japanese, Chinese and Korean
+pub const DEFAULT_CODEPAGES: &[&str] = &[
+    "utf-8",
+    "windows-1251",
+    "ibm866",
+    "koi8-r",
+    "shift_jis",
+    "gbk",
+    "big5",
+    "euc-kr",
+];
+
+/// A repaired name and the codepage it was decoded from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepairedName {
+    pub name: String,
+    pub encoding: &'static str,
+    /// The name's bytes on disk
+    pub raw: Vec<u8>,
+}
+
+impl RepairedName {
+    /// Store as `FileEntry` metadata keys
+    pub fn apply(&self, meta: &mut HashMap<String, String>) {
+        meta.insert("repaired_name".into(), self.name.clone());
+        meta.insert("name_encoding".into(), self.encoding.into());
+        meta.insert("name_raw".into(), hex::encode(&self.raw));
+    }
+}
+
+/// Mojibake detector and fixer for file names
+#[derive(Debug, Clone)]
+pub struct NameRepair {
+    hints: Vec<&'static Encoding>,
+}
+
+impl Default for NameRepair {
+    fn default() -> Self {
+        Self::new::<&str>(&[]).expect("default codepages are known")
+    }
+}
+
+impl NameRepair {
+    /// Repair with these codepages, tried in order (WHATWG labels such as
+    /// `windows-1251`, `cp866`, `shift_jis`). Empty means [`DEFAULT_CODEPAGES`].
+    pub fn new<S: AsRef<str>>(codepages: &[S]) -> Result<Self> {
+        let labels: Vec<&str> = if codepages.is_empty() {
+            DEFAULT_CODEPAGES.to_vec()
+        } else {
+            codepages.iter().map(AsRef::as_ref).collect()
+        };
+        let hints = labels
+            .iter()
+            .map(|label| {
+                Encoding::for_label(label.trim().as_bytes())
+                    .ok_or_else(|| anyhow::anyhow!("Unknown codepage: {}", label))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { hints })
+    }
+
+    /// The repaired form of `name`, or None when it looks right already
+    pub fn repair(&self, name: &OsStr) -> Option<RepairedName> {
+        let (raw, misread) = match name.to_str() {
+            Some(name) if name.is_ascii() => return None,
+            // Valid text: only mojibake if it maps back to single bytes
+            Some(name) => (name.as_bytes().to_vec(), Some(name.to_string())),
+            // Not UTF-8 (ext volumes); only Unix hands out such names
+            None => (os_bytes(name)?, None),
+        };
+        let bytes = match &misread {
+            Some(name) => latin1_bytes(name)?,
+            None => raw.clone(),
+        };
+        let current = misread.as_deref().map_or(i32::MIN, plausibility);
+
+        let (name, encoding) = self
+            .hints
+            .iter()
+            .filter_map(|encoding| {
+                let decoded =
+                    encoding.decode_without_bom_handling_and_without_replacement(&bytes)?;
+                let usable = !decoded
+                    .chars()
+                    .any(|c| c.is_control() || c == '/' || c == '\\');
+                usable.then(|| (decoded.into_owned(), encoding.name()))
+            })
+            .filter(|(decoded, _)| Some(decoded) != misread.as_ref())
+            .map(|(decoded, encoding)| (plausibility(&decoded), decoded, encoding))
+            // First hint wins ties
+            .fold(
+                None,
+                |best: Option<(i32, String, &str)>, candidate| match best {
+                    Some(best) if best.0 >= candidate.0 => Some(best),
+                    _ => Some(candidate),
+                },
+            )
+            .filter(|(score, _, _)| *score > current)
+            .map(|(_, name, encoding)| (name, encoding))?;
+
+        Some(RepairedName {
+            name,
+            encoding,
+            raw,
+        })
+    }
+}
+
+#[cfg(unix)]
+fn os_bytes(name: &OsStr) -> Option<Vec<u8>> {
+    use std::os::unix::ffi::OsStrExt;
+    Some(name.as_bytes().to_vec())
+}
+
+#[cfg(not(unix))]
+fn os_bytes(_name: &OsStr) -> Option<Vec<u8>> {
+    // Windows names are UTF-16 and always decode
+    None
+}
+
+/// The bytes `name` was read from as Latin-1 / Windows-1252, if it could
+/// have been
+fn latin1_bytes(name: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(name.len());
+    let mut buf = [0u8; 4];
+    for c in name.chars() {
+        if (c as u32) < 0x100 {
+            bytes.push(c as u32 as u8);
+            continue;
+        }
+        let (encoded, _, unmappable) = WINDOWS_1252.encode(c.encode_utf8(&mut buf));
+        if unmappable || encoded.len() != 1 {
+            return None;
+        }
+        bytes.push(encoded[0]);
+    }
+    Some(bytes)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Script {
+    Ascii,
+    Latin,
+    Greek,
+    Cyrillic,
+    Cjk,
+    Hangul,
+    Other,
+}
+
+fn script(c: char) -> Script {
+    match c {
+        _ if c.is_ascii_alphabetic() => Script::Ascii,
+        '\u{00C0}'..='\u{024F}' => Script::Latin,
+        '\u{0370}'..='\u{03FF}' => Script::Greek,
+        '\u{0400}'..='\u{052F}' => Script::Cyrillic,
+        '\u{3040}'..='\u{30FF}' | '\u{3400}'..='\u{9FFF}' | '\u{FF66}'..='\u{FF9F}' => Script::Cjk,
+        '\u{AC00}'..='\u{D7AF}' | '\u{1100}'..='\u{11FF}' => Script::Hangul,
+        _ => Script::Other,
+    }
+}
+
+/// Symbols that turn up in mojibake but rarely in real names
+fn is_suspicious(c: char) -> bool {
+    matches!(c,
+        '\u{0080}'..='\u{00BF}'
+        | '\u{00D7}' | '\u{00F7}'
+        | '\u{0152}'..='\u{0153}' | '\u{0160}'..='\u{0161}' | '\u{0178}' | '\u{017D}'..='\u{017E}'
+        | '\u{0192}' | '\u{02C6}' | '\u{02DC}'
+        | '\u{2013}'..='\u{2044}' | '\u{20AC}' | '\u{2122}'
+        | '\u{2500}'..='\u{25FF}'
+        | '\u{E000}'..='\u{F8FF}' | '\u{FFFD}')
+}
+
+/// How much `name` reads like real text: letters of a script count for it,
+/// stray symbols and script changes inside a word count against it. Runs of
+/// accented Latin letters with no plain letter next to them are what
+/// single-byte codepages look like read as Latin-1, so they count for
+/// nothing.
+fn plausibility(name: &str) -> i32 {
+    let chars: Vec<char> = name.chars().collect();
+    let mut score = 0;
+    for (i, &c) in chars.iter().enumerate() {
+        if is_suspicious(c) {
+            score -= 3;
+            continue;
+        }
+        let here = script(c);
+        let prev = i.checked_sub(1).map(|i| script(chars[i]));
+        let next = chars.get(i + 1).map(|&c| script(c));
+        match here {
+            Script::Ascii | Script::Other => {}
+            Script::Latin => {
+                if prev == Some(Script::Ascii) || next == Some(Script::Ascii) {
+                    score += 1;
+                }
+            }
+            _ => score += 1,
+        }
+        // A word switching between two scripts
+        let letter = |s: Script| s != Script::Other;
+        if let Some(prev) = prev.filter(|&p| letter(p) && letter(here) && p != here) {
+            let latin = |s: Script| matches!(s, Script::Ascii | Script::Latin);
+            if !(latin(prev) && latin(here)) {
+                score -= 3;
+            }
+        }
+    }
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repaired(name: &str) -> Option<String> {
+        NameRepair::default()
+            .repair(OsStr::new(name))
+            .map(|r| r.name)
+    }
+
+    #[test]
+    fn test_repairs_mojibake() {
+        // UTF-8 read as Windows-1252
+        assert_eq!(
+            repaired("Ð´Ð¾ÐºÑƒÐ¼ÐµÐ½Ñ‚.doc").as_deref(),
+            Some("документ.doc")
+        );
+        // CP1251 read as Latin-1
+        assert_eq!(repaired("ÄÎÊÓÌÅÍÒ.txt").as_deref(), Some("ДОКУМЕНТ.txt"));
+        // Shift-JIS read as Windows-1252
+        let sjis = encoding_rs::SHIFT_JIS.encode("写真フォルダ.jpg").0;
+        let misread: String = sjis.iter().map(|&b| b as char).collect();
+        assert_eq!(repaired(&misread).as_deref(), Some("写真フォルダ.jpg"));
+    }
+
+    #[test]
+    fn test_leaves_real_names_alone() {
+        for name in [
+            "plain.txt",
+            "Résumé.pdf",
+            "Café Müller.jpg",
+            "документ.doc",
+            "写真.png",
+        ] {
+            assert_eq!(repaired(name), None, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_codepage_hints() {
+        let repair = NameRepair::new(&["koi8-r"]).unwrap();
+        let koi8 = encoding_rs::KOI8_R.encode("отчёт.xls").0;
+        let misread: String = koi8.iter().map(|&b| b as char).collect();
+        let fixed = repair.repair(OsStr::new(&misread)).unwrap();
+        assert_eq!(fixed.name, "отчёт.xls");
+        assert_eq!(fixed.encoding, "KOI8-R");
+        assert_eq!(fixed.raw, misread.as_bytes());
+
+        assert!(NameRepair::new(&["no-such-codepage"]).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_raw_bytes() {
+        use std::os::unix::ffi::OsStrExt;
+        let cp1251 = encoding_rs::WINDOWS_1251.encode("Фото.jpg").0;
+        let fixed = NameRepair::default()
+            .repair(OsStr::from_bytes(&cp1251))
+            .unwrap();
+        assert_eq!(fixed.name, "Фото.jpg");
+        assert_eq!(fixed.raw, cp1251.as_ref());
+    }
+}
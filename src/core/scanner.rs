@@ -2,21 +2,29 @@
 //!
 //! Uses rayon for parallel traversal and handles I/O errors gracefully.
 
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Instant;
 
 use anyhow::Result;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use crossbeam_channel;
 use parking_lot::RwLock;
 use rayon::prelude::*;
 use tokio::sync::mpsc;
 use walkdir::{DirEntry, WalkDir};
 
+use super::archives::member_entries;
 use super::index::FileEntry;
+use super::links::{dedup_hardlinks, leads_outside, link_entry, LinkPolicy};
+use super::metadata::extract_metadata;
+use super::names::NameRepair;
+use super::snapshots::snapshot_entries;
+use super::trash::trash_info;
 use super::BadSector;
+use crate::events::{self, Event};
 
 /// Scanner configuration options
 #[derive(Debug, Clone)]
@@ -33,6 +41,23 @@ pub struct ScanOptions {
     pub workers: usize,
     /// Stay on the same filesystem (avoid crossing mount points)
     pub same_file_system: bool,
+    /// Entries from a previous index, keyed by path. Files whose size and
+    /// mtime still match are reused instead of being re-read.
+    pub previous: Option<Arc<HashMap<String, FileEntry>>>,
+    /// Also re-hash reused files that have a stored hash and rescan them on mismatch
+    pub verify_hash: bool,
+    /// Extract EXIF/ID3/PDF metadata into each entry
+    pub extract_metadata: bool,
+    /// Repair mis-encoded file names into `repaired_name` metadata
+    pub repair_names: Option<NameRepair>,
+    /// What to do with symbolic links and junctions
+    pub links: LinkPolicy,
+    /// Index every name of a hard-linked file instead of only the first
+    pub all_hardlinks: bool,
+    /// Also index the files inside ZIP, tar and 7z archives
+    pub archives: bool,
+    /// Also index the files in the source volume's shadow copies
+    pub snapshots: bool,
 }
 
 impl Default for ScanOptions {
@@ -44,6 +69,14 @@ impl Default for ScanOptions {
             extensions: None,
             workers: num_cpus::get(),
             same_file_system: false,
+            previous: None,
+            verify_hash: false,
+            extract_metadata: false,
+            repair_names: None,
+            links: LinkPolicy::default(),
+            all_hardlinks: false,
+            archives: false,
+            snapshots: false,
         }
     }
 }
@@ -57,6 +90,20 @@ pub struct ScanStats {
     pub errors: usize,
     pub bad_sectors: usize,
     pub duration_ms: u64,
+    /// Files reused unchanged from the previous index
+    pub unchanged: usize,
+    /// Symbolic links and junctions found
+    pub symlinks: usize,
+    /// Followed links not descended into because they loop back up the tree
+    pub link_loops: usize,
+    /// Followed links not descended into because they lead out of the source
+    pub links_outside_source: usize,
+    /// Extra names of hard-linked files, not indexed separately
+    pub hardlinks_skipped: usize,
+    /// Files found inside archives
+    pub archive_members: usize,
+    /// Files found in shadow copies
+    pub snapshot_files: usize,
 }
 
 /// Parallel file system scanner
@@ -91,11 +138,18 @@ impl Scanner {
         let bytes_total = Arc::new(AtomicU64::new(0));
         let errors = Arc::new(AtomicUsize::new(0));
         let bad_sector_count = Arc::new(AtomicUsize::new(0));
+        let unchanged = Arc::new(AtomicUsize::new(0));
+        let archive_members = Arc::new(AtomicUsize::new(0));
+
+        let symlinks = Arc::new(AtomicUsize::new(0));
+        let link_loops = Arc::new(AtomicUsize::new(0));
+        let links_outside = Arc::new(AtomicUsize::new(0));
 
         // Collect directory entries in a single pass (count dirs + collect files)
-        let entries: Vec<DirEntry> = {
+        let mut entries: Vec<DirEntry> = {
+            let follow = options.links == LinkPolicy::Follow;
             let mut walker = WalkDir::new(&options.source)
-                .follow_links(false)
+                .follow_links(follow)
                 .same_file_system(options.same_file_system);
 
             if let Some(depth) = options.max_depth {
@@ -103,24 +157,45 @@ impl Scanner {
             }
 
             let source_path = options.source.clone();
+            let root = std::fs::canonicalize(&options.source).unwrap_or_default();
             let dirs_found_ref = Arc::clone(&dirs_found);
+            let symlinks_ref = Arc::clone(&symlinks);
+            let links_outside_ref = Arc::clone(&links_outside);
+            let link_loops_ref = Arc::clone(&link_loops);
+            let record_links = options.links == LinkPolicy::Record;
 
             walker
                 .into_iter()
                 .filter_entry(move |e| {
-                    if options.skip_hidden {
-                        e.path() == source_path || !is_hidden(e)
-                    } else {
-                        true
+                    if options.skip_hidden && e.path() != source_path && is_hidden(e) {
+                        return false;
+                    }
+                    if e.path_is_symlink() && e.depth() > 0 {
+                        symlinks_ref.fetch_add(1, Ordering::Relaxed);
+                        // Followed links must not lead out of the source
+                        if follow && leads_outside(e.path(), &root) {
+                            links_outside_ref.fetch_add(1, Ordering::Relaxed);
+                            return false;
+                        }
+                    }
+                    true
+                })
+                .filter_map(|e| match e {
+                    Ok(e) => Some(e),
+                    Err(e) => {
+                        if e.loop_ancestor().is_some() {
+                            link_loops_ref.fetch_add(1, Ordering::Relaxed);
+                        }
+                        None
                     }
                 })
-                .filter_map(|e| e.ok())
                 .filter(|e| {
                     if e.file_type().is_dir() {
                         dirs_found_ref.fetch_add(1, Ordering::Relaxed);
                         return false; // don't include dirs in the file list
                     }
-                    if !e.file_type().is_file() {
+                    let recorded_link = record_links && e.file_type().is_symlink();
+                    if !e.file_type().is_file() && !recorded_link {
                         return false;
                     }
                     if let Some(ref exts) = options.extensions {
@@ -140,14 +215,22 @@ impl Scanner {
                 .collect()
         };
 
+        let hardlinks = if options.all_hardlinks {
+            HashMap::new()
+        } else {
+            dedup_hardlinks(&mut entries)
+        };
+        let hardlinks_skipped = hardlinks.values().map(Vec::len).sum();
+
         // Process entries in parallel with rayon
         let (sender, receiver) = crossbeam_channel::bounded::<FileEntry>(1000);
 
-        // Spawn a task to forward from crossbeam to tokio mpsc
+        // Forward from crossbeam to tokio mpsc on a blocking thread; an async
+        // task could sit behind this one, which blocks while rayon runs
         let tx_clone = tx.clone();
-        let forward_handle = tokio::spawn(async move {
+        let forward_handle = tokio::task::spawn_blocking(move || {
             while let Ok(entry) = receiver.recv() {
-                if tx_clone.send(entry).await.is_err() {
+                if tx_clone.blocking_send(entry).is_err() {
                     break;
                 }
             }
@@ -155,18 +238,87 @@ impl Scanner {
 
         // Process in parallel
         {
+            let previous = options.previous.clone();
+            let verify_hash = options.verify_hash;
+            let with_metadata = options.extract_metadata;
+            let repair_names = options.repair_names.clone();
+            let links = options.links;
+            let hardlinks = &hardlinks;
+            let archives = options.archives;
+            let archive_members = Arc::clone(&archive_members);
+            let unchanged = Arc::clone(&unchanged);
             let files_found = Arc::clone(&files_found);
             let bytes_total = Arc::clone(&bytes_total);
             let errors = Arc::clone(&errors);
             let bad_sector_count = Arc::clone(&bad_sector_count);
             let bad_sectors = Arc::clone(&bad_sectors);
             let sender = sender.clone();
+            let send_members = |archive: &FileEntry| {
+                if !archives {
+                    return;
+                }
+                match member_entries(archive) {
+                    Ok(members) => {
+                        archive_members.fetch_add(members.len(), Ordering::Relaxed);
+                        for member in members {
+                            let _ = sender.send(member);
+                        }
+                    }
+                    Err(e) => {
+                        tracing::debug!("Not looking inside {}: {:#}", archive.path.display(), e)
+                    }
+                }
+            };
 
             entries.par_iter().for_each(|entry| {
-                match process_entry(entry, &bad_sectors, &bad_sector_count) {
-                    Ok(file_entry) => {
+                if let Some(mut prev) = previous
+                    .as_deref()
+                    .and_then(|prev| reuse_unchanged(entry, prev, verify_hash))
+                {
+                    // Entries indexed before --metadata was used get it now
+                    if with_metadata && prev.metadata.is_empty() {
+                        prev.metadata = extract_metadata(&prev.path, prev.file_type);
+                    }
+                    if !prev.metadata.contains_key("original_path") {
+                        if let Some(info) = trash_info(&prev.path) {
+                            info.apply(&mut prev.metadata);
+                        }
+                    }
+                    if !prev.metadata.contains_key("repaired_name") {
+                        repair_name(repair_names.as_ref(), &mut prev);
+                    }
+                    files_found.fetch_add(1, Ordering::Relaxed);
+                    bytes_total.fetch_add(prev.size, Ordering::Relaxed);
+                    unchanged.fetch_add(1, Ordering::Relaxed);
+                    send_members(&prev);
+                    let _ = sender.send(prev);
+                    return;
+                }
+
+                let processed = if links == LinkPolicy::Record && entry.path_is_symlink() {
+                    link_entry(entry)
+                } else {
+                    process_entry(entry, &bad_sectors, &bad_sector_count)
+                };
+                match processed {
+                    Ok(mut file_entry) => {
+                        if with_metadata {
+                            file_entry.metadata =
+                                extract_metadata(&file_entry.path, file_entry.file_type);
+                        }
+                        // Deleted files get back where they came from
+                        if let Some(info) = trash_info(&file_entry.path) {
+                            info.apply(&mut file_entry.metadata);
+                        }
+                        repair_name(repair_names.as_ref(), &mut file_entry);
+                        if let Some(others) = hardlinks.get(&file_entry.path) {
+                            file_entry
+                                .metadata
+                                .insert("hardlinks".into(), join_paths(others));
+                        }
                         files_found.fetch_add(1, Ordering::Relaxed);
                         bytes_total.fetch_add(file_entry.size, Ordering::Relaxed);
+                        send_members(&file_entry);
                         let _ = sender.send(file_entry);
                     }
                     Err(e) => {
@@ -177,6 +329,23 @@ impl Scanner {
             });
         }
 
+        let mut snapshot_files = 0;
+        if options.snapshots {
+            match snapshot_entries(&options.source) {
+                Ok(files) => {
+                    snapshot_files = files.len();
+                    for file in files {
+                        let _ = sender.send(file);
+                    }
+                }
+                Err(e) => tracing::warn!(
+                    "Could not index shadow copies of {}: {:#}",
+                    options.source.display(),
+                    e
+                ),
+            }
+        }
+
         // Signal completion
         drop(sender);
         forward_handle.await?;
@@ -190,10 +359,56 @@ impl Scanner {
             errors: errors.load(Ordering::Relaxed),
             bad_sectors: bad_sector_count.load(Ordering::Relaxed),
             duration_ms: duration.as_millis() as u64,
+            unchanged: unchanged.load(Ordering::Relaxed),
+            symlinks: symlinks.load(Ordering::Relaxed),
+            link_loops: link_loops.load(Ordering::Relaxed),
+            links_outside_source: links_outside.load(Ordering::Relaxed),
+            hardlinks_skipped,
+            archive_members: archive_members.load(Ordering::Relaxed),
+            snapshot_files,
         })
     }
 }
 
+/// Return the previous entry for `entry` if the file has not changed since.
+///
+/// A file counts as unchanged when its size and mtime match. With
+/// `verify_hash`, entries that carry a hash are also re-hashed.
+fn reuse_unchanged(
+    entry: &DirEntry,
+    previous: &HashMap<String, FileEntry>,
+    verify_hash: bool,
+) -> Option<FileEntry> {
+    let prev = previous.get(entry.path().to_string_lossy().as_ref())?;
+    let metadata = entry.metadata().ok()?;
+    let modified = metadata.modified().ok().map(DateTime::<Utc>::from);
+
+    if prev.size != metadata.len() || prev.modified.is_none() || prev.modified != modified {
+        return None;
+    }
+
+    if verify_hash {
+        if let Some(ref expected) = prev.hash {
+            match crate::dedup::hash_file(entry.path()) {
+                Ok(actual) if actual == *expected => {}
+                _ => return None,
+            }
+        }
+    }
+
+    Some(prev.clone())
+}
+
+/// Record the repaired form of a mis-encoded file name
+fn repair_name(repair: Option<&NameRepair>, entry: &mut FileEntry) {
+    let repaired = repair
+        .zip(entry.path.file_name())
+        .and_then(|(repair, name)| repair.repair(name));
+    if let Some(repaired) = repaired {
+        repaired.apply(&mut entry.metadata);
+    }
+}
+
 /// Process a single directory entry into a FileEntry
 fn process_entry(
     entry: &DirEntry,
@@ -216,6 +431,7 @@ fn process_entry(
                 retry_count: 0,
                 block_size: 4096,
             };
+            events::emit(Event::BadSector(bad.clone()));
             bad_sectors.write().push(bad);
             bad_sector_count.fetch_add(1, Ordering::Relaxed);
 
@@ -240,6 +456,7 @@ fn process_entry(
             retry_count: 0,
             block_size: 4096,
         };
+        events::emit(Event::BadSector(bad.clone()));
         bad_sectors.write().push(bad);
         bad_sector_count.fetch_add(1, Ordering::Relaxed);
     }
@@ -261,6 +478,15 @@ fn check_file_readable(path: &std::path::Path) -> Result<()> {
     }
 }
 
+/// Paths for a metadata value, one per line
+fn join_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|p| p.display().to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 /// Check if entry is hidden (starts with .)
 fn is_hidden(entry: &DirEntry) -> bool {
     entry
@@ -297,6 +523,7 @@ mod tests {
             extensions: None,
             workers: 1,
             same_file_system: false,
+            ..Default::default()
         };
 
         let scanner = Scanner::new(options);
@@ -341,6 +568,7 @@ mod tests {
             extensions: Some(vec!["jpg".to_string(), "rs".to_string()]),
             workers: 1,
             same_file_system: false,
+            ..Default::default()
         };
 
         let scanner = Scanner::new(options);
@@ -355,4 +583,95 @@ mod tests {
             stats.files_found
         );
     }
+
+    #[tokio::test]
+    async fn test_scanner_archives() {
+        let dir = tempdir().unwrap();
+        let archive = dir.path().join("home.tar");
+        let mut tar = tar::Builder::new(std::fs::File::create(&archive).unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(5);
+        header.set_mode(0o644);
+        tar.append_data(&mut header, "photos/a.jpg", &b"jpeg!"[..])
+            .unwrap();
+        tar.finish().unwrap();
+
+        let scanner = Scanner::new(ScanOptions {
+            source: dir.path().to_path_buf(),
+            workers: 1,
+            archives: true,
+            ..Default::default()
+        });
+        let (tx, mut rx) = mpsc::channel(1000);
+        let stats = scanner
+            .scan_parallel(tx, Arc::new(RwLock::new(Vec::new())))
+            .await
+            .unwrap();
+        let mut paths = Vec::new();
+        while let Ok(entry) = rx.try_recv() {
+            paths.push(entry.path);
+        }
+        paths.sort();
+        assert_eq!(paths, [archive.clone(), archive.join("photos/a.jpg")]);
+        assert_eq!(stats.archive_members, 1);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_scanner_link_policies() {
+        use std::os::unix::fs::symlink;
+
+        let outside = tempdir().unwrap();
+        std::fs::write(outside.path().join("secret.txt"), "outside the source").unwrap();
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir(root.join("photos")).unwrap();
+        std::fs::write(root.join("photos").join("a.jpg"), "fake image test content").unwrap();
+        symlink(&root, root.join("photos").join("loop")).unwrap();
+        symlink(outside.path(), root.join("escape")).unwrap();
+        symlink(root.join("photos"), root.join("album")).unwrap();
+
+        let scan = |links| {
+            let root = root.clone();
+            async move {
+                let scanner = Scanner::new(ScanOptions {
+                    source: root,
+                    skip_hidden: false,
+                    workers: 1,
+                    links,
+                    ..Default::default()
+                });
+                let (tx, mut rx) = mpsc::channel(1000);
+                let stats = scanner
+                    .scan_parallel(tx, Arc::new(RwLock::new(Vec::new())))
+                    .await
+                    .unwrap();
+                let mut entries = Vec::new();
+                while let Ok(entry) = rx.try_recv() {
+                    entries.push(entry);
+                }
+                (stats, entries)
+            }
+        };
+
+        let (stats, entries) = scan(LinkPolicy::Skip).await;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(stats.symlinks, 3);
+
+        // The album link is followed; the loop (met through photos/ and
+        // album/) and the escape are not
+        let (stats, entries) = scan(LinkPolicy::Follow).await;
+        assert_eq!(entries.len(), 2);
+        assert_eq!(stats.link_loops, 2);
+        assert_eq!(stats.links_outside_source, 1);
+        assert!(entries.iter().all(|e| e.path.starts_with(&root)));
+
+        let (_, entries) = scan(LinkPolicy::Record).await;
+        assert_eq!(entries.len(), 4);
+        let escape = entries.iter().find(|e| e.name() == "escape").unwrap();
+        assert_eq!(
+            escape.metadata["link_target"],
+            outside.path().display().to_string()
+        );
+    }
 }
@@ -0,0 +1,162 @@
+//! Persistent file selections (`.ddsel`)
+//!
+//! Files marked in the TUI, the interactive session or by `search --select`
+//! are stored as a small JSON document, so a selection can be built up over
+//! several sessions and handed to `export --selection` as one batch.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+/// Extension used for selection files
+pub const SELECTION_EXTENSION: &str = "ddsel";
+
+/// Selection file used when none is given
+pub const DEFAULT_SELECTION_FILE: &str = "selection.ddsel";
+
+/// A named set of indexed file paths
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Selection {
+    pub version: u32,
+    /// Source the paths were indexed from, if known
+    #[serde(default)]
+    pub source: Option<PathBuf>,
+    pub created_at: String,
+    pub updated_at: String,
+    /// Paths exactly as stored in the index
+    pub files: BTreeSet<String>,
+}
+
+impl Selection {
+    pub const VERSION: u32 = 1;
+
+    /// Create an empty selection
+    pub fn new(source: Option<&Path>) -> Self {
+        let now = Utc::now().to_rfc3339();
+        Self {
+            version: Self::VERSION,
+            source: source.map(Path::to_path_buf),
+            created_at: now.clone(),
+            updated_at: now,
+            files: BTreeSet::new(),
+        }
+    }
+
+    /// Load a selection file
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read selection: {}", path.display()))?;
+        let selection: Self = serde_json::from_str(&data)
+            .with_context(|| format!("Failed to parse selection: {}", path.display()))?;
+        anyhow::ensure!(
+            selection.version <= Self::VERSION,
+            "Selection {} has version {}, newer than supported ({})",
+            path.display(),
+            selection.version,
+            Self::VERSION
+        );
+        Ok(selection)
+    }
+
+    /// Load a selection file, or start an empty one if it does not exist yet
+    pub fn load_or_new(path: &Path, source: Option<&Path>) -> Result<Self> {
+        if path.exists() {
+            Self::load(path)
+        } else {
+            Ok(Self::new(source))
+        }
+    }
+
+    /// Write the selection, replacing the file atomically
+    pub fn save(&mut self, path: &Path) -> Result<()> {
+        self.updated_at = Utc::now().to_rfc3339();
+        let data = serde_json::to_string_pretty(self).context("Failed to serialize selection")?;
+
+        let tmp = path.with_extension(format!("{}.tmp", SELECTION_EXTENSION));
+        std::fs::write(&tmp, data)
+            .with_context(|| format!("Failed to write selection: {}", tmp.display()))?;
+        std::fs::rename(&tmp, path)
+            .with_context(|| format!("Failed to write selection: {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Add paths; returns how many were not already selected
+    pub fn extend<I, S>(&mut self, files: I) -> usize
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let before = self.files.len();
+        self.files.extend(files.into_iter().map(Into::into));
+        self.files.len() - before
+    }
+
+    /// Replace the selected paths
+    pub fn set_files<I, S>(&mut self, files: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.files = files.into_iter().map(Into::into).collect();
+    }
+
+    /// Whether `source` differs from the source the selection was built on
+    pub fn is_from_other_source(&self, source: &Path) -> bool {
+        self.source.as_deref().is_some_and(|s| s != source)
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selection_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("photos.ddsel");
+        let source = Path::new("/mnt/backup");
+
+        let mut selection = Selection::load_or_new(&path, Some(source)).unwrap();
+        assert!(selection.is_empty());
+        assert_eq!(
+            selection.extend(["/mnt/backup/a.jpg", "/mnt/backup/b.jpg"]),
+            2
+        );
+        selection.save(&path).unwrap();
+
+        // A later session adds to the same file
+        let mut selection = Selection::load_or_new(&path, Some(source)).unwrap();
+        assert_eq!(
+            selection.extend(["/mnt/backup/b.jpg", "/mnt/backup/c.jpg"]),
+            1
+        );
+        selection.save(&path).unwrap();
+        assert!(!path.with_extension("ddsel.tmp").exists());
+
+        let loaded = Selection::load(&path).unwrap();
+        assert_eq!(
+            loaded.files.iter().map(String::as_str).collect::<Vec<_>>(),
+            [
+                "/mnt/backup/a.jpg",
+                "/mnt/backup/b.jpg",
+                "/mnt/backup/c.jpg"
+            ]
+        );
+        assert!(!loaded.is_from_other_source(source));
+        assert!(loaded.is_from_other_source(Path::new("/mnt/other")));
+
+        std::fs::write(&path, r#"{"version": 99}"#).unwrap();
+        assert!(Selection::load(&path).is_err());
+    }
+}
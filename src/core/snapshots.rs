@@ -0,0 +1,281 @@
+//! Volume Shadow Copy indexing
+//!
+//! An NTFS volume's shadow copies hold earlier versions of its files: the
+//! document before it was overwritten, the folder before it was deleted.
+//! With snapshot indexing, each file in each snapshot of the source's
+//! volume is indexed as a virtual [`FileEntry`] at
+//! `<source>/@vss/<n>/<path>` (`@vss/p<k>/<n>/...` for partition `k` of a
+//! disk image), where `n` numbers the snapshots oldest first as
+//! `convert --list-snapshots` does. Search finds them like any other file
+//! and export reads them out of the snapshot.
+//!
+//! The volume is the source itself when it is an image or block device,
+//! or, on Linux, the device an NTFS source directory is mounted from; only
+//! the source's own subtree of each snapshot is indexed then.
+
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+
+use super::index::FileEntry;
+use super::FileType;
+use crate::diskimage::ntfs::{self, Volume};
+use crate::diskimage::{list_shadow_copies, open_image, read_partitions, ShadowCopyReader};
+
+/// Directory under the source holding the snapshots' files
+pub const SNAPSHOTS_DIR: &str = "@vss";
+
+/// Metadata key holding the image or device the snapshot is in
+pub const SNAPSHOT_VOLUME_KEY: &str = "vss_volume";
+
+/// Metadata key holding the byte offset of the NTFS volume in it
+pub const SNAPSHOT_OFFSET_KEY: &str = "vss_offset";
+
+/// Metadata key holding the snapshot number
+pub const SNAPSHOT_KEY: &str = "vss_snapshot";
+
+/// Metadata key holding the file's MFT record in the snapshot
+pub const SNAPSHOT_RECORD_KEY: &str = "vss_record";
+
+/// Metadata key holding when the snapshot was taken
+pub const SNAPSHOT_CREATED_KEY: &str = "vss_created";
+
+/// A file inside a shadow copy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotFile {
+    pub volume: PathBuf,
+    pub offset: u64,
+    pub snapshot: usize,
+    pub record: u64,
+}
+
+impl SnapshotFile {
+    /// The snapshot file an index entry stands for, if it is one
+    pub fn of(entry: &FileEntry) -> Option<Self> {
+        let get = |key: &str| entry.metadata.get(key);
+        Some(Self {
+            volume: PathBuf::from(get(SNAPSHOT_VOLUME_KEY)?),
+            offset: get(SNAPSHOT_OFFSET_KEY)?.parse().ok()?,
+            snapshot: get(SNAPSHOT_KEY)?.parse().ok()?,
+            record: get(SNAPSHOT_RECORD_KEY)?.parse().ok()?,
+        })
+    }
+
+    /// Run `f` on a reader of the file's contents as of the snapshot
+    pub fn read<T>(&self, f: impl FnOnce(&mut dyn Read) -> Result<T>) -> Result<T> {
+        let (image, _, _) = open_image(&self.volume)?;
+        let snapshot = ShadowCopyReader::open(image, self.offset, self.snapshot)
+            .with_context(|| format!("Failed to open shadow copy {}", self.snapshot))?;
+        let mut file = Volume::open(snapshot)?
+            .open_file(self.record)
+            .with_context(|| {
+                format!(
+                    "Failed to read MFT record {} of shadow copy {}",
+                    self.record, self.snapshot
+                )
+            })?;
+        f(&mut file)
+    }
+}
+
+/// Whether `entry` is a file inside a shadow copy rather than on the source
+pub fn is_snapshot_file(entry: &FileEntry) -> bool {
+    entry.metadata.contains_key(SNAPSHOT_RECORD_KEY)
+}
+
+/// An NTFS volume to look for snapshots in
+struct SnapshotVolume {
+    /// Image or device holding it
+    path: PathBuf,
+    offset: u64,
+    /// Partition number, for images with several NTFS partitions
+    partition: Option<usize>,
+    /// Part of the volume the source covers
+    subtree: PathBuf,
+}
+
+/// Virtual entries for the files in every shadow copy of the volume
+/// holding `source`, or an empty list when it has none
+pub fn snapshot_entries(source: &Path) -> Result<Vec<FileEntry>> {
+    let mut entries = Vec::new();
+    for volume in snapshot_volumes(source)? {
+        let (mut image, _, _) = open_image(&volume.path)?;
+        let copies = list_shadow_copies(&mut image, volume.offset).with_context(|| {
+            format!("Failed to list shadow copies of {}", volume.path.display())
+        })?;
+        for copy in copies {
+            let mut base = source.join(SNAPSHOTS_DIR);
+            if let Some(partition) = volume.partition {
+                base.push(format!("p{}", partition));
+            }
+            base.push(copy.number.to_string());
+
+            let (image, _, _) = open_image(&volume.path)?;
+            let files = ShadowCopyReader::open(image, volume.offset, copy.number)
+                .and_then(Volume::open)
+                .and_then(|mut v| v.files())
+                .with_context(|| format!("Failed to read shadow copy {}", copy.number))?;
+            for file in files {
+                let Ok(inside) = file.path.strip_prefix(&volume.subtree) else {
+                    continue;
+                };
+                entries.push(entry(
+                    &base.join(inside),
+                    &file,
+                    &volume,
+                    copy.number,
+                    copy.created,
+                ));
+            }
+        }
+    }
+    Ok(entries)
+}
+
+fn entry(
+    path: &Path,
+    file: &ntfs::NtfsFile,
+    volume: &SnapshotVolume,
+    snapshot: usize,
+    created: Option<DateTime<Utc>>,
+) -> FileEntry {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let mut entry = FileEntry {
+        file_type: FileType::from_extension(&extension),
+        extension,
+        path: path.to_path_buf(),
+        size: file.size,
+        modified: file.modified,
+        created: file.created,
+        hash: None,
+        has_bad_sectors: false,
+        thumbnail: None,
+        metadata: Default::default(),
+    };
+    let metadata = &mut entry.metadata;
+    metadata.insert(
+        SNAPSHOT_VOLUME_KEY.into(),
+        volume.path.display().to_string(),
+    );
+    metadata.insert(SNAPSHOT_OFFSET_KEY.into(), volume.offset.to_string());
+    metadata.insert(SNAPSHOT_KEY.into(), snapshot.to_string());
+    metadata.insert(SNAPSHOT_RECORD_KEY.into(), file.record.to_string());
+    if let Some(created) = created {
+        metadata.insert(SNAPSHOT_CREATED_KEY.into(), created.to_rfc3339());
+    }
+    entry
+}
+
+/// NTFS volumes `source` is on or is
+fn snapshot_volumes(source: &Path) -> Result<Vec<SnapshotVolume>> {
+    if source.is_dir() {
+        return Ok(mounted_volume(source).into_iter().collect());
+    }
+    let (mut image, _, _) = open_image(source)?;
+    if boot_is_ntfs(&mut image, 0) {
+        return Ok(vec![SnapshotVolume {
+            path: source.to_path_buf(),
+            offset: 0,
+            partition: None,
+            subtree: PathBuf::new(),
+        }]);
+    }
+    Ok(read_partitions(&mut image)?
+        .into_iter()
+        .filter(|p| boot_is_ntfs(&mut image, p.start))
+        .map(|p| SnapshotVolume {
+            path: source.to_path_buf(),
+            offset: p.start,
+            partition: Some(p.number),
+            subtree: PathBuf::new(),
+        })
+        .collect())
+}
+
+/// The device an NTFS directory is mounted from
+#[cfg(target_os = "linux")]
+fn mounted_volume(dir: &Path) -> Option<SnapshotVolume> {
+    let dir = dir.canonicalize().ok()?;
+    let mountinfo = std::fs::read_to_string("/proc/self/mountinfo").ok()?;
+    let (mount_point, _, device) = crate::health::mount_entry(&mountinfo, &dir)?;
+    if !device.starts_with("/dev/") {
+        return None;
+    }
+    let (mut image, _, _) = open_image(Path::new(device))
+        .map_err(|e| tracing::debug!("Cannot read {} for shadow copies: {:#}", device, e))
+        .ok()?;
+    if !boot_is_ntfs(&mut image, 0) {
+        return None;
+    }
+    Some(SnapshotVolume {
+        path: device.into(),
+        offset: 0,
+        partition: None,
+        subtree: dir.strip_prefix(&mount_point).ok()?.to_path_buf(),
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn mounted_volume(_dir: &Path) -> Option<SnapshotVolume> {
+    None
+}
+
+fn boot_is_ntfs<R: Read + Seek>(reader: &mut R, offset: u64) -> bool {
+    let mut boot = [0u8; 512];
+    reader.seek(SeekFrom::Start(offset)).is_ok()
+        && reader.read_exact(&mut boot).is_ok()
+        && ntfs::is_ntfs(&boot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diskimage::vss::tests::{add_stores, CATALOG};
+
+    #[test]
+    fn test_snapshot_entries() {
+        // One snapshot, taken before the live photo.jpg (cluster 20, in
+        // VSS block 5) was overwritten with 0x99
+        let snapshot = ntfs::tests::volume();
+        let mut live = snapshot.clone();
+        live.resize(64 * 0x4000, 0);
+        let saved = [(5, 0x42, 0, 0)];
+        add_stores(
+            &mut live,
+            snapshot.len() as u64,
+            &[(0xA1, 133_000_000_000_000_000, CATALOG + 8 * 0x4000, &saved)],
+        );
+        live[20 * 4096..21 * 4096].fill(0x99);
+
+        let dir = tempfile::tempdir().unwrap();
+        let image = dir.path().join("c.dd");
+        std::fs::write(&image, &live).unwrap();
+
+        let entries = snapshot_entries(&image).unwrap();
+        let paths: Vec<_> = entries.iter().map(|e| e.path.clone()).collect();
+        assert_eq!(
+            paths,
+            [
+                image.join("@vss/1/Docs/notes.txt"),
+                image.join("@vss/1/photo.jpg")
+            ]
+        );
+        assert!(entries.iter().all(is_snapshot_file));
+        assert_eq!(entries[1].file_type, FileType::Image);
+
+        let file = SnapshotFile::of(&entries[1]).unwrap();
+        let contents = file
+            .read(|r| {
+                let mut all = Vec::new();
+                r.read_to_end(&mut all)?;
+                Ok(all)
+            })
+            .unwrap();
+        assert_eq!(contents, vec![0x42; 3000]);
+    }
+}
@@ -0,0 +1,638 @@
+//! SqliteIndex - Disk-backed file index for very large sources
+//!
+//! The in-memory [`FileIndex`](super::FileIndex) holds every entry in RAM,
+//! which stops being practical around ten million files. This backend keeps
+//! entries in a SQLite database with indexed columns for extension, size,
+//! modification time, type and hash, so filtered searches and dedup
+//! candidate selection run as SQL queries and never load the whole index.
+//!
+//! Enabled with the `sqlite` feature.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, Row, ToSql};
+
+use super::engine::{parse_size_str, report_dedup};
+use super::{
+    content_regex, display_timezone, is_searchable, search_entries, transliterate, ContentMatch,
+    FileEntry, FileIndex, FileType,
+};
+use crate::cli::{DedupArgs, FileTypeFilter, SearchArgs, SearchType};
+
+/// Schema version stored in the `meta` table
+const SCHEMA_VERSION: u32 = 1;
+
+/// Rows written per transaction during bulk inserts
+const INSERT_BATCH: usize = 10_000;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS meta (
+    key   TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS files (
+    path            TEXT PRIMARY KEY,
+    name            TEXT NOT NULL,
+    size            INTEGER NOT NULL,
+    file_type       TEXT NOT NULL,
+    extension       TEXT NOT NULL,
+    modified        INTEGER,
+    created         INTEGER,
+    hash            TEXT,
+    has_bad_sectors INTEGER NOT NULL DEFAULT 0,
+    thumbnail       TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_files_extension ON files(extension);
+CREATE INDEX IF NOT EXISTS idx_files_size      ON files(size);
+CREATE INDEX IF NOT EXISTS idx_files_modified  ON files(modified);
+CREATE INDEX IF NOT EXISTS idx_files_type      ON files(file_type);
+CREATE INDEX IF NOT EXISTS idx_files_hash      ON files(hash);
+";
+
+const COLUMNS: &str =
+    "path, size, file_type, extension, modified, created, hash, has_bad_sectors, thumbnail";
+
+/// Filters applied in SQL before any pattern matching
+#[derive(Debug, Clone, Default)]
+pub struct IndexQuery {
+    pub file_type: Option<FileType>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    pub modified_after: Option<DateTime<Utc>>,
+    pub modified_before: Option<DateTime<Utc>>,
+    pub limit: Option<usize>,
+}
+
+impl IndexQuery {
+    /// Translate `search` filters into a query
+    pub fn from_search_args(args: &SearchArgs) -> Self {
+        let tz = display_timezone();
+
+        Self {
+            file_type: args.file_type.and_then(|ft| match ft {
+                FileTypeFilter::Image => Some(FileType::Image),
+                FileTypeFilter::Video => Some(FileType::Video),
+                FileTypeFilter::Audio => Some(FileType::Audio),
+                FileTypeFilter::Document => Some(FileType::Document),
+                FileTypeFilter::Archive => Some(FileType::Archive),
+                FileTypeFilter::Code => Some(FileType::Code),
+                FileTypeFilter::All => None,
+            }),
+            min_size: args.min_size.as_deref().and_then(parse_size_str),
+            max_size: args.max_size.as_deref().and_then(parse_size_str),
+            modified_after: args.after.as_ref().and_then(|s| tz.parse_date(s, false)),
+            modified_before: args.before.as_ref().and_then(|s| tz.parse_date(s, true)),
+            limit: Some(args.limit),
+        }
+    }
+
+    /// Build the WHERE clause and its parameters
+    fn where_clause(&self) -> (String, Vec<Box<dyn ToSql>>) {
+        let mut clauses: Vec<&str> = Vec::new();
+        let mut params: Vec<Box<dyn ToSql>> = Vec::new();
+
+        if let Some(ft) = self.file_type {
+            clauses.push("file_type = ?");
+            params.push(Box::new(file_type_name(ft)));
+        }
+        if let Some(min) = self.min_size {
+            clauses.push("size >= ?");
+            params.push(Box::new(min as i64));
+        }
+        if let Some(max) = self.max_size {
+            clauses.push("size <= ?");
+            params.push(Box::new(max as i64));
+        }
+        if let Some(after) = self.modified_after {
+            clauses.push("(modified IS NULL OR modified >= ?)");
+            params.push(Box::new(after.timestamp()));
+        }
+        if let Some(before) = self.modified_before {
+            clauses.push("(modified IS NULL OR modified <= ?)");
+            params.push(Box::new(before.timestamp()));
+        }
+
+        if clauses.is_empty() {
+            (String::from("1 = 1"), params)
+        } else {
+            (clauses.join(" AND "), params)
+        }
+    }
+}
+
+/// A file index stored in SQLite
+pub struct SqliteIndex {
+    conn: Connection,
+}
+
+impl SqliteIndex {
+    /// Open (or create) an index database
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open SQLite index: {}", path.display()))?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
+        conn.execute_batch(SCHEMA)
+            .context("Failed to create SQLite index schema")?;
+        conn.execute(
+            "INSERT OR IGNORE INTO meta (key, value) VALUES ('schema_version', ?1)",
+            params![SCHEMA_VERSION.to_string()],
+        )?;
+
+        Ok(Self { conn })
+    }
+
+    /// Record the source path this index describes
+    pub fn set_source(&self, source: &Path) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO meta (key, value) VALUES ('source', ?1)",
+            params![source.to_string_lossy()],
+        )?;
+        Ok(())
+    }
+
+    /// Remove every indexed file, before indexing the source afresh
+    pub fn clear(&self) -> Result<()> {
+        self.conn.execute("DELETE FROM files", [])?;
+        Ok(())
+    }
+
+    /// Source path recorded with [`set_source`](Self::set_source)
+    pub fn source(&self) -> Result<Option<PathBuf>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT value FROM meta WHERE key = 'source'")?;
+        let mut rows = stmt.query([])?;
+        Ok(match rows.next()? {
+            Some(row) => Some(PathBuf::from(row.get::<_, String>(0)?)),
+            None => None,
+        })
+    }
+
+    /// Insert or replace entries, committing in batches
+    pub fn insert_entries<'a>(
+        &mut self,
+        entries: impl IntoIterator<Item = &'a FileEntry>,
+    ) -> Result<usize> {
+        let mut entries = entries.into_iter().peekable();
+        let mut written = 0usize;
+
+        while entries.peek().is_some() {
+            let tx = self.conn.transaction()?;
+            {
+                let mut stmt = tx.prepare_cached(
+                    "INSERT OR REPLACE INTO files
+                     (path, name, size, file_type, extension, modified, created, hash,
+                      has_bad_sectors, thumbnail)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                )?;
+                for entry in entries.by_ref().take(INSERT_BATCH) {
+                    stmt.execute(params![
+                        entry.path.to_string_lossy(),
+                        entry.name().to_lowercase(),
+                        entry.size as i64,
+                        file_type_name(entry.file_type),
+                        entry.extension,
+                        entry.modified.map(|d| d.timestamp()),
+                        entry.created.map(|d| d.timestamp()),
+                        entry.hash,
+                        entry.has_bad_sectors,
+                        entry
+                            .thumbnail
+                            .as_ref()
+                            .map(|p| p.to_string_lossy().to_string()),
+                    ])?;
+                    written += 1;
+                }
+            }
+            tx.commit()?;
+        }
+
+        Ok(written)
+    }
+
+    /// Copy every entry of an in-memory index into the database
+    pub fn import(&mut self, index: &FileIndex) -> Result<usize> {
+        self.set_source(index.source())?;
+        self.insert_entries(index.entries())
+    }
+
+    /// Number of indexed files
+    pub fn len(&self) -> Result<usize> {
+        let n: i64 = self
+            .conn
+            .query_row("SELECT COUNT(*) FROM files", [], |r| r.get(0))?;
+        Ok(n as usize)
+    }
+
+    /// Check if the index is empty
+    pub fn is_empty(&self) -> Result<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Entries matching the filters, in path order
+    pub fn query(&self, query: &IndexQuery) -> Result<Vec<FileEntry>> {
+        let mut out = Vec::new();
+        self.for_each(query, |entry| {
+            out.push(entry);
+            true
+        })?;
+        Ok(out)
+    }
+
+    /// Glob match on the file name (case-insensitive)
+    pub fn search_glob(&self, pattern: &str, query: &IndexQuery) -> Result<Vec<String>> {
+        self.search_where("name GLOB ?", pattern.to_lowercase(), query)
+    }
+
+    /// Substring match on the file name (case-insensitive)
+    pub fn search_exact(&self, pattern: &str, query: &IndexQuery) -> Result<Vec<String>> {
+        let escaped = pattern
+            .to_lowercase()
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        self.search_where("name LIKE ? ESCAPE '\\'", format!("%{}%", escaped), query)
+    }
+
+    /// Regex match on the full path. Rows are streamed, never all in memory.
+    pub fn search_regex(&self, pattern: &str, query: &IndexQuery) -> Result<Vec<String>> {
+        let regex = regex::Regex::new(pattern)
+            .with_context(|| format!("Invalid regex pattern: {}", pattern))?;
+        let limit = query.limit.unwrap_or(usize::MAX);
+
+        let mut out = Vec::new();
+        self.for_each(
+            &IndexQuery {
+                limit: None,
+                ..query.clone()
+            },
+            |entry| {
+                let path = entry.path.to_string_lossy();
+                if regex.is_match(&path) {
+                    out.push(path.to_string());
+                }
+                out.len() < limit
+            },
+        )?;
+        Ok(out)
+    }
+
+    /// Fuzzy match on the file name, keeping only the best `limit` results
+    pub fn search_fuzzy(&self, pattern: &str, query: &IndexQuery) -> Result<Vec<String>> {
+        self.search_fuzzy_with(pattern, false, query)
+    }
+
+    /// Fuzzy match on the file name, also comparing transliterated forms
+    /// with `translit` set, like [`DrillEngine::search_fuzzy_with`](super::DrillEngine::search_fuzzy_with)
+    pub fn search_fuzzy_with(
+        &self,
+        pattern: &str,
+        translit: bool,
+        query: &IndexQuery,
+    ) -> Result<Vec<String>> {
+        use fuzzy_matcher::skim::SkimMatcherV2;
+        use fuzzy_matcher::FuzzyMatcher;
+
+        let matcher = SkimMatcherV2::default();
+        let pattern_lower = pattern.to_lowercase();
+        let pattern_latin = translit.then(|| transliterate(pattern));
+        let limit = query.limit.unwrap_or(100).max(1);
+
+        // Min-heap of the best matches seen so far
+        let mut best: BinaryHeap<Reverse<(i64, String)>> = BinaryHeap::with_capacity(limit + 1);
+
+        let (where_sql, params) = query.where_clause();
+        let sql = format!("SELECT path, name FROM files WHERE {}", where_sql);
+        let mut stmt = self.conn.prepare(&sql)?;
+        let mut rows = stmt.query(rusqlite::params_from_iter(params.iter()))?;
+        while let Some(row) = rows.next()? {
+            let name: String = row.get(1)?;
+            let direct = matcher.fuzzy_match(&name, &pattern_lower);
+            let latin = pattern_latin
+                .as_ref()
+                .and_then(|p| matcher.fuzzy_match(&transliterate(&name), p));
+            if let Some(score) = direct.max(latin) {
+                best.push(Reverse((score, row.get(0)?)));
+                if best.len() > limit {
+                    best.pop();
+                }
+            }
+        }
+
+        let mut matches: Vec<(i64, String)> = best.into_iter().map(|Reverse(m)| m).collect();
+        matches.sort_by_key(|m| Reverse(m.0));
+        Ok(matches.into_iter().map(|(_, path)| path).collect())
+    }
+
+    /// Files whose size is shared with at least one other file.
+    ///
+    /// These are the only possible exact duplicates, so dedup can hash just
+    /// this subset instead of loading the whole index.
+    pub fn size_collisions(&self, min_size: u64) -> Result<Vec<FileEntry>> {
+        let sql = format!(
+            "SELECT {} FROM files
+             WHERE size >= ?1
+               AND size IN (SELECT size FROM files WHERE size >= ?1
+                            GROUP BY size HAVING COUNT(*) > 1)
+             ORDER BY size",
+            COLUMNS
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(params![min_size as i64], entry_from_row)?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    /// Look up a single entry by its full path
+    pub fn entry(&self, path: &str) -> Result<Option<FileEntry>> {
+        let sql = format!("SELECT {} FROM files WHERE path = ?1", COLUMNS);
+        let mut stmt = self.conn.prepare(&sql)?;
+        let mut rows = stmt.query_map(params![path], entry_from_row)?;
+        Ok(rows.next().transpose()?)
+    }
+
+    /// Record a computed hash for a file
+    pub fn set_hash(&self, path: &Path, hash: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE files SET hash = ?1 WHERE path = ?2",
+            params![hash, path.to_string_lossy()],
+        )?;
+        Ok(())
+    }
+
+    fn search_where(&self, cond: &str, value: String, query: &IndexQuery) -> Result<Vec<String>> {
+        let (where_sql, mut params) = query.where_clause();
+        params.push(Box::new(value));
+        let sql = format!(
+            "SELECT path FROM files WHERE {} AND {} ORDER BY path LIMIT {}",
+            where_sql,
+            cond,
+            query.limit.map_or(-1, |l| l as i64)
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(params.iter()), |r| r.get(0))?;
+        Ok(rows.collect::<rusqlite::Result<Vec<String>>>()?)
+    }
+
+    /// Stream entries matching `query` until `f` returns false
+    fn for_each(&self, query: &IndexQuery, mut f: impl FnMut(FileEntry) -> bool) -> Result<()> {
+        let (where_sql, params) = query.where_clause();
+        let sql = format!(
+            "SELECT {} FROM files WHERE {} ORDER BY path LIMIT {}",
+            COLUMNS,
+            where_sql,
+            query.limit.map_or(-1, |l| l as i64)
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let mut rows = stmt.query(rusqlite::params_from_iter(params.iter()))?;
+        while let Some(row) = rows.next()? {
+            if !f(entry_from_row(row)?) {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Run `search` against a SQLite index database
+pub fn search_sqlite_index(args: &SearchArgs) -> Result<()> {
+    let results = search_paths(&SqliteIndex::open(&args.source)?, args)?;
+
+    for path in &results {
+        println!("{}", path);
+    }
+
+    println!("\nFound {} matches", results.len());
+    Ok(())
+}
+
+/// Run `search` against a SQLite index database and return full entries
+pub fn search_sqlite_entries(args: &SearchArgs) -> Result<Vec<FileEntry>> {
+    let db = SqliteIndex::open(&args.source)?;
+    let mut entries = Vec::new();
+    for path in search_paths(&db, args)? {
+        if let Some(entry) = db.entry(&path)? {
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
+
+/// Run `search --content` against a SQLite index database
+pub fn search_sqlite_content(args: &SearchArgs) -> Result<Vec<ContentMatch>> {
+    reject_meta_filter(args)?;
+    let regex = content_regex(&args.pattern, args.search_type)?;
+    let db = SqliteIndex::open(&args.source)?;
+    let query = IndexQuery {
+        limit: None,
+        ..IndexQuery::from_search_args(args)
+    };
+    let candidates: Vec<FileEntry> = db
+        .query(&query)?
+        .into_iter()
+        .filter(is_searchable)
+        .collect();
+    Ok(search_entries(&candidates, &regex, args.limit))
+}
+
+/// The SQLite schema has no metadata column, so `--meta` cannot be honoured
+fn reject_meta_filter(args: &SearchArgs) -> Result<()> {
+    if !args.meta.is_empty() {
+        anyhow::bail!("--meta is not supported for SQLite indexes; search the source or its .idx index instead");
+    }
+    Ok(())
+}
+
+fn search_paths(db: &SqliteIndex, args: &SearchArgs) -> Result<Vec<String>> {
+    reject_meta_filter(args)?;
+    let query = IndexQuery::from_search_args(args);
+    match args.search_type {
+        SearchType::Fuzzy => db.search_fuzzy_with(&args.pattern, args.translit, &query),
+        SearchType::Glob => db.search_glob(&args.pattern, &query),
+        SearchType::Regex => db.search_regex(&args.pattern, &query),
+        SearchType::Exact => db.search_exact(&args.pattern, &query),
+    }
+}
+
+/// Run `dedup` against a SQLite index database.
+///
+/// Exact dedup only loads files whose size collides with another file;
+/// fuzzy and media dedup need every file and load the full index.
+pub fn dedup_sqlite_index(args: &DedupArgs) -> Result<()> {
+    let db = SqliteIndex::open(&args.source)?;
+
+    println!("Diamond Drill Dedup Engine");
+    println!("Reading SQLite index {}...\n", args.source.display());
+
+    let entries = if args.fuzzy || args.media {
+        db.query(&IndexQuery {
+            min_size: Some(args.min_size),
+            ..Default::default()
+        })?
+    } else {
+        db.size_collisions(args.min_size)?
+    };
+
+    println!(
+        "{} of {} indexed files are dedup candidates. Running dedup analysis...\n",
+        entries.len(),
+        db.len()?
+    );
+
+    report_dedup(&entries, args)
+}
+
+/// Check whether `path` is a SQLite database (by its file header)
+pub fn is_sqlite_index(path: &Path) -> bool {
+    use std::io::Read;
+
+    let mut header = [0u8; 16];
+    std::fs::File::open(path)
+        .and_then(|mut f| f.read_exact(&mut header))
+        .map(|_| &header == b"SQLite format 3\0")
+        .unwrap_or(false)
+}
+
+fn file_type_name(ft: FileType) -> String {
+    format!("{:?}", ft)
+}
+
+fn parse_file_type(name: &str) -> FileType {
+    serde_json::from_value(serde_json::Value::String(name.to_string())).unwrap_or(FileType::Other)
+}
+
+fn timestamp(secs: Option<i64>) -> Option<DateTime<Utc>> {
+    secs.and_then(|s| DateTime::from_timestamp(s, 0))
+}
+
+fn entry_from_row(row: &Row<'_>) -> rusqlite::Result<FileEntry> {
+    Ok(FileEntry {
+        path: PathBuf::from(row.get::<_, String>(0)?),
+        size: row.get::<_, i64>(1)? as u64,
+        file_type: parse_file_type(&row.get::<_, String>(2)?),
+        extension: row.get(3)?,
+        modified: timestamp(row.get(4)?),
+        created: timestamp(row.get(5)?),
+        hash: row.get(6)?,
+        has_bad_sectors: row.get(7)?,
+        thumbnail: row.get::<_, Option<String>>(8)?.map(PathBuf::from),
+        metadata: Default::default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn entry(path: &str, size: u64) -> FileEntry {
+        let path = PathBuf::from(path);
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        FileEntry {
+            file_type: FileType::from_extension(&extension),
+            path,
+            size,
+            extension,
+            modified: DateTime::from_timestamp(1_700_000_000, 0),
+            created: None,
+            hash: None,
+            has_bad_sectors: false,
+            thumbnail: None,
+            metadata: Default::default(),
+        }
+    }
+
+    fn sample_index(dir: &Path) -> SqliteIndex {
+        let mut db = SqliteIndex::open(&dir.join("index.sqlite")).unwrap();
+        let entries = vec![
+            entry("/src/photos/Vacation.JPG", 2048),
+            entry("/src/photos/family.png", 4096),
+            entry("/src/docs/report_100%.pdf", 2048),
+            entry("/src/code/main.rs", 10),
+        ];
+        assert_eq!(db.insert_entries(&entries).unwrap(), 4);
+        db
+    }
+
+    #[test]
+    fn test_roundtrip_and_filters() {
+        let dir = tempdir().unwrap();
+        let db = sample_index(dir.path());
+        assert_eq!(db.len().unwrap(), 4);
+
+        let images = db
+            .query(&IndexQuery {
+                file_type: Some(FileType::Image),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(images.len(), 2);
+        assert_eq!(
+            images[1].modified,
+            DateTime::from_timestamp(1_700_000_000, 0)
+        );
+
+        let big = db
+            .query(&IndexQuery {
+                min_size: Some(3000),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(big.len(), 1);
+        assert_eq!(big[0].extension, "png");
+    }
+
+    #[test]
+    fn test_pattern_searches() {
+        let dir = tempdir().unwrap();
+        let db = sample_index(dir.path());
+        let q = IndexQuery::default();
+
+        assert_eq!(db.search_glob("*.jpg", &q).unwrap().len(), 1);
+        assert_eq!(db.search_exact("100%", &q).unwrap().len(), 1);
+        assert_eq!(db.search_exact("_", &q).unwrap().len(), 1);
+        assert_eq!(db.search_regex(r"\.(png|rs)$", &q).unwrap().len(), 2);
+
+        let fuzzy = db.search_fuzzy("vacatn", &q).unwrap();
+        assert_eq!(fuzzy.len(), 1);
+        assert!(fuzzy[0].contains("Vacation"));
+    }
+
+    #[test]
+    fn test_fuzzy_translit() {
+        let dir = tempdir().unwrap();
+        let mut db = sample_index(dir.path());
+        db.insert_entries(&[entry("/src/docs/документ.pdf", 100)])
+            .unwrap();
+        let q = IndexQuery::default();
+
+        assert!(db.search_fuzzy("dokument", &q).unwrap().is_empty());
+        let found = db.search_fuzzy_with("dokument", true, &q).unwrap();
+        assert_eq!(found, ["/src/docs/документ.pdf"]);
+    }
+
+    #[test]
+    fn test_size_collisions_and_import() {
+        let dir = tempdir().unwrap();
+        let db = sample_index(dir.path());
+
+        let candidates = db.size_collisions(1).unwrap();
+        assert_eq!(candidates.len(), 2);
+        assert!(candidates.iter().all(|e| e.size == 2048));
+
+        let mut index = FileIndex::new(PathBuf::from("/src"));
+        index.add_entry(entry("/src/a.txt", 5));
+        let mut db2 = SqliteIndex::open(&dir.path().join("second.sqlite")).unwrap();
+        assert_eq!(db2.import(&index).unwrap(), 1);
+        assert_eq!(db2.source().unwrap(), Some(PathBuf::from("/src")));
+        assert!(is_sqlite_index(&dir.path().join("second.sqlite")));
+        assert!(!is_sqlite_index(Path::new("/nonexistent")));
+    }
+}
@@ -0,0 +1,290 @@
+//! Timezone handling for recovered timestamps
+//!
+//! Timestamps are stored in UTC everywhere. This module converts them for
+//! display (local time by default, or a zone forced with `--timezone`), turns
+//! user-supplied dates into UTC bounds, and converts raw on-disk formats:
+//! NTFS FILETIME values are already UTC, while FAT date-times are wall-clock
+//! values in the zone of the machine that wrote the volume.
+//!
+//! Note that Windows file-name tunneling can hand a recreated file the
+//! creation time of the file it replaced (within 15 seconds), so creation
+//! times from NTFS/FAT volumes can predate the content they describe.
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use chrono::{
+    DateTime, Duration, FixedOffset, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc,
+};
+use chrono_tz::Tz;
+
+/// Seconds between 1601-01-01 (FILETIME epoch) and 1970-01-01
+const FILETIME_UNIX_OFFSET_SECS: i64 = 11_644_473_600;
+
+/// Timezone used to show timestamps and interpret user-entered dates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DisplayTz {
+    Utc,
+    /// The timezone of the machine running Diamond Drill
+    #[default]
+    Local,
+    Fixed(FixedOffset),
+    Named(Tz),
+}
+
+impl DisplayTz {
+    /// Format a UTC timestamp in this timezone
+    pub fn format(&self, dt: &DateTime<Utc>, fmt: &str) -> String {
+        match self {
+            DisplayTz::Utc => dt.format(fmt).to_string(),
+            DisplayTz::Local => dt.with_timezone(&Local).format(fmt).to_string(),
+            DisplayTz::Fixed(offset) => dt.with_timezone(offset).format(fmt).to_string(),
+            DisplayTz::Named(tz) => dt.with_timezone(tz).format(fmt).to_string(),
+        }
+    }
+
+    /// Calendar date of a UTC timestamp in this timezone
+    pub fn date(&self, dt: &DateTime<Utc>) -> NaiveDate {
+        match self {
+            DisplayTz::Utc => dt.date_naive(),
+            DisplayTz::Local => dt.with_timezone(&Local).date_naive(),
+            DisplayTz::Fixed(offset) => dt.with_timezone(offset).date_naive(),
+            DisplayTz::Named(tz) => dt.with_timezone(tz).date_naive(),
+        }
+    }
+
+    /// Interpret a wall-clock time in this timezone.
+    ///
+    /// Ambiguous times (DST fall-back) resolve to the earlier instant; times
+    /// skipped by a DST jump are moved forward by an hour.
+    pub fn to_utc(&self, naive: NaiveDateTime) -> Option<DateTime<Utc>> {
+        fn resolve<Z: TimeZone>(zone: &Z, naive: NaiveDateTime) -> Option<DateTime<Utc>> {
+            zone.from_local_datetime(&naive)
+                .earliest()
+                .or_else(|| {
+                    zone.from_local_datetime(&(naive + Duration::hours(1)))
+                        .earliest()
+                })
+                .map(|dt| dt.with_timezone(&Utc))
+        }
+
+        match self {
+            DisplayTz::Utc => Some(naive.and_utc()),
+            DisplayTz::Local => resolve(&Local, naive),
+            DisplayTz::Fixed(offset) => resolve(offset, naive),
+            DisplayTz::Named(tz) => resolve(tz, naive),
+        }
+    }
+
+    /// Parse a `YYYY-MM-DD` date as the start (or end) of that day in this timezone
+    pub fn parse_date(&self, s: &str, end_of_day: bool) -> Option<DateTime<Utc>> {
+        let date = NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d").ok()?;
+        let time = if end_of_day {
+            NaiveTime::from_hms_opt(23, 59, 59)?
+        } else {
+            NaiveTime::MIN
+        };
+        self.to_utc(date.and_time(time))
+    }
+}
+
+impl FromStr for DisplayTz {
+    type Err = String;
+
+    /// Accepts `local`, `UTC`, offsets like `+02:00` / `-0530`, or IANA names
+    /// like `Europe/Berlin`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.eq_ignore_ascii_case("utc") || s.eq_ignore_ascii_case("gmt") || s == "Z" {
+            return Ok(DisplayTz::Utc);
+        }
+        if s.eq_ignore_ascii_case("local") {
+            return Ok(DisplayTz::Local);
+        }
+        if s.starts_with('+') || s.starts_with('-') {
+            return parse_offset(s)
+                .map(DisplayTz::Fixed)
+                .ok_or_else(|| format!("invalid UTC offset '{}' (expected e.g. +02:00)", s));
+        }
+        s.parse::<Tz>().map(DisplayTz::Named).map_err(|_| {
+            format!(
+                "unknown timezone '{}': use local, UTC, an offset like +02:00, or an IANA name like Europe/Berlin",
+                s
+            )
+        })
+    }
+}
+
+impl fmt::Display for DisplayTz {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DisplayTz::Utc => write!(f, "UTC"),
+            DisplayTz::Local => write!(f, "local"),
+            DisplayTz::Fixed(offset) => write!(f, "{}", offset),
+            DisplayTz::Named(tz) => write!(f, "{}", tz.name()),
+        }
+    }
+}
+
+/// Parse `+HH`, `+HHMM` or `+HH:MM` (and the `-` equivalents)
+fn parse_offset(s: &str) -> Option<FixedOffset> {
+    let (sign, rest) = match s.as_bytes().first()? {
+        b'+' => (1, &s[1..]),
+        b'-' => (-1, &s[1..]),
+        _ => return None,
+    };
+    let digits: String = rest.chars().filter(|c| *c != ':').collect();
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let (hours, minutes) = match digits.len() {
+        1 | 2 => (digits.parse::<i32>().ok()?, 0),
+        4 => (
+            digits[..2].parse::<i32>().ok()?,
+            digits[2..].parse::<i32>().ok()?,
+        ),
+        _ => return None,
+    };
+    if hours > 14 || minutes > 59 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+static DISPLAY_TZ: OnceLock<DisplayTz> = OnceLock::new();
+
+/// Set the process-wide display timezone. Only the first call takes effect.
+pub fn set_display_timezone(tz: DisplayTz) -> bool {
+    DISPLAY_TZ.set(tz).is_ok()
+}
+
+/// The process-wide display timezone (local time unless `--timezone` was given)
+pub fn display_timezone() -> DisplayTz {
+    *DISPLAY_TZ.get_or_init(DisplayTz::default)
+}
+
+/// Format a stored UTC timestamp in the display timezone
+pub fn format_timestamp(dt: &DateTime<Utc>, fmt: &str) -> String {
+    display_timezone().format(dt, fmt)
+}
+
+/// Convert an NTFS/Windows FILETIME (100ns ticks since 1601-01-01 UTC)
+pub fn filetime_to_utc(ticks: u64) -> Option<DateTime<Utc>> {
+    if ticks == 0 {
+        return None;
+    }
+    let secs = (ticks / 10_000_000) as i64 - FILETIME_UNIX_OFFSET_SECS;
+    let nanos = ((ticks % 10_000_000) * 100) as u32;
+    DateTime::from_timestamp(secs, nanos)
+}
+
+/// Convert a FAT directory-entry date/time, which is local wall-clock time
+/// of the machine that wrote it, into UTC.
+///
+/// `centis` is the optional 10ms creation-time refinement (0-199).
+pub fn fat_to_utc(
+    date: u16,
+    time: u16,
+    centis: u8,
+    volume_tz: &DisplayTz,
+) -> Option<DateTime<Utc>> {
+    if date == 0 {
+        return None;
+    }
+    let day = NaiveDate::from_ymd_opt(
+        1980 + (date >> 9) as i32,
+        ((date >> 5) & 0x0F) as u32,
+        (date & 0x1F) as u32,
+    )?;
+    let secs = (time & 0x1F) as u32 * 2 + centis as u32 / 100;
+    let naive = day.and_hms_milli_opt(
+        (time >> 11) as u32,
+        ((time >> 5) & 0x3F) as u32,
+        secs,
+        (centis as u32 % 100) * 10,
+    )?;
+    volume_tz.to_utc(naive)
+}
+
+/// Convert an exFAT timestamp. exFAT records the writer's UTC offset (in 15
+/// minute steps, valid when the top bit is set); older writers leave it
+/// empty, in which case `volume_tz` is assumed.
+pub fn exfat_to_utc(
+    timestamp: u32,
+    centis: u8,
+    utc_offset: u8,
+    volume_tz: &DisplayTz,
+) -> Option<DateTime<Utc>> {
+    let tz = if utc_offset & 0x80 != 0 {
+        // Sign-extend the 7-bit two's complement value
+        let quarters = ((utc_offset << 1) as i8 >> 1) as i32;
+        DisplayTz::Fixed(FixedOffset::east_opt(quarters * 15 * 60)?)
+    } else {
+        *volume_tz
+    };
+    fat_to_utc((timestamp >> 16) as u16, timestamp as u16, centis, &tz)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_display_tz() {
+        assert_eq!("utc".parse::<DisplayTz>().unwrap(), DisplayTz::Utc);
+        assert_eq!("Local".parse::<DisplayTz>().unwrap(), DisplayTz::Local);
+        assert_eq!(
+            "+05:30".parse::<DisplayTz>().unwrap(),
+            DisplayTz::Fixed(FixedOffset::east_opt(5 * 3600 + 30 * 60).unwrap())
+        );
+        assert_eq!(
+            "-0800".parse::<DisplayTz>().unwrap(),
+            DisplayTz::Fixed(FixedOffset::west_opt(8 * 3600).unwrap())
+        );
+        assert_eq!(
+            "Europe/Berlin".parse::<DisplayTz>().unwrap().to_string(),
+            "Europe/Berlin"
+        );
+        assert!("Mars/Olympus".parse::<DisplayTz>().is_err());
+        assert!("+25:00".parse::<DisplayTz>().is_err());
+    }
+
+    #[test]
+    fn test_format_and_date_bounds() {
+        let berlin: DisplayTz = "Europe/Berlin".parse().unwrap();
+        let dt = Utc.with_ymd_and_hms(2024, 7, 1, 22, 30, 0).unwrap();
+        assert_eq!(berlin.format(&dt, "%Y-%m-%d %H:%M"), "2024-07-02 00:30");
+        assert_eq!(
+            DisplayTz::Utc.format(&dt, "%Y-%m-%d %H:%M"),
+            "2024-07-01 22:30"
+        );
+
+        // Start of 2024-07-02 in Berlin (CEST, +02:00) is 22:00 UTC the day before
+        let start = berlin.parse_date("2024-07-02", false).unwrap();
+        assert_eq!(start, Utc.with_ymd_and_hms(2024, 7, 1, 22, 0, 0).unwrap());
+        let end = berlin.parse_date("2024-07-02", true).unwrap();
+        assert_eq!(end, Utc.with_ymd_and_hms(2024, 7, 2, 21, 59, 59).unwrap());
+        assert!(berlin.parse_date("02/07/2024", false).is_none());
+    }
+
+    #[test]
+    fn test_filetime_and_fat_conversion() {
+        // 2001-01-01 00:00:00 UTC
+        let ft = filetime_to_utc(126_227_808_000_000_000).unwrap();
+        assert_eq!(ft, Utc.with_ymd_and_hms(2001, 1, 1, 0, 0, 0).unwrap());
+        assert!(filetime_to_utc(0).is_none());
+
+        // FAT 2023-03-15 14:20:30 written on a UTC+01:00 machine
+        let date = ((2023 - 1980) << 9) | (3 << 5) | 15;
+        let time = (14 << 11) | (20 << 5) | (30 / 2);
+        let cet = DisplayTz::Fixed(FixedOffset::east_opt(3600).unwrap());
+        let utc = fat_to_utc(date, time, 150, &cet).unwrap();
+        assert_eq!(utc.format("%H:%M:%S%.3f").to_string(), "13:20:31.500");
+
+        // exFAT with an explicit -05:00 offset ignores the fallback zone
+        let offset = 0x80 | (-20i8 as u8 & 0x7F);
+        let ex = exfat_to_utc(((date as u32) << 16) | time as u32, 0, offset, &cet).unwrap();
+        assert_eq!(ex, Utc.with_ymd_and_hms(2023, 3, 15, 19, 20, 30).unwrap());
+    }
+}
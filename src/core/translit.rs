@@ -0,0 +1,322 @@
+//! Filename transliteration for cross-script search
+//!
+//! Maps non-Latin scripts onto plain ASCII so that a query typed on a Latin
+//! keyboard can still find recovered files named in another script
+//! (e.g. "dokument" matches "документ.pdf").
+//!
+//! Covered: Cyrillic (Russian, Ukrainian, Belarusian), Greek, Latin
+//! letters with diacritics, and Japanese kana (Hepburn romaji, so
+//! "shashin" matches "しゃしん.jpg" and "kohi" matches "コーヒー.txt").
+//! Han characters (Chinese hanzi, Japanese kanji) and Hangul are out of
+//! scope: their readings need a dictionary. Anything unmapped passes
+//! through unchanged.
+
+use std::iter::Peekable;
+
+use unicode_normalization::UnicodeNormalization;
+
+/// Transliterate `input` to lowercase ASCII where a mapping is known.
+pub fn transliterate(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    // Names written on macOS arrive decomposed (か + ゙ for が)
+    let mut chars = input.nfc().flat_map(char::to_lowercase).peekable();
+    // A small っ doubles the consonant of the kana after it
+    let mut sokuon = false;
+
+    while let Some(c) = chars.next() {
+        let c = to_hiragana(c);
+        if let Some(latin) = romanize_kana(c, &mut chars) {
+            if std::mem::take(&mut sokuon) {
+                if latin.starts_with("ch") {
+                    out.push('t');
+                } else if let Some(first) = latin.chars().next().filter(|c| !is_vowel(*c)) {
+                    out.push(first);
+                }
+            }
+            out.push_str(&latin);
+            continue;
+        }
+        sokuon = false;
+        match c {
+            'っ' => {
+                sokuon = true;
+                continue;
+            }
+            // The long vowel mark repeats the vowel before it
+            'ー' => {
+                if let Some(vowel) = out.chars().last().filter(|c| is_vowel(*c)) {
+                    out.push(vowel);
+                }
+                continue;
+            }
+            _ => {}
+        }
+
+        // Map precomposed letters first so й/ї keep their own spelling,
+        // then fall back to stripping diacritics (é -> e, ή -> η -> i).
+        if let Some(latin) = map_letter(c) {
+            out.push_str(latin);
+            continue;
+        }
+        for d in std::iter::once(c).nfd() {
+            if let Some(latin) = map_letter(d) {
+                out.push_str(latin);
+            } else if !is_combining_mark(d) {
+                out.push(d);
+            }
+        }
+    }
+
+    out
+}
+
+fn map_letter(c: char) -> Option<&'static str> {
+    if c.is_ascii() {
+        return None;
+    }
+    map_cyrillic(c).or_else(|| map_greek(c))
+}
+
+fn is_combining_mark(c: char) -> bool {
+    matches!(c, '\u{0300}'..='\u{036F}')
+}
+
+fn is_vowel(c: char) -> bool {
+    matches!(c, 'a' | 'e' | 'i' | 'o' | 'u')
+}
+
+/// Katakana map onto hiragana, which share their romaji
+fn to_hiragana(c: char) -> char {
+    match c {
+        'ァ'..='ヶ' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+        _ => c,
+    }
+}
+
+/// Romaji for the hiragana `c`, taking along a small kana after it that
+/// joins its syllable: き + ゃ -> kya, し + ょ -> sho, ふ + ぁ -> fa
+fn romanize_kana(c: char, chars: &mut Peekable<impl Iterator<Item = char>>) -> Option<String> {
+    let latin = map_kana(c)?;
+    let stem = &latin[..latin.len() - 1];
+    let small = chars
+        .peek()
+        .and_then(|&next| small_kana(to_hiragana(next)))
+        .filter(|&(yoon, _)| !stem.is_empty() && (!yoon || latin.ends_with('i')));
+    let Some((yoon, vowel)) = small else {
+        return Some(latin.to_string());
+    };
+    chars.next();
+
+    let mut joined = stem.to_string();
+    if yoon && !["sh", "ch", "j"].iter().any(|s| stem.ends_with(s)) {
+        joined.push('y');
+    }
+    joined.push(vowel);
+    Some(joined)
+}
+
+/// Small kana that join the kana before them: whether it is a ya/yu/yo,
+/// and the vowel it gives the syllable
+fn small_kana(c: char) -> Option<(bool, char)> {
+    Some(match c {
+        'ゃ' => (true, 'a'),
+        'ゅ' => (true, 'u'),
+        'ょ' => (true, 'o'),
+        'ぁ' => (false, 'a'),
+        'ぃ' => (false, 'i'),
+        'ぅ' => (false, 'u'),
+        'ぇ' => (false, 'e'),
+        'ぉ' => (false, 'o'),
+        _ => return None,
+    })
+}
+
+fn map_kana(c: char) -> Option<&'static str> {
+    let s = match c {
+        'あ' | 'ぁ' => "a",
+        'い' | 'ぃ' | 'ゐ' => "i",
+        'う' | 'ぅ' => "u",
+        'え' | 'ぇ' | 'ゑ' => "e",
+        'お' | 'ぉ' | 'を' => "o",
+        'か' | 'ゕ' => "ka",
+        'き' => "ki",
+        'く' => "ku",
+        'け' | 'ゖ' => "ke",
+        'こ' => "ko",
+        'が' => "ga",
+        'ぎ' => "gi",
+        'ぐ' => "gu",
+        'げ' => "ge",
+        'ご' => "go",
+        'さ' => "sa",
+        'し' => "shi",
+        'す' => "su",
+        'せ' => "se",
+        'そ' => "so",
+        'ざ' => "za",
+        'じ' | 'ぢ' => "ji",
+        'ず' | 'づ' => "zu",
+        'ぜ' => "ze",
+        'ぞ' => "zo",
+        'た' => "ta",
+        'ち' => "chi",
+        'つ' => "tsu",
+        'て' => "te",
+        'と' => "to",
+        'だ' => "da",
+        'で' => "de",
+        'ど' => "do",
+        'な' => "na",
+        'に' => "ni",
+        'ぬ' => "nu",
+        'ね' => "ne",
+        'の' => "no",
+        'は' => "ha",
+        'ひ' => "hi",
+        'ふ' => "fu",
+        'へ' => "he",
+        'ほ' => "ho",
+        'ば' => "ba",
+        'び' => "bi",
+        'ぶ' => "bu",
+        'べ' => "be",
+        'ぼ' => "bo",
+        'ぱ' => "pa",
+        'ぴ' => "pi",
+        'ぷ' => "pu",
+        'ぺ' => "pe",
+        'ぽ' => "po",
+        'ま' => "ma",
+        'み' => "mi",
+        'む' => "mu",
+        'め' => "me",
+        'も' => "mo",
+        'や' | 'ゃ' => "ya",
+        'ゆ' | 'ゅ' => "yu",
+        'よ' | 'ょ' => "yo",
+        'ら' => "ra",
+        'り' => "ri",
+        'る' => "ru",
+        'れ' => "re",
+        'ろ' => "ro",
+        'わ' | 'ゎ' => "wa",
+        'ん' => "n",
+        'ゔ' => "vu",
+        _ => return None,
+    };
+    Some(s)
+}
+
+fn map_cyrillic(c: char) -> Option<&'static str> {
+    let s = match c {
+        'а' => "a",
+        'б' => "b",
+        'в' => "v",
+        'г' => "g",
+        'ґ' => "g",
+        'д' => "d",
+        'е' => "e",
+        'ё' => "e",
+        'є' => "ye",
+        'ж' => "zh",
+        'з' => "z",
+        'и' => "i",
+        'і' => "i",
+        'ї' => "yi",
+        'й' => "y",
+        'к' => "k",
+        'л' => "l",
+        'м' => "m",
+        'н' => "n",
+        'о' => "o",
+        'п' => "p",
+        'р' => "r",
+        'с' => "s",
+        'т' => "t",
+        'у' => "u",
+        'ў' => "u",
+        'ф' => "f",
+        'х' => "kh",
+        'ц' => "ts",
+        'ч' => "ch",
+        'ш' => "sh",
+        'щ' => "shch",
+        'ъ' => "",
+        'ы' => "y",
+        'ь' => "",
+        'э' => "e",
+        'ю' => "yu",
+        'я' => "ya",
+        _ => return None,
+    };
+    Some(s)
+}
+
+fn map_greek(c: char) -> Option<&'static str> {
+    let s = match c {
+        'α' => "a",
+        'β' => "v",
+        'γ' => "g",
+        'δ' => "d",
+        'ε' => "e",
+        'ζ' => "z",
+        'η' => "i",
+        'θ' => "th",
+        'ι' => "i",
+        'κ' => "k",
+        'λ' => "l",
+        'μ' => "m",
+        'ν' => "n",
+        'ξ' => "x",
+        'ο' => "o",
+        'π' => "p",
+        'ρ' => "r",
+        'σ' | 'ς' => "s",
+        'τ' => "t",
+        'υ' => "y",
+        'φ' => "f",
+        'χ' => "ch",
+        'ψ' => "ps",
+        'ω' => "o",
+        _ => return None,
+    };
+    Some(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cyrillic() {
+        assert_eq!(transliterate("документ"), "dokument");
+        assert_eq!(transliterate("Фото_2019.jpg"), "foto_2019.jpg");
+        assert_eq!(transliterate("щука"), "shchuka");
+        assert_eq!(transliterate("їжак"), "yizhak");
+    }
+
+    #[test]
+    fn test_greek_and_diacritics() {
+        assert_eq!(transliterate("Αθήνα"), "athina");
+        assert_eq!(transliterate("Café Résumé"), "cafe resume");
+    }
+
+    #[test]
+    fn test_kana() {
+        assert_eq!(transliterate("しゃしん.jpg"), "shashin.jpg");
+        assert_eq!(transliterate("ドキュメント"), "dokyumento");
+        assert_eq!(transliterate("コーヒー.txt"), "koohii.txt");
+        assert_eq!(transliterate("きって"), "kitte");
+        assert_eq!(transliterate("マッチ"), "matchi");
+        assert_eq!(transliterate("ファイル"), "fairu");
+        assert_eq!(transliterate("じゅんび"), "junbi");
+        // Decomposed, as macOS stores it: か + combining dakuten
+        assert_eq!(transliterate("か\u{3099}そ\u{3099}く"), "gazoku");
+    }
+
+    #[test]
+    fn test_unmapped_passthrough() {
+        assert_eq!(transliterate("写真.png"), "写真.png");
+        assert_eq!(transliterate("plain.txt"), "plain.txt");
+    }
+}
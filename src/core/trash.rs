@@ -0,0 +1,321 @@
+//! Recycle Bin and Trash metadata
+//!
+//! Deleted files sitting in a trash folder have lost their name and
+//! location: Windows renames them to `$R…`, and all three systems move them
+//! away from where they lived. Each keeps a record on the side, read here
+//! into `original_path`, `original_name`, `deleted_at` and `trash` keys on
+//! [`FileEntry::metadata`](super::FileEntry):
+//!
+//! - Windows `$Recycle.Bin\<SID>\$R<id>.<ext>` has a matching `$I<id>.<ext>`
+//!   with the original path and the deletion FILETIME.
+//! - Linux `.Trash-<uid>/files/<name>` (and `~/.local/share/Trash`) has
+//!   `info/<name>.trashinfo` with the URL-encoded path and deletion date.
+//! - macOS `.Trashes/<uid>/` and `~/.Trash/` keep the "put back" location
+//!   in the folder's `.DS_Store`; the deletion time is the file's status
+//!   change time, as moving it to the trash updates it.
+//!
+//! Files inside deleted folders get the folder's original path joined with
+//! their path below it.
+
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+use super::{display_timezone, filetime_to_utc};
+
+/// Where a trashed file came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrashInfo {
+    pub original_path: PathBuf,
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// `recycle_bin`, `freedesktop` or `macos`
+    pub kind: &'static str,
+}
+
+impl TrashInfo {
+    /// Store as `FileEntry` metadata keys
+    pub fn apply(&self, meta: &mut HashMap<String, String>) {
+        meta.insert(
+            "original_path".into(),
+            self.original_path.display().to_string(),
+        );
+        if let Some(name) = self.original_path.file_name() {
+            meta.insert("original_name".into(), name.to_string_lossy().into_owned());
+        }
+        if let Some(deleted) = self.deleted_at {
+            meta.insert("deleted_at".into(), deleted.to_rfc3339());
+        }
+        meta.insert("trash".into(), self.kind.into());
+    }
+}
+
+/// Original location of `path` if it is (or is inside) a trashed item.
+/// Cheap for paths outside trash folders.
+pub fn trash_info(path: &Path) -> Option<TrashInfo> {
+    let components: Vec<&str> = path
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(s) => s.to_str(),
+            _ => None,
+        })
+        .collect();
+    // Index of the trashed item itself, with everything from there down
+    let (item, info) = (1..components.len()).find_map(|i| {
+        let parent = &components[..i];
+        let name = components[i];
+        let dir = ancestor(path, components.len() - i);
+        recycle_bin(&dir, parent, name)
+            .or_else(|| freedesktop(&dir, parent, name))
+            .or_else(|| macos(&dir, parent, name))
+            .map(|info| (i, info))
+    })?;
+    let below: PathBuf = components[item + 1..].iter().collect();
+    Some(TrashInfo {
+        original_path: info.original_path.join(below),
+        ..info
+    })
+}
+
+/// The directory `levels` above `path`'s last component
+fn ancestor(path: &Path, levels: usize) -> PathBuf {
+    path.ancestors()
+        .nth(levels)
+        .unwrap_or(Path::new(""))
+        .to_path_buf()
+}
+
+/// `$Recycle.Bin\<SID>\$R…` with its `$I…` record
+fn recycle_bin(dir: &Path, parent: &[&str], name: &str) -> Option<TrashInfo> {
+    let [.., bin, _sid] = parent else {
+        return None;
+    };
+    if !bin.eq_ignore_ascii_case("$Recycle.Bin") || !name.starts_with("$R") {
+        return None;
+    }
+    let record = std::fs::read(dir.join(format!("$I{}", &name[2..]))).ok()?;
+    let (deleted_at, original_path) = parse_recycle_record(&record)?;
+    Some(TrashInfo {
+        original_path: PathBuf::from(original_path),
+        deleted_at,
+        kind: "recycle_bin",
+    })
+}
+
+/// Deletion time and original path from a `$I` file (Vista+ version 1,
+/// Windows 10+ version 2)
+fn parse_recycle_record(b: &[u8]) -> Option<(Option<DateTime<Utc>>, String)> {
+    let u64_at = |at: usize| Some(u64::from_le_bytes(b.get(at..at + 8)?.try_into().ok()?));
+    let deleted = filetime_to_utc(u64_at(0x10)?);
+    let path = match u64_at(0)? {
+        1 => b.get(0x18..(0x18 + 520).min(b.len()))?,
+        2 => {
+            let chars = u32::from_le_bytes(b.get(0x18..0x1C)?.try_into().ok()?) as usize;
+            b.get(0x1C..(0x1C + chars * 2).min(b.len()))?
+        }
+        _ => return None,
+    };
+    let units: Vec<u16> = path
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .take_while(|&u| u != 0)
+        .collect();
+    let path = String::from_utf16(&units).ok()?;
+    (!path.is_empty()).then_some((deleted, path))
+}
+
+/// `<trash>/files/<name>` with `<trash>/info/<name>.trashinfo`
+fn freedesktop(dir: &Path, parent: &[&str], name: &str) -> Option<TrashInfo> {
+    let [.., trash, "files"] = parent else {
+        return None;
+    };
+    if !trash.starts_with(".Trash") && *trash != "Trash" {
+        return None;
+    }
+    let trash_dir = dir.parent()?;
+    let info =
+        std::fs::read_to_string(trash_dir.join("info").join(format!("{name}.trashinfo"))).ok()?;
+    let (path, deleted) = parse_trashinfo(&info)?;
+    // Relative paths are relative to the folder holding a `.Trash-<uid>`
+    // (the root of the volume it was deleted from)
+    let original_path = match trash_dir.parent() {
+        Some(top) if path.is_relative() => top.join(path),
+        _ => path,
+    };
+    Some(TrashInfo {
+        original_path,
+        deleted_at: deleted,
+        kind: "freedesktop",
+    })
+}
+
+fn parse_trashinfo(info: &str) -> Option<(PathBuf, Option<DateTime<Utc>>)> {
+    let mut path = None;
+    let mut deleted = None;
+    for line in info.lines() {
+        if let Some(value) = line.strip_prefix("Path=") {
+            path = Some(PathBuf::from(percent_decode(value.trim())?));
+        } else if let Some(value) = line.strip_prefix("DeletionDate=") {
+            // Local time of the machine that deleted the file
+            deleted = NaiveDateTime::parse_from_str(value.trim(), "%Y-%m-%dT%H:%M:%S")
+                .ok()
+                .and_then(|naive| display_timezone().to_utc(naive));
+        }
+    }
+    Some((path?, deleted))
+}
+
+fn percent_decode(s: &str) -> Option<String> {
+    let mut out = Vec::with_capacity(s.len());
+    let mut bytes = s.bytes();
+    while let Some(b) = bytes.next() {
+        if b == b'%' {
+            let hex = [bytes.next()?, bytes.next()?];
+            out.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+        } else {
+            out.push(b);
+        }
+    }
+    String::from_utf8(out).ok()
+}
+
+/// `.Trashes/<uid>/<name>` or `.Trash/<name>` with a put-back location in
+/// the folder's `.DS_Store`
+fn macos(dir: &Path, parent: &[&str], name: &str) -> Option<TrashInfo> {
+    let in_trash = matches!(parent, [.., ".Trash"] | [.., ".Trashes", _]);
+    if !in_trash || name == ".DS_Store" {
+        return None;
+    }
+    let store = std::fs::read(dir.join(".DS_Store")).ok()?;
+    let location = ds_store_string(&store, name, b"ptbL")?;
+    let original_name = ds_store_string(&store, name, b"ptbN").unwrap_or_else(|| name.into());
+    #[cfg(unix)]
+    let deleted_at = std::fs::symlink_metadata(dir.join(name))
+        .ok()
+        .and_then(|m| {
+            use std::os::unix::fs::MetadataExt;
+            DateTime::from_timestamp(m.ctime(), m.ctime_nsec() as u32)
+        });
+    #[cfg(not(unix))]
+    let deleted_at = None;
+    // ptbL is the folder relative to the volume root, without a leading slash
+    Some(TrashInfo {
+        original_path: Path::new("/").join(location).join(original_name),
+        deleted_at,
+        kind: "macos",
+    })
+}
+
+/// Value of a `ustr` record for `name` in a `.DS_Store`.
+///
+/// Records are a UTF-16BE file name prefixed with its length, a four-byte
+/// code and a four-byte type. Rather than walk the B-tree, look for the
+/// code and check the name in front of it.
+fn ds_store_string(store: &[u8], name: &str, code: &[u8; 4]) -> Option<String> {
+    let name: Vec<u8> = name.encode_utf16().flat_map(u16::to_be_bytes).collect();
+    let mut needle = name.clone();
+    needle.extend_from_slice(code);
+    needle.extend_from_slice(b"ustr");
+    let at = store
+        .windows(needle.len())
+        .enumerate()
+        .filter(|(_, w)| *w == needle.as_slice())
+        .map(|(at, _)| at)
+        .find(|&at| {
+            at >= 4
+                && u32::from_be_bytes(store[at - 4..at].try_into().unwrap()) as usize
+                    == name.len() / 2
+        })?;
+    let value = at + needle.len();
+    let len = u32::from_be_bytes(store.get(value..value + 4)?.try_into().ok()?) as usize;
+    let units: Vec<u16> = store
+        .get(value + 4..value + 4 + len * 2)?
+        .chunks_exact(2)
+        .map(|c| u16::from_be_bytes([c[0], c[1]]))
+        .collect();
+    String::from_utf16(&units).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16le(s: &str) -> Vec<u8> {
+        s.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    fn ds_record(name: &str, code: &[u8; 4], value: &str) -> Vec<u8> {
+        let mut r = (name.encode_utf16().count() as u32).to_be_bytes().to_vec();
+        r.extend(name.encode_utf16().flat_map(u16::to_be_bytes));
+        r.extend_from_slice(code);
+        r.extend_from_slice(b"ustr");
+        r.extend((value.encode_utf16().count() as u32).to_be_bytes());
+        r.extend(value.encode_utf16().flat_map(u16::to_be_bytes));
+        r
+    }
+
+    #[test]
+    fn test_recycle_bin_trashinfo_and_ds_store() {
+        let dir = tempfile::tempdir().unwrap();
+
+        // Windows 10 $I (version 2) for a deleted folder
+        let bin = dir.path().join("$Recycle.Bin").join("S-1-5-21-1000");
+        std::fs::create_dir_all(bin.join("$RAB12CD").join("sub")).unwrap();
+        std::fs::write(bin.join("$RAB12CD").join("sub").join("a.txt"), b"x").unwrap();
+        let original = "C:\\Users\\ana\\Reports";
+        let mut record = 2u64.to_le_bytes().to_vec();
+        record.extend(4096u64.to_le_bytes());
+        record.extend(133_000_000_000_000_000u64.to_le_bytes());
+        record.extend((original.len() as u32 + 1).to_le_bytes());
+        record.extend(utf16le(original));
+        record.extend([0, 0]);
+        std::fs::write(bin.join("$IAB12CD"), record).unwrap();
+
+        let info = trash_info(&bin.join("$RAB12CD").join("sub").join("a.txt")).unwrap();
+        assert_eq!(info.kind, "recycle_bin");
+        assert_eq!(
+            info.original_path,
+            Path::new(original).join("sub").join("a.txt")
+        );
+        assert_eq!(info.deleted_at, filetime_to_utc(133_000_000_000_000_000));
+
+        // freedesktop trash on a removable volume
+        let volume = dir.path().join("usb");
+        let trash = volume.join(".Trash-1000");
+        std::fs::create_dir_all(trash.join("files")).unwrap();
+        std::fs::create_dir_all(trash.join("info")).unwrap();
+        std::fs::write(trash.join("files").join("photo 1.jpg"), b"x").unwrap();
+        std::fs::write(
+            trash.join("info").join("photo 1.jpg.trashinfo"),
+            "[Trash Info]\nPath=DCIM/photo%201.jpg\nDeletionDate=2024-03-01T10:20:30\n",
+        )
+        .unwrap();
+        let info = trash_info(&trash.join("files").join("photo 1.jpg")).unwrap();
+        assert_eq!(info.kind, "freedesktop");
+        assert_eq!(info.original_path, volume.join("DCIM").join("photo 1.jpg"));
+        assert!(info.deleted_at.is_some());
+
+        // macOS per-user trash on a volume
+        let mac = dir.path().join(".Trashes").join("501");
+        std::fs::create_dir_all(&mac).unwrap();
+        std::fs::write(mac.join("notes 2.txt"), b"x").unwrap();
+        let mut store = vec![0u8; 32];
+        store.extend(ds_record("notes 2.txt", b"ptbL", "Users/ana/Desktop/"));
+        store.extend(ds_record("notes 2.txt", b"ptbN", "notes.txt"));
+        std::fs::write(mac.join(".DS_Store"), store).unwrap();
+        let info = trash_info(&mac.join("notes 2.txt")).unwrap();
+        assert_eq!(info.kind, "macos");
+        assert_eq!(
+            info.original_path,
+            Path::new("/Users/ana/Desktop/notes.txt")
+        );
+
+        let mut meta = HashMap::new();
+        info.apply(&mut meta);
+        assert_eq!(meta["original_name"], "notes.txt");
+        assert_eq!(meta["trash"], "macos");
+
+        assert!(trash_info(&dir.path().join("usb").join("plain.txt")).is_none());
+        assert!(trash_info(&mac.join(".DS_Store")).is_none());
+    }
+}
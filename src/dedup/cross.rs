@@ -0,0 +1,317 @@
+//! Dedup one source against others
+//!
+//! Answers "which files on this drive do I already have?": every file of
+//! the source is looked up by content in reference sources (other drives,
+//! indexes) and baseline manifests from earlier exports. Files found
+//! nowhere are the ones still worth exporting.
+//!
+//! Only files whose size matches something in a reference are hashed, and
+//! always in full (Blake3, the same digest manifests record), so a match
+//! means identical content.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::hash_file;
+use crate::core::FileEntry;
+
+/// Content already known from an earlier export
+#[derive(Debug, Clone)]
+pub struct Baseline {
+    /// The manifest it was read from
+    pub manifest: PathBuf,
+    /// (size, blake3) -> exported path
+    files: HashMap<(u64, String), String>,
+}
+
+/// Just the fields export and proof manifests share
+#[derive(Deserialize)]
+struct BaselineManifest {
+    entries: Vec<BaselineEntry>,
+}
+
+#[derive(Deserialize)]
+struct BaselineEntry {
+    dest_path: String,
+    size: u64,
+    blake3_hash: String,
+}
+
+impl Baseline {
+    /// Load an export manifest or proof manifest
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read baseline {}", path.display()))?;
+        let manifest: BaselineManifest = serde_json::from_str(&data)
+            .with_context(|| format!("Failed to parse baseline {}", path.display()))?;
+        Ok(Self {
+            manifest: path.to_path_buf(),
+            files: manifest
+                .entries
+                .into_iter()
+                .map(|e| ((e.size, e.blake3_hash.to_lowercase()), e.dest_path))
+                .collect(),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+/// A source file that already exists elsewhere
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KnownFile {
+    pub path: PathBuf,
+    pub size: u64,
+    /// A reference file or baseline entry with the same content
+    pub copy: String,
+}
+
+/// Result of comparing a source against references and baselines
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceComparison {
+    pub source: PathBuf,
+    /// Reference sources and baseline manifests compared against
+    pub references: Vec<PathBuf>,
+    pub scanned_files: usize,
+    pub known: Vec<KnownFile>,
+    pub known_bytes: u64,
+    /// Files found in no reference, largest first
+    pub missing: Vec<PathBuf>,
+    pub missing_bytes: u64,
+    /// Files that could not be read
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+    pub generated_at: DateTime<Utc>,
+}
+
+/// Compare `entries` of `source` against the entries of `references` and
+/// the contents of `baselines`. Files smaller than `min_size` are skipped.
+pub fn compare_sources(
+    source: &Path,
+    entries: &[FileEntry],
+    references: &[(PathBuf, Vec<FileEntry>)],
+    baselines: &[Baseline],
+    min_size: u64,
+) -> SourceComparison {
+    let eligible: Vec<&FileEntry> = entries.iter().filter(|e| e.size >= min_size).collect();
+    let sizes: HashSet<u64> = eligible.iter().map(|e| e.size).collect();
+
+    // Hash the reference files a source file could match
+    let candidates: Vec<&FileEntry> = references
+        .iter()
+        .flat_map(|(_, entries)| entries)
+        .filter(|e| sizes.contains(&e.size))
+        .collect();
+    let mut known_content: HashMap<(u64, String), String> = candidates
+        .par_iter()
+        .filter_map(|e| {
+            let hash = content_hash(e)
+                .map_err(|err| tracing::warn!("Failed to hash {}: {}", e.path.display(), err))
+                .ok()?;
+            Some(((e.size, hash), e.path.to_string_lossy().into_owned()))
+        })
+        .collect();
+    for baseline in baselines {
+        for (key, path) in &baseline.files {
+            known_content
+                .entry(key.clone())
+                .or_insert_with(|| format!("{} ({})", path, baseline.manifest.display()));
+        }
+    }
+    let known_sizes: HashSet<u64> = known_content.keys().map(|(size, _)| *size).collect();
+
+    // Source files of a size nothing else has are missing without reading them
+    let lookups: Vec<(&FileEntry, Result<Option<String>>)> = eligible
+        .par_iter()
+        .map(|e| {
+            if !known_sizes.contains(&e.size) {
+                return (*e, Ok(None));
+            }
+            let found = content_hash(e).map(|hash| known_content.get(&(e.size, hash)).cloned());
+            (*e, found)
+        })
+        .collect();
+
+    let mut comparison = SourceComparison {
+        source: source.to_path_buf(),
+        references: references
+            .iter()
+            .map(|(path, _)| path.clone())
+            .chain(baselines.iter().map(|b| b.manifest.clone()))
+            .collect(),
+        scanned_files: eligible.len(),
+        known: Vec::new(),
+        known_bytes: 0,
+        missing: Vec::new(),
+        missing_bytes: 0,
+        errors: Vec::new(),
+        generated_at: Utc::now(),
+    };
+    let mut missing: Vec<&FileEntry> = Vec::new();
+    for (entry, found) in lookups {
+        match found {
+            Ok(Some(copy)) => {
+                comparison.known_bytes += entry.size;
+                comparison.known.push(KnownFile {
+                    path: entry.path.clone(),
+                    size: entry.size,
+                    copy,
+                });
+            }
+            Ok(None) => missing.push(entry),
+            Err(e) => {
+                // Unreadable here means it can't be checked; treat as missing
+                // so it isn't silently left out of an export
+                comparison
+                    .errors
+                    .push(format!("{}: {}", entry.path.display(), e));
+                missing.push(entry);
+            }
+        }
+    }
+    missing.sort_by_key(|e| std::cmp::Reverse(e.size));
+    comparison.missing_bytes = missing.iter().map(|e| e.size).sum();
+    comparison.missing = missing.into_iter().map(|e| e.path.clone()).collect();
+    comparison.known.sort_by(|a, b| a.path.cmp(&b.path));
+    comparison
+}
+
+/// Full Blake3 of a file, from the index when it was hashed while indexing
+fn content_hash(entry: &FileEntry) -> Result<String> {
+    match &entry.hash {
+        Some(hash) => Ok(hash.to_lowercase()),
+        None => hash_file(&entry.path),
+    }
+}
+
+impl SourceComparison {
+    /// Format as human-readable summary.
+    pub fn to_human_string(&self) -> String {
+        let size = |bytes| humansize::format_size(bytes, humansize::BINARY);
+        let mut out = String::new();
+
+        out.push_str(&format!(
+            "\n  Diamond Drill Source Comparison\n  {}\n\n",
+            "=".repeat(40)
+        ));
+        out.push_str(&format!("  Source:           {}\n", self.source.display()));
+        for reference in &self.references {
+            out.push_str(&format!("  Compared with:    {}\n", reference.display()));
+        }
+        out.push_str(&format!(
+            "  Scanned:          {} files\n",
+            self.scanned_files
+        ));
+        out.push_str(&format!(
+            "  Already have:     {} files ({})\n",
+            self.known.len(),
+            size(self.known_bytes)
+        ));
+        out.push_str(&format!(
+            "  Missing:          {} files ({})\n\n",
+            self.missing.len(),
+            size(self.missing_bytes)
+        ));
+
+        for file in &self.known {
+            out.push_str(&format!("    HAVE  {}\n", file.path.display()));
+            out.push_str(&format!("          = {}\n", file.copy));
+        }
+        for path in &self.missing {
+            out.push_str(&format!("    NEW   {}\n", path.display()));
+        }
+        if !self.errors.is_empty() {
+            out.push_str("\n  Could not read (listed as missing):\n");
+            for err in &self.errors {
+                out.push_str(&format!("    {}\n", err));
+            }
+        }
+        out.push('\n');
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::FileType;
+
+    fn entry(path: PathBuf) -> FileEntry {
+        let size = std::fs::metadata(&path).unwrap().len();
+        FileEntry {
+            path,
+            size,
+            file_type: FileType::Document,
+            extension: "txt".to_string(),
+            modified: None,
+            created: None,
+            hash: None,
+            has_bad_sectors: false,
+            thumbnail: None,
+            metadata: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_compare_against_reference_and_baseline() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, data: &[u8]| {
+            let path = dir.path().join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, data).unwrap();
+            path
+        };
+        let drive = vec![
+            entry(write("drive/a.txt", b"on the nas")),
+            entry(write("drive/b.txt", b"exported before")),
+            entry(write("drive/c.txt", b"only here!")), // same size as a.txt
+            entry(write("drive/d.txt", b"new")),
+        ];
+        let nas = vec![entry(write("nas/copy-of-a.txt", b"on the nas"))];
+
+        let exported = hash_file(&drive[1].path).unwrap();
+        let manifest = write(
+            "manifest.json",
+            format!(
+                r#"{{"version": 1, "entries": [{{"source_path": "/old/b.txt",
+                "dest_path": "/backup/b.txt", "size": 15, "blake3_hash": "{}"}}]}}"#,
+                exported
+            )
+            .as_bytes(),
+        );
+        let baseline = Baseline::load(&manifest).unwrap();
+        assert_eq!(baseline.len(), 1);
+
+        let result = compare_sources(
+            &dir.path().join("drive"),
+            &drive,
+            &[(dir.path().join("nas"), nas)],
+            &[baseline],
+            1,
+        );
+        let known: Vec<&Path> = result.known.iter().map(|k| k.path.as_path()).collect();
+        assert_eq!(
+            known,
+            vec![drive[0].path.as_path(), drive[1].path.as_path()]
+        );
+        assert!(result.known[0].copy.ends_with("copy-of-a.txt"));
+        assert!(result.known[1].copy.starts_with("/backup/b.txt"));
+        assert_eq!(
+            result.missing,
+            vec![drive[2].path.clone(), drive[3].path.clone()]
+        );
+        assert_eq!(result.missing_bytes, 13);
+        assert_eq!(result.references.len(), 2);
+    }
+}
@@ -0,0 +1,266 @@
+//! Text diff between near-duplicate documents
+//!
+//! Extracts text from the master and each candidate of a duplicate group and
+//! produces a line-based unified diff, so users can see what actually differs
+//! before deciding which version to keep.
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff as LineDiff};
+
+use super::DupGroup;
+
+/// Files larger than this are not diffed.
+pub const MAX_DIFF_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Default number of unchanged context lines around each hunk.
+pub const DEFAULT_CONTEXT_LINES: usize = 3;
+
+/// Kind of a line in a diff hunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DiffLineKind {
+    Context,
+    Added,
+    Removed,
+}
+
+/// A single line in a diff hunk.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffLine {
+    pub kind: DiffLineKind,
+    pub text: String,
+}
+
+/// A contiguous block of changes with surrounding context.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffHunk {
+    /// Unified diff header, e.g. `@@ -3,7 +3,8 @@`
+    pub header: String,
+    pub lines: Vec<DiffLine>,
+}
+
+/// Diff of one candidate against the group master.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextDiff {
+    pub master: PathBuf,
+    pub candidate: PathBuf,
+    /// Lines present only in the candidate.
+    pub added: usize,
+    /// Lines present only in the master.
+    pub removed: usize,
+    /// Line similarity 0–100.
+    pub similarity: u8,
+    pub hunks: Vec<DiffHunk>,
+}
+
+impl TextDiff {
+    /// True when the extracted text is identical.
+    pub fn is_identical(&self) -> bool {
+        self.hunks.is_empty()
+    }
+
+    /// Format as a unified diff.
+    pub fn to_unified_string(&self) -> String {
+        let mut out = format!(
+            "--- {}\n+++ {}\n",
+            self.master.display(),
+            self.candidate.display()
+        );
+        for hunk in &self.hunks {
+            out.push_str(&hunk.header);
+            out.push('\n');
+            for line in &hunk.lines {
+                let sign = match line.kind {
+                    DiffLineKind::Context => ' ',
+                    DiffLineKind::Added => '+',
+                    DiffLineKind::Removed => '-',
+                };
+                out.push(sign);
+                out.push_str(&line.text);
+                out.push('\n');
+            }
+        }
+        out
+    }
+}
+
+/// Extract comparable text from a document.
+///
+/// PDFs go through pdf-extract; everything else must look like text (no NUL
+/// bytes in the first 8 KB) and is decoded as lossy UTF-8.
+pub fn extract_text(path: &Path) -> Result<String> {
+    let size = std::fs::metadata(path)
+        .with_context(|| format!("Failed to stat {}", path.display()))?
+        .len();
+    if size > MAX_DIFF_BYTES {
+        bail!(
+            "{} is too large to diff ({})",
+            path.display(),
+            humansize::format_size(size, humansize::BINARY)
+        );
+    }
+
+    let mut bytes = Vec::with_capacity(size as usize);
+    std::fs::File::open(path)
+        .and_then(|mut f| f.read_to_end(&mut bytes))
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    let is_pdf = path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("pdf"));
+    if is_pdf {
+        return pdf_extract::extract_text_from_mem(&bytes)
+            .with_context(|| format!("Failed to extract text from PDF: {}", path.display()));
+    }
+
+    if bytes.iter().take(8192).any(|&b| b == 0) {
+        bail!("{} is not a text document", path.display());
+    }
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Diff the text of `candidate` against `master`.
+pub fn diff_files(master: &Path, candidate: &Path, context: usize) -> Result<TextDiff> {
+    let old = extract_text(master)?;
+    let new = extract_text(candidate)?;
+    Ok(diff_text(master, candidate, &old, &new, context))
+}
+
+/// Diff two already-extracted texts.
+pub fn diff_text(
+    master: &Path,
+    candidate: &Path,
+    old: &str,
+    new: &str,
+    context: usize,
+) -> TextDiff {
+    let diff = LineDiff::from_lines(old, new);
+
+    let mut added = 0;
+    let mut removed = 0;
+    let mut hunks = Vec::new();
+
+    for group in diff.grouped_ops(context) {
+        let (Some(first), Some(last)) = (group.first(), group.last()) else {
+            continue;
+        };
+        let old_start = first.old_range().start;
+        let new_start = first.new_range().start;
+        let old_len = last.old_range().end - old_start;
+        let new_len = last.new_range().end - new_start;
+
+        let mut lines = Vec::new();
+        for op in &group {
+            for change in diff.iter_changes(op) {
+                let kind = match change.tag() {
+                    ChangeTag::Equal => DiffLineKind::Context,
+                    ChangeTag::Insert => {
+                        added += 1;
+                        DiffLineKind::Added
+                    }
+                    ChangeTag::Delete => {
+                        removed += 1;
+                        DiffLineKind::Removed
+                    }
+                };
+                lines.push(DiffLine {
+                    kind,
+                    text: change.value().trim_end_matches(['\r', '\n']).to_string(),
+                });
+            }
+        }
+
+        hunks.push(DiffHunk {
+            header: format!(
+                "@@ -{},{} +{},{} @@",
+                old_start + 1,
+                old_len,
+                new_start + 1,
+                new_len
+            ),
+            lines,
+        });
+    }
+
+    TextDiff {
+        master: master.to_path_buf(),
+        candidate: candidate.to_path_buf(),
+        added,
+        removed,
+        similarity: (diff.ratio() * 100.0).round() as u8,
+        hunks,
+    }
+}
+
+/// Diff every duplicate in `group` against the group master.
+///
+/// Each candidate gets its own result so one unreadable file does not hide
+/// the others.
+pub fn diff_group(group: &DupGroup, context: usize) -> Vec<(PathBuf, Result<TextDiff>)> {
+    group
+        .duplicates
+        .iter()
+        .map(|dup| (dup.clone(), diff_files(&group.master, dup, context)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_diff_group() {
+        let dir = tempdir().unwrap();
+        let master = dir.path().join("report.txt");
+        let edited = dir.path().join("report_v2.txt");
+        let binary = dir.path().join("report (1).txt");
+        std::fs::write(&master, "Title\nline one\nline two\nline three\n").unwrap();
+        std::fs::write(&edited, "Title\nline one\nline 2\nline three\nappendix\n").unwrap();
+        std::fs::write(&binary, b"Title\0\x01\x02").unwrap();
+
+        let group = DupGroup {
+            hash: None,
+            similarity: 90,
+            master: master.clone(),
+            duplicates: vec![edited.clone(), binary.clone()],
+            wasted_bytes: 0,
+            digests: Default::default(),
+            media: None,
+        };
+
+        let results = diff_group(&group, DEFAULT_CONTEXT_LINES);
+        assert_eq!(results.len(), 2);
+
+        let diff = results[0].1.as_ref().unwrap();
+        assert_eq!(diff.added, 2);
+        assert_eq!(diff.removed, 1);
+        assert!(!diff.is_identical());
+        let unified = diff.to_unified_string();
+        assert!(unified.contains("-line two\n"));
+        assert!(unified.contains("+line 2\n"));
+        assert!(unified.contains("+appendix\n"));
+        assert!(unified.starts_with("--- "));
+
+        assert!(
+            results[1].1.is_err(),
+            "binary candidate should not be diffed"
+        );
+    }
+
+    #[test]
+    fn test_identical_text() {
+        let diff = diff_text(
+            Path::new("a.txt"),
+            Path::new("b.txt"),
+            "same\ntext\n",
+            "same\ntext\n",
+            3,
+        );
+        assert!(diff.is_identical());
+        assert_eq!(diff.similarity, 100);
+    }
+}
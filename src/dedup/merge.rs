@@ -0,0 +1,233 @@
+//! Merge duplicates into links instead of deleting them
+//!
+//! `dedup --merge` keeps every path of an export tree but lets duplicates
+//! share the master's storage: a hardlink makes them the same file, a
+//! reflink (FICLONE on btrfs/XFS, `clonefile` on APFS) gives them their own
+//! inode backed by the same extents, so editing one later doesn't change
+//! the others.
+//!
+//! Only exact groups are merged, and each duplicate is re-hashed in full
+//! against the master first (large files are grouped on a partial hash).
+//! The link is made next to the duplicate and renamed over it, so a failure
+//! leaves the original in place.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::{hash_file, DupGroup};
+
+/// How duplicates are merged into their master
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeMode {
+    /// Same inode as the master (shares its permissions and timestamps)
+    Hardlink,
+    /// Copy-on-write clone of the master, keeping the duplicate's
+    /// permissions and modification time
+    Reflink,
+}
+
+/// Replace the duplicates in exact groups with links to their master.
+/// Returns (merged_count, reclaimed_bytes, errors).
+pub fn merge_duplicates(
+    groups: &[DupGroup],
+    mode: MergeMode,
+    dry_run: bool,
+) -> (usize, u64, Vec<String>) {
+    let mut merged = 0usize;
+    let mut reclaimed = 0u64;
+    let mut errors = Vec::new();
+
+    for group in groups {
+        if group.hash.is_none() {
+            tracing::info!(
+                "Not merging {} (similar, not identical)",
+                group.master.display()
+            );
+            continue;
+        }
+        let master_hash = match hash_file(&group.master) {
+            Ok(hash) => hash,
+            Err(e) => {
+                errors.push(format!("{}: {}", group.master.display(), e));
+                continue;
+            }
+        };
+        for dup in &group.duplicates {
+            match merge_one(&group.master, &master_hash, dup, mode, dry_run) {
+                Ok(Some(size)) => {
+                    merged += 1;
+                    reclaimed += size;
+                }
+                Ok(None) => {}
+                Err(e) => errors.push(format!("{}: {}", dup.display(), e)),
+            }
+        }
+    }
+
+    (merged, reclaimed, errors)
+}
+
+/// Merge one duplicate, returning its size, or None if it already shares
+/// the master's inode
+fn merge_one(
+    master: &Path,
+    master_hash: &str,
+    dup: &Path,
+    mode: MergeMode,
+    dry_run: bool,
+) -> anyhow::Result<Option<u64>> {
+    let meta = std::fs::metadata(dup)?;
+    if same_file(master, dup) {
+        return Ok(None);
+    }
+    if hash_file(dup)? != master_hash {
+        anyhow::bail!("content differs from {}", master.display());
+    }
+    if dry_run {
+        tracing::info!(
+            "[DRY RUN] Would link {} -> {}",
+            dup.display(),
+            master.display()
+        );
+        return Ok(Some(meta.len()));
+    }
+
+    let temp = temp_path(dup);
+    let linked = match mode {
+        MergeMode::Hardlink => std::fs::hard_link(master, &temp),
+        MergeMode::Reflink => reflink(master, &temp).and_then(|()| {
+            std::fs::set_permissions(&temp, meta.permissions())?;
+            if let Ok(modified) = meta.modified() {
+                std::fs::File::options()
+                    .write(true)
+                    .open(&temp)?
+                    .set_modified(modified)?;
+            }
+            Ok(())
+        }),
+    }
+    .and_then(|()| std::fs::rename(&temp, dup));
+    if let Err(e) = linked {
+        let _ = std::fs::remove_file(&temp);
+        return Err(e.into());
+    }
+    tracing::info!("Linked: {} -> {}", dup.display(), master.display());
+    Ok(Some(meta.len()))
+}
+
+/// Hidden sibling of `path` the link is made at before replacing it
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}.ddmerge", name))
+}
+
+#[cfg(unix)]
+fn same_file(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (std::fs::metadata(a), std::fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn same_file(_a: &Path, _b: &Path) -> bool {
+    false
+}
+
+#[cfg(target_os = "linux")]
+fn reflink(src: &Path, dst: &Path) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let source = std::fs::File::open(src)?;
+    let target = std::fs::File::options()
+        .write(true)
+        .create_new(true)
+        .open(dst)?;
+    // SAFETY: both descriptors are open for the duration of the call
+    if unsafe { libc::ioctl(target.as_raw_fd(), libc::FICLONE, source.as_raw_fd()) } != 0 {
+        let err = io::Error::last_os_error();
+        drop(target);
+        let _ = std::fs::remove_file(dst);
+        return Err(err);
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn reflink(src: &Path, dst: &Path) -> io::Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c = |p: &Path| {
+        CString::new(p.as_os_str().as_bytes())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+    };
+    let (src, dst) = (c(src)?, c(dst)?);
+    // SAFETY: both are valid NUL-terminated paths
+    if unsafe { libc::clonefile(src.as_ptr(), dst.as_ptr(), 0) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn reflink(_src: &Path, _dst: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "reflinks are only supported on Linux and macOS",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Digests;
+
+    #[test]
+    fn test_hardlink_merge_keeps_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let master = dir.path().join("a.bin");
+        let dup = dir.path().join("sub").join("a.bin");
+        let changed = dir.path().join("b.bin");
+        std::fs::create_dir_all(dup.parent().unwrap()).unwrap();
+        std::fs::write(&master, vec![7u8; 5000]).unwrap();
+        std::fs::write(&dup, vec![7u8; 5000]).unwrap();
+        std::fs::write(&changed, vec![8u8; 5000]).unwrap();
+
+        let group = DupGroup {
+            hash: Some(hash_file(&master).unwrap()),
+            similarity: 100,
+            master: master.clone(),
+            duplicates: vec![dup.clone(), changed.clone()],
+            wasted_bytes: 10000,
+            digests: Digests::new(),
+            media: None,
+        };
+
+        let (merged, reclaimed, errors) =
+            merge_duplicates(std::slice::from_ref(&group), MergeMode::Hardlink, true);
+        assert_eq!((merged, reclaimed, errors.len()), (1, 5000, 1));
+        assert!(!same_file(&master, &dup));
+
+        let (merged, _, errors) =
+            merge_duplicates(std::slice::from_ref(&group), MergeMode::Hardlink, false);
+        assert_eq!((merged, errors.len()), (1, 1));
+        assert!(errors[0].contains("content differs"));
+        #[cfg(unix)]
+        assert!(same_file(&master, &dup));
+        assert_eq!(std::fs::read(&dup).unwrap(), vec![7u8; 5000]);
+        assert_eq!(std::fs::read(&changed).unwrap(), vec![8u8; 5000]);
+        assert!(!temp_path(&dup).exists());
+
+        // Already linked: nothing left to do
+        let (merged, _, errors) =
+            merge_duplicates(std::slice::from_ref(&group), MergeMode::Hardlink, false);
+        #[cfg(unix)]
+        assert_eq!(merged, 0);
+        assert_eq!(errors.len(), 1);
+    }
+}
@@ -1,7 +1,15 @@
 //! Dedup module - Content-addressable and fuzzy file deduplication
 //!
 //! Provides exact (Blake3) and near-duplicate detection with
-//! intelligent master selection and purge/merge workflows.
+//! intelligent master selection and purge/merge workflows. Audio and video
+//! can also be matched on what they sound or look like (see [`media`]), and
+//! a source can be checked against other sources or earlier exports (see
+//! [`cross`]).
+
+pub mod cross;
+pub mod diff;
+pub mod media;
+pub mod merge;
 
 use std::collections::HashMap;
 use std::io::Read;
@@ -12,7 +20,10 @@ use chrono::{DateTime, Utc};
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use crate::core::FileEntry;
+use crate::core::{compute_digests, Digests, FileEntry, HashAlgorithm};
+
+pub use cross::{compare_sources, Baseline, SourceComparison};
+pub use merge::{merge_duplicates, MergeMode};
 
 // ---------------------------------------------------------------------------
 // Types
@@ -45,6 +56,12 @@ pub struct DupGroup {
     pub duplicates: Vec<PathBuf>,
     /// Total bytes that would be freed by purging duplicates.
     pub wasted_bytes: u64,
+    /// Extra digests of the shared content (`DedupOptions::hash_algorithms`).
+    #[serde(default, skip_serializing_if = "Digests::is_empty")]
+    pub digests: Digests,
+    /// Set for groups matched on audio or video fingerprints.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub media: Option<media::MediaKind>,
 }
 
 /// Full dedup analysis report.
@@ -72,6 +89,10 @@ pub struct DedupOptions {
     pub fuzzy_threshold: u8,
     /// Minimum file size to consider (skip tiny files).
     pub min_size: u64,
+    /// Extra digests (e.g. MD5, SHA-256) to report for exact groups.
+    pub hash_algorithms: Vec<HashAlgorithm>,
+    /// Match audio and video by content fingerprint (uses `fuzzy_threshold`).
+    pub media: bool,
 }
 
 impl Default for DedupOptions {
@@ -81,6 +102,8 @@ impl Default for DedupOptions {
             fuzzy: false,
             fuzzy_threshold: 85,
             min_size: 1, // skip 0-byte files
+            hash_algorithms: Vec::new(),
+            media: false,
         }
     }
 }
@@ -309,6 +332,19 @@ pub fn find_exact_duplicates(
             master,
             duplicates,
             wasted_bytes: wasted,
+            digests: Digests::new(),
+            media: None,
+        });
+    }
+
+    // Extra digests are read in full from the master (large files were
+    // grouped on a partial hash)
+    if !options.hash_algorithms.is_empty() {
+        groups.par_iter_mut().for_each(|group| {
+            match compute_digests(&group.master, &options.hash_algorithms) {
+                Ok(digests) => group.digests = digests,
+                Err(e) => tracing::warn!("Failed to hash {}: {}", group.master.display(), e),
+            }
         });
     }
 
@@ -482,6 +518,8 @@ pub fn find_fuzzy_duplicates(
                 master,
                 duplicates,
                 wasted_bytes: wasted,
+                digests: Digests::new(),
+                media: None,
             });
         }
     }
@@ -494,9 +532,9 @@ pub fn find_fuzzy_duplicates(
 // Combined analysis
 // ---------------------------------------------------------------------------
 
-/// Run full dedup analysis: exact first, then optionally fuzzy.
-/// Exact groups take priority — files already in an exact group are excluded
-/// from fuzzy analysis to avoid double-counting.
+/// Run full dedup analysis: exact first, then optionally fuzzy and media.
+/// Earlier phases take priority — files already in a group are excluded
+/// from later phases to avoid double-counting.
 pub fn analyze(entries: &[FileEntry], options: &DedupOptions) -> Result<DedupReport> {
     let mut all_groups: Vec<DupGroup> = Vec::new();
 
@@ -519,9 +557,23 @@ pub fn analyze(entries: &[FileEntry], options: &DedupOptions) -> Result<DedupRep
             .cloned()
             .collect();
         let fuzzy_groups = find_fuzzy_duplicates(&remaining, options)?;
+        for group in &fuzzy_groups {
+            seen.insert(group.master.clone());
+            seen.extend(group.duplicates.iter().cloned());
+        }
         all_groups.extend(fuzzy_groups);
     }
 
+    // Phase 3: audio/video fingerprints (on remaining files)
+    if options.media {
+        let remaining: Vec<FileEntry> = entries
+            .iter()
+            .filter(|e| !seen.contains(&e.path))
+            .cloned()
+            .collect();
+        all_groups.extend(media::find_media_duplicates(&remaining, options));
+    }
+
     let total_dups: usize = all_groups.iter().map(|g| g.duplicates.len()).sum();
     let wasted: u64 = all_groups.iter().map(|g| g.wasted_bytes).sum();
 
@@ -620,10 +672,11 @@ impl DedupReport {
         ));
 
         for (i, group) in self.groups.iter().enumerate() {
-            let kind = if group.similarity == 100 {
-                "EXACT"
-            } else {
-                "FUZZY"
+            let kind = match group.media {
+                Some(media::MediaKind::Audio) => "AUDIO",
+                Some(media::MediaKind::Video) => "VIDEO",
+                None if group.similarity == 100 => "EXACT",
+                None => "FUZZY",
             };
             out.push_str(&format!(
                 "  Group #{} [{}] ({}% similar, {} wasted)\n",
@@ -632,6 +685,13 @@ impl DedupReport {
                 group.similarity,
                 humansize::format_size(group.wasted_bytes, humansize::BINARY)
             ));
+            for (algorithm, digest) in &group.digests {
+                out.push_str(&format!(
+                    "    {:<6}{}\n",
+                    algorithm.name().to_uppercase(),
+                    digest
+                ));
+            }
             out.push_str(&format!("    KEEP  {}\n", group.master.display()));
             for dup in &group.duplicates {
                 out.push_str(&format!("    PURGE {}\n", dup.display()));
@@ -664,6 +724,7 @@ mod tests {
             hash: None,
             has_bad_sectors: false,
             thumbnail: None,
+            metadata: Default::default(),
         }
     }
 
@@ -796,12 +857,17 @@ mod tests {
 
         let options = DedupOptions {
             strategy: KeepStrategy::Cleanest,
+            hash_algorithms: vec![HashAlgorithm::Md5],
             ..Default::default()
         };
 
         let groups = find_exact_duplicates(&entries, &options).unwrap();
         assert_eq!(groups.len(), 1);
         assert_eq!(groups[0].master, clean);
+        assert_eq!(
+            groups[0].digests[&HashAlgorithm::Md5],
+            compute_digests(&clean, &[HashAlgorithm::Md5]).unwrap()[&HashAlgorithm::Md5]
+        );
     }
 
     #[test]
@@ -874,6 +940,8 @@ mod tests {
             master: p1.clone(),
             duplicates: vec![p2.clone()],
             wasted_bytes: 6,
+            digests: Digests::new(),
+            media: None,
         }];
 
         let (deleted, _freed, errors) = purge_duplicates(&groups, true);
@@ -898,6 +966,8 @@ mod tests {
             master: p1.clone(),
             duplicates: vec![p2.clone()],
             wasted_bytes: 6,
+            digests: Digests::new(),
+            media: None,
         }];
 
         let (deleted, freed, errors) = purge_duplicates(&groups, false);
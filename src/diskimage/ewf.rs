@@ -0,0 +1,712 @@
+//! Expert Witness Format (EWF-E01) reading and writing
+//!
+//! An E01 image is a set of segment files (`.E01`, `.E02`, … `.E99`, `.EAA`,
+//! …). Each segment starts with a 13-byte file header followed by a chain of
+//! sections, each with a 76-byte descriptor:
+//!
+//! - `header` – zlib-compressed acquisition metadata (case, examiner, dates)
+//! - `volume` / `data` – media geometry (chunk and sector sizes, sector count)
+//! - `sectors` – the chunk data, 64 sectors per chunk
+//! - `table` / `table2` – offsets of each chunk in the preceding `sectors`
+//!   section (bit 31 set = zlib-compressed chunk)
+//! - `hash` – MD5 of the media, `next` / `done` – end of segment / image
+//!
+//! The reader handles compressed and uncompressed chunks; the writer always
+//! stores chunks uncompressed, each followed by its Adler-32 checksum.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use chrono::{Datelike, Local, Timelike};
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+use crate::error::DrillError;
+
+/// Signature at the start of every EWF segment file
+pub const EVF_SIGNATURE: [u8; 8] = *b"EVF\x09\x0d\x0a\xff\x00";
+
+const FILE_HEADER_SIZE: u64 = 13;
+const SECTION_DESCRIPTOR_SIZE: u64 = 76;
+const VOLUME_DATA_SIZE: usize = 1052;
+const TABLE_HEADER_SIZE: u64 = 24;
+
+/// Sectors per chunk used by EnCase and by this writer
+pub const SECTORS_PER_CHUNK: u32 = 64;
+pub const BYTES_PER_SECTOR: u32 = 512;
+const CHUNK_SIZE: usize = (SECTORS_PER_CHUNK * BYTES_PER_SECTOR) as usize;
+
+/// EnCase limits a table section to this many chunk entries
+const MAX_TABLE_ENTRIES: usize = 16375;
+
+/// Table offsets are 31-bit, so segment files must stay below 2 GiB
+pub const MAX_SEGMENT_SIZE: u64 = (1 << 31) - 1;
+
+/// Smallest segment that still fits the metadata sections and a full table
+pub const MIN_SEGMENT_SIZE: u64 = 1024 * 1024;
+
+/// Room kept free at the end of a segment for the table, table2, hash and
+/// done/next sections
+const SEGMENT_RESERVE: u64 = 2
+    * (SECTION_DESCRIPTOR_SIZE + TABLE_HEADER_SIZE + 4 + 4 * MAX_TABLE_ENTRIES as u64)
+    + 3 * SECTION_DESCRIPTOR_SIZE
+    + 36;
+
+/// Adler-32 checksum as used throughout EWF
+pub(crate) fn adler32(data: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+    // 5552 is the largest block that cannot overflow `b` before reducing
+    for block in data.chunks(5552) {
+        for &byte in block {
+            a += byte as u32;
+            b += a;
+        }
+        a %= MOD;
+        b %= MOD;
+    }
+    (b << 16) | a
+}
+
+/// Whether `path` starts with the EWF segment signature
+pub fn is_ewf(path: &Path) -> bool {
+    let mut signature = [0u8; 8];
+    File::open(path)
+        .and_then(|mut f| f.read_exact(&mut signature))
+        .is_ok()
+        && signature == EVF_SIGNATURE
+}
+
+/// Path of segment `number` (1-based) given the first segment's path:
+/// `.E01`–`.E99`, then `.EAA`–`.EZZ`, `.FAA`, …
+pub fn segment_path(first: &Path, number: u32) -> Result<PathBuf> {
+    let ext = first
+        .extension()
+        .map(|e| e.to_string_lossy().to_string())
+        .unwrap_or_else(|| "E01".to_string());
+    let lead = ext.chars().next().unwrap_or('E');
+    let upper = lead.is_ascii_uppercase();
+    let ext = if number == 0 {
+        bail!("EWF segment numbers start at 1");
+    } else if number <= 99 {
+        format!("{}{:02}", lead, number)
+    } else {
+        let n = number - 100;
+        let (first_letter, rest) = (
+            (lead as u32 - if upper { 'A' } else { 'a' } as u32) + n / (26 * 26),
+            n % (26 * 26),
+        );
+        if first_letter >= 26 {
+            bail!("Too many EWF segments ({})", number);
+        }
+        let base = if upper { b'A' } else { b'a' };
+        [first_letter as u8, (rest / 26) as u8, (rest % 26) as u8]
+            .iter()
+            .map(|c| (base + c) as char)
+            .collect()
+    };
+    Ok(first.with_extension(ext))
+}
+
+/// Acquisition metadata stored in the `header` section
+#[derive(Debug, Clone, Default)]
+pub struct EwfMetadata {
+    pub case_number: String,
+    pub evidence_number: String,
+    pub description: String,
+    pub examiner: String,
+    pub notes: String,
+}
+
+/// `path` can't be read as an E01 image
+fn malformed(path: &Path, message: impl Into<String>) -> anyhow::Error {
+    DrillError::FormatParse {
+        path: path.to_path_buf(),
+        format: "EWF (E01) image",
+        message: message.into(),
+    }
+    .into()
+}
+
+fn section_descriptor(kind: &str, next: u64, size: u64) -> [u8; 76] {
+    let mut desc = [0u8; 76];
+    desc[..kind.len()].copy_from_slice(kind.as_bytes());
+    desc[16..24].copy_from_slice(&next.to_le_bytes());
+    desc[24..32].copy_from_slice(&size.to_le_bytes());
+    let checksum = adler32(&desc[..72]);
+    desc[72..76].copy_from_slice(&checksum.to_le_bytes());
+    desc
+}
+
+fn volume_data(chunks: u32, sectors: u64) -> Vec<u8> {
+    let mut data = vec![0u8; VOLUME_DATA_SIZE];
+    data[0] = 0x01; // fixed disk
+    data[4..8].copy_from_slice(&chunks.to_le_bytes());
+    data[8..12].copy_from_slice(&SECTORS_PER_CHUNK.to_le_bytes());
+    data[12..16].copy_from_slice(&BYTES_PER_SECTOR.to_le_bytes());
+    data[16..24].copy_from_slice(&sectors.to_le_bytes());
+    data[36] = 0x01; // image file
+    data[52] = 0x00; // no compression
+    data[56..60].copy_from_slice(&SECTORS_PER_CHUNK.to_le_bytes());
+    data[64..80].copy_from_slice(uuid::Uuid::new_v4().as_bytes());
+    let checksum = adler32(&data[..1048]);
+    data[1048..1052].copy_from_slice(&checksum.to_le_bytes());
+    data
+}
+
+fn header_data(meta: &EwfMetadata) -> Result<Vec<u8>> {
+    let now = Local::now();
+    let date = format!(
+        "{} {} {} {} {} {}",
+        now.year(),
+        now.month(),
+        now.day(),
+        now.hour(),
+        now.minute(),
+        now.second()
+    );
+    let clean = |s: &str| s.replace(['\t', '\n', '\r'], " ");
+    let text = format!(
+        "1\r\nmain\r\nc\tn\ta\te\tt\tav\tov\tm\tu\tp\tr\r\n{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t0\tn\r\n\r\n",
+        clean(&meta.case_number),
+        clean(&meta.evidence_number),
+        clean(&meta.description),
+        clean(&meta.examiner),
+        clean(&meta.notes),
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        date,
+        date,
+    );
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(text.as_bytes())?;
+    Ok(encoder.finish()?)
+}
+
+/// Streaming E01 writer. Bytes written are split into 32 KiB chunks and
+/// stored uncompressed across as many segment files as `segment_size` needs.
+pub struct EwfWriter {
+    first_path: PathBuf,
+    segment_size: u64,
+    media_size: u64,
+    metadata: EwfMetadata,
+    segment_number: u32,
+    out: Option<BufWriter<File>>,
+    pos: u64,
+    /// Offset of the open `sectors` section descriptor
+    sectors_start: Option<u64>,
+    /// Chunk offsets in the open `sectors` section
+    table: Vec<u32>,
+    buffer: Vec<u8>,
+    written: u64,
+    segments: Vec<PathBuf>,
+}
+
+impl EwfWriter {
+    /// Create `path` (which should end in `.E01`) for an image of
+    /// `media_size` bytes
+    pub fn create(
+        path: &Path,
+        media_size: u64,
+        segment_size: u64,
+        metadata: EwfMetadata,
+    ) -> Result<Self> {
+        if !(MIN_SEGMENT_SIZE..=MAX_SEGMENT_SIZE).contains(&segment_size) {
+            bail!(
+                "E01 segment size must be between {} and {}",
+                humansize::format_size(MIN_SEGMENT_SIZE, humansize::BINARY),
+                humansize::format_size(MAX_SEGMENT_SIZE, humansize::BINARY)
+            );
+        }
+        if !media_size.is_multiple_of(BYTES_PER_SECTOR as u64) {
+            bail!(
+                "E01 stores whole {}-byte sectors; {} bytes is not a multiple",
+                BYTES_PER_SECTOR,
+                media_size
+            );
+        }
+        let mut writer = Self {
+            first_path: path.to_path_buf(),
+            segment_size,
+            media_size,
+            metadata,
+            segment_number: 0,
+            out: None,
+            pos: 0,
+            sectors_start: None,
+            table: Vec::new(),
+            buffer: Vec::with_capacity(CHUNK_SIZE),
+            written: 0,
+            segments: Vec::new(),
+        };
+        writer.open_segment()?;
+        Ok(writer)
+    }
+
+    fn out(&mut self) -> &mut BufWriter<File> {
+        self.out.as_mut().expect("segment is open")
+    }
+
+    fn emit(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.out().write_all(bytes)?;
+        self.pos += bytes.len() as u64;
+        Ok(())
+    }
+
+    /// Write a section descriptor followed by `data`
+    fn write_section(&mut self, kind: &str, data: &[u8], last: bool) -> io::Result<()> {
+        let size = SECTION_DESCRIPTOR_SIZE + data.len() as u64;
+        let next = if last { self.pos } else { self.pos + size };
+        let desc = section_descriptor(kind, next, size);
+        self.emit(&desc)?;
+        self.emit(data)
+    }
+
+    fn open_segment(&mut self) -> Result<()> {
+        self.segment_number += 1;
+        let path = segment_path(&self.first_path, self.segment_number)?;
+        let file = File::create(&path)
+            .with_context(|| format!("Failed to create segment {}", path.display()))?;
+        self.out = Some(BufWriter::with_capacity(1024 * 1024, file));
+        self.pos = 0;
+        self.segments.push(path);
+
+        let mut header = Vec::with_capacity(FILE_HEADER_SIZE as usize);
+        header.extend_from_slice(&EVF_SIGNATURE);
+        header.push(0x01);
+        header.extend_from_slice(&(self.segment_number as u16).to_le_bytes());
+        header.extend_from_slice(&[0, 0]);
+        self.emit(&header)?;
+
+        let chunks = self.media_size.div_ceil(CHUNK_SIZE as u64) as u32;
+        let sectors = self.media_size.div_ceil(BYTES_PER_SECTOR as u64);
+        let volume = volume_data(chunks, sectors);
+        if self.segment_number == 1 {
+            let header = header_data(&self.metadata)?;
+            // EnCase writes the header section twice
+            self.write_section("header", &header, false)?;
+            self.write_section("header", &header, false)?;
+            self.write_section("volume", &volume, false)?;
+        } else {
+            self.write_section("data", &volume, false)?;
+        }
+        Ok(())
+    }
+
+    fn start_sectors(&mut self) -> io::Result<()> {
+        self.sectors_start = Some(self.pos);
+        // Placeholder, rewritten once the section's size is known
+        self.emit(&[0u8; SECTION_DESCRIPTOR_SIZE as usize])
+    }
+
+    /// Close the open `sectors` section and write its table and table2
+    fn close_sectors(&mut self) -> io::Result<()> {
+        let Some(start) = self.sectors_start.take() else {
+            return Ok(());
+        };
+        let end = self.pos;
+        let desc = section_descriptor("sectors", end, end - start);
+        let out = self.out();
+        out.seek(SeekFrom::Start(start))?;
+        out.write_all(&desc)?;
+        out.seek(SeekFrom::Start(end))?;
+
+        let mut data = Vec::with_capacity(TABLE_HEADER_SIZE as usize + 4 * self.table.len() + 4);
+        data.extend_from_slice(&(self.table.len() as u32).to_le_bytes());
+        data.extend_from_slice(&[0u8; 4]);
+        data.extend_from_slice(&0u64.to_le_bytes()); // base offset
+        data.extend_from_slice(&[0u8; 4]);
+        let checksum = adler32(&data);
+        data.extend_from_slice(&checksum.to_le_bytes());
+        let entries_start = data.len();
+        for offset in &self.table {
+            data.extend_from_slice(&offset.to_le_bytes());
+        }
+        let checksum = adler32(&data[entries_start..]);
+        data.extend_from_slice(&checksum.to_le_bytes());
+        self.table.clear();
+
+        self.write_section("table", &data, false)?;
+        self.write_section("table2", &data, false)
+    }
+
+    fn write_chunk(&mut self, chunk: &[u8]) -> Result<()> {
+        let needed = chunk.len() as u64 + 4;
+        if self.pos + needed + SEGMENT_RESERVE > self.segment_size {
+            self.close_sectors()?;
+            self.write_section("next", &[], true)?;
+            self.out().flush()?;
+            self.open_segment()?;
+        }
+        if self.sectors_start.is_none() || self.table.len() >= MAX_TABLE_ENTRIES {
+            self.close_sectors()?;
+            self.start_sectors()?;
+        }
+        self.table.push(self.pos as u32);
+        self.emit(chunk)?;
+        self.emit(&adler32(chunk).to_le_bytes())?;
+        Ok(())
+    }
+
+    /// Flush the last chunk, write the MD5 `hash` section and `done`, and
+    /// return the segment files written
+    pub fn finish(mut self, md5: [u8; 16]) -> Result<Vec<PathBuf>> {
+        if self.written != self.media_size {
+            bail!(
+                "E01 writer received {} bytes but the volume declares {}",
+                self.written,
+                self.media_size
+            );
+        }
+        if !self.buffer.is_empty() {
+            let chunk = std::mem::take(&mut self.buffer);
+            self.write_chunk(&chunk)?;
+        }
+        self.close_sectors()?;
+
+        let mut hash = Vec::with_capacity(36);
+        hash.extend_from_slice(&md5);
+        hash.extend_from_slice(&[0u8; 16]);
+        let checksum = adler32(&hash);
+        hash.extend_from_slice(&checksum.to_le_bytes());
+        self.write_section("hash", &hash, false)?;
+        self.write_section("done", &[], true)?;
+        self.out().flush()?;
+        Ok(self.segments)
+    }
+}
+
+impl Write for EwfWriter {
+    fn write(&mut self, mut buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len();
+        while !buf.is_empty() {
+            let take = (CHUNK_SIZE - self.buffer.len()).min(buf.len());
+            self.buffer.extend_from_slice(&buf[..take]);
+            buf = &buf[take..];
+            if self.buffer.len() == CHUNK_SIZE {
+                let chunk = std::mem::take(&mut self.buffer);
+                self.write_chunk(&chunk).map_err(io::Error::other)?;
+                self.buffer = chunk;
+                self.buffer.clear();
+            }
+        }
+        self.written += len as u64;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out().flush()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ChunkLocation {
+    segment: usize,
+    offset: u64,
+    /// Bytes stored for the chunk, including a trailing checksum if any
+    stored_size: u64,
+    compressed: bool,
+}
+
+/// Random-access reader over the media stored in an E01 segment set
+pub struct EwfReader {
+    segments: Vec<File>,
+    chunks: Vec<ChunkLocation>,
+    chunk_size: u64,
+    media_size: u64,
+    md5: Option<[u8; 16]>,
+    pos: u64,
+    cached_chunk: Option<(usize, Vec<u8>)>,
+}
+
+impl EwfReader {
+    /// Open an E01 image given its first segment; later segments are found
+    /// by name next to it
+    pub fn open(first: &Path) -> Result<Self> {
+        let mut segments = Vec::new();
+        let mut chunks = Vec::new();
+        let mut geometry: Option<(u64, u64)> = None;
+        let mut md5 = None;
+        let mut done = false;
+
+        for number in 1.. {
+            let path = segment_path(first, number)?;
+            if number > 1 && !path.exists() {
+                break;
+            }
+            let mut file = File::open(&path)
+                .with_context(|| format!("Failed to open EWF segment {}", path.display()))?;
+            let mut header = [0u8; FILE_HEADER_SIZE as usize];
+            file.read_exact(&mut header)
+                .with_context(|| format!("Truncated EWF segment {}", path.display()))?;
+            if header[..8] != EVF_SIGNATURE {
+                return Err(malformed(&path, "no EVF signature"));
+            }
+            let file_len = file.metadata()?.len();
+            let segment = segments.len();
+
+            let mut offset = FILE_HEADER_SIZE;
+            let mut sectors_end = None;
+            loop {
+                let mut desc = [0u8; SECTION_DESCRIPTOR_SIZE as usize];
+                file.seek(SeekFrom::Start(offset))?;
+                file.read_exact(&mut desc).with_context(|| {
+                    format!("Truncated section at {} in {}", offset, path.display())
+                })?;
+                let kind = String::from_utf8_lossy(&desc[..16])
+                    .trim_end_matches('\0')
+                    .to_string();
+                let next = u64::from_le_bytes(desc[16..24].try_into().expect("8 bytes"));
+                let size = u64::from_le_bytes(desc[24..32].try_into().expect("8 bytes"));
+
+                match kind.as_str() {
+                    "volume" | "disk" | "data" => {
+                        let mut data = vec![0u8; 24];
+                        file.read_exact(&mut data)?;
+                        let sectors_per_chunk =
+                            u32::from_le_bytes(data[8..12].try_into().expect("4 bytes")) as u64;
+                        let bytes_per_sector =
+                            u32::from_le_bytes(data[12..16].try_into().expect("4 bytes")) as u64;
+                        let sectors = u64::from_le_bytes(data[16..24].try_into().expect("8 bytes"));
+                        if sectors_per_chunk == 0 || bytes_per_sector == 0 {
+                            return Err(malformed(&path, "invalid volume geometry"));
+                        }
+                        geometry.get_or_insert((
+                            sectors_per_chunk * bytes_per_sector,
+                            sectors * bytes_per_sector,
+                        ));
+                    }
+                    "sectors" => sectors_end = Some(offset + size),
+                    "table" => {
+                        let mut table_header = [0u8; TABLE_HEADER_SIZE as usize];
+                        file.read_exact(&mut table_header)?;
+                        let count =
+                            u32::from_le_bytes(table_header[..4].try_into().expect("4 bytes"))
+                                as usize;
+                        let base =
+                            u64::from_le_bytes(table_header[8..16].try_into().expect("8 bytes"));
+                        if count as u64 * 4 > size.saturating_sub(SECTION_DESCRIPTOR_SIZE) {
+                            return Err(malformed(&path, format!("corrupt table at {}", offset)));
+                        }
+                        let mut raw = vec![0u8; count * 4];
+                        file.read_exact(&mut raw)?;
+                        let entries: Vec<(u64, bool)> = raw
+                            .chunks_exact(4)
+                            .map(|e| {
+                                let v = u32::from_le_bytes(e.try_into().expect("4 bytes"));
+                                (base + (v & 0x7FFF_FFFF) as u64, v & 0x8000_0000 != 0)
+                            })
+                            .collect();
+                        // The last chunk runs to the end of its sectors section
+                        let end = sectors_end.filter(|&e| e <= offset).unwrap_or(offset);
+                        for (i, &(chunk_offset, compressed)) in entries.iter().enumerate() {
+                            let chunk_end = entries.get(i + 1).map_or(end, |next| next.0);
+                            chunks.push(ChunkLocation {
+                                segment,
+                                offset: chunk_offset,
+                                stored_size: chunk_end.saturating_sub(chunk_offset),
+                                compressed,
+                            });
+                        }
+                    }
+                    "hash" => {
+                        let mut digest = [0u8; 16];
+                        file.read_exact(&mut digest)?;
+                        md5 = Some(digest);
+                    }
+                    "done" => done = true,
+                    _ => {}
+                }
+
+                if kind == "next" || kind == "done" || next <= offset || next >= file_len {
+                    break;
+                }
+                offset = next;
+            }
+            segments.push(file);
+            if done {
+                break;
+            }
+        }
+
+        let Some((chunk_size, media_size)) = geometry else {
+            return Err(malformed(first, "no volume section"));
+        };
+        if (chunks.len() as u64) < media_size.div_ceil(chunk_size) {
+            return Err(malformed(
+                first,
+                format!(
+                    "incomplete, {} of {} chunks present",
+                    chunks.len(),
+                    media_size.div_ceil(chunk_size)
+                ),
+            ));
+        }
+
+        Ok(Self {
+            segments,
+            chunks,
+            chunk_size,
+            media_size,
+            md5,
+            pos: 0,
+            cached_chunk: None,
+        })
+    }
+
+    /// Size of the stored media in bytes
+    pub fn media_size(&self) -> u64 {
+        self.media_size
+    }
+
+    /// MD5 recorded by the acquiring tool, if any
+    pub fn stored_md5(&self) -> Option<[u8; 16]> {
+        self.md5
+    }
+
+    fn load_chunk(&mut self, index: usize) -> io::Result<&[u8]> {
+        if self.cached_chunk.as_ref().map(|(i, _)| *i) != Some(index) {
+            let loc = self.chunks[index];
+            let expected =
+                (self.media_size - index as u64 * self.chunk_size).min(self.chunk_size) as usize;
+            let file = &mut self.segments[loc.segment];
+            file.seek(SeekFrom::Start(loc.offset))?;
+            let mut stored = vec![0u8; loc.stored_size as usize];
+            file.read_exact(&mut stored)?;
+
+            let mut data = if loc.compressed {
+                let mut out = Vec::with_capacity(self.chunk_size as usize);
+                ZlibDecoder::new(&stored[..]).read_to_end(&mut out)?;
+                out
+            } else {
+                stored
+            };
+            if data.len() < expected {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "EWF chunk {} is short ({} of {} bytes)",
+                        index,
+                        data.len(),
+                        expected
+                    ),
+                ));
+            }
+            data.truncate(expected);
+            self.cached_chunk = Some((index, data));
+        }
+        Ok(&self.cached_chunk.as_ref().expect("chunk cached").1)
+    }
+}
+
+impl Read for EwfReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos >= self.media_size || buf.is_empty() {
+            return Ok(0);
+        }
+        let index = (self.pos / self.chunk_size) as usize;
+        let within = (self.pos % self.chunk_size) as usize;
+        let chunk = self.load_chunk(index)?;
+        let n = (chunk.len() - within).min(buf.len());
+        buf[..n].copy_from_slice(&chunk[within..within + n]);
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for EwfReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new = match pos {
+            SeekFrom::Start(p) => p as i128,
+            SeekFrom::End(d) => self.media_size as i128 + d as i128,
+            SeekFrom::Current(d) => self.pos as i128 + d as i128,
+        };
+        if new < 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek before start",
+            ));
+        }
+        self.pos = new as u64;
+        Ok(self.pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_adler32() {
+        assert_eq!(adler32(b""), 1);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+    }
+
+    #[test]
+    fn test_segment_names() {
+        let first = Path::new("/cases/disk.E01");
+        assert_eq!(
+            segment_path(first, 2).unwrap(),
+            Path::new("/cases/disk.E02")
+        );
+        assert_eq!(
+            segment_path(first, 99).unwrap(),
+            Path::new("/cases/disk.E99")
+        );
+        assert_eq!(
+            segment_path(first, 100).unwrap(),
+            Path::new("/cases/disk.EAA")
+        );
+        assert_eq!(
+            segment_path(first, 127).unwrap(),
+            Path::new("/cases/disk.EBB")
+        );
+    }
+
+    #[test]
+    fn test_write_and_read_back_multi_segment() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("disk.E01");
+        // Enough data for several segments at the minimum segment size, plus
+        // a partial last chunk
+        let media: Vec<u8> = (0..3 * 1024 * 1024 + 1024)
+            .map(|i| (i * 7 % 251) as u8)
+            .collect();
+
+        let mut writer = EwfWriter::create(
+            &path,
+            media.len() as u64,
+            MIN_SEGMENT_SIZE,
+            EwfMetadata {
+                case_number: "2024-001".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        for block in media.chunks(10_000) {
+            writer.write_all(block).unwrap();
+        }
+        let segments = writer.finish([7u8; 16]).unwrap();
+        assert!(segments.len() > 3);
+        for segment in &segments {
+            assert!(std::fs::metadata(segment).unwrap().len() <= MIN_SEGMENT_SIZE);
+        }
+        assert!(is_ewf(&path));
+
+        let mut reader = EwfReader::open(&path).unwrap();
+        assert_eq!(reader.media_size(), media.len() as u64);
+        assert_eq!(reader.stored_md5(), Some([7u8; 16]));
+        let mut back = Vec::new();
+        reader.read_to_end(&mut back).unwrap();
+        assert!(back == media, "round-tripped media differs");
+
+        reader.seek(SeekFrom::Start(40_000)).unwrap();
+        let mut buf = [0u8; 100];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf[..], &media[40_000..40_100]);
+    }
+}
@@ -0,0 +1,457 @@
+//! Disk image conversion - raw, split raw and E01
+//!
+//! `diamond-drill convert` copies an image from one container format to
+//! another, optionally trimming it to a single partition. The source is
+//! hashed (BLAKE3 and MD5) while it is read, each output segment is hashed
+//! after it is written, and everything is recorded in a
+//! `<output>.manifest.json` next to the output. For NTFS volumes with
+//! Volume Shadow Copies, a snapshot can be copied out instead of the
+//! current contents (see [`vss`]), and its files read directly (see
+//! [`ntfs`]).
+
+pub mod ewf;
+pub mod ntfs;
+pub mod partition;
+pub mod split;
+pub mod unlock;
+pub mod vss;
+
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use md5::{Digest, Md5};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{DrillError, DrillResult};
+use crate::events::{self, Event};
+
+pub use ewf::{EwfMetadata, EwfReader, EwfWriter};
+pub use partition::{read_partitions, Partition};
+pub use split::{SplitReader, SplitWriter};
+pub use vss::{list_shadow_copies, ShadowCopy, ShadowCopyReader};
+
+const BLOCK_SIZE: usize = 1024 * 1024;
+
+/// Default segment size for split raw and E01 output
+pub const DEFAULT_SEGMENT_SIZE: u64 = 2 * 1024 * 1024 * 1024 - 1;
+
+/// Container format of a disk image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    /// Single raw (dd) file or block device
+    Raw,
+    /// Raw image split into `.001`, `.002`, … segments
+    Split,
+    /// Expert Witness Format (EnCase `.E01`)
+    E01,
+}
+
+impl ImageFormat {
+    /// Detect the format of an existing image
+    pub fn detect(path: &Path) -> Self {
+        if ewf::is_ewf(path) {
+            Self::E01
+        } else if split::is_split_raw(path) {
+            Self::Split
+        } else {
+            Self::Raw
+        }
+    }
+
+    /// Format implied by an output file name
+    pub fn from_output_path(path: &Path) -> Self {
+        match path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .as_deref()
+        {
+            Some("e01") => Self::E01,
+            Some("001") => Self::Split,
+            _ => Self::Raw,
+        }
+    }
+}
+
+impl std::fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Raw => "raw",
+            Self::Split => "split",
+            Self::E01 => "e01",
+        })
+    }
+}
+
+/// Seekable reader over any supported image
+pub trait ImageReader: Read + Seek + Send {}
+impl<T: Read + Seek + Send> ImageReader for T {}
+
+/// Open an image for reading, returning the reader, its format and the
+/// media size in bytes
+pub fn open_image(path: &Path) -> Result<(Box<dyn ImageReader>, ImageFormat, u64)> {
+    let format = ImageFormat::detect(path);
+    let mut reader: Box<dyn ImageReader> = match format {
+        ImageFormat::E01 => Box::new(EwfReader::open(path)?),
+        ImageFormat::Split => Box::new(SplitReader::open(path)?),
+        ImageFormat::Raw => Box::new(
+            File::open(path).with_context(|| format!("Failed to open {}", path.display()))?,
+        ),
+    };
+    // Seeking to the end also works for block devices, where metadata().len() is 0
+    let size = reader
+        .seek(SeekFrom::End(0))
+        .with_context(|| format!("Failed to determine size of {}", path.display()))?;
+    reader.seek(SeekFrom::Start(0))?;
+    Ok((reader, format, size))
+}
+
+/// Options for [`convert`]
+#[derive(Debug, Clone)]
+pub struct ConvertOptions {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub format: ImageFormat,
+    /// Maximum segment size for split raw and E01 output
+    pub segment_size: u64,
+    /// Only copy this partition (number as listed by [`read_partitions`])
+    pub partition: Option<usize>,
+    /// Copy this Volume Shadow Copy snapshot of the volume (or partition)
+    /// instead of its current contents, numbered as by [`list_shadow_copies`]
+    pub snapshot: Option<usize>,
+    /// Re-read the output after writing and compare hashes
+    pub verify: bool,
+    /// Acquisition metadata for E01 output
+    pub metadata: EwfMetadata,
+}
+
+/// One output file and its hash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SegmentRecord {
+    pub path: PathBuf,
+    pub size: u64,
+    pub blake3: String,
+}
+
+/// Record of a conversion, saved as `<output>.manifest.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConvertManifest {
+    pub version: u32,
+    pub created_at: String,
+    pub source: PathBuf,
+    pub source_format: ImageFormat,
+    pub output: PathBuf,
+    pub output_format: ImageFormat,
+    /// Partition copied, if the image was trimmed
+    pub partition: Option<Partition>,
+    /// Snapshot copied, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<ShadowCopy>,
+    /// Byte offset in the source where copying started
+    pub offset: u64,
+    /// Bytes copied
+    pub bytes: u64,
+    /// Hashes of the copied media (not of the container files)
+    pub blake3: String,
+    pub md5: String,
+    /// MD5 stored in the source E01, if any
+    pub source_stored_md5: Option<String>,
+    pub segments: Vec<SegmentRecord>,
+    /// `Some(true)` if the output was re-read and matched
+    pub verified: Option<bool>,
+    pub duration_ms: u64,
+}
+
+impl ConvertManifest {
+    /// Path of the manifest written for `output`
+    pub fn path_for(output: &Path) -> PathBuf {
+        let mut name = output.as_os_str().to_owned();
+        name.push(".manifest.json");
+        PathBuf::from(name)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json)
+            .with_context(|| format!("Failed to write manifest {}", path.display()))
+    }
+}
+
+enum Sink {
+    Raw(BufWriter<File>, PathBuf),
+    Split(SplitWriter),
+    E01(Box<EwfWriter>),
+}
+
+impl Sink {
+    fn writer(&mut self) -> &mut dyn Write {
+        match self {
+            Self::Raw(w, _) => w,
+            Self::Split(w) => w,
+            Self::E01(w) => w.as_mut(),
+        }
+    }
+
+    fn finish(self, md5: [u8; 16]) -> Result<Vec<PathBuf>> {
+        match self {
+            Self::Raw(mut w, path) => {
+                w.flush()?;
+                Ok(vec![path])
+            }
+            Self::Split(w) => w.finish(),
+            Self::E01(w) => w.finish(md5),
+        }
+    }
+}
+
+/// Hash `len` bytes of `reader` starting at its current position with BLAKE3
+/// and MD5, passing each block to `sink` and reporting `(done, len)` to
+/// `progress`
+fn copy_hashed(
+    reader: &mut dyn ImageReader,
+    len: u64,
+    mut sink: Option<&mut dyn Write>,
+    progress: &dyn Fn(u64, u64),
+) -> Result<(blake3::Hash, [u8; 16])> {
+    let mut blake = blake3::Hasher::new();
+    let mut md5 = Md5::new();
+    let mut buf = vec![0u8; BLOCK_SIZE];
+    let mut done = 0u64;
+    while done < len {
+        let want = (len - done).min(BLOCK_SIZE as u64) as usize;
+        let n = match reader.read(&mut buf[..want]) {
+            Ok(0) => bail!("Source ended after {} of {} bytes", done, len),
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e).with_context(|| format!("Read failed at offset {}", done)),
+        };
+        blake.update(&buf[..n]);
+        md5.update(&buf[..n]);
+        if let Some(sink) = sink.as_deref_mut() {
+            sink.write_all(&buf[..n])
+                .context("Write to output failed")?;
+        }
+        done += n as u64;
+        progress(done, len);
+    }
+    Ok((blake.finalize(), md5.finalize().into()))
+}
+
+/// Convert an image, returning the manifest (already saved next to the
+/// output). `progress` receives the bytes copied so far and the total.
+pub fn convert(opts: &ConvertOptions, progress: impl Fn(u64, u64)) -> DrillResult<ConvertManifest> {
+    let started = Instant::now();
+    let (mut reader, source_format, size) = open_image(&opts.input)?;
+    let source_stored_md5 = if source_format == ImageFormat::E01 {
+        EwfReader::open(&opts.input)?.stored_md5().map(hex::encode)
+    } else {
+        None
+    };
+
+    let partition = match opts.partition {
+        Some(number) => {
+            let partitions = read_partitions(&mut reader)?;
+            let part = partitions
+                .into_iter()
+                .find(|p| p.number == number)
+                .ok_or_else(|| {
+                    DrillError::InvalidInput(format!(
+                        "Partition {} not found in {}",
+                        number,
+                        opts.input.display()
+                    ))
+                })?;
+            if part.end() > size {
+                return Err(DrillError::FormatParse {
+                    path: opts.input.clone(),
+                    format: "partition table",
+                    message: format!(
+                        "partition {} extends past the end of the image ({} > {})",
+                        number,
+                        part.end(),
+                        size
+                    ),
+                });
+            }
+            Some(part)
+        }
+        None => None,
+    };
+    let (offset, bytes) = partition.as_ref().map_or((0, size), |p| (p.start, p.size));
+
+    let (mut reader, bytes, snapshot): (Box<dyn ImageReader>, u64, Option<ShadowCopy>) =
+        match opts.snapshot {
+            Some(number) => {
+                let copies = list_shadow_copies(&mut reader, offset)?;
+                let copy = copies
+                    .into_iter()
+                    .find(|c| c.number == number)
+                    .ok_or_else(|| {
+                        DrillError::InvalidInput(format!(
+                            "Shadow copy {} not found in {}",
+                            number,
+                            opts.input.display()
+                        ))
+                    })?;
+                let volume_size = copy.volume_size;
+                let snapshot_reader = ShadowCopyReader::open(reader, offset, number)?;
+                (Box::new(snapshot_reader), volume_size, Some(copy))
+            }
+            None => {
+                reader.seek(SeekFrom::Start(offset))?;
+                (reader, bytes, None)
+            }
+        };
+
+    if let Some(parent) = opts.output.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let mut sink = match opts.format {
+        ImageFormat::Raw => {
+            let file = File::create(&opts.output)
+                .with_context(|| format!("Failed to create {}", opts.output.display()))?;
+            Sink::Raw(
+                BufWriter::with_capacity(BLOCK_SIZE, file),
+                opts.output.clone(),
+            )
+        }
+        ImageFormat::Split => Sink::Split(SplitWriter::create(&opts.output, opts.segment_size)?),
+        ImageFormat::E01 => Sink::E01(Box::new(EwfWriter::create(
+            &opts.output,
+            bytes,
+            opts.segment_size.min(ewf::MAX_SEGMENT_SIZE),
+            opts.metadata.clone(),
+        )?)),
+    };
+
+    let (blake3_hash, md5) = copy_hashed(&mut reader, bytes, Some(sink.writer()), &progress)?;
+    events::emit(Event::Hash {
+        path: opts.input.clone(),
+        blake3: blake3_hash.to_hex().to_string(),
+        bytes,
+    });
+    let paths = sink.finish(md5)?;
+
+    let segments = paths
+        .into_iter()
+        .map(|path| {
+            let mut hasher = blake3::Hasher::new();
+            let file =
+                File::open(&path).with_context(|| format!("Failed to open {}", path.display()))?;
+            hasher
+                .update_reader(file)
+                .with_context(|| format!("Failed to hash {}", path.display()))?;
+            Ok(SegmentRecord {
+                size: std::fs::metadata(&path)?.len(),
+                blake3: hasher.finalize().to_hex().to_string(),
+                path,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let verified = if opts.verify {
+        let (mut out, _, out_size) = open_image(&opts.output)?;
+        let matches =
+            out_size == bytes && copy_hashed(&mut out, bytes, None, &|_, _| {})?.0 == blake3_hash;
+        Some(matches)
+    } else {
+        None
+    };
+
+    let manifest = ConvertManifest {
+        version: 1,
+        created_at: Utc::now().to_rfc3339(),
+        source: opts.input.clone(),
+        source_format,
+        output: opts.output.clone(),
+        output_format: opts.format,
+        partition,
+        snapshot,
+        offset,
+        bytes,
+        blake3: blake3_hash.to_hex().to_string(),
+        md5: hex::encode(md5),
+        source_stored_md5,
+        segments,
+        verified,
+        duration_ms: started.elapsed().as_millis() as u64,
+    };
+    manifest.save(&ConvertManifest::path_for(&opts.output))?;
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn options(input: &Path, output: &Path, format: ImageFormat) -> ConvertOptions {
+        ConvertOptions {
+            input: input.to_path_buf(),
+            output: output.to_path_buf(),
+            format,
+            segment_size: ewf::MIN_SEGMENT_SIZE,
+            partition: None,
+            snapshot: None,
+            verify: true,
+            metadata: EwfMetadata::default(),
+        }
+    }
+
+    #[test]
+    fn test_raw_e01_split_raw_round_trip() {
+        let dir = tempdir().unwrap();
+        let raw = dir.path().join("disk.dd");
+        let data: Vec<u8> = (0..2_500_096u32).map(|i| (i * 31 % 241) as u8).collect();
+        std::fs::write(&raw, &data).unwrap();
+        let expected = blake3::hash(&data).to_hex().to_string();
+
+        let e01 = dir.path().join("disk.E01");
+        let m = convert(&options(&raw, &e01, ImageFormat::E01), |_, _| {}).unwrap();
+        assert_eq!(m.blake3, expected);
+        assert_eq!(m.verified, Some(true));
+        assert!(m.segments.len() >= 3);
+        assert!(ConvertManifest::path_for(&e01).exists());
+
+        let split = dir.path().join("back.001");
+        let m = convert(&options(&e01, &split, ImageFormat::Split), |_, _| {}).unwrap();
+        assert_eq!(m.source_format, ImageFormat::E01);
+        assert_eq!(m.source_stored_md5.as_deref(), Some(m.md5.as_str()));
+        assert_eq!(m.segments.len(), 3);
+        assert_eq!(m.verified, Some(true));
+
+        let back = dir.path().join("back.dd");
+        let m = convert(&options(&split, &back, ImageFormat::Raw), |_, _| {}).unwrap();
+        assert_eq!(m.blake3, expected);
+        assert_eq!(std::fs::read(&back).unwrap(), data);
+    }
+
+    #[test]
+    fn test_trim_to_partition() {
+        let dir = tempdir().unwrap();
+        let raw = dir.path().join("disk.img");
+        let mut data = vec![0u8; 64 * 512];
+        data[446 + 4] = 0x0C;
+        data[446 + 8..446 + 12].copy_from_slice(&8u32.to_le_bytes());
+        data[446 + 12..446 + 16].copy_from_slice(&16u32.to_le_bytes());
+        data[510..512].copy_from_slice(&[0x55, 0xAA]);
+        data[8 * 512..24 * 512].fill(0xAB);
+        std::fs::write(&raw, &data).unwrap();
+
+        let out = dir.path().join("part1.dd");
+        let mut opts = options(&raw, &out, ImageFormat::Raw);
+        opts.partition = Some(1);
+        let m = convert(&opts, |_, _| {}).unwrap();
+        assert_eq!((m.offset, m.bytes), (8 * 512, 16 * 512));
+        assert_eq!(m.partition.unwrap().kind, "0x0C");
+        assert_eq!(std::fs::read(&out).unwrap(), vec![0xAB; 16 * 512]);
+
+        opts.partition = Some(2);
+        assert!(convert(&opts, |_, _| {}).is_err());
+    }
+}
@@ -0,0 +1,888 @@
+//! Just enough NTFS to list and read the files of a volume
+//!
+//! Used to look inside Volume Shadow Copy snapshots (see [`super::vss`]),
+//! which are whole volumes rather than mounted file systems. The MFT is
+//! walked record by record: each in-use file record's long `$FILE_NAME`
+//! gives its name and parent directory, and its unnamed `$DATA` attribute
+//! (resident, or a run list, possibly spread over extension records via
+//! `$ATTRIBUTE_LIST`) its contents. Compressed and encrypted files are
+//! listed but cannot be read. Metadata files (`$MFT`, `$Extend`, ...) and
+//! `System Volume Information` are left out.
+
+use std::collections::HashMap;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+
+use crate::core::filetime_to_utc;
+
+const OEM_ID: &[u8; 8] = b"NTFS    ";
+
+const ATTR_STANDARD_INFORMATION: u32 = 0x10;
+const ATTR_ATTRIBUTE_LIST: u32 = 0x20;
+const ATTR_FILE_NAME: u32 = 0x30;
+const ATTR_DATA: u32 = 0x80;
+const ATTR_END: u32 = 0xFFFF_FFFF;
+
+/// Record header flags
+const RECORD_IN_USE: u16 = 0x1;
+const RECORD_DIRECTORY: u16 = 0x2;
+
+/// Attribute flags that make the contents unreadable here
+const ATTR_COMPRESSED: u16 = 0x0001;
+const ATTR_ENCRYPTED: u16 = 0x4000;
+
+/// `$FILE_NAME` namespace holding only the 8.3 name
+const NAMESPACE_DOS: u8 = 2;
+
+/// MFT record of the root directory
+const ROOT_RECORD: u64 = 5;
+
+/// Records below this are reserved for metadata files
+const FIRST_USER_RECORD: u64 = 24;
+
+/// Deepest directory nesting followed when building paths
+const MAX_DEPTH: usize = 256;
+
+/// Directories at the root never listed
+const HIDDEN_ROOT_DIRS: &[&str] = &["System Volume Information", "$Extend", "$RECYCLE.BIN"];
+
+/// Whether `boot` (the first bytes of a volume) is an NTFS boot sector
+pub fn is_ntfs(boot: &[u8]) -> bool {
+    boot.len() >= 11 && &boot[3..11] == OEM_ID
+}
+
+/// A file found in the MFT
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NtfsFile {
+    /// MFT record number, to read the file with [`Volume::open_file`]
+    pub record: u64,
+    /// Path from the root of the volume
+    pub path: PathBuf,
+    pub size: u64,
+    pub modified: Option<DateTime<Utc>>,
+    pub created: Option<DateTime<Utc>>,
+}
+
+/// Clusters `vcn..vcn + clusters` of an attribute, at `lcn` on the volume
+/// (None for a sparse run, which reads as zeros)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Run {
+    vcn: u64,
+    lcn: Option<u64>,
+    clusters: u64,
+}
+
+/// An unnamed `$DATA` attribute
+#[derive(Debug, Clone)]
+enum Data {
+    Resident(Vec<u8>),
+    NonResident {
+        runs: Vec<Run>,
+        size: u64,
+        /// Compressed or encrypted
+        unreadable: bool,
+    },
+}
+
+impl Data {
+    fn size(&self) -> u64 {
+        match self {
+            Data::Resident(bytes) => bytes.len() as u64,
+            Data::NonResident { size, .. } => *size,
+        }
+    }
+}
+
+/// What the listing needs from one file record
+struct Record {
+    directory: bool,
+    parent: u64,
+    name: String,
+    size: u64,
+    modified: Option<DateTime<Utc>>,
+    created: Option<DateTime<Utc>>,
+}
+
+/// An NTFS volume read through `R`, which starts at the boot sector
+pub struct Volume<R> {
+    reader: R,
+    cluster_size: u64,
+    record_size: usize,
+    mft: Vec<Run>,
+    mft_size: u64,
+}
+
+impl<R: Read + Seek> Volume<R> {
+    pub fn open(mut reader: R) -> Result<Self> {
+        let mut boot = [0u8; 512];
+        reader.seek(SeekFrom::Start(0))?;
+        reader
+            .read_exact(&mut boot)
+            .context("Failed to read NTFS boot sector")?;
+        if !is_ntfs(&boot) {
+            bail!("Not an NTFS volume");
+        }
+        let sector_size = u16::from_le_bytes([boot[11], boot[12]]) as u64;
+        let sectors_per_cluster = match boot[13] {
+            // Clusters over 64 KiB are stored as a negative power of two
+            n if n > 0x80 => 1u64.checked_shl(256 - n as u32).unwrap_or(0),
+            n => n as u64,
+        };
+        let cluster_size = sector_size * sectors_per_cluster;
+        let record_size = match boot[64] as i8 {
+            n if n < 0 => 1usize.checked_shl(n.unsigned_abs() as u32).unwrap_or(0),
+            n => n as usize * cluster_size as usize,
+        };
+        if !sector_size.is_power_of_two()
+            || !(512..=4096).contains(&sector_size)
+            || cluster_size == 0
+            || cluster_size > 2 * 1024 * 1024
+            || !(512..=65536).contains(&record_size)
+        {
+            bail!("Invalid NTFS boot sector");
+        }
+        let mft_lcn = u64_at(&boot, 48);
+
+        let mut volume = Self {
+            reader,
+            cluster_size,
+            record_size,
+            mft: Vec::new(),
+            mft_size: 0,
+        };
+        // Record 0 describes the MFT itself
+        let mut record = vec![0u8; record_size];
+        let mft_offset = mft_lcn
+            .checked_mul(cluster_size)
+            .context("Invalid NTFS boot sector")?;
+        volume.reader.seek(SeekFrom::Start(mft_offset))?;
+        volume
+            .reader
+            .read_exact(&mut record)
+            .context("Failed to read the MFT")?;
+        apply_fixups(&mut record).context("Damaged $MFT record")?;
+        match find_data(&record)? {
+            Some(Data::NonResident { runs, size, .. }) => {
+                volume.mft = runs;
+                volume.mft_size = size;
+            }
+            _ => bail!("$MFT has no data runs"),
+        }
+        Ok(volume)
+    }
+
+    /// Every file on the volume, with paths from the root
+    pub fn files(&mut self) -> Result<Vec<NtfsFile>> {
+        let records = self.mft_size / self.record_size as u64;
+        let mut found: HashMap<u64, Record> = HashMap::new();
+        let mut mft = BufReader::with_capacity(
+            1024 * 1024,
+            Runs {
+                reader: &mut self.reader,
+                runs: self.mft.clone(),
+                cluster_size: self.cluster_size,
+                size: self.mft_size,
+                pos: 0,
+            },
+        );
+        let mut buf = vec![0u8; self.record_size];
+        let mut extended = Vec::new();
+        for number in 0..records {
+            mft.read_exact(&mut buf)
+                .with_context(|| format!("Failed to read MFT record {}", number))?;
+            if apply_fixups(&mut buf).is_err() {
+                continue;
+            }
+            match parse_record(&buf) {
+                Ok(Some((record, complete))) => {
+                    if !complete {
+                        extended.push(number);
+                    }
+                    found.insert(number, record);
+                }
+                Ok(None) => {}
+                Err(e) => tracing::debug!("Skipping MFT record {}: {:#}", number, e),
+            }
+        }
+        drop(mft);
+
+        // Files whose $DATA lives in extension records
+        for number in extended {
+            match self.data(number) {
+                Ok(Some(data)) => {
+                    if let Some(record) = found.get_mut(&number) {
+                        record.size = data.size();
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => tracing::debug!("No $DATA for MFT record {}: {:#}", number, e),
+            }
+        }
+
+        let mut files: Vec<NtfsFile> = found
+            .iter()
+            .filter(|(&number, record)| number >= FIRST_USER_RECORD && !record.directory)
+            .filter_map(|(&number, record)| {
+                Some(NtfsFile {
+                    record: number,
+                    path: path_of(&found, number)?,
+                    size: record.size,
+                    modified: record.modified,
+                    created: record.created,
+                })
+            })
+            .collect();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(files)
+    }
+
+    /// Reader of the contents of the file at MFT record `record`
+    pub fn open_file(mut self, record: u64) -> Result<FileReader<R>> {
+        let data = self
+            .data(record)?
+            .with_context(|| format!("MFT record {} has no data", record))?;
+        let runs = match data {
+            Data::Resident(bytes) => return Ok(FileReader::Resident(io::Cursor::new(bytes))),
+            Data::NonResident {
+                unreadable: true, ..
+            } => bail!("Compressed and encrypted NTFS files cannot be read"),
+            Data::NonResident { runs, size, .. } => Runs {
+                reader: self.reader,
+                runs,
+                cluster_size: self.cluster_size,
+                size,
+                pos: 0,
+            },
+        };
+        Ok(FileReader::NonResident(runs))
+    }
+
+    /// Record `number`, fixups applied
+    fn record(&mut self, number: u64) -> Result<Vec<u8>> {
+        let mut buf = vec![0u8; self.record_size];
+        let mut mft = Runs {
+            reader: &mut self.reader,
+            runs: self.mft.clone(),
+            cluster_size: self.cluster_size,
+            size: self.mft_size,
+            pos: 0,
+        };
+        let offset = number
+            .checked_mul(self.record_size as u64)
+            .filter(|&offset| offset < self.mft_size)
+            .with_context(|| format!("MFT record {} out of range", number))?;
+        mft.seek(SeekFrom::Start(offset))?;
+        mft.read_exact(&mut buf)
+            .with_context(|| format!("Failed to read MFT record {}", number))?;
+        apply_fixups(&mut buf).with_context(|| format!("Damaged MFT record {}", number))?;
+        Ok(buf)
+    }
+
+    /// The unnamed `$DATA` of record `number`, following `$ATTRIBUTE_LIST`
+    /// into extension records
+    fn data(&mut self, number: u64) -> Result<Option<Data>> {
+        let record = self.record(number)?;
+        if let Some(data) = find_data(&record)? {
+            return Ok(Some(data));
+        }
+        let Some(list) = attribute_list(&record) else {
+            return Ok(None);
+        };
+        let list = match list {
+            Data::Resident(bytes) => bytes,
+            Data::NonResident { runs, size, .. } => {
+                let mut bytes = Vec::new();
+                Runs {
+                    reader: &mut self.reader,
+                    runs,
+                    cluster_size: self.cluster_size,
+                    size: size.min(1024 * 1024),
+                    pos: 0,
+                }
+                .read_to_end(&mut bytes)?;
+                bytes
+            }
+        };
+
+        // (starting VCN, record) of each extent, in list order
+        let mut extents = Vec::new();
+        let mut at = 0;
+        while at + 26 <= list.len() {
+            let kind = u32_at(&list, at);
+            let length = u16_at(&list, at + 4) as usize;
+            if length == 0 {
+                break;
+            }
+            if kind == ATTR_DATA && list[at + 6] == 0 {
+                extents.push((
+                    u64_at(&list, at + 8),
+                    u64_at(&list, at + 16) & 0xFFFF_FFFF_FFFF,
+                ));
+            }
+            at += length;
+        }
+        extents.sort_unstable();
+        extents.dedup();
+
+        let mut all_runs = Vec::new();
+        let mut size = None;
+        let mut unreadable = false;
+        for (_, extent) in extents {
+            let extent_record = if extent == number {
+                record.clone()
+            } else {
+                self.record(extent)?
+            };
+            match find_data(&extent_record)? {
+                Some(Data::Resident(bytes)) => return Ok(Some(Data::Resident(bytes))),
+                Some(Data::NonResident {
+                    runs,
+                    size: extent_size,
+                    unreadable: u,
+                }) => {
+                    // Only the first extent carries the sizes
+                    if runs.first().is_some_and(|r| r.vcn == 0) {
+                        size = Some(extent_size);
+                        unreadable = u;
+                    }
+                    all_runs.extend(runs);
+                }
+                None => {}
+            }
+        }
+        Ok(size.map(|size| Data::NonResident {
+            runs: all_runs,
+            size,
+            unreadable,
+        }))
+    }
+}
+
+/// Contents of one file
+pub enum FileReader<R> {
+    Resident(io::Cursor<Vec<u8>>),
+    NonResident(Runs<R>),
+}
+
+impl<R: Read + Seek> Read for FileReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            FileReader::Resident(cursor) => cursor.read(buf),
+            FileReader::NonResident(runs) => runs.read(buf),
+        }
+    }
+}
+
+/// A non-resident attribute read as a stream
+pub struct Runs<R> {
+    reader: R,
+    runs: Vec<Run>,
+    cluster_size: u64,
+    size: u64,
+    pos: u64,
+}
+
+impl<R: Read + Seek> Read for Runs<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos >= self.size || buf.is_empty() {
+            return Ok(0);
+        }
+        let vcn = self.pos / self.cluster_size;
+        let Some(run) = self
+            .runs
+            .iter()
+            .find(|r| vcn >= r.vcn && vcn - r.vcn < r.clusters)
+            .copied()
+        else {
+            // Allocated but never written: reads as zeros
+            let n = buf.len().min((self.size - self.pos) as usize);
+            buf[..n].fill(0);
+            self.pos += n as u64;
+            return Ok(n);
+        };
+        let into_run = self.pos - run.vcn * self.cluster_size;
+        let left = (run.clusters * self.cluster_size - into_run).min(self.size - self.pos);
+        let n = buf.len().min(left as usize);
+        match run.lcn {
+            Some(lcn) => {
+                let offset = lcn
+                    .checked_mul(self.cluster_size)
+                    .and_then(|o| o.checked_add(into_run))
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Bad NTFS run"))?;
+                self.reader.seek(SeekFrom::Start(offset))?;
+                self.reader.read_exact(&mut buf[..n])?;
+            }
+            None => buf[..n].fill(0),
+        }
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl<R: Read + Seek> Seek for Runs<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(p) => p as i64,
+            SeekFrom::End(d) => self.size as i64 + d,
+            SeekFrom::Current(d) => self.pos as i64 + d,
+        };
+        if target < 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek before start",
+            ));
+        }
+        self.pos = target as u64;
+        Ok(self.pos)
+    }
+}
+
+/// Undo the update sequence: the last two bytes of every 512-byte stride
+/// were swapped for a check value when the record was written
+fn apply_fixups(record: &mut [u8]) -> Result<()> {
+    if &record[..4] != b"FILE" {
+        bail!("no FILE signature");
+    }
+    let offset = u16_at(record, 4) as usize;
+    let count = u16_at(record, 6) as usize;
+    if count < 2 || offset + count * 2 > record.len() || (count - 1) * 512 > record.len() {
+        bail!("bad update sequence");
+    }
+    let check = [record[offset], record[offset + 1]];
+    for i in 1..count {
+        let end = i * 512;
+        if record[end - 2..end] != check {
+            bail!("torn write in stride {}", i);
+        }
+        let (a, b) = (record[offset + i * 2], record[offset + i * 2 + 1]);
+        record[end - 2] = a;
+        record[end - 1] = b;
+    }
+    Ok(())
+}
+
+/// Attributes of a record as (type, header bytes)
+fn attributes(record: &[u8]) -> impl Iterator<Item = (u32, &[u8])> {
+    let mut at = u16_at(record, 20) as usize;
+    std::iter::from_fn(move || {
+        if at + 16 > record.len() {
+            return None;
+        }
+        let kind = u32_at(record, at);
+        let length = u32_at(record, at + 4) as usize;
+        if kind == ATTR_END || length < 16 || at + length > record.len() {
+            return None;
+        }
+        let attribute = &record[at..at + length];
+        at += length;
+        Some((kind, attribute))
+    })
+}
+
+/// Value of a resident attribute
+fn resident_value(attribute: &[u8]) -> Option<&[u8]> {
+    if attribute[8] != 0 || attribute.len() < 24 {
+        return None;
+    }
+    let length = u32_at(attribute, 16) as usize;
+    let offset = u16_at(attribute, 20) as usize;
+    attribute.get(offset..offset.checked_add(length)?)
+}
+
+/// An attribute's contents: its value, or its run list and sizes
+fn attribute_data(attribute: &[u8]) -> Result<Data> {
+    if attribute[8] == 0 {
+        let value = resident_value(attribute).context("Bad resident attribute")?;
+        return Ok(Data::Resident(value.to_vec()));
+    }
+    if attribute.len() < 64 {
+        bail!("Bad non-resident attribute");
+    }
+    let flags = u16_at(attribute, 12);
+    let start_vcn = u64_at(attribute, 16);
+    let runs_offset = u16_at(attribute, 32) as usize;
+    let runs = decode_runs(attribute.get(runs_offset..).unwrap_or_default(), start_vcn)?;
+    Ok(Data::NonResident {
+        runs,
+        size: u64_at(attribute, 48),
+        unreadable: flags & (ATTR_COMPRESSED | ATTR_ENCRYPTED) != 0,
+    })
+}
+
+/// The unnamed `$DATA` attribute in this record, if it is here
+fn find_data(record: &[u8]) -> Result<Option<Data>> {
+    attributes(record)
+        .find(|(kind, attribute)| *kind == ATTR_DATA && attribute[9] == 0)
+        .map(|(_, attribute)| attribute_data(attribute))
+        .transpose()
+}
+
+fn attribute_list(record: &[u8]) -> Option<Data> {
+    attributes(record)
+        .find(|(kind, _)| *kind == ATTR_ATTRIBUTE_LIST)
+        .and_then(|(_, attribute)| attribute_data(attribute).ok())
+}
+
+/// Decode a run list: each run is a header byte giving the sizes of a
+/// length and a signed LCN delta (no delta = sparse), ending at a 0
+fn decode_runs(bytes: &[u8], start_vcn: u64) -> Result<Vec<Run>> {
+    let mut runs = Vec::new();
+    let mut vcn = start_vcn;
+    let mut lcn: i64 = 0;
+    let mut at = 0;
+    while let Some(&header) = bytes.get(at) {
+        if header == 0 {
+            break;
+        }
+        let (length_size, offset_size) = ((header & 0x0F) as usize, (header >> 4) as usize);
+        if length_size == 0 || length_size > 8 || offset_size > 8 {
+            bail!("Bad NTFS run list");
+        }
+        let field = |from: usize, size: usize| -> Result<&[u8]> {
+            bytes
+                .get(from..from + size)
+                .context("Truncated NTFS run list")
+        };
+        let mut length = [0u8; 8];
+        length[..length_size].copy_from_slice(field(at + 1, length_size)?);
+        let clusters = u64::from_le_bytes(length);
+        let lcn_here = if offset_size == 0 {
+            None
+        } else {
+            let raw = field(at + 1 + length_size, offset_size)?;
+            // Sign-extend the delta
+            let fill = if raw[offset_size - 1] & 0x80 != 0 {
+                0xFF
+            } else {
+                0
+            };
+            let mut delta = [fill; 8];
+            delta[..offset_size].copy_from_slice(raw);
+            lcn = lcn
+                .checked_add(i64::from_le_bytes(delta))
+                .filter(|&l| l >= 0)
+                .context("Bad NTFS run list")?;
+            Some(lcn as u64)
+        };
+        runs.push(Run {
+            vcn,
+            lcn: lcn_here,
+            clusters,
+        });
+        vcn = vcn.checked_add(clusters).context("Bad NTFS run list")?;
+        at += 1 + length_size + offset_size;
+    }
+    Ok(runs)
+}
+
+/// Name, parent and times of an in-use base record, and whether its
+/// `$DATA` is all here (false when it is listed in `$ATTRIBUTE_LIST`)
+fn parse_record(record: &[u8]) -> Result<Option<(Record, bool)>> {
+    let flags = u16_at(record, 22);
+    let base = u64_at(record, 32) & 0xFFFF_FFFF_FFFF;
+    if flags & RECORD_IN_USE == 0 || base != 0 {
+        return Ok(None);
+    }
+
+    let mut name: Option<(u8, u64, String)> = None;
+    let mut times = (None, None);
+    let mut data = None;
+    let mut has_list = false;
+    for (kind, attribute) in attributes(record) {
+        match kind {
+            ATTR_STANDARD_INFORMATION => {
+                if let Some(value) = resident_value(attribute).filter(|v| v.len() >= 16) {
+                    times = (
+                        filetime_to_utc(u64_at(value, 8)),
+                        filetime_to_utc(u64_at(value, 0)),
+                    );
+                }
+            }
+            ATTR_FILE_NAME => {
+                let Some(value) = resident_value(attribute).filter(|v| v.len() >= 66) else {
+                    continue;
+                };
+                let namespace = value[65];
+                if namespace == NAMESPACE_DOS && name.is_some() {
+                    continue;
+                }
+                let length = value[64] as usize;
+                let Some(raw) = value.get(66..66 + length * 2) else {
+                    continue;
+                };
+                let units: Vec<u16> = raw
+                    .chunks_exact(2)
+                    .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                    .collect();
+                let parent = u64_at(value, 0) & 0xFFFF_FFFF_FFFF;
+                let replace = name.as_ref().is_none_or(|(ns, _, _)| *ns == NAMESPACE_DOS);
+                if replace {
+                    name = Some((namespace, parent, String::from_utf16_lossy(&units)));
+                }
+            }
+            ATTR_DATA if attribute[9] == 0 => data = Some(attribute_data(attribute)?),
+            ATTR_ATTRIBUTE_LIST => has_list = true,
+            _ => {}
+        }
+    }
+    let Some((_, parent, name)) = name else {
+        return Ok(None);
+    };
+    let complete = data.is_some() || !has_list;
+    Ok(Some((
+        Record {
+            directory: flags & RECORD_DIRECTORY != 0,
+            parent,
+            name,
+            size: data.as_ref().map_or(0, Data::size),
+            modified: times.0,
+            created: times.1,
+        },
+        complete,
+    )))
+}
+
+/// Path of record `number` from the root, or None when it is hidden or
+/// not reachable from the root
+fn path_of(records: &HashMap<u64, Record>, number: u64) -> Option<PathBuf> {
+    let mut names: Vec<&str> = Vec::new();
+    let mut current = number;
+    for _ in 0..MAX_DEPTH {
+        if current == ROOT_RECORD {
+            let top = *names.last()?;
+            if HIDDEN_ROOT_DIRS.contains(&top) || top.starts_with('$') {
+                return None;
+            }
+            return Some(names.iter().rev().collect());
+        }
+        let record = records.get(&current)?;
+        // Names must not climb out of the snapshot's subtree
+        if record.name.is_empty()
+            || record.name == "."
+            || record.name == ".."
+            || record.name.contains(['/', '\\'])
+        {
+            return None;
+        }
+        names.push(record.name.as_str());
+        current = record.parent;
+    }
+    None
+}
+
+fn u16_at(b: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes(b[at..at + 2].try_into().expect("2 bytes"))
+}
+
+fn u32_at(b: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(b[at..at + 4].try_into().expect("4 bytes"))
+}
+
+fn u64_at(b: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(b[at..at + 8].try_into().expect("8 bytes"))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const CLUSTER: usize = 4096;
+    const RECORD: usize = 1024;
+    const MFT_LCN: usize = 4;
+    const MFT_RECORDS: usize = 32;
+
+    fn attribute(kind: u32, body: &[u8], non_resident: bool) -> Vec<u8> {
+        let header = if non_resident { 64 } else { 24 };
+        let mut a = vec![0u8; (header + body.len() + 7) & !7];
+        a[..4].copy_from_slice(&kind.to_le_bytes());
+        let length = a.len() as u32;
+        a[4..8].copy_from_slice(&length.to_le_bytes());
+        a[8] = non_resident as u8;
+        a[header..header + body.len()].copy_from_slice(body);
+        if !non_resident {
+            a[16..20].copy_from_slice(&(body.len() as u32).to_le_bytes());
+            a[20..22].copy_from_slice(&24u16.to_le_bytes());
+        }
+        a
+    }
+
+    fn resident(kind: u32, value: &[u8]) -> Vec<u8> {
+        attribute(kind, value, false)
+    }
+
+    /// Non-resident `$DATA` of `size` bytes in one run at `lcn`
+    fn non_resident(lcn: u8, clusters: u8, size: u64) -> Vec<u8> {
+        let mut a = attribute(ATTR_DATA, &[0x11, clusters, lcn, 0], true);
+        a[24..32].copy_from_slice(&(clusters as u64 - 1).to_le_bytes());
+        a[32..34].copy_from_slice(&64u16.to_le_bytes());
+        a[48..56].copy_from_slice(&size.to_le_bytes());
+        a
+    }
+
+    fn file_name(parent: u64, name: &str, namespace: u8) -> Vec<u8> {
+        let units: Vec<u16> = name.encode_utf16().collect();
+        let mut v = vec![0u8; 66 + units.len() * 2];
+        v[..8].copy_from_slice(&parent.to_le_bytes());
+        v[64] = units.len() as u8;
+        v[65] = namespace;
+        for (i, u) in units.iter().enumerate() {
+            v[66 + i * 2..68 + i * 2].copy_from_slice(&u.to_le_bytes());
+        }
+        resident(ATTR_FILE_NAME, &v)
+    }
+
+    fn standard_information(modified: u64) -> Vec<u8> {
+        let mut v = vec![0u8; 48];
+        v[..8].copy_from_slice(&modified.to_le_bytes());
+        v[8..16].copy_from_slice(&modified.to_le_bytes());
+        resident(ATTR_STANDARD_INFORMATION, &v)
+    }
+
+    /// A FILE record holding `attributes`, with the update sequence applied
+    fn record(flags: u16, attributes: &[Vec<u8>]) -> Vec<u8> {
+        let mut r = vec![0u8; RECORD];
+        r[..4].copy_from_slice(b"FILE");
+        r[4..6].copy_from_slice(&48u16.to_le_bytes());
+        r[6..8].copy_from_slice(&3u16.to_le_bytes());
+        r[20..22].copy_from_slice(&56u16.to_le_bytes());
+        r[22..24].copy_from_slice(&flags.to_le_bytes());
+        let mut at = 56;
+        for a in attributes {
+            r[at..at + a.len()].copy_from_slice(a);
+            at += a.len();
+        }
+        r[at..at + 4].copy_from_slice(&ATTR_END.to_le_bytes());
+        // Move the last two bytes of each stride into the update sequence
+        r[48..50].copy_from_slice(&[0xAB, 0xCD]);
+        for i in 1..3 {
+            let end = i * 512;
+            let saved = [r[end - 2], r[end - 1]];
+            r[48 + i * 2..50 + i * 2].copy_from_slice(&saved);
+            r[end - 2..end].copy_from_slice(&[0xAB, 0xCD]);
+        }
+        r
+    }
+
+    /// A small NTFS volume: `Docs/notes.txt` (resident), `photo.jpg` (one
+    /// cluster of 0x42 at LCN 20), plus metadata files that are not listed
+    pub(crate) fn volume() -> Vec<u8> {
+        let mut v = vec![0u8; 32 * CLUSTER];
+        v[3..11].copy_from_slice(OEM_ID);
+        v[11..13].copy_from_slice(&512u16.to_le_bytes());
+        v[13] = (CLUSTER / 512) as u8;
+        v[48..56].copy_from_slice(&(MFT_LCN as u64).to_le_bytes());
+        v[64] = (-10i8) as u8;
+
+        let mft_clusters = (MFT_RECORDS * RECORD / CLUSTER) as u8;
+        let mut records = vec![vec![0u8; RECORD]; MFT_RECORDS];
+        records[0] = record(
+            RECORD_IN_USE,
+            &[
+                file_name(ROOT_RECORD, "$MFT", 3),
+                non_resident(MFT_LCN as u8, mft_clusters, (MFT_RECORDS * RECORD) as u64),
+            ],
+        );
+        records[5] = record(
+            RECORD_IN_USE | RECORD_DIRECTORY,
+            &[file_name(ROOT_RECORD, ".", 3)],
+        );
+        records[11] = record(
+            RECORD_IN_USE | RECORD_DIRECTORY,
+            &[file_name(ROOT_RECORD, "$Extend", 3)],
+        );
+        records[24] = record(
+            RECORD_IN_USE | RECORD_DIRECTORY,
+            &[file_name(ROOT_RECORD, "Docs", 3)],
+        );
+        records[25] = record(
+            RECORD_IN_USE,
+            &[
+                standard_information(133_000_000_000_000_000),
+                file_name(24, "NOTES~1.TXT", NAMESPACE_DOS),
+                file_name(24, "notes.txt", 1),
+                resident(ATTR_DATA, b"remember the milk"),
+            ],
+        );
+        records[26] = record(
+            RECORD_IN_USE,
+            &[
+                file_name(ROOT_RECORD, "photo.jpg", 3),
+                non_resident(20, 1, 3000),
+            ],
+        );
+        // Deleted: not in use
+        records[27] = record(0, &[file_name(ROOT_RECORD, "gone.txt", 3)]);
+        records[28] = record(RECORD_IN_USE, &[file_name(11, "$Quota", 3)]);
+        for (i, r) in records.iter().enumerate() {
+            let at = MFT_LCN * CLUSTER + i * RECORD;
+            v[at..at + RECORD].copy_from_slice(r);
+        }
+        v[20 * CLUSTER..21 * CLUSTER].fill(0x42);
+        v
+    }
+
+    #[test]
+    fn test_list_and_read_files() {
+        let image = volume();
+        assert!(is_ntfs(&image));
+        let mut vol = Volume::open(Cursor::new(image.clone())).unwrap();
+        let files = vol.files().unwrap();
+        let paths: Vec<_> = files.iter().map(|f| f.path.clone()).collect();
+        assert_eq!(
+            paths,
+            [PathBuf::from("Docs/notes.txt"), PathBuf::from("photo.jpg")]
+        );
+        assert_eq!(files[0].size, 17);
+        assert!(files[0].modified.is_some());
+        assert_eq!(files[1].size, 3000);
+
+        let mut notes = String::new();
+        Volume::open(Cursor::new(image.clone()))
+            .unwrap()
+            .open_file(files[0].record)
+            .unwrap()
+            .read_to_string(&mut notes)
+            .unwrap();
+        assert_eq!(notes, "remember the milk");
+
+        let mut photo = Vec::new();
+        Volume::open(Cursor::new(image))
+            .unwrap()
+            .open_file(files[1].record)
+            .unwrap()
+            .read_to_end(&mut photo)
+            .unwrap();
+        assert_eq!(photo, vec![0x42; 3000]);
+    }
+
+    #[test]
+    fn test_decode_runs() {
+        // 4 clusters at 100, 2 sparse, 3 at 100 - 16
+        let runs = decode_runs(&[0x21, 4, 100, 0, 0x01, 2, 0x11, 3, 0xF0, 0], 0).unwrap();
+        assert_eq!(
+            runs,
+            [
+                Run {
+                    vcn: 0,
+                    lcn: Some(100),
+                    clusters: 4
+                },
+                Run {
+                    vcn: 4,
+                    lcn: None,
+                    clusters: 2
+                },
+                Run {
+                    vcn: 6,
+                    lcn: Some(84),
+                    clusters: 3
+                },
+            ]
+        );
+        assert!(decode_runs(&[0x11, 1, 0x80, 0], 0).is_err());
+    }
+}
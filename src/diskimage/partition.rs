@@ -0,0 +1,308 @@
+//! MBR and GPT partition table parsing
+
+use std::io::{Read, Seek, SeekFrom};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+const SECTOR: u64 = 512;
+const MBR_SIGNATURE: [u8; 2] = [0x55, 0xAA];
+const GPT_SIGNATURE: &[u8; 8] = b"EFI PART";
+const GPT_PROTECTIVE: u8 = 0xEE;
+
+/// Extended partition types, whose contents are a chain of EBRs
+const EXTENDED_TYPES: &[u8] = &[0x05, 0x0F, 0x85];
+
+/// Logical partitions in a broken EBR chain are not followed past this
+const MAX_LOGICAL_PARTITIONS: usize = 128;
+
+/// Largest GPT entry array read (the spec's 128 entries of 128 bytes is 16 KiB)
+const MAX_GPT_TABLE_BYTES: usize = 1024 * 1024;
+
+/// A partition found in the image's partition table
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Partition {
+    /// 1-based partition number (MBR logical partitions start at 5)
+    pub number: usize,
+    /// Byte offset of the partition in the image
+    pub start: u64,
+    /// Size in bytes
+    pub size: u64,
+    /// MBR type byte (`0x07`) or GPT type GUID
+    pub kind: String,
+    /// GPT partition name, empty for MBR
+    pub name: String,
+}
+
+impl Partition {
+    pub fn end(&self) -> u64 {
+        self.start.saturating_add(self.size)
+    }
+}
+
+/// Read the partition table of an image, trying GPT first and falling back
+/// to MBR. Returns an empty list for images without a partition table.
+pub fn read_partitions<R: Read + Seek>(reader: &mut R) -> Result<Vec<Partition>> {
+    let mut mbr = [0u8; SECTOR as usize];
+    reader.seek(SeekFrom::Start(0))?;
+    if reader.read_exact(&mut mbr).is_err() || mbr[510..512] != MBR_SIGNATURE {
+        return Ok(Vec::new());
+    }
+
+    let entries: Vec<[u8; 16]> = (0..4)
+        .map(|i| {
+            mbr[446 + i * 16..462 + i * 16]
+                .try_into()
+                .expect("16-byte entry")
+        })
+        .collect();
+    if entries.iter().any(|e| e[4] == GPT_PROTECTIVE) {
+        // GPT header is at LBA 1; 4Kn disks put it at 4096
+        for sector_size in [SECTOR, 4096] {
+            if let Some(parts) = read_gpt(reader, sector_size)? {
+                return Ok(parts);
+            }
+        }
+        bail!("Protective MBR found but no valid GPT header");
+    }
+
+    let mut partitions = Vec::new();
+    for (i, entry) in entries.iter().enumerate() {
+        let (kind, start, sectors) = mbr_entry(entry);
+        if kind == 0 || sectors == 0 {
+            continue;
+        }
+        if EXTENDED_TYPES.contains(&kind) {
+            read_logical(reader, start, &mut partitions)?;
+            continue;
+        }
+        partitions.push(Partition {
+            number: i + 1,
+            start: to_bytes(start, SECTOR)?,
+            size: to_bytes(sectors, SECTOR)?,
+            kind: format!("0x{:02X}", kind),
+            name: String::new(),
+        });
+    }
+    partitions.sort_by_key(|p| p.number);
+    Ok(partitions)
+}
+
+fn mbr_entry(entry: &[u8; 16]) -> (u8, u64, u64) {
+    let start = u32::from_le_bytes(entry[8..12].try_into().expect("4 bytes")) as u64;
+    let sectors = u32::from_le_bytes(entry[12..16].try_into().expect("4 bytes")) as u64;
+    (entry[4], start, sectors)
+}
+
+/// Byte offset of sector `lba`, refusing values no real disk has
+fn to_bytes(lba: u64, sector_size: u64) -> Result<u64> {
+    match lba.checked_mul(sector_size) {
+        Some(bytes) => Ok(bytes),
+        None => bail!("Invalid partition table: sector {} out of range", lba),
+    }
+}
+
+/// Walk the EBR chain of an extended partition starting at LBA `base`
+fn read_logical<R: Read + Seek>(
+    reader: &mut R,
+    base: u64,
+    partitions: &mut Vec<Partition>,
+) -> Result<()> {
+    let mut ebr_lba = base;
+    for number in 5..5 + MAX_LOGICAL_PARTITIONS {
+        let mut ebr = [0u8; SECTOR as usize];
+        reader.seek(SeekFrom::Start(to_bytes(ebr_lba, SECTOR)?))?;
+        reader
+            .read_exact(&mut ebr)
+            .with_context(|| format!("Failed to read EBR at sector {}", ebr_lba))?;
+        if ebr[510..512] != MBR_SIGNATURE {
+            break;
+        }
+        let first: [u8; 16] = ebr[446..462].try_into().expect("16-byte entry");
+        let (kind, start, sectors) = mbr_entry(&first);
+        if kind != 0 && sectors != 0 {
+            let Some(lba) = ebr_lba.checked_add(start) else {
+                bail!(
+                    "Invalid partition table: logical partition {} out of range",
+                    number
+                );
+            };
+            partitions.push(Partition {
+                number,
+                start: to_bytes(lba, SECTOR)?,
+                size: to_bytes(sectors, SECTOR)?,
+                kind: format!("0x{:02X}", kind),
+                name: String::new(),
+            });
+        }
+        // The second entry links to the next EBR, relative to the extended
+        // partition's start
+        let link: [u8; 16] = ebr[462..478].try_into().expect("16-byte entry");
+        let (_, next, _) = mbr_entry(&link);
+        if next == 0 {
+            break;
+        }
+        ebr_lba = base
+            .checked_add(next)
+            .context("Invalid partition table: EBR chain out of range")?;
+    }
+    Ok(())
+}
+
+fn read_gpt<R: Read + Seek>(reader: &mut R, sector_size: u64) -> Result<Option<Vec<Partition>>> {
+    let mut header = [0u8; 92];
+    reader.seek(SeekFrom::Start(sector_size))?;
+    if reader.read_exact(&mut header).is_err() || &header[..8] != GPT_SIGNATURE {
+        return Ok(None);
+    }
+    let entries_lba = u64::from_le_bytes(header[72..80].try_into().expect("8 bytes"));
+    let count = u32::from_le_bytes(header[80..84].try_into().expect("4 bytes")) as usize;
+    let entry_size = u32::from_le_bytes(header[84..88].try_into().expect("4 bytes")) as usize;
+    // The spec allows 128 * 2^n byte entries; nothing writes more than 4 KiB
+    let valid_entry = entry_size.is_power_of_two() && (128..=4096).contains(&entry_size);
+    let table_bytes = count
+        .checked_mul(entry_size)
+        .filter(|&bytes| valid_entry && count <= 1024 && bytes <= MAX_GPT_TABLE_BYTES);
+    let Some(table_bytes) = table_bytes else {
+        bail!(
+            "Unsupported GPT layout ({} entries of {} bytes)",
+            count,
+            entry_size
+        );
+    };
+
+    let mut table = vec![0u8; table_bytes];
+    reader.seek(SeekFrom::Start(to_bytes(entries_lba, sector_size)?))?;
+    reader
+        .read_exact(&mut table)
+        .context("Failed to read GPT partition entries")?;
+
+    let mut partitions = Vec::new();
+    for (i, e) in table.chunks_exact(entry_size).enumerate() {
+        if e[..16].iter().all(|&b| b == 0) {
+            continue;
+        }
+        let first = u64::from_le_bytes(e[32..40].try_into().expect("8 bytes"));
+        let last = u64::from_le_bytes(e[40..48].try_into().expect("8 bytes"));
+        if last < first {
+            continue;
+        }
+        let name: Vec<u16> = e[56..128]
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .take_while(|&c| c != 0)
+            .collect();
+        let start = to_bytes(first, sector_size)?;
+        let size = to_bytes(last - first, sector_size)?
+            .checked_add(sector_size)
+            .filter(|size| start.checked_add(*size).is_some())
+            .with_context(|| {
+                format!("Invalid partition table: partition {} out of range", i + 1)
+            })?;
+        partitions.push(Partition {
+            number: i + 1,
+            start,
+            size,
+            kind: guid_string(&e[..16]),
+            name: String::from_utf16_lossy(&name),
+        });
+    }
+    Ok(Some(partitions))
+}
+
+/// Format a mixed-endian GPT GUID
+pub(crate) fn guid_string(b: &[u8]) -> String {
+    format!(
+        "{:02X}{:02X}{:02X}{:02X}-{:02X}{:02X}-{:02X}{:02X}-{:02X}{:02X}-{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}",
+        b[3], b[2], b[1], b[0], b[5], b[4], b[7], b[6], b[8], b[9], b[10], b[11], b[12], b[13], b[14], b[15]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn set_entry(sector: &mut [u8], index: usize, kind: u8, start: u32, sectors: u32) {
+        let e = &mut sector[446 + index * 16..462 + index * 16];
+        e[4] = kind;
+        e[8..12].copy_from_slice(&start.to_le_bytes());
+        e[12..16].copy_from_slice(&sectors.to_le_bytes());
+    }
+
+    #[test]
+    fn test_mbr_with_logical_partitions() {
+        let mut disk = vec![0u8; 200 * 512];
+        set_entry(&mut disk[..512], 0, 0x07, 2, 40);
+        set_entry(&mut disk[..512], 1, 0x05, 100, 100);
+        disk[510..512].copy_from_slice(&MBR_SIGNATURE);
+
+        // First EBR at LBA 100: logical at +1, next EBR at +50
+        let ebr = &mut disk[100 * 512..101 * 512];
+        set_entry(ebr, 0, 0x83, 1, 20);
+        set_entry(ebr, 1, 0x05, 50, 30);
+        ebr[510..512].copy_from_slice(&MBR_SIGNATURE);
+        let ebr = &mut disk[150 * 512..151 * 512];
+        set_entry(ebr, 0, 0x0B, 1, 10);
+        ebr[510..512].copy_from_slice(&MBR_SIGNATURE);
+
+        let parts = read_partitions(&mut Cursor::new(disk)).unwrap();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(
+            (parts[0].number, parts[0].start, parts[0].size),
+            (1, 1024, 40 * 512)
+        );
+        assert_eq!(parts[0].kind, "0x07");
+        assert_eq!((parts[1].number, parts[1].start), (5, 101 * 512));
+        assert_eq!(
+            (parts[2].number, parts[2].start, parts[2].size),
+            (6, 151 * 512, 10 * 512)
+        );
+    }
+
+    /// Protective MBR, GPT header at LBA 1 and entries at LBA 2
+    fn gpt_disk(entry_size: u32, entries: &[(u64, u64)]) -> Vec<u8> {
+        let mut disk = vec![0u8; 64 * 512];
+        set_entry(&mut disk[..512], 0, GPT_PROTECTIVE, 1, 63);
+        disk[510..512].copy_from_slice(&MBR_SIGNATURE);
+        let header = &mut disk[512..1024];
+        header[..8].copy_from_slice(GPT_SIGNATURE);
+        header[72..80].copy_from_slice(&2u64.to_le_bytes());
+        header[80..84].copy_from_slice(&(entries.len() as u32).to_le_bytes());
+        header[84..88].copy_from_slice(&entry_size.to_le_bytes());
+        for (i, &(first, last)) in entries.iter().enumerate() {
+            let e = &mut disk[1024 + i * 128..1024 + (i + 1) * 128];
+            e[..16].fill(0xAB);
+            e[32..40].copy_from_slice(&first.to_le_bytes());
+            e[40..48].copy_from_slice(&last.to_le_bytes());
+        }
+        disk
+    }
+
+    #[test]
+    fn test_gpt_entries() {
+        let disk = gpt_disk(128, &[(34, 43), (50, 40)]);
+        let parts = read_partitions(&mut Cursor::new(disk)).unwrap();
+        // The second entry ends before it starts and is skipped
+        assert_eq!(parts.len(), 1);
+        assert_eq!((parts[0].start, parts[0].size), (34 * 512, 10 * 512));
+    }
+
+    #[test]
+    fn test_corrupt_gpt_rejected() {
+        let disk = gpt_disk(u32::MAX, &[(34, 43)]);
+        assert!(read_partitions(&mut Cursor::new(disk)).is_err());
+        let disk = gpt_disk(200, &[(34, 43)]);
+        assert!(read_partitions(&mut Cursor::new(disk)).is_err());
+
+        let disk = gpt_disk(128, &[(u64::MAX / 2, u64::MAX)]);
+        let err = read_partitions(&mut Cursor::new(disk)).unwrap_err();
+        assert!(err.to_string().contains("Invalid partition table"));
+    }
+
+    #[test]
+    fn test_no_partition_table() {
+        let parts = read_partitions(&mut Cursor::new(vec![0u8; 4096])).unwrap();
+        assert!(parts.is_empty());
+    }
+}
@@ -0,0 +1,197 @@
+//! Split raw images (`disk.001`, `disk.002`, …)
+
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+
+/// Whether `path` looks like the first segment of a split raw image
+pub fn is_split_raw(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == "001")
+}
+
+/// Path of segment `number` (1-based) next to `first`: `.001`, `.002`, …
+pub fn segment_path(first: &Path, number: u32) -> PathBuf {
+    first.with_extension(format!("{:03}", number))
+}
+
+/// Reader presenting the segments of a split raw image as one stream
+pub struct SplitReader {
+    segments: Vec<(File, u64)>,
+    size: u64,
+    pos: u64,
+}
+
+impl SplitReader {
+    /// Open `first` (`.001`) and every consecutive segment after it
+    pub fn open(first: &Path) -> Result<Self> {
+        let mut segments = Vec::new();
+        let mut size = 0;
+        for number in 1.. {
+            let path = segment_path(first, number);
+            if !path.exists() {
+                if number == 1 {
+                    bail!("Split image segment not found: {}", path.display());
+                }
+                break;
+            }
+            let file = File::open(&path)
+                .with_context(|| format!("Failed to open segment {}", path.display()))?;
+            let len = file.metadata()?.len();
+            size += len;
+            segments.push((file, len));
+        }
+        Ok(Self {
+            segments,
+            size,
+            pos: 0,
+        })
+    }
+
+    /// Combined size of all segments
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+impl Read for SplitReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut start = 0;
+        for (file, len) in &mut self.segments {
+            if self.pos < start + *len {
+                let within = self.pos - start;
+                file.seek(SeekFrom::Start(within))?;
+                let want = ((*len - within) as usize).min(buf.len());
+                let n = file.read(&mut buf[..want])?;
+                self.pos += n as u64;
+                return Ok(n);
+            }
+            start += *len;
+        }
+        Ok(0)
+    }
+}
+
+impl Seek for SplitReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new = match pos {
+            SeekFrom::Start(p) => p as i128,
+            SeekFrom::End(d) => self.size as i128 + d as i128,
+            SeekFrom::Current(d) => self.pos as i128 + d as i128,
+        };
+        if new < 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek before start",
+            ));
+        }
+        self.pos = new as u64;
+        Ok(self.pos)
+    }
+}
+
+/// Writer that starts a new `.NNN` segment every `segment_size` bytes
+pub struct SplitWriter {
+    first: PathBuf,
+    segment_size: u64,
+    current: Option<BufWriter<File>>,
+    in_segment: u64,
+    segments: Vec<PathBuf>,
+}
+
+impl SplitWriter {
+    pub fn create(first: &Path, segment_size: u64) -> Result<Self> {
+        if segment_size == 0 {
+            bail!("Segment size must be greater than zero");
+        }
+        Ok(Self {
+            first: first.to_path_buf(),
+            segment_size,
+            current: None,
+            in_segment: 0,
+            segments: Vec::new(),
+        })
+    }
+
+    fn next_segment(&mut self) -> io::Result<()> {
+        if let Some(mut out) = self.current.take() {
+            out.flush()?;
+        }
+        let number = self.segments.len() as u32 + 1;
+        if number > 999 {
+            return Err(io::Error::other("More than 999 split segments needed"));
+        }
+        let path = segment_path(&self.first, number);
+        let file = File::create(&path)?;
+        self.current = Some(BufWriter::with_capacity(1024 * 1024, file));
+        self.in_segment = 0;
+        self.segments.push(path);
+        Ok(())
+    }
+
+    /// Flush the last segment and return every segment written
+    pub fn finish(mut self) -> Result<Vec<PathBuf>> {
+        if self.segments.is_empty() {
+            // An empty source still produces an (empty) first segment
+            self.next_segment()?;
+        }
+        if let Some(mut out) = self.current.take() {
+            out.flush()?;
+        }
+        Ok(self.segments)
+    }
+}
+
+impl Write for SplitWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.current.is_none() || self.in_segment >= self.segment_size {
+            self.next_segment()?;
+        }
+        let room = (self.segment_size - self.in_segment) as usize;
+        let n = self
+            .current
+            .as_mut()
+            .expect("segment is open")
+            .write(&buf[..room.min(buf.len())])?;
+        self.in_segment += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.current.as_mut() {
+            Some(out) => out.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_split_round_trip() {
+        let dir = tempdir().unwrap();
+        let first = dir.path().join("disk.001");
+        let data: Vec<u8> = (0..25_000u32).map(|i| (i % 253) as u8).collect();
+
+        let mut writer = SplitWriter::create(&first, 10_000).unwrap();
+        writer.write_all(&data).unwrap();
+        let segments = writer.finish().unwrap();
+        assert_eq!(segments.len(), 3);
+        assert_eq!(std::fs::metadata(&segments[2]).unwrap().len(), 5_000);
+
+        let mut reader = SplitReader::open(&first).unwrap();
+        assert_eq!(reader.size(), 25_000);
+        let mut back = Vec::new();
+        reader.read_to_end(&mut back).unwrap();
+        assert_eq!(back, data);
+
+        reader.seek(SeekFrom::Start(9_995)).unwrap();
+        let mut buf = [0u8; 10];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf[..], &data[9_995..10_005]);
+    }
+}
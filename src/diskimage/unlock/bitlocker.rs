@@ -0,0 +1,529 @@
+//! BitLocker (Windows 7 and later) volume unlocking
+//!
+//! The volume boot sector points at three copies of the FVE metadata
+//! block. Its dataset holds volume master key (VMK) entries, one per
+//! protector, each with the VMK encrypted by AES-CCM under a key derived
+//! from that protector, and the full volume encryption key (FVEK)
+//! encrypted under the VMK. The first sectors of the volume are moved to a
+//! backup location and the metadata blocks read as zeros in the plaintext.
+
+use std::io;
+
+use aes::Aes256;
+use anyhow::{bail, ensure, Context, Result};
+use ccm::aead::generic_array::GenericArray;
+use ccm::aead::{AeadInPlace, KeyInit};
+use ccm::consts::{U12, U16};
+use ccm::Ccm;
+use sha2::{Digest, Sha256};
+
+use super::sector::{Aes, SectorCipher};
+use super::{read_at, EncryptedVolume, ImageReader, UnlockKey, VolumeDecryptor};
+
+/// Size of an FVE metadata block, hidden from the plaintext view
+const METADATA_BLOCK_SIZE: u64 = 64 * 1024;
+
+/// SHA-256 rounds used to stretch passwords and recovery passwords
+const STRETCH_ROUNDS: u64 = 0x10_0000;
+
+// Datum value types
+const VALUE_KEY: u16 = 0x0001;
+const VALUE_STRETCH_KEY: u16 = 0x0003;
+const VALUE_AES_CCM: u16 = 0x0005;
+const VALUE_VMK: u16 = 0x0008;
+
+/// Datum entry type of the FVEK
+const ENTRY_FVEK: u16 = 0x0003;
+
+// VMK protection types
+const PROTECTION_CLEAR_KEY: u16 = 0x0000;
+const PROTECTION_RECOVERY_PASSWORD: u16 = 0x0800;
+const PROTECTION_PASSWORD: u16 = 0x2000;
+
+type BitLockerCcm = Ccm<Aes256, U16, U12>;
+
+/// Unlocked BitLocker volume
+pub(super) struct BitLocker {
+    volume_offset: u64,
+    size: u64,
+    sector_size: usize,
+    cipher: SectorCipher,
+    /// XTS uses sector numbers as IVs, the CBC modes byte offsets
+    xts: bool,
+    /// Bytes from the start of the volume that are encrypted (conversion
+    /// may still be running)
+    encrypted_size: u64,
+    /// Where the first `backup_len` bytes of the volume were moved
+    backup_offset: u64,
+    backup_len: u64,
+    metadata_offsets: [u64; 3],
+}
+
+/// Entry in an FVE metadata dataset
+struct Datum<'a> {
+    entry_type: u16,
+    value_type: u16,
+    /// Whole datum, header included
+    data: &'a [u8],
+}
+
+/// Datums packed back to back in `buf`
+fn datums(mut buf: &[u8]) -> impl Iterator<Item = Datum<'_>> {
+    std::iter::from_fn(move || {
+        if buf.len() < 8 {
+            return None;
+        }
+        let size = u16::from_le_bytes([buf[0], buf[1]]) as usize;
+        if size < 8 || size > buf.len() {
+            return None;
+        }
+        let (data, rest) = buf.split_at(size);
+        buf = rest;
+        Some(Datum {
+            entry_type: u16::from_le_bytes([data[2], data[3]]),
+            value_type: u16::from_le_bytes([data[4], data[5]]),
+            data,
+        })
+    })
+}
+
+fn u16_at(buf: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([buf[at], buf[at + 1]])
+}
+
+fn u32_at(buf: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(buf[at..at + 4].try_into().unwrap())
+}
+
+fn u64_at(buf: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(buf[at..at + 8].try_into().unwrap())
+}
+
+/// Decrypt an AES-CCM datum (nonce, MAC, ciphertext) and return the datum
+/// it wraps
+fn decrypt_ccm(datum: &[u8], key: &[u8]) -> Result<Vec<u8>> {
+    ensure!(datum.len() > 36, "AES-CCM datum too short");
+    let cipher = BitLockerCcm::new_from_slice(key).context("Bad AES-CCM key length")?;
+    let mut plain = datum[36..].to_vec();
+    cipher
+        .decrypt_in_place_detached(
+            GenericArray::from_slice(&datum[8..20]),
+            &[],
+            &mut plain,
+            GenericArray::from_slice(&datum[20..36]),
+        )
+        .map_err(|_| anyhow::anyhow!("AES-CCM authentication failed"))?;
+    Ok(plain)
+}
+
+/// Key bytes of a decrypted key datum
+fn key_bytes(datum: &[u8]) -> Result<&[u8]> {
+    ensure!(
+        datum.len() > 12 && u16_at(datum, 4) == VALUE_KEY,
+        "Decrypted key has an unexpected layout"
+    );
+    let size = (u16_at(datum, 0) as usize).clamp(12, datum.len());
+    Ok(&datum[12..size])
+}
+
+/// 16-byte key encoded by a 48-digit recovery password
+fn parse_recovery_password(password: &str) -> Option<[u8; 16]> {
+    let groups: Vec<&str> = password.trim().split('-').collect();
+    if groups.len() != 8 {
+        return None;
+    }
+    let mut key = [0u8; 16];
+    for (i, group) in groups.iter().enumerate() {
+        if group.len() != 6 {
+            return None;
+        }
+        let value: u32 = group.parse().ok()?;
+        if !value.is_multiple_of(11) || value / 11 > u16::MAX as u32 {
+            return None;
+        }
+        key[i * 2..i * 2 + 2].copy_from_slice(&((value / 11) as u16).to_le_bytes());
+    }
+    Some(key)
+}
+
+/// BitLocker's SHA-256 key stretching
+fn stretch(hash: [u8; 32], salt: &[u8]) -> [u8; 32] {
+    let mut state = [0u8; 88];
+    state[32..64].copy_from_slice(&hash);
+    state[64..80].copy_from_slice(&salt[..16]);
+    for count in 0..STRETCH_ROUNDS {
+        state[80..88].copy_from_slice(&count.to_le_bytes());
+        let next = Sha256::digest(state);
+        state[..32].copy_from_slice(&next);
+    }
+    state[..32].try_into().unwrap()
+}
+
+/// Try to decrypt the VMK of one protector with `key`
+fn unlock_vmk(vmk: &[u8], key: &UnlockKey) -> Result<Option<Vec<u8>>> {
+    let protection = u16_at(vmk, 34);
+    let nested: Vec<Datum> = datums(&vmk[36..]).collect();
+    let encrypted = nested
+        .iter()
+        .find(|d| d.value_type == VALUE_AES_CCM)
+        .context("VMK entry has no encrypted key")?;
+    let salt = || {
+        nested
+            .iter()
+            .find(|d| d.value_type == VALUE_STRETCH_KEY && d.data.len() >= 28)
+            .map(|d| &d.data[12..28])
+            .context("VMK entry has no stretch key")
+    };
+
+    let wrapping_key: Vec<u8> = match (protection, key) {
+        (PROTECTION_CLEAR_KEY, _) => {
+            let clear = nested
+                .iter()
+                .find(|d| d.value_type == VALUE_KEY)
+                .context("Clear key entry has no key")?;
+            key_bytes(clear.data)?.to_vec()
+        }
+        (PROTECTION_RECOVERY_PASSWORD, UnlockKey::Secret(secret)) => {
+            let Some(recovery) = parse_recovery_password(secret) else {
+                return Ok(None);
+            };
+            stretch(Sha256::digest(recovery).into(), salt()?).to_vec()
+        }
+        (PROTECTION_PASSWORD, UnlockKey::Secret(secret)) => {
+            let utf16: Vec<u8> = secret.encode_utf16().flat_map(u16::to_le_bytes).collect();
+            stretch(Sha256::digest(Sha256::digest(utf16)).into(), salt()?).to_vec()
+        }
+        _ => return Ok(None),
+    };
+    match decrypt_ccm(encrypted.data, &wrapping_key) {
+        Ok(plain) => Ok(Some(key_bytes(&plain)?.to_vec())),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Sector cipher for an FVEK with the given encryption method
+fn fvek_cipher(method: u16, key: &[u8]) -> Result<(SectorCipher, bool)> {
+    let key_at = |range: std::ops::Range<usize>| {
+        key.get(range)
+            .with_context(|| format!("FVEK too short for method {:#06x}", method))
+    };
+    Ok(match method {
+        0x8000 => (
+            SectorCipher::BitLockerCbc {
+                data: Aes::new(key_at(0..16)?)?,
+                diffuser: Some(Aes::new(key_at(32..48)?)?),
+            },
+            false,
+        ),
+        0x8001 => (
+            SectorCipher::BitLockerCbc {
+                data: Aes::new(key_at(0..32)?)?,
+                diffuser: Some(Aes::new(key_at(32..64)?)?),
+            },
+            false,
+        ),
+        0x8002 => (
+            SectorCipher::BitLockerCbc {
+                data: Aes::new(key_at(0..16)?)?,
+                diffuser: None,
+            },
+            false,
+        ),
+        0x8003 => (
+            SectorCipher::BitLockerCbc {
+                data: Aes::new(key_at(0..32)?)?,
+                diffuser: None,
+            },
+            false,
+        ),
+        0x8004 => (SectorCipher::xts(key_at(0..32)?)?, true),
+        0x8005 => (SectorCipher::xts(key_at(0..64)?)?, true),
+        m => bail!("Unsupported BitLocker encryption method {:#06x}", m),
+    })
+}
+
+impl BitLocker {
+    pub(super) fn unlock(
+        inner: &mut dyn ImageReader,
+        volume: &EncryptedVolume,
+        key: &UnlockKey,
+    ) -> Result<Self> {
+        let mut boot = [0u8; 512];
+        read_at(inner, volume.offset, &mut boot)?;
+        let sector_size = u16_at(&boot, 0x0B) as usize;
+        ensure!(
+            matches!(sector_size, 512 | 1024 | 2048 | 4096),
+            "Invalid BitLocker sector size {}",
+            sector_size
+        );
+        let metadata_offsets = [
+            u64_at(&boot, 0xB0),
+            u64_at(&boot, 0xB8),
+            u64_at(&boot, 0xC0),
+        ];
+        ensure!(
+            metadata_offsets[0] != 0,
+            "BitLocker volumes created by Windows Vista are not supported"
+        );
+
+        // Any intact copy of the metadata will do
+        let block = metadata_offsets
+            .iter()
+            .find_map(|&offset| {
+                let mut block = vec![0u8; METADATA_BLOCK_SIZE as usize];
+                read_at(inner, volume.offset + offset, &mut block).ok()?;
+                (&block[..8] == b"-FVE-FS-" && u16_at(&block, 10) >= 2).then_some(block)
+            })
+            .context("No intact FVE metadata block found")?;
+        Self::from_metadata(&block, volume, sector_size, metadata_offsets, key)
+    }
+
+    fn from_metadata(
+        block: &[u8],
+        volume: &EncryptedVolume,
+        sector_size: usize,
+        metadata_offsets: [u64; 3],
+        key: &UnlockKey,
+    ) -> Result<Self> {
+        let encrypted_size = u64_at(block, 16);
+        let backup_sectors = u32_at(block, 28) as u64;
+        let backup_offset = u64_at(block, 56);
+
+        let dataset = &block[64..];
+        let dataset_size = (u32_at(dataset, 0) as usize).min(dataset.len());
+        let header_size = u32_at(dataset, 8) as usize;
+        ensure!(header_size < dataset_size, "Bad FVE dataset header");
+        let entries: Vec<Datum> = datums(&dataset[header_size..dataset_size]).collect();
+
+        let mut vmk = None;
+        for entry in entries
+            .iter()
+            .filter(|d| d.value_type == VALUE_VMK && d.data.len() > 36)
+        {
+            if let Some(found) = unlock_vmk(entry.data, key)? {
+                vmk = Some(found);
+                break;
+            }
+        }
+        let vmk = vmk.context(match key {
+            UnlockKey::ClearKey => {
+                "BitLocker protection is not suspended: a password or recovery password is needed"
+            }
+            UnlockKey::Secret(_) => "Wrong password or recovery password",
+        })?;
+
+        let fvek = entries
+            .iter()
+            .find(|d| d.entry_type == ENTRY_FVEK && d.value_type == VALUE_AES_CCM)
+            .context("FVE metadata has no FVEK")?;
+        let fvek = decrypt_ccm(fvek.data, &vmk).context("FVEK does not decrypt with the VMK")?;
+        let method = u16_at(&fvek, 8);
+        let (cipher, xts) = fvek_cipher(method, key_bytes(&fvek)?)?;
+
+        let size = volume.size - volume.size % sector_size as u64;
+        Ok(Self {
+            volume_offset: volume.offset,
+            size,
+            sector_size,
+            cipher,
+            xts,
+            encrypted_size: if encrypted_size == 0 {
+                size
+            } else {
+                encrypted_size
+            },
+            backup_offset,
+            backup_len: backup_sectors * sector_size as u64,
+            metadata_offsets,
+        })
+    }
+}
+
+impl VolumeDecryptor for BitLocker {
+    fn size(&self) -> u64 {
+        self.size
+    }
+
+    fn read_sectors(
+        &self,
+        inner: &mut dyn ImageReader,
+        offset: u64,
+        buf: &mut [u8],
+    ) -> io::Result<()> {
+        read_at(inner, self.volume_offset + offset, buf)?;
+        for (i, sector) in buf.chunks_exact_mut(self.sector_size).enumerate() {
+            let plain_offset = offset + (i * self.sector_size) as u64;
+            if self
+                .metadata_offsets
+                .iter()
+                .any(|&m| plain_offset >= m && plain_offset < m + METADATA_BLOCK_SIZE)
+            {
+                sector.fill(0);
+                continue;
+            }
+            // The original boot sectors live (encrypted) at the backup location
+            let raw_offset = if plain_offset < self.backup_len {
+                let raw = self.backup_offset + plain_offset;
+                read_at(inner, self.volume_offset + raw, sector)?;
+                raw
+            } else {
+                plain_offset
+            };
+            if raw_offset < self.encrypted_size {
+                let iv = if self.xts {
+                    raw_offset / self.sector_size as u64
+                } else {
+                    raw_offset
+                };
+                self.cipher.decrypt(iv, sector);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::UnlockedReader;
+    use super::*;
+    use crate::analyze::VolumeHeaderKind;
+    use std::io::{Cursor, Read, Seek, SeekFrom};
+
+    fn datum(entry_type: u16, value_type: u16, body: &[u8]) -> Vec<u8> {
+        let mut d = Vec::new();
+        d.extend(((body.len() + 8) as u16).to_le_bytes());
+        d.extend(entry_type.to_le_bytes());
+        d.extend(value_type.to_le_bytes());
+        d.extend(1u16.to_le_bytes());
+        d.extend(body);
+        d
+    }
+
+    fn key_datum(method: u16, key: &[u8]) -> Vec<u8> {
+        let mut body = method.to_le_bytes().to_vec();
+        body.extend([0, 0]);
+        body.extend(key);
+        datum(0, VALUE_KEY, &body)
+    }
+
+    fn ccm_datum(entry_type: u16, key: &[u8], plain: &[u8]) -> Vec<u8> {
+        let nonce = [7u8; 12];
+        let mut data = plain.to_vec();
+        let tag = BitLockerCcm::new_from_slice(key)
+            .unwrap()
+            .encrypt_in_place_detached(GenericArray::from_slice(&nonce), &[], &mut data)
+            .unwrap();
+        let mut body = nonce.to_vec();
+        body.extend(tag);
+        body.extend(data);
+        datum(entry_type, VALUE_AES_CCM, &body)
+    }
+
+    /// Suspended-protection volume: VMK under a clear key, FVEK (AES-128
+    /// with diffuser) under the VMK
+    #[test]
+    fn test_clear_key_volume() {
+        const SECTOR: usize = 512;
+        const SIZE: usize = 512 * 1024;
+        let metadata = [0x1_0000u64, 0x3_0000, 0x5_0000];
+        let backup_offset = 0x7_0000u64;
+        let clear_key = [3u8; 32];
+        let vmk_key = [4u8; 32];
+        let mut fvek = vec![5u8; 64];
+        fvek[32..].fill(6);
+
+        let plain: Vec<u8> = (0..SIZE).map(|i| (i / SECTOR + i % 13) as u8).collect();
+        let cipher = fvek_cipher(0x8000, &fvek).unwrap().0;
+
+        // Encrypt in place; the boot sector goes (encrypted) to the backup
+        let mut image = plain.clone();
+        for (i, sector) in image.chunks_exact_mut(SECTOR).enumerate() {
+            cipher.encrypt((i * SECTOR) as u64, sector);
+        }
+        let mut boot = plain[..SECTOR].to_vec();
+        cipher.encrypt(backup_offset, &mut boot);
+        image[backup_offset as usize..backup_offset as usize + SECTOR].copy_from_slice(&boot);
+
+        let mut vmk_body = vec![0u8; 28];
+        vmk_body[26..28].copy_from_slice(&PROTECTION_CLEAR_KEY.to_le_bytes());
+        vmk_body.extend(key_datum(0x2000, &clear_key));
+        vmk_body.extend(ccm_datum(0, &clear_key, &key_datum(0x2000, &vmk_key)));
+        let mut entries = datum(2, VALUE_VMK, &vmk_body);
+        entries.extend(ccm_datum(ENTRY_FVEK, &vmk_key, &key_datum(0x8000, &fvek)));
+
+        let mut block = vec![0u8; 64 + 48];
+        block[..8].copy_from_slice(b"-FVE-FS-");
+        block[10..12].copy_from_slice(&2u16.to_le_bytes());
+        block[16..24].copy_from_slice(&(SIZE as u64).to_le_bytes());
+        block[28..32].copy_from_slice(&1u32.to_le_bytes());
+        block[56..64].copy_from_slice(&backup_offset.to_le_bytes());
+        block[64..68].copy_from_slice(&((48 + entries.len()) as u32).to_le_bytes());
+        block[72..76].copy_from_slice(&48u32.to_le_bytes());
+        block.extend(entries);
+        image[metadata[0] as usize..metadata[0] as usize + block.len()].copy_from_slice(&block);
+
+        let mut header = [0u8; SECTOR];
+        header[3..11].copy_from_slice(b"-FVE-FS-");
+        header[0x0B..0x0D].copy_from_slice(&(SECTOR as u16).to_le_bytes());
+        for (i, m) in metadata.iter().enumerate() {
+            header[0xB0 + i * 8..0xB8 + i * 8].copy_from_slice(&m.to_le_bytes());
+        }
+        image[..SECTOR].copy_from_slice(&header);
+
+        let volume = EncryptedVolume {
+            kind: VolumeHeaderKind::BitLocker,
+            offset: 0,
+            size: SIZE as u64,
+            detail: String::new(),
+        };
+        // Suspended protection opens with any secret, but not without metadata
+        let mut damaged = image.clone();
+        damaged[metadata[0] as usize] = 0;
+        assert!(UnlockedReader::new(
+            Box::new(Cursor::new(damaged)),
+            volume.clone(),
+            &UnlockKey::ClearKey
+        )
+        .is_err());
+
+        let mut reader =
+            UnlockedReader::new(Box::new(Cursor::new(image)), volume, &UnlockKey::ClearKey)
+                .unwrap();
+        let mut out = vec![0u8; SIZE];
+        reader.read_exact(&mut out).unwrap();
+        for (i, sector) in out.chunks(SECTOR).enumerate() {
+            let offset = (i * SECTOR) as u64;
+            if metadata
+                .iter()
+                .any(|&m| (m..m + METADATA_BLOCK_SIZE).contains(&offset))
+            {
+                assert!(
+                    sector.iter().all(|&b| b == 0),
+                    "metadata hidden at {}",
+                    offset
+                );
+            } else if offset != backup_offset {
+                assert_eq!(
+                    sector,
+                    &plain[i * SECTOR..(i + 1) * SECTOR],
+                    "sector at {}",
+                    offset
+                );
+            }
+        }
+
+        reader.seek(SeekFrom::Start(SIZE as u64 - 100)).unwrap();
+        let mut tail = Vec::new();
+        reader.read_to_end(&mut tail).unwrap();
+        assert_eq!(tail, plain[SIZE - 100..]);
+
+        assert_eq!(
+            parse_recovery_password("000011-000022-000033-000044-000055-000066-000077-000088"),
+            Some([1, 0, 2, 0, 3, 0, 4, 0, 5, 0, 6, 0, 7, 0, 8, 0])
+        );
+        assert_eq!(
+            parse_recovery_password("000012-000022-000033-000044-000055-000066-000077-000088"),
+            None
+        );
+    }
+}
@@ -0,0 +1,680 @@
+//! LUKS1 and LUKS2 volume unlocking
+//!
+//! A passphrase opens a keyslot: the KDF (PBKDF2, or Argon2 in LUKS2)
+//! turns it into a key that decrypts the slot's anti-forensic split key
+//! material, which merges back into the volume (master) key. The key is
+//! accepted when its PBKDF2 digest matches the one in the header.
+
+use std::io;
+
+use anyhow::{bail, ensure, Context, Result};
+use base64::Engine;
+use serde_json::Value;
+use sha2::Digest;
+
+use super::sector::{Aes, SectorCipher};
+use super::{read_at, EncryptedVolume, ImageReader, UnlockKey, VolumeDecryptor};
+
+const SECTOR: usize = 512;
+const LUKS1_KEYSLOTS: usize = 8;
+const LUKS1_KEYSLOT_ACTIVE: u32 = 0x00AC_71F3;
+/// Size of the LUKS2 binary header that precedes the JSON area
+const LUKS2_BINARY_HEADER: usize = 4096;
+/// Volume and keyslot key sizes LUKS uses (AES-128 up to AES-256 in XTS mode)
+const KEY_LEN_RANGE: std::ops::RangeInclusive<usize> = 16..=64;
+/// cryptsetup always writes 4000 anti-forensic stripes
+const MAX_STRIPES: usize = 4000;
+/// Largest Argon2 memory cost accepted from a header (cryptsetup's 4 GiB limit)
+const MAX_ARGON2_MEMORY_KIB: u64 = 4 * 1024 * 1024;
+
+/// Hash used by a LUKS KDF, digest or anti-forensic splitter
+#[derive(Debug, Clone, Copy)]
+enum LuksHash {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl LuksHash {
+    fn parse(name: &str) -> Result<Self> {
+        Ok(match name.to_ascii_lowercase().as_str() {
+            "sha1" => Self::Sha1,
+            "sha256" => Self::Sha256,
+            "sha512" => Self::Sha512,
+            other => bail!("Unsupported LUKS hash {}", other),
+        })
+    }
+
+    fn pbkdf2(self, password: &[u8], salt: &[u8], rounds: u32, out: &mut [u8]) {
+        match self {
+            Self::Sha1 => pbkdf2::pbkdf2_hmac::<sha1::Sha1>(password, salt, rounds, out),
+            Self::Sha256 => pbkdf2::pbkdf2_hmac::<sha2::Sha256>(password, salt, rounds, out),
+            Self::Sha512 => pbkdf2::pbkdf2_hmac::<sha2::Sha512>(password, salt, rounds, out),
+        }
+    }
+
+    fn digest(self, parts: &[&[u8]]) -> Vec<u8> {
+        fn run<D: Digest>(parts: &[&[u8]]) -> Vec<u8> {
+            let mut hasher = D::new();
+            for part in parts {
+                hasher.update(part);
+            }
+            hasher.finalize().to_vec()
+        }
+        match self {
+            Self::Sha1 => run::<sha1::Sha1>(parts),
+            Self::Sha256 => run::<sha2::Sha256>(parts),
+            Self::Sha512 => run::<sha2::Sha512>(parts),
+        }
+    }
+}
+
+/// Key derivation of a keyslot
+enum Kdf {
+    Pbkdf2 {
+        hash: LuksHash,
+        iterations: u32,
+        salt: Vec<u8>,
+    },
+    Argon2 {
+        algorithm: argon2::Algorithm,
+        time: u32,
+        memory_kib: u32,
+        lanes: u32,
+        salt: Vec<u8>,
+    },
+}
+
+impl Kdf {
+    fn derive(&self, passphrase: &[u8], key_len: usize) -> Result<Vec<u8>> {
+        let mut key = vec![0u8; key_len];
+        match self {
+            Self::Pbkdf2 {
+                hash,
+                iterations,
+                salt,
+            } => hash.pbkdf2(passphrase, salt, *iterations, &mut key),
+            Self::Argon2 {
+                algorithm,
+                time,
+                memory_kib,
+                lanes,
+                salt,
+            } => {
+                let params = argon2::Params::new(*memory_kib, *time, *lanes, Some(key_len))
+                    .map_err(|e| anyhow::anyhow!("Bad Argon2 parameters: {}", e))?;
+                argon2::Argon2::new(*algorithm, argon2::Version::V0x13, params)
+                    .hash_password_into(passphrase, salt, &mut key)
+                    .map_err(|e| anyhow::anyhow!("Argon2 failed: {}", e))?;
+            }
+        }
+        Ok(key)
+    }
+}
+
+/// An active keyslot
+struct Keyslot {
+    kdf: Kdf,
+    /// Cipher spec of the key material, e.g. `aes-xts-plain64`
+    encryption: String,
+    key_len: usize,
+    /// Byte offset of the key material in the volume
+    area_offset: u64,
+    stripes: usize,
+    af_hash: LuksHash,
+    /// LUKS2 digest that checks the key this slot yields
+    digest: Option<VolumeKeyDigest>,
+}
+
+/// PBKDF2 digest of the volume key
+struct VolumeKeyDigest {
+    hash: LuksHash,
+    iterations: u32,
+    salt: Vec<u8>,
+    digest: Vec<u8>,
+}
+
+impl VolumeKeyDigest {
+    fn matches(&self, key: &[u8]) -> bool {
+        let mut out = vec![0u8; self.digest.len()];
+        self.hash.pbkdf2(key, &self.salt, self.iterations, &mut out);
+        out == self.digest
+    }
+}
+
+impl Keyslot {
+    /// Reject header values that would crash or exhaust memory before the KDF runs
+    fn validate(&self) -> Result<()> {
+        ensure!(
+            KEY_LEN_RANGE.contains(&self.key_len),
+            "Bad LUKS key size {}",
+            self.key_len
+        );
+        ensure!(
+            (1..=MAX_STRIPES).contains(&self.stripes),
+            "Bad LUKS anti-forensic stripe count {}",
+            self.stripes
+        );
+        Ok(())
+    }
+}
+
+/// Unlocked LUKS volume
+pub(super) struct Luks {
+    /// Byte offset of the encrypted data in the image
+    data_offset: u64,
+    size: u64,
+    sector_size: usize,
+    /// IV of the first data sector
+    iv_tweak: u64,
+    cipher: SectorCipher,
+}
+
+/// Build the sector cipher for a spec like `aes-xts-plain64`
+fn sector_cipher(spec: &str, key: &[u8]) -> Result<SectorCipher> {
+    let (cipher, mode) = spec.split_once('-').unwrap_or((spec, ""));
+    ensure!(cipher == "aes", "Unsupported LUKS cipher {}", spec);
+    match mode {
+        "xts-plain64" | "xts-plain" => SectorCipher::xts(key),
+        "cbc-essiv:sha256" => SectorCipher::cbc_essiv(key),
+        "cbc-plain64" | "cbc-plain" => Ok(SectorCipher::CbcPlain {
+            data: Aes::new(key)?,
+        }),
+        _ => bail!("Unsupported LUKS cipher mode {}", spec),
+    }
+}
+
+/// Merge anti-forensic stripes back into the key they were split from
+fn af_merge(material: &[u8], key_len: usize, stripes: usize, hash: LuksHash) -> Vec<u8> {
+    let mut d = vec![0u8; key_len];
+    for (i, stripe) in material.chunks_exact(key_len).take(stripes).enumerate() {
+        for (a, b) in d.iter_mut().zip(stripe) {
+            *a ^= b;
+        }
+        if i + 1 < stripes {
+            d = af_diffuse(&d, hash);
+        }
+    }
+    d
+}
+
+fn af_diffuse(d: &[u8], hash: LuksHash) -> Vec<u8> {
+    let digest_len = hash.digest(&[]).len();
+    d.chunks(digest_len)
+        .enumerate()
+        .flat_map(|(i, chunk)| {
+            let mut h = hash.digest(&[&(i as u32).to_be_bytes(), chunk]);
+            h.truncate(chunk.len());
+            h
+        })
+        .collect()
+}
+
+/// NUL-padded string field of the binary header
+fn c_string(field: &[u8]) -> String {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+fn be_u32(buf: &[u8], at: usize) -> u32 {
+    u32::from_be_bytes(buf[at..at + 4].try_into().unwrap())
+}
+
+/// LUKS2 JSON numbers are strings when they may exceed 2^53
+fn json_u64(value: &Value) -> Option<u64> {
+    value.as_u64().or_else(|| value.as_str()?.parse().ok())
+}
+
+fn json_base64(value: &Value) -> Result<Vec<u8>> {
+    base64::engine::general_purpose::STANDARD
+        .decode(value.as_str().context("Expected a base64 string")?)
+        .context("Bad base64 in LUKS2 header")
+}
+
+impl Luks {
+    pub(super) fn unlock(
+        inner: &mut dyn ImageReader,
+        volume: &EncryptedVolume,
+        key: &UnlockKey,
+    ) -> Result<Self> {
+        let UnlockKey::Secret(passphrase) = key else {
+            bail!("LUKS volumes need a passphrase");
+        };
+        let mut header = vec![0u8; LUKS2_BINARY_HEADER];
+        read_at(inner, volume.offset, &mut header)?;
+        match u16::from_be_bytes([header[6], header[7]]) {
+            1 => Self::unlock_luks1(inner, volume, &header, passphrase.as_bytes()),
+            2 => Self::unlock_luks2(inner, volume, &header, passphrase.as_bytes()),
+            v => bail!("Unsupported LUKS version {}", v),
+        }
+    }
+
+    fn unlock_luks1(
+        inner: &mut dyn ImageReader,
+        volume: &EncryptedVolume,
+        header: &[u8],
+        passphrase: &[u8],
+    ) -> Result<Self> {
+        let spec = format!("{}-{}", c_string(&header[8..40]), c_string(&header[40..72]));
+        let hash = LuksHash::parse(&c_string(&header[72..104]))?;
+        let payload_offset = be_u32(header, 104) as u64 * SECTOR as u64;
+        let key_len = be_u32(header, 108) as usize;
+        let digest = VolumeKeyDigest {
+            hash,
+            iterations: be_u32(header, 164),
+            salt: header[132..164].to_vec(),
+            digest: header[112..132].to_vec(),
+        };
+
+        let slots = (0..LUKS1_KEYSLOTS)
+            .map(|i| &header[208 + i * 48..208 + (i + 1) * 48])
+            .filter(|slot| be_u32(slot, 0) == LUKS1_KEYSLOT_ACTIVE)
+            .map(|slot| Keyslot {
+                kdf: Kdf::Pbkdf2 {
+                    hash,
+                    iterations: be_u32(slot, 4),
+                    salt: slot[8..40].to_vec(),
+                },
+                encryption: spec.clone(),
+                key_len,
+                area_offset: be_u32(slot, 40) as u64 * SECTOR as u64,
+                stripes: be_u32(slot, 44) as usize,
+                af_hash: hash,
+                digest: None,
+            })
+            .collect::<Vec<_>>();
+
+        let volume_key = Self::open_keyslots(inner, volume, &slots, passphrase, Some(&digest))?;
+        Ok(Self {
+            data_offset: volume.offset + payload_offset,
+            size: volume.size.saturating_sub(payload_offset) / SECTOR as u64 * SECTOR as u64,
+            sector_size: SECTOR,
+            iv_tweak: 0,
+            cipher: sector_cipher(&spec, &volume_key)?,
+        })
+    }
+
+    fn unlock_luks2(
+        inner: &mut dyn ImageReader,
+        volume: &EncryptedVolume,
+        header: &[u8],
+        passphrase: &[u8],
+    ) -> Result<Self> {
+        let header_size = u64::from_be_bytes(header[8..16].try_into().unwrap()) as usize;
+        ensure!(
+            header_size > LUKS2_BINARY_HEADER && header_size <= 4 * 1024 * 1024,
+            "Bad LUKS2 header size {}",
+            header_size
+        );
+        let mut json_area = vec![0u8; header_size - LUKS2_BINARY_HEADER];
+        read_at(
+            inner,
+            volume.offset + LUKS2_BINARY_HEADER as u64,
+            &mut json_area,
+        )?;
+        let json: Value =
+            serde_json::from_str(&c_string(&json_area)).context("Bad LUKS2 JSON metadata")?;
+
+        let digests = json["digests"]
+            .as_object()
+            .context("LUKS2 header has no digests")?;
+        let mut slots = Vec::new();
+        for (id, slot) in json["keyslots"]
+            .as_object()
+            .context("LUKS2 header has no keyslots")?
+        {
+            let kdf = &slot["kdf"];
+            let kdf = match kdf["type"].as_str() {
+                Some("pbkdf2") => Kdf::Pbkdf2 {
+                    hash: LuksHash::parse(kdf["hash"].as_str().unwrap_or("sha256"))?,
+                    iterations: json_u64(&kdf["iterations"])
+                        .context("PBKDF2 keyslot without iterations")?
+                        as u32,
+                    salt: json_base64(&kdf["salt"])?,
+                },
+                Some(kind @ ("argon2i" | "argon2id")) => Kdf::Argon2 {
+                    algorithm: if kind == "argon2i" {
+                        argon2::Algorithm::Argon2i
+                    } else {
+                        argon2::Algorithm::Argon2id
+                    },
+                    time: json_u64(&kdf["time"]).unwrap_or(4) as u32,
+                    memory_kib: match json_u64(&kdf["memory"]).unwrap_or(1024 * 1024) {
+                        kib if kib <= MAX_ARGON2_MEMORY_KIB => kib as u32,
+                        kib => bail!("LUKS2 keyslot {} asks for {} KiB of Argon2 memory", id, kib),
+                    },
+                    lanes: json_u64(&kdf["cpus"]).unwrap_or(1) as u32,
+                    salt: json_base64(&kdf["salt"])?,
+                },
+                other => {
+                    tracing::debug!("Skipping LUKS2 keyslot {} with KDF {:?}", id, other);
+                    continue;
+                }
+            };
+            let digest = digests
+                .values()
+                .find(|d| {
+                    d["keyslots"]
+                        .as_array()
+                        .is_some_and(|k| k.iter().any(|k| k.as_str() == Some(id)))
+                })
+                .context("LUKS2 keyslot has no digest")?;
+            slots.push(Keyslot {
+                kdf,
+                encryption: slot["area"]["encryption"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                key_len: json_u64(&slot["area"]["key_size"]).context("Keyslot without key size")?
+                    as usize,
+                area_offset: json_u64(&slot["area"]["offset"]).context("Keyslot without offset")?,
+                stripes: json_u64(&slot["af"]["stripes"]).unwrap_or(4000) as usize,
+                af_hash: LuksHash::parse(slot["af"]["hash"].as_str().unwrap_or("sha256"))?,
+                digest: Some(VolumeKeyDigest {
+                    hash: LuksHash::parse(digest["hash"].as_str().unwrap_or("sha256"))?,
+                    iterations: json_u64(&digest["iterations"]).unwrap_or(1000) as u32,
+                    salt: json_base64(&digest["salt"])?,
+                    digest: json_base64(&digest["digest"])?,
+                }),
+            });
+        }
+
+        let volume_key = Self::open_keyslots(inner, volume, &slots, passphrase, None)?;
+
+        let segments = json["segments"]
+            .as_object()
+            .context("LUKS2 header has no segments")?;
+        let segment = segments
+            .iter()
+            .min_by_key(|(id, _)| id.parse::<u32>().unwrap_or(u32::MAX))
+            .map(|(_, s)| s)
+            .context("LUKS2 header has no data segment")?;
+        let offset = json_u64(&segment["offset"]).context("Segment without offset")?;
+        let sector_size = json_u64(&segment["sector_size"]).unwrap_or(SECTOR as u64);
+        ensure!(
+            sector_size.is_power_of_two() && (512..=4096).contains(&sector_size),
+            "Bad LUKS2 sector size {}",
+            sector_size
+        );
+        let sector_size = sector_size as usize;
+        let size = match segment["size"].as_str() {
+            Some("dynamic") => volume.size.saturating_sub(offset),
+            _ => json_u64(&segment["size"]).context("Segment without size")?,
+        };
+        Ok(Self {
+            data_offset: volume.offset + offset,
+            size: size / sector_size as u64 * sector_size as u64,
+            sector_size,
+            iv_tweak: json_u64(&segment["iv_tweak"]).unwrap_or(0),
+            cipher: sector_cipher(
+                segment["encryption"].as_str().unwrap_or_default(),
+                &volume_key,
+            )?,
+        })
+    }
+
+    /// Try the passphrase on each keyslot and return the volume key
+    fn open_keyslots(
+        inner: &mut dyn ImageReader,
+        volume: &EncryptedVolume,
+        slots: &[Keyslot],
+        passphrase: &[u8],
+        luks1_digest: Option<&VolumeKeyDigest>,
+    ) -> Result<Vec<u8>> {
+        ensure!(!slots.is_empty(), "LUKS header has no usable keyslots");
+        for slot in slots {
+            let Some(digest) = slot.digest.as_ref().or(luks1_digest) else {
+                continue;
+            };
+            slot.validate()?;
+            let material_len = slot
+                .key_len
+                .checked_mul(slot.stripes)
+                .context("LUKS keyslot material size overflows")?
+                .div_ceil(SECTOR)
+                * SECTOR;
+            let mut material = vec![0u8; material_len];
+            read_at(inner, volume.offset + slot.area_offset, &mut material)
+                .context("Failed to read keyslot material")?;
+
+            let slot_key = slot.kdf.derive(passphrase, slot.key_len)?;
+            let cipher = sector_cipher(&slot.encryption, &slot_key)?;
+            for (i, sector) in material.chunks_exact_mut(SECTOR).enumerate() {
+                cipher.decrypt(i as u64, sector);
+            }
+            let candidate = af_merge(&material, slot.key_len, slot.stripes, slot.af_hash);
+            if digest.matches(&candidate) {
+                return Ok(candidate);
+            }
+        }
+        bail!("Wrong passphrase: no keyslot opened")
+    }
+}
+
+impl VolumeDecryptor for Luks {
+    fn size(&self) -> u64 {
+        self.size
+    }
+
+    fn read_sectors(
+        &self,
+        inner: &mut dyn ImageReader,
+        offset: u64,
+        buf: &mut [u8],
+    ) -> io::Result<()> {
+        read_at(inner, self.data_offset + offset, buf)?;
+        // LUKS2 counts IVs in units of its sector size (dm-crypt iv_large_sectors)
+        let first = offset / self.sector_size as u64 + self.iv_tweak;
+        for (i, sector) in buf.chunks_exact_mut(self.sector_size).enumerate() {
+            self.cipher.decrypt(first + i as u64, sector);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::UnlockedReader;
+    use super::*;
+    use crate::analyze::VolumeHeaderKind;
+    use std::io::{Cursor, Read};
+
+    const KEY_LEN: usize = 32;
+    const STRIPES: usize = 4;
+
+    /// Keyslot material (AF-split and encrypted) for `volume_key`
+    fn keyslot_material(volume_key: &[u8], slot_key: &[u8]) -> Vec<u8> {
+        let mut material: Vec<u8> = (0..KEY_LEN * (STRIPES - 1))
+            .map(|i| (i * 31 % 256) as u8)
+            .collect();
+        let mut d = vec![0u8; KEY_LEN];
+        for stripe in material.chunks_exact(KEY_LEN) {
+            for (a, b) in d.iter_mut().zip(stripe) {
+                *a ^= b;
+            }
+            d = af_diffuse(&d, LuksHash::Sha256);
+        }
+        material.extend(d.iter().zip(volume_key).map(|(a, b)| a ^ b));
+        material.resize(SECTOR, 0);
+        let cipher = SectorCipher::xts(slot_key).unwrap();
+        cipher.encrypt(0, &mut material);
+        material
+    }
+
+    fn image_with(
+        header: &[u8],
+        slot_at: usize,
+        material: &[u8],
+        data_at: usize,
+        data: &[u8],
+    ) -> Vec<u8> {
+        let mut image = vec![0u8; data_at + data.len()];
+        image[..header.len()].copy_from_slice(header);
+        image[slot_at..slot_at + material.len()].copy_from_slice(material);
+        image[data_at..].copy_from_slice(data);
+        image
+    }
+
+    fn encrypted_data(volume_key: &[u8], plain: &[u8]) -> Vec<u8> {
+        let cipher = SectorCipher::xts(volume_key).unwrap();
+        let mut data = plain.to_vec();
+        for (i, sector) in data.chunks_exact_mut(SECTOR).enumerate() {
+            cipher.encrypt(i as u64, sector);
+        }
+        data
+    }
+
+    fn read_all(image: Vec<u8>, passphrase: &str) -> Result<Vec<u8>> {
+        let volume = EncryptedVolume {
+            kind: VolumeHeaderKind::Luks,
+            offset: 0,
+            size: image.len() as u64,
+            detail: String::new(),
+        };
+        let mut reader = UnlockedReader::new(
+            Box::new(Cursor::new(image)),
+            volume,
+            &UnlockKey::Secret(passphrase.to_string()),
+        )?;
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out)?;
+        Ok(out)
+    }
+
+    #[test]
+    fn test_luks1_and_luks2_unlock() {
+        let volume_key: Vec<u8> = (0..KEY_LEN as u8).collect();
+        let plain: Vec<u8> = (0..SECTOR * 8).map(|i| (i % 199) as u8).collect();
+        let data = encrypted_data(&volume_key, &plain);
+        let salt = [9u8; 32];
+
+        // LUKS1: keyslot 0 at sector 8, payload at sector 16
+        let mut slot_key = vec![0u8; KEY_LEN];
+        LuksHash::Sha256.pbkdf2(b"hunter2", &salt, 10, &mut slot_key);
+        let mut header = vec![0u8; 592];
+        header[..6].copy_from_slice(b"LUKS\xba\xbe");
+        header[6..8].copy_from_slice(&1u16.to_be_bytes());
+        header[8..11].copy_from_slice(b"aes");
+        header[40..51].copy_from_slice(b"xts-plain64");
+        header[72..78].copy_from_slice(b"sha256");
+        header[104..108].copy_from_slice(&16u32.to_be_bytes());
+        header[108..112].copy_from_slice(&(KEY_LEN as u32).to_be_bytes());
+        LuksHash::Sha256.pbkdf2(&volume_key, &salt, 10, &mut header[112..132]);
+        header[132..164].copy_from_slice(&salt);
+        header[164..168].copy_from_slice(&10u32.to_be_bytes());
+        header[208..212].copy_from_slice(&LUKS1_KEYSLOT_ACTIVE.to_be_bytes());
+        header[212..216].copy_from_slice(&10u32.to_be_bytes());
+        header[216..248].copy_from_slice(&salt);
+        header[248..252].copy_from_slice(&8u32.to_be_bytes());
+        header[252..256].copy_from_slice(&(STRIPES as u32).to_be_bytes());
+        let material = keyslot_material(&volume_key, &slot_key);
+        let image = image_with(&header, 8 * SECTOR, &material, 16 * SECTOR, &data);
+
+        assert_eq!(read_all(image.clone(), "hunter2").unwrap(), plain);
+        assert!(read_all(image, "hunter3").is_err());
+
+        // LUKS2: same key material behind a PBKDF2 keyslot and JSON metadata
+        let b64 = |b: &[u8]| base64::engine::general_purpose::STANDARD.encode(b);
+        let mut digest = [0u8; 32];
+        LuksHash::Sha256.pbkdf2(&volume_key, &salt, 10, &mut digest);
+        let metadata = serde_json::json!({
+            "keyslots": {"0": {
+                "type": "luks2", "key_size": KEY_LEN,
+                "af": {"type": "luks1", "stripes": STRIPES, "hash": "sha256"},
+                "area": {"type": "raw", "offset": "16384", "size": "4096",
+                         "encryption": "aes-xts-plain64", "key_size": KEY_LEN},
+                "kdf": {"type": "pbkdf2", "hash": "sha256", "iterations": 10, "salt": b64(&salt)}
+            }},
+            "segments": {"0": {"type": "crypt", "offset": "20480", "size": "dynamic",
+                               "iv_tweak": "0", "encryption": "aes-xts-plain64", "sector_size": 512}},
+            "digests": {"0": {"type": "pbkdf2", "keyslots": ["0"], "segments": ["0"], "hash": "sha256",
+                              "iterations": 10, "salt": b64(&salt), "digest": b64(&digest)}}
+        });
+        let mut header = vec![0u8; 12288];
+        header[..6].copy_from_slice(b"LUKS\xba\xbe");
+        header[6..8].copy_from_slice(&2u16.to_be_bytes());
+        header[8..16].copy_from_slice(&12288u64.to_be_bytes());
+        let json = metadata.to_string();
+        header[4096..4096 + json.len()].copy_from_slice(json.as_bytes());
+        let image = image_with(&header, 16384, &material, 20480, &data);
+
+        assert_eq!(read_all(image, "hunter2").unwrap(), plain);
+    }
+
+    #[test]
+    fn test_bad_luks_headers_are_rejected() {
+        let volume_key: Vec<u8> = (0..KEY_LEN as u8).collect();
+        let plain = vec![0u8; SECTOR * 8];
+        let data = encrypted_data(&volume_key, &plain);
+        let salt = [9u8; 32];
+        let mut slot_key = vec![0u8; KEY_LEN];
+        LuksHash::Sha256.pbkdf2(b"hunter2", &salt, 10, &mut slot_key);
+        let material = keyslot_material(&volume_key, &slot_key);
+
+        let b64 = |b: &[u8]| base64::engine::general_purpose::STANDARD.encode(b);
+        let mut digest = [0u8; 32];
+        LuksHash::Sha256.pbkdf2(&volume_key, &salt, 10, &mut digest);
+        let luks2 = |edit: &dyn Fn(&mut Value)| {
+            let mut metadata = serde_json::json!({
+                "keyslots": {"0": {
+                    "type": "luks2", "key_size": KEY_LEN,
+                    "af": {"type": "luks1", "stripes": STRIPES, "hash": "sha256"},
+                    "area": {"type": "raw", "offset": "16384", "size": "4096",
+                             "encryption": "aes-xts-plain64", "key_size": KEY_LEN},
+                    "kdf": {"type": "pbkdf2", "hash": "sha256", "iterations": 10, "salt": b64(&salt)}
+                }},
+                "segments": {"0": {"type": "crypt", "offset": "20480", "size": "dynamic",
+                                   "iv_tweak": "0", "encryption": "aes-xts-plain64", "sector_size": 512}},
+                "digests": {"0": {"type": "pbkdf2", "keyslots": ["0"], "segments": ["0"], "hash": "sha256",
+                                  "iterations": 10, "salt": b64(&salt), "digest": b64(&digest)}}
+            });
+            edit(&mut metadata);
+            let mut header = vec![0u8; 12288];
+            header[..6].copy_from_slice(b"LUKS\xba\xbe");
+            header[6..8].copy_from_slice(&2u16.to_be_bytes());
+            header[8..16].copy_from_slice(&12288u64.to_be_bytes());
+            let json = metadata.to_string();
+            header[4096..4096 + json.len()].copy_from_slice(json.as_bytes());
+            read_all(
+                image_with(&header, 16384, &material, 20480, &data),
+                "hunter2",
+            )
+        };
+
+        assert_eq!(luks2(&|_| {}).unwrap(), plain);
+        for (field, value) in [
+            ("/keyslots/0/area/key_size", serde_json::json!(0)),
+            ("/keyslots/0/area/key_size", serde_json::json!(1u64 << 62)),
+            ("/keyslots/0/af/stripes", serde_json::json!(0)),
+            ("/keyslots/0/af/stripes", serde_json::json!(1u64 << 40)),
+            ("/segments/0/sector_size", serde_json::json!(0)),
+            ("/segments/0/sector_size", serde_json::json!(1000)),
+            ("/segments/0/sector_size", serde_json::json!(8192)),
+        ] {
+            let result = luks2(&|m| *m.pointer_mut(field).unwrap() = value.clone());
+            assert!(result.is_err(), "{} = {} was accepted", field, value);
+        }
+        let argon2 = luks2(&|m| {
+            m["keyslots"]["0"]["kdf"] = serde_json::json!({
+                "type": "argon2id", "time": 1, "memory": 1u64 << 40, "cpus": 1, "salt": b64(&salt)
+            })
+        });
+        assert!(argon2.is_err());
+
+        // LUKS1 with a zero key size
+        let mut header = vec![0u8; 592];
+        header[..6].copy_from_slice(b"LUKS\xba\xbe");
+        header[6..8].copy_from_slice(&1u16.to_be_bytes());
+        header[8..11].copy_from_slice(b"aes");
+        header[40..51].copy_from_slice(b"xts-plain64");
+        header[72..78].copy_from_slice(b"sha256");
+        header[104..108].copy_from_slice(&16u32.to_be_bytes());
+        header[164..168].copy_from_slice(&10u32.to_be_bytes());
+        header[208..212].copy_from_slice(&LUKS1_KEYSLOT_ACTIVE.to_be_bytes());
+        header[212..216].copy_from_slice(&10u32.to_be_bytes());
+        header[248..252].copy_from_slice(&8u32.to_be_bytes());
+        header[252..256].copy_from_slice(&(STRIPES as u32).to_be_bytes());
+        let image = image_with(&header, 8 * SECTOR, &material, 16 * SECTOR, &data);
+        assert!(read_all(image, "hunter2").is_err());
+    }
+}
@@ -0,0 +1,500 @@
+//! Volume Shadow Copy (VSS) snapshots inside NTFS volumes
+//!
+//! Windows keeps earlier versions of a volume as copy-on-write stores in
+//! `System Volume Information`: before a 16 KiB block of the live volume is
+//! overwritten, its old contents are copied into the newest store. The
+//! volume header at offset `0x1E00` points to a catalog listing each store
+//! and where its block list lives.
+//!
+//! A snapshot is read block by block: the first store, from that snapshot
+//! forward to the newest, holding a copy of the block has its contents at
+//! snapshot time; blocks no store holds are unchanged since and read from
+//! the live volume. [`ShadowCopyReader`] presents a snapshot as a whole
+//! volume, so it can be copied out with `convert --snapshot` and mounted
+//! like any other volume image, or have its files listed and read with
+//! [`super::ntfs`] for `index --snapshots`.
+
+use std::collections::HashMap;
+use std::io::{self, Read, Seek, SeekFrom};
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::partition::guid_string;
+use crate::core::filetime_to_utc;
+
+/// `{3808876B-C176-4E48-B7AE-04046E6CC752}`, the VSS identifier
+const VSS_IDENTIFIER: [u8; 16] = [
+    0x6B, 0x87, 0x08, 0x38, 0x76, 0xC1, 0x48, 0x4E, 0xB7, 0xAE, 0x04, 0x04, 0x6E, 0x6C, 0xC7, 0x52,
+];
+
+/// Offset of the volume header from the start of the volume
+const VOLUME_HEADER_OFFSET: u64 = 0x1E00;
+
+/// Catalog and block list blocks, and the unit stores copy
+const BLOCK_SIZE: u64 = 0x4000;
+
+/// Header at the start of every catalog and block list block
+const BLOCK_HEADER_SIZE: usize = 128;
+
+const RECORD_VOLUME_HEADER: u32 = 1;
+const RECORD_CATALOG: u32 = 2;
+const RECORD_BLOCK_LIST: u32 = 3;
+
+const CATALOG_ENTRY_SIZE: usize = 128;
+const BLOCK_DESCRIPTOR_SIZE: usize = 32;
+
+/// Descriptor flag: the block is found at another original offset
+const FLAG_FORWARDER: u32 = 0x1;
+/// Descriptor flag: only the sectors in the allocation bitmap are stored
+const FLAG_OVERLAY: u32 = 0x2;
+/// Descriptor flag: the descriptor is unused
+const FLAG_NOT_USED: u32 = 0x4;
+
+/// Catalog and block list chains are not followed past this many blocks
+const MAX_CHAIN_BLOCKS: usize = 1 << 20;
+
+/// One snapshot found in a volume
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShadowCopy {
+    /// 1-based, oldest first
+    pub number: usize,
+    pub store_id: String,
+    pub created: Option<DateTime<Utc>>,
+    /// Size of the volume when the snapshot was taken
+    pub volume_size: u64,
+    /// Offsets of the store's block list and header, from the volume start
+    pub block_list_offset: u64,
+    pub store_header_offset: u64,
+}
+
+/// List the shadow copies of the volume starting at `volume_offset`,
+/// oldest first. Returns an empty list for volumes without VSS.
+pub fn list_shadow_copies<R: Read + Seek>(
+    reader: &mut R,
+    volume_offset: u64,
+) -> Result<Vec<ShadowCopy>> {
+    let mut header = [0u8; BLOCK_HEADER_SIZE];
+    reader.seek(SeekFrom::Start(volume_offset + VOLUME_HEADER_OFFSET))?;
+    if reader.read_exact(&mut header).is_err()
+        || header[..16] != VSS_IDENTIFIER
+        || u32_at(&header, 0x14) != RECORD_VOLUME_HEADER
+    {
+        return Ok(Vec::new());
+    }
+    let catalog_offset = u64_at(&header, 0x30);
+    if catalog_offset == 0 {
+        return Ok(Vec::new());
+    }
+
+    // Type 2 entries describe a store, type 3 entries say where it lives
+    let mut info: HashMap<String, (u64, Option<DateTime<Utc>>)> = HashMap::new();
+    let mut locations: Vec<(String, u64, u64)> = Vec::new();
+    let mut block = vec![0u8; BLOCK_SIZE as usize];
+    let mut next = catalog_offset;
+    for _ in 0..MAX_CHAIN_BLOCKS {
+        read_record_block(reader, volume_offset, next, RECORD_CATALOG, &mut block)
+            .context("Failed to read VSS catalog")?;
+        for entry in block[BLOCK_HEADER_SIZE..].chunks_exact(CATALOG_ENTRY_SIZE) {
+            match u64_at(entry, 0) {
+                2 => {
+                    info.insert(
+                        guid_string(&entry[0x10..0x20]),
+                        (u64_at(entry, 0x08), filetime_to_utc(u64_at(entry, 0x30))),
+                    );
+                }
+                3 => locations.push((
+                    guid_string(&entry[0x10..0x20]),
+                    u64_at(entry, 0x08),
+                    u64_at(entry, 0x20),
+                )),
+                _ => {}
+            }
+        }
+        next = u64_at(&block, 0x28);
+        if next == 0 {
+            break;
+        }
+    }
+
+    let mut copies: Vec<ShadowCopy> = locations
+        .into_iter()
+        .filter_map(|(store_id, block_list_offset, store_header_offset)| {
+            let &(volume_size, created) = info.get(&store_id)?;
+            Some(ShadowCopy {
+                number: 0,
+                store_id,
+                created,
+                volume_size,
+                block_list_offset,
+                store_header_offset,
+            })
+        })
+        .collect();
+    copies.sort_by_key(|c| c.created);
+    for (i, copy) in copies.iter_mut().enumerate() {
+        copy.number = i + 1;
+    }
+    Ok(copies)
+}
+
+/// Where a store keeps its copy of one block
+#[derive(Debug, Clone, Copy)]
+struct Descriptor {
+    /// Forwarders: original offset to continue the lookup at. Otherwise
+    /// the store data offset from the volume start.
+    target: u64,
+    /// Sectors held, for overlays
+    bitmap: u32,
+}
+
+#[derive(Debug, Default)]
+struct Store {
+    blocks: HashMap<u64, Descriptor>,
+    forwarders: HashMap<u64, Descriptor>,
+    overlays: HashMap<u64, Descriptor>,
+}
+
+/// A snapshot of a volume, read as a whole volume
+pub struct ShadowCopyReader<R> {
+    inner: R,
+    volume_offset: u64,
+    size: u64,
+    /// Stores from the snapshot's to the newest
+    stores: Vec<Store>,
+    pos: u64,
+    /// Last block read and its contents
+    cached: Option<u64>,
+    data: Vec<u8>,
+}
+
+impl<R: Read + Seek> ShadowCopyReader<R> {
+    /// Read snapshot `number` (see [`list_shadow_copies`]) of the volume at
+    /// `volume_offset` in `inner`
+    pub fn open(mut inner: R, volume_offset: u64, number: usize) -> Result<Self> {
+        let copies = list_shadow_copies(&mut inner, volume_offset)?;
+        let Some(snapshot) = copies.iter().find(|c| c.number == number) else {
+            bail!(
+                "Snapshot {} not found ({} shadow copies in the volume)",
+                number,
+                copies.len()
+            );
+        };
+        let size = snapshot.volume_size;
+        let stores = copies[number - 1..]
+            .iter()
+            .map(|copy| read_store(&mut inner, volume_offset, copy.block_list_offset))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            inner,
+            volume_offset,
+            size,
+            stores,
+            pos: 0,
+            cached: None,
+            data: vec![0u8; BLOCK_SIZE as usize],
+        })
+    }
+
+    /// Fill `buf` with the snapshot's block at `block` (a multiple of the
+    /// block size), as of store `from` onwards
+    fn read_block(&mut self, from: usize, block: u64, buf: &mut [u8]) -> io::Result<()> {
+        let mut store = from;
+        let mut original = block;
+        while store < self.stores.len() {
+            let current = &self.stores[store];
+            if let Some(d) = current.blocks.get(&original).copied() {
+                self.read_volume(d.target, buf)?;
+                return self.apply_overlays(from, store, block, buf);
+            }
+            if let Some(d) = current.forwarders.get(&original) {
+                original = d.target;
+            }
+            store += 1;
+        }
+        self.read_volume(original, buf)?;
+        self.apply_overlays(from, self.stores.len(), block, buf)
+    }
+
+    /// Lay the sectors overlays in stores `from..until` hold for `block`
+    /// over `buf`, nearest the snapshot last
+    fn apply_overlays(
+        &mut self,
+        from: usize,
+        until: usize,
+        block: u64,
+        buf: &mut [u8],
+    ) -> io::Result<()> {
+        let sector = BLOCK_SIZE as usize / 32;
+        for store in (from..until).rev() {
+            let Some(d) = self.stores[store].overlays.get(&block).copied() else {
+                continue;
+            };
+            for bit in (0..32).filter(|bit| d.bitmap & (1 << bit) != 0) {
+                let at = bit * sector;
+                self.read_volume(d.target + at as u64, &mut buf[at..at + sector])?;
+            }
+        }
+        Ok(())
+    }
+
+    fn read_volume(&mut self, offset: u64, buf: &mut [u8]) -> io::Result<()> {
+        self.inner
+            .seek(SeekFrom::Start(self.volume_offset + offset))?;
+        match self.inner.read_exact(buf) {
+            // The live volume may have shrunk since the snapshot
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                buf.fill(0);
+                Ok(())
+            }
+            result => result,
+        }
+    }
+}
+
+impl<R: Read + Seek> Read for ShadowCopyReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos >= self.size || buf.is_empty() {
+            return Ok(0);
+        }
+        let block = self.pos - self.pos % BLOCK_SIZE;
+        let within = (self.pos - block) as usize;
+        let len = buf
+            .len()
+            .min(BLOCK_SIZE as usize - within)
+            .min((self.size - self.pos) as usize);
+        if self.cached != Some(block) {
+            let mut data = std::mem::take(&mut self.data);
+            self.cached = None;
+            let result = self.read_block(0, block, &mut data);
+            self.data = data;
+            result?;
+            self.cached = Some(block);
+        }
+        buf[..len].copy_from_slice(&self.data[within..within + len]);
+        self.pos += len as u64;
+        Ok(len)
+    }
+}
+
+impl<R: Read + Seek> Seek for ShadowCopyReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(p) => Some(p),
+            SeekFrom::End(d) => self.size.checked_add_signed(d),
+            SeekFrom::Current(d) => self.pos.checked_add_signed(d),
+        };
+        self.pos = target.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "seek before start of snapshot")
+        })?;
+        Ok(self.pos)
+    }
+}
+
+/// Load a store's block list
+fn read_store<R: Read + Seek>(reader: &mut R, volume_offset: u64, offset: u64) -> Result<Store> {
+    let mut store = Store::default();
+    let mut block = vec![0u8; BLOCK_SIZE as usize];
+    let mut next = offset;
+    for _ in 0..MAX_CHAIN_BLOCKS {
+        if next == 0 {
+            break;
+        }
+        read_record_block(reader, volume_offset, next, RECORD_BLOCK_LIST, &mut block)
+            .context("Failed to read VSS store block list")?;
+        for entry in block[BLOCK_HEADER_SIZE..].chunks_exact(BLOCK_DESCRIPTOR_SIZE) {
+            let original = u64_at(entry, 0x00);
+            let relative = u64_at(entry, 0x08);
+            let data = u64_at(entry, 0x10);
+            let flags = u32_at(entry, 0x18);
+            let bitmap = u32_at(entry, 0x1C);
+            if flags & FLAG_NOT_USED != 0 || (original == 0 && data == 0 && flags == 0) {
+                continue;
+            }
+            let map = if flags & FLAG_FORWARDER != 0 {
+                &mut store.forwarders
+            } else if flags & FLAG_OVERLAY != 0 {
+                &mut store.overlays
+            } else {
+                &mut store.blocks
+            };
+            let target = if flags & FLAG_FORWARDER != 0 {
+                relative
+            } else {
+                data
+            };
+            // Overlays of one block accumulate their sectors
+            map.entry(original)
+                .and_modify(|d| d.bitmap |= bitmap)
+                .or_insert(Descriptor { target, bitmap });
+        }
+        next = u64_at(&block, 0x28);
+    }
+    Ok(store)
+}
+
+/// Read the 16 KiB block at `offset` in the volume and check its record type
+fn read_record_block<R: Read + Seek>(
+    reader: &mut R,
+    volume_offset: u64,
+    offset: u64,
+    record_type: u32,
+    block: &mut [u8],
+) -> Result<()> {
+    reader.seek(SeekFrom::Start(volume_offset + offset))?;
+    reader.read_exact(block)?;
+    if block[..16] != VSS_IDENTIFIER || u32_at(block, 0x14) != record_type {
+        bail!(
+            "no VSS record of type {} at offset {:#x}",
+            record_type,
+            offset
+        );
+    }
+    Ok(())
+}
+
+fn u32_at(b: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(b[at..at + 4].try_into().expect("4 bytes"))
+}
+
+fn u64_at(b: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(b[at..at + 8].try_into().expect("8 bytes"))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const VOLUME_BLOCKS: u64 = 16;
+    pub(crate) const CATALOG: u64 = 32 * BLOCK_SIZE;
+    const STORE_BASE: u64 = 40 * BLOCK_SIZE;
+
+    fn record_header(volume: &mut [u8], offset: u64, record_type: u32, next: u64) {
+        let at = offset as usize;
+        volume[at..at + 16].copy_from_slice(&VSS_IDENTIFIER);
+        volume[at + 0x10..at + 0x14].copy_from_slice(&1u32.to_le_bytes());
+        volume[at + 0x14..at + 0x18].copy_from_slice(&record_type.to_le_bytes());
+        volume[at + 0x28..at + 0x30].copy_from_slice(&next.to_le_bytes());
+    }
+
+    fn put_u64(volume: &mut [u8], at: u64, value: u64) {
+        volume[at as usize..at as usize + 8].copy_from_slice(&value.to_le_bytes());
+    }
+
+    /// Block number, fill byte, descriptor flags and bitmap
+    pub(crate) type SavedBlock = (u64, u8, u32, u32);
+
+    /// Store id byte, creation FILETIME, block list offset and saved blocks
+    pub(crate) type StoreSpec<'a> = (u8, u64, u64, &'a [SavedBlock]);
+
+    /// A volume whose live blocks hold 0xCC, with two stores: the older
+    /// saved block 2 (0x11) and block 5 (0x55); the newer saved block 2
+    /// again (0x22) and half of block 7 as an overlay (0x77)
+    fn volume() -> Vec<u8> {
+        let mut v = vec![0u8; 64 * BLOCK_SIZE as usize];
+        v[..(VOLUME_BLOCKS * BLOCK_SIZE) as usize].fill(0xCC);
+        v[VOLUME_HEADER_OFFSET as usize..VOLUME_HEADER_OFFSET as usize + 0x200].fill(0);
+        add_stores(
+            &mut v,
+            VOLUME_BLOCKS * BLOCK_SIZE,
+            &[
+                (
+                    0xA1,
+                    132_000_000_000_000_000,
+                    STORE_BASE,
+                    &[(2, 0x11, 0, 0), (5, 0x55, 0, 0)],
+                ),
+                (
+                    0xB2,
+                    133_000_000_000_000_000,
+                    STORE_BASE + 8 * BLOCK_SIZE,
+                    &[(2, 0x22, 0, 0), (7, 0x77, FLAG_OVERLAY, 0x0000_FFFF)],
+                ),
+            ],
+        );
+        v
+    }
+
+    /// Write a VSS volume header, a catalog at [`CATALOG`] and `stores`
+    /// (oldest first) of a `volume_size` byte volume into `v`, which must
+    /// be at least 64 blocks long
+    pub(crate) fn add_stores(v: &mut [u8], volume_size: u64, stores: &[StoreSpec]) {
+        record_header(v, VOLUME_HEADER_OFFSET, RECORD_VOLUME_HEADER, 0);
+        put_u64(v, VOLUME_HEADER_OFFSET + 0x30, CATALOG);
+
+        record_header(v, CATALOG, RECORD_CATALOG, 0);
+        let mut entry = CATALOG + BLOCK_HEADER_SIZE as u64;
+        // Newest first, as Windows writes them
+        for (id, created, list, saved) in stores.iter().rev() {
+            put_u64(v, entry, 2);
+            put_u64(v, entry + 0x08, volume_size);
+            v[(entry + 0x10) as usize] = *id;
+            put_u64(v, entry + 0x30, *created);
+            entry += CATALOG_ENTRY_SIZE as u64;
+            put_u64(v, entry, 3);
+            put_u64(v, entry + 0x08, *list);
+            v[(entry + 0x10) as usize] = *id;
+            entry += CATALOG_ENTRY_SIZE as u64;
+
+            record_header(v, *list, RECORD_BLOCK_LIST, 0);
+            let mut desc = list + BLOCK_HEADER_SIZE as u64;
+            for (n, (block, fill, flags, bitmap)) in saved.iter().enumerate() {
+                let data = list + (n as u64 + 1) * BLOCK_SIZE;
+                v[data as usize..(data + BLOCK_SIZE) as usize].fill(*fill);
+                put_u64(v, desc, block * BLOCK_SIZE);
+                put_u64(v, desc + 0x10, data);
+                v[(desc + 0x18) as usize..(desc + 0x1C) as usize]
+                    .copy_from_slice(&flags.to_le_bytes());
+                v[(desc + 0x1C) as usize..(desc + 0x20) as usize]
+                    .copy_from_slice(&bitmap.to_le_bytes());
+                desc += BLOCK_DESCRIPTOR_SIZE as u64;
+            }
+        }
+    }
+
+    fn block(reader: &mut impl Read, n: u64) -> Vec<u8> {
+        let mut all = Vec::new();
+        reader.read_to_end(&mut all).unwrap();
+        all[(n * BLOCK_SIZE) as usize..((n + 1) * BLOCK_SIZE) as usize].to_vec()
+    }
+
+    #[test]
+    fn test_list_and_read_snapshots() {
+        let mut disk = vec![0u8; 2048];
+        disk.extend(volume());
+        let mut cursor = Cursor::new(disk);
+
+        let copies = list_shadow_copies(&mut cursor, 2048).unwrap();
+        assert_eq!(copies.len(), 2);
+        assert_eq!(copies[0].number, 1);
+        assert!(copies[0].store_id.starts_with("000000A1-"));
+        assert!(copies[0].created < copies[1].created);
+        assert_eq!(copies[0].volume_size, VOLUME_BLOCKS * BLOCK_SIZE);
+        assert!(list_shadow_copies(&mut cursor, 0).unwrap().is_empty());
+
+        let mut oldest = ShadowCopyReader::open(cursor.clone(), 2048, 1).unwrap();
+        assert_eq!(block(&mut oldest, 2), vec![0x11; BLOCK_SIZE as usize]);
+        oldest.seek(SeekFrom::Start(0)).unwrap();
+        assert_eq!(block(&mut oldest, 5), vec![0x55; BLOCK_SIZE as usize]);
+        oldest.seek(SeekFrom::Start(0)).unwrap();
+        let overlaid = block(&mut oldest, 7);
+        let half = BLOCK_SIZE as usize / 2;
+        assert!(overlaid[..half].iter().all(|&b| b == 0x77));
+        assert!(overlaid[half..].iter().all(|&b| b == 0xCC));
+        oldest.seek(SeekFrom::Start(0)).unwrap();
+        assert_eq!(block(&mut oldest, 3), vec![0xCC; BLOCK_SIZE as usize]);
+
+        let mut newest = ShadowCopyReader::open(cursor.clone(), 2048, 2).unwrap();
+        assert_eq!(block(&mut newest, 2), vec![0x22; BLOCK_SIZE as usize]);
+        newest.seek(SeekFrom::Start(0)).unwrap();
+        assert_eq!(block(&mut newest, 5), vec![0xCC; BLOCK_SIZE as usize]);
+        assert_eq!(
+            newest.seek(SeekFrom::End(0)).unwrap(),
+            VOLUME_BLOCKS * BLOCK_SIZE
+        );
+
+        assert!(ShadowCopyReader::open(cursor, 2048, 3).is_err());
+    }
+}
@@ -0,0 +1,159 @@
+//! Error taxonomy for library consumers
+//!
+//! The main entry points (indexing and exporting through
+//! [`DrillEngine`](crate::core::DrillEngine), [`Exporter`](crate::export::Exporter),
+//! [`Carver`](crate::carve::Carver), sector-tracked reads and image
+//! conversion) return [`DrillError`], so callers can match on the kind of
+//! failure instead of string-parsing `anyhow` chains.
+//!
+//! Inside the crate errors stay `anyhow` with context. A failure of a known
+//! kind is raised as a `DrillError` somewhere in the chain, and converting
+//! the chain at the API boundary finds it again; otherwise the chain is
+//! classified by the I/O error in it, if any.
+
+use std::io;
+use std::path::PathBuf;
+
+/// Result of a public API call
+pub type DrillResult<T> = std::result::Result<T, DrillError>;
+
+/// What went wrong, for callers that react differently to each kind
+#[derive(Debug, thiserror::Error)]
+pub enum DrillError {
+    /// An I/O error that may go away when retried (interrupted, timed out)
+    #[error("{message}")]
+    IoTransient {
+        kind: io::ErrorKind,
+        message: String,
+    },
+    /// An I/O error retrying won't fix (missing file, permission denied, ...)
+    #[error("{message}")]
+    IoPermanent {
+        kind: io::ErrorKind,
+        message: String,
+    },
+    /// Part of `path` could not be read, even block by block
+    #[error("{} has unreadable sectors: {detail}", path.display())]
+    BadSector { path: PathBuf, detail: String },
+    /// `path` is not valid as the format it was read as
+    #[error("{} is not a valid {format}: {message}", path.display())]
+    FormatParse {
+        path: PathBuf,
+        format: &'static str,
+        message: String,
+    },
+    /// The operation was stopped before it finished
+    #[error("Cancelled")]
+    Cancelled,
+    /// A copy still differs from its source after every attempt
+    #[error(
+        "Hash mismatch for {} after {attempts} attempt(s): source={expected}, dest={actual}",
+        path.display()
+    )]
+    VerificationFailed {
+        path: PathBuf,
+        attempts: usize,
+        expected: String,
+        actual: String,
+    },
+    /// Arguments or options the operation can't work with
+    #[error("{0}")]
+    InvalidInput(String),
+    /// Any other failure, with its context chain
+    #[error(transparent)]
+    Other(anyhow::Error),
+}
+
+impl DrillError {
+    /// Whether trying the same operation again may succeed
+    pub fn is_transient(&self) -> bool {
+        matches!(self, DrillError::IoTransient { .. })
+    }
+
+    fn io(kind: io::ErrorKind, message: String) -> Self {
+        if is_transient_io(kind) {
+            DrillError::IoTransient { kind, message }
+        } else {
+            DrillError::IoPermanent { kind, message }
+        }
+    }
+}
+
+/// Whether an I/O error of `kind` is worth retrying
+pub fn is_transient_io(kind: io::ErrorKind) -> bool {
+    matches!(
+        kind,
+        io::ErrorKind::Interrupted | io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+    )
+}
+
+impl From<anyhow::Error> for DrillError {
+    fn from(error: anyhow::Error) -> Self {
+        // A kind raised further down; `Other` carries no kind of its own
+        if error
+            .downcast_ref::<DrillError>()
+            .is_some_and(|e| !matches!(e, DrillError::Other(_)))
+        {
+            return error
+                .downcast::<DrillError>()
+                .expect("downcast_ref found a DrillError");
+        }
+        if let Some(io) = error.chain().find_map(|e| e.downcast_ref::<io::Error>()) {
+            return DrillError::io(io.kind(), format!("{:#}", error));
+        }
+        if error
+            .chain()
+            .filter_map(|e| e.downcast_ref::<tokio::task::JoinError>())
+            .any(|e| e.is_cancelled())
+        {
+            return DrillError::Cancelled;
+        }
+        DrillError::Other(error)
+    }
+}
+
+impl From<io::Error> for DrillError {
+    fn from(error: io::Error) -> Self {
+        DrillError::io(error.kind(), error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_kinds_are_found_through_context() {
+        let typed: anyhow::Result<()> = Err(DrillError::VerificationFailed {
+            path: PathBuf::from("a.jpg"),
+            attempts: 3,
+            expected: "aa".into(),
+            actual: "bb".into(),
+        }
+        .into());
+        let error = DrillError::from(typed.context("Export failed").unwrap_err());
+        assert!(matches!(
+            error,
+            DrillError::VerificationFailed { attempts: 3, .. }
+        ));
+
+        let io: anyhow::Result<()> = Err(io::Error::from(io::ErrorKind::TimedOut).into());
+        let error = DrillError::from(io.context("Failed to read a.jpg").unwrap_err());
+        assert!(error.is_transient());
+        assert!(error.to_string().starts_with("Failed to read a.jpg: "));
+
+        let error = DrillError::from(io::Error::from(io::ErrorKind::PermissionDenied));
+        assert!(matches!(
+            error,
+            DrillError::IoPermanent {
+                kind: io::ErrorKind::PermissionDenied,
+                ..
+            }
+        ));
+
+        let error = DrillError::from(anyhow::anyhow!("something else"));
+        assert!(matches!(error, DrillError::Other(_)));
+        assert_eq!(error.to_string(), "something else");
+    }
+}
@@ -0,0 +1,131 @@
+//! Library-wide progress events
+//!
+//! Indexing, hashing, export, bad-sector reads, carving, the swarm and
+//! healing publish typed [`Event`]s on one process-wide broadcast channel.
+//! Front ends (CLI, TUI, GUI, JSON-RPC, metrics) call [`subscribe`] and react
+//! to whatever they care about, instead of threading a callback of their own
+//! shape through every call. Callbacks such as [`crate::export::ExportFileEvents`] still exist
+//! for callers that need the events of one operation only.
+//!
+//! Emitting is cheap when nobody listens, and a subscriber that falls more
+//! than [`EVENT_BUFFER`] events behind misses the oldest ones
+//! (`RecvError::Lagged`) rather than slowing the work down.
+
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::carve::CarveProgress;
+use crate::core::BadSector;
+use crate::export::ExportFileEvent;
+use crate::swarm::{HealLogEntry, SwarmProgress};
+
+/// Events buffered per subscriber
+pub const EVENT_BUFFER: usize = 1024;
+
+/// Something the library did that a user interface may want to show
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// Indexing `source` has found `files` files of `bytes` bytes so far,
+    /// the latest being `path` (sent every few files, not for each one)
+    Scan {
+        source: PathBuf,
+        files: usize,
+        bytes: u64,
+        path: PathBuf,
+    },
+    /// The BLAKE3 hash of a file or image of `bytes` bytes was computed
+    Hash {
+        path: PathBuf,
+        blake3: String,
+        bytes: u64,
+    },
+    /// Progress of one exported file
+    Export(ExportFileEvent),
+    /// A region of a file or image could not be read
+    BadSector(BadSector),
+    /// Progress of a carve
+    Carve(CarveProgress),
+    /// Progress of a running swarm, sent every
+    /// [`DEFAULT_PROGRESS_INTERVAL`](crate::swarm::DEFAULT_PROGRESS_INTERVAL)
+    /// (or the orchestrator's own interval) and once when it finishes
+    Swarm(SwarmProgress),
+    /// A swarm agent failure was retried, healed or given up on
+    Heal(HealLogEntry),
+}
+
+fn bus() -> &'static broadcast::Sender<Event> {
+    static BUS: OnceLock<broadcast::Sender<Event>> = OnceLock::new();
+    BUS.get_or_init(|| broadcast::channel(EVENT_BUFFER).0)
+}
+
+/// Publish `event` to every current subscriber
+pub fn emit(event: Event) {
+    // No subscribers is fine; nothing is showing progress
+    let _ = bus().send(event);
+}
+
+/// Whether anyone is subscribed, so costly events can be skipped
+pub fn is_observed() -> bool {
+    bus().receiver_count() > 0
+}
+
+/// Events emitted from now on
+pub fn subscribe() -> broadcast::Receiver<Event> {
+    bus().subscribe()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subscribers_receive_events_as_tagged_json() {
+        let mut events = subscribe();
+        assert!(is_observed());
+
+        let path = PathBuf::from("/events-test/a.jpg");
+        emit(Event::Hash {
+            path: path.clone(),
+            blake3: "abc".into(),
+            bytes: 3,
+        });
+        emit(Event::Carve(CarveProgress::ScanComplete {
+            headers_found: 3,
+        }));
+
+        // Other tests publish on the same bus, so look for ours only
+        let mut seen = Vec::new();
+        loop {
+            match events.try_recv() {
+                Ok(event) => seen.push(event),
+                Err(broadcast::error::TryRecvError::Lagged(_)) => continue,
+                Err(_) => break,
+            }
+        }
+        let hash = seen
+            .iter()
+            .find(|e| matches!(e, Event::Hash { path: p, .. } if *p == path))
+            .unwrap();
+        let json = serde_json::to_value(hash).unwrap();
+        assert_eq!(json["event"], "hash");
+        assert_eq!(json["blake3"], "abc");
+
+        let carve = seen
+            .iter()
+            .find(|e| {
+                matches!(
+                    e,
+                    Event::Carve(CarveProgress::ScanComplete { headers_found: 3 })
+                )
+            })
+            .unwrap();
+        let json = serde_json::to_value(carve).unwrap();
+        assert_eq!(json["event"], "carve");
+        assert_eq!(json["stage"], "scan_complete");
+        assert_eq!(json["headers_found"], 3);
+    }
+}
@@ -0,0 +1,541 @@
+//! Export into a single ZIP or tar.gz archive
+//!
+//! Files are streamed into the archive one after another (hashed with blake3
+//! and any extra digests on the way in) and the manifest is added as the last member. Writing one
+//! large file is far faster than many small ones on network shares.
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{Datelike, Timelike, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    get_dest_path, is_quarantined, resolve_collision, run_hooks, ExportError, ExportManifest,
+    ExportOptions, ExportResult, ManifestEntry, MANIFEST_FILE, QUARANTINE_DIR,
+};
+use crate::core::{
+    ArchiveMember, Digests, FileEntry, FileType, HashAlgorithm, MultiHasher, Progress, SnapshotFile,
+};
+use crate::throttle::Throttle;
+
+/// Archive container for `ExportOptions::archive`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ArchiveFormat {
+    Zip,
+    TarGz,
+}
+
+impl ArchiveFormat {
+    /// Pick the format from the archive file name (`.zip`, `.tar.gz`, `.tgz`)
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+        if name.ends_with(".zip") {
+            Some(Self::Zip)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(Self::TarGz)
+        } else {
+            None
+        }
+    }
+}
+
+/// Write `entries` into the archive at `options.dest`.
+///
+/// The archive is written on a blocking thread; progress is forwarded to
+/// the callback as each file is added.
+pub(super) async fn export_to_archive<F>(
+    entries: &[FileEntry],
+    format: ArchiveFormat,
+    options: &ExportOptions,
+    throttle: &Throttle,
+    progress_callback: F,
+) -> Result<ExportResult>
+where
+    F: Fn(Progress) + Send + Sync,
+{
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let entries = entries.to_vec();
+    let options = options.clone();
+    let throttle = throttle.clone();
+    let writer = tokio::task::spawn_blocking(move || {
+        write_archive(&entries, format, &options, &throttle, |progress| {
+            let _ = tx.send(progress);
+        })
+    });
+    while let Some(progress) = rx.recv().await {
+        progress_callback(progress);
+    }
+    writer.await?
+}
+
+fn write_archive(
+    entries: &[FileEntry],
+    format: ArchiveFormat,
+    options: &ExportOptions,
+    throttle: &Throttle,
+    progress: impl Fn(Progress),
+) -> Result<ExportResult> {
+    let archive_path = &options.dest;
+    let mut result = ExportResult::default();
+    let mut manifest = ExportManifest::new(
+        &entries
+            .first()
+            .map(|e| e.path.parent().unwrap_or(&e.path).to_path_buf())
+            .unwrap_or_default(),
+        archive_path,
+    );
+
+    let mut sink = if options.dry_run {
+        None
+    } else {
+        if let Some(parent) = archive_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create destination: {}", parent.display()))?;
+        }
+        let file = File::create(archive_path)
+            .with_context(|| format!("Failed to create archive: {}", archive_path.display()))?;
+        Some(ArchiveSink::new(format, BufWriter::new(file)))
+    };
+
+    // Member names are laid out as if exporting into an empty directory.
+    // Tar headers need sizes up front, so compressed files go in as they are.
+    let layout = ExportOptions {
+        dest: PathBuf::new(),
+        decompress: false,
+        ..options.clone()
+    };
+    let mut claimed = HashSet::from([PathBuf::from(MANIFEST_FILE)]);
+
+    for (i, entry) in entries.iter().enumerate() {
+        progress(Progress {
+            total: entries.len(),
+            completed: i,
+            current_file: entry.path.to_string_lossy().to_string(),
+            bytes_processed: result.total_bytes,
+            errors: result.failed,
+            bad_sectors: 0,
+        });
+
+        let member = match resolve_collision(
+            get_dest_path(entry, &layout),
+            options.on_conflict,
+            &mut claimed,
+            |_| false,
+        ) {
+            Ok(Some(member)) => member_name(&member),
+            Ok(None) => {
+                result.skipped += 1;
+                continue;
+            }
+            Err(e) => {
+                record_failure(&mut result, options, entry, archive_path, e)?;
+                continue;
+            }
+        };
+
+        let Some(sink) = sink.as_mut() else {
+            tracing::info!(
+                "Would archive: {} -> {}:{}",
+                entry.path.display(),
+                archive_path.display(),
+                member
+            );
+            result.successful += 1;
+            result.total_bytes += entry.size;
+            continue;
+        };
+
+        let detections = match run_hooks(entry, &layout) {
+            Ok(detections) => detections,
+            Err(e) => {
+                record_failure(&mut result, options, entry, archive_path, e)?;
+                continue;
+            }
+        };
+        let member = if is_quarantined(&detections) {
+            format!("{}/{}", QUARANTINE_DIR, member)
+        } else {
+            member
+        };
+
+        match sink.add_file(&member, entry, &options.hash_algorithms, throttle) {
+            Ok((bytes, hash, digests)) => {
+                result.successful += 1;
+                result.total_bytes += bytes;
+                if !detections.is_empty() {
+                    result.detections += 1;
+                }
+                manifest.entries.push(ManifestEntry {
+                    source_path: entry.path.to_string_lossy().to_string(),
+                    dest_path: member,
+                    size: bytes,
+                    blake3_hash: hash,
+                    digests,
+                    exported_at: Utc::now().to_rfc3339(),
+                    verified: options.verify_hash,
+                    mirror_path: None,
+                    retry_history: Vec::new(),
+                    compressed: None,
+                    detections,
+                });
+            }
+            Err(e) => record_failure(&mut result, options, entry, archive_path, e)?,
+        }
+    }
+
+    let Some(mut sink) = sink else {
+        return Ok(result);
+    };
+    manifest.total_files = result.successful;
+    manifest.total_bytes = result.total_bytes;
+    sink.add_bytes(MANIFEST_FILE, &serde_json::to_vec_pretty(&manifest)?)?;
+    sink.finish()
+        .with_context(|| format!("Failed to finish archive: {}", archive_path.display()))?;
+
+    if options.verify_hash {
+        verify_archive(archive_path, format, &manifest)?;
+    }
+    Ok(result)
+}
+
+/// Count a failed file, or abort when not continuing on errors
+fn record_failure(
+    result: &mut ExportResult,
+    options: &ExportOptions,
+    entry: &FileEntry,
+    archive_path: &Path,
+    error: anyhow::Error,
+) -> Result<()> {
+    result.failed += 1;
+    if !options.continue_on_error {
+        return Err(error);
+    }
+    tracing::warn!("Failed to archive {}: {:#}", entry.path.display(), error);
+    result.errors.push(ExportError {
+        source_path: entry.path.clone(),
+        dest_path: archive_path.to_path_buf(),
+        error: format!("{:#}", error),
+        recoverable: true,
+    });
+    Ok(())
+}
+
+/// Archive member name: relative, `/`-separated
+pub(super) fn member_name(path: &Path) -> String {
+    path.components()
+        .filter_map(|c| match c {
+            Component::Normal(part) => Some(part.to_string_lossy()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Re-read every archived file and compare it with the manifest hash
+fn verify_archive(path: &Path, format: ArchiveFormat, manifest: &ExportManifest) -> Result<()> {
+    let expected: std::collections::HashMap<&str, &str> = manifest
+        .entries
+        .iter()
+        .map(|e| (e.dest_path.as_str(), e.blake3_hash.as_str()))
+        .collect();
+    let mut checked = 0;
+    let mut check = |name: &str, reader: &mut dyn Read| -> Result<()> {
+        let Some(&want) = expected.get(name) else {
+            return Ok(());
+        };
+        let mut hasher = blake3::Hasher::new();
+        std::io::copy(reader, &mut hasher)?;
+        let got = hasher.finalize().to_hex().to_string();
+        if got != want {
+            anyhow::bail!(
+                "Archive member {} failed verification: expected {}, got {}",
+                name,
+                want,
+                got
+            );
+        }
+        checked += 1;
+        Ok(())
+    };
+
+    let file = File::open(path)?;
+    match format {
+        ArchiveFormat::Zip => {
+            let mut zip = zip::ZipArchive::new(file)?;
+            for i in 0..zip.len() {
+                let mut member = zip.by_index(i)?;
+                let name = member.name().to_string();
+                check(&name, &mut member)?;
+            }
+        }
+        ArchiveFormat::TarGz => {
+            let mut tar = tar::Archive::new(GzDecoder::new(file));
+            for member in tar.entries()? {
+                let mut member = member?;
+                let name = member.path()?.to_string_lossy().to_string();
+                check(&name, &mut member)?;
+            }
+        }
+    }
+    if checked != expected.len() {
+        anyhow::bail!(
+            "Archive {} is missing {} file(s) listed in its manifest",
+            path.display(),
+            expected.len() - checked
+        );
+    }
+    Ok(())
+}
+
+/// Reader that hashes and counts what passes through it
+pub(super) struct HashingReader<'a, R> {
+    pub(super) inner: R,
+    pub(super) hasher: blake3::Hasher,
+    pub(super) digests: MultiHasher,
+    pub(super) bytes: u64,
+    pub(super) throttle: &'a Throttle,
+}
+
+impl<R: Read> Read for HashingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        self.digests.update(&buf[..n]);
+        self.bytes += n as u64;
+        self.throttle.consume(n as u64);
+        Ok(n)
+    }
+}
+
+enum ArchiveSink {
+    Zip(zip::ZipWriter<BufWriter<File>>),
+    TarGz(tar::Builder<GzEncoder<BufWriter<File>>>),
+}
+
+impl ArchiveSink {
+    fn new(format: ArchiveFormat, out: BufWriter<File>) -> Self {
+        match format {
+            ArchiveFormat::Zip => Self::Zip(zip::ZipWriter::new(out)),
+            ArchiveFormat::TarGz => Self::TarGz(tar::Builder::new(GzEncoder::new(
+                out,
+                Compression::default(),
+            ))),
+        }
+    }
+
+    /// Stream one file in; returns its size and blake3 hash
+    fn add_file(
+        &mut self,
+        name: &str,
+        entry: &FileEntry,
+        algorithms: &[HashAlgorithm],
+        throttle: &Throttle,
+    ) -> Result<(u64, String, Digests)> {
+        if let Some(member) = ArchiveMember::of(entry) {
+            return member.read(|source| {
+                self.add_reader(name, entry, source, entry.size, algorithms, throttle)
+            });
+        }
+        if let Some(file) = SnapshotFile::of(entry) {
+            return file.read(|source| {
+                self.add_reader(name, entry, source, entry.size, algorithms, throttle)
+            });
+        }
+        let mut file = File::open(&entry.path)
+            .with_context(|| format!("Failed to open {}", entry.path.display()))?;
+        let size = file.metadata()?.len();
+        self.add_reader(name, entry, &mut file, size, algorithms, throttle)
+    }
+
+    fn add_reader(
+        &mut self,
+        name: &str,
+        entry: &FileEntry,
+        source: &mut dyn Read,
+        size: u64,
+        algorithms: &[HashAlgorithm],
+        throttle: &Throttle,
+    ) -> Result<(u64, String, Digests)> {
+        let mut reader = HashingReader {
+            inner: source,
+            hasher: blake3::Hasher::new(),
+            digests: MultiHasher::new(algorithms),
+            bytes: 0,
+            throttle,
+        };
+
+        match self {
+            Self::Zip(zip) => {
+                // Photos, video and audio are already compressed
+                let method = match entry.file_type {
+                    FileType::Image | FileType::Video | FileType::Audio | FileType::Archive => {
+                        zip::CompressionMethod::Stored
+                    }
+                    _ => zip::CompressionMethod::Deflated,
+                };
+                let mut file_options = zip::write::SimpleFileOptions::default()
+                    .compression_method(method)
+                    .large_file(size >= u32::MAX as u64);
+                if let Some(modified) = entry.modified.and_then(zip_time) {
+                    file_options = file_options.last_modified_time(modified);
+                }
+                zip.start_file(name, file_options)?;
+                if let Err(e) = std::io::copy(&mut reader, zip) {
+                    zip.abort_file()?;
+                    return Err(e)
+                        .with_context(|| format!("Failed to read {}", entry.path.display()));
+                }
+            }
+            Self::TarGz(tar) => {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(size);
+                header.set_mode(0o644);
+                header.set_mtime(entry.modified.map_or(0, |m| m.timestamp().max(0) as u64));
+                // A short read cannot be undone in a tar stream, so it ends the export
+                tar.append_data(&mut header, name, (&mut reader).take(size))
+                    .with_context(|| format!("Failed to archive {}", entry.path.display()))?;
+                if reader.bytes != size {
+                    anyhow::bail!(
+                        "{} shrank while archiving ({} of {} bytes)",
+                        entry.path.display(),
+                        reader.bytes,
+                        size
+                    );
+                }
+            }
+        }
+        Ok((
+            reader.bytes,
+            reader.hasher.finalize().to_hex().to_string(),
+            reader.digests.finish(),
+        ))
+    }
+
+    fn add_bytes(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        match self {
+            Self::Zip(zip) => {
+                zip.start_file(name, zip::write::SimpleFileOptions::default())?;
+                zip.write_all(data)?;
+            }
+            Self::TarGz(tar) => {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(data.len() as u64);
+                header.set_mode(0o644);
+                header.set_mtime(Utc::now().timestamp().max(0) as u64);
+                tar.append_data(&mut header, name, data)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<()> {
+        match self {
+            Self::Zip(mut zip) => zip.finish()?.flush()?,
+            Self::TarGz(tar) => tar.into_inner()?.finish()?.flush()?,
+        }
+        Ok(())
+    }
+}
+
+/// ZIP timestamps are local-less DOS times covering 1980..=2107
+fn zip_time(t: chrono::DateTime<Utc>) -> Option<zip::DateTime> {
+    zip::DateTime::from_date_and_time(
+        u16::try_from(t.year()).ok()?,
+        t.month() as u8,
+        t.day() as u8,
+        t.hour() as u8,
+        t.minute() as u8,
+        t.second() as u8,
+    )
+    .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_zip_and_tar_gz_export() {
+        let source = tempfile::tempdir().unwrap();
+        let entries: Vec<FileEntry> = [
+            ("a", "IMG_0001.jpg"),
+            ("b", "IMG_0001.jpg"),
+            ("b", "notes.txt"),
+        ]
+        .iter()
+        .map(|(dir, name)| {
+            let path = source.path().join(dir).join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, format!("{}/{}", dir, name).repeat(100)).unwrap();
+            FileEntry::new(path.clone(), &std::fs::metadata(&path).unwrap())
+        })
+        .collect();
+
+        let out = tempfile::tempdir().unwrap();
+        for name in ["export.zip", "export.tar.gz"] {
+            let dest = out.path().join(name);
+            let format = ArchiveFormat::from_path(&dest).unwrap();
+            let exporter = super::super::Exporter::new(ExportOptions {
+                dest: dest.clone(),
+                verify_hash: true,
+                archive: Some(format),
+                ..Default::default()
+            });
+            let result = exporter.export_batch(&entries, |_| {}).await.unwrap();
+            assert_eq!(result.successful, 3, "{}", name);
+
+            // Read the members back
+            let mut members = std::collections::BTreeMap::new();
+            let file = File::open(&dest).unwrap();
+            let mut add = |name: String, reader: &mut dyn Read| {
+                let mut data = String::new();
+                reader.read_to_string(&mut data).unwrap();
+                members.insert(name, data);
+            };
+            match format {
+                ArchiveFormat::Zip => {
+                    let mut zip = zip::ZipArchive::new(file).unwrap();
+                    for i in 0..zip.len() {
+                        let mut m = zip.by_index(i).unwrap();
+                        add(m.name().to_string(), &mut m);
+                    }
+                }
+                ArchiveFormat::TarGz => {
+                    let mut tar = tar::Archive::new(GzDecoder::new(file));
+                    for m in tar.entries().unwrap() {
+                        let mut m = m.unwrap();
+                        add(m.path().unwrap().to_string_lossy().to_string(), &mut m);
+                    }
+                }
+            }
+
+            assert_eq!(members["IMG_0001.jpg"], "a/IMG_0001.jpg".repeat(100));
+            assert_eq!(members["IMG_0001_1.jpg"], "b/IMG_0001.jpg".repeat(100));
+            let manifest: ExportManifest = serde_json::from_str(&members[MANIFEST_FILE]).unwrap();
+            assert_eq!(manifest.total_files, 3);
+            let notes = manifest
+                .entries
+                .iter()
+                .find(|e| e.dest_path == "notes.txt")
+                .unwrap();
+            assert_eq!(
+                notes.blake3_hash,
+                blake3::hash(members["notes.txt"].as_bytes())
+                    .to_hex()
+                    .to_string()
+            );
+        }
+        assert_eq!(
+            ArchiveFormat::from_path(Path::new("x.tgz")),
+            Some(ArchiveFormat::TarGz)
+        );
+        assert_eq!(ArchiveFormat::from_path(Path::new("x.7z")), None);
+    }
+}
@@ -0,0 +1,246 @@
+//! Export hooks
+//!
+//! An [`ExportHook`] looks at every file before it is written to the
+//! destination. Files a hook flags are exported under [`QUARANTINE_DIR`] or
+//! exported as usual ([`DetectionAction`]), and the detection is recorded
+//! in the manifest either way. A hook that fails fails the file: a file
+//! that could not be scanned is not handed over as if it were clean.
+//!
+//! [`ClamAv`] scans with a running clamd, over its Unix socket or TCP.
+//!
+//! [`QUARANTINE_DIR`]: super::QUARANTINE_DIR
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// Bytes per INSTREAM chunk
+const CLAMD_CHUNK_SIZE: usize = 64 * 1024;
+
+/// How long clamd may take to answer, e.g. while unpacking a large archive
+const CLAMD_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// A check run on each file before it is exported
+pub trait ExportHook: std::fmt::Debug + Send + Sync {
+    /// Name recorded with detections, e.g. `clamav`
+    fn name(&self) -> &str;
+
+    /// Read the contents of `source` from `data`; returns what was found in
+    /// it (e.g. a signature name), or None when it is clean
+    fn scan(&self, source: &Path, data: &mut dyn Read) -> Result<Option<String>>;
+}
+
+/// What is done with a file a hook flags
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DetectionAction {
+    /// Export it under the quarantine folder instead of its usual place
+    #[default]
+    Quarantine,
+    /// Export it as usual; only the manifest records the detection
+    Flag,
+}
+
+/// A hook's finding on an exported file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Detection {
+    /// [`ExportHook::name`] of the hook that flagged the file
+    pub hook: String,
+    /// What was found, e.g. `Win.Trojan.Agent-123`
+    pub finding: String,
+    /// Whether the file went to the quarantine folder
+    pub quarantined: bool,
+}
+
+/// Whether any of `detections` sends its file to quarantine
+pub fn is_quarantined(detections: &[Detection]) -> bool {
+    detections.iter().any(|d| d.quarantined)
+}
+
+/// Virus scanning with ClamAV's clamd, streaming each file to it
+/// (`INSTREAM`), so clamd needs no access to the source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClamAv {
+    addr: ClamdAddr,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ClamdAddr {
+    Unix(PathBuf),
+    Tcp(String),
+}
+
+impl ClamAv {
+    /// clamd at `addr`: a Unix socket path (`/run/clamav/clamd.ctl`) or
+    /// `host:port` (`127.0.0.1:3310`)
+    pub fn new(addr: &str) -> Self {
+        let addr = if addr.contains('/') || addr.contains('\\') {
+            ClamdAddr::Unix(PathBuf::from(addr))
+        } else {
+            ClamdAddr::Tcp(addr.to_string())
+        };
+        Self { addr }
+    }
+
+    /// Check that clamd answers, so an export does not fail file by file
+    pub fn ping(&self) -> Result<()> {
+        let mut conn = self.connect()?;
+        conn.write_all(b"zPING\0")?;
+        let reply = read_reply(&mut *conn)?;
+        if reply != "PONG" {
+            anyhow::bail!("Unexpected reply from clamd: {}", reply);
+        }
+        Ok(())
+    }
+
+    fn connect(&self) -> Result<Box<dyn Conn>> {
+        match &self.addr {
+            ClamdAddr::Tcp(addr) => {
+                let stream = TcpStream::connect(addr)
+                    .with_context(|| format!("Failed to connect to clamd at {}", addr))?;
+                stream.set_read_timeout(Some(CLAMD_TIMEOUT))?;
+                stream.set_write_timeout(Some(CLAMD_TIMEOUT))?;
+                Ok(Box::new(stream))
+            }
+            #[cfg(unix)]
+            ClamdAddr::Unix(path) => {
+                let stream = std::os::unix::net::UnixStream::connect(path)
+                    .with_context(|| format!("Failed to connect to clamd at {}", path.display()))?;
+                stream.set_read_timeout(Some(CLAMD_TIMEOUT))?;
+                stream.set_write_timeout(Some(CLAMD_TIMEOUT))?;
+                Ok(Box::new(stream))
+            }
+            #[cfg(not(unix))]
+            ClamdAddr::Unix(path) => anyhow::bail!(
+                "clamd sockets ({}) need a Unix system; give clamd's host:port",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl ExportHook for ClamAv {
+    fn name(&self) -> &str {
+        "clamav"
+    }
+
+    fn scan(&self, source: &Path, data: &mut dyn Read) -> Result<Option<String>> {
+        let mut conn = self.connect()?;
+        conn.write_all(b"zINSTREAM\0")?;
+        let mut buffer = vec![0u8; CLAMD_CHUNK_SIZE];
+        loop {
+            let n = data
+                .read(&mut buffer)
+                .with_context(|| format!("Failed to read {}", source.display()))?;
+            // clamd stops reading once a file is over its StreamMaxLength
+            // and says so in its reply
+            if conn.write_all(&(n as u32).to_be_bytes()).is_err()
+                || conn.write_all(&buffer[..n]).is_err()
+                || n == 0
+            {
+                break;
+            }
+        }
+        let reply = read_reply(&mut *conn)?;
+        let verdict = reply.strip_prefix("stream: ").unwrap_or(&reply);
+        if verdict == "OK" {
+            return Ok(None);
+        }
+        if let Some(signature) = verdict.strip_suffix(" FOUND") {
+            return Ok(Some(signature.to_string()));
+        }
+        anyhow::bail!("clamd could not scan {}: {}", source.display(), verdict)
+    }
+}
+
+trait Conn: Read + Write {}
+impl<T: Read + Write> Conn for T {}
+
+/// Read a NUL-terminated clamd reply
+fn read_reply(conn: &mut dyn Conn) -> Result<String> {
+    let mut reply = Vec::new();
+    conn.read_to_end(&mut reply)
+        .context("Failed to read clamd's reply")?;
+    let end = reply.iter().position(|&b| b == 0).unwrap_or(reply.len());
+    Ok(String::from_utf8_lossy(&reply[..end]).trim().to_string())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    /// A clamd that flags anything containing "EICAR"; returns its address
+    pub(crate) fn fake_clamd() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        std::thread::spawn(move || {
+            for conn in listener.incoming() {
+                let mut conn = conn.unwrap();
+                let mut command = Vec::new();
+                let mut byte = [0u8; 1];
+                while conn.read_exact(&mut byte).is_ok() && byte[0] != 0 {
+                    command.push(byte[0]);
+                }
+                if command == b"zPING" {
+                    conn.write_all(b"PONG\0").unwrap();
+                    continue;
+                }
+                let mut data = Vec::new();
+                loop {
+                    let mut len = [0u8; 4];
+                    conn.read_exact(&mut len).unwrap();
+                    let len = u32::from_be_bytes(len) as usize;
+                    if len == 0 {
+                        break;
+                    }
+                    let mut chunk = vec![0u8; len];
+                    conn.read_exact(&mut chunk).unwrap();
+                    data.extend(chunk);
+                }
+                let infected = data.windows(5).any(|w| w == b"EICAR");
+                let reply: &[u8] = if infected {
+                    b"stream: Eicar-Test-Signature FOUND\0"
+                } else {
+                    b"stream: OK\0"
+                };
+                conn.write_all(reply).unwrap();
+            }
+        });
+        addr
+    }
+
+    #[test]
+    fn test_clamav_instream() {
+        let clamav = ClamAv::new(&fake_clamd());
+        clamav.ping().unwrap();
+
+        let path = Path::new("eicar.com");
+        let found = clamav
+            .scan(
+                path,
+                &mut &b"X5O!P%@AP[4\\PZX54(P^)7CC)7}$EICAR-STANDARD"[..],
+            )
+            .unwrap();
+        assert_eq!(found.as_deref(), Some("Eicar-Test-Signature"));
+        let clean = vec![b'a'; CLAMD_CHUNK_SIZE * 2 + 10];
+        assert_eq!(clamav.scan(path, &mut clean.as_slice()).unwrap(), None);
+    }
+
+    #[test]
+    fn test_clamd_addr() {
+        assert_eq!(
+            ClamAv::new("/run/clamav/clamd.ctl").addr,
+            ClamdAddr::Unix(PathBuf::from("/run/clamav/clamd.ctl"))
+        );
+        assert_eq!(
+            ClamAv::new("localhost:3310").addr,
+            ClamdAddr::Tcp("localhost:3310".into())
+        );
+        assert!(ClamAv::new("127.0.0.1:1").ping().is_err());
+    }
+}
@@ -1,18 +1,73 @@
 //! Export module - Safe file export with verification
 //!
 //! Provides async copy with blake3 hash verification and manifest generation.
+//! Hash mismatches are retried a bounded number of times by re-reading the
+//! source, and every failed attempt is recorded in the manifest entry.
+//! Extra digests (`ExportOptions::hash_algorithms`, e.g. MD5 and SHA-256)
+//! are computed in the same read as the blake3 hash. Sources are read on a
+//! blocking thread through the configured [`crate::iobackend`].
+//! A UI that shows each file of a batch can follow it through
+//! [`Exporter::with_file_events`]. Besides a directory, the destination can
+//! be a ZIP/tar.gz archive or an SFTP server or SMB share. Export hooks
+//! (e.g. ClamAV) see each file first and can send it to quarantine.
 
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::io::{Read, SeekFrom};
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::{Context, Result};
 use chrono::Utc;
+use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
 use tokio::fs;
-use tokio::io::{AsyncReadExt, AsyncWriteExt, BufReader, BufWriter};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufReader, BufWriter};
 
-use crate::core::{FileEntry, Progress};
+mod archive;
+mod hooks;
+mod plan;
+mod remote;
+
+pub use archive::ArchiveFormat;
+pub use hooks::{is_quarantined, ClamAv, Detection, DetectionAction, ExportHook};
+pub use plan::{disk_space, DestinationPlan, DiskSpace, ExportPlan, SpaceStatus};
+pub use remote::{RemoteScheme, RemoteTarget};
+
+use crate::badsector::{export_with_bad_sector_handling, SectorReader};
+use crate::checkpoint::{Checkpoint, CheckpointManager, CheckpointPhase};
+use crate::core::{
+    compute_digests, decompressed_name, extract_metadata, format_timestamp, ArchiveMember,
+    CompressedFormat, Digests, FileEntry, FileType, HashAlgorithm, MultiHasher, NameRepair,
+    Progress, SnapshotFile,
+};
+use crate::error::{DrillError, DrillResult};
+use crate::events::{self, Event};
+use crate::iobackend::{self, IoOptions};
+use crate::proof::{self, ChainOfCustody, CustodyEventKind, ProofEntry};
+use crate::sparse::{self, Extent};
+use crate::throttle::Throttle;
+
+/// Files copied at once unless configured otherwise
+pub const DEFAULT_EXPORT_WORKERS: usize = 8;
+
+/// Default number of re-copies after a hash mismatch
+pub const DEFAULT_HASH_RETRIES: u32 = 2;
+
+/// Source bytes per read when copying
+const COPY_BLOCK_SIZE: usize = 256 * 1024;
+
+/// Exported files between checkpoint saves
+const CHECKPOINT_INTERVAL: usize = 100;
+
+/// Manifest written next to exported files (or as an archive member)
+pub const MANIFEST_FILE: &str = "diamond-drill-manifest.json";
+
+/// Proof manifest covering both copies of a mirrored export, written to
+/// each destination
+pub const PROOF_MANIFEST_FILE: &str = "diamond-drill-proof.json";
 
 /// Export configuration options
 #[derive(Debug, Clone, Default)]
@@ -29,15 +84,103 @@ pub struct ExportOptions {
     pub create_manifest: bool,
     /// Dry run mode
     pub dry_run: bool,
+    /// Extra copy attempts after a hash mismatch (0 = fail immediately)
+    pub hash_retries: u32,
+    /// Re-read the source through the sector reader with small blocks on retry
+    pub retry_with_sector_reader: bool,
+    /// Sort files into folders by date or type (takes precedence over
+    /// `preserve_structure`)
+    pub organize_by: Option<OrganizeBy>,
+    /// What to do when the destination path is already taken
+    pub on_conflict: CollisionPolicy,
+    /// Write `dest` as a single archive of this format instead of a directory
+    pub archive: Option<ArchiveFormat>,
+    /// Upload to this SFTP server or SMB share instead of writing under
+    /// `dest`
+    pub remote: Option<RemoteTarget>,
+    /// Cap on source read rate in bytes per second (None = unlimited)
+    pub max_bytes_per_sec: Option<u64>,
+    /// Skip files that an interrupted earlier export to the same `dest`
+    /// already copied, as recorded in its checkpoint
+    pub resume: bool,
+    /// Second destination receiving an identical copy of every file
+    /// (e.g. an evidence copy on a NAS next to a working copy on SSD)
+    pub mirror: Option<PathBuf>,
+    /// When the index the files come from was started, for the proof
+    /// manifest's custody log
+    pub index_started: Option<chrono::DateTime<Utc>>,
+    /// Digests recorded next to the blake3 hash in the manifests (e.g. MD5
+    /// and SHA-256 for tools that do not accept Blake3)
+    pub hash_algorithms: Vec<HashAlgorithm>,
+    /// How sources are read (backend and queue depth)
+    pub io: IoOptions,
+    /// Files copied at once (None = [`DEFAULT_EXPORT_WORKERS`])
+    pub workers: Option<usize>,
+    /// Start even when the destination looks too small (see
+    /// [`Exporter::plan`])
+    pub skip_space_check: bool,
+    /// Name mis-encoded files by their repaired names (entries indexed with
+    /// name repair are renamed either way)
+    pub repair_names: Option<NameRepair>,
+    /// Decompress gzip, bzip2, xz and zstd files on the way out, dropping
+    /// the compression extension from their names
+    pub decompress: bool,
+    /// Checks run on each file before it is written, such as [`ClamAv`]
+    pub hooks: Vec<Arc<dyn ExportHook>>,
+    /// What happens to files a hook flags
+    pub on_detection: DetectionAction,
+}
+
+/// Handling of destination files that already exist, or that an earlier
+/// file in the same batch was assigned
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CollisionPolicy {
+    /// Export under a free name: `IMG_0001_1.jpg`, `IMG_0001_2.jpg`, ...
+    #[default]
+    Rename,
+    /// Leave the existing file and do not export this one
+    Skip,
+    /// Replace the existing file (a name taken earlier in the same batch
+    /// is renamed instead)
+    Overwrite,
+    /// Fail this file
+    Error,
 }
 
+/// Folder layout for `ExportOptions::organize_by`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OrganizeBy {
+    /// `YYYY/MM/` from the EXIF capture date, falling back to the
+    /// modification time
+    DateTaken,
+    /// `YYYY/MM/` from the modification time
+    DateModified,
+    /// One folder per file type (`image/`, `video/`, ...)
+    FileType,
+}
+
+/// Folder for files without any usable date
+const UNDATED_DIR: &str = "undated";
+
+/// Folder under the destination for files an export hook flagged
+/// (`DetectionAction::Quarantine`)
+pub const QUARANTINE_DIR: &str = "quarantine";
+
 /// Result of an export operation
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct ExportResult {
     /// Number of successfully exported files
     pub successful: usize,
     /// Number of failed exports
     pub failed: usize,
+    /// Files not exported because their destination was taken
+    /// (`CollisionPolicy::Skip`)
+    pub skipped: usize,
+    /// Files skipped because an earlier run already exported them
+    /// (`ExportOptions::resume`)
+    pub resumed: usize,
+    /// Exported files an export hook flagged (see their manifest entries)
+    pub detections: usize,
     /// Total bytes exported
     pub total_bytes: u64,
     /// Path to manifest file if created
@@ -62,8 +205,95 @@ pub struct ManifestEntry {
     pub dest_path: String,
     pub size: u64,
     pub blake3_hash: String,
+    /// Digests from `ExportOptions::hash_algorithms`
+    #[serde(default, skip_serializing_if = "Digests::is_empty")]
+    pub digests: Digests,
     pub exported_at: String,
     pub verified: bool,
+    /// Copy under `ExportOptions::mirror`, hashed and verified on its own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mirror_path: Option<String>,
+    /// Failed copy attempts that preceded the successful one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub retry_history: Vec<RetryAttempt>,
+    /// The compressed source, when the file was decompressed on export;
+    /// `size` and `blake3_hash` are then of the decompressed file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compressed: Option<CompressedSource>,
+    /// What export hooks found in the file
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub detections: Vec<Detection>,
+}
+
+/// A compressed file as it is on the source
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompressedSource {
+    /// `gzip`, `bzip2`, `xz` or `zstd`
+    pub format: String,
+    pub size: u64,
+    pub blake3_hash: String,
+}
+
+impl CompressedSource {
+    /// Describe the compressed file at `path`, reading it through for its hash
+    fn read(path: &Path, format: CompressedFormat) -> Result<Self> {
+        Ok(Self {
+            format: format.name().to_string(),
+            size: std::fs::metadata(path)?.len(),
+            blake3_hash: crate::dedup::hash_file(path)?,
+        })
+    }
+}
+
+/// A copy attempt that failed hash verification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetryAttempt {
+    /// 1-based attempt number
+    pub attempt: u32,
+    /// Hash of the source bytes as read during this attempt
+    pub source_hash: String,
+    /// Hash of the destination file after writing
+    pub dest_hash: String,
+    /// Whether this attempt read the source through the sector reader
+    pub sector_reader: bool,
+    /// Extra detail (e.g. unreadable blocks)
+    pub note: Option<String>,
+    /// When the attempt finished
+    pub at: String,
+}
+
+/// Progress of one file in a batch, for UIs that list every file
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "stage", rename_all = "snake_case")]
+pub enum ExportFileEvent {
+    /// Copying `source` to `dest` started
+    Started { source: PathBuf, dest: PathBuf },
+    /// Bytes of `source` copied so far in the current attempt
+    Copied { source: PathBuf, bytes: u64 },
+    /// The copy is being hashed and compared with the source
+    Verifying { source: PathBuf },
+    /// Not copied: its destination was taken or an earlier run exported it
+    Skipped { source: PathBuf, reason: String },
+    /// Done: the BLAKE3 hash and the number of retries it took, or why it
+    /// failed
+    Finished {
+        source: PathBuf,
+        result: Result<(String, usize), String>,
+    },
+}
+
+/// Receiver of [`ExportFileEvent`]s, called from the export's tasks
+pub type ExportFileEvents = Arc<dyn Fn(ExportFileEvent) + Send + Sync>;
+
+/// Hand `event` to the batch's own receiver, if any, and publish it on the
+/// event bus
+fn publish_file_event(file_events: Option<&ExportFileEvents>, event: ExportFileEvent) {
+    if events::is_observed() {
+        events::emit(Event::Export(event.clone()));
+    }
+    if let Some(callback) = file_events {
+        callback(event);
+    }
 }
 
 /// Manifest file format
@@ -95,12 +325,51 @@ impl ExportManifest {
 /// File exporter with async operations
 pub struct Exporter {
     options: ExportOptions,
+    throttle: Throttle,
+    checkpoints: CheckpointManager,
+    file_events: Option<ExportFileEvents>,
 }
 
 impl Exporter {
     /// Create a new exporter with options
     pub fn new(options: ExportOptions) -> Self {
-        Self { options }
+        let throttle = Throttle::new(options.max_bytes_per_sec);
+        Self {
+            options,
+            throttle,
+            checkpoints: CheckpointManager::new(),
+            file_events: None,
+        }
+    }
+
+    /// Keep export checkpoints somewhere other than the default directory
+    pub fn with_checkpoint_manager(mut self, checkpoints: CheckpointManager) -> Self {
+        self.checkpoints = checkpoints;
+        self
+    }
+
+    /// Pace reads with a shared throttle instead of one built from
+    /// `max_bytes_per_sec`, so the rate can be changed while exporting
+    pub fn with_throttle(mut self, throttle: Throttle) -> Self {
+        self.throttle = throttle;
+        self
+    }
+
+    /// Report each file's progress and hash status to `events` (not
+    /// reported for archive exports)
+    pub fn with_file_events(mut self, events: ExportFileEvents) -> Self {
+        self.file_events = Some(events);
+        self
+    }
+
+    fn file_event(&self, event: ExportFileEvent) {
+        publish_file_event(self.file_events.as_ref(), event);
+    }
+
+    /// Work out the space `entries` will take on each destination file
+    /// system and how much is free there
+    pub fn plan(&self, entries: &[FileEntry]) -> ExportPlan {
+        ExportPlan::new(entries, &self.options)
     }
 
     /// Export a batch of files with progress callback
@@ -108,10 +377,44 @@ impl Exporter {
         &self,
         entries: &[FileEntry],
         progress_callback: F,
-    ) -> Result<ExportResult>
+    ) -> DrillResult<ExportResult>
     where
         F: Fn(Progress) + Send + Sync,
     {
+        self.run_batch(entries, progress_callback)
+            .await
+            .map_err(DrillError::from)
+    }
+
+    async fn run_batch<F>(&self, entries: &[FileEntry], progress_callback: F) -> Result<ExportResult>
+    where
+        F: Fn(Progress) + Send + Sync,
+    {
+        if !self.options.dry_run && !self.options.skip_space_check {
+            self.plan(entries).check()?;
+        }
+        if let Some(target) = &self.options.remote {
+            return remote::export_to_remote(
+                entries,
+                target,
+                &self.options,
+                &self.throttle,
+                progress_callback,
+            )
+            .await;
+        }
+        if let Some(format) = self.options.archive {
+            return archive::export_to_archive(
+                entries,
+                format,
+                &self.options,
+                &self.throttle,
+                progress_callback,
+            )
+            .await;
+        }
+
+        let started_at = Utc::now();
         let mut result = ExportResult::default();
         let mut manifest = ExportManifest::new(
             &entries
@@ -126,44 +429,209 @@ impl Exporter {
             fs::create_dir_all(&self.options.dest)
                 .await
                 .with_context(|| format!("Failed to create destination: {}", self.options.dest.display()))?;
+            if let Some(mirror) = &self.options.mirror {
+                fs::create_dir_all(mirror)
+                    .await
+                    .with_context(|| format!("Failed to create mirror: {}", mirror.display()))?;
+            }
         }
 
+        // Checkpoints are keyed by destination, so exporting one source to
+        // several places resumes each independently
+        let checkpoint = if self.options.dry_run {
+            None
+        } else {
+            Some(Arc::new(Mutex::new(self.start_checkpoint()?)))
+        };
+
         let total = entries.len();
         let completed = Arc::new(AtomicUsize::new(0));
         let total_bytes = Arc::new(AtomicU64::new(0));
         let errors = Arc::new(AtomicUsize::new(0));
 
         // Process files concurrently with bounded concurrency
-        let semaphore = Arc::new(tokio::sync::Semaphore::new(8));
+        let workers = self
+            .options
+            .workers
+            .unwrap_or(DEFAULT_EXPORT_WORKERS)
+            .max(1);
+        let semaphore = Arc::new(tokio::sync::Semaphore::new(workers));
 
         let mut handles = Vec::new();
+        let mut claimed = HashSet::new();
 
         for entry in entries {
+            if let Some(done) = checkpoint
+                .as_ref()
+                .and_then(|cp| resumed_entry(&cp.lock(), entry, &self.options))
+            {
+                self.file_event(ExportFileEvent::Skipped {
+                    source: entry.path.clone(),
+                    reason: "exported by an earlier run".to_string(),
+                });
+                result.resumed += 1;
+                // Later files must not take the resumed copy's name
+                claimed.insert(PathBuf::from(&done.dest_path));
+                manifest.entries.push(done);
+                completed.fetch_add(1, Ordering::Relaxed);
+                continue;
+            }
+
+            // A file whose earlier copy no longer checks out replaces it
+            let earlier = checkpoint
+                .as_ref()
+                .and_then(|cp| {
+                    cp.lock()
+                        .outputs
+                        .get(entry.path.to_string_lossy().as_ref())
+                        .map(PathBuf::from)
+                })
+                .filter(|dest| !claimed.contains(dest));
+
+            // Destinations are assigned here, in order, so concurrent copies
+            // never race for the same name
+            let resolved = match earlier {
+                Some(dest) => {
+                    claimed.insert(dest.clone());
+                    Ok(Some(dest))
+                }
+                None => resolve_collision(
+                    get_dest_path(entry, &self.options),
+                    self.options.on_conflict,
+                    &mut claimed,
+                    // A name must be free in both trees
+                    |path| {
+                        path.exists()
+                            || mirror_path(path, &self.options).is_some_and(|m| m.exists())
+                    },
+                ),
+            };
+            let dest_path = match resolved {
+                Ok(Some(dest_path)) => dest_path,
+                Ok(None) => {
+                    tracing::info!("Skipping {}: destination exists", entry.path.display());
+                    self.file_event(ExportFileEvent::Skipped {
+                        source: entry.path.clone(),
+                        reason: "destination exists".to_string(),
+                    });
+                    result.skipped += 1;
+                    completed.fetch_add(1, Ordering::Relaxed);
+                    continue;
+                }
+                Err(e) => {
+                    self.file_event(ExportFileEvent::Finished {
+                        source: entry.path.clone(),
+                        result: Err(format!("{:#}", e)),
+                    });
+                    result.failed += 1;
+                    completed.fetch_add(1, Ordering::Relaxed);
+                    if !self.options.continue_on_error {
+                        self.save_checkpoint(checkpoint.as_deref());
+                        return Err(e);
+                    }
+                    result.errors.push(ExportError {
+                        source_path: entry.path.clone(),
+                        dest_path: get_dest_path(entry, &self.options),
+                        error: e.to_string(),
+                        recoverable: true,
+                    });
+                    continue;
+                }
+            };
+
             let permit = semaphore.clone().acquire_owned().await?;
             let entry_clone = entry.clone();
             let options = self.options.clone();
+            let throttle = self.throttle.clone();
+            let checkpoint = checkpoint.clone();
+            let checkpoints = self.checkpoints.clone();
             let completed_clone = Arc::clone(&completed);
             let total_bytes_clone = Arc::clone(&total_bytes);
             let errors_clone = Arc::clone(&errors);
+            let file_events = self.file_events.clone();
 
             let handle = tokio::spawn(async move {
-                let result = export_single_file(&entry_clone, &options).await;
+                let event = |event| publish_file_event(file_events.as_ref(), event);
+                let scanned = scan_source(&entry_clone, &options).await;
+                let dest_path = match &scanned {
+                    Ok(detections) if is_quarantined(detections) => {
+                        quarantine_path(&dest_path, &options.dest)
+                    }
+                    _ => dest_path,
+                };
+                event(ExportFileEvent::Started {
+                    source: entry_clone.path.clone(),
+                    dest: dest_path.clone(),
+                });
+                let result = match scanned {
+                    Ok(detections) => {
+                        export_single_file(&entry_clone, &dest_path, &options, &throttle, &event)
+                            .await
+                            .map(|copied| (copied, detections))
+                    }
+                    Err(e) => Err(e),
+                };
+                let result = match (result, StreamSource::of(&entry_clone, &options)) {
+                    (Ok(copied), Some(StreamSource::Compressed(path, format)))
+                        if !options.dry_run =>
+                    {
+                        tokio::task::spawn_blocking(move || CompressedSource::read(&path, format))
+                            .await
+                            .map_err(anyhow::Error::from)
+                            .and_then(|compressed| Ok((copied, Some(compressed?))))
+                    }
+                    (result, _) => result.map(|copied| (copied, None)),
+                };
                 drop(permit);
+                // Dry runs finish without a hash
+                if let Ok((((bytes, hash, _, _), _), _)) = &result {
+                    if !hash.is_empty() {
+                        events::emit(Event::Hash {
+                            path: entry_clone.path.clone(),
+                            blake3: hash.clone(),
+                            bytes: *bytes,
+                        });
+                    }
+                }
+                event(ExportFileEvent::Finished {
+                    source: entry_clone.path.clone(),
+                    result: match &result {
+                        Ok((((_, hash, _, history), _), _)) => Ok((hash.clone(), history.len())),
+                        Err(e) => Err(format!("{:#}", e)),
+                    },
+                });
 
                 completed_clone.fetch_add(1, Ordering::Relaxed);
 
                 match result {
-                    Ok((bytes, hash)) => {
+                    Ok((((bytes, hash, digests, retry_history), detections), compressed)) => {
                         total_bytes_clone.fetch_add(bytes, Ordering::Relaxed);
+                        if let Some(checkpoint) = checkpoint {
+                            let mut checkpoint = checkpoint.lock();
+                            let source = entry_clone.path.to_string_lossy().to_string();
+                            checkpoint.mark_processed(&source, Some(hash.clone()));
+                            checkpoint
+                                .outputs
+                                .insert(source, dest_path.to_string_lossy().to_string());
+                            if checkpoint.should_auto_save() {
+                                if let Err(e) = checkpoints.auto_save(&mut checkpoint) {
+                                    tracing::warn!("Failed to save export checkpoint: {:#}", e);
+                                }
+                            }
+                        }
                         Ok(ManifestEntry {
                             source_path: entry_clone.path.to_string_lossy().to_string(),
-                            dest_path: get_dest_path(&entry_clone.path, &options)
-                                .to_string_lossy()
-                                .to_string(),
+                            dest_path: dest_path.to_string_lossy().to_string(),
                             size: bytes,
                             blake3_hash: hash,
+                            digests,
                             exported_at: Utc::now().to_rfc3339(),
                             verified: options.verify_hash,
+                            mirror_path: mirror_path(&dest_path, &options)
+                                .map(|m| m.to_string_lossy().to_string()),
+                            retry_history,
+                            compressed,
+                            detections,
                         })
                     }
                     Err(e) => {
@@ -192,11 +660,15 @@ impl Exporter {
             match handle.await {
                 Ok(Ok(manifest_entry)) => {
                     result.successful += 1;
+                    if !manifest_entry.detections.is_empty() {
+                        result.detections += 1;
+                    }
                     manifest.entries.push(manifest_entry);
                 }
                 Ok(Err(e)) => {
                     result.failed += 1;
                     if !self.options.continue_on_error {
+                        self.save_checkpoint(checkpoint.as_deref());
                         return Err(e);
                     }
                     result.errors.push(ExportError {
@@ -220,124 +692,842 @@ impl Exporter {
 
         result.total_bytes = total_bytes.load(Ordering::Relaxed);
 
-        // Create manifest
+        // A clean run needs no checkpoint; otherwise keep it for --resume
+        if checkpoint.is_some() && result.failed == 0 {
+            self.checkpoints
+                .clear(&self.options.dest, CheckpointPhase::Exporting)?;
+        } else {
+            self.save_checkpoint(checkpoint.as_deref());
+        }
+
+        // Create manifest (covering files exported by earlier runs too)
         if self.options.create_manifest && !self.options.dry_run {
-            manifest.total_files = result.successful;
-            manifest.total_bytes = result.total_bytes;
+            manifest.total_files = manifest.entries.len();
+            manifest.total_bytes = manifest.entries.iter().map(|e| e.size).sum();
 
-            let manifest_path = self.options.dest.join("diamond-drill-manifest.json");
+            let manifest_path = self.options.dest.join(MANIFEST_FILE);
             let manifest_json = serde_json::to_string_pretty(&manifest)?;
-            fs::write(&manifest_path, manifest_json).await?;
+            fs::write(&manifest_path, &manifest_json).await?;
+            if let Some(mirror) = &self.options.mirror {
+                fs::write(mirror.join(MANIFEST_FILE), &manifest_json).await?;
+            }
             result.manifest_path = Some(manifest_path);
         }
 
+        if self.options.mirror.is_some() && !self.options.dry_run {
+            self.write_mirror_proof(&manifest, started_at)?;
+        }
+
         Ok(result)
     }
+
+    /// Write one proof manifest listing both copies of every file to each
+    /// destination, so either copy can be verified against the other
+    fn write_mirror_proof(
+        &self,
+        manifest: &ExportManifest,
+        started_at: chrono::DateTime<Utc>,
+    ) -> Result<()> {
+        let Some(mirror) = &self.options.mirror else {
+            return Ok(());
+        };
+
+        let mut entries = Vec::with_capacity(manifest.entries.len() * 2);
+        for entry in &manifest.entries {
+            let exported_at = chrono::DateTime::parse_from_rfc3339(&entry.exported_at)
+                .map(|t| t.with_timezone(&Utc))
+                .unwrap_or(started_at);
+            let copies = std::iter::once(&entry.dest_path).chain(entry.mirror_path.as_ref());
+            for dest_path in copies {
+                entries.push(ProofEntry {
+                    source_path: entry.source_path.clone(),
+                    dest_path: dest_path.clone(),
+                    size: entry.size,
+                    blake3_hash: entry.blake3_hash.clone(),
+                    digests: entry
+                        .digests
+                        .iter()
+                        .map(|(algorithm, digest)| (algorithm.name().to_string(), digest.clone()))
+                        .collect(),
+                    exported_at,
+                    bad_sector_notes: None,
+                    verified: entry.verified,
+                });
+            }
+        }
+
+        let mut custody = ChainOfCustody::from_environment();
+        custody.started_at = started_at;
+        custody.completed_at = Some(Utc::now());
+        if let Some(index_started) = self.options.index_started {
+            custody.record_event_at(
+                index_started,
+                CustodyEventKind::IndexStarted,
+                &manifest.source_root,
+            );
+        }
+        custody.record_event_at(
+            started_at,
+            CustodyEventKind::ExportStarted,
+            format!(
+                "{} -> {} + {}",
+                manifest.source_root,
+                self.options.dest.display(),
+                mirror.display()
+            ),
+        );
+        for entry in &manifest.entries {
+            for attempt in &entry.retry_history {
+                if let Some(note) = &attempt.note {
+                    let at = chrono::DateTime::parse_from_rfc3339(&attempt.at)
+                        .map_or(started_at, |t| t.with_timezone(&Utc));
+                    custody.record_event_at(
+                        at,
+                        CustodyEventKind::BadSector,
+                        format!("{}: {}", entry.source_path, note),
+                    );
+                }
+            }
+        }
+        custody.record_event(
+            CustodyEventKind::ExportCompleted,
+            format!(
+                "{} files, {} bytes",
+                manifest.entries.len(),
+                manifest.entries.iter().map(|e| e.size).sum::<u64>()
+            ),
+        );
+        let options_used = &mut custody.options_used;
+        options_used.insert("dest".into(), self.options.dest.display().to_string());
+        options_used.insert("mirror".into(), mirror.display().to_string());
+        options_used.insert("verify_hash".into(), self.options.verify_hash.to_string());
+        if !self.options.hash_algorithms.is_empty() {
+            let names: Vec<&str> = self
+                .options
+                .hash_algorithms
+                .iter()
+                .map(|a| a.name())
+                .collect();
+            options_used.insert("hash_algorithms".into(), names.join(","));
+        }
+        options_used.insert(
+            "on_conflict".into(),
+            format!("{:?}", self.options.on_conflict).to_lowercase(),
+        );
+
+        let proof = proof::build_manifest(
+            Path::new(&manifest.source_root),
+            &self.options.dest,
+            entries,
+            custody,
+        );
+        for root in [&self.options.dest, mirror] {
+            proof::save_manifest(&proof, &root.join(PROOF_MANIFEST_FILE))?;
+        }
+        Ok(())
+    }
+
+    /// Load the checkpoint of an earlier export to `dest` when resuming,
+    /// otherwise start a new one
+    fn start_checkpoint(&self) -> Result<Checkpoint> {
+        let dest = &self.options.dest;
+        if self.options.resume {
+            if let Some(checkpoint) = self.checkpoints.load(dest, CheckpointPhase::Exporting)? {
+                return Ok(checkpoint);
+            }
+            tracing::info!(
+                "No export checkpoint for {}, starting fresh",
+                dest.display()
+            );
+        }
+        Ok(Checkpoint::new(
+            dest,
+            CheckpointPhase::Exporting,
+            CHECKPOINT_INTERVAL,
+        ))
+    }
+
+    /// Persist progress so far; failures are logged, not fatal
+    fn save_checkpoint(&self, checkpoint: Option<&Mutex<Checkpoint>>) {
+        if let Some(checkpoint) = checkpoint {
+            if let Err(e) = self.checkpoints.save(&checkpoint.lock()) {
+                tracing::warn!("Failed to save export checkpoint: {:#}", e);
+            }
+        }
+    }
 }
 
-/// Export a single file
-async fn export_single_file(entry: &FileEntry, options: &ExportOptions) -> Result<(u64, String)> {
-    let dest_path = get_dest_path(&entry.path, options);
+/// Manifest entry for a file an earlier run already exported, if its copy
+/// is still in place
+fn resumed_entry(
+    checkpoint: &Checkpoint,
+    entry: &FileEntry,
+    options: &ExportOptions,
+) -> Option<ManifestEntry> {
+    let source = entry.path.to_string_lossy();
+    if !checkpoint.is_already_processed(&source) {
+        return None;
+    }
+    let dest = checkpoint.outputs.get(source.as_ref())?;
+    let size = std::fs::metadata(dest).ok()?.len();
+    // A decompressed copy is bigger than its source; any other copy of a
+    // file that has not changed since is the same size
+    let decompressed = matches!(
+        StreamSource::of(entry, options),
+        Some(StreamSource::Compressed(..))
+    );
+    if !decompressed && size != entry.size {
+        return None;
+    }
+    // Both copies must have survived for the file to count as done
+    let mirror = mirror_path(Path::new(dest), options);
+    if mirror
+        .as_deref()
+        .is_some_and(|m| std::fs::metadata(m).map_or(true, |meta| meta.len() != size))
+    {
+        return None;
+    }
+    let hash = checkpoint
+        .hashes_computed
+        .get(source.as_ref())
+        .cloned()
+        .unwrap_or_default();
+    if options.verify_hash {
+        let intact = |path: &Path| {
+            file_hash(path)
+                .map_err(|e| tracing::debug!("Cannot re-hash {}: {:#}", path.display(), e))
+                .is_ok_and(|h| h == hash)
+        };
+        if hash.is_empty()
+            || !intact(Path::new(dest))
+            || mirror.as_deref().is_some_and(|m| !intact(m))
+        {
+            tracing::info!(
+                "{} no longer matches its checkpoint, copying it again",
+                dest
+            );
+            return None;
+        }
+    }
+    Some(ManifestEntry {
+        source_path: source.to_string(),
+        dest_path: dest.clone(),
+        size,
+        blake3_hash: hash,
+        // The checkpoint only keeps blake3 hashes
+        digests: Digests::new(),
+        exported_at: checkpoint.updated_at.to_rfc3339(),
+        verified: options.verify_hash,
+        mirror_path: mirror.map(|m| m.to_string_lossy().to_string()),
+        retry_history: Vec::new(),
+        compressed: None,
+        detections: Vec::new(),
+    })
+}
 
+/// Export a single file, retrying on hash mismatch.
+///
+/// With a mirror, one read of the source feeds both copies and each copy is
+/// verified independently; a mismatch in either retries both.
+///
+/// Returns the byte count, the verified hash, the extra digests and the
+/// history of failed attempts (empty when the first copy verified).
+async fn export_single_file(
+    entry: &FileEntry,
+    dest_path: &Path,
+    options: &ExportOptions,
+    throttle: &Throttle,
+    event: &(dyn Fn(ExportFileEvent) + Sync),
+) -> Result<(u64, String, Digests, Vec<RetryAttempt>)> {
     if options.dry_run {
         tracing::info!(
             "Would export: {} -> {}",
             entry.path.display(),
             dest_path.display()
         );
-        return Ok((entry.size, String::new()));
+        return Ok((entry.size, String::new(), Digests::new(), Vec::new()));
     }
 
-    // Ensure parent directory exists
-    if let Some(parent) = dest_path.parent() {
-        fs::create_dir_all(parent).await?;
+    let mirror = mirror_path(dest_path, options);
+    let targets: Vec<&Path> = std::iter::once(dest_path)
+        .chain(mirror.as_deref())
+        .collect();
+
+    // Ensure parent directories exist
+    for target in &targets {
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).await?;
+        }
     }
 
-    // Copy file with hash computation
-    let (bytes, hash) = copy_with_hash(&entry.path, &dest_path)
-        .await
-        .with_context(|| {
-            format!(
-                "Failed to copy {} to {}",
-                entry.path.display(),
-                dest_path.display()
+    let max_attempts = if options.verify_hash {
+        options.hash_retries + 1
+    } else {
+        1
+    };
+    let mut history = Vec::new();
+
+    // Streamed sources are decoded on the way, so there are no source
+    // sectors to re-read
+    let stream = StreamSource::of(entry, options);
+    for attempt in 1..=max_attempts {
+        let use_sector_reader = attempt > 1 && options.retry_with_sector_reader && stream.is_none();
+
+        let (bytes, hash, digests, note) = if use_sector_reader {
+            let copied = copy_with_sector_reader(
+                &entry.path,
+                dest_path,
+                &options.hash_algorithms,
+                throttle,
+                &options.io,
             )
-        })?;
+            .await?;
+            if let Some(mirror) = &mirror {
+                fs::copy(dest_path, mirror).await.with_context(|| {
+                    format!(
+                        "Failed to copy {} to {}",
+                        dest_path.display(),
+                        mirror.display()
+                    )
+                })?;
+            }
+            copied
+        } else {
+            let copied = |bytes| {
+                event(ExportFileEvent::Copied {
+                    source: entry.path.clone(),
+                    bytes,
+                })
+            };
+            let copy = match &stream {
+                Some(stream) => {
+                    copy_stream_with_hash(
+                        stream,
+                        &targets,
+                        &options.hash_algorithms,
+                        throttle,
+                        &options.io,
+                        &copied,
+                    )
+                    .await
+                }
+                None => {
+                    copy_with_hash(
+                        &entry.path,
+                        &targets,
+                        &options.hash_algorithms,
+                        throttle,
+                        &options.io,
+                        &copied,
+                    )
+                    .await
+                }
+            };
+            let (bytes, hash, digests) = copy.with_context(|| {
+                format!(
+                    "Failed to copy {} to {}",
+                    entry.path.display(),
+                    dest_path.display()
+                )
+            })?;
+            (bytes, hash, digests, None)
+        };
 
-    // Verify hash if requested
-    if options.verify_hash {
-        let dest_hash = compute_file_hash(&dest_path).await?;
-        if hash != dest_hash {
-            fs::remove_file(&dest_path).await.ok();
-            anyhow::bail!(
-                "Hash mismatch for {}: source={}, dest={}",
-                entry.path.display(),
-                hash,
-                dest_hash
-            );
+        if !options.verify_hash {
+            return Ok((bytes, hash, digests, history));
+        }
+        event(ExportFileEvent::Verifying {
+            source: entry.path.clone(),
+        });
+
+        // Report the first copy that disagrees with the source
+        let mut dest_hash = hash.clone();
+        for target in &targets {
+            let target_hash = compute_file_hash(target).await?;
+            if target_hash != hash {
+                dest_hash = target_hash;
+                break;
+            }
+        }
+        if hash == dest_hash && note.is_none() {
+            if attempt > 1 {
+                tracing::info!(
+                    path = %entry.path.display(),
+                    attempt,
+                    "Hash verified after retry"
+                );
+            }
+            return Ok((bytes, hash, digests, history));
+        }
+
+        for target in &targets {
+            fs::remove_file(target).await.ok();
+        }
+        tracing::warn!(
+            path = %entry.path.display(),
+            attempt,
+            max_attempts,
+            source_hash = %hash,
+            dest_hash = %dest_hash,
+            "Export verification failed"
+        );
+        history.push(RetryAttempt {
+            attempt,
+            source_hash: hash,
+            dest_hash,
+            sector_reader: use_sector_reader,
+            note,
+            at: Utc::now().to_rfc3339(),
+        });
+
+        if attempt < max_attempts {
+            tokio::time::sleep(Duration::from_millis(100 * u64::from(attempt))).await;
         }
     }
 
-    Ok((bytes, hash))
+    let last = history.last().expect("at least one attempt was made");
+    // Zero-filled blocks, not a bad copy, kept the last attempt from verifying
+    if let Some(note) = &last.note {
+        return Err(DrillError::BadSector {
+            path: entry.path.clone(),
+            detail: note.clone(),
+        }
+        .into());
+    }
+    Err(DrillError::VerificationFailed {
+        path: entry.path.clone(),
+        attempts: history.len(),
+        expected: last.source_hash.clone(),
+        actual: last.dest_hash.clone(),
+    }
+    .into())
+}
+
+/// Re-copy a file block-by-block through the multi-pass sector reader,
+/// which narrows failed blocks down to single sectors.
+///
+/// Unreadable blocks are zero-filled by the sector reader, which would make
+/// the hashes agree on a damaged copy, so any zeroed bytes are returned as a
+/// note and the attempt is treated as failed. Extra digests are taken from
+/// the finished copy.
+async fn copy_with_sector_reader(
+    source: &Path,
+    dest: &Path,
+    algorithms: &[HashAlgorithm],
+    throttle: &Throttle,
+    io: &IoOptions,
+) -> Result<(u64, String, Digests, Option<String>)> {
+    let source = source.to_path_buf();
+    let dest = dest.to_path_buf();
+    let algorithms = algorithms.to_vec();
+    let throttle = throttle.clone();
+    let io = *io;
+
+    tokio::task::spawn_blocking(move || {
+        let map = SectorReader::multi_pass()
+            .with_io(io)
+            .read_with_sector_tracking(&source)?;
+        let copied = export_with_bad_sector_handling(&source, &dest, &map, &io)?;
+        throttle.consume(copied.total_bytes);
+        let note = (copied.bytes_zeroed > 0).then(|| {
+            format!(
+                "{} bytes unreadable in {} blocks",
+                copied.bytes_zeroed,
+                map.bad_blocks.len()
+            )
+        });
+        let digests = if algorithms.is_empty() {
+            Digests::new()
+        } else {
+            compute_digests(&dest, &algorithms)?
+        };
+        Ok((copied.total_bytes, copied.blake3_hash, digests, note))
+    })
+    .await?
+}
+
+/// Where the mirror copy of `dest_path` goes: the same path relative to
+/// the mirror root as `dest_path` has relative to `dest`
+fn mirror_path(dest_path: &Path, options: &ExportOptions) -> Option<PathBuf> {
+    let mirror = options.mirror.as_ref()?;
+    let relative = dest_path
+        .strip_prefix(&options.dest)
+        .unwrap_or_else(|_| Path::new(dest_path.file_name().unwrap_or_default()));
+    Some(mirror.join(relative))
 }
 
 /// Get destination path for a file
-fn get_dest_path(source: &Path, options: &ExportOptions) -> PathBuf {
+fn get_dest_path(entry: &FileEntry, options: &ExportOptions) -> PathBuf {
+    let source = entry.path.as_path();
+    let file_name = dest_file_name(entry, options);
+    if let Some(organize_by) = options.organize_by {
+        return options
+            .dest
+            .join(organize_dir(entry, organize_by))
+            .join(file_name);
+    }
     if options.preserve_structure {
-        // Try to preserve directory structure
-        if let Some(file_name) = source.file_name() {
-            // Get relative path components
-            let components: Vec<_> = source
-                .components()
-                .skip(1) // Skip root
-                .collect();
+        // Get relative path components
+        let components: Vec<_> = source
+            .components()
+            .skip(1) // Skip root
+            .collect();
 
-            if components.len() > 1 {
-                let mut dest = options.dest.clone();
-                for comp in components {
-                    dest.push(comp);
+        if components.len() > 1 {
+            let mut dest = options.dest.clone();
+            for comp in &components[..components.len() - 1] {
+                dest.push(comp);
+            }
+            return dest.join(file_name);
+        }
+    }
+    options.dest.join(file_name)
+}
+
+/// The name a file gets in the destination: its repaired name when it was
+/// mis-encoded, else its name on the source, without the compression
+/// extension when it is decompressed
+fn dest_file_name(entry: &FileEntry, options: &ExportOptions) -> OsString {
+    let name = entry.path.file_name().unwrap_or_default();
+    let name = match entry.metadata.get("repaired_name") {
+        Some(repaired) => repaired.into(),
+        None => options
+            .repair_names
+            .as_ref()
+            .and_then(|repair| repair.repair(name))
+            .map_or_else(|| name.to_os_string(), |repaired| repaired.name.into()),
+    };
+    if matches!(
+        StreamSource::of(entry, options),
+        Some(StreamSource::Compressed(..))
+    ) {
+        return decompressed_name(&name);
+    }
+    name
+}
+
+/// Apply the collision policy to a destination path.
+///
+/// Returns `None` when the file should be skipped. Paths handed out earlier
+/// in the batch count as taken even before their copy has started.
+fn resolve_collision(
+    mut dest: PathBuf,
+    policy: CollisionPolicy,
+    claimed: &mut HashSet<PathBuf>,
+    exists: impl Fn(&Path) -> bool,
+) -> Result<Option<PathBuf>> {
+    let taken = |claimed: &HashSet<PathBuf>, path: &Path| claimed.contains(path) || exists(path);
+    if taken(claimed, &dest) {
+        match policy {
+            CollisionPolicy::Skip => return Ok(None),
+            CollisionPolicy::Error => {
+                anyhow::bail!("Destination already exists: {}", dest.display())
+            }
+            // Two files of one batch must not be copied onto the same path
+            // concurrently, so only files from before the export are replaced
+            CollisionPolicy::Overwrite if !claimed.contains(&dest) => {}
+            CollisionPolicy::Overwrite | CollisionPolicy::Rename => {
+                let stem = dest
+                    .file_stem()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string();
+                let ext = dest.extension().map(|e| e.to_string_lossy().to_string());
+                dest = (1u32..)
+                    .map(|n| {
+                        dest.with_file_name(match &ext {
+                            Some(ext) => format!("{}_{}.{}", stem, n, ext),
+                            None => format!("{}_{}", stem, n),
+                        })
+                    })
+                    .find(|candidate| !taken(claimed, candidate))
+                    .expect("some suffix is free");
+            }
+        }
+    }
+    claimed.insert(dest.clone());
+    Ok(Some(dest))
+}
+
+/// Folder (relative to the destination) a file is sorted into
+fn organize_dir(entry: &FileEntry, organize_by: OrganizeBy) -> PathBuf {
+    let modified = || {
+        entry
+            .modified
+            .map(|m| (format_timestamp(&m, "%Y"), format_timestamp(&m, "%m")))
+    };
+    let year_month = match organize_by {
+        OrganizeBy::DateTaken => date_taken(entry).or_else(modified),
+        OrganizeBy::DateModified => modified(),
+        OrganizeBy::FileType => {
+            return PathBuf::from(format!("{:?}", entry.file_type).to_lowercase());
+        }
+    };
+    match year_month {
+        Some((year, month)) => PathBuf::from(year).join(month),
+        None => PathBuf::from(UNDATED_DIR),
+    }
+}
+
+/// Year and month of the EXIF capture date, from the index when it was
+/// built with `--metadata`, otherwise read from the photo itself
+fn date_taken(entry: &FileEntry) -> Option<(String, String)> {
+    let date = match entry.metadata.get("date_taken") {
+        Some(date) => date.clone(),
+        None if entry.file_type == FileType::Image => {
+            extract_metadata(&entry.path, FileType::Image).remove("date_taken")?
+        }
+        None => return None,
+    };
+    // "2021-07-14 18:02:11"; cameras with an unset clock write zeros
+    let year = date.get(..4)?;
+    let month = date.get(5..7)?;
+    let valid = year.bytes().all(|b| b.is_ascii_digit())
+        && year != "0000"
+        && month.parse::<u32>().is_ok_and(|m| (1..=12).contains(&m));
+    valid.then(|| (year.to_string(), month.to_string()))
+}
+
+/// A piece of a source on its way to the destinations
+enum CopyChunk {
+    Data(Vec<u8>),
+    /// Bytes of a hole in a sparse source, skipped in the copies
+    Hole(u64),
+}
+
+/// Copy a file to every destination in one read, computing its blake3
+/// hash and the extra `algorithms` digests on the way. `copied` is told the
+/// running byte count after each buffer. Holes in a sparse source are
+/// hashed as the zeros they read as but seeked over in the copies, so they
+/// stay holes.
+async fn copy_with_hash(
+    source: &Path,
+    dests: &[&Path],
+    algorithms: &[HashAlgorithm],
+    throttle: &Throttle,
+    io: &IoOptions,
+    copied: &(dyn Fn(u64) + Sync),
+) -> Result<(u64, String, Digests)> {
+    let len = fs::metadata(source).await?.len();
+    let ranges = std::fs::File::open(source)
+        .ok()
+        .and_then(|file| sparse::data_ranges(&file));
+    let extents = match &ranges {
+        Some(ranges) => sparse::extents(ranges, len),
+        None => vec![Extent::Data(0..len)],
+    };
+
+    // The bounded channel holds the reader at most a queue depth of blocks
+    // ahead of the writers
+    let (tx, rx) = tokio::sync::mpsc::channel(io.queue_depth.max(1));
+    let path = source.to_path_buf();
+    let io = *io;
+    let block_size = io.block_size.unwrap_or(COPY_BLOCK_SIZE);
+    let reader = tokio::task::spawn_blocking(move || {
+        let stopped = || anyhow::anyhow!("Copy stopped reading");
+        for extent in extents {
+            match extent {
+                Extent::Hole(range) => tx
+                    .blocking_send(Ok(CopyChunk::Hole(range.end - range.start)))
+                    .map_err(|_| stopped())?,
+                Extent::Data(range) => {
+                    iobackend::stream(&path, range, block_size, &io, |_, block| {
+                        tx.blocking_send(block.map(|b| CopyChunk::Data(b.to_vec())))
+                            .map_err(|_| stopped())
+                    })?
                 }
-                return dest;
             }
+        }
+        anyhow::Ok(())
+    });
 
-            options.dest.join(file_name)
-        } else {
-            options.dest.join(source.file_name().unwrap_or_default())
+    let sparse_len = ranges.is_some().then_some(len);
+    write_copies(rx, reader, dests, sparse_len, algorithms, throttle, copied).await
+}
+
+/// Run the export hooks over a source before it is copied
+async fn scan_source(entry: &FileEntry, options: &ExportOptions) -> Result<Vec<Detection>> {
+    if options.hooks.is_empty() || options.dry_run {
+        return Ok(Vec::new());
+    }
+    let entry = entry.clone();
+    let options = options.clone();
+    tokio::task::spawn_blocking(move || run_hooks(&entry, &options)).await?
+}
+
+/// Show each hook the contents of `entry` as they will be exported
+fn run_hooks(entry: &FileEntry, options: &ExportOptions) -> Result<Vec<Detection>> {
+    let stream = StreamSource::of(entry, options);
+    let mut detections = Vec::new();
+    for hook in &options.hooks {
+        let scan = |data: &mut dyn Read| hook.scan(&entry.path, data);
+        let found = match &stream {
+            Some(stream) => stream.read(scan),
+            None => std::fs::File::open(&entry.path)
+                .with_context(|| format!("Failed to open {}", entry.path.display()))
+                .and_then(|mut file| scan(&mut file)),
+        }
+        .with_context(|| format!("{} failed on {}", hook.name(), entry.path.display()))?;
+        if let Some(finding) = found {
+            tracing::warn!(
+                "{} flagged {}: {}",
+                hook.name(),
+                entry.path.display(),
+                finding
+            );
+            detections.push(Detection {
+                hook: hook.name().to_string(),
+                finding,
+                quarantined: options.on_detection == DetectionAction::Quarantine,
+            });
         }
-    } else {
-        options.dest.join(source.file_name().unwrap_or_default())
     }
+    Ok(detections)
 }
 
-/// Copy file and compute blake3 hash simultaneously
-async fn copy_with_hash(source: &Path, dest: &Path) -> Result<(u64, String)> {
-    let source_file = fs::File::open(source).await?;
-    let dest_file = fs::File::create(dest).await?;
+/// Where a file bound for `dest` goes instead when quarantined: the same
+/// place relative to the quarantine folder under `root`
+fn quarantine_path(dest: &Path, root: &Path) -> PathBuf {
+    root.join(QUARANTINE_DIR)
+        .join(dest.strip_prefix(root).unwrap_or(dest))
+}
 
-    let mut reader = BufReader::new(source_file);
-    let mut writer = BufWriter::new(dest_file);
-    let mut hasher = blake3::Hasher::new();
+/// A source read front to back rather than by range: a file inside an
+/// archive or a shadow copy, or a compressed file being decompressed
+#[derive(Clone)]
+enum StreamSource {
+    Member(ArchiveMember),
+    Snapshot(SnapshotFile),
+    Compressed(PathBuf, CompressedFormat),
+}
 
-    let mut total_bytes = 0u64;
-    let mut buffer = vec![0u8; 64 * 1024]; // 64KB buffer
+impl StreamSource {
+    fn of(entry: &FileEntry, options: &ExportOptions) -> Option<Self> {
+        if let Some(member) = ArchiveMember::of(entry) {
+            return Some(Self::Member(member));
+        }
+        if let Some(file) = SnapshotFile::of(entry) {
+            return Some(Self::Snapshot(file));
+        }
+        if !options.decompress {
+            return None;
+        }
+        CompressedFormat::detect(&entry.path)
+            .map(|format| Self::Compressed(entry.path.clone(), format))
+    }
 
-    loop {
-        let bytes_read = reader.read(&mut buffer).await?;
-        if bytes_read == 0 {
-            break;
+    /// Run `f` on a reader of the decoded contents
+    fn read<T>(&self, f: impl FnOnce(&mut dyn Read) -> Result<T>) -> Result<T> {
+        match self {
+            Self::Member(member) => member.read(f),
+            Self::Snapshot(file) => file.read(f),
+            Self::Compressed(path, format) => f(&mut format.open(path)?),
         }
+    }
+}
 
-        hasher.update(&buffer[..bytes_read]);
-        writer.write_all(&buffer[..bytes_read]).await?;
-        total_bytes += bytes_read as u64;
+/// [`copy_with_hash`] for a source that is decoded on the way
+async fn copy_stream_with_hash(
+    source: &StreamSource,
+    dests: &[&Path],
+    algorithms: &[HashAlgorithm],
+    throttle: &Throttle,
+    io: &IoOptions,
+    copied: &(dyn Fn(u64) + Sync),
+) -> Result<(u64, String, Digests)> {
+    let (tx, rx) = tokio::sync::mpsc::channel(io.queue_depth.max(1));
+    let source = source.clone();
+    let io = *io;
+    let reader = tokio::task::spawn_blocking(move || {
+        source.read(|reader| {
+            let mut buffer = vec![0u8; io.block_size.unwrap_or(COPY_BLOCK_SIZE)];
+            loop {
+                let n = reader.read(&mut buffer)?;
+                if n == 0 {
+                    return Ok(());
+                }
+                tx.blocking_send(Ok(CopyChunk::Data(buffer[..n].to_vec())))
+                    .map_err(|_| anyhow::anyhow!("Copy stopped reading"))?;
+            }
+        })
+    });
+    write_copies(rx, reader, dests, None, algorithms, throttle, copied).await
+}
+
+/// Write what a copy reader sends to every destination, hashing it on the
+/// way. `sparse_len` is the source length when the copies are sparse.
+async fn write_copies(
+    mut rx: tokio::sync::mpsc::Receiver<std::io::Result<CopyChunk>>,
+    reader: tokio::task::JoinHandle<Result<()>>,
+    dests: &[&Path],
+    sparse_len: Option<u64>,
+    algorithms: &[HashAlgorithm],
+    throttle: &Throttle,
+    copied: &(dyn Fn(u64) + Sync),
+) -> Result<(u64, String, Digests)> {
+    let mut writers = Vec::with_capacity(dests.len());
+    for dest in dests {
+        let mut file = fs::File::create(dest).await?;
+        if sparse_len.is_some() {
+            let std_file = file.into_std().await;
+            sparse::mark_sparse(&std_file);
+            file = fs::File::from_std(std_file);
+        }
+        writers.push(BufWriter::new(file));
     }
+    let mut hasher = blake3::Hasher::new();
+    let mut digests = MultiHasher::new(algorithms);
 
-    writer.flush().await?;
+    let mut total_bytes = 0u64;
+    while let Some(chunk) = rx.recv().await {
+        match chunk? {
+            CopyChunk::Data(buffer) => {
+                hasher.update(&buffer);
+                digests.update(&buffer);
+                for writer in &mut writers {
+                    writer.write_all(&buffer).await?;
+                }
+                total_bytes += buffer.len() as u64;
+                throttle.consume_async(buffer.len() as u64).await;
+            }
+            CopyChunk::Hole(hole) => {
+                let zeros = [0u8; 64 * 1024];
+                let mut left = hole;
+                while left > 0 {
+                    let n = left.min(zeros.len() as u64) as usize;
+                    hasher.update(&zeros[..n]);
+                    digests.update(&zeros[..n]);
+                    left -= n as u64;
+                }
+                for writer in &mut writers {
+                    writer.seek(SeekFrom::Current(hole as i64)).await?;
+                }
+                total_bytes += hole;
+            }
+        }
+        copied(total_bytes);
+    }
+    reader.await??;
+
+    for writer in &mut writers {
+        writer.flush().await?;
+        if let Some(len) = sparse_len {
+            // A trailing hole is only there once the length is set
+            writer.get_ref().set_len(len).await?;
+        }
+    }
 
     let hash = hasher.finalize();
     let hash_hex = hex::encode(hash.as_bytes());
 
-    Ok((total_bytes, hash_hex))
+    Ok((total_bytes, hash_hex, digests.finish()))
+}
+
+/// Compute blake3 hash of a file, blocking
+fn file_hash(path: &Path) -> Result<String> {
+    let mut hasher = blake3::Hasher::new();
+    std::io::copy(&mut std::fs::File::open(path)?, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize().as_bytes()))
 }
 
 /// Compute blake3 hash of a file
@@ -379,10 +1569,24 @@ mod tests {
             .unwrap();
 
         // Copy with hash
-        let (bytes, hash) = copy_with_hash(&source_path, &dest_path).await.unwrap();
+        let (bytes, hash, digests) = copy_with_hash(
+            &source_path,
+            &[&dest_path],
+            &[HashAlgorithm::Md5, HashAlgorithm::Sha256],
+            &Throttle::default(),
+            &IoOptions::default(),
+            &|_| {},
+        )
+        .await
+        .unwrap();
 
         assert_eq!(bytes, 21);
         assert!(!hash.is_empty());
+        assert_eq!(
+            digests,
+            compute_digests(&dest_path, &[HashAlgorithm::Md5, HashAlgorithm::Sha256]).unwrap()
+        );
+        assert_eq!(digests[&HashAlgorithm::Md5].len(), 32);
 
         // Verify content
         let content = fs::read_to_string(&dest_path).await.unwrap();
@@ -393,6 +1597,37 @@ mod tests {
         assert_eq!(hash, verify_hash);
     }
 
+    #[tokio::test]
+    async fn test_copy_with_hash_sparse_source() {
+        use std::io::{Seek, Write};
+
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("disk.img");
+        let mut file = std::fs::File::create(&source).unwrap();
+        file.set_len(8 << 20).unwrap();
+        file.seek(SeekFrom::Start(1 << 20)).unwrap();
+        file.write_all(b"partition table").unwrap();
+        drop(file);
+
+        let dest = dir.path().join("copy.img");
+        let (bytes, hash, _) = copy_with_hash(
+            &source,
+            &[&dest],
+            &[],
+            &Throttle::default(),
+            &IoOptions::default(),
+            &|_| {},
+        )
+        .await
+        .unwrap();
+
+        // Holes are hashed as zeros, so the hash matches a plain read
+        assert_eq!(bytes, 8 << 20);
+        assert_eq!(hash, compute_file_hash(&source).await.unwrap());
+        assert_eq!(hash, compute_file_hash(&dest).await.unwrap());
+        assert_eq!(std::fs::metadata(&dest).unwrap().len(), 8 << 20);
+    }
+
     #[tokio::test]
     async fn test_exporter_basic() {
         let source_dir = tempdir().unwrap();
@@ -412,6 +1647,7 @@ mod tests {
             hash: None,
             has_bad_sectors: false,
             thumbnail: None,
+            metadata: Default::default(),
         };
 
         let options = ExportOptions {
@@ -421,6 +1657,24 @@ mod tests {
             continue_on_error: false,
             create_manifest: true,
             dry_run: false,
+            hash_retries: DEFAULT_HASH_RETRIES,
+            retry_with_sector_reader: false,
+            organize_by: None,
+            on_conflict: CollisionPolicy::Rename,
+            archive: None,
+            remote: None,
+            max_bytes_per_sec: None,
+            resume: false,
+            mirror: None,
+            index_started: None,
+            hash_algorithms: Vec::new(),
+            io: IoOptions::default(),
+            workers: None,
+            skip_space_check: false,
+            repair_names: None,
+            decompress: false,
+            hooks: Vec::new(),
+            on_detection: DetectionAction::default(),
         };
 
         let exporter = Exporter::new(options);
@@ -430,4 +1684,693 @@ mod tests {
         assert_eq!(result.failed, 0);
         assert!(result.manifest_path.is_some());
     }
+
+    #[test]
+    fn test_organize_by_dest_paths() {
+        use chrono::TimeZone;
+
+        let mut entry = FileEntry {
+            path: PathBuf::from("/recovered/DCIM/IMG_0042.JPG"),
+            size: 10,
+            file_type: crate::core::FileType::Image,
+            extension: "jpg".to_string(),
+            modified: Some(Utc.with_ymd_and_hms(2023, 3, 15, 12, 0, 0).unwrap()),
+            created: None,
+            hash: None,
+            has_bad_sectors: false,
+            thumbnail: None,
+            metadata: Default::default(),
+        };
+        let mut options = ExportOptions {
+            dest: PathBuf::from("/out"),
+            organize_by: Some(OrganizeBy::DateTaken),
+            ..Default::default()
+        };
+        let dest = |entry: &FileEntry, options: &ExportOptions| get_dest_path(entry, options);
+
+        // No EXIF date (the file does not exist): falls back to mtime
+        assert_eq!(
+            dest(&entry, &options),
+            PathBuf::from("/out/2023/03/IMG_0042.JPG")
+        );
+
+        entry
+            .metadata
+            .insert("date_taken".into(), "2021-07-14 18:02:11".into());
+        assert_eq!(
+            dest(&entry, &options),
+            PathBuf::from("/out/2021/07/IMG_0042.JPG")
+        );
+
+        // Unset camera clock is ignored
+        entry
+            .metadata
+            .insert("date_taken".into(), "0000-00-00 00:00:00".into());
+        assert_eq!(
+            dest(&entry, &options),
+            PathBuf::from("/out/2023/03/IMG_0042.JPG")
+        );
+
+        options.organize_by = Some(OrganizeBy::FileType);
+        assert_eq!(
+            dest(&entry, &options),
+            PathBuf::from("/out/image/IMG_0042.JPG")
+        );
+
+        options.organize_by = Some(OrganizeBy::DateModified);
+        entry.modified = None;
+        assert_eq!(
+            dest(&entry, &options),
+            PathBuf::from("/out/undated/IMG_0042.JPG")
+        );
+    }
+
+    #[test]
+    fn test_repaired_names_in_dest_paths() {
+        let mut entry = FileEntry {
+            path: PathBuf::from("/recovered/docs/ÄÎÊÓÌÅÍÒ.txt"),
+            size: 10,
+            file_type: crate::core::FileType::Document,
+            extension: "txt".to_string(),
+            modified: None,
+            created: None,
+            hash: None,
+            has_bad_sectors: false,
+            thumbnail: None,
+            metadata: Default::default(),
+        };
+        let mut options = ExportOptions {
+            dest: PathBuf::from("/out"),
+            preserve_structure: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            get_dest_path(&entry, &options),
+            PathBuf::from("/out/recovered/docs/ÄÎÊÓÌÅÍÒ.txt")
+        );
+
+        options.repair_names = Some(NameRepair::new(&["windows-1251"]).unwrap());
+        assert_eq!(
+            get_dest_path(&entry, &options),
+            PathBuf::from("/out/recovered/docs/ДОКУМЕНТ.txt")
+        );
+
+        // Names repaired while indexing are used without repairing again
+        options.repair_names = None;
+        options.preserve_structure = false;
+        entry
+            .metadata
+            .insert("repaired_name".into(), "ДОКУМЕНТ.txt".into());
+        assert_eq!(
+            get_dest_path(&entry, &options),
+            PathBuf::from("/out/ДОКУМЕНТ.txt")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_export_archive_member() {
+        use std::io::Write;
+
+        let source_dir = tempdir().unwrap();
+        let dest_dir = tempdir().unwrap();
+        let checkpoint_dir = tempdir().unwrap();
+        let zip_path = source_dir.path().join("backup.zip");
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&zip_path).unwrap());
+        zip.start_file(
+            "docs/taxes_2020.pdf",
+            zip::write::SimpleFileOptions::default(),
+        )
+        .unwrap();
+        zip.write_all(b"%PDF-1.4 taxes").unwrap();
+        zip.finish().unwrap();
+        let archive = FileEntry::new(zip_path.clone(), &std::fs::metadata(&zip_path).unwrap());
+        let members = crate::core::member_entries(&archive).unwrap();
+
+        let result = Exporter::new(ExportOptions {
+            dest: dest_dir.path().to_path_buf(),
+            verify_hash: true,
+            ..Default::default()
+        })
+        .with_checkpoint_manager(CheckpointManager::with_dir(
+            checkpoint_dir.path().to_path_buf(),
+        ))
+        .export_batch(&members, |_| {})
+        .await
+        .unwrap();
+        assert_eq!((result.successful, result.failed), (1, 0));
+        assert_eq!(result.total_bytes, 14);
+        assert_eq!(
+            std::fs::read(dest_dir.path().join("taxes_2020.pdf")).unwrap(),
+            b"%PDF-1.4 taxes"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_export_decompressed() {
+        use std::io::Write;
+
+        let source_dir = tempdir().unwrap();
+        let dest_dir = tempdir().unwrap();
+        let checkpoint_dir = tempdir().unwrap();
+        let path = source_dir.path().join("syslog.2.gz");
+        let mut gz = flate2::write::GzEncoder::new(
+            std::fs::File::create(&path).unwrap(),
+            flate2::Compression::default(),
+        );
+        gz.write_all(b"Oct 18 03:14:07 host sshd[42]: Accepted\n")
+            .unwrap();
+        gz.finish().unwrap();
+        let entry = FileEntry::new(path.clone(), &std::fs::metadata(&path).unwrap());
+
+        let result = Exporter::new(ExportOptions {
+            dest: dest_dir.path().to_path_buf(),
+            verify_hash: true,
+            create_manifest: true,
+            decompress: true,
+            ..Default::default()
+        })
+        .with_checkpoint_manager(CheckpointManager::with_dir(
+            checkpoint_dir.path().to_path_buf(),
+        ))
+        .export_batch(std::slice::from_ref(&entry), |_| {})
+        .await
+        .unwrap();
+        assert_eq!(result.successful, 1);
+        assert_eq!(
+            std::fs::read(dest_dir.path().join("syslog.2")).unwrap(),
+            b"Oct 18 03:14:07 host sshd[42]: Accepted\n"
+        );
+
+        let manifest: ExportManifest =
+            serde_json::from_str(&std::fs::read_to_string(result.manifest_path.unwrap()).unwrap())
+                .unwrap();
+        let exported = &manifest.entries[0];
+        assert_eq!(exported.size, 40);
+        let compressed = exported.compressed.as_ref().unwrap();
+        assert_eq!(compressed.format, "gzip");
+        assert_eq!(compressed.size, entry.size);
+        assert_eq!(
+            compressed.blake3_hash,
+            crate::dedup::hash_file(&path).unwrap()
+        );
+        assert_ne!(compressed.blake3_hash, exported.blake3_hash);
+    }
+
+    #[tokio::test]
+    async fn test_virus_scan_quarantines() {
+        let source_dir = tempdir().unwrap();
+        let dest_dir = tempdir().unwrap();
+        let checkpoint_dir = tempdir().unwrap();
+        let entries: Vec<FileEntry> = [("report.doc", "quarterly numbers"), ("setup.exe", "EICAR")]
+            .iter()
+            .map(|(name, contents)| {
+                let path = source_dir.path().join(name);
+                std::fs::write(&path, contents).unwrap();
+                FileEntry::new(path.clone(), &std::fs::metadata(&path).unwrap())
+            })
+            .collect();
+        let clamav = ClamAv::new(&hooks::tests::fake_clamd());
+        let exporter = |on_detection| {
+            Exporter::new(ExportOptions {
+                dest: dest_dir.path().to_path_buf(),
+                create_manifest: true,
+                on_conflict: CollisionPolicy::Overwrite,
+                hooks: vec![Arc::new(clamav.clone())],
+                on_detection,
+                ..Default::default()
+            })
+            .with_checkpoint_manager(CheckpointManager::with_dir(
+                checkpoint_dir.path().to_path_buf(),
+            ))
+        };
+
+        let result = exporter(DetectionAction::Quarantine)
+            .export_batch(&entries, |_| {})
+            .await
+            .unwrap();
+        assert_eq!((result.successful, result.detections), (2, 1));
+        assert!(dest_dir.path().join("report.doc").exists());
+        assert!(!dest_dir.path().join("setup.exe").exists());
+        assert!(dest_dir
+            .path()
+            .join(QUARANTINE_DIR)
+            .join("setup.exe")
+            .exists());
+        let manifest: ExportManifest =
+            serde_json::from_str(&std::fs::read_to_string(result.manifest_path.unwrap()).unwrap())
+                .unwrap();
+        let flagged: Vec<_> = manifest
+            .entries
+            .iter()
+            .filter(|e| !e.detections.is_empty())
+            .collect();
+        assert_eq!(flagged.len(), 1);
+        assert_eq!(
+            flagged[0].detections,
+            [Detection {
+                hook: "clamav".into(),
+                finding: "Eicar-Test-Signature".into(),
+                quarantined: true,
+            }]
+        );
+
+        let result = exporter(DetectionAction::Flag)
+            .export_batch(&entries, |_| {})
+            .await
+            .unwrap();
+        assert_eq!(result.detections, 1);
+        assert!(dest_dir.path().join("setup.exe").exists());
+
+        // A scanner that cannot be reached fails the file
+        let result = Exporter::new(ExportOptions {
+            dest: dest_dir.path().to_path_buf(),
+            continue_on_error: true,
+            hooks: vec![Arc::new(ClamAv::new("127.0.0.1:1"))],
+            ..Default::default()
+        })
+        .with_checkpoint_manager(CheckpointManager::with_dir(
+            checkpoint_dir.path().to_path_buf(),
+        ))
+        .export_batch(&entries, |_| {})
+        .await
+        .unwrap();
+        assert_eq!((result.successful, result.failed), (0, 2));
+    }
+
+    #[tokio::test]
+    async fn test_resume_skips_exported_files() {
+        let source_dir = tempdir().unwrap();
+        let dest_dir = tempdir().unwrap();
+        let checkpoint_dir = tempdir().unwrap();
+        let entries: Vec<FileEntry> = ["a.txt", "b.txt", "c.txt"]
+            .iter()
+            .map(|name| {
+                let path = source_dir.path().join(name);
+                std::fs::write(&path, format!("contents of {}", name)).unwrap();
+                FileEntry::new(path.clone(), &std::fs::metadata(&path).unwrap())
+            })
+            .collect();
+        let exporter = |resume| {
+            Exporter::new(ExportOptions {
+                dest: dest_dir.path().to_path_buf(),
+                verify_hash: true,
+                continue_on_error: true,
+                create_manifest: true,
+                resume,
+                ..Default::default()
+            })
+            .with_checkpoint_manager(CheckpointManager::with_dir(
+                checkpoint_dir.path().to_path_buf(),
+            ))
+        };
+        let checkpoints = CheckpointManager::with_dir(checkpoint_dir.path().to_path_buf());
+
+        // First run: c.txt is unreadable, so the checkpoint is kept
+        let c_path = entries[2].path.clone();
+        std::fs::rename(&c_path, source_dir.path().join("c.bak")).unwrap();
+        let result = exporter(false)
+            .export_batch(&entries, |_| {})
+            .await
+            .unwrap();
+        assert_eq!((result.successful, result.failed), (2, 1));
+        assert!(checkpoints.exists(dest_dir.path(), CheckpointPhase::Exporting));
+
+        // Resumed run copies only c.txt instead of renamed copies of all three
+        std::fs::rename(source_dir.path().join("c.bak"), &c_path).unwrap();
+        let result = exporter(true).export_batch(&entries, |_| {}).await.unwrap();
+        assert_eq!(
+            (result.successful, result.resumed, result.failed),
+            (1, 2, 0)
+        );
+        let mut names: Vec<_> = std::fs::read_dir(dest_dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["a.txt", "b.txt", "c.txt", MANIFEST_FILE]);
+        assert!(!checkpoints.exists(dest_dir.path(), CheckpointPhase::Exporting));
+
+        let manifest: ExportManifest = serde_json::from_str(
+            &std::fs::read_to_string(dest_dir.path().join(MANIFEST_FILE)).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(manifest.total_files, 3);
+        assert!(manifest.entries.iter().all(|e| !e.blake3_hash.is_empty()));
+    }
+
+    #[tokio::test]
+    async fn test_resume_keeps_name_of_resumed_copy() {
+        let source_dir = tempdir().unwrap();
+        let dest_dir = tempdir().unwrap();
+        let checkpoint_dir = tempdir().unwrap();
+        let entries: Vec<FileEntry> = ["one/x.txt", "two/x.txt"]
+            .iter()
+            .map(|name| {
+                let path = source_dir.path().join(name);
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(&path, format!("contents of {}", name)).unwrap();
+                FileEntry::new(path.clone(), &std::fs::metadata(&path).unwrap())
+            })
+            .collect();
+        let exporter = |resume| {
+            Exporter::new(ExportOptions {
+                dest: dest_dir.path().to_path_buf(),
+                on_conflict: CollisionPolicy::Overwrite,
+                verify_hash: true,
+                continue_on_error: true,
+                create_manifest: true,
+                resume,
+                ..Default::default()
+            })
+            .with_checkpoint_manager(CheckpointManager::with_dir(
+                checkpoint_dir.path().to_path_buf(),
+            ))
+        };
+
+        // First run: only one/x.txt gets copied
+        let two = entries[1].path.clone();
+        std::fs::rename(&two, source_dir.path().join("two.bak")).unwrap();
+        let result = exporter(false)
+            .export_batch(&entries, |_| {})
+            .await
+            .unwrap();
+        assert_eq!((result.successful, result.failed), (1, 1));
+
+        // The resumed copy keeps x.txt; two/x.txt is renamed, not copied over it
+        std::fs::rename(source_dir.path().join("two.bak"), &two).unwrap();
+        let result = exporter(true).export_batch(&entries, |_| {}).await.unwrap();
+        assert_eq!((result.successful, result.resumed), (1, 1));
+        assert_eq!(
+            std::fs::read_to_string(dest_dir.path().join("x.txt")).unwrap(),
+            "contents of one/x.txt"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dest_dir.path().join("x_1.txt")).unwrap(),
+            "contents of two/x.txt"
+        );
+        let manifest: ExportManifest = serde_json::from_str(
+            &std::fs::read_to_string(dest_dir.path().join(MANIFEST_FILE)).unwrap(),
+        )
+        .unwrap();
+        for entry in &manifest.entries {
+            assert_eq!(
+                file_hash(Path::new(&entry.dest_path)).unwrap(),
+                entry.blake3_hash
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resume_recopies_changed_files() {
+        let source_dir = tempdir().unwrap();
+        let dest_dir = tempdir().unwrap();
+        let checkpoint_dir = tempdir().unwrap();
+        let paths: Vec<PathBuf> = ["a.txt", "b.txt", "c.txt"]
+            .iter()
+            .map(|name| {
+                let path = source_dir.path().join(name);
+                std::fs::write(&path, format!("contents of {}", name)).unwrap();
+                path
+            })
+            .collect();
+        let entries = |paths: &[PathBuf]| -> Vec<FileEntry> {
+            paths
+                .iter()
+                .map(|p| FileEntry::new(p.clone(), &std::fs::metadata(p).unwrap()))
+                .collect()
+        };
+        let exporter = |resume| {
+            Exporter::new(ExportOptions {
+                dest: dest_dir.path().to_path_buf(),
+                verify_hash: true,
+                continue_on_error: true,
+                resume,
+                ..Default::default()
+            })
+            .with_checkpoint_manager(CheckpointManager::with_dir(
+                checkpoint_dir.path().to_path_buf(),
+            ))
+        };
+
+        // First run: c.txt is unreadable, so the checkpoint is kept
+        let batch = entries(&paths);
+        std::fs::rename(&paths[2], source_dir.path().join("c.bak")).unwrap();
+        let result = exporter(false).export_batch(&batch, |_| {}).await.unwrap();
+        assert_eq!((result.successful, result.failed), (2, 1));
+        std::fs::rename(source_dir.path().join("c.bak"), &paths[2]).unwrap();
+
+        // a.txt grew on the source; b.txt's copy was damaged in place
+        std::fs::write(&paths[0], "contents of a.txt, longer now").unwrap();
+        std::fs::write(dest_dir.path().join("b.txt"), "CONTENTS OF B.TXT").unwrap();
+
+        let result = exporter(true)
+            .export_batch(&entries(&paths), |_| {})
+            .await
+            .unwrap();
+        assert_eq!((result.successful, result.resumed), (3, 0));
+        assert_eq!(
+            std::fs::read_to_string(dest_dir.path().join("a.txt")).unwrap(),
+            "contents of a.txt, longer now"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dest_dir.path().join("b.txt")).unwrap(),
+            "contents of b.txt"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mirror_export() {
+        let source_dir = tempdir().unwrap();
+        let dest_dir = tempdir().unwrap();
+        let mirror_dir = tempdir().unwrap();
+        let checkpoint_dir = tempdir().unwrap();
+        let entries: Vec<FileEntry> = ["a.txt", "sub/b.txt"]
+            .iter()
+            .map(|name| {
+                let path = source_dir.path().join(name);
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(&path, format!("contents of {}", name)).unwrap();
+                FileEntry::new(path.clone(), &std::fs::metadata(&path).unwrap())
+            })
+            .collect();
+        // Taken in the mirror only, so the whole file is renamed in both
+        std::fs::write(mirror_dir.path().join("a.txt"), "unrelated").unwrap();
+
+        let result = Exporter::new(ExportOptions {
+            dest: dest_dir.path().to_path_buf(),
+            verify_hash: true,
+            create_manifest: true,
+            mirror: Some(mirror_dir.path().to_path_buf()),
+            ..Default::default()
+        })
+        .with_checkpoint_manager(CheckpointManager::with_dir(
+            checkpoint_dir.path().to_path_buf(),
+        ))
+        .export_batch(&entries, |_| {})
+        .await
+        .unwrap();
+        assert_eq!((result.successful, result.failed), (2, 0));
+
+        for root in [dest_dir.path(), mirror_dir.path()] {
+            assert_eq!(
+                std::fs::read_to_string(root.join("a_1.txt")).unwrap(),
+                "contents of a.txt"
+            );
+            assert!(root.join("b.txt").exists());
+            assert!(root.join(MANIFEST_FILE).exists());
+        }
+        assert!(!dest_dir.path().join("a.txt").exists());
+
+        // The proof lists both copies, and either side verifies on its own
+        let proof = proof::load_manifest(&mirror_dir.path().join(PROOF_MANIFEST_FILE)).unwrap();
+        assert_eq!(proof.total_files, 4);
+        assert_eq!(
+            proof.chain_of_custody.options_used["mirror"],
+            mirror_dir.path().display().to_string()
+        );
+        let kinds: Vec<_> = proof
+            .chain_of_custody
+            .events
+            .iter()
+            .map(|e| e.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                CustodyEventKind::ExportStarted,
+                CustodyEventKind::ExportCompleted
+            ]
+        );
+        assert!(proof::verify_manifest(&proof).unwrap().is_clean());
+
+        std::fs::write(mirror_dir.path().join("b.txt"), "tampered").unwrap();
+        let verify = proof::verify_manifest(&proof).unwrap();
+        assert_eq!((verify.verified, verify.failed), (3, 1));
+    }
+
+    #[tokio::test]
+    async fn test_collision_policies() {
+        let source_dir = tempdir().unwrap();
+        let entries: Vec<FileEntry> = ["a", "b"]
+            .iter()
+            .map(|dir| {
+                let path = source_dir.path().join(dir).join("IMG_0001.jpg");
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(&path, format!("photo from {}", dir)).unwrap();
+                FileEntry::new(path.clone(), &std::fs::metadata(&path).unwrap())
+            })
+            .collect();
+
+        let checkpoints = tempdir().unwrap();
+        let export = |on_conflict| {
+            let dest = tempdir().unwrap();
+            std::fs::write(dest.path().join("IMG_0001.jpg"), "already there").unwrap();
+            let exporter = Exporter::new(ExportOptions {
+                dest: dest.path().to_path_buf(),
+                verify_hash: true,
+                continue_on_error: true,
+                on_conflict,
+                ..Default::default()
+            })
+            .with_checkpoint_manager(CheckpointManager::with_dir(
+                checkpoints.path().to_path_buf(),
+            ));
+            let entries = entries.clone();
+            async move {
+                let result = exporter.export_batch(&entries, |_| {}).await.unwrap();
+                (dest, result)
+            }
+        };
+        let read = |dest: &tempfile::TempDir, name: &str| {
+            std::fs::read_to_string(dest.path().join(name)).unwrap()
+        };
+
+        let (dest, result) = export(CollisionPolicy::Rename).await;
+        assert_eq!(result.successful, 2);
+        assert_eq!(read(&dest, "IMG_0001.jpg"), "already there");
+        assert_eq!(read(&dest, "IMG_0001_1.jpg"), "photo from a");
+        assert_eq!(read(&dest, "IMG_0001_2.jpg"), "photo from b");
+
+        let (dest, result) = export(CollisionPolicy::Skip).await;
+        assert_eq!((result.successful, result.skipped), (0, 2));
+        assert_eq!(read(&dest, "IMG_0001.jpg"), "already there");
+
+        let (_dest, result) = export(CollisionPolicy::Error).await;
+        assert_eq!((result.successful, result.failed), (0, 2));
+        assert_eq!(result.errors.len(), 2);
+
+        let (dest, result) = export(CollisionPolicy::Overwrite).await;
+        assert_eq!(result.successful, 2);
+        assert_eq!(read(&dest, "IMG_0001.jpg"), "photo from a");
+        assert_eq!(read(&dest, "IMG_0001_1.jpg"), "photo from b");
+    }
+
+    #[tokio::test]
+    async fn test_file_events_follow_each_file() {
+        let source_dir = tempdir().unwrap();
+        let dest_dir = tempdir().unwrap();
+        let entries: Vec<FileEntry> = ["kept.txt", "taken.txt"]
+            .iter()
+            .map(|name| {
+                let path = source_dir.path().join(name);
+                std::fs::write(&path, vec![7u8; 100_000]).unwrap();
+                FileEntry::new(path.clone(), &std::fs::metadata(&path).unwrap())
+            })
+            .collect();
+        std::fs::write(dest_dir.path().join("taken.txt"), "already there").unwrap();
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&events);
+        let checkpoints = tempdir().unwrap();
+        let exporter = Exporter::new(ExportOptions {
+            dest: dest_dir.path().to_path_buf(),
+            verify_hash: true,
+            on_conflict: CollisionPolicy::Skip,
+            ..Default::default()
+        })
+        .with_checkpoint_manager(CheckpointManager::with_dir(
+            checkpoints.path().to_path_buf(),
+        ))
+        .with_file_events(Arc::new(move |event| sink.lock().push(event)));
+        exporter.export_batch(&entries, |_| {}).await.unwrap();
+
+        let events = events.lock();
+        let kept = &entries[0].path;
+        let for_kept: Vec<_> = events
+            .iter()
+            .filter(|e| match e {
+                ExportFileEvent::Started { source, .. }
+                | ExportFileEvent::Copied { source, .. }
+                | ExportFileEvent::Verifying { source }
+                | ExportFileEvent::Skipped { source, .. }
+                | ExportFileEvent::Finished { source, .. } => source == kept,
+            })
+            .collect();
+        assert!(matches!(for_kept[0], ExportFileEvent::Started { .. }));
+        assert!(matches!(
+            for_kept[for_kept.len() - 3],
+            ExportFileEvent::Copied { bytes: 100_000, .. }
+        ));
+        assert!(matches!(
+            for_kept[for_kept.len() - 2],
+            ExportFileEvent::Verifying { .. }
+        ));
+        let ExportFileEvent::Finished {
+            result: Ok((hash, 0)),
+            ..
+        } = for_kept[for_kept.len() - 1]
+        else {
+            panic!("kept.txt did not finish cleanly: {:?}", for_kept.last());
+        };
+        assert_eq!(hash, &hex::encode(blake3::hash(&[7u8; 100_000]).as_bytes()));
+        assert!(events.iter().any(|e| matches!(
+            e,
+            ExportFileEvent::Skipped { source, reason }
+                if source == &entries[1].path && reason == "destination exists"
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_copy_with_sector_reader() {
+        let source_dir = tempdir().unwrap();
+        let dest_dir = tempdir().unwrap();
+
+        let source_path = source_dir.path().join("blocks.bin");
+        let dest_path = dest_dir.path().join("blocks.bin");
+        let data: Vec<u8> = (0..3000u32).map(|i| (i % 251) as u8).collect();
+        fs::write(&source_path, &data).await.unwrap();
+
+        let (bytes, hash, digests, note) = copy_with_sector_reader(
+            &source_path,
+            &dest_path,
+            &[],
+            &Throttle::default(),
+            &IoOptions::default(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(bytes, 3000);
+        assert!(note.is_none());
+        assert!(digests.is_empty());
+        assert_eq!(fs::read(&dest_path).await.unwrap(), data);
+        assert_eq!(hash, compute_file_hash(&dest_path).await.unwrap());
+    }
+
+    #[test]
+    fn test_manifest_entry_without_retry_history() {
+        let json = r#"{
+            "source_path": "/src/a.txt",
+            "dest_path": "/out/a.txt",
+            "size": 1,
+            "blake3_hash": "00",
+            "exported_at": "2024-01-01T00:00:00Z",
+            "verified": true
+        }"#;
+        let entry: ManifestEntry = serde_json::from_str(json).unwrap();
+        assert!(entry.retry_history.is_empty());
+
+        let out = serde_json::to_string(&entry).unwrap();
+        assert!(!out.contains("retry_history"));
+    }
 }
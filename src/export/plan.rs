@@ -0,0 +1,415 @@
+//! Capacity planning before an export starts
+//!
+//! The selection is summed per destination file system with the space the
+//! file system adds on top: every file occupies whole clusters, needs a
+//! directory entry / file record, and gets a line in the manifest. The
+//! result is compared with the free space reported by the OS, so an export
+//! that cannot fit is refused before the first byte is copied rather than
+//! failing hours in.
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use super::ExportOptions;
+use crate::core::FileEntry;
+
+/// Estimated directory entry / file record per exported file (the size of
+/// an NTFS MFT record; ext4 and APFS need less)
+const FILE_RECORD_BYTES: u64 = 1024;
+
+/// Estimated manifest size per file (paths, hashes and timestamps as JSON)
+const MANIFEST_BYTES_PER_FILE: u64 = 512;
+
+/// Cluster size assumed when the file system does not report one
+const DEFAULT_CLUSTER_SIZE: u64 = 4096;
+
+/// Less free space than this fraction of the capacity left after the export
+/// counts as tight
+const TIGHT_FRACTION: f64 = 0.05;
+
+/// Space on the file system holding a path
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiskSpace {
+    /// Bytes an unprivileged writer can still use
+    pub available: u64,
+    pub capacity: u64,
+    /// Allocation unit every file is rounded up to
+    pub cluster_size: u64,
+    /// Identifies the file system, to spot a mirror on the same disk
+    device: Option<u64>,
+}
+
+/// How an export fits a destination
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpaceStatus {
+    Fits,
+    /// Fits, but leaves less than 5% of the disk free
+    Tight,
+    Insufficient,
+    /// Free space could not be determined (e.g. a remote target)
+    Unknown,
+}
+
+/// Space needed on one destination file system
+#[derive(Debug, Clone, Serialize)]
+pub struct DestinationPlan {
+    /// Destination directories on this file system
+    pub paths: Vec<String>,
+    /// File contents written here
+    pub data_bytes: u64,
+    /// Cluster slack, file records and manifests
+    pub overhead_bytes: u64,
+    pub available: Option<u64>,
+    pub capacity: Option<u64>,
+    pub cluster_size: Option<u64>,
+}
+
+impl DestinationPlan {
+    pub fn required(&self) -> u64 {
+        self.data_bytes + self.overhead_bytes
+    }
+
+    pub fn status(&self) -> SpaceStatus {
+        let (Some(available), Some(capacity)) = (self.available, self.capacity) else {
+            return SpaceStatus::Unknown;
+        };
+        let required = self.required();
+        if required > available {
+            SpaceStatus::Insufficient
+        } else if ((available - required) as f64) < capacity as f64 * TIGHT_FRACTION {
+            SpaceStatus::Tight
+        } else {
+            SpaceStatus::Fits
+        }
+    }
+}
+
+/// What an export will write and where, from [`super::Exporter::plan`]
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportPlan {
+    pub files: usize,
+    /// Total size of the selection
+    pub bytes: u64,
+    /// Largest single file
+    pub largest_file: u64,
+    pub destinations: Vec<DestinationPlan>,
+}
+
+impl ExportPlan {
+    pub(super) fn new(entries: &[FileEntry], options: &ExportOptions) -> Self {
+        let mut plan = Self {
+            files: entries.len(),
+            bytes: entries.iter().map(|e| e.size).sum(),
+            largest_file: entries.iter().map(|e| e.size).max().unwrap_or(0),
+            destinations: Vec::new(),
+        };
+
+        if let Some(target) = &options.remote {
+            plan.destinations.push(DestinationPlan {
+                paths: vec![target.to_string()],
+                data_bytes: plan.bytes,
+                overhead_bytes: manifest_bytes(entries, options),
+                available: None,
+                capacity: None,
+                cluster_size: None,
+            });
+            return plan;
+        }
+
+        let mut targets = vec![(options.dest.clone(), true)];
+        targets.extend(options.mirror.clone().map(|mirror| (mirror, false)));
+        let mut devices: Vec<Option<u64>> = Vec::new();
+        for (path, primary) in targets {
+            let space = disk_space(&path)
+                .map_err(|e| tracing::debug!("No free space for {}: {}", path.display(), e))
+                .ok();
+            let cluster_size = space.map_or(DEFAULT_CLUSTER_SIZE, |s| s.cluster_size);
+            let data_bytes = plan.bytes;
+            let mut overhead_bytes = if options.archive.is_some() {
+                // One file, and the manifest goes inside it
+                cluster_size + manifest_bytes(entries, options)
+            } else {
+                entries
+                    .iter()
+                    .map(|e| slack(e.size, cluster_size) + FILE_RECORD_BYTES)
+                    .sum::<u64>()
+                    + manifest_bytes(entries, options)
+            };
+            if !primary {
+                // The proof manifest lists both copies
+                overhead_bytes += manifest_bytes(entries, options);
+            }
+
+            let device = space.and_then(|s| s.device);
+            if let Some(i) = device.and_then(|d| devices.iter().position(|&known| known == Some(d)))
+            {
+                let shared = &mut plan.destinations[i];
+                shared.paths.push(path.display().to_string());
+                shared.data_bytes += data_bytes;
+                shared.overhead_bytes += overhead_bytes;
+                continue;
+            }
+            devices.push(device);
+            plan.destinations.push(DestinationPlan {
+                paths: vec![path.display().to_string()],
+                data_bytes,
+                overhead_bytes,
+                available: space.map(|s| s.available),
+                capacity: space.map(|s| s.capacity),
+                cluster_size: space.map(|s| s.cluster_size),
+            });
+        }
+        plan
+    }
+
+    /// Worst status over all destinations
+    pub fn status(&self) -> SpaceStatus {
+        let statuses: Vec<SpaceStatus> = self.destinations.iter().map(|d| d.status()).collect();
+        [
+            SpaceStatus::Insufficient,
+            SpaceStatus::Unknown,
+            SpaceStatus::Tight,
+        ]
+        .into_iter()
+        .find(|s| statuses.contains(s))
+        .unwrap_or(SpaceStatus::Fits)
+    }
+
+    /// Refuse an export that cannot fit, and warn about one that barely
+    /// does or cannot be checked
+    pub fn check(&self) -> anyhow::Result<()> {
+        for destination in &self.destinations {
+            let paths = destination.paths.join(" + ");
+            match destination.status() {
+                SpaceStatus::Insufficient => anyhow::bail!(
+                    "Not enough space on {}: export needs {}, {} free (run with --plan for the breakdown, or --no-space-check to try anyway)",
+                    paths,
+                    size(destination.required()),
+                    size(destination.available.unwrap_or(0))
+                ),
+                SpaceStatus::Tight => tracing::warn!(
+                    "{} will have less than {:.0}% free after this export ({} of {} free)",
+                    paths,
+                    TIGHT_FRACTION * 100.0,
+                    size(destination.available.unwrap_or(0) - destination.required()),
+                    size(destination.capacity.unwrap_or(0))
+                ),
+                SpaceStatus::Unknown => {
+                    tracing::warn!("Free space on {} is unknown; not checked", paths)
+                }
+                SpaceStatus::Fits => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ExportPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Export plan:")?;
+        writeln!(f, "  Files:        {}", self.files)?;
+        writeln!(f, "  Selection:    {}", size(self.bytes))?;
+        writeln!(f, "  Largest file: {}", size(self.largest_file))?;
+        for destination in &self.destinations {
+            writeln!(f)?;
+            writeln!(f, "  {}", destination.paths.join(" + "))?;
+            writeln!(f, "    Data:       {}", size(destination.data_bytes))?;
+            write!(f, "    Overhead:   {}", size(destination.overhead_bytes))?;
+            match destination.cluster_size {
+                Some(cluster) => writeln!(
+                    f,
+                    " (clusters of {}, file records, manifests)",
+                    size(cluster)
+                )?,
+                None => writeln!(f, " (manifests)")?,
+            }
+            writeln!(f, "    Required:   {}", size(destination.required()))?;
+            match (destination.available, destination.capacity) {
+                (Some(available), Some(capacity)) => writeln!(
+                    f,
+                    "    Free:       {} of {}",
+                    size(available),
+                    size(capacity)
+                )?,
+                _ => writeln!(f, "    Free:       unknown")?,
+            }
+            let verdict = match destination.status() {
+                SpaceStatus::Fits => "fits",
+                SpaceStatus::Tight => "fits, but leaves under 5% free",
+                SpaceStatus::Insufficient => "DOES NOT FIT",
+                SpaceStatus::Unknown => "not checked",
+            };
+            writeln!(f, "    Verdict:    {}", verdict)?;
+        }
+        Ok(())
+    }
+}
+
+fn manifest_bytes(entries: &[FileEntry], options: &ExportOptions) -> u64 {
+    if options.create_manifest || options.archive.is_some() || options.remote.is_some() {
+        entries.len() as u64 * MANIFEST_BYTES_PER_FILE
+    } else {
+        0
+    }
+}
+
+/// Unused tail of the last cluster of a file
+fn slack(size: u64, cluster_size: u64) -> u64 {
+    match size % cluster_size.max(1) {
+        0 => 0,
+        used => cluster_size - used,
+    }
+}
+
+fn size(bytes: u64) -> String {
+    humansize::format_size(bytes, humansize::BINARY)
+}
+
+/// Free space on the file system holding `path`, or on the one holding its
+/// nearest existing ancestor when `path` is yet to be created
+pub fn disk_space(path: &Path) -> io::Result<DiskSpace> {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()?.join(path)
+    };
+    let existing: PathBuf = absolute
+        .ancestors()
+        .find(|p| p.exists())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no existing ancestor"))?
+        .to_path_buf();
+    query_disk_space(&existing)
+}
+
+#[cfg(unix)]
+fn query_disk_space(path: &Path) -> io::Result<DiskSpace> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::fs::MetadataExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    // SAFETY: statvfs only writes into the zeroed struct we pass
+    let stat = unsafe {
+        let mut stat: libc::statvfs = std::mem::zeroed();
+        if libc::statvfs(c_path.as_ptr(), &mut stat) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        stat
+    };
+    let fragment = match stat.f_frsize as u64 {
+        0 => stat.f_bsize as u64,
+        n => n,
+    };
+    Ok(DiskSpace {
+        available: stat.f_bavail as u64 * fragment,
+        capacity: stat.f_blocks as u64 * fragment,
+        cluster_size: fragment.max(1),
+        device: Some(std::fs::metadata(path)?.dev()),
+    })
+}
+
+#[cfg(windows)]
+fn query_disk_space(path: &Path) -> io::Result<DiskSpace> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain([0]).collect();
+    let (mut available, mut capacity) = (0u64, 0u64);
+    // SAFETY: `wide` is NUL-terminated and the out-pointers are valid
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(
+            wide.as_ptr(),
+            &mut available,
+            &mut capacity,
+            std::ptr::null_mut(),
+        )
+    };
+    if ok == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(DiskSpace {
+        available,
+        capacity,
+        // NTFS default; the cluster size needs the volume root to query
+        cluster_size: DEFAULT_CLUSTER_SIZE,
+        device: None,
+    })
+}
+
+#[cfg(not(any(unix, windows)))]
+fn query_disk_space(_path: &Path) -> io::Result<DiskSpace> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "free space is only available on Unix and Windows",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_counts_cluster_slack_and_shared_disks() {
+        let dir = tempfile::tempdir().unwrap();
+        let entries: Vec<FileEntry> = [1u64, 4096, 5000]
+            .iter()
+            .enumerate()
+            .map(|(i, &len)| {
+                let path = dir.path().join(format!("{}.bin", i));
+                std::fs::write(&path, vec![0u8; len as usize]).unwrap();
+                FileEntry::new(path.clone(), &std::fs::metadata(&path).unwrap())
+            })
+            .collect();
+        let options = ExportOptions {
+            dest: dir.path().join("out"),
+            mirror: Some(dir.path().join("mirror")),
+            create_manifest: true,
+            ..Default::default()
+        };
+
+        let plan = ExportPlan::new(&entries, &options);
+        assert_eq!(plan.files, 3);
+        assert_eq!(plan.bytes, 9097);
+        assert_eq!(plan.largest_file, 5000);
+
+        // Both copies land on the temp directory's file system
+        assert_eq!(plan.destinations.len(), 1);
+        let shared = &plan.destinations[0];
+        assert_eq!(shared.paths.len(), 2);
+        assert_eq!(shared.data_bytes, 2 * 9097);
+        let cluster = shared.cluster_size.unwrap();
+        let per_copy = slack(1, cluster)
+            + slack(4096, cluster)
+            + slack(5000, cluster)
+            + 3 * (FILE_RECORD_BYTES + MANIFEST_BYTES_PER_FILE);
+        assert_eq!(
+            shared.overhead_bytes,
+            2 * per_copy + 3 * MANIFEST_BYTES_PER_FILE
+        );
+        assert_ne!(plan.status(), SpaceStatus::Unknown);
+
+        let full = DestinationPlan {
+            paths: vec!["/mnt/usb".into()],
+            data_bytes: 900,
+            overhead_bytes: 200,
+            available: Some(1000),
+            capacity: Some(64_000),
+            cluster_size: Some(4096),
+        };
+        assert_eq!(full.status(), SpaceStatus::Insufficient);
+        assert_eq!(
+            DestinationPlan {
+                available: Some(4000),
+                ..full.clone()
+            }
+            .status(),
+            SpaceStatus::Tight
+        );
+        assert_eq!(slack(4097, 4096), 4095);
+    }
+}
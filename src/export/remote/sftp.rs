@@ -0,0 +1,589 @@
+//! SFTP through a system libssh2 (loaded at runtime)
+
+use std::ffi::{c_char, c_int, c_long, c_uint, c_ulong, c_void, CString};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use anyhow::{bail, Context, Result};
+use libloading::Library;
+
+use super::{ancestors, load_library, RemoteFs, RemoteTarget, PASSWORD_ENV};
+
+/// Directory holding libssh2, checked before the system library path
+const LIBRARY_DIR_ENV: &str = "DIAMOND_DRILL_LIBSSH2_DIR";
+
+const DEFAULT_PORT: u16 = 22;
+const CHUNK: usize = 64 * 1024;
+
+const FXF_READ: c_ulong = 0x01;
+const FXF_WRITE: c_ulong = 0x02;
+const FXF_CREAT: c_ulong = 0x08;
+const FXF_TRUNC: c_ulong = 0x10;
+const OPENFILE: c_int = 0;
+const SFTP_STAT: c_int = 0;
+const DISCONNECT_BY_APPLICATION: c_int = 11;
+
+const KNOWNHOST_TYPE_PLAIN: c_int = 1;
+const KNOWNHOST_KEYENC_RAW: c_int = 1 << 16;
+const KNOWNHOST_KEY_SHIFT: c_int = 18;
+const KNOWNHOST_FILE_OPENSSH: c_int = 1;
+const KNOWNHOST_CHECK_MATCH: c_int = 0;
+const KNOWNHOST_CHECK_MISMATCH: c_int = 1;
+
+#[cfg(unix)]
+type RawSocket = c_int;
+#[cfg(windows)]
+type RawSocket = usize;
+
+type Session = c_void;
+type Sftp = c_void;
+type SftpHandle = c_void;
+type KnownHosts = c_void;
+type Agent = c_void;
+type AgentIdentity = c_void;
+
+#[repr(C)]
+#[derive(Default)]
+struct SftpAttributes {
+    flags: c_ulong,
+    filesize: u64,
+    uid: c_ulong,
+    gid: c_ulong,
+    permissions: c_ulong,
+    atime: c_ulong,
+    mtime: c_ulong,
+}
+
+/// libssh2 entry points, valid for as long as `_lib` is loaded
+struct LibSsh2 {
+    session_init_ex: unsafe extern "C" fn(
+        *const c_void,
+        *const c_void,
+        *const c_void,
+        *mut c_void,
+    ) -> *mut Session,
+    session_handshake: unsafe extern "C" fn(*mut Session, RawSocket) -> c_int,
+    session_hostkey: unsafe extern "C" fn(*mut Session, *mut usize, *mut c_int) -> *const c_char,
+    session_last_error:
+        unsafe extern "C" fn(*mut Session, *mut *mut c_char, *mut c_int, c_int) -> c_int,
+    session_disconnect_ex:
+        unsafe extern "C" fn(*mut Session, c_int, *const c_char, *const c_char) -> c_int,
+    session_free: unsafe extern "C" fn(*mut Session) -> c_int,
+    knownhost_init: unsafe extern "C" fn(*mut Session) -> *mut KnownHosts,
+    knownhost_readfile: unsafe extern "C" fn(*mut KnownHosts, *const c_char, c_int) -> c_int,
+    knownhost_checkp: unsafe extern "C" fn(
+        *mut KnownHosts,
+        *const c_char,
+        c_int,
+        *const c_char,
+        usize,
+        c_int,
+        *mut *mut c_void,
+    ) -> c_int,
+    knownhost_free: unsafe extern "C" fn(*mut KnownHosts),
+    agent_init: unsafe extern "C" fn(*mut Session) -> *mut Agent,
+    agent_connect: unsafe extern "C" fn(*mut Agent) -> c_int,
+    agent_list_identities: unsafe extern "C" fn(*mut Agent) -> c_int,
+    agent_get_identity:
+        unsafe extern "C" fn(*mut Agent, *mut *mut AgentIdentity, *mut AgentIdentity) -> c_int,
+    agent_userauth: unsafe extern "C" fn(*mut Agent, *const c_char, *mut AgentIdentity) -> c_int,
+    agent_disconnect: unsafe extern "C" fn(*mut Agent) -> c_int,
+    agent_free: unsafe extern "C" fn(*mut Agent),
+    userauth_publickey_fromfile_ex: unsafe extern "C" fn(
+        *mut Session,
+        *const c_char,
+        c_uint,
+        *const c_char,
+        *const c_char,
+        *const c_char,
+    ) -> c_int,
+    userauth_password_ex: unsafe extern "C" fn(
+        *mut Session,
+        *const c_char,
+        c_uint,
+        *const c_char,
+        c_uint,
+        *const c_void,
+    ) -> c_int,
+    sftp_init: unsafe extern "C" fn(*mut Session) -> *mut Sftp,
+    sftp_shutdown: unsafe extern "C" fn(*mut Sftp) -> c_int,
+    sftp_last_error: unsafe extern "C" fn(*mut Sftp) -> c_ulong,
+    sftp_open_ex: unsafe extern "C" fn(
+        *mut Sftp,
+        *const c_char,
+        c_uint,
+        c_ulong,
+        c_long,
+        c_int,
+    ) -> *mut SftpHandle,
+    sftp_read: unsafe extern "C" fn(*mut SftpHandle, *mut c_char, usize) -> isize,
+    sftp_write: unsafe extern "C" fn(*mut SftpHandle, *const c_char, usize) -> isize,
+    sftp_close_handle: unsafe extern "C" fn(*mut SftpHandle) -> c_int,
+    sftp_mkdir_ex: unsafe extern "C" fn(*mut Sftp, *const c_char, c_uint, c_long) -> c_int,
+    sftp_stat_ex:
+        unsafe extern "C" fn(*mut Sftp, *const c_char, c_uint, c_int, *mut SftpAttributes) -> c_int,
+    _lib: Library,
+}
+
+static LIBSSH2: OnceLock<Option<LibSsh2>> = OnceLock::new();
+
+/// The process-wide libssh2, or None when the library is missing
+fn instance() -> Option<&'static LibSsh2> {
+    LIBSSH2
+        .get_or_init(|| {
+            let lib = load_library(
+                "ssh2",
+                &["libssh2.so.1", "libssh2.1.dylib", "libssh2.dll"],
+                LIBRARY_DIR_ENV,
+            );
+            match lib.map(LibSsh2::bind) {
+                Some(Ok(lib)) => Some(lib),
+                Some(Err(e)) => {
+                    tracing::warn!("libssh2 found but unusable: {:#}", e);
+                    None
+                }
+                None => {
+                    tracing::info!("libssh2 not found; SFTP export is unavailable");
+                    None
+                }
+            }
+        })
+        .as_ref()
+}
+
+impl LibSsh2 {
+    fn bind(lib: Library) -> Result<Self> {
+        // SAFETY: the signatures match libssh2's public C API (libssh2.h,
+        // libssh2_sftp.h), and the pointers are kept alongside the library
+        // they came from
+        unsafe {
+            let init: unsafe extern "C" fn(c_int) -> c_int = *lib.get(b"libssh2_init\0")?;
+            if init(0) != 0 {
+                bail!("libssh2_init failed");
+            }
+            Ok(Self {
+                session_init_ex: *lib.get(b"libssh2_session_init_ex\0")?,
+                session_handshake: *lib.get(b"libssh2_session_handshake\0")?,
+                session_hostkey: *lib.get(b"libssh2_session_hostkey\0")?,
+                session_last_error: *lib.get(b"libssh2_session_last_error\0")?,
+                session_disconnect_ex: *lib.get(b"libssh2_session_disconnect_ex\0")?,
+                session_free: *lib.get(b"libssh2_session_free\0")?,
+                knownhost_init: *lib.get(b"libssh2_knownhost_init\0")?,
+                knownhost_readfile: *lib.get(b"libssh2_knownhost_readfile\0")?,
+                knownhost_checkp: *lib.get(b"libssh2_knownhost_checkp\0")?,
+                knownhost_free: *lib.get(b"libssh2_knownhost_free\0")?,
+                agent_init: *lib.get(b"libssh2_agent_init\0")?,
+                agent_connect: *lib.get(b"libssh2_agent_connect\0")?,
+                agent_list_identities: *lib.get(b"libssh2_agent_list_identities\0")?,
+                agent_get_identity: *lib.get(b"libssh2_agent_get_identity\0")?,
+                agent_userauth: *lib.get(b"libssh2_agent_userauth\0")?,
+                agent_disconnect: *lib.get(b"libssh2_agent_disconnect\0")?,
+                agent_free: *lib.get(b"libssh2_agent_free\0")?,
+                userauth_publickey_fromfile_ex: *lib
+                    .get(b"libssh2_userauth_publickey_fromfile_ex\0")?,
+                userauth_password_ex: *lib.get(b"libssh2_userauth_password_ex\0")?,
+                sftp_init: *lib.get(b"libssh2_sftp_init\0")?,
+                sftp_shutdown: *lib.get(b"libssh2_sftp_shutdown\0")?,
+                sftp_last_error: *lib.get(b"libssh2_sftp_last_error\0")?,
+                sftp_open_ex: *lib.get(b"libssh2_sftp_open_ex\0")?,
+                sftp_read: *lib.get(b"libssh2_sftp_read\0")?,
+                sftp_write: *lib.get(b"libssh2_sftp_write\0")?,
+                sftp_close_handle: *lib.get(b"libssh2_sftp_close_handle\0")?,
+                sftp_mkdir_ex: *lib.get(b"libssh2_sftp_mkdir_ex\0")?,
+                sftp_stat_ex: *lib.get(b"libssh2_sftp_stat_ex\0")?,
+                _lib: lib,
+            })
+        }
+    }
+}
+
+/// An authenticated SFTP session
+pub(super) struct SftpFs {
+    lib: &'static LibSsh2,
+    session: *mut Session,
+    sftp: *mut Sftp,
+    root: String,
+    // The session reads and writes this socket until it is freed
+    _socket: TcpStream,
+}
+
+impl SftpFs {
+    pub(super) fn connect(target: &RemoteTarget, password: Option<&str>) -> Result<Self> {
+        let lib = instance().ok_or_else(|| {
+            anyhow::anyhow!(
+                "libssh2 not found; cannot export to {} (set {} to its directory)",
+                target,
+                LIBRARY_DIR_ENV
+            )
+        })?;
+        let port = target.port.unwrap_or(DEFAULT_PORT);
+        let socket = TcpStream::connect((target.host.as_str(), port))
+            .with_context(|| format!("Failed to connect to {}:{}", target.host, port))?;
+        let user = target
+            .user
+            .clone()
+            .or_else(|| std::env::var("USER").ok())
+            .or_else(|| std::env::var("USERNAME").ok())
+            .context("No user in the URL or environment")?;
+
+        // SAFETY: the session is freed exactly once, by Drop or below when
+        // setup fails; the socket outlives it
+        unsafe {
+            let session = (lib.session_init_ex)(
+                std::ptr::null(),
+                std::ptr::null(),
+                std::ptr::null(),
+                std::ptr::null_mut(),
+            );
+            if session.is_null() {
+                bail!("libssh2 could not allocate a session");
+            }
+            let mut fs = Self {
+                lib,
+                session,
+                sftp: std::ptr::null_mut(),
+                root: target.path.clone(),
+                _socket: socket,
+            };
+            fs.start(target, port, &user, password)?;
+            Ok(fs)
+        }
+    }
+
+    unsafe fn start(
+        &mut self,
+        target: &RemoteTarget,
+        port: u16,
+        user: &str,
+        password: Option<&str>,
+    ) -> Result<()> {
+        if (self.lib.session_handshake)(self.session, raw_socket(&self._socket)) != 0 {
+            bail!("SSH handshake failed: {}", self.last_error());
+        }
+        self.check_host_key(&target.host, port)?;
+        self.authenticate(user, password)
+            .with_context(|| format!("SSH login as {} failed", user))?;
+        self.sftp = (self.lib.sftp_init)(self.session);
+        if self.sftp.is_null() {
+            bail!("SFTP subsystem unavailable: {}", self.last_error());
+        }
+        Ok(())
+    }
+
+    /// Refuse servers whose key is not in `~/.ssh/known_hosts`
+    unsafe fn check_host_key(&self, host: &str, port: u16) -> Result<()> {
+        let mut len = 0usize;
+        let mut key_type: c_int = 0;
+        let key = (self.lib.session_hostkey)(self.session, &mut len, &mut key_type);
+        if key.is_null() {
+            bail!("Server sent no host key");
+        }
+        let hosts = (self.lib.knownhost_init)(self.session);
+        if hosts.is_null() {
+            bail!("libssh2 could not allocate a known-hosts list");
+        }
+        let file = known_hosts_file();
+        if let Some(file) = file.as_ref().and_then(|f| path_cstring(f).ok()) {
+            // A missing file just leaves the list empty
+            (self.lib.knownhost_readfile)(hosts, file.as_ptr(), KNOWNHOST_FILE_OPENSSH);
+        }
+        let c_host = CString::new(host)?;
+        let type_mask =
+            KNOWNHOST_TYPE_PLAIN | KNOWNHOST_KEYENC_RAW | ((key_type + 1) << KNOWNHOST_KEY_SHIFT);
+        let check = (self.lib.knownhost_checkp)(
+            hosts,
+            c_host.as_ptr(),
+            c_int::from(port),
+            key,
+            len,
+            type_mask,
+            std::ptr::null_mut(),
+        );
+        (self.lib.knownhost_free)(hosts);
+        match check {
+            KNOWNHOST_CHECK_MATCH => Ok(()),
+            KNOWNHOST_CHECK_MISMATCH => bail!(
+                "Host key for {} does not match known_hosts; refusing to connect",
+                host
+            ),
+            _ => bail!(
+                "Host key for {} is not in {}; connect once with ssh to add it",
+                host,
+                file.map_or("known_hosts".into(), |f| f.display().to_string())
+            ),
+        }
+    }
+
+    /// Password when one is given, else the SSH agent, else the default
+    /// key files
+    unsafe fn authenticate(&self, user: &str, password: Option<&str>) -> Result<()> {
+        let c_user = CString::new(user)?;
+        let user_len = user.len() as c_uint;
+        if let Some(password) = password {
+            let c_password = CString::new(password)?;
+            if (self.lib.userauth_password_ex)(
+                self.session,
+                c_user.as_ptr(),
+                user_len,
+                c_password.as_ptr(),
+                password.len() as c_uint,
+                std::ptr::null(),
+            ) == 0
+            {
+                return Ok(());
+            }
+            tracing::debug!("SSH password login failed: {}", self.last_error());
+        }
+
+        if self.agent_login(&c_user) {
+            return Ok(());
+        }
+
+        let passphrase = password.map(CString::new).transpose()?;
+        let ssh_dir = dirs::home_dir().unwrap_or_default().join(".ssh");
+        for name in ["id_ed25519", "id_ecdsa", "id_rsa"] {
+            let key = ssh_dir.join(name);
+            if !key.exists() {
+                continue;
+            }
+            let c_key = path_cstring(&key)?;
+            if (self.lib.userauth_publickey_fromfile_ex)(
+                self.session,
+                c_user.as_ptr(),
+                user_len,
+                std::ptr::null(),
+                c_key.as_ptr(),
+                passphrase.as_ref().map_or(std::ptr::null(), |p| p.as_ptr()),
+            ) == 0
+            {
+                return Ok(());
+            }
+            tracing::debug!("SSH key {} rejected: {}", key.display(), self.last_error());
+        }
+        bail!(
+            "no accepted SSH agent identity or key, and {} {}",
+            PASSWORD_ENV,
+            if password.is_some() {
+                "was rejected"
+            } else {
+                "is not set"
+            }
+        )
+    }
+
+    unsafe fn agent_login(&self, user: &CString) -> bool {
+        let agent = (self.lib.agent_init)(self.session);
+        if agent.is_null() {
+            return false;
+        }
+        let mut ok = false;
+        if (self.lib.agent_connect)(agent) == 0 {
+            if (self.lib.agent_list_identities)(agent) == 0 {
+                let mut previous = std::ptr::null_mut();
+                loop {
+                    let mut identity = std::ptr::null_mut();
+                    if (self.lib.agent_get_identity)(agent, &mut identity, previous) != 0 {
+                        break;
+                    }
+                    if (self.lib.agent_userauth)(agent, user.as_ptr(), identity) == 0 {
+                        ok = true;
+                        break;
+                    }
+                    previous = identity;
+                }
+            }
+            (self.lib.agent_disconnect)(agent);
+        }
+        (self.lib.agent_free)(agent);
+        ok
+    }
+
+    unsafe fn last_error(&self) -> String {
+        let mut message: *mut c_char = std::ptr::null_mut();
+        let mut len: c_int = 0;
+        let code = (self.lib.session_last_error)(self.session, &mut message, &mut len, 0);
+        if message.is_null() || len <= 0 {
+            return format!("libssh2 error {}", code);
+        }
+        let bytes = std::slice::from_raw_parts(message as *const u8, len as usize);
+        String::from_utf8_lossy(bytes).into_owned()
+    }
+
+    unsafe fn sftp_error(&self, what: &str, path: &str) -> anyhow::Error {
+        anyhow::anyhow!(
+            "{} {}: SFTP status {} ({})",
+            what,
+            path,
+            (self.lib.sftp_last_error)(self.sftp),
+            self.last_error()
+        )
+    }
+
+    fn full_path(&self, relative: &str) -> String {
+        match (self.root.as_str(), relative) {
+            (root, "") => root.to_string(),
+            ("", relative) => relative.to_string(),
+            (root, relative) => format!("{}/{}", root.trim_end_matches('/'), relative),
+        }
+    }
+
+    unsafe fn open(&self, path: &str, flags: c_ulong) -> Result<*mut SftpHandle> {
+        let c_path = CString::new(path)?;
+        let handle = (self.lib.sftp_open_ex)(
+            self.sftp,
+            c_path.as_ptr(),
+            path.len() as c_uint,
+            flags,
+            0o644,
+            OPENFILE,
+        );
+        if handle.is_null() {
+            return Err(self.sftp_error("Failed to open", path));
+        }
+        Ok(handle)
+    }
+
+    unsafe fn stat(&self, path: &str) -> bool {
+        let Ok(c_path) = CString::new(path) else {
+            return false;
+        };
+        let mut attributes = SftpAttributes::default();
+        (self.lib.sftp_stat_ex)(
+            self.sftp,
+            c_path.as_ptr(),
+            path.len() as c_uint,
+            SFTP_STAT,
+            &mut attributes,
+        ) == 0
+    }
+}
+
+impl RemoteFs for SftpFs {
+    fn create_dir_all(&self, dir: &str) -> Result<()> {
+        let full = self.full_path(dir);
+        // SAFETY: the session and SFTP channel are open until Drop
+        unsafe {
+            for path in ancestors(&full) {
+                if self.stat(path) {
+                    continue;
+                }
+                let c_path = CString::new(path)?;
+                if (self.lib.sftp_mkdir_ex)(self.sftp, c_path.as_ptr(), path.len() as c_uint, 0o755)
+                    != 0
+                    // Created by someone else in the meantime
+                    && !self.stat(path)
+                {
+                    return Err(self.sftp_error("Failed to create directory", path));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        // SAFETY: the session and SFTP channel are open until Drop
+        unsafe { self.stat(&self.full_path(path)) }
+    }
+
+    fn write_file(&self, path: &str, data: &mut dyn Read) -> Result<u64> {
+        let full = self.full_path(path);
+        let mut buf = vec![0u8; CHUNK];
+        let mut total = 0u64;
+        // SAFETY: the handle is closed exactly once, after its last use
+        unsafe {
+            let handle = self.open(&full, FXF_WRITE | FXF_CREAT | FXF_TRUNC)?;
+            let written = (|| -> Result<()> {
+                loop {
+                    let n = data.read(&mut buf)?;
+                    if n == 0 {
+                        return Ok(());
+                    }
+                    let mut chunk = &buf[..n];
+                    while !chunk.is_empty() {
+                        let sent =
+                            (self.lib.sftp_write)(handle, chunk.as_ptr().cast(), chunk.len());
+                        if sent < 0 {
+                            return Err(self.sftp_error("Failed to write", &full));
+                        }
+                        chunk = &chunk[sent as usize..];
+                        total += sent as u64;
+                    }
+                }
+            })();
+            let closed = (self.lib.sftp_close_handle)(handle);
+            written?;
+            if closed != 0 {
+                return Err(self.sftp_error("Failed to close", &full));
+            }
+        }
+        Ok(total)
+    }
+
+    fn read_file(&self, path: &str, out: &mut dyn Write) -> Result<u64> {
+        let full = self.full_path(path);
+        let mut buf = vec![0u8; CHUNK];
+        let mut total = 0u64;
+        // SAFETY: the handle is closed exactly once, after its last use
+        unsafe {
+            let handle = self.open(&full, FXF_READ)?;
+            let read = (|| -> Result<()> {
+                loop {
+                    let n = (self.lib.sftp_read)(handle, buf.as_mut_ptr().cast(), buf.len());
+                    if n < 0 {
+                        return Err(self.sftp_error("Failed to read", &full));
+                    }
+                    if n == 0 {
+                        return Ok(());
+                    }
+                    out.write_all(&buf[..n as usize])?;
+                    total += n as u64;
+                }
+            })();
+            (self.lib.sftp_close_handle)(handle);
+            read?;
+        }
+        Ok(total)
+    }
+}
+
+impl Drop for SftpFs {
+    fn drop(&mut self) {
+        // SAFETY: both objects came from this library and are freed once
+        unsafe {
+            if !self.sftp.is_null() {
+                (self.lib.sftp_shutdown)(self.sftp);
+            }
+            (self.lib.session_disconnect_ex)(
+                self.session,
+                DISCONNECT_BY_APPLICATION,
+                c"export finished".as_ptr(),
+                c"".as_ptr(),
+            );
+            (self.lib.session_free)(self.session);
+        }
+    }
+}
+
+fn known_hosts_file() -> Option<PathBuf> {
+    Some(dirs::home_dir()?.join(".ssh").join("known_hosts"))
+}
+
+fn path_cstring(path: &std::path::Path) -> Result<CString> {
+    Ok(CString::new(path.to_str().with_context(|| {
+        format!("Non-UTF-8 path: {}", path.display())
+    })?)?)
+}
+
+#[cfg(unix)]
+fn raw_socket(socket: &TcpStream) -> RawSocket {
+    use std::os::unix::io::AsRawFd;
+    socket.as_raw_fd()
+}
+
+#[cfg(windows)]
+fn raw_socket(socket: &TcpStream) -> RawSocket {
+    use std::os::windows::io::AsRawSocket;
+    socket.as_raw_socket() as RawSocket
+}
@@ -0,0 +1,296 @@
+//! SMB shares through a system libsmbclient (loaded at runtime)
+
+use std::ffi::{c_char, c_int, c_void, CString};
+use std::io::{Read, Write};
+use std::sync::OnceLock;
+
+use anyhow::{bail, Result};
+use libloading::Library;
+
+use super::{ancestors, load_library, RemoteFs, RemoteTarget};
+
+/// Directory holding libsmbclient, checked before the system library path
+const LIBRARY_DIR_ENV: &str = "DIAMOND_DRILL_LIBSMBCLIENT_DIR";
+
+const CHUNK: usize = 64 * 1024;
+
+type Context = c_void;
+type SmbFile = c_void;
+
+type OpenFn =
+    unsafe extern "C" fn(*mut Context, *const c_char, c_int, libc::mode_t) -> *mut SmbFile;
+type ReadFn = unsafe extern "C" fn(*mut Context, *mut SmbFile, *mut c_void, usize) -> isize;
+type WriteFn = unsafe extern "C" fn(*mut Context, *mut SmbFile, *const c_void, usize) -> isize;
+type CloseFn = unsafe extern "C" fn(*mut Context, *mut SmbFile) -> c_int;
+type MkdirFn = unsafe extern "C" fn(*mut Context, *const c_char, libc::mode_t) -> c_int;
+
+/// libsmbclient entry points, valid for as long as `_lib` is loaded
+struct LibSmbClient {
+    new_context: unsafe extern "C" fn() -> *mut Context,
+    init_context: unsafe extern "C" fn(*mut Context) -> *mut Context,
+    free_context: unsafe extern "C" fn(*mut Context, c_int) -> c_int,
+    set_credentials:
+        unsafe extern "C" fn(*mut Context, *const c_char, *const c_char, *const c_char),
+    get_open: unsafe extern "C" fn(*mut Context) -> Option<OpenFn>,
+    get_read: unsafe extern "C" fn(*mut Context) -> Option<ReadFn>,
+    get_write: unsafe extern "C" fn(*mut Context) -> Option<WriteFn>,
+    get_close: unsafe extern "C" fn(*mut Context) -> Option<CloseFn>,
+    get_mkdir: unsafe extern "C" fn(*mut Context) -> Option<MkdirFn>,
+    _lib: Library,
+}
+
+static LIBSMBCLIENT: OnceLock<Option<LibSmbClient>> = OnceLock::new();
+
+/// The process-wide libsmbclient, or None when the library is missing
+fn instance() -> Option<&'static LibSmbClient> {
+    LIBSMBCLIENT
+        .get_or_init(|| {
+            let lib = load_library(
+                "smbclient",
+                &["libsmbclient.so.0", "libsmbclient.0.dylib"],
+                LIBRARY_DIR_ENV,
+            );
+            match lib.map(LibSmbClient::bind) {
+                Some(Ok(lib)) => Some(lib),
+                Some(Err(e)) => {
+                    tracing::warn!("libsmbclient found but unusable: {:#}", e);
+                    None
+                }
+                None => {
+                    tracing::info!("libsmbclient not found; SMB export is unavailable");
+                    None
+                }
+            }
+        })
+        .as_ref()
+}
+
+impl LibSmbClient {
+    fn bind(lib: Library) -> Result<Self> {
+        // SAFETY: the signatures match libsmbclient's public C API
+        // (libsmbclient.h), and the pointers are kept alongside the library
+        // they came from
+        unsafe {
+            Ok(Self {
+                new_context: *lib.get(b"smbc_new_context\0")?,
+                init_context: *lib.get(b"smbc_init_context\0")?,
+                free_context: *lib.get(b"smbc_free_context\0")?,
+                set_credentials: *lib.get(b"smbc_set_credentials_with_fallback\0")?,
+                get_open: *lib.get(b"smbc_getFunctionOpen\0")?,
+                get_read: *lib.get(b"smbc_getFunctionRead\0")?,
+                get_write: *lib.get(b"smbc_getFunctionWrite\0")?,
+                get_close: *lib.get(b"smbc_getFunctionClose\0")?,
+                get_mkdir: *lib.get(b"smbc_getFunctionMkdir\0")?,
+                _lib: lib,
+            })
+        }
+    }
+}
+
+/// A libsmbclient context logged in to one share
+pub(super) struct SmbFs {
+    lib: &'static LibSmbClient,
+    context: *mut Context,
+    open: OpenFn,
+    read: ReadFn,
+    write: WriteFn,
+    close: CloseFn,
+    mkdir: MkdirFn,
+    /// `smb://host/share/dir`, without the login
+    root: String,
+}
+
+impl SmbFs {
+    pub(super) fn connect(target: &RemoteTarget, password: Option<&str>) -> Result<Self> {
+        let lib = instance().ok_or_else(|| {
+            anyhow::anyhow!(
+                "libsmbclient not found; cannot export to {} (set {} to its directory)",
+                target,
+                LIBRARY_DIR_ENV
+            )
+        })?;
+        let domain = CString::new(target.domain.as_deref().unwrap_or("WORKGROUP"))?;
+        let user = CString::new(target.user.as_deref().unwrap_or("guest"))?;
+        let password = CString::new(password.unwrap_or(""))?;
+        let root = match target.port {
+            Some(port) => format!("smb://{}:{}/{}", target.host, port, target.path),
+            None => format!("smb://{}/{}", target.host, target.path),
+        };
+
+        // SAFETY: the context is freed exactly once, by Drop or below when
+        // setup fails; libsmbclient copies the credentials
+        unsafe {
+            let context = (lib.new_context)();
+            if context.is_null() {
+                bail!("libsmbclient could not allocate a context");
+            }
+            if (lib.init_context)(context).is_null() {
+                let error = std::io::Error::last_os_error();
+                (lib.free_context)(context, 1);
+                bail!("libsmbclient could not start: {}", error);
+            }
+            (lib.set_credentials)(context, domain.as_ptr(), user.as_ptr(), password.as_ptr());
+            let functions = (
+                (lib.get_open)(context),
+                (lib.get_read)(context),
+                (lib.get_write)(context),
+                (lib.get_close)(context),
+                (lib.get_mkdir)(context),
+            );
+            let (Some(open), Some(read), Some(write), Some(close), Some(mkdir)) = functions else {
+                (lib.free_context)(context, 1);
+                bail!("libsmbclient context is missing file operations");
+            };
+            Ok(Self {
+                lib,
+                context,
+                open,
+                read,
+                write,
+                close,
+                mkdir,
+                root,
+            })
+        }
+    }
+
+    fn url(&self, relative: &str) -> String {
+        if relative.is_empty() {
+            self.root.clone()
+        } else {
+            format!("{}/{}", self.root, relative)
+        }
+    }
+
+    unsafe fn open_url(&self, url: &str, flags: c_int) -> std::io::Result<*mut SmbFile> {
+        let c_url = CString::new(url)?;
+        let file = (self.open)(self.context, c_url.as_ptr(), flags, 0o644);
+        if file.is_null() {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(file)
+    }
+}
+
+impl RemoteFs for SmbFs {
+    fn create_dir_all(&self, dir: &str) -> Result<()> {
+        // The share itself (`smb://host/share`) always exists
+        let share_end = self
+            .root
+            .match_indices('/')
+            .nth(3)
+            .map_or(self.root.len(), |(i, _)| i);
+        let url = self.url(dir);
+        // SAFETY: the context is open until Drop
+        unsafe {
+            for path in ancestors(&url).filter(|p| p.len() > share_end) {
+                let c_path = CString::new(path)?;
+                if (self.mkdir)(self.context, c_path.as_ptr(), 0o755) != 0 {
+                    let error = std::io::Error::last_os_error();
+                    if error.raw_os_error() != Some(libc::EEXIST) {
+                        bail!("Failed to create directory {}: {}", path, error);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        // SAFETY: the file is closed right after opening
+        unsafe {
+            match self.open_url(&self.url(path), libc::O_RDONLY) {
+                Ok(file) => {
+                    (self.close)(self.context, file);
+                    true
+                }
+                Err(_) => false,
+            }
+        }
+    }
+
+    fn write_file(&self, path: &str, data: &mut dyn Read) -> Result<u64> {
+        let url = self.url(path);
+        let mut buf = vec![0u8; CHUNK];
+        let mut total = 0u64;
+        // SAFETY: the file is closed exactly once, after its last use
+        unsafe {
+            let file = self
+                .open_url(&url, libc::O_WRONLY | libc::O_CREAT | libc::O_TRUNC)
+                .map_err(|e| anyhow::anyhow!("Failed to create {}: {}", url, e))?;
+            let written = (|| -> Result<()> {
+                loop {
+                    let n = data.read(&mut buf)?;
+                    if n == 0 {
+                        return Ok(());
+                    }
+                    let mut chunk = &buf[..n];
+                    while !chunk.is_empty() {
+                        let sent =
+                            (self.write)(self.context, file, chunk.as_ptr().cast(), chunk.len());
+                        if sent < 0 {
+                            bail!(
+                                "Failed to write {}: {}",
+                                url,
+                                std::io::Error::last_os_error()
+                            );
+                        }
+                        chunk = &chunk[sent as usize..];
+                        total += sent as u64;
+                    }
+                }
+            })();
+            let closed = (self.close)(self.context, file);
+            written?;
+            if closed != 0 {
+                bail!(
+                    "Failed to close {}: {}",
+                    url,
+                    std::io::Error::last_os_error()
+                );
+            }
+        }
+        Ok(total)
+    }
+
+    fn read_file(&self, path: &str, out: &mut dyn Write) -> Result<u64> {
+        let url = self.url(path);
+        let mut buf = vec![0u8; CHUNK];
+        let mut total = 0u64;
+        // SAFETY: the file is closed exactly once, after its last use
+        unsafe {
+            let file = self
+                .open_url(&url, libc::O_RDONLY)
+                .map_err(|e| anyhow::anyhow!("Failed to open {}: {}", url, e))?;
+            let read = (|| -> Result<()> {
+                loop {
+                    let n = (self.read)(self.context, file, buf.as_mut_ptr().cast(), buf.len());
+                    if n < 0 {
+                        bail!(
+                            "Failed to read {}: {}",
+                            url,
+                            std::io::Error::last_os_error()
+                        );
+                    }
+                    if n == 0 {
+                        return Ok(());
+                    }
+                    out.write_all(&buf[..n as usize])?;
+                    total += n as u64;
+                }
+            })();
+            (self.close)(self.context, file);
+            read?;
+        }
+        Ok(total)
+    }
+}
+
+impl Drop for SmbFs {
+    fn drop(&mut self) {
+        // SAFETY: the context came from this library and is freed once
+        unsafe {
+            (self.lib.free_context)(self.context, 1);
+        }
+    }
+}
@@ -1,30 +1,64 @@
 //! Diamond Drill GUI — Modern dark-themed file recovery interface
 //!
-//! 5-view layout: Source → Browse → Carve → Export → Stats
+//! 6-view layout: Source → Browse → Photos → Carve → Export → Stats
 //! Built on Iced 0.12 with the Elm architecture.
+//!
+//! A folder dropped on the window is scanned and a disk image is opened for
+//! carving. Exports show every queued file with its progress and hash
+//! status, and can be paused and resumed. Indexing and carving progress, and
+//! a count of unreadable regions, follow the library's event bus.
+//!
+//! The Photos view lays out only the grid rows on screen and loads their
+//! thumbnails in the background, small first. Photos are selected for
+//! export by clicking them or dragging from one photo to another.
 
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
+use iced::widget::image::Handle as ImageHandle;
 use iced::widget::{
-    button, column, container, horizontal_rule, horizontal_space, progress_bar, row, scrollable,
-    text, text_input, vertical_space, Column, Row,
+    button, column, container, horizontal_rule, horizontal_space, mouse_area, progress_bar, row,
+    scrollable, text, text_input, vertical_space, Column, Image, Row,
+};
+use iced::{
+    event, executor, window, Application, Command, Element, Event, Length, Settings, Subscription,
+    Theme,
 };
-use iced::{executor, Application, Command, Element, Length, Settings, Theme};
 use parking_lot::RwLock;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
 
-use crate::carve::{CarveOptions, CarveResult, CarvedFile, Carver};
+use super::grid::{load_thumbnails, PhotoGrid, Thumb, ThumbLoaded, CELL_SIZE};
+use super::queue::{ExportQueue, QueueStatus};
+use crate::carve::{CarveOptions, CarveProgress, CarveResult, CarvedFile, Carver};
 use crate::cli::GuiArgs;
 use crate::core::{DrillEngine, FileEntry, FileType};
-use crate::export::{ExportOptions, Exporter};
+use crate::events::{self, Event as LibraryEvent};
+use crate::export::{ExportFileEvent, ExportOptions, Exporter, DEFAULT_HASH_RETRIES};
+use crate::preview::ThumbnailGenerator;
+use crate::throttle::Throttle;
+
+/// How often the export queue and photo thumbnails are refreshed from their
+/// background work
+const REFRESH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Window space around the photo grid (sidebar, padding, heading and bars),
+/// used to size the grid until it reports its own viewport
+const GRID_CHROME: (f32, f32) = (130.0, 190.0);
 
 pub fn run_gui(args: GuiArgs) -> anyhow::Result<()> {
     let (width, height) = parse_size(&args.size);
+    let size = iced::Size::new(width as f32, height as f32);
 
     DiamondDrillApp::run(Settings {
+        flags: Flags {
+            source: args.source,
+            window: size,
+        },
         window: iced::window::Settings {
-            size: iced::Size::new(width as f32, height as f32),
+            size,
             ..Default::default()
         },
         default_font: iced::Font::DEFAULT,
@@ -48,6 +82,12 @@ fn parse_size(size: &str) -> (u32, u32) {
 
 // ── State ────────────────────────────────────────────────────────────────
 
+struct Flags {
+    /// Source to open at start
+    source: Option<PathBuf>,
+    window: iced::Size,
+}
+
 struct DiamondDrillApp {
     view: AppView,
     source_input: String,
@@ -67,12 +107,31 @@ struct DiamondDrillApp {
     progress: f32,
     progress_label: String,
     error: Option<String>,
+    /// A file is being dragged over the window
+    drop_hover: bool,
+    /// Files of the running or last export
+    export_queue: Option<ExportQueue>,
+    export_events: Option<UnboundedReceiver<ExportFileEvent>>,
+    /// Shared with the running export; pausing it holds the copy
+    throttle: Throttle,
+    /// Images of the index, for the Photos view
+    grid: PhotoGrid,
+    /// Created the first time thumbnails are needed
+    thumbnails: Option<Arc<ThumbnailGenerator>>,
+    thumb_tx: UnboundedSender<ThumbLoaded>,
+    thumb_events: UnboundedReceiver<ThumbLoaded>,
+    /// Thumbnail batches still loading
+    thumb_batches: usize,
+    window_size: iced::Size,
+    /// Unreadable regions reported by any operation this session
+    unreadable: usize,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum AppView {
     Source,
     Browse,
+    Photos,
     Carve,
     Export,
     Stats,
@@ -83,6 +142,7 @@ impl AppView {
         match self {
             AppView::Source => "Source",
             AppView::Browse => "Browse",
+            AppView::Photos => "Photos",
             AppView::Carve => "Carve",
             AppView::Export => "Export",
             AppView::Stats => "Stats",
@@ -93,6 +153,7 @@ impl AppView {
         match self {
             AppView::Source => "📁",
             AppView::Browse => "🔍",
+            AppView::Photos => "🖼",
             AppView::Carve => "💎",
             AppView::Export => "📤",
             AppView::Stats => "📊",
@@ -100,9 +161,10 @@ impl AppView {
     }
 }
 
-const ALL_VIEWS: [AppView; 5] = [
+const ALL_VIEWS: [AppView; 6] = [
     AppView::Source,
     AppView::Browse,
+    AppView::Photos,
     AppView::Carve,
     AppView::Export,
     AppView::Stats,
@@ -132,8 +194,23 @@ enum Message {
     SelectNone,
     SetTypeFilter(Option<FileType>),
 
+    GridScrolled(scrollable::Viewport),
+    WindowResized(u32, u32),
+    PhotoPressed(usize),
+    PhotoReleased(usize),
+    SelectAllPhotos,
+    RefreshThumbs,
+    ThumbBatchDone,
+    Library(LibraryEvent),
+
     StartExport,
     ExportComplete(Result<usize, String>),
+    RefreshQueue,
+    TogglePause,
+
+    FileHovered,
+    FilesHoveredLeft,
+    FileDropped(PathBuf),
 
     StartCarve,
     CarveComplete(Result<(Vec<CarvedFile>, CarveResult), String>),
@@ -147,32 +224,46 @@ impl Application for DiamondDrillApp {
     type Executor = executor::Default;
     type Message = Message;
     type Theme = Theme;
-    type Flags = ();
-
-    fn new(_flags: ()) -> (Self, Command<Message>) {
-        (
-            Self {
-                view: AppView::Source,
-                source_input: String::new(),
-                dest_input: String::new(),
-                carve_source_input: String::new(),
-                carve_output_input: String::new(),
-                filter_input: String::new(),
-                engine: None,
-                files: Vec::new(),
-                filtered_indices: Vec::new(),
-                selected: Vec::new(),
-                carved_files: Vec::new(),
-                carve_result: None,
-                type_filter: None,
-                status: "Ready — select a source to begin".to_string(),
-                loading: false,
-                progress: 0.0,
-                progress_label: String::new(),
-                error: None,
-            },
-            Command::none(),
-        )
+    type Flags = Flags;
+
+    fn new(flags: Flags) -> (Self, Command<Message>) {
+        let (thumb_tx, thumb_events) = unbounded_channel();
+        let mut app = Self {
+            view: AppView::Source,
+            source_input: String::new(),
+            dest_input: String::new(),
+            carve_source_input: String::new(),
+            carve_output_input: String::new(),
+            filter_input: String::new(),
+            engine: None,
+            files: Vec::new(),
+            filtered_indices: Vec::new(),
+            selected: Vec::new(),
+            carved_files: Vec::new(),
+            carve_result: None,
+            type_filter: None,
+            status: "Ready — select a source to begin".to_string(),
+            loading: false,
+            progress: 0.0,
+            progress_label: String::new(),
+            error: None,
+            drop_hover: false,
+            export_queue: None,
+            export_events: None,
+            throttle: Throttle::default(),
+            grid: PhotoGrid::default(),
+            thumbnails: None,
+            thumb_tx,
+            thumb_events,
+            thumb_batches: 0,
+            window_size: flags.window,
+            unreadable: 0,
+        };
+        let command = match flags.source {
+            Some(path) => app.open_path(path),
+            None => Command::none(),
+        };
+        (app, command)
     }
 
     fn title(&self) -> String {
@@ -189,6 +280,7 @@ impl Application for DiamondDrillApp {
             Message::SetView(view) => {
                 self.view = view;
                 self.error = None;
+                return self.load_visible_thumbs();
             }
 
             // ── Text inputs ──────────────────────────────
@@ -248,6 +340,10 @@ impl Application for DiamondDrillApp {
                     self.error = Some("Enter a source path first".into());
                     return Command::none();
                 }
+                if self.loading {
+                    self.error = Some("Wait for the current operation to finish".into());
+                    return Command::none();
+                }
                 self.loading = true;
                 self.progress = 0.0;
                 self.progress_label = "Indexing files...".into();
@@ -267,6 +363,8 @@ impl Application for DiamondDrillApp {
                         let count = files.len();
                         self.filtered_indices = (0..count).collect();
                         self.files = files;
+                        self.grid = PhotoGrid::new(&self.files);
+                        self.fit_grid_to_window();
                         self.status = format!("Indexed {} files", count);
                         self.view = AppView::Browse;
                     }
@@ -296,6 +394,49 @@ impl Application for DiamondDrillApp {
                 self.rebuild_filter(&self.filter_input.clone());
             }
 
+            // ── Photo grid ───────────────────────────────
+            Message::GridScrolled(viewport) => {
+                let bounds = viewport.bounds();
+                self.grid
+                    .set_viewport(bounds.width, bounds.height, viewport.absolute_offset().y);
+                return self.load_visible_thumbs();
+            }
+            Message::WindowResized(width, height) => {
+                self.window_size = iced::Size::new(width as f32, height as f32);
+                self.fit_grid_to_window();
+                return self.load_visible_thumbs();
+            }
+            Message::PhotoPressed(cell) => self.grid.drag_from = Some(cell),
+            Message::PhotoReleased(cell) => match self.grid.drag_from.take() {
+                Some(from) if from == cell => {
+                    let file = self.grid.photos[cell].file;
+                    return self.update(Message::ToggleSelect(file));
+                }
+                Some(from) => {
+                    for file in self.grid.band(from, cell) {
+                        if !self.selected.contains(&file) {
+                            self.selected.push(file);
+                        }
+                    }
+                    self.status = format!("{} files selected", self.selected.len());
+                }
+                // Pressed outside the grid
+                None => {}
+            },
+            Message::SelectAllPhotos => {
+                for file in self.grid.photos.iter().map(|p| p.file) {
+                    if !self.selected.contains(&file) {
+                        self.selected.push(file);
+                    }
+                }
+                self.status = format!("{} files selected", self.selected.len());
+            }
+            Message::RefreshThumbs => self.refresh_thumbs(),
+            Message::ThumbBatchDone => {
+                self.thumb_batches = self.thumb_batches.saturating_sub(1);
+                self.refresh_thumbs();
+            }
+
             // ── Export ───────────────────────────────────
             Message::StartExport => {
                 if self.selected.is_empty() {
@@ -306,6 +447,10 @@ impl Application for DiamondDrillApp {
                     self.error = Some("Enter a destination path".into());
                     return Command::none();
                 }
+                if self.loading {
+                    self.error = Some("Wait for the current operation to finish".into());
+                    return Command::none();
+                }
                 self.loading = true;
                 self.progress = 0.0;
                 self.progress_label = "Exporting...".into();
@@ -316,23 +461,52 @@ impl Application for DiamondDrillApp {
                     .iter()
                     .filter_map(|&i| self.files.get(i).cloned())
                     .collect();
+                let (tx, rx) = unbounded_channel();
+                self.export_queue = Some(ExportQueue::new(&entries));
+                self.export_events = Some(rx);
+                self.throttle.set_paused(false);
                 let dest = self.dest_input.clone();
+                let throttle = self.throttle.clone();
                 return Command::perform(
-                    async move { run_export(entries, dest).await },
+                    async move { run_export(entries, dest, throttle, tx).await },
                     Message::ExportComplete,
                 );
             }
             Message::ExportComplete(result) => {
+                self.refresh_queue();
+                self.export_events = None;
                 self.loading = false;
                 self.progress = 1.0;
                 match result {
                     Ok(count) => {
-                        self.status = format!("Exported {} files", count);
-                        self.view = AppView::Stats;
+                        let failed = self.export_queue.as_ref().map_or(0, |q| q.counts().1);
+                        self.status = if failed > 0 {
+                            format!("Exported {} files, {} failed", count, failed)
+                        } else {
+                            format!("Exported {} files", count)
+                        };
                     }
                     Err(e) => self.error = Some(e),
                 }
             }
+            Message::RefreshQueue => self.refresh_queue(),
+            Message::TogglePause => {
+                let paused = !self.throttle.is_paused();
+                self.throttle.set_paused(paused);
+                self.status = if paused {
+                    "Export paused".into()
+                } else {
+                    "Export resumed".into()
+                };
+            }
+
+            // ── Drag and drop ────────────────────────────
+            Message::FileHovered => self.drop_hover = true,
+            Message::FilesHoveredLeft => self.drop_hover = false,
+            Message::FileDropped(path) => {
+                self.drop_hover = false;
+                return self.open_path(path);
+            }
 
             // ── Carving ──────────────────────────────────
             Message::StartCarve => {
@@ -344,6 +518,10 @@ impl Application for DiamondDrillApp {
                     self.error = Some("Enter an output folder".into());
                     return Command::none();
                 }
+                if self.loading {
+                    self.error = Some("Wait for the current operation to finish".into());
+                    return Command::none();
+                }
                 self.loading = true;
                 self.progress = 0.0;
                 self.progress_label = "Carving...".into();
@@ -375,6 +553,8 @@ impl Application for DiamondDrillApp {
                 }
             }
 
+            Message::Library(event) => self.apply_library_event(event),
+
             Message::DismissError => self.error = None,
         }
 
@@ -386,6 +566,7 @@ impl Application for DiamondDrillApp {
         let content = match self.view {
             AppView::Source => self.view_source(),
             AppView::Browse => self.view_browse(),
+            AppView::Photos => self.view_photos(),
             AppView::Carve => self.view_carve(),
             AppView::Export => self.view_export(),
             AppView::Stats => self.view_stats(),
@@ -419,6 +600,26 @@ impl Application for DiamondDrillApp {
     fn theme(&self) -> Theme {
         Theme::Dark
     }
+
+    fn subscription(&self) -> Subscription<Message> {
+        let drops = event::listen_with(|event, _status| match event {
+            Event::Window(_, window::Event::FileHovered(_)) => Some(Message::FileHovered),
+            Event::Window(_, window::Event::FilesHoveredLeft) => Some(Message::FilesHoveredLeft),
+            Event::Window(_, window::Event::FileDropped(path)) => Some(Message::FileDropped(path)),
+            Event::Window(_, window::Event::Resized { width, height }) => {
+                Some(Message::WindowResized(width, height))
+            }
+            _ => None,
+        });
+        let mut subscriptions = vec![drops, library_events()];
+        if self.export_events.is_some() {
+            subscriptions.push(iced::time::every(REFRESH_INTERVAL).map(|_| Message::RefreshQueue));
+        }
+        if self.thumb_batches > 0 {
+            subscriptions.push(iced::time::every(REFRESH_INTERVAL).map(|_| Message::RefreshThumbs));
+        }
+        Subscription::batch(subscriptions)
+    }
 }
 
 // ── View builders ────────────────────────────────────────────────────────
@@ -489,6 +690,18 @@ impl DiamondDrillApp {
 
         let hint = text("Read-only — your source data is never modified.").size(13);
 
+        let drop_label = if self.drop_hover {
+            "Release to open"
+        } else {
+            "…or drop a folder to scan, or a disk image to carve, onto this window"
+        };
+        let drop_zone = container(text(drop_label).size(if self.drop_hover { 18 } else { 14 }))
+            .center_x()
+            .center_y()
+            .width(Length::Fill)
+            .height(Length::Fixed(96.0))
+            .style(iced::theme::Container::Box);
+
         column![
             heading,
             vertical_space().height(8),
@@ -498,6 +711,8 @@ impl DiamondDrillApp {
             path_row,
             vertical_space().height(20),
             scan_btn,
+            vertical_space().height(20),
+            drop_zone,
             vertical_space().height(12),
             hint,
         ]
@@ -620,6 +835,118 @@ impl DiamondDrillApp {
         r.into()
     }
 
+    // ── Photos View ─────────────────────────────────────────────────
+
+    fn view_photos(&self) -> Element<Message> {
+        let heading = row![
+            text("Photos").size(28),
+            horizontal_space(),
+            text(format!(
+                "{} photos / {} selected",
+                self.grid.photos.len(),
+                self.selected.len(),
+            ))
+            .size(13),
+        ]
+        .align_items(iced::Alignment::Center);
+
+        let toolbar = row![
+            text("Click to select · drag from one photo to another to select a block").size(13),
+            horizontal_space(),
+            button(text("All")).on_press(Message::SelectAllPhotos).padding(8),
+            button(text("None")).on_press(Message::SelectNone).padding(8),
+        ]
+        .spacing(6)
+        .align_items(iced::Alignment::Center);
+
+        let grid: Element<Message> = if self.grid.photos.is_empty() {
+            container(
+                column![
+                    text("No photos loaded").size(18),
+                    vertical_space().height(8),
+                    text("Go to Source tab and scan a folder with images.").size(14),
+                ]
+                .align_items(iced::Alignment::Center),
+            )
+            .center_x()
+            .center_y()
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into()
+        } else {
+            // Rows off screen are replaced by spacers of the same height so
+            // the scrollbar still spans the whole grid
+            let rows = self.grid.visible_rows();
+            let columns = self.grid.columns;
+            let mut body = Column::new()
+                .width(Length::Fill)
+                .push(vertical_space().height(rows.start as f32 * CELL_SIZE));
+            for r in rows.clone() {
+                let end = ((r + 1) * columns).min(self.grid.photos.len());
+                let cells: Vec<Element<Message>> = (r * columns..end)
+                    .map(|cell| self.view_photo_cell(cell))
+                    .collect();
+                body = body.push(Row::with_children(cells));
+            }
+            body = body
+                .push(vertical_space().height((self.grid.rows() - rows.end) as f32 * CELL_SIZE));
+            scrollable(body)
+                .on_scroll(Message::GridScrolled)
+                .height(Length::Fill)
+                .into()
+        };
+
+        column![heading, toolbar, horizontal_rule(1), grid]
+            .spacing(10)
+            .height(Length::Fill)
+            .into()
+    }
+
+    fn view_photo_cell(&self, cell: usize) -> Element<Message> {
+        let photo = &self.grid.photos[cell];
+        let is_sel = self.selected.contains(&photo.file);
+
+        let thumb: Element<Message> = match &photo.thumb {
+            Thumb::Small(path) | Thumb::Large(path) => Image::new(ImageHandle::from_path(path))
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .into(),
+            Thumb::Failed => text("⚠").size(32).into(),
+            Thumb::Missing | Thumb::Loading => text("🖼").size(32).into(),
+        };
+        let name: String = photo
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().chars().take(16).collect())
+            .unwrap_or_default();
+        let check = if is_sel { "☑" } else { "☐" };
+
+        let content = column![
+            container(thumb)
+                .center_x()
+                .center_y()
+                .width(Length::Fill)
+                .height(Length::Fill),
+            text(format!("{} {}", check, name)).size(11),
+        ]
+        .spacing(2);
+
+        let frame = container(content)
+            .width(Length::Fixed(CELL_SIZE))
+            .height(Length::Fixed(CELL_SIZE))
+            .padding(6);
+        let frame = if is_sel {
+            frame.style(iced::theme::Container::Box)
+        } else {
+            frame
+        };
+
+        mouse_area(frame)
+            .on_press(Message::PhotoPressed(cell))
+            .on_release(Message::PhotoReleased(cell))
+            .into()
+    }
+
     // ── Carve View ──────────────────────────────────────────────────
 
     fn view_carve(&self) -> Element<Message> {
@@ -722,13 +1049,31 @@ impl DiamondDrillApp {
         .on_press(Message::StartExport)
         .padding(14);
 
-        let notes = column![
-            text("Export options:").size(14),
-            text("  • Blake3 hash verification on every file").size(13),
-            text("  • JSON manifest with file hashes generated").size(13),
-            text("  • Source data is never modified (read-only)").size(13),
-        ]
-        .spacing(3);
+        let mut actions = row![export_btn].spacing(8);
+        if self.export_events.is_some() {
+            let label = if self.throttle.is_paused() {
+                "▶  Resume"
+            } else {
+                "⏸  Pause"
+            };
+            actions = actions.push(
+                button(text(label))
+                    .on_press(Message::TogglePause)
+                    .padding(14),
+            );
+        }
+
+        let details: Element<Message> = match &self.export_queue {
+            Some(queue) => self.view_export_queue(queue),
+            None => column![
+                text("Export options:").size(14),
+                text("  • Blake3 hash verification on every file").size(13),
+                text("  • JSON manifest with file hashes generated").size(13),
+                text("  • Source data is never modified (read-only)").size(13),
+            ]
+            .spacing(3)
+            .into(),
+        };
 
         column![
             heading,
@@ -738,13 +1083,78 @@ impl DiamondDrillApp {
             text("Destination:").size(14),
             dest_row,
             vertical_space().height(20),
-            export_btn,
+            actions,
             vertical_space().height(20),
             horizontal_rule(1),
             vertical_space().height(12),
-            notes,
+            details,
         ]
         .spacing(4)
+        .height(Length::Fill)
+        .into()
+    }
+
+    /// One row per queued file: status, name, progress and hash
+    fn view_export_queue(&self, queue: &ExportQueue) -> Element<Message> {
+        let (finished, failed) = queue.counts();
+        let header = text(format!(
+            "Export queue: {} of {} files done, {} failed",
+            finished,
+            queue.items.len(),
+            failed
+        ))
+        .size(14);
+
+        let rows: Vec<Element<Message>> = queue
+            .items
+            .iter()
+            .take(500)
+            .map(|item| {
+                let (icon, detail) = match &item.status {
+                    QueueStatus::Queued => ("⏳", "queued".to_string()),
+                    QueueStatus::Copying { bytes } => (
+                        "📤",
+                        format!(
+                            "{} / {}",
+                            humansize::format_size(*bytes, humansize::BINARY),
+                            humansize::format_size(item.size, humansize::BINARY)
+                        ),
+                    ),
+                    QueueStatus::Verifying => ("🔍", "verifying hash".to_string()),
+                    QueueStatus::Verified { hash, retries: 0 } => {
+                        ("✅", format!("blake3 {}", &hash[..16.min(hash.len())]))
+                    }
+                    QueueStatus::Verified { hash, retries } => (
+                        "✅",
+                        format!(
+                            "blake3 {} after {} retries",
+                            &hash[..16.min(hash.len())],
+                            retries
+                        ),
+                    ),
+                    QueueStatus::Skipped(reason) => ("⏭", reason.clone()),
+                    QueueStatus::Failed(e) => ("❌", e.clone()),
+                };
+                row![
+                    text(icon).size(14).width(Length::Fixed(28.0)),
+                    text(item.name()).size(13).width(Length::FillPortion(3)),
+                    progress_bar(0.0..=1.0, item.ratio())
+                        .height(6)
+                        .width(Length::FillPortion(2)),
+                    text(detail).size(12).width(Length::FillPortion(3)),
+                ]
+                .spacing(8)
+                .align_items(iced::Alignment::Center)
+                .into()
+            })
+            .collect();
+
+        column![
+            header,
+            scrollable(Column::with_children(rows).spacing(2)).height(Length::Fill),
+        ]
+        .spacing(6)
+        .height(Length::Fill)
         .into()
     }
 
@@ -884,13 +1294,16 @@ impl DiamondDrillApp {
 
     fn view_status_bar(&self) -> Element<Message> {
         let status = text(format!("✓ {}", self.status)).size(12);
-        let counts = text(format!(
+        let mut counts = format!(
             "{} files | {} selected | {} carved",
             self.files.len(),
             self.selected.len(),
             self.carved_files.len(),
-        ))
-        .size(12);
+        );
+        if self.unreadable > 0 {
+            counts.push_str(&format!(" | ⚠ {} unreadable", self.unreadable));
+        }
+        let counts = text(counts).size(12);
 
         container(row![status, horizontal_space(), counts].align_items(iced::Alignment::Center))
             .padding([6, 24])
@@ -900,6 +1313,108 @@ impl DiamondDrillApp {
 
     // ── Logic helpers ───────────────────────────────────────────────
 
+    /// Follow indexing and carving progress, and count unreadable regions
+    fn apply_library_event(&mut self, event: LibraryEvent) {
+        match event {
+            LibraryEvent::Scan { files, .. } if self.loading => {
+                self.progress_label = format!("Indexing... {} files", files);
+            }
+            LibraryEvent::Carve(CarveProgress::ScanComplete { headers_found }) if self.loading => {
+                self.progress_label = format!("Found {} headers, extracting...", headers_found);
+            }
+            LibraryEvent::Carve(CarveProgress::Extracting { current, total, .. })
+                if self.loading =>
+            {
+                self.progress = current as f32 / total.max(1) as f32;
+                self.progress_label = format!("Extracting {} of {}", current, total);
+            }
+            LibraryEvent::BadSector(_) => self.unreadable += 1,
+            _ => {}
+        }
+    }
+
+    /// Scan a folder, or open a disk image on the Carve view
+    fn open_path(&mut self, path: PathBuf) -> Command<Message> {
+        if path.is_dir() {
+            self.source_input = path.to_string_lossy().to_string();
+            return self.update(Message::StartIndex);
+        }
+        self.carve_source_input = path.to_string_lossy().to_string();
+        self.view = AppView::Carve;
+        self.error = None;
+        self.status = if self.carve_output_input.is_empty() {
+            format!(
+                "Opened {} — choose an output folder to carve",
+                path.display()
+            )
+        } else {
+            format!("Opened {} — ready to carve", path.display())
+        };
+        Command::none()
+    }
+
+    /// Apply the export's queued file events
+    fn refresh_queue(&mut self) {
+        let (Some(queue), Some(events)) = (&mut self.export_queue, &mut self.export_events) else {
+            return;
+        };
+        while let Ok(event) = events.try_recv() {
+            queue.apply(event);
+        }
+        let (finished, failed) = queue.counts();
+        self.progress = queue.progress();
+        let state = if self.throttle.is_paused() {
+            "Paused at"
+        } else {
+            "Exporting..."
+        };
+        self.progress_label = format!("{} {}/{} files", state, finished, queue.items.len());
+        if failed > 0 {
+            self.progress_label += &format!(", {} failed", failed);
+        }
+    }
+
+    /// Size the photo grid from the window, keeping its scroll position
+    fn fit_grid_to_window(&mut self) {
+        self.grid.resize(
+            self.window_size.width - GRID_CHROME.0,
+            self.window_size.height - GRID_CHROME.1,
+        );
+    }
+
+    /// Start loading the thumbnails of photos scrolled into view
+    fn load_visible_thumbs(&mut self) -> Command<Message> {
+        if self.view != AppView::Photos {
+            return Command::none();
+        }
+        let sources = self.grid.take_unloaded();
+        if sources.is_empty() {
+            return Command::none();
+        }
+        let generator = self
+            .thumbnails
+            .get_or_insert_with(|| Arc::new(ThumbnailGenerator::new()))
+            .clone();
+        let tx = self.thumb_tx.clone();
+        self.thumb_batches += 1;
+        Command::perform(
+            async move {
+                // A panicking batch leaves its photos on the placeholder
+                let _ =
+                    tokio::task::spawn_blocking(move || load_thumbnails(&generator, &sources, &tx))
+                        .await;
+            },
+            |_| Message::ThumbBatchDone,
+        )
+    }
+
+    /// Apply the thumbnails loaded since the last refresh
+    fn refresh_thumbs(&mut self) {
+        while let Ok(loaded) = self.thumb_events.try_recv() {
+            self.grid.apply(loaded);
+        }
+    }
+
     fn rebuild_filter(&mut self, query: &str) {
         if query.is_empty() && self.type_filter.is_none() {
             self.filtered_indices = (0..self.files.len()).collect();
@@ -928,6 +1443,23 @@ impl DiamondDrillApp {
 
 // ── Async operations (run off the GUI thread) ────────────────────────────
 
+/// Events published by the library while the app runs
+fn library_events() -> Subscription<Message> {
+    iced::subscription::unfold(
+        "library-events",
+        events::subscribe(),
+        |mut events| async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) => return (Message::Library(event), events),
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => std::future::pending::<()>().await,
+                }
+            }
+        },
+    )
+}
+
 async fn index_source(source: String) -> Result<Vec<FileEntry>, String> {
     let path = PathBuf::from(&source);
     if !path.exists() {
@@ -950,6 +1482,18 @@ async fn index_source(source: String) -> Result<Vec<FileEntry>, String> {
         checkpoint_interval: 1000,
         bad_sector_report: None,
         block_size: 4096,
+        sqlite: None,
+        incremental: false,
+        verify_hash: false,
+        metadata: false,
+        report_format: None,
+        report_file: None,
+        hashset: Vec::new(),
+        repair_names: None,
+        links: Default::default(),
+        all_hardlinks: false,
+        archives: false,
+        snapshots: false,
     };
 
     engine
@@ -970,7 +1514,12 @@ async fn index_source(source: String) -> Result<Vec<FileEntry>, String> {
     Ok(entries)
 }
 
-async fn run_export(entries: Vec<FileEntry>, dest: String) -> Result<usize, String> {
+async fn run_export(
+    entries: Vec<FileEntry>,
+    dest: String,
+    throttle: Throttle,
+    events: tokio::sync::mpsc::UnboundedSender<ExportFileEvent>,
+) -> Result<usize, String> {
     let options = ExportOptions {
         dest: PathBuf::from(&dest),
         preserve_structure: true,
@@ -978,9 +1527,32 @@ async fn run_export(entries: Vec<FileEntry>, dest: String) -> Result<usize, Stri
         continue_on_error: true,
         create_manifest: true,
         dry_run: false,
+        hash_retries: DEFAULT_HASH_RETRIES,
+        retry_with_sector_reader: true,
+        organize_by: None,
+        on_conflict: Default::default(),
+        archive: None,
+        remote: None,
+        max_bytes_per_sec: None,
+        resume: false,
+        mirror: None,
+        index_started: None,
+        hash_algorithms: Vec::new(),
+        io: Default::default(),
+        workers: None,
+        skip_space_check: false,
+        repair_names: None,
+        decompress: false,
+        hooks: Vec::new(),
+        on_detection: Default::default(),
     };
 
-    let exporter = Exporter::new(options);
+    let exporter = Exporter::new(options)
+        .with_throttle(throttle)
+        .with_file_events(Arc::new(move |event| {
+            // The receiver is dropped only once the export has completed
+            let _ = events.send(event);
+        }));
     let result = exporter
         .export_batch(&entries, |_| {})
         .await
@@ -1002,6 +1574,14 @@ async fn run_carve(
         workers: num_cpus::get(),
         dry_run: false,
         verify: true,
+        mapfile: None,
+        max_bytes_per_sec: None,
+        unlock: None,
+        extra_signatures: Vec::new(),
+        faststart: false,
+        text_min_len: None,
+        min_confidence: 0,
+        io: None,
     };
 
     let carver = Carver::new(opts);
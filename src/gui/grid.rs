@@ -0,0 +1,272 @@
+//! Photo grid for the GUI's Photos view
+//!
+//! Only the rows in (or near) the viewport are laid out, and only their
+//! thumbnails are requested. Thumbnails arrive in two passes: every 64px
+//! thumb of a batch first so the grid fills quickly, then the 512px ones
+//! that replace them.
+
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::PathBuf;
+
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::core::{FileEntry, FileType};
+use crate::preview::ThumbnailGenerator;
+
+/// Thumbnail drawn while the full-size one loads
+pub const THUMB_SMALL: u32 = 64;
+/// Thumbnail the grid settles on
+pub const THUMB_LARGE: u32 = 512;
+/// Width and height of one grid cell, spacing included
+pub const CELL_SIZE: f32 = 144.0;
+/// Rows laid out above and below the viewport
+const OVERSCAN_ROWS: usize = 2;
+
+/// Thumbnail state of one photo
+#[derive(Debug, Clone, PartialEq)]
+pub enum Thumb {
+    Missing,
+    Loading,
+    Small(PathBuf),
+    Large(PathBuf),
+    Failed,
+}
+
+#[derive(Debug, Clone)]
+pub struct Photo {
+    /// Index into the app's file list
+    pub file: usize,
+    pub path: PathBuf,
+    pub thumb: Thumb,
+}
+
+/// A thumbnail written by [`load_thumbnails`]
+#[derive(Debug, Clone)]
+pub struct ThumbLoaded {
+    pub source: PathBuf,
+    pub size: u32,
+    pub result: Result<PathBuf, String>,
+}
+
+/// Images of the index laid out in a virtualized grid
+#[derive(Debug, Clone, Default)]
+pub struct PhotoGrid {
+    pub photos: Vec<Photo>,
+    by_path: HashMap<PathBuf, usize>,
+    pub columns: usize,
+    scroll_y: f32,
+    viewport_height: f32,
+    /// Cell a rubber-band drag started on
+    pub drag_from: Option<usize>,
+}
+
+impl PhotoGrid {
+    pub fn new(files: &[FileEntry]) -> Self {
+        let photos: Vec<Photo> = files
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| e.file_type == FileType::Image)
+            .map(|(file, e)| Photo {
+                file,
+                path: e.path.clone(),
+                thumb: Thumb::Missing,
+            })
+            .collect();
+        let by_path = photos
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (p.path.clone(), i))
+            .collect();
+        Self {
+            photos,
+            by_path,
+            columns: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Fit the grid to a viewport of `width` x `height` scrolled down by
+    /// `scroll_y`
+    pub fn set_viewport(&mut self, width: f32, height: f32, scroll_y: f32) {
+        self.columns = ((width / CELL_SIZE) as usize).max(1);
+        self.viewport_height = height;
+        self.scroll_y = scroll_y.max(0.0);
+    }
+
+    /// Fit the grid to a new viewport size at the same scroll position
+    pub fn resize(&mut self, width: f32, height: f32) {
+        self.set_viewport(width, height, self.scroll_y);
+    }
+
+    pub fn rows(&self) -> usize {
+        self.photos.len().div_ceil(self.columns)
+    }
+
+    /// Rows to lay out: those in the viewport plus a few either side
+    pub fn visible_rows(&self) -> Range<usize> {
+        let first = (self.scroll_y / CELL_SIZE) as usize;
+        let shown = (self.viewport_height / CELL_SIZE).ceil() as usize + 1;
+        let start = first.saturating_sub(OVERSCAN_ROWS).min(self.rows());
+        let end = (first + shown + OVERSCAN_ROWS).min(self.rows());
+        start..end
+    }
+
+    /// Photos in the visible rows
+    pub fn visible(&self) -> Range<usize> {
+        let rows = self.visible_rows();
+        (rows.start * self.columns).min(self.photos.len())
+            ..(rows.end * self.columns).min(self.photos.len())
+    }
+
+    /// Visible photos without a thumbnail yet, marked as loading
+    pub fn take_unloaded(&mut self) -> Vec<PathBuf> {
+        let range = self.visible();
+        self.photos[range]
+            .iter_mut()
+            .filter(|p| p.thumb == Thumb::Missing)
+            .map(|p| {
+                p.thumb = Thumb::Loading;
+                p.path.clone()
+            })
+            .collect()
+    }
+
+    pub fn apply(&mut self, loaded: ThumbLoaded) {
+        let Some(&i) = self.by_path.get(&loaded.source) else {
+            return;
+        };
+        let photo = &mut self.photos[i];
+        photo.thumb = match (loaded.result, &photo.thumb) {
+            // A late small thumb never replaces the large one
+            (Ok(_), Thumb::Large(_)) if loaded.size < THUMB_LARGE => return,
+            (Ok(path), _) if loaded.size >= THUMB_LARGE => Thumb::Large(path),
+            (Ok(path), _) => Thumb::Small(path),
+            // The small thumb stays up if only the large one failed
+            (Err(_), Thumb::Small(_)) => return,
+            (Err(_), _) => Thumb::Failed,
+        };
+    }
+
+    /// Files of the photos in the rectangle of cells spanned by a drag from
+    /// cell `from` to cell `to`
+    pub fn band(&self, from: usize, to: usize) -> Vec<usize> {
+        let (r0, c0) = (from / self.columns, from % self.columns);
+        let (r1, c1) = (to / self.columns, to % self.columns);
+        let rows = r0.min(r1)..=r0.max(r1);
+        let cols = c0.min(c1)..=c0.max(c1);
+        self.photos
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| {
+                rows.contains(&(i / self.columns)) && cols.contains(&(i % self.columns))
+            })
+            .map(|(_, p)| p.file)
+            .collect()
+    }
+}
+
+/// Write the small thumbnails of `sources`, then the large ones, reporting
+/// each through `tx` as it is ready. Blocking; run it off the GUI thread.
+pub fn load_thumbnails(
+    generator: &ThumbnailGenerator,
+    sources: &[PathBuf],
+    tx: &UnboundedSender<ThumbLoaded>,
+) {
+    // The small size comes back cached from the first pass, so the second
+    // decodes each image only for the large one
+    let passes: [&[u32]; 2] = [&[THUMB_SMALL], &[THUMB_SMALL, THUMB_LARGE]];
+    let mut pending: Vec<&PathBuf> = sources.iter().collect();
+    for sizes in passes {
+        let size = sizes[sizes.len() - 1];
+        let mut loaded_ok = Vec::with_capacity(pending.len());
+        for source in pending {
+            let result = generator
+                .generate_progressive_multi(source, sizes)
+                .map(|mut paths| paths.remove(paths.len() - 1))
+                .map_err(|e| format!("{:#}", e));
+            if result.is_ok() {
+                loaded_ok.push(source);
+            }
+            let loaded = ThumbLoaded {
+                source: source.clone(),
+                size,
+                result,
+            };
+            // The grid is gone if the receiver is
+            if tx.send(loaded).is_err() {
+                return;
+            }
+        }
+        pending = loaded_ok;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn photo(name: &str) -> FileEntry {
+        FileEntry {
+            path: PathBuf::from(name),
+            size: 1000,
+            file_type: FileType::Image,
+            extension: "png".to_string(),
+            modified: None,
+            created: None,
+            hash: None,
+            has_bad_sectors: false,
+            thumbnail: None,
+            metadata: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_virtualized_rows_and_rubber_band() {
+        let mut files: Vec<FileEntry> = (0..100).map(|i| photo(&format!("{}.png", i))).collect();
+        files[3].file_type = FileType::Document;
+        let mut grid = PhotoGrid::new(&files);
+        assert_eq!(grid.photos.len(), 99);
+
+        // 4 columns, 3 rows tall, scrolled to row 10
+        grid.set_viewport(CELL_SIZE * 4.5, CELL_SIZE * 3.0, CELL_SIZE * 10.0);
+        assert_eq!(grid.columns, 4);
+        assert_eq!(grid.rows(), 25);
+        assert_eq!(grid.visible_rows(), 8..16);
+        assert_eq!(grid.visible(), 32..64);
+        assert_eq!(grid.take_unloaded().len(), 32);
+        assert!(grid.take_unloaded().is_empty(), "already loading");
+
+        // Dragging from cell 1 to cell 10 spans rows 0-2, columns 1-2; cell
+        // 3 onwards is file 4 because file 3 isn't an image
+        assert_eq!(grid.band(10, 1), vec![1, 2, 6, 7, 10, 11]);
+    }
+
+    #[test]
+    fn test_small_thumbs_arrive_before_large_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        let files: Vec<FileEntry> = (0..3)
+            .map(|i| {
+                let path = dir.path().join(format!("photo-{}.png", i));
+                image::DynamicImage::new_rgb8(600, 400).save(&path).unwrap();
+                FileEntry::new(path.clone(), &std::fs::metadata(&path).unwrap())
+            })
+            .collect();
+        let mut grid = PhotoGrid::new(&files);
+        grid.set_viewport(CELL_SIZE * 3.0, CELL_SIZE, 0.0);
+        let sources = grid.take_unloaded();
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        load_thumbnails(&ThumbnailGenerator::new(), &sources, &tx);
+        let mut sizes = Vec::new();
+        while let Ok(loaded) = rx.try_recv() {
+            sizes.push(loaded.size);
+            grid.apply(loaded);
+        }
+        assert_eq!(sizes, vec![64, 64, 64, 512, 512, 512]);
+        assert!(grid
+            .photos
+            .iter()
+            .all(|p| matches!(p.thumb, Thumb::Large(_))));
+    }
+}
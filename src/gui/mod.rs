@@ -6,6 +6,10 @@
 #[cfg(feature = "gui")]
 mod app;
 #[cfg(feature = "gui")]
+pub mod grid;
+#[cfg(feature = "gui")]
+pub mod queue;
+#[cfg(feature = "gui")]
 pub mod theme;
 
 #[cfg(feature = "gui")]
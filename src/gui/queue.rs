@@ -0,0 +1,185 @@
+//! Export queue shown in the GUI's Export view
+//!
+//! Follows the [`ExportFileEvent`]s of a running export so every file is
+//! drawn with its own progress and hash status.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::core::FileEntry;
+use crate::export::ExportFileEvent;
+
+/// Where one file of the queue is
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueueStatus {
+    Queued,
+    Copying {
+        bytes: u64,
+    },
+    Verifying,
+    /// Copied and verified; retries counts failed attempts before that
+    Verified {
+        hash: String,
+        retries: usize,
+    },
+    Skipped(String),
+    Failed(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct QueueItem {
+    pub source: PathBuf,
+    pub size: u64,
+    pub status: QueueStatus,
+}
+
+impl QueueItem {
+    pub fn name(&self) -> String {
+        self.source
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| self.source.display().to_string())
+    }
+
+    /// Fraction of this file copied
+    pub fn ratio(&self) -> f32 {
+        match &self.status {
+            QueueStatus::Queued => 0.0,
+            QueueStatus::Copying { bytes } if self.size > 0 => {
+                (*bytes as f64 / self.size as f64).min(1.0) as f32
+            }
+            QueueStatus::Copying { .. } => 0.0,
+            _ => 1.0,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self.status,
+            QueueStatus::Verified { .. } | QueueStatus::Skipped(_) | QueueStatus::Failed(_)
+        )
+    }
+}
+
+/// Files of one export, in the order they were queued
+#[derive(Debug, Clone, Default)]
+pub struct ExportQueue {
+    pub items: Vec<QueueItem>,
+    by_source: HashMap<PathBuf, usize>,
+}
+
+impl ExportQueue {
+    pub fn new(entries: &[FileEntry]) -> Self {
+        let items: Vec<QueueItem> = entries
+            .iter()
+            .map(|e| QueueItem {
+                source: e.path.clone(),
+                size: e.size,
+                status: QueueStatus::Queued,
+            })
+            .collect();
+        let by_source = items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| (item.source.clone(), i))
+            .collect();
+        Self { items, by_source }
+    }
+
+    pub fn apply(&mut self, event: ExportFileEvent) {
+        let (source, status) = match event {
+            ExportFileEvent::Started { source, .. } => (source, QueueStatus::Copying { bytes: 0 }),
+            ExportFileEvent::Copied { source, bytes } => (source, QueueStatus::Copying { bytes }),
+            ExportFileEvent::Verifying { source } => (source, QueueStatus::Verifying),
+            ExportFileEvent::Skipped { source, reason } => (source, QueueStatus::Skipped(reason)),
+            ExportFileEvent::Finished { source, result } => (
+                source,
+                match result {
+                    Ok((hash, retries)) => QueueStatus::Verified { hash, retries },
+                    Err(e) => QueueStatus::Failed(e),
+                },
+            ),
+        };
+        if let Some(&i) = self.by_source.get(&source) {
+            self.items[i].status = status;
+        }
+    }
+
+    /// Fraction of all queued bytes copied
+    pub fn progress(&self) -> f32 {
+        let total: u64 = self.items.iter().map(|i| i.size).sum();
+        if total == 0 {
+            return if self.items.iter().all(QueueItem::is_finished) {
+                1.0
+            } else {
+                0.0
+            };
+        }
+        let done: f64 = self
+            .items
+            .iter()
+            .map(|i| i.ratio() as f64 * i.size as f64)
+            .sum();
+        (done / total as f64) as f32
+    }
+
+    /// Finished and failed file counts
+    pub fn counts(&self) -> (usize, usize) {
+        let finished = self.items.iter().filter(|i| i.is_finished()).count();
+        let failed = self
+            .items
+            .iter()
+            .filter(|i| matches!(i.status, QueueStatus::Failed(_)))
+            .count();
+        (finished, failed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, size: u64) -> FileEntry {
+        FileEntry {
+            path: PathBuf::from(name),
+            size,
+            file_type: crate::core::FileType::Image,
+            extension: "jpg".to_string(),
+            modified: None,
+            created: None,
+            hash: None,
+            has_bad_sectors: false,
+            thumbnail: None,
+            metadata: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_queue_follows_file_events() {
+        let mut queue = ExportQueue::new(&[entry("a.jpg", 300), entry("b.jpg", 100)]);
+        queue.apply(ExportFileEvent::Copied {
+            source: "a.jpg".into(),
+            bytes: 150,
+        });
+        assert_eq!(queue.items[0].ratio(), 0.5);
+        assert_eq!(queue.progress(), 150.0 / 400.0);
+
+        queue.apply(ExportFileEvent::Finished {
+            source: "a.jpg".into(),
+            result: Ok(("abc".into(), 1)),
+        });
+        queue.apply(ExportFileEvent::Finished {
+            source: "b.jpg".into(),
+            result: Err("read error".into()),
+        });
+        assert_eq!(
+            queue.items[0].status,
+            QueueStatus::Verified {
+                hash: "abc".into(),
+                retries: 1
+            }
+        );
+        assert_eq!(queue.counts(), (2, 1));
+        assert_eq!(queue.progress(), 1.0);
+    }
+}
@@ -0,0 +1,356 @@
+//! Drive health from SMART data
+//!
+//! Reading every sector of a drive that is already remapping or failing to
+//! read sectors can finish it off. Before indexing, carving or exporting
+//! from such a drive it should be imaged once (`diamond-drill image`) and
+//! the image worked on instead. SMART data is read through smartmontools'
+//! `smartctl --json`, which knows how to talk to SATA, SAS, NVMe and most USB
+//! bridges on every platform we run on.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// ATA attribute: reallocated sector count
+const ATTR_REALLOCATED: u64 = 5;
+/// ATA attribute: current pending sector count
+const ATTR_PENDING: u64 = 197;
+/// ATA attribute: offline uncorrectable sector count
+const ATTR_UNCORRECTABLE: u64 = 198;
+/// ATA attributes carrying the temperature in their raw value
+const ATTR_TEMPERATURE: [u64; 2] = [194, 190];
+
+/// Reallocated sectors above which a drive counts as failing
+const REALLOCATED_FAILING: u64 = 100;
+/// Temperature (°C) from which a drive counts as running hot
+const TEMPERATURE_WARM: i64 = 50;
+/// Temperature (°C) from which the drive should cool down between reads
+const TEMPERATURE_HOT: i64 = 60;
+
+/// SMART values that matter for recovery, as far as the drive reports them
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SmartHealth {
+    pub device: PathBuf,
+    pub model: Option<String>,
+    pub serial: Option<String>,
+    /// Overall self-assessment
+    pub passed: Option<bool>,
+    pub reallocated_sectors: Option<u64>,
+    /// Sectors the drive could not read and is waiting to remap
+    pub pending_sectors: Option<u64>,
+    pub uncorrectable_sectors: Option<u64>,
+    /// NVMe media and data integrity errors
+    pub media_errors: Option<u64>,
+    pub temperature_c: Option<i64>,
+    pub power_on_hours: Option<u64>,
+}
+
+/// How safe it is to keep reading a drive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verdict {
+    Healthy,
+    /// Wearing or warm: fine to read, worth watching
+    Degraded,
+    /// Actively failing: image it before anything else
+    Failing,
+}
+
+impl Verdict {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Healthy => "healthy",
+            Self::Degraded => "degraded",
+            Self::Failing => "failing",
+        }
+    }
+}
+
+/// A verdict and what led to it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthAssessment {
+    pub verdict: Verdict,
+    pub reasons: Vec<String>,
+}
+
+impl SmartHealth {
+    /// Judge the drive from its SMART values
+    pub fn assess(&self) -> HealthAssessment {
+        let mut verdict = Verdict::Healthy;
+        let mut reasons = Vec::new();
+        let mut flag = |level: Verdict, reason: String| {
+            verdict = verdict.max(level);
+            reasons.push(reason);
+        };
+
+        if self.passed == Some(false) {
+            flag(
+                Verdict::Failing,
+                "SMART overall self-assessment FAILED".into(),
+            );
+        }
+        if let Some(n) = self.pending_sectors.filter(|&n| n > 0) {
+            flag(
+                Verdict::Failing,
+                format!("{} sectors pending reallocation", n),
+            );
+        }
+        if let Some(n) = self.uncorrectable_sectors.filter(|&n| n > 0) {
+            flag(Verdict::Failing, format!("{} uncorrectable sectors", n));
+        }
+        if let Some(n) = self.media_errors.filter(|&n| n > 0) {
+            flag(Verdict::Failing, format!("{} media errors", n));
+        }
+        match self.reallocated_sectors {
+            Some(n) if n > REALLOCATED_FAILING => {
+                flag(Verdict::Failing, format!("{} reallocated sectors", n))
+            }
+            Some(n) if n > 0 => flag(Verdict::Degraded, format!("{} reallocated sectors", n)),
+            _ => {}
+        }
+        match self.temperature_c {
+            // Heat alone is a reason to pause, not a sign of failure
+            Some(t) if t >= TEMPERATURE_HOT => flag(
+                Verdict::Degraded,
+                format!("running hot at {}°C; let it cool down", t),
+            ),
+            Some(t) if t >= TEMPERATURE_WARM => {
+                flag(Verdict::Degraded, format!("running warm at {}°C", t))
+            }
+            _ => {}
+        }
+
+        HealthAssessment { verdict, reasons }
+    }
+}
+
+/// Read SMART data for `device` with `smartctl`
+pub fn query(device: &Path) -> Result<SmartHealth> {
+    let output = Command::new("smartctl")
+        .args(["--json", "-i", "-H", "-A"])
+        .arg(device)
+        .output()
+        .context("Failed to run smartctl (install smartmontools)")?;
+    // smartctl sets status bits for failing drives too; the JSON is what counts
+    let text = String::from_utf8_lossy(&output.stdout);
+    let mut health = parse_smartctl_json(&text)
+        .with_context(|| format!("No SMART data for {}", device.display()))?;
+    health.device = device.to_path_buf();
+    Ok(health)
+}
+
+/// Pull the recovery-relevant values out of `smartctl --json` output
+pub fn parse_smartctl_json(text: &str) -> Result<SmartHealth> {
+    let json: Value = serde_json::from_str(text).context("smartctl output is not JSON")?;
+    let str_at = |pointer: &str| json.pointer(pointer).and_then(Value::as_str);
+
+    let attributes: Vec<(u64, u64)> = json
+        .pointer("/ata_smart_attributes/table")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|attr| {
+            let id = attr.get("id")?.as_u64()?;
+            let raw = attr.pointer("/raw/value")?.as_u64()?;
+            Some((id, raw))
+        })
+        .collect();
+    let attribute = |id: u64| attributes.iter().find(|a| a.0 == id).map(|a| a.1);
+    let nvme = |field: &str| {
+        json.pointer(&format!("/nvme_smart_health_information_log/{}", field))
+            .and_then(Value::as_u64)
+    };
+
+    let health = SmartHealth {
+        device: str_at("/device/name")
+            .map(PathBuf::from)
+            .unwrap_or_default(),
+        model: str_at("/model_name").map(str::to_string),
+        serial: str_at("/serial_number").map(str::to_string),
+        passed: json
+            .pointer("/smart_status/passed")
+            .and_then(Value::as_bool),
+        reallocated_sectors: attribute(ATTR_REALLOCATED),
+        pending_sectors: attribute(ATTR_PENDING),
+        uncorrectable_sectors: attribute(ATTR_UNCORRECTABLE),
+        media_errors: nvme("media_errors"),
+        temperature_c: json
+            .pointer("/temperature/current")
+            .and_then(Value::as_i64)
+            // Raw temperature attributes pack min/max into the high bytes
+            .or_else(|| {
+                ATTR_TEMPERATURE
+                    .iter()
+                    .find_map(|&id| attribute(id))
+                    .map(|raw| (raw & 0xFF) as i64)
+            }),
+        power_on_hours: json.pointer("/power_on_time/hours").and_then(Value::as_u64),
+    };
+    if health.passed.is_none() && attributes.is_empty() && health.media_errors.is_none() {
+        let messages: Vec<&str> = json
+            .pointer("/smartctl/messages")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|m| m.get("string")?.as_str())
+            .collect();
+        if messages.is_empty() {
+            bail!("device reported no SMART data");
+        }
+        bail!("{}", messages.join("; "));
+    }
+    Ok(health)
+}
+
+/// The device holding `path`: the path itself for a block device, else the
+/// device the containing filesystem is mounted from
+pub fn source_device(path: &Path) -> Option<PathBuf> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        if std::fs::metadata(path).ok()?.file_type().is_block_device() {
+            return Some(path.to_path_buf());
+        }
+    }
+    mounted_from(path)
+}
+
+#[cfg(target_os = "linux")]
+fn mounted_from(path: &Path) -> Option<PathBuf> {
+    let path = std::fs::canonicalize(path).ok()?;
+    let mountinfo = std::fs::read_to_string("/proc/self/mountinfo").ok()?;
+    mount_source(&mountinfo, &path)
+}
+
+/// smartctl accepts a drive letter for the disk holding it
+#[cfg(windows)]
+fn mounted_from(path: &Path) -> Option<PathBuf> {
+    use std::path::{Component, Prefix};
+    match path.components().next()? {
+        Component::Prefix(p) => match p.kind() {
+            Prefix::Disk(d) | Prefix::VerbatimDisk(d) => {
+                Some(PathBuf::from(format!("{}:", d as char)))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn mounted_from(_path: &Path) -> Option<PathBuf> {
+    None
+}
+
+/// Source device of the deepest mount in `/proc/self/mountinfo` that
+/// contains `path`, if it is a `/dev` node
+#[cfg(any(target_os = "linux", test))]
+fn mount_source(mountinfo: &str, path: &Path) -> Option<PathBuf> {
+    mount_of(mountinfo, path)
+        .filter(|(_, source)| source.starts_with("/dev/"))
+        .map(|(_, source)| PathBuf::from(source))
+}
+
+/// Filesystem type and source of the deepest mount in
+/// `/proc/self/mountinfo` that contains `path`
+#[cfg(any(target_os = "linux", test))]
+pub(crate) fn mount_of<'a>(mountinfo: &'a str, path: &Path) -> Option<(&'a str, &'a str)> {
+    mount_entry(mountinfo, path).map(|(_, fstype, source)| (fstype, source))
+}
+
+/// [`mount_of`] with the mount point
+#[cfg(any(target_os = "linux", test))]
+pub(crate) fn mount_entry<'a>(
+    mountinfo: &'a str,
+    path: &Path,
+) -> Option<(PathBuf, &'a str, &'a str)> {
+    mountinfo
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let mount_point = fields.get(4)?;
+            // Optional fields end with "-", then fstype and source
+            let dash = fields.iter().position(|f| *f == "-")?;
+            let fstype = fields.get(dash + 1)?;
+            let source = fields.get(dash + 2)?;
+            Some((mount_point.replace("\\040", " "), *fstype, *source))
+        })
+        .filter(|(mount_point, _, _)| path.starts_with(mount_point))
+        .max_by_key(|(mount_point, _, _)| mount_point.len())
+        .map(|(mount_point, fstype, source)| (mount_point.into(), fstype, source))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ATA: &str = r#"{
+        "smartctl": {"exit_status": 8},
+        "device": {"name": "/dev/sdb", "type": "sat"},
+        "model_name": "WDC WD10EZEX",
+        "serial_number": "WD-123",
+        "smart_status": {"passed": true},
+        "ata_smart_attributes": {"table": [
+            {"id": 5, "name": "Reallocated_Sector_Ct", "raw": {"value": 12}},
+            {"id": 194, "name": "Temperature_Celsius", "raw": {"value": 214749413409}},
+            {"id": 197, "name": "Current_Pending_Sector", "raw": {"value": 3}},
+            {"id": 198, "name": "Offline_Uncorrectable", "raw": {"value": 0}}
+        ]},
+        "power_on_time": {"hours": 41000}
+    }"#;
+
+    #[test]
+    fn test_parse_and_assess_ata() {
+        let health = parse_smartctl_json(ATA).unwrap();
+        assert_eq!(health.device, PathBuf::from("/dev/sdb"));
+        assert_eq!(health.reallocated_sectors, Some(12));
+        assert_eq!(health.pending_sectors, Some(3));
+        assert_eq!(health.temperature_c, Some(33));
+        assert_eq!(health.power_on_hours, Some(41000));
+
+        let assessment = health.assess();
+        assert_eq!(assessment.verdict, Verdict::Failing);
+        assert_eq!(assessment.reasons.len(), 2);
+
+        let healed = SmartHealth {
+            pending_sectors: Some(0),
+            ..health
+        };
+        assert_eq!(healed.assess().verdict, Verdict::Degraded);
+
+        let nvme = r#"{"smart_status": {"passed": true}, "temperature": {"current": 41},
+            "nvme_smart_health_information_log": {"media_errors": 0}}"#;
+        let health = parse_smartctl_json(nvme).unwrap();
+        assert_eq!(health.temperature_c, Some(41));
+        assert_eq!(health.assess().verdict, Verdict::Healthy);
+
+        let hot = SmartHealth {
+            temperature_c: Some(65),
+            ..health
+        };
+        assert_eq!(hot.assess().verdict, Verdict::Degraded);
+
+        let unsupported = r#"{"smartctl": {"messages": [{"string": "Unknown USB bridge"}]}}"#;
+        let err = parse_smartctl_json(unsupported).unwrap_err();
+        assert!(err.to_string().contains("Unknown USB bridge"));
+    }
+
+    #[test]
+    fn test_mount_source() {
+        let mountinfo = "\
+22 1 8:2 / / rw,relatime shared:1 - ext4 /dev/sda2 rw
+40 22 8:17 / /mnt/evidence ro,relatime shared:30 - ntfs3 /dev/sdb1 ro
+41 22 0:5 / /mnt/evidence/tmp rw - tmpfs tmpfs rw
+";
+        let source = |p: &str| mount_source(mountinfo, Path::new(p));
+        assert_eq!(source("/home/user"), Some(PathBuf::from("/dev/sda2")));
+        assert_eq!(
+            source("/mnt/evidence/DCIM"),
+            Some(PathBuf::from("/dev/sdb1"))
+        );
+        assert_eq!(source("/mnt/evidence/tmp/x"), None);
+    }
+}
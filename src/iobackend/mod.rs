@@ -0,0 +1,308 @@
+//! Source read backends
+//!
+//! Recovery reads are long and sequential: carving an image, sweeping a file
+//! for bad blocks, copying an export. One blocking `read` at a time leaves an
+//! NVMe or USB3 source waiting on syscall round trips instead of working
+//! through a queue. [`stream`] reads a byte range in fixed-size blocks with
+//! up to `queue_depth` requests in flight and hands them back in order:
+//!
+//! - **io_uring** (Linux, `async-io` feature): the block reads are submitted
+//!   to a ring together and reaped as they complete
+//! - **Overlapped I/O** (Windows, `async-io` feature): one `ReadFile` per
+//!   block, each with its own completion event
+//! - **Sync** (everywhere): positional reads with adaptive read-ahead. A read
+//!   spans one block at first and doubles after each clean read up to
+//!   `queue_depth` blocks; after an error it drops back to one block so a bad
+//!   block is reported on its own
+//!
+//! [`ReadAhead`] streams a range in the background and discards the data,
+//! pulling it into the page cache ahead of a scan over a memory map.
+
+#[cfg(all(feature = "async-io", windows))]
+mod overlapped;
+#[cfg(all(feature = "async-io", target_os = "linux"))]
+mod uring;
+
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// Requests kept in flight unless configured otherwise
+pub const DEFAULT_QUEUE_DEPTH: usize = 32;
+
+/// How often a read-ahead that got a window ahead checks on its consumer
+const READ_AHEAD_POLL: Duration = Duration::from_millis(50);
+
+/// Consumer of streamed blocks: offset, then bytes or read error
+type OnBlock<'a> = dyn FnMut(u64, std::io::Result<&[u8]>) -> Result<()> + 'a;
+
+/// What reads the source
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IoBackend {
+    /// io_uring or overlapped I/O when compiled in, sync reads otherwise
+    #[default]
+    Auto,
+    /// Blocking positional reads with adaptive read-ahead
+    Sync,
+    /// io_uring (Linux)
+    Uring,
+    /// Overlapped I/O (Windows)
+    Overlapped,
+}
+
+impl IoBackend {
+    /// The backend `Auto` stands for in this build
+    pub fn resolve(self) -> Self {
+        match self {
+            Self::Auto if cfg!(all(feature = "async-io", target_os = "linux")) => Self::Uring,
+            Self::Auto if cfg!(all(feature = "async-io", windows)) => Self::Overlapped,
+            Self::Auto => Self::Sync,
+            backend => backend,
+        }
+    }
+}
+
+/// How sources are read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IoOptions {
+    pub backend: IoBackend,
+    /// Block reads in flight (the largest read-ahead window for sync reads)
+    pub queue_depth: usize,
+    /// Size of each block read (None = the caller's own default), e.g. as
+    /// calibrated by `bench`
+    #[serde(default)]
+    pub block_size: Option<usize>,
+}
+
+impl Default for IoOptions {
+    fn default() -> Self {
+        Self {
+            backend: IoBackend::Auto,
+            queue_depth: DEFAULT_QUEUE_DEPTH,
+            block_size: None,
+        }
+    }
+}
+
+/// Read `range` of the file at `path` in `block_size` blocks, passing each
+/// block's offset and bytes (or its read error) to `on_block` in order. The
+/// last block may be short, and reading stops at the end of the file.
+/// Stops early with the first error `on_block` returns.
+pub fn stream<F>(
+    path: &Path,
+    range: Range<u64>,
+    block_size: usize,
+    options: &IoOptions,
+    mut on_block: F,
+) -> Result<()>
+where
+    F: FnMut(u64, std::io::Result<&[u8]>) -> Result<()>,
+{
+    let block_size = block_size.max(1);
+    let queue_depth = options.queue_depth.max(1);
+    match options.backend.resolve() {
+        #[cfg(all(feature = "async-io", target_os = "linux"))]
+        IoBackend::Uring => uring::stream(path, range, block_size, queue_depth, &mut on_block),
+        #[cfg(all(feature = "async-io", windows))]
+        IoBackend::Overlapped => {
+            overlapped::stream(path, range, block_size, queue_depth, &mut on_block)
+        }
+        IoBackend::Sync | IoBackend::Auto => {
+            stream_sync(path, range, block_size, queue_depth, &mut on_block)
+        }
+        backend => Err(unavailable(backend)),
+    }
+}
+
+/// Why `backend` can't be used in this build
+fn unavailable(backend: IoBackend) -> anyhow::Error {
+    match backend {
+        IoBackend::Uring if cfg!(not(target_os = "linux")) => {
+            anyhow::anyhow!("io_uring is only available on Linux")
+        }
+        IoBackend::Overlapped if cfg!(not(windows)) => {
+            anyhow::anyhow!("Overlapped I/O is only available on Windows")
+        }
+        _ => {
+            anyhow::anyhow!("Async I/O backends not compiled in; rebuild with --features async-io")
+        }
+    }
+}
+
+fn stream_sync(
+    path: &Path,
+    range: Range<u64>,
+    block_size: usize,
+    queue_depth: usize,
+    on_block: &mut OnBlock,
+) -> Result<()> {
+    let file =
+        std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut buffer = Vec::new();
+    let mut window = 1;
+    let mut pos = range.start;
+
+    while pos < range.end {
+        let len = (range.end - pos).min((window * block_size) as u64) as usize;
+        buffer.resize(len, 0);
+        match read_full_at(&file, &mut buffer, pos) {
+            Ok(read) => {
+                for (i, block) in buffer[..read].chunks(block_size).enumerate() {
+                    on_block(pos + (i * block_size) as u64, Ok(block))?;
+                }
+                if read < len {
+                    break;
+                }
+                pos += len as u64;
+                window = (window * 2).min(queue_depth);
+            }
+            // Go back over the window a block at a time to find the bad one
+            Err(_) if window > 1 => window = 1,
+            Err(e) => {
+                on_block(pos, Err(e))?;
+                pos += len as u64;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Fill `buf` from `offset`, short only at the end of the file
+pub(crate) fn read_full_at(
+    file: &std::fs::File,
+    buf: &mut [u8],
+    offset: u64,
+) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        #[cfg(unix)]
+        let read =
+            std::os::unix::fs::FileExt::read_at(file, &mut buf[filled..], offset + filled as u64);
+        #[cfg(windows)]
+        let read = std::os::windows::fs::FileExt::seek_read(
+            file,
+            &mut buf[filled..],
+            offset + filled as u64,
+        );
+        match read {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+/// Shared between a [`ReadAhead`] and its reader thread
+struct Cursor {
+    /// Offset the consumer has reached
+    consumer: AtomicU64,
+    stop: AtomicBool,
+}
+
+/// Background read of a range through a backend, held at most a window of
+/// `queue_depth` blocks ahead of where the consumer says it is
+pub struct ReadAhead {
+    cursor: Arc<Cursor>,
+    reader: Option<JoinHandle<()>>,
+}
+
+impl ReadAhead {
+    pub fn spawn(path: &Path, range: Range<u64>, block_size: usize, options: IoOptions) -> Self {
+        let cursor = Arc::new(Cursor {
+            consumer: AtomicU64::new(range.start),
+            stop: AtomicBool::new(false),
+        });
+        let shared = Arc::clone(&cursor);
+        let path: PathBuf = path.to_path_buf();
+        let window = (block_size * options.queue_depth.max(1)) as u64;
+
+        let reader = std::thread::spawn(move || {
+            let result = stream(&path, range, block_size, &options, |offset, _| {
+                while offset > shared.consumer.load(Ordering::Relaxed) + window {
+                    if shared.stop.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    std::thread::park_timeout(READ_AHEAD_POLL);
+                }
+                anyhow::ensure!(!shared.stop.load(Ordering::Relaxed), "read-ahead stopped");
+                Ok(())
+            });
+            match result {
+                Err(_) if shared.stop.load(Ordering::Relaxed) => {}
+                Err(e) => tracing::debug!(path = %path.display(), "Read-ahead ended early: {e:#}"),
+                Ok(()) => {}
+            }
+        });
+        Self {
+            cursor,
+            reader: Some(reader),
+        }
+    }
+
+    /// Tell the reader the consumer has reached `offset`
+    pub fn advance(&self, offset: u64) {
+        self.cursor.consumer.store(offset, Ordering::Relaxed);
+        if let Some(reader) = &self.reader {
+            reader.thread().unpark();
+        }
+    }
+}
+
+impl Drop for ReadAhead {
+    fn drop(&mut self) {
+        self.cursor.stop.store(true, Ordering::Relaxed);
+        if let Some(reader) = self.reader.take() {
+            reader.thread().unpark();
+            let _ = reader.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_returns_blocks_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("source.bin");
+        let data: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&path, &data).unwrap();
+
+        for backend in [IoBackend::Auto, IoBackend::Sync] {
+            let options = IoOptions {
+                backend,
+                queue_depth: 4,
+                block_size: None,
+            };
+            let mut seen = Vec::new();
+            stream(&path, 100..20_000, 512, &options, |offset, block| {
+                let block = block?;
+                assert_eq!(offset, 100 + seen.len() as u64);
+                seen.extend_from_slice(block);
+                Ok(())
+            })
+            .unwrap();
+            assert_eq!(seen, data[100..]);
+        }
+
+        // The consumer stopping ends the stream
+        let mut blocks = 0;
+        let stopped = stream(&path, 0..10_000, 1000, &IoOptions::default(), |_, _| {
+            blocks += 1;
+            anyhow::ensure!(blocks < 3, "enough");
+            Ok(())
+        });
+        assert!(stopped.is_err());
+        assert_eq!(blocks, 3);
+    }
+}
@@ -0,0 +1,164 @@
+//! Overlapped I/O backend (Windows)
+//!
+//! The file is opened for overlapped reads and each of `queue_depth`
+//! requests gets a buffer and a manual-reset event. Block `n` is issued on
+//! request `n % queue_depth`, which is reissued with the next block once
+//! `n` has been handed back. Every issued read is waited for before the
+//! buffers are freed, even when the consumer stops early.
+
+use std::ops::Range;
+use std::os::windows::fs::OpenOptionsExt;
+use std::os::windows::io::AsRawHandle;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use windows_sys::Win32::Foundation::{
+    CloseHandle, GetLastError, ERROR_HANDLE_EOF, ERROR_IO_PENDING, FALSE, HANDLE, TRUE,
+};
+use windows_sys::Win32::Storage::FileSystem::{ReadFile, FILE_FLAG_OVERLAPPED};
+use windows_sys::Win32::System::Threading::CreateEventW;
+use windows_sys::Win32::System::IO::{GetOverlappedResult, OVERLAPPED};
+
+use super::OnBlock;
+
+/// One read slot. Boxed so `overlapped` keeps its address while pending.
+struct Request {
+    overlapped: OVERLAPPED,
+    buffer: Vec<u8>,
+    /// Error from `ReadFile` itself when the read never started
+    failed: Option<u32>,
+    pending: bool,
+}
+
+impl Request {
+    fn new(block_size: usize) -> Result<Box<Self>> {
+        // SAFETY: a manual-reset, initially unsignalled, unnamed event
+        let event = unsafe { CreateEventW(std::ptr::null(), TRUE, FALSE, std::ptr::null()) };
+        anyhow::ensure!(!event.is_null(), "CreateEventW failed: {}", unsafe {
+            GetLastError()
+        });
+        // SAFETY: OVERLAPPED is plain data; all zeroes is its initial state
+        let mut overlapped: OVERLAPPED = unsafe { std::mem::zeroed() };
+        overlapped.hEvent = event;
+        Ok(Box::new(Self {
+            overlapped,
+            buffer: vec![0u8; block_size],
+            failed: None,
+            pending: false,
+        }))
+    }
+
+    fn issue(&mut self, handle: HANDLE, offset: u64, len: usize) {
+        self.overlapped.Anonymous.Anonymous.Offset = offset as u32;
+        self.overlapped.Anonymous.Anonymous.OffsetHigh = (offset >> 32) as u32;
+        self.failed = None;
+        // SAFETY: the buffer and OVERLAPPED live in this box, which is not
+        // touched or dropped until `wait` has returned for this read
+        let started = unsafe {
+            ReadFile(
+                handle,
+                self.buffer.as_mut_ptr(),
+                len as u32,
+                std::ptr::null_mut(),
+                &mut self.overlapped,
+            )
+        };
+        if started == 0 {
+            let error = unsafe { GetLastError() };
+            if error != ERROR_IO_PENDING {
+                self.failed = Some(error);
+                return;
+            }
+        }
+        self.pending = true;
+    }
+
+    /// Bytes read by the issued request, 0 at the end of the file
+    fn wait(&mut self, handle: HANDLE) -> std::io::Result<usize> {
+        let error = match self.failed.take() {
+            Some(error) => error,
+            None => {
+                let mut read = 0u32;
+                // SAFETY: the request was issued on `handle` with this OVERLAPPED
+                let ok = unsafe { GetOverlappedResult(handle, &self.overlapped, &mut read, TRUE) };
+                self.pending = false;
+                if ok != 0 {
+                    return Ok(read as usize);
+                }
+                unsafe { GetLastError() }
+            }
+        };
+        match error {
+            ERROR_HANDLE_EOF => Ok(0),
+            error => Err(std::io::Error::from_raw_os_error(error as i32)),
+        }
+    }
+}
+
+impl Drop for Request {
+    fn drop(&mut self) {
+        // SAFETY: the event was created in `new` and is only closed here
+        unsafe { CloseHandle(self.overlapped.hEvent) };
+    }
+}
+
+pub fn stream(
+    path: &Path,
+    range: Range<u64>,
+    block_size: usize,
+    queue_depth: usize,
+    on_block: &mut OnBlock,
+) -> Result<()> {
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(FILE_FLAG_OVERLAPPED)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let handle = file.as_raw_handle() as HANDLE;
+    let mut requests = (0..queue_depth)
+        .map(|_| Request::new(block_size))
+        .collect::<Result<Vec<_>>>()?;
+
+    let result = run(handle, &mut requests, range, block_size, on_block);
+    for request in requests.iter_mut().filter(|r| r.pending) {
+        let _ = request.wait(handle);
+    }
+    result
+}
+
+fn run(
+    handle: HANDLE,
+    requests: &mut [Box<Request>],
+    range: Range<u64>,
+    block_size: usize,
+    on_block: &mut OnBlock,
+) -> Result<()> {
+    let queue_depth = requests.len() as u64;
+    let blocks = (range.end.saturating_sub(range.start)).div_ceil(block_size as u64);
+    let block_len = |n: u64| {
+        let offset = range.start + n * block_size as u64;
+        (offset, (range.end - offset).min(block_size as u64) as usize)
+    };
+
+    let mut issued = 0u64;
+    for next in 0..blocks {
+        while issued < blocks && issued < next + queue_depth {
+            let (offset, len) = block_len(issued);
+            requests[(issued % queue_depth) as usize].issue(handle, offset, len);
+            issued += 1;
+        }
+
+        let request = &mut requests[(next % queue_depth) as usize];
+        let (offset, len) = block_len(next);
+        match request.wait(handle) {
+            Ok(read) => {
+                on_block(offset, Ok(&request.buffer[..read]))?;
+                if read < len {
+                    break;
+                }
+            }
+            Err(e) => on_block(offset, Err(e))?,
+        }
+    }
+    Ok(())
+}
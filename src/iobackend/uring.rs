@@ -0,0 +1,139 @@
+//! io_uring backend
+//!
+//! Each of `queue_depth` buffers belongs to one slot of the ring. Block `n`
+//! is read into slot `n % queue_depth`; the slot is refilled with the next
+//! block once `n` has been handed back, so reads stay queued while the
+//! consumer works. Everything in flight is reaped before the buffers are
+//! freed, even when the consumer stops early.
+
+use std::ops::Range;
+use std::os::fd::AsRawFd;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use io_uring::{opcode, types, IoUring};
+
+use super::OnBlock;
+
+pub fn stream(
+    path: &Path,
+    range: Range<u64>,
+    block_size: usize,
+    queue_depth: usize,
+    on_block: &mut OnBlock,
+) -> Result<()> {
+    let file =
+        std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let queue_depth = queue_depth.min(4096);
+    let mut ring = IoUring::new(queue_depth as u32).context("Failed to set up io_uring")?;
+    let mut slots = Slots {
+        buffers: vec![vec![0u8; block_size]; queue_depth],
+        results: vec![None; queue_depth],
+        in_flight: 0,
+    };
+
+    let result = run(&file, &mut ring, &mut slots, range, block_size, on_block);
+    // The kernel may still be writing into the buffers
+    while slots.in_flight > 0 {
+        if slots.reap(&mut ring).is_err() {
+            // Can't confirm the reads finished: leak the buffers rather than
+            // free memory the kernel may write to
+            std::mem::forget(slots.buffers);
+            break;
+        }
+    }
+    result
+}
+
+struct Slots {
+    buffers: Vec<Vec<u8>>,
+    /// Completed read result of each slot (bytes read, or -errno)
+    results: Vec<Option<i32>>,
+    in_flight: usize,
+}
+
+impl Slots {
+    /// Submit queued reads, wait for one to finish and collect every
+    /// finished one
+    fn reap(&mut self, ring: &mut IoUring) -> std::io::Result<()> {
+        ring.submit_and_wait(1)?;
+        let done: Vec<(u64, i32)> = ring
+            .completion()
+            .map(|cqe| (cqe.user_data(), cqe.result()))
+            .collect();
+        for (slot, result) in done {
+            self.results[slot as usize] = Some(result);
+            self.in_flight -= 1;
+        }
+        Ok(())
+    }
+}
+
+fn run(
+    file: &std::fs::File,
+    ring: &mut IoUring,
+    slots: &mut Slots,
+    range: Range<u64>,
+    block_size: usize,
+    on_block: &mut OnBlock,
+) -> Result<()> {
+    let fd = types::Fd(file.as_raw_fd());
+    let queue_depth = slots.buffers.len() as u64;
+    let blocks = (range.end.saturating_sub(range.start)).div_ceil(block_size as u64);
+    let block_len = |n: u64| {
+        let offset = range.start + n * block_size as u64;
+        (offset, (range.end - offset).min(block_size as u64) as usize)
+    };
+
+    let mut submitted = 0u64;
+    for next in 0..blocks {
+        let queued = submitted;
+        while submitted < blocks && submitted < next + queue_depth {
+            let slot = (submitted % queue_depth) as usize;
+            let (offset, len) = block_len(submitted);
+            let entry = opcode::Read::new(fd, slots.buffers[slot].as_mut_ptr(), len as u32)
+                .offset(offset)
+                .build()
+                .user_data(slot as u64);
+            // SAFETY: the slot's buffer is neither read nor freed until its
+            // completion has been reaped
+            unsafe { ring.submission().push(&entry) }
+                .map_err(|_| anyhow::anyhow!("io_uring submission queue full"))?;
+            slots.results[slot] = None;
+            slots.in_flight += 1;
+            submitted += 1;
+        }
+
+        let slot = (next % queue_depth) as usize;
+        if slots.results[slot].is_some() && submitted > queued {
+            ring.submit().context("io_uring submit failed")?;
+        }
+        while slots.results[slot].is_none() {
+            slots.reap(ring).context("io_uring wait failed")?;
+        }
+        let (offset, len) = block_len(next);
+        let read = match slots.results[slot].take() {
+            Some(n) if n < 0 => {
+                on_block(offset, Err(std::io::Error::from_raw_os_error(-n)))?;
+                continue;
+            }
+            Some(n) => n as usize,
+            None => unreachable!("waited for the slot above"),
+        };
+        // A short read mid-file is finished synchronously; a short one at
+        // the end of the file ends the stream
+        let buffer = &mut slots.buffers[slot][..len];
+        let read = match super::read_full_at(file, &mut buffer[read..], offset + read as u64) {
+            Ok(rest) => read + rest,
+            Err(e) => {
+                on_block(offset, Err(e))?;
+                continue;
+            }
+        };
+        on_block(offset, Ok(&buffer[..read]))?;
+        if read < len {
+            break;
+        }
+    }
+    Ok(())
+}
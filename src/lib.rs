@@ -12,6 +12,10 @@
 //! - **Blake3 Verification**: Cryptographic hash verification on exports
 //! - **Bad Sector Handling**: Graceful skip with offset logging
 //!
+//! Everything except [`proof`] sits behind the default `cli` feature, so
+//! `--no-default-features --features verify-only` builds a small
+//! `diamond-verify` binary for recipients of an export.
+//!
 //! # Example
 //!
 //! ```no_run
@@ -31,38 +35,99 @@
 //! }
 //! ```
 
+#[cfg(feature = "cli")]
+pub mod analyze;
+#[cfg(feature = "cli")]
+pub mod artifacts;
+#[cfg(feature = "cli")]
+pub mod audit;
+#[cfg(feature = "cli")]
 pub mod badsector;
+#[cfg(feature = "cli")]
+pub mod bench;
+#[cfg(feature = "cli")]
 pub mod carve;
+#[cfg(feature = "cli")]
+pub mod chat;
+#[cfg(feature = "cli")]
 pub mod checkpoint;
+#[cfg(feature = "cli")]
 pub mod cli;
+#[cfg(feature = "cli")]
 pub mod config;
+#[cfg(feature = "cli")]
 pub mod core;
+#[cfg(feature = "cli")]
 pub mod dedup;
+#[cfg(feature = "cli")]
+pub mod diskimage;
+#[cfg(feature = "cli")]
+pub mod error;
+#[cfg(feature = "cli")]
+pub mod events;
+#[cfg(feature = "cli")]
 pub mod export;
+#[cfg(feature = "cli")]
+pub mod health;
+#[cfg(feature = "cli")]
+pub mod iobackend;
+#[cfg(feature = "cli")]
+pub mod maintenance;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "cli")]
 pub mod preview;
 pub mod proof;
+#[cfg(feature = "cli")]
 pub mod readonly;
+#[cfg(feature = "cli")]
 pub mod report;
+#[cfg(feature = "cli")]
+pub mod rpc;
+#[cfg(feature = "serve")]
+pub mod serve;
+#[cfg(feature = "cli")]
+pub mod sparse;
+#[cfg(feature = "cli")]
 pub mod spinner;
+#[cfg(feature = "cli")]
 pub mod swarm;
+#[cfg(feature = "cli")]
+pub mod throttle;
+#[cfg(feature = "cli")]
 pub mod tui;
+#[cfg(feature = "cli")]
+pub mod watch;
 
 #[cfg(feature = "gui")]
 pub mod gui;
 
 // Re-export commonly used types
+#[cfg(feature = "cli")]
 pub use carve::{CarveOptions, CarveProgress, CarveResult, CarvedFile, Carver};
+#[cfg(feature = "cli")]
 pub use config::Config;
+#[cfg(feature = "cli")]
 pub use core::{DrillEngine, FileEntry, FileIndex, FileType};
+#[cfg(feature = "cli")]
 pub use dedup::{analyze, DedupOptions, DedupReport, DupGroup, KeepStrategy};
+#[cfg(feature = "cli")]
+pub use error::{DrillError, DrillResult};
+#[cfg(feature = "cli")]
 pub use export::{ExportOptions, ExportResult, Exporter};
+#[cfg(feature = "cli")]
 pub use preview::ThumbnailGenerator;
+#[cfg(feature = "cli")]
 pub use readonly::{
     is_readonly_enforced, open_readonly, run_safety_checks, safe_copy, warn_if_writable,
 };
+#[cfg(feature = "cli")]
 pub use spinner::{DiamondSpinner, PulseProgress, StatusIcons};
+#[cfg(feature = "cli")]
 pub use swarm::{
     run_swarm, run_swarm_async, run_swarm_with_config, with_gpu_fallback, with_retry,
     with_retry_async, AgentRole, HealConfig, HealResult, Healer, SwarmBuilder, SwarmConfig,
     SwarmOrchestrator, SwarmStats, SwarmSummary,
 };
+#[cfg(feature = "cli")]
+pub use throttle::Throttle;
@@ -4,35 +4,88 @@
 //! searches, selects and exports files from disk images/clones with extreme
 //! speed and safety.
 
-use anyhow::Result;
-use clap::Parser;
+use anyhow::{Context, Result};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 
-use diamond_drill::cli::{self, Cli, Commands};
+use diamond_drill::cli::{self, Commands};
 use diamond_drill::core::DrillEngine;
 #[cfg(feature = "gui")]
 use diamond_drill::gui;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize logging
+    let cli = cli::profile::parse_args()?;
+
+    // Initialize logging (on stderr in --rpc mode, where stdout carries
+    // only protocol messages)
+    let log_writer = if cli.rpc {
+        BoxMakeWriter::new(std::io::stderr)
+    } else {
+        BoxMakeWriter::new(std::io::stdout)
+    };
     tracing_subscriber::registry()
-        .with(fmt::layer().with_target(false).compact())
+        .with(
+            fmt::layer()
+                .with_target(false)
+                .compact()
+                .with_writer(log_writer),
+        )
         .with(EnvFilter::from_default_env().add_directive("diamond_drill=info".parse()?))
         .init();
 
-    let cli = Cli::parse();
+    diamond_drill::core::set_display_timezone(cli.timezone);
+
+    if cli.rpc {
+        return diamond_drill::rpc::run_rpc().await;
+    }
 
     // Handle grandma mode - simplified interactive workflow
     if cli.easy {
         return cli::easy_mode::run_easy_mode().await;
     }
 
+    // The TUI shows bad sectors itself; elsewhere they are logged as found
+    if !matches!(cli.command, Some(Commands::Tui(_))) {
+        log_bad_sectors();
+    }
+
+    if let Some(ref addr) = cli.metrics_addr {
+        serve_metrics(addr)?;
+    }
+
+    // Every run but reading the logs themselves leaves an operation log
+    let run_log = match cli.command {
+        Some(Commands::Logs(_)) => None,
+        _ if cli.no_log => None,
+        ref command => {
+            let name = command.as_ref().map_or("interactive", Commands::name);
+            diamond_drill::audit::RunLog::start(name)
+                .map_err(|e| tracing::warn!("Not writing an operation log: {:#}", e))
+                .ok()
+        }
+    };
+
+    let result = run_command(cli).await;
+    if let Some(run_log) = run_log {
+        run_log.finish(&result);
+    }
+    result
+}
+
+async fn run_command(cli: cli::Cli) -> Result<()> {
+    let ignore_health = cli.ignore_health;
     match cli.command {
-        Some(Commands::Index(args)) => {
+        Some(Commands::Index(mut args)) => {
             use colored::Colorize;
             use indicatif::{ProgressBar, ProgressStyle};
 
+            diamond_drill::run_safety_checks(&args.source, ignore_health)
+                .map_err(anyhow::Error::msg)?;
+            if args.workers.is_none() {
+                args.workers = auto_tuning(&args.source, None).map(|t| t.read_workers);
+            }
+
             println!(
                 "\n{} Indexing: {}",
                 "💎".bright_cyan(),
@@ -51,12 +104,12 @@ async fn main() -> Result<()> {
             let engine = DrillEngine::new(args.source.clone()).await?;
             engine.index_with_progress(&args).await?;
 
-            let file_count = engine.file_count().await;
+            let stats = engine.stats().await;
             let bad_sector_count = engine.bad_sector_count().await;
             pb.finish_with_message(format!(
                 "{} Indexed {} files{}",
                 "✓".bright_green(),
-                file_count,
+                stats.total_files,
                 if bad_sector_count > 0 {
                     format!(" ({} bad sectors detected)", bad_sector_count)
                 } else {
@@ -64,56 +117,235 @@ async fn main() -> Result<()> {
                 }
             ));
 
+            if let Some(changes) = engine.last_changes().await {
+                println!(
+                    "  {} {} added, {} modified, {} removed, {} unchanged",
+                    "↻".bright_cyan(),
+                    changes.added.to_string().bright_green(),
+                    changes.modified.to_string().bright_yellow(),
+                    changes.removed.to_string().bright_red(),
+                    changes.unchanged
+                );
+            }
+
+            if let Some(counts) = engine.last_hashset_counts().await {
+                println!(
+                    "  {} {} known-good files filtered, {} known-bad flagged",
+                    "#".bright_cyan(),
+                    counts.known_good,
+                    counts.known_bad.to_string().bright_red()
+                );
+            }
+
+            if stats.symlinks > 0 || stats.hardlinks_skipped > 0 {
+                println!(
+                    "  {} {} links ({} looping, {} leading outside), {} extra hard link names",
+                    "⤷".bright_cyan(),
+                    stats.symlinks,
+                    stats.link_loops,
+                    stats.links_outside_source,
+                    stats.hardlinks_skipped
+                );
+            }
+            if stats.archive_members > 0 {
+                println!(
+                    "  {} {} files inside archives",
+                    "📦".bright_cyan(),
+                    stats.archive_members
+                );
+            }
+            if stats.snapshot_files > 0 {
+                println!(
+                    "  {} {} files in shadow copies",
+                    "🕒".bright_cyan(),
+                    stats.snapshot_files
+                );
+            }
+
+            let encrypted: Vec<_> = engine
+                .get_all_entries()
+                .await
+                .into_iter()
+                .filter_map(|e| {
+                    let volume = e
+                        .metadata
+                        .get(diamond_drill::diskimage::unlock::ENCRYPTED_VOLUME_META_KEY)?
+                        .clone();
+                    Some((e.path, volume))
+                })
+                .collect();
+            for (path, volume) in &encrypted {
+                println!(
+                    "  {} {} contains {}; run `carve --unlock` on it to recover files",
+                    "🔒".bright_yellow(),
+                    path.display(),
+                    volume
+                );
+            }
+
             // Write bad sector report if requested
             if let Some(ref report_path) = args.bad_sector_report {
-                let bad_sectors = engine.get_bad_sectors().await;
-                let entries = engine.get_all_entries().await;
-                let report = diamond_drill::badsector::generate_report(
-                    &args.source,
-                    &[], // sector maps not available from index scan
-                    entries.len(),
+                use diamond_drill::badsector::{self, ParallelScanner, SectorReader};
+
+                let paths: Vec<_> = engine
+                    .get_all_entries()
+                    .await
+                    .into_iter()
+                    .map(|e| e.path)
+                    .collect();
+                let pb = ProgressBar::new(0);
+                pb.set_style(
+                    ProgressStyle::default_bar()
+                        .template(
+                            "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} {msg}",
+                        )
+                        .expect("valid progress bar template")
+                        .progress_chars("█▓▒░"),
                 );
-                // Build report from the BadSector vec in the engine
-                let bsr = diamond_drill::badsector::BadSectorReport {
-                    source: args.source.clone(),
-                    scan_time: chrono::Utc::now(),
-                    total_files_scanned: entries.len(),
-                    files_with_bad_sectors: bad_sectors.len(),
-                    total_bad_blocks: bad_sectors.len() as u64,
-                    total_bad_bytes: bad_sectors.iter().map(|b| b.length).sum(),
-                    files: Vec::new(), // full sector maps require SectorReader pass
-                };
+                let scanner = ParallelScanner::new(SectorReader::with_block_size(args.block_size));
+                let results = scanner.scan(&paths, |progress| {
+                    pb.set_length(progress.bytes_total);
+                    pb.set_position(progress.bytes_done);
+                    pb.set_message(format!(
+                        "{}/{} files, {} bad blocks",
+                        progress.files_done, progress.files_total, progress.bad_blocks
+                    ));
+                });
+                pb.finish_and_clear();
+
+                let mut maps = Vec::with_capacity(results.len());
+                for (path, result) in paths.iter().zip(results) {
+                    match result {
+                        Ok(map) => maps.push(map),
+                        Err(e) => tracing::warn!("Sector scan of {} failed: {}", path.display(), e),
+                    }
+                }
+                let report = badsector::generate_report(&args.source, &maps, maps.len());
                 let is_json = report_path
                     .extension()
                     .map(|e| e == "json")
                     .unwrap_or(false);
-                diamond_drill::badsector::write_report(&bsr, report_path, is_json)?;
+                badsector::write_report(&report, report_path, is_json)?;
                 println!(
                     "  {} Bad sector report: {}",
                     "📋".bright_cyan(),
                     report_path.display().to_string().bright_white()
                 );
-                let _ = report; // suppress unused
+            }
+
+            if let Some(format) = args.report_format {
+                let format = forensic_format(format);
+                let report_path = args.report_file.clone().unwrap_or_else(|| {
+                    std::path::PathBuf::from(format!("diamond-drill-index.{}", format.extension()))
+                });
+                let records: Vec<_> = engine
+                    .get_all_entries()
+                    .await
+                    .iter()
+                    .map(diamond_drill::report::ForensicRecord::from_index_entry)
+                    .collect();
+                diamond_drill::report::write_forensic_report(
+                    format,
+                    &records,
+                    &args.source,
+                    &report_path,
+                )?;
+                println!(
+                    "  {} {} report: {}",
+                    "📋".bright_cyan(),
+                    format.extension(),
+                    report_path.display().to_string().bright_white()
+                );
+            }
+
+            if let Some(ref db_path) = args.sqlite {
+                println!(
+                    "  {} SQLite index ({} files): {}",
+                    "🗄".bright_cyan(),
+                    stats.total_files,
+                    db_path.display().to_string().bright_white()
+                );
+            }
+        }
+        #[cfg(feature = "sqlite")]
+        Some(Commands::Search(args)) if diamond_drill::core::is_sqlite_index(&args.source) => {
+            if args.content {
+                let matches = diamond_drill::core::search_sqlite_content(&args)?;
+                print_content_matches(&matches, cli.output)?;
+                if let Some(ref selection) = args.select {
+                    add_to_selection(selection, &args.source, matches.iter().map(|m| &m.path))?;
+                }
+            } else {
+                match cli.output {
+                    Some(format @ (cli::OutputFormat::Json | cli::OutputFormat::Csv)) => {
+                        let entries = diamond_drill::core::search_sqlite_entries(&args)?;
+                        cli::output::print_entries(&entries, format)?;
+                    }
+                    _ => diamond_drill::core::search_sqlite_index(&args)?,
+                }
+                if let Some(ref selection) = args.select {
+                    let entries = diamond_drill::core::search_sqlite_entries(&args)?;
+                    add_to_selection(selection, &args.source, entries.iter().map(|e| &e.path))?;
+                }
+            }
+        }
+        Some(Commands::Search(args)) if args.content => {
+            let engine = DrillEngine::load_or_create(&args.source).await?;
+            let matches = engine.search_content(&args).await?;
+            print_content_matches(&matches, cli.output)?;
+            if let Some(ref selection) = args.select {
+                add_to_selection(selection, &args.source, matches.iter().map(|m| &m.path))?;
             }
         }
         Some(Commands::Search(args)) => {
             let engine = DrillEngine::load_or_create(&args.source).await?;
-            engine.search_interactive(&args).await?;
+            let entries = match cli.output {
+                Some(format @ (cli::OutputFormat::Json | cli::OutputFormat::Csv)) => {
+                    let entries = engine.search(&args).await?;
+                    cli::output::print_entries(&entries, format)?;
+                    entries
+                }
+                _ => engine.search_interactive(&args).await?,
+            };
+            if let Some(ref selection) = args.select {
+                add_to_selection(selection, &args.source, entries.iter().map(|e| &e.path))?;
+            }
         }
         Some(Commands::Preview(args)) => {
             let engine = DrillEngine::load_or_create(&args.source).await?;
             engine.preview_files(&args).await?;
         }
-        Some(Commands::Export(args)) => {
+        Some(Commands::Export(mut args)) => {
+            diamond_drill::run_safety_checks(&args.source, ignore_health)
+                .map_err(anyhow::Error::msg)?;
+            if args.workers.is_none() || args.block_size.is_none() {
+                if let Some(tuning) = auto_tuning(&args.source, Some(&args.dest)) {
+                    args.workers.get_or_insert(tuning.export_workers);
+                    if let Some(block_size) = tuning.block_size {
+                        args.block_size.get_or_insert(block_size.to_string());
+                    }
+                }
+            }
             let engine = DrillEngine::load_or_create(&args.source).await?;
             engine.export_selected(&args).await?;
         }
         Some(Commands::Carve(args)) => {
+            diamond_drill::run_safety_checks(&args.source, ignore_health)
+                .map_err(anyhow::Error::msg)?;
             run_carve(args).await?;
         }
         Some(Commands::Interactive(args)) => {
             cli::interactive::run_interactive_session(&args).await?;
         }
+        Some(Commands::Dedup(args))
+            if !args.sources.is_empty() || !args.baseline.is_empty() || args.missing.is_some() =>
+        {
+            diamond_drill::core::run_dedup_compare(&args).await?;
+        }
+        #[cfg(feature = "sqlite")]
+        Some(Commands::Dedup(args)) if diamond_drill::core::is_sqlite_index(&args.source) => {
+            diamond_drill::core::dedup_sqlite_index(&args)?;
+        }
         Some(Commands::Dedup(args)) => {
             let engine = DrillEngine::load_or_create(&args.source).await?;
             engine.run_dedup(&args).await?;
@@ -133,7 +365,37 @@ async fn main() -> Result<()> {
             );
             println!("Operator: {}\n", manifest.chain_of_custody.operator);
 
-            let result = proof::verify_manifest(&manifest)?;
+            let trusted = args
+                .pubkey
+                .as_deref()
+                .map(proof::load_verifying_key)
+                .transpose()?;
+
+            if let Some(file) = &args.single {
+                let result = proof::verify_single_file(&manifest, file, trusted.as_ref())?;
+                match args.report {
+                    cli::VerifyReportFormat::Human | cli::VerifyReportFormat::Pdf => {
+                        print!("{}", proof::format_single_file_result(&result));
+                    }
+                    cli::VerifyReportFormat::Json => {
+                        println!("{}", serde_json::to_string_pretty(&result)?);
+                    }
+                }
+                if args.record {
+                    let outcome = if result.is_clean() {
+                        "PASSED"
+                    } else {
+                        "FAILED"
+                    };
+                    record_verify_run(&args.manifest, &format!("{} {}", result.path, outcome))?;
+                }
+                if !result.is_clean() {
+                    std::process::exit(1);
+                }
+                return Ok(());
+            }
+
+            let result = proof::verify_manifest_with_key(&manifest, trusted.as_ref())?;
 
             match args.report {
                 cli::VerifyReportFormat::Human => {
@@ -142,6 +404,49 @@ async fn main() -> Result<()> {
                 cli::VerifyReportFormat::Json => {
                     println!("{}", serde_json::to_string_pretty(&result)?);
                 }
+                cli::VerifyReportFormat::Pdf => {
+                    use diamond_drill::report;
+
+                    print!("{}", proof::format_verify_result(&result));
+                    let data =
+                        report::report_data_from_proof(&args.manifest, &manifest, Some(&result));
+                    let path = args.report_path.clone().unwrap_or_else(|| {
+                        args.manifest
+                            .with_file_name("diamond-drill-verify-report.pdf")
+                    });
+                    let options = report::PdfOptions {
+                        logo: args.logo.clone(),
+                    };
+                    report::generate_pdf_report_with(&data, &path, &options)?;
+                    println!("\nPDF report written: {}", path.display());
+                }
+            }
+
+            if args.self_contained {
+                if result.is_clean() {
+                    let kit = proof::write_verifier_kit(&manifest, &args.manifest)?;
+                    println!("\nStandalone verifier written:");
+                    println!("  {}", kit.script.display());
+                    println!("  {}", kit.checksums.display());
+                    println!("  {}", kit.schema.display());
+                } else {
+                    eprintln!("\nNot writing standalone verifier: export did not verify cleanly");
+                }
+            }
+
+            if args.record {
+                let outcome = if result.is_clean() {
+                    "PASSED"
+                } else {
+                    "FAILED"
+                };
+                record_verify_run(
+                    &args.manifest,
+                    &format!(
+                        "{}: {}/{} verified, {} failed, {} missing",
+                        outcome, result.verified, result.total, result.failed, result.missing
+                    ),
+                )?;
             }
 
             if !result.is_clean() {
@@ -151,14 +456,44 @@ async fn main() -> Result<()> {
         Some(Commands::Swarm(args)) => {
             use diamond_drill::swarm;
 
+            let source = match (&args.source, &args.resume) {
+                (Some(source), _) => source.clone(),
+                (None, Some(resume)) => swarm::SwarmSession::read(resume)?.source,
+                (None, None) => unreachable!("clap requires a source without --resume"),
+            };
+
+            let session = match (&args.resume, &args.session_dir) {
+                (Some(resume), _) => swarm::SessionConfig::resume(resume.clone()),
+                (None, Some(dir)) => swarm::SessionConfig {
+                    dir: dir.clone(),
+                    ..Default::default()
+                },
+                (None, None) => swarm::SessionConfig::default(),
+            };
+
             println!("Diamond Drill Swarm Pipeline");
-            println!("Source: {}\n", args.source.display());
+            println!("Source: {}", source.display());
+            match args.resume {
+                Some(ref resume) => println!("Resuming: {}\n", resume.display()),
+                None => println!("Session saved in: {}\n", session.dir.display()),
+            }
 
-            let mut config = swarm::SwarmConfig::new(args.source.clone());
+            let mut config = swarm::SwarmConfig::new(source);
             config.heal.max_retries = args.max_retries;
             config.skip_hidden = args.skip_hidden;
             config.chunk_size = args.chunk_size;
             config.chunk_overlap = args.chunk_overlap;
+            config.channel_size = args.queue_depth;
+            config.embed.batch_size = args.embed_batch;
+            if let Some(workers) = args.scan_workers {
+                config.scan_workers = workers;
+            }
+            if let Some(workers) = args.chunk_workers {
+                config.chunk_workers = workers;
+            }
+            if let Some(workers) = args.embed_workers {
+                config.embed.workers = workers;
+            }
 
             if let Some(ref exts) = args.extensions {
                 config.extensions = Some(exts.clone());
@@ -172,11 +507,62 @@ async fn main() -> Result<()> {
             if let Some(ref log) = args.heal_log {
                 config.heal.log_path = Some(log.clone());
             }
+            if let Some(ref log) = args.retry_failed {
+                config = config.with_retry_failed(log)?;
+                let files = config.files.as_ref().map_or(0, Vec::len);
+                if files == 0 {
+                    println!("No failed files in {}", log.display());
+                    return Ok(());
+                }
+                println!("Retrying {} failed files from {}\n", files, log.display());
+            }
             if args.gpu_fallback {
                 config.heal.enable_gpu_fallback = true;
             }
+            if let Some(ref transcripts) = args.transcripts {
+                config.transcribe = Some(swarm::TranscribeConfig {
+                    model: args.whisper_model.clone(),
+                    language: Some(args.language.clone()),
+                    report: Some(transcripts.clone()),
+                });
+            }
+            if let Some(ref report) = args.sensitivity_report {
+                config.classify = Some(swarm::ClassifyConfig {
+                    report: Some(report.clone()),
+                    model: args.classify_model.clone(),
+                    ..Default::default()
+                });
+            }
+            config.session = Some(session);
+            if args.embed_cache || args.embed_cache_dir.is_some() {
+                let mut cache = swarm::EmbedCacheConfig {
+                    max_disk_bytes: args.embed_cache_size * 1024 * 1024,
+                    ..Default::default()
+                };
+                if let Some(ref dir) = args.embed_cache_dir {
+                    cache.dir = dir.clone();
+                }
+                config.embed_cache = Some(cache);
+            }
 
-            let result = swarm::run_swarm_with_config(config)?;
+            let orchestrator = swarm::SwarmOrchestrator::new(config);
+            let dashboard = (args.dashboard
+                && matches!(args.report, cli::SwarmReportFormat::Human))
+            .then(swarm::SwarmDashboard::new);
+            let orchestrator = match dashboard {
+                Some(ref dashboard) => orchestrator
+                    .on_progress(swarm::DEFAULT_PROGRESS_INTERVAL, dashboard.callback()),
+                None => orchestrator,
+            };
+
+            let result = orchestrator.run();
+            if let Some(ref dashboard) = dashboard {
+                match result {
+                    Ok(ref summary) => dashboard.finish(summary),
+                    Err(ref e) => dashboard.fail(e),
+                }
+            }
+            let result = result?;
 
             match args.report {
                 cli::SwarmReportFormat::Human => {
@@ -196,6 +582,36 @@ async fn main() -> Result<()> {
                             result.errors_encountered, result.errors_healed
                         );
                     }
+                    if result.files_skipped > 0 {
+                        println!(
+                            "  {} files already done in the resumed session",
+                            result.files_skipped
+                        );
+                    }
+                    if result.embeddings_cached > 0 {
+                        println!(
+                            "  {} embeddings reused from the cache",
+                            result.embeddings_cached
+                        );
+                    }
+                    if let Some(ref session) = result.session {
+                        println!("  Session: {}", session.display());
+                    }
+                    if let Some(ref transcripts) = args.transcripts {
+                        println!(
+                            "  {} recordings transcribed, transcripts: {}",
+                            result.files_transcribed,
+                            transcripts.display()
+                        );
+                    }
+                    if let Some(ref report) = args.sensitivity_report {
+                        println!(
+                            "  {} sensitive files ({} chunks flagged), report: {}",
+                            result.sensitive_files,
+                            result.chunks_flagged,
+                            report.display()
+                        );
+                    }
                 }
                 cli::SwarmReportFormat::Json => {
                     println!("{}", serde_json::to_string_pretty(&result)?);
@@ -203,8 +619,55 @@ async fn main() -> Result<()> {
             }
         }
         Some(Commands::Report(args)) => {
-            run_report(args)?;
+            run_report(args).await?;
+        }
+        Some(Commands::Chats(args)) => {
+            run_chats(args, cli.output).await?;
+        }
+        Some(Commands::Convert(args)) => {
+            run_convert(args, cli.output)?;
+        }
+        Some(Commands::Analyze(args)) => {
+            run_analyze(args, cli.output)?;
+        }
+        Some(Commands::Image(args)) => {
+            run_image(args, cli.output)?;
+        }
+        Some(Commands::Ask(args)) => {
+            run_ask(args, cli.output)?;
+        }
+        Some(Commands::HealReport(args)) => {
+            run_heal_report(args, cli.output)?;
+        }
+        Some(Commands::Health(args)) => {
+            run_health(args, cli.output)?;
+        }
+        Some(Commands::Bench(args)) => {
+            run_bench(args, cli.output)?;
         }
+        Some(Commands::Artifacts(args)) => {
+            run_artifacts(args, cli.output).await?;
+        }
+        Some(Commands::Stats(args)) => {
+            run_stats(args, cli.output).await?;
+        }
+        Some(Commands::Watch(args)) => {
+            run_watch(args, cli.output).await?;
+        }
+        Some(Commands::Proof(args)) => match args.action {
+            cli::ProofAction::Keygen(keygen) => run_proof_keygen(keygen)?,
+            cli::ProofAction::Sign(sign) => run_proof_sign(sign)?,
+            cli::ProofAction::Prove(prove) => run_proof_prove(prove)?,
+            cli::ProofAction::Timestamp(timestamp) => run_proof_timestamp(timestamp)?,
+            cli::ProofAction::Log(log) => run_proof_log(log)?,
+        },
+        Some(Commands::Maintenance(args)) => match args.action {
+            cli::MaintenanceAction::Gc(gc) => run_gc(gc, cli.output)?,
+        },
+        Some(Commands::Logs(args)) => match args.action {
+            cli::LogsAction::List(list) => run_logs_list(list, cli.output)?,
+            cli::LogsAction::Show(show) => run_logs_show(show, cli.output)?,
+        },
         Some(Commands::Tui(args)) => {
             diamond_drill::tui::run_tui(args).await?;
         }
@@ -212,6 +675,10 @@ async fn main() -> Result<()> {
         Some(Commands::Gui(args)) => {
             gui::run_gui(args)?;
         }
+        #[cfg(feature = "serve")]
+        Some(Commands::Serve(args)) => {
+            diamond_drill::serve::run_serve(args).await?;
+        }
         None => {
             // Default: run interactive mode
             cli::interactive::run_interactive_session(&cli::InteractiveArgs::default()).await?;
@@ -221,12 +688,120 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+/// Warn about each unreadable region as soon as any command runs into it
+fn log_bad_sectors() {
+    use diamond_drill::events::{self, Event};
+    use tokio::sync::broadcast::error::RecvError;
+
+    let mut events = events::subscribe();
+    tokio::spawn(async move {
+        loop {
+            match events.recv().await {
+                Ok(Event::BadSector(bad)) => tracing::warn!(
+                    "Unreadable: {} at offset {} ({} bytes): {}",
+                    bad.file_path.display(),
+                    bad.offset,
+                    bad.length,
+                    bad.error
+                ),
+                Ok(_) => {}
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+/// Serve Prometheus metrics for the rest of the run
+#[cfg(feature = "metrics")]
+fn serve_metrics(addr: &str) -> Result<()> {
+    let bound = diamond_drill::metrics::serve(addr)?;
+    tracing::info!("Serving metrics on http://{}/metrics", bound);
+    Ok(())
+}
+
+#[cfg(not(feature = "metrics"))]
+fn serve_metrics(_addr: &str) -> Result<()> {
+    anyhow::bail!("Metrics exporter not compiled in; rebuild with --features metrics")
+}
+
+/// Settings for options left unset: what `bench` measured for `source`
+/// (and `dest`), or a guess from the storage holding them
+fn auto_tuning(
+    source: &std::path::Path,
+    dest: Option<&std::path::Path>,
+) -> Option<diamond_drill::bench::Tuning> {
+    use diamond_drill::bench::{self, Calibrations, TuningOrigin};
+
+    let config = diamond_drill::Config::load();
+    let calibrations = Calibrations::load(&Calibrations::default_path()).unwrap_or_else(|e| {
+        tracing::warn!("Ignoring benchmark calibrations: {:#}", e);
+        Calibrations::default()
+    });
+    let (tuning, origin) = bench::auto_tune(source, dest, &config.tuning, &calibrations)?;
+    let origin = match origin {
+        TuningOrigin::Bench => "benchmark".to_string(),
+        TuningOrigin::Storage {
+            source,
+            dest: Some(dest),
+        } if dest != source => format!("{} → {}", source.label(), dest.label()),
+        TuningOrigin::Storage { source, .. } => source.label().to_string(),
+    };
+    tracing::info!(
+        "Tuned for {}: {} read workers, {} export workers (override with --workers)",
+        origin,
+        tuning.read_workers,
+        tuning.export_workers
+    );
+    Some(tuning)
+}
+
+/// Unlock secret for `carve --unlock`: read from the key file or prompted
+/// for, so it never appears on the command line or in shell history
+fn read_unlock_key(
+    args: &cli::CarveArgs,
+) -> Result<Option<diamond_drill::diskimage::unlock::UnlockKey>> {
+    use diamond_drill::diskimage::unlock::UnlockKey;
+
+    let secret = if let Some(ref path) = args.unlock_key_file {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read unlock key file: {}", path.display()))?;
+        text.lines().next().unwrap_or_default().trim().to_string()
+    } else if args.unlock {
+        dialoguer::Password::new()
+            .with_prompt("Passphrase or recovery password (empty for clear key)")
+            .allow_empty_password(true)
+            .interact()
+            .context("Failed to read the unlock passphrase")?
+    } else {
+        return Ok(None);
+    };
+    Ok(Some(if secret.is_empty() {
+        UnlockKey::ClearKey
+    } else {
+        UnlockKey::Secret(secret)
+    }))
+}
+
 async fn run_carve(args: cli::CarveArgs) -> Result<()> {
     use colored::Colorize;
     use diamond_drill::carve::{CarveOptions, CarveProgress, Carver};
+    use diamond_drill::iobackend::IoOptions;
     use indicatif::{ProgressBar, ProgressStyle};
 
     let min_size = parse_size_str(&args.min_size).unwrap_or(512);
+    let max_bytes_per_sec = args
+        .max_rate
+        .as_deref()
+        .map(|rate| {
+            parse_size_str(rate).ok_or_else(|| anyhow::anyhow!("Invalid --max-rate: {}", rate))
+        })
+        .transpose()?;
+    let unlock = read_unlock_key(&args)?;
+    let extra_signatures = match &args.signatures {
+        Some(path) => diamond_drill::carve::custom::load_signatures(path)?,
+        None => Vec::new(),
+    };
 
     let file_types = args.file_type.map(|filters| {
         filters
@@ -245,15 +820,34 @@ async fn run_carve(args: cli::CarveArgs) -> Result<()> {
 
     let image_size = std::fs::metadata(&args.source).map(|m| m.len()).unwrap_or(0);
 
+    let tuning = if args.workers.is_none() || args.io_backend.is_some() {
+        auto_tuning(&args.source, None)
+    } else {
+        None
+    };
     let opts = CarveOptions {
         source: args.source.clone(),
         output_dir: args.output.clone(),
         sector_aligned: args.sector_aligned,
         min_size,
         file_types,
-        workers: args.workers.unwrap_or_else(num_cpus::get),
+        workers: args
+            .workers
+            .or(tuning.map(|t| t.read_workers))
+            .unwrap_or_else(num_cpus::get),
         dry_run: args.dry_run,
         verify: !args.no_verify,
+        mapfile: args.mapfile.clone(),
+        max_bytes_per_sec,
+        unlock,
+        extra_signatures,
+        faststart: args.faststart,
+        text_min_len: args.text.then_some(args.text_min_len),
+        min_confidence: args.min_confidence,
+        io: args.io_backend.map(|backend| IoOptions {
+            block_size: tuning.and_then(|t| t.block_size),
+            ..backend.options(args.queue_depth)
+        }),
     };
 
     let json_output = matches!(args.output_format, Some(cli::OutputFormat::Json));
@@ -325,7 +919,37 @@ async fn run_carve(args: cli::CarveArgs) -> Result<()> {
         })
         .await?;
 
+    let report_path = args.report_format.map(|format| {
+        let format = forensic_format(format);
+        let path = args.report_file.clone().unwrap_or_else(|| {
+            args.output
+                .join(format!("diamond-drill-carve.{}", format.extension()))
+        });
+        (format, path)
+    });
+    if let Some((format, path)) = &report_path {
+        let records: Vec<_> = carved
+            .iter()
+            .map(diamond_drill::report::ForensicRecord::from_carved)
+            .collect();
+        if args.dry_run {
+            std::fs::create_dir_all(&args.output).ok();
+        }
+        diamond_drill::report::write_forensic_report(*format, &records, &args.source, path)?;
+    }
+
+    // Lets `tui --carve-results` browse and re-extract these files later
+    let listing_path = diamond_drill::carve::CarveListing {
+        source: args.source.clone(),
+        result: result.clone(),
+        files: carved.clone(),
+    }
+    .save(&args.output)?;
+
     if json_output {
+        // Most trustworthy first
+        let mut ranked: Vec<_> = carved.iter().collect();
+        ranked.sort_by_key(|f| std::cmp::Reverse(f.confidence));
         let output = serde_json::json!({
             "files_found": result.files_found,
             "files_extracted": result.files_extracted,
@@ -335,7 +959,15 @@ async fn run_carve(args: cli::CarveArgs) -> Result<()> {
             "image_size": result.image_size,
             "duration_ms": result.duration_ms,
             "by_type": result.by_type,
-            "files": carved,
+            "bytes_skipped": result.bytes_skipped,
+            "files_in_bad_regions": result.files_in_bad_regions,
+            "files_repaired": result.files_repaired,
+            "files_faststarted": result.files_faststarted,
+            "files_below_confidence": result.files_below_confidence,
+            "encrypted_volume": result.encrypted_volume,
+            "unlocked": result.unlocked,
+            "results_file": listing_path,
+            "files": ranked,
         });
         println!("{}", serde_json::to_string_pretty(&output)?);
         return Ok(());
@@ -354,6 +986,76 @@ async fn run_carve(args: cli::CarveArgs) -> Result<()> {
     if result.files_failed > 0 {
         println!("  {} {} failed", "⚠".yellow(), result.files_failed);
     }
+    if result.files_below_confidence > 0 {
+        println!(
+            "  {} {} skipped below confidence {}",
+            "·".dimmed(),
+            result.files_below_confidence,
+            args.min_confidence
+        );
+    }
+    if result.bytes_skipped > 0 {
+        println!(
+            "  {} Skipped {} of unreadable regions (mapfile)",
+            "⚠".yellow(),
+            humansize::format_size(result.bytes_skipped, humansize::BINARY)
+        );
+    }
+    if result.files_in_bad_regions > 0 {
+        println!(
+            "  {} {} files overlap unreadable regions and may be corrupt",
+            "⚠".yellow(),
+            result.files_in_bad_regions
+        );
+    }
+    let damaged_databases = carved
+        .iter()
+        .filter(|f| f.page_check.as_ref().is_some_and(|c| !c.is_intact()))
+        .count();
+    if damaged_databases > 0 {
+        println!(
+            "  {} {} SQLite databases have pages that failed validation (likely fragmented)",
+            "⚠".yellow(),
+            damaged_databases
+        );
+    }
+    if result.files_repaired > 0 {
+        println!(
+            "  {} {} truncated Office documents rebuilt",
+            "✓".bright_green(),
+            result.files_repaired
+        );
+    }
+    if result.files_faststarted > 0 {
+        println!(
+            "  {} {} videos rewritten with moov in front (faststart)",
+            "✓".bright_green(),
+            result.files_faststarted
+        );
+    }
+    let corrupt_documents = carved
+        .iter()
+        .filter(|f| f.integrity == Some(diamond_drill::carve::Integrity::Corrupt))
+        .count();
+    if corrupt_documents > 0 {
+        println!(
+            "  {} {} Office documents are damaged beyond repair",
+            "⚠".yellow(),
+            corrupt_documents
+        );
+    }
+    match (&result.encrypted_volume, result.unlocked) {
+        (Some(volume), true) => println!(
+            "  🔓 Carved the decrypted {}; offsets are relative to the volume",
+            volume
+        ),
+        (Some(volume), false) => println!(
+            "  {} Image contains {}; carve with --unlock to recover its contents",
+            "⚠".yellow(),
+            volume
+        ),
+        _ => {}
+    }
     println!(
         "  {} Total extracted: {}",
         "📊",
@@ -376,29 +1078,119 @@ async fn run_carve(args: cli::CarveArgs) -> Result<()> {
             println!("    {} .{}: {}", "•".bright_cyan(), ext, count);
         }
     }
+    if let Some((format, path)) = &report_path {
+        println!("  📋 {} report: {}", format.extension(), path.display());
+    }
+    println!("  📋 Results: {}", listing_path.display());
     println!("{}", "═".repeat(60).bright_cyan());
     Ok(())
 }
 
-fn run_report(args: cli::ReportArgs) -> Result<()> {
+fn forensic_format(format: cli::ForensicReportFormat) -> diamond_drill::report::ForensicFormat {
+    match format {
+        cli::ForensicReportFormat::Dfxml => diamond_drill::report::ForensicFormat::Dfxml,
+        cli::ForensicReportFormat::Bodyfile => diamond_drill::report::ForensicFormat::Bodyfile,
+    }
+}
+
+fn print_content_matches(
+    matches: &[diamond_drill::core::ContentMatch],
+    output: Option<cli::OutputFormat>,
+) -> Result<()> {
+    let format = output.unwrap_or(cli::OutputFormat::Human);
+    cli::output::print_matches(matches, format)?;
+    if matches!(format, cli::OutputFormat::Human) {
+        let files: std::collections::HashSet<_> = matches.iter().map(|m| &m.path).collect();
+        println!("\nFound {} matches in {} files", matches.len(), files.len());
+    }
+    Ok(())
+}
+
+/// Add search results to a `.ddsel` selection file (`search --select`)
+fn add_to_selection<'a>(
+    selection_path: &std::path::Path,
+    source: &std::path::Path,
+    files: impl Iterator<Item = &'a std::path::PathBuf>,
+) -> Result<()> {
+    let mut selection = diamond_drill::core::Selection::load_or_new(selection_path, Some(source))?;
+    let added = selection.extend(files.map(|p| p.to_string_lossy().to_string()));
+    selection.save(selection_path)?;
+    // stderr keeps --output json/csv clean
+    eprintln!(
+        "Added {} files to {} ({} selected)",
+        added,
+        selection_path.display(),
+        selection.len()
+    );
+    Ok(())
+}
+
+async fn run_report(args: cli::ReportArgs) -> Result<()> {
     use colored::Colorize;
+    use diamond_drill::badsector::imaging::mapfile_path_for;
     use diamond_drill::report;
 
     println!(
         "\n{} Generating recovery report from: {}",
         "💎".bright_cyan(),
-        args.manifest.display().to_string().bright_white()
+        args.input.display().to_string().bright_white()
     );
 
-    let mut data = report::report_data_from_manifest(&args.manifest)?;
+    let (mut data, base_path) = match report::report_data_for_run(&args.input)? {
+        Some(data) if args.input.is_dir() => (data, args.input.clone()),
+        Some(data) => {
+            let dir = args.input.parent().unwrap_or(std::path::Path::new("."));
+            (data, dir.to_path_buf())
+        }
+        None => {
+            // An indexed source or image; the report doesn't go into evidence
+            let engine = DrillEngine::load_or_create(&args.input).await?;
+            let entries = engine.get_all_entries().await;
+            if entries.is_empty() {
+                anyhow::bail!(
+                    "{} is not an export or carve output and has no index; \
+                     run `diamond-drill index {}` first",
+                    args.input.display(),
+                    args.input.display()
+                );
+            }
+            let data = report::report_data_from_index(&args.input, &entries);
+            (data, std::path::PathBuf::from("."))
+        }
+    };
 
     if let Some(title) = args.title {
         data.title = title;
     }
 
-    let base_path = args.manifest.parent().unwrap_or(std::path::Path::new("."));
+    let source = std::path::PathBuf::from(&data.source_path);
+    let image_size = std::fs::metadata(&source)
+        .ok()
+        .filter(|m| m.is_file())
+        .map(|m| m.len());
+    let bad_sectors = args
+        .bad_sectors
+        .clone()
+        .or_else(|| Some(mapfile_path_for(&source)).filter(|p| p.is_file()));
+    if let Some(path) = bad_sectors {
+        data.heatmaps = report::load_bad_sectors(&path, image_size)?;
+    }
+
+    // The PDF has no gallery
+    if !matches!(args.format, cli::ReportFormat::Pdf) {
+        if !data.thumbnails.is_empty() && args.thumbnails > 0 {
+            println!(
+                "  Embedding thumbnails of up to {} recovered images...",
+                args.thumbnails.min(data.thumbnails.len())
+            );
+        }
+        report::embed_thumbnails(&mut data, args.thumbnails);
+    }
 
     let open_browser = args.open;
+    let pdf_options = report::PdfOptions {
+        logo: args.logo.clone(),
+    };
 
     match args.format {
         cli::ReportFormat::Html => {
@@ -416,7 +1208,7 @@ fn run_report(args: cli::ReportArgs) -> Result<()> {
             let output = args
                 .output
                 .unwrap_or_else(|| base_path.join("diamond-drill-report.pdf"));
-            report::generate_pdf_report(&data, &output)?;
+            report::generate_pdf_report_with(&data, &output, &pdf_options)?;
             println!(
                 "  {} PDF report saved to: {}",
                 "✓".bright_green().bold(),
@@ -427,7 +1219,7 @@ fn run_report(args: cli::ReportArgs) -> Result<()> {
             let html_path = base_path.join("diamond-drill-report.html");
             let pdf_path = base_path.join("diamond-drill-report.pdf");
             report::save_html_report(&data, &html_path, open_browser)?;
-            report::generate_pdf_report(&data, &pdf_path)?;
+            report::generate_pdf_report_with(&data, &pdf_path, &pdf_options)?;
             println!(
                 "  {} HTML report: {}",
                 "✓".bright_green().bold(),
@@ -444,6 +1236,1455 @@ fn run_report(args: cli::ReportArgs) -> Result<()> {
     Ok(())
 }
 
+async fn run_chats(args: cli::ChatsArgs, output: Option<cli::OutputFormat>) -> Result<()> {
+    use colored::Colorize;
+    use diamond_drill::chat::{self, ChatApp};
+
+    let mut scan = chat::scan(&args.source)?;
+
+    let filters: Vec<String> = args.conversation.iter().map(|c| c.to_lowercase()).collect();
+    scan.conversations.retain(|c| {
+        let app_ok = match args.app {
+            Some(cli::ChatAppFilter::Whatsapp) => c.app == ChatApp::WhatsApp,
+            Some(cli::ChatAppFilter::Telegram) => c.app == ChatApp::Telegram,
+            None => true,
+        };
+        let name = c.name.to_lowercase();
+        app_ok && (filters.is_empty() || filters.iter().any(|f| name.contains(f)))
+    });
+
+    if matches!(output, Some(cli::OutputFormat::Json)) && args.export.is_none() {
+        println!("{}", serde_json::to_string_pretty(&scan)?);
+        return Ok(());
+    }
+
+    println!(
+        "\n{} Chat conversations in: {}",
+        "💎".bright_cyan(),
+        args.source.display().to_string().bright_white()
+    );
+    if scan.conversations.is_empty() {
+        println!("  No WhatsApp or Telegram media found");
+    }
+    for conversation in &scan.conversations {
+        let dates = conversation
+            .date_range()
+            .map(|(first, last)| format!("{} → {}", first, last))
+            .unwrap_or_default();
+        println!(
+            "  {} {:<9} {} ({} files, {}) {}",
+            "•".bright_cyan(),
+            conversation.app.to_string(),
+            conversation.name.bright_white(),
+            conversation.media.len(),
+            humansize::format_size(conversation.total_bytes(), humansize::BINARY),
+            dates.dimmed()
+        );
+        if !conversation.missing.is_empty() {
+            println!(
+                "      {} {} referenced attachments not found",
+                "⚠".bright_yellow(),
+                conversation.missing.len()
+            );
+        }
+    }
+    for path in &scan.encrypted_databases {
+        println!(
+            "  {} Encrypted backup (needs key, skipped): {}",
+            "🔒".bright_yellow(),
+            path.display()
+        );
+    }
+    for warning in &scan.warnings {
+        println!("  {} {}", "⚠".bright_yellow(), warning);
+    }
+
+    if let Some(dest) = args.export {
+        let exports =
+            chat::export_conversations(&scan.conversations, &dest, !args.no_verify).await?;
+        for export in &exports {
+            println!(
+                "  {} {} – {}: {} exported{}",
+                "✓".bright_green().bold(),
+                export.app,
+                export.name,
+                export.exported,
+                if export.failed > 0 {
+                    format!(", {} failed", export.failed)
+                        .bright_red()
+                        .to_string()
+                } else {
+                    String::new()
+                }
+            );
+        }
+        if !exports.is_empty() {
+            println!(
+                "  {} Review page: {}",
+                "✓".bright_green().bold(),
+                dest.join(chat::INDEX_FILE)
+                    .display()
+                    .to_string()
+                    .bright_white()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn run_convert(args: cli::ConvertArgs, output: Option<cli::OutputFormat>) -> Result<()> {
+    use colored::Colorize;
+    use diamond_drill::diskimage::{self, ConvertOptions, EwfMetadata, ImageFormat};
+    use indicatif::{ProgressBar, ProgressStyle};
+
+    let json_output = matches!(output, Some(cli::OutputFormat::Json));
+
+    if args.list_partitions {
+        let (mut reader, format, size) = diskimage::open_image(&args.input)?;
+        let partitions = diskimage::read_partitions(&mut reader)?;
+        if json_output {
+            println!("{}", serde_json::to_string_pretty(&partitions)?);
+            return Ok(());
+        }
+        println!(
+            "\n{} {} ({}, {})",
+            "💎".bright_cyan(),
+            args.input.display().to_string().bright_white(),
+            format,
+            humansize::format_size(size, humansize::BINARY)
+        );
+        if partitions.is_empty() {
+            println!("  No MBR or GPT partition table found");
+        }
+        for p in &partitions {
+            println!(
+                "  {:>3}  offset {:>14}  {:>10}  {}{}",
+                p.number,
+                p.start,
+                humansize::format_size(p.size, humansize::BINARY),
+                p.kind,
+                if p.name.is_empty() {
+                    String::new()
+                } else {
+                    format!("  {}", p.name)
+                }
+            );
+        }
+        return Ok(());
+    }
+
+    if args.list_snapshots {
+        let (mut reader, _, _) = diskimage::open_image(&args.input)?;
+        let offset = match args.partition {
+            Some(number) => diskimage::read_partitions(&mut reader)?
+                .into_iter()
+                .find(|p| p.number == number)
+                .map(|p| p.start)
+                .ok_or_else(|| anyhow::anyhow!("Partition {} not found", number))?,
+            None => 0,
+        };
+        let copies = diskimage::list_shadow_copies(&mut reader, offset)?;
+        if json_output {
+            println!("{}", serde_json::to_string_pretty(&copies)?);
+            return Ok(());
+        }
+        println!(
+            "\n{} Shadow copies in {}",
+            "💎".bright_cyan(),
+            args.input.display().to_string().bright_white()
+        );
+        if copies.is_empty() {
+            println!("  No Volume Shadow Copies found");
+        }
+        for c in &copies {
+            println!(
+                "  {:>3}  {}  {:>10}  {}",
+                c.number,
+                c.created
+                    .map(|t| t.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+                    .unwrap_or_else(|| "unknown time".to_string()),
+                humansize::format_size(c.volume_size, humansize::BINARY),
+                c.store_id
+            );
+        }
+        return Ok(());
+    }
+
+    let Some(out_path) = args.output.clone() else {
+        anyhow::bail!("An output path is required");
+    };
+    let format = match args.format {
+        Some(cli::ImageFormatArg::Raw) => ImageFormat::Raw,
+        Some(cli::ImageFormatArg::Split) => ImageFormat::Split,
+        Some(cli::ImageFormatArg::E01) => ImageFormat::E01,
+        None => ImageFormat::from_output_path(&out_path),
+    };
+    let segment_size = parse_size_str(&args.segment_size)
+        .filter(|&s| s > 0)
+        .ok_or_else(|| anyhow::anyhow!("Invalid segment size: {}", args.segment_size))?;
+
+    let opts = ConvertOptions {
+        input: args.input.clone(),
+        output: out_path.clone(),
+        format,
+        segment_size,
+        partition: args.partition,
+        snapshot: args.snapshot,
+        verify: args.verify,
+        metadata: EwfMetadata {
+            case_number: args.case_number.unwrap_or_default(),
+            description: args.description.unwrap_or_default(),
+            examiner: args.examiner.unwrap_or_default(),
+            ..Default::default()
+        },
+    };
+
+    let pb = if !json_output {
+        println!(
+            "\n{} Converting {} → {} ({})",
+            "💎".bright_cyan(),
+            args.input.display().to_string().bright_white(),
+            out_path.display().to_string().bright_white(),
+            format
+        );
+        let pb = ProgressBar::new(0);
+        pb.set_style(
+            ProgressStyle::default_bar()
+                .template(
+                    "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})",
+                )
+                .expect("valid progress bar template")
+                .progress_chars("█▓▒░"),
+        );
+        Some(pb)
+    } else {
+        None
+    };
+
+    let manifest = diskimage::convert(&opts, |done, total| {
+        if let Some(ref pb) = pb {
+            pb.set_length(total);
+            pb.set_position(done);
+        }
+    });
+    if let Some(ref pb) = pb {
+        pb.finish_and_clear();
+    }
+    let manifest = manifest?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&manifest)?);
+        return Ok(());
+    }
+
+    if let Some(ref p) = manifest.partition {
+        println!(
+            "  {} Partition {} ({}) at offset {}",
+            "•".bright_cyan(),
+            p.number,
+            p.kind,
+            p.start
+        );
+    }
+    println!(
+        "  {} {} copied in {:.1}s",
+        "✓".bright_green().bold(),
+        humansize::format_size(manifest.bytes, humansize::BINARY),
+        manifest.duration_ms as f64 / 1000.0
+    );
+    println!("  BLAKE3: {}", manifest.blake3);
+    println!("  MD5:    {}", manifest.md5);
+    if let Some(ref stored) = manifest.source_stored_md5 {
+        if manifest.partition.is_none() && *stored != manifest.md5 {
+            println!(
+                "  {} MD5 differs from the one stored in the source E01 ({})",
+                "⚠".yellow(),
+                stored
+            );
+        }
+    }
+    for segment in &manifest.segments {
+        println!(
+            "  {} {} ({})",
+            "•".bright_cyan(),
+            segment.path.display(),
+            humansize::format_size(segment.size, humansize::BINARY)
+        );
+    }
+    match manifest.verified {
+        Some(true) => println!("  {} Output verified", "✓".bright_green().bold()),
+        Some(false) => println!(
+            "  {} Output does not match the source",
+            "✗".bright_red().bold()
+        ),
+        None => {}
+    }
+    println!(
+        "  {} Manifest: {}",
+        "✓".bright_green().bold(),
+        diskimage::ConvertManifest::path_for(&out_path)
+            .display()
+            .to_string()
+            .bright_white()
+    );
+    if manifest.verified == Some(false) {
+        anyhow::bail!("Verification failed");
+    }
+
+    Ok(())
+}
+
+fn run_analyze(args: cli::AnalyzeArgs, output: Option<cli::OutputFormat>) -> Result<()> {
+    use colored::Colorize;
+    use diamond_drill::analyze::{self, EntropyOptions, RegionClass};
+    use indicatif::{ProgressBar, ProgressStyle};
+
+    /// Regions listed in the human summary
+    const MAX_REGIONS: usize = 20;
+
+    let json_output = matches!(output, Some(cli::OutputFormat::Json));
+    let block_size = parse_size_str(&args.block_size)
+        .filter(|&s| s > 0)
+        .ok_or_else(|| anyhow::anyhow!("Invalid block size: {}", args.block_size))?;
+    let max_bytes_per_sec = args
+        .max_rate
+        .as_deref()
+        .map(|rate| {
+            parse_size_str(rate).ok_or_else(|| anyhow::anyhow!("Invalid --max-rate: {}", rate))
+        })
+        .transpose()?;
+    let opts = EntropyOptions {
+        block_size: block_size as usize,
+        max_blocks: args.sample,
+        max_bytes_per_sec,
+    };
+
+    let pb = if !json_output {
+        println!(
+            "\n{} Analyzing entropy of {}",
+            "💎".bright_cyan(),
+            args.source.display().to_string().bright_white()
+        );
+        let pb = ProgressBar::new(0);
+        pb.set_style(
+            ProgressStyle::default_bar()
+                .template(
+                    "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} blocks ({eta})",
+                )
+                .expect("valid progress bar template")
+                .progress_chars("█▓▒░"),
+        );
+        Some(pb)
+    } else {
+        None
+    };
+
+    let report = analyze::analyze_image(&args.source, &opts, |done, total| {
+        if let Some(ref pb) = pb {
+            pb.set_length(total);
+            pb.set_position(done);
+        }
+    });
+    if let Some(ref pb) = pb {
+        pb.finish_and_clear();
+    }
+    let report = report?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    let colorize = |class: RegionClass, s: String| match class {
+        RegionClass::Blank => s.dimmed(),
+        RegionClass::Data => s.green(),
+        RegionClass::Compressed => s.yellow(),
+        RegionClass::Encrypted => s.red(),
+    };
+
+    println!(
+        "  {} ({}), {} blocks of {}{}",
+        humansize::format_size(report.image_size, humansize::BINARY),
+        report.format,
+        report.blocks.len(),
+        humansize::format_size(report.block_size as u64, humansize::BINARY),
+        if report.sampled { ", sampled" } else { "" }
+    );
+    let strip: String = report
+        .heat_strip(args.width)
+        .iter()
+        .map(|cell| colorize(cell.class, cell.class.glyph().to_string()).to_string())
+        .collect();
+    println!("  [{}]", strip);
+    for class in RegionClass::ALL {
+        let bytes = report.class_bytes(class);
+        let percent = if report.image_size == 0 {
+            0.0
+        } else {
+            bytes as f64 * 100.0 / report.image_size as f64
+        };
+        println!(
+            "  {} {:<10} {:>10}  {:>5.1}%",
+            colorize(class, class.glyph().to_string().repeat(2)),
+            class.label(),
+            humansize::format_size(bytes, humansize::BINARY),
+            percent
+        );
+    }
+
+    println!("\n  Regions:");
+    for region in report.regions.iter().take(MAX_REGIONS) {
+        println!(
+            "  {:>14} – {:<14} {:>10}  {}  {:.3} bits/byte",
+            region.start,
+            region.end,
+            humansize::format_size(region.len(), humansize::BINARY),
+            colorize(region.class, format!("{:<10}", region.class.label())),
+            region.mean_entropy
+        );
+    }
+    if report.regions.len() > MAX_REGIONS {
+        println!(
+            "  … {} more (use --output json for the full list)",
+            report.regions.len() - MAX_REGIONS
+        );
+    }
+
+    if !report.headers.is_empty() {
+        println!("\n  Encrypted volume headers:");
+        for header in &report.headers {
+            println!(
+                "  {} offset {:>14}  {}  {}",
+                "🔒".red(),
+                header.offset,
+                header.kind.label().bright_white(),
+                header.detail
+            );
+        }
+    }
+    if report.has_encrypted() {
+        println!(
+            "\n  {} Encrypted regions found: carving cannot find files there without the key",
+            "⚠".yellow()
+        );
+    }
+
+    Ok(())
+}
+
+fn run_image(args: cli::ImageArgs, output: Option<cli::OutputFormat>) -> Result<()> {
+    use colored::Colorize;
+    use diamond_drill::badsector::imaging::{self, ImagingOptions};
+    use indicatif::{ProgressBar, ProgressStyle};
+
+    /// Bad regions listed in the human summary
+    const MAX_REGIONS: usize = 20;
+
+    let json_output = matches!(output, Some(cli::OutputFormat::Json));
+    let size_arg = |value: &str, name: &str| {
+        parse_size_str(value)
+            .filter(|&s| s > 0)
+            .map(|s| s as usize)
+            .ok_or_else(|| anyhow::anyhow!("Invalid {}: {}", name, value))
+    };
+    let opts = ImagingOptions {
+        source: args.device.clone(),
+        output: args.image.clone(),
+        block_size: size_arg(&args.block_size, "block size")?,
+        sector_size: size_arg(&args.sector_size, "sector size")?,
+        retry_passes: args.retries,
+        reverse: args.reverse,
+        mapfile: args.mapfile.clone(),
+        io: args.io_backend.options(args.queue_depth),
+    };
+
+    let pb = if !json_output {
+        println!(
+            "\n{} Imaging {} → {}",
+            "💎".bright_cyan(),
+            args.device.display().to_string().bright_white(),
+            args.image.display().to_string().bright_white()
+        );
+        let pb = ProgressBar::new(0);
+        pb.set_style(
+            ProgressStyle::default_bar()
+                .template(
+                    "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})",
+                )
+                .expect("valid progress bar template")
+                .progress_chars("█▓▒░"),
+        );
+        Some(pb)
+    } else {
+        None
+    };
+
+    let summary = imaging::image_device(&opts, |done, total| {
+        if let Some(ref pb) = pb {
+            pb.set_length(total);
+            pb.set_position(done);
+        }
+    });
+    if let Some(ref pb) = pb {
+        pb.finish_and_clear();
+    }
+    let summary = summary?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+        return Ok(());
+    }
+
+    println!(
+        "  {} {} of {} rescued in {:.1}s",
+        "✓".bright_green().bold(),
+        humansize::format_size(summary.rescued_bytes, humansize::BINARY),
+        humansize::format_size(summary.size, humansize::BINARY),
+        summary.duration_ms as f64 / 1000.0
+    );
+    if summary.bad_bytes > 0 {
+        println!(
+            "  {} {} unreadable in {} regions, zero-filled:",
+            "⚠".yellow(),
+            humansize::format_size(summary.bad_bytes, humansize::BINARY),
+            summary.bad_regions.len()
+        );
+        for region in summary.bad_regions.iter().take(MAX_REGIONS) {
+            println!(
+                "  {:>14} – {:<14} {:>10}  {}",
+                region.pos,
+                region.end(),
+                humansize::format_size(region.size, humansize::BINARY),
+                region.status.label()
+            );
+        }
+        if summary.bad_regions.len() > MAX_REGIONS {
+            println!(
+                "  … {} more (see the mapfile)",
+                summary.bad_regions.len() - MAX_REGIONS
+            );
+        }
+    }
+    println!("  BLAKE3: {}", summary.blake3);
+    println!(
+        "  {} Mapfile: {}",
+        "✓".bright_green().bold(),
+        summary.mapfile.display().to_string().bright_white()
+    );
+    println!(
+        "  {} Proof:   {}",
+        "✓".bright_green().bold(),
+        summary.proof_manifest.display().to_string().bright_white()
+    );
+
+    Ok(())
+}
+
+fn run_heal_report(args: cli::HealReportArgs, output: Option<cli::OutputFormat>) -> Result<()> {
+    use colored::Colorize;
+    use diamond_drill::swarm::{HealLog, HealReport};
+
+    let report = HealReport::from_entries(&HealLog::read(&args.log)?);
+
+    if matches!(output, Some(cli::OutputFormat::Json)) {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    let summary = &report.summary;
+    println!(
+        "\n{} {}",
+        "💊".bright_cyan(),
+        args.log.display().to_string().bright_white()
+    );
+    println!(
+        "  {} attempts: {} healed, {} retrying, {} failed, {} skipped",
+        summary.total_attempts, summary.healed, summary.retrying, summary.failed, summary.skipped
+    );
+    if report.files.is_empty() {
+        println!("\n  {} No files left failed", "✓".bright_green());
+        return Ok(());
+    }
+
+    println!(
+        "\n  {} files failed:",
+        report.files.len().to_string().bright_red()
+    );
+    for class in &report.by_class {
+        println!(
+            "    {:<12} {:>6} files {:>7} attempts   e.g. {}",
+            class.class.name(),
+            class.files,
+            class.attempts,
+            class.example
+        );
+    }
+
+    println!();
+    for file in report.files.iter().take(args.top) {
+        println!(
+            "  {} ({}, {}, {} attempts)",
+            file.source.display().to_string().bright_cyan(),
+            file.agent,
+            file.class.name(),
+            file.attempts
+        );
+        println!("      {}", file.error);
+    }
+    if report.files.len() > args.top {
+        println!(
+            "  … and {} more (--top to list them)",
+            report.files.len() - args.top
+        );
+    }
+    if report.unconfirmed > 0 {
+        println!(
+            "\n  {} files were retried with no outcome logged",
+            report.unconfirmed
+        );
+    }
+    println!(
+        "\n  {} Reprocess them: diamond-drill swarm <source> --retry-failed {}",
+        "→".bright_cyan(),
+        args.log.display()
+    );
+    Ok(())
+}
+
+fn run_ask(args: cli::AskArgs, output: Option<cli::OutputFormat>) -> Result<()> {
+    use colored::Colorize;
+    use diamond_drill::swarm::{self, ChatConfig};
+
+    let corpus = swarm::Corpus::build(
+        &args.source,
+        args.extensions.as_deref(),
+        swarm::ChunkConfig::default(),
+        swarm::EmbedderConfig {
+            model: args.embed_model.clone(),
+            ..Default::default()
+        },
+    )?;
+    let passages = corpus.retrieve(&args.question, args.top)?;
+
+    let result = if args.answer {
+        let mut chat = match args.llm {
+            cli::LlmBackendArg::LmStudio => ChatConfig::lm_studio(args.chat_model.clone()),
+            cli::LlmBackendArg::Ollama => ChatConfig::ollama(args.chat_model.clone()),
+        };
+        if let Some(endpoint) = &args.llm_endpoint {
+            chat = chat.with_endpoint(endpoint);
+        }
+        swarm::answer(&args.question, passages, &chat)?
+    } else {
+        swarm::Answer {
+            question: args.question.clone(),
+            answer: None,
+            cited: Vec::new(),
+            passages,
+        }
+    };
+
+    if matches!(output, Some(cli::OutputFormat::Json)) {
+        println!("{}", serde_json::to_string_pretty(&result)?);
+        return Ok(());
+    }
+
+    println!(
+        "\n{} {}",
+        "💎".bright_cyan(),
+        result.question.bright_white()
+    );
+    if let Some(answer) = &result.answer {
+        println!("\n{}\n", answer);
+        if !result.cited.is_empty() {
+            println!("Sources:");
+            for number in &result.cited {
+                if let Some(passage) = result.passages.iter().find(|p| p.number == *number) {
+                    println!(
+                        "  [{}] {}",
+                        number,
+                        passage.source.display().to_string().bright_cyan()
+                    );
+                }
+            }
+        }
+    } else if result.passages.is_empty() {
+        println!("  No passages in {} chunks match", corpus.chunks);
+    } else {
+        for passage in &result.passages {
+            let snippet: String = passage.content.chars().take(240).collect();
+            println!(
+                "\n  [{}] {} (chunk {}, score {:.3})",
+                passage.number,
+                passage.source.display().to_string().bright_cyan(),
+                passage.chunk_id,
+                passage.score
+            );
+            println!(
+                "      {}",
+                snippet.split_whitespace().collect::<Vec<_>>().join(" ")
+            );
+        }
+        println!(
+            "\n  {} Add --answer to have a local LLM answer from these passages",
+            "→".bright_cyan()
+        );
+    }
+    Ok(())
+}
+
+fn run_health(args: cli::HealthArgs, output: Option<cli::OutputFormat>) -> Result<()> {
+    use colored::Colorize;
+    use diamond_drill::health::{self, Verdict};
+
+    let device = health::source_device(&args.source).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Cannot tell which drive holds {}; pass the device instead",
+            args.source.display()
+        )
+    })?;
+    let smart = health::query(&device)?;
+    let assessment = smart.assess();
+
+    if matches!(output, Some(cli::OutputFormat::Json)) {
+        let report = serde_json::json!({ "smart": smart, "assessment": assessment });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        println!(
+            "\n{} {}{}",
+            "💎".bright_cyan(),
+            device.display().to_string().bright_white(),
+            smart
+                .model
+                .as_deref()
+                .map(|m| format!(" ({})", m))
+                .unwrap_or_default()
+        );
+        let value = |v: Option<u64>| v.map_or("n/a".to_string(), |v| v.to_string());
+        let overall = match smart.passed {
+            Some(true) => "PASSED",
+            Some(false) => "FAILED",
+            None => "n/a",
+        };
+        println!("  Self-assessment:   {}", overall);
+        println!("  Reallocated:       {}", value(smart.reallocated_sectors));
+        println!("  Pending:           {}", value(smart.pending_sectors));
+        println!(
+            "  Uncorrectable:     {}",
+            value(smart.uncorrectable_sectors)
+        );
+        if smart.media_errors.is_some() {
+            println!("  Media errors:      {}", value(smart.media_errors));
+        }
+        println!(
+            "  Temperature:       {}",
+            smart
+                .temperature_c
+                .map_or("n/a".to_string(), |t| format!("{}°C", t))
+        );
+        println!("  Power-on hours:    {}", value(smart.power_on_hours));
+        println!();
+        let verdict = match assessment.verdict {
+            Verdict::Healthy => "healthy".bright_green().bold(),
+            Verdict::Degraded => "degraded".yellow().bold(),
+            Verdict::Failing => "failing".bright_red().bold(),
+        };
+        println!("  Verdict: {}", verdict);
+        for reason in &assessment.reasons {
+            println!("    • {}", reason);
+        }
+        if assessment.verdict == Verdict::Failing {
+            println!(
+                "\n  {} Image the drive before anything else: diamond-drill image {} <image>",
+                "⚠".yellow(),
+                device.display()
+            );
+        }
+    }
+
+    if assessment.verdict == Verdict::Failing {
+        anyhow::bail!("Drive is failing");
+    }
+    Ok(())
+}
+
+fn run_bench(args: cli::BenchArgs, output: Option<cli::OutputFormat>) -> Result<()> {
+    use colored::Colorize;
+    use diamond_drill::bench::{self, BenchOptions, Calibrations};
+
+    let json_output = matches!(output, Some(cli::OutputFormat::Json));
+    let size = |value: &str, name: &str| {
+        parse_size_str(value)
+            .filter(|&s| s > 0)
+            .ok_or_else(|| anyhow::anyhow!("Invalid {}: {}", name, value))
+    };
+    let mut options = BenchOptions {
+        source: args.source.clone(),
+        dest: args.dest.clone(),
+        block_sizes: args
+            .block_sizes
+            .iter()
+            .map(|b| size(b, "block size").map(|s| s as usize))
+            .collect::<Result<_>>()?,
+        sample_bytes: size(&args.sample, "--sample")?,
+        time_limit: std::time::Duration::from_secs(args.seconds.max(1)),
+        ..Default::default()
+    };
+    if let Some(max_workers) = args.max_workers {
+        options.max_workers = max_workers.max(1);
+    }
+
+    if !json_output {
+        println!(
+            "\n{} Benchmarking {}{}",
+            "💎".bright_cyan(),
+            args.source.display().to_string().bright_white(),
+            args.dest
+                .as_ref()
+                .map(|d| format!(" → {}", d.display().to_string().bright_white()))
+                .unwrap_or_default()
+        );
+    }
+    let rate = |bytes: f64| {
+        format!(
+            "{}/s",
+            humansize::format_size(bytes as u64, humansize::BINARY)
+        )
+    };
+    let report = bench::run(&options, |m| {
+        if json_output {
+            return;
+        }
+        println!(
+            "  {:<16} {:>9} × {:<2} {:>14} {:>10.0} IOPS{}",
+            m.pattern.label(),
+            humansize::format_size(m.block_size as u64, humansize::BINARY),
+            m.workers,
+            rate(m.bytes_per_sec()),
+            m.iops(),
+            if m.errors > 0 {
+                format!("  {} {} errors", "⚠".yellow(), m.errors)
+            } else {
+                String::new()
+            }
+        );
+    })?;
+
+    let saved = if args.no_save {
+        None
+    } else {
+        let path = Calibrations::default_path();
+        let mut calibrations = Calibrations::load(&path)?;
+        calibrations.record(report.clone());
+        calibrations.save(&path)?;
+        Some(path)
+    };
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    let tuning = report.tuning;
+    println!("\n  Recommended:");
+    if let Some(block_size) = tuning.block_size {
+        println!(
+            "    Block size:      {}",
+            humansize::format_size(block_size as u64, humansize::BINARY)
+        );
+    }
+    println!(
+        "    Read workers:    {} (index, carve)",
+        tuning.read_workers
+    );
+    println!("    Export workers:  {}", tuning.export_workers);
+    match saved {
+        Some(path) => println!(
+            "\n  {} Saved to {}; later commands on this source use it",
+            "✓".bright_green(),
+            path.display()
+        ),
+        None => println!("\n  Not saved (--no-save)"),
+    }
+    Ok(())
+}
+
+async fn run_artifacts(args: cli::ArtifactsArgs, output: Option<cli::OutputFormat>) -> Result<()> {
+    use colored::Colorize;
+    use diamond_drill::artifacts;
+
+    let engine = DrillEngine::load_or_create(&args.source).await?;
+    let entries = engine.get_all_entries().await;
+    let databases = if entries.is_empty() {
+        artifacts::locate_in_dir(&args.source)
+    } else {
+        artifacts::locate_in_entries(&entries)
+    };
+    let report = artifacts::extract(&args.source, databases);
+    if let Some(path) = &args.report {
+        report.save(path)?;
+    }
+
+    let format = output.unwrap_or(cli::OutputFormat::Human);
+    if matches!(format, cli::OutputFormat::Human) {
+        println!(
+            "\n{} {} browser database(s) in {}",
+            "💎".bright_cyan(),
+            report.databases.len(),
+            args.source.display().to_string().bright_white()
+        );
+        for db in &report.databases {
+            let status = match &db.error {
+                Some(e) => e.yellow().to_string(),
+                None => format!("{} events", db.events),
+            };
+            println!(
+                "  {:<7}  {:<9} {:<20} {}  ({})",
+                db.browser,
+                format!("{:?}", db.kind).to_lowercase(),
+                db.profile,
+                db.path.display(),
+                status
+            );
+        }
+        println!();
+    }
+    cli::output::print_artifacts(&report.events, format)?;
+    if let Some(path) = &args.report {
+        if matches!(format, cli::OutputFormat::Human) {
+            println!("\n  {} Report: {}", "✓".bright_green(), path.display());
+        }
+    }
+    Ok(())
+}
+
+async fn run_stats(args: cli::StatsArgs, output: Option<cli::OutputFormat>) -> Result<()> {
+    use colored::Colorize;
+    use diamond_drill::report::StatsReport;
+
+    let engine = DrillEngine::load_or_create(&args.source).await?;
+    let entries = engine.get_all_entries().await;
+    if entries.is_empty() {
+        anyhow::bail!(
+            "No index for {}; run `diamond-drill index {}` first",
+            args.source.display(),
+            args.source.display()
+        );
+    }
+    let report = StatsReport::build(&args.source, &entries, args.top);
+
+    if matches!(output, Some(cli::OutputFormat::Json)) {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print!("{}", report.to_human_string());
+    }
+    if let Some(path) = &args.html {
+        report.save_html(path)?;
+        if !matches!(output, Some(cli::OutputFormat::Json)) {
+            println!("  {} HTML: {}", "✓".bright_green(), path.display());
+        }
+        if args.open {
+            if let Err(e) = opener::open(path) {
+                tracing::warn!("Could not open {}: {}", path.display(), e);
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn run_watch(args: cli::WatchArgs, output: Option<cli::OutputFormat>) -> Result<()> {
+    use colored::Colorize;
+    use diamond_drill::watch::{self, WatchOptions};
+
+    let json = matches!(output, Some(cli::OutputFormat::Json));
+    let dir = args
+        .dir
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve path: {}", args.dir.display()))?;
+    anyhow::ensure!(dir.is_dir(), "{} is not a directory", dir.display());
+    let engine = DrillEngine::load_or_create(&dir).await?;
+    let options = WatchOptions {
+        dir: dir.clone(),
+        settle: std::time::Duration::from_secs(args.settle),
+        thumbnails: !args.no_thumbnails,
+        dedup: !args.no_dedup,
+        skip_hidden: args.skip_hidden,
+    };
+
+    if !json {
+        println!(
+            "\n{} Watching {} ({} files indexed); Ctrl-C to stop\n",
+            "💎".bright_cyan(),
+            dir.display().to_string().bright_white(),
+            engine.file_count().await
+        );
+    }
+    let summary = watch::watch(&engine, &options, |file| {
+        if json {
+            if let Ok(line) = serde_json::to_string(file) {
+                println!("{}", line);
+            }
+            return;
+        }
+        let size = humansize::format_size(file.size, humansize::BINARY);
+        if file.hash.is_none() {
+            println!(
+                "  {} {} ({}, unreadable)",
+                "!".bright_red(),
+                file.path.display(),
+                size
+            );
+        } else {
+            println!(
+                "  {} {} ({})",
+                "+".bright_green(),
+                file.path.display(),
+                size
+            );
+        }
+        if let Some(original) = &file.duplicate_of {
+            println!(
+                "      {} duplicate of {}",
+                "=".bright_yellow(),
+                original.display()
+            );
+        }
+    })
+    .await?;
+
+    if json {
+        return Ok(());
+    }
+    println!(
+        "\n  Indexed {} files ({}), {} duplicates ({}), {} unreadable",
+        summary.files_indexed,
+        humansize::format_size(summary.bytes_indexed, humansize::BINARY),
+        summary.duplicates,
+        humansize::format_size(summary.duplicate_bytes, humansize::BINARY),
+        summary.unreadable
+    );
+    if summary.pending > 0 {
+        println!(
+            "  {} files were still being written and were not indexed",
+            summary.pending
+        );
+    }
+    Ok(())
+}
+
+fn run_gc(args: cli::GcArgs, output: Option<cli::OutputFormat>) -> Result<()> {
+    use colored::Colorize;
+    use diamond_drill::maintenance::{self, RetentionPolicy};
+
+    let dir = args.data_dir.unwrap_or_else(maintenance::data_dir);
+    let days = |d: u32| (d > 0).then(|| chrono::Duration::days(d as i64));
+    let policy = RetentionPolicy {
+        max_age: days(args.max_age_days),
+        keep_per_source: (args.keep_per_source > 0).then_some(args.keep_per_source),
+        compact_after: if args.no_compact {
+            None
+        } else {
+            Some(chrono::Duration::days(args.compact_after_days as i64))
+        },
+        compact_min_paths: args.compact_min_paths,
+    };
+
+    let artifacts = maintenance::scan(&dir)?;
+    let plan = maintenance::plan(artifacts, &policy, chrono::Utc::now());
+    let json_output = matches!(output, Some(cli::OutputFormat::Json));
+
+    if args.dry_run {
+        if json_output {
+            println!("{}", serde_json::to_string_pretty(&plan)?);
+            return Ok(());
+        }
+        println!(
+            "\n{} Garbage collection plan for {} (dry run)",
+            "💎".bright_cyan(),
+            dir.display().to_string().bright_white()
+        );
+        for removal in &plan.remove {
+            println!(
+                "  {} {} {} ({}, {})",
+                "✗".bright_red(),
+                removal.artifact.kind,
+                removal.artifact.path.display(),
+                humansize::format_size(removal.artifact.size, humansize::BINARY),
+                removal.reason
+            );
+        }
+        for a in &plan.compact {
+            println!(
+                "  {} compact {} ({} processed paths)",
+                "≈".bright_yellow(),
+                a.path.display(),
+                a.processed_paths.unwrap_or(0)
+            );
+        }
+        println!(
+            "  {} to remove ({}), {} to compact, {} kept",
+            plan.remove.len(),
+            humansize::format_size(plan.bytes_removed(), humansize::BINARY),
+            plan.compact.len(),
+            plan.kept
+        );
+        return Ok(());
+    }
+
+    let report = maintenance::apply(&plan);
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        println!(
+            "  {} Removed {} artifacts ({}), compacted {} checkpoints (saved {}), kept {}",
+            "✓".bright_green().bold(),
+            report.removed,
+            humansize::format_size(report.bytes_removed, humansize::BINARY),
+            report.compacted,
+            humansize::format_size(report.bytes_compacted, humansize::BINARY),
+            plan.kept
+        );
+        for error in &report.errors {
+            println!("  {} {}", "⚠".yellow(), error);
+        }
+    }
+    if !report.errors.is_empty() {
+        anyhow::bail!("{} artifacts could not be cleaned up", report.errors.len());
+    }
+    Ok(())
+}
+
+fn run_logs_list(args: cli::LogsListArgs, output: Option<cli::OutputFormat>) -> Result<()> {
+    use colored::Colorize;
+    use diamond_drill::core::format_timestamp;
+    use diamond_drill::spinner::format_duration;
+    use diamond_drill::{audit, maintenance};
+
+    let dir = args
+        .dir
+        .unwrap_or_else(|| maintenance::data_dir().join(maintenance::LOGS_DIR));
+    let mut runs = audit::list_runs(&dir)?;
+    runs.truncate(args.limit);
+
+    if matches!(output, Some(cli::OutputFormat::Json)) {
+        println!("{}", serde_json::to_string_pretty(&runs)?);
+        return Ok(());
+    }
+    if runs.is_empty() {
+        println!("No runs logged in {}", dir.display());
+        return Ok(());
+    }
+
+    println!(
+        "\n{} Runs logged in {}",
+        "💎".bright_cyan(),
+        dir.display().to_string().bright_white()
+    );
+    for run in &runs {
+        let status = match run.finished {
+            Some(ref end) if end.ok => format!(
+                "ok in {}",
+                format_duration(std::time::Duration::from_millis(end.duration_ms))
+            )
+            .bright_green(),
+            Some(_) => "failed".bright_red(),
+            None => "incomplete".yellow(),
+        };
+        println!(
+            "  {}  {:<12} {}  {}",
+            run.run_id.bright_cyan(),
+            run.command,
+            format_timestamp(&run.started, "%Y-%m-%d %H:%M:%S"),
+            status
+        );
+    }
+    println!(
+        "\n  {} Show one: diamond-drill logs show <run>",
+        "→".bright_cyan()
+    );
+    Ok(())
+}
+
+fn run_logs_show(args: cli::LogsShowArgs, output: Option<cli::OutputFormat>) -> Result<()> {
+    use colored::Colorize;
+    use diamond_drill::audit::{self, Record};
+    use diamond_drill::core::format_timestamp;
+    use diamond_drill::maintenance;
+    use diamond_drill::spinner::format_duration;
+
+    let dir = args
+        .dir
+        .unwrap_or_else(|| maintenance::data_dir().join(maintenance::LOGS_DIR));
+    let path = audit::find_run(&dir, &args.run)?;
+    let records = audit::read_run(&path)?;
+
+    if matches!(output, Some(cli::OutputFormat::Json)) {
+        println!("{}", serde_json::to_string_pretty(&records)?);
+        return Ok(());
+    }
+
+    for record in &records {
+        match record {
+            Record::Start {
+                run_id,
+                command,
+                args,
+                version,
+                cwd,
+                at,
+            } => {
+                println!(
+                    "\n{} {} ({})",
+                    "💎".bright_cyan(),
+                    run_id.bright_white(),
+                    command
+                );
+                println!("  Started:  {}", format_timestamp(at, "%Y-%m-%d %H:%M:%S"));
+                println!("  Command:  {}", args.join(" "));
+                if let Some(cwd) = cwd {
+                    println!("  In:       {}", cwd.display());
+                }
+                println!("  Version:  {}\n", version);
+            }
+            Record::Event { at, detail } => {
+                let kind = detail["event"].as_str().unwrap_or("event");
+                let stage = detail["stage"].as_str().map(|s| format!(" {}", s));
+                let subject = ["file_path", "source", "path"]
+                    .iter()
+                    .find_map(|key| detail[*key].as_str())
+                    .unwrap_or_default();
+                println!(
+                    "  {}  {}{}  {}",
+                    format_timestamp(at, "%H:%M:%S"),
+                    kind.bright_cyan(),
+                    stage.unwrap_or_default(),
+                    subject
+                );
+                for key in ["error", "reason", "result"] {
+                    match &detail[key] {
+                        serde_json::Value::Null => {}
+                        serde_json::Value::String(text) => println!("            {}", text),
+                        value => println!("            {}", value),
+                    }
+                }
+            }
+            Record::End {
+                duration_ms,
+                ok,
+                error,
+                counts,
+                ..
+            } => {
+                let took = format_duration(std::time::Duration::from_millis(*duration_ms));
+                if *ok {
+                    println!("\n  {} Finished in {}", "✓".bright_green(), took);
+                } else {
+                    println!("\n  {} Failed after {}", "✗".bright_red(), took);
+                }
+                if let Some(error) = error {
+                    println!("    {}", error);
+                }
+                println!(
+                    "    {} exported, {} failed, {} skipped, {} hashed, {} bad sectors, {} heals",
+                    counts.exported,
+                    counts.export_failed,
+                    counts.skipped,
+                    counts.hashed,
+                    counts.bad_sectors,
+                    counts.heals
+                );
+            }
+        }
+    }
+    if !matches!(records.last(), Some(Record::End { .. })) {
+        println!(
+            "\n  {} No end record: the run is still going or was killed",
+            "⚠".yellow()
+        );
+    }
+    Ok(())
+}
+
+fn run_proof_keygen(args: cli::ProofKeygenArgs) -> Result<()> {
+    use colored::Colorize;
+    use diamond_drill::proof;
+
+    let pub_path = args.key.with_extension("pub");
+    let key = proof::generate_key();
+    proof::save_key_pair(&key, &args.key, &pub_path)?;
+
+    println!(
+        "  {} Signing key:  {}",
+        "✓".bright_green().bold(),
+        args.key.display()
+    );
+    println!(
+        "  {} Public key:   {}",
+        "✓".bright_green().bold(),
+        pub_path.display()
+    );
+    println!(
+        "  Fingerprint:  {}",
+        proof::key_fingerprint(&key.verifying_key()).bright_white()
+    );
+    println!("  Keep the signing key private; hand out the public key to verifiers.");
+    Ok(())
+}
+
+fn run_proof_sign(args: cli::ProofSignArgs) -> Result<()> {
+    use colored::Colorize;
+    use diamond_drill::proof;
+
+    let key = proof::load_signing_key(&args.key)?;
+    let mut manifest = proof::load_manifest(&args.manifest)?;
+
+    // Signing a manifest whose files no longer match would vouch for
+    // tampered evidence
+    let result = proof::verify_manifest(&manifest)?;
+    if result.failed > 0 || result.missing > 0 || !result.root_hash_valid {
+        print!("{}", proof::format_verify_result(&result));
+        anyhow::bail!("Refusing to sign: manifest does not verify against the files on disk");
+    }
+    if manifest.chain_of_custody.signature.is_some() {
+        println!("  {} Replacing existing signature", "⚠".yellow());
+    }
+
+    let signer = args
+        .signer
+        .unwrap_or_else(|| manifest.chain_of_custody.operator.clone());
+    proof::sign_manifest(&mut manifest, &key, &signer)?;
+    proof::save_manifest(&manifest, &args.manifest)?;
+
+    println!(
+        "  {} Signed {} as {} (key {})",
+        "✓".bright_green().bold(),
+        args.manifest.display(),
+        signer.bright_white(),
+        proof::key_fingerprint(&key.verifying_key())
+    );
+    Ok(())
+}
+
+fn run_proof_prove(args: cli::ProofProveArgs) -> Result<()> {
+    use diamond_drill::proof;
+
+    let manifest = proof::load_manifest(&args.manifest)?;
+    let proof = proof::prove_file(&manifest, &args.file)?;
+    let json = serde_json::to_string_pretty(&proof)?;
+    match &args.out {
+        Some(out) => {
+            std::fs::write(out, json)
+                .with_context(|| format!("Failed to write {}", out.display()))?;
+            eprintln!(
+                "Inclusion proof for {} ({} sibling hashes) written to {}",
+                proof.entry.dest_path,
+                proof.path.len(),
+                out.display()
+            );
+        }
+        None => println!("{}", json),
+    }
+    Ok(())
+}
+
+fn run_proof_timestamp(args: cli::ProofTimestampArgs) -> Result<()> {
+    use colored::Colorize;
+    use diamond_drill::proof;
+
+    let mut manifest = proof::load_manifest(&args.manifest)?;
+    // The signature covers the timestamp, so adding one afterwards would
+    // invalidate it
+    anyhow::ensure!(
+        manifest.chain_of_custody.signature.is_none(),
+        "{} is already signed; timestamp before signing",
+        args.manifest.display()
+    );
+    let result = proof::verify_manifest(&manifest)?;
+    if result.failed > 0 || result.missing > 0 || !result.root_hash_valid {
+        print!("{}", proof::format_verify_result(&result));
+        anyhow::bail!("Refusing to timestamp: manifest does not verify against the files on disk");
+    }
+    if manifest.chain_of_custody.timestamp.is_some() {
+        println!("  {} Replacing existing timestamp", "⚠".yellow());
+    }
+
+    let timestamp = proof::request_timestamp(&manifest, &args.tsa)?;
+    let token = hex::decode(&timestamp.token).context("Timestamp token is not hex")?;
+    let token_path = args.manifest.with_extension("tst");
+    std::fs::write(&token_path, token)
+        .with_context(|| format!("Failed to write {}", token_path.display()))?;
+    let (gen_time, imprint) = (timestamp.gen_time, timestamp.message_imprint.clone());
+    let tsa_url = timestamp.tsa_url.clone();
+    manifest.chain_of_custody.timestamp = Some(timestamp);
+    proof::save_manifest(&manifest, &args.manifest)?;
+
+    println!(
+        "  {} Timestamped {} at {} by {}",
+        "✓".bright_green().bold(),
+        args.manifest.display(),
+        gen_time
+            .format("%Y-%m-%d %H:%M:%S UTC")
+            .to_string()
+            .bright_white(),
+        tsa_url
+    );
+    println!("  Token:        {}", token_path.display());
+    println!(
+        "  Check the TSA signature with: openssl ts -verify -token_in -in {} -digest {} -CAfile <tsa.crt>",
+        token_path.display(),
+        imprint
+    );
+    Ok(())
+}
+
+fn run_proof_log(args: cli::ProofLogArgs) -> Result<()> {
+    use colored::Colorize;
+    use diamond_drill::proof::{self, CustodyEventKind};
+
+    let mut manifest = proof::load_manifest(&args.manifest)?;
+    let (Some(kind), Some(detail)) = (args.add, args.detail) else {
+        if manifest.chain_of_custody.events.is_empty() {
+            println!("  No custody events recorded");
+        } else {
+            print!("{}", proof::format_custody_log(&manifest.chain_of_custody));
+        }
+        return Ok(());
+    };
+
+    // Appending to a broken log would hide where it was broken
+    let check = proof::verify_custody_log(&manifest.chain_of_custody);
+    if let Some(seq) = check.broken_at {
+        anyhow::bail!("Custody log is broken at event {}; not appending", seq);
+    }
+    let kind = match kind {
+        cli::CustodyEventArg::Transferred => CustodyEventKind::ManifestTransferred,
+        cli::CustodyEventArg::Note => CustodyEventKind::Note,
+    };
+    manifest.chain_of_custody.record_event(kind, detail);
+    proof::save_manifest(&manifest, &args.manifest)?;
+    println!(
+        "  {} Recorded event {} in {}",
+        "✓".bright_green().bold(),
+        manifest.chain_of_custody.events.len() - 1,
+        args.manifest.display()
+    );
+    Ok(())
+}
+
+/// Append a verify run to the manifest's custody log
+fn record_verify_run(path: &std::path::Path, detail: &str) -> Result<()> {
+    use diamond_drill::proof;
+
+    let mut manifest = proof::load_manifest(path)?;
+    manifest
+        .chain_of_custody
+        .record_event(proof::CustodyEventKind::VerifyRun, detail);
+    proof::save_manifest(&manifest, path)?;
+    println!("\nRecorded in custody log: {}", path.display());
+    Ok(())
+}
+
 fn parse_size_str(s: &str) -> Option<u64> {
     let s = s.trim().to_uppercase();
     let (num, unit) = if s.ends_with("GB") {
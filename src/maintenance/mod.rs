@@ -0,0 +1,416 @@
+//! Maintenance - garbage collection of accumulated run artifacts
+//!
+//! Indexes, checkpoints, swarm sessions and heal logs pile up in the data
+//! directory across runs. `maintenance gc` applies a retention policy:
+//!
+//! - artifacts older than `max_age` are removed
+//! - only the newest `keep_per_source` artifacts of each kind are kept for
+//!   any one source
+//! - surviving checkpoints older than `compact_after` with at least
+//!   `compact_min_paths` processed paths have that set folded into a bloom
+//!   filter summary (see [`Checkpoint::compact`])
+//!
+//! Active swarm sessions are never touched.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::checkpoint::Checkpoint;
+use crate::swarm::{SessionStatus, SwarmSession};
+
+/// Subdirectory of the data directory holding checkpoints
+pub const CHECKPOINTS_DIR: &str = "checkpoints";
+/// Subdirectory of the data directory for saved swarm sessions
+pub const SESSIONS_DIR: &str = "sessions";
+/// Subdirectory of the data directory for swarm heal logs
+pub const HEAL_LOGS_DIR: &str = "heal";
+/// Subdirectory of the data directory for the swarm embedding cache (kept
+/// within its own size budget, so `gc` leaves it alone)
+pub const EMBEDDINGS_DIR: &str = "embeddings";
+/// Subdirectory of the data directory for per-run operation logs (an audit
+/// trail, so `gc` leaves it alone)
+pub const LOGS_DIR: &str = "logs";
+
+/// Default data directory (where indexes and checkpoints are written)
+pub fn data_dir() -> PathBuf {
+    directories::ProjectDirs::from("com", "tunclon", "diamond-drill")
+        .map(|dirs| dirs.data_dir().to_path_buf())
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArtifactKind {
+    Index,
+    Checkpoint,
+    Session,
+    HealLog,
+}
+
+impl std::fmt::Display for ArtifactKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ArtifactKind::Index => "index",
+            ArtifactKind::Checkpoint => "checkpoint",
+            ArtifactKind::Session => "session",
+            ArtifactKind::HealLog => "heal log",
+        })
+    }
+}
+
+/// A file found in the data directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Artifact {
+    pub path: PathBuf,
+    pub kind: ArtifactKind,
+    /// Groups artifacts of one source: the source path when the file records
+    /// it, otherwise the source hash in the file name
+    pub source: String,
+    pub modified: DateTime<Utc>,
+    pub size: u64,
+    /// Checkpoint processed-path count, for compaction decisions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub processed_paths: Option<usize>,
+    /// Sessions that are still running are never collected
+    #[serde(skip)]
+    pub active: bool,
+}
+
+/// What `gc` keeps, removes and compacts
+#[derive(Debug, Clone)]
+pub struct RetentionPolicy {
+    /// Remove artifacts older than this
+    pub max_age: Option<Duration>,
+    /// Keep at most this many artifacts of each kind per source
+    pub keep_per_source: Option<usize>,
+    /// Compact checkpoints older than this
+    pub compact_after: Option<Duration>,
+    /// Only compact checkpoints with at least this many processed paths
+    pub compact_min_paths: usize,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            max_age: Some(Duration::days(90)),
+            keep_per_source: Some(5),
+            compact_after: Some(Duration::days(7)),
+            compact_min_paths: 10_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Removal {
+    pub artifact: Artifact,
+    pub reason: String,
+}
+
+/// The result of applying a policy to a data directory
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GcPlan {
+    pub remove: Vec<Removal>,
+    pub compact: Vec<Artifact>,
+    pub kept: usize,
+}
+
+impl GcPlan {
+    pub fn bytes_removed(&self) -> u64 {
+        self.remove.iter().map(|r| r.artifact.size).sum()
+    }
+}
+
+/// Outcome of [`apply`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GcReport {
+    pub removed: usize,
+    pub bytes_removed: u64,
+    pub compacted: usize,
+    /// Bytes saved by compaction
+    pub bytes_compacted: u64,
+    pub errors: Vec<String>,
+}
+
+/// List the artifacts under `dir`
+pub fn scan(dir: &Path) -> Result<Vec<Artifact>> {
+    let mut artifacts = Vec::new();
+
+    for path in list_files(dir)? {
+        if path.extension().is_some_and(|e| e == "idx") {
+            let source = file_stem(&path);
+            artifacts.push(artifact(path, ArtifactKind::Index, source)?);
+        }
+    }
+
+    for path in list_files(&dir.join(CHECKPOINTS_DIR))? {
+        if path.extension().is_none_or(|e| e != "json") {
+            continue;
+        }
+        let checkpoint: Option<Checkpoint> = fs::read_to_string(&path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok());
+        let source = checkpoint.as_ref().map_or_else(
+            || {
+                file_stem(&path)
+                    .split('-')
+                    .next()
+                    .unwrap_or_default()
+                    .to_string()
+            },
+            |c| c.source_path.clone(),
+        );
+        let mut a = artifact(path, ArtifactKind::Checkpoint, source)?;
+        a.processed_paths = checkpoint.map(|c| c.processed_paths.len());
+        artifacts.push(a);
+    }
+
+    for path in list_files(&dir.join(SESSIONS_DIR))? {
+        let session: Option<SwarmSession> = match path.extension().and_then(|e| e.to_str()) {
+            Some("session") => fs::File::open(&path)
+                .ok()
+                .and_then(|f| bincode::deserialize_from(std::io::BufReader::new(f)).ok()),
+            Some("json") => fs::read_to_string(&path)
+                .ok()
+                .and_then(|data| serde_json::from_str(&data).ok()),
+            _ => continue,
+        };
+        let source = session
+            .as_ref()
+            .map_or_else(|| file_stem(&path), |s| s.source.display().to_string());
+        let mut a = artifact(path, ArtifactKind::Session, source)?;
+        a.active = session.is_some_and(|s| s.status == SessionStatus::Active);
+        artifacts.push(a);
+    }
+
+    for path in list_files(&dir.join(HEAL_LOGS_DIR))? {
+        let source = file_stem(&path);
+        artifacts.push(artifact(path, ArtifactKind::HealLog, source)?);
+    }
+
+    Ok(artifacts)
+}
+
+fn list_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let entry = entry?;
+        if entry.file_type()?.is_file() {
+            files.push(entry.path());
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn artifact(path: PathBuf, kind: ArtifactKind, source: String) -> Result<Artifact> {
+    let meta = fs::metadata(&path).with_context(|| format!("Failed to stat {}", path.display()))?;
+    let modified = meta
+        .modified()
+        .map(DateTime::<Utc>::from)
+        .unwrap_or_else(|_| Utc::now());
+    Ok(Artifact {
+        path,
+        kind,
+        source,
+        modified,
+        size: meta.len(),
+        processed_paths: None,
+        active: false,
+    })
+}
+
+/// Decide what to remove and compact, without touching anything
+pub fn plan(artifacts: Vec<Artifact>, policy: &RetentionPolicy, now: DateTime<Utc>) -> GcPlan {
+    let mut groups: HashMap<(ArtifactKind, String), Vec<Artifact>> = HashMap::new();
+    for a in artifacts {
+        groups
+            .entry((a.kind, a.source.clone()))
+            .or_default()
+            .push(a);
+    }
+
+    let mut plan = GcPlan::default();
+    let mut keys: Vec<_> = groups.keys().cloned().collect();
+    keys.sort_by(|a, b| (a.0 as u8, &a.1).cmp(&(b.0 as u8, &b.1)));
+    for key in keys {
+        let mut group = groups.remove(&key).unwrap_or_default();
+        // Newest first
+        group.sort_by_key(|a| std::cmp::Reverse(a.modified));
+        let mut kept_in_group = 0;
+        for a in group {
+            let age = now - a.modified;
+            let reason = if a.active {
+                None
+            } else if let Some(max) = policy.max_age.filter(|max| age > *max) {
+                Some(format!("older than {} days", max.num_days()))
+            } else if let Some(keep) = policy.keep_per_source.filter(|keep| kept_in_group >= *keep)
+            {
+                Some(format!("more than {} {}s for this source", keep, a.kind))
+            } else {
+                None
+            };
+
+            match reason {
+                Some(reason) => plan.remove.push(Removal {
+                    artifact: a,
+                    reason,
+                }),
+                None => {
+                    kept_in_group += 1;
+                    plan.kept += 1;
+                    let compactable = a.kind == ArtifactKind::Checkpoint
+                        && a.processed_paths
+                            .is_some_and(|n| n >= policy.compact_min_paths.max(1))
+                        && policy.compact_after.is_some_and(|after| age > after);
+                    if compactable {
+                        plan.compact.push(a);
+                    }
+                }
+            }
+        }
+    }
+    plan
+}
+
+/// Carry out a plan. Failures are collected rather than aborting the run.
+pub fn apply(plan: &GcPlan) -> GcReport {
+    let mut report = GcReport::default();
+    for removal in &plan.remove {
+        match fs::remove_file(&removal.artifact.path) {
+            Ok(()) => {
+                report.removed += 1;
+                report.bytes_removed += removal.artifact.size;
+            }
+            Err(e) => report
+                .errors
+                .push(format!("{}: {}", removal.artifact.path.display(), e)),
+        }
+    }
+    for a in &plan.compact {
+        match compact_checkpoint(&a.path) {
+            Ok(saved) => {
+                report.compacted += 1;
+                report.bytes_compacted += saved;
+            }
+            Err(e) => report.errors.push(format!("{}: {:#}", a.path.display(), e)),
+        }
+    }
+    report
+}
+
+/// Compact one checkpoint file in place, returning the bytes saved
+pub fn compact_checkpoint(path: &Path) -> Result<u64> {
+    let data = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read checkpoint: {}", path.display()))?;
+    let mut checkpoint: Checkpoint = serde_json::from_str(&data)
+        .with_context(|| format!("Failed to parse checkpoint: {}", path.display()))?;
+    if !checkpoint.compact() {
+        return Ok(0);
+    }
+    let compacted = serde_json::to_string_pretty(&checkpoint)?;
+    // Write next to the original and rename so a crash can't leave half a file
+    let temp = path.with_extension("json.tmp");
+    fs::write(&temp, &compacted).with_context(|| format!("Failed to write {}", temp.display()))?;
+    fs::rename(&temp, path).with_context(|| format!("Failed to replace {}", path.display()))?;
+    Ok((data.len() as u64).saturating_sub(compacted.len() as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::{CheckpointManager, CheckpointPhase};
+    use tempfile::tempdir;
+
+    fn fake(kind: ArtifactKind, source: &str, days_old: i64, now: DateTime<Utc>) -> Artifact {
+        Artifact {
+            path: PathBuf::from(format!("/data/{}-{}-{}", kind, source, days_old)),
+            kind,
+            source: source.to_string(),
+            modified: now - Duration::days(days_old),
+            size: 100,
+            processed_paths: None,
+            active: false,
+        }
+    }
+
+    #[test]
+    fn test_plan_applies_age_and_per_source_limits() {
+        let now = Utc::now();
+        let mut artifacts: Vec<Artifact> = (0..4)
+            .map(|d| fake(ArtifactKind::Checkpoint, "/mnt/a", d, now))
+            .collect();
+        artifacts.push(fake(ArtifactKind::Index, "/mnt/a", 200, now));
+        artifacts.push(fake(ArtifactKind::Index, "/mnt/b", 1, now));
+        let mut active = fake(ArtifactKind::Session, "/mnt/a", 400, now);
+        active.active = true;
+        artifacts.push(active);
+        let mut big = fake(ArtifactKind::Checkpoint, "/mnt/c", 30, now);
+        big.processed_paths = Some(50_000);
+        artifacts.push(big);
+
+        let policy = RetentionPolicy {
+            keep_per_source: Some(2),
+            ..Default::default()
+        };
+        let plan = plan(artifacts, &policy, now);
+
+        let removed: Vec<_> = plan
+            .remove
+            .iter()
+            .map(|r| r.artifact.path.clone())
+            .collect();
+        assert_eq!(removed.len(), 3);
+        assert!(removed.contains(&PathBuf::from("/data/checkpoint-/mnt/a-2")));
+        assert!(removed.contains(&PathBuf::from("/data/checkpoint-/mnt/a-3")));
+        assert!(removed.contains(&PathBuf::from("/data/index-/mnt/a-200")));
+        assert_eq!(plan.kept, 5);
+        assert_eq!(plan.compact.len(), 1);
+        assert_eq!(plan.compact[0].source, "/mnt/c");
+    }
+
+    #[test]
+    fn test_scan_and_compact_checkpoint() {
+        let dir = tempdir().unwrap();
+        let mgr = CheckpointManager::with_dir(dir.path().join(CHECKPOINTS_DIR));
+        let mut cp = Checkpoint::new(Path::new("/mnt/evidence"), CheckpointPhase::Indexing, 0);
+        for i in 0..2000 {
+            cp.mark_processed(&format!("/mnt/evidence/photos/IMG_{:05}.jpg", i), None);
+        }
+        mgr.save(&cp).unwrap();
+        fs::write(dir.path().join("0123456789abcdef.idx"), b"index").unwrap();
+
+        let artifacts = scan(dir.path()).unwrap();
+        assert_eq!(artifacts.len(), 2);
+        let checkpoint = artifacts
+            .iter()
+            .find(|a| a.kind == ArtifactKind::Checkpoint)
+            .unwrap();
+        assert_eq!(checkpoint.source, "/mnt/evidence");
+        assert_eq!(checkpoint.processed_paths, Some(2000));
+
+        let saved = compact_checkpoint(&checkpoint.path).unwrap();
+        assert!(saved > 0);
+        let loaded = mgr
+            .load(Path::new("/mnt/evidence"), CheckpointPhase::Indexing)
+            .unwrap()
+            .unwrap();
+        assert!(loaded.is_compacted());
+        assert!(loaded.processed_paths.is_empty());
+        assert_eq!(loaded.processed_count(), 2000);
+        assert!(loaded.is_already_processed("/mnt/evidence/photos/IMG_00042.jpg"));
+    }
+}
@@ -0,0 +1,518 @@
+//! Metrics - a Prometheus endpoint for long-running jobs
+//!
+//! With `--metrics-addr` (built with `--features metrics`) any command
+//! serves its counters in the Prometheus text format on
+//! `http://<addr>/metrics`, so Grafana can follow a multi-day recovery:
+//!
+//! - bytes and files scanned (indexing, carving, swarm)
+//! - files and bytes exported, failed and skipped exports, hashes
+//! - bad sectors and their bytes, heals
+//! - swarm chunks and embeddings, and the current embedding rate
+//!
+//! Everything is read off the [`events`](crate::events) bus, so no
+//! operation needs to know it is being watched. The endpoint binds to
+//! localhost unless told otherwise.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::{Read, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use parking_lot::Mutex;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::carve::CarveProgress;
+use crate::events::{self, Event};
+use crate::export::ExportFileEvent;
+
+/// Port used when `--metrics-addr` is given a bare port or nothing better
+pub const DEFAULT_METRICS_PORT: u16 = 9464;
+
+/// Longest request head read from a scraper
+const MAX_REQUEST: usize = 8 * 1024;
+
+/// Counters and gauges of this process
+#[derive(Debug)]
+pub struct Metrics {
+    bytes_scanned: AtomicU64,
+    files_scanned: AtomicU64,
+    files_exported: AtomicU64,
+    bytes_exported: AtomicU64,
+    export_failures: AtomicU64,
+    files_skipped: AtomicU64,
+    files_hashed: AtomicU64,
+    bad_sectors: AtomicU64,
+    bad_sector_bytes: AtomicU64,
+    heals: AtomicU64,
+    chunks: AtomicU64,
+    embeddings: AtomicU64,
+    /// f64 bits
+    embeddings_per_second: AtomicU64,
+    /// f64 bits
+    carve_progress: AtomicU64,
+    /// Last cumulative value each progress stream reported, so only the
+    /// growth since is counted
+    last: Mutex<HashMap<String, u64>>,
+    /// Embeddings and elapsed time at the previous swarm snapshot
+    last_embed: Mutex<Option<(usize, Duration)>>,
+    started: SystemTime,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self {
+            bytes_scanned: AtomicU64::new(0),
+            files_scanned: AtomicU64::new(0),
+            files_exported: AtomicU64::new(0),
+            bytes_exported: AtomicU64::new(0),
+            export_failures: AtomicU64::new(0),
+            files_skipped: AtomicU64::new(0),
+            files_hashed: AtomicU64::new(0),
+            bad_sectors: AtomicU64::new(0),
+            bad_sector_bytes: AtomicU64::new(0),
+            heals: AtomicU64::new(0),
+            chunks: AtomicU64::new(0),
+            embeddings: AtomicU64::new(0),
+            embeddings_per_second: AtomicU64::new(0),
+            carve_progress: AtomicU64::new(0),
+            last: Mutex::new(HashMap::new()),
+            last_embed: Mutex::new(None),
+            started: SystemTime::now(),
+        }
+    }
+
+    /// Growth of the cumulative `value` reported by `stream` since its last
+    /// report; a smaller value means the stream started over
+    fn advance(&self, stream: String, value: u64) -> u64 {
+        let mut last = self.last.lock();
+        let previous = last.insert(stream, value).unwrap_or(0);
+        if value >= previous {
+            value - previous
+        } else {
+            value
+        }
+    }
+
+    /// Update the metrics from one published event
+    pub fn record(&self, event: &Event) {
+        let add = |counter: &AtomicU64, n: u64| {
+            counter.fetch_add(n, Ordering::Relaxed);
+        };
+
+        match event {
+            Event::Scan {
+                source,
+                files,
+                bytes,
+                ..
+            } => {
+                let source = source.display();
+                add(
+                    &self.files_scanned,
+                    self.advance(format!("index-files:{}", source), *files as u64),
+                );
+                add(
+                    &self.bytes_scanned,
+                    self.advance(format!("index-bytes:{}", source), *bytes),
+                );
+            }
+            Event::Hash { .. } => add(&self.files_hashed, 1),
+            Event::Export(export) => match export {
+                ExportFileEvent::Copied { source, bytes } => add(
+                    &self.bytes_exported,
+                    self.advance(format!("export:{}", source.display()), *bytes),
+                ),
+                ExportFileEvent::Finished { source, result } => {
+                    self.last
+                        .lock()
+                        .remove(&format!("export:{}", source.display()));
+                    match result {
+                        Ok(_) => add(&self.files_exported, 1),
+                        Err(_) => add(&self.export_failures, 1),
+                    }
+                }
+                ExportFileEvent::Skipped { .. } => add(&self.files_skipped, 1),
+                ExportFileEvent::Started { .. } | ExportFileEvent::Verifying { .. } => {}
+            },
+            Event::BadSector(bad) => {
+                add(&self.bad_sectors, 1);
+                add(&self.bad_sector_bytes, bad.length);
+            }
+            Event::Carve(CarveProgress::Scanning {
+                bytes_scanned,
+                total_bytes,
+            }) => {
+                add(
+                    &self.bytes_scanned,
+                    self.advance("carve".to_string(), *bytes_scanned),
+                );
+                let ratio = *bytes_scanned as f64 / (*total_bytes).max(1) as f64;
+                self.carve_progress
+                    .store(ratio.min(1.0).to_bits(), Ordering::Relaxed);
+            }
+            Event::Carve(CarveProgress::Done) => {
+                self.carve_progress
+                    .store(1.0f64.to_bits(), Ordering::Relaxed);
+            }
+            Event::Carve(_) => {}
+            Event::Swarm(progress) => {
+                add(
+                    &self.bytes_scanned,
+                    self.advance("swarm-bytes".to_string(), progress.bytes_processed),
+                );
+                add(
+                    &self.files_scanned,
+                    self.advance("swarm-files".to_string(), progress.files_scanned as u64),
+                );
+                add(
+                    &self.chunks,
+                    self.advance("swarm-chunks".to_string(), progress.chunks_created as u64),
+                );
+                add(
+                    &self.embeddings,
+                    self.advance(
+                        "swarm-embeddings".to_string(),
+                        progress.embeddings_generated as u64,
+                    ),
+                );
+
+                let mut last = self.last_embed.lock();
+                let rate = match *last {
+                    _ if progress.finished => 0.0,
+                    Some((embeddings, elapsed)) if progress.elapsed > elapsed => {
+                        progress.embeddings_generated.saturating_sub(embeddings) as f64
+                            / (progress.elapsed - elapsed).as_secs_f64()
+                    }
+                    _ => 0.0,
+                };
+                *last = (!progress.finished)
+                    .then_some((progress.embeddings_generated, progress.elapsed));
+                self.embeddings_per_second
+                    .store(rate.to_bits(), Ordering::Relaxed);
+            }
+            Event::Heal(_) => add(&self.heals, 1),
+        }
+    }
+
+    /// The metrics in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: String| {
+            let _ = writeln!(out, "# HELP diamond_drill_{} {}", name, help);
+            let _ = writeln!(out, "# TYPE diamond_drill_{} {}", name, kind);
+            let _ = writeln!(out, "diamond_drill_{} {}", name, value);
+        };
+        let counter = |c: &AtomicU64| c.load(Ordering::Relaxed).to_string();
+        let gauge = |g: &AtomicU64| f64::from_bits(g.load(Ordering::Relaxed)).to_string();
+
+        metric(
+            "bytes_scanned_total",
+            "counter",
+            "Bytes read while indexing, carving or running the swarm",
+            counter(&self.bytes_scanned),
+        );
+        metric(
+            "files_scanned_total",
+            "counter",
+            "Files found while indexing or running the swarm",
+            counter(&self.files_scanned),
+        );
+        metric(
+            "files_exported_total",
+            "counter",
+            "Files exported and verified",
+            counter(&self.files_exported),
+        );
+        metric(
+            "bytes_exported_total",
+            "counter",
+            "Bytes copied by exports, retries included",
+            counter(&self.bytes_exported),
+        );
+        metric(
+            "export_failures_total",
+            "counter",
+            "Files that could not be exported",
+            counter(&self.export_failures),
+        );
+        metric(
+            "files_skipped_total",
+            "counter",
+            "Files an export skipped (already exported, or destination taken)",
+            counter(&self.files_skipped),
+        );
+        metric(
+            "files_hashed_total",
+            "counter",
+            "Files and images BLAKE3-hashed",
+            counter(&self.files_hashed),
+        );
+        metric(
+            "bad_sectors_total",
+            "counter",
+            "Unreadable regions met",
+            counter(&self.bad_sectors),
+        );
+        metric(
+            "bad_sector_bytes_total",
+            "counter",
+            "Bytes in unreadable regions",
+            counter(&self.bad_sector_bytes),
+        );
+        metric(
+            "heals_total",
+            "counter",
+            "Swarm failures retried, healed or given up on",
+            counter(&self.heals),
+        );
+        metric(
+            "chunks_total",
+            "counter",
+            "Swarm chunks created",
+            counter(&self.chunks),
+        );
+        metric(
+            "embeddings_total",
+            "counter",
+            "Swarm embeddings generated",
+            counter(&self.embeddings),
+        );
+        metric(
+            "embeddings_per_second",
+            "gauge",
+            "Swarm embedding rate between the last two progress snapshots",
+            gauge(&self.embeddings_per_second),
+        );
+        metric(
+            "carve_progress_ratio",
+            "gauge",
+            "Share of the image the current carve has scanned",
+            gauge(&self.carve_progress),
+        );
+        let started = self
+            .started
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        metric(
+            "start_time_seconds",
+            "gauge",
+            "When this process started, in seconds since the Unix epoch",
+            started.to_string(),
+        );
+        let _ = writeln!(
+            out,
+            "# HELP diamond_drill_build_info Version of the running binary"
+        );
+        let _ = writeln!(out, "# TYPE diamond_drill_build_info gauge");
+        let _ = writeln!(
+            out,
+            "diamond_drill_build_info{{version=\"{}\"}} 1",
+            env!("CARGO_PKG_VERSION")
+        );
+        out
+    }
+}
+
+/// Address to serve on: `host:port`, or a bare port on localhost
+pub fn parse_addr(addr: &str) -> Result<SocketAddr> {
+    if let Ok(port) = addr.parse::<u16>() {
+        return Ok(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port));
+    }
+    addr.to_socket_addrs()
+        .with_context(|| format!("Invalid metrics address {}", addr))?
+        .next()
+        .with_context(|| format!("{} does not resolve to an address", addr))
+}
+
+/// Collect metrics from the event bus and serve them on `addr` for the
+/// rest of the process; returns the address bound
+pub fn serve(addr: &str) -> Result<SocketAddr> {
+    let addr = parse_addr(addr)?;
+    let listener = TcpListener::bind(addr)
+        .with_context(|| format!("Failed to bind metrics endpoint {}", addr))?;
+    let bound = listener.local_addr()?;
+    if !bound.ip().is_loopback() {
+        tracing::warn!("Metrics are served beyond this machine on {}", bound);
+    }
+
+    let metrics = Arc::new(Metrics::new());
+    {
+        let metrics = Arc::clone(&metrics);
+        let mut events = events::subscribe();
+        thread::Builder::new()
+            .name("metrics-events".to_string())
+            .spawn(move || loop {
+                match events.blocking_recv() {
+                    Ok(event) => metrics.record(&event),
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                }
+            })
+            .context("Failed to start the metrics collector")?;
+    }
+    thread::Builder::new()
+        .name("metrics-http".to_string())
+        .spawn(move || {
+            for stream in listener.incoming().flatten() {
+                if let Err(e) = respond(stream, &metrics) {
+                    tracing::debug!("Metrics request failed: {}", e);
+                }
+            }
+        })
+        .context("Failed to start the metrics endpoint")?;
+
+    Ok(bound)
+}
+
+/// Answer one scrape
+fn respond(mut stream: TcpStream, metrics: &Metrics) -> Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    stream.set_write_timeout(Some(Duration::from_secs(5)))?;
+
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < MAX_REQUEST {
+        let n = stream.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        request.extend_from_slice(&buf[..n]);
+    }
+
+    let head = String::from_utf8_lossy(&request);
+    let mut parts = head.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    let (status, body) = match (method, path.split('?').next().unwrap_or("")) {
+        ("GET" | "HEAD", "/metrics" | "/") => ("200 OK", metrics.render()),
+        ("GET" | "HEAD", _) => ("404 Not Found", "Not found; try /metrics\n".to_string()),
+        _ => ("405 Method Not Allowed", String::new()),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4; charset=utf-8\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        body.len()
+    )?;
+    if method != "HEAD" {
+        stream.write_all(body.as_bytes())?;
+    }
+    stream.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    use crate::swarm::SwarmProgress;
+
+    fn value(text: &str, name: &str) -> f64 {
+        text.lines()
+            .find_map(|line| line.strip_prefix(&format!("diamond_drill_{} ", name)))
+            .and_then(|v| v.parse().ok())
+            .unwrap_or_else(|| panic!("{} missing", name))
+    }
+
+    #[test]
+    fn test_counters_follow_cumulative_progress() {
+        let metrics = Metrics::new();
+        let scan = |files: usize, bytes: u64| Event::Scan {
+            source: PathBuf::from("/src"),
+            files,
+            bytes,
+            path: PathBuf::from("/src/a"),
+        };
+        metrics.record(&scan(100, 1000));
+        metrics.record(&scan(250, 4000));
+        // A second index of the same source starts over
+        metrics.record(&scan(10, 500));
+
+        let copied = |bytes: u64| {
+            Event::Export(ExportFileEvent::Copied {
+                source: PathBuf::from("/src/a"),
+                bytes,
+            })
+        };
+        metrics.record(&copied(300));
+        metrics.record(&copied(800));
+        metrics.record(&Event::Export(ExportFileEvent::Finished {
+            source: PathBuf::from("/src/a"),
+            result: Ok(("abc".to_string(), 0)),
+        }));
+        metrics.record(&Event::Export(ExportFileEvent::Finished {
+            source: PathBuf::from("/src/b"),
+            result: Err("gone".to_string()),
+        }));
+
+        let swarm = |embeddings: usize, secs: u64, finished: bool| {
+            Event::Swarm(SwarmProgress {
+                elapsed: Duration::from_secs(secs),
+                embeddings_generated: embeddings,
+                bytes_processed: 2000,
+                finished,
+                ..Default::default()
+            })
+        };
+        metrics.record(&swarm(100, 10, false));
+        metrics.record(&swarm(300, 12, false));
+
+        let text = metrics.render();
+        assert_eq!(value(&text, "files_scanned_total"), 260.0);
+        assert_eq!(value(&text, "bytes_scanned_total"), 6500.0);
+        assert_eq!(value(&text, "bytes_exported_total"), 800.0);
+        assert_eq!(value(&text, "files_exported_total"), 1.0);
+        assert_eq!(value(&text, "export_failures_total"), 1.0);
+        assert_eq!(value(&text, "embeddings_total"), 300.0);
+        assert_eq!(value(&text, "embeddings_per_second"), 100.0);
+        assert!(text.contains("# TYPE diamond_drill_bad_sectors_total counter"));
+
+        metrics.record(&swarm(300, 13, true));
+        assert_eq!(value(&metrics.render(), "embeddings_per_second"), 0.0);
+    }
+
+    #[test]
+    fn test_parse_addr() {
+        assert_eq!(
+            parse_addr("9464").unwrap(),
+            "127.0.0.1:9464".parse().unwrap()
+        );
+        assert_eq!(
+            parse_addr("0.0.0.0:9100").unwrap(),
+            "0.0.0.0:9100".parse().unwrap()
+        );
+        assert!(parse_addr("not an address").is_err());
+    }
+
+    #[test]
+    fn test_serve_answers_scrapes() {
+        let addr = serve("127.0.0.1:0").unwrap();
+        let get = |path: &str| {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+
+        let response = get("/metrics");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("text/plain; version=0.0.4"));
+        assert!(response.contains("diamond_drill_build_info{version="));
+        assert!(get("/other").starts_with("HTTP/1.1 404"));
+    }
+}
@@ -0,0 +1,351 @@
+//! First-page previews for PDF and Office documents
+//!
+//! PDFs are rendered with libpdfium when the `pdfium` feature is enabled and
+//! the library can be loaded. Otherwise (and for PDFs pdfium cannot open) the
+//! page's embedded `/Thumb` or its largest image is used. Office and
+//! OpenDocument files carry a thumbnail of their first page inside the zip
+//! container, which is decoded directly.
+
+use std::io::Read;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use image::{DynamicImage, GrayImage, RgbImage};
+
+/// Longest edge pdfium renders pages at; thumbnails are scaled down from this
+#[cfg(feature = "pdfium")]
+const RENDER_SIZE: i32 = 1024;
+
+/// Embedded thumbnails larger than this are not read
+const MAX_THUMBNAIL_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Where OOXML and ODF packages usually keep their thumbnail
+const THUMBNAIL_PARTS: &[&str] = &[
+    "docProps/thumbnail.jpeg",
+    "docProps/thumbnail.jpg",
+    "docProps/thumbnail.png",
+    "Thumbnails/thumbnail.png",
+];
+
+const THUMBNAIL_RELATIONSHIP: &str = "relationships/metadata/thumbnail";
+
+/// Check if a file is a document whose first page can be previewed
+pub fn is_document_previewable(path: &Path) -> bool {
+    document_kind(path).is_some()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DocumentKind {
+    Pdf,
+    /// Zip container (OOXML or OpenDocument)
+    Package,
+}
+
+fn document_kind(path: &Path) -> Option<DocumentKind> {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "pdf" => Some(DocumentKind::Pdf),
+        "docx" | "docm" | "xlsx" | "xlsm" | "pptx" | "pptm" | "odt" | "ods" | "odp" => {
+            Some(DocumentKind::Package)
+        }
+        _ => None,
+    }
+}
+
+/// Load an image of the document's first page
+pub fn load_first_page(path: &Path) -> Result<DynamicImage> {
+    match document_kind(path) {
+        Some(DocumentKind::Pdf) => load_pdf(path),
+        Some(DocumentKind::Package) => load_package_thumbnail(path),
+        None => bail!("Not a previewable document: {}", path.display()),
+    }
+}
+
+fn load_pdf(path: &Path) -> Result<DynamicImage> {
+    #[cfg(feature = "pdfium")]
+    if let Some(pdfium) = pdfium::instance() {
+        match pdfium::render_first_page(pdfium, path) {
+            Ok(img) => return Ok(img),
+            Err(e) => tracing::debug!("pdfium could not render {}: {}", path.display(), e),
+        }
+    }
+    pdf_embedded_image(path)
+}
+
+/// The first page's `/Thumb`, or failing that its largest embedded image
+fn pdf_embedded_image(path: &Path) -> Result<DynamicImage> {
+    let doc = lopdf::Document::load(path)
+        .with_context(|| format!("Failed to parse PDF: {}", path.display()))?;
+    let page_id = *doc
+        .get_pages()
+        .values()
+        .next()
+        .with_context(|| format!("PDF has no pages: {}", path.display()))?;
+
+    let thumb = doc
+        .get_dictionary(page_id)
+        .ok()
+        .and_then(|page| page.get(b"Thumb").ok())
+        .and_then(|thumb| thumb.as_reference().ok())
+        .and_then(|id| doc.get_object(id).ok())
+        .and_then(|obj| obj.as_stream().ok())
+        .and_then(decode_pdf_image);
+    if let Some(img) = thumb {
+        return Ok(img);
+    }
+
+    let mut images = doc.get_page_images(page_id).unwrap_or_default();
+    images.sort_by_key(|img| std::cmp::Reverse(img.width * img.height));
+    images
+        .iter()
+        .filter_map(|img| doc.get_object(img.id).ok()?.as_stream().ok())
+        .find_map(decode_pdf_image)
+        .with_context(|| {
+            format!(
+                "No renderable preview in {} (build with --features pdfium for full page rendering)",
+                path.display()
+            )
+        })
+}
+
+/// Decode an image XObject: JPEG streams directly, otherwise 8-bit gray or
+/// RGB samples after the stream's filters are undone
+fn decode_pdf_image(stream: &lopdf::Stream) -> Option<DynamicImage> {
+    let filters = stream.filters().unwrap_or_default();
+    if filters.last().is_some_and(|f| f == "DCTDecode") {
+        if filters.len() != 1 {
+            return None;
+        }
+        return image::load_from_memory(&stream.content).ok();
+    }
+
+    let dict = &stream.dict;
+    let width = dict.get(b"Width").and_then(|w| w.as_i64()).ok()? as u32;
+    let height = dict.get(b"Height").and_then(|h| h.as_i64()).ok()? as u32;
+    let bits = dict
+        .get(b"BitsPerComponent")
+        .and_then(|b| b.as_i64())
+        .unwrap_or(8);
+    if bits != 8 {
+        return None;
+    }
+    let samples = if filters.is_empty() {
+        stream.content.clone()
+    } else {
+        stream.decompressed_content().ok()?
+    };
+
+    // Indexed, CMYK and other colour spaces fall through to None
+    let pixels = width as usize * height as usize;
+    if samples.len() == pixels * 3 {
+        RgbImage::from_raw(width, height, samples).map(DynamicImage::ImageRgb8)
+    } else if samples.len() == pixels {
+        GrayImage::from_raw(width, height, samples).map(DynamicImage::ImageLuma8)
+    } else {
+        None
+    }
+}
+
+/// Decode the thumbnail stored inside an OOXML or OpenDocument package
+fn load_package_thumbnail(path: &Path) -> Result<DynamicImage> {
+    let file =
+        std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut archive = zip::ZipArchive::new(file)
+        .with_context(|| format!("Not a valid Office document: {}", path.display()))?;
+
+    let mut candidates: Vec<String> = thumbnail_relationship_target(&mut archive)
+        .into_iter()
+        .collect();
+    candidates.extend(THUMBNAIL_PARTS.iter().map(|p| p.to_string()));
+
+    for part in candidates {
+        let Ok(entry) = archive.by_name(&part) else {
+            continue;
+        };
+        let mut bytes = Vec::new();
+        entry
+            .take(MAX_THUMBNAIL_BYTES)
+            .read_to_end(&mut bytes)
+            .with_context(|| format!("Failed to read {} from {}", part, path.display()))?;
+        return image::load_from_memory(&bytes).with_context(|| {
+            format!(
+                "Unsupported thumbnail format in {} ({})",
+                path.display(),
+                part
+            )
+        });
+    }
+    bail!("{} has no embedded thumbnail", path.display())
+}
+
+/// Target of the package-level thumbnail relationship in `_rels/.rels`
+fn thumbnail_relationship_target<R: Read + std::io::Seek>(
+    archive: &mut zip::ZipArchive<R>,
+) -> Option<String> {
+    let mut rels = String::new();
+    archive
+        .by_name("_rels/.rels")
+        .ok()?
+        .take(MAX_THUMBNAIL_BYTES)
+        .read_to_string(&mut rels)
+        .ok()?;
+    rels.split("<Relationship ")
+        .skip(1)
+        .find(|rel| rel.contains(THUMBNAIL_RELATIONSHIP))
+        .and_then(|rel| xml_attribute(rel, "Target"))
+        .map(|target| target.trim_start_matches('/').to_string())
+}
+
+fn xml_attribute<'a>(element: &'a str, name: &str) -> Option<&'a str> {
+    let start = element.find(&format!("{}=\"", name))? + name.len() + 2;
+    let len = element[start..].find('"')?;
+    Some(&element[start..start + len])
+}
+
+#[cfg(feature = "pdfium")]
+mod pdfium {
+    use std::path::Path;
+    use std::sync::OnceLock;
+
+    use anyhow::{Context, Result};
+    use image::DynamicImage;
+    use pdfium_render::prelude::*;
+
+    /// Directory holding libpdfium, checked before the executable's directory
+    /// and the system library path
+    const LIBRARY_DIR_ENV: &str = "DIAMOND_DRILL_PDFIUM_DIR";
+
+    static PDFIUM: OnceLock<Option<Pdfium>> = OnceLock::new();
+
+    /// The process-wide pdfium instance, or None when the library is missing
+    pub(super) fn instance() -> Option<&'static Pdfium> {
+        PDFIUM
+            .get_or_init(|| {
+                let dirs = std::env::var_os(LIBRARY_DIR_ENV)
+                    .map(std::path::PathBuf::from)
+                    .into_iter()
+                    .chain(
+                        std::env::current_exe()
+                            .ok()
+                            .and_then(|exe| exe.parent().map(Path::to_path_buf)),
+                    );
+                let bindings = dirs
+                    .map(|dir| {
+                        Pdfium::bind_to_library(Pdfium::pdfium_platform_library_name_at_path(&dir))
+                    })
+                    .find_map(Result::ok)
+                    .or_else(|| Pdfium::bind_to_system_library().ok());
+                if bindings.is_none() {
+                    tracing::info!(
+                        "libpdfium not found; PDF previews fall back to embedded images"
+                    );
+                }
+                bindings.map(Pdfium::new)
+            })
+            .as_ref()
+    }
+
+    pub(super) fn render_first_page(pdfium: &Pdfium, path: &Path) -> Result<DynamicImage> {
+        let document = pdfium
+            .load_pdf_from_file(path, None)
+            .with_context(|| format!("Failed to open PDF: {}", path.display()))?;
+        let page = document
+            .pages()
+            .first()
+            .with_context(|| format!("PDF has no pages: {}", path.display()))?;
+        let config = PdfRenderConfig::new()
+            .set_target_width(super::RENDER_SIZE)
+            .set_maximum_height(super::RENDER_SIZE);
+        let bitmap = page
+            .render_with_config(&config)
+            .with_context(|| format!("Failed to render {}", path.display()))?;
+        Ok(bitmap.as_image())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn png_bytes(img: &DynamicImage) -> Vec<u8> {
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        img.write_to(&mut bytes, image::ImageFormat::Png).unwrap();
+        bytes.into_inner()
+    }
+
+    #[test]
+    fn test_docx_embedded_thumbnail() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("letter.docx");
+        let thumb =
+            DynamicImage::ImageRgb8(RgbImage::from_pixel(30, 40, image::Rgb([200, 10, 10])));
+
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        zip.start_file("_rels/.rels", options).unwrap();
+        zip.write_all(
+            br#"<Relationships><Relationship Id="rId2" Type="http://schemas.openxmlformats.org/package/2006/relationships/metadata/thumbnail" Target="docProps/preview.png"/></Relationships>"#,
+        )
+        .unwrap();
+        zip.start_file("docProps/preview.png", options).unwrap();
+        zip.write_all(&png_bytes(&thumb)).unwrap();
+        zip.finish().unwrap();
+
+        assert!(is_document_previewable(&path));
+        let img = load_first_page(&path).unwrap();
+        assert_eq!((img.width(), img.height()), (30, 40));
+    }
+
+    #[test]
+    fn test_pdf_embedded_image_fallback() {
+        use lopdf::{dictionary, Document, Object, Stream};
+
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let pixels: Vec<u8> = (0..16 * 8).map(|i| i as u8).collect();
+        let image_id = doc.add_object(Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Image",
+                "Width" => 16,
+                "Height" => 8,
+                "ColorSpace" => "DeviceGray",
+                "BitsPerComponent" => 8,
+            },
+            pixels,
+        ));
+        let page_id = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), 160.into(), 80.into()],
+            "Resources" => dictionary! {
+                "XObject" => dictionary! { "Im1" => image_id },
+            },
+        });
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => vec![page_id.into()],
+                "Count" => 1,
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        doc.trailer.set("Root", catalog_id);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("scan.pdf");
+        doc.save(&path).unwrap();
+
+        let img = pdf_embedded_image(&path).unwrap();
+        assert_eq!((img.width(), img.height()), (16, 8));
+        assert_eq!(img.to_luma8().get_pixel(3, 1).0, [19]);
+    }
+}
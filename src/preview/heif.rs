@@ -0,0 +1,226 @@
+//! HEIC/HEIF previews
+//!
+//! HEIC photos are HEVC-coded, which has no pure-Rust decoder. With the
+//! `heif` feature the system libheif is loaded at runtime and decodes the
+//! primary image (with the container's rotation and mirroring applied).
+
+use std::path::Path;
+
+use anyhow::Result;
+use image::DynamicImage;
+
+/// Check if a file is a HEIF container (HEIC photos from iPhones and most
+/// recent Android phones)
+pub fn is_heif(path: &Path) -> bool {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    matches!(ext.as_str(), "heic" | "heif" | "hif")
+}
+
+/// Decode the primary image of a HEIF file
+pub fn load_primary_image(path: &Path) -> Result<DynamicImage> {
+    #[cfg(feature = "heif")]
+    {
+        let lib = libheif::instance().ok_or_else(|| {
+            anyhow::anyhow!(
+                "libheif not found; cannot decode {} (set {} to its directory)",
+                path.display(),
+                libheif::LIBRARY_DIR_ENV
+            )
+        })?;
+        lib.decode_primary(path)
+    }
+
+    #[cfg(not(feature = "heif"))]
+    anyhow::bail!(
+        "HEIC/HEIF decoding needs a build with --features heif: {}",
+        path.display()
+    )
+}
+
+#[cfg(feature = "heif")]
+mod libheif {
+    use std::ffi::{c_char, c_int, c_void, CStr, CString};
+    use std::path::{Path, PathBuf};
+    use std::sync::OnceLock;
+
+    use anyhow::{bail, Context, Result};
+    use image::{DynamicImage, RgbImage};
+    use libloading::Library;
+
+    /// Directory holding libheif, checked before the system library path
+    pub(super) const LIBRARY_DIR_ENV: &str = "DIAMOND_DRILL_LIBHEIF_DIR";
+
+    const COLORSPACE_RGB: c_int = 1;
+    const CHROMA_INTERLEAVED_RGB: c_int = 10;
+    const CHANNEL_INTERLEAVED: c_int = 10;
+
+    #[repr(C)]
+    struct HeifError {
+        code: c_int,
+        subcode: c_int,
+        message: *const c_char,
+    }
+
+    type HeifContext = c_void;
+    type HeifHandle = c_void;
+    type HeifImage = c_void;
+
+    /// libheif entry points, valid for as long as `_lib` is loaded
+    pub(super) struct LibHeif {
+        context_alloc: unsafe extern "C" fn() -> *mut HeifContext,
+        context_free: unsafe extern "C" fn(*mut HeifContext),
+        read_from_file:
+            unsafe extern "C" fn(*mut HeifContext, *const c_char, *const c_void) -> HeifError,
+        primary_handle: unsafe extern "C" fn(*mut HeifContext, *mut *mut HeifHandle) -> HeifError,
+        handle_release: unsafe extern "C" fn(*const HeifHandle),
+        decode_image: unsafe extern "C" fn(
+            *const HeifHandle,
+            *mut *mut HeifImage,
+            c_int,
+            c_int,
+            *const c_void,
+        ) -> HeifError,
+        image_width: unsafe extern "C" fn(*const HeifImage, c_int) -> c_int,
+        image_height: unsafe extern "C" fn(*const HeifImage, c_int) -> c_int,
+        plane_readonly: unsafe extern "C" fn(*const HeifImage, c_int, *mut c_int) -> *const u8,
+        image_release: unsafe extern "C" fn(*const HeifImage),
+        _lib: Library,
+    }
+
+    static LIBHEIF: OnceLock<Option<LibHeif>> = OnceLock::new();
+
+    /// The process-wide libheif, or None when the library is missing
+    pub(super) fn instance() -> Option<&'static LibHeif> {
+        LIBHEIF
+            .get_or_init(|| {
+                let mut candidates: Vec<PathBuf> = Vec::new();
+                if let Some(dir) = std::env::var_os(LIBRARY_DIR_ENV) {
+                    candidates.push(PathBuf::from(dir).join(libloading::library_filename("heif")));
+                }
+                candidates.push(libloading::library_filename("heif").into());
+                candidates.push("libheif.so.1".into());
+                candidates.push("libheif.1.dylib".into());
+
+                let lib = candidates.iter().find_map(|name| {
+                    // SAFETY: loading libheif runs no initialisers with
+                    // preconditions on our side
+                    unsafe { Library::new(name) }.ok()
+                });
+                match lib.map(LibHeif::bind) {
+                    Some(Ok(lib)) => Some(lib),
+                    Some(Err(e)) => {
+                        tracing::warn!("libheif found but unusable: {:#}", e);
+                        None
+                    }
+                    None => {
+                        tracing::info!("libheif not found; HEIC previews are unavailable");
+                        None
+                    }
+                }
+            })
+            .as_ref()
+    }
+
+    impl LibHeif {
+        fn bind(lib: Library) -> Result<Self> {
+            // SAFETY: the signatures match libheif's public C API (heif.h),
+            // and the pointers are kept alongside the library they came from
+            unsafe {
+                Ok(Self {
+                    context_alloc: *lib.get(b"heif_context_alloc\0")?,
+                    context_free: *lib.get(b"heif_context_free\0")?,
+                    read_from_file: *lib.get(b"heif_context_read_from_file\0")?,
+                    primary_handle: *lib.get(b"heif_context_get_primary_image_handle\0")?,
+                    handle_release: *lib.get(b"heif_image_handle_release\0")?,
+                    decode_image: *lib.get(b"heif_decode_image\0")?,
+                    image_width: *lib.get(b"heif_image_get_width\0")?,
+                    image_height: *lib.get(b"heif_image_get_height\0")?,
+                    plane_readonly: *lib.get(b"heif_image_get_plane_readonly\0")?,
+                    image_release: *lib.get(b"heif_image_release\0")?,
+                    _lib: lib,
+                })
+            }
+        }
+
+        pub(super) fn decode_primary(&self, path: &Path) -> Result<DynamicImage> {
+            let c_path = CString::new(
+                path.to_str()
+                    .with_context(|| format!("Non-UTF-8 path: {}", path.display()))?,
+            )?;
+            // SAFETY: every object libheif hands out is released exactly once
+            // below, after the last use of anything borrowed from it
+            unsafe {
+                let ctx = (self.context_alloc)();
+                if ctx.is_null() {
+                    bail!("libheif could not allocate a context");
+                }
+                let result = self.decode_in_context(ctx, &c_path);
+                (self.context_free)(ctx);
+                result.with_context(|| format!("Failed to decode HEIF: {}", path.display()))
+            }
+        }
+
+        unsafe fn decode_in_context(
+            &self,
+            ctx: *mut HeifContext,
+            path: &CStr,
+        ) -> Result<DynamicImage> {
+            check((self.read_from_file)(ctx, path.as_ptr(), std::ptr::null()))?;
+            let mut handle = std::ptr::null_mut();
+            check((self.primary_handle)(ctx, &mut handle))?;
+
+            let mut image = std::ptr::null_mut();
+            let decoded = check((self.decode_image)(
+                handle,
+                &mut image,
+                COLORSPACE_RGB,
+                CHROMA_INTERLEAVED_RGB,
+                std::ptr::null(),
+            ))
+            .and_then(|()| self.copy_rgb(image));
+            if !image.is_null() {
+                (self.image_release)(image);
+            }
+            (self.handle_release)(handle);
+            decoded
+        }
+
+        unsafe fn copy_rgb(&self, image: *const HeifImage) -> Result<DynamicImage> {
+            let width = (self.image_width)(image, CHANNEL_INTERLEAVED);
+            let height = (self.image_height)(image, CHANNEL_INTERLEAVED);
+            let mut stride: c_int = 0;
+            let plane = (self.plane_readonly)(image, CHANNEL_INTERLEAVED, &mut stride);
+            if plane.is_null() || width <= 0 || height <= 0 || stride < width * 3 {
+                bail!("libheif returned no interleaved RGB plane");
+            }
+
+            let row = width as usize * 3;
+            let mut pixels = Vec::with_capacity(row * height as usize);
+            for y in 0..height as usize {
+                let line = std::slice::from_raw_parts(plane.add(y * stride as usize), row);
+                pixels.extend_from_slice(line);
+            }
+            RgbImage::from_raw(width as u32, height as u32, pixels)
+                .map(DynamicImage::ImageRgb8)
+                .context("Decoded HEIF plane has the wrong size")
+        }
+    }
+
+    fn check(err: HeifError) -> Result<()> {
+        if err.code == 0 {
+            return Ok(());
+        }
+        let message = if err.message.is_null() {
+            String::new()
+        } else {
+            // SAFETY: libheif error messages are static NUL-terminated strings
+            unsafe { CStr::from_ptr(err.message) }
+                .to_string_lossy()
+                .into_owned()
+        };
+        bail!("libheif error {}.{}: {}", err.code, err.subcode, message)
+    }
+}
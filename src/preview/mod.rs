@@ -10,6 +10,17 @@ use image::imageops::FilterType;
 use image::{DynamicImage, ImageFormat};
 use parking_lot::RwLock;
 use rayon::prelude::*;
+use serde::Serialize;
+
+mod document;
+mod heif;
+mod raw;
+mod waveform;
+
+pub use document::is_document_previewable;
+pub use heif::is_heif;
+pub use raw::is_raw;
+pub use waveform::is_waveform_previewable;
 
 /// Cache for generated thumbnails
 type ThumbnailCache = Arc<RwLock<std::collections::HashMap<String, PathBuf>>>;
@@ -57,8 +68,7 @@ impl ThumbnailGenerator {
         }
 
         // Load image
-        let img = image::open(source)
-            .with_context(|| format!("Failed to open image: {}", source.display()))?;
+        let img = load_source(source)?;
 
         // Generate small thumbnail first (64x64) - fast preview
         let small_thumb = self.resize_image(&img, small_size);
@@ -88,8 +98,7 @@ impl ThumbnailGenerator {
         }
 
         // Load and resize
-        let img = image::open(source)
-            .with_context(|| format!("Failed to open image: {}", source.display()))?;
+        let img = load_source(source)?;
 
         let thumb = self.resize_image(&img, size);
         let thumb_path = self.thumbnail_path(source, size);
@@ -125,9 +134,11 @@ impl ThumbnailGenerator {
             std::fs::create_dir_all(parent)?;
         }
 
-        // Save as JPEG with quality 85 (good balance of size/quality)
+        // Save as JPEG with quality 85 (good balance of size/quality), or
+        // PNG for waveforms whose hard edges JPEG would smear
+        let format = ImageFormat::from_path(path).unwrap_or(ImageFormat::Jpeg);
         let mut output = std::fs::File::create(path)?;
-        img.write_to(&mut output, ImageFormat::Jpeg)?;
+        img.write_to(&mut output, format)?;
 
         Ok(())
     }
@@ -141,7 +152,12 @@ impl ThumbnailGenerator {
     /// Get thumbnail path for source and size
     fn thumbnail_path(&self, source: &Path, size: u32) -> PathBuf {
         let key = self.cache_key(source, size);
-        self.cache_dir.join(format!("{}.jpg", key))
+        let ext = if is_waveform_previewable(source) {
+            "png"
+        } else {
+            "jpg"
+        };
+        self.cache_dir.join(format!("{}.{}", key, ext))
     }
 
     /// Get cache directory
@@ -161,20 +177,15 @@ impl ThumbnailGenerator {
 
     /// Generate progressive thumbnails at multiple sizes in one pass
     ///
-    /// Loads the image once and creates all requested sizes, applying EXIF rotation.
+    /// Loads the image at most once (not at all when every size is cached) and
+    /// creates all requested sizes, applying EXIF rotation.
     /// Returns paths for each requested size.
     pub fn generate_progressive_multi(&self, source: &Path, sizes: &[u32]) -> Result<Vec<PathBuf>> {
         if sizes.is_empty() {
             return Ok(Vec::new());
         }
 
-        // Load image once
-        let img = image::open(source)
-            .with_context(|| format!("Failed to open image: {}", source.display()))?;
-
-        // Apply EXIF rotation
-        let img = self.apply_exif_rotation(source, img);
-
+        let mut img = None;
         let mut paths = Vec::with_capacity(sizes.len());
 
         // Sort sizes ascending so we can generate small first (faster preview)
@@ -182,21 +193,24 @@ impl ThumbnailGenerator {
         sorted_sizes.sort_unstable();
 
         for &size in &sorted_sizes {
-            let cache_key = self.cache_key(source, size);
-
-            // Check cache
-            if let Some(cached) = self.cache.read().get(&cache_key) {
-                if cached.exists() {
-                    paths.push(cached.clone());
-                    continue;
-                }
+            // Check the memory and disk caches
+            if let Some(cached) = self.get_cached(source, size) {
+                paths.push(cached);
+                continue;
             }
 
-            let thumb = self.resize_image(&img, size);
+            // Load image once, applying EXIF rotation
+            let img = match &mut img {
+                Some(img) => img,
+                None => img.insert(self.apply_exif_rotation(source, load_source(source)?)),
+            };
+            let thumb = self.resize_image(img, size);
             let thumb_path = self.thumbnail_path(source, size);
             self.save_thumbnail(&thumb, &thumb_path)?;
 
-            self.cache.write().insert(cache_key, thumb_path.clone());
+            self.cache
+                .write()
+                .insert(self.cache_key(source, size), thumb_path.clone());
             paths.push(thumb_path);
         }
 
@@ -215,6 +229,10 @@ impl ThumbnailGenerator {
 
     /// Read EXIF orientation and apply rotation/flip to correct image orientation
     fn apply_exif_rotation(&self, source: &Path, img: DynamicImage) -> DynamicImage {
+        // libheif already applies the container's rotation and mirroring
+        if is_heif(source) {
+            return img;
+        }
         let orientation = read_exif_orientation(source);
         match orientation {
             // 1 = normal, no rotation needed
@@ -265,7 +283,7 @@ impl Default for ThumbnailGenerator {
 }
 
 /// Preview information for display
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct PreviewInfo {
     /// Original file path
     pub source: PathBuf,
@@ -302,6 +320,24 @@ fn read_exif_orientation(path: &Path) -> u32 {
         .unwrap_or(1)
 }
 
+/// Decode an image, the first page of a previewable document, or draw the
+/// waveform of an audio file
+fn load_source(source: &Path) -> Result<DynamicImage> {
+    if is_waveform_previewable(source) {
+        return waveform::render_waveform(source);
+    }
+    if is_document_previewable(source) {
+        return document::load_first_page(source);
+    }
+    if is_raw(source) {
+        return raw::load_embedded_preview(source);
+    }
+    if is_heif(source) {
+        return heif::load_primary_image(source);
+    }
+    image::open(source).with_context(|| format!("Failed to open image: {}", source.display()))
+}
+
 /// Check if a file is previewable (image)
 pub fn is_previewable(path: &Path) -> bool {
     let ext = path
@@ -311,8 +347,20 @@ pub fn is_previewable(path: &Path) -> bool {
 
     matches!(
         ext.as_str(),
-        "jpg" | "jpeg" | "png" | "gif" | "webp" | "bmp" | "ico" | "tiff" | "tif"
-    )
+        "jpg"
+            | "jpeg"
+            | "png"
+            | "gif"
+            | "webp"
+            | "bmp"
+            | "ico"
+            | "tiff"
+            | "tif"
+            | "cr2"
+            | "nef"
+            | "arw"
+            | "dng"
+    ) || (cfg!(feature = "heif") && is_heif(path))
 }
 
 #[cfg(test)]
@@ -338,6 +386,7 @@ mod tests {
         assert!(is_previewable(&PathBuf::from("test.PNG")));
         assert!(!is_previewable(&PathBuf::from("test.txt")));
         assert!(!is_previewable(&PathBuf::from("test.mp4")));
+        assert!(is_previewable(&PathBuf::from("IMG_0001.CR2")));
     }
 
     #[test]
@@ -374,6 +423,14 @@ mod tests {
         for path in &paths {
             assert!(path.exists());
         }
+
+        // Cached sizes are served without decoding the source again
+        std::fs::remove_file(&img_path).unwrap();
+        assert_eq!(
+            gen.generate_progressive_multi(&img_path, &sizes).unwrap(),
+            paths
+        );
+        assert!(gen.generate_progressive_multi(&img_path, &[48]).is_err());
     }
 
     #[test]
@@ -0,0 +1,64 @@
+//! Embedded JPEG previews of camera RAW files (CR2/NEF/ARW/DNG)
+//!
+//! Decoding sensor data is out of scope; every RAW format also stores a
+//! camera-rendered JPEG, which is what gets thumbnailed.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use image::{DynamicImage, ImageFormat};
+
+use crate::carve::tiff;
+
+/// Check if a file is a TIFF-based camera RAW
+pub fn is_raw(path: &Path) -> bool {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    matches!(ext.as_str(), "cr2" | "nef" | "arw" | "dng")
+}
+
+/// Decode the largest embedded JPEG preview that the image decoder accepts
+/// (DNG lossless-JPEG tiles are skipped in favour of the baseline preview)
+pub fn load_embedded_preview(path: &Path) -> Result<DynamicImage> {
+    let file = std::fs::File::open(path)
+        .with_context(|| format!("Failed to open RAW file: {}", path.display()))?;
+    // SAFETY: read-only mapping of a file nothing else in the process writes
+    let data = unsafe { memmap2::Mmap::map(&file) }
+        .with_context(|| format!("Failed to map RAW file: {}", path.display()))?;
+
+    let layout = tiff::parse(&data)
+        .with_context(|| format!("Not a TIFF-based RAW file: {}", path.display()))?;
+    layout
+        .jpegs
+        .iter()
+        .filter_map(|j| data.get(j.offset as usize..(j.offset + j.length) as usize))
+        .find_map(|bytes| image::load_from_memory_with_format(bytes, ImageFormat::Jpeg).ok())
+        .with_context(|| format!("No embedded preview in {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::carve::tiff::tests::sample_raw;
+
+    #[test]
+    fn test_nef_embedded_preview() {
+        let preview = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(
+            48,
+            32,
+            image::Rgb([10, 120, 200]),
+        ));
+        let mut jpeg = std::io::Cursor::new(Vec::new());
+        preview.write_to(&mut jpeg, ImageFormat::Jpeg).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("DSC_0001.NEF");
+        std::fs::write(&path, sample_raw("NIKON CORPORATION", jpeg.get_ref())).unwrap();
+
+        assert!(is_raw(&path));
+        let img = load_embedded_preview(&path).unwrap();
+        assert_eq!((img.width(), img.height()), (48, 32));
+    }
+}
@@ -0,0 +1,204 @@
+//! Waveform previews of audio files (WAV/MP3/FLAC)
+//!
+//! The audio is decoded once, folded into per-column min/max peaks and drawn
+//! as a small waveform image, so recovered audio gets a recognisable
+//! thumbnail instead of a generic icon. Truncated or partly corrupt files
+//! (common after carving) render whatever decoded before the damage.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use image::{DynamicImage, Rgb, RgbImage};
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as AudioError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+/// Rendered waveform size; thumbnails scale it down keeping the aspect ratio
+pub const WAVEFORM_WIDTH: u32 = 512;
+pub const WAVEFORM_HEIGHT: u32 = 128;
+
+/// Frames folded into one peak while decoding
+const PEAK_BLOCK: usize = 256;
+
+const BACKGROUND: Rgb<u8> = Rgb([24, 26, 32]);
+const CENTER_LINE: Rgb<u8> = Rgb([60, 64, 74]);
+const WAVE: Rgb<u8> = Rgb([86, 182, 255]);
+
+/// Check if a file is audio that gets a waveform preview
+pub fn is_waveform_previewable(path: &Path) -> bool {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    matches!(ext.as_str(), "wav" | "mp3" | "flac")
+}
+
+/// Decode an audio file and draw its waveform
+pub fn render_waveform(path: &Path) -> Result<DynamicImage> {
+    let peaks = decode_peaks(path)
+        .with_context(|| format!("Failed to decode audio: {}", path.display()))?;
+    if peaks.is_empty() {
+        anyhow::bail!("No audio samples decoded from {}", path.display());
+    }
+    Ok(DynamicImage::ImageRgb8(draw(
+        &peaks,
+        WAVEFORM_WIDTH,
+        WAVEFORM_HEIGHT,
+    )))
+}
+
+/// Min/max sample (across all channels) of every PEAK_BLOCK frames
+fn decode_peaks(path: &Path) -> Result<Vec<(f32, f32)>> {
+    let file = std::fs::File::open(path)?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+    let mut format = symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )?
+        .format;
+
+    let track = format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .context("No audio track")?;
+    let track_id = track.id;
+    let mut decoder =
+        symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+
+    let mut peaks = Vec::new();
+    let (mut lo, mut hi, mut frames) = (0.0f32, 0.0f32, 0usize);
+    let mut samples: Option<SampleBuffer<f32>> = None;
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            // End of stream, or the file is cut short
+            Err(AudioError::IoError(_)) | Err(AudioError::ResetRequired) => break,
+            Err(e) => return Err(e.into()),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // Skip damaged packets and keep going
+            Err(AudioError::DecodeError(_)) => continue,
+            Err(AudioError::IoError(_)) => break,
+            Err(e) => return Err(e.into()),
+        };
+
+        let channels = decoded.spec().channels.count().max(1);
+        let buf = match &mut samples {
+            Some(buf) if buf.capacity() >= decoded.capacity() * channels => buf,
+            _ => samples.insert(SampleBuffer::new(
+                decoded.capacity() as u64,
+                *decoded.spec(),
+            )),
+        };
+        buf.copy_interleaved_ref(decoded);
+
+        for frame in buf.samples().chunks(channels) {
+            for &s in frame {
+                lo = lo.min(s);
+                hi = hi.max(s);
+            }
+            frames += 1;
+            if frames == PEAK_BLOCK {
+                peaks.push((lo, hi));
+                (lo, hi, frames) = (0.0, 0.0, 0);
+            }
+        }
+    }
+    if frames > 0 {
+        peaks.push((lo, hi));
+    }
+    Ok(peaks)
+}
+
+/// Draw peaks as one vertical bar per column around a centre line
+fn draw(peaks: &[(f32, f32)], width: u32, height: u32) -> RgbImage {
+    let mut img = RgbImage::from_pixel(width, height, BACKGROUND);
+    let mid = height / 2;
+    for x in 0..width {
+        img.put_pixel(x, mid, CENTER_LINE);
+    }
+
+    let half = (height - 1) as f32 / 2.0;
+    let to_y = |s: f32| ((1.0 - s.clamp(-1.0, 1.0)) * half).round() as u32;
+    for x in 0..width {
+        // Columns cover an even share of the peaks (repeating when the
+        // clip is shorter than the image is wide)
+        let start = x as usize * peaks.len() / width as usize;
+        let end = ((x as usize + 1) * peaks.len() / width as usize).max(start + 1);
+        let (lo, hi) = peaks[start..end.min(peaks.len())]
+            .iter()
+            .fold((0.0f32, 0.0f32), |(lo, hi), &(l, h)| (lo.min(l), hi.max(h)));
+        for y in to_y(hi)..=to_y(lo) {
+            img.put_pixel(x, y, WAVE);
+        }
+    }
+    img
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 16-bit mono PCM WAV whose amplitude ramps from silence to full scale
+    fn ramp_wav(frames: u32) -> Vec<u8> {
+        let data_len = frames * 2;
+        let mut out = b"RIFF".to_vec();
+        out.extend_from_slice(&(36 + data_len).to_le_bytes());
+        out.extend_from_slice(b"WAVEfmt ");
+        out.extend_from_slice(&16u32.to_le_bytes());
+        out.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        out.extend_from_slice(&1u16.to_le_bytes()); // mono
+        out.extend_from_slice(&8000u32.to_le_bytes());
+        out.extend_from_slice(&16000u32.to_le_bytes());
+        out.extend_from_slice(&2u16.to_le_bytes());
+        out.extend_from_slice(&16u16.to_le_bytes());
+        out.extend_from_slice(b"data");
+        out.extend_from_slice(&data_len.to_le_bytes());
+        for i in 0..frames {
+            let amplitude = i as f32 / frames as f32;
+            let s = (i as f32 * 0.3).sin() * amplitude * i16::MAX as f32;
+            out.extend_from_slice(&(s as i16).to_le_bytes());
+        }
+        out
+    }
+
+    #[test]
+    fn test_wav_waveform() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("recovered.wav");
+        std::fs::write(&path, ramp_wav(32_000)).unwrap();
+        assert!(is_waveform_previewable(&path));
+
+        let img = render_waveform(&path).unwrap().to_rgb8();
+        assert_eq!(img.dimensions(), (WAVEFORM_WIDTH, WAVEFORM_HEIGHT));
+        let extent = |x: u32| {
+            (0..WAVEFORM_HEIGHT)
+                .filter(|&y| *img.get_pixel(x, y) == WAVE)
+                .count()
+        };
+        assert!(extent(2) < extent(WAVEFORM_WIDTH / 2));
+        assert!(extent(WAVEFORM_WIDTH / 2) < extent(WAVEFORM_WIDTH - 2));
+        assert!(extent(WAVEFORM_WIDTH - 2) > WAVEFORM_HEIGHT as usize * 3 / 4);
+
+        // A truncated copy still renders what was decodable
+        let truncated = dir.path().join("truncated.wav");
+        std::fs::write(&truncated, &ramp_wav(32_000)[..20_000]).unwrap();
+        assert!(render_waveform(&truncated).is_ok());
+    }
+}
@@ -0,0 +1,232 @@
+//! Append-only custody event log
+//!
+//! Every step that touches the evidence (indexing, export, bad sectors hit
+//! while copying, verification runs, hand-overs) is recorded in the chain of
+//! custody. Each event stores the hash of the one before it, so removing,
+//! reordering or editing an event breaks every hash after it:
+//!
+//! ```text
+//! hash = blake3("E" || json([seq, at, kind, actor, detail, prev_hash]))
+//! ```
+//!
+//! A signature covers the events recorded before signing; events appended
+//! later still chain from the signed ones.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::ChainOfCustody;
+
+/// `prev_hash` of the first event
+pub const CUSTODY_LOG_GENESIS: &str =
+    "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// What happened to the evidence
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CustodyEventKind {
+    IndexStarted,
+    ExportStarted,
+    BadSector,
+    ExportCompleted,
+    VerifyRun,
+    ManifestTransferred,
+    ImagingStarted,
+    ImagingCompleted,
+    Note,
+}
+
+impl CustodyEventKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::IndexStarted => "Index started",
+            Self::ExportStarted => "Export started",
+            Self::BadSector => "Bad sector",
+            Self::ExportCompleted => "Export completed",
+            Self::VerifyRun => "Verify run",
+            Self::ManifestTransferred => "Transferred",
+            Self::ImagingStarted => "Imaging started",
+            Self::ImagingCompleted => "Imaging completed",
+            Self::Note => "Note",
+        }
+    }
+}
+
+/// One entry of the custody log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustodyEvent {
+    /// Position in the log, starting at 0
+    pub seq: u64,
+    pub at: DateTime<Utc>,
+    pub kind: CustodyEventKind,
+    /// Who recorded the event (user@host)
+    pub actor: String,
+    pub detail: String,
+    /// Hash of the previous event ([`CUSTODY_LOG_GENESIS`] for the first)
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+impl CustodyEvent {
+    /// Hash over every field except `hash` itself
+    pub fn compute_hash(&self) -> String {
+        let fields = (
+            self.seq,
+            self.at,
+            self.kind,
+            &self.actor,
+            &self.detail,
+            &self.prev_hash,
+        );
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(b"E");
+        hasher.update(&serde_json::to_vec(&fields).unwrap_or_default());
+        hasher.finalize().to_hex().to_string()
+    }
+}
+
+impl ChainOfCustody {
+    /// Append an event that happens now
+    pub fn record_event(&mut self, kind: CustodyEventKind, detail: impl Into<String>) {
+        self.record_event_at(Utc::now(), kind, detail);
+    }
+
+    /// Append an event that happened at `at` (e.g. an index built earlier)
+    pub fn record_event_at(
+        &mut self,
+        at: DateTime<Utc>,
+        kind: CustodyEventKind,
+        detail: impl Into<String>,
+    ) {
+        let prev_hash = self
+            .events
+            .last()
+            .map_or_else(|| CUSTODY_LOG_GENESIS.to_string(), |e| e.hash.clone());
+        let mut event = CustodyEvent {
+            seq: self.events.len() as u64,
+            at,
+            kind,
+            actor: self.operator.clone(),
+            detail: detail.into(),
+            prev_hash,
+            hash: String::new(),
+        };
+        event.hash = event.compute_hash();
+        self.events.push(event);
+    }
+}
+
+/// Result of checking the custody log's hash chain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustodyLogCheck {
+    pub events: usize,
+    /// Events covered by the manifest signature
+    pub signed: usize,
+    pub valid: bool,
+    /// First event whose link or hash does not match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub broken_at: Option<u64>,
+}
+
+/// Check that every event links to the one before it and hashes correctly
+pub fn verify_custody_log(custody: &ChainOfCustody) -> CustodyLogCheck {
+    let mut prev_hash = CUSTODY_LOG_GENESIS;
+    let mut broken_at = None;
+    for (i, event) in custody.events.iter().enumerate() {
+        if event.seq != i as u64
+            || event.prev_hash != prev_hash
+            || event.hash != event.compute_hash()
+        {
+            broken_at = Some(i as u64);
+            break;
+        }
+        prev_hash = &event.hash;
+    }
+    CustodyLogCheck {
+        events: custody.events.len(),
+        signed: custody
+            .signature
+            .as_ref()
+            .map_or(0, |s| s.events_signed.min(custody.events.len())),
+        valid: broken_at.is_none(),
+        broken_at,
+    }
+}
+
+/// Format the custody log as a timeline
+pub fn format_custody_log(custody: &ChainOfCustody) -> String {
+    let check = verify_custody_log(custody);
+    let mut out = String::new();
+    for event in &custody.events {
+        let marker = match check.broken_at {
+            Some(broken) if event.seq >= broken => "!",
+            _ if (event.seq as usize) < check.signed => "✓",
+            _ => " ",
+        };
+        out.push_str(&format!(
+            "  {} {:>3}  {}  {:<17} {}  [{}]\n",
+            marker,
+            event.seq,
+            event.at.format("%Y-%m-%d %H:%M:%S UTC"),
+            event.kind.label(),
+            event.detail,
+            event.actor
+        ));
+    }
+    out.push('\n');
+    match check.broken_at {
+        None => out.push_str(&format!(
+            "  Custody log:    VALID ({} events, {} signed)\n",
+            check.events, check.signed
+        )),
+        Some(seq) => out.push_str(&format!(
+            "  Custody log:    BROKEN at event {} (edited, removed or reordered)\n",
+            seq
+        )),
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custody_log_hash_chain() {
+        let mut custody = ChainOfCustody::from_environment();
+        assert!(verify_custody_log(&custody).valid);
+
+        custody.record_event_at(
+            custody.started_at - chrono::Duration::hours(1),
+            CustodyEventKind::IndexStarted,
+            "/mnt/evidence",
+        );
+        custody.record_event(CustodyEventKind::ExportStarted, "/out");
+        custody.record_event(
+            CustodyEventKind::BadSector,
+            "photo.jpg: 2 sectors zero-filled",
+        );
+        custody.record_event(CustodyEventKind::ExportCompleted, "3 files");
+        let check = verify_custody_log(&custody);
+        assert!(check.valid);
+        assert_eq!(check.events, 4);
+        assert_eq!(custody.events[0].prev_hash, CUSTODY_LOG_GENESIS);
+        assert_eq!(custody.events[2].prev_hash, custody.events[1].hash);
+
+        let mut edited = custody.clone();
+        edited.events[2].detail = "no bad sectors".into();
+        assert_eq!(verify_custody_log(&edited).broken_at, Some(2));
+
+        let mut removed = custody.clone();
+        removed.events.remove(1);
+        assert_eq!(verify_custody_log(&removed).broken_at, Some(1));
+
+        // Rehashing an edited event still breaks the link from the next one
+        let mut rehashed = custody.clone();
+        rehashed.events[1].detail = "/elsewhere".into();
+        rehashed.events[1].hash = rehashed.events[1].compute_hash();
+        assert_eq!(verify_custody_log(&rehashed).broken_at, Some(2));
+
+        assert!(format_custody_log(&custody).contains("VALID (4 events, 0 signed)"));
+    }
+}
@@ -0,0 +1,152 @@
+//! Self-contained verifier kit
+//!
+//! Drops everything a recipient needs to check an export without installing
+//! Diamond Drill: a `b3sum`-compatible checksum list, a POSIX shell script
+//! that checks every file plus the root hash, and the JSON schema of the
+//! manifest itself.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use super::merkle::{sorted_entries, MERKLE_VERSION};
+use super::ProofManifest;
+
+/// JSON schema describing the proof manifest format
+pub const MANIFEST_SCHEMA: &str = include_str!("manifest.schema.json");
+
+/// File name of the schema written next to the manifest
+pub const SCHEMA_FILE_NAME: &str = "proof-manifest.schema.json";
+
+/// Paths of the files written by [`write_verifier_kit`]
+#[derive(Debug, Clone)]
+pub struct VerifierKit {
+    /// `b3sum --check` compatible checksum list
+    pub checksums: PathBuf,
+    /// Shell script that runs the checks
+    pub script: PathBuf,
+    /// Manifest JSON schema
+    pub schema: PathBuf,
+}
+
+/// Write the verifier kit into the directory containing `manifest_path`.
+///
+/// Destination paths under that directory are written relative to it, so
+/// the export folder can be moved or copied as a whole.
+pub fn write_verifier_kit(manifest: &ProofManifest, manifest_path: &Path) -> Result<VerifierKit> {
+    let dir = manifest_path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let stem = manifest_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "manifest".to_string());
+
+    let checksums = dir.join(format!("{}.b3", stem));
+    let script = dir.join(format!("{}.verify.sh", stem));
+    let schema = dir.join(SCHEMA_FILE_NAME);
+
+    // Same ordering as compute_root_hash, so the script can rebuild the
+    // root hash from the first column.
+    let mut list = String::new();
+    for entry in sorted_entries(&manifest.entries) {
+        list.push_str(&format!(
+            "{}  {}\n",
+            entry.blake3_hash,
+            relative_dest(&entry.dest_path, dir)
+        ));
+    }
+
+    let checksums_name = format!("{}.b3", stem);
+    std::fs::write(&checksums, list)
+        .with_context(|| format!("Failed to write {}", checksums.display()))?;
+    std::fs::write(&script, render_script(manifest, &checksums_name))
+        .with_context(|| format!("Failed to write {}", script.display()))?;
+    std::fs::write(&schema, MANIFEST_SCHEMA)
+        .with_context(|| format!("Failed to write {}", schema.display()))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755))?;
+    }
+
+    Ok(VerifierKit {
+        checksums,
+        script,
+        schema,
+    })
+}
+
+fn relative_dest(dest_path: &str, dir: &Path) -> String {
+    Path::new(dest_path)
+        .strip_prefix(dir)
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|_| dest_path.to_string())
+}
+
+fn render_script(manifest: &ProofManifest, checksums_name: &str) -> String {
+    let root_command = if manifest.entries.is_empty() {
+        "printf 'empty' | b3sum --no-names".to_string()
+    } else if manifest.version >= MERKLE_VERSION {
+        merkle_root_command(checksums_name)
+    } else {
+        format!(
+            "cut -c1-64 \"{}\" | tr -d '\\n' | b3sum --no-names",
+            checksums_name
+        )
+    };
+
+    format!(
+        r#"#!/bin/sh
+# Standalone verifier for a {tool} {version} export ({files} files).
+# Requires b3sum: https://github.com/BLAKE3-team/BLAKE3 (cargo install b3sum)
+set -eu
+cd "$(dirname "$0")"
+
+if ! command -v b3sum >/dev/null 2>&1; then
+    echo "b3sum not found; install it with: cargo install b3sum" >&2
+    exit 2
+fi
+
+EXPECTED_ROOT="{root}"
+
+echo "Checking file hashes..."
+b3sum --check --quiet "{checksums}" || {{ echo "VERIFICATION FAILED"; exit 1; }}
+
+ROOT=$({root_command})
+if [ "$ROOT" != "$EXPECTED_ROOT" ]; then
+    echo "Root hash: INVALID (expected $EXPECTED_ROOT, got $ROOT)"
+    echo "VERIFICATION FAILED"
+    exit 1
+fi
+
+echo "Root hash: VALID"
+echo "VERIFICATION PASSED"
+"#,
+        tool = manifest.tool,
+        version = manifest.tool_version,
+        files = manifest.total_files,
+        root = manifest.root_hash,
+        checksums = checksums_name,
+        root_command = root_command,
+    )
+}
+
+/// Shell pipeline that rebuilds the Merkle root (see the `merkle` module)
+/// one level at a time, promoting an odd last node unchanged
+fn merkle_root_command(checksums_name: &str) -> String {
+    format!(
+        r#"
+    level=$(cut -c1-64 "{checksums}" | while read -r h; do printf 'L%s' "$h" | b3sum --no-names; done)
+    while [ "$(printf '%s\n' "$level" | wc -l)" -gt 1 ]; do
+        level=$(printf '%s\n' "$level" | paste -d' ' - - | while read -r l r; do
+            if [ -n "$r" ]; then printf 'N%s%s' "$l" "$r" | b3sum --no-names; else echo "$l"; fi
+        done)
+    done
+    echo "$level"
+"#,
+        checksums = checksums_name
+    )
+}
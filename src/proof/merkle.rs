@@ -0,0 +1,358 @@
+//! Merkle tree over manifest entries and per-file inclusion proofs
+//!
+//! Leaves are the entries' blake3 hashes in manifest order (sorted by source
+//! then destination path). Hashes are combined as lowercase hex text so the
+//! shell verifier can rebuild the tree with nothing but `b3sum`:
+//!
+//! ```text
+//! leaf = blake3("L" || file_hash)
+//! node = blake3("N" || left || right)
+//! ```
+//!
+//! An odd node at the end of a level is promoted unchanged. A proof for one
+//! file is the list of sibling hashes from its leaf up to the root, so a
+//! single file can be checked against a (possibly signed) root without
+//! rehashing the rest of the export.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    compute_file_digests_sync, digest_mismatch, verify_signature, ProofEntry, ProofManifest,
+    SignatureCheck, SignatureStatus, VerifyingKey, PROOF_VERSION,
+};
+
+/// First manifest version whose root hash is a Merkle root
+pub const MERKLE_VERSION: u32 = 2;
+
+/// Root of an empty manifest
+pub(super) fn empty_root() -> String {
+    blake3::hash(b"empty").to_hex().to_string()
+}
+
+/// Entries in tree order
+pub(super) fn sorted_entries(entries: &[ProofEntry]) -> Vec<&ProofEntry> {
+    let mut sorted: Vec<&ProofEntry> = entries.iter().collect();
+    sorted.sort_by(|a, b| {
+        a.source_path
+            .cmp(&b.source_path)
+            .then_with(|| a.dest_path.cmp(&b.dest_path))
+    });
+    sorted
+}
+
+fn leaf_hash(file_hash: &str) -> String {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(b"L");
+    hasher.update(file_hash.as_bytes());
+    hasher.finalize().to_hex().to_string()
+}
+
+fn node_hash(left: &str, right: &str) -> String {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(b"N");
+    hasher.update(left.as_bytes());
+    hasher.update(right.as_bytes());
+    hasher.finalize().to_hex().to_string()
+}
+
+/// Every level of the tree, leaves first
+fn levels(entries: &[ProofEntry]) -> Vec<Vec<String>> {
+    let mut level: Vec<String> = sorted_entries(entries)
+        .iter()
+        .map(|e| leaf_hash(&e.blake3_hash))
+        .collect();
+    let mut levels = Vec::new();
+    while level.len() > 1 {
+        let next = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => node_hash(left, right),
+                [single] => single.clone(),
+                _ => unreachable!(),
+            })
+            .collect();
+        levels.push(std::mem::replace(&mut level, next));
+    }
+    levels.push(level);
+    levels
+}
+
+/// Merkle root of a set of entries
+pub(super) fn merkle_root(entries: &[ProofEntry]) -> String {
+    if entries.is_empty() {
+        return empty_root();
+    }
+    levels(entries)
+        .pop()
+        .and_then(|mut top| top.pop())
+        .unwrap_or_default()
+}
+
+/// Which side of the running hash a sibling sits on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// One step from a leaf towards the root
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProofStep {
+    pub side: Side,
+    pub hash: String,
+}
+
+/// Evidence that one file belongs to a manifest with a given root hash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InclusionProof {
+    /// Root hash of the manifest the file belongs to
+    pub root_hash: String,
+    /// Position of the file in tree order
+    pub leaf_index: usize,
+    /// Number of files in the manifest
+    pub leaf_count: usize,
+    /// The file's manifest entry
+    pub entry: ProofEntry,
+    /// Sibling hashes from the leaf up to the root
+    pub path: Vec<ProofStep>,
+}
+
+impl InclusionProof {
+    /// Root hash obtained by folding the path into the entry's leaf
+    pub fn computed_root(&self) -> String {
+        self.path
+            .iter()
+            .fold(leaf_hash(&self.entry.blake3_hash), |acc, step| {
+                match step.side {
+                    Side::Left => node_hash(&step.hash, &acc),
+                    Side::Right => node_hash(&acc, &step.hash),
+                }
+            })
+    }
+
+    /// Whether the path leads from the entry to `root_hash`
+    pub fn is_valid(&self) -> bool {
+        self.computed_root() == self.root_hash
+    }
+}
+
+/// Build the inclusion proof for an exported file (matched by its
+/// destination path)
+pub fn prove_file(manifest: &ProofManifest, file: &Path) -> Result<InclusionProof> {
+    anyhow::ensure!(
+        manifest.version >= MERKLE_VERSION,
+        "Manifest version {} has a flat root hash; inclusion proofs need version {} or later",
+        manifest.version,
+        MERKLE_VERSION
+    );
+    let sorted = sorted_entries(&manifest.entries);
+    let leaf_index = find_entry(&sorted, file)
+        .with_context(|| format!("{} is not listed in the manifest", file.display()))?;
+
+    let mut path = Vec::new();
+    let mut index = leaf_index;
+    for level in levels(&manifest.entries) {
+        if level.len() == 1 {
+            break;
+        }
+        let sibling = index ^ 1;
+        if let Some(hash) = level.get(sibling) {
+            path.push(ProofStep {
+                side: if sibling < index {
+                    Side::Left
+                } else {
+                    Side::Right
+                },
+                hash: hash.clone(),
+            });
+        }
+        index /= 2;
+    }
+
+    Ok(InclusionProof {
+        root_hash: manifest.root_hash.clone(),
+        leaf_index,
+        leaf_count: sorted.len(),
+        entry: sorted[leaf_index].clone(),
+        path,
+    })
+}
+
+/// Index (in tree order) of the entry whose destination is `file`
+fn find_entry(sorted: &[&ProofEntry], file: &Path) -> Option<usize> {
+    let wanted = file.to_string_lossy();
+    if let Some(i) = sorted.iter().position(|e| e.dest_path == wanted) {
+        return Some(i);
+    }
+    // Same file spelled differently (relative path, symlinked directory)
+    let canonical = std::fs::canonicalize(file).ok()?;
+    sorted
+        .iter()
+        .position(|e| std::fs::canonicalize(&e.dest_path).is_ok_and(|p| p == canonical))
+}
+
+/// Result of checking a single exported file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SingleFileResult {
+    /// Destination path from the manifest
+    pub path: String,
+    pub expected_hash: String,
+    /// Hash of the file on disk
+    pub actual_hash: String,
+    /// File content matches its manifest entry
+    pub hash_valid: bool,
+    /// The entry is part of the manifest's root hash
+    pub inclusion_valid: bool,
+    /// Siblings needed to reach the root
+    pub proof_length: usize,
+    /// Signature check; absent for unsigned manifests when no trusted key
+    /// was given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<SignatureCheck>,
+}
+
+impl SingleFileResult {
+    pub fn is_clean(&self) -> bool {
+        self.hash_valid
+            && self.inclusion_valid
+            && self.signature.as_ref().is_none_or(SignatureCheck::is_ok)
+    }
+}
+
+/// Verify one exported file against a manifest: hash the file, check it
+/// against its entry and check the entry's inclusion proof against the
+/// root (and the signature, which covers the root)
+pub fn verify_single_file(
+    manifest: &ProofManifest,
+    file: &Path,
+    trusted: Option<&VerifyingKey>,
+) -> Result<SingleFileResult> {
+    anyhow::ensure!(
+        manifest.version <= PROOF_VERSION,
+        "Manifest version {} is newer than supported ({})",
+        manifest.version,
+        PROOF_VERSION
+    );
+    let proof = prove_file(manifest, file)?;
+    let (actual_hash, digests) =
+        compute_file_digests_sync(Path::new(&proof.entry.dest_path), &proof.entry.digests)?;
+
+    let signature = verify_signature(manifest, trusted)?;
+    let signature =
+        (signature.status != SignatureStatus::Unsigned || trusted.is_some()).then_some(signature);
+
+    Ok(SingleFileResult {
+        path: proof.entry.dest_path.clone(),
+        hash_valid: actual_hash == proof.entry.blake3_hash
+            && digest_mismatch(&proof.entry, &digests).is_none(),
+        expected_hash: proof.entry.blake3_hash.clone(),
+        actual_hash,
+        inclusion_valid: proof.is_valid(),
+        proof_length: proof.path.len(),
+        signature,
+    })
+}
+
+/// Format a SingleFileResult for human display
+pub fn format_single_file_result(result: &SingleFileResult) -> String {
+    let mut out = String::new();
+    out.push_str(if result.is_clean() {
+        "  VERIFICATION PASSED\n\n"
+    } else {
+        "  VERIFICATION FAILED\n\n"
+    });
+    out.push_str(&format!("  File:           {}\n", result.path));
+    out.push_str(&format!(
+        "  Content hash:   {}\n",
+        if result.hash_valid {
+            "VALID"
+        } else {
+            "MISMATCH"
+        }
+    ));
+    if !result.hash_valid {
+        out.push_str(&format!("    Expected: {}\n", result.expected_hash));
+        out.push_str(&format!("    Actual:   {}\n", result.actual_hash));
+    }
+    out.push_str(&format!(
+        "  Inclusion:      {} ({} sibling hashes)\n",
+        if result.inclusion_valid {
+            "VALID"
+        } else {
+            "INVALID"
+        },
+        result.proof_length
+    ));
+    if let Some(check) = &result.signature {
+        out.push_str(&format!(
+            "  Signature:      {}\n",
+            if check.is_ok() { "VALID" } else { "INVALID" }
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proof::{build_manifest, compute_file_hash_sync, ChainOfCustody};
+    use chrono::Utc;
+
+    #[test]
+    fn test_inclusion_proofs() {
+        let dir = tempfile::tempdir().unwrap();
+        // Odd count, so the last leaf is promoted on the way up
+        let entries: Vec<ProofEntry> = (0..5)
+            .map(|i| {
+                let path = dir.path().join(format!("file{}.txt", i));
+                std::fs::write(&path, format!("contents {}", i)).unwrap();
+                ProofEntry {
+                    source_path: format!("/source/file{}.txt", i),
+                    dest_path: path.to_string_lossy().to_string(),
+                    size: 10,
+                    blake3_hash: compute_file_hash_sync(&path).unwrap(),
+                    digests: Default::default(),
+                    exported_at: Utc::now(),
+                    bad_sector_notes: None,
+                    verified: true,
+                }
+            })
+            .collect();
+        let manifest = build_manifest(
+            Path::new("/source"),
+            dir.path(),
+            entries,
+            ChainOfCustody::from_environment(),
+        );
+        assert_eq!(manifest.version, MERKLE_VERSION);
+
+        for i in 0..5 {
+            let file = dir.path().join(format!("file{}.txt", i));
+            let proof = prove_file(&manifest, &file).unwrap();
+            assert_eq!(proof.leaf_index, i);
+            assert!(proof.path.len() <= 3);
+            assert!(proof.is_valid(), "proof for file{} must reach the root", i);
+        }
+
+        // A proof is useless for any other content or root
+        let file = dir.path().join("file2.txt");
+        let mut forged = prove_file(&manifest, &file).unwrap();
+        forged.entry.blake3_hash = blake3::hash(b"forged").to_hex().to_string();
+        assert!(!forged.is_valid());
+
+        let result = verify_single_file(&manifest, &file, None).unwrap();
+        assert!(result.is_clean());
+        std::fs::write(&file, "tampered").unwrap();
+        let result = verify_single_file(&manifest, &file, None).unwrap();
+        assert!(!result.hash_valid && result.inclusion_valid);
+
+        assert!(prove_file(&manifest, &dir.path().join("other.txt")).is_err());
+        let mut legacy = manifest.clone();
+        legacy.version = 1;
+        assert!(prove_file(&legacy, &file).is_err());
+    }
+}
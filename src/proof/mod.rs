@@ -1,7 +1,41 @@
 //! Proof Manifest module - Cryptographic chain-of-custody for exports
 //!
-//! Generates Blake3-based proof manifests with Merkle-like root hashes,
-//! chain-of-custody metadata, and offline verification capability.
+//! Generates Blake3-based proof manifests with Merkle root hashes (and
+//! per-file inclusion proofs), chain-of-custody metadata with a hash-chained
+//! event log, optional Ed25519 signatures and RFC 3161 timestamps, and
+//! offline verification capability.
+//!
+//! This module only depends on serde, blake3, chrono, anyhow, sha2, md-5 and
+//! ed25519-dalek so it stays available in the `verify-only` build (only
+//! requesting a new timestamp needs the `cli` feature).
+
+mod custody;
+mod kit;
+mod merkle;
+mod signing;
+mod timestamp;
+
+pub use custody::{
+    format_custody_log, verify_custody_log, CustodyEvent, CustodyEventKind, CustodyLogCheck,
+    CUSTODY_LOG_GENESIS,
+};
+pub use ed25519_dalek::{SigningKey, VerifyingKey};
+pub use kit::{write_verifier_kit, VerifierKit, MANIFEST_SCHEMA, SCHEMA_FILE_NAME};
+pub use merkle::{
+    format_single_file_result, prove_file, verify_single_file, InclusionProof, ProofStep, Side,
+    SingleFileResult, MERKLE_VERSION,
+};
+pub use signing::{
+    generate_key, key_fingerprint, load_signing_key, load_verifying_key, save_key_pair,
+    sign_manifest, verify_signature, ManifestSignature, SignatureCheck, SignatureStatus,
+    SIGNATURE_ALGORITHM,
+};
+#[cfg(feature = "cli")]
+pub use timestamp::request_timestamp;
+pub use timestamp::{
+    parse_token, root_imprint, verify_timestamp, ManifestTimestamp, TimestampCheck, TstInfo,
+    DEFAULT_TSA_URL, TIMESTAMP_HASH_ALGORITHM,
+};
 
 use std::collections::BTreeMap;
 use std::path::Path;
@@ -10,8 +44,9 @@ use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-/// Current proof manifest format version
-pub const PROOF_VERSION: u32 = 1;
+/// Current proof manifest format version (1 = flat root hash,
+/// 2 = Merkle root)
+pub const PROOF_VERSION: u32 = 2;
 
 /// Tool identification string
 pub const TOOL_NAME: &str = "Diamond Drill";
@@ -31,7 +66,8 @@ pub struct ProofManifest {
     pub source_root: String,
     /// Destination root path
     pub dest_root: String,
-    /// Merkle-like root hash (Blake3 of sorted entry hashes)
+    /// Merkle root over the entry hashes (version 1: Blake3 of the
+    /// concatenated sorted entry hashes)
     pub root_hash: String,
     /// Total files in manifest
     pub total_files: usize,
@@ -54,6 +90,10 @@ pub struct ProofEntry {
     pub size: u64,
     /// Blake3 hash of the file contents
     pub blake3_hash: String,
+    /// Additional digests of the contents keyed by algorithm (`md5`,
+    /// `sha256`), for tools and courts that do not accept Blake3
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub digests: BTreeMap<String, String>,
     /// When this file was exported
     pub exported_at: DateTime<Utc>,
     /// Notes about bad sectors (if any)
@@ -77,6 +117,15 @@ pub struct ChainOfCustody {
     pub completed_at: Option<DateTime<Utc>>,
     /// Export options summary
     pub options_used: BTreeMap<String, String>,
+    /// Hash-chained log of everything done to the evidence, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<CustodyEvent>,
+    /// RFC 3161 token for the root hash, added by `proof timestamp`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<ManifestTimestamp>,
+    /// Signature over the rest of the manifest, added by `proof sign`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<ManifestSignature>,
 }
 
 impl ChainOfCustody {
@@ -105,6 +154,9 @@ impl ChainOfCustody {
             started_at: Utc::now(),
             completed_at: None,
             options_used: BTreeMap::new(),
+            events: Vec::new(),
+            timestamp: None,
+            signature: None,
         }
     }
 }
@@ -128,12 +180,27 @@ pub struct VerifyResult {
     pub expected_root_hash: String,
     /// Computed root hash
     pub computed_root_hash: String,
+    /// Signature check; absent for unsigned manifests when no trusted key
+    /// was given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<SignatureCheck>,
+    /// Timestamp check, for timestamped manifests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<TimestampCheck>,
+    /// Custody log check, for manifests with custody events
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custody_log: Option<CustodyLogCheck>,
 }
 
 impl VerifyResult {
     /// Check if verification passed completely
     pub fn is_clean(&self) -> bool {
-        self.failed == 0 && self.missing == 0 && self.root_hash_valid
+        self.failed == 0
+            && self.missing == 0
+            && self.root_hash_valid
+            && self.signature.as_ref().is_none_or(SignatureCheck::is_ok)
+            && self.timestamp.as_ref().is_none_or(|t| t.imprint_matches)
+            && self.custody_log.as_ref().is_none_or(|c| c.valid)
     }
 }
 
@@ -161,13 +228,29 @@ pub enum TamperType {
     SizeChanged,
 }
 
-/// Compute a Merkle-like root hash from proof entries.
+/// Compute the Merkle root hash of proof entries.
 ///
-/// Algorithm: sort entries by source_path, concatenate their blake3 hashes
-/// in order, then Blake3 the concatenation.
+/// Entries are ordered by source (then destination) path, so the root does
+/// not depend on the order they were exported in. See [`prove_file`] for
+/// proving a single entry against the root.
 pub fn compute_root_hash(entries: &[ProofEntry]) -> String {
+    merkle::merkle_root(entries)
+}
+
+/// Root hash as computed for the manifest's format version
+pub fn manifest_root_hash(manifest: &ProofManifest) -> String {
+    if manifest.version >= MERKLE_VERSION {
+        compute_root_hash(&manifest.entries)
+    } else {
+        compute_flat_root_hash(&manifest.entries)
+    }
+}
+
+/// Version 1 root hash: sort entries by source_path, concatenate their
+/// blake3 hashes in order, then Blake3 the concatenation.
+fn compute_flat_root_hash(entries: &[ProofEntry]) -> String {
     if entries.is_empty() {
-        return blake3::hash(b"empty").to_hex().to_string();
+        return merkle::empty_root();
     }
 
     // Sort entries by source path for deterministic ordering
@@ -237,8 +320,18 @@ pub fn load_manifest(path: &Path) -> Result<ProofManifest> {
 /// Verify a proof manifest against files on disk.
 ///
 /// Re-hashes every dest file and compares against manifest entries.
-/// Also recomputes and verifies the root hash.
+/// Also recomputes and verifies the root hash, and the signature if the
+/// manifest is signed.
 pub fn verify_manifest(manifest: &ProofManifest) -> Result<VerifyResult> {
+    verify_manifest_with_key(manifest, None)
+}
+
+/// [`verify_manifest`], additionally requiring a signature by `trusted`
+/// when a key is given
+pub fn verify_manifest_with_key(
+    manifest: &ProofManifest,
+    trusted: Option<&VerifyingKey>,
+) -> Result<VerifyResult> {
     let mut verified = 0usize;
     let mut failed = 0usize;
     let mut missing = 0usize;
@@ -272,12 +365,10 @@ pub fn verify_manifest(manifest: &ProofManifest) -> Result<VerifyResult> {
             }
         }
 
-        // Compute blake3 hash
-        match compute_file_hash_sync(path) {
-            Ok(hash) => {
-                if hash == entry.blake3_hash {
-                    verified += 1;
-                } else {
+        // Compute blake3 hash (and any extra digests in the same read)
+        match compute_file_digests_sync(path, &entry.digests) {
+            Ok((hash, digests)) => {
+                if hash != entry.blake3_hash {
                     failed += 1;
                     tampered.push(TamperInfo {
                         path: entry.dest_path.clone(),
@@ -285,6 +376,16 @@ pub fn verify_manifest(manifest: &ProofManifest) -> Result<VerifyResult> {
                         actual_hash: hash,
                         issue: TamperType::HashMismatch,
                     });
+                } else if let Some((algorithm, expected)) = digest_mismatch(entry, &digests) {
+                    failed += 1;
+                    tampered.push(TamperInfo {
+                        path: entry.dest_path.clone(),
+                        expected_hash: format!("{}:{}", algorithm, expected),
+                        actual_hash: format!("{}:{}", algorithm, digests[algorithm]),
+                        issue: TamperType::HashMismatch,
+                    });
+                } else {
+                    verified += 1;
                 }
             }
             Err(_) => {
@@ -300,9 +401,13 @@ pub fn verify_manifest(manifest: &ProofManifest) -> Result<VerifyResult> {
     }
 
     // Verify root hash
-    let computed_root = compute_root_hash(&manifest.entries);
+    let computed_root = manifest_root_hash(manifest);
     let root_hash_valid = computed_root == manifest.root_hash;
 
+    let signature = verify_signature(manifest, trusted)?;
+    let signature =
+        (signature.status != SignatureStatus::Unsigned || trusted.is_some()).then_some(signature);
+
     Ok(VerifyResult {
         total: manifest.entries.len(),
         verified,
@@ -312,17 +417,35 @@ pub fn verify_manifest(manifest: &ProofManifest) -> Result<VerifyResult> {
         root_hash_valid,
         expected_root_hash: manifest.root_hash.clone(),
         computed_root_hash: computed_root,
+        signature,
+        timestamp: verify_timestamp(manifest),
+        custody_log: (!manifest.chain_of_custody.events.is_empty())
+            .then(|| verify_custody_log(&manifest.chain_of_custody)),
     })
 }
 
 /// Compute blake3 hash of a file synchronously
+#[cfg(test)]
 fn compute_file_hash_sync(path: &Path) -> Result<String> {
+    Ok(compute_file_digests_sync(path, &BTreeMap::new())?.0)
+}
+
+/// Compute the blake3 hash of a file together with those of the `expected`
+/// digests this module can recompute (md5, sha256), in one read.
+/// Digests of other algorithms are left out.
+fn compute_file_digests_sync(
+    path: &Path,
+    expected: &BTreeMap<String, String>,
+) -> Result<(String, BTreeMap<String, String>)> {
+    use sha2::Digest;
     use std::io::Read;
 
     let mut file =
         std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
 
     let mut hasher = blake3::Hasher::new();
+    let mut md5 = expected.contains_key("md5").then(md5::Md5::new);
+    let mut sha256 = expected.contains_key("sha256").then(sha2::Sha256::new);
     let mut buffer = vec![0u8; 64 * 1024]; // 64KB buffer
 
     loop {
@@ -331,9 +454,53 @@ fn compute_file_hash_sync(path: &Path) -> Result<String> {
             break;
         }
         hasher.update(&buffer[..bytes_read]);
+        if let Some(md5) = &mut md5 {
+            md5.update(&buffer[..bytes_read]);
+        }
+        if let Some(sha256) = &mut sha256 {
+            sha256.update(&buffer[..bytes_read]);
+        }
+    }
+
+    let mut digests = BTreeMap::new();
+    if let Some(md5) = md5 {
+        digests.insert("md5".to_string(), hex_encode(&md5.finalize()));
     }
+    if let Some(sha256) = sha256 {
+        digests.insert("sha256".to_string(), hex_encode(&sha256.finalize()));
+    }
+    Ok((hasher.finalize().to_hex().to_string(), digests))
+}
+
+/// First recorded digest of `entry` that differs from the recomputed
+/// `actual` ones, as (algorithm, expected)
+fn digest_mismatch<'a>(
+    entry: &'a ProofEntry,
+    actual: &BTreeMap<String, String>,
+) -> Option<(&'a str, &'a str)> {
+    entry
+        .digests
+        .iter()
+        .find(|(algorithm, expected)| {
+            actual
+                .get(algorithm.as_str())
+                .is_some_and(|a| !a.eq_ignore_ascii_case(expected))
+        })
+        .map(|(algorithm, expected)| (algorithm.as_str(), expected.as_str()))
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
 
-    Ok(hasher.finalize().to_hex().to_string())
+fn hex_decode(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) || !s.is_ascii() {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok())
+        .collect()
 }
 
 /// Format a VerifyResult for human display
@@ -361,6 +528,46 @@ pub fn format_verify_result(result: &VerifyResult) -> String {
             "INVALID"
         }
     ));
+    if let Some(check) = &result.signature {
+        let status = match check.status {
+            SignatureStatus::Trusted => "VALID (trusted key)",
+            SignatureStatus::ValidUntrusted => "VALID (key not checked, pass --pubkey)",
+            SignatureStatus::WrongKey => "VALID BUT NOT BY THE TRUSTED KEY",
+            SignatureStatus::Invalid => "INVALID",
+            SignatureStatus::Unsigned => "MISSING",
+        };
+        out.push_str(&format!("  Signature:      {}\n", status));
+        if let (Some(signer), Some(fingerprint)) = (&check.signer, &check.key_fingerprint) {
+            out.push_str(&format!(
+                "  Signed by:      {} (key {})\n",
+                signer, fingerprint
+            ));
+        }
+    }
+    if let Some(check) = &result.timestamp {
+        match &check.error {
+            None => {
+                out.push_str(&format!(
+                    "  Timestamp:      IMPRINT MATCHES {} ({})\n",
+                    check.gen_time.format("%Y-%m-%d %H:%M:%S UTC"),
+                    check.tsa_url
+                ));
+                out.push_str(
+                    "                  signature not verified; use `openssl ts -verify` on the .tst token\n",
+                );
+            }
+            Some(error) => out.push_str(&format!("  Timestamp:      INVALID ({})\n", error)),
+        }
+    }
+    if let Some(check) = &result.custody_log {
+        match check.broken_at {
+            None => out.push_str(&format!(
+                "  Custody log:    VALID ({} events, {} signed)\n",
+                check.events, check.signed
+            )),
+            Some(seq) => out.push_str(&format!("  Custody log:    BROKEN at event {}\n", seq)),
+        }
+    }
 
     if !result.tampered.is_empty() {
         out.push_str("\n  Tampered Files:\n");
@@ -396,6 +603,7 @@ mod tests {
                 dest_path: "/out/a.txt".to_string(),
                 size: 100,
                 blake3_hash: "abc123".to_string(),
+                digests: Default::default(),
                 exported_at: Utc::now(),
                 bad_sector_notes: None,
                 verified: true,
@@ -405,6 +613,7 @@ mod tests {
                 dest_path: "/out/b.txt".to_string(),
                 size: 200,
                 blake3_hash: "def456".to_string(),
+                digests: Default::default(),
                 exported_at: Utc::now(),
                 bad_sector_notes: None,
                 verified: true,
@@ -424,6 +633,7 @@ mod tests {
             dest_path: "/out/a.txt".to_string(),
             size: 100,
             blake3_hash: "abc123".to_string(),
+            digests: Default::default(),
             exported_at: Utc::now(),
             bad_sector_notes: None,
             verified: true,
@@ -433,6 +643,7 @@ mod tests {
             dest_path: "/out/b.txt".to_string(),
             size: 200,
             blake3_hash: "def456".to_string(),
+            digests: Default::default(),
             exported_at: Utc::now(),
             bad_sector_notes: None,
             verified: true,
@@ -470,6 +681,7 @@ mod tests {
             dest_path: file_path.to_string_lossy().to_string(),
             size: 16, // "original content" is 16 bytes
             blake3_hash: hash.clone(),
+            digests: Default::default(),
             exported_at: Utc::now(),
             bad_sector_notes: None,
             verified: true,
@@ -482,6 +694,9 @@ mod tests {
             started_at: Utc::now(),
             completed_at: Some(Utc::now()),
             options_used: BTreeMap::new(),
+            events: Vec::new(),
+            timestamp: None,
+            signature: None,
         };
 
         let manifest = build_manifest(Path::new("/source"), dir.path(), entries, custody);
@@ -502,6 +717,49 @@ mod tests {
         assert_eq!(result2.tampered.len(), 1);
     }
 
+    #[test]
+    fn test_extra_digests_verified() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("evidence.txt");
+        std::fs::write(&file_path, "abc").unwrap();
+
+        let mut digests = BTreeMap::new();
+        digests.insert(
+            "md5".to_string(),
+            "900150983cd24fb0d6963f7d28e17f72".to_string(),
+        );
+        digests.insert(
+            "sha256".to_string(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad".to_string(),
+        );
+        // Algorithms this build cannot recompute are skipped
+        digests.insert("sha1".to_string(), "00".repeat(20));
+        let (blake3, actual) = compute_file_digests_sync(&file_path, &digests).unwrap();
+        assert_eq!(actual.len(), 2);
+
+        let entry = ProofEntry {
+            source_path: "/source/evidence.txt".to_string(),
+            dest_path: file_path.to_string_lossy().to_string(),
+            size: 3,
+            blake3_hash: blake3,
+            digests,
+            exported_at: Utc::now(),
+            bad_sector_notes: None,
+            verified: true,
+        };
+        let custody = ChainOfCustody::from_environment();
+        let mut manifest = build_manifest(Path::new("/source"), dir.path(), vec![entry], custody);
+        assert!(verify_manifest(&manifest).unwrap().is_clean());
+
+        // A wrong recorded MD5 fails even though blake3 matches
+        manifest.entries[0]
+            .digests
+            .insert("md5".to_string(), "0".repeat(32));
+        let result = verify_manifest(&manifest).unwrap();
+        assert_eq!(result.failed, 1);
+        assert!(result.tampered[0].expected_hash.starts_with("md5:"));
+    }
+
     #[test]
     fn test_missing_file_detection() {
         let entries = vec![ProofEntry {
@@ -509,6 +767,7 @@ mod tests {
             dest_path: "/nonexistent/path/gone.txt".to_string(),
             size: 10,
             blake3_hash: "fakehash".to_string(),
+            digests: Default::default(),
             exported_at: Utc::now(),
             bad_sector_notes: None,
             verified: true,
@@ -521,6 +780,9 @@ mod tests {
             started_at: Utc::now(),
             completed_at: None,
             options_used: BTreeMap::new(),
+            events: Vec::new(),
+            timestamp: None,
+            signature: None,
         };
 
         let manifest = build_manifest(
@@ -545,6 +807,7 @@ mod tests {
             dest_path: "/out/a.txt".to_string(),
             size: 42,
             blake3_hash: "testhash".to_string(),
+            digests: Default::default(),
             exported_at: Utc::now(),
             bad_sector_notes: Some("2 bad blocks zero-filled".to_string()),
             verified: true,
@@ -560,6 +823,9 @@ mod tests {
                 ("verify_hash".to_string(), "true".to_string()),
                 ("preserve_structure".to_string(), "true".to_string()),
             ]),
+            events: Vec::new(),
+            timestamp: None,
+            signature: None,
         };
 
         let manifest = build_manifest(Path::new("/source"), Path::new("/dest"), entries, custody);
@@ -594,6 +860,9 @@ mod tests {
             root_hash_valid: false,
             expected_root_hash: "expected_root".to_string(),
             computed_root_hash: "computed_root".to_string(),
+            signature: None,
+            timestamp: None,
+            custody_log: None,
         };
 
         let text = format_verify_result(&result);
@@ -603,4 +872,49 @@ mod tests {
         assert!(text.contains("HASH MISMATCH"));
         assert!(text.contains("/out/bad.txt"));
     }
+
+    #[test]
+    fn test_verifier_kit() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("photos").join("a.jpg");
+        std::fs::create_dir_all(file_path.parent().unwrap()).unwrap();
+        std::fs::write(&file_path, "jpeg bytes").unwrap();
+        let hash = compute_file_hash_sync(&file_path).unwrap();
+
+        let entries = vec![ProofEntry {
+            source_path: "/source/photos/a.jpg".to_string(),
+            dest_path: file_path.to_string_lossy().to_string(),
+            size: 10,
+            blake3_hash: hash.clone(),
+            digests: Default::default(),
+            exported_at: Utc::now(),
+            bad_sector_notes: None,
+            verified: true,
+        }];
+        let manifest = build_manifest(
+            Path::new("/source"),
+            dir.path(),
+            entries,
+            ChainOfCustody::from_environment(),
+        );
+        let manifest_path = dir.path().join("proof.json");
+
+        let kit = write_verifier_kit(&manifest, &manifest_path).unwrap();
+
+        let list = std::fs::read_to_string(&kit.checksums).unwrap();
+        let rel = Path::new("photos").join("a.jpg");
+        assert_eq!(list, format!("{}  {}\n", hash, rel.to_string_lossy()));
+
+        let script = std::fs::read_to_string(&kit.script).unwrap();
+        assert!(script.starts_with("#!/bin/sh"));
+        assert!(script.contains(&manifest.root_hash));
+        assert!(script.contains("proof.b3"));
+
+        let schema: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&kit.schema).unwrap()).unwrap();
+        assert_eq!(
+            schema["properties"]["version"]["enum"],
+            serde_json::json!([1, PROOF_VERSION])
+        );
+    }
 }
@@ -0,0 +1,313 @@
+//! Ed25519 signatures for proof manifests
+//!
+//! Anyone can rehash a set of files and write a fresh manifest with matching
+//! hashes, so hashes alone only prove that the files still match *a*
+//! manifest. Signing the manifest with the examiner's key binds it to that
+//! key: editing any field afterwards (or replacing the manifest wholesale)
+//! breaks the signature.
+//!
+//! Keys are stored as PEM (PKCS#8 private keys, SPKI public keys), the same
+//! format `openssl genpkey -algorithm ed25519` produces.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use ed25519_dalek::pkcs8::spki::der::pem::LineEnding;
+use ed25519_dalek::pkcs8::{DecodePrivateKey, DecodePublicKey, EncodePrivateKey, EncodePublicKey};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+
+use super::{hex_decode, hex_encode, ProofManifest};
+
+/// Signature algorithm recorded in the manifest
+pub const SIGNATURE_ALGORITHM: &str = "ed25519";
+
+/// Prefix of the signed message, so a manifest signature cannot be replayed
+/// as a signature over anything else
+const SIGNING_CONTEXT: &[u8] = b"diamond-drill proof manifest v1\n";
+
+/// Signature embedded in a manifest's chain of custody
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestSignature {
+    /// Always `ed25519`
+    pub algorithm: String,
+    /// Who signed (free text, e.g. examiner name or badge number)
+    pub signer: String,
+    /// Signer's public key (hex)
+    pub public_key: String,
+    /// Signature over the manifest with this field removed (hex)
+    pub signature: String,
+    /// When the manifest was signed
+    pub signed_at: DateTime<Utc>,
+    /// Custody events that existed when signing; later events are not
+    /// covered by the signature
+    #[serde(default)]
+    pub events_signed: usize,
+}
+
+/// Outcome of checking a manifest's signature
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SignatureStatus {
+    /// Valid, made by the trusted key
+    Trusted,
+    /// Valid, but no trusted key was given to compare the signer's key with
+    ValidUntrusted,
+    /// Valid, but made by a key other than the trusted one
+    WrongKey,
+    /// Does not match the manifest contents
+    Invalid,
+    /// The manifest carries no signature
+    Unsigned,
+}
+
+/// Signature details reported alongside a verification result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignatureCheck {
+    pub status: SignatureStatus,
+    /// Signer recorded in the manifest
+    pub signer: Option<String>,
+    /// Short fingerprint of the signing key
+    pub key_fingerprint: Option<String>,
+}
+
+impl SignatureCheck {
+    /// Whether the signature holds up
+    pub fn is_ok(&self) -> bool {
+        matches!(
+            self.status,
+            SignatureStatus::Trusted | SignatureStatus::ValidUntrusted
+        )
+    }
+}
+
+/// Generate a new signing key
+pub fn generate_key() -> SigningKey {
+    SigningKey::generate(&mut rand_core::OsRng)
+}
+
+/// Write a key pair as PEM: the private key to `key_path` (owner-only on
+/// Unix) and the public key to `pub_path`
+pub fn save_key_pair(key: &SigningKey, key_path: &Path, pub_path: &Path) -> Result<()> {
+    let private = key
+        .to_pkcs8_pem(LineEnding::LF)
+        .context("Failed to encode private key")?;
+    let public = key
+        .verifying_key()
+        .to_public_key_pem(LineEnding::LF)
+        .context("Failed to encode public key")?;
+
+    write_private(key_path, private.as_bytes())
+        .with_context(|| format!("Failed to write key to {}", key_path.display()))?;
+    std::fs::write(pub_path, public)
+        .with_context(|| format!("Failed to write public key to {}", pub_path.display()))?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn write_private(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)?
+        .write_all(data)
+}
+
+#[cfg(not(unix))]
+fn write_private(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)?
+        .write_all(data)
+}
+
+/// Load a PKCS#8 PEM private key
+pub fn load_signing_key(path: &Path) -> Result<SigningKey> {
+    let pem = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read key from {}", path.display()))?;
+    SigningKey::from_pkcs8_pem(&pem)
+        .map_err(|e| anyhow::anyhow!("Not an Ed25519 private key: {}: {}", path.display(), e))
+}
+
+/// Load an SPKI PEM public key
+pub fn load_verifying_key(path: &Path) -> Result<VerifyingKey> {
+    let pem = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read public key from {}", path.display()))?;
+    VerifyingKey::from_public_key_pem(&pem)
+        .map_err(|e| anyhow::anyhow!("Not an Ed25519 public key: {}: {}", path.display(), e))
+}
+
+/// Short, human-comparable fingerprint of a public key
+pub fn key_fingerprint(key: &VerifyingKey) -> String {
+    blake3::hash(key.as_bytes()).to_hex()[..16].to_string()
+}
+
+/// Sign a manifest, replacing any earlier signature
+pub fn sign_manifest(manifest: &mut ProofManifest, key: &SigningKey, signer: &str) -> Result<()> {
+    manifest.chain_of_custody.signature = None;
+    let message = signing_message(manifest)?;
+    let signature = key.sign(&message);
+
+    manifest.chain_of_custody.signature = Some(ManifestSignature {
+        algorithm: SIGNATURE_ALGORITHM.to_string(),
+        signer: signer.to_string(),
+        public_key: hex_encode(key.verifying_key().as_bytes()),
+        signature: hex_encode(&signature.to_bytes()),
+        signed_at: Utc::now(),
+        events_signed: manifest.chain_of_custody.events.len(),
+    });
+    Ok(())
+}
+
+/// Check a manifest's signature, optionally against a trusted public key
+pub fn verify_signature(
+    manifest: &ProofManifest,
+    trusted: Option<&VerifyingKey>,
+) -> Result<SignatureCheck> {
+    let Some(sig) = &manifest.chain_of_custody.signature else {
+        return Ok(SignatureCheck {
+            status: SignatureStatus::Unsigned,
+            signer: None,
+            key_fingerprint: None,
+        });
+    };
+    anyhow::ensure!(
+        sig.algorithm == SIGNATURE_ALGORITHM,
+        "Unsupported signature algorithm: {}",
+        sig.algorithm
+    );
+
+    let invalid = |fingerprint: Option<String>| SignatureCheck {
+        status: SignatureStatus::Invalid,
+        signer: Some(sig.signer.clone()),
+        key_fingerprint: fingerprint,
+    };
+    let Some(key) =
+        hex_decode_array::<32>(&sig.public_key).and_then(|b| VerifyingKey::from_bytes(&b).ok())
+    else {
+        return Ok(invalid(None));
+    };
+    let fingerprint = Some(key_fingerprint(&key));
+    let Some(signature) = hex_decode_array::<64>(&sig.signature).map(|b| Signature::from_bytes(&b))
+    else {
+        return Ok(invalid(fingerprint));
+    };
+
+    let mut unsigned = manifest.clone();
+    unsigned.chain_of_custody.signature = None;
+    if unsigned.chain_of_custody.events.len() < sig.events_signed {
+        return Ok(invalid(fingerprint));
+    }
+    unsigned.chain_of_custody.events.truncate(sig.events_signed);
+    if key
+        .verify(&signing_message(&unsigned)?, &signature)
+        .is_err()
+    {
+        return Ok(invalid(fingerprint));
+    }
+
+    let status = match trusted {
+        None => SignatureStatus::ValidUntrusted,
+        Some(trusted) if trusted == &key => SignatureStatus::Trusted,
+        Some(_) => SignatureStatus::WrongKey,
+    };
+    Ok(SignatureCheck {
+        status,
+        signer: Some(sig.signer.clone()),
+        key_fingerprint: fingerprint,
+    })
+}
+
+/// Bytes covered by the signature: the manifest as JSON, without signature
+/// and without events appended after signing
+fn signing_message(manifest: &ProofManifest) -> Result<Vec<u8>> {
+    let mut message = SIGNING_CONTEXT.to_vec();
+    serde_json::to_writer(&mut message, manifest).context("Failed to serialize proof manifest")?;
+    Ok(message)
+}
+
+fn hex_decode_array<const N: usize>(s: &str) -> Option<[u8; N]> {
+    hex_decode(s)?.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proof::{build_manifest, ChainOfCustody};
+
+    #[test]
+    fn test_sign_and_verify_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let (key_path, pub_path) = (
+            dir.path().join("examiner.key"),
+            dir.path().join("examiner.pub"),
+        );
+        save_key_pair(&generate_key(), &key_path, &pub_path).unwrap();
+        // Never overwrite an existing private key
+        assert!(save_key_pair(&generate_key(), &key_path, &pub_path).is_err());
+
+        let key = load_signing_key(&key_path).unwrap();
+        let trusted = load_verifying_key(&pub_path).unwrap();
+        let mut manifest = build_manifest(
+            Path::new("/src"),
+            Path::new("/dest"),
+            Vec::new(),
+            ChainOfCustody::from_environment(),
+        );
+        assert_eq!(
+            verify_signature(&manifest, Some(&trusted)).unwrap().status,
+            SignatureStatus::Unsigned
+        );
+
+        sign_manifest(&mut manifest, &key, "Examiner 42").unwrap();
+        // Survives a save/load round trip
+        let path = dir.path().join("proof.json");
+        crate::proof::save_manifest(&manifest, &path).unwrap();
+        let manifest = crate::proof::load_manifest(&path).unwrap();
+
+        let check = verify_signature(&manifest, Some(&trusted)).unwrap();
+        assert_eq!(check.status, SignatureStatus::Trusted);
+        assert_eq!(check.signer.as_deref(), Some("Examiner 42"));
+        assert_eq!(
+            verify_signature(&manifest, None).unwrap().status,
+            SignatureStatus::ValidUntrusted
+        );
+        let other = generate_key().verifying_key();
+        assert_eq!(
+            verify_signature(&manifest, Some(&other)).unwrap().status,
+            SignatureStatus::WrongKey
+        );
+
+        // Custody events appended after signing keep the signature intact,
+        // dropping signed ones does not
+        let mut manifest = manifest;
+        manifest
+            .chain_of_custody
+            .record_event(crate::proof::CustodyEventKind::Note, "sealed");
+        sign_manifest(&mut manifest, &key, "Examiner 42").unwrap();
+        manifest.chain_of_custody.record_event(
+            crate::proof::CustodyEventKind::ManifestTransferred,
+            "to court",
+        );
+        assert!(verify_signature(&manifest, Some(&trusted)).unwrap().is_ok());
+        let mut truncated = manifest.clone();
+        truncated.chain_of_custody.events.clear();
+        assert_eq!(
+            verify_signature(&truncated, Some(&trusted)).unwrap().status,
+            SignatureStatus::Invalid
+        );
+
+        let mut tampered = manifest.clone();
+        tampered.chain_of_custody.operator = "someone-else".into();
+        let check = verify_signature(&tampered, Some(&trusted)).unwrap();
+        assert_eq!(check.status, SignatureStatus::Invalid);
+        assert!(!check.is_ok());
+    }
+}
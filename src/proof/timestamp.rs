@@ -0,0 +1,456 @@
+//! RFC 3161 trusted timestamps for proof manifests
+//!
+//! The manifest root hash is sent to a timestamp authority (TSA), which
+//! returns a signed token binding that hash to the current time. Stored in
+//! the chain of custody, the token proves the export existed at that time,
+//! independently of the examiner's clock.
+//!
+//! The imprint is SHA-256 over the 32 raw root hash bytes, as most TSAs only
+//! accept SHA-2 digests. Verification here checks that the token belongs to
+//! the manifest's root hash; checking the TSA's signature needs the TSA
+//! certificate and is left to `openssl ts -verify` on the `.tst` token file.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+#[cfg(any(feature = "cli", test))]
+use super::hex_encode;
+use super::{hex_decode, ProofManifest};
+
+/// Timestamp authority used when none is given
+pub const DEFAULT_TSA_URL: &str = "https://freetsa.org/tsr";
+
+/// Hash algorithm of the imprint sent to the TSA
+pub const TIMESTAMP_HASH_ALGORITHM: &str = "sha256";
+
+/// 2.16.840.1.101.3.4.2.1
+const OID_SHA256: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01];
+/// 1.2.840.113549.1.7.2
+const OID_SIGNED_DATA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x02];
+/// 1.2.840.113549.1.9.16.1.4
+const OID_TST_INFO: &[u8] = &[
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x10, 0x01, 0x04,
+];
+
+#[cfg(any(feature = "cli", test))]
+const TAG_BOOLEAN: u8 = 0x01;
+const TAG_INTEGER: u8 = 0x02;
+const TAG_OCTET_STRING: u8 = 0x04;
+#[cfg(any(feature = "cli", test))]
+const TAG_NULL: u8 = 0x05;
+const TAG_OID: u8 = 0x06;
+const TAG_GENERALIZED_TIME: u8 = 0x18;
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_CONTEXT_0: u8 = 0xa0;
+
+/// Timestamp token stored in a manifest's chain of custody
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestTimestamp {
+    /// Authority that issued the token
+    pub tsa_url: String,
+    /// Always `sha256`
+    pub hash_algorithm: String,
+    /// SHA-256 of the raw root hash bytes (hex)
+    pub message_imprint: String,
+    /// Time asserted by the TSA
+    pub gen_time: DateTime<Utc>,
+    /// Token serial number assigned by the TSA (hex)
+    pub serial_number: String,
+    /// DER-encoded TimeStampToken (hex)
+    pub token: String,
+}
+
+/// Outcome of checking a manifest's timestamp
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimestampCheck {
+    pub tsa_url: String,
+    pub gen_time: DateTime<Utc>,
+    /// The token is for this manifest's root hash. The TSA's signature over
+    /// the token is not checked; see the module docs
+    pub imprint_matches: bool,
+    /// Why the token does not match, if it does not
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Fields of a token's TSTInfo
+#[derive(Debug, Clone)]
+pub struct TstInfo {
+    pub message_imprint: Vec<u8>,
+    pub serial_number: Vec<u8>,
+    pub gen_time: DateTime<Utc>,
+    pub nonce: Option<Vec<u8>>,
+}
+
+/// Imprint submitted for a root hash
+pub fn root_imprint(root_hash: &str) -> Result<[u8; 32]> {
+    let root = hex_decode(root_hash)
+        .filter(|b| b.len() == 32)
+        .with_context(|| format!("Root hash is not 32 bytes of hex: {}", root_hash))?;
+    Ok(Sha256::digest(root).into())
+}
+
+/// DER TimeStampReq for an imprint, asking for the TSA certificate to be
+/// included so the token can be verified offline
+#[cfg(any(feature = "cli", test))]
+pub fn timestamp_request(imprint: &[u8; 32], nonce: u64) -> Vec<u8> {
+    let message_imprint = tlv(
+        TAG_SEQUENCE,
+        &[sha256_algorithm(), tlv(TAG_OCTET_STRING, imprint)].concat(),
+    );
+    tlv(
+        TAG_SEQUENCE,
+        &[
+            tlv(TAG_INTEGER, &[1]),
+            message_imprint,
+            tlv(TAG_INTEGER, &unsigned_integer(&nonce.to_be_bytes())),
+            tlv(TAG_BOOLEAN, &[0xff]),
+        ]
+        .concat(),
+    )
+}
+
+/// Check the status of a DER TimeStampResp and return its token
+#[cfg(any(feature = "cli", test))]
+pub fn parse_response(response: &[u8]) -> Result<Vec<u8>> {
+    let mut resp = Der::new(Der::new(response).expect(TAG_SEQUENCE)?);
+    let mut status = Der::new(resp.expect(TAG_SEQUENCE)?);
+    let code = status.expect(TAG_INTEGER)?;
+    // 0 = granted, 1 = granted with modifications
+    if !matches!(code, [0] | [1]) {
+        let text = status.read().ok().map(|(_, text, _)| text).unwrap_or(&[]);
+        anyhow::bail!(
+            "Timestamp request rejected (status {:?}): {}",
+            code,
+            String::from_utf8_lossy(text)
+        );
+    }
+    let (tag, _, raw) = resp.read().context("Response carries no timestamp token")?;
+    anyhow::ensure!(tag == TAG_SEQUENCE, "Malformed timestamp token");
+    Ok(raw.to_vec())
+}
+
+/// Extract TSTInfo from a DER TimeStampToken (CMS SignedData)
+pub fn parse_token(token: &[u8]) -> Result<TstInfo> {
+    let mut content_info = Der::new(Der::new(token).expect(TAG_SEQUENCE)?);
+    anyhow::ensure!(
+        content_info.expect(TAG_OID)? == OID_SIGNED_DATA,
+        "Timestamp token is not CMS SignedData"
+    );
+    let signed_data = Der::new(content_info.expect(TAG_CONTEXT_0)?).expect(TAG_SEQUENCE)?;
+    let mut signed_data = Der::new(signed_data);
+    signed_data.expect(TAG_INTEGER)?; // version
+    signed_data.read()?; // digestAlgorithms
+    let mut encap = Der::new(signed_data.expect(TAG_SEQUENCE)?);
+    anyhow::ensure!(
+        encap.expect(TAG_OID)? == OID_TST_INFO,
+        "Timestamp token does not contain TSTInfo"
+    );
+    let tst_info = Der::new(encap.expect(TAG_CONTEXT_0)?).expect(TAG_OCTET_STRING)?;
+
+    let mut tst = Der::new(Der::new(tst_info).expect(TAG_SEQUENCE)?);
+    tst.expect(TAG_INTEGER)?; // version
+    tst.expect(TAG_OID)?; // policy
+    let mut imprint = Der::new(tst.expect(TAG_SEQUENCE)?);
+    let mut algorithm = Der::new(imprint.expect(TAG_SEQUENCE)?);
+    anyhow::ensure!(
+        algorithm.expect(TAG_OID)? == OID_SHA256,
+        "Timestamp token imprint is not SHA-256"
+    );
+    let message_imprint = imprint.expect(TAG_OCTET_STRING)?.to_vec();
+    let serial_number = tst.expect(TAG_INTEGER)?.to_vec();
+    let gen_time = parse_generalized_time(tst.expect(TAG_GENERALIZED_TIME)?)?;
+
+    // accuracy and ordering may precede the nonce
+    let mut nonce = None;
+    while let Ok((tag, value, _)) = tst.read() {
+        if tag == TAG_INTEGER {
+            nonce = Some(value.to_vec());
+            break;
+        }
+    }
+
+    Ok(TstInfo {
+        message_imprint,
+        serial_number,
+        gen_time,
+        nonce,
+    })
+}
+
+/// Submit the manifest's root hash to a TSA
+#[cfg(feature = "cli")]
+pub fn request_timestamp(manifest: &ProofManifest, tsa_url: &str) -> Result<ManifestTimestamp> {
+    use rand_core::RngCore;
+    use std::io::Read;
+
+    let imprint = root_imprint(&manifest.root_hash)?;
+    let nonce = rand_core::OsRng.next_u64();
+    let request = timestamp_request(&imprint, nonce);
+
+    let response = ureq::post(tsa_url)
+        .timeout(std::time::Duration::from_secs(30))
+        .set("Content-Type", "application/timestamp-query")
+        .send_bytes(&request)
+        .with_context(|| format!("Timestamp request to {} failed", tsa_url))?;
+    let mut body = Vec::new();
+    response
+        .into_reader()
+        .take(1 << 20)
+        .read_to_end(&mut body)
+        .context("Failed to read timestamp response")?;
+
+    let token = parse_response(&body)?;
+    let info = parse_token(&token)?;
+    anyhow::ensure!(
+        info.message_imprint == imprint,
+        "TSA returned a token for a different hash"
+    );
+    anyhow::ensure!(
+        info.nonce.as_deref() == Some(&unsigned_integer(&nonce.to_be_bytes())[..]),
+        "TSA response nonce does not match the request"
+    );
+
+    Ok(ManifestTimestamp {
+        tsa_url: tsa_url.to_string(),
+        hash_algorithm: TIMESTAMP_HASH_ALGORITHM.to_string(),
+        message_imprint: hex_encode(&imprint),
+        gen_time: info.gen_time,
+        serial_number: hex_encode(&info.serial_number),
+        token: hex_encode(&token),
+    })
+}
+
+/// Check that a manifest's timestamp token covers its root hash
+pub fn verify_timestamp(manifest: &ProofManifest) -> Option<TimestampCheck> {
+    let timestamp = manifest.chain_of_custody.timestamp.as_ref()?;
+    let error = match check_token(manifest, timestamp) {
+        Ok(()) => None,
+        Err(e) => Some(format!("{:#}", e)),
+    };
+    Some(TimestampCheck {
+        tsa_url: timestamp.tsa_url.clone(),
+        gen_time: timestamp.gen_time,
+        imprint_matches: error.is_none(),
+        error,
+    })
+}
+
+fn check_token(manifest: &ProofManifest, timestamp: &ManifestTimestamp) -> Result<()> {
+    let imprint = root_imprint(&manifest.root_hash)?;
+    let token = hex_decode(&timestamp.token).context("Timestamp token is not hex")?;
+    let info = parse_token(&token)?;
+    anyhow::ensure!(
+        info.message_imprint == imprint,
+        "Timestamp token is for a different root hash"
+    );
+    anyhow::ensure!(
+        info.gen_time == timestamp.gen_time,
+        "Recorded time differs from the token ({})",
+        info.gen_time
+    );
+    Ok(())
+}
+
+#[cfg(any(feature = "cli", test))]
+fn sha256_algorithm() -> Vec<u8> {
+    tlv(
+        TAG_SEQUENCE,
+        &[tlv(TAG_OID, OID_SHA256), tlv(TAG_NULL, &[])].concat(),
+    )
+}
+
+/// Encode one DER tag-length-value
+#[cfg(any(feature = "cli", test))]
+fn tlv(tag: u8, value: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    let len = value.len();
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let bytes = len.to_be_bytes();
+        let skip = bytes.iter().take_while(|&&b| b == 0).count();
+        out.push(0x80 | (bytes.len() - skip) as u8);
+        out.extend_from_slice(&bytes[skip..]);
+    }
+    out.extend_from_slice(value);
+    out
+}
+
+/// Minimal DER INTEGER content for an unsigned big-endian number
+#[cfg(any(feature = "cli", test))]
+fn unsigned_integer(bytes: &[u8]) -> Vec<u8> {
+    let skip = bytes
+        .iter()
+        .take_while(|&&b| b == 0)
+        .count()
+        .min(bytes.len() - 1);
+    let mut out = Vec::with_capacity(bytes.len() + 1);
+    if bytes[skip] & 0x80 != 0 {
+        out.push(0);
+    }
+    out.extend_from_slice(&bytes[skip..]);
+    out
+}
+
+/// `YYYYMMDDHHMMSS[.fff]Z`
+fn parse_generalized_time(value: &[u8]) -> Result<DateTime<Utc>> {
+    let text = std::str::from_utf8(value).context("Malformed GeneralizedTime")?;
+    let trimmed = text
+        .strip_suffix('Z')
+        .with_context(|| format!("GeneralizedTime is not UTC: {}", text))?;
+    let (seconds, fraction) = trimmed.split_once('.').unwrap_or((trimmed, ""));
+    let time = NaiveDateTime::parse_from_str(seconds, "%Y%m%d%H%M%S")
+        .with_context(|| format!("Malformed GeneralizedTime: {}", text))?;
+    let nanos = if fraction.is_empty() {
+        0
+    } else {
+        format!("{:0<9}", fraction)[..9]
+            .parse::<i64>()
+            .with_context(|| format!("Malformed GeneralizedTime: {}", text))?
+    };
+    Ok(time.and_utc() + chrono::Duration::nanoseconds(nanos))
+}
+
+/// Reader over a run of DER elements
+struct Der<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Der<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    /// Next element as (tag, contents, whole encoding)
+    fn read(&mut self) -> Result<(u8, &'a [u8], &'a [u8])> {
+        let data = self.data;
+        anyhow::ensure!(data.len() >= 2, "Truncated DER");
+        let tag = data[0];
+        let (len, header) = match data[1] {
+            len @ 0..=0x7f => (len as usize, 2),
+            0x80 => anyhow::bail!("Indefinite-length encoding is not DER"),
+            first => {
+                let count = (first & 0x7f) as usize;
+                anyhow::ensure!(count <= 4 && data.len() >= 2 + count, "Bad DER length");
+                let len = data[2..2 + count]
+                    .iter()
+                    .fold(0usize, |acc, &b| (acc << 8) | b as usize);
+                (len, 2 + count)
+            }
+        };
+        anyhow::ensure!(data.len() >= header + len, "Truncated DER");
+        self.data = &data[header + len..];
+        Ok((tag, &data[header..header + len], &data[..header + len]))
+    }
+
+    /// Contents of the next element, which must have `tag`
+    fn expect(&mut self, tag: u8) -> Result<&'a [u8]> {
+        let (found, value, _) = self.read()?;
+        anyhow::ensure!(
+            found == tag,
+            "Unexpected DER tag {:#04x} (expected {:#04x})",
+            found,
+            tag
+        );
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// TimeStampResp shaped like a TSA's, minus certificates and signer info
+    fn fake_response(imprint: &[u8], nonce: &[u8], gen_time: &str) -> Vec<u8> {
+        let tst_info = tlv(
+            TAG_SEQUENCE,
+            &[
+                tlv(TAG_INTEGER, &[1]),
+                tlv(TAG_OID, &[0x2a, 0x03, 0x04]),
+                tlv(
+                    TAG_SEQUENCE,
+                    &[sha256_algorithm(), tlv(TAG_OCTET_STRING, imprint)].concat(),
+                ),
+                tlv(TAG_INTEGER, &[0x01, 0x23]),
+                tlv(TAG_GENERALIZED_TIME, gen_time.as_bytes()),
+                tlv(TAG_SEQUENCE, &tlv(TAG_INTEGER, &[1])), // accuracy
+                tlv(TAG_INTEGER, nonce),
+            ]
+            .concat(),
+        );
+        let signed_data = tlv(
+            TAG_SEQUENCE,
+            &[
+                tlv(TAG_INTEGER, &[3]),
+                tlv(0x31, &sha256_algorithm()),
+                tlv(
+                    TAG_SEQUENCE,
+                    &[
+                        tlv(TAG_OID, OID_TST_INFO),
+                        tlv(TAG_CONTEXT_0, &tlv(TAG_OCTET_STRING, &tst_info)),
+                    ]
+                    .concat(),
+                ),
+                tlv(0x31, &[]),
+            ]
+            .concat(),
+        );
+        let token = tlv(
+            TAG_SEQUENCE,
+            &[
+                tlv(TAG_OID, OID_SIGNED_DATA),
+                tlv(TAG_CONTEXT_0, &signed_data),
+            ]
+            .concat(),
+        );
+        let status = tlv(TAG_SEQUENCE, &tlv(TAG_INTEGER, &[0]));
+        tlv(TAG_SEQUENCE, &[status, token].concat())
+    }
+
+    #[test]
+    fn test_timestamp_token_round_trip() {
+        let root = blake3::hash(b"export").to_hex().to_string();
+        let imprint = root_imprint(&root).unwrap();
+        let nonce = 0x8000_0000_0000_0001u64;
+
+        let request = timestamp_request(&imprint, nonce);
+        assert_eq!(request[0], TAG_SEQUENCE);
+        assert!(request.windows(32).any(|w| w == imprint));
+
+        let nonce_der = unsigned_integer(&nonce.to_be_bytes());
+        assert_eq!(nonce_der.len(), 9, "high bit set needs a leading zero");
+        let response = fake_response(&imprint, &nonce_der, "20261018093000.25Z");
+        let token = parse_response(&response).unwrap();
+        let info = parse_token(&token).unwrap();
+        assert_eq!(info.message_imprint, imprint);
+        assert_eq!(info.nonce.as_deref(), Some(&nonce_der[..]));
+        assert_eq!(info.serial_number, [0x01, 0x23]);
+        assert_eq!(info.gen_time.to_rfc3339(), "2026-10-18T09:30:00.250+00:00");
+
+        // Stored in a manifest, the token only matches that manifest's root
+        let mut manifest = super::super::build_manifest(
+            std::path::Path::new("/src"),
+            std::path::Path::new("/dest"),
+            Vec::new(),
+            super::super::ChainOfCustody::from_environment(),
+        );
+        manifest.root_hash = root;
+        manifest.chain_of_custody.timestamp = Some(ManifestTimestamp {
+            tsa_url: DEFAULT_TSA_URL.to_string(),
+            hash_algorithm: TIMESTAMP_HASH_ALGORITHM.to_string(),
+            message_imprint: hex_encode(&imprint),
+            gen_time: info.gen_time,
+            serial_number: hex_encode(&info.serial_number),
+            token: hex_encode(&token),
+        });
+        assert!(verify_timestamp(&manifest).unwrap().imprint_matches);
+        manifest.root_hash = blake3::hash(b"other").to_hex().to_string();
+        assert!(!verify_timestamp(&manifest).unwrap().imprint_matches);
+
+        let rejected = tlv(TAG_SEQUENCE, &tlv(TAG_SEQUENCE, &tlv(TAG_INTEGER, &[2])));
+        assert!(parse_response(&rejected).is_err());
+    }
+}
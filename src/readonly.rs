@@ -4,6 +4,7 @@
 //! - Panic guard if write access is detected
 //! - File handle validation
 //! - Mount point verification
+//! - Drive health (SMART) pre-flight check
 //! - Runtime enforcement checks
 
 use std::fs::{File, OpenOptions};
@@ -172,8 +173,9 @@ pub fn warn_if_writable(path: &Path) {
 // Pre-Operation Checks
 // ============================================================================
 
-/// Run all safety checks before starting an operation
-pub fn run_safety_checks(source: &Path) -> Result<(), String> {
+/// Run all safety checks before starting an operation. With `ignore_health`
+/// a failing source drive is only warned about.
+pub fn run_safety_checks(source: &Path, ignore_health: bool) -> Result<(), String> {
     println!("\n  {} Running safety checks...", "🔐".bright_cyan());
 
     // Check 1: Read-only enforcement is enabled
@@ -198,15 +200,69 @@ pub fn run_safety_checks(source: &Path) -> Result<(), String> {
     // Check 4: Mount status
     warn_if_writable(source);
 
+    // Check 5: Drive health
+    check_drive_health(source, ignore_health)?;
+
     println!();
     Ok(())
 }
 
+/// Warn about a degraded source drive and refuse to read a failing one
+/// unless `ignore_health` is set.
+/// Skipped quietly when smartctl or SMART data is unavailable.
+fn check_drive_health(source: &Path, ignore_health: bool) -> Result<(), String> {
+    use crate::health::{self, Verdict};
+
+    let Some(device) = health::source_device(source) else {
+        return Ok(());
+    };
+    let smart = match health::query(&device) {
+        Ok(smart) => smart,
+        Err(e) => {
+            tracing::debug!("Skipping SMART check of {}: {:#}", device.display(), e);
+            return Ok(());
+        }
+    };
+    let assessment = smart.assess();
+    match assessment.verdict {
+        Verdict::Healthy => {
+            println!("  {} Drive health OK ({})", "✓".green(), device.display());
+        }
+        Verdict::Degraded => {
+            println!(
+                "  {} Drive {} is degraded: {}",
+                "⚠".yellow(),
+                device.display(),
+                assessment.reasons.join(", ")
+            );
+        }
+        Verdict::Failing if ignore_health => {
+            println!(
+                "  {} Drive {} is failing ({}); reading it anyway (--ignore-health)",
+                "⚠".red().bold(),
+                device.display(),
+                assessment.reasons.join(", ")
+            );
+        }
+        Verdict::Failing => {
+            return Err(format!(
+                "Drive {} is failing ({}). Image it first with `diamond-drill image {} <image>` and work on the image, or pass --ignore-health to read it anyway",
+                device.display(),
+                assessment.reasons.join(", "),
+                device.display()
+            ));
+        }
+    }
+    Ok(())
+}
+
 // ============================================================================
 // Safe Copy (for export)
 // ============================================================================
 
 /// Copy a file safely (source is read-only, dest is created)
+///
+/// Holes in sparse sources (disk images, VM disks) stay holes in the copy.
 pub fn safe_copy(source: &Path, dest: &Path) -> io::Result<u64> {
     // Open source in read-only mode (with enforcement)
     let mut src_file = open_readonly(source)?;
@@ -214,6 +270,10 @@ pub fn safe_copy(source: &Path, dest: &Path) -> io::Result<u64> {
     // Create destination file
     let mut dst_file = File::create(dest)?;
 
+    if let Some(ranges) = crate::sparse::data_ranges(&src_file) {
+        return crate::sparse::copy_ranges(&mut src_file, &mut dst_file, &ranges);
+    }
+
     // Copy contents
     io::copy(&mut src_file, &mut dst_file)
 }
@@ -0,0 +1,253 @@
+//! Interchange formats for other forensics tools
+//!
+//! - **DFXML** (Digital Forensics XML): one `<fileobject>` per indexed or
+//!   carved file, readable by the DFXML Python tools and by tools built on
+//!   them (e.g. bulk_extractor viewers, idifference).
+//! - **Body file** (Sleuth Kit 3.x): `MD5|name|inode|mode|UID|GID|size|
+//!   atime|mtime|ctime|crtime`, the input of `mactime` for timelines.
+//!
+//! Only blake3 hashes are computed, so the body file's MD5 column is `0`.
+//! Unknown times are written as `0`, as `fls -m` does.
+
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+
+use super::html_escape as xml_escape;
+use crate::carve::CarvedFile;
+use crate::core::FileEntry;
+
+/// Interchange format of a forensic report
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForensicFormat {
+    Dfxml,
+    Bodyfile,
+}
+
+impl ForensicFormat {
+    /// File extension used for reports in this format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Dfxml => "dfxml",
+            Self::Bodyfile => "body",
+        }
+    }
+}
+
+/// One file in a forensic report
+#[derive(Debug, Clone)]
+pub struct ForensicRecord {
+    pub name: String,
+    pub size: u64,
+    pub modified: Option<DateTime<Utc>>,
+    pub created: Option<DateTime<Utc>>,
+    pub blake3: Option<String>,
+    /// Offset of the data in the source image, for carved files
+    pub img_offset: Option<u64>,
+    pub has_bad_sectors: bool,
+}
+
+impl ForensicRecord {
+    pub fn from_index_entry(entry: &FileEntry) -> Self {
+        Self {
+            name: entry.path.to_string_lossy().to_string(),
+            size: entry.size,
+            modified: entry.modified,
+            created: entry.created,
+            blake3: entry.hash.clone(),
+            img_offset: None,
+            has_bad_sectors: entry.has_bad_sectors,
+        }
+    }
+
+    /// Carved files are named after their extracted copy, or after their
+    /// offset in dry runs
+    pub fn from_carved(carved: &CarvedFile) -> Self {
+        let name = match &carved.path {
+            Some(path) => path.to_string_lossy().to_string(),
+            None => format!("carved@{:#x}.{}", carved.offset, carved.extension),
+        };
+        Self {
+            name,
+            size: carved.size,
+            modified: None,
+            created: None,
+            blake3: carved.hash.clone(),
+            img_offset: Some(carved.offset),
+            has_bad_sectors: carved.bad_region_bytes > 0,
+        }
+    }
+}
+
+/// Write records as a DFXML document describing `source`
+pub fn write_dfxml(
+    records: &[ForensicRecord],
+    source: &Path,
+    command_line: &str,
+    out: &mut impl Write,
+) -> std::io::Result<()> {
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        out,
+        r#"<dfxml xmlns="http://www.forensicswiki.org/wiki/Category:Digital_Forensics_XML" xmlns:dc="http://purl.org/dc/elements/1.1/" version="1.2.0">"#
+    )?;
+    writeln!(out, "  <metadata>")?;
+    writeln!(out, "    <dc:type>File System Listing</dc:type>")?;
+    writeln!(out, "  </metadata>")?;
+    writeln!(out, "  <creator>")?;
+    writeln!(out, "    <program>diamond-drill</program>")?;
+    writeln!(out, "    <version>{}</version>", env!("CARGO_PKG_VERSION"))?;
+    writeln!(out, "    <execution_environment>")?;
+    writeln!(
+        out,
+        "      <command_line>{}</command_line>",
+        xml_escape(command_line)
+    )?;
+    writeln!(
+        out,
+        "      <start_time>{}</start_time>",
+        Utc::now().format("%Y-%m-%dT%H:%M:%SZ")
+    )?;
+    writeln!(out, "    </execution_environment>")?;
+    writeln!(out, "  </creator>")?;
+    writeln!(out, "  <source>")?;
+    writeln!(
+        out,
+        "    <image_filename>{}</image_filename>",
+        xml_escape(&source.to_string_lossy())
+    )?;
+    writeln!(out, "  </source>")?;
+
+    for record in records {
+        writeln!(out, "  <fileobject>")?;
+        writeln!(out, "    <filename>{}</filename>", xml_escape(&record.name))?;
+        writeln!(out, "    <filesize>{}</filesize>", record.size)?;
+        for (tag, time) in [("mtime", record.modified), ("crtime", record.created)] {
+            if let Some(time) = time {
+                writeln!(
+                    out,
+                    "    <{tag}>{}</{tag}>",
+                    time.format("%Y-%m-%dT%H:%M:%SZ")
+                )?;
+            }
+        }
+        if let Some(offset) = record.img_offset {
+            writeln!(out, "    <byte_runs>")?;
+            writeln!(
+                out,
+                r#"      <byte_run file_offset="0" img_offset="{}" len="{}"/>"#,
+                offset, record.size
+            )?;
+            writeln!(out, "    </byte_runs>")?;
+        }
+        if record.has_bad_sectors {
+            writeln!(out, "    <error>bad sectors</error>")?;
+        }
+        if let Some(hash) = &record.blake3 {
+            writeln!(
+                out,
+                r#"    <hashdigest type="blake3">{}</hashdigest>"#,
+                hash
+            )?;
+        }
+        writeln!(out, "  </fileobject>")?;
+    }
+    writeln!(out, "</dfxml>")
+}
+
+/// Write records as a Sleuth Kit body file
+pub fn write_bodyfile(records: &[ForensicRecord], out: &mut impl Write) -> std::io::Result<()> {
+    let epoch = |time: Option<DateTime<Utc>>| time.map_or(0, |t| t.timestamp());
+    for record in records {
+        // '|' separates fields and mactime has no escaping
+        let name = record.name.replace(['|', '\n'], "_");
+        let name = match record.img_offset {
+            Some(offset) => format!("{} (carved at offset {})", name, offset),
+            None => name,
+        };
+        writeln!(
+            out,
+            "0|{}|0|r/----------|0|0|{}|0|{}|0|{}",
+            name,
+            record.size,
+            epoch(record.modified),
+            epoch(record.created)
+        )?;
+    }
+    Ok(())
+}
+
+/// Write a report file in `format`
+pub fn write_forensic_report(
+    format: ForensicFormat,
+    records: &[ForensicRecord],
+    source: &Path,
+    path: &Path,
+) -> Result<()> {
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("Failed to create report: {}", path.display()))?;
+    let mut out = std::io::BufWriter::new(file);
+    match format {
+        ForensicFormat::Dfxml => {
+            let command_line = std::env::args().collect::<Vec<_>>().join(" ");
+            write_dfxml(records, source, &command_line, &mut out)
+        }
+        ForensicFormat::Bodyfile => write_bodyfile(records, &mut out),
+    }
+    .and_then(|_| out.flush())
+    .with_context(|| format!("Failed to write report: {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dfxml_and_bodyfile_output() {
+        let modified = DateTime::parse_from_rfc3339("2024-03-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let records = vec![
+            ForensicRecord {
+                name: "/mnt/photos/a&b|c.jpg".into(),
+                size: 2048,
+                modified: Some(modified),
+                created: None,
+                blake3: Some("ab".repeat(32)),
+                img_offset: None,
+                has_bad_sectors: false,
+            },
+            ForensicRecord {
+                name: "/out/00000000_000000001000.png".into(),
+                size: 512,
+                modified: None,
+                created: None,
+                blake3: None,
+                img_offset: Some(4096),
+                has_bad_sectors: true,
+            },
+        ];
+
+        let mut xml = Vec::new();
+        write_dfxml(&records, Path::new("/dev/sdb"), "diamond-drill", &mut xml).unwrap();
+        let xml = String::from_utf8(xml).unwrap();
+        assert!(xml.contains("<filename>/mnt/photos/a&amp;b|c.jpg</filename>"));
+        assert!(xml.contains("<mtime>2024-03-01T12:00:00Z</mtime>"));
+        assert!(xml.contains(r#"<byte_run file_offset="0" img_offset="4096" len="512"/>"#));
+        assert_eq!(xml.matches("<fileobject>").count(), 2);
+        assert!(xml.trim_end().ends_with("</dfxml>"));
+
+        let mut body = Vec::new();
+        write_bodyfile(&records, &mut body).unwrap();
+        let body = String::from_utf8(body).unwrap();
+        let lines: Vec<&str> = body.lines().collect();
+        assert_eq!(
+            lines[0],
+            "0|/mnt/photos/a&b_c.jpg|0|r/----------|0|0|2048|0|1709294400|0|0"
+        );
+        assert_eq!(lines[1].split('|').count(), 11);
+        assert!(lines[1].contains("(carved at offset 4096)"));
+    }
+}
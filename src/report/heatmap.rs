@@ -0,0 +1,126 @@
+//! Bad sector heatmaps as inline SVG
+//!
+//! Each [`SectorMap`] is drawn as a grid of cells covering the file or
+//! image from start to end; a cell's red shade grows with the share of its
+//! bytes that could not be read, so a handful of bad sectors on a large
+//! disk still shows up as a visible cell.
+
+use std::fmt::Write as FmtWrite;
+
+use super::{format_bytes, html_escape};
+use crate::badsector::SectorMap;
+
+/// Cells per row
+pub(super) const COLUMNS: u64 = 64;
+/// Rows for a map covering at least `COLUMNS * MAX_ROWS` blocks
+const MAX_ROWS: u64 = 8;
+/// Cell edge in SVG units
+const CELL: u64 = 10;
+const GAP: u64 = 2;
+
+/// One cell of a heatmap: a byte range of the file or image
+pub(super) struct Cell {
+    pub start: u64,
+    pub len: u64,
+    /// Unreadable bytes in the range
+    pub bad: u64,
+}
+
+impl Cell {
+    /// Shade for a cell with bad bytes, 0.5..=1.0; at least half-strength
+    /// so single sectors are visible
+    pub fn intensity(&self) -> f64 {
+        0.5 + (self.bad as f64 / self.len.max(1) as f64).min(1.0) * 0.5
+    }
+}
+
+/// Split `map` into at most `COLUMNS * MAX_ROWS` cells, row by row
+pub(super) fn cells(map: &SectorMap) -> Vec<Cell> {
+    let size = map.file_size.max(1);
+    let block = (map.block_size as u64).max(1);
+    let count = size.div_ceil(block).clamp(1, COLUMNS * MAX_ROWS);
+    let span = size.div_ceil(count);
+
+    let mut cells: Vec<Cell> = (0..count)
+        .map(|i| Cell {
+            start: i * span,
+            len: span.min(size.saturating_sub(i * span)),
+            bad: 0,
+        })
+        .collect();
+    for block in &map.bad_blocks {
+        let end = (block.offset + block.length).min(size);
+        let mut pos = block.offset;
+        while pos < end {
+            let cell = pos / span;
+            let cell_end = ((cell + 1) * span).min(end);
+            if let Some(c) = cells.get_mut(cell as usize) {
+                c.bad += cell_end - pos;
+            }
+            pos = cell_end;
+        }
+    }
+    cells
+}
+
+/// Render `map` as an SVG heatmap
+pub fn heatmap_svg(map: &SectorMap) -> String {
+    let cells = cells(map);
+    let count = cells.len() as u64;
+    let rows = count.div_ceil(COLUMNS);
+
+    let width = COLUMNS.min(count) * (CELL + GAP) - GAP;
+    let height = rows * (CELL + GAP) - GAP;
+    let mut svg = String::with_capacity(cells.len() * 96);
+    let _ = write!(
+        svg,
+        r#"<svg class="heatmap" xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {width} {height}" width="{width}" height="{height}" role="img" aria-label="{label}">"#,
+        label = html_escape(&map.path.display().to_string()),
+    );
+    for (i, cell) in cells.iter().enumerate() {
+        let i = i as u64;
+        let (x, y) = ((i % COLUMNS) * (CELL + GAP), (i / COLUMNS) * (CELL + GAP));
+        let fill = if cell.bad == 0 {
+            "#10b981".to_string()
+        } else {
+            format!("rgba(244,63,94,{:.2})", cell.intensity())
+        };
+        let _ = write!(
+            svg,
+            r#"<rect x="{x}" y="{y}" width="{CELL}" height="{CELL}" rx="2" fill="{fill}"><title>0x{start:X}: {bad} unreadable</title></rect>"#,
+            start = cell.start,
+            bad = format_bytes(cell.bad),
+        );
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::badsector::BlockInfo;
+
+    #[test]
+    fn test_heatmap_marks_bad_cells() {
+        let map = SectorMap {
+            path: "/dev/sdb".into(),
+            total_blocks: 1 << 20,
+            bad_blocks: vec![BlockInfo {
+                offset: 4096 * 1000,
+                length: 512,
+                error: "read error".to_string(),
+                retry_count: 3,
+            }],
+            good_bytes: (4096 << 20) - 512,
+            bad_bytes: 512,
+            file_size: 4096 << 20,
+            block_size: 4096,
+            passes: Vec::new(),
+        };
+        let svg = heatmap_svg(&map);
+        assert_eq!(svg.matches("<rect").count(), 512);
+        assert_eq!(svg.matches("rgba(244,63,94").count(), 1);
+        assert!(svg.contains("512 B unreadable"));
+    }
+}
@@ -6,15 +6,35 @@
 //! - CSS-only file type distribution bar chart
 //! - Thumbnail gallery grid for recovered media
 //! - Chain of custody / forensic provenance section
-//! - PDF export with title page, stats, and hash verification
+//! - Bad sector heatmaps and the proof manifest summary
+//! - PDF deliverable with custody details, a signature block and optional logo
 //!
 //! Reports are fully offline and contain no external dependencies.
 
 use std::fmt::Write as FmtWrite;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result};
 
+use crate::badsector::SectorMap;
+
+mod forensic;
+mod heatmap;
+mod pdf;
+mod runs;
+mod stats;
+
+pub use forensic::{
+    write_bodyfile, write_dfxml, write_forensic_report, ForensicFormat, ForensicRecord,
+};
+pub use heatmap::heatmap_svg;
+pub use pdf::{generate_pdf_report, generate_pdf_report_with, PdfOptions};
+pub use runs::{
+    embed_thumbnails, load_bad_sectors, report_data_for_run, report_data_from_carve,
+    report_data_from_index, report_data_from_proof, DEFAULT_GALLERY_SIZE,
+};
+pub use stats::{Bucket, DuplicateEstimate, LargeFile, StatsReport, DEFAULT_TOP_FILES};
+
 // ---------------------------------------------------------------------------
 // Data structures
 // ---------------------------------------------------------------------------
@@ -54,6 +74,31 @@ pub struct ReportData {
     pub machine: String,
     /// Blake3 root hash of the recovered file tree
     pub root_hash: String,
+    /// Bad sector maps, drawn as heatmaps
+    pub heatmaps: Vec<SectorMap>,
+    /// The run's proof manifest, if it wrote one
+    pub proof: Option<ProofSummary>,
+}
+
+/// What a proof manifest vouches for
+#[derive(Debug, Clone)]
+pub struct ProofSummary {
+    /// Where the manifest was read from
+    pub path: String,
+    /// Merkle root over the entry hashes
+    pub root_hash: String,
+    pub files: usize,
+    pub bytes: u64,
+    pub created_at: String,
+    /// Files whose copy was hashed again after writing
+    pub verified: usize,
+    pub custody_events: usize,
+    /// `signer (key prefix)`, if the manifest is signed
+    pub signed_by: Option<String>,
+    /// `authority at time`, if the root hash is timestamped
+    pub timestamped: Option<String>,
+    /// Outcome of `verify`, when the report was made while verifying
+    pub verification: Option<String>,
 }
 
 /// A single thumbnail entry for the recovered-files gallery.
@@ -63,8 +108,9 @@ pub struct ThumbnailEntry {
     pub name: String,
     /// Full path to the recovered file
     pub path: String,
-    /// Optional relative path to a generated thumbnail image.
-    /// If `None`, a placeholder icon is shown instead.
+    /// Thumbnail image source: a relative path or an embedded `data:` URI
+    /// (see [`embed_thumbnails`]). If `None`, a placeholder icon is shown
+    /// instead.
     pub thumb_path: Option<String>,
     /// File size in bytes
     pub size: u64,
@@ -375,6 +421,17 @@ body {
     word-break: break-all;
 }
 
+/* ---- Bad sector heatmaps ---- */
+.heatmap-block { margin-bottom: 1rem; }
+.heatmap-block .heatmap-label {
+    font-family: var(--mono);
+    font-size: 0.8rem;
+    color: var(--text-muted);
+    margin-bottom: 0.35rem;
+    word-break: break-all;
+}
+.heatmap-block .heatmap { max-width: 100%; height: auto; }
+
 /* ---- Chain of custody ---- */
 .custody-table {
     width: 100%;
@@ -646,6 +703,68 @@ pub fn generate_html_report(data: &ReportData) -> String {
         h.push_str("</div>\n</div>\n");
     }
 
+    // ---- Bad sector heatmaps ----
+    if !data.heatmaps.is_empty() {
+        let _ = write!(
+            h,
+            r#"<div class="card">
+<div class="section-title"><span class="icon">&#x1F525;</span> Bad Sector Map</div>
+"#,
+        );
+        for map in &data.heatmaps {
+            let _ = write!(
+                h,
+                r#"<div class="heatmap-block">
+<div class="heatmap-label">{path} &middot; {bad} unreadable of {size} ({readable:.2}% readable)</div>
+{svg}
+</div>
+"#,
+                path = html_escape(&map.path.display().to_string()),
+                bad = format_bytes(map.bad_bytes),
+                size = format_bytes(map.file_size),
+                readable = map.readable_percent(),
+                svg = heatmap_svg(map),
+            );
+        }
+        h.push_str("</div>\n");
+    }
+
+    // ---- Proof manifest ----
+    if let Some(proof) = &data.proof {
+        let optional =
+            |value: &Option<String>| value.as_deref().map_or("no".to_string(), html_escape);
+        let _ = write!(
+            h,
+            r#"<div class="card">
+<div class="section-title"><span class="icon">&#x1F4DC;</span> Proof Manifest</div>
+<table class="custody-table">
+<tr><th>Manifest</th><td>{path}</td></tr>
+<tr><th>Merkle Root</th><td>{root}</td></tr>
+<tr><th>Files</th><td>{files} ({bytes}), {verified} verified after copy</td></tr>
+<tr><th>Created</th><td>{created}</td></tr>
+<tr><th>Custody Events</th><td>{events}</td></tr>
+<tr><th>Signed</th><td>{signed}</td></tr>
+<tr><th>Timestamped</th><td>{timestamped}</td></tr>
+<tr><th>Verification</th><td>{verification}</td></tr>
+</table>
+</div>
+"#,
+            path = html_escape(&proof.path),
+            root = html_escape(&proof.root_hash),
+            files = proof.files,
+            bytes = format_bytes(proof.bytes),
+            verified = proof.verified,
+            created = html_escape(&proof.created_at),
+            events = proof.custody_events,
+            signed = optional(&proof.signed_by),
+            timestamped = optional(&proof.timestamped),
+            verification = proof
+                .verification
+                .as_deref()
+                .map_or("not verified by this report".to_string(), html_escape),
+        );
+    }
+
     // ---- Errors ----
     if !data.errors.is_empty() {
         let _ = write!(
@@ -730,232 +849,6 @@ pub fn save_html_report(data: &ReportData, path: &Path, open_in_browser: bool) -
     Ok(())
 }
 
-// ---------------------------------------------------------------------------
-// PDF generation (lopdf)
-// ---------------------------------------------------------------------------
-
-/// Generate a PDF recovery report.
-///
-/// The PDF contains:
-/// - Title page with "Diamond Drill Recovery Report"
-/// - Summary statistics as formatted text
-/// - Blake3 root hash for chain-of-custody verification
-/// - Timestamp
-///
-/// Uses the `lopdf` crate to build the PDF structure manually.
-pub fn generate_pdf_report(data: &ReportData, path: &Path) -> Result<()> {
-    use lopdf::dictionary;
-    use lopdf::{Document, Object, Stream};
-
-    // -- Build text content for the PDF page --
-    let speed_str = if data.duration_secs > 0.0 {
-        format!(
-            "{}/s",
-            format_bytes((data.total_bytes as f64 / data.duration_secs) as u64)
-        )
-    } else {
-        "N/A".to_string()
-    };
-
-    let now_utc = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
-
-    // PDF text lines (each will be rendered with Tf/Td/Tj operators)
-    let lines: Vec<(f32, &str, String)> = vec![
-        (24.0, "title", "Diamond Drill Recovery Report".to_string()),
-        (10.0, "spacer", String::new()),
-        (
-            12.0,
-            "body",
-            format!("Case ID:          {}", data.case_id),
-        ),
-        (
-            12.0,
-            "body",
-            format!("Timestamp:        {}", data.timestamp),
-        ),
-        (
-            12.0,
-            "body",
-            format!("Source:           {}", data.source_path),
-        ),
-        (
-            12.0,
-            "body",
-            format!("Destination:      {}", data.dest_path),
-        ),
-        (10.0, "spacer", String::new()),
-        (14.0, "title", "Recovery Summary".to_string()),
-        (10.0, "spacer", String::new()),
-        (
-            12.0,
-            "body",
-            format!("Files Recovered:  {}", data.files_recovered),
-        ),
-        (
-            12.0,
-            "body",
-            format!("Files Failed:     {}", data.files_failed),
-        ),
-        (
-            12.0,
-            "body",
-            format!("Total Size:       {}", format_bytes(data.total_bytes)),
-        ),
-        (
-            12.0,
-            "body",
-            format!("Duration:         {}", format_duration(data.duration_secs)),
-        ),
-        (12.0, "body", format!("Throughput:       {}", speed_str)),
-        (
-            12.0,
-            "body",
-            format!("Bad Sectors:      {}", data.bad_sectors),
-        ),
-        (10.0, "spacer", String::new()),
-        (14.0, "title", "Chain of Custody".to_string()),
-        (10.0, "spacer", String::new()),
-        (
-            12.0,
-            "body",
-            format!("Operator:         {}", data.operator),
-        ),
-        (12.0, "body", format!("Machine:          {}", data.machine)),
-        (
-            12.0,
-            "body",
-            format!("Root Hash:        {}", data.root_hash),
-        ),
-        (
-            12.0,
-            "body",
-            format!("Report Generated: {}", now_utc),
-        ),
-        (10.0, "spacer", String::new()),
-        (10.0, "spacer", String::new()),
-        (
-            9.0,
-            "watermark",
-            "Generated by Diamond Drill".to_string(),
-        ),
-    ];
-
-    // -- Build PDF stream content --
-    let mut stream_content = String::with_capacity(4096);
-    let mut y: f32 = 760.0; // Start near top of A4 (792 pt height)
-
-    for (size, style, text) in &lines {
-        if *style == "spacer" {
-            y -= size * 1.2;
-            continue;
-        }
-
-        let font_tag = if *style == "title" { "/F1" } else { "/F2" };
-
-        // Escape PDF string special chars
-        let escaped = text
-            .replace('\\', "\\\\")
-            .replace('(', "\\(")
-            .replace(')', "\\)");
-
-        let _ = write!(
-            stream_content,
-            "BT\n{font_tag} {size} Tf\n50 {y:.1} Td\n({escaped}) Tj\nET\n",
-            font_tag = font_tag,
-            size = size,
-            y = y,
-            escaped = escaped,
-        );
-        y -= size * 1.5;
-    }
-
-    // -- Assemble lopdf Document --
-    let mut doc = Document::with_version("1.5");
-
-    let pages_id = doc.new_object_id();
-    let page_id = doc.new_object_id();
-    let font1_id = doc.new_object_id();
-    let font2_id = doc.new_object_id();
-    let content_id = doc.new_object_id();
-
-    // Helvetica-Bold for titles
-    doc.objects.insert(
-        font1_id,
-        Object::Dictionary(dictionary! {
-            "Type" => "Font",
-            "Subtype" => "Type1",
-            "BaseFont" => "Helvetica-Bold",
-        }),
-    );
-
-    // Courier for body text (monospace for forensic data)
-    doc.objects.insert(
-        font2_id,
-        Object::Dictionary(dictionary! {
-            "Type" => "Font",
-            "Subtype" => "Type1",
-            "BaseFont" => "Courier",
-        }),
-    );
-
-    // Content stream
-    let stream = Stream::new(dictionary! {}, stream_content.into_bytes());
-    doc.objects.insert(content_id, Object::Stream(stream));
-
-    // Page
-    let resources = dictionary! {
-        "Font" => dictionary! {
-            "F1" => Object::Reference(font1_id),
-            "F2" => Object::Reference(font2_id),
-        },
-    };
-
-    doc.objects.insert(
-        page_id,
-        Object::Dictionary(dictionary! {
-            "Type" => "Page",
-            "Parent" => Object::Reference(pages_id),
-            "MediaBox" => vec![
-                Object::Integer(0),
-                Object::Integer(0),
-                Object::Integer(612),
-                Object::Integer(792),
-            ],
-            "Resources" => resources,
-            "Contents" => Object::Reference(content_id),
-        }),
-    );
-
-    // Pages
-    doc.objects.insert(
-        pages_id,
-        Object::Dictionary(dictionary! {
-            "Type" => "Pages",
-            "Kids" => vec![Object::Reference(page_id)],
-            "Count" => Object::Integer(1),
-        }),
-    );
-
-    // Catalog
-    let catalog_id = doc.add_object(dictionary! {
-        "Type" => "Catalog",
-        "Pages" => Object::Reference(pages_id),
-    });
-    doc.trailer.set("Root", Object::Reference(catalog_id));
-
-    // -- Write PDF file --
-    if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent)
-            .with_context(|| format!("Failed to create directory {}", parent.display()))?;
-    }
-
-    doc.save(path)
-        .with_context(|| format!("Failed to write PDF report to {}", path.display()))?;
-
-    tracing::info!("PDF report saved to {}", path.display());
-    Ok(())
-}
-
 // ---------------------------------------------------------------------------
 // Convenience: build ReportData from an ExportManifest
 // ---------------------------------------------------------------------------
@@ -971,49 +864,34 @@ pub fn report_data_from_manifest(manifest_path: &Path) -> Result<ReportData> {
     let manifest: crate::export::ExportManifest = serde_json::from_str(&data)
         .with_context(|| format!("Failed to parse manifest: {}", manifest_path.display()))?;
 
-    // Build file type counts from manifest entries
-    let mut type_counts: std::collections::HashMap<String, (usize, u64)> =
-        std::collections::HashMap::new();
-    for entry in &manifest.entries {
-        let ext = std::path::Path::new(&entry.source_path)
-            .extension()
-            .and_then(|e| e.to_str())
-            .unwrap_or("unknown")
-            .to_uppercase();
-        let counter = type_counts.entry(ext).or_insert((0, 0));
-        counter.0 += 1;
-        counter.1 += entry.size;
-    }
-
-    let mut file_type_counts: Vec<(String, usize, u64)> = type_counts
-        .into_iter()
-        .map(|(name, (count, bytes))| (name, count, bytes))
-        .collect();
-    file_type_counts.sort_by(|a, b| b.1.cmp(&a.1));
-
-    // Compute root hash from all entry hashes
-    let mut hasher = blake3::Hasher::new();
-    for entry in &manifest.entries {
-        hasher.update(entry.blake3_hash.as_bytes());
-    }
-    let root_hash = hex::encode(hasher.finalize().as_bytes());
-
-    let operator = format!(
-        "{}@{}",
-        whoami::username(),
-        hostname::get()
-            .map(|h| h.to_string_lossy().to_string())
-            .unwrap_or_else(|_| "unknown".to_string())
-    );
-
-    let machine = format!(
-        "{} ({} CPUs, {})",
-        hostname::get()
-            .map(|h| h.to_string_lossy().to_string())
-            .unwrap_or_else(|_| "unknown".to_string()),
-        num_cpus::get(),
-        std::env::consts::ARCH,
+    let file_type_counts = runs::file_type_counts(manifest.entries.iter().map(|e| {
+        let ext = Path::new(&e.source_path).extension();
+        (ext.and_then(|x| x.to_str()), e.size)
+    }));
+    let root_hash = runs::hash_list_root(manifest.entries.iter().map(|e| e.blake3_hash.as_str()));
+    let (operator, machine) = runs::operator_and_machine();
+    let thumbnails = runs::gallery_entries(
+        manifest
+            .entries
+            .iter()
+            .map(|e| (PathBuf::from(&e.dest_path), e.size)),
     );
+    let proof = runs::proof_summary_near(manifest_path);
+    // Files that needed the sector reader, and what it could not read
+    let bad_sectors = manifest
+        .entries
+        .iter()
+        .filter(|e| e.retry_history.iter().any(|a| a.sector_reader))
+        .count();
+    let errors = manifest
+        .entries
+        .iter()
+        .flat_map(|e| {
+            e.retry_history
+                .iter()
+                .filter_map(|a| Some(format!("{}: {}", e.source_path, a.note.as_ref()?)))
+        })
+        .collect();
 
     Ok(ReportData {
         title: "Recovery Report".to_string(),
@@ -1025,13 +903,15 @@ pub fn report_data_from_manifest(manifest_path: &Path) -> Result<ReportData> {
         files_recovered: manifest.total_files,
         files_failed: 0,
         total_bytes: manifest.total_bytes,
-        bad_sectors: 0,
+        bad_sectors,
         file_type_counts,
-        thumbnails: Vec::new(),
-        errors: Vec::new(),
+        thumbnails,
+        errors,
         operator,
         machine,
         root_hash,
+        heatmaps: Vec::new(),
+        proof,
     })
 }
 
@@ -1087,6 +967,8 @@ mod tests {
             machine: "DRILL-RIG (16 CPUs, x86_64)".to_string(),
             root_hash: "a1b2c3d4e5f6a1b2c3d4e5f6a1b2c3d4e5f6a1b2c3d4e5f6a1b2c3d4e5f6a1b2"
                 .to_string(),
+            heatmaps: Vec::new(),
+            proof: None,
         }
     }
 
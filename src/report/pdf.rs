@@ -0,0 +1,526 @@
+//! PDF reports for chain-of-custody deliverables
+//!
+//! Built directly with `lopdf` from the standard Type1 fonts, so the file
+//! needs no embedded fonts and opens anywhere. Content flows over as many
+//! Letter pages as it needs; every page carries a footer with the root
+//! hash and page number so loose pages can be matched to their report.
+
+use std::fmt::Write as FmtWrite;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use lopdf::{dictionary, Document, Object, Stream};
+
+use super::heatmap::{cells, COLUMNS};
+use super::{format_bytes, format_duration, ReportData};
+
+const PAGE_WIDTH: f32 = 612.0;
+const PAGE_HEIGHT: f32 = 792.0;
+const MARGIN: f32 = 50.0;
+/// Space kept free at the bottom of each page for the footer
+const FOOTER_HEIGHT: f32 = 30.0;
+/// Courier is 0.6 em wide
+const BODY_SIZE: f32 = 9.5;
+const BODY_COLUMNS: usize = ((PAGE_WIDTH - 2.0 * MARGIN) / (BODY_SIZE * 0.6)) as usize;
+const LABEL_WIDTH: usize = 18;
+/// Errors listed before the rest are summarised as a count
+const MAX_ERRORS: usize = 60;
+/// Logo bounding box in points
+const LOGO_WIDTH: f32 = 140.0;
+const LOGO_HEIGHT: f32 = 48.0;
+
+/// Extras for [`generate_pdf_report_with`]
+#[derive(Debug, Clone, Default)]
+pub struct PdfOptions {
+    /// PNG or JPEG printed at the top of the first page (e.g. the lab's)
+    pub logo: Option<PathBuf>,
+}
+
+/// Generate a PDF recovery report.
+///
+/// The PDF contains:
+/// - Case details and the recovery / export summary
+/// - Operator, machine and Blake3 root hash for chain of custody
+/// - The proof manifest summary and verification outcome, if any
+/// - Bad sector maps as heatmap grids
+/// - A signature block for the examiner
+pub fn generate_pdf_report(data: &ReportData, path: &Path) -> Result<()> {
+    generate_pdf_report_with(data, path, &PdfOptions::default())
+}
+
+/// [`generate_pdf_report`] with a logo
+pub fn generate_pdf_report_with(
+    data: &ReportData,
+    path: &Path,
+    options: &PdfOptions,
+) -> Result<()> {
+    let logo = options
+        .logo
+        .as_deref()
+        .map(|logo| {
+            image::open(logo)
+                .map(|img| img.to_rgb8())
+                .with_context(|| format!("Failed to read logo {}", logo.display()))
+        })
+        .transpose()?;
+
+    let mut layout = Layout::new();
+    if let Some(logo) = &logo {
+        let scale = (LOGO_WIDTH / logo.width() as f32).min(LOGO_HEIGHT / logo.height() as f32);
+        let (w, h) = (logo.width() as f32 * scale, logo.height() as f32 * scale);
+        let _ = writeln!(
+            layout.page(),
+            "q\n{w:.2} 0 0 {h:.2} {x:.2} {y:.2} cm\n/Logo Do\nQ",
+            x = PAGE_WIDTH - MARGIN - w,
+            y = PAGE_HEIGHT - MARGIN - h,
+        );
+    }
+    write_body(&mut layout, data);
+
+    let generated = chrono::Utc::now()
+        .format("%Y-%m-%d %H:%M:%S UTC")
+        .to_string();
+    let total = layout.pages.len();
+    for (i, page) in layout.pages.iter_mut().enumerate() {
+        let footer = format!(
+            "Diamond Drill | {} | root {} | generated {} | page {} of {}",
+            data.case_id,
+            &data.root_hash[..data.root_hash.len().min(16)],
+            generated,
+            i + 1,
+            total
+        );
+        text_at(page, Font::Sans, 7.5, MARGIN, MARGIN - 15.0, &footer);
+    }
+
+    save(&layout.pages, logo.as_ref(), path)?;
+    tracing::info!("PDF report saved to {}", path.display());
+    Ok(())
+}
+
+fn write_body(layout: &mut Layout, data: &ReportData) {
+    layout.line(Font::Bold, 20.0, &format!("Diamond Drill {}", data.title));
+    layout.gap(6.0);
+
+    layout.heading("Case");
+    layout.field("Case ID", &data.case_id);
+    layout.field("Timestamp", &data.timestamp);
+    layout.field("Source", &data.source_path);
+    layout.field("Destination", &data.dest_path);
+
+    layout.heading("Summary");
+    let throughput = if data.duration_secs > 0.0 {
+        format!(
+            "{}/s",
+            format_bytes((data.total_bytes as f64 / data.duration_secs) as u64)
+        )
+    } else {
+        "N/A".to_string()
+    };
+    layout.field("Files", &data.files_recovered.to_string());
+    layout.field("Files Failed", &data.files_failed.to_string());
+    layout.field("Total Size", &format_bytes(data.total_bytes));
+    layout.field("Duration", &format_duration(data.duration_secs));
+    layout.field("Throughput", &throughput);
+    layout.field("Bad Sectors", &data.bad_sectors.to_string());
+    for (name, count, bytes) in data.file_type_counts.iter().take(10) {
+        layout.field(
+            &format!("  {}", name),
+            &format!("{} files, {}", count, format_bytes(*bytes)),
+        );
+    }
+
+    layout.heading("Chain of Custody");
+    layout.field("Operator", &data.operator);
+    layout.field("Machine", &data.machine);
+    layout.field("Root Hash", &data.root_hash);
+
+    if let Some(proof) = &data.proof {
+        let optional = |value: &Option<String>| value.clone().unwrap_or_else(|| "no".to_string());
+        layout.heading("Proof Manifest");
+        layout.field("Manifest", &proof.path);
+        layout.field("Merkle Root", &proof.root_hash);
+        layout.field(
+            "Files",
+            &format!(
+                "{} ({}), {} verified after copy",
+                proof.files,
+                format_bytes(proof.bytes),
+                proof.verified
+            ),
+        );
+        layout.field("Created", &proof.created_at);
+        layout.field("Custody Events", &proof.custody_events.to_string());
+        layout.field("Signed", &optional(&proof.signed_by));
+        layout.field("Timestamped", &optional(&proof.timestamped));
+        if let Some(verification) = &proof.verification {
+            layout.field("Verification", verification);
+        }
+    }
+
+    if !data.heatmaps.is_empty() {
+        layout.heading("Bad Sector Map");
+        for map in &data.heatmaps {
+            layout.wrapped(Font::Mono, &map.path.display().to_string());
+            layout.wrapped(
+                Font::Mono,
+                &format!(
+                    "{} unreadable of {} ({:.2}% readable)",
+                    format_bytes(map.bad_bytes),
+                    format_bytes(map.file_size),
+                    map.readable_percent()
+                ),
+            );
+            layout.heatmap(map);
+        }
+    }
+
+    if !data.errors.is_empty() {
+        layout.heading("Errors");
+        for error in data.errors.iter().take(MAX_ERRORS) {
+            layout.wrapped(Font::Mono, error);
+        }
+        if data.errors.len() > MAX_ERRORS {
+            layout.wrapped(
+                Font::Mono,
+                &format!("... and {} more", data.errors.len() - MAX_ERRORS),
+            );
+        }
+    }
+
+    layout.signature_block();
+}
+
+#[derive(Clone, Copy)]
+enum Font {
+    Bold,
+    Sans,
+    Mono,
+}
+
+impl Font {
+    fn resource(self) -> &'static str {
+        match self {
+            Font::Bold => "/F1",
+            Font::Mono => "/F2",
+            Font::Sans => "/F3",
+        }
+    }
+}
+
+/// Content streams of the pages so far and the cursor on the last one
+struct Layout {
+    pages: Vec<String>,
+    y: f32,
+}
+
+impl Layout {
+    fn new() -> Self {
+        Self {
+            pages: vec![String::new()],
+            y: PAGE_HEIGHT - MARGIN,
+        }
+    }
+
+    fn page(&mut self) -> &mut String {
+        self.pages.last_mut().expect("layout has a page")
+    }
+
+    /// Start a new page unless `height` still fits on this one
+    fn reserve(&mut self, height: f32) {
+        if self.y - height < MARGIN + FOOTER_HEIGHT {
+            self.pages.push(String::new());
+            self.y = PAGE_HEIGHT - MARGIN;
+        }
+    }
+
+    fn gap(&mut self, height: f32) {
+        self.y -= height;
+    }
+
+    fn line(&mut self, font: Font, size: f32, text: &str) {
+        self.reserve(size * 1.4);
+        self.y -= size;
+        let y = self.y;
+        text_at(self.page(), font, size, MARGIN, y, text);
+        self.y -= size * 0.4;
+    }
+
+    /// Section title with a rule under it, kept with the first lines below
+    fn heading(&mut self, title: &str) {
+        self.reserve(60.0);
+        self.gap(10.0);
+        self.line(Font::Bold, 13.0, title);
+        let y = self.y;
+        let _ = writeln!(
+            self.page(),
+            "0.6 G 0.5 w {MARGIN} {y:.2} m {x:.2} {y:.2} l S 0 G",
+            x = PAGE_WIDTH - MARGIN,
+        );
+        self.gap(5.0);
+    }
+
+    /// `Label:  value`, the value wrapped under itself
+    fn field(&mut self, label: &str, value: &str) {
+        let indent = " ".repeat(LABEL_WIDTH);
+        for (i, chunk) in wrap(value, BODY_COLUMNS - LABEL_WIDTH).iter().enumerate() {
+            let line = if i == 0 {
+                format!(
+                    "{:<width$}{}",
+                    format!("{}:", label),
+                    chunk,
+                    width = LABEL_WIDTH
+                )
+            } else {
+                format!("{}{}", indent, chunk)
+            };
+            self.line(Font::Mono, BODY_SIZE, &line);
+        }
+    }
+
+    fn wrapped(&mut self, font: Font, text: &str) {
+        for chunk in wrap(text, BODY_COLUMNS) {
+            self.line(font, BODY_SIZE, &chunk);
+        }
+    }
+
+    /// The same grid as the HTML heatmap: green readable cells, red cells
+    /// shaded by how much of them could not be read
+    fn heatmap(&mut self, map: &crate::badsector::SectorMap) {
+        let cells = cells(map);
+        let pitch = (PAGE_WIDTH - 2.0 * MARGIN) / COLUMNS as f32;
+        let rows = cells.len().div_ceil(COLUMNS as usize);
+        self.reserve(rows as f32 * pitch + 10.0);
+        self.gap(4.0);
+        let top = self.y;
+        let page = self.page();
+        for (i, cell) in cells.iter().enumerate() {
+            let (column, row) = (i % COLUMNS as usize, i / COLUMNS as usize);
+            let (r, g, b) = if cell.bad == 0 {
+                (0.063, 0.725, 0.506)
+            } else {
+                // rgb(244, 63, 94) over white at the cell's intensity
+                let alpha = cell.intensity() as f32;
+                let blend = |c: f32| 1.0 - alpha * (1.0 - c / 255.0);
+                (blend(244.0), blend(63.0), blend(94.0))
+            };
+            let _ = writeln!(
+                page,
+                "{r:.3} {g:.3} {b:.3} rg {x:.2} {y:.2} {w:.2} {w:.2} re f",
+                x = MARGIN + column as f32 * pitch,
+                y = top - (row + 1) as f32 * pitch,
+                w = pitch - 1.0,
+            );
+        }
+        page.push_str("0 g\n");
+        self.y = top - rows as f32 * pitch - 8.0;
+    }
+
+    /// Lines for the examiner to sign on a printed copy
+    fn signature_block(&mut self) {
+        self.reserve(150.0);
+        self.heading("Signature");
+        self.wrapped(
+            Font::Sans,
+            "I confirm that this report accurately describes the evidence handled and the \
+             operations performed on it.",
+        );
+        self.gap(12.0);
+        for label in ["Examiner (name)", "Signature", "Date"] {
+            self.gap(22.0);
+            let y = self.y;
+            let page = self.page();
+            text_at(page, Font::Sans, BODY_SIZE, MARGIN, y, label);
+            let _ = writeln!(
+                page,
+                "0.5 w {x0:.2} {y:.2} m {x1:.2} {y:.2} l S",
+                x0 = MARGIN + 110.0,
+                x1 = MARGIN + 360.0,
+            );
+        }
+    }
+}
+
+fn text_at(page: &mut String, font: Font, size: f32, x: f32, y: f32, text: &str) {
+    let _ = writeln!(
+        page,
+        "BT\n{} {size} Tf\n{x:.2} {y:.2} Td\n({}) Tj\nET",
+        font.resource(),
+        pdf_escape(text),
+    );
+}
+
+/// Split `text` into lines of at most `width` characters, preferring to
+/// break at spaces and slashes
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut lines = Vec::new();
+    let mut start = 0;
+    while chars.len() - start > width {
+        let end = start + width;
+        let split = (start + width / 2..end)
+            .rev()
+            .find(|&i| matches!(chars[i], ' ' | '/' | '\\' | ','))
+            .map_or(end, |i| i + 1);
+        lines.push(
+            chars[start..split]
+                .iter()
+                .collect::<String>()
+                .trim_end()
+                .to_string(),
+        );
+        start = split;
+        while chars.get(start) == Some(&' ') {
+            start += 1;
+        }
+    }
+    lines.push(chars[start..].iter().collect());
+    lines
+}
+
+/// Escape a PDF string; the standard fonts only cover ASCII reliably
+fn pdf_escape(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\\' | '(' | ')' => format!("\\{}", c),
+            ' '..='~' => c.to_string(),
+            _ => "?".to_string(),
+        })
+        .collect()
+}
+
+fn save(pages: &[String], logo: Option<&image::RgbImage>, path: &Path) -> Result<()> {
+    let mut doc = Document::with_version("1.5");
+    let pages_id = doc.new_object_id();
+
+    let fonts = dictionary! {
+        "F1" => doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Helvetica-Bold",
+        }),
+        // Courier for forensic data, so hashes and paths line up
+        "F2" => doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Courier",
+        }),
+        "F3" => doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Helvetica",
+        }),
+    };
+    let mut resources = dictionary! { "Font" => fonts };
+    if let Some(logo) = logo {
+        let mut image = Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Image",
+                "Width" => logo.width() as i64,
+                "Height" => logo.height() as i64,
+                "ColorSpace" => "DeviceRGB",
+                "BitsPerComponent" => 8,
+            },
+            logo.as_raw().clone(),
+        );
+        image.compress().context("Failed to compress logo")?;
+        resources.set("XObject", dictionary! { "Logo" => doc.add_object(image) });
+    }
+    let resources_id = doc.add_object(resources);
+
+    let mut kids = Vec::with_capacity(pages.len());
+    for content in pages {
+        let mut stream = Stream::new(dictionary! {}, content.clone().into_bytes());
+        stream.compress().context("Failed to compress page")?;
+        let content_id = doc.add_object(stream);
+        kids.push(Object::Reference(doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), PAGE_WIDTH.into(), PAGE_HEIGHT.into()],
+            "Resources" => resources_id,
+            "Contents" => content_id,
+        })));
+    }
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Count" => kids.len() as i64,
+            "Kids" => kids,
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory {}", parent.display()))?;
+    }
+    doc.save(path)
+        .with_context(|| format!("Failed to write PDF report to {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap_breaks_long_paths() {
+        let path = "/evidence/drive/Users/someone/Pictures/2019/holiday/IMG_0001.JPG";
+        let lines = wrap(path, 30);
+        assert!(lines.iter().all(|l| l.chars().count() <= 30));
+        assert_eq!(lines.concat(), path);
+        assert!(lines[0].ends_with('/'));
+        assert_eq!(pdf_escape("a (b) \\ é"), "a \\(b\\) \\\\ ?");
+    }
+
+    #[test]
+    fn test_long_report_flows_over_pages_with_logo() {
+        let dir = tempfile::tempdir().unwrap();
+        let logo = dir.path().join("logo.png");
+        image::RgbImage::from_pixel(200, 50, image::Rgb([10, 20, 200]))
+            .save(&logo)
+            .unwrap();
+        let data = ReportData {
+            title: "Recovery Report".to_string(),
+            case_id: "DD-1".to_string(),
+            source_path: "/dev/sdb".to_string(),
+            dest_path: "/out".to_string(),
+            timestamp: "2026-01-01 00:00:00 UTC".to_string(),
+            duration_secs: 1.0,
+            files_recovered: 1,
+            files_failed: 0,
+            total_bytes: 1,
+            bad_sectors: 0,
+            file_type_counts: Vec::new(),
+            thumbnails: Vec::new(),
+            errors: (0..200)
+                .map(|i| format!("/out/file{}: read error", i))
+                .collect(),
+            operator: "examiner@lab".to_string(),
+            machine: "lab".to_string(),
+            root_hash: "ab".repeat(32),
+            heatmaps: Vec::new(),
+            proof: None,
+        };
+        let path = dir.path().join("report.pdf");
+        generate_pdf_report_with(&data, &path, &PdfOptions { logo: Some(logo) }).unwrap();
+
+        let doc = Document::load(&path).unwrap();
+        let pages = doc.get_pages();
+        assert!(pages.len() > 1);
+        let last = doc
+            .get_page_content(*pages.values().last().unwrap())
+            .unwrap();
+        let last = String::from_utf8_lossy(&last);
+        assert!(last.contains(&format!("page {} of {}", pages.len(), pages.len())));
+        assert!(last.contains("(Signature) Tj"));
+        let first = doc.get_page_content(pages[&1]).unwrap();
+        assert!(String::from_utf8_lossy(&first).contains("/Logo Do"));
+    }
+}
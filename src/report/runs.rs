@@ -0,0 +1,462 @@
+//! Report data for index, carve and export runs
+//!
+//! Each kind of run leaves different traces: an export writes its manifest
+//! (and proof manifest) into the destination, a carve its listing into the
+//! output directory, and an index lives with the engine. These build one
+//! [`ReportData`] from whichever is given, with the gallery filled from
+//! the recovered images and thumbnails embedded as `data:` URIs so the
+//! HTML file can be handed over on its own.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use base64::Engine;
+
+use super::{report_data_from_manifest, ProofSummary, ReportData, ThumbnailEntry};
+use crate::badsector::{BadSectorReport, RescueMap, SectorMap};
+use crate::carve::{CarveListing, CARVE_LISTING_FILE};
+use crate::core::FileEntry;
+use crate::export::{MANIFEST_FILE, PROOF_MANIFEST_FILE};
+use crate::preview::{is_previewable, ThumbnailGenerator};
+use crate::proof::{ProofManifest, TamperType, VerifyResult};
+
+/// Images embedded in the gallery unless told otherwise
+pub const DEFAULT_GALLERY_SIZE: usize = 48;
+
+/// Edge of embedded thumbnails in pixels
+const THUMB_SIZE: u32 = 160;
+
+/// Block size mapfile regions are drawn at
+const MAPFILE_BLOCK_SIZE: usize = 4096;
+
+/// Build the report for an export or carve output (a directory, manifest
+/// or carve listing). Returns `None` for anything else, e.g. an indexed
+/// source.
+pub fn report_data_for_run(path: &Path) -> Result<Option<ReportData>> {
+    if path.is_dir() {
+        if path.join(MANIFEST_FILE).is_file() {
+            return report_data_from_manifest(&path.join(MANIFEST_FILE)).map(Some);
+        }
+        if path.join(CARVE_LISTING_FILE).is_file() {
+            let listing = CarveListing::load(path)?;
+            return Ok(Some(report_data_from_carve(&listing, path)));
+        }
+        return Ok(None);
+    }
+    if path.file_name().is_some_and(|n| n == CARVE_LISTING_FILE) {
+        let listing = CarveListing::load(path)?;
+        let dir = path.parent().unwrap_or(Path::new("."));
+        return Ok(Some(report_data_from_carve(&listing, dir)));
+    }
+    if path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("json"))
+    {
+        return report_data_from_manifest(path).map(Some);
+    }
+    Ok(None)
+}
+
+/// Build a ReportData from a carve run's listing; `dir` is the carve
+/// output directory.
+pub fn report_data_from_carve(listing: &CarveListing, dir: &Path) -> ReportData {
+    let result = &listing.result;
+    let (operator, machine) = operator_and_machine();
+    let mut errors = Vec::new();
+    if result.files_in_bad_regions > 0 {
+        errors.push(format!(
+            "{} carved files overlap regions the mapfile marks unreadable",
+            result.files_in_bad_regions
+        ));
+    }
+    for file in listing.files.iter().filter(|f| f.bad_region_bytes > 0) {
+        errors.push(format!(
+            "{} at offset 0x{:X}: {} bytes from unreadable regions",
+            file.path
+                .as_deref()
+                .map_or(file.signature_name.clone(), |p| p.display().to_string()),
+            file.offset,
+            file.bad_region_bytes
+        ));
+    }
+
+    ReportData {
+        title: "Carve Report".to_string(),
+        case_id: chrono::Utc::now().format("DD-%Y%m%d-%H%M").to_string(),
+        source_path: listing.source.display().to_string(),
+        dest_path: dir.display().to_string(),
+        timestamp: modified_time(&dir.join(CARVE_LISTING_FILE)),
+        duration_secs: result.duration_ms as f64 / 1000.0,
+        files_recovered: result.files_extracted,
+        files_failed: result.files_failed,
+        total_bytes: result.total_bytes_extracted,
+        bad_sectors: 0,
+        file_type_counts: file_type_counts(
+            listing
+                .files
+                .iter()
+                .map(|f| (Some(f.extension.as_str()), f.size)),
+        ),
+        thumbnails: gallery_entries(
+            listing
+                .files
+                .iter()
+                .filter_map(|f| Some((f.path.clone()?, f.size))),
+        ),
+        errors,
+        operator,
+        machine,
+        root_hash: hash_list_root(listing.files.iter().filter_map(|f| f.hash.as_deref())),
+        heatmaps: Vec::new(),
+        proof: None,
+    }
+}
+
+/// Build a ReportData from the files indexed in `source`
+pub fn report_data_from_index(source: &Path, entries: &[FileEntry]) -> ReportData {
+    let (operator, machine) = operator_and_machine();
+    ReportData {
+        title: "Index Report".to_string(),
+        case_id: chrono::Utc::now().format("DD-%Y%m%d-%H%M").to_string(),
+        source_path: source.display().to_string(),
+        dest_path: "(not exported)".to_string(),
+        timestamp: chrono::Utc::now()
+            .format("%Y-%m-%d %H:%M:%S UTC")
+            .to_string(),
+        duration_secs: 0.0,
+        files_recovered: entries.len(),
+        files_failed: 0,
+        total_bytes: entries.iter().map(|e| e.size).sum(),
+        bad_sectors: entries.iter().filter(|e| e.has_bad_sectors).count(),
+        file_type_counts: file_type_counts(
+            entries
+                .iter()
+                .map(|e| (Some(e.extension.as_str()).filter(|x| !x.is_empty()), e.size)),
+        ),
+        thumbnails: gallery_entries(entries.iter().map(|e| (e.path.clone(), e.size))),
+        errors: Vec::new(),
+        operator,
+        machine,
+        root_hash: hash_list_root(entries.iter().filter_map(|e| e.hash.as_deref())),
+        heatmaps: Vec::new(),
+        proof: None,
+    }
+}
+
+/// Load bad sector maps from a bad sector report (`index
+/// --bad-sector-report`, JSON) or a ddrescue mapfile. A mapfile only
+/// lists regions, so `image_size` gives the extent of the device when
+/// known; otherwise the map ends with the last region listed.
+pub fn load_bad_sectors(path: &Path, image_size: Option<u64>) -> Result<Vec<SectorMap>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    if let Ok(report) = serde_json::from_str::<BadSectorReport>(&text) {
+        return Ok(report.files);
+    }
+    let map = RescueMap::parse(&text).with_context(|| {
+        format!(
+            "{} is neither a bad sector report nor a mapfile",
+            path.display()
+        )
+    })?;
+    let extent = map.regions.last().map_or(0, |r| r.end());
+    let image = path.with_extension("");
+    Ok(vec![map.to_sector_map(
+        &image,
+        image_size.unwrap_or(extent).max(extent),
+        MAPFILE_BLOCK_SIZE,
+    )])
+}
+
+/// Keep the first `limit` gallery entries and embed their thumbnails.
+/// Returns how many were embedded; the rest keep a placeholder.
+pub fn embed_thumbnails(data: &mut ReportData, limit: usize) -> usize {
+    data.thumbnails.truncate(limit);
+    let generator = ThumbnailGenerator::new();
+    let mut embedded = 0;
+    for thumb in &mut data.thumbnails {
+        let image = generator
+            .generate(Path::new(&thumb.path), THUMB_SIZE)
+            .and_then(|p| Ok((std::fs::read(&p)?, p)));
+        match image {
+            Ok((bytes, thumb_file)) => {
+                let mime = match thumb_file.extension().and_then(|e| e.to_str()) {
+                    Some("png") => "image/png",
+                    _ => "image/jpeg",
+                };
+                thumb.thumb_path = Some(format!(
+                    "data:{};base64,{}",
+                    mime,
+                    base64::engine::general_purpose::STANDARD.encode(bytes)
+                ));
+                embedded += 1;
+            }
+            Err(e) => tracing::debug!("No thumbnail for {}: {}", thumb.path, e),
+        }
+    }
+    embedded
+}
+
+/// Build a ReportData from a proof manifest, with the outcome of
+/// verifying it when `verification` is given (`verify --report pdf`)
+pub fn report_data_from_proof(
+    path: &Path,
+    manifest: &ProofManifest,
+    verification: Option<&VerifyResult>,
+) -> ReportData {
+    let custody = &manifest.chain_of_custody;
+    let duration_secs = custody.completed_at.map_or(0.0, |end| {
+        (end - custody.started_at).num_milliseconds() as f64 / 1000.0
+    });
+    let mut errors: Vec<String> = manifest
+        .entries
+        .iter()
+        .filter_map(|e| {
+            Some(format!(
+                "{}: {}",
+                e.source_path,
+                e.bad_sector_notes.as_ref()?
+            ))
+        })
+        .collect();
+    if let Some(result) = verification {
+        errors.extend(result.tampered.iter().map(|t| {
+            let issue = match t.issue {
+                TamperType::HashMismatch => "HASH MISMATCH",
+                TamperType::Missing => "MISSING",
+                TamperType::SizeChanged => "SIZE CHANGED",
+            };
+            format!("{}: {}", t.path, issue)
+        }));
+    }
+
+    ReportData {
+        title: if verification.is_some() {
+            "Verification Report".to_string()
+        } else {
+            "Proof Report".to_string()
+        },
+        case_id: manifest.created_at.format("DD-%Y%m%d-%H%M").to_string(),
+        source_path: manifest.source_root.clone(),
+        dest_path: manifest.dest_root.clone(),
+        timestamp: manifest
+            .created_at
+            .format("%Y-%m-%d %H:%M:%S UTC")
+            .to_string(),
+        duration_secs,
+        files_recovered: manifest.total_files,
+        files_failed: verification.map_or(0, |r| r.failed + r.missing),
+        total_bytes: manifest.total_bytes,
+        bad_sectors: manifest
+            .entries
+            .iter()
+            .filter(|e| e.bad_sector_notes.is_some())
+            .count(),
+        file_type_counts: file_type_counts(manifest.entries.iter().map(|e| {
+            let ext = Path::new(&e.source_path).extension();
+            (ext.and_then(|x| x.to_str()), e.size)
+        })),
+        thumbnails: gallery_entries(
+            manifest
+                .entries
+                .iter()
+                .map(|e| (PathBuf::from(&e.dest_path), e.size)),
+        ),
+        errors,
+        operator: custody.operator.clone(),
+        machine: custody.machine.clone(),
+        root_hash: manifest.root_hash.clone(),
+        heatmaps: Vec::new(),
+        proof: Some(proof_summary(path, manifest, verification)),
+    }
+}
+
+/// Summary of the proof manifest written next to an export manifest
+pub(super) fn proof_summary_near(manifest_path: &Path) -> Option<ProofSummary> {
+    let path = manifest_path.parent()?.join(PROOF_MANIFEST_FILE);
+    if !path.is_file() {
+        return None;
+    }
+    match crate::proof::load_manifest(&path) {
+        Ok(manifest) => Some(proof_summary(&path, &manifest, None)),
+        Err(e) => {
+            tracing::warn!("Ignoring proof manifest {}: {:#}", path.display(), e);
+            None
+        }
+    }
+}
+
+fn proof_summary(
+    path: &Path,
+    manifest: &ProofManifest,
+    verification: Option<&VerifyResult>,
+) -> ProofSummary {
+    ProofSummary {
+        path: path.display().to_string(),
+        root_hash: manifest.root_hash.clone(),
+        files: manifest.total_files,
+        bytes: manifest.total_bytes,
+        created_at: manifest
+            .created_at
+            .format("%Y-%m-%d %H:%M:%S UTC")
+            .to_string(),
+        verified: manifest.entries.iter().filter(|e| e.verified).count(),
+        custody_events: manifest.chain_of_custody.events.len(),
+        signed_by: manifest.chain_of_custody.signature.as_ref().map(|s| {
+            format!(
+                "{} (key {}...)",
+                s.signer,
+                &s.public_key[..s.public_key.len().min(16)]
+            )
+        }),
+        timestamped: manifest
+            .chain_of_custody
+            .timestamp
+            .as_ref()
+            .map(|t| format!("{} at {}", t.tsa_url, t.gen_time.to_rfc3339())),
+        verification: verification.map(|r| {
+            format!(
+                "{} on {}: {}/{} verified, {} failed, {} missing, root hash {}",
+                if r.is_clean() { "PASSED" } else { "FAILED" },
+                chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC"),
+                r.verified,
+                r.total,
+                r.failed,
+                r.missing,
+                if r.root_hash_valid {
+                    "valid"
+                } else {
+                    "INVALID"
+                }
+            )
+        }),
+    }
+}
+
+/// Per-extension (count, bytes), most files first
+pub(super) fn file_type_counts<'a>(
+    files: impl Iterator<Item = (Option<&'a str>, u64)>,
+) -> Vec<(String, usize, u64)> {
+    let mut counts: HashMap<String, (usize, u64)> = HashMap::new();
+    for (ext, size) in files {
+        let counter = counts
+            .entry(ext.unwrap_or("unknown").to_uppercase())
+            .or_default();
+        counter.0 += 1;
+        counter.1 += size;
+    }
+    let mut counts: Vec<(String, usize, u64)> = counts
+        .into_iter()
+        .map(|(name, (count, bytes))| (name, count, bytes))
+        .collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts
+}
+
+/// Blake3 over the concatenated file hashes, in the order given
+pub(super) fn hash_list_root<'a>(hashes: impl Iterator<Item = &'a str>) -> String {
+    let mut hasher = blake3::Hasher::new();
+    for hash in hashes {
+        hasher.update(hash.as_bytes());
+    }
+    hex::encode(hasher.finalize().as_bytes())
+}
+
+/// `user@host` and `host (N CPUs, arch)` of this machine
+pub(super) fn operator_and_machine() -> (String, String) {
+    let host = hostname::get()
+        .map(|h| h.to_string_lossy().to_string())
+        .unwrap_or_else(|_| "unknown".to_string());
+    (
+        format!("{}@{}", whoami::username(), host),
+        format!(
+            "{} ({} CPUs, {})",
+            host,
+            num_cpus::get(),
+            std::env::consts::ARCH
+        ),
+    )
+}
+
+/// Gallery entries for the previewable images among `files`
+pub(super) fn gallery_entries(files: impl Iterator<Item = (PathBuf, u64)>) -> Vec<ThumbnailEntry> {
+    files
+        .filter(|(path, _)| is_previewable(path))
+        .map(|(path, size)| ThumbnailEntry {
+            name: path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            file_type: path
+                .extension()
+                .map(|e| e.to_string_lossy().to_uppercase())
+                .unwrap_or_default(),
+            path: path.display().to_string(),
+            thumb_path: None,
+            size,
+        })
+        .collect()
+}
+
+fn modified_time(path: &Path) -> String {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .map(chrono::DateTime::<chrono::Utc>::from)
+        .unwrap_or_else(|_| chrono::Utc::now())
+        .format("%Y-%m-%d %H:%M:%S UTC")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_run_embeds_thumbnails_and_proof() {
+        let dir = tempfile::tempdir().unwrap();
+        let photo = dir.path().join("photo.png");
+        image::RgbImage::from_pixel(32, 24, image::Rgb([200, 40, 40]))
+            .save(&photo)
+            .unwrap();
+        let size = std::fs::metadata(&photo).unwrap().len();
+
+        let mut manifest = crate::export::ExportManifest::new(Path::new("/evidence"), dir.path());
+        manifest.total_files = 1;
+        manifest.total_bytes = size;
+        manifest.entries.push(crate::export::ManifestEntry {
+            source_path: "/evidence/photo.png".to_string(),
+            dest_path: photo.display().to_string(),
+            size,
+            blake3_hash: crate::dedup::hash_file(&photo).unwrap(),
+            digests: Default::default(),
+            exported_at: chrono::Utc::now().to_rfc3339(),
+            verified: true,
+            mirror_path: None,
+            retry_history: Vec::new(),
+            compressed: None,
+            detections: Vec::new(),
+        });
+        std::fs::write(
+            dir.path().join(MANIFEST_FILE),
+            serde_json::to_string(&manifest).unwrap(),
+        )
+        .unwrap();
+
+        let mut data = report_data_for_run(dir.path()).unwrap().unwrap();
+        assert_eq!(data.file_type_counts, vec![("PNG".to_string(), 1, size)]);
+        assert!(data.proof.is_none());
+        assert_eq!(embed_thumbnails(&mut data, DEFAULT_GALLERY_SIZE), 1);
+        assert!(data.thumbnails[0]
+            .thumb_path
+            .as_deref()
+            .unwrap()
+            .starts_with("data:image/"));
+
+        let html = super::super::generate_html_report(&data);
+        assert!(html.contains(r#"src="data:image/"#));
+
+        // Anything else is left to the caller (an indexed source)
+        assert!(report_data_for_run(&photo).unwrap().is_none());
+    }
+}
@@ -0,0 +1,419 @@
+//! Index statistics (`stats`)
+//!
+//! Breaks an index down by file type, extension, size and age, lists the
+//! largest files and estimates duplicate waste. Everything comes from the
+//! index alone, so it's instant even for sources that are slow to read;
+//! the duplicate figure groups files by stored hash when the index has one
+//! and by size and extension otherwise (run `dedup` for exact numbers).
+
+use std::collections::HashMap;
+use std::fmt::Write as FmtWrite;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{css, format_bytes, html_escape};
+use crate::core::FileEntry;
+
+/// Upper bounds (exclusive) and labels of the size histogram
+const SIZE_BUCKETS: &[(u64, &str)] = &[
+    (1, "empty"),
+    (1 << 10, "< 1 KiB"),
+    (64 << 10, "1 KiB - 64 KiB"),
+    (1 << 20, "64 KiB - 1 MiB"),
+    (16 << 20, "1 MiB - 16 MiB"),
+    (256 << 20, "16 MiB - 256 MiB"),
+    (1 << 30, "256 MiB - 1 GiB"),
+    (u64::MAX, ">= 1 GiB"),
+];
+
+/// Default number of largest files listed
+pub const DEFAULT_TOP_FILES: usize = 20;
+
+/// Files and bytes in one row of a breakdown
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bucket {
+    pub label: String,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LargeFile {
+    pub path: PathBuf,
+    pub size: u64,
+    pub modified: Option<DateTime<Utc>>,
+}
+
+/// Rough duplicate waste, from the index only
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DuplicateEstimate {
+    pub groups: usize,
+    /// Copies beyond the first in each group
+    pub files: usize,
+    pub wasted_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatsReport {
+    pub source: PathBuf,
+    pub generated_at: DateTime<Utc>,
+    pub total_files: usize,
+    pub total_bytes: u64,
+    /// Largest share of bytes first
+    pub by_type: Vec<Bucket>,
+    /// Largest share of bytes first
+    pub by_extension: Vec<Bucket>,
+    /// Smallest files first
+    pub by_size: Vec<Bucket>,
+    /// Modification year, oldest first, then files without a time
+    pub by_year: Vec<Bucket>,
+    pub largest: Vec<LargeFile>,
+    pub duplicates: DuplicateEstimate,
+}
+
+impl StatsReport {
+    /// Build the report over `entries`, listing the `top` largest files
+    pub fn build(source: &Path, entries: &[FileEntry], top: usize) -> Self {
+        let mut by_type: HashMap<String, Bucket> = HashMap::new();
+        let mut by_extension: HashMap<String, Bucket> = HashMap::new();
+        let mut by_year: HashMap<Option<i32>, Bucket> = HashMap::new();
+        let mut by_size: Vec<Bucket> = SIZE_BUCKETS
+            .iter()
+            .map(|(_, label)| Bucket::new(label))
+            .collect();
+        let mut copies: HashMap<(u64, &str), usize> = HashMap::new();
+
+        for entry in entries {
+            let type_name = format!("{:?}", entry.file_type);
+            by_type
+                .entry(type_name.clone())
+                .or_insert_with(|| Bucket::new(&type_name))
+                .add(entry.size);
+            let ext = if entry.extension.is_empty() {
+                "(none)".to_string()
+            } else {
+                entry.extension.to_lowercase()
+            };
+            by_extension
+                .entry(ext.clone())
+                .or_insert_with(|| Bucket::new(&ext))
+                .add(entry.size);
+            let slot = SIZE_BUCKETS
+                .iter()
+                .position(|(limit, _)| entry.size < *limit)
+                .unwrap_or(SIZE_BUCKETS.len() - 1);
+            by_size[slot].add(entry.size);
+            let year = entry.modified.map(|m| m.year());
+            by_year
+                .entry(year)
+                .or_insert_with(|| Bucket::new(&year.map_or("unknown".into(), |y| y.to_string())))
+                .add(entry.size);
+            if entry.size > 0 {
+                let key = entry.hash.as_deref().unwrap_or(&entry.extension);
+                *copies.entry((entry.size, key)).or_default() += 1;
+            }
+        }
+
+        let mut duplicates = DuplicateEstimate::default();
+        for ((size, _), count) in copies {
+            if count > 1 {
+                duplicates.groups += 1;
+                duplicates.files += count - 1;
+                duplicates.wasted_bytes += size * (count as u64 - 1);
+            }
+        }
+
+        let mut largest: Vec<&FileEntry> = entries.iter().collect();
+        largest.sort_by_key(|e| std::cmp::Reverse(e.size));
+        largest.truncate(top);
+
+        let by_bytes = |map: HashMap<String, Bucket>| {
+            let mut rows: Vec<Bucket> = map.into_values().collect();
+            rows.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.label.cmp(&b.label)));
+            rows
+        };
+        let mut by_year: Vec<(Option<i32>, Bucket)> = by_year.into_iter().collect();
+        // None sorts first, so key on (missing, year)
+        by_year.sort_by_key(|(year, _)| (year.is_none(), *year));
+
+        Self {
+            source: source.to_path_buf(),
+            generated_at: Utc::now(),
+            total_files: entries.len(),
+            total_bytes: entries.iter().map(|e| e.size).sum(),
+            by_type: by_bytes(by_type),
+            by_extension: by_bytes(by_extension),
+            by_size: by_size.into_iter().filter(|b| b.files > 0).collect(),
+            by_year: by_year.into_iter().map(|(_, b)| b).collect(),
+            largest: largest
+                .into_iter()
+                .map(|e| LargeFile {
+                    path: e.path.clone(),
+                    size: e.size,
+                    modified: e.modified,
+                })
+                .collect(),
+            duplicates,
+        }
+    }
+
+    /// Format as human-readable summary.
+    pub fn to_human_string(&self) -> String {
+        let mut out = String::new();
+        let _ = write!(
+            out,
+            "\n  Diamond Drill Index Statistics\n  {}\n\n",
+            "=".repeat(40)
+        );
+        let _ = writeln!(out, "  Source:        {}", self.source.display());
+        let _ = writeln!(
+            out,
+            "  Files:         {} ({})",
+            self.total_files,
+            format_bytes(self.total_bytes)
+        );
+        let _ = writeln!(
+            out,
+            "  Duplicates:    ~{} files in {} groups, ~{} wasted (estimate)",
+            self.duplicates.files,
+            self.duplicates.groups,
+            format_bytes(self.duplicates.wasted_bytes)
+        );
+
+        let sections = [
+            ("By type", &self.by_type[..]),
+            (
+                "By extension (top 20)",
+                &self.by_extension[..self.by_extension.len().min(20)],
+            ),
+            ("By size", &self.by_size[..]),
+            ("By modification year", &self.by_year[..]),
+        ];
+        for (title, rows) in sections {
+            let _ = writeln!(out, "\n  {}", title);
+            let max = rows.iter().map(|b| b.files).max().unwrap_or(0).max(1);
+            for row in rows {
+                let _ = writeln!(
+                    out,
+                    "    {:<18} {:>9} {:>11}  {}",
+                    row.label,
+                    row.files,
+                    format_bytes(row.bytes),
+                    "#".repeat((row.files * 30).div_ceil(max))
+                );
+            }
+        }
+
+        if !self.largest.is_empty() {
+            let _ = writeln!(out, "\n  Largest files");
+            for file in &self.largest {
+                let _ = writeln!(
+                    out,
+                    "    {:>11}  {}",
+                    format_bytes(file.size),
+                    file.path.display()
+                );
+            }
+        }
+        out.push('\n');
+        out
+    }
+
+    /// Render a self-contained HTML page in the recovery report's style
+    pub fn to_html(&self) -> String {
+        let mut h = String::with_capacity(16_384);
+        let _ = write!(
+            h,
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="UTF-8">
+<meta name="viewport" content="width=device-width, initial-scale=1.0">
+<title>{source} - Diamond Drill Index Statistics</title>
+<style>{css}</style>
+</head>
+<body>
+<div class="container">
+<div class="card report-header">
+<span class="diamond-icon">&#x1F48E;</span>
+<h1>Diamond Drill Index Statistics</h1>
+<div class="meta"><span>{source}</span><span>|</span><span>{generated}</span></div>
+</div>
+<div class="card">
+<div class="section-title"><span class="icon">&#x1F4CA;</span> Summary</div>
+<div class="stats-grid">
+<div class="stat-cell recovered"><div class="value">{files}</div><div class="label">Files</div></div>
+<div class="stat-cell size"><div class="value">{bytes}</div><div class="label">Total Size</div></div>
+<div class="stat-cell failed"><div class="value">~{dup_files}</div><div class="label">Duplicate Copies</div></div>
+<div class="stat-cell sectors"><div class="value">~{dup_bytes}</div><div class="label">Duplicate Waste</div></div>
+</div>
+</div>
+"#,
+            source = html_escape(&self.source.display().to_string()),
+            css = css(),
+            generated = self.generated_at.format("%Y-%m-%d %H:%M:%S UTC"),
+            files = self.total_files,
+            bytes = format_bytes(self.total_bytes),
+            dup_files = self.duplicates.files,
+            dup_bytes = format_bytes(self.duplicates.wasted_bytes),
+        );
+
+        let sections = [
+            ("&#x1F4C2;", "By Type", &self.by_type[..]),
+            ("&#x1F3F7;", "By Extension", &self.by_extension[..]),
+            ("&#x1F4CF;", "By Size", &self.by_size[..]),
+            ("&#x1F4C5;", "By Modification Year", &self.by_year[..]),
+        ];
+        for (icon, title, rows) in sections {
+            let _ = write!(
+                h,
+                r#"<div class="card">
+<div class="section-title"><span class="icon">{icon}</span> {title}</div>
+<div class="chart-container">
+"#
+            );
+            let max = rows.iter().map(|b| b.files).max().unwrap_or(0).max(1);
+            for row in rows {
+                let _ = write!(
+                    h,
+                    r#"<div class="chart-row">
+<span class="type-label" title="{label}">{label}</span>
+<div class="bar-track"><div class="bar-fill" style="width:{pct:.1}%"></div></div>
+<span class="bar-count">{files} ({bytes})</span>
+</div>
+"#,
+                    label = html_escape(&row.label),
+                    pct = row.files as f64 / max as f64 * 100.0,
+                    files = row.files,
+                    bytes = format_bytes(row.bytes),
+                );
+            }
+            h.push_str("</div>\n</div>\n");
+        }
+
+        if !self.largest.is_empty() {
+            h.push_str(
+                r#"<div class="card">
+<div class="section-title"><span class="icon">&#x1F4E6;</span> Largest Files</div>
+<table class="custody-table">
+"#,
+            );
+            for file in &self.largest {
+                let _ = writeln!(
+                    h,
+                    "<tr><th>{}</th><td>{}</td></tr>",
+                    format_bytes(file.size),
+                    html_escape(&file.path.display().to_string())
+                );
+            }
+            h.push_str("</table>\n</div>\n");
+        }
+
+        h.push_str(
+            r#"<div class="report-footer">
+<p>Generated by <span class="brand">Diamond Drill</span></p>
+<p style="margin-top:0.3rem">Duplicate figures are estimated from the index; run <code>dedup</code> for exact numbers</p>
+</div>
+</div>
+</body>
+</html>
+"#,
+        );
+        h
+    }
+
+    pub fn save_html(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.to_html())
+            .with_context(|| format!("Failed to write stats page to {}", path.display()))
+    }
+}
+
+impl Bucket {
+    fn new(label: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            files: 0,
+            bytes: 0,
+        }
+    }
+
+    fn add(&mut self, size: u64) {
+        self.files += 1;
+        self.bytes += size;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::FileType;
+    use chrono::TimeZone;
+
+    fn entry(path: &str, size: u64, year: Option<i32>) -> FileEntry {
+        let extension = Path::new(path)
+            .extension()
+            .map(|e| e.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        FileEntry {
+            path: PathBuf::from(path),
+            size,
+            file_type: FileType::from_extension(&extension),
+            extension,
+            modified: year.map(|y| Utc.with_ymd_and_hms(y, 6, 1, 0, 0, 0).unwrap()),
+            created: None,
+            hash: None,
+            has_bad_sectors: false,
+            thumbnail: None,
+            metadata: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_stats_breakdown() {
+        let entries = vec![
+            entry("/d/a.jpg", 2_000_000, Some(2019)),
+            entry("/d/copy/a.jpg", 2_000_000, Some(2021)),
+            entry("/d/b.JPG", 500, Some(2019)),
+            entry("/d/notes.txt", 100_000, None),
+            entry("/d/Makefile", 0, Some(2021)),
+        ];
+        let report = StatsReport::build(Path::new("/d"), &entries, 2);
+
+        assert_eq!(report.total_files, 5);
+        assert_eq!(report.total_bytes, 4_100_500);
+        assert_eq!(report.by_type[0].label, "Image");
+        assert_eq!(
+            (report.by_type[0].files, report.by_type[0].bytes),
+            (3, 4_000_500)
+        );
+        assert_eq!(report.by_extension[0].label, "jpg");
+        assert_eq!(report.by_extension[0].files, 3);
+        let sizes: Vec<(&str, usize)> = report
+            .by_size
+            .iter()
+            .map(|b| (b.label.as_str(), b.files))
+            .collect();
+        assert_eq!(
+            sizes,
+            vec![
+                ("empty", 1),
+                ("< 1 KiB", 1),
+                ("64 KiB - 1 MiB", 1),
+                ("1 MiB - 16 MiB", 2)
+            ]
+        );
+        let years: Vec<&str> = report.by_year.iter().map(|b| b.label.as_str()).collect();
+        assert_eq!(years, vec!["2019", "2021", "unknown"]);
+        assert_eq!(report.largest.len(), 2);
+        assert_eq!(report.largest[0].size, 2_000_000);
+        assert_eq!(report.duplicates.files, 1);
+        assert_eq!(report.duplicates.wasted_bytes, 2_000_000);
+
+        let html = report.to_html();
+        assert!(html.contains("By Modification Year"));
+        assert!(html.contains("/d/copy/a.jpg"));
+    }
+}
@@ -0,0 +1,712 @@
+//! `--rpc` — JSON-RPC 2.0 over stdin/stdout for GUI frontends
+//!
+//! One JSON object per line in each direction. Requests run concurrently;
+//! while one runs, `progress` notifications carrying its id are written
+//! before its response:
+//!
+//! ```text
+//! → {"jsonrpc":"2.0","id":1,"method":"index","params":{"source":"/mnt/disk"}}
+//! ← {"jsonrpc":"2.0","method":"progress","params":{"request":1,"done":500,"total":null,"detail":"/mnt/disk/a.jpg"}}
+//! ← {"jsonrpc":"2.0","id":1,"result":{"files":1234,"total_bytes":56789,"bad_sectors":0}}
+//! ```
+//!
+//! Methods: `index`, `search`, `preview`, `export`, `carve` and `shutdown`.
+//! After `subscribe`, every library event ([`Event`]: bad sectors, hashes,
+//! heals, ...) is also written as an `event` notification, whichever request
+//! caused it:
+//!
+//! ```text
+//! ← {"jsonrpc":"2.0","method":"event","params":{"event":"bad_sector","file_path":"/mnt/disk/a.jpg","offset":4096,...}}
+//! ```
+//!
+//! Logs go to stderr so stdout carries nothing but protocol messages.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use parking_lot::Mutex;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::broadcast::error::{RecvError, TryRecvError};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tokio::sync::{broadcast, oneshot};
+use tokio::task::{JoinHandle, JoinSet};
+
+use crate::carve::{CarveListing, CarveOptions, CarveProgress, Carver};
+use crate::cli::{FileTypeFilter, IndexArgs, SearchArgs, SearchType};
+use crate::core::{DrillEngine, FileType};
+use crate::error::DrillError;
+use crate::events::{self, Event};
+use crate::export::{ExportFileEvent, ExportOptions, Exporter, DEFAULT_HASH_RETRIES};
+use crate::preview::{is_previewable, PreviewInfo, ThumbnailGenerator};
+
+/// Invalid JSON
+const PARSE_ERROR: i64 = -32700;
+/// Valid JSON that is not a request
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// The operation itself failed
+const OPERATION_FAILED: i64 = -32000;
+
+/// Files indexed between progress notifications
+const INDEX_PROGRESS_EVERY: usize = 500;
+
+/// Sizes of the thumbnails `preview` writes
+const PREVIEW_SIZES: [u32; 2] = [64, 512];
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    jsonrpc: String,
+    /// Absent for notifications, which get no response
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+/// A failed request, answered as a JSON-RPC error object
+#[derive(Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<anyhow::Error> for RpcError {
+    fn from(e: anyhow::Error) -> Self {
+        Self::new(OPERATION_FAILED, format!("{:#}", e))
+    }
+}
+
+impl From<DrillError> for RpcError {
+    fn from(e: DrillError) -> Self {
+        Self::new(OPERATION_FAILED, format!("{:#}", e))
+    }
+}
+
+type RpcResult = std::result::Result<Value, RpcError>;
+
+/// Messages for stdout, written in order by a single task
+#[derive(Clone)]
+struct Output(UnboundedSender<Value>);
+
+impl Output {
+    fn send(&self, message: Value) {
+        // The writer outlives every request
+        let _ = self.0.send(message);
+    }
+
+    fn respond(&self, id: Value, result: RpcResult) {
+        self.send(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(e) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": e.code, "message": e.message },
+            }),
+        });
+    }
+
+    fn progress(&self, request: &Value, done: u64, total: Option<u64>, detail: impl Into<String>) {
+        self.send(json!({
+            "jsonrpc": "2.0",
+            "method": "progress",
+            "params": {
+                "request": request,
+                "done": done,
+                "total": total,
+                "detail": detail.into(),
+            },
+        }));
+    }
+
+    fn event(&self, event: &Event) {
+        match serde_json::to_value(event) {
+            Ok(params) => {
+                self.send(json!({ "jsonrpc": "2.0", "method": "event", "params": params }))
+            }
+            Err(e) => tracing::debug!("Event not sent: {}", e),
+        }
+    }
+}
+
+/// Forward library events as `event` notifications until `stop` fires, then
+/// those still buffered
+async fn forward_events(
+    out: Output,
+    mut events: broadcast::Receiver<Event>,
+    mut stop: oneshot::Receiver<()>,
+) {
+    loop {
+        let event = tokio::select! {
+            event = events.recv() => event,
+            _ = &mut stop => break,
+        };
+        match event {
+            Ok(event) => out.event(&event),
+            Err(RecvError::Lagged(missed)) => tracing::warn!("Dropped {} events", missed),
+            Err(RecvError::Closed) => return,
+        }
+    }
+    loop {
+        match events.try_recv() {
+            Ok(event) => out.event(&event),
+            Err(TryRecvError::Lagged(_)) => continue,
+            Err(_) => return,
+        }
+    }
+}
+
+/// Engines by source, so a search sees what an earlier `index` found
+#[derive(Default)]
+struct Engines(Mutex<HashMap<PathBuf, Arc<DrillEngine>>>);
+
+impl Engines {
+    async fn get(&self, source: &Path) -> Result<Arc<DrillEngine>> {
+        let source = source
+            .canonicalize()
+            .with_context(|| format!("Failed to resolve path: {}", source.display()))?;
+        if let Some(engine) = self.0.lock().get(&source) {
+            return Ok(Arc::clone(engine));
+        }
+        let engine = Arc::new(DrillEngine::load_or_create(&source).await?);
+        Ok(Arc::clone(self.0.lock().entry(source).or_insert(engine)))
+    }
+}
+
+/// Serve requests from stdin until it closes or `shutdown` is called
+pub async fn run_rpc() -> Result<()> {
+    serve(tokio::io::stdin(), tokio::io::stdout()).await
+}
+
+async fn serve<R, W>(input: R, mut output: W) -> Result<()>
+where
+    R: tokio::io::AsyncRead + Unpin,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let (tx, mut rx) = unbounded_channel::<Value>();
+    let writer = tokio::spawn(async move {
+        while let Some(message) = rx.recv().await {
+            let mut line = serde_json::to_vec(&message)?;
+            line.push(b'\n');
+            output.write_all(&line).await?;
+            output.flush().await?;
+        }
+        anyhow::Ok(())
+    });
+
+    let out = Output(tx);
+    let engines = Arc::new(Engines::default());
+    let mut running = JoinSet::new();
+    let mut forwarding: Option<(oneshot::Sender<()>, JoinHandle<()>)> = None;
+    let mut lines = BufReader::new(input).lines();
+    while let Some(line) = lines.next_line().await.context("Failed to read request")? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let request = match parse_request(&line) {
+            Ok(request) => request,
+            Err((id, e)) => {
+                out.respond(id, Err(e));
+                continue;
+            }
+        };
+        if request.method == "shutdown" {
+            if let Some(id) = request.id {
+                out.respond(id, Ok(Value::Null));
+            }
+            break;
+        }
+        if request.method == "subscribe" {
+            if forwarding.is_none() {
+                let (stop, stopped) = oneshot::channel();
+                let task = tokio::spawn(forward_events(out.clone(), events::subscribe(), stopped));
+                forwarding = Some((stop, task));
+            }
+            if let Some(id) = request.id {
+                out.respond(id, Ok(Value::Bool(true)));
+            }
+            continue;
+        }
+
+        let out = out.clone();
+        let engines = Arc::clone(&engines);
+        let handle = tokio::runtime::Handle::current();
+        // Operations block on disk I/O (and some futures are not Send), so
+        // each request gets a blocking thread of its own
+        running.spawn_blocking(move || {
+            let progress_id = request.id.clone().unwrap_or(Value::Null);
+            let result = handle.block_on(dispatch(
+                &request.method,
+                request.params,
+                &engines,
+                &out,
+                &progress_id,
+            ));
+            if let Some(id) = request.id {
+                out.respond(id, result);
+            }
+        });
+    }
+
+    // Let running requests answer before stdout closes
+    while running.join_next().await.is_some() {}
+    if let Some((stop, task)) = forwarding {
+        let _ = stop.send(());
+        task.await.context("Event task panicked")?;
+    }
+    drop(out);
+    writer.await.context("Output task panicked")?
+}
+
+/// The request on `line`, or the id to answer with and why it is invalid
+fn parse_request(line: &str) -> std::result::Result<Request, (Value, RpcError)> {
+    let value: Value = serde_json::from_str(line)
+        .map_err(|e| (Value::Null, RpcError::new(PARSE_ERROR, e.to_string())))?;
+    let id = value.get("id").cloned().unwrap_or(Value::Null);
+    let request: Request = serde_json::from_value(value)
+        .map_err(|e| (id.clone(), RpcError::new(INVALID_REQUEST, e.to_string())))?;
+    if request.jsonrpc != "2.0" {
+        return Err((
+            id,
+            RpcError::new(INVALID_REQUEST, "jsonrpc must be \"2.0\""),
+        ));
+    }
+    Ok(request)
+}
+
+async fn dispatch(
+    method: &str,
+    params: Value,
+    engines: &Engines,
+    out: &Output,
+    id: &Value,
+) -> RpcResult {
+    match method {
+        "index" => index(params_of(params)?, engines, out, id).await,
+        "search" => search(params_of(params)?, engines).await,
+        "preview" => preview(params_of(params)?),
+        "export" => export(params_of(params)?, engines, out, id).await,
+        "carve" => carve(params_of(params)?, out, id).await,
+        _ => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("Unknown method: {}", method),
+        )),
+    }
+}
+
+fn params_of<T: DeserializeOwned>(params: Value) -> std::result::Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn to_value(value: impl Serialize) -> RpcResult {
+    serde_json::to_value(value).map_err(|e| RpcError::from(anyhow::Error::from(e)))
+}
+
+fn file_type_of(name: &str) -> std::result::Result<FileTypeFilter, RpcError> {
+    FileTypeFilter::from_str(name, true).map_err(|e| RpcError::new(INVALID_PARAMS, e))
+}
+
+/// Core type of a type filter (`all` doesn't filter)
+fn core_file_type(filter: FileTypeFilter) -> Option<FileType> {
+    match filter {
+        FileTypeFilter::Image => Some(FileType::Image),
+        FileTypeFilter::Video => Some(FileType::Video),
+        FileTypeFilter::Audio => Some(FileType::Audio),
+        FileTypeFilter::Document => Some(FileType::Document),
+        FileTypeFilter::Archive => Some(FileType::Archive),
+        FileTypeFilter::Code => Some(FileType::Code),
+        FileTypeFilter::All => None,
+    }
+}
+
+// ── Methods ──────────────────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize)]
+struct IndexParams {
+    source: PathBuf,
+    /// Rescan only files changed since the last index
+    #[serde(default)]
+    incremental: bool,
+    /// Extract EXIF/document metadata
+    #[serde(default)]
+    metadata: bool,
+}
+
+async fn index(params: IndexParams, engines: &Engines, out: &Output, id: &Value) -> RpcResult {
+    let source = params
+        .source
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve path: {}", params.source.display()))?;
+    let engine = engines.get(&source).await?;
+    let args = IndexArgs {
+        source,
+        resume: false,
+        index_file: None,
+        skip_hidden: false,
+        depth: None,
+        extensions: None,
+        thumbnails: false,
+        workers: None,
+        checkpoint_interval: 1000,
+        bad_sector_report: None,
+        block_size: 4096,
+        sqlite: None,
+        incremental: params.incremental,
+        verify_hash: false,
+        metadata: params.metadata,
+        report_format: None,
+        report_file: None,
+        hashset: Vec::new(),
+        repair_names: None,
+        links: Default::default(),
+        all_hardlinks: false,
+        archives: false,
+        snapshots: false,
+    };
+    engine
+        .index_with_live_progress(&args, |count, entry| {
+            if count % INDEX_PROGRESS_EVERY == 0 {
+                out.progress(id, count as u64, None, entry.path.display().to_string());
+            }
+        })
+        .await?;
+
+    let entries = engine.get_all_entries().await;
+    Ok(json!({
+        "files": entries.len(),
+        "total_bytes": entries.iter().map(|e| e.size).sum::<u64>(),
+        "bad_sectors": engine.bad_sector_count().await,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchParams {
+    source: PathBuf,
+    pattern: String,
+    /// fuzzy (default), glob, regex or exact
+    mode: Option<String>,
+    #[serde(rename = "type")]
+    file_type: Option<String>,
+    #[serde(default = "default_limit")]
+    limit: usize,
+    /// Match file contents instead of names
+    #[serde(default)]
+    content: bool,
+}
+
+fn default_limit() -> usize {
+    100
+}
+
+async fn search(params: SearchParams, engines: &Engines) -> RpcResult {
+    let search_type = match params.mode.as_deref() {
+        Some(mode) => {
+            SearchType::from_str(mode, true).map_err(|e| RpcError::new(INVALID_PARAMS, e))?
+        }
+        None => SearchType::Fuzzy,
+    };
+    let file_type = params.file_type.as_deref().map(file_type_of).transpose()?;
+    let engine = engines.get(&params.source).await?;
+    let args = SearchArgs {
+        source: params.source,
+        pattern: params.pattern,
+        search_type,
+        file_type,
+        min_size: None,
+        max_size: None,
+        after: None,
+        before: None,
+        limit: params.limit,
+        translit: false,
+        content: params.content,
+        meta: Vec::new(),
+        select: None,
+        hashset: Vec::new(),
+    };
+    if args.content {
+        to_value(engine.search_content(&args).await?)
+    } else {
+        to_value(engine.search(&args).await?)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PreviewParams {
+    path: PathBuf,
+}
+
+fn preview(params: PreviewParams) -> RpcResult {
+    let source = params.path;
+    let mime_type = infer::get_from_path(&source)
+        .ok()
+        .flatten()
+        .map(|kind| kind.mime_type().to_string())
+        .unwrap_or_else(|| "application/octet-stream".to_string());
+    let previewable = is_previewable(&source);
+    let mut info = PreviewInfo {
+        source,
+        thumb_small: None,
+        thumb_large: None,
+        mime_type,
+        previewable,
+        error: None,
+    };
+    if previewable {
+        match ThumbnailGenerator::new().generate_progressive_multi(&info.source, &PREVIEW_SIZES) {
+            Ok(mut paths) => {
+                info.thumb_large = paths.pop();
+                info.thumb_small = paths.pop();
+            }
+            Err(e) => info.error = Some(format!("{:#}", e)),
+        }
+    }
+    to_value(info)
+}
+
+#[derive(Debug, Deserialize)]
+struct ExportParams {
+    /// Source the files were indexed from
+    source: PathBuf,
+    /// Indexed files to export
+    paths: Vec<String>,
+    dest: PathBuf,
+    #[serde(default = "default_true")]
+    verify: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+async fn export(params: ExportParams, engines: &Engines, out: &Output, id: &Value) -> RpcResult {
+    let engine = engines.get(&params.source).await?;
+    let mut entries = Vec::with_capacity(params.paths.len());
+    for path in &params.paths {
+        let entry = engine
+            .get_file_info(path)
+            .await
+            .map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))?;
+        entries.push(entry);
+    }
+
+    let options = ExportOptions {
+        dest: params.dest,
+        preserve_structure: true,
+        verify_hash: params.verify,
+        continue_on_error: true,
+        create_manifest: true,
+        hash_retries: DEFAULT_HASH_RETRIES,
+        retry_with_sector_reader: true,
+        ..Default::default()
+    };
+    let total = entries.len() as u64;
+    let done = Arc::new(std::sync::atomic::AtomicU64::new(0));
+    let (out_events, id_events) = (out.clone(), id.clone());
+    let exporter = Exporter::new(options).with_file_events(Arc::new(move |event| {
+        let source = match event {
+            ExportFileEvent::Skipped { source, .. } | ExportFileEvent::Finished { source, .. } => {
+                source
+            }
+            _ => return,
+        };
+        let done = done.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1;
+        out_events.progress(&id_events, done, Some(total), source.display().to_string());
+    }));
+    to_value(exporter.export_batch(&entries, |_| {}).await?)
+}
+
+#[derive(Debug, Deserialize)]
+struct CarveParams {
+    /// Raw disk image
+    source: PathBuf,
+    output: PathBuf,
+    /// Only these types (image, video, ...)
+    #[serde(default)]
+    types: Vec<String>,
+    min_size: Option<u64>,
+    #[serde(default)]
+    dry_run: bool,
+}
+
+async fn carve(params: CarveParams, out: &Output, id: &Value) -> RpcResult {
+    let mut file_types = Vec::new();
+    for name in &params.types {
+        if let Some(ft) = core_file_type(file_type_of(name)?) {
+            file_types.push(ft);
+        }
+    }
+    let defaults = CarveOptions::default();
+    let options = CarveOptions {
+        source: params.source.clone(),
+        output_dir: params.output.clone(),
+        min_size: params.min_size.unwrap_or(defaults.min_size),
+        file_types: (!file_types.is_empty()).then_some(file_types),
+        dry_run: params.dry_run,
+        ..defaults
+    };
+
+    let (files, result) = Carver::new(options)
+        .carve_with_progress(|progress| match progress {
+            CarveProgress::Scanning {
+                bytes_scanned,
+                total_bytes,
+            } => out.progress(id, bytes_scanned, Some(total_bytes), "scanning"),
+            CarveProgress::Extracting {
+                current,
+                total,
+                extension,
+            } => out.progress(
+                id,
+                current as u64,
+                Some(total as u64),
+                format!("extracting .{}", extension),
+            ),
+            CarveProgress::ScanComplete { .. } | CarveProgress::Done => {}
+        })
+        .await?;
+
+    let results_file = if params.dry_run {
+        None
+    } else {
+        let listing = CarveListing {
+            source: params.source,
+            result: result.clone(),
+            files,
+        };
+        Some(listing.save(&params.output)?)
+    };
+    Ok(json!({ "result": to_value(result)?, "results_file": results_file }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Run `requests` through the protocol and return every line written
+    async fn exchange(requests: &[Value]) -> Vec<Value> {
+        let input: String = requests.iter().map(|r| format!("{}\n", r)).collect();
+        let (client, server) = tokio::io::duplex(1 << 20);
+        serve(input.as_bytes(), server).await.unwrap();
+        let mut lines = BufReader::new(client).lines();
+        let mut messages = Vec::new();
+        while let Some(line) = lines.next_line().await.unwrap() {
+            messages.push(serde_json::from_str(&line).unwrap());
+        }
+        messages
+    }
+
+    fn response(messages: &[Value], id: u64) -> &Value {
+        messages
+            .iter()
+            .find(|m| m["id"] == id)
+            .unwrap_or_else(|| panic!("no response to {}: {:?}", id, messages))
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_index_then_search_and_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source");
+        std::fs::create_dir(&source).unwrap();
+        for i in 0..3 {
+            std::fs::write(source.join(format!("report-{}.txt", i)), b"text").unwrap();
+        }
+        std::fs::write(source.join("photo.jpg"), b"not really").unwrap();
+
+        let messages = exchange(&[
+            json!({"jsonrpc": "2.0", "id": 1, "method": "index", "params": {"source": source}}),
+            json!({"jsonrpc": "2.0", "id": 2, "method": "frobnicate"}),
+            json!({"jsonrpc": "2.0", "id": 3, "method": "search", "params": {"pattern": 1}}),
+        ])
+        .await;
+        assert_eq!(response(&messages, 1)["result"]["files"], 4);
+        assert_eq!(response(&messages, 2)["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(response(&messages, 3)["error"]["code"], INVALID_PARAMS);
+
+        // A new session loads the index the first one wrote
+        let messages = exchange(&[
+            json!({"jsonrpc": "2.0", "id": 4, "method": "search",
+                   "params": {"source": source, "pattern": "*.txt", "mode": "glob"}}),
+            json!({"jsonrpc": "2.0", "id": 5, "method": "shutdown"}),
+            json!({"jsonrpc": "2.0", "id": 6, "method": "search",
+                   "params": {"source": source, "pattern": "photo"}}),
+        ])
+        .await;
+        assert_eq!(
+            response(&messages, 4)["result"].as_array().unwrap().len(),
+            3
+        );
+        assert_eq!(response(&messages, 5)["result"], Value::Null);
+        assert!(
+            messages.iter().all(|m| m["id"] != 6),
+            "nothing is read after shutdown"
+        );
+
+        let messages = exchange(&[json!({"jsonrpc": "1.0", "id": 7, "method": "index"})]).await;
+        assert_eq!(response(&messages, 7)["error"]["code"], INVALID_REQUEST);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_carve_streams_progress_before_its_response() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut image = vec![0u8; 4096];
+        image.extend([0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]);
+        image.extend(vec![0x42; 2048]);
+        image.extend([0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xAE, 0x42, 0x60, 0x82]);
+        image.resize(16384, 0);
+        let source = dir.path().join("disk.img");
+        std::fs::write(&source, &image).unwrap();
+        let output = dir.path().join("carved");
+
+        let messages = exchange(&[json!({
+            "jsonrpc": "2.0",
+            "id": "carve-1",
+            "method": "carve",
+            "params": {"source": source, "output": output, "types": ["image"]},
+        })])
+        .await;
+        let (last, progress) = messages.split_last().unwrap();
+        assert_eq!(last["id"], "carve-1");
+        assert_eq!(last["result"]["result"]["files_found"], 1);
+        assert!(output.join(crate::carve::CARVE_LISTING_FILE).exists());
+        assert!(!progress.is_empty());
+        assert!(progress
+            .iter()
+            .all(|m| m["method"] == "progress" && m["params"]["request"] == "carve-1"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_subscribe_forwards_library_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source");
+        std::fs::create_dir(&source).unwrap();
+        std::fs::write(source.join("notes.txt"), b"text").unwrap();
+
+        let messages = exchange(&[
+            json!({"jsonrpc": "2.0", "id": 1, "method": "subscribe"}),
+            json!({"jsonrpc": "2.0", "id": 2, "method": "index", "params": {"source": source}}),
+        ])
+        .await;
+        assert_eq!(response(&messages, 1)["result"], true);
+        assert_eq!(response(&messages, 2)["result"]["files"], 1);
+        // Other tests publish on the same bus, so look for this index only
+        assert!(messages.iter().any(|m| m["method"] == "event"
+            && m["params"]["event"] == "scan"
+            && m["params"]["source"] == json!(source)
+            && m["params"]["files"] == 1));
+    }
+}
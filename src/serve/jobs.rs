@@ -0,0 +1,179 @@
+//! Jobs started through the `serve` API
+//!
+//! Every change to a job is broadcast so WebSocket clients follow index and
+//! export progress without polling `/api/jobs`.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use parking_lot::RwLock;
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+/// Job updates buffered per WebSocket client before it starts missing some
+const EVENT_BUFFER: usize = 256;
+
+/// Kind of job (at most one of each runs at a time)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobKind {
+    Index,
+    Export,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase", tag = "state", content = "error")]
+pub enum JobStatus {
+    Running,
+    Done,
+    Failed(String),
+}
+
+/// One job as reported by `/api/jobs` and the event stream
+#[derive(Debug, Clone, Serialize)]
+pub struct JobInfo {
+    pub id: u64,
+    pub kind: JobKind,
+    pub status: JobStatus,
+    /// Units of work done so far (files)
+    pub done: u64,
+    /// Total units of work, when known up front
+    pub total: Option<u64>,
+    /// What the job is working on right now, or its outcome
+    pub detail: String,
+    /// Files that failed without failing the job
+    pub errors: Vec<String>,
+}
+
+/// All jobs of the server, oldest first
+pub struct Jobs {
+    jobs: RwLock<Vec<JobInfo>>,
+    next_id: AtomicU64,
+    events: broadcast::Sender<JobInfo>,
+}
+
+impl Default for Jobs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Jobs {
+    pub fn new() -> Self {
+        let (events, _) = broadcast::channel(EVENT_BUFFER);
+        Self {
+            jobs: RwLock::new(Vec::new()),
+            next_id: AtomicU64::new(0),
+            events,
+        }
+    }
+
+    pub fn list(&self) -> Vec<JobInfo> {
+        self.jobs.read().clone()
+    }
+
+    pub fn get(&self, id: u64) -> Option<JobInfo> {
+        self.jobs.read().iter().find(|j| j.id == id).cloned()
+    }
+
+    pub fn is_running(&self, kind: JobKind) -> bool {
+        self.jobs
+            .read()
+            .iter()
+            .any(|j| j.kind == kind && j.status == JobStatus::Running)
+    }
+
+    /// Updates of every job from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<JobInfo> {
+        self.events.subscribe()
+    }
+
+    /// Register a new running job, or None if one of the same kind is
+    /// still running
+    pub fn start(&self, kind: JobKind, total: Option<u64>, detail: String) -> Option<JobInfo> {
+        let mut jobs = self.jobs.write();
+        if jobs
+            .iter()
+            .any(|j| j.kind == kind && j.status == JobStatus::Running)
+        {
+            return None;
+        }
+        let job = JobInfo {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            kind,
+            status: JobStatus::Running,
+            done: 0,
+            total,
+            detail,
+            errors: Vec::new(),
+        };
+        jobs.push(job.clone());
+        drop(jobs);
+        self.broadcast(job.clone());
+        Some(job)
+    }
+
+    /// Change a job and tell subscribers
+    pub fn update(&self, id: u64, change: impl FnOnce(&mut JobInfo)) {
+        let job = {
+            let mut jobs = self.jobs.write();
+            let Some(job) = jobs.iter_mut().find(|j| j.id == id) else {
+                return;
+            };
+            change(job);
+            job.clone()
+        };
+        self.broadcast(job);
+    }
+
+    /// Mark a job done with `detail` as its outcome, or failed
+    pub fn finish(&self, id: u64, result: anyhow::Result<String>) {
+        self.update(id, |job| match result {
+            Ok(detail) => {
+                job.status = JobStatus::Done;
+                job.detail = detail;
+            }
+            Err(e) => job.status = JobStatus::Failed(format!("{:#}", e)),
+        });
+    }
+
+    fn broadcast(&self, job: JobInfo) {
+        // No subscribers is fine; nobody has the page open
+        let _ = self.events.send(job);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_one_job_per_kind_and_updates_are_broadcast() {
+        let jobs = Jobs::new();
+        let mut events = jobs.subscribe();
+
+        let index = jobs.start(JobKind::Index, None, "scanning".into()).unwrap();
+        assert!(jobs.start(JobKind::Index, None, "again".into()).is_none());
+        let export = jobs
+            .start(JobKind::Export, Some(2), "2 files".into())
+            .unwrap();
+
+        jobs.update(export.id, |j| j.done = 1);
+        jobs.finish(index.id, Ok("Indexed 10 files".into()));
+        jobs.finish(export.id, Err(anyhow::anyhow!("disk full")));
+
+        let seen: Vec<JobInfo> = std::iter::from_fn(|| events.try_recv().ok()).collect();
+        assert_eq!(seen.len(), 5);
+        assert_eq!(seen[2].done, 1);
+        assert_eq!(seen[3].status, JobStatus::Done);
+        assert_eq!(
+            jobs.get(export.id).unwrap().status,
+            JobStatus::Failed("disk full".into())
+        );
+        assert!(!jobs.is_running(JobKind::Index));
+        assert!(jobs.start(JobKind::Index, None, "rescan".into()).is_some());
+
+        let json = serde_json::to_value(jobs.get(export.id).unwrap()).unwrap();
+        assert_eq!(json["kind"], "export");
+        assert_eq!(json["status"]["state"], "failed");
+    }
+}
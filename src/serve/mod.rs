@@ -0,0 +1,580 @@
+//! `serve` — drive a recovery rig from a browser
+//!
+//! Serves one source over HTTP: a REST API for index status, search,
+//! thumbnails and export jobs, a WebSocket (`/api/events`) streaming job
+//! progress, and a minimal web UI bundled into the binary.
+//!
+//! Listening on loopback needs no credentials. Listening on any other
+//! address requires an access token, generated at start unless `--token` is
+//! given, sent as `Authorization: Bearer <token>` or `?token=<token>` (which
+//! is what the web UI and WebSocket use).
+
+pub mod jobs;
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use axum::extract::ws::{Message as WsMessage, WebSocket, WebSocketUpgrade};
+use axum::extract::{Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::error::RecvError;
+
+use self::jobs::{JobInfo, JobKind, Jobs};
+use crate::cli::{FileTypeFilter, IndexArgs, SearchArgs, SearchType, ServeArgs};
+use crate::core::{DrillEngine, FileEntry};
+use crate::export::{ExportFileEvent, ExportOptions, Exporter, DEFAULT_HASH_RETRIES};
+use crate::preview::ThumbnailGenerator;
+
+/// The bundled web UI
+const INDEX_HTML: &str = include_str!("index.html");
+
+/// Results returned by `/api/search` when no limit is given
+const DEFAULT_SEARCH_LIMIT: usize = 200;
+
+/// Largest thumbnail `/api/thumbnail` renders
+const MAX_THUMBNAIL_SIZE: u32 = 1024;
+
+/// Files indexed between progress updates of an index job
+const INDEX_PROGRESS_EVERY: usize = 500;
+
+#[derive(Clone)]
+struct ServerState {
+    source: PathBuf,
+    engine: Arc<DrillEngine>,
+    thumbnails: Arc<ThumbnailGenerator>,
+    jobs: Arc<Jobs>,
+    /// Required on every request when set
+    token: Option<Arc<str>>,
+}
+
+/// Run the server until interrupted
+pub async fn run_serve(args: ServeArgs) -> Result<()> {
+    let source = args
+        .source
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve path: {}", args.source.display()))?;
+    let token = access_token(&args);
+    let state = ServerState {
+        engine: Arc::new(DrillEngine::load_or_create(&source).await?),
+        source,
+        thumbnails: Arc::new(ThumbnailGenerator::new()),
+        jobs: Arc::new(Jobs::new()),
+        token: token.as_deref().map(Arc::from),
+    };
+
+    let listener = tokio::net::TcpListener::bind(args.bind)
+        .await
+        .with_context(|| format!("Failed to listen on {}", args.bind))?;
+    let url = match &token {
+        Some(token) => format!("http://{}/?token={}", args.bind, token),
+        None => format!("http://{}/", args.bind),
+    };
+    println!(
+        "💎 Serving {} ({} files indexed)",
+        state.source.display(),
+        state.engine.file_count().await
+    );
+    println!("   Open {}", url);
+    println!("   Press Ctrl+C to stop");
+
+    axum::serve(listener, router(state))
+        .with_graceful_shutdown(async {
+            tokio::signal::ctrl_c().await.ok();
+        })
+        .await
+        .context("Server failed")
+}
+
+/// `--token`, or a fresh one when listening beyond loopback
+fn access_token(args: &ServeArgs) -> Option<String> {
+    args.token.clone().or_else(|| {
+        (!args.bind.ip().is_loopback()).then(|| uuid::Uuid::new_v4().simple().to_string())
+    })
+}
+
+fn router(state: ServerState) -> Router {
+    Router::new()
+        .route("/", get(|| async { Html(INDEX_HTML) }))
+        .route("/api/status", get(status))
+        .route("/api/index", post(start_index))
+        .route("/api/search", get(search))
+        .route("/api/thumbnail", get(thumbnail))
+        .route("/api/export", post(start_export))
+        .route("/api/jobs", get(list_jobs))
+        .route("/api/events", get(events))
+        .layer(middleware::from_fn_with_state(state.clone(), require_token))
+        .with_state(state)
+}
+
+// ── Errors ───────────────────────────────────────────────────────────────
+
+/// An error answered as `{"error": "..."}` with its status code
+struct ApiError(StatusCode, String);
+
+impl ApiError {
+    fn bad_request(message: impl Into<String>) -> Self {
+        Self(StatusCode::BAD_REQUEST, message.into())
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        Self(StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e))
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(serde_json::json!({ "error": self.1 }))).into_response()
+    }
+}
+
+type ApiResult<T> = std::result::Result<T, ApiError>;
+
+async fn require_token(State(state): State<ServerState>, request: Request, next: Next) -> Response {
+    let Some(expected) = state.token.as_deref() else {
+        return next.run(request).await;
+    };
+    let bearer = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    let query = request
+        .uri()
+        .query()
+        .and_then(|q| q.split('&').find_map(|pair| pair.strip_prefix("token=")));
+    if bearer.or(query) == Some(expected) {
+        next.run(request).await
+    } else {
+        ApiError(
+            StatusCode::UNAUTHORIZED,
+            "Missing or wrong access token".into(),
+        )
+        .into_response()
+    }
+}
+
+// ── Handlers ─────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize)]
+struct StatusResponse {
+    source: PathBuf,
+    files: usize,
+    total_bytes: u64,
+    bad_sectors: usize,
+    /// File count per type
+    by_type: BTreeMap<String, usize>,
+    indexing: bool,
+    exporting: bool,
+}
+
+async fn status(State(state): State<ServerState>) -> Json<StatusResponse> {
+    let entries = state.engine.get_all_entries().await;
+    let mut by_type = BTreeMap::new();
+    for entry in &entries {
+        *by_type
+            .entry(format!("{:?}", entry.file_type).to_lowercase())
+            .or_insert(0) += 1;
+    }
+    Json(StatusResponse {
+        source: state.source.clone(),
+        files: entries.len(),
+        total_bytes: entries.iter().map(|e| e.size).sum(),
+        bad_sectors: state.engine.bad_sector_count().await,
+        by_type,
+        indexing: state.jobs.is_running(JobKind::Index),
+        exporting: state.jobs.is_running(JobKind::Export),
+    })
+}
+
+/// Index (or re-index) the source in the background
+async fn start_index(State(state): State<ServerState>) -> ApiResult<Json<JobInfo>> {
+    let job = state
+        .jobs
+        .start(JobKind::Index, None, state.source.display().to_string())
+        .ok_or_else(|| ApiError(StatusCode::CONFLICT, "Indexing is already running".into()))?;
+
+    let id = job.id;
+    tokio::task::spawn_blocking(move || {
+        let result = tokio::runtime::Handle::current().block_on(async {
+            let jobs = &state.jobs;
+            state
+                .engine
+                .index_with_live_progress(&index_args(&state.source), |count, entry| {
+                    if count % INDEX_PROGRESS_EVERY == 0 {
+                        jobs.update(id, |job| {
+                            job.done = count as u64;
+                            job.detail = entry.path.display().to_string();
+                        });
+                    }
+                })
+                .await?;
+            let count = state.engine.file_count().await;
+            jobs.update(id, |job| job.done = count as u64);
+            Ok(format!("Indexed {} files", count))
+        });
+        state.jobs.finish(id, result);
+    });
+    Ok(Json(job))
+}
+
+fn index_args(source: &Path) -> IndexArgs {
+    IndexArgs {
+        source: source.to_path_buf(),
+        resume: false,
+        index_file: None,
+        skip_hidden: false,
+        depth: None,
+        extensions: None,
+        thumbnails: false,
+        workers: None,
+        checkpoint_interval: 1000,
+        bad_sector_report: None,
+        block_size: 4096,
+        sqlite: None,
+        incremental: false,
+        verify_hash: false,
+        metadata: false,
+        report_format: None,
+        report_file: None,
+        hashset: Vec::new(),
+        repair_names: None,
+        links: Default::default(),
+        all_hardlinks: false,
+        archives: false,
+        snapshots: false,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchQuery {
+    /// Pattern; every file when empty
+    #[serde(default)]
+    q: String,
+    /// fuzzy (default), glob, regex or exact
+    mode: Option<String>,
+    /// image, video, audio, document, archive or code
+    #[serde(rename = "type")]
+    file_type: Option<String>,
+    limit: Option<usize>,
+}
+
+async fn search(
+    State(state): State<ServerState>,
+    Query(query): Query<SearchQuery>,
+) -> ApiResult<Json<Vec<FileEntry>>> {
+    let file_type = query
+        .file_type
+        .as_deref()
+        .map(|t| FileTypeFilter::from_str(t, true).map_err(ApiError::bad_request))
+        .transpose()?;
+    let (pattern, search_type) = if query.q.is_empty() {
+        ("*".to_string(), SearchType::Glob)
+    } else {
+        let search_type = query
+            .mode
+            .as_deref()
+            .map(|m| SearchType::from_str(m, true).map_err(ApiError::bad_request))
+            .transpose()?
+            .unwrap_or(SearchType::Fuzzy);
+        (query.q, search_type)
+    };
+    let args = SearchArgs {
+        source: state.source.clone(),
+        pattern,
+        search_type,
+        file_type,
+        min_size: None,
+        max_size: None,
+        after: None,
+        before: None,
+        limit: query.limit.unwrap_or(DEFAULT_SEARCH_LIMIT),
+        translit: false,
+        content: false,
+        meta: Vec::new(),
+        select: None,
+        hashset: Vec::new(),
+    };
+    Ok(Json(state.engine.search(&args).await?))
+}
+
+#[derive(Debug, Deserialize)]
+struct ThumbnailQuery {
+    path: String,
+    size: Option<u32>,
+}
+
+/// Thumbnail of an indexed file (never of a path outside the index)
+async fn thumbnail(
+    State(state): State<ServerState>,
+    Query(query): Query<ThumbnailQuery>,
+) -> ApiResult<Response> {
+    let entry = state
+        .engine
+        .get_file_info(&query.path)
+        .await
+        .map_err(|e| ApiError(StatusCode::NOT_FOUND, e.to_string()))?;
+    let size = query.size.unwrap_or(256).clamp(16, MAX_THUMBNAIL_SIZE);
+
+    let generator = Arc::clone(&state.thumbnails);
+    let thumb = tokio::task::spawn_blocking(move || -> Result<(PathBuf, Vec<u8>)> {
+        let path = generator.generate(&entry.path, size)?;
+        let data = std::fs::read(&path)
+            .with_context(|| format!("Failed to read thumbnail: {}", path.display()))?;
+        Ok((path, data))
+    })
+    .await
+    .context("Thumbnail task panicked")?;
+    let (path, data) =
+        thumb.map_err(|e| ApiError(StatusCode::UNPROCESSABLE_ENTITY, format!("{:#}", e)))?;
+
+    let content_type = match path.extension().and_then(|e| e.to_str()) {
+        Some("png") => "image/png",
+        _ => "image/jpeg",
+    };
+    Ok(([(header::CONTENT_TYPE, content_type)], data).into_response())
+}
+
+#[derive(Debug, Deserialize)]
+struct ExportRequest {
+    /// Indexed files to export
+    paths: Vec<String>,
+    /// Destination directory on the machine running the server
+    dest: PathBuf,
+    #[serde(default = "default_true")]
+    verify: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Export indexed files in the background
+async fn start_export(
+    State(state): State<ServerState>,
+    Json(request): Json<ExportRequest>,
+) -> ApiResult<Json<JobInfo>> {
+    if request.paths.is_empty() {
+        return Err(ApiError::bad_request("No files to export"));
+    }
+    let mut entries = Vec::with_capacity(request.paths.len());
+    for path in &request.paths {
+        let entry = state
+            .engine
+            .get_file_info(path)
+            .await
+            .map_err(|e| ApiError::bad_request(e.to_string()))?;
+        entries.push(entry);
+    }
+
+    let job = state
+        .jobs
+        .start(
+            JobKind::Export,
+            Some(entries.len() as u64),
+            format!("{} files to {}", entries.len(), request.dest.display()),
+        )
+        .ok_or_else(|| ApiError(StatusCode::CONFLICT, "An export is already running".into()))?;
+
+    let id = job.id;
+    let jobs = Arc::clone(&state.jobs);
+    let options = ExportOptions {
+        dest: request.dest,
+        preserve_structure: true,
+        verify_hash: request.verify,
+        continue_on_error: true,
+        create_manifest: true,
+        hash_retries: DEFAULT_HASH_RETRIES,
+        retry_with_sector_reader: true,
+        ..Default::default()
+    };
+    tokio::spawn(async move {
+        let events = Arc::clone(&jobs);
+        let exporter = Exporter::new(options).with_file_events(Arc::new(move |event| {
+            events.update(id, |job| match event {
+                ExportFileEvent::Started { source, .. } => {
+                    job.detail = source.display().to_string();
+                }
+                ExportFileEvent::Skipped { source, reason } => {
+                    job.done += 1;
+                    job.errors
+                        .push(format!("{}: skipped, {}", source.display(), reason));
+                }
+                ExportFileEvent::Finished { source, result } => {
+                    job.done += 1;
+                    if let Err(e) = result {
+                        job.errors.push(format!("{}: {}", source.display(), e));
+                    }
+                }
+                ExportFileEvent::Copied { .. } | ExportFileEvent::Verifying { .. } => {}
+            });
+        }));
+        let result = exporter
+            .export_batch(&entries, |_| {})
+            .await
+            .map(|r| format!("Exported {} files ({} failed)", r.successful, r.failed))
+            .map_err(Into::into);
+        jobs.finish(id, result);
+    });
+    Ok(Json(job))
+}
+
+async fn list_jobs(State(state): State<ServerState>) -> Json<Vec<JobInfo>> {
+    Json(state.jobs.list())
+}
+
+/// Stream every job update as JSON, starting with the current jobs
+async fn events(State(state): State<ServerState>, ws: WebSocketUpgrade) -> Response {
+    ws.on_upgrade(move |socket| stream_jobs(socket, state.jobs))
+}
+
+async fn stream_jobs(mut socket: WebSocket, jobs: Arc<Jobs>) {
+    let mut updates = jobs.subscribe();
+    for job in jobs.list() {
+        if send_job(&mut socket, &job).await.is_err() {
+            return;
+        }
+    }
+    loop {
+        let job = match updates.recv().await {
+            Ok(job) => job,
+            // A slow client misses intermediate progress, not the outcome:
+            // the latest state of every job is sent again
+            Err(RecvError::Lagged(_)) => {
+                for job in jobs.list() {
+                    if send_job(&mut socket, &job).await.is_err() {
+                        return;
+                    }
+                }
+                continue;
+            }
+            Err(RecvError::Closed) => return,
+        };
+        if send_job(&mut socket, &job).await.is_err() {
+            return;
+        }
+    }
+}
+
+async fn send_job(socket: &mut WebSocket, job: &JobInfo) -> Result<()> {
+    let text = serde_json::to_string(job)?;
+    socket.send(WsMessage::Text(text)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+
+    async fn spawn_server(source: &Path, token: Option<&str>) -> SocketAddr {
+        let state = ServerState {
+            source: source.to_path_buf(),
+            engine: Arc::new(DrillEngine::new(source.to_path_buf()).await.unwrap()),
+            thumbnails: Arc::new(ThumbnailGenerator::new()),
+            jobs: Arc::new(Jobs::new()),
+            token: token.map(Arc::from),
+        };
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router(state)).await });
+        addr
+    }
+
+    /// Blocking HTTP calls run off the runtime the server is on
+    async fn call(
+        request: ureq::Request,
+        body: Option<serde_json::Value>,
+    ) -> (u16, serde_json::Value) {
+        tokio::task::spawn_blocking(move || {
+            let sent = match body {
+                Some(body) => request.send_json(body),
+                None => request.call(),
+            };
+            let response = match sent {
+                Ok(r) => r,
+                Err(ureq::Error::Status(_, r)) => r,
+                Err(e) => panic!("{}", e),
+            };
+            (response.status(), response.into_json().unwrap_or_default())
+        })
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_index_search_and_export_over_http() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source");
+        std::fs::create_dir(&source).unwrap();
+        std::fs::write(source.join("holiday.txt"), b"beach").unwrap();
+        std::fs::write(source.join("notes.txt"), b"todo").unwrap();
+        let source = source.canonicalize().unwrap();
+        let addr = spawn_server(&source, Some("secret")).await;
+        let url = |path: &str| format!("http://{}{}", addr, path);
+
+        let (code, _) = call(ureq::get(&url("/api/status")), None).await;
+        assert_eq!(code, 401, "token required");
+
+        let (code, job) = call(ureq::post(&url("/api/index?token=secret")), None).await;
+        assert_eq!(code, 200);
+        assert_eq!(job["kind"], "index");
+        loop {
+            let request = ureq::get(&url("/api/status")).set("Authorization", "Bearer secret");
+            let (_, status) = call(request, None).await;
+            if status["indexing"] == false {
+                assert_eq!(status["files"], 2);
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+
+        let (_, found) = call(ureq::get(&url("/api/search?token=secret&q=holiday")), None).await;
+        let found = found.as_array().unwrap();
+        assert_eq!(found.len(), 1);
+        let path = found[0]["path"].as_str().unwrap().to_string();
+
+        let elsewhere = dir.path().join("elsewhere.png");
+        let request = ureq::get(&url("/api/thumbnail?token=secret"))
+            .query("path", &elsewhere.to_string_lossy());
+        let (code, _) = call(request, None).await;
+        assert_eq!(code, 404, "only indexed files");
+
+        let dest = dir.path().join("out");
+        let body = serde_json::json!({ "paths": [path], "dest": dest });
+        let (code, job) = call(ureq::post(&url("/api/export?token=secret")), Some(body)).await;
+        assert_eq!(code, 200);
+        let id = job["id"].as_u64().unwrap();
+        loop {
+            let (_, jobs) = call(ureq::get(&url("/api/jobs?token=secret")), None).await;
+            let job = jobs
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|j| j["id"] == id)
+                .unwrap()
+                .clone();
+            if job["status"]["state"] != "running" {
+                assert_eq!(job["status"]["state"], "done", "{}", job);
+                assert_eq!(job["done"], 1);
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        let exported: Vec<_> = walkdir::WalkDir::new(&dest)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name() == "holiday.txt")
+            .collect();
+        assert_eq!(exported.len(), 1);
+    }
+}
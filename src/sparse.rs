@@ -0,0 +1,280 @@
+//! Sparse files
+//!
+//! Disk images and VM disks are mostly holes: ranges that read as zeros but
+//! take no space. Copying them byte for byte materialises every hole. These
+//! helpers find where a source actually has data (`SEEK_DATA`/`SEEK_HOLE`,
+//! `FSCTL_QUERY_ALLOCATED_RANGES` on Windows) so a copy can seek over the
+//! holes instead, after marking the destination sparse where the file
+//! system needs that (`FSCTL_SET_SPARSE`).
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+
+/// A stretch of a sparse file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Extent {
+    Data(Range<u64>),
+    Hole(Range<u64>),
+}
+
+/// Byte ranges of `file` that hold data, or None when it has no holes or
+/// the platform / file system cannot tell
+pub fn data_ranges(file: &File) -> Option<Vec<Range<u64>>> {
+    let len = file.metadata().ok()?.len();
+    if len == 0 {
+        return None;
+    }
+    // Hole queries move the file offset, which callers share
+    let offset = (&*file).stream_position().ok()?;
+    let queried = query_data_ranges(file, len);
+    (&*file).seek(SeekFrom::Start(offset)).ok()?;
+    let ranges = match queried {
+        Ok(ranges) => ranges?,
+        Err(e) => {
+            tracing::debug!("Could not map holes: {}", e);
+            return None;
+        }
+    };
+    // One extent covering everything is a regular file
+    let whole = ranges.len() == 1 && ranges[0] == (0..len);
+    (!whole).then_some(ranges)
+}
+
+/// The file split into data and holes, in order, covering `0..len`
+pub fn extents(ranges: &[Range<u64>], len: u64) -> Vec<Extent> {
+    let mut extents = Vec::with_capacity(ranges.len() * 2 + 1);
+    let mut pos = 0;
+    for range in ranges {
+        let start = range.start.min(len);
+        let end = range.end.min(len);
+        if start > pos {
+            extents.push(Extent::Hole(pos..start));
+        }
+        if end > start {
+            extents.push(Extent::Data(start..end));
+        }
+        pos = pos.max(end);
+    }
+    if pos < len {
+        extents.push(Extent::Hole(pos..len));
+    }
+    extents
+}
+
+/// Let holes in `file` stay unallocated. Only Windows needs this; failing
+/// (e.g. on FAT) just means the holes are written as zeros.
+pub fn mark_sparse(file: &File) {
+    if let Err(e) = set_sparse(file) {
+        tracing::debug!("Could not mark destination sparse: {}", e);
+    }
+}
+
+/// Copy the `ranges` of `src` that hold data into `dst`, seeking over the
+/// holes between them, and give `dst` the length of `src`. Returns that
+/// length.
+pub fn copy_ranges(src: &mut File, dst: &mut File, ranges: &[Range<u64>]) -> io::Result<u64> {
+    let len = src.metadata()?.len();
+    mark_sparse(dst);
+    for extent in extents(ranges, len) {
+        if let Extent::Data(range) = extent {
+            src.seek(SeekFrom::Start(range.start))?;
+            dst.seek(SeekFrom::Start(range.start))?;
+            let copied = io::copy(&mut Read::by_ref(src).take(range.end - range.start), dst)?;
+            if copied != range.end - range.start {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "source shrank while copying",
+                ));
+            }
+        }
+    }
+    // A trailing hole is only there once the length is set
+    dst.set_len(len)?;
+    dst.flush()?;
+    Ok(len)
+}
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "freebsd",
+    target_os = "macos"
+))]
+fn query_data_ranges(file: &File, len: u64) -> io::Result<Option<Vec<Range<u64>>>> {
+    use std::os::unix::io::AsRawFd;
+
+    let fd = file.as_raw_fd();
+    let mut ranges = Vec::new();
+    let mut pos = 0u64;
+    while pos < len {
+        // SAFETY: lseek only moves the offset of a descriptor we hold open
+        let data = unsafe { libc::lseek(fd, pos as libc::off_t, libc::SEEK_DATA) };
+        if data < 0 {
+            let error = io::Error::last_os_error();
+            return match error.raw_os_error() {
+                // No data after `pos`: the rest is a hole
+                Some(libc::ENXIO) => Ok(Some(ranges)),
+                // The file system doesn't support hole queries
+                Some(libc::EINVAL) | Some(libc::ENOTSUP) => Ok(None),
+                _ => Err(error),
+            };
+        }
+        // SAFETY: as above
+        let hole = unsafe { libc::lseek(fd, data, libc::SEEK_HOLE) };
+        if hole < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let (data, hole) = (data as u64, (hole as u64).min(len));
+        if hole <= data {
+            break;
+        }
+        ranges.push(data..hole);
+        pos = hole;
+    }
+    Ok(Some(ranges))
+}
+
+#[cfg(windows)]
+fn query_data_ranges(file: &File, len: u64) -> io::Result<Option<Vec<Range<u64>>>> {
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::Foundation::ERROR_MORE_DATA;
+    use windows_sys::Win32::System::Ioctl::{
+        FILE_ALLOCATED_RANGE_BUFFER, FSCTL_QUERY_ALLOCATED_RANGES,
+    };
+    use windows_sys::Win32::System::IO::DeviceIoControl;
+
+    let mut ranges = Vec::new();
+    let mut query = FILE_ALLOCATED_RANGE_BUFFER {
+        FileOffset: 0,
+        Length: len as i64,
+    };
+    let mut out = [FILE_ALLOCATED_RANGE_BUFFER {
+        FileOffset: 0,
+        Length: 0,
+    }; 256];
+    loop {
+        let mut returned = 0u32;
+        // SAFETY: the handle is open, and the buffers and their sizes match
+        let ok = unsafe {
+            DeviceIoControl(
+                file.as_raw_handle() as _,
+                FSCTL_QUERY_ALLOCATED_RANGES,
+                &query as *const _ as *const _,
+                std::mem::size_of::<FILE_ALLOCATED_RANGE_BUFFER>() as u32,
+                out.as_mut_ptr() as *mut _,
+                std::mem::size_of_val(&out) as u32,
+                &mut returned,
+                std::ptr::null_mut(),
+            )
+        };
+        let error = (ok == 0).then(io::Error::last_os_error);
+        let more = error
+            .as_ref()
+            .is_some_and(|e| e.raw_os_error() == Some(ERROR_MORE_DATA as i32));
+        if let (Some(error), false) = (error, more) {
+            return Err(error);
+        }
+        let count = returned as usize / std::mem::size_of::<FILE_ALLOCATED_RANGE_BUFFER>();
+        ranges.extend(
+            out[..count]
+                .iter()
+                .map(|r| r.FileOffset as u64..(r.FileOffset + r.Length) as u64),
+        );
+        match ranges.last() {
+            Some(last) if more => {
+                query.FileOffset = last.end as i64;
+                query.Length = len as i64 - query.FileOffset;
+            }
+            _ => return Ok(Some(ranges)),
+        }
+    }
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "freebsd",
+    target_os = "macos",
+    windows
+)))]
+fn query_data_ranges(_file: &File, _len: u64) -> io::Result<Option<Vec<Range<u64>>>> {
+    Ok(None)
+}
+
+#[cfg(windows)]
+fn set_sparse(file: &File) -> io::Result<()> {
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::System::Ioctl::FSCTL_SET_SPARSE;
+    use windows_sys::Win32::System::IO::DeviceIoControl;
+
+    let mut returned = 0u32;
+    // SAFETY: the handle is open; FSCTL_SET_SPARSE takes no buffers
+    let ok = unsafe {
+        DeviceIoControl(
+            file.as_raw_handle() as _,
+            FSCTL_SET_SPARSE,
+            std::ptr::null(),
+            0,
+            std::ptr::null_mut(),
+            0,
+            &mut returned,
+            std::ptr::null_mut(),
+        )
+    };
+    if ok == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(windows))]
+fn set_sparse(_file: &File) -> io::Result<()> {
+    // Unix file systems leave skipped ranges unallocated by themselves
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sparse_copy_keeps_holes() {
+        let dir = tempfile::tempdir().unwrap();
+        let image = dir.path().join("disk.img");
+        let mut file = File::create(&image).unwrap();
+        file.set_len(16 << 20).unwrap();
+        file.seek(SeekFrom::Start(4 << 20)).unwrap();
+        file.write_all(&[0xab; 8192]).unwrap();
+        drop(file);
+
+        assert_eq!(
+            extents(std::slice::from_ref(&(4..6)), 10),
+            vec![Extent::Hole(0..4), Extent::Data(4..6), Extent::Hole(6..10)]
+        );
+
+        let mut src = File::open(&image).unwrap();
+        // Not every file system used for temp directories reports holes
+        let Some(ranges) = data_ranges(&src) else {
+            return;
+        };
+        assert!(ranges
+            .iter()
+            .any(|r| r.start <= 4 << 20 && r.end >= (4 << 20) + 8192));
+
+        let copy = dir.path().join("copy.img");
+        let mut dst = File::create(&copy).unwrap();
+        assert_eq!(copy_ranges(&mut src, &mut dst, &ranges).unwrap(), 16 << 20);
+        drop(dst);
+        assert_eq!(
+            std::fs::read(&copy).unwrap(),
+            std::fs::read(&image).unwrap()
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            let allocated = std::fs::metadata(&copy).unwrap().blocks() * 512;
+            assert!(allocated < 1 << 20, "copy allocated {} bytes", allocated);
+        }
+    }
+}
@@ -208,6 +208,11 @@ impl PulseProgress {
         self.current.store(current, Ordering::Relaxed);
     }
 
+    /// Change the total, e.g. once a scan has counted the work
+    pub fn set_total(&self, total: u64) {
+        self.total.store(total, Ordering::Relaxed);
+    }
+
     /// Increment progress
     pub fn inc(&self, delta: u64) {
         self.current.fetch_add(delta, Ordering::Relaxed);
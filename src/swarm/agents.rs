@@ -3,16 +3,21 @@
 //! Each agent is a specialized worker that can operate independently
 //! while coordinating through message channels.
 
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 
 use anyhow::{Context, Result};
-use crossbeam_channel::{Receiver, Sender};
+use crossbeam_channel::{bounded, Receiver, Sender};
 use parking_lot::RwLock;
 use rayon::prelude::*;
 use tracing::{debug, info, warn};
 
+use super::cache::EmbeddingCache;
+use super::chunker::{extract_document_text, MediaType};
+use super::session::SessionStore;
+
 // ============================================================================
 // Agent Messages
 // ============================================================================
@@ -28,6 +33,8 @@ pub enum SwarmMessage {
         chunk_id: usize,
         data: Vec<u8>,
     },
+    /// All chunks of a file have been sent
+    FileChunked { source: PathBuf, chunks: usize },
     /// Embedded vector
     Embedding {
         source: PathBuf,
@@ -55,7 +62,9 @@ pub enum SwarmMessage {
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum AgentRole {
     Scan,
+    Transcribe,
     Chunk,
+    Classify,
     Embed,
     Heal,
     VerifyExport,
@@ -65,7 +74,9 @@ impl AgentRole {
     pub fn icon(&self) -> &'static str {
         match self {
             AgentRole::Scan => "🔍",
+            AgentRole::Transcribe => "🎙️",
             AgentRole::Chunk => "✂️",
+            AgentRole::Classify => "🏷️",
             AgentRole::Embed => "🧠",
             AgentRole::Heal => "💊",
             AgentRole::VerifyExport => "✅",
@@ -73,6 +84,16 @@ impl AgentRole {
     }
 }
 
+/// Dedicated thread pool for one agent, so a stage blocked on a full
+/// channel never holds threads another stage needs
+fn worker_pool(threads: usize, name: &'static str) -> Result<rayon::ThreadPool> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads.max(1))
+        .thread_name(move |i| format!("{}-{}", name, i))
+        .build()
+        .with_context(|| format!("Failed to start {} workers", name))
+}
+
 // ============================================================================
 // Swarm Statistics
 // ============================================================================
@@ -80,6 +101,8 @@ impl AgentRole {
 /// Statistics tracked by the swarm
 #[derive(Debug, Default)]
 pub struct SwarmStats {
+    /// Files the scan found to process, set once the walk is done
+    pub files_discovered: AtomicUsize,
     pub files_scanned: AtomicUsize,
     pub chunks_created: AtomicUsize,
     pub embeddings_generated: AtomicUsize,
@@ -88,6 +111,14 @@ pub struct SwarmStats {
     pub bytes_processed: AtomicU64,
     pub errors_encountered: AtomicUsize,
     pub errors_healed: AtomicUsize,
+    pub chunks_flagged: AtomicUsize,
+    pub sensitive_files: AtomicUsize,
+    pub files_transcribed: AtomicUsize,
+    pub files_skipped: AtomicUsize,
+    /// Files whose chunks have all reached VerifyExportAgent
+    pub files_completed: AtomicUsize,
+    /// Embeddings served from the embedding cache
+    pub embeddings_cached: AtomicUsize,
 }
 
 impl SwarmStats {
@@ -105,6 +136,12 @@ impl SwarmStats {
             bytes_processed: self.bytes_processed.load(Ordering::Relaxed),
             errors_encountered: self.errors_encountered.load(Ordering::Relaxed),
             errors_healed: self.errors_healed.load(Ordering::Relaxed),
+            chunks_flagged: self.chunks_flagged.load(Ordering::Relaxed),
+            sensitive_files: self.sensitive_files.load(Ordering::Relaxed),
+            files_transcribed: self.files_transcribed.load(Ordering::Relaxed),
+            files_skipped: self.files_skipped.load(Ordering::Relaxed),
+            embeddings_cached: self.embeddings_cached.load(Ordering::Relaxed),
+            session: None,
         }
     }
 }
@@ -120,6 +157,26 @@ pub struct SwarmSummary {
     pub bytes_processed: u64,
     pub errors_encountered: usize,
     pub errors_healed: usize,
+    /// Chunks the classifier found personal data in
+    #[serde(default)]
+    pub chunks_flagged: usize,
+    /// Files the classifier rated above [`Sensitivity::None`]
+    ///
+    /// [`Sensitivity::None`]: super::Sensitivity::None
+    #[serde(default)]
+    pub sensitive_files: usize,
+    /// Audio and video files the transcriber turned into text
+    #[serde(default)]
+    pub files_transcribed: usize,
+    /// Files already processed by the session being resumed
+    #[serde(default)]
+    pub files_skipped: usize,
+    /// Embeddings reused from the embedding cache instead of computed
+    #[serde(default)]
+    pub embeddings_cached: usize,
+    /// Session file the run can be resumed from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<PathBuf>,
 }
 
 // ============================================================================
@@ -134,6 +191,9 @@ pub struct ScanAgent {
     stats: Arc<SwarmStats>,
     skip_hidden: bool,
     extensions: Option<Vec<String>>,
+    skip_files: HashSet<PathBuf>,
+    files: Option<Vec<PathBuf>>,
+    workers: usize,
 }
 
 impl ScanAgent {
@@ -150,9 +210,31 @@ impl ScanAgent {
             stats,
             skip_hidden: true,
             extensions: None,
+            skip_files: HashSet::new(),
+            files: None,
+            workers: num_cpus::get(),
         }
     }
 
+    /// Threads queueing files for chunking
+    pub fn with_workers(mut self, workers: usize) -> Self {
+        self.workers = workers;
+        self
+    }
+
+    /// Leave out files finished in an earlier run of a resumed session
+    pub fn skip_files(mut self, files: HashSet<PathBuf>) -> Self {
+        self.skip_files = files;
+        self
+    }
+
+    /// Queue exactly these files instead of walking the source, e.g. the
+    /// failures from an earlier run's heal log
+    pub fn with_files(mut self, files: Vec<PathBuf>) -> Self {
+        self.files = Some(files);
+        self
+    }
+
     pub fn with_extensions(mut self, exts: Vec<String>) -> Self {
         self.extensions = Some(exts);
         self
@@ -171,44 +253,64 @@ impl ScanAgent {
             self.source.display()
         );
 
-        let entries: Vec<PathBuf> = walkdir::WalkDir::new(&self.source)
-            .follow_links(false)
+        let candidates: Vec<PathBuf> = match self.files {
+            Some(ref files) => files.clone(),
+            None => walkdir::WalkDir::new(&self.source)
+                .follow_links(false)
+                .into_iter()
+                .filter_entry(|e| {
+                    if self.skip_hidden {
+                        !e.file_name()
+                            .to_str()
+                            .map(|s| s.starts_with('.'))
+                            .unwrap_or(false)
+                    } else {
+                        true
+                    }
+                })
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_file())
+                .filter(|e| self.matches_extensions(e.path()))
+                .map(|e| e.path().to_path_buf())
+                .collect(),
+        };
+        let entries: Vec<PathBuf> = candidates
             .into_iter()
-            .filter_entry(|e| {
-                if self.skip_hidden {
-                    !e.file_name()
-                        .to_str()
-                        .map(|s| s.starts_with('.'))
-                        .unwrap_or(false)
-                } else {
-                    true
+            .filter(|path| {
+                let done = self.skip_files.contains(path);
+                if done {
+                    self.stats.files_skipped.fetch_add(1, Ordering::Relaxed);
                 }
+                !done
             })
-            .filter_map(|e| e.ok())
-            .filter(|e| e.file_type().is_file())
-            .filter(|e| self.matches_extensions(e.path()))
-            .map(|e| e.path().to_path_buf())
             .collect();
-
-        // Process in parallel with rayon
-        entries.par_iter().for_each(|path| {
-            match self.process_file(path) {
-                Ok(()) => {
-                    self.stats.files_scanned.fetch_add(1, Ordering::Relaxed);
-                }
-                Err(e) => {
-                    self.stats
-                        .errors_encountered
-                        .fetch_add(1, Ordering::Relaxed);
-                    // Send to heal agent
-                    let _ = self.heal_tx.send(SwarmMessage::Failure {
-                        agent: AgentRole::Scan,
-                        source: path.clone(),
-                        error: e.to_string(),
-                        retries_left: 3,
-                    });
+        self.stats
+            .files_discovered
+            .store(entries.len(), Ordering::Relaxed);
+
+        // Process in parallel with rayon; workers block while the chunk
+        // stage's channel is full
+        let pool = worker_pool(self.workers, "swarm-scan")?;
+        pool.install(|| {
+            entries.par_iter().for_each(|path| {
+                match self.process_file(path) {
+                    Ok(()) => {
+                        self.stats.files_scanned.fetch_add(1, Ordering::Relaxed);
+                    }
+                    Err(e) => {
+                        self.stats
+                            .errors_encountered
+                            .fetch_add(1, Ordering::Relaxed);
+                        // Send to heal agent
+                        let _ = self.heal_tx.send(SwarmMessage::Failure {
+                            agent: AgentRole::Scan,
+                            source: path.clone(),
+                            error: e.to_string(),
+                            retries_left: 3,
+                        });
+                    }
                 }
-            }
+            })
         });
 
         // Signal done
@@ -262,6 +364,7 @@ pub struct ChunkAgent {
     stats: Arc<SwarmStats>,
     chunk_size: usize,
     overlap: usize,
+    workers: usize,
 }
 
 impl ChunkAgent {
@@ -278,9 +381,16 @@ impl ChunkAgent {
             stats,
             chunk_size: 1024, // 1KB default chunks
             overlap: 128,     // 128 byte overlap
+            workers: num_cpus::get(),
         }
     }
 
+    /// Files read and split at once
+    pub fn with_workers(mut self, workers: usize) -> Self {
+        self.workers = workers;
+        self
+    }
+
     pub fn with_chunk_size(mut self, size: usize, overlap: usize) -> Self {
         self.chunk_size = size;
         self.overlap = overlap;
@@ -289,30 +399,34 @@ impl ChunkAgent {
 
     /// Run the chunk agent - parallel document splitting
     pub fn run(&self) -> Result<()> {
-        info!("{} ChunkAgent starting", AgentRole::Chunk.icon());
+        info!(
+            "{} ChunkAgent starting ({} workers)",
+            AgentRole::Chunk.icon(),
+            self.workers
+        );
 
-        while let Ok(msg) = self.input.recv() {
-            match msg {
-                SwarmMessage::FilePath(path) => {
-                    if let Err(e) = self.process_file(&path) {
-                        self.stats
-                            .errors_encountered
-                            .fetch_add(1, Ordering::Relaxed);
-                        let _ = self.heal_tx.send(SwarmMessage::Failure {
-                            agent: AgentRole::Chunk,
-                            source: path,
-                            error: e.to_string(),
-                            retries_left: 3,
+        let pool = worker_pool(self.workers, "swarm-chunk")?;
+        // One slot per worker: while all are busy, files wait in the
+        // bounded input channel rather than piling up in the pool's queue
+        let (slot_tx, slot_rx) = bounded::<()>(self.workers.max(1));
+        pool.in_place_scope(|scope| {
+            while let Ok(msg) = self.input.recv() {
+                match msg {
+                    SwarmMessage::FilePath(path) => {
+                        let _ = slot_tx.send(());
+                        let slot_rx = &slot_rx;
+                        scope.spawn(move |_| {
+                            self.chunk_file(path);
+                            let _ = slot_rx.recv();
                         });
                     }
+                    SwarmMessage::Done => break,
+                    _ => {}
                 }
-                SwarmMessage::Done => {
-                    let _ = self.output.send(SwarmMessage::Done);
-                    break;
-                }
-                _ => {}
             }
-        }
+        });
+        // Every file's chunks are out before Done
+        let _ = self.output.send(SwarmMessage::Done);
 
         info!(
             "{} ChunkAgent complete: {} chunks",
@@ -323,9 +437,30 @@ impl ChunkAgent {
         Ok(())
     }
 
+    fn chunk_file(&self, path: PathBuf) {
+        if let Err(e) = self.process_file(&path) {
+            self.stats
+                .errors_encountered
+                .fetch_add(1, Ordering::Relaxed);
+            let _ = self.heal_tx.send(SwarmMessage::Failure {
+                agent: AgentRole::Chunk,
+                source: path,
+                error: e.to_string(),
+                retries_left: 3,
+            });
+        }
+    }
+
     fn process_file(&self, path: &Path) -> Result<()> {
-        let data = std::fs::read(path)
-            .with_context(|| format!("Failed to read file: {}", path.display()))?;
+        // Office documents are chunked by their text, not their zipped XML
+        let data = if MediaType::from_path(path) == MediaType::Document {
+            extract_document_text(path)
+                .with_context(|| format!("Not an Office document: {}", path.display()))??
+                .into_bytes()
+        } else {
+            std::fs::read(path)
+                .with_context(|| format!("Failed to read file: {}", path.display()))?
+        };
 
         // Split into chunks with overlap using par_chunks
         let chunks: Vec<(usize, Vec<u8>)> = data
@@ -348,6 +483,7 @@ impl ChunkAgent {
             })
             .collect();
 
+        let total = chunks.len();
         for (chunk_id, chunk_data) in chunks {
             self.stats.chunks_created.fetch_add(1, Ordering::Relaxed);
             self.output.send(SwarmMessage::Chunk {
@@ -356,6 +492,10 @@ impl ChunkAgent {
                 data: chunk_data,
             })?;
         }
+        self.output.send(SwarmMessage::FileChunked {
+            source: path.to_path_buf(),
+            chunks: total,
+        })?;
 
         Ok(())
     }
@@ -371,6 +511,8 @@ pub struct EmbedConfig {
     pub use_gpu: bool,
     pub model_dim: usize,
     pub batch_size: usize,
+    /// Chunks of a batch embedded at once
+    pub workers: usize,
 }
 
 impl Default for EmbedConfig {
@@ -379,6 +521,7 @@ impl Default for EmbedConfig {
             use_gpu: true,
             model_dim: 768,
             batch_size: 32,
+            workers: num_cpus::get(),
         }
     }
 }
@@ -391,6 +534,7 @@ pub struct EmbedAgent {
     stats: Arc<SwarmStats>,
     config: EmbedConfig,
     gpu_available: Arc<RwLock<bool>>,
+    cache: Option<Arc<EmbeddingCache>>,
 }
 
 impl EmbedAgent {
@@ -407,6 +551,7 @@ impl EmbedAgent {
             stats,
             config: EmbedConfig::default(),
             gpu_available: Arc::new(RwLock::new(true)),
+            cache: None,
         }
     }
 
@@ -415,6 +560,12 @@ impl EmbedAgent {
         self
     }
 
+    /// Reuse embeddings of chunks seen before, here or in earlier runs
+    pub fn with_cache(mut self, cache: Arc<EmbeddingCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
     /// Run the embed agent - parallel vectorization
     pub fn run(&self) -> Result<()> {
         info!(
@@ -423,22 +574,28 @@ impl EmbedAgent {
             self.config.use_gpu
         );
 
+        let pool = worker_pool(self.config.workers, "swarm-embed")?;
         let mut batch: Vec<SwarmMessage> = Vec::with_capacity(self.config.batch_size);
+        // End-of-file markers wait for the batch holding the file's chunks
+        let mut chunked: Vec<SwarmMessage> = Vec::new();
 
         while let Ok(msg) = self.input.recv() {
             match msg {
                 SwarmMessage::Chunk { .. } => {
                     batch.push(msg);
                     if batch.len() >= self.config.batch_size {
-                        self.process_batch(&batch);
+                        self.process_batch(&pool, &batch);
                         batch.clear();
+                        self.forward(&mut chunked);
                     }
                 }
+                SwarmMessage::FileChunked { .. } => chunked.push(msg),
                 SwarmMessage::Done => {
                     // Process remaining batch
                     if !batch.is_empty() {
-                        self.process_batch(&batch);
+                        self.process_batch(&pool, &batch);
                     }
+                    self.forward(&mut chunked);
                     let _ = self.output.send(SwarmMessage::Done);
                     break;
                 }
@@ -455,28 +612,36 @@ impl EmbedAgent {
         Ok(())
     }
 
-    fn process_batch(&self, batch: &[SwarmMessage]) {
+    fn forward(&self, messages: &mut Vec<SwarmMessage>) {
+        for msg in messages.drain(..) {
+            let _ = self.output.send(msg);
+        }
+    }
+
+    fn process_batch(&self, pool: &rayon::ThreadPool, batch: &[SwarmMessage]) {
         // Try GPU first, fall back to CPU
         let use_gpu = *self.gpu_available.read();
 
-        let results: Vec<_> = batch
-            .par_iter()
-            .map(|msg| {
-                if let SwarmMessage::Chunk {
-                    source,
-                    chunk_id,
-                    data,
-                } = msg
-                {
-                    match self.embed_chunk(data, use_gpu) {
-                        Ok(vector) => Ok((source.clone(), *chunk_id, vector)),
-                        Err(e) => Err((source.clone(), *chunk_id, e.to_string())),
+        let results: Vec<_> = pool.install(|| {
+            batch
+                .par_iter()
+                .map(|msg| {
+                    if let SwarmMessage::Chunk {
+                        source,
+                        chunk_id,
+                        data,
+                    } = msg
+                    {
+                        match self.embed_cached(data, use_gpu) {
+                            Ok(vector) => Ok((source.clone(), *chunk_id, vector)),
+                            Err(e) => Err((source.clone(), *chunk_id, e.to_string())),
+                        }
+                    } else {
+                        Err((PathBuf::new(), 0, "Invalid message".to_string()))
                     }
-                } else {
-                    Err((PathBuf::new(), 0, "Invalid message".to_string()))
-                }
-            })
-            .collect();
+                })
+                .collect()
+        });
 
         for result in results {
             match result {
@@ -512,6 +677,19 @@ impl EmbedAgent {
         }
     }
 
+    fn embed_cached(&self, data: &[u8], use_gpu: bool) -> Result<Vec<f32>> {
+        let Some(ref cache) = self.cache else {
+            return self.embed_chunk(data, use_gpu);
+        };
+        if let Some(vector) = cache.get(data) {
+            self.stats.embeddings_cached.fetch_add(1, Ordering::Relaxed);
+            return Ok(vector);
+        }
+        let vector = self.embed_chunk(data, use_gpu)?;
+        cache.insert(data, &vector);
+        Ok(vector)
+    }
+
     fn embed_chunk(&self, data: &[u8], use_gpu: bool) -> Result<Vec<f32>> {
         // Placeholder: In production, this would call actual embedding model
         // For now, generate deterministic hash-based pseudo-embedding
@@ -539,8 +717,10 @@ impl EmbedAgent {
 // VerifyExportAgent - Validation and output
 // ============================================================================
 
-/// Type alias for stored embeddings (source path, chunk id, vector)
-type EmbeddingEntry = (PathBuf, usize, Vec<f32>);
+/// Manifest entry kept per verified embedding (source path, chunk id,
+/// dimension, norm); the vector itself is not held, so memory stays flat
+/// over millions of chunks
+type ExportEntry = (PathBuf, usize, usize, f32);
 
 /// Verifies embeddings and exports results
 pub struct VerifyExportAgent {
@@ -548,7 +728,8 @@ pub struct VerifyExportAgent {
     heal_tx: Sender<SwarmMessage>,
     stats: Arc<SwarmStats>,
     output_path: Option<PathBuf>,
-    embeddings: Arc<RwLock<Vec<EmbeddingEntry>>>,
+    embeddings: Arc<RwLock<Vec<ExportEntry>>>,
+    session: Option<Arc<SessionStore>>,
 }
 
 impl VerifyExportAgent {
@@ -563,9 +744,16 @@ impl VerifyExportAgent {
             stats,
             output_path: None,
             embeddings: Arc::new(RwLock::new(Vec::new())),
+            session: None,
         }
     }
 
+    /// Record each file in the session once all its chunks are verified
+    pub fn with_session(mut self, session: Arc<SessionStore>) -> Self {
+        self.session = Some(session);
+        self
+    }
+
     pub fn with_output(mut self, path: PathBuf) -> Self {
         self.output_path = Some(path);
         self
@@ -578,14 +766,18 @@ impl VerifyExportAgent {
             AgentRole::VerifyExport.icon()
         );
 
+        // Chunks verified per file since its last FileChunked marker
+        let mut verified: HashMap<PathBuf, usize> = HashMap::new();
+
         while let Ok(msg) = self.input.recv() {
             match msg {
                 SwarmMessage::Embedding {
                     source,
                     chunk_id,
                     vector,
-                } => {
-                    if let Err(e) = self.verify_and_store(&source, chunk_id, vector) {
+                } => match self.verify_and_store(&source, chunk_id, vector) {
+                    Ok(()) => *verified.entry(source).or_default() += 1,
+                    Err(e) => {
                         self.stats
                             .errors_encountered
                             .fetch_add(1, Ordering::Relaxed);
@@ -596,6 +788,22 @@ impl VerifyExportAgent {
                             retries_left: 3,
                         });
                     }
+                },
+                // Embeddings arrive ahead of their file's marker; a file
+                // with failed chunks stays pending in the session
+                SwarmMessage::FileChunked { source, chunks } => {
+                    self.stats.files_completed.fetch_add(1, Ordering::Relaxed);
+                    let done = verified.remove(&source).unwrap_or(0);
+                    match self.session {
+                        Some(ref session) if done == chunks => {
+                            if let Err(e) =
+                                session.update(|s| s.mark_processed(source, chunks, done))
+                            {
+                                warn!("Failed to save session: {}", e);
+                            }
+                        }
+                        _ => {}
+                    }
                 }
                 SwarmMessage::Done => break,
                 _ => {}
@@ -635,9 +843,10 @@ impl VerifyExportAgent {
         }
 
         // Store verified embedding
+        let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
         self.embeddings
             .write()
-            .push((source.to_path_buf(), chunk_id, vector));
+            .push((source.to_path_buf(), chunk_id, vector.len(), norm));
         self.stats.exports_completed.fetch_add(1, Ordering::Relaxed);
 
         Ok(())
@@ -649,12 +858,12 @@ impl VerifyExportAgent {
         // Create export manifest
         let manifest = serde_json::json!({
             "total_embeddings": embeddings.len(),
-            "files": embeddings.iter().map(|(path, chunk_id, vec)| {
+            "files": embeddings.iter().map(|(path, chunk_id, dim, norm)| {
                 serde_json::json!({
                     "source": path.to_string_lossy(),
                     "chunk_id": chunk_id,
-                    "dim": vec.len(),
-                    "norm": (vec.iter().map(|v| v * v).sum::<f32>()).sqrt()
+                    "dim": dim,
+                    "norm": norm
                 })
             }).collect::<Vec<_>>()
         });
@@ -665,8 +874,8 @@ impl VerifyExportAgent {
         Ok(())
     }
 
-    /// Get all stored embeddings
-    pub fn get_embeddings(&self) -> Vec<EmbeddingEntry> {
+    /// Get the manifest entries of all verified embeddings
+    pub fn get_embeddings(&self) -> Vec<ExportEntry> {
         self.embeddings.read().clone()
     }
 }
@@ -721,6 +930,119 @@ mod tests {
         assert!(count >= 2, "Expected at least 2 messages, got {}", count);
     }
 
+    #[test]
+    fn test_scan_agent_file_list() {
+        let dir = tempdir().unwrap();
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            std::fs::write(dir.path().join(name), "content").unwrap();
+        }
+
+        let (scan_tx, scan_rx) = bounded(100);
+        let (heal_tx, heal_rx) = bounded(100);
+        let stats = Arc::new(SwarmStats::new());
+
+        // Only the listed files are queued; a vanished one goes to heal
+        ScanAgent::new(
+            dir.path().to_path_buf(),
+            scan_tx,
+            heal_tx,
+            Arc::clone(&stats),
+        )
+        .with_files(vec![dir.path().join("b.txt"), dir.path().join("gone.txt")])
+        .run()
+        .unwrap();
+
+        let queued: Vec<PathBuf> = scan_rx
+            .try_iter()
+            .filter_map(|msg| match msg {
+                SwarmMessage::FilePath(path) => Some(path),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(queued, vec![dir.path().join("b.txt")]);
+        assert_eq!(stats.files_discovered.load(Ordering::Relaxed), 2);
+        assert!(matches!(
+            heal_rx.try_recv(),
+            Ok(SwarmMessage::Failure { source, .. }) if source.ends_with("gone.txt")
+        ));
+    }
+
+    #[test]
+    fn test_chunk_agent_workers() {
+        let dir = tempdir().unwrap();
+        let (in_tx, in_rx) = bounded(10);
+        let (out_tx, out_rx) = bounded(100);
+        let (heal_tx, _heal_rx) = bounded(10);
+        let stats = Arc::new(SwarmStats::new());
+
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            let path = dir.path().join(name);
+            std::fs::write(&path, "x".repeat(40)).unwrap();
+            in_tx.send(SwarmMessage::FilePath(path)).unwrap();
+        }
+        in_tx.send(SwarmMessage::Done).unwrap();
+
+        ChunkAgent::new(in_rx, out_tx, heal_tx, Arc::clone(&stats))
+            .with_chunk_size(16, 0)
+            .with_workers(2)
+            .run()
+            .unwrap();
+
+        // Each file's marker follows its own chunks; Done comes last
+        let messages: Vec<_> = out_rx.try_iter().collect();
+        let mut seen: HashMap<PathBuf, usize> = HashMap::new();
+        for msg in &messages[..messages.len() - 1] {
+            match msg {
+                SwarmMessage::Chunk { source, .. } => *seen.entry(source.clone()).or_default() += 1,
+                SwarmMessage::FileChunked { source, chunks } => {
+                    assert_eq!(seen.get(source), Some(chunks));
+                    assert_eq!(*chunks, 3);
+                }
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+        assert!(matches!(messages.last(), Some(SwarmMessage::Done)));
+        assert_eq!(stats.chunks_created.load(Ordering::Relaxed), 9);
+    }
+
+    #[test]
+    fn test_embed_agent_cache() {
+        let dir = tempdir().unwrap();
+        let cache = Arc::new(EmbeddingCache::persistent(dir.path(), "test").unwrap());
+
+        let run = |stats: &Arc<SwarmStats>| -> Vec<f32> {
+            let (in_tx, in_rx) = bounded(10);
+            let (out_tx, out_rx) = bounded(10);
+            let (heal_tx, _heal_rx) = bounded(10);
+            in_tx
+                .send(SwarmMessage::Chunk {
+                    source: PathBuf::from("a.txt"),
+                    chunk_id: 0,
+                    data: b"same content".to_vec(),
+                })
+                .unwrap();
+            in_tx.send(SwarmMessage::Done).unwrap();
+            EmbedAgent::new(in_rx, out_tx, heal_tx, Arc::clone(stats))
+                .with_cache(Arc::clone(&cache))
+                .run()
+                .unwrap();
+            match out_rx.recv().unwrap() {
+                SwarmMessage::Embedding { vector, .. } => vector,
+                other => panic!("unexpected {:?}", other),
+            }
+        };
+
+        let first = Arc::new(SwarmStats::new());
+        let vector = run(&first);
+        assert_eq!(first.embeddings_cached.load(Ordering::Relaxed), 0);
+
+        // A second run over the same content reuses the vector
+        let second = Arc::new(SwarmStats::new());
+        assert_eq!(run(&second), vector);
+        assert_eq!(second.embeddings_cached.load(Ordering::Relaxed), 1);
+        assert_eq!(second.embeddings_generated.load(Ordering::Relaxed), 1);
+    }
+
     #[test]
     fn test_embed_agent_fallback() {
         let gpu_available = Arc::new(RwLock::new(true));
@@ -744,7 +1066,9 @@ mod tests {
     #[test]
     fn test_agent_role_icons() {
         assert_eq!(AgentRole::Scan.icon(), "🔍");
+        assert_eq!(AgentRole::Transcribe.icon(), "🎙️");
         assert_eq!(AgentRole::Chunk.icon(), "✂️");
+        assert_eq!(AgentRole::Classify.icon(), "🏷️");
         assert_eq!(AgentRole::Embed.icon(), "🧠");
         assert_eq!(AgentRole::Heal.icon(), "💊");
         assert_eq!(AgentRole::VerifyExport.icon(), "✅");
@@ -0,0 +1,396 @@
+//! Ask - Questions answered from the recovered corpus
+//!
+//! `diamond-drill ask` finds the passages of a source most relevant to a
+//! question:
+//! - Files are chunked and embedded, then searched with BM25 + vector fusion
+//!   (keyword only when no embedding server is running)
+//! - With `--answer`, the top passages go to a local chat model (LM Studio
+//!   or Ollama), told to answer only from them and cite them as `[n]`
+//! - Citations map back to file paths, and nothing leaves the machine
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use super::chunker::{ChunkConfig, MediaAwareChunker};
+use super::embedder::{AdaptiveEmbedder, Embedder, EmbedderConfig};
+use super::searcher::{IndexedDocument, SearchConfig, SearchIndex, SearchMode, SearchOptions};
+
+/// Characters of each passage put in the prompt
+const MAX_PASSAGE_CHARS: usize = 2000;
+
+const SYSTEM_PROMPT: &str = "You answer questions about a set of recovered files. \
+Use only the numbered excerpts you are given. Cite the excerpts that support each \
+statement as [n]. If the excerpts do not contain the answer, say so plainly.";
+
+/// Local chat server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatBackend {
+    /// LM Studio (OpenAI-compatible chat completions)
+    LmStudio,
+    /// Ollama
+    Ollama,
+}
+
+/// Which chat model answers, and where it runs
+#[derive(Debug, Clone)]
+pub struct ChatConfig {
+    pub backend: ChatBackend,
+    /// Server base URL
+    pub endpoint: String,
+    /// Model name; LM Studio answers with its loaded model if empty
+    pub model: String,
+    pub temperature: f32,
+    pub max_tokens: usize,
+    pub timeout: Duration,
+}
+
+impl ChatConfig {
+    /// LM Studio on localhost:1234
+    pub fn lm_studio(model: Option<String>) -> Self {
+        Self {
+            backend: ChatBackend::LmStudio,
+            endpoint: "http://localhost:1234/v1".to_string(),
+            model: model.unwrap_or_default(),
+            temperature: 0.1,
+            max_tokens: 1024,
+            timeout: Duration::from_secs(300),
+        }
+    }
+
+    /// Ollama on localhost:11434
+    pub fn ollama(model: Option<String>) -> Self {
+        Self {
+            backend: ChatBackend::Ollama,
+            endpoint: "http://localhost:11434".to_string(),
+            model: model.unwrap_or_else(|| "llama3.2".to_string()),
+            ..Self::lm_studio(None)
+        }
+    }
+
+    pub fn with_endpoint(mut self, endpoint: &str) -> Self {
+        self.endpoint = endpoint.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Send a system and user message, returning the reply
+    pub fn chat(&self, system: &str, user: &str) -> Result<String> {
+        let messages = json!([
+            { "role": "system", "content": system },
+            { "role": "user", "content": user },
+        ]);
+        let (url, body) = match self.backend {
+            ChatBackend::LmStudio => (
+                format!("{}/chat/completions", self.endpoint),
+                json!({
+                    "model": self.model,
+                    "messages": messages,
+                    "temperature": self.temperature,
+                    "max_tokens": self.max_tokens,
+                    "stream": false,
+                }),
+            ),
+            ChatBackend::Ollama => (
+                format!("{}/api/chat", self.endpoint),
+                json!({
+                    "model": self.model,
+                    "messages": messages,
+                    "stream": false,
+                    "options": {
+                        "temperature": self.temperature,
+                        "num_predict": self.max_tokens,
+                    },
+                }),
+            ),
+        };
+
+        let response: Value = ureq::post(&url)
+            .timeout(self.timeout)
+            .send_json(&body)
+            .with_context(|| format!("Chat request to {} failed", url))?
+            .into_json()
+            .context("Failed to parse chat response")?;
+
+        let content = match self.backend {
+            ChatBackend::LmStudio => &response["choices"][0]["message"]["content"],
+            ChatBackend::Ollama => &response["message"]["content"],
+        };
+        content
+            .as_str()
+            .map(|s| s.trim().to_string())
+            .context("No message in chat response")
+    }
+}
+
+/// A retrieved chunk, numbered for citation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Passage {
+    /// Number the model cites it by
+    pub number: usize,
+    pub source: PathBuf,
+    pub chunk_id: usize,
+    pub score: f32,
+    pub content: String,
+}
+
+/// Retrieved passages, and the model's answer if one was asked for
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Answer {
+    pub question: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub answer: Option<String>,
+    /// Numbers of the passages the answer cites
+    #[serde(default)]
+    pub cited: Vec<usize>,
+    pub passages: Vec<Passage>,
+}
+
+impl Answer {
+    /// Files the answer cites, in citation order
+    pub fn cited_files(&self) -> Vec<&Path> {
+        let mut files: Vec<&Path> = Vec::new();
+        for number in &self.cited {
+            if let Some(passage) = self.passages.iter().find(|p| p.number == *number) {
+                if !files.contains(&passage.source.as_path()) {
+                    files.push(&passage.source);
+                }
+            }
+        }
+        files
+    }
+}
+
+/// A source chunked and indexed for questions
+pub struct Corpus {
+    index: SearchIndex,
+    /// False when only pseudo-embeddings were available
+    semantic: bool,
+    pub chunks: usize,
+}
+
+impl Corpus {
+    /// Chunk, embed and index everything under `source`
+    pub fn build(
+        source: &Path,
+        extensions: Option<&[String]>,
+        chunk: ChunkConfig,
+        embedder: EmbedderConfig,
+    ) -> Result<Self> {
+        let chunker = MediaAwareChunker::new(chunk);
+        let chunks = if source.is_dir() {
+            let exts: Option<Vec<&str>> =
+                extensions.map(|exts| exts.iter().map(String::as_str).collect());
+            chunker.chunk_directory(source, exts.as_deref())?
+        } else {
+            chunker.chunk_file(source)?
+        };
+        if chunks.is_empty() {
+            anyhow::bail!("No text found to search in {}", source.display());
+        }
+
+        let embedder = Arc::new(AdaptiveEmbedder::new(embedder));
+        let semantic = embedder.current_backend() != "blake3-pseudo";
+        if !semantic {
+            warn!("No embedding model available; ranking passages by keywords only");
+        }
+
+        let texts: Vec<&str> = chunks.iter().map(|c| c.content.as_str()).collect();
+        let embeddings = if semantic {
+            embedder.embed_batch(&texts)?
+        } else {
+            vec![Vec::new(); texts.len()]
+        };
+        info!("Indexed {} chunks from {}", chunks.len(), source.display());
+
+        let count = chunks.len();
+        let index = SearchIndex::new(embedder, SearchConfig::default());
+        index.add_batch(
+            chunks
+                .into_iter()
+                .zip(embeddings)
+                .map(|(chunk, embedding)| IndexedDocument {
+                    id: chunk.id,
+                    source: chunk.source,
+                    chunk_id: chunk.index,
+                    content: chunk.content,
+                    embedding,
+                    metadata: chunk.metadata,
+                    indexed_at: chrono::Utc::now(),
+                })
+                .collect(),
+        );
+
+        Ok(Self {
+            index,
+            semantic,
+            chunks: count,
+        })
+    }
+
+    /// The `top_k` passages most relevant to `question`
+    pub fn retrieve(&self, question: &str, top_k: usize) -> Result<Vec<Passage>> {
+        let mode = if self.semantic {
+            SearchMode::Hybrid
+        } else {
+            SearchMode::Keyword
+        };
+        let options = SearchOptions {
+            limit: Some(top_k),
+            ..SearchOptions::mode(mode)
+        };
+
+        let results = self.index.search_with(question, &options)?;
+        Ok(results
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, result)| {
+                let doc = self.index.document(&result.id)?;
+                Some(Passage {
+                    number: i + 1,
+                    source: result.source,
+                    chunk_id: result.chunk_id,
+                    score: result.score,
+                    content: doc.content,
+                })
+            })
+            .collect())
+    }
+}
+
+/// Answer `question` from `passages` with the chat model
+pub fn answer(question: &str, passages: Vec<Passage>, chat: &ChatConfig) -> Result<Answer> {
+    if passages.is_empty() {
+        return Ok(Answer {
+            question: question.to_string(),
+            answer: Some("No passages in the corpus match this question.".to_string()),
+            cited: Vec::new(),
+            passages,
+        });
+    }
+
+    let reply = chat.chat(SYSTEM_PROMPT, &build_prompt(question, &passages))?;
+    let cited = citations(&reply, passages.len());
+    Ok(Answer {
+        question: question.to_string(),
+        answer: Some(reply),
+        cited,
+        passages,
+    })
+}
+
+/// Numbered excerpts followed by the question
+fn build_prompt(question: &str, passages: &[Passage]) -> String {
+    let mut prompt = String::from("Excerpts:\n\n");
+    for passage in passages {
+        let content: String = passage.content.chars().take(MAX_PASSAGE_CHARS).collect();
+        prompt.push_str(&format!(
+            "[{}] {} (chunk {})\n{}\n\n",
+            passage.number,
+            passage.source.display(),
+            passage.chunk_id,
+            content.trim()
+        ));
+    }
+    prompt.push_str(&format!("Question: {}\nAnswer:", question));
+    prompt
+}
+
+/// Passage numbers cited as `[n]` or `[n, m]`, first citation first
+fn citations(reply: &str, count: usize) -> Vec<usize> {
+    let pattern = Regex::new(r"\[(\d+(?:\s*,\s*\d+)*)\]").expect("valid citation pattern");
+    let mut cited = Vec::new();
+    for group in pattern.captures_iter(reply) {
+        for number in group[1].split(',').filter_map(|n| n.trim().parse().ok()) {
+            if (1..=count).contains(&number) && !cited.contains(&number) {
+                cited.push(number);
+            }
+        }
+    }
+    cited
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn passage(number: usize, source: &str, content: &str) -> Passage {
+        Passage {
+            number,
+            source: PathBuf::from(source),
+            chunk_id: 0,
+            score: 1.0,
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_citations() {
+        let reply = "The lease ends in May [2]. Rent was raised twice [1, 2] [7].";
+        assert_eq!(citations(reply, 3), vec![2, 1]);
+        assert!(citations("No excerpt says.", 3).is_empty());
+    }
+
+    #[test]
+    fn test_prompt_and_cited_files() {
+        let passages = vec![
+            passage(1, "/r/lease.txt", "Lease ends 31 May."),
+            passage(2, "/r/mail.eml", "Rent goes up in March."),
+        ];
+        let prompt = build_prompt("When does the lease end?", &passages);
+        assert!(prompt.contains("[1] /r/lease.txt (chunk 0)\nLease ends 31 May."));
+        assert!(prompt.ends_with("Question: When does the lease end?\nAnswer:"));
+
+        let answer = Answer {
+            question: String::new(),
+            answer: Some("31 May [1][1].".to_string()),
+            cited: citations("31 May [1][1].", 2),
+            passages,
+        };
+        assert_eq!(answer.cited_files(), vec![Path::new("/r/lease.txt")]);
+    }
+
+    #[test]
+    fn test_retrieve_without_embedding_server() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("lease.txt"),
+            "The lease on the workshop ends on the thirty-first of May next year.",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("recipe.txt"),
+            "Whisk the eggs with sugar until pale, then fold in the flour gently.",
+        )
+        .unwrap();
+
+        let corpus = Corpus::build(
+            dir.path(),
+            None,
+            ChunkConfig {
+                min_chunk_size: 1,
+                ..Default::default()
+            },
+            EmbedderConfig {
+                backend: super::super::embedder::EmbedderBackend::Blake3,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let passages = corpus.retrieve("when does the lease end", 1).unwrap();
+        assert_eq!(passages.len(), 1);
+        assert_eq!(passages[0].number, 1);
+        assert!(passages[0].source.ends_with("lease.txt"));
+        assert!(passages[0].content.contains("thirty-first of May"));
+    }
+}
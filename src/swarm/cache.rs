@@ -0,0 +1,486 @@
+//! Embedding Cache - Bounded in-memory and on-disk vector reuse
+//!
+//! Identical chunks turn up again and again, within a source and across
+//! runs on overlapping datasets. Vectors are keyed by blake3 of the model
+//! name and the chunk content:
+//! - In memory, least recently used vectors are dropped past a byte budget
+//! - On disk (optional), each vector is a small file under the cache
+//!   directory; the least recently used files are deleted past a byte budget,
+//!   so repeated swarm runs skip re-embedding without filling the drive
+//!
+//! Disk files are written to a temporary name and renamed into place, so
+//! runs sharing a cache directory never read a half-written vector.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::SystemTime;
+
+use anyhow::{Context, Result};
+use parking_lot::Mutex;
+use tracing::{debug, warn};
+
+use super::embedder::Embedder;
+
+/// Default RAM budget for cached vectors (256 MiB)
+pub const DEFAULT_CACHE_MEMORY: u64 = 256 * 1024 * 1024;
+
+/// Default disk budget for a persistent cache (4 GiB)
+pub const DEFAULT_CACHE_DISK: u64 = 4 * 1024 * 1024 * 1024;
+
+/// Extension of cached vector files (raw little-endian f32)
+const VECTOR_EXT: &str = "f32";
+
+/// Bookkeeping per in-memory entry on top of the vector itself
+const ENTRY_OVERHEAD: u64 = 64;
+
+type Key = [u8; 32];
+
+struct MemoryEntry {
+    vector: Vec<f32>,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct MemoryCache {
+    entries: HashMap<Key, MemoryEntry>,
+    bytes: u64,
+    /// Logical clock for recency
+    tick: u64,
+}
+
+struct DiskCache {
+    dir: PathBuf,
+    max_bytes: u64,
+    bytes: AtomicU64,
+    /// One eviction pass at a time
+    evicting: Mutex<()>,
+}
+
+/// Cache for embeddings, keyed by blake3 of model name and input.
+/// Avoids re-computing embeddings for identical chunks.
+pub struct EmbeddingCache {
+    model: String,
+    memory: Mutex<MemoryCache>,
+    max_memory: u64,
+    disk: Option<DiskCache>,
+    hits: AtomicUsize,
+    disk_hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl EmbeddingCache {
+    /// In-memory cache with the default budget
+    pub fn new() -> Self {
+        Self {
+            model: String::new(),
+            memory: Mutex::new(MemoryCache::default()),
+            max_memory: DEFAULT_CACHE_MEMORY,
+            disk: None,
+            hits: AtomicUsize::new(0),
+            disk_hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    /// Persistent cache in `dir` for vectors from `model`
+    pub fn persistent(dir: impl Into<PathBuf>, model: &str) -> Result<Self> {
+        Self::new()
+            .with_model(model)
+            .with_disk(dir, DEFAULT_CACHE_DISK)
+    }
+
+    /// Name of the model the vectors come from; part of every key, so
+    /// switching models never returns stale vectors
+    pub fn with_model(mut self, model: &str) -> Self {
+        self.model = model.to_string();
+        self
+    }
+
+    /// Bytes of vectors kept in memory
+    pub fn with_memory_limit(mut self, bytes: u64) -> Self {
+        self.max_memory = bytes;
+        self
+    }
+
+    /// Also keep vectors in `dir`, using at most `max_bytes` of disk
+    pub fn with_disk(mut self, dir: impl Into<PathBuf>, max_bytes: u64) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create cache directory {}", dir.display()))?;
+        let bytes = vector_files(&dir).iter().map(|(_, _, size)| size).sum();
+        let disk = DiskCache {
+            dir,
+            max_bytes,
+            bytes: AtomicU64::new(bytes),
+            evicting: Mutex::new(()),
+        };
+        if bytes > max_bytes {
+            disk.evict();
+        }
+        self.disk = Some(disk);
+        Ok(self)
+    }
+
+    fn key(&self, data: &[u8]) -> Key {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(self.model.as_bytes());
+        hasher.update(&[0]);
+        hasher.update(data);
+        *hasher.finalize().as_bytes()
+    }
+
+    /// Cached vector for `data`, from memory or else disk
+    pub fn get(&self, data: &[u8]) -> Option<Vec<f32>> {
+        let key = self.key(data);
+        if let Some(vector) = self.memory_get(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Some(vector);
+        }
+        if let Some(vector) = self.disk.as_ref().and_then(|disk| disk.get(&key)) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            self.disk_hits.fetch_add(1, Ordering::Relaxed);
+            self.memory_insert(key, vector.clone());
+            return Some(vector);
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        None
+    }
+
+    /// Cache the vector computed for `data`
+    pub fn insert(&self, data: &[u8], vector: &[f32]) {
+        let key = self.key(data);
+        if let Some(ref disk) = self.disk {
+            if let Err(e) = disk.insert(&key, vector) {
+                warn!("Failed to write cached embedding: {}", e);
+            }
+        }
+        self.memory_insert(key, vector.to_vec());
+    }
+
+    /// Get cached embedding or compute and cache it
+    pub fn get_or_compute(&self, text: &str, embedder: &dyn Embedder) -> Result<Vec<f32>> {
+        if let Some(vector) = self.get(text.as_bytes()) {
+            return Ok(vector);
+        }
+        let embedding = embedder.embed(text)?;
+        self.insert(text.as_bytes(), &embedding);
+        Ok(embedding)
+    }
+
+    /// Batch get-or-compute
+    pub fn get_or_compute_batch(
+        &self,
+        texts: &[&str],
+        embedder: &dyn Embedder,
+    ) -> Result<Vec<Vec<f32>>> {
+        let mut results: Vec<Option<Vec<f32>>> =
+            texts.iter().map(|text| self.get(text.as_bytes())).collect();
+        let (uncached_indices, uncached_texts): (Vec<usize>, Vec<&str>) = texts
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| results[*i].is_none())
+            .map(|(i, text)| (i, *text))
+            .unzip();
+
+        // Batch-embed uncached texts
+        if !uncached_texts.is_empty() {
+            let new_embeddings = embedder.embed_batch(&uncached_texts)?;
+            for (idx, embedding) in uncached_indices.into_iter().zip(new_embeddings) {
+                self.insert(texts[idx].as_bytes(), &embedding);
+                results[idx] = Some(embedding);
+            }
+        }
+
+        results
+            .into_iter()
+            .map(|r| r.context("Embedder returned too few vectors"))
+            .collect()
+    }
+
+    /// Cache statistics: (entries in memory, hits, misses)
+    pub fn stats(&self) -> (usize, usize, usize) {
+        let size = self.memory.lock().entries.len();
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        (size, hits, misses)
+    }
+
+    /// Hits served from disk rather than memory
+    pub fn disk_hits(&self) -> usize {
+        self.disk_hits.load(Ordering::Relaxed)
+    }
+
+    /// Bytes of vectors in memory
+    pub fn memory_bytes(&self) -> u64 {
+        self.memory.lock().bytes
+    }
+
+    /// Bytes of vectors on disk (0 without a disk cache)
+    pub fn disk_bytes(&self) -> u64 {
+        self.disk
+            .as_ref()
+            .map_or(0, |disk| disk.bytes.load(Ordering::Relaxed))
+    }
+
+    /// Clear the in-memory cache and counters; the disk cache is kept
+    pub fn clear(&self) {
+        *self.memory.lock() = MemoryCache::default();
+        self.hits.store(0, Ordering::Relaxed);
+        self.disk_hits.store(0, Ordering::Relaxed);
+        self.misses.store(0, Ordering::Relaxed);
+    }
+
+    fn memory_get(&self, key: &Key) -> Option<Vec<f32>> {
+        let mut memory = self.memory.lock();
+        memory.tick += 1;
+        let tick = memory.tick;
+        let entry = memory.entries.get_mut(key)?;
+        entry.last_used = tick;
+        Some(entry.vector.clone())
+    }
+
+    fn memory_insert(&self, key: Key, vector: Vec<f32>) {
+        let size = entry_bytes(&vector);
+        if size > self.max_memory {
+            return;
+        }
+        let mut memory = self.memory.lock();
+        memory.tick += 1;
+        let last_used = memory.tick;
+        if let Some(old) = memory
+            .entries
+            .insert(key, MemoryEntry { vector, last_used })
+        {
+            memory.bytes -= entry_bytes(&old.vector);
+        }
+        memory.bytes += size;
+
+        if memory.bytes > self.max_memory {
+            // Drop the least recently used down to 90% so eviction is not
+            // repeated on every insert
+            let target = self.max_memory / 10 * 9;
+            let mut by_age: Vec<(u64, Key)> = memory
+                .entries
+                .iter()
+                .map(|(key, entry)| (entry.last_used, *key))
+                .collect();
+            by_age.sort_unstable();
+            for (_, key) in by_age {
+                if memory.bytes <= target {
+                    break;
+                }
+                if let Some(old) = memory.entries.remove(&key) {
+                    memory.bytes -= entry_bytes(&old.vector);
+                }
+            }
+        }
+    }
+}
+
+impl Default for EmbeddingCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DiskCache {
+    fn path(&self, key: &Key) -> PathBuf {
+        let hex = hex::encode(key);
+        self.dir
+            .join(&hex[..2])
+            .join(format!("{}.{}", hex, VECTOR_EXT))
+    }
+
+    fn get(&self, key: &Key) -> Option<Vec<f32>> {
+        let path = self.path(key);
+        let bytes = fs::read(&path).ok()?;
+        if bytes.is_empty() || bytes.len() % 4 != 0 {
+            return None;
+        }
+        // Reads count as use for eviction
+        if let Ok(file) = fs::File::options().write(true).open(&path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+        Some(
+            bytes
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect(),
+        )
+    }
+
+    fn insert(&self, key: &Key, vector: &[f32]) -> Result<()> {
+        let path = self.path(key);
+        if path.exists() {
+            return Ok(());
+        }
+        let bytes: Vec<u8> = vector.iter().flat_map(|v| v.to_le_bytes()).collect();
+        if bytes.len() as u64 > self.max_bytes {
+            return Ok(());
+        }
+        let dir = path.parent().unwrap_or(&self.dir);
+        fs::create_dir_all(dir)?;
+        let tmp = dir.join(format!(
+            ".{}.{}.tmp",
+            hex::encode(&key[..8]),
+            std::process::id()
+        ));
+        fs::write(&tmp, &bytes)?;
+        fs::rename(&tmp, &path)?;
+
+        let total =
+            self.bytes.fetch_add(bytes.len() as u64, Ordering::Relaxed) + bytes.len() as u64;
+        if total > self.max_bytes {
+            self.evict();
+        }
+        Ok(())
+    }
+
+    /// Delete the least recently used files down to 90% of the budget
+    fn evict(&self) {
+        let Some(_guard) = self.evicting.try_lock() else {
+            return;
+        };
+        let mut files = vector_files(&self.dir);
+        let mut total: u64 = files.iter().map(|(_, _, size)| size).sum();
+        let target = self.max_bytes / 10 * 9;
+        files.sort_by_key(|(_, modified, _)| *modified);
+
+        let mut removed = 0usize;
+        for (path, _, size) in files {
+            if total <= target {
+                break;
+            }
+            if fs::remove_file(&path).is_ok() {
+                total -= size;
+                removed += 1;
+            }
+        }
+        debug!(
+            "Evicted {} cached embeddings from {}",
+            removed,
+            self.dir.display()
+        );
+        self.bytes.store(total, Ordering::Relaxed);
+    }
+}
+
+fn entry_bytes(vector: &[f32]) -> u64 {
+    std::mem::size_of_val(vector) as u64 + ENTRY_OVERHEAD
+}
+
+/// Cached vector files under `dir`: (path, last used, size)
+fn vector_files(dir: &Path) -> Vec<(PathBuf, SystemTime, u64)> {
+    let Ok(shards) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    shards
+        .flatten()
+        .filter_map(|shard| fs::read_dir(shard.path()).ok())
+        .flat_map(|files| files.flatten())
+        .filter(|file| file.path().extension().is_some_and(|e| e == VECTOR_EXT))
+        .filter_map(|file| {
+            let meta = file.metadata().ok()?;
+            let modified = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            Some((file.path(), modified, meta.len()))
+        })
+        .collect()
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::super::embedder::Blake3Embedder;
+    use super::*;
+
+    #[test]
+    fn test_memory_cache_hits() {
+        let cache = EmbeddingCache::new();
+        let embedder = Blake3Embedder::new(8);
+
+        let a = cache.get_or_compute("alpha", &embedder).unwrap();
+        let b = cache.get_or_compute("alpha", &embedder).unwrap();
+        assert_eq!(a, b);
+
+        let batch = cache
+            .get_or_compute_batch(&["alpha", "beta"], &embedder)
+            .unwrap();
+        assert_eq!(batch[0], a);
+        assert_eq!(cache.stats(), (2, 2, 2));
+    }
+
+    #[test]
+    fn test_memory_limit_evicts_least_recently_used() {
+        // Room for ten 4-dim vectors
+        let entry = 16 + ENTRY_OVERHEAD;
+        let cache = EmbeddingCache::new().with_memory_limit(10 * entry);
+        for i in 0..10u8 {
+            cache.insert(&[i], &[i as f32; 4]);
+        }
+        assert!(cache.get(&[0]).is_some());
+
+        // Over budget: the least recently used go until 90% is left
+        cache.insert(b"new", &[1.0; 4]);
+        assert_eq!(cache.memory_bytes(), 9 * entry);
+        assert!(cache.get(&[0]).is_some());
+        assert!(cache.get(&[1]).is_none());
+        assert!(cache.get(&[2]).is_none());
+        assert!(cache.get(b"new").is_some());
+    }
+
+    #[test]
+    fn test_disk_cache_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let cache = EmbeddingCache::persistent(dir.path(), "model-a").unwrap();
+            cache.insert(b"chunk", &[0.5, -0.25]);
+            assert_eq!(cache.disk_bytes(), 8);
+        }
+
+        let cache = EmbeddingCache::persistent(dir.path(), "model-a").unwrap();
+        assert_eq!(cache.disk_bytes(), 8);
+        assert_eq!(cache.get(b"chunk"), Some(vec![0.5, -0.25]));
+        assert_eq!(cache.disk_hits(), 1);
+
+        // Another model never sees these vectors
+        let other = EmbeddingCache::persistent(dir.path(), "model-b").unwrap();
+        assert_eq!(other.get(b"chunk"), None);
+    }
+
+    #[test]
+    fn test_disk_limit_evicts_oldest() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = EmbeddingCache::new()
+            .with_disk(dir.path(), 40)
+            .unwrap()
+            .with_memory_limit(0);
+        for i in 0..5u8 {
+            cache.insert(&[i], &[i as f32; 2]);
+        }
+        assert_eq!(cache.disk_bytes(), 40);
+        // Age the files in insertion order
+        let disk = cache.disk.as_ref().unwrap();
+        for i in 0..5u8 {
+            let age = std::time::Duration::from_secs(100 - i as u64);
+            fs::File::options()
+                .write(true)
+                .open(disk.path(&cache.key(&[i])))
+                .unwrap()
+                .set_modified(SystemTime::now() - age)
+                .unwrap();
+        }
+
+        // The sixth vector goes over budget: the oldest go until 90% is left
+        cache.insert(b"last", &[9.0; 2]);
+        assert_eq!(cache.disk_bytes(), 32);
+        assert_eq!(cache.get(&[0]), None);
+        assert_eq!(cache.get(&[1]), None);
+        assert_eq!(cache.get(&[2]), Some(vec![2.0; 2]));
+        assert_eq!(cache.get(b"last"), Some(vec![9.0; 2]));
+    }
+}
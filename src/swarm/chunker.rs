@@ -1,10 +1,12 @@
-//! Media-Aware Chunker - Intelligent document splitting for text/code/image/PDF
+//! Media-Aware Chunker - Intelligent document splitting for text/code/image/PDF/Office
 //!
 //! Implements adaptive chunking strategies based on content type:
-//! - Text: Sentence/paragraph boundaries with overlap
-//! - Code: Function/class/block boundaries with syntax awareness
+//! - Text: Sentence/paragraph boundaries with overlap, per detected language
+//! - Code: Function/class/block boundaries, from tree-sitter where a grammar
+//!   is compiled in
 //! - Image: Metadata extraction (no chunking, single "chunk")
 //! - PDF: Page-based extraction with text flow preservation
+//! - Document: DOCX/XLSX/PPTX and ODF text, unzipped and stripped of XML
 
 use std::collections::HashMap;
 use std::fs;
@@ -15,6 +17,9 @@ use anyhow::{Context, Result};
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 
+use super::language::{detect_language, Language};
+use super::office::{extract_office_text, OfficeFormat};
+
 // ============================================================================
 // Core Types
 // ============================================================================
@@ -27,6 +32,7 @@ pub enum MediaType {
     Code,
     Image,
     Pdf,
+    Document,
     Binary,
     Unknown,
 }
@@ -56,6 +62,11 @@ impl MediaType {
             // PDF
             "pdf" => MediaType::Pdf,
 
+            // Office documents
+            "docx" | "docm" | "xlsx" | "xlsm" | "pptx" | "pptm" | "odt" | "ods" | "odp" => {
+                MediaType::Document
+            }
+
             // Binary
             "exe" | "dll" | "so" | "dylib" | "bin" | "o" | "a" | "lib" | "wasm" | "zip" | "tar"
             | "gz" | "bz2" | "xz" | "7z" | "rar" => MediaType::Binary,
@@ -80,6 +91,7 @@ impl MediaType {
             MediaType::Code => "code",
             MediaType::Image => "image",
             MediaType::Pdf => "pdf",
+            MediaType::Document => "document",
             MediaType::Binary => "binary",
             MediaType::Unknown => "unknown",
         }
@@ -233,35 +245,25 @@ pub trait ChunkStrategy: Send + Sync {
     fn supported_types(&self) -> &[MediaType];
 }
 
-/// Text chunker - sentence/paragraph aware
+/// Text chunker - sentence/paragraph aware, using the sentence rules of the
+/// document's language
 pub struct TextChunker;
 
 impl TextChunker {
-    /// Find sentence boundaries
-    fn find_sentence_end(text: &str, start: usize, target: usize) -> usize {
-        let search_range = &text[start..std::cmp::min(text.len(), target + 200)];
-
-        // Look for sentence endings near target
-        let sentence_ends = [
-            ". ", ".\n", "! ", "!\n", "? ", "?\n", ".\r\n", "!\r\n", "?\r\n",
-        ];
+    /// Find the sentence end closest to `target`
+    fn find_sentence_end(text: &str, start: usize, target: usize, language: Language) -> usize {
+        let range_end = floor_char_boundary(text, std::cmp::min(text.len(), target + 200));
+        let search_range = &text[start..range_end];
+        let target = target - start;
 
-        let mut best_end = target.saturating_sub(start);
+        let mut best_end = target;
         let mut best_distance = usize::MAX;
 
-        for end in &sentence_ends {
-            if let Some(pos) = search_range.find(end) {
-                let actual_pos = pos + end.len() - 1; // Include the punctuation
-                let distance = if actual_pos > target.saturating_sub(start) {
-                    actual_pos - (target - start)
-                } else {
-                    (target - start) - actual_pos
-                };
-
-                if distance < best_distance {
-                    best_distance = distance;
-                    best_end = actual_pos;
-                }
+        for end in language.sentence_rules().sentence_ends(search_range) {
+            let distance = end.abs_diff(target);
+            if distance < best_distance {
+                best_distance = distance;
+                best_end = end;
             }
         }
 
@@ -269,8 +271,9 @@ impl TextChunker {
     }
 
     /// Find paragraph boundary
-    fn find_paragraph_end(text: &str, start: usize, target: usize) -> usize {
-        let search_range = &text[start..std::cmp::min(text.len(), target + 500)];
+    fn find_paragraph_end(text: &str, start: usize, target: usize, language: Language) -> usize {
+        let range_end = floor_char_boundary(text, std::cmp::min(text.len(), target + 500));
+        let search_range = &text[start..range_end];
 
         // Look for paragraph breaks
         if let Some(pos) = search_range.find("\n\n") {
@@ -285,8 +288,24 @@ impl TextChunker {
         }
 
         // Fallback to sentence boundary
-        Self::find_sentence_end(text, start, target)
+        Self::find_sentence_end(text, start, target, language)
+    }
+}
+
+/// Largest char boundary in `s` at or before `index`
+pub(super) fn floor_char_boundary(s: &str, mut index: usize) -> usize {
+    while !s.is_char_boundary(index) {
+        index -= 1;
     }
+    index
+}
+
+/// Smallest char boundary in `s` at or after `index`
+pub(super) fn ceil_char_boundary(s: &str, mut index: usize) -> usize {
+    while !s.is_char_boundary(index) {
+        index += 1;
+    }
+    index
 }
 
 impl ChunkStrategy for TextChunker {
@@ -295,6 +314,7 @@ impl ChunkStrategy for TextChunker {
             return Ok(vec![]);
         }
 
+        let language = detect_language(content);
         let mut chunks = Vec::new();
         let mut start = 0;
         let content_len = content.len();
@@ -304,14 +324,18 @@ impl ChunkStrategy for TextChunker {
 
             // Find natural boundary
             let end = if config.preserve_sentences && target_end < content_len {
-                Self::find_paragraph_end(content, start, target_end)
+                Self::find_paragraph_end(content, start, target_end, language)
             } else {
                 target_end
             };
 
-            // Ensure we don't exceed max
+            // Ensure we don't exceed max, or cut a character in half
             let end = std::cmp::min(end, start + config.max_chunk_size);
             let end = std::cmp::min(end, content_len);
+            let end = match floor_char_boundary(content, end) {
+                end if end > start => end,
+                _ => ceil_char_boundary(content, start + 1),
+            };
 
             let chunk_content = content[start..end].to_string();
 
@@ -320,15 +344,18 @@ impl ChunkStrategy for TextChunker {
                 || chunks.is_empty()
                 || end == content_len
             {
-                chunks.push(Chunk::new(
-                    path.to_path_buf(),
-                    chunks.len(),
-                    0, // Will be updated after
-                    chunk_content,
-                    start,
-                    end,
-                    MediaType::Text,
-                ));
+                chunks.push(
+                    Chunk::new(
+                        path.to_path_buf(),
+                        chunks.len(),
+                        0, // Will be updated after
+                        chunk_content,
+                        start,
+                        end,
+                        MediaType::Text,
+                    )
+                    .with_metadata("language", language.code()),
+                );
             }
 
             // Move start with overlap, but ALWAYS advance by at least 1
@@ -337,7 +364,7 @@ impl ChunkStrategy for TextChunker {
             } else {
                 let next = end.saturating_sub(config.overlap);
                 // Guard: never go backwards or stall
-                std::cmp::max(next, start + 1)
+                ceil_char_boundary(content, std::cmp::max(next, start + 1))
             };
         }
 
@@ -384,6 +411,7 @@ impl ChunkStrategy for MarkdownChunker {
             return Ok(vec![]);
         }
 
+        let language = detect_language(content);
         let mut chunks = Vec::new();
         let mut current_chunk = String::new();
         let mut current_start = 0;
@@ -412,7 +440,8 @@ impl ChunkStrategy for MarkdownChunker {
                             current_start,
                             byte_end,
                             MediaType::Markdown,
-                        );
+                        )
+                        .with_metadata("language", language.code());
                         if let Some(ref h) = current_heading {
                             chunk = chunk.with_metadata("heading", h.clone());
                         }
@@ -440,14 +469,18 @@ impl ChunkStrategy for MarkdownChunker {
                     current_start,
                     byte_end,
                     MediaType::Markdown,
-                );
+                )
+                .with_metadata("language", language.code());
                 if let Some(ref h) = current_heading {
                     chunk = chunk.with_metadata("heading", h.clone());
                 }
                 chunks.push(chunk);
 
                 // Start new chunk with overlap
-                let overlap_start = current_chunk.len().saturating_sub(config.overlap);
+                let overlap_start = ceil_char_boundary(
+                    &current_chunk,
+                    current_chunk.len().saturating_sub(config.overlap),
+                );
                 current_chunk = current_chunk[overlap_start..].to_string();
                 current_start = byte_end - current_chunk.len();
             }
@@ -464,7 +497,8 @@ impl ChunkStrategy for MarkdownChunker {
                 current_start,
                 byte_end,
                 MediaType::Markdown,
-            );
+            )
+            .with_metadata("language", language.code());
             if let Some(ref h) = current_heading {
                 chunk = chunk.with_metadata("heading", h.clone());
             }
@@ -486,6 +520,9 @@ impl ChunkStrategy for MarkdownChunker {
 }
 
 /// Code chunker - function/class/block aware
+///
+/// Parses with tree-sitter when the language's `syntax-*` feature is
+/// enabled, and falls back to brace/blank-line heuristics otherwise.
 pub struct CodeChunker;
 
 impl CodeChunker {
@@ -562,6 +599,12 @@ impl ChunkStrategy for CodeChunker {
         }
 
         let language = Self::detect_language(path);
+
+        #[cfg(feature = "tree-sitter")]
+        if let Some(chunks) = super::syntax::chunk_definitions(path, content, config, language)? {
+            return Ok(chunks);
+        }
+
         let mut chunks = Vec::new();
         let mut start = 0;
         let content_len = content.len();
@@ -665,6 +708,10 @@ impl ChunkStrategy for ImageChunker {
 }
 
 /// PDF chunker - page-based with text extraction via pdf-extract
+///
+/// Each page is chunked on its own, so chunks carry a `page` number and
+/// never straddle a page break. Byte offsets are into the extracted text
+/// (pages joined in order), not the PDF file.
 pub struct PdfChunker;
 
 impl PdfChunker {
@@ -676,83 +723,107 @@ impl PdfChunker {
         pdf_extract::extract_text_from_mem(&bytes)
             .with_context(|| format!("Failed to extract text from PDF: {}", path.display()))
     }
+
+    /// Extract the text of each page, in page order
+    fn extract_pages(path: &Path) -> Result<Vec<String>> {
+        let bytes =
+            fs::read(path).with_context(|| format!("Failed to read PDF: {}", path.display()))?;
+
+        pdf_extract::extract_text_from_mem_by_pages(&bytes)
+            .with_context(|| format!("Failed to extract text from PDF: {}", path.display()))
+    }
+
+    /// Single metadata chunk for a PDF without usable text
+    fn placeholder(path: &Path, note: &str, key: &str, value: String) -> Vec<Chunk> {
+        let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        let description = format!(
+            "[PDF: {} ({} bytes) - {}]",
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("unknown"),
+            size,
+            note
+        );
+
+        let chunk = Chunk::new(
+            path.to_path_buf(),
+            0,
+            1,
+            description,
+            0,
+            size as usize,
+            MediaType::Pdf,
+        )
+        .with_metadata("size_bytes", size.to_string())
+        .with_metadata(key, value);
+
+        vec![chunk]
+    }
 }
 
 impl ChunkStrategy for PdfChunker {
     fn chunk(&self, path: &Path, content: &str, config: &ChunkConfig) -> Result<Vec<Chunk>> {
+        let text_chunker = TextChunker;
+
         // If content is provided (pre-extracted text), use text chunker
-        let text = if !content.is_empty() {
-            content.to_string()
-        } else {
-            // Extract text from the PDF file
-            match Self::extract_text(path) {
-                Ok(extracted) if !extracted.trim().is_empty() => extracted,
-                Ok(_) => {
-                    // PDF exists but has no extractable text (scanned/image-only)
-                    let metadata = fs::metadata(path).ok();
-                    let size = metadata.as_ref().map(|m| m.len()).unwrap_or(0);
-
-                    let description = format!(
-                        "[PDF: {} ({} bytes) - no extractable text (scanned/image-only)]",
-                        path.file_name()
-                            .and_then(|n| n.to_str())
-                            .unwrap_or("unknown"),
-                        size
-                    );
-
-                    let chunk = Chunk::new(
-                        path.to_path_buf(),
-                        0,
-                        1,
-                        description,
-                        0,
-                        size as usize,
-                        MediaType::Pdf,
-                    )
-                    .with_metadata("size_bytes", size.to_string())
-                    .with_metadata("scanned", "true");
+        if !content.is_empty() {
+            let mut chunks = text_chunker.chunk(path, content, config)?;
+            for chunk in &mut chunks {
+                chunk.media_type = MediaType::Pdf;
+            }
+            return Ok(chunks);
+        }
 
-                    return Ok(vec![chunk]);
-                }
-                Err(e) => {
-                    // Extraction failed — create a fallback metadata chunk
-                    tracing::warn!("PDF extraction failed for {}: {}", path.display(), e);
-                    let metadata = fs::metadata(path).ok();
-                    let size = metadata.as_ref().map(|m| m.len()).unwrap_or(0);
-
-                    let description = format!(
-                        "[PDF: {} ({} bytes) - extraction failed: {}]",
-                        path.file_name()
-                            .and_then(|n| n.to_str())
-                            .unwrap_or("unknown"),
-                        size,
-                        e
-                    );
-
-                    let chunk = Chunk::new(
+        let pages = match Self::extract_pages(path) {
+            Ok(pages) => pages,
+            Err(e) => {
+                // Extraction failed — create a fallback metadata chunk
+                tracing::warn!("PDF extraction failed for {}: {}", path.display(), e);
+                return Ok(Self::placeholder(
+                    path,
+                    &format!("extraction failed: {}", e),
+                    "extraction_error",
+                    e.to_string(),
+                ));
+            }
+        };
+        if pages.iter().all(|page| page.trim().is_empty()) {
+            // PDF exists but has no extractable text (scanned/image-only)
+            return Ok(Self::placeholder(
+                path,
+                "no extractable text (scanned/image-only)",
+                "scanned",
+                "true".to_string(),
+            ));
+        }
+
+        // Chunk page by page, renumbering across the whole document
+        let mut chunks = Vec::new();
+        let mut offset = 0;
+        for (page_index, page) in pages.iter().enumerate() {
+            if !page.trim().is_empty() {
+                for chunk in text_chunker.chunk(path, page, config)? {
+                    let mut renumbered = Chunk::new(
                         path.to_path_buf(),
+                        chunks.len(),
                         0,
-                        1,
-                        description,
-                        0,
-                        size as usize,
+                        chunk.content,
+                        offset + chunk.byte_start,
+                        offset + chunk.byte_end,
                         MediaType::Pdf,
                     )
-                    .with_metadata("size_bytes", size.to_string())
-                    .with_metadata("extraction_error", e.to_string());
-
-                    return Ok(vec![chunk]);
+                    .with_metadata("page", (page_index + 1).to_string())
+                    .with_metadata("pages", pages.len().to_string());
+                    renumbered.metadata.extend(chunk.metadata);
+                    chunks.push(renumbered);
                 }
             }
-        };
-
-        // Chunk the extracted text using the text chunker
-        let text_chunker = TextChunker;
-        let mut chunks = text_chunker.chunk(path, &text, config)?;
+            offset += page.len();
+        }
 
-        // Update media type to PDF
+        let total = chunks.len();
         for chunk in &mut chunks {
-            chunk.media_type = MediaType::Pdf;
+            chunk.total = total;
         }
 
         Ok(chunks)
@@ -763,6 +834,74 @@ impl ChunkStrategy for PdfChunker {
     }
 }
 
+/// Office document chunker - text of DOCX/XLSX/PPTX and ODF files
+///
+/// The extracted text is chunked like plain text; byte offsets are into
+/// the extracted text, not the zip archive.
+pub struct DocumentChunker;
+
+impl ChunkStrategy for DocumentChunker {
+    fn chunk(&self, path: &Path, content: &str, config: &ChunkConfig) -> Result<Vec<Chunk>> {
+        let extracted;
+        let content = if content.is_empty() {
+            extracted = match extract_document_text(path) {
+                Some(result) => result?,
+                None => anyhow::bail!("Not an Office document: {}", path.display()),
+            };
+            extracted.as_str()
+        } else {
+            content
+        };
+
+        let mut chunks = TextChunker.chunk(path, content, config)?;
+        let format = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        for chunk in &mut chunks {
+            chunk.media_type = MediaType::Document;
+            chunk.metadata.insert("format".to_string(), format.clone());
+        }
+        Ok(chunks)
+    }
+
+    fn supported_types(&self) -> &[MediaType] {
+        &[MediaType::Document]
+    }
+}
+
+/// Largest PDF/Office document (in bytes) that text is extracted from
+pub const MAX_EXTRACT_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Extract plain text from a document for search.
+///
+/// PDFs go through pdf-extract; DOCX, XLSX, PPTX and ODF files are unzipped
+/// and their XML parts stripped to text. Returns `None` for files that are
+/// read as plain text as-is.
+pub fn extract_document_text(path: &Path) -> Option<Result<String>> {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let format = match ext.as_str() {
+        "pdf" => None,
+        ext => Some(OfficeFormat::from_extension(ext)?),
+    };
+
+    let result = fs::metadata(path)
+        .with_context(|| format!("Failed to stat {}", path.display()))
+        .and_then(|meta| {
+            if meta.len() > MAX_EXTRACT_BYTES {
+                anyhow::bail!("{} is too large to extract text from", path.display());
+            }
+            match format {
+                None => PdfChunker::extract_text(path),
+                Some(format) => extract_office_text(path, format),
+            }
+        });
+    Some(result)
+}
+
 // ============================================================================
 // Unified Chunker
 // ============================================================================
@@ -783,6 +922,7 @@ impl MediaAwareChunker {
         strategies.insert(MediaType::Code, Arc::new(CodeChunker));
         strategies.insert(MediaType::Image, Arc::new(ImageChunker));
         strategies.insert(MediaType::Pdf, Arc::new(PdfChunker));
+        strategies.insert(MediaType::Document, Arc::new(DocumentChunker));
         strategies.insert(MediaType::Unknown, Arc::new(TextChunker)); // Fallback
 
         Self { strategies, config }
@@ -802,8 +942,12 @@ impl MediaAwareChunker {
             return Ok(vec![]);
         }
 
-        // Read content (for non-image files)
-        let content = if media_type == MediaType::Image {
+        // Read content (images, PDFs and Office documents are read by their
+        // strategy)
+        let content = if matches!(
+            media_type,
+            MediaType::Image | MediaType::Pdf | MediaType::Document
+        ) {
             String::new()
         } else {
             fs::read_to_string(path)
@@ -908,6 +1052,7 @@ mod tests {
         assert_eq!(MediaType::from_extension("txt"), MediaType::Text);
         assert_eq!(MediaType::from_extension("png"), MediaType::Image);
         assert_eq!(MediaType::from_extension("pdf"), MediaType::Pdf);
+        assert_eq!(MediaType::from_extension("XLSX"), MediaType::Document);
         assert_eq!(MediaType::from_extension("exe"), MediaType::Binary);
         assert_eq!(MediaType::from_extension("xyz"), MediaType::Unknown);
     }
@@ -941,6 +1086,27 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_text_chunker_cjk() {
+        let chunker = TextChunker;
+        let config = ChunkConfig {
+            chunk_size: 40,
+            overlap: 0,
+            min_chunk_size: 1,
+            ..Default::default()
+        };
+
+        // No spaces after the full stops, and 3-byte characters throughout
+        let content = "报告在抽屉里。已经签字了。律师明天会来取。";
+        let chunks = chunker
+            .chunk(Path::new("report.txt"), content, &config)
+            .unwrap();
+
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|c| c.content.ends_with('。')));
+        assert_eq!(chunks[0].metadata.get("language"), Some(&"zh".to_string()));
+    }
+
     #[test]
     fn test_markdown_chunker() {
         let chunker = MarkdownChunker;
@@ -1070,4 +1236,93 @@ fn other() {
         assert_eq!(chunks[0].media_type, MediaType::Pdf);
         assert!(chunks[0].metadata.contains_key("extraction_error"));
     }
+
+    #[test]
+    fn test_pdf_chunker_pages() {
+        use lopdf::content::{Content, Operation};
+        use lopdf::{dictionary, Document, Object, Stream};
+
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let font_id = doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Courier",
+        });
+        let mut kids = Vec::new();
+        for text in ["Invoice for the storage unit", "Signed by the tenant"] {
+            let content = Content {
+                operations: vec![
+                    Operation::new("BT", vec![]),
+                    Operation::new("Tf", vec!["F1".into(), 12.into()]),
+                    Operation::new("Td", vec![72.into(), 720.into()]),
+                    Operation::new("Tj", vec![Object::string_literal(text)]),
+                    Operation::new("ET", vec![]),
+                ],
+            };
+            let content_id = doc.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+            kids.push(
+                doc.add_object(dictionary! {
+                    "Type" => "Page",
+                    "Parent" => pages_id,
+                    "Contents" => content_id,
+                })
+                .into(),
+            );
+        }
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => kids,
+                "Count" => 2,
+                "Resources" => dictionary! { "Font" => dictionary! { "F1" => font_id } },
+                "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        doc.trailer.set("Root", catalog_id);
+
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("invoice.pdf");
+        doc.save(&path).unwrap();
+
+        let config = ChunkConfig {
+            min_chunk_size: 1,
+            ..Default::default()
+        };
+        let chunks = PdfChunker.chunk(&path, "", &config).unwrap();
+
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks[0].content.contains("storage unit"));
+        assert!(chunks[1].content.contains("tenant"));
+        assert_eq!(chunks[1].metadata.get("page"), Some(&"2".to_string()));
+        assert_eq!(chunks[1].metadata.get("pages"), Some(&"2".to_string()));
+        assert_eq!((chunks[1].index, chunks[1].total), (1, 2));
+        assert_eq!(chunks[1].byte_start, chunks[0].byte_end);
+    }
+
+    #[test]
+    fn test_extract_docx_text() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("contract.docx");
+        let mut zip = zip::ZipWriter::new(fs::File::create(&path).unwrap());
+        zip.start_file(
+            "word/document.xml",
+            zip::write::SimpleFileOptions::default(),
+        )
+        .unwrap();
+        zip.write_all(
+            br#"<w:document><w:body><w:p><w:r><w:t>Tenancy &amp; lease</w:t></w:r></w:p><w:p><w:r><w:t xml:space="preserve">Rent:</w:t><w:tab/><w:t>500</w:t></w:r></w:p></w:body></w:document>"#,
+        )
+        .unwrap();
+        zip.finish().unwrap();
+
+        let text = extract_document_text(&path).unwrap().unwrap();
+        assert_eq!(text, "Tenancy & lease\nRent:\t500\n");
+        assert!(extract_document_text(Path::new("notes.txt")).is_none());
+    }
 }
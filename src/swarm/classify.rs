@@ -0,0 +1,584 @@
+//! Swarm Classifier - PII and sensitive content tagging
+//!
+//! The ClassifyAgent sits between ChunkAgent and EmbedAgent. Every chunk is
+//! checked for likely personal data before it is passed on unchanged:
+//! - Email addresses and phone numbers (contact details)
+//! - US social security numbers, with impossible ranges ruled out
+//! - Payment card numbers that pass the Luhn check
+//!
+//! An optional embedding classifier tags chunks whose meaning is close to a
+//! sensitive topic (medical, financial, ...). Findings are gathered per file
+//! into a [`SensitivityReport`]. The report holds counts only, never the
+//! matched values themselves.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use crossbeam_channel::{Receiver, Sender};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use super::agents::{AgentRole, SwarmMessage, SwarmStats};
+use super::embedder::{cosine_similarity, Embedder};
+
+/// Distinct contact details (emails, phone numbers) that make a file a
+/// contact list rather than a document that happens to mention someone
+const CONTACT_LIST_THRESHOLD: usize = 10;
+
+/// Topics the embedding classifier looks for by default: label and a
+/// description that is embedded as the topic's prototype
+pub const DEFAULT_SENSITIVE_TOPICS: &[(&str, &str)] = &[
+    (
+        "medical",
+        "patient medical record with diagnosis, prescription and treatment history",
+    ),
+    (
+        "financial",
+        "bank account statement, tax return, salary and payment details",
+    ),
+    (
+        "legal",
+        "confidential legal agreement, court filing or attorney-client correspondence",
+    ),
+    (
+        "credentials",
+        "passwords, API keys, private keys and login credentials",
+    ),
+];
+
+// ============================================================================
+// Findings
+// ============================================================================
+
+/// Kinds of personal data recognized by pattern
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PiiKind {
+    Email,
+    Phone,
+    Ssn,
+    CreditCard,
+}
+
+impl PiiKind {
+    /// Identifiers that are sensitive on their own, unlike contact details
+    pub fn is_identifier(self) -> bool {
+        matches!(self, PiiKind::Ssn | PiiKind::CreditCard)
+    }
+}
+
+/// How sensitive a file is, from its findings
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Sensitivity {
+    /// Nothing found
+    #[default]
+    None,
+    /// A few contact details
+    Low,
+    /// A contact list, or content on a sensitive topic
+    Medium,
+    /// Social security or payment card numbers
+    High,
+}
+
+/// What one chunk contained
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChunkFindings {
+    /// Matched values by kind
+    pub pii: Vec<(PiiKind, String)>,
+    /// Sensitive topics from the embedding classifier
+    pub topics: Vec<String>,
+}
+
+impl ChunkFindings {
+    pub fn is_empty(&self) -> bool {
+        self.pii.is_empty() && self.topics.is_empty()
+    }
+}
+
+// ============================================================================
+// Classifiers
+// ============================================================================
+
+/// Tags text close to a sensitive topic, by cosine similarity between the
+/// text's embedding and each topic's prototype
+pub struct EmbeddingClassifier {
+    embedder: Arc<dyn Embedder>,
+    topics: Vec<(String, Vec<f32>)>,
+    threshold: f32,
+}
+
+impl EmbeddingClassifier {
+    /// Embed the description of each `(label, description)` topic
+    pub fn new(
+        embedder: Arc<dyn Embedder>,
+        topics: &[(&str, &str)],
+        threshold: f32,
+    ) -> Result<Self> {
+        let descriptions: Vec<&str> = topics.iter().map(|(_, d)| *d).collect();
+        let prototypes = embedder
+            .embed_batch(&descriptions)
+            .context("Failed to embed sensitive topics")?;
+        let topics = topics
+            .iter()
+            .zip(prototypes)
+            .map(|((label, _), vector)| (label.to_string(), vector))
+            .collect();
+        Ok(Self {
+            embedder,
+            topics,
+            threshold,
+        })
+    }
+
+    /// Labels of the topics `text` is close to
+    pub fn classify(&self, text: &str) -> Result<Vec<String>> {
+        let vector = self.embedder.embed(text)?;
+        Ok(self
+            .topics
+            .iter()
+            .filter(|(_, prototype)| cosine_similarity(&vector, prototype) >= self.threshold)
+            .map(|(label, _)| label.clone())
+            .collect())
+    }
+}
+
+/// Pattern matching for personal data, plus an optional embedding classifier
+pub struct SensitivityClassifier {
+    email: Regex,
+    phone: Regex,
+    ssn: Regex,
+    card: Regex,
+    embedding: Option<EmbeddingClassifier>,
+}
+
+impl SensitivityClassifier {
+    pub fn new() -> Self {
+        Self {
+            email: Regex::new(r"(?i)\b[a-z0-9._%+-]+@[a-z0-9-]+(?:\.[a-z0-9-]+)*\.[a-z]{2,}\b")
+                .expect("valid email pattern"),
+            phone: Regex::new(
+                r"(?:\+\d{1,3}[ .-]?)?(?:\(\d{3}\)\s?|\b\d{3}[ .-])\d{3}[ .-]\d{4}\b",
+            )
+            .expect("valid phone pattern"),
+            ssn: Regex::new(r"\b(\d{3})-(\d{2})-(\d{4})\b").expect("valid SSN pattern"),
+            card: Regex::new(r"\b\d(?:[ -]?\d){12,18}\b").expect("valid card pattern"),
+            embedding: None,
+        }
+    }
+
+    pub fn with_embedding(mut self, classifier: EmbeddingClassifier) -> Self {
+        self.embedding = Some(classifier);
+        self
+    }
+
+    /// Find personal data and sensitive topics in `text`
+    pub fn classify(&self, text: &str) -> Result<ChunkFindings> {
+        let mut findings = ChunkFindings::default();
+
+        for m in self.email.find_iter(text) {
+            findings
+                .pii
+                .push((PiiKind::Email, m.as_str().to_lowercase()));
+        }
+        for caps in self.ssn.captures_iter(text) {
+            if is_valid_ssn(&caps[1], &caps[2], &caps[3]) {
+                findings.pii.push((PiiKind::Ssn, caps[0].to_string()));
+            }
+        }
+        for m in self.card.find_iter(text) {
+            let digits: String = m.as_str().chars().filter(char::is_ascii_digit).collect();
+            if luhn_valid(&digits) {
+                findings.pii.push((PiiKind::CreditCard, digits));
+            }
+        }
+        for m in self.phone.find_iter(text) {
+            let digits: String = m.as_str().chars().filter(char::is_ascii_digit).collect();
+            findings.pii.push((PiiKind::Phone, digits));
+        }
+
+        if let Some(ref embedding) = self.embedding {
+            findings.topics = embedding.classify(text)?;
+        }
+
+        Ok(findings)
+    }
+}
+
+impl Default for SensitivityClassifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// SSNs are never issued with area 000, 666 or 900-999, group 00 or
+/// serial 0000
+fn is_valid_ssn(area: &str, group: &str, serial: &str) -> bool {
+    area != "000" && area != "666" && !area.starts_with('9') && group != "00" && serial != "0000"
+}
+
+/// Luhn checksum of a 13-19 digit card number
+fn luhn_valid(digits: &str) -> bool {
+    if !(13..=19).contains(&digits.len()) {
+        return false;
+    }
+    let sum: u32 = digits
+        .bytes()
+        .rev()
+        .enumerate()
+        .map(|(i, b)| {
+            let d = (b - b'0') as u32;
+            if i % 2 == 1 {
+                let doubled = d * 2;
+                if doubled > 9 {
+                    doubled - 9
+                } else {
+                    doubled
+                }
+            } else {
+                d
+            }
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+// ============================================================================
+// Sensitivity Report
+// ============================================================================
+
+/// Findings for one file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileSensitivity {
+    pub source: PathBuf,
+    pub level: Sensitivity,
+    /// Distinct values found, by kind
+    pub pii: BTreeMap<PiiKind, usize>,
+    /// Sensitive topics from the embedding classifier
+    pub topics: Vec<String>,
+    /// Chunks with at least one finding
+    pub flagged_chunks: Vec<usize>,
+    pub total_chunks: usize,
+}
+
+/// Per-file sensitivity of everything the swarm processed, most sensitive
+/// first
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SensitivityReport {
+    pub files: Vec<FileSensitivity>,
+}
+
+impl SensitivityReport {
+    /// Files at or above `level`
+    pub fn at_least(&self, level: Sensitivity) -> impl Iterator<Item = &FileSensitivity> {
+        self.files.iter().filter(move |f| f.level >= level)
+    }
+
+    pub fn write_json(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write sensitivity report: {}", path.display()))
+    }
+}
+
+/// Findings gathered from a file's chunks so far. Values are kept as hashes
+/// so chunk overlap does not count a value twice and the values themselves
+/// are not held on to.
+#[derive(Debug, Default)]
+struct FileTally {
+    pii: HashMap<PiiKind, HashSet<blake3::Hash>>,
+    topics: BTreeSet<String>,
+    flagged_chunks: BTreeSet<usize>,
+    total_chunks: usize,
+}
+
+impl FileTally {
+    fn add(&mut self, chunk_id: usize, findings: ChunkFindings) {
+        self.total_chunks += 1;
+        if findings.is_empty() {
+            return;
+        }
+        self.flagged_chunks.insert(chunk_id);
+        for (kind, value) in findings.pii {
+            self.pii
+                .entry(kind)
+                .or_default()
+                .insert(blake3::hash(value.as_bytes()));
+        }
+        self.topics.extend(findings.topics);
+    }
+
+    fn into_sensitivity(self, source: PathBuf) -> FileSensitivity {
+        let pii: BTreeMap<PiiKind, usize> =
+            self.pii.into_iter().map(|(k, v)| (k, v.len())).collect();
+        let contacts: usize = pii
+            .iter()
+            .filter(|(k, _)| !k.is_identifier())
+            .map(|(_, n)| n)
+            .sum();
+        let level = if pii.keys().any(|k| k.is_identifier()) {
+            Sensitivity::High
+        } else if contacts >= CONTACT_LIST_THRESHOLD || !self.topics.is_empty() {
+            Sensitivity::Medium
+        } else if contacts > 0 {
+            Sensitivity::Low
+        } else {
+            Sensitivity::None
+        };
+        FileSensitivity {
+            source,
+            level,
+            pii,
+            topics: self.topics.into_iter().collect(),
+            flagged_chunks: self.flagged_chunks.into_iter().collect(),
+            total_chunks: self.total_chunks,
+        }
+    }
+}
+
+// ============================================================================
+// ClassifyAgent - PII tagging between chunking and embedding
+// ============================================================================
+
+/// Tags chunks containing personal data and passes them on to EmbedAgent
+pub struct ClassifyAgent {
+    input: Receiver<SwarmMessage>,
+    output: Sender<SwarmMessage>,
+    heal_tx: Sender<SwarmMessage>,
+    stats: Arc<SwarmStats>,
+    classifier: Arc<SensitivityClassifier>,
+    report_path: Option<PathBuf>,
+    tallies: HashMap<PathBuf, FileTally>,
+}
+
+impl ClassifyAgent {
+    pub fn new(
+        input: Receiver<SwarmMessage>,
+        output: Sender<SwarmMessage>,
+        heal_tx: Sender<SwarmMessage>,
+        stats: Arc<SwarmStats>,
+        classifier: Arc<SensitivityClassifier>,
+    ) -> Self {
+        Self {
+            input,
+            output,
+            heal_tx,
+            stats,
+            classifier,
+            report_path: None,
+            tallies: HashMap::new(),
+        }
+    }
+
+    pub fn with_report(mut self, path: PathBuf) -> Self {
+        self.report_path = Some(path);
+        self
+    }
+
+    /// Run the classify agent; returns the report once all chunks are in
+    pub fn run(mut self) -> Result<SensitivityReport> {
+        info!("{} ClassifyAgent starting", AgentRole::Classify.icon());
+
+        while let Ok(msg) = self.input.recv() {
+            match msg {
+                SwarmMessage::Chunk {
+                    ref source,
+                    chunk_id,
+                    ref data,
+                } => {
+                    let text = String::from_utf8_lossy(data);
+                    match self.classifier.classify(&text) {
+                        Ok(findings) => {
+                            if !findings.is_empty() {
+                                self.stats.chunks_flagged.fetch_add(1, Ordering::Relaxed);
+                            }
+                            self.tallies
+                                .entry(source.clone())
+                                .or_default()
+                                .add(chunk_id, findings);
+                        }
+                        Err(e) => {
+                            self.stats
+                                .errors_encountered
+                                .fetch_add(1, Ordering::Relaxed);
+                            let _ = self.heal_tx.send(SwarmMessage::Failure {
+                                agent: AgentRole::Classify,
+                                source: source.clone(),
+                                error: e.to_string(),
+                                retries_left: 3,
+                            });
+                        }
+                    }
+                    // Classification never holds up the pipeline
+                    self.output.send(msg)?;
+                }
+                SwarmMessage::FileChunked { .. } => self.output.send(msg)?,
+                SwarmMessage::Done => {
+                    let _ = self.output.send(SwarmMessage::Done);
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        let report = self.report();
+        let sensitive = report.at_least(Sensitivity::Low).count();
+        self.stats
+            .sensitive_files
+            .fetch_add(sensitive, Ordering::Relaxed);
+        if let Some(ref path) = self.report_path {
+            report.write_json(path)?;
+            info!("Wrote sensitivity report to: {}", path.display());
+        }
+
+        info!(
+            "{} ClassifyAgent complete: {} of {} files sensitive",
+            AgentRole::Classify.icon(),
+            sensitive,
+            report.files.len()
+        );
+
+        Ok(report)
+    }
+
+    fn report(&mut self) -> SensitivityReport {
+        let mut files: Vec<FileSensitivity> = self
+            .tallies
+            .drain()
+            .map(|(source, tally)| tally.into_sensitivity(source))
+            .collect();
+        files.sort_by(|a, b| b.level.cmp(&a.level).then_with(|| a.source.cmp(&b.source)));
+        SensitivityReport { files }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::swarm::Blake3Embedder;
+    use crossbeam_channel::bounded;
+
+    fn kinds(text: &str) -> Vec<PiiKind> {
+        let mut kinds: Vec<PiiKind> = SensitivityClassifier::new()
+            .classify(text)
+            .unwrap()
+            .pii
+            .into_iter()
+            .map(|(k, _)| k)
+            .collect();
+        kinds.sort();
+        kinds
+    }
+
+    #[test]
+    fn test_pii_patterns() {
+        assert_eq!(kinds("mail jane.doe@example.co.uk today"), [PiiKind::Email]);
+        assert_eq!(kinds("call (555) 867-5309"), [PiiKind::Phone]);
+        assert_eq!(kinds("call +44 555.867.5309"), [PiiKind::Phone]);
+        assert_eq!(kinds("SSN 123-45-6789"), [PiiKind::Ssn]);
+        assert_eq!(kinds("card 4111 1111 1111 1111 exp"), [PiiKind::CreditCard]);
+
+        // Impossible SSNs and numbers failing the Luhn check are not flagged
+        assert!(kinds("ref 666-12-3456 and 123-00-4567").is_empty());
+        assert!(kinds("order 4111 1111 1111 1112").is_empty());
+        assert!(kinds("version 1.2.3, build 20231018").is_empty());
+    }
+
+    #[test]
+    fn test_luhn() {
+        assert!(luhn_valid("4111111111111111"));
+        assert!(luhn_valid("378282246310005"));
+        assert!(!luhn_valid("4111111111111112"));
+        assert!(!luhn_valid("0"));
+    }
+
+    #[test]
+    fn test_classify_agent_report() {
+        let (in_tx, in_rx) = bounded(16);
+        let (out_tx, out_rx) = bounded(16);
+        let (heal_tx, _heal_rx) = bounded(16);
+        let stats = Arc::new(SwarmStats::new());
+        let dir = tempfile::tempdir().unwrap();
+        let report_path = dir.path().join("sensitivity.json");
+
+        let chunk = |source: &str, chunk_id, text: &str| SwarmMessage::Chunk {
+            source: PathBuf::from(source),
+            chunk_id,
+            data: text.as_bytes().to_vec(),
+        };
+        // The overlap repeats the address; it still counts once
+        in_tx
+            .send(chunk("contacts.txt", 0, "bob@example.com"))
+            .unwrap();
+        in_tx
+            .send(chunk("contacts.txt", 1, "bob@example.com, 555-867-5309"))
+            .unwrap();
+        in_tx
+            .send(chunk("payroll.csv", 0, "Bob,123-45-6789"))
+            .unwrap();
+        in_tx.send(chunk("notes.txt", 0, "nothing here")).unwrap();
+        in_tx.send(SwarmMessage::Done).unwrap();
+
+        let report = ClassifyAgent::new(
+            in_rx,
+            out_tx,
+            heal_tx,
+            Arc::clone(&stats),
+            Arc::new(SensitivityClassifier::new()),
+        )
+        .with_report(report_path.clone())
+        .run()
+        .unwrap();
+
+        // Chunks pass through untouched
+        assert_eq!(out_rx.try_iter().count(), 5);
+
+        let levels: Vec<_> = report
+            .files
+            .iter()
+            .map(|f| (f.source.to_str().unwrap(), f.level))
+            .collect();
+        assert_eq!(
+            levels,
+            [
+                ("payroll.csv", Sensitivity::High),
+                ("contacts.txt", Sensitivity::Low),
+                ("notes.txt", Sensitivity::None),
+            ]
+        );
+        let contacts = &report.files[1];
+        assert_eq!(contacts.pii[&PiiKind::Email], 1);
+        assert_eq!(contacts.pii[&PiiKind::Phone], 1);
+        assert_eq!(contacts.flagged_chunks, [0, 1]);
+        assert_eq!(stats.sensitive_files.load(Ordering::Relaxed), 2);
+        assert_eq!(stats.chunks_flagged.load(Ordering::Relaxed), 3);
+
+        let written = std::fs::read_to_string(&report_path).unwrap();
+        assert!(written.contains("\"ssn\": 1"));
+        assert!(!written.contains("123-45-6789"));
+    }
+
+    #[test]
+    fn test_embedding_classifier() {
+        let embedder: Arc<dyn Embedder> = Arc::new(Blake3Embedder::new(64));
+        let topic = "patient record";
+        let classifier =
+            EmbeddingClassifier::new(Arc::clone(&embedder), &[("medical", topic)], 0.99).unwrap();
+        assert_eq!(classifier.classify(topic).unwrap(), ["medical"]);
+        assert!(classifier.classify("holiday photos").unwrap().is_empty());
+
+        let findings = SensitivityClassifier::new()
+            .with_embedding(classifier)
+            .classify(topic)
+            .unwrap();
+        assert_eq!(findings.topics, ["medical"]);
+    }
+}
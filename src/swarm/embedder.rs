@@ -4,7 +4,9 @@
 //! - LM Studio (OpenAI-compatible local server, GPU accelerated)
 //! - Ollama (local embeddings with GPU support)
 //! - Candle for GPU acceleration (CUDA/Metal)
+//! - ONNX Runtime sentence-transformers on the CPU (`--features onnx`)
 //! - Fast CPU fallback with SIMD
+//! - Hosted APIs (OpenAI, Azure OpenAI, Gemini) for labs without a GPU server
 //! - Blake3-based pseudo-embeddings for testing
 
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
@@ -16,6 +18,8 @@ use parking_lot::RwLock;
 use rayon::prelude::*;
 use tracing::{info, warn};
 
+use super::remote::{RemoteEmbedder, RemoteProvider, DEFAULT_AZURE_API_VERSION};
+
 // ============================================================================
 // Embedding Configuration
 // ============================================================================
@@ -23,7 +27,7 @@ use tracing::{info, warn};
 /// Preferred embedding backend
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum EmbedderBackend {
-    /// Auto-detect best available (LM Studio > Ollama > Blake3)
+    /// Auto-detect best available (LM Studio > Ollama > ONNX > Blake3)
     #[default]
     Auto,
     /// LM Studio (OpenAI-compatible, localhost:1234)
@@ -32,8 +36,16 @@ pub enum EmbedderBackend {
     Ollama,
     /// Candle GPU (requires feature = "gpu")
     Candle,
+    /// Sentence-transformer on ONNX Runtime, CPU only (requires feature = "onnx")
+    Onnx,
     /// Fast Blake3 pseudo-embeddings (testing/fallback)
     Blake3,
+    /// OpenAI embeddings API (requires an API key)
+    OpenAi,
+    /// Azure OpenAI deployment (requires an API key and endpoint)
+    AzureOpenAi,
+    /// Google Gemini embeddings API (requires an API key)
+    Gemini,
 }
 
 /// Configuration for the embedder
@@ -57,6 +69,16 @@ pub struct EmbedderConfig {
     pub lm_studio_endpoint: String,
     /// Ollama endpoint (default: http://localhost:11434)
     pub ollama_endpoint: String,
+    /// API key for a remote backend (default: the provider's env variable)
+    pub api_key: Option<String>,
+    /// Base URL for a remote backend (required for Azure, unless
+    /// AZURE_OPENAI_ENDPOINT is set)
+    pub api_endpoint: Option<String>,
+    /// Azure OpenAI REST API version
+    pub api_version: String,
+    /// ONNX model directory or Hugging Face repo (default:
+    /// Xenova/all-MiniLM-L6-v2); Auto only tries ONNX when this is set
+    pub onnx_model: Option<String>,
 }
 
 impl Default for EmbedderConfig {
@@ -71,6 +93,10 @@ impl Default for EmbedderConfig {
             max_length: 8192,
             lm_studio_endpoint: "http://localhost:1234/v1".to_string(),
             ollama_endpoint: "http://localhost:11434".to_string(),
+            api_key: None,
+            api_endpoint: None,
+            api_version: DEFAULT_AZURE_API_VERSION.to_string(),
+            onnx_model: None,
         }
     }
 }
@@ -663,117 +689,6 @@ impl Embedder for CandleEmbedder {
     }
 }
 
-// ============================================================================
-// Embedding Cache (in-memory, blake3-keyed)
-// ============================================================================
-
-/// In-memory cache for embeddings, keyed by blake3 hash of input text.
-/// Avoids re-computing embeddings for identical chunks.
-pub struct EmbeddingCache {
-    cache: RwLock<std::collections::HashMap<[u8; 32], Vec<f32>>>,
-    hits: AtomicUsize,
-    misses: AtomicUsize,
-}
-
-impl EmbeddingCache {
-    pub fn new() -> Self {
-        Self {
-            cache: RwLock::new(std::collections::HashMap::new()),
-            hits: AtomicUsize::new(0),
-            misses: AtomicUsize::new(0),
-        }
-    }
-
-    /// Get cached embedding or compute and cache it
-    pub fn get_or_compute(&self, text: &str, embedder: &dyn Embedder) -> Result<Vec<f32>> {
-        let key = *blake3::hash(text.as_bytes()).as_bytes();
-
-        // Check cache
-        {
-            let cache = self.cache.read();
-            if let Some(embedding) = cache.get(&key) {
-                self.hits.fetch_add(1, Ordering::Relaxed);
-                return Ok(embedding.clone());
-            }
-        }
-
-        // Compute and cache
-        self.misses.fetch_add(1, Ordering::Relaxed);
-        let embedding = embedder.embed(text)?;
-
-        {
-            let mut cache = self.cache.write();
-            cache.insert(key, embedding.clone());
-        }
-
-        Ok(embedding)
-    }
-
-    /// Batch get-or-compute
-    pub fn get_or_compute_batch(
-        &self,
-        texts: &[&str],
-        embedder: &dyn Embedder,
-    ) -> Result<Vec<Vec<f32>>> {
-        let mut results = Vec::with_capacity(texts.len());
-        let mut uncached_indices = Vec::new();
-        let mut uncached_texts = Vec::new();
-
-        // Check cache for each text
-        {
-            let cache = self.cache.read();
-            for (i, text) in texts.iter().enumerate() {
-                let key = *blake3::hash(text.as_bytes()).as_bytes();
-                if let Some(embedding) = cache.get(&key) {
-                    self.hits.fetch_add(1, Ordering::Relaxed);
-                    results.push(Some(embedding.clone()));
-                } else {
-                    self.misses.fetch_add(1, Ordering::Relaxed);
-                    results.push(None);
-                    uncached_indices.push(i);
-                    uncached_texts.push(*text);
-                }
-            }
-        }
-
-        // Batch-embed uncached texts
-        if !uncached_texts.is_empty() {
-            let new_embeddings = embedder.embed_batch(&uncached_texts)?;
-            let mut cache = self.cache.write();
-
-            for (idx, embedding) in uncached_indices.into_iter().zip(new_embeddings) {
-                let key = *blake3::hash(texts[idx].as_bytes()).as_bytes();
-                cache.insert(key, embedding.clone());
-                results[idx] = Some(embedding);
-            }
-        }
-
-        // Unwrap all (safe — all Nones were filled above)
-        Ok(results.into_iter().map(|r| r.unwrap()).collect())
-    }
-
-    /// Cache statistics
-    pub fn stats(&self) -> (usize, usize, usize) {
-        let size = self.cache.read().len();
-        let hits = self.hits.load(Ordering::Relaxed);
-        let misses = self.misses.load(Ordering::Relaxed);
-        (size, hits, misses)
-    }
-
-    /// Clear cache
-    pub fn clear(&self) {
-        self.cache.write().clear();
-        self.hits.store(0, Ordering::Relaxed);
-        self.misses.store(0, Ordering::Relaxed);
-    }
-}
-
-impl Default for EmbeddingCache {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
 // ============================================================================
 // Adaptive Embedder (auto GPU/CPU fallback)
 // ============================================================================
@@ -807,9 +722,8 @@ pub struct EmbedderStats {
 
 impl AdaptiveEmbedder {
     /// Create with automatic backend detection
-    pub fn new(config: EmbedderConfig) -> Self {
+    pub fn new(mut config: EmbedderConfig) -> Self {
         let dimension = config.dimension;
-        let blake3_fallback = Arc::new(Blake3Embedder::new(dimension));
 
         let primary: Arc<dyn Embedder> = match config.backend {
             EmbedderBackend::LmStudio => {
@@ -835,15 +749,50 @@ impl AdaptiveEmbedder {
                 info!("Using Candle GPU embedder (placeholder)");
                 Arc::new(Blake3Embedder::new(dimension))
             }
+            EmbedderBackend::Onnx => match Self::load_onnx(&config) {
+                Ok(onnx) => onnx,
+                Err(e) => {
+                    warn!("ONNX embedder unavailable ({}), falling back to Blake3", e);
+                    Arc::new(Blake3Embedder::new(dimension))
+                }
+            },
+            EmbedderBackend::OpenAi | EmbedderBackend::AzureOpenAi | EmbedderBackend::Gemini => {
+                let provider = match config.backend {
+                    EmbedderBackend::OpenAi => RemoteProvider::OpenAi,
+                    EmbedderBackend::AzureOpenAi => RemoteProvider::AzureOpenAi,
+                    _ => RemoteProvider::Gemini,
+                };
+                match RemoteEmbedder::from_config(provider, &config) {
+                    Ok(remote) => {
+                        info!("Using {} embedder ({})", provider.name(), config.model);
+                        Arc::new(remote)
+                    }
+                    Err(e) => {
+                        warn!("{}, falling back to Blake3", e);
+                        Arc::new(Blake3Embedder::new(dimension))
+                    }
+                }
+            }
             EmbedderBackend::Auto => {
-                // Auto-detect: LM Studio > Ollama > Blake3
+                // Auto-detect: LM Studio > Ollama > ONNX > Blake3
                 Self::auto_detect_backend(&config, dimension)
             }
         };
 
+        // A local model has a fixed size; keep the fallback's vectors comparable
+        if primary.dimension() != dimension {
+            warn!(
+                "{} embeddings are {}-dimensional, not the configured {}",
+                primary.backend(),
+                primary.dimension(),
+                dimension
+            );
+            config.dimension = primary.dimension();
+        }
+
         Self {
             primary,
-            fallback: blake3_fallback,
+            fallback: Arc::new(Blake3Embedder::new(config.dimension)),
             primary_available: AtomicBool::new(true),
             primary_errors: AtomicUsize::new(0),
             max_errors: 3,
@@ -882,11 +831,40 @@ impl AdaptiveEmbedder {
             return Arc::new(ollama);
         }
 
-        // 3. Fall back to Blake3
+        // 3. Try a configured ONNX model
+        if config.onnx_model.is_some() {
+            match Self::load_onnx(config) {
+                Ok(onnx) => {
+                    info!("No embedding server detected, using ONNX embedder");
+                    return onnx;
+                }
+                Err(e) => warn!("ONNX embedder unavailable: {}", e),
+            }
+        }
+
+        // 4. Fall back to Blake3
         warn!("No embedding server detected, using Blake3 pseudo-embeddings");
         Arc::new(Blake3Embedder::new(dimension))
     }
 
+    /// Load the configured ONNX model, or the default MiniLM
+    #[cfg(feature = "onnx")]
+    fn load_onnx(config: &EmbedderConfig) -> Result<Arc<dyn Embedder>> {
+        let model = config
+            .onnx_model
+            .as_deref()
+            .unwrap_or(super::onnx::DEFAULT_ONNX_MODEL);
+        // Texts are embedded in parallel, one session run each
+        let onnx = super::onnx::OnnxEmbedder::new(model, config.max_length, 1)?;
+        info!("Using ONNX embedder ({})", onnx.model_name());
+        Ok(Arc::new(onnx))
+    }
+
+    #[cfg(not(feature = "onnx"))]
+    fn load_onnx(_config: &EmbedderConfig) -> Result<Arc<dyn Embedder>> {
+        anyhow::bail!("ONNX embedder not compiled in; rebuild with --features onnx")
+    }
+
     /// Get current backend
     pub fn current_backend(&self) -> &str {
         if self.primary_available.load(Ordering::Relaxed) {
@@ -7,18 +7,19 @@
 //! - Silent heal for recoverable errors
 
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use crossbeam_channel::{Receiver, Sender};
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use tracing::{debug, error, info, warn};
 
 use super::agents::{AgentRole, SwarmMessage, SwarmStats};
+use crate::events::{self, Event};
 
 // ============================================================================
 // Heal Configuration
@@ -123,7 +124,17 @@ impl HealLog {
         Ok(entries)
     }
 
+    /// Read the entries of a heal log written by an earlier run
+    pub fn read(path: &Path) -> Result<Vec<HealLogEntry>> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read heal log {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse heal log {}", path.display()))
+    }
+
+    /// Record a heal attempt and publish it as [`Event::Heal`]
     pub fn log(&self, entry: HealLogEntry) {
+        events::emit(Event::Heal(entry.clone()));
         self.entries.write().push(entry);
         self.persist();
     }
@@ -159,8 +170,23 @@ impl HealLog {
 
     /// Get summary statistics
     pub fn summary(&self) -> HealSummary {
-        let entries = self.entries.read();
-        HealSummary {
+        HealSummary::of(&self.entries.read())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HealSummary {
+    pub total_attempts: usize,
+    pub healed: usize,
+    pub failed: usize,
+    pub retrying: usize,
+    pub skipped: usize,
+}
+
+impl HealSummary {
+    /// Count the attempts in `entries` by result
+    pub fn of(entries: &[HealLogEntry]) -> Self {
+        Self {
             total_attempts: entries.len(),
             healed: entries
                 .iter()
@@ -182,13 +208,168 @@ impl HealLog {
     }
 }
 
+// ============================================================================
+// Heal Report - Failure triage from a heal log
+// ============================================================================
+
+/// Broad cause of a failure, read from its error message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ErrorClass {
+    Permission,
+    NotFound,
+    BadSector,
+    Io,
+    Timeout,
+    Network,
+    Gpu,
+    Decode,
+    Other,
+}
+
+impl ErrorClass {
+    /// Classify an error message, matching the patterns the Healer acts on
+    pub fn of(error: &str) -> Self {
+        let error = error.to_lowercase();
+        let any = |patterns: &[&str]| patterns.iter().any(|p| error.contains(p));
+
+        if any(&["permission denied", "access denied", "access is denied"]) {
+            ErrorClass::Permission
+        } else if any(&["not found", "no such file", "cannot find", "invalid path"]) {
+            ErrorClass::NotFound
+        } else if any(&["bad sector", "read error", "medium error"]) {
+            ErrorClass::BadSector
+        } else if any(&["i/o error", "io error", "input/output error"]) {
+            ErrorClass::Io
+        } else if any(&["timeout", "timed out"]) {
+            ErrorClass::Timeout
+        } else if any(&["connection", "network", "refused"]) {
+            ErrorClass::Network
+        } else if any(&["gpu", "cuda", "out of memory"]) {
+            ErrorClass::Gpu
+        } else if any(&[
+            "utf-8",
+            "utf8",
+            "decode",
+            "parse",
+            "corrupt",
+            "invalid data",
+        ]) {
+            ErrorClass::Decode
+        } else {
+            ErrorClass::Other
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorClass::Permission => "permission",
+            ErrorClass::NotFound => "not-found",
+            ErrorClass::BadSector => "bad-sector",
+            ErrorClass::Io => "io",
+            ErrorClass::Timeout => "timeout",
+            ErrorClass::Network => "network",
+            ErrorClass::Gpu => "gpu",
+            ErrorClass::Decode => "decode",
+            ErrorClass::Other => "other",
+        }
+    }
+}
+
+/// A file that ended its run failed or skipped
 #[derive(Debug, Clone, Serialize)]
-pub struct HealSummary {
-    pub total_attempts: usize,
-    pub healed: usize,
-    pub failed: usize,
-    pub retrying: usize,
-    pub skipped: usize,
+pub struct FileFailure {
+    pub source: PathBuf,
+    /// Agent of the last attempt
+    pub agent: String,
+    pub class: ErrorClass,
+    pub result: HealResult,
+    /// Heal attempts logged for the file
+    pub attempts: usize,
+    /// Error of the last attempt
+    pub error: String,
+    pub last_attempt: chrono::DateTime<chrono::Utc>,
+}
+
+/// Failed files sharing an error class
+#[derive(Debug, Clone, Serialize)]
+pub struct ClassFailures {
+    pub class: ErrorClass,
+    pub files: usize,
+    pub attempts: usize,
+    /// Error of the first file in the class
+    pub example: String,
+}
+
+/// Failures in a heal log, by error class and by file
+#[derive(Debug, Clone, Serialize)]
+pub struct HealReport {
+    pub summary: HealSummary,
+    /// Files whose last attempt was a retry, with no outcome logged after it
+    pub unconfirmed: usize,
+    /// Largest classes first
+    pub by_class: Vec<ClassFailures>,
+    /// Most attempts first
+    pub files: Vec<FileFailure>,
+}
+
+impl HealReport {
+    /// Triage a heal log: each file is judged by its last entry
+    pub fn from_entries(entries: &[HealLogEntry]) -> Self {
+        let mut by_file: HashMap<&str, (usize, &HealLogEntry)> = HashMap::new();
+        for entry in entries {
+            let slot = by_file.entry(&entry.source).or_insert((0, entry));
+            slot.0 += 1;
+            if entry.timestamp >= slot.1.timestamp {
+                slot.1 = entry;
+            }
+        }
+
+        let mut unconfirmed = 0;
+        let mut files: Vec<FileFailure> = Vec::new();
+        for (source, (attempts, last)) in by_file {
+            match last.result {
+                HealResult::Failed | HealResult::Skipped => files.push(FileFailure {
+                    source: PathBuf::from(source),
+                    agent: last.agent.clone(),
+                    class: ErrorClass::of(&last.error),
+                    result: last.result.clone(),
+                    attempts,
+                    error: last.error.clone(),
+                    last_attempt: last.timestamp,
+                }),
+                HealResult::Retrying => unconfirmed += 1,
+                HealResult::Healed => {}
+            }
+        }
+        files.sort_by(|a, b| b.attempts.cmp(&a.attempts).then(a.source.cmp(&b.source)));
+
+        let mut classes: HashMap<ErrorClass, ClassFailures> = HashMap::new();
+        for file in &files {
+            let class = classes.entry(file.class).or_insert_with(|| ClassFailures {
+                class: file.class,
+                files: 0,
+                attempts: 0,
+                example: file.error.clone(),
+            });
+            class.files += 1;
+            class.attempts += file.attempts;
+        }
+        let mut by_class: Vec<ClassFailures> = classes.into_values().collect();
+        by_class.sort_by(|a, b| b.files.cmp(&a.files).then(a.class.cmp(&b.class)));
+
+        Self {
+            summary: HealSummary::of(entries),
+            unconfirmed,
+            by_class,
+            files,
+        }
+    }
+
+    /// Files to reprocess with `swarm --retry-failed`
+    pub fn failed_files(&self) -> Vec<PathBuf> {
+        self.files.iter().map(|f| f.source.clone()).collect()
+    }
 }
 
 // ============================================================================
@@ -289,7 +470,9 @@ impl Healer {
         for entry in pending {
             let agent = match entry.agent.as_str() {
                 "Scan" => AgentRole::Scan,
+                "Transcribe" => AgentRole::Transcribe,
                 "Chunk" => AgentRole::Chunk,
+                "Classify" => AgentRole::Classify,
                 "Embed" => AgentRole::Embed,
                 "VerifyExport" => AgentRole::VerifyExport,
                 _ => continue,
@@ -697,6 +880,81 @@ mod tests {
         assert_eq!(summary.failed, 1);
     }
 
+    #[test]
+    fn test_error_class() {
+        assert_eq!(
+            ErrorClass::of("Permission denied (os error 13)"),
+            ErrorClass::Permission
+        );
+        assert_eq!(
+            ErrorClass::of("Failed to read metadata: No such file or directory"),
+            ErrorClass::NotFound
+        );
+        assert_eq!(
+            ErrorClass::of("Input/output error (os error 5)"),
+            ErrorClass::Io
+        );
+        assert_eq!(ErrorClass::of("CUDA out of memory"), ErrorClass::Gpu);
+        assert_eq!(
+            ErrorClass::of("stream did not contain valid UTF-8"),
+            ErrorClass::Decode
+        );
+        assert_eq!(ErrorClass::of("something odd"), ErrorClass::Other);
+    }
+
+    #[test]
+    fn test_heal_report_and_read() {
+        let start = chrono::Utc::now();
+        let entry = |secs: i64, source: &str, error: &str, result: HealResult| HealLogEntry {
+            timestamp: start + chrono::Duration::seconds(secs),
+            agent: "Chunk".to_string(),
+            source: source.to_string(),
+            error: error.to_string(),
+            retries_left: 0,
+            result,
+            duration_ms: 1,
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("heal.json");
+        let log = HealLog::new(Some(path.clone()));
+        log.log(entry(0, "/r/a.doc", "I/O error", HealResult::Retrying));
+        log.log(entry(1, "/r/a.doc", "I/O error", HealResult::Retrying));
+        log.log(entry(2, "/r/a.doc", "I/O error", HealResult::Failed));
+        log.log(entry(3, "/r/b.doc", "I/O error", HealResult::Failed));
+        log.log(entry(
+            4,
+            "/r/c.doc",
+            "Permission denied",
+            HealResult::Skipped,
+        ));
+        log.log(entry(5, "/r/d.doc", "timed out", HealResult::Retrying));
+        log.log(entry(6, "/r/e.doc", "timed out", HealResult::Retrying));
+        log.log(entry(7, "/r/e.doc", "timed out", HealResult::Healed));
+
+        let report = HealReport::from_entries(&HealLog::read(&path).unwrap());
+        assert_eq!(report.summary.total_attempts, 8);
+        assert_eq!(report.unconfirmed, 1);
+
+        assert_eq!(report.files.len(), 3);
+        assert_eq!(report.files[0].source, PathBuf::from("/r/a.doc"));
+        assert_eq!(report.files[0].attempts, 3);
+
+        assert_eq!(report.by_class[0].class, ErrorClass::Io);
+        assert_eq!(report.by_class[0].files, 2);
+        assert_eq!(report.by_class[0].attempts, 4);
+        assert_eq!(report.by_class[1].class, ErrorClass::Permission);
+
+        let mut failed = report.failed_files();
+        failed.sort();
+        assert_eq!(
+            failed,
+            ["/r/a.doc", "/r/b.doc", "/r/c.doc"]
+                .map(PathBuf::from)
+                .to_vec()
+        );
+    }
+
     #[test]
     fn test_gpu_fallback() {
         let result = with_gpu_fallback(
@@ -0,0 +1,268 @@
+//! Language Detection - Per-document language and sentence rules
+//!
+//! Recovered documents are not all English. The language is guessed from
+//! the letters in a sample of the text:
+//! - Script first: CJK, Hangul, Thai, Arabic and Cyrillic are unambiguous
+//!   enough to name the language (kana tells Japanese from Chinese)
+//! - Latin text is told apart by counting common function words
+//!
+//! Each language comes with the sentence rules the chunkers split on, so
+//! chunk boundaries fall between sentences in scripts that end them with
+//! `。` or `؟`, or (Thai) only separate them with a space.
+
+use serde::{Deserialize, Serialize};
+
+/// Characters of a document sampled for detection
+const SAMPLE_CHARS: usize = 4096;
+
+/// Common function words of the Latin-script languages told apart
+const STOPWORDS: &[(Language, &[&str])] = &[
+    (
+        Language::English,
+        &[
+            "the", "and", "of", "to", "is", "in", "that", "it", "with", "for",
+        ],
+    ),
+    (
+        Language::German,
+        &[
+            "der", "die", "und", "das", "ist", "nicht", "mit", "ein", "zu", "ich",
+        ],
+    ),
+    (
+        Language::French,
+        &[
+            "le", "la", "les", "et", "est", "des", "une", "pour", "dans", "que",
+        ],
+    ),
+    (
+        Language::Spanish,
+        &[
+            "el", "los", "las", "y", "es", "del", "por", "una", "con", "que",
+        ],
+    ),
+    (
+        Language::Portuguese,
+        &["o", "os", "as", "e", "não", "do", "da", "uma", "com", "que"],
+    ),
+    (
+        Language::Italian,
+        &[
+            "il", "di", "che", "è", "per", "non", "una", "gli", "con", "sono",
+        ],
+    ),
+    (
+        Language::Dutch,
+        &[
+            "de", "het", "een", "en", "van", "is", "niet", "dat", "op", "zijn",
+        ],
+    ),
+];
+
+/// Natural language of a document
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Language {
+    English,
+    German,
+    French,
+    Spanish,
+    Portuguese,
+    Italian,
+    Dutch,
+    Russian,
+    Arabic,
+    Chinese,
+    Japanese,
+    Korean,
+    Thai,
+    Unknown,
+}
+
+impl Language {
+    /// ISO 639-1 code (`und` when unknown)
+    pub fn code(&self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::German => "de",
+            Language::French => "fr",
+            Language::Spanish => "es",
+            Language::Portuguese => "pt",
+            Language::Italian => "it",
+            Language::Dutch => "nl",
+            Language::Russian => "ru",
+            Language::Arabic => "ar",
+            Language::Chinese => "zh",
+            Language::Japanese => "ja",
+            Language::Korean => "ko",
+            Language::Thai => "th",
+            Language::Unknown => "und",
+        }
+    }
+
+    /// How sentences end in this language
+    pub fn sentence_rules(&self) -> SentenceRules {
+        match self {
+            Language::Chinese | Language::Japanese => SentenceRules {
+                terminators: &['。', '！', '？', '．', '!', '?', '…'],
+                needs_space: false,
+            },
+            Language::Arabic => SentenceRules {
+                terminators: &['.', '!', '؟', '۔'],
+                needs_space: true,
+            },
+            // No sentence punctuation; a space separates sentences
+            Language::Thai => SentenceRules {
+                terminators: &[' '],
+                needs_space: false,
+            },
+            _ => SentenceRules {
+                terminators: &['.', '!', '?'],
+                needs_space: true,
+            },
+        }
+    }
+}
+
+/// Where a sentence may end
+#[derive(Debug, Clone, Copy)]
+pub struct SentenceRules {
+    terminators: &'static [char],
+    /// Terminators only count when followed by whitespace (so `3.14` or
+    /// `example.com` do not end a sentence)
+    needs_space: bool,
+}
+
+impl SentenceRules {
+    /// Byte offsets in `text` just past each sentence end
+    pub fn sentence_ends<'a>(&'a self, text: &'a str) -> impl Iterator<Item = usize> + 'a {
+        text.char_indices().filter_map(move |(i, c)| {
+            if !self.terminators.contains(&c) {
+                return None;
+            }
+            let after = i + c.len_utf8();
+            if self.needs_space && !text[after..].starts_with(char::is_whitespace) {
+                return None;
+            }
+            Some(after)
+        })
+    }
+}
+
+/// Guess the language of `text` from a sample of its letters
+pub fn detect_language(text: &str) -> Language {
+    let (mut latin, mut cyrillic, mut arabic, mut thai) = (0usize, 0usize, 0usize, 0usize);
+    let (mut han, mut kana, mut hangul) = (0usize, 0usize, 0usize);
+
+    for c in text.chars().take(SAMPLE_CHARS) {
+        match c as u32 {
+            0x3040..=0x30FF | 0x31F0..=0x31FF => kana += 1,
+            0x4E00..=0x9FFF | 0x3400..=0x4DBF | 0xF900..=0xFAFF => han += 1,
+            0xAC00..=0xD7AF | 0x1100..=0x11FF | 0x3130..=0x318F => hangul += 1,
+            0x0E00..=0x0E7F => thai += 1,
+            0x0600..=0x06FF | 0x0750..=0x077F | 0xFB50..=0xFDFF | 0xFE70..=0xFEFF => arabic += 1,
+            0x0400..=0x04FF => cyrillic += 1,
+            _ if c.is_alphabetic() => latin += 1,
+            _ => {}
+        }
+    }
+
+    let scripts = [
+        (Language::Japanese, kana + han),
+        (Language::Korean, hangul),
+        (Language::Thai, thai),
+        (Language::Arabic, arabic),
+        (Language::Russian, cyrillic),
+        (Language::Unknown, latin),
+    ];
+    let (script, count) = scripts
+        .iter()
+        .copied()
+        .max_by_key(|(_, n)| *n)
+        .unwrap_or((Language::Unknown, 0));
+    if count == 0 {
+        return Language::Unknown;
+    }
+
+    match script {
+        // Japanese mixes kanji with kana; Chinese has no kana
+        Language::Japanese if kana * 10 < kana + han => Language::Chinese,
+        Language::Unknown => detect_latin(text),
+        language => language,
+    }
+}
+
+/// Latin-script language with the most function words in the sample
+fn detect_latin(text: &str) -> Language {
+    let mut hits = [0usize; STOPWORDS.len()];
+    let sample: String = text.chars().take(SAMPLE_CHARS).collect();
+    for word in sample
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|w| !w.is_empty())
+    {
+        let word = word.to_lowercase();
+        for (i, (_, words)) in STOPWORDS.iter().enumerate() {
+            if words.contains(&word.as_str()) {
+                hits[i] += 1;
+            }
+        }
+    }
+
+    hits.iter()
+        .enumerate()
+        .filter(|(_, n)| **n > 0)
+        .max_by_key(|(i, n)| (**n, std::cmp::Reverse(*i)))
+        .map(|(i, _)| STOPWORDS[i].0)
+        .unwrap_or(Language::Unknown)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_language() {
+        let cases = [
+            ("The report is in the drawer and it is signed.", "en"),
+            (
+                "Der Bericht ist nicht in der Schublade und das ist gut.",
+                "de",
+            ),
+            (
+                "Le rapport est dans le tiroir et il est signé pour la banque.",
+                "fr",
+            ),
+            ("El informe está en el cajón y es para los abogados.", "es"),
+            ("Отчёт лежит в ящике стола.", "ru"),
+            ("التقرير في الدرج. هل هو موقع؟", "ar"),
+            ("报告在抽屉里。已经签字了。", "zh"),
+            ("報告書は引き出しの中にあります。署名済みです。", "ja"),
+            ("보고서는 서랍에 있습니다. 서명되었습니다.", "ko"),
+            ("รายงานอยู่ในลิ้นชัก ลงนามแล้ว", "th"),
+            ("1234 5678 -- ###", "und"),
+        ];
+        for (text, code) in cases {
+            assert_eq!(detect_language(text).code(), code, "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_sentence_ends() {
+        let ends = |language: Language, text: &str| -> Vec<usize> {
+            language.sentence_rules().sentence_ends(text).collect()
+        };
+
+        // Western punctuation needs a following space
+        assert_eq!(ends(Language::English, "Pi is 3.14. Done."), [11]);
+        // CJK full stops end a sentence directly
+        let zh = "报告在抽屉里。已经签字了。";
+        assert_eq!(ends(Language::Chinese, zh), [21, zh.len()]);
+        // Arabic question mark
+        assert_eq!(ends(Language::Arabic, "هل هو موقع؟ نعم").len(), 1);
+        // Thai sentences are separated by spaces
+        assert_eq!(ends(Language::Thai, "รายงานอยู่ในลิ้นชัก ลงนามแล้ว").len(), 1);
+    }
+}
@@ -2,29 +2,59 @@
 //!
 //! Implements the CaseStar Swarm Guardian pattern with:
 //! - ScanAgent: Directory crawl with Rayon par_iter
+//! - TranscribeAgent: Optional speech-to-text for audio and video
 //! - ChunkAgent: Document splitting with par_chunks
+//! - ClassifyAgent: Optional PII tagging and per-file sensitivity report
 //! - EmbedAgent: Vectorization with GPU/CPU fallback
 //! - HealAgent: Retry/fix failures with exponential backoff
 //! - VerifyExportAgent: Validation and output generation
 //!
 //! Enhanced modules:
+//! - Ask: Questions answered from the corpus by a local chat model
 //! - Session: Persistent state with save/load/resume
-//! - Chunker: Media-aware splitting for text/code/image/PDF
+//! - Chunker: Media-aware splitting for text/code/image/PDF/Office
+//! - Office: DOCX/XLSX/PPTX and ODF text extraction
+//! - Language: Per-document language detection and sentence rules
 //! - Embedder: Adaptive GPU/CPU vector generation
-//! - Searcher: Hybrid keyword + vector semantic search
+//! - Cache: Size-bounded memory and disk reuse of embeddings across runs
+//! - Onnx: Sentence-transformer embeddings on the CPU (`--features onnx`)
+//! - Progress: Per-agent counters, ETA and the live dashboard
+//! - Remote: OpenAI, Azure OpenAI and Gemini embedding APIs
+//! - Searcher: BM25 + vector search fused by rank, with optional reranking
 
 mod agents;
+mod ask;
+mod cache;
 mod chunker;
+mod classify;
 mod embedder;
 mod heal;
+mod language;
+mod office;
+#[cfg(feature = "onnx")]
+mod onnx;
 mod orchestrator;
+mod progress;
+mod remote;
 mod searcher;
 mod session;
+#[cfg(feature = "tree-sitter")]
+mod syntax;
+mod transcribe;
 
 pub use agents::*;
+pub use ask::*;
+pub use cache::*;
 pub use chunker::*;
+pub use classify::*;
 pub use embedder::*;
 pub use heal::*;
+pub use language::*;
+#[cfg(feature = "onnx")]
+pub use onnx::*;
 pub use orchestrator::*;
+pub use progress::*;
+pub use remote::*;
 pub use searcher::*;
 pub use session::*;
+pub use transcribe::*;
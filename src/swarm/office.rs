@@ -0,0 +1,438 @@
+//! Office Document Text - DOCX/XLSX/PPTX and ODF text extraction
+//!
+//! Office files are zip archives of XML parts. The text is pulled out of
+//! the parts that hold it, without a full XML parser:
+//! - Word (DOCX) and ODF: the main document part, keeping paragraph breaks
+//! - Excel (XLSX): every sheet, one line per row with tab-separated cells,
+//!   shared strings resolved
+//! - PowerPoint (PPTX): every slide in order, then the speaker notes
+
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+/// Office formats text is extracted from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum OfficeFormat {
+    Word,
+    Excel,
+    PowerPoint,
+    /// OpenDocument text, spreadsheet or presentation
+    OpenDocument,
+}
+
+impl OfficeFormat {
+    pub(super) fn from_extension(ext: &str) -> Option<Self> {
+        match ext {
+            "docx" | "docm" => Some(OfficeFormat::Word),
+            "xlsx" | "xlsm" => Some(OfficeFormat::Excel),
+            "pptx" | "pptm" => Some(OfficeFormat::PowerPoint),
+            "odt" | "ods" | "odp" => Some(OfficeFormat::OpenDocument),
+            _ => None,
+        }
+    }
+}
+
+type Archive = zip::ZipArchive<fs::File>;
+
+/// Extract the text of an office document
+pub(super) fn extract_office_text(path: &Path, format: OfficeFormat) -> Result<String> {
+    let file =
+        fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut archive = zip::ZipArchive::new(file)
+        .with_context(|| format!("Not a valid Office document: {}", path.display()))?;
+
+    match format {
+        OfficeFormat::Word => Ok(xml_to_text(&read_part(
+            &mut archive,
+            path,
+            "word/document.xml",
+        )?)),
+        OfficeFormat::OpenDocument => {
+            Ok(xml_to_text(&read_part(&mut archive, path, "content.xml")?))
+        }
+        OfficeFormat::Excel => extract_workbook(&mut archive, path),
+        OfficeFormat::PowerPoint => extract_presentation(&mut archive, path),
+    }
+}
+
+/// Read one XML part out of the archive
+fn read_part(archive: &mut Archive, path: &Path, part: &str) -> Result<String> {
+    let mut xml = String::new();
+    archive
+        .by_name(part)
+        .with_context(|| format!("{} has no {}", path.display(), part))?
+        .read_to_string(&mut xml)
+        .with_context(|| format!("Failed to read {} from {}", part, path.display()))?;
+    Ok(xml)
+}
+
+/// Names of numbered parts like `ppt/slides/slide7.xml`, in numeric order
+fn numbered_parts(archive: &Archive, prefix: &str, suffix: &str) -> Vec<String> {
+    let mut parts: Vec<(u32, String)> = archive
+        .file_names()
+        .filter_map(|name| {
+            let n = name
+                .strip_prefix(prefix)?
+                .strip_suffix(suffix)?
+                .parse()
+                .ok()?;
+            Some((n, name.to_string()))
+        })
+        .collect();
+    parts.sort();
+    parts.into_iter().map(|(_, name)| name).collect()
+}
+
+/// Every sheet, rows on their own lines and cells separated by tabs
+fn extract_workbook(archive: &mut Archive, path: &Path) -> Result<String> {
+    // Workbooks without any text cells have no shared strings part
+    let has_shared = archive
+        .file_names()
+        .any(|name| name == "xl/sharedStrings.xml");
+    let shared = if has_shared {
+        shared_strings(&read_part(archive, path, "xl/sharedStrings.xml")?)
+    } else {
+        Vec::new()
+    };
+
+    let sheets = numbered_parts(archive, "xl/worksheets/sheet", ".xml");
+    if sheets.is_empty() {
+        anyhow::bail!("{} has no worksheets", path.display());
+    }
+    let mut text = String::new();
+    for sheet in sheets {
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(&sheet_text(&read_part(archive, path, &sheet)?, &shared));
+    }
+    Ok(text)
+}
+
+/// The strings of `xl/sharedStrings.xml`, by index. Phonetic guides
+/// (`rPh`) are left out.
+fn shared_strings(xml: &str) -> Vec<String> {
+    let mut strings = Vec::new();
+    let mut current = String::new();
+    let (mut in_text, mut in_phonetic) = (false, false);
+    for token in XmlTokens::new(xml) {
+        match token {
+            XmlToken::Open("si", _) => current.clear(),
+            XmlToken::Close("si") => strings.push(std::mem::take(&mut current)),
+            XmlToken::Open("t", _) => in_text = true,
+            XmlToken::Close("t") => in_text = false,
+            XmlToken::Open("rPh", _) => in_phonetic = true,
+            XmlToken::Close("rPh") => in_phonetic = false,
+            XmlToken::Empty("si", _) => strings.push(String::new()),
+            XmlToken::Text(text) if in_text && !in_phonetic => {
+                current.push_str(&decode_xml_entities(text))
+            }
+            _ => {}
+        }
+    }
+    strings
+}
+
+/// Text of one worksheet: shared, inline and literal cell values
+fn sheet_text(xml: &str, shared: &[String]) -> String {
+    let mut text = String::new();
+    let mut row: Vec<String> = Vec::new();
+    let mut cell_type = "";
+    let mut value = String::new();
+    let mut in_value = false;
+    for token in XmlTokens::new(xml) {
+        match token {
+            XmlToken::Open("c", attrs) => {
+                cell_type = attribute(attrs, "t").unwrap_or("");
+                value.clear();
+            }
+            XmlToken::Close("c") => {
+                let cell = match cell_type {
+                    "s" => value
+                        .trim()
+                        .parse::<usize>()
+                        .ok()
+                        .and_then(|i| shared.get(i).cloned())
+                        .unwrap_or_default(),
+                    _ => decode_xml_entities(&value),
+                };
+                row.push(cell);
+            }
+            // Formula text is not what the cell shows
+            XmlToken::Open("v", _) | XmlToken::Open("t", _) => in_value = true,
+            XmlToken::Close("v") | XmlToken::Close("t") => in_value = false,
+            XmlToken::Text(t) if in_value => value.push_str(t),
+            XmlToken::Close("row") => {
+                while row.last().is_some_and(|c| c.is_empty()) {
+                    row.pop();
+                }
+                if !row.is_empty() {
+                    text.push_str(&row.join("\t"));
+                    text.push('\n');
+                }
+                row.clear();
+            }
+            _ => {}
+        }
+    }
+    text
+}
+
+/// Slides in order, then speaker notes
+fn extract_presentation(archive: &mut Archive, path: &Path) -> Result<String> {
+    let slides = numbered_parts(archive, "ppt/slides/slide", ".xml");
+    if slides.is_empty() {
+        anyhow::bail!("{} has no slides", path.display());
+    }
+    let notes = numbered_parts(archive, "ppt/notesSlides/notesSlide", ".xml");
+
+    let mut text = String::new();
+    for part in slides.iter().chain(&notes) {
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(&xml_to_text(&read_part(archive, path, part)?));
+    }
+    Ok(text)
+}
+
+/// Strip tags from WordprocessingML/DrawingML/ODF XML, keeping paragraph
+/// breaks and tabs
+fn xml_to_text(xml: &str) -> String {
+    let mut text = String::with_capacity(xml.len() / 4);
+    for token in XmlTokens::new(xml) {
+        match token {
+            XmlToken::Text(t) => text.push_str(&decode_xml_entities(t)),
+            XmlToken::Close("w:p" | "a:p" | "text:p" | "text:h") => text.push('\n'),
+            XmlToken::Open(name, _) | XmlToken::Empty(name, _) => match name {
+                "w:br" | "w:cr" | "a:br" | "text:line-break" => text.push('\n'),
+                "w:tab" | "text:tab" => text.push('\t'),
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+    text
+}
+
+// ============================================================================
+// XML scanning
+// ============================================================================
+
+/// A piece of an XML document; element names keep their namespace prefix
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum XmlToken<'a> {
+    /// `<name attrs>`
+    Open(&'a str, &'a str),
+    /// `</name>`
+    Close(&'a str),
+    /// `<name attrs/>`
+    Empty(&'a str, &'a str),
+    /// Character data between tags, entities still encoded
+    Text(&'a str),
+}
+
+/// Tags and text of an XML document, skipping declarations and comments
+struct XmlTokens<'a> {
+    rest: &'a str,
+}
+
+impl<'a> XmlTokens<'a> {
+    fn new(xml: &'a str) -> Self {
+        Self { rest: xml }
+    }
+}
+
+impl<'a> Iterator for XmlTokens<'a> {
+    type Item = XmlToken<'a>;
+
+    fn next(&mut self) -> Option<XmlToken<'a>> {
+        loop {
+            if self.rest.is_empty() {
+                return None;
+            }
+            let Some(start) = self.rest.find('<') else {
+                let text = self.rest;
+                self.rest = "";
+                return Some(XmlToken::Text(text));
+            };
+            if start > 0 {
+                let text = &self.rest[..start];
+                self.rest = &self.rest[start..];
+                return Some(XmlToken::Text(text));
+            }
+            let end = self.rest.find('>')?;
+            let tag = &self.rest[1..end];
+            self.rest = &self.rest[end + 1..];
+
+            if tag.starts_with('?') || tag.starts_with('!') {
+                continue;
+            }
+            if let Some(name) = tag.strip_prefix('/') {
+                return Some(XmlToken::Close(name.trim()));
+            }
+            let (tag, empty) = match tag.strip_suffix('/') {
+                Some(tag) => (tag, true),
+                None => (tag, false),
+            };
+            let split = tag.find(char::is_whitespace).unwrap_or(tag.len());
+            let (name, attrs) = tag.split_at(split);
+            return Some(if empty {
+                XmlToken::Empty(name, attrs)
+            } else {
+                XmlToken::Open(name, attrs)
+            });
+        }
+    }
+}
+
+/// Value of attribute `name` in an element's attribute string
+fn attribute<'a>(attrs: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = attrs;
+    loop {
+        let pos = rest.find(name)?;
+        let preceded_by_space = rest[..pos].ends_with(char::is_whitespace);
+        let after = rest[pos + name.len()..].trim_start();
+        rest = &rest[pos + name.len()..];
+        if !preceded_by_space {
+            continue;
+        }
+        let Some(after) = after.strip_prefix('=') else {
+            continue;
+        };
+        let after = after.trim_start();
+        let quote = after.chars().next()?;
+        if quote != '"' && quote != '\'' {
+            continue;
+        }
+        let value = &after[1..];
+        return value.find(quote).map(|end| &value[..end]);
+    }
+}
+
+fn decode_xml_entities(s: &str) -> String {
+    if !s.contains('&') {
+        return s.to_string();
+    }
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        let after = &rest[amp + 1..];
+        let decoded = after.find(';').and_then(|semi| {
+            let entity = &after[..semi];
+            let c = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                _ => entity
+                    .strip_prefix("#x")
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                    .and_then(char::from_u32),
+            };
+            c.map(|c| (c, semi))
+        });
+        match decoded {
+            Some((c, semi)) => {
+                out.push(c);
+                rest = &after[semi + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    fn write_zip(path: &Path, parts: &[(&str, &str)]) {
+        let mut zip = zip::ZipWriter::new(fs::File::create(path).unwrap());
+        for (name, xml) in parts {
+            zip.start_file(*name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(xml.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn test_extract_xlsx_text() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("ledger.xlsx");
+        write_zip(
+            &path,
+            &[
+                (
+                    "xl/sharedStrings.xml",
+                    r#"<?xml version="1.0"?><sst><si><t>Rent</t></si><si><r><t>Deposit </t></r><r><t>&amp; fees</t></r><rPh><t>ignored</t></rPh></si></sst>"#,
+                ),
+                (
+                    "xl/worksheets/sheet1.xml",
+                    r#"<worksheet><sheetData><row r="1"><c r="A1" t="s"><v>0</v></c><c r="B1"><f>SUM(B2:B3)</f><v>500</v></c></row><row r="2"><c r="A2" t="s"><v>1</v></c><c r="B2" t="inlineStr"><is><t>n/a</t></is></c></row><row r="3"/></sheetData></worksheet>"#,
+                ),
+                (
+                    "xl/worksheets/sheet10.xml",
+                    r#"<worksheet><sheetData><row><c t="str"><v>last</v></c></row></sheetData></worksheet>"#,
+                ),
+                (
+                    "xl/worksheets/sheet2.xml",
+                    r#"<worksheet><sheetData><row><c t="b"><v>1</v></c></row></sheetData></worksheet>"#,
+                ),
+            ],
+        );
+
+        let text = extract_office_text(&path, OfficeFormat::Excel).unwrap();
+        assert_eq!(text, "Rent\t500\nDeposit & fees\tn/a\n\n1\n\nlast\n");
+    }
+
+    #[test]
+    fn test_extract_pptx_text() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("pitch.pptx");
+        write_zip(
+            &path,
+            &[
+                (
+                    "ppt/slides/slide2.xml",
+                    r#"<p:sld><a:p><a:r><a:t>Second</a:t></a:r></a:p></p:sld>"#,
+                ),
+                (
+                    "ppt/slides/slide1.xml",
+                    r#"<p:sld><a:p><a:r><a:t>Quarterly</a:t></a:r><a:br/><a:r><a:t>results</a:t></a:r></a:p></p:sld>"#,
+                ),
+                (
+                    "ppt/notesSlides/notesSlide1.xml",
+                    r#"<p:notes><a:p><a:r><a:t>Mention the audit</a:t></a:r></a:p></p:notes>"#,
+                ),
+            ],
+        );
+
+        let text = extract_office_text(&path, OfficeFormat::PowerPoint).unwrap();
+        assert_eq!(text, "Quarterly\nresults\n\nSecond\n\nMention the audit\n");
+    }
+
+    #[test]
+    fn test_attribute() {
+        assert_eq!(attribute(r#" r="A1" t="s""#, "t"), Some("s"));
+        assert_eq!(attribute(r#" ht="12" t='n'"#, "t"), Some("n"));
+        assert_eq!(attribute(r#" r="A1""#, "t"), None);
+    }
+}
@@ -0,0 +1,309 @@
+//! ONNX Embedder - Sentence-transformer embeddings on the CPU
+//!
+//! Real embeddings on an offline rig, without an embedding server or the
+//! CUDA toolchain the `gpu` feature needs (`--features onnx`):
+//! - Runs an exported sentence-transformer through ONNX Runtime; a
+//!   quantized (int8) model such as all-MiniLM-L6-v2 embeds fast on the CPU
+//! - Loads from a local directory (`model.onnx` + `tokenizer.json`), or from
+//!   a Hugging Face repo, downloaded once into the hub cache
+//! - Mean-pools the token states over the attention mask and normalizes,
+//!   as sentence-transformers does
+
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use ort::session::builder::GraphOptimizationLevel;
+use ort::session::{Session, SessionInputValue};
+use ort::value::Tensor;
+use tokenizers::{PaddingParams, Tokenizer, TruncationParams};
+use tracing::info;
+
+use super::embedder::Embedder;
+
+/// Hugging Face repo used when no model is configured
+pub const DEFAULT_ONNX_MODEL: &str = "Xenova/all-MiniLM-L6-v2";
+
+/// Model files tried in a model directory, quantized first
+const MODEL_FILES: &[&str] = &[
+    "model_quantized.onnx",
+    "model_int8.onnx",
+    "model_qint8_avx2.onnx",
+    "model.onnx",
+];
+
+/// Longest input BERT-style models accept
+const MAX_POSITIONS: usize = 512;
+
+/// Sentence-transformer embedder running on ONNX Runtime
+pub struct OnnxEmbedder {
+    session: Session,
+    tokenizer: Tokenizer,
+    /// The model takes `token_type_ids` (BERT does, DistilBERT does not)
+    token_types: bool,
+    dimension: usize,
+    name: String,
+}
+
+impl OnnxEmbedder {
+    /// Load `model`: a local model directory, or else a Hugging Face repo
+    pub fn new(model: &str, max_length: usize, threads: usize) -> Result<Self> {
+        let path = Path::new(model);
+        if path.is_dir() {
+            Self::from_dir(path, max_length, threads)
+        } else {
+            Self::from_hub(model, max_length, threads)
+        }
+    }
+
+    /// Default quantized all-MiniLM-L6-v2 (384-dim)
+    pub fn minilm(threads: usize) -> Result<Self> {
+        Self::from_hub(DEFAULT_ONNX_MODEL, 256, threads)
+    }
+
+    /// Fetch a model's tokenizer and ONNX export from the Hugging Face Hub
+    pub fn from_hub(repo_id: &str, max_length: usize, threads: usize) -> Result<Self> {
+        let api = hf_hub::api::sync::Api::new().context("Failed to init HF Hub API")?;
+        let repo = api.model(repo_id.to_string());
+        let tokenizer = repo
+            .get("tokenizer.json")
+            .with_context(|| format!("Failed to download tokenizer.json from {}", repo_id))?;
+        let onnx = ["onnx/model_quantized.onnx", "onnx/model.onnx", "model.onnx"]
+            .iter()
+            .find_map(|file| repo.get(file).ok())
+            .with_context(|| format!("No ONNX export in {}", repo_id))?;
+
+        let dir = tokenizer.parent().unwrap_or(Path::new("."));
+        Self::load(&onnx, &dir.join("tokenizer.json"), max_length, threads)
+            .map(|embedder| embedder.named(repo_id))
+    }
+
+    /// Load from a directory holding an ONNX model and `tokenizer.json`
+    pub fn from_dir(dir: &Path, max_length: usize, threads: usize) -> Result<Self> {
+        let model =
+            find_model(dir).with_context(|| format!("No ONNX model in {}", dir.display()))?;
+        Self::load(&model, &dir.join("tokenizer.json"), max_length, threads)
+            .map(|embedder| embedder.named(&dir.display().to_string()))
+    }
+
+    fn load(model: &Path, tokenizer: &Path, max_length: usize, threads: usize) -> Result<Self> {
+        info!("Loading ONNX embedder: {}", model.display());
+
+        let mut tokenizer = Tokenizer::from_file(tokenizer)
+            .map_err(|e| anyhow::anyhow!("Tokenizer load error: {}", e))?;
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: max_length.clamp(1, MAX_POSITIONS),
+                ..Default::default()
+            }))
+            .map_err(|e| anyhow::anyhow!("Tokenizer truncation error: {}", e))?;
+        // Pad each batch to its longest text
+        tokenizer.with_padding(Some(PaddingParams::default()));
+
+        let session = Session::builder()?
+            .with_optimization_level(GraphOptimizationLevel::Level3)?
+            .with_intra_threads(threads.max(1))?
+            .commit_from_file(model)
+            .with_context(|| format!("Failed to load ONNX model {}", model.display()))?;
+        let token_types = session
+            .inputs
+            .iter()
+            .any(|input| input.name == "token_type_ids");
+
+        let mut embedder = Self {
+            session,
+            tokenizer,
+            token_types,
+            dimension: 0,
+            name: String::new(),
+        };
+        // The hidden size is easiest read off a real output
+        embedder.dimension = embedder
+            .run(&["dimension probe"])?
+            .first()
+            .map(Vec::len)
+            .context("ONNX model returned no embedding")?;
+
+        info!("ONNX embedder loaded (dim={})", embedder.dimension);
+        Ok(embedder)
+    }
+
+    fn named(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    /// Model directory or repo the embedder was loaded from
+    pub fn model_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Embed one padded batch
+    fn run(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        let encodings = self
+            .tokenizer
+            .encode_batch(texts.to_vec(), true)
+            .map_err(|e| anyhow::anyhow!("Tokenize error: {}", e))?;
+        let batch = encodings.len();
+        let len = encodings.first().map_or(0, |e| e.get_ids().len());
+
+        let column = |values: fn(&tokenizers::Encoding) -> &[u32]| -> Vec<i64> {
+            encodings
+                .iter()
+                .flat_map(|e| values(e).iter().map(|&v| v as i64))
+                .collect()
+        };
+        let ids = column(tokenizers::Encoding::get_ids);
+        let mask = column(tokenizers::Encoding::get_attention_mask);
+
+        let mut inputs: Vec<(Cow<str>, SessionInputValue)> = vec![
+            (
+                "input_ids".into(),
+                Tensor::from_array(([batch, len], ids.into_boxed_slice()))?.into(),
+            ),
+            (
+                "attention_mask".into(),
+                Tensor::from_array(([batch, len], mask.clone().into_boxed_slice()))?.into(),
+            ),
+        ];
+        if self.token_types {
+            let types = column(tokenizers::Encoding::get_type_ids);
+            inputs.push((
+                "token_type_ids".into(),
+                Tensor::from_array(([batch, len], types.into_boxed_slice()))?.into(),
+            ));
+        }
+
+        let outputs = self.session.run(inputs)?;
+        let (shape, data) = outputs[0].try_extract_raw_tensor::<f32>()?;
+        Ok(pool(&shape, data, &mask, batch))
+    }
+}
+
+impl Embedder for OnnxEmbedder {
+    fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        self.run(&[text])?
+            .pop()
+            .context("ONNX model returned no embedding")
+    }
+
+    fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        let mut vectors = Vec::with_capacity(texts.len());
+        // Bounded so one long text does not pad a huge batch
+        for chunk in texts.chunks(32) {
+            vectors.extend(self.run(chunk)?);
+        }
+        Ok(vectors)
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn backend(&self) -> &str {
+        "onnx-cpu"
+    }
+
+    fn is_gpu(&self) -> bool {
+        false
+    }
+}
+
+/// First of `MODEL_FILES` in `dir` or its `onnx/` subdirectory
+fn find_model(dir: &Path) -> Option<PathBuf> {
+    [dir.to_path_buf(), dir.join("onnx")]
+        .iter()
+        .flat_map(|d| MODEL_FILES.iter().map(move |file| d.join(file)))
+        .find(|path| path.is_file())
+}
+
+/// Sentence vectors from a model output: token states `[batch, len, dim]`
+/// are mean-pooled over the attention mask, pooled `[batch, dim]` outputs
+/// taken as they are; both come back unit length
+fn pool(shape: &[i64], data: &[f32], mask: &[i64], batch: usize) -> Vec<Vec<f32>> {
+    let dim = shape.last().copied().unwrap_or(0).max(0) as usize;
+    if dim == 0 || batch == 0 {
+        return Vec::new();
+    }
+
+    let mut vectors: Vec<Vec<f32>> = if shape.len() == 3 {
+        let len = shape[1] as usize;
+        (0..batch)
+            .map(|b| {
+                let mut sum = vec![0.0f32; dim];
+                let mut count = 0.0f32;
+                for t in 0..len {
+                    if mask[b * len + t] == 0 {
+                        continue;
+                    }
+                    let token = &data[(b * len + t) * dim..(b * len + t + 1) * dim];
+                    for (s, v) in sum.iter_mut().zip(token) {
+                        *s += v;
+                    }
+                    count += 1.0;
+                }
+                sum.iter().map(|s| s / count.max(1e-9)).collect()
+            })
+            .collect()
+    } else {
+        data.chunks(dim).take(batch).map(<[f32]>::to_vec).collect()
+    };
+
+    for vector in &mut vectors {
+        let norm: f32 = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            for v in vector.iter_mut() {
+                *v /= norm;
+            }
+        }
+    }
+    vectors
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pool_token_states() {
+        // Two texts, three tokens, dim 2; the second text is one token plus padding
+        let shape = [2, 3, 2];
+        let data = [
+            1.0, 0.0, 3.0, 0.0, 9.0, 9.0, //
+            0.0, 2.0, 5.0, 5.0, 5.0, 5.0,
+        ];
+        let mask = [1, 1, 1, 1, 0, 0];
+        let vectors = pool(&shape, &data, &mask, 2);
+
+        // Mean of (1,0), (3,0), (9,9) = (13/3, 3), then unit length
+        let norm = ((13.0f32 / 3.0).powi(2) + 9.0).sqrt();
+        assert!((vectors[0][0] - 13.0 / 3.0 / norm).abs() < 1e-6);
+        // Padding tokens are ignored
+        assert_eq!(vectors[1], vec![0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_pool_sentence_output() {
+        let vectors = pool(&[2, 2], &[3.0, 4.0, 0.0, 0.0], &[1, 1], 2);
+        assert_eq!(vectors[0], vec![0.6, 0.8]);
+        assert_eq!(vectors[1], vec![0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_find_model_prefers_quantized() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("onnx")).unwrap();
+        assert_eq!(find_model(dir.path()), None);
+
+        std::fs::write(dir.path().join("onnx/model.onnx"), b"").unwrap();
+        std::fs::write(dir.path().join("onnx/model_quantized.onnx"), b"").unwrap();
+        assert_eq!(
+            find_model(dir.path()),
+            Some(dir.path().join("onnx/model_quantized.onnx"))
+        );
+    }
+}
@@ -1,21 +1,34 @@
 //! Swarm Orchestrator - Supervisor pattern for coordinating agents
 //!
 //! The orchestrator:
-//! - Spawns all 5 agent types
+//! - Spawns all 5 agent types, plus the optional TranscribeAgent and
+//!   ClassifyAgent
 //! - Manages message channels between agents
 //! - Coordinates parallel execution with rayon::join
 //! - Handles graceful shutdown and error propagation
+//! - Persists a session as files finish, so an interrupted run resumes
+//! - Reports progress on the event bus, and to an optional callback, while
+//!   agents run
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
-use crossbeam_channel::{bounded, Receiver, Sender};
-use tracing::{error, info};
+use crossbeam_channel::{bounded, Receiver, RecvTimeoutError, Sender};
+use tracing::{error, info, warn};
 
 use super::agents::*;
+use super::cache::*;
+use super::classify::*;
+use super::embedder::HttpEmbedder;
 use super::heal::*;
+use super::progress::*;
+use super::session::*;
+use super::transcribe::*;
+use crate::events::{self, Event};
+use crate::maintenance::{data_dir, EMBEDDINGS_DIR, SESSIONS_DIR};
 
 // ============================================================================
 // Swarm Configuration
@@ -28,8 +41,13 @@ pub struct SwarmConfig {
     pub source: PathBuf,
     /// Output path for exports
     pub output: Option<PathBuf>,
-    /// Channel buffer size
+    /// Messages buffered between stages; a stage blocks while the next
+    /// one's channel is full, bounding memory however large the source
     pub channel_size: usize,
+    /// Threads queueing scanned files
+    pub scan_workers: usize,
+    /// Files read and chunked at once
+    pub chunk_workers: usize,
     /// Heal configuration
     pub heal: HealConfig,
     /// Embed configuration
@@ -42,6 +60,187 @@ pub struct SwarmConfig {
     pub skip_hidden: bool,
     /// File extensions filter
     pub extensions: Option<Vec<String>>,
+    /// PII classification, off unless set
+    pub classify: Option<ClassifyConfig>,
+    /// Speech-to-text for audio and video, off unless set
+    pub transcribe: Option<TranscribeConfig>,
+    /// Session persistence for resuming, off unless set
+    pub session: Option<SessionConfig>,
+    /// Embedding reuse across runs, off unless set
+    pub embed_cache: Option<EmbedCacheConfig>,
+    /// Process only these files instead of walking the source (retrying
+    /// the failures of an earlier run)
+    pub files: Option<Vec<PathBuf>>,
+}
+
+/// Where the swarm session is saved, and whether to continue one
+#[derive(Debug, Clone)]
+pub struct SessionConfig {
+    /// Directory the session file is saved in
+    pub dir: PathBuf,
+    /// Session file to continue instead of starting a new session
+    pub resume: Option<PathBuf>,
+    /// How often progress is saved while files finish
+    pub save_interval: Duration,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            dir: data_dir().join(SESSIONS_DIR),
+            resume: None,
+            save_interval: Duration::from_secs(10),
+        }
+    }
+}
+
+impl SessionConfig {
+    /// Continue the session in `file`, saving it back in place
+    pub fn resume(file: PathBuf) -> Self {
+        let dir = file
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from("."));
+        Self {
+            dir,
+            resume: Some(file),
+            ..Default::default()
+        }
+    }
+
+    /// Start or resume the session for `source`
+    pub fn open(&self, source: &Path, output: Option<PathBuf>) -> Result<SessionStore> {
+        let store = SessionStore::new(self.dir.clone())
+            .use_json()
+            .with_auto_save(self.save_interval);
+
+        match self.resume {
+            Some(ref file) => {
+                let session = store.resume_file(file)?;
+                if session.source != source {
+                    anyhow::bail!(
+                        "Session {} is for {}, not {}",
+                        file.display(),
+                        session.source.display(),
+                        source.display()
+                    );
+                }
+            }
+            None => {
+                if let Some(existing) = store.find_resumable(source)? {
+                    warn!(
+                        "Unfinished session {} for this source ({} files done); pass --resume {} to continue it",
+                        existing.session_id,
+                        existing.processed_files.len(),
+                        self.dir.join(format!("{}.json", existing.session_id)).display()
+                    );
+                }
+                store.start(source.to_path_buf(), output)?;
+            }
+        }
+        Ok(store)
+    }
+}
+
+/// Where embeddings are cached between runs, and how much room they get
+#[derive(Debug, Clone)]
+pub struct EmbedCacheConfig {
+    /// Directory holding the cached vectors
+    pub dir: PathBuf,
+    /// Disk budget; least recently used vectors are deleted past it
+    pub max_disk_bytes: u64,
+    /// RAM budget for vectors also held in memory
+    pub max_memory_bytes: u64,
+}
+
+impl Default for EmbedCacheConfig {
+    fn default() -> Self {
+        Self {
+            dir: data_dir().join(EMBEDDINGS_DIR),
+            max_disk_bytes: DEFAULT_CACHE_DISK,
+            max_memory_bytes: DEFAULT_CACHE_MEMORY,
+        }
+    }
+}
+
+impl EmbedCacheConfig {
+    /// Open the cache for vectors from `model`
+    pub fn open(&self, model: &str) -> Result<EmbeddingCache> {
+        EmbeddingCache::new()
+            .with_model(model)
+            .with_memory_limit(self.max_memory_bytes)
+            .with_disk(&self.dir, self.max_disk_bytes)
+    }
+}
+
+/// Configuration for the TranscribeAgent
+#[derive(Debug, Clone)]
+pub struct TranscribeConfig {
+    /// Whisper model on HuggingFace Hub
+    pub model: String,
+    /// Spoken language (ISO 639-1); ignored by English-only models
+    pub language: Option<String>,
+    /// Where to write the transcripts (JSON)
+    pub report: Option<PathBuf>,
+}
+
+impl Default for TranscribeConfig {
+    fn default() -> Self {
+        Self {
+            model: DEFAULT_WHISPER_MODEL.to_string(),
+            language: Some("en".to_string()),
+            report: None,
+        }
+    }
+}
+
+impl TranscribeConfig {
+    /// Load the Whisper model, on the GPU if `prefer_gpu` and one is present
+    pub fn transcriber(&self, prefer_gpu: bool) -> Result<WhisperTranscriber> {
+        WhisperTranscriber::new(&self.model, self.language.as_deref(), prefer_gpu)
+            .with_context(|| format!("Whisper model {} is not available", self.model))
+    }
+}
+
+/// Configuration for the ClassifyAgent
+#[derive(Debug, Clone)]
+pub struct ClassifyConfig {
+    /// Where to write the per-file sensitivity report (JSON)
+    pub report: Option<PathBuf>,
+    /// Ollama embedding model for topic classification; patterns only if unset
+    pub model: Option<String>,
+    /// Cosine similarity at which a chunk is tagged with a topic
+    pub threshold: f32,
+}
+
+impl Default for ClassifyConfig {
+    fn default() -> Self {
+        Self {
+            report: None,
+            model: None,
+            threshold: 0.6,
+        }
+    }
+}
+
+impl ClassifyConfig {
+    /// Build the classifier, embedding the default topics if a model is set
+    pub fn classifier(&self) -> Result<SensitivityClassifier> {
+        let classifier = SensitivityClassifier::new();
+        match self.model {
+            Some(ref model) => {
+                let embedding = EmbeddingClassifier::new(
+                    Arc::new(HttpEmbedder::ollama(model)),
+                    DEFAULT_SENSITIVE_TOPICS,
+                    self.threshold,
+                )
+                .with_context(|| format!("Embedding model {} is not available", model))?;
+                Ok(classifier.with_embedding(embedding))
+            }
+            None => Ok(classifier),
+        }
+    }
 }
 
 impl Default for SwarmConfig {
@@ -50,12 +249,19 @@ impl Default for SwarmConfig {
             source: PathBuf::from("."),
             output: None,
             channel_size: 1000,
+            scan_workers: num_cpus::get(),
+            chunk_workers: num_cpus::get(),
             heal: HealConfig::default(),
             embed: EmbedConfig::default(),
             chunk_size: 1024,
             chunk_overlap: 128,
             skip_hidden: true,
             extensions: None,
+            classify: None,
+            transcribe: None,
+            session: None,
+            embed_cache: None,
+            files: None,
         }
     }
 }
@@ -82,6 +288,33 @@ impl SwarmConfig {
         self.heal = config;
         self
     }
+
+    pub fn with_classify(mut self, config: ClassifyConfig) -> Self {
+        self.classify = Some(config);
+        self
+    }
+
+    pub fn with_transcribe(mut self, config: TranscribeConfig) -> Self {
+        self.transcribe = Some(config);
+        self
+    }
+
+    pub fn with_session(mut self, config: SessionConfig) -> Self {
+        self.session = Some(config);
+        self
+    }
+
+    pub fn with_embed_cache(mut self, config: EmbedCacheConfig) -> Self {
+        self.embed_cache = Some(config);
+        self
+    }
+
+    /// Retry the files that failed in an earlier run's heal log
+    pub fn with_retry_failed(mut self, heal_log: &Path) -> Result<Self> {
+        let report = HealReport::from_entries(&HealLog::read(heal_log)?);
+        self.files = Some(report.failed_files());
+        Ok(self)
+    }
 }
 
 // ============================================================================
@@ -92,6 +325,7 @@ impl SwarmConfig {
 pub struct SwarmOrchestrator {
     config: SwarmConfig,
     stats: Arc<SwarmStats>,
+    progress: Option<(Duration, ProgressCallback)>,
 }
 
 impl SwarmOrchestrator {
@@ -99,9 +333,17 @@ impl SwarmOrchestrator {
         Self {
             config,
             stats: Arc::new(SwarmStats::new()),
+            progress: None,
         }
     }
 
+    /// Call `callback` with a progress snapshot every `interval`, and once
+    /// more when the swarm has finished
+    pub fn on_progress(mut self, interval: Duration, callback: ProgressCallback) -> Self {
+        self.progress = Some((interval, callback));
+        self
+    }
+
     /// Run the full swarm pipeline
     pub fn run(&self) -> Result<SwarmSummary> {
         info!("🐝 Swarm Orchestrator starting");
@@ -109,6 +351,21 @@ impl SwarmOrchestrator {
         if let Some(ref output) = self.config.output {
             info!("  Output: {}", output.display());
         }
+        if let Some(ref files) = self.config.files {
+            info!("  Retrying: {} files", files.len());
+        }
+
+        // Opened first so a bad --resume fails before any agent starts
+        let session = match self.config.session {
+            Some(ref config) => {
+                let store = config.open(&self.config.source, self.config.output.clone())?;
+                if let Some(file) = store.session_file() {
+                    info!("  Session: {}", file.display());
+                }
+                Some(Arc::new(store))
+            }
+            None => None,
+        };
 
         // Create channels
         let (scan_tx, scan_rx) = bounded::<SwarmMessage>(self.config.channel_size);
@@ -134,8 +391,28 @@ impl SwarmOrchestrator {
             heal_tx.clone(),
             heal_rx,
             scan_retry_tx,
+            session.clone(),
         )?;
 
+        // Snapshots always go on the event bus (metrics, UIs), and to the
+        // caller's callback if there is one
+        let started = Instant::now();
+        let (interval, publish) = {
+            let callback = self.progress.as_ref().map(|(_, c)| Arc::clone(c));
+            let publish: ProgressCallback = Arc::new(move |progress: &SwarmProgress| {
+                events::emit(Event::Swarm(progress.clone()));
+                if let Some(ref callback) = callback {
+                    callback(progress);
+                }
+            });
+            let interval = self
+                .progress
+                .as_ref()
+                .map_or(DEFAULT_PROGRESS_INTERVAL, |(interval, _)| *interval);
+            (interval, publish)
+        };
+        let (stop_monitor, monitor) = self.spawn_monitor(interval, Arc::clone(&publish), started);
+
         // Wait for all agents to complete
         let mut errors = Vec::new();
         for (name, handle) in handles {
@@ -153,7 +430,22 @@ impl SwarmOrchestrator {
             }
         }
 
-        let summary = self.stats.to_summary();
+        drop(stop_monitor);
+        let _ = monitor.join();
+        let mut progress = SwarmProgress::from_stats(&self.stats, started.elapsed());
+        progress.finished = true;
+        publish(&progress);
+
+        let mut summary = self.stats.to_summary();
+
+        if let Some(ref session) = session {
+            // An agent failure leaves the session open to resume
+            if errors.is_empty() {
+                session.update(|s| s.complete(summary.clone()))?;
+            }
+            session.save()?;
+            summary.session = session.session_file();
+        }
 
         if errors.is_empty() {
             info!("🐝 Swarm complete!");
@@ -162,6 +454,18 @@ impl SwarmOrchestrator {
             info!("  Embeddings: {}", summary.embeddings_generated);
             info!("  Heals: {}", summary.heals_performed);
             info!("  Exports: {}", summary.exports_completed);
+            if self.config.transcribe.is_some() {
+                info!("  Transcribed: {}", summary.files_transcribed);
+            }
+            if self.config.classify.is_some() {
+                info!("  Sensitive files: {}", summary.sensitive_files);
+            }
+            if summary.files_skipped > 0 {
+                info!("  Done in earlier runs: {}", summary.files_skipped);
+            }
+            if summary.embeddings_cached > 0 {
+                info!("  Embeddings from cache: {}", summary.embeddings_cached);
+            }
         } else {
             error!("🐝 Swarm completed with {} errors", errors.len());
             for err in &errors {
@@ -184,9 +488,30 @@ impl SwarmOrchestrator {
         heal_tx: Sender<SwarmMessage>,
         heal_rx: Receiver<SwarmMessage>,
         scan_retry_tx: Sender<SwarmMessage>,
+        session: Option<Arc<SessionStore>>,
     ) -> Result<Vec<(String, JoinHandle<Result<()>>)>> {
         let mut handles = Vec::new();
 
+        // Built up front so a missing model fails before any agent starts
+        let classifier = match self.config.classify {
+            Some(ref classify) => Some(Arc::new(classify.classifier()?)),
+            None => None,
+        };
+        let transcriber: Option<Arc<dyn Transcriber>> = match self.config.transcribe {
+            Some(ref transcribe) => {
+                Some(Arc::new(transcribe.transcriber(self.config.embed.use_gpu)?))
+            }
+            None => None,
+        };
+        let embed_cache = match self.config.embed_cache {
+            Some(ref cache) => {
+                // Keyed to the embedding in use, so a model change starts afresh
+                let model = format!("swarm-pseudo-{}", self.config.embed.model_dim);
+                Some(Arc::new(cache.open(&model)?))
+            }
+            None => None,
+        };
+
         // Channel flow: ScanAgent -> scan_tx/rx -> ChunkAgent -> chunk_tx/rx -> EmbedAgent -> embed_tx/rx -> VerifyExportAgent
         //               All agents send failures to heal_tx -> heal_rx -> HealAgent
 
@@ -198,7 +523,8 @@ impl SwarmOrchestrator {
             heal_tx.clone(),
             Arc::clone(&self.stats),
         )
-        .skip_hidden(self.config.skip_hidden);
+        .skip_hidden(self.config.skip_hidden)
+        .with_workers(self.config.scan_workers);
 
         let scan_agent = if let Some(ref exts) = self.config.extensions {
             scan_agent.with_extensions(exts.clone())
@@ -206,11 +532,54 @@ impl SwarmOrchestrator {
             scan_agent
         };
 
+        let scan_agent = match self.config.files {
+            Some(ref files) => scan_agent.with_files(files.clone()),
+            None => scan_agent,
+        };
+
+        let scan_agent = match session.as_ref().and_then(|s| s.current()) {
+            Some(resumed) => scan_agent.skip_files(resumed.processed_files),
+            None => scan_agent,
+        };
+
         handles.push((
             "ScanAgent".to_string(),
             thread::spawn(move || scan_agent.run()),
         ));
 
+        // === Transcribe Agent ===
+        // TranscribeAgent, if enabled, reads from scan_rx; audio and video
+        // become chunks on chunk_tx, other files go on to ChunkAgent
+        let scan_rx = match transcriber {
+            Some(transcriber) => {
+                let (files_tx, files_rx) = bounded::<SwarmMessage>(self.config.channel_size);
+                let transcribe_agent = TranscribeAgent::new(
+                    scan_rx,
+                    files_tx,
+                    chunk_tx.clone(),
+                    heal_tx.clone(),
+                    Arc::clone(&self.stats),
+                    transcriber,
+                );
+                let report = self
+                    .config
+                    .transcribe
+                    .as_ref()
+                    .and_then(|t| t.report.clone());
+                let transcribe_agent = match report {
+                    Some(report) => transcribe_agent.with_report(report),
+                    None => transcribe_agent,
+                };
+
+                handles.push((
+                    "TranscribeAgent".to_string(),
+                    thread::spawn(move || transcribe_agent.run().map(|_| ())),
+                ));
+                files_rx
+            }
+            None => scan_rx,
+        };
+
         // === Chunk Agent ===
         // ChunkAgent reads from scan_rx, writes to chunk_tx
         let chunk_agent = ChunkAgent::new(
@@ -219,18 +588,51 @@ impl SwarmOrchestrator {
             heal_tx.clone(),
             Arc::clone(&self.stats),
         )
-        .with_chunk_size(self.config.chunk_size, self.config.chunk_overlap);
+        .with_chunk_size(self.config.chunk_size, self.config.chunk_overlap)
+        .with_workers(self.config.chunk_workers);
 
         handles.push((
             "ChunkAgent".to_string(),
             thread::spawn(move || chunk_agent.run()),
         ));
 
+        // === Classify Agent ===
+        // ClassifyAgent, if enabled, reads from chunk_rx and passes chunks on
+        let chunk_rx = match classifier {
+            Some(classifier) => {
+                let (classified_tx, classified_rx) =
+                    bounded::<SwarmMessage>(self.config.channel_size);
+                let classify_agent = ClassifyAgent::new(
+                    chunk_rx,
+                    classified_tx,
+                    heal_tx.clone(),
+                    Arc::clone(&self.stats),
+                    classifier,
+                );
+                let report = self.config.classify.as_ref().and_then(|c| c.report.clone());
+                let classify_agent = match report {
+                    Some(report) => classify_agent.with_report(report),
+                    None => classify_agent,
+                };
+
+                handles.push((
+                    "ClassifyAgent".to_string(),
+                    thread::spawn(move || classify_agent.run().map(|_| ())),
+                ));
+                classified_rx
+            }
+            None => chunk_rx,
+        };
+
         // === Embed Agent ===
         // EmbedAgent reads from chunk_rx, writes to embed_tx
         let embed_agent =
             EmbedAgent::new(chunk_rx, embed_tx, heal_tx.clone(), Arc::clone(&self.stats))
                 .with_config(self.config.embed.clone());
+        let embed_agent = match embed_cache {
+            Some(cache) => embed_agent.with_cache(cache),
+            None => embed_agent,
+        };
 
         handles.push((
             "EmbedAgent".to_string(),
@@ -249,6 +651,11 @@ impl SwarmOrchestrator {
             verify_agent
         };
 
+        let verify_agent = match session {
+            Some(session) => verify_agent.with_session(session),
+            None => verify_agent,
+        };
+
         handles.push((
             "VerifyExportAgent".to_string(),
             thread::spawn(move || {
@@ -268,6 +675,23 @@ impl SwarmOrchestrator {
         Ok(handles)
     }
 
+    /// Sample the stats every `interval` until the returned sender is dropped
+    fn spawn_monitor(
+        &self,
+        interval: Duration,
+        callback: ProgressCallback,
+        started: Instant,
+    ) -> (Sender<()>, JoinHandle<()>) {
+        let (stop_tx, stop_rx) = bounded::<()>(1);
+        let stats = Arc::clone(&self.stats);
+        let handle = thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stop_rx.recv_timeout(interval) {
+                callback(&SwarmProgress::from_stats(&stats, started.elapsed()));
+            }
+        });
+        (stop_tx, handle)
+    }
+
     /// Get current statistics
     pub fn stats(&self) -> SwarmSummary {
         self.stats.to_summary()
@@ -313,12 +737,14 @@ pub async fn run_swarm_async(source: PathBuf, output: Option<PathBuf>) -> Result
 /// Builder pattern for swarm configuration
 pub struct SwarmBuilder {
     config: SwarmConfig,
+    progress: Option<ProgressCallback>,
 }
 
 impl SwarmBuilder {
     pub fn new(source: PathBuf) -> Self {
         Self {
             config: SwarmConfig::new(source),
+            progress: None,
         }
     }
 
@@ -338,6 +764,20 @@ impl SwarmBuilder {
         self
     }
 
+    /// Threads for scanning, chunking and embedding
+    pub fn workers(mut self, scan: usize, chunk: usize, embed: usize) -> Self {
+        self.config.scan_workers = scan;
+        self.config.chunk_workers = chunk;
+        self.config.embed.workers = embed;
+        self
+    }
+
+    /// Messages buffered between stages
+    pub fn channel_size(mut self, size: usize) -> Self {
+        self.config.channel_size = size;
+        self
+    }
+
     pub fn max_retries(mut self, retries: u32) -> Self {
         self.config.heal.max_retries = retries;
         self
@@ -363,8 +803,43 @@ impl SwarmBuilder {
         self
     }
 
+    pub fn classify(mut self, config: ClassifyConfig) -> Self {
+        self.config.classify = Some(config);
+        self
+    }
+
+    pub fn transcribe(mut self, config: TranscribeConfig) -> Self {
+        self.config.transcribe = Some(config);
+        self
+    }
+
+    pub fn session(mut self, config: SessionConfig) -> Self {
+        self.config.session = Some(config);
+        self
+    }
+
+    pub fn embed_cache(mut self, config: EmbedCacheConfig) -> Self {
+        self.config.embed_cache = Some(config);
+        self
+    }
+
+    /// Process only these files instead of walking the source
+    pub fn files(mut self, files: Vec<PathBuf>) -> Self {
+        self.config.files = Some(files);
+        self
+    }
+
+    pub fn on_progress(mut self, callback: ProgressCallback) -> Self {
+        self.progress = Some(callback);
+        self
+    }
+
     pub fn build(self) -> SwarmOrchestrator {
-        SwarmOrchestrator::new(self.config)
+        let orchestrator = SwarmOrchestrator::new(self.config);
+        match self.progress {
+            Some(callback) => orchestrator.on_progress(DEFAULT_PROGRESS_INTERVAL, callback),
+            None => orchestrator,
+        }
     }
 
     pub fn run(self) -> Result<SwarmSummary> {
@@ -399,9 +874,15 @@ mod tests {
             .extensions(vec!["rs".to_string()])
             .max_retries(5)
             .silent_heal(true)
+            .workers(2, 3, 4)
+            .channel_size(64)
             .build();
 
         assert_eq!(orchestrator.config.heal.max_retries, 5);
+        assert_eq!(orchestrator.config.scan_workers, 2);
+        assert_eq!(orchestrator.config.chunk_workers, 3);
+        assert_eq!(orchestrator.config.embed.workers, 4);
+        assert_eq!(orchestrator.config.channel_size, 64);
         assert!(orchestrator.config.heal.silent_heal);
     }
 
@@ -432,6 +913,35 @@ mod tests {
         assert!(summary.embeddings_generated > 0);
     }
 
+    #[test]
+    fn test_session_config_resume_dir() {
+        let config = SessionConfig::resume(PathBuf::from("/runs/abc.json"));
+        assert_eq!(config.dir, PathBuf::from("/runs"));
+        assert_eq!(config.resume, Some(PathBuf::from("/runs/abc.json")));
+
+        let config = SessionConfig::resume(PathBuf::from("abc.json"));
+        assert_eq!(config.dir, PathBuf::from("."));
+    }
+
+    #[test]
+    fn test_session_resume_rejects_other_source() {
+        let dir = tempdir().unwrap();
+        let config = SessionConfig {
+            dir: dir.path().to_path_buf(),
+            ..Default::default()
+        };
+        let store = config.open(Path::new("/mnt/a"), None).unwrap();
+        let file = store.session_file().unwrap();
+
+        let resumed = SessionConfig::resume(file.clone())
+            .open(Path::new("/mnt/a"), None)
+            .unwrap();
+        assert_eq!(resumed.session_file(), Some(file.clone()));
+        assert!(SessionConfig::resume(file)
+            .open(Path::new("/mnt/b"), None)
+            .is_err());
+    }
+
     #[test]
     fn test_swarm_summary_serialization() {
         let summary = SwarmSummary {
@@ -443,6 +953,12 @@ mod tests {
             bytes_processed: 1024,
             errors_encountered: 2,
             errors_healed: 2,
+            chunks_flagged: 3,
+            sensitive_files: 1,
+            files_transcribed: 0,
+            files_skipped: 0,
+            embeddings_cached: 0,
+            session: None,
         };
 
         let json = serde_json::to_string(&summary).unwrap();
@@ -0,0 +1,213 @@
+//! Swarm Progress - Live per-agent counters, ETA and terminal dashboard
+//!
+//! The orchestrator samples [`SwarmStats`] on an interval and hands each
+//! snapshot to a progress callback:
+//! - Counts per agent: files scanned, chunks produced, embeddings done,
+//!   heals performed
+//! - ETA from the pace files finish the pipeline, once the scan has counted
+//!   them
+//!
+//! [`SwarmDashboard`] is the callback behind `swarm --dashboard`: a single
+//! live line on a [`PulseProgress`] bar.
+
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use super::agents::{AgentRole, SwarmStats, SwarmSummary};
+use crate::spinner::{format_duration, PulseProgress};
+
+/// Called with each progress snapshot while the swarm runs
+pub type ProgressCallback = Arc<dyn Fn(&SwarmProgress) + Send + Sync>;
+
+/// Default time between progress snapshots
+pub const DEFAULT_PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Point-in-time view of a running swarm
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SwarmProgress {
+    /// Time since the swarm started
+    pub elapsed: Duration,
+    /// Files the scan found to process; 0 until the walk is done
+    pub files_discovered: usize,
+    pub files_scanned: usize,
+    pub files_transcribed: usize,
+    /// Files whose chunks have all been through embedding and verification
+    pub files_completed: usize,
+    pub chunks_created: usize,
+    pub embeddings_generated: usize,
+    pub exports_completed: usize,
+    pub heals_performed: usize,
+    pub errors_encountered: usize,
+    pub bytes_processed: u64,
+    /// Set on the last snapshot, after every agent has exited
+    pub finished: bool,
+}
+
+impl SwarmProgress {
+    /// Snapshot the swarm's counters
+    pub fn from_stats(stats: &SwarmStats, elapsed: Duration) -> Self {
+        Self {
+            elapsed,
+            files_discovered: stats.files_discovered.load(Ordering::Relaxed),
+            files_scanned: stats.files_scanned.load(Ordering::Relaxed),
+            files_transcribed: stats.files_transcribed.load(Ordering::Relaxed),
+            files_completed: stats.files_completed.load(Ordering::Relaxed),
+            chunks_created: stats.chunks_created.load(Ordering::Relaxed),
+            embeddings_generated: stats.embeddings_generated.load(Ordering::Relaxed),
+            exports_completed: stats.exports_completed.load(Ordering::Relaxed),
+            heals_performed: stats.heals_performed.load(Ordering::Relaxed),
+            errors_encountered: stats.errors_encountered.load(Ordering::Relaxed),
+            bytes_processed: stats.bytes_processed.load(Ordering::Relaxed),
+            finished: false,
+        }
+    }
+
+    /// Share of discovered files completed, if the scan has counted them
+    pub fn fraction(&self) -> Option<f64> {
+        if self.files_discovered == 0 {
+            return None;
+        }
+        Some((self.files_completed as f64 / self.files_discovered as f64).min(1.0))
+    }
+
+    /// Time left at the pace files have completed so far
+    pub fn eta(&self) -> Option<Duration> {
+        if self.finished {
+            return Some(Duration::ZERO);
+        }
+        if self.files_completed == 0 || self.files_discovered <= self.files_completed {
+            return None;
+        }
+        let per_file = self.elapsed.as_secs_f64() / self.files_completed as f64;
+        let remaining = (self.files_discovered - self.files_completed) as f64;
+        Some(Duration::from_secs_f64(per_file * remaining))
+    }
+
+    /// One-line per-agent status, e.g. for a progress bar message
+    pub fn status_line(&self) -> String {
+        let mut line = format!(
+            "{} {} {} {} {} {}",
+            AgentRole::Scan.icon(),
+            self.files_scanned,
+            AgentRole::Chunk.icon(),
+            self.chunks_created,
+            AgentRole::Embed.icon(),
+            self.embeddings_generated,
+        );
+        if self.files_transcribed > 0 {
+            line.push_str(&format!(
+                " {} {}",
+                AgentRole::Transcribe.icon(),
+                self.files_transcribed
+            ));
+        }
+        if self.heals_performed > 0 {
+            line.push_str(&format!(
+                " {} {}",
+                AgentRole::Heal.icon(),
+                self.heals_performed
+            ));
+        }
+        match self.eta() {
+            Some(eta) if !self.finished => {
+                line.push_str(&format!(" | ETA {}", format_duration(eta)))
+            }
+            _ => {}
+        }
+        line
+    }
+}
+
+/// Live terminal view of a running swarm (`swarm --dashboard`)
+pub struct SwarmDashboard {
+    bar: Mutex<Option<PulseProgress>>,
+}
+
+impl SwarmDashboard {
+    /// Start the dashboard; the bar fills once the scan has counted files
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            bar: Mutex::new(Some(PulseProgress::new(0, "Scanning..."))),
+        })
+    }
+
+    /// Progress callback that draws on this dashboard
+    pub fn callback(self: &Arc<Self>) -> ProgressCallback {
+        let dashboard = Arc::clone(self);
+        Arc::new(move |progress| dashboard.update(progress))
+    }
+
+    /// Redraw from a progress snapshot
+    pub fn update(&self, progress: &SwarmProgress) {
+        if let Some(ref bar) = *self.bar.lock() {
+            bar.set_total(progress.files_discovered as u64);
+            bar.set(progress.files_completed as u64);
+            bar.set_message(&progress.status_line());
+        }
+    }
+
+    /// Replace the bar with the final counts
+    pub fn finish(&self, summary: &SwarmSummary) {
+        if let Some(bar) = self.bar.lock().take() {
+            bar.success(&format!(
+                "Swarm complete: {} files, {} chunks, {} embeddings",
+                summary.files_scanned, summary.chunks_created, summary.embeddings_generated
+            ));
+        }
+    }
+
+    /// Replace the bar with an error
+    pub fn fail(&self, error: &anyhow::Error) {
+        if let Some(bar) = self.bar.lock().take() {
+            bar.error(&format!("Swarm failed: {}", error));
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_eta() {
+        let mut progress = SwarmProgress {
+            elapsed: Duration::from_secs(10),
+            ..Default::default()
+        };
+        // Scan still walking
+        assert_eq!(progress.fraction(), None);
+        assert_eq!(progress.eta(), None);
+
+        progress.files_discovered = 100;
+        progress.files_completed = 25;
+        assert_eq!(progress.fraction(), Some(0.25));
+        assert_eq!(progress.eta(), Some(Duration::from_secs(30)));
+        assert!(progress.status_line().contains("ETA 30.0s"));
+
+        progress.finished = true;
+        assert_eq!(progress.eta(), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_progress_from_stats() {
+        let stats = SwarmStats::new();
+        stats.files_discovered.store(4, Ordering::Relaxed);
+        stats.chunks_created.store(12, Ordering::Relaxed);
+        stats.heals_performed.store(1, Ordering::Relaxed);
+
+        let progress = SwarmProgress::from_stats(&stats, Duration::from_secs(1));
+        assert_eq!(progress.files_discovered, 4);
+        assert_eq!(progress.chunks_created, 12);
+        let line = progress.status_line();
+        assert!(line.contains("12"));
+        assert!(line.contains(AgentRole::Heal.icon()));
+    }
+}
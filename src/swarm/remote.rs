@@ -0,0 +1,467 @@
+//! Remote Embedding Providers - OpenAI, Azure OpenAI and Gemini APIs
+//!
+//! For labs without a local GPU server, embeddings come from a hosted API:
+//! - API keys from the embedder config, or the provider's usual environment
+//!   variable (`OPENAI_API_KEY`, `AZURE_OPENAI_API_KEY`, `GEMINI_API_KEY`)
+//! - Texts are sent in batches, up to the provider's per-request limit
+//! - Rate limits (429) and server errors are retried after the response's
+//!   `Retry-After`, or with exponential backoff when it has none
+
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use tracing::warn;
+
+use super::embedder::{Embedder, EmbedderConfig};
+
+/// Default Azure OpenAI REST API version
+pub const DEFAULT_AZURE_API_VERSION: &str = "2024-02-01";
+
+/// Longest wait honoured from a `Retry-After` header
+const MAX_RETRY_WAIT: Duration = Duration::from_secs(300);
+
+/// Hosted embedding API
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoteProvider {
+    OpenAi,
+    AzureOpenAi,
+    Gemini,
+}
+
+impl RemoteProvider {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RemoteProvider::OpenAi => "openai",
+            RemoteProvider::AzureOpenAi => "azure-openai",
+            RemoteProvider::Gemini => "gemini",
+        }
+    }
+
+    /// Environment variables an API key is read from, in order
+    fn key_vars(&self) -> &'static [&'static str] {
+        match self {
+            RemoteProvider::OpenAi => &["OPENAI_API_KEY"],
+            RemoteProvider::AzureOpenAi => &["AZURE_OPENAI_API_KEY"],
+            RemoteProvider::Gemini => &["GEMINI_API_KEY", "GOOGLE_API_KEY"],
+        }
+    }
+
+    /// Base URL when none is configured (Azure's is per resource)
+    fn default_endpoint(&self) -> Option<&'static str> {
+        match self {
+            RemoteProvider::OpenAi => Some("https://api.openai.com/v1"),
+            RemoteProvider::AzureOpenAi => None,
+            RemoteProvider::Gemini => Some("https://generativelanguage.googleapis.com/v1beta"),
+        }
+    }
+
+    /// Most texts accepted in one request
+    fn max_batch(&self) -> usize {
+        match self {
+            RemoteProvider::OpenAi | RemoteProvider::AzureOpenAi => 2048,
+            RemoteProvider::Gemini => 100,
+        }
+    }
+}
+
+/// Embedder backed by a hosted embedding API
+pub struct RemoteEmbedder {
+    provider: RemoteProvider,
+    api_key: String,
+    endpoint: String,
+    /// Model name; the deployment name for Azure
+    model: String,
+    dimension: usize,
+    api_version: String,
+    batch_size: usize,
+    max_retries: u32,
+    timeout: Duration,
+}
+
+impl RemoteEmbedder {
+    /// Create from the embedder config, falling back to the environment
+    /// for the API key (and the Azure endpoint)
+    pub fn from_config(provider: RemoteProvider, config: &EmbedderConfig) -> Result<Self> {
+        let api_key = config
+            .api_key
+            .clone()
+            .or_else(|| {
+                provider
+                    .key_vars()
+                    .iter()
+                    .find_map(|var| std::env::var(var).ok())
+            })
+            .filter(|key| !key.is_empty())
+            .with_context(|| {
+                format!(
+                    "No API key for {}: set api_key or {}",
+                    provider.name(),
+                    provider.key_vars().join(" or ")
+                )
+            })?;
+
+        let endpoint = config
+            .api_endpoint
+            .clone()
+            .or_else(|| match provider {
+                RemoteProvider::AzureOpenAi => std::env::var("AZURE_OPENAI_ENDPOINT").ok(),
+                _ => None,
+            })
+            .or_else(|| provider.default_endpoint().map(str::to_string))
+            .with_context(|| {
+                format!(
+                    "No endpoint for {}: set api_endpoint or AZURE_OPENAI_ENDPOINT",
+                    provider.name()
+                )
+            })?;
+
+        Ok(Self {
+            provider,
+            api_key,
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            model: config.model.clone(),
+            dimension: config.dimension,
+            api_version: config.api_version.clone(),
+            batch_size: config.batch_size.clamp(1, provider.max_batch()),
+            max_retries: 5,
+            timeout: Duration::from_secs(60),
+        })
+    }
+
+    /// Attempts after the first for a rate-limited or failed request
+    pub fn with_max_retries(mut self, retries: u32) -> Self {
+        self.max_retries = retries;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// URL, auth header and JSON body of one embedding request
+    fn request(&self, texts: &[&str]) -> (String, (&'static str, String), Value) {
+        match self.provider {
+            RemoteProvider::OpenAi => {
+                let mut body = json!({ "model": self.model, "input": texts });
+                // Only the v3 models can be shortened to a dimension
+                if self.model.starts_with("text-embedding-3") {
+                    body["dimensions"] = json!(self.dimension);
+                }
+                (
+                    format!("{}/embeddings", self.endpoint),
+                    ("Authorization", format!("Bearer {}", self.api_key)),
+                    body,
+                )
+            }
+            RemoteProvider::AzureOpenAi => (
+                format!(
+                    "{}/openai/deployments/{}/embeddings?api-version={}",
+                    self.endpoint, self.model, self.api_version
+                ),
+                ("api-key", self.api_key.clone()),
+                json!({ "input": texts }),
+            ),
+            RemoteProvider::Gemini => {
+                let model = format!("models/{}", self.model);
+                let requests: Vec<Value> = texts
+                    .iter()
+                    .map(|text| {
+                        json!({
+                            "model": model,
+                            "content": { "parts": [{ "text": text }] },
+                            "outputDimensionality": self.dimension,
+                        })
+                    })
+                    .collect();
+                (
+                    format!("{}/{}:batchEmbedContents", self.endpoint, model),
+                    ("x-goog-api-key", self.api_key.clone()),
+                    json!({ "requests": requests }),
+                )
+            }
+        }
+    }
+
+    /// Vectors of a response, in the order the texts were sent
+    fn parse(&self, json: &Value, count: usize) -> Result<Vec<Vec<f32>>> {
+        let vectors: Vec<Vec<f32>> = match self.provider {
+            RemoteProvider::OpenAi | RemoteProvider::AzureOpenAi => {
+                let mut items: Vec<&Value> = json["data"]
+                    .as_array()
+                    .context("No data array in embedding response")?
+                    .iter()
+                    .collect();
+                items.sort_by_key(|item| item["index"].as_u64().unwrap_or(0));
+                items
+                    .iter()
+                    .map(|item| floats(&item["embedding"]))
+                    .collect::<Result<_>>()?
+            }
+            RemoteProvider::Gemini => json["embeddings"]
+                .as_array()
+                .context("No embeddings array in Gemini response")?
+                .iter()
+                .map(|item| floats(&item["values"]))
+                .collect::<Result<_>>()?,
+        };
+
+        if vectors.len() != count {
+            anyhow::bail!(
+                "{} returned {} embeddings for {} texts",
+                self.provider.name(),
+                vectors.len(),
+                count
+            );
+        }
+        if let Some(v) = vectors.iter().find(|v| v.len() != self.dimension) {
+            anyhow::bail!(
+                "{} model {} returned {}-dimensional embeddings, expected {}",
+                self.provider.name(),
+                self.model,
+                v.len(),
+                self.dimension
+            );
+        }
+        Ok(vectors)
+    }
+
+    /// Send one request, retrying rate limits and server errors
+    fn post(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        let (url, (header, value), body) = self.request(texts);
+        let mut attempt = 0;
+        loop {
+            let result = ureq::post(&url)
+                .timeout(self.timeout)
+                .set("Content-Type", "application/json")
+                .set(header, &value)
+                .send_json(&body);
+
+            let (retry_after, error) = match result {
+                Ok(response) => {
+                    let json: Value = response
+                        .into_json()
+                        .context("Failed to parse embedding response")?;
+                    return self.parse(&json, texts.len());
+                }
+                Err(ureq::Error::Status(code, response)) if is_retryable(code) => {
+                    let wait = retry_after(&response);
+                    let message = response.into_string().unwrap_or_default();
+                    (wait, format!("HTTP {}: {}", code, message.trim()))
+                }
+                Err(ureq::Error::Status(code, response)) => {
+                    let message = response.into_string().unwrap_or_default();
+                    anyhow::bail!(
+                        "{} embedding request failed (HTTP {}): {}",
+                        self.provider.name(),
+                        code,
+                        message.trim()
+                    );
+                }
+                Err(e) => (None, e.to_string()),
+            };
+
+            if attempt >= self.max_retries {
+                anyhow::bail!(
+                    "{} embedding request failed after {} attempts: {}",
+                    self.provider.name(),
+                    attempt + 1,
+                    error
+                );
+            }
+            let wait = retry_after
+                .unwrap_or_else(|| backoff(attempt))
+                .min(MAX_RETRY_WAIT);
+            warn!(
+                "{} embedding request failed ({}), retrying in {:.1}s",
+                self.provider.name(),
+                error,
+                wait.as_secs_f64()
+            );
+            std::thread::sleep(wait);
+            attempt += 1;
+        }
+    }
+}
+
+impl Embedder for RemoteEmbedder {
+    fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        self.post(&[text])?
+            .pop()
+            .context("Empty embedding response")
+    }
+
+    fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        let mut vectors = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(self.batch_size) {
+            vectors.extend(self.post(batch)?);
+        }
+        Ok(vectors)
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn backend(&self) -> &str {
+        self.provider.name()
+    }
+
+    fn is_gpu(&self) -> bool {
+        false
+    }
+}
+
+fn floats(value: &Value) -> Result<Vec<f32>> {
+    Ok(value
+        .as_array()
+        .context("Missing embedding in response")?
+        .iter()
+        .filter_map(|v| v.as_f64().map(|f| f as f32))
+        .collect())
+}
+
+fn is_retryable(status: u16) -> bool {
+    matches!(status, 408 | 429 | 500 | 502 | 503 | 504)
+}
+
+/// Exponential backoff from 1s, for responses without `Retry-After`
+fn backoff(attempt: u32) -> Duration {
+    Duration::from_secs(1 << attempt.min(6))
+}
+
+/// Wait requested by a response: Azure's `retry-after-ms`, or
+/// `Retry-After` in seconds or as an HTTP date
+fn retry_after(response: &ureq::Response) -> Option<Duration> {
+    parse_retry_after(
+        response.header("retry-after-ms"),
+        response.header("retry-after"),
+    )
+}
+
+fn parse_retry_after(millis: Option<&str>, value: Option<&str>) -> Option<Duration> {
+    if let Some(ms) = millis.and_then(|ms| ms.trim().parse::<u64>().ok()) {
+        return Some(Duration::from_millis(ms));
+    }
+    let value = value?.trim();
+    if let Ok(secs) = value.parse::<f64>() {
+        return (secs >= 0.0).then(|| Duration::from_secs_f64(secs));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = date.with_timezone(&chrono::Utc) - chrono::Utc::now();
+    Some(wait.to_std().unwrap_or(Duration::ZERO))
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn embedder(provider: RemoteProvider, model: &str, endpoint: Option<&str>) -> RemoteEmbedder {
+        let config = EmbedderConfig {
+            model: model.to_string(),
+            dimension: 3,
+            api_key: Some("sk-test".to_string()),
+            api_endpoint: endpoint.map(str::to_string),
+            ..Default::default()
+        };
+        RemoteEmbedder::from_config(provider, &config).unwrap()
+    }
+
+    #[test]
+    fn test_openai_request_and_response() {
+        let e = embedder(RemoteProvider::OpenAi, "text-embedding-3-small", None);
+        let (url, header, body) = e.request(&["a", "b"]);
+        assert_eq!(url, "https://api.openai.com/v1/embeddings");
+        assert_eq!(header, ("Authorization", "Bearer sk-test".to_string()));
+        assert_eq!(body["input"], json!(["a", "b"]));
+        assert_eq!(body["dimensions"], json!(3));
+
+        // Items may come back out of order
+        let response = json!({ "data": [
+            { "index": 1, "embedding": [0.0, 1.0, 0.0] },
+            { "index": 0, "embedding": [1.0, 0.0, 0.0] },
+        ]});
+        let vectors = e.parse(&response, 2).unwrap();
+        assert_eq!(vectors[0], vec![1.0, 0.0, 0.0]);
+        assert_eq!(vectors[1], vec![0.0, 1.0, 0.0]);
+
+        // Wrong dimension or count is an error, not a silent mismatch
+        assert!(e.parse(&response, 3).is_err());
+        let short = json!({ "data": [{ "index": 0, "embedding": [1.0] }] });
+        assert!(e.parse(&short, 1).is_err());
+    }
+
+    #[test]
+    fn test_azure_request() {
+        let e = embedder(
+            RemoteProvider::AzureOpenAi,
+            "embed-deploy",
+            Some("https://lab.openai.azure.com/"),
+        );
+        let (url, header, body) = e.request(&["a"]);
+        assert_eq!(
+            url,
+            format!(
+                "https://lab.openai.azure.com/openai/deployments/embed-deploy/embeddings?api-version={}",
+                DEFAULT_AZURE_API_VERSION
+            )
+        );
+        assert_eq!(header.0, "api-key");
+        assert!(body.get("model").is_none());
+    }
+
+    #[test]
+    fn test_gemini_request_and_response() {
+        let e = embedder(RemoteProvider::Gemini, "text-embedding-004", None);
+        let (url, header, body) = e.request(&["a", "b"]);
+        assert_eq!(
+            url,
+            "https://generativelanguage.googleapis.com/v1beta/models/text-embedding-004:batchEmbedContents"
+        );
+        assert_eq!(header.0, "x-goog-api-key");
+        assert_eq!(body["requests"][1]["content"]["parts"][0]["text"], "b");
+
+        let response = json!({ "embeddings": [
+            { "values": [1.0, 0.0, 0.0] },
+            { "values": [0.0, 0.0, 1.0] },
+        ]});
+        assert_eq!(e.parse(&response, 2).unwrap()[1], vec![0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_missing_azure_endpoint() {
+        let config = EmbedderConfig {
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        };
+        if std::env::var("AZURE_OPENAI_ENDPOINT").is_err() {
+            assert!(RemoteEmbedder::from_config(RemoteProvider::AzureOpenAi, &config).is_err());
+        }
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(
+            parse_retry_after(None, Some("7")),
+            Some(Duration::from_secs(7))
+        );
+        assert_eq!(
+            parse_retry_after(Some("1500"), Some("7")),
+            Some(Duration::from_millis(1500))
+        );
+        // A date in the past means retry now
+        assert_eq!(
+            parse_retry_after(None, Some("Wed, 21 Oct 2015 07:28:00 GMT")),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after(None, Some("soon")), None);
+        assert_eq!(parse_retry_after(None, None), None);
+        assert_eq!(backoff(0), Duration::from_secs(1));
+        assert_eq!(backoff(3), Duration::from_secs(8));
+    }
+}
@@ -1,10 +1,12 @@
 //! Swarm Searcher - Hybrid Keyword + Vector Search
 //!
 //! Combines multiple search strategies:
-//! - Exact keyword matching (regex)
-//! - Fuzzy text matching
+//! - BM25 keyword ranking over an inverted index, with fuzzy term matching
 //! - Vector similarity (cosine)
-//! - Hybrid scoring with configurable weights
+//! - Hybrid ranking: the two lists fused by weighted reciprocal rank fusion,
+//!   which needs no calibration between BM25 and cosine scores
+//! - An optional [`Reranker`] (e.g. a cross-encoder) rescoring the top hits
+//! - Exact regex matching
 
 use std::collections::HashMap;
 use std::path::PathBuf;
@@ -25,9 +27,9 @@ use super::embedder::{cosine_similarity, Embedder};
 /// Configuration for hybrid search
 #[derive(Debug, Clone)]
 pub struct SearchConfig {
-    /// Weight for keyword matches (0.0 - 1.0)
+    /// Weight of the keyword ranking in hybrid fusion
     pub keyword_weight: f32,
-    /// Weight for vector similarity (0.0 - 1.0)
+    /// Weight of the vector ranking in hybrid fusion
     pub vector_weight: f32,
     /// Minimum score to include in results
     pub min_score: f32,
@@ -41,6 +43,15 @@ pub struct SearchConfig {
     pub recency_boost: bool,
     /// Case insensitive keyword search
     pub case_insensitive: bool,
+    /// BM25 term frequency saturation
+    pub bm25_k1: f32,
+    /// BM25 document length normalization (0 = none, 1 = full)
+    pub bm25_b: f32,
+    /// Reciprocal rank fusion constant; higher flattens the gap between
+    /// top ranks
+    pub rrf_k: f32,
+    /// Top hits handed to the reranker
+    pub rerank_candidates: usize,
 }
 
 impl Default for SearchConfig {
@@ -54,10 +65,72 @@ impl Default for SearchConfig {
             fuzzy_threshold: 0.7,
             recency_boost: false,
             case_insensitive: true,
+            bm25_k1: 1.2,
+            bm25_b: 0.75,
+            rrf_k: 60.0,
+            rerank_candidates: 50,
         }
     }
 }
 
+/// Which rankings a search uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchMode {
+    /// BM25 only; the query is not embedded
+    Keyword,
+    /// Vector similarity only
+    Vector,
+    /// Keyword and vector rankings fused
+    #[default]
+    Hybrid,
+}
+
+/// Per-query search options
+#[derive(Debug, Clone)]
+pub struct SearchOptions {
+    pub mode: SearchMode,
+    /// Rescore the top hits with the index's reranker, if it has one
+    pub rerank: bool,
+    /// Results to return (default: the index's `max_results`)
+    pub limit: Option<usize>,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            mode: SearchMode::Hybrid,
+            rerank: true,
+            limit: None,
+        }
+    }
+}
+
+impl SearchOptions {
+    pub fn mode(mode: SearchMode) -> Self {
+        Self {
+            mode,
+            ..Default::default()
+        }
+    }
+}
+
+/// Rescores search hits against the query, e.g. with a cross-encoder
+///
+/// Returns one score per document, higher is better. Closures of the same
+/// shape implement it.
+pub trait Reranker: Send + Sync {
+    fn rerank(&self, query: &str, documents: &[&str]) -> Result<Vec<f32>>;
+}
+
+impl<F> Reranker for F
+where
+    F: Fn(&str, &[&str]) -> Result<Vec<f32>> + Send + Sync,
+{
+    fn rerank(&self, query: &str, documents: &[&str]) -> Result<Vec<f32>> {
+        self(query, documents)
+    }
+}
+
 // ============================================================================
 // Search Index
 // ============================================================================
@@ -94,14 +167,72 @@ pub struct SearchResult {
     pub snippet: String,
     /// Overall score
     pub score: f32,
-    /// Keyword match score
+    /// Keyword (BM25) score, relative to the best keyword hit
     pub keyword_score: f32,
     /// Vector similarity score
     pub vector_score: f32,
+    /// Reranker score, if the hit was reranked
+    pub rerank_score: Option<f32>,
     /// Matched terms (for highlighting)
     pub matched_terms: Vec<String>,
 }
 
+/// Inverted index with the statistics BM25 needs
+#[derive(Default)]
+struct Bm25Index {
+    /// Term -> (document, occurrences in it)
+    postings: HashMap<String, Vec<(usize, u32)>>,
+    /// Terms per document
+    doc_lengths: Vec<u32>,
+    total_length: u64,
+}
+
+impl Bm25Index {
+    fn add(&mut self, doc_idx: usize, terms: Vec<String>) {
+        if self.doc_lengths.len() <= doc_idx {
+            self.doc_lengths.resize(doc_idx + 1, 0);
+        }
+        self.doc_lengths[doc_idx] = terms.len() as u32;
+        self.total_length += terms.len() as u64;
+
+        let mut counts: HashMap<String, u32> = HashMap::new();
+        for term in terms {
+            *counts.entry(term).or_default() += 1;
+        }
+        for (term, count) in counts {
+            self.postings
+                .entry(term)
+                .or_default()
+                .push((doc_idx, count));
+        }
+    }
+
+    /// BM25 contribution of `term` to each document containing it
+    fn score_term(
+        &self,
+        term: &str,
+        k1: f32,
+        b: f32,
+        weight: f32,
+        scores: &mut HashMap<usize, f32>,
+    ) {
+        let Some(postings) = self.postings.get(term) else {
+            return;
+        };
+        let n = self.doc_lengths.len() as f32;
+        let df = postings.len() as f32;
+        let idf = (1.0 + (n - df + 0.5) / (df + 0.5)).ln();
+        let avg_length = (self.total_length as f32 / n.max(1.0)).max(1.0);
+
+        for &(doc_idx, tf) in postings {
+            let tf = tf as f32;
+            let length = self.doc_lengths[doc_idx] as f32;
+            let norm = k1 * (1.0 - b + b * length / avg_length);
+            *scores.entry(doc_idx).or_insert(0.0) += weight * idf * tf * (k1 + 1.0) / (tf + norm);
+        }
+    }
+}
+
 /// In-memory search index
 pub struct SearchIndex {
     /// All indexed documents
@@ -109,7 +240,9 @@ pub struct SearchIndex {
     /// Embedder for query vectorization
     embedder: Arc<dyn Embedder>,
     /// Inverted index for keyword search
-    inverted_index: Arc<RwLock<HashMap<String, Vec<usize>>>>,
+    inverted_index: Arc<RwLock<Bm25Index>>,
+    /// Optional second-stage scorer
+    reranker: Option<Arc<dyn Reranker>>,
     /// Configuration
     config: SearchConfig,
 }
@@ -120,95 +253,94 @@ impl SearchIndex {
         Self {
             documents: Arc::new(RwLock::new(Vec::new())),
             embedder,
-            inverted_index: Arc::new(RwLock::new(HashMap::new())),
+            inverted_index: Arc::new(RwLock::new(Bm25Index::default())),
+            reranker: None,
             config,
         }
     }
 
+    /// Rescore the top hits of each search with `reranker`
+    pub fn with_reranker(mut self, reranker: Arc<dyn Reranker>) -> Self {
+        self.reranker = Some(reranker);
+        self
+    }
+
     /// Add a document to the index
     pub fn add(&self, doc: IndexedDocument) {
-        let doc_idx = {
-            let mut docs = self.documents.write();
-            let idx = docs.len();
-            docs.push(doc.clone());
-            idx
-        };
-
-        // Update inverted index
-        self.update_inverted_index(&doc.content, doc_idx);
+        let terms = tokenize(&doc.content);
+        // Both locks held, so searches never see a document without its terms
+        let mut docs = self.documents.write();
+        let mut index = self.inverted_index.write();
+        index.add(docs.len(), terms);
+        docs.push(doc);
     }
 
     /// Add multiple documents
     pub fn add_batch(&self, docs: Vec<IndexedDocument>) {
-        let start_idx = {
-            let mut stored = self.documents.write();
-            let start = stored.len();
-            stored.extend(docs.clone());
-            start
-        };
-
-        // Update inverted index in parallel
-        let updates: Vec<(usize, Vec<String>)> = docs
-            .par_iter()
-            .enumerate()
-            .map(|(i, doc)| (start_idx + i, tokenize(&doc.content)))
-            .collect();
+        // Tokenize in parallel
+        let terms: Vec<Vec<String>> = docs.par_iter().map(|doc| tokenize(&doc.content)).collect();
 
+        let mut stored = self.documents.write();
         let mut index = self.inverted_index.write();
-        for (doc_idx, terms) in updates {
-            for term in terms {
-                index.entry(term).or_default().push(doc_idx);
-            }
+        let start_idx = stored.len();
+        for (i, terms) in terms.into_iter().enumerate() {
+            index.add(start_idx + i, terms);
         }
+        stored.extend(docs);
     }
 
     /// Search with hybrid ranking
     pub fn search(&self, query: &str) -> Result<Vec<SearchResult>> {
-        info!("Searching: {:?}", query);
+        self.search_with(query, &SearchOptions::default())
+    }
 
-        // Get query embedding
-        let query_embedding = self.embedder.embed(query)?;
+    /// Search with the given mode and options
+    pub fn search_with(&self, query: &str, options: &SearchOptions) -> Result<Vec<SearchResult>> {
+        info!("Searching ({:?}): {:?}", options.mode, query);
 
-        // Get keyword matches
-        let keyword_results = self.keyword_search(query);
+        let keyword_results = match options.mode {
+            SearchMode::Vector => HashMap::new(),
+            _ => self.keyword_search(query),
+        };
+        let vector_results = match options.mode {
+            SearchMode::Keyword => HashMap::new(),
+            _ => {
+                let query_embedding = self.embedder.embed(query)?;
+                self.vector_search(&query_embedding)
+            }
+        };
 
-        // Get vector matches
-        let vector_results = self.vector_search(&query_embedding);
+        let mut results = self.merge_results(query, options.mode, keyword_results, vector_results);
 
-        // Merge and rank results
-        let merged = self.merge_results(query, keyword_results, vector_results);
+        if options.rerank {
+            if let Some(ref reranker) = self.reranker {
+                self.rerank(query, reranker.as_ref(), &mut results)?;
+            }
+        }
 
-        debug!("Found {} results", merged.len());
-        Ok(merged)
+        results.truncate(options.limit.unwrap_or(self.config.max_results));
+        debug!("Found {} results", results.len());
+        Ok(results)
     }
 
-    /// Keyword-only search
+    /// BM25 keyword search, scores relative to the best hit
     fn keyword_search(&self, query: &str) -> HashMap<usize, f32> {
         let mut scores: HashMap<usize, f32> = HashMap::new();
-        let query_terms = tokenize(query);
+        let mut query_terms = tokenize(query);
+        query_terms.sort_unstable();
+        query_terms.dedup();
         let index = self.inverted_index.read();
-        let total_docs = self.documents.read().len() as f32;
+        let (k1, b) = (self.config.bm25_k1, self.config.bm25_b);
 
         for term in &query_terms {
-            if let Some(doc_indices) = index.get(term) {
-                // TF-IDF scoring
-                let idf = (total_docs / doc_indices.len() as f32).ln() + 1.0;
+            index.score_term(term, k1, b, 1.0, &mut scores);
 
-                for &doc_idx in doc_indices {
-                    let tf = 1.0; // Simplified - could count occurrences
-                    *scores.entry(doc_idx).or_insert(0.0) += tf * idf;
-                }
-            }
-
-            // Fuzzy matching if enabled
+            // Fuzzy matching if enabled: near-miss terms count at a discount
             if self.config.fuzzy {
-                for (indexed_term, doc_indices) in index.iter() {
+                for indexed_term in index.postings.keys() {
                     let similarity = fuzzy_similarity(term, indexed_term);
                     if similarity >= self.config.fuzzy_threshold && similarity < 1.0 {
-                        let idf = (total_docs / doc_indices.len() as f32).ln() + 1.0;
-                        for &doc_idx in doc_indices {
-                            *scores.entry(doc_idx).or_insert(0.0) += similarity * idf * 0.5;
-                        }
+                        index.score_term(indexed_term, k1, b, similarity * 0.5, &mut scores);
                     }
                 }
             }
@@ -240,45 +372,61 @@ impl SearchIndex {
             .collect()
     }
 
-    /// Merge keyword and vector results with hybrid scoring
+    /// Weighted reciprocal rank fusion, scaled so a document ranked first in
+    /// both lists scores 1.0
+    fn fuse(
+        &self,
+        keyword_scores: &HashMap<usize, f32>,
+        vector_scores: &HashMap<usize, f32>,
+    ) -> HashMap<usize, f32> {
+        let k = self.config.rrf_k;
+        let (kw_weight, vec_weight) = (self.config.keyword_weight, self.config.vector_weight);
+        let best = (kw_weight + vec_weight) / (k + 1.0);
+
+        let mut fused: HashMap<usize, f32> = HashMap::new();
+        for (scores, weight) in [(keyword_scores, kw_weight), (vector_scores, vec_weight)] {
+            for (rank, idx) in ranked(scores).into_iter().enumerate() {
+                *fused.entry(idx).or_insert(0.0) += weight / (k + rank as f32 + 1.0);
+            }
+        }
+        if best > 0.0 {
+            for score in fused.values_mut() {
+                *score /= best;
+            }
+        }
+        fused
+    }
+
+    /// Merge keyword and vector results, scored by `mode`
     fn merge_results(
         &self,
         query: &str,
+        mode: SearchMode,
         keyword_scores: HashMap<usize, f32>,
         vector_scores: HashMap<usize, f32>,
     ) -> Vec<SearchResult> {
         let docs = self.documents.read();
         let query_terms: Vec<String> = tokenize(query);
 
-        // Combine all document indices
-        let mut all_indices: Vec<usize> = keyword_scores
-            .keys()
-            .chain(vector_scores.keys())
-            .cloned()
-            .collect();
-        all_indices.sort_unstable();
-        all_indices.dedup();
+        let scores = match mode {
+            SearchMode::Keyword => keyword_scores.clone(),
+            SearchMode::Vector => vector_scores.clone(),
+            SearchMode::Hybrid => self.fuse(&keyword_scores, &vector_scores),
+        };
 
-        // Calculate hybrid scores
-        let mut results: Vec<SearchResult> = all_indices
+        let mut results: Vec<SearchResult> = scores
             .par_iter()
-            .filter_map(|&idx| {
-                let doc = docs.get(idx)?;
-
-                let kw_score = keyword_scores.get(&idx).cloned().unwrap_or(0.0);
-                let vec_score = vector_scores.get(&idx).cloned().unwrap_or(0.0);
-
-                let hybrid_score =
-                    self.config.keyword_weight * kw_score + self.config.vector_weight * vec_score;
-
-                if hybrid_score < self.config.min_score {
+            .filter_map(|(&idx, &score)| {
+                if score < self.config.min_score {
                     return None;
                 }
+                let doc = docs.get(idx)?;
 
                 // Find matched terms for highlighting
+                let content = doc.content.to_lowercase();
                 let matched: Vec<String> = query_terms
                     .iter()
-                    .filter(|t| doc.content.to_lowercase().contains(&t.to_lowercase()))
+                    .filter(|t| content.contains(t.as_str()))
                     .cloned()
                     .collect();
 
@@ -290,21 +438,15 @@ impl SearchIndex {
                     source: doc.source.clone(),
                     chunk_id: doc.chunk_id,
                     snippet,
-                    score: hybrid_score,
-                    keyword_score: kw_score,
-                    vector_score: vec_score,
+                    score,
+                    keyword_score: keyword_scores.get(&idx).cloned().unwrap_or(0.0),
+                    vector_score: vector_scores.get(&idx).cloned().unwrap_or(0.0),
+                    rerank_score: None,
                     matched_terms: matched,
                 })
             })
             .collect();
 
-        // Sort by score descending
-        results.sort_by(|a, b| {
-            b.score
-                .partial_cmp(&a.score)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
-
         // Apply recency boost if enabled
         if self.config.recency_boost {
             // Boost more recent documents slightly
@@ -316,26 +458,63 @@ impl SearchIndex {
                     result.score *= 1.0 + 0.1 * recency_factor;
                 }
             }
-            results.sort_by(|a, b| {
-                b.score
-                    .partial_cmp(&a.score)
-                    .unwrap_or(std::cmp::Ordering::Equal)
-            });
         }
 
-        // Limit results
-        results.truncate(self.config.max_results);
+        sort_by_score(&mut results);
         results
     }
 
-    /// Update the inverted index for a document
-    fn update_inverted_index(&self, content: &str, doc_idx: usize) {
-        let terms = tokenize(content);
-        let mut index = self.inverted_index.write();
+    /// Reorder the top candidates by reranker score; the rest keep their
+    /// order after them
+    fn rerank(
+        &self,
+        query: &str,
+        reranker: &dyn Reranker,
+        results: &mut [SearchResult],
+    ) -> Result<()> {
+        let count = results.len().min(self.config.rerank_candidates);
+        if count == 0 {
+            return Ok(());
+        }
+        let scores = {
+            let docs = self.documents.read();
+            let contents: HashMap<&str, &str> = docs
+                .iter()
+                .map(|d| (d.id.as_str(), d.content.as_str()))
+                .collect();
+            let texts: Vec<&str> = results[..count]
+                .iter()
+                .map(|r| {
+                    contents
+                        .get(r.id.as_str())
+                        .copied()
+                        .unwrap_or(r.snippet.as_str())
+                })
+                .collect();
+            reranker.rerank(query, &texts)?
+        };
+        if scores.len() != count {
+            anyhow::bail!(
+                "Reranker returned {} scores for {} documents",
+                scores.len(),
+                count
+            );
+        }
 
-        for term in terms {
-            index.entry(term).or_default().push(doc_idx);
+        for (result, score) in results[..count].iter_mut().zip(scores) {
+            result.rerank_score = Some(score);
         }
+        results[..count].sort_by(|a, b| {
+            b.rerank_score
+                .partial_cmp(&a.rerank_score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        Ok(())
+    }
+
+    /// Indexed document by ID, with its full content
+    pub fn document(&self, id: &str) -> Option<IndexedDocument> {
+        self.documents.read().iter().find(|d| d.id == id).cloned()
     }
 
     /// Get index statistics
@@ -345,15 +524,17 @@ impl SearchIndex {
 
         IndexStats {
             document_count: docs.len(),
-            term_count: index.len(),
+            term_count: index.postings.len(),
             total_embeddings: docs.iter().filter(|d| !d.embedding.is_empty()).count(),
         }
     }
 
     /// Clear the index
     pub fn clear(&self) {
-        self.documents.write().clear();
-        self.inverted_index.write().clear();
+        let mut docs = self.documents.write();
+        let mut index = self.inverted_index.write();
+        docs.clear();
+        *index = Bm25Index::default();
     }
 }
 
@@ -394,6 +575,7 @@ pub fn regex_search(pattern: &str, documents: &[IndexedDocument]) -> Result<Vec<
                 score: score.min(1.0),
                 keyword_score: score.min(1.0),
                 vector_score: 0.0,
+                rerank_score: None,
                 matched_terms,
             })
         })
@@ -415,6 +597,26 @@ fn tokenize(text: &str) -> Vec<String> {
         .collect()
 }
 
+/// Document indices by descending score (ties by index, so ranks are stable)
+fn ranked(scores: &HashMap<usize, f32>) -> Vec<usize> {
+    let mut indices: Vec<(usize, f32)> = scores.iter().map(|(&i, &s)| (i, s)).collect();
+    indices.sort_by(|a, b| {
+        b.1.partial_cmp(&a.1)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(a.0.cmp(&b.0))
+    });
+    indices.into_iter().map(|(i, _)| i).collect()
+}
+
+/// Sort results by score descending
+fn sort_by_score(results: &mut [SearchResult]) {
+    results.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+}
+
 /// Calculate fuzzy similarity between two strings (Jaro-Winkler simplified)
 fn fuzzy_similarity(a: &str, b: &str) -> f32 {
     if a == b {
@@ -569,6 +771,144 @@ mod tests {
         assert_eq!(results[0].id, "doc1");
     }
 
+    /// Embeds every query to the same vector
+    struct FixedEmbedder(Vec<f32>);
+
+    impl Embedder for FixedEmbedder {
+        fn embed(&self, _text: &str) -> Result<Vec<f32>> {
+            Ok(self.0.clone())
+        }
+
+        fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+            Ok(vec![self.0.clone(); texts.len()])
+        }
+
+        fn dimension(&self) -> usize {
+            self.0.len()
+        }
+
+        fn backend(&self) -> &str {
+            "fixed"
+        }
+
+        fn is_gpu(&self) -> bool {
+            false
+        }
+    }
+
+    fn doc(id: &str, content: &str, embedding: Vec<f32>) -> IndexedDocument {
+        IndexedDocument {
+            id: id.to_string(),
+            source: PathBuf::from(format!("/test/{}.txt", id)),
+            chunk_id: 0,
+            content: content.to_string(),
+            embedding,
+            metadata: HashMap::new(),
+            indexed_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_bm25_ranking() {
+        let index = SearchIndex::new(
+            Arc::new(Blake3Embedder::new(4)),
+            SearchConfig {
+                fuzzy: false,
+                min_score: 0.0,
+                ..Default::default()
+            },
+        );
+        index.add_batch(vec![
+            doc(
+                "once",
+                "invoice for the month of march and other notes here",
+                vec![],
+            ),
+            doc("twice", "invoice invoice overdue", vec![]),
+            doc("none", "meeting notes", vec![]),
+        ]);
+
+        let results = index
+            .search_with("invoice", &SearchOptions::mode(SearchMode::Keyword))
+            .unwrap();
+        // More occurrences in a shorter document rank higher
+        let ids: Vec<&str> = results.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, ["twice", "once"]);
+        assert_eq!(results[0].keyword_score, 1.0);
+        assert_eq!(results[0].vector_score, 0.0);
+    }
+
+    #[test]
+    fn test_hybrid_fusion_and_modes() {
+        let index = SearchIndex::new(
+            Arc::new(FixedEmbedder(vec![1.0, 0.0])),
+            SearchConfig {
+                fuzzy: false,
+                min_score: 0.0,
+                ..Default::default()
+            },
+        );
+        index.add_batch(vec![
+            // Keyword hit, far from the query vector
+            doc("keyword", "quarterly audit report", vec![0.1, 1.0]),
+            // Close to the query vector, no keyword
+            doc("vector", "numbers for the board", vec![1.0, 0.05]),
+            // Both
+            doc("both", "audit of the numbers", vec![1.0, 0.1]),
+        ]);
+
+        let hybrid = index.search("audit").unwrap();
+        assert_eq!(hybrid[0].id, "both");
+
+        let vector = index
+            .search_with("audit", &SearchOptions::mode(SearchMode::Vector))
+            .unwrap();
+        assert_eq!(vector[0].id, "vector");
+        assert!(vector.iter().all(|r| r.keyword_score == 0.0));
+
+        let keyword = index
+            .search_with("audit", &SearchOptions::mode(SearchMode::Keyword))
+            .unwrap();
+        assert_eq!(keyword.len(), 2);
+    }
+
+    #[test]
+    fn test_reranker_reorders_top_hits() {
+        // Prefers shorter documents, whatever the first-stage order
+        let reranker = |_: &str, docs: &[&str]| -> Result<Vec<f32>> {
+            Ok(docs.iter().map(|d| -(d.len() as f32)).collect())
+        };
+        let index = SearchIndex::new(
+            Arc::new(Blake3Embedder::new(4)),
+            SearchConfig {
+                fuzzy: false,
+                min_score: 0.0,
+                ..Default::default()
+            },
+        )
+        .with_reranker(Arc::new(reranker));
+        index.add_batch(vec![
+            doc(
+                "long",
+                "contract contract contract signed by both parties in full",
+                vec![],
+            ),
+            doc("short", "contract draft", vec![]),
+        ]);
+
+        let keyword = SearchOptions::mode(SearchMode::Keyword);
+        let results = index.search_with("contract", &keyword).unwrap();
+        assert_eq!(results[0].id, "short");
+        assert!(results[0].rerank_score.is_some());
+
+        let plain = SearchOptions {
+            rerank: false,
+            ..keyword
+        };
+        let results = index.search_with("contract", &plain).unwrap();
+        assert_eq!(results[0].rerank_score, None);
+    }
+
     #[test]
     fn test_create_snippet() {
         let content = "This is a long document with some important keywords embedded in it.";
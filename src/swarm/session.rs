@@ -160,6 +160,24 @@ impl SwarmSession {
         }
     }
 
+    /// Read a session file (JSON, or bincode with a `.session` extension)
+    pub fn read(path: &Path) -> Result<Self> {
+        let is_binary = path.extension().map(|e| e == "session").unwrap_or(false);
+
+        if is_binary {
+            let file = File::open(path)
+                .with_context(|| format!("Failed to open session {}", path.display()))?;
+            let reader = BufReader::new(file);
+            bincode::deserialize_from(reader)
+                .with_context(|| format!("Failed to deserialize session {}", path.display()))
+        } else {
+            let content = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read session {}", path.display()))?;
+            serde_json::from_str(&content)
+                .with_context(|| format!("Failed to parse session {}", path.display()))
+        }
+    }
+
     /// Check if a file has been processed
     pub fn is_processed(&self, path: &Path) -> bool {
         self.processed_files.contains(path)
@@ -208,6 +226,8 @@ impl SwarmSession {
     pub fn complete(&mut self, summary: SwarmSummary) {
         self.status = SessionStatus::Completed;
         self.stats = summary.into();
+        // Includes files done before a resume, which the summary does not
+        self.stats.files_processed = self.processed_files.len();
         self.updated_at = chrono::Utc::now();
 
         self.events.push(SessionEvent::Completed {
@@ -275,26 +295,34 @@ impl SessionStore {
     /// Resume an existing session
     pub fn resume(&self, session_id: &str) -> Result<SwarmSession> {
         let session = self.load(session_id)?;
+        Ok(self.activate(session))
+    }
 
+    /// Resume the session saved in `path`; it is saved back into this
+    /// store's directory
+    pub fn resume_file(&self, path: &Path) -> Result<SwarmSession> {
+        let session = SwarmSession::read(path)?;
+        Ok(self.activate(session))
+    }
+
+    fn activate(&self, mut session: SwarmSession) -> SwarmSession {
         info!(
             "Resuming session {} ({} files processed, {} pending)",
-            session_id,
+            session.session_id,
             session.processed_files.len(),
-            session.stats.files_discovered - session.processed_files.len()
+            session
+                .stats
+                .files_discovered
+                .saturating_sub(session.processed_files.len())
         );
 
-        let mut current = self.current.write();
-        *current = Some(session.clone());
-
-        // Add resume event
-        if let Some(ref mut s) = *current {
-            s.events.push(SessionEvent::Resumed {
-                timestamp: chrono::Utc::now(),
-            });
-            s.status = SessionStatus::Active;
-        }
+        session.events.push(SessionEvent::Resumed {
+            timestamp: chrono::Utc::now(),
+        });
+        session.status = SessionStatus::Active;
 
-        Ok(session)
+        *self.current.write() = Some(session.clone());
+        session
     }
 
     /// Get current session (read-only)
@@ -302,6 +330,14 @@ impl SessionStore {
         self.current.read().clone()
     }
 
+    /// File the current session is saved to
+    pub fn session_file(&self) -> Option<PathBuf> {
+        self.current
+            .read()
+            .as_ref()
+            .map(|s| self.session_path(&s.session_id))
+    }
+
     /// Update current session with callback
     pub fn update<F>(&self, f: F) -> Result<()>
     where
@@ -382,8 +418,9 @@ impl SessionStore {
             let entry = entry?;
             let path = entry.path();
 
-            if path.extension().map(|e| e == "session").unwrap_or(false) {
-                if let Ok(session) = self.load_from_path(&path) {
+            let ext = path.extension().and_then(|e| e.to_str());
+            if matches!(ext, Some("session" | "json")) {
+                if let Ok(session) = SwarmSession::read(&path) {
                     sessions.push(session);
                 }
             }
@@ -440,19 +477,6 @@ impl SessionStore {
         let ext = if self.use_binary { "session" } else { "json" };
         self.base_dir.join(format!("{}.{}", session_id, ext))
     }
-
-    fn load_from_path(&self, path: &Path) -> Result<SwarmSession> {
-        let is_binary = path.extension().map(|e| e == "session").unwrap_or(false);
-
-        if is_binary {
-            let file = File::open(path)?;
-            let reader = BufReader::new(file);
-            Ok(bincode::deserialize_from(reader)?)
-        } else {
-            let content = fs::read_to_string(path)?;
-            Ok(serde_json::from_str(&content)?)
-        }
-    }
 }
 
 // ============================================================================
@@ -568,6 +592,36 @@ mod tests {
         assert!(resumed.is_processed(&PathBuf::from("/test/file1.txt")));
     }
 
+    #[test]
+    fn test_resume_file() {
+        let dir = tempdir().unwrap();
+        let store = SessionStore::new(dir.path().to_path_buf()).use_json();
+        store.start(PathBuf::from("/test/source"), None).unwrap();
+        store
+            .update(|s| s.mark_processed(PathBuf::from("/test/source/a.txt"), 2, 2))
+            .unwrap();
+        store.save().unwrap();
+        let file = store.session_file().unwrap();
+        assert!(file.exists());
+
+        let store2 = SessionStore::new(dir.path().to_path_buf()).use_json();
+        let resumed = store2.resume_file(&file).unwrap();
+        assert!(resumed.is_processed(Path::new("/test/source/a.txt")));
+        assert!(matches!(
+            resumed.events.last(),
+            Some(SessionEvent::Resumed { .. })
+        ));
+        // Saved back to the same file
+        assert_eq!(store2.session_file(), Some(file));
+        assert_eq!(
+            store2
+                .find_resumable(Path::new("/test/source"))
+                .unwrap()
+                .map(|s| s.session_id),
+            Some(resumed.session_id)
+        );
+    }
+
     #[test]
     fn test_pending_files() {
         let session = SwarmSession::new(PathBuf::from("/test"), None);
@@ -0,0 +1,298 @@
+//! Syntax-Aware Code Chunking - tree-sitter definitions as chunk boundaries
+//!
+//! CodeChunker's brace and blank-line heuristics lose track of Python and
+//! deeply nested code. With a grammar compiled in (`--features syntax-rust`,
+//! `syntax-python`, ... or `syntax` for all of them) the source is parsed
+//! instead:
+//! - Top-level definitions are the units chunks are built from
+//! - A definition too large for one chunk is split at its own members
+//!   (methods of a class or impl, statements of a body)
+//! - Consecutive small definitions are packed together up to the chunk size
+//!
+//! Chunks cover the file without gaps or overlap; comments and blank lines
+//! before a definition go with it.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use tree_sitter::{Node, Parser};
+
+use super::chunker::{ceil_char_boundary, floor_char_boundary, Chunk, ChunkConfig, MediaType};
+
+/// Grammar for a source file, if one is compiled in
+fn grammar(path: &Path) -> Option<tree_sitter::Language> {
+    let ext = path.extension()?.to_str()?.to_lowercase();
+    match ext.as_str() {
+        #[cfg(feature = "syntax-rust")]
+        "rs" => Some(tree_sitter_rust::LANGUAGE.into()),
+        #[cfg(feature = "syntax-python")]
+        "py" | "pyw" => Some(tree_sitter_python::LANGUAGE.into()),
+        #[cfg(feature = "syntax-javascript")]
+        "js" | "jsx" | "mjs" | "cjs" => Some(tree_sitter_javascript::LANGUAGE.into()),
+        #[cfg(feature = "syntax-typescript")]
+        "ts" => Some(tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into()),
+        #[cfg(feature = "syntax-typescript")]
+        "tsx" => Some(tree_sitter_typescript::LANGUAGE_TSX.into()),
+        #[cfg(feature = "syntax-go")]
+        "go" => Some(tree_sitter_go::LANGUAGE.into()),
+        #[cfg(feature = "syntax-java")]
+        "java" => Some(tree_sitter_java::LANGUAGE.into()),
+        #[cfg(feature = "syntax-c")]
+        "c" | "h" => Some(tree_sitter_c::LANGUAGE.into()),
+        #[cfg(feature = "syntax-cpp")]
+        "cpp" | "hpp" | "cc" | "cxx" => Some(tree_sitter_cpp::LANGUAGE.into()),
+        _ => None,
+    }
+}
+
+/// A run of source ending at a definition (or member) boundary
+struct Unit {
+    start: usize,
+    end: usize,
+    kind: &'static str,
+    /// Name of the definition, or the type of an impl block
+    name: Option<String>,
+}
+
+impl Unit {
+    fn new(node: Node, start: usize, end: usize, source: &str) -> Self {
+        let kind = node.kind();
+        let is_definition = ["definition", "declaration", "_item", "_specifier"]
+            .iter()
+            .any(|suffix| kind.ends_with(suffix));
+        let name = if is_definition {
+            node.child_by_field_name("name")
+                .or_else(|| node.child_by_field_name("type"))
+                // Python decorators wrap the function or class
+                .or_else(|| {
+                    node.child_by_field_name("definition")
+                        .and_then(|d| d.child_by_field_name("name"))
+                })
+                .and_then(|n| n.utf8_text(source.as_bytes()).ok())
+                .map(str::to_string)
+        } else {
+            None
+        };
+        Self {
+            start,
+            end,
+            kind,
+            name,
+        }
+    }
+}
+
+/// Chunk `content` at definition boundaries; `None` if no grammar for the
+/// file is compiled in
+pub(super) fn chunk_definitions(
+    path: &Path,
+    content: &str,
+    config: &ChunkConfig,
+    language: &str,
+) -> Result<Option<Vec<Chunk>>> {
+    let Some(grammar) = grammar(path) else {
+        return Ok(None);
+    };
+    let mut parser = Parser::new();
+    parser
+        .set_language(&grammar)
+        .with_context(|| format!("Incompatible {} grammar", language))?;
+    let Some(tree) = parser.parse(content, None) else {
+        return Ok(None);
+    };
+
+    let mut units = Vec::new();
+    collect_units(
+        tree.root_node(),
+        0,
+        content.len(),
+        content,
+        config.chunk_size,
+        &mut units,
+    );
+    Ok(Some(pack(path, content, &units, config, language)))
+}
+
+/// Split `[start, end)` at the children of `node`, descending into children
+/// larger than `limit`
+fn collect_units(
+    node: Node,
+    start: usize,
+    end: usize,
+    source: &str,
+    limit: usize,
+    units: &mut Vec<Unit>,
+) {
+    let mut cursor = node.walk();
+    let children: Vec<Node> = node.named_children(&mut cursor).collect();
+    if children.is_empty() {
+        units.push(Unit::new(node, start, end, source));
+        return;
+    }
+
+    let mut pos = start;
+    let last = children.len() - 1;
+    for (i, child) in children.into_iter().enumerate() {
+        // The last child takes the rest, e.g. a class's closing brace
+        let child_end = if i == last {
+            end
+        } else {
+            // A comment goes with the definition after it
+            if child.kind().contains("comment") {
+                continue;
+            }
+            child.end_byte().clamp(pos, end)
+        };
+        if child_end - pos > limit && child.named_child_count() > 0 {
+            collect_units(child, pos, child_end, source, limit, units);
+        } else {
+            units.push(Unit::new(child, pos, child_end, source));
+        }
+        pos = child_end;
+    }
+}
+
+/// Pack consecutive units into chunks of up to `chunk_size` bytes
+fn pack(
+    path: &Path,
+    content: &str,
+    units: &[Unit],
+    config: &ChunkConfig,
+    language: &str,
+) -> Vec<Chunk> {
+    let mut chunks = Vec::new();
+    let mut push = |start: usize, end: usize, units: &[Unit]| {
+        if start == end {
+            return;
+        }
+        let mut chunk = Chunk::new(
+            path.to_path_buf(),
+            chunks.len(),
+            0,
+            content[start..end].to_string(),
+            start,
+            end,
+            MediaType::Code,
+        )
+        .with_metadata("language", language)
+        .with_metadata("syntax", "tree-sitter");
+        if let [unit] = units {
+            chunk = chunk.with_metadata("kind", unit.kind);
+        }
+        let symbols: Vec<&str> = units.iter().filter_map(|u| u.name.as_deref()).collect();
+        if !symbols.is_empty() {
+            chunk = chunk.with_metadata("symbol", symbols.join(", "));
+        }
+        chunks.push(chunk);
+    };
+
+    let mut i = 0;
+    while i < units.len() {
+        let start = units[i].start;
+        let mut j = i + 1;
+        while j < units.len() && units[j].end - start <= config.chunk_size {
+            j += 1;
+        }
+        let end = units[j - 1].end;
+
+        if end - start > config.max_chunk_size {
+            // A single leaf with nothing to split at, e.g. a huge literal
+            let mut pos = start;
+            while pos < end {
+                let mut next = floor_char_boundary(content, (pos + config.max_chunk_size).min(end));
+                if next <= pos {
+                    next = ceil_char_boundary(content, pos + 1);
+                }
+                push(pos, next, &[]);
+                pos = next;
+            }
+        } else {
+            push(start, end, &units[i..j]);
+        }
+        i = j;
+    }
+
+    let total = chunks.len();
+    for chunk in &mut chunks {
+        chunk.total = total;
+    }
+    chunks
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Chunks must tile the source exactly
+    fn assert_covers(chunks: &[Chunk], content: &str) {
+        let joined: String = chunks.iter().map(|c| c.content.as_str()).collect();
+        assert_eq!(joined, content);
+        for chunk in chunks {
+            assert_eq!(&content[chunk.byte_start..chunk.byte_end], chunk.content);
+        }
+    }
+
+    #[cfg(feature = "syntax-python")]
+    #[test]
+    fn test_python_chunks_at_definitions() {
+        let content = "import os\n\n\ndef load(path):\n    if path:\n\n        return open(path).read()\n\n\n# Keeps the cache\nclass Store:\n    def get(self, key):\n        return self.items[key]\n";
+        let config = ChunkConfig {
+            chunk_size: 100,
+            ..Default::default()
+        };
+
+        let chunks = chunk_definitions(Path::new("store.py"), content, &config, "python")
+            .unwrap()
+            .unwrap();
+
+        assert_covers(&chunks, content);
+        let load = chunks
+            .iter()
+            .find(|c| c.metadata.get("symbol").map(String::as_str) == Some("load"))
+            .expect("load() is not split");
+        // The blank line inside the function does not split it
+        assert!(load.content.contains("return open(path).read()"));
+        assert!(chunks
+            .iter()
+            .any(|c| c.content.contains("# Keeps the cache\nclass Store")));
+    }
+
+    #[cfg(feature = "syntax-rust")]
+    #[test]
+    fn test_rust_splits_large_impl_at_methods() {
+        let content = "struct Store;\n\nimpl Store {\n    fn get(&self) -> u32 {\n        { { 1 } }\n    }\n\n    fn set(&mut self, value: u32) {\n        let _ = value;\n    }\n}\n";
+        let config = ChunkConfig {
+            chunk_size: 70,
+            ..Default::default()
+        };
+
+        let chunks = chunk_definitions(Path::new("store.rs"), content, &config, "rust")
+            .unwrap()
+            .unwrap();
+
+        assert_covers(&chunks, content);
+        assert!(chunks.len() >= 3);
+        let symbols: Vec<&str> = chunks
+            .iter()
+            .filter_map(|c| c.metadata.get("symbol").map(String::as_str))
+            .collect();
+        assert!(symbols.contains(&"get"));
+        assert!(symbols.contains(&"set"));
+    }
+
+    #[test]
+    fn test_no_grammar_for_unknown_extension() {
+        let chunks = chunk_definitions(
+            Path::new("notes.txt"),
+            "hello",
+            &ChunkConfig::default(),
+            "unknown",
+        )
+        .unwrap();
+        assert!(chunks.is_none());
+    }
+}
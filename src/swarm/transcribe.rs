@@ -0,0 +1,754 @@
+//! Swarm Transcriber - Speech-to-text for recovered audio and video
+//!
+//! The TranscribeAgent sits between ScanAgent and ChunkAgent. Voice memos,
+//! recordings and videos are decoded to 16 kHz mono PCM and run through
+//! Whisper; each transcribed segment is sent on as a text chunk, so the
+//! spoken content is embedded and classified like any document. Everything
+//! else passes through to ChunkAgent unchanged.
+//!
+//! Whisper runs through candle and needs a build with `--features gpu`
+//! (it falls back to the CPU when no CUDA device is available).
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use crossbeam_channel::{Receiver, Sender};
+use serde::{Deserialize, Serialize};
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as AudioError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use tracing::info;
+
+use super::agents::{AgentRole, SwarmMessage, SwarmStats};
+
+/// Sample rate Whisper expects its input at
+pub const WHISPER_SAMPLE_RATE: u32 = 16_000;
+
+/// Default Whisper model on HuggingFace Hub
+pub const DEFAULT_WHISPER_MODEL: &str = "openai/whisper-base";
+
+/// Check if a file is audio or video the transcriber can decode
+pub fn is_transcribable(path: &Path) -> bool {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    matches!(
+        ext.as_str(),
+        "wav" | "mp3" | "flac" | "m4a" | "aac" | "mp4" | "m4v" | "mov"
+    )
+}
+
+// ============================================================================
+// Transcripts
+// ============================================================================
+
+/// A span of transcribed speech
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptSegment {
+    /// Offset into the recording, in seconds
+    pub start: f32,
+    pub end: f32,
+    pub text: String,
+}
+
+/// Speech-to-text over 16 kHz mono PCM
+pub trait Transcriber: Send + Sync {
+    /// Transcribe `pcm`, returning segments in order
+    fn transcribe(&self, pcm: &[f32]) -> Result<Vec<TranscriptSegment>>;
+
+    /// Backend name for logging
+    fn backend(&self) -> &str;
+}
+
+/// Transcript of one recording
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileTranscript {
+    pub source: PathBuf,
+    /// Length of the decoded audio, in seconds
+    pub duration: f32,
+    pub segments: Vec<TranscriptSegment>,
+}
+
+impl FileTranscript {
+    /// Segment texts joined into one string
+    pub fn text(&self) -> String {
+        self.segments
+            .iter()
+            .map(|s| s.text.as_str())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Transcripts of every recording the swarm processed, by path
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TranscriptReport {
+    pub files: Vec<FileTranscript>,
+}
+
+impl TranscriptReport {
+    pub fn write_json(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write transcripts: {}", path.display()))
+    }
+}
+
+// ============================================================================
+// Audio decoding
+// ============================================================================
+
+/// Decode the first audio track of `path` to mono PCM at
+/// [`WHISPER_SAMPLE_RATE`]. Truncated files (common after carving) yield
+/// whatever decoded before the damage.
+pub fn decode_pcm(path: &Path) -> Result<Vec<f32>> {
+    let file = std::fs::File::open(path)?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+    let mut format = symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )?
+        .format;
+
+    // Videos list their video track too, which symphonia has no decoder for
+    let (track_id, mut decoder) = format
+        .tracks()
+        .iter()
+        .filter(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .find_map(|t| {
+            symphonia::default::get_codecs()
+                .make(&t.codec_params, &DecoderOptions::default())
+                .ok()
+                .map(|d| (t.id, d))
+        })
+        .context("No audio track")?;
+
+    let mut mono = Vec::new();
+    let mut rate = 0;
+    let mut samples: Option<SampleBuffer<f32>> = None;
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            // End of stream, or the file is cut short
+            Err(AudioError::IoError(_)) | Err(AudioError::ResetRequired) => break,
+            Err(e) => return Err(e.into()),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // Skip damaged packets and keep going
+            Err(AudioError::DecodeError(_)) => continue,
+            Err(AudioError::IoError(_)) => break,
+            Err(e) => return Err(e.into()),
+        };
+
+        rate = decoded.spec().rate;
+        let channels = decoded.spec().channels.count().max(1);
+        let buf = match &mut samples {
+            Some(buf) if buf.capacity() >= decoded.capacity() * channels => buf,
+            _ => samples.insert(SampleBuffer::new(
+                decoded.capacity() as u64,
+                *decoded.spec(),
+            )),
+        };
+        buf.copy_interleaved_ref(decoded);
+        mono.extend(
+            buf.samples()
+                .chunks(channels)
+                .map(|frame| frame.iter().sum::<f32>() / channels as f32),
+        );
+    }
+
+    Ok(resample(&mono, rate, WHISPER_SAMPLE_RATE))
+}
+
+/// Linear resampling; plenty for speech going into a 16 kHz model
+fn resample(samples: &[f32], from: u32, to: u32) -> Vec<f32> {
+    if from == to || from == 0 || samples.is_empty() {
+        return samples.to_vec();
+    }
+    let step = from as f64 / to as f64;
+    let len = (samples.len() as f64 / step).floor() as usize;
+    (0..len)
+        .map(|i| {
+            let pos = i as f64 * step;
+            let idx = pos as usize;
+            let frac = (pos - idx as f64) as f32;
+            let a = samples[idx];
+            let b = samples.get(idx + 1).copied().unwrap_or(a);
+            a + (b - a) * frac
+        })
+        .collect()
+}
+
+// ============================================================================
+// Whisper
+// ============================================================================
+
+/// Whisper speech-to-text through candle
+///
+/// When compiled with `--features gpu`, this runs OpenAI Whisper models from
+/// HuggingFace Hub on CUDA, or the CPU if CUDA is unavailable. Without the
+/// feature, construction fails.
+#[cfg(feature = "gpu")]
+pub struct WhisperTranscriber {
+    model: parking_lot::Mutex<candle_transformers::models::whisper::model::Whisper>,
+    config: candle_transformers::models::whisper::Config,
+    tokenizer: tokenizers::Tokenizer,
+    device: candle_core::Device,
+    mel_filters: Vec<f32>,
+    suppress: candle_core::Tensor,
+    /// Prompt: start of transcript, language (multilingual models only),
+    /// transcribe task and no timestamps
+    prompt: Vec<u32>,
+    eot_token: u32,
+    no_speech_token: Option<u32>,
+}
+
+#[cfg(feature = "gpu")]
+impl WhisperTranscriber {
+    /// Segments the model thinks are most likely silence are dropped
+    const NO_SPEECH_THRESHOLD: f32 = 0.6;
+
+    /// Load a model from HuggingFace Hub
+    ///
+    /// `language` is an ISO 639-1 code such as `en`; English-only models
+    /// (`*.en`) ignore it.
+    pub fn new(model_id: &str, language: Option<&str>, prefer_gpu: bool) -> Result<Self> {
+        use candle_core::{Device, Tensor};
+        use candle_nn::VarBuilder;
+        use candle_transformers::models::whisper::{self as m, model::Whisper, Config};
+
+        info!("Loading Whisper transcriber: {}", model_id);
+
+        let device = if prefer_gpu {
+            match Device::cuda_if_available(0) {
+                Ok(d) => d,
+                Err(e) => {
+                    tracing::warn!("CUDA not available ({}), using CPU", e);
+                    Device::Cpu
+                }
+            }
+        } else {
+            Device::Cpu
+        };
+
+        let api = hf_hub::api::sync::Api::new().context("Failed to init HF Hub API")?;
+        let repo = api.model(model_id.to_string());
+
+        let config_path = repo
+            .get("config.json")
+            .context("Failed to download config.json")?;
+        let config: Config = serde_json::from_str(&std::fs::read_to_string(&config_path)?)?;
+
+        let tokenizer_path = repo
+            .get("tokenizer.json")
+            .context("Failed to download tokenizer.json")?;
+        let tokenizer = tokenizers::Tokenizer::from_file(&tokenizer_path)
+            .map_err(|e| anyhow::anyhow!("Tokenizer load error: {}", e))?;
+
+        let weights_path = repo
+            .get("model.safetensors")
+            .context("Failed to download model weights")?;
+        let vb =
+            unsafe { VarBuilder::from_mmaped_safetensors(&[weights_path], m::DTYPE, &device)? };
+        let model = Whisper::load(&vb, config.clone())?;
+
+        let token = |t: &str| {
+            tokenizer
+                .token_to_id(t)
+                .with_context(|| format!("Tokenizer has no {} token", t))
+        };
+        let mut prompt = vec![token(m::SOT_TOKEN)?];
+        if let Some(lang) = language {
+            // English-only models have no language tokens
+            if let Some(id) = tokenizer.token_to_id(&format!("<|{}|>", lang)) {
+                prompt.push(id);
+            }
+        }
+        prompt.push(token(m::TRANSCRIBE_TOKEN)?);
+        prompt.push(token(m::NO_TIMESTAMPS_TOKEN)?);
+        let eot_token = token(m::EOT_TOKEN)?;
+        let no_speech_token = m::NO_SPEECH_TOKENS
+            .iter()
+            .find_map(|t| tokenizer.token_to_id(t));
+
+        let suppress: Vec<f32> = (0..config.vocab_size as u32)
+            .map(|i| {
+                if config.suppress_tokens.contains(&i) {
+                    f32::NEG_INFINITY
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        let suppress = Tensor::new(suppress.as_slice(), &device)?;
+        let mel_filters = mel_filters(config.num_mel_bins);
+
+        info!(
+            "Whisper transcriber loaded: {} (device={:?})",
+            model_id, device
+        );
+
+        Ok(Self {
+            model: parking_lot::Mutex::new(model),
+            config,
+            tokenizer,
+            device,
+            mel_filters,
+            suppress,
+            prompt,
+            eot_token,
+            no_speech_token,
+        })
+    }
+
+    /// Greedy decode of one mel window; `None` if it holds no speech
+    fn decode_window(
+        &self,
+        model: &mut candle_transformers::models::whisper::model::Whisper,
+        mel: &candle_core::Tensor,
+    ) -> Result<Option<String>> {
+        use candle_core::{IndexOp, Tensor};
+        use candle_nn::ops::softmax;
+
+        let audio_features = model.encoder.forward(mel, true)?;
+        let mut tokens = self.prompt.clone();
+        let max_tokens = self.config.max_target_positions / 2;
+
+        for i in 0..max_tokens {
+            let tokens_t = Tensor::new(tokens.as_slice(), &self.device)?.unsqueeze(0)?;
+            let ys = model.decoder.forward(&tokens_t, &audio_features, i == 0)?;
+
+            // The start-of-transcript position predicts "no speech"
+            if i == 0 {
+                if let Some(no_speech) = self.no_speech_token {
+                    let logits = model.decoder.final_linear(&ys.i(..1)?)?.i(0)?.i(0)?;
+                    let p = softmax(&logits, 0)?
+                        .i(no_speech as usize)?
+                        .to_scalar::<f32>()?;
+                    if p > Self::NO_SPEECH_THRESHOLD {
+                        return Ok(None);
+                    }
+                }
+            }
+
+            let (_, seq_len, _) = ys.dims3()?;
+            let logits = model
+                .decoder
+                .final_linear(&ys.i((..1, seq_len - 1..))?)?
+                .i(0)?
+                .i(0)?
+                .broadcast_add(&self.suppress)?;
+            let logits: Vec<f32> = logits.to_vec1()?;
+            let next = logits
+                .iter()
+                .enumerate()
+                .max_by(|a, b| a.1.total_cmp(b.1))
+                .map(|(id, _)| id as u32)
+                .unwrap_or(self.eot_token);
+            if next == self.eot_token {
+                break;
+            }
+            tokens.push(next);
+        }
+
+        let text = self
+            .tokenizer
+            .decode(&tokens[self.prompt.len()..], true)
+            .map_err(|e| anyhow::anyhow!("Detokenize error: {}", e))?;
+        let text = text.trim();
+        Ok((!text.is_empty()).then(|| text.to_string()))
+    }
+}
+
+#[cfg(feature = "gpu")]
+impl Transcriber for WhisperTranscriber {
+    fn transcribe(&self, pcm: &[f32]) -> Result<Vec<TranscriptSegment>> {
+        use candle_core::Tensor;
+        use candle_transformers::models::whisper::{self as m, audio};
+
+        let mel = audio::pcm_to_mel(&self.config, pcm, &self.mel_filters);
+        let bins = self.config.num_mel_bins;
+        let frames = mel.len() / bins;
+        let mel = Tensor::from_vec(mel, (1, bins, frames), &self.device)?;
+
+        let mut model = self.model.lock();
+        let mut segments = Vec::new();
+        let seconds = |frame: usize| (frame * m::HOP_LENGTH) as f32 / m::SAMPLE_RATE as f32;
+        let mut seek = 0;
+        // Whisper works on 30 second windows
+        while seek < frames {
+            let size = usize::min(frames - seek, m::N_FRAMES);
+            let window = mel.narrow(2, seek, size)?;
+            if let Some(text) = self.decode_window(&mut model, &window)? {
+                tracing::debug!(
+                    "{:.1}s-{:.1}s: {}",
+                    seconds(seek),
+                    seconds(seek + size),
+                    text
+                );
+                segments.push(TranscriptSegment {
+                    start: seconds(seek),
+                    end: seconds(seek + size),
+                    text,
+                });
+            }
+            seek += size;
+        }
+
+        Ok(segments)
+    }
+
+    fn backend(&self) -> &str {
+        if matches!(self.device, candle_core::Device::Cpu) {
+            "whisper-cpu"
+        } else {
+            "whisper-gpu"
+        }
+    }
+}
+
+/// Slaney mel filterbank over Whisper's 400-point FFT (what librosa's
+/// `filters.mel` returns), `n_mels` rows of 201 weights
+#[cfg(feature = "gpu")]
+fn mel_filters(n_mels: usize) -> Vec<f32> {
+    use candle_transformers::models::whisper as m;
+
+    let n_freqs = m::N_FFT / 2 + 1;
+    let hz_to_mel = |hz: f64| {
+        if hz < 1000.0 {
+            hz * 3.0 / 200.0
+        } else {
+            15.0 + (hz / 1000.0).ln() * 27.0 / 6.4f64.ln()
+        }
+    };
+    let mel_to_hz = |mel: f64| {
+        if mel < 15.0 {
+            mel * 200.0 / 3.0
+        } else {
+            1000.0 * ((mel - 15.0) * 6.4f64.ln() / 27.0).exp()
+        }
+    };
+
+    let top = hz_to_mel(m::SAMPLE_RATE as f64 / 2.0);
+    let points: Vec<f64> = (0..n_mels + 2)
+        .map(|i| mel_to_hz(top * i as f64 / (n_mels + 1) as f64))
+        .collect();
+
+    let mut filters = vec![0.0f32; n_mels * n_freqs];
+    for mel in 0..n_mels {
+        let (lower, center, upper) = (points[mel], points[mel + 1], points[mel + 2]);
+        let norm = 2.0 / (upper - lower);
+        for f in 0..n_freqs {
+            let hz = f as f64 * m::SAMPLE_RATE as f64 / m::N_FFT as f64;
+            let weight = ((hz - lower) / (center - lower)).min((upper - hz) / (upper - center));
+            filters[mel * n_freqs + f] = (weight.max(0.0) * norm) as f32;
+        }
+    }
+    filters
+}
+
+// Stand-in when the gpu feature is not enabled
+#[cfg(not(feature = "gpu"))]
+pub struct WhisperTranscriber {
+    _private: (),
+}
+
+#[cfg(not(feature = "gpu"))]
+impl WhisperTranscriber {
+    pub fn new(model_id: &str, _language: Option<&str>, _prefer_gpu: bool) -> Result<Self> {
+        anyhow::bail!(
+            "Transcribing with {} needs a build with --features gpu",
+            model_id
+        )
+    }
+}
+
+#[cfg(not(feature = "gpu"))]
+impl Transcriber for WhisperTranscriber {
+    fn transcribe(&self, _pcm: &[f32]) -> Result<Vec<TranscriptSegment>> {
+        anyhow::bail!("Transcription needs a build with --features gpu")
+    }
+
+    fn backend(&self) -> &str {
+        "whisper-unavailable"
+    }
+}
+
+// ============================================================================
+// TranscribeAgent - Speech-to-text between scanning and chunking
+// ============================================================================
+
+/// Transcribes audio and video into text chunks; passes other files on to
+/// ChunkAgent
+pub struct TranscribeAgent {
+    input: Receiver<SwarmMessage>,
+    passthrough: Sender<SwarmMessage>,
+    chunks: Sender<SwarmMessage>,
+    heal_tx: Sender<SwarmMessage>,
+    stats: Arc<SwarmStats>,
+    transcriber: Arc<dyn Transcriber>,
+    report_path: Option<PathBuf>,
+}
+
+impl TranscribeAgent {
+    /// `passthrough` feeds ChunkAgent; transcript chunks go to `chunks`,
+    /// alongside ChunkAgent's output
+    pub fn new(
+        input: Receiver<SwarmMessage>,
+        passthrough: Sender<SwarmMessage>,
+        chunks: Sender<SwarmMessage>,
+        heal_tx: Sender<SwarmMessage>,
+        stats: Arc<SwarmStats>,
+        transcriber: Arc<dyn Transcriber>,
+    ) -> Self {
+        Self {
+            input,
+            passthrough,
+            chunks,
+            heal_tx,
+            stats,
+            transcriber,
+            report_path: None,
+        }
+    }
+
+    pub fn with_report(mut self, path: PathBuf) -> Self {
+        self.report_path = Some(path);
+        self
+    }
+
+    /// Run the transcribe agent; returns the transcripts once scanning ends
+    pub fn run(self) -> Result<TranscriptReport> {
+        info!(
+            "{} TranscribeAgent starting ({})",
+            AgentRole::Transcribe.icon(),
+            self.transcriber.backend()
+        );
+
+        let mut report = TranscriptReport::default();
+        while let Ok(msg) = self.input.recv() {
+            match msg {
+                SwarmMessage::FilePath(ref path) if is_transcribable(path) => {
+                    match self.process_file(path) {
+                        Ok(transcript) => {
+                            self.stats.files_transcribed.fetch_add(1, Ordering::Relaxed);
+                            report.files.push(transcript);
+                        }
+                        Err(e) => {
+                            self.stats
+                                .errors_encountered
+                                .fetch_add(1, Ordering::Relaxed);
+                            let _ = self.heal_tx.send(SwarmMessage::Failure {
+                                agent: AgentRole::Transcribe,
+                                source: path.clone(),
+                                error: e.to_string(),
+                                retries_left: 3,
+                            });
+                        }
+                    }
+                }
+                SwarmMessage::Done => {
+                    // Transcript chunks are all sent before ChunkAgent can
+                    // pass Done on behind them
+                    let _ = self.passthrough.send(SwarmMessage::Done);
+                    break;
+                }
+                msg => self.passthrough.send(msg)?,
+            }
+        }
+
+        report.files.sort_by(|a, b| a.source.cmp(&b.source));
+        if let Some(ref path) = self.report_path {
+            report.write_json(path)?;
+            info!("Wrote transcripts to: {}", path.display());
+        }
+
+        info!(
+            "{} TranscribeAgent complete: {} recordings",
+            AgentRole::Transcribe.icon(),
+            report.files.len()
+        );
+
+        Ok(report)
+    }
+
+    fn process_file(&self, path: &Path) -> Result<FileTranscript> {
+        let pcm = decode_pcm(path)
+            .with_context(|| format!("Failed to decode audio: {}", path.display()))?;
+        let segments = self
+            .transcriber
+            .transcribe(&pcm)
+            .with_context(|| format!("Failed to transcribe: {}", path.display()))?;
+
+        // One chunk per segment, so search hits land on a point in time
+        for (chunk_id, segment) in segments.iter().enumerate() {
+            self.stats.chunks_created.fetch_add(1, Ordering::Relaxed);
+            self.chunks.send(SwarmMessage::Chunk {
+                source: path.to_path_buf(),
+                chunk_id,
+                data: segment.text.as_bytes().to_vec(),
+            })?;
+        }
+        self.chunks.send(SwarmMessage::FileChunked {
+            source: path.to_path_buf(),
+            chunks: segments.len(),
+        })?;
+
+        Ok(FileTranscript {
+            source: path.to_path_buf(),
+            duration: pcm.len() as f32 / WHISPER_SAMPLE_RATE as f32,
+            segments,
+        })
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossbeam_channel::bounded;
+    use tempfile::tempdir;
+
+    /// Reports how many seconds of audio it was given
+    struct LengthTranscriber;
+
+    impl Transcriber for LengthTranscriber {
+        fn transcribe(&self, pcm: &[f32]) -> Result<Vec<TranscriptSegment>> {
+            let seconds = pcm.len() as f32 / WHISPER_SAMPLE_RATE as f32;
+            Ok(vec![TranscriptSegment {
+                start: 0.0,
+                end: seconds,
+                text: format!("{:.0} seconds", seconds),
+            }])
+        }
+
+        fn backend(&self) -> &str {
+            "test"
+        }
+    }
+
+    /// Minimal 16-bit PCM WAV
+    fn write_wav(path: &Path, rate: u32, channels: u16, samples: &[i16]) {
+        let data_len = (samples.len() * 2) as u32;
+        let mut wav = Vec::new();
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&channels.to_le_bytes());
+        wav.extend_from_slice(&rate.to_le_bytes());
+        wav.extend_from_slice(&(rate * channels as u32 * 2).to_le_bytes());
+        wav.extend_from_slice(&(channels * 2).to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&data_len.to_le_bytes());
+        for s in samples {
+            wav.extend_from_slice(&s.to_le_bytes());
+        }
+        std::fs::write(path, wav).unwrap();
+    }
+
+    #[test]
+    fn test_is_transcribable() {
+        assert!(is_transcribable(Path::new("memo.M4A")));
+        assert!(is_transcribable(Path::new("clip.mov")));
+        assert!(is_transcribable(Path::new("call.wav")));
+        assert!(!is_transcribable(Path::new("notes.txt")));
+        assert!(!is_transcribable(Path::new("noext")));
+    }
+
+    #[test]
+    fn test_resample() {
+        let samples: Vec<f32> = (0..48).map(|i| i as f32).collect();
+        let out = resample(&samples, 48_000, 16_000);
+        assert_eq!(out.len(), 16);
+        assert_eq!(out[1], 3.0);
+        assert_eq!(resample(&samples, 16_000, 16_000), samples);
+    }
+
+    #[test]
+    fn test_decode_pcm_downmixes_and_resamples() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("stereo.wav");
+        // One second of stereo at 32 kHz, left and right cancelling out
+        let samples: Vec<i16> = (0..32_000).flat_map(|_| [8000i16, -8000]).collect();
+        write_wav(&path, 32_000, 2, &samples);
+
+        let pcm = decode_pcm(&path).unwrap();
+        assert_eq!(pcm.len(), WHISPER_SAMPLE_RATE as usize);
+        assert!(pcm.iter().all(|s| s.abs() < 1e-3));
+    }
+
+    #[test]
+    fn test_transcribe_agent() {
+        let dir = tempdir().unwrap();
+        let memo = dir.path().join("memo.wav");
+        write_wav(&memo, 16_000, 1, &vec![0i16; 32_000]);
+        let notes = dir.path().join("notes.txt");
+        std::fs::write(&notes, "not audio").unwrap();
+        let report_path = dir.path().join("transcripts.json");
+
+        let (in_tx, in_rx) = bounded(10);
+        let (pass_tx, pass_rx) = bounded(10);
+        let (chunk_tx, chunk_rx) = bounded(10);
+        let (heal_tx, _heal_rx) = bounded(10);
+        let stats = Arc::new(SwarmStats::new());
+
+        in_tx.send(SwarmMessage::FilePath(memo.clone())).unwrap();
+        in_tx.send(SwarmMessage::FilePath(notes.clone())).unwrap();
+        in_tx.send(SwarmMessage::Done).unwrap();
+
+        let report = TranscribeAgent::new(
+            in_rx,
+            pass_tx,
+            chunk_tx,
+            heal_tx,
+            Arc::clone(&stats),
+            Arc::new(LengthTranscriber),
+        )
+        .with_report(report_path.clone())
+        .run()
+        .unwrap();
+
+        // Audio becomes a transcript chunk, everything else goes to chunking
+        match chunk_rx.try_recv().unwrap() {
+            SwarmMessage::Chunk { source, data, .. } => {
+                assert_eq!(source, memo);
+                assert_eq!(data, b"2 seconds");
+            }
+            other => panic!("expected a chunk, got {:?}", other),
+        }
+        assert!(matches!(pass_rx.try_recv().unwrap(), SwarmMessage::FilePath(p) if p == notes));
+        assert!(matches!(pass_rx.try_recv().unwrap(), SwarmMessage::Done));
+
+        assert_eq!(report.files.len(), 1);
+        assert_eq!(report.files[0].duration, 2.0);
+        assert_eq!(report.files[0].text(), "2 seconds");
+        assert_eq!(stats.files_transcribed.load(Ordering::Relaxed), 1);
+
+        let saved: TranscriptReport =
+            serde_json::from_str(&std::fs::read_to_string(&report_path).unwrap()).unwrap();
+        assert_eq!(saved.files, report.files);
+    }
+}
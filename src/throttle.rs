@@ -0,0 +1,160 @@
+//! Bandwidth throttling for long-running reads and writes
+//!
+//! A [`Throttle`] paces I/O to a byte rate so a recovery from a failing drive
+//! can run gently instead of at full speed (less heat, fewer retries on weak
+//! sectors). Clones share one budget and one limit, so a handle kept by the
+//! TUI can raise or lower the rate of an export or carve while it runs, and
+//! the GUI can pause and resume one.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How often a paused throttle checks whether it was resumed
+const PAUSE_POLL: Duration = Duration::from_millis(50);
+
+/// Shared, live-adjustable rate limit (unlimited by default)
+#[derive(Debug, Clone, Default)]
+pub struct Throttle {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    /// Bytes per second, 0 = unlimited
+    limit: AtomicU64,
+    /// While set, callers wait in `consume` instead of doing more I/O
+    paused: AtomicBool,
+    /// Instant at which everything consumed so far has been paid for
+    paid_until: Mutex<Option<Instant>>,
+}
+
+impl Throttle {
+    /// Create a throttle limited to `max_bytes_per_sec` (`None` or 0 = unlimited)
+    pub fn new(max_bytes_per_sec: Option<u64>) -> Self {
+        let throttle = Self::default();
+        throttle.set_limit(max_bytes_per_sec);
+        throttle
+    }
+
+    /// Current limit in bytes per second (`None` = unlimited)
+    pub fn limit(&self) -> Option<u64> {
+        match self.inner.limit.load(Ordering::Relaxed) {
+            0 => None,
+            limit => Some(limit),
+        }
+    }
+
+    /// Change the limit; takes effect for every clone on its next call
+    pub fn set_limit(&self, max_bytes_per_sec: Option<u64>) {
+        self.inner
+            .limit
+            .store(max_bytes_per_sec.unwrap_or(0), Ordering::Relaxed);
+        // Forget debt accrued under the old rate
+        *self.lock() = None;
+    }
+
+    /// Hold every clone at its next `consume` until resumed
+    pub fn set_paused(&self, paused: bool) {
+        self.inner.paused.store(paused, Ordering::Relaxed);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.inner.paused.load(Ordering::Relaxed)
+    }
+
+    /// Account for `bytes` of I/O and return how long the caller should
+    /// wait before doing more
+    pub fn reserve(&self, bytes: u64) -> Duration {
+        let Some(limit) = self.limit() else {
+            return Duration::ZERO;
+        };
+        let now = Instant::now();
+        let mut paid_until = self.lock();
+        // Idle time is not banked, so there is no burst after a pause
+        let start = paid_until.map_or(now, |t| t.max(now));
+        let end = start + Duration::from_secs_f64(bytes as f64 / limit as f64);
+        *paid_until = Some(end);
+        end - now
+    }
+
+    /// Blocking variant for synchronous readers
+    pub fn consume(&self, bytes: u64) {
+        while self.is_paused() {
+            std::thread::sleep(PAUSE_POLL);
+        }
+        let wait = self.reserve(bytes);
+        if !wait.is_zero() {
+            std::thread::sleep(wait);
+        }
+    }
+
+    /// Async variant for tokio tasks
+    pub async fn consume_async(&self, bytes: u64) {
+        while self.is_paused() {
+            tokio::time::sleep(PAUSE_POLL).await;
+        }
+        let wait = self.reserve(bytes);
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<Instant>> {
+        self.inner
+            .paid_until
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_throttle_paces_and_adjusts_live() {
+        let throttle = Throttle::default();
+        assert_eq!(throttle.limit(), None);
+        assert!(throttle.reserve(u64::MAX).is_zero());
+
+        // Clones share the limit and the budget
+        let shared = throttle.clone();
+        shared.set_limit(Some(1000));
+        assert_eq!(throttle.limit(), Some(1000));
+        let first = throttle.reserve(500);
+        let second = shared.reserve(500);
+        assert!(first <= Duration::from_millis(500));
+        assert!(second > Duration::from_millis(900) && second <= Duration::from_secs(1));
+
+        // Raising the limit drops the old debt
+        throttle.set_limit(Some(1_000_000));
+        assert!(shared.reserve(1000) <= Duration::from_millis(1));
+
+        let start = Instant::now();
+        throttle.set_limit(Some(100_000));
+        for _ in 0..5 {
+            throttle.consume(10_000);
+        }
+        assert!(start.elapsed() >= Duration::from_millis(450));
+
+        throttle.set_limit(None);
+        assert!(throttle.reserve(1 << 40).is_zero());
+    }
+
+    #[test]
+    fn test_pause_holds_consumers_until_resumed() {
+        let throttle = Throttle::default();
+        throttle.set_paused(true);
+        let worker = throttle.clone();
+        let handle = std::thread::spawn(move || {
+            let start = Instant::now();
+            worker.consume(1);
+            start.elapsed()
+        });
+        std::thread::sleep(Duration::from_millis(200));
+        assert!(!handle.is_finished(), "a paused throttle holds consume");
+        throttle.set_paused(false);
+        assert!(handle.join().unwrap() >= Duration::from_millis(200));
+    }
+}
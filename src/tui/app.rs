@@ -1,16 +1,54 @@
 //! App state - Central state management for the TUI
 
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 
 use anyhow::Result;
 use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::layout::Rect;
+use tokio::sync::broadcast::{self, error::TryRecvError};
 
+use super::carved::CarveBrowser;
 use super::file_tree::FileTree;
+use super::graphics::{GraphicsProtocol, Placement, Preview};
+use super::jobs::{JobKind, JobOutput, JobRunner};
+use super::throughput::{IoCounters, ThroughputHistory};
+use super::timeline::Timeline;
+use crate::analyze::{EntropyOptions, EntropyReport};
 use crate::badsector::SectorMap;
-use crate::cli::TuiArgs;
-use crate::core::FileType;
-use crate::dedup::{DedupOptions, DedupReport};
+use crate::carve::{CarveListing, CarveOptions, CarveProgress, CarvedFile, Carver};
+use crate::cli::{IndexArgs, TuiArgs};
+use crate::core::{
+    display_timezone, DrillEngine, FileEntry, FileType, Selection, DEFAULT_SELECTION_FILE,
+};
+use crate::dedup::diff::TextDiff;
+use crate::dedup::{DedupOptions, DedupReport, DupGroup};
+use crate::events::{self, Event};
+use crate::export::{ExportFileEvent, ExportOptions, ExportResult, Exporter, DEFAULT_HASH_RETRIES};
+use crate::preview::ThumbnailGenerator;
+use crate::throttle::Throttle;
+
+/// I/O rate limits stepped through with '+' / '-' (bytes per second)
+const RATE_STEPS: [u64; 7] = [
+    1 << 20,
+    5 << 20,
+    10 << 20,
+    25 << 20,
+    50 << 20,
+    100 << 20,
+    250 << 20,
+];
+
+/// Blocks sampled by the Carve tab's entropy map, so it stays quick on
+/// large images
+const ENTROPY_SAMPLE_BLOCKS: u64 = 512;
+
+/// Thumbnail sizes generated for the preview pane (half-block, protocol)
+const PREVIEW_SIZES: [u32; 2] = [64, 512];
+
+/// Files indexed between progress updates from the index job
+const INDEX_PROGRESS_EVERY: usize = 64;
 
 /// Current view/tab in the TUI
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -91,6 +129,26 @@ pub enum AppState {
     Browse,
     /// Typing in the search/filter bar
     SearchInput,
+    /// Date histogram of the indexed files ('t')
+    Timeline,
+}
+
+/// Actions that need their key pressed twice
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Confirm {
+    /// Quit while background jobs are still running
+    Quit,
+    /// Delete the duplicates of the reviewed dedup groups
+    Purge,
+}
+
+impl Confirm {
+    fn label(&self) -> &'static str {
+        match self {
+            Confirm::Quit => "Quit",
+            Confirm::Purge => "Purge",
+        }
+    }
 }
 
 /// Main application state
@@ -121,6 +179,12 @@ pub struct App {
     pub dedup_report: Option<DedupReport>,
     /// Dedup scroll offset for rendering
     pub dedup_scroll: usize,
+    /// Group under the cursor in the dedup list
+    pub dedup_selected: usize,
+    /// Text diffs for the selected group, one per candidate ('v' to toggle)
+    pub dedup_diff: Option<Vec<(PathBuf, Result<TextDiff, String>)>>,
+    /// Diff pane scroll offset
+    pub dedup_diff_scroll: usize,
     /// Bad sector data (populated on demand)
     ///
     /// Requirements:
@@ -130,6 +194,12 @@ pub struct App {
     pub bad_sector_maps: Vec<SectorMap>,
     /// Bad sector scroll offset
     pub bad_sector_scroll: usize,
+    /// Entropy map of the source image ('e' on the Carve tab)
+    pub entropy_report: Option<EntropyReport>,
+    /// Files of the last carve ('c' on the Carve tab, or `--carve-results`)
+    pub carve_browser: Option<CarveBrowser>,
+    /// Date histogram shown in the timeline view (kept between visits)
+    pub timeline: Option<Timeline>,
     /// Cached file entries for dedup operations
     pub cached_entries: Vec<crate::core::FileEntry>,
     /// File type distribution counts
@@ -144,6 +214,42 @@ pub struct App {
     pub source_label: String,
     /// Elapsed time for indexing
     pub index_elapsed: std::time::Duration,
+    /// I/O counters bumped by the active operation
+    pub io: IoCounters,
+    /// Per-second read/write/error history for the status bar sparkline
+    pub throughput: ThroughputHistory,
+    /// Rate limit shared with running operations ('+' / '-' to adjust)
+    pub throttle: Throttle,
+    /// Selection file loaded at start and written with 'w'
+    pub selection_path: PathBuf,
+    /// How thumbnails are drawn (`--graphics`)
+    pub graphics: GraphicsProtocol,
+    /// Thumbnails of the image under the cursor
+    pub preview: Option<Preview>,
+    /// Path the preview was last refreshed for, so failures aren't retried
+    /// on every key
+    preview_for: Option<String>,
+    /// Where the last frame left room for a protocol image
+    pub preview_area: Cell<Option<Rect>>,
+    /// Created on first preview (it sets up the thumbnail cache directory)
+    thumbnails: Option<ThumbnailGenerator>,
+    /// Indexing, carving, dedup and export running in the background
+    pub jobs: JobRunner,
+    /// Where carving and export write (`--out-dir`)
+    pub out_dir: PathBuf,
+    /// Action waiting for its key to be pressed a second time
+    confirm: Option<Confirm>,
+    /// Position in the selected dedup group (0 = master, then duplicates)
+    pub dedup_file: usize,
+    /// Duplicates the user chose to keep; purge leaves them alone
+    pub dedup_keep: HashSet<PathBuf>,
+    /// Unreadable regions reported by any operation this session
+    pub unreadable_regions: usize,
+    /// Library events (bad sectors and I/O are counted from here)
+    events: broadcast::Receiver<Event>,
+    /// Last cumulative byte count of each event stream (an exported file,
+    /// the carve scan), so progress events turn into I/O deltas
+    streamed: HashMap<String, u64>,
 }
 
 impl App {
@@ -161,22 +267,61 @@ impl App {
             (AppState::Init, FileTree::new(), 0, None)
         };
 
+        let selection_path = args
+            .selection
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_SELECTION_FILE));
+        let (selected_files, status_message) = if selection_path.exists() {
+            let selection = Selection::load(&selection_path)?;
+            let message = format!(
+                "Loaded {} selected files from {}",
+                selection.len(),
+                selection_path.display()
+            );
+            (selection.files.into_iter().collect(), message)
+        } else {
+            (Vec::new(), "Press '?' for help".to_string())
+        };
+
+        let carve_browser = args
+            .carve_results
+            .as_deref()
+            .map(CarveListing::load)
+            .transpose()?
+            .map(CarveBrowser::new);
+        // Carve results can be browsed without a source to index
+        let state = match state {
+            AppState::Init if carve_browser.is_some() => AppState::Browse,
+            state => state,
+        };
+
+        let io = IoCounters::default();
         Ok(Self {
             state,
-            tab: Tab::Files,
+            tab: if carve_browser.is_some() {
+                Tab::Carve
+            } else {
+                Tab::Files
+            },
             should_quit: false,
             show_help: false,
             source: source.clone(),
             file_tree,
             file_count,
-            selected_files: Vec::new(),
+            selected_files,
             filter: String::new(),
-            status_message: "Press '?' for help".to_string(),
+            status_message,
             index_progress: 0.0,
             dedup_report: None,
             dedup_scroll: 0,
+            dedup_selected: 0,
+            dedup_diff: None,
+            dedup_diff_scroll: 0,
             bad_sector_maps: Vec::new(),
             bad_sector_scroll: 0,
+            entropy_report: None,
+            carve_browser,
+            timeline: None,
             cached_entries: Vec::new(),
             type_counts: HashMap::new(),
             type_sizes: HashMap::new(),
@@ -187,6 +332,23 @@ impl App {
                 .map(|p| p.display().to_string())
                 .unwrap_or_else(|| "No source".to_string()),
             index_elapsed: std::time::Duration::ZERO,
+            throughput: ThroughputHistory::new(io.clone()),
+            io,
+            throttle: Throttle::default(),
+            selection_path,
+            graphics: GraphicsProtocol::from_mode(args.graphics),
+            preview: None,
+            preview_for: None,
+            preview_area: Cell::new(None),
+            thumbnails: None,
+            jobs: JobRunner::new(),
+            out_dir: args.out_dir,
+            confirm: None,
+            dedup_file: 0,
+            dedup_keep: HashSet::new(),
+            unreadable_regions: 0,
+            events: events::subscribe(),
+            streamed: HashMap::new(),
         })
     }
 
@@ -203,7 +365,7 @@ impl App {
     }
 
     /// Recompute selected_size from selected_files
-    fn update_selected_size(&mut self) {
+    pub(super) fn update_selected_size(&mut self) {
         self.selected_size = self
             .cached_entries
             .iter()
@@ -222,22 +384,137 @@ impl App {
             self.show_help = false;
             return;
         }
+        let pending = self.confirm;
 
         match self.state {
             AppState::Browse => self.handle_browse_key(key),
             AppState::SearchInput => self.handle_search_key(key),
+            AppState::Timeline => self.handle_timeline_key(key),
             _ => {
                 if key.code == KeyCode::Char('q') || key.code == KeyCode::Esc {
-                    self.should_quit = true;
+                    self.request_quit();
                 }
             }
         }
+        // Any other key cancels a pending confirmation
+        if let Some(confirm) = pending.filter(|_| self.confirm == pending) {
+            self.confirm = None;
+            if !self.should_quit {
+                self.status_message = format!("{} cancelled", confirm.label());
+            }
+        }
+        self.refresh_preview();
+    }
+
+    /// Quit, asking first when background jobs would be abandoned
+    fn request_quit(&mut self) {
+        let running = self
+            .jobs
+            .jobs()
+            .iter()
+            .filter(|j| j.status == super::jobs::JobStatus::Running)
+            .count();
+        if running == 0 || self.confirm == Some(Confirm::Quit) {
+            self.should_quit = true;
+        } else {
+            self.confirm = Some(Confirm::Quit);
+            self.status_message = format!(
+                "{} job(s) still running — press q again to abandon them",
+                running
+            );
+        }
+    }
+
+    /// Load thumbnails when the cursor lands on a different image
+    pub fn refresh_preview(&mut self) {
+        if self.graphics == GraphicsProtocol::Off {
+            return;
+        }
+        let selected = match (&self.carve_browser, self.tab) {
+            (Some(browser), Tab::Carve) => browser
+                .selected()
+                .filter(|f| f.file_type == FileType::Image)
+                .and_then(|f| f.path.as_ref())
+                .map(|p| p.display().to_string()),
+            _ => self
+                .file_tree
+                .selected_node()
+                .filter(|n| n.file_type == FileType::Image)
+                .map(|n| n.path.clone()),
+        };
+        if selected == self.preview_for {
+            return;
+        }
+        self.preview_for = selected.clone();
+        self.preview = None;
+        let Some(path) = selected else {
+            return;
+        };
+
+        let thumbnails = self.thumbnails.get_or_insert_with(ThumbnailGenerator::new);
+        let loaded = thumbnails
+            .generate_progressive_multi(Path::new(&path), &PREVIEW_SIZES)
+            .and_then(|paths| {
+                let [small, large] = paths.as_slice() else {
+                    anyhow::bail!("expected {} thumbnails", PREVIEW_SIZES.len());
+                };
+                Ok((
+                    image::open(small)?.to_rgba8(),
+                    image::open(large)?.to_rgba8(),
+                ))
+            });
+        match loaded {
+            Ok((small, large)) => self.preview = Some(Preview { path, small, large }),
+            Err(e) => tracing::debug!("No preview for {}: {:#}", path, e),
+        }
+    }
+
+    /// Protocol image the last frame has room for
+    pub fn preview_placement(&self) -> Option<Placement> {
+        if !self.graphics.is_inline() {
+            return None;
+        }
+        Some(Placement {
+            path: self.preview.as_ref()?.path.clone(),
+            area: self.preview_area.get()?,
+        })
     }
 
     /// Key handler for main browse mode
     fn handle_browse_key(&mut self, key: KeyEvent) {
         match key.code {
-            KeyCode::Char('q') | KeyCode::Esc => self.should_quit = true,
+            // Esc closes the dedup diff pane before quitting
+            KeyCode::Esc if self.tab == Tab::Dedup && self.dedup_diff.is_some() => {
+                self.dedup_diff = None;
+            }
+            KeyCode::Char('q') | KeyCode::Esc => self.request_quit(),
+
+            // Dedup group cursor
+            KeyCode::Char('k') | KeyCode::Up if self.tab == Tab::Dedup => {
+                self.move_dedup_selection(-1)
+            }
+            KeyCode::Char('j') | KeyCode::Down if self.tab == Tab::Dedup => {
+                self.move_dedup_selection(1)
+            }
+
+            // Dedup review: J/K pick a file in the group, m makes it the
+            // master, Space keeps it, P purges, x exports the masters
+            KeyCode::Char('J') if self.tab == Tab::Dedup => self.move_dedup_file(1),
+            KeyCode::Char('K') if self.tab == Tab::Dedup => self.move_dedup_file(-1),
+            KeyCode::Char('m') if self.tab == Tab::Dedup => self.make_dedup_master(),
+            KeyCode::Char(' ') if self.tab == Tab::Dedup => self.toggle_dedup_keep(),
+            KeyCode::Char('P') if self.tab == Tab::Dedup => self.request_purge(),
+            KeyCode::Char('x') if self.tab == Tab::Dedup => self.export_dedup_masters(),
+
+            // Carve results: Space marks files, B picks the boundary and R
+            // extracts the marked files (or the one under the cursor) again
+            KeyCode::Char('k') | KeyCode::Up if self.carve_browsing() => self.move_carve_cursor(-1),
+            KeyCode::Char('j') | KeyCode::Down if self.carve_browsing() => {
+                self.move_carve_cursor(1)
+            }
+            KeyCode::Char(' ') if self.carve_browsing() => self.toggle_carve_mark(),
+            KeyCode::Char('B') if self.carve_browsing() => self.cycle_reextract_boundary(),
+            KeyCode::Char('R') if self.carve_browsing() => self.start_reextract(),
 
             // Navigation
             KeyCode::Char('k') | KeyCode::Up => self.file_tree.select_prev(),
@@ -274,6 +551,7 @@ impl App {
             KeyCode::Char('a') => self.select_all(),
             KeyCode::Char('n') => self.select_none(),
             KeyCode::Char('i') => self.invert_selection(),
+            KeyCode::Char('w') => self.save_selection(),
 
             // Document "Touching"
             KeyCode::Char('o') => self.open_selected(),
@@ -286,6 +564,9 @@ impl App {
                 }
             }
 
+            // Dedup: 'v' to diff the selected group's master against its candidates
+            KeyCode::Char('v') if self.tab == Tab::Dedup => self.toggle_dedup_diff(),
+
             // Bad sectors: 'b' to scan
             KeyCode::Char('b') => {
                 if self.tab == Tab::BadSectors {
@@ -293,8 +574,21 @@ impl App {
                 }
             }
 
+            // Carve: 'e' to map entropy of the source image, 'c' to carve it
+            KeyCode::Char('e') if self.tab == Tab::Carve => self.run_entropy_analysis(),
+            KeyCode::Char('c') if self.tab == Tab::Carve => self.start_carve(),
+
+            // Export: 'x' to export the selection into --out-dir
+            KeyCode::Char('x') if self.tab == Tab::Export => self.start_export(),
+
+            // Timeline of file dates
+            KeyCode::Char('t') => self.open_timeline(),
+
             // Scroll for dedup / bad sector tabs
             KeyCode::Char('[') => match self.tab {
+                Tab::Dedup if self.dedup_diff.is_some() => {
+                    self.dedup_diff_scroll = self.dedup_diff_scroll.saturating_sub(5)
+                }
                 Tab::Dedup => self.dedup_scroll = self.dedup_scroll.saturating_sub(5),
                 Tab::BadSectors => {
                     self.bad_sector_scroll = self.bad_sector_scroll.saturating_sub(5)
@@ -302,6 +596,9 @@ impl App {
                 _ => {}
             },
             KeyCode::Char(']') => match self.tab {
+                Tab::Dedup if self.dedup_diff.is_some() => {
+                    self.dedup_diff_scroll = self.dedup_diff_scroll.saturating_add(5)
+                }
                 Tab::Dedup => self.dedup_scroll = self.dedup_scroll.saturating_add(5),
                 Tab::BadSectors => {
                     self.bad_sector_scroll = self.bad_sector_scroll.saturating_add(5)
@@ -309,6 +606,10 @@ impl App {
                 _ => {}
             },
 
+            // I/O rate limit
+            KeyCode::Char('+') | KeyCode::Char('=') => self.adjust_throttle(true),
+            KeyCode::Char('-') => self.adjust_throttle(false),
+
             // Help overlay
             KeyCode::Char('?') | KeyCode::F(1) => {
                 self.show_help = true;
@@ -344,6 +645,94 @@ impl App {
         }
     }
 
+    /// Handle keys in the timeline view
+    fn handle_timeline_key(&mut self, key: KeyEvent) {
+        let Some(timeline) = self.timeline.as_mut() else {
+            self.state = AppState::Browse;
+            return;
+        };
+        match key.code {
+            KeyCode::Char('q') => self.request_quit(),
+            KeyCode::Esc | KeyCode::Char('t') => {
+                self.state = AppState::Browse;
+                self.status_message = "Timeline closed".to_string();
+                return;
+            }
+            KeyCode::Char('h') | KeyCode::Left => timeline.move_cursor(-1),
+            KeyCode::Char('l') | KeyCode::Right => timeline.move_cursor(1),
+            KeyCode::Char('g') | KeyCode::Home => timeline.jump_to(false),
+            KeyCode::Char('G') | KeyCode::End => timeline.jump_to(true),
+            KeyCode::Enter | KeyCode::Char('j') | KeyCode::Down => {
+                if !timeline.zoom_in(&self.cached_entries) {
+                    self.status_message = "Already at day resolution".to_string();
+                    return;
+                }
+            }
+            KeyCode::Backspace | KeyCode::Char('k') | KeyCode::Up => {
+                timeline.zoom_out(&self.cached_entries);
+            }
+            KeyCode::Char('v') => timeline.toggle_anchor(),
+            KeyCode::Char('c') => timeline.toggle_field(&self.cached_entries),
+            KeyCode::Char(' ') => {
+                self.select_timeline_range();
+                return;
+            }
+            KeyCode::Char('?') | KeyCode::F(1) => self.show_help = true,
+            _ => return,
+        }
+        self.update_timeline_status();
+    }
+
+    /// Show the timeline view, building it on first use
+    fn open_timeline(&mut self) {
+        if self.cached_entries.is_empty() {
+            self.status_message = "No indexed files — index a source first".to_string();
+            return;
+        }
+        if self.timeline.is_none() {
+            self.timeline = Some(Timeline::new(&self.cached_entries, display_timezone()));
+        }
+        self.state = AppState::Timeline;
+        self.update_timeline_status();
+    }
+
+    fn update_timeline_status(&mut self) {
+        let Some(ref timeline) = self.timeline else {
+            return;
+        };
+        let files = timeline.selected_files(&self.cached_entries);
+        self.status_message = format!(
+            "{}: {} files, {} — Space selects them for export",
+            timeline.selected_label(),
+            files.len(),
+            humansize::format_size(files.iter().map(|e| e.size).sum::<u64>(), humansize::BINARY),
+        );
+    }
+
+    /// Add every file in the timeline's selected range to the export selection
+    fn select_timeline_range(&mut self) {
+        let Some(ref timeline) = self.timeline else {
+            return;
+        };
+        let label = timeline.selected_label();
+        let mut already: HashSet<String> = self.selected_files.iter().cloned().collect();
+        let mut added = 0;
+        for entry in timeline.selected_files(&self.cached_entries) {
+            let path = entry.path.to_string_lossy().into_owned();
+            if already.insert(path.clone()) {
+                self.selected_files.push(path);
+                added += 1;
+            }
+        }
+        self.update_selected_size();
+        self.status_message = format!(
+            "Selected {} files from {} ({} total) — Export tab to copy them",
+            added,
+            label,
+            self.selected_files.len()
+        );
+    }
+
     /// Move up one page
     fn page_up(&mut self) {
         for _ in 0..20 {
@@ -446,37 +835,713 @@ impl App {
         self.status_message = "Selection cleared".to_string();
     }
 
-    /// Run dedup analysis on cached entries
+    /// Apply progress and results from background jobs, and count the bad
+    /// sectors they ran into (called every frame)
+    pub fn poll_jobs(&mut self) {
+        for (kind, result) in self.jobs.poll() {
+            match result {
+                Ok(JobOutput::Indexed { files, entries }) => self.apply_index(&files, entries),
+                Ok(JobOutput::Carved(listing)) => self.apply_carve(listing),
+                Ok(JobOutput::Reextracted(files)) => self.apply_reextract(files),
+                Ok(JobOutput::Deduped(report)) => self.apply_dedup(report),
+                Ok(JobOutput::Purged { deleted, errors }) => self.apply_purge(deleted, errors),
+                Ok(JobOutput::Exported(result)) => self.apply_export(result),
+                Err(e) => {
+                    self.io.add_errors(1);
+                    self.status_message = format!("{} failed: {}", kind.label(), e);
+                }
+            }
+        }
+        loop {
+            match self.events.try_recv() {
+                Ok(event) => self.apply_event(&event),
+                Err(TryRecvError::Lagged(_)) => {}
+                Err(_) => break,
+            }
+        }
+    }
+
+    /// Count what a library event says was read, written or found
+    /// unreadable, so the sparkline moves while an operation runs
+    fn apply_event(&mut self, event: &Event) {
+        match event {
+            Event::BadSector(_) => self.unreadable_regions += 1,
+            Event::Hash { bytes, .. } => self.io.add_read(*bytes),
+            Event::Export(ExportFileEvent::Copied { source, bytes }) => {
+                let copied = self.advance(format!("export:{}", source.display()), *bytes);
+                self.io.add_written(copied);
+            }
+            Event::Export(ExportFileEvent::Finished { source, .. }) => {
+                self.streamed
+                    .remove(&format!("export:{}", source.display()));
+            }
+            Event::Carve(CarveProgress::Scanning { bytes_scanned, .. }) => {
+                let scanned = self.advance("carve".to_string(), *bytes_scanned);
+                self.io.add_read(scanned);
+            }
+            Event::Carve(CarveProgress::Done) => {
+                self.streamed.remove("carve");
+            }
+            _ => {}
+        }
+    }
+
+    /// Growth of the cumulative `value` reported by `stream` since its last
+    /// event; a smaller value means the stream started over (a retry)
+    fn advance(&mut self, stream: String, value: u64) -> u64 {
+        let previous = self.streamed.insert(stream, value).unwrap_or(0);
+        value.checked_sub(previous).unwrap_or(value)
+    }
+
+    /// Start a job unless one of the same kind is still running
+    fn start_job<F>(&mut self, kind: JobKind, detail: String, work: F) -> bool
+    where
+        F: FnOnce(super::jobs::JobProgress) -> Result<JobOutput> + Send + 'static,
+    {
+        if self.jobs.is_running(kind) {
+            self.status_message = format!("{} is already running", kind.label());
+            return false;
+        }
+        self.jobs.spawn(kind, detail, work);
+        true
+    }
+
+    /// Index the source in the background; the tree fills in when it's done
+    pub fn start_index(&mut self) {
+        let Some(source) = self.source.clone() else {
+            self.status_message = "No source to index".to_string();
+            return;
+        };
+        let detail = source.display().to_string();
+        let started = self.start_job(JobKind::Index, detail, move |progress| {
+            let args = IndexArgs {
+                source: source.clone(),
+                resume: false,
+                index_file: None,
+                skip_hidden: false,
+                depth: None,
+                extensions: None,
+                thumbnails: false,
+                workers: None,
+                checkpoint_interval: 1000,
+                bad_sector_report: None,
+                block_size: 4096,
+                sqlite: None,
+                incremental: false,
+                verify_hash: false,
+                metadata: false,
+                report_format: None,
+                report_file: None,
+                hashset: Vec::new(),
+                repair_names: None,
+                links: Default::default(),
+                all_hardlinks: false,
+                archives: false,
+                snapshots: false,
+            };
+            tokio::runtime::Handle::current().block_on(async {
+                let engine = DrillEngine::new(source).await?;
+                engine
+                    .index_with_live_progress(&args, |count, entry| {
+                        if count % INDEX_PROGRESS_EVERY == 0 {
+                            progress.update(count as u64, None, entry.path.display().to_string());
+                        }
+                    })
+                    .await?;
+                Ok(JobOutput::Indexed {
+                    files: engine.get_all_files().await?,
+                    entries: engine.get_all_entries().await,
+                })
+            })
+        });
+        if started {
+            self.status_message = "Indexing in the background...".to_string();
+        }
+    }
+
+    /// Replace the tree and cached entries with a finished index
+    fn apply_index(&mut self, files: &[String], entries: Vec<FileEntry>) {
+        self.file_tree = FileTree::from_paths(files);
+        if !self.filter.is_empty() {
+            self.file_tree.apply_filter(&self.filter);
+        }
+        self.file_count = files.len();
+        self.cached_entries = entries;
+        self.index_elapsed = self
+            .jobs
+            .jobs()
+            .iter()
+            .rfind(|j| j.kind == JobKind::Index)
+            .map(|j| j.elapsed())
+            .unwrap_or_default();
+        self.timeline = None;
+
+        self.compute_stats();
+        self.update_selected_size();
+        self.refresh_preview();
+
+        self.status_message = format!(
+            "Indexed {} files ({}) in {:.1}s",
+            self.file_count,
+            humansize::format_size(self.total_size, humansize::BINARY),
+            self.index_elapsed.as_secs_f64(),
+        );
+    }
+
+    /// Run dedup analysis on cached entries in the background
     pub fn run_dedup_analysis(&mut self) {
         if self.cached_entries.is_empty() {
             self.status_message = "No indexed files — index a source first".to_string();
             return;
         }
 
-        self.status_message = "Running dedup analysis...".to_string();
-
         let options = DedupOptions {
             strategy: crate::dedup::KeepStrategy::Newest,
             fuzzy: true,
             fuzzy_threshold: 80,
             min_size: 1,
+            hash_algorithms: Vec::new(),
+            media: false,
         };
 
-        match crate::dedup::analyze(&self.cached_entries, &options) {
-            Ok(report) => {
-                self.status_message = format!(
-                    "Dedup: {} groups, {} duplicates, {} wasted",
-                    report.duplicate_groups,
-                    report.total_duplicates,
-                    humansize::format_size(report.wasted_bytes, humansize::BINARY),
-                );
-                self.dedup_report = Some(report);
-                self.dedup_scroll = 0;
+        let entries = self.cached_entries.clone();
+        let detail = format!("hashing {} files", entries.len());
+        if self.start_job(JobKind::Dedup, detail, move |_| {
+            crate::dedup::analyze(&entries, &options).map(JobOutput::Deduped)
+        }) {
+            self.status_message = "Running dedup analysis...".to_string();
+        }
+    }
+
+    fn apply_dedup(&mut self, report: DedupReport) {
+        self.status_message = format!(
+            "Dedup: {} groups, {} duplicates, {} wasted",
+            report.duplicate_groups,
+            report.total_duplicates,
+            humansize::format_size(report.wasted_bytes, humansize::BINARY),
+        );
+        self.dedup_report = Some(report);
+        self.dedup_scroll = 0;
+        self.dedup_selected = 0;
+        self.dedup_file = 0;
+        self.dedup_keep.clear();
+        self.dedup_diff = None;
+    }
+
+    /// Indexed size of a file (0 when it isn't in the index)
+    fn entry_size(&self, path: &Path) -> u64 {
+        self.cached_entries
+            .iter()
+            .find(|e| e.path == path)
+            .map_or(0, |e| e.size)
+    }
+
+    /// File under the cursor in the selected dedup group
+    pub fn dedup_cursor(&self) -> Option<&Path> {
+        let group = self
+            .dedup_report
+            .as_ref()?
+            .groups
+            .get(self.dedup_selected)?;
+        match self.dedup_file {
+            0 => Some(&group.master),
+            i => group.duplicates.get(i - 1).map(PathBuf::as_path),
+        }
+    }
+
+    /// Move the file cursor within the selected dedup group
+    fn move_dedup_file(&mut self, delta: isize) {
+        let Some(group) = self
+            .dedup_report
+            .as_ref()
+            .and_then(|r| r.groups.get(self.dedup_selected))
+        else {
+            return;
+        };
+        self.dedup_file = self
+            .dedup_file
+            .saturating_add_signed(delta)
+            .min(group.duplicates.len());
+    }
+
+    /// Keep the file under the cursor instead of the group's master
+    fn make_dedup_master(&mut self) {
+        if self.dedup_file == 0 {
+            self.status_message = "Already the master of this group".to_string();
+            return;
+        }
+        let (Some(new_master), Some(old_master)) = (
+            self.dedup_cursor().map(Path::to_path_buf),
+            self.dedup_report
+                .as_ref()
+                .map(|r| r.groups[self.dedup_selected].master.clone()),
+        ) else {
+            return;
+        };
+        let (gained, lost) = (self.entry_size(&new_master), self.entry_size(&old_master));
+
+        let Some(report) = self.dedup_report.as_mut() else {
+            return;
+        };
+        let group = &mut report.groups[self.dedup_selected];
+        group.duplicates[self.dedup_file - 1] = old_master;
+        group.master = new_master.clone();
+        group.wasted_bytes = (group.wasted_bytes + lost).saturating_sub(gained);
+        report.wasted_bytes = (report.wasted_bytes + lost).saturating_sub(gained);
+
+        self.dedup_keep.remove(&new_master);
+        self.dedup_file = 0;
+        self.dedup_diff = None;
+        self.status_message = format!(
+            "Group #{} now keeps {}",
+            self.dedup_selected + 1,
+            new_master.display()
+        );
+    }
+
+    /// Keep (or stop keeping) the duplicate under the cursor
+    fn toggle_dedup_keep(&mut self) {
+        if self.dedup_file == 0 {
+            self.status_message =
+                "The master is always kept — press m on another file to switch".to_string();
+            return;
+        }
+        let Some(path) = self.dedup_cursor().map(Path::to_path_buf) else {
+            return;
+        };
+        self.status_message = if self.dedup_keep.remove(&path) {
+            format!("{} will be purged", path.display())
+        } else {
+            let message = format!("Keeping {}", path.display());
+            self.dedup_keep.insert(path);
+            message
+        };
+    }
+
+    /// Groups as reviewed: the duplicates a purge would delete
+    pub fn dedup_purge_plan(&self) -> Vec<DupGroup> {
+        let Some(report) = &self.dedup_report else {
+            return Vec::new();
+        };
+        report
+            .groups
+            .iter()
+            .map(|g| {
+                let mut group = g.clone();
+                group.duplicates.retain(|p| !self.dedup_keep.contains(p));
+                group
+            })
+            .filter(|g| !g.duplicates.is_empty())
+            .collect()
+    }
+
+    /// Files and bytes a purge would delete
+    pub fn dedup_purge_totals(&self) -> (usize, u64) {
+        let Some(report) = &self.dedup_report else {
+            return (0, 0);
+        };
+        let duplicates: usize = report.groups.iter().map(|g| g.duplicates.len()).sum();
+        let kept_bytes: u64 = self.dedup_keep.iter().map(|p| self.entry_size(p)).sum();
+        (
+            duplicates.saturating_sub(self.dedup_keep.len()),
+            report.wasted_bytes.saturating_sub(kept_bytes),
+        )
+    }
+
+    /// Delete the reviewed duplicates in the background ('P' twice)
+    fn request_purge(&mut self) {
+        let (files, bytes) = self.dedup_purge_totals();
+        if files == 0 {
+            self.status_message = "Nothing to purge — press 'd' to analyze".to_string();
+            return;
+        }
+        if self.confirm != Some(Confirm::Purge) {
+            self.confirm = Some(Confirm::Purge);
+            self.status_message = format!(
+                "Delete {} duplicate(s), freeing {}? Press P again to purge",
+                files,
+                humansize::format_size(bytes, humansize::BINARY)
+            );
+            return;
+        }
+
+        self.confirm = None;
+        let groups = self.dedup_purge_plan();
+        let detail = format!("deleting {} files", files);
+        if self.start_job(JobKind::Purge, detail, move |_| {
+            let (_, _, errors) = crate::dedup::purge_duplicates(&groups, false);
+            let deleted = groups
+                .iter()
+                .flat_map(|g| &g.duplicates)
+                .filter(|p| !p.exists())
+                .cloned()
+                .collect();
+            Ok(JobOutput::Purged { deleted, errors })
+        }) {
+            self.status_message = "Purging duplicates...".to_string();
+        }
+    }
+
+    /// Drop purged files from the index, tree and dedup report
+    fn apply_purge(&mut self, deleted: Vec<PathBuf>, errors: Vec<String>) {
+        let sizes: HashMap<&Path, u64> = self
+            .cached_entries
+            .iter()
+            .map(|e| (e.path.as_path(), e.size))
+            .collect();
+        let gone: HashSet<&Path> = deleted.iter().map(PathBuf::as_path).collect();
+        let freed: u64 = gone.iter().filter_map(|p| sizes.get(p)).sum();
+
+        if let Some(report) = self.dedup_report.as_mut() {
+            for group in &mut report.groups {
+                let removed: u64 = group
+                    .duplicates
+                    .iter()
+                    .filter(|p| gone.contains(p.as_path()))
+                    .filter_map(|p| sizes.get(p.as_path()))
+                    .sum();
+                group.duplicates.retain(|p| !gone.contains(p.as_path()));
+                group.wasted_bytes = group.wasted_bytes.saturating_sub(removed);
             }
-            Err(e) => {
-                self.status_message = format!("Dedup error: {}", e);
+            report.groups.retain(|g| !g.duplicates.is_empty());
+            report.duplicate_groups = report.groups.len();
+            report.total_duplicates = report.groups.iter().map(|g| g.duplicates.len()).sum();
+            report.wasted_bytes = report.wasted_bytes.saturating_sub(freed);
+            self.dedup_selected = self
+                .dedup_selected
+                .min(report.groups.len().saturating_sub(1));
+        }
+        self.dedup_file = 0;
+        self.dedup_diff = None;
+
+        self.cached_entries
+            .retain(|e| !gone.contains(e.path.as_path()));
+        self.selected_files.retain(|s| !gone.contains(Path::new(s)));
+        let paths: Vec<String> = self
+            .cached_entries
+            .iter()
+            .map(|e| e.path.to_string_lossy().to_string())
+            .collect();
+        self.file_tree = FileTree::from_paths(&paths);
+        if !self.filter.is_empty() {
+            self.file_tree.apply_filter(&self.filter);
+        }
+        self.file_count = paths.len();
+        self.timeline = None;
+        self.compute_stats();
+        self.update_selected_size();
+        self.refresh_preview();
+
+        self.io.add_errors(errors.len() as u64);
+        self.status_message = format!(
+            "Purged {} duplicate(s), freed {}{}",
+            deleted.len(),
+            humansize::format_size(freed, humansize::BINARY),
+            match errors.first() {
+                Some(first) => format!(" — {} failed ({})", errors.len(), first),
+                None => String::new(),
             }
+        );
+    }
+
+    /// Export one copy per dedup group (its master) plus the kept duplicates
+    fn export_dedup_masters(&mut self) {
+        let Some(report) = &self.dedup_report else {
+            self.status_message = "No dedup report — press 'd' to analyze".to_string();
+            return;
+        };
+        let wanted: HashSet<&Path> = report
+            .groups
+            .iter()
+            .map(|g| g.master.as_path())
+            .chain(self.dedup_keep.iter().map(PathBuf::as_path))
+            .collect();
+        let entries = self
+            .cached_entries
+            .iter()
+            .filter(|e| wanted.contains(e.path.as_path()))
+            .cloned()
+            .collect();
+        self.export_entries(entries);
+    }
+
+    /// Carve the source image into `<out-dir>/carved` in the background
+    pub fn start_carve(&mut self) {
+        let Some(source) = self.source.clone().filter(|p| p.is_file()) else {
+            self.status_message = "Carving needs a disk image as the source".to_string();
+            return;
+        };
+        let output_dir = self.out_dir.join("carved");
+        let carver = Carver::new(CarveOptions {
+            source: source.clone(),
+            output_dir: output_dir.clone(),
+            ..Default::default()
+        })
+        .with_throttle(self.throttle.clone());
+
+        let detail = format!("into {}", output_dir.display());
+        if self.start_job(JobKind::Carve, detail, move |progress| {
+            let carve = carver.carve_with_progress(|event| match event {
+                CarveProgress::Scanning {
+                    bytes_scanned,
+                    total_bytes,
+                } => progress.update(bytes_scanned, Some(total_bytes), "scanning"),
+                CarveProgress::ScanComplete { headers_found } => {
+                    progress.update(0, None, format!("{} headers found", headers_found))
+                }
+                CarveProgress::Extracting {
+                    current,
+                    total,
+                    extension,
+                } => progress.update(
+                    current as u64,
+                    Some(total as u64),
+                    format!("extracting .{}", extension),
+                ),
+                CarveProgress::Done => {}
+            });
+            let (files, result) = tokio::runtime::Handle::current().block_on(carve)?;
+            let listing = CarveListing {
+                source,
+                result,
+                files,
+            };
+            listing.save(&output_dir)?;
+            Ok(JobOutput::Carved(listing))
+        }) {
+            self.status_message = "Carving in the background...".to_string();
+        }
+    }
+
+    fn apply_carve(&mut self, listing: CarveListing) {
+        let result = &listing.result;
+        self.io.add_written(result.total_bytes_extracted);
+        self.status_message = format!(
+            "Carved {} of {} files found ({}) into {}",
+            result.files_extracted,
+            result.files_found,
+            humansize::format_size(result.total_bytes_extracted, humansize::BINARY),
+            self.out_dir.join("carved").display()
+        );
+        self.carve_browser = Some(CarveBrowser::new(listing));
+        self.refresh_preview();
+    }
+
+    /// Whether the Carve tab is showing a results list
+    fn carve_browsing(&self) -> bool {
+        self.tab == Tab::Carve
+            && self
+                .carve_browser
+                .as_ref()
+                .is_some_and(|b| !b.listing.files.is_empty())
+    }
+
+    fn move_carve_cursor(&mut self, delta: isize) {
+        if let Some(browser) = &mut self.carve_browser {
+            browser.move_cursor(delta);
+        }
+    }
+
+    fn toggle_carve_mark(&mut self) {
+        if let Some(browser) = &mut self.carve_browser {
+            browser.toggle_mark();
+            self.status_message = format!("{} carved files marked", browser.marked.len());
+        }
+    }
+
+    fn cycle_reextract_boundary(&mut self) {
+        if let Some(browser) = &mut self.carve_browser {
+            browser.cycle_boundary();
+            self.status_message = format!("Re-extract boundary: {}", browser.boundary.label());
+        }
+    }
+
+    /// Extract the marked carved files again into `<out-dir>/reextracted`,
+    /// ending at the chosen boundary
+    pub fn start_reextract(&mut self) {
+        let Some(browser) = &self.carve_browser else {
+            return;
+        };
+        if browser.listing.result.unlocked {
+            self.status_message =
+                "Can't re-extract from an unlocked volume: offsets refer to its plaintext"
+                    .to_string();
+            return;
         }
+        let files = browser.targets();
+        let boundary = browser.boundary;
+        let output_dir = self.out_dir.join("reextracted");
+        let carver = Carver::new(CarveOptions {
+            source: browser.listing.source.clone(),
+            output_dir: output_dir.clone(),
+            ..Default::default()
+        })
+        .with_throttle(self.throttle.clone());
+
+        let detail = format!("{} files ({})", files.len(), boundary.label());
+        if self.start_job(JobKind::Reextract, detail, move |_| {
+            Ok(JobOutput::Reextracted(carver.reextract(
+                &files,
+                boundary,
+                &output_dir,
+            )?))
+        }) {
+            self.status_message = "Re-extracting in the background...".to_string();
+        }
+    }
+
+    fn apply_reextract(&mut self, files: Vec<CarvedFile>) {
+        let bytes = files.iter().map(|f| f.size).sum();
+        self.io.add_written(bytes);
+        self.status_message = format!(
+            "Re-extracted {} files ({}) into {}",
+            files.len(),
+            humansize::format_size(bytes, humansize::BINARY),
+            self.out_dir.join("reextracted").display()
+        );
+        if let Some(browser) = &mut self.carve_browser {
+            browser.apply_reextracted(files);
+        }
+        self.refresh_preview();
+    }
+
+    /// Export the selected files into `<out-dir>/export` in the background
+    pub fn start_export(&mut self) {
+        let selected: HashSet<&str> = self.selected_files.iter().map(String::as_str).collect();
+        let entries = self
+            .cached_entries
+            .iter()
+            .filter(|e| selected.contains(e.path.to_string_lossy().as_ref()))
+            .cloned()
+            .collect();
+        self.export_entries(entries);
+    }
+
+    fn export_entries(&mut self, entries: Vec<FileEntry>) {
+        if entries.is_empty() {
+            self.status_message = "Nothing selected to export".to_string();
+            return;
+        }
+
+        let dest = self.out_dir.join("export");
+        let exporter = Exporter::new(ExportOptions {
+            dest: dest.clone(),
+            verify_hash: true,
+            continue_on_error: true,
+            create_manifest: true,
+            hash_retries: DEFAULT_HASH_RETRIES,
+            retry_with_sector_reader: true,
+            ..Default::default()
+        })
+        .with_throttle(self.throttle.clone());
+
+        let detail = format!("{} files to {}", entries.len(), dest.display());
+        if self.start_job(JobKind::Export, detail, move |progress| {
+            let export = exporter.export_batch(&entries, |p| {
+                progress.update(p.completed as u64, Some(p.total as u64), p.current_file)
+            });
+            Ok(JobOutput::Exported(
+                tokio::runtime::Handle::current().block_on(export)?,
+            ))
+        }) {
+            self.status_message = "Exporting in the background...".to_string();
+        }
+    }
+
+    fn apply_export(&mut self, result: ExportResult) {
+        // Bytes written were counted from the export's events
+        self.io.add_errors(result.failed as u64);
+        self.status_message = format!(
+            "Exported {} files ({}), {} failed, to {}",
+            result.successful,
+            humansize::format_size(result.total_bytes, humansize::BINARY),
+            result.failed,
+            self.out_dir.join("export").display()
+        );
+    }
+
+    /// Move the dedup group cursor by `delta`, closing any open diff
+    fn move_dedup_selection(&mut self, delta: isize) {
+        let Some(ref report) = self.dedup_report else {
+            return;
+        };
+        let last = report.groups.len().saturating_sub(1);
+        self.dedup_selected = self.dedup_selected.saturating_add_signed(delta).min(last);
+        self.dedup_file = 0;
+        self.dedup_diff = None;
+    }
+
+    /// Show or hide text diffs for the selected dedup group
+    pub fn toggle_dedup_diff(&mut self) {
+        if self.dedup_diff.take().is_some() {
+            return;
+        }
+        let Some(group) = self
+            .dedup_report
+            .as_ref()
+            .and_then(|r| r.groups.get(self.dedup_selected))
+        else {
+            self.status_message = "No dedup group selected — press 'd' to analyze".to_string();
+            return;
+        };
+
+        let diffs: Vec<_> = crate::dedup::diff::diff_group(
+            group,
+            crate::dedup::diff::DEFAULT_CONTEXT_LINES,
+        )
+        .into_iter()
+        .map(|(path, result)| (path, result.map_err(|e| format!("{:#}", e))))
+        .collect();
+
+        self.status_message = format!(
+            "Diff for group #{}: {} candidate(s) — Esc to close",
+            self.dedup_selected + 1,
+            diffs.len()
+        );
+        self.dedup_diff = Some(diffs);
+        self.dedup_diff_scroll = 0;
+    }
+
+    /// Write the selected files to the selection file, keeping its source
+    /// and creation time when it already exists
+    fn save_selection(&mut self) {
+        let saved = Selection::load_or_new(&self.selection_path, self.source.as_deref()).and_then(
+            |mut selection| {
+                selection.set_files(self.selected_files.iter().cloned());
+                selection.save(&self.selection_path)
+            },
+        );
+        self.status_message = match saved {
+            Ok(()) => format!(
+                "Saved {} selected files to {}",
+                self.selected_files.len(),
+                self.selection_path.display()
+            ),
+            Err(e) => format!("Failed to save selection: {:#}", e),
+        };
+    }
+
+    /// Step the I/O rate limit up (towards unlimited) or down
+    fn adjust_throttle(&mut self, faster: bool) {
+        let limit = match (self.throttle.limit(), faster) {
+            (None, true) => None,
+            (None, false) => RATE_STEPS.last().copied(),
+            (Some(current), true) => RATE_STEPS.iter().copied().find(|&s| s > current),
+            (Some(current), false) => RATE_STEPS
+                .iter()
+                .copied()
+                .rev()
+                .find(|&s| s < current)
+                .or(Some(RATE_STEPS[0])),
+        };
+        self.throttle.set_limit(limit);
+        self.status_message = match limit {
+            Some(limit) => format!(
+                "I/O limit: {}/s",
+                humansize::format_size(limit, humansize::BINARY)
+            ),
+            None => "I/O limit: unlimited".to_string(),
+        };
     }
 
     /// Run bad sector scan on a sample of cached files
@@ -498,6 +1563,9 @@ impl App {
         for entry in &self.cached_entries[..limit] {
             match reader.read_with_sector_tracking(&entry.path) {
                 Ok(map) => {
+                    self.throttle.consume(map.good_bytes);
+                    self.io.add_read(map.good_bytes);
+                    self.io.add_errors(map.bad_blocks.len() as u64);
                     scanned += 1;
                     if map.has_bad_sectors() {
                         bad_files += 1;
@@ -517,6 +1585,46 @@ impl App {
             scanned, bad_files,
         );
     }
+
+    /// Map the entropy of the source image to show where carving can't see
+    pub fn run_entropy_analysis(&mut self) {
+        let Some(source) = self.source.clone().filter(|p| p.is_file()) else {
+            self.status_message = "Entropy map needs a disk image as the source".to_string();
+            return;
+        };
+
+        let options = EntropyOptions {
+            max_blocks: Some(ENTROPY_SAMPLE_BLOCKS),
+            max_bytes_per_sec: self.throttle.limit(),
+            ..Default::default()
+        };
+        match crate::analyze::analyze_image(&source, &options, |_, _| {}) {
+            Ok(report) => {
+                self.io
+                    .add_read(report.blocks.len() as u64 * report.block_size as u64);
+                self.status_message = if report.has_encrypted() {
+                    format!(
+                        "Entropy: {} encrypted, {} volume header(s) — carving can't see into those",
+                        humansize::format_size(
+                            report.class_bytes(crate::analyze::RegionClass::Encrypted),
+                            humansize::BINARY
+                        ),
+                        report.headers.len()
+                    )
+                } else {
+                    format!(
+                        "Entropy: {} regions, nothing encrypted",
+                        report.regions.len()
+                    )
+                };
+                self.entropy_report = Some(report);
+            }
+            Err(e) => {
+                self.io.add_errors(1);
+                self.status_message = format!("Entropy analysis failed: {}", e);
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -525,7 +1633,13 @@ mod tests {
     use crossterm::event::KeyModifiers;
 
     fn make_test_args(source: Option<PathBuf>) -> TuiArgs {
-        TuiArgs { source }
+        TuiArgs {
+            source,
+            selection: Some(PathBuf::from("/nonexistent/selection.ddsel")),
+            out_dir: PathBuf::from("/nonexistent/out"),
+            carve_results: None,
+            graphics: crate::cli::GraphicsMode::Off,
+        }
     }
 
     #[tokio::test]
@@ -556,6 +1670,234 @@ mod tests {
         assert_eq!(app.tab, Tab::BadSectors);
     }
 
+    #[tokio::test]
+    async fn test_entropy_map_on_carve_tab() {
+        let dir = tempfile::tempdir().unwrap();
+        let image = dir.path().join("disk.img");
+        let mut data = vec![0u8; 1 << 20];
+        data.extend((0..1u32 << 20).map(|i| (i % 7) as u8));
+        std::fs::write(&image, &data).unwrap();
+
+        let mut app = App::new(make_test_args(Some(image))).await.unwrap();
+        app.on_key(KeyEvent::new(KeyCode::Char('e'), KeyModifiers::NONE));
+        assert!(
+            app.entropy_report.is_none(),
+            "'e' only maps on the Carve tab"
+        );
+
+        app.tab = Tab::Carve;
+        app.on_key(KeyEvent::new(KeyCode::Char('e'), KeyModifiers::NONE));
+        let report = app.entropy_report.as_ref().unwrap();
+        assert_eq!(report.blocks.len(), 2);
+        assert!(!report.has_encrypted());
+    }
+
+    #[tokio::test]
+    async fn test_timeline_selects_range_for_export() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = App::new(make_test_args(None)).await.unwrap();
+        app.state = AppState::Browse;
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+
+        app.on_key(key(KeyCode::Char('t')));
+        assert_eq!(app.state, AppState::Browse, "nothing indexed yet");
+
+        for (name, year) in [("old.txt", 2019), ("new.txt", 2023), ("newer.txt", 2023)] {
+            let path = dir.path().join(name);
+            std::fs::write(&path, name).unwrap();
+            let mut entry =
+                crate::core::FileEntry::new(path.clone(), &std::fs::metadata(&path).unwrap());
+            entry.modified = chrono::NaiveDate::from_ymd_opt(year, 6, 15)
+                .and_then(|d| d.and_hms_opt(12, 0, 0))
+                .map(|dt| dt.and_utc());
+            app.cached_entries.push(entry);
+        }
+
+        app.on_key(key(KeyCode::Char('t')));
+        assert_eq!(app.state, AppState::Timeline);
+        // Cursor starts on the latest year
+        app.on_key(key(KeyCode::Char(' ')));
+        assert_eq!(app.selected_files.len(), 2);
+
+        // Extend the range back to 2019
+        app.on_key(key(KeyCode::Char('v')));
+        app.on_key(key(KeyCode::Char('g')));
+        app.on_key(key(KeyCode::Char(' ')));
+        assert_eq!(app.selected_files.len(), 3);
+
+        app.on_key(key(KeyCode::Esc));
+        assert_eq!(app.state, AppState::Browse);
+        assert!(!app.should_quit);
+    }
+
+    #[tokio::test]
+    async fn test_image_preview_follows_cursor() {
+        let dir = tempfile::tempdir().unwrap();
+        let photo = dir.path().join("a.png");
+        image::RgbaImage::from_pixel(40, 20, image::Rgba([200, 10, 10, 255]))
+            .save(&photo)
+            .unwrap();
+        let notes = dir.path().join("b.txt");
+        std::fs::write(&notes, "notes").unwrap();
+
+        let mut args = make_test_args(None);
+        args.graphics = crate::cli::GraphicsMode::Blocks;
+        let mut app = App::new(args).await.unwrap();
+        app.state = AppState::Browse;
+        let paths: Vec<String> = [&photo, &notes]
+            .iter()
+            .map(|p| p.display().to_string())
+            .collect();
+        app.file_tree = super::super::file_tree::FileTree::from_paths(&paths);
+
+        app.refresh_preview();
+        let preview = app.preview.as_ref().expect("thumbnail for the png");
+        assert_eq!(preview.small.dimensions(), (64, 32));
+        assert_eq!(app.preview_placement(), None, "blocks draw inside ratatui");
+
+        app.on_key(KeyEvent::new(KeyCode::Char('j'), KeyModifiers::NONE));
+        assert!(app.preview.is_none());
+    }
+
+    /// Poll until no job is running
+    async fn wait_for_jobs(app: &mut App) {
+        for _ in 0..1000 {
+            app.poll_jobs();
+            if !app.jobs.is_busy() {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        panic!("background jobs did not finish");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_index_and_dedup_run_as_jobs() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), "same contents").unwrap();
+        std::fs::write(dir.path().join("b.txt"), "same contents").unwrap();
+        std::fs::write(dir.path().join("c.txt"), "different").unwrap();
+
+        let mut app = App::new(make_test_args(Some(dir.path().to_path_buf())))
+            .await
+            .unwrap();
+        app.start_index();
+        assert!(app.jobs.is_running(JobKind::Index));
+        // Still navigable while indexing
+        app.on_key(KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE));
+        assert_eq!(app.tab, Tab::Search);
+
+        // Quitting with a job running asks first
+        app.on_key(KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE));
+        assert!(!app.should_quit);
+        app.on_key(KeyEvent::new(KeyCode::Char('j'), KeyModifiers::NONE));
+        assert_eq!(app.status_message, "Quit cancelled");
+
+        wait_for_jobs(&mut app).await;
+        assert_eq!(app.file_count, 3);
+        assert_eq!(app.cached_entries.len(), 3);
+
+        app.tab = Tab::Dedup;
+        app.on_key(KeyEvent::new(KeyCode::Char('d'), KeyModifiers::NONE));
+        wait_for_jobs(&mut app).await;
+        let report = app.dedup_report.as_ref().unwrap();
+        assert_eq!(report.total_duplicates, 1);
+        assert!(app.jobs.jobs().iter().all(|j| j.ratio() == Some(1.0)));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_dedup_review_purge_and_export_masters() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("src");
+        std::fs::create_dir(&source).unwrap();
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            std::fs::write(source.join(name), "same contents").unwrap();
+        }
+        std::fs::write(source.join("d.txt"), "unique").unwrap();
+
+        let mut args = make_test_args(Some(source.clone()));
+        args.out_dir = dir.path().join("out");
+        let mut app = App::new(args).await.unwrap();
+        app.start_index();
+        wait_for_jobs(&mut app).await;
+        app.tab = Tab::Dedup;
+        app.on_key(KeyEvent::new(KeyCode::Char('d'), KeyModifiers::NONE));
+        wait_for_jobs(&mut app).await;
+        assert_eq!(app.dedup_purge_totals().0, 2);
+
+        let key = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+        // Make the first duplicate the master, then keep the other one
+        app.on_key(key('J'));
+        let new_master = app.dedup_cursor().unwrap().to_path_buf();
+        app.on_key(key('m'));
+        assert_eq!(
+            app.dedup_report.as_ref().unwrap().groups[0].master,
+            new_master
+        );
+        app.on_key(key('J'));
+        app.on_key(key('J'));
+        let kept = app.dedup_cursor().unwrap().to_path_buf();
+        app.on_key(key(' '));
+        assert_eq!(app.dedup_purge_totals(), (1, 13));
+
+        // Purge asks first, and any other key cancels
+        app.on_key(key('P'));
+        app.on_key(key('K'));
+        assert_eq!(app.status_message, "Purge cancelled");
+        app.on_key(key('P'));
+        app.on_key(key('P'));
+        wait_for_jobs(&mut app).await;
+
+        let remaining: Vec<_> = std::fs::read_dir(&source).unwrap().collect();
+        assert_eq!(remaining.len(), 3);
+        assert!(new_master.exists() && kept.exists());
+        assert_eq!(app.cached_entries.len(), 3);
+        assert_eq!(app.file_count, 3);
+        let report = app.dedup_report.as_ref().unwrap();
+        assert_eq!(report.groups[0].duplicates, vec![kept]);
+        assert_eq!(app.dedup_purge_totals(), (0, 0));
+
+        // Masters only: one copy of the group plus the kept duplicate
+        app.on_key(key('x'));
+        wait_for_jobs(&mut app).await;
+        assert!(
+            app.status_message.starts_with("Exported 2 files"),
+            "{}",
+            app.status_message
+        );
+    }
+
+    #[tokio::test]
+    async fn test_events_feed_throughput() {
+        let mut app = App::new(make_test_args(None)).await.unwrap();
+        let source = PathBuf::from("/photos/a.jpg");
+        let copied = |bytes| {
+            Event::Export(ExportFileEvent::Copied {
+                source: source.clone(),
+                bytes,
+            })
+        };
+        app.apply_event(&copied(1000));
+        app.apply_event(&copied(3000));
+        // A retry starts counting from zero again
+        app.apply_event(&copied(500));
+        app.apply_event(&Event::Carve(CarveProgress::Scanning {
+            bytes_scanned: 4096,
+            total_bytes: 1 << 20,
+        }));
+        app.apply_event(&Event::Hash {
+            path: source.clone(),
+            blake3: String::new(),
+            bytes: 3000,
+        });
+
+        app.throughput
+            .tick(std::time::Instant::now() + std::time::Duration::from_millis(1500));
+        let latest = app.throughput.latest();
+        assert_eq!(latest.written, 3500);
+        assert_eq!(latest.read, 4096 + 3000);
+    }
+
     #[tokio::test]
     async fn test_keybinding_quit() {
         let mut app = App::new(make_test_args(None)).await.unwrap();
@@ -624,4 +1966,115 @@ mod tests {
         app.on_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
         assert_eq!(app.state, AppState::Browse);
     }
+
+    #[tokio::test]
+    async fn test_selection_saved_and_restored() {
+        let dir = tempfile::tempdir().unwrap();
+        let selection = dir.path().join("session.ddsel");
+        let args = TuiArgs {
+            source: None,
+            selection: Some(selection.clone()),
+            out_dir: dir.path().join("out"),
+            carve_results: None,
+            graphics: crate::cli::GraphicsMode::Off,
+        };
+
+        let mut app = App::new(args.clone()).await.unwrap();
+        app.state = AppState::Browse;
+        let paths = vec!["a.txt".to_string(), "b.txt".to_string()];
+        app.file_tree = super::super::file_tree::FileTree::from_paths(&paths);
+        app.select_all();
+        app.on_key(KeyEvent::new(KeyCode::Char('w'), KeyModifiers::NONE));
+        assert!(app.status_message.starts_with("Saved 2"));
+
+        // Next session starts with the same selection
+        let app = App::new(args).await.unwrap();
+        assert_eq!(app.selected_files, paths);
+    }
+
+    #[tokio::test]
+    async fn test_throttle_keys() {
+        let mut app = App::new(make_test_args(None)).await.unwrap();
+        app.state = AppState::Browse;
+        let shared = app.throttle.clone();
+        let key = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+
+        app.on_key(key('-'));
+        assert_eq!(shared.limit(), Some(250 << 20));
+        app.on_key(key('-'));
+        assert_eq!(shared.limit(), Some(100 << 20));
+        for _ in 0..10 {
+            app.on_key(key('-'));
+        }
+        assert_eq!(shared.limit(), Some(1 << 20));
+        assert_eq!(app.status_message, "I/O limit: 1 MiB/s");
+
+        app.on_key(key('+'));
+        assert_eq!(shared.limit(), Some(5 << 20));
+        for _ in 0..10 {
+            app.on_key(key('+'));
+        }
+        assert_eq!(shared.limit(), None);
+        assert_eq!(app.status_message, "I/O limit: unlimited");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_carve_results_browsed_and_reextracted() {
+        let dir = tempfile::tempdir().unwrap();
+        // A JPEG whose embedded thumbnail header cut the original carve short
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0];
+        jpeg.extend(vec![0x11; 1020]);
+        jpeg.extend([0xFF, 0xD8, 0xFF, 0xE0]);
+        jpeg.extend(vec![0x22; 1020]);
+        jpeg.extend([0xFF, 0xD9]);
+        let mut image = vec![0u8; 512];
+        image.extend(&jpeg);
+        image.resize(8192, 0);
+        let source = dir.path().join("disk.img");
+        std::fs::write(&source, &image).unwrap();
+        let carved = dir.path().join("carved");
+        let (files, result) = Carver::new(CarveOptions {
+            source: source.clone(),
+            output_dir: carved.clone(),
+            sector_aligned: false,
+            min_size: 16,
+            ..Default::default()
+        })
+        .carve()
+        .await
+        .unwrap();
+        CarveListing {
+            source,
+            result,
+            files,
+        }
+        .save(&carved)
+        .unwrap();
+
+        let mut args = make_test_args(None);
+        args.out_dir = dir.path().join("out");
+        args.carve_results = Some(carved);
+        let mut app = App::new(args).await.unwrap();
+        assert_eq!(app.tab, Tab::Carve);
+        let browser = app.carve_browser.as_ref().unwrap();
+        assert_eq!(browser.selected().unwrap().offset, 512);
+        assert_eq!(browser.selected().unwrap().size, 1024);
+
+        app.on_key(KeyEvent::new(KeyCode::Char(' '), KeyModifiers::NONE));
+        app.on_key(KeyEvent::new(KeyCode::Char('B'), KeyModifiers::NONE));
+        assert_eq!(
+            app.status_message,
+            "Re-extract boundary: ignore next header"
+        );
+        app.on_key(KeyEvent::new(KeyCode::Char('R'), KeyModifiers::NONE));
+        wait_for_jobs(&mut app).await;
+
+        let browser = app.carve_browser.as_ref().unwrap();
+        let file = browser.selected().unwrap();
+        assert_eq!(file.size, jpeg.len() as u64);
+        assert!(browser.marked.is_empty());
+        let path = file.path.as_ref().unwrap();
+        assert!(path.starts_with(dir.path().join("out").join("reextracted")));
+        assert_eq!(std::fs::read(path).unwrap(), jpeg);
+    }
 }
@@ -0,0 +1,133 @@
+//! Carve results browser for the TUI
+//!
+//! Lists the files of a carve (the one just run with 'c', or one loaded with
+//! `--carve-results`) and tracks which of them to extract again and at what
+//! boundary.
+
+use std::collections::BTreeSet;
+
+use crate::carve::{CarveListing, CarvedFile, ReextractBoundary};
+
+/// Cursor, marks and re-extract boundary over a [`CarveListing`]
+#[derive(Debug, Clone)]
+pub struct CarveBrowser {
+    pub listing: CarveListing,
+    /// Index into `listing.files`
+    pub cursor: usize,
+    /// Files chosen for re-extraction
+    pub marked: BTreeSet<usize>,
+    /// Where re-extracted files end
+    pub boundary: ReextractBoundary,
+}
+
+impl CarveBrowser {
+    pub fn new(mut listing: CarveListing) -> Self {
+        listing.files.sort_by_key(|f| f.offset);
+        Self {
+            listing,
+            cursor: 0,
+            marked: BTreeSet::new(),
+            boundary: ReextractBoundary::default(),
+        }
+    }
+
+    pub fn selected(&self) -> Option<&CarvedFile> {
+        self.listing.files.get(self.cursor)
+    }
+
+    pub fn move_cursor(&mut self, delta: isize) {
+        let last = self.listing.files.len().saturating_sub(1);
+        self.cursor = self.cursor.saturating_add_signed(delta).min(last);
+    }
+
+    /// Mark or unmark the file under the cursor
+    pub fn toggle_mark(&mut self) {
+        if self.cursor < self.listing.files.len() && !self.marked.remove(&self.cursor) {
+            self.marked.insert(self.cursor);
+        }
+    }
+
+    pub fn cycle_boundary(&mut self) {
+        self.boundary = self.boundary.next();
+    }
+
+    /// Files to re-extract: the marked ones, or the one under the cursor
+    pub fn targets(&self) -> Vec<CarvedFile> {
+        if self.marked.is_empty() {
+            self.selected().cloned().into_iter().collect()
+        } else {
+            self.marked
+                .iter()
+                .filter_map(|&i| self.listing.files.get(i).cloned())
+                .collect()
+        }
+    }
+
+    /// Replace the entries `files` were re-extracted from and clear the marks
+    pub fn apply_reextracted(&mut self, files: Vec<CarvedFile>) {
+        for file in files {
+            if let Some(entry) = self
+                .listing
+                .files
+                .iter_mut()
+                .find(|f| f.offset == file.offset)
+            {
+                *entry = file;
+            }
+        }
+        self.marked.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::carve::{BoundaryMethod, CarveResult};
+    use crate::core::FileType;
+
+    fn carved(offset: u64) -> CarvedFile {
+        CarvedFile {
+            offset,
+            size: 100,
+            signature_name: "JPEG".into(),
+            extension: "jpg".into(),
+            file_type: FileType::Image,
+            boundary_method: BoundaryMethod::NextHeader,
+            hash: None,
+            bad_region_bytes: 0,
+            path: None,
+            page_check: None,
+            integrity: None,
+            confidence: None,
+        }
+    }
+
+    #[test]
+    fn test_targets_follow_marks_and_reextract_replaces_entries() {
+        let mut browser = CarveBrowser::new(CarveListing {
+            source: "disk.img".into(),
+            result: CarveResult::default(),
+            files: vec![carved(4096), carved(0), carved(8192)],
+        });
+        assert_eq!(browser.selected().unwrap().offset, 0, "sorted by offset");
+        assert_eq!(browser.targets().len(), 1, "cursor file when none marked");
+
+        browser.move_cursor(5);
+        assert_eq!(browser.cursor, 2);
+        browser.toggle_mark();
+        browser.move_cursor(-2);
+        browser.toggle_mark();
+        let offsets: Vec<u64> = browser.targets().iter().map(|f| f.offset).collect();
+        assert_eq!(offsets, vec![0, 8192]);
+
+        browser.cycle_boundary();
+        assert_eq!(browser.boundary, ReextractBoundary::IgnoreNextHeader);
+
+        let mut redone = carved(8192);
+        redone.size = 300;
+        redone.boundary_method = BoundaryMethod::FooterScan;
+        browser.apply_reextracted(vec![redone]);
+        assert_eq!(browser.listing.files[2].size, 300);
+        assert!(browser.marked.is_empty());
+    }
+}
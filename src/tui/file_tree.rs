@@ -96,6 +96,13 @@ impl FileTree {
             .map(|n| n.path.clone())
     }
 
+    /// Get the currently selected node
+    pub fn selected_node(&self) -> Option<&TreeNode> {
+        self.visible
+            .get(self.selected)
+            .and_then(|&idx| self.nodes.get(idx))
+    }
+
     /// Get visible nodes for rendering
     pub fn visible_nodes(&self) -> Vec<&TreeNode> {
         self.visible
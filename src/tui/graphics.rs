@@ -0,0 +1,389 @@
+//! Inline thumbnails in the terminal
+//!
+//! Kitty, iTerm2 and sixel terminals get the real thumbnail drawn over the
+//! preview pane with their graphics protocol; every other terminal gets a
+//! half-block rendering ('▀' with the upper pixel as foreground and the
+//! lower as background), which ratatui draws like any other text.
+//!
+//! Protocol images live outside ratatui's buffer, so the event loop draws
+//! them after each frame in which the preview changed (see [`Placement`]).
+
+use std::io::{self, Write};
+
+use base64::Engine;
+use image::imageops::FilterType;
+use image::{DynamicImage, RgbaImage};
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
+
+use crate::cli::GraphicsMode;
+
+/// Payload bytes per kitty escape sequence
+const KITTY_CHUNK: usize = 4096;
+
+/// Cell size assumed when the terminal doesn't report its pixel size
+const DEFAULT_CELL_PX: (u32, u32) = (8, 16);
+
+/// How thumbnails reach the terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphicsProtocol {
+    Kitty,
+    Iterm2,
+    Sixel,
+    /// Half-block characters (works everywhere with true colour)
+    Blocks,
+    /// No thumbnails
+    Off,
+}
+
+impl GraphicsProtocol {
+    /// Resolve `--graphics`, sniffing the terminal for `auto`
+    pub fn from_mode(mode: GraphicsMode) -> Self {
+        match mode {
+            GraphicsMode::Auto => Self::detect(|name| std::env::var(name).ok()),
+            GraphicsMode::Kitty => Self::Kitty,
+            GraphicsMode::Iterm2 => Self::Iterm2,
+            GraphicsMode::Sixel => Self::Sixel,
+            GraphicsMode::Blocks => Self::Blocks,
+            GraphicsMode::Off => Self::Off,
+        }
+    }
+
+    /// Guess the protocol from the environment. Terminals can't be asked
+    /// without reading their reply from stdin, so this goes by the names
+    /// they announce themselves with.
+    fn detect(var: impl Fn(&str) -> Option<String>) -> Self {
+        let term = var("TERM").unwrap_or_default();
+        let program = var("TERM_PROGRAM").unwrap_or_default();
+        // Multiplexers swallow graphics escapes
+        if var("TMUX").is_some() || term.starts_with("screen") {
+            return Self::Blocks;
+        }
+        if var("KITTY_WINDOW_ID").is_some() || term == "xterm-kitty" || program == "ghostty" {
+            Self::Kitty
+        } else if matches!(program.as_str(), "iTerm.app" | "WezTerm") {
+            Self::Iterm2
+        } else if ["foot", "mlterm", "yaft", "contour"]
+            .iter()
+            .any(|t| term.starts_with(t))
+        {
+            Self::Sixel
+        } else {
+            Self::Blocks
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Kitty => "kitty",
+            Self::Iterm2 => "iTerm2",
+            Self::Sixel => "sixel",
+            Self::Blocks => "blocks",
+            Self::Off => "off",
+        }
+    }
+
+    /// Whether images are drawn with escape sequences outside ratatui
+    pub fn is_inline(&self) -> bool {
+        matches!(self, Self::Kitty | Self::Iterm2 | Self::Sixel)
+    }
+}
+
+/// Thumbnails of the file under the cursor
+pub struct Preview {
+    /// Indexed path the thumbnails belong to
+    pub path: String,
+    /// 64px thumbnail, enough for the half-block rendering
+    pub small: RgbaImage,
+    /// 512px thumbnail drawn by graphics protocols
+    pub large: RgbaImage,
+}
+
+/// An image drawn by a graphics protocol: which file, and where
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placement {
+    pub path: String,
+    pub area: Rect,
+}
+
+/// Largest size with `img`'s aspect ratio that fits `max_w` x `max_h`
+fn fit(img: &RgbaImage, max_w: u32, max_h: u32) -> (u32, u32) {
+    let (w, h) = (img.width().max(1) as f64, img.height().max(1) as f64);
+    let scale = (max_w as f64 / w).min(max_h as f64 / h);
+    (
+        ((w * scale) as u32).clamp(1, max_w.max(1)),
+        ((h * scale) as u32).clamp(1, max_h.max(1)),
+    )
+}
+
+fn resize(img: &RgbaImage, width: u32, height: u32) -> RgbaImage {
+    DynamicImage::ImageRgba8(img.clone())
+        .resize_exact(width, height, FilterType::Triangle)
+        .to_rgba8()
+}
+
+/// Half-block rendering of `img` in at most `cols` x `rows` cells
+pub fn block_lines(img: &RgbaImage, cols: u16, rows: u16) -> Vec<Line<'static>> {
+    if cols == 0 || rows == 0 {
+        return Vec::new();
+    }
+    // A cell is about twice as tall as wide and holds two pixel rows
+    let (w, h) = fit(img, cols as u32, rows as u32 * 2);
+    let small = resize(img, w, h);
+    let rgb = |x, y| {
+        if y >= h {
+            return Color::Reset;
+        }
+        let p = small.get_pixel(x, y).0;
+        Color::Rgb(p[0], p[1], p[2])
+    };
+    (0..h.div_ceil(2))
+        .map(|row| {
+            Line::from(
+                (0..w)
+                    .map(|x| {
+                        Span::styled(
+                            "\u{2580}",
+                            Style::default().fg(rgb(x, row * 2)).bg(rgb(x, row * 2 + 1)),
+                        )
+                    })
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .collect()
+}
+
+/// Pixel size of one terminal cell
+fn cell_pixels() -> (u32, u32) {
+    crossterm::terminal::window_size()
+        .ok()
+        .filter(|s| s.width > 0 && s.height > 0 && s.columns > 0 && s.rows > 0)
+        .map(|s| ((s.width / s.columns) as u32, (s.height / s.rows) as u32))
+        .unwrap_or(DEFAULT_CELL_PX)
+}
+
+fn encode_png(img: &RgbaImage) -> io::Result<Vec<u8>> {
+    let mut png = Vec::new();
+    DynamicImage::ImageRgba8(img.clone())
+        .write_to(&mut io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .map_err(io::Error::other)?;
+    Ok(png)
+}
+
+/// Escape sequence drawing `img` into `cols` x `rows` cells from the cursor
+pub fn encode(
+    protocol: GraphicsProtocol,
+    img: &RgbaImage,
+    cols: u16,
+    rows: u16,
+) -> io::Result<Vec<u8>> {
+    let (cell_w, cell_h) = cell_pixels();
+    let (w, h) = fit(img, cols as u32 * cell_w, rows as u32 * cell_h);
+    // Cells the image actually covers, so it isn't stretched
+    let (used_cols, used_rows) = (w.div_ceil(cell_w).max(1), h.div_ceil(cell_h).max(1));
+    match protocol {
+        GraphicsProtocol::Kitty => {
+            let b64 = base64::engine::general_purpose::STANDARD.encode(encode_png(img)?);
+            let chunks: Vec<&[u8]> = b64.as_bytes().chunks(KITTY_CHUNK).collect();
+            let mut out = Vec::with_capacity(b64.len() + chunks.len() * 32);
+            for (i, chunk) in chunks.iter().enumerate() {
+                let more = u8::from(i + 1 < chunks.len());
+                if i == 0 {
+                    write!(
+                        out,
+                        "\x1b_Ga=T,f=100,q=2,c={},r={},m={};",
+                        used_cols, used_rows, more
+                    )?;
+                } else {
+                    write!(out, "\x1b_Gm={};", more)?;
+                }
+                out.extend_from_slice(chunk);
+                out.extend_from_slice(b"\x1b\\");
+            }
+            Ok(out)
+        }
+        GraphicsProtocol::Iterm2 => {
+            let png = encode_png(img)?;
+            let mut out = Vec::new();
+            write!(
+                out,
+                "\x1b]1337;File=inline=1;size={};width={};height={};preserveAspectRatio=1:",
+                png.len(),
+                used_cols,
+                used_rows
+            )?;
+            out.extend_from_slice(
+                base64::engine::general_purpose::STANDARD
+                    .encode(png)
+                    .as_bytes(),
+            );
+            out.push(0x07);
+            Ok(out)
+        }
+        GraphicsProtocol::Sixel => Ok(sixel(&resize(img, w, h))),
+        GraphicsProtocol::Blocks | GraphicsProtocol::Off => Ok(Vec::new()),
+    }
+}
+
+/// Draw `img` over `area` at the terminal's current screen
+pub fn draw<W: Write>(
+    out: &mut W,
+    protocol: GraphicsProtocol,
+    img: &RgbaImage,
+    area: Rect,
+) -> io::Result<()> {
+    let payload = encode(protocol, img, area.width, area.height)?;
+    crossterm::queue!(
+        out,
+        crossterm::cursor::SavePosition,
+        crossterm::cursor::MoveTo(area.x, area.y)
+    )?;
+    out.write_all(&payload)?;
+    crossterm::queue!(out, crossterm::cursor::RestorePosition)?;
+    out.flush()
+}
+
+/// Remove protocol images before the screen is redrawn. Kitty keeps images
+/// on their own layer; sixel and iTerm2 images are painted into the cells
+/// and go away when ratatui repaints them.
+pub fn clear<W: Write>(out: &mut W, protocol: GraphicsProtocol) -> io::Result<()> {
+    if protocol == GraphicsProtocol::Kitty {
+        out.write_all(b"\x1b_Ga=d,q=2\x1b\\")?;
+        out.flush()?;
+    }
+    Ok(())
+}
+
+/// Sixel encoding with a 6x6x6 colour cube
+fn sixel(img: &RgbaImage) -> Vec<u8> {
+    let level = |v: u8| (v as u32 * 5 + 127) / 255;
+    let index = |p: &image::Rgba<u8>| -> usize {
+        if p.0[3] < 128 {
+            return usize::MAX;
+        }
+        (level(p.0[0]) * 36 + level(p.0[1]) * 6 + level(p.0[2])) as usize
+    };
+
+    let (w, h) = img.dimensions();
+    let mut out = Vec::new();
+    // DCS with 1:1 pixel aspect and transparent background, then the raster size
+    let _ = write!(out, "\x1bP0;1;0q\"1;1;{};{}", w, h);
+    for i in 0..216u32 {
+        let _ = write!(
+            out,
+            "#{};2;{};{};{}",
+            i,
+            i / 36 * 20,
+            i / 6 % 6 * 20,
+            i % 6 * 20
+        );
+    }
+
+    let mut bits = vec![0u8; w as usize];
+    for band in (0..h).step_by(6) {
+        let rows = band..(band + 6).min(h);
+        let mut colors: Vec<usize> = rows
+            .clone()
+            .flat_map(|y| (0..w).map(move |x| (x, y)))
+            .map(|(x, y)| index(img.get_pixel(x, y)))
+            .filter(|&c| c != usize::MAX)
+            .collect();
+        colors.sort_unstable();
+        colors.dedup();
+
+        for (n, &color) in colors.iter().enumerate() {
+            bits.fill(0);
+            for y in rows.clone() {
+                for x in 0..w {
+                    if index(img.get_pixel(x, y)) == color {
+                        bits[x as usize] |= 1 << (y - band);
+                    }
+                }
+            }
+            let _ = write!(out, "#{}", color);
+            let mut x = 0;
+            while x < bits.len() {
+                let run = bits[x..].iter().take_while(|&&b| b == bits[x]).count();
+                let ch = b'?' + bits[x];
+                if run > 3 {
+                    let _ = write!(out, "!{}", run);
+                    out.push(ch);
+                } else {
+                    out.extend(std::iter::repeat_n(ch, run));
+                }
+                x += run;
+            }
+            // Back to the start of the band for the next colour
+            if n + 1 < colors.len() {
+                out.push(b'$');
+            }
+        }
+        out.push(b'-');
+    }
+    out.extend_from_slice(b"\x1b\\");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_protocol_detection_and_encodings() {
+        let env = |pairs: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                pairs
+                    .iter()
+                    .find(|(k, _)| *k == name)
+                    .map(|(_, v)| v.to_string())
+            }
+        };
+        assert_eq!(
+            GraphicsProtocol::detect(env(&[("TERM", "xterm-kitty")])),
+            GraphicsProtocol::Kitty
+        );
+        assert_eq!(
+            GraphicsProtocol::detect(env(&[("TERM_PROGRAM", "iTerm.app")])),
+            GraphicsProtocol::Iterm2
+        );
+        assert_eq!(
+            GraphicsProtocol::detect(env(&[("TERM", "foot")])),
+            GraphicsProtocol::Sixel
+        );
+        assert_eq!(
+            GraphicsProtocol::detect(env(&[("TERM", "xterm-kitty"), ("TMUX", "1")])),
+            GraphicsProtocol::Blocks
+        );
+
+        // Top half red, bottom half blue
+        let img = RgbaImage::from_fn(16, 16, |_, y| {
+            if y < 8 {
+                image::Rgba([255, 0, 0, 255])
+            } else {
+                image::Rgba([0, 0, 255, 255])
+            }
+        });
+
+        let lines = block_lines(&img, 8, 4);
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0].spans.len(), 8);
+        assert_eq!(lines[0].spans[0].style.fg, Some(Color::Rgb(255, 0, 0)));
+        assert_eq!(lines[3].spans[0].style.bg, Some(Color::Rgb(0, 0, 255)));
+
+        let sixel = encode(GraphicsProtocol::Sixel, &img, 2, 1).unwrap();
+        assert!(sixel.starts_with(b"\x1bP"));
+        assert!(sixel.ends_with(b"\x1b\\"));
+        let body = String::from_utf8_lossy(&sixel);
+        assert!(
+            body.contains("#180") && body.contains("#5"),
+            "red and blue used"
+        );
+
+        let kitty = encode(GraphicsProtocol::Kitty, &img, 2, 1).unwrap();
+        assert!(kitty.starts_with(b"\x1b_Ga=T,f=100"));
+        let iterm = encode(GraphicsProtocol::Iterm2, &img, 2, 1).unwrap();
+        assert!(iterm.starts_with(b"\x1b]1337;File=inline=1"));
+        assert_eq!(iterm.last(), Some(&0x07));
+    }
+}
@@ -0,0 +1,289 @@
+//! Background jobs for the TUI
+//!
+//! Indexing, carving, re-extraction, dedup, purge and export run on tokio's blocking pool
+//! so the event loop keeps drawing and handling keys. Jobs report progress
+//! and their result over a channel that [`JobRunner::poll`] drains once per
+//! frame.
+
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+use crate::carve::{CarveListing, CarvedFile};
+use crate::core::FileEntry;
+use crate::dedup::DedupReport;
+use crate::export::ExportResult;
+
+/// How long a finished job stays in the jobs pane
+const FINISHED_LINGER: Duration = Duration::from_secs(5);
+
+/// Kind of background job (at most one of each runs at a time)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum JobKind {
+    Index,
+    Carve,
+    Reextract,
+    Dedup,
+    Purge,
+    Export,
+}
+
+impl JobKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            JobKind::Index => "Index",
+            JobKind::Carve => "Carve",
+            JobKind::Reextract => "Re-extract",
+            JobKind::Dedup => "Dedup",
+            JobKind::Purge => "Purge",
+            JobKind::Export => "Export",
+        }
+    }
+}
+
+/// What a finished job hands back to the app
+#[derive(Debug)]
+pub enum JobOutput {
+    Indexed {
+        files: Vec<String>,
+        entries: Vec<FileEntry>,
+    },
+    Carved(CarveListing),
+    Reextracted(Vec<CarvedFile>),
+    Deduped(DedupReport),
+    /// Duplicates that are gone after a purge, and why others weren't removed
+    Purged {
+        deleted: Vec<PathBuf>,
+        errors: Vec<String>,
+    },
+    Exported(ExportResult),
+}
+
+/// Where a job is in its life
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobStatus {
+    Running,
+    Done,
+    Failed(String),
+}
+
+/// One job as shown in the jobs pane
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub id: u64,
+    pub kind: JobKind,
+    pub status: JobStatus,
+    /// Units of work done so far (files, bytes, ...)
+    pub done: u64,
+    /// Total units of work, when known up front
+    pub total: Option<u64>,
+    /// What the job is working on right now
+    pub detail: String,
+    started: Instant,
+    finished: Option<Instant>,
+}
+
+impl Job {
+    /// Fraction complete, when the total is known
+    pub fn ratio(&self) -> Option<f64> {
+        match self.status {
+            JobStatus::Running => self
+                .total
+                .filter(|&t| t > 0)
+                .map(|t| (self.done as f64 / t as f64).min(1.0)),
+            _ => Some(1.0),
+        }
+    }
+
+    /// Time spent running (frozen once the job finishes)
+    pub fn elapsed(&self) -> Duration {
+        self.finished
+            .unwrap_or_else(Instant::now)
+            .duration_since(self.started)
+    }
+}
+
+enum JobEvent {
+    Progress {
+        id: u64,
+        done: u64,
+        total: Option<u64>,
+        detail: String,
+    },
+    Finished {
+        id: u64,
+        result: Result<JobOutput, String>,
+    },
+}
+
+/// Handle a running job reports progress through
+#[derive(Clone)]
+pub struct JobProgress {
+    id: u64,
+    tx: UnboundedSender<JobEvent>,
+}
+
+impl JobProgress {
+    pub fn update(&self, done: u64, total: Option<u64>, detail: impl Into<String>) {
+        // The runner only goes away when the TUI quits
+        let _ = self.tx.send(JobEvent::Progress {
+            id: self.id,
+            done,
+            total,
+            detail: detail.into(),
+        });
+    }
+}
+
+/// Spawns jobs and collects their progress and results
+pub struct JobRunner {
+    tx: UnboundedSender<JobEvent>,
+    rx: UnboundedReceiver<JobEvent>,
+    jobs: Vec<Job>,
+    next_id: u64,
+}
+
+impl Default for JobRunner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JobRunner {
+    pub fn new() -> Self {
+        let (tx, rx) = unbounded_channel();
+        Self {
+            tx,
+            rx,
+            jobs: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Jobs still running or finished within the last few seconds
+    pub fn jobs(&self) -> &[Job] {
+        &self.jobs
+    }
+
+    pub fn is_running(&self, kind: JobKind) -> bool {
+        self.jobs
+            .iter()
+            .any(|j| j.kind == kind && j.status == JobStatus::Running)
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.jobs.iter().any(|j| j.status == JobStatus::Running)
+    }
+
+    /// Run `work` on the blocking pool. Async work can `block_on` the
+    /// current runtime handle from inside it. Must be called from within a
+    /// tokio runtime.
+    pub fn spawn<F>(&mut self, kind: JobKind, detail: impl Into<String>, work: F)
+    where
+        F: FnOnce(JobProgress) -> Result<JobOutput> + Send + 'static,
+    {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.jobs.push(Job {
+            id,
+            kind,
+            status: JobStatus::Running,
+            done: 0,
+            total: None,
+            detail: detail.into(),
+            started: Instant::now(),
+            finished: None,
+        });
+
+        let tx = self.tx.clone();
+        tokio::task::spawn_blocking(move || {
+            let progress = JobProgress { id, tx: tx.clone() };
+            let result = work(progress).map_err(|e| format!("{:#}", e));
+            let _ = tx.send(JobEvent::Finished { id, result });
+        });
+    }
+
+    /// Apply queued progress and return the results of jobs that finished
+    /// since the last call
+    pub fn poll(&mut self) -> Vec<(JobKind, Result<JobOutput, String>)> {
+        let mut finished = Vec::new();
+        while let Ok(event) = self.rx.try_recv() {
+            match event {
+                JobEvent::Progress {
+                    id,
+                    done,
+                    total,
+                    detail,
+                } => {
+                    if let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) {
+                        job.done = done;
+                        job.total = total;
+                        job.detail = detail;
+                    }
+                }
+                JobEvent::Finished { id, result } => {
+                    let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) else {
+                        continue;
+                    };
+                    job.status = match &result {
+                        Ok(_) => JobStatus::Done,
+                        Err(e) => JobStatus::Failed(e.clone()),
+                    };
+                    job.finished = Some(Instant::now());
+                    finished.push((job.kind, result));
+                }
+            }
+        }
+
+        let now = Instant::now();
+        self.jobs.retain(|j| {
+            j.finished
+                .is_none_or(|at| now.duration_since(at) < FINISHED_LINGER)
+        });
+        finished
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_progress_and_results_arrive_through_poll() {
+        let mut runner = JobRunner::new();
+        let (go_tx, go_rx) = std::sync::mpsc::channel::<()>();
+        runner.spawn(JobKind::Index, "waiting", move |progress| {
+            progress.update(1, Some(4), "step one");
+            go_rx.recv()?;
+            Ok(JobOutput::Indexed {
+                files: Vec::new(),
+                entries: Vec::new(),
+            })
+        });
+        runner.spawn(JobKind::Carve, "failing", |_| anyhow::bail!("no image"));
+        assert!(runner.is_running(JobKind::Index));
+
+        let mut results = Vec::new();
+        while results.is_empty() || runner.jobs()[0].done == 0 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            results.extend(runner.poll());
+        }
+        assert!(matches!(&results[0], (JobKind::Carve, Err(e)) if e == "no image"));
+        let index = &runner.jobs()[0];
+        assert_eq!(index.detail, "step one");
+        assert_eq!(index.ratio(), Some(0.25));
+
+        go_tx.send(()).unwrap();
+        while runner.is_busy() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            results.extend(runner.poll());
+        }
+        assert!(matches!(
+            &results[1],
+            (JobKind::Index, Ok(JobOutput::Indexed { .. }))
+        ));
+        assert_eq!(runner.jobs().len(), 2, "finished jobs linger in the pane");
+    }
+}
@@ -1,10 +1,16 @@
 //! TUI Module - Terminal User Interface powered by ratatui
 //!
 //! Full-featured terminal UI with file tree, vim keybindings,
-//! search, export, dedup, and bad sector visualization.
+//! search, export, dedup, bad sector visualization and a date timeline.
+//! Indexing, carving, dedup and export run as background jobs.
 
 mod app;
+pub mod carved;
 pub mod file_tree;
+pub mod graphics;
+pub mod jobs;
+pub mod throughput;
+pub mod timeline;
 mod ui;
 
 pub use app::{App, AppState};
@@ -19,7 +25,6 @@ use ratatui::prelude::*;
 use std::time::Duration;
 
 use crate::cli::TuiArgs;
-use crate::core::DrillEngine;
 
 /// Run the TUI application
 pub async fn run_tui(args: TuiArgs) -> Result<()> {
@@ -34,52 +39,14 @@ pub async fn run_tui(args: TuiArgs) -> Result<()> {
     // Create app
     let mut app = App::new(args.clone()).await?;
 
-    // If source provided, index it
-    if let Some(ref source) = args.source {
-        app.status_message = format!("Indexing {}...", source.display());
-        app.state = AppState::Indexing;
-
-        let index_start = std::time::Instant::now();
-
-        // Index synchronously before entering the event loop
-        let engine = DrillEngine::new(source.clone()).await?;
-        let index_args = crate::cli::IndexArgs {
-            source: source.clone(),
-            resume: false,
-            index_file: None,
-            skip_hidden: false,
-            depth: None,
-            extensions: None,
-            thumbnails: false,
-            workers: None,
-            checkpoint_interval: 1000,
-            bad_sector_report: None,
-            block_size: 4096,
-        };
-        engine.index_with_progress(&index_args).await?;
-
-        // Populate file tree and cached entries from indexed files
-        let files = engine.get_all_files().await?;
-        let paths: Vec<String> = files.iter().map(|p| p.to_string()).collect();
-        app.file_tree = file_tree::FileTree::from_paths(&paths);
-        app.file_count = paths.len();
-        app.cached_entries = engine.get_all_entries().await;
-        app.index_elapsed = index_start.elapsed();
-
-        // Compute file type distribution stats
-        app.compute_stats();
-
-        app.state = AppState::Browse;
-        app.status_message = format!(
-            "Indexed {} files ({}) in {:.1}s",
-            app.file_count,
-            humansize::format_size(app.total_size, humansize::BINARY),
-            app.index_elapsed.as_secs_f64(),
-        );
+    // Index the source in the background while the UI comes up
+    if app.source.is_some() {
+        app.start_index();
     }
 
     // Run main loop
     let result = run_event_loop(&mut terminal, &mut app);
+    graphics::clear(&mut std::io::stdout(), app.graphics).ok();
 
     // Restore terminal
     disable_raw_mode()?;
@@ -94,14 +61,39 @@ pub async fn run_tui(args: TuiArgs) -> Result<()> {
         eprintln!("TUI error: {}", e);
     }
 
+    // The runtime would wait for blocking jobs on shutdown
+    if app.jobs.is_busy() {
+        eprintln!("Abandoned unfinished background jobs");
+        std::process::exit(0);
+    }
+
     Ok(())
 }
 
 /// Main TUI event loop
 fn run_event_loop<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<()> {
+    let mut out = std::io::stdout();
+    // Protocol image currently on screen
+    let mut shown: Option<graphics::Placement> = None;
     loop {
+        app.poll_jobs();
+        app.throughput.tick(std::time::Instant::now());
         terminal.draw(|frame| ui::draw(frame, app))?;
 
+        let wanted = app.preview_placement();
+        if wanted != shown {
+            if shown.is_some() {
+                // Repaint every cell to wipe sixel/iTerm2 pixels
+                graphics::clear(&mut out, app.graphics)?;
+                terminal.clear()?;
+                terminal.draw(|frame| ui::draw(frame, app))?;
+            }
+            if let (Some(placement), Some(preview)) = (&wanted, &app.preview) {
+                graphics::draw(&mut out, app.graphics, &preview.large, placement.area)?;
+            }
+            shown = wanted;
+        }
+
         // Poll for events with timeout
         if event::poll(Duration::from_millis(100))? {
             if let Event::Key(key) = event::read()? {
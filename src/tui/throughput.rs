@@ -0,0 +1,173 @@
+//! Read/write throughput and error-rate history for the status bar sparkline
+//!
+//! Operations bump the shared [`IoCounters`]; the event loop calls
+//! [`ThroughputHistory::tick`], which turns counter deltas into one-second
+//! buckets covering the last few minutes.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Width of one history bucket
+pub const BUCKET: Duration = Duration::from_secs(1);
+
+/// Buckets kept (three minutes)
+pub const HISTORY_LEN: usize = 180;
+
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Cumulative I/O counters shared with running operations
+#[derive(Debug, Clone, Default)]
+pub struct IoCounters {
+    read: Arc<AtomicU64>,
+    written: Arc<AtomicU64>,
+    errors: Arc<AtomicU64>,
+}
+
+impl IoCounters {
+    pub fn add_read(&self, bytes: u64) {
+        self.read.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn add_written(&self, bytes: u64) {
+        self.written.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn add_errors(&self, count: u64) {
+        self.errors.fetch_add(count, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> IoSample {
+        IoSample {
+            read: self.read.load(Ordering::Relaxed),
+            written: self.written.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Bytes read, bytes written and errors in one bucket
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IoSample {
+    pub read: u64,
+    pub written: u64,
+    pub errors: u64,
+}
+
+/// Rolling per-second history built from [`IoCounters`]
+#[derive(Debug)]
+pub struct ThroughputHistory {
+    counters: IoCounters,
+    last: IoSample,
+    last_tick: Instant,
+    buckets: VecDeque<IoSample>,
+}
+
+impl ThroughputHistory {
+    pub fn new(counters: IoCounters) -> Self {
+        Self {
+            last: counters.snapshot(),
+            counters,
+            last_tick: Instant::now(),
+            buckets: VecDeque::with_capacity(HISTORY_LEN),
+        }
+    }
+
+    /// Close every bucket that has elapsed since the last tick. Activity
+    /// since then lands in the first closed bucket; any further elapsed
+    /// buckets are recorded as idle.
+    pub fn tick(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_tick);
+        let buckets = (elapsed.as_millis() / BUCKET.as_millis()) as usize;
+        if buckets == 0 {
+            return;
+        }
+        let current = self.counters.snapshot();
+        self.push(IoSample {
+            read: current.read - self.last.read,
+            written: current.written - self.last.written,
+            errors: current.errors - self.last.errors,
+        });
+        for _ in 1..buckets.min(HISTORY_LEN) {
+            self.push(IoSample::default());
+        }
+        self.last = current;
+        self.last_tick += BUCKET * buckets as u32;
+    }
+
+    fn push(&mut self, sample: IoSample) {
+        if self.buckets.len() == HISTORY_LEN {
+            self.buckets.pop_front();
+        }
+        self.buckets.push_back(sample);
+    }
+
+    /// Whether anything happened within the kept history
+    pub fn is_active(&self) -> bool {
+        self.buckets.iter().any(|s| *s != IoSample::default())
+    }
+
+    /// The most recent `n` buckets, oldest first
+    pub fn recent(&self, n: usize) -> Vec<IoSample> {
+        let skip = self.buckets.len().saturating_sub(n);
+        self.buckets.iter().skip(skip).copied().collect()
+    }
+
+    /// The latest closed bucket (per-second rates)
+    pub fn latest(&self) -> IoSample {
+        self.buckets.back().copied().unwrap_or_default()
+    }
+}
+
+/// Render values as a block-character sparkline scaled to their maximum
+pub fn sparkline(values: &[u64]) -> String {
+    let max = values.iter().copied().max().unwrap_or(0);
+    values
+        .iter()
+        .map(|&v| {
+            if max == 0 || v == 0 {
+                ' '
+            } else {
+                BARS[((v * (BARS.len() as u64 - 1)).div_ceil(max)) as usize]
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_buckets_and_sparkline() {
+        let counters = IoCounters::default();
+        let mut history = ThroughputHistory::new(counters.clone());
+        let start = history.last_tick;
+        assert!(!history.is_active());
+
+        counters.add_read(4096);
+        counters.add_errors(1);
+        history.tick(start + Duration::from_millis(500));
+        assert!(!history.is_active(), "bucket not closed yet");
+
+        history.tick(start + Duration::from_millis(1100));
+        assert_eq!(
+            history.latest(),
+            IoSample {
+                read: 4096,
+                written: 0,
+                errors: 1
+            }
+        );
+
+        counters.add_written(100);
+        history.tick(start + Duration::from_secs(4));
+        let recent = history.recent(10);
+        assert_eq!(recent.len(), 4);
+        assert_eq!(recent[1].written, 100);
+        assert_eq!(recent[3], IoSample::default());
+
+        assert_eq!(sparkline(&[0, 1, 4, 8]), " ▂▅█");
+    }
+}
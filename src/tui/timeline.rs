@@ -0,0 +1,411 @@
+//! Date histogram of indexed files for the timeline view
+//!
+//! Files are bucketed by modified or created date (in the display
+//! timezone) per year, month or day. Zooming into a bucket re-buckets just
+//! that span at the next finer resolution, so "everything from March 2023"
+//! is two zooms and a keypress away.
+
+use chrono::{Datelike, Months, NaiveDate};
+
+use crate::core::{DisplayTz, FileEntry};
+
+/// Timestamp the timeline buckets files by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimelineField {
+    Modified,
+    Created,
+}
+
+impl TimelineField {
+    pub fn label(&self) -> &'static str {
+        match self {
+            TimelineField::Modified => "modified",
+            TimelineField::Created => "created",
+        }
+    }
+
+    fn toggle(self) -> Self {
+        match self {
+            TimelineField::Modified => TimelineField::Created,
+            TimelineField::Created => TimelineField::Modified,
+        }
+    }
+
+    fn of(self, entry: &FileEntry) -> Option<chrono::DateTime<chrono::Utc>> {
+        match self {
+            TimelineField::Modified => entry.modified,
+            TimelineField::Created => entry.created,
+        }
+    }
+}
+
+/// Width of one histogram bucket
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    Year,
+    Month,
+    Day,
+}
+
+impl Resolution {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Resolution::Year => "year",
+            Resolution::Month => "month",
+            Resolution::Day => "day",
+        }
+    }
+
+    fn finer(self) -> Option<Self> {
+        match self {
+            Resolution::Year => Some(Resolution::Month),
+            Resolution::Month => Some(Resolution::Day),
+            Resolution::Day => None,
+        }
+    }
+
+    /// Start of the bucket containing `date`
+    fn floor(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Resolution::Year => date.with_ordinal(1).unwrap_or(date),
+            Resolution::Month => date.with_day(1).unwrap_or(date),
+            Resolution::Day => date,
+        }
+    }
+
+    /// Start of the bucket after the one starting at `start`
+    fn next(self, start: NaiveDate) -> NaiveDate {
+        match self {
+            Resolution::Year => start.checked_add_months(Months::new(12)),
+            Resolution::Month => start.checked_add_months(Months::new(1)),
+            Resolution::Day => start.succ_opt(),
+        }
+        .unwrap_or(NaiveDate::MAX)
+    }
+
+    /// Axis label of the bucket starting at `start`
+    fn short_label(self, start: NaiveDate) -> String {
+        match self {
+            Resolution::Year => start.format("%Y").to_string(),
+            Resolution::Month => start.format("%b").to_string(),
+            Resolution::Day => start.format("%d").to_string(),
+        }
+    }
+
+    /// Full label of the bucket starting at `start`
+    fn label_of(self, start: NaiveDate) -> String {
+        match self {
+            Resolution::Year => start.format("%Y").to_string(),
+            Resolution::Month => start.format("%b %Y").to_string(),
+            Resolution::Day => start.format("%Y-%m-%d").to_string(),
+        }
+    }
+}
+
+/// Files whose date falls in `[start, end)`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bucket {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub label: String,
+    pub count: usize,
+    pub bytes: u64,
+}
+
+/// Zoom level to return to with [`Timeline::zoom_out`]
+#[derive(Debug, Clone)]
+struct Level {
+    span: Option<(NaiveDate, NaiveDate)>,
+    resolution: Resolution,
+    cursor: usize,
+}
+
+/// Histogram of file dates with a cursor, a range anchor and a zoom stack
+#[derive(Debug, Clone)]
+pub struct Timeline {
+    tz: DisplayTz,
+    field: TimelineField,
+    resolution: Resolution,
+    /// Dates shown (None = all dated files)
+    span: Option<(NaiveDate, NaiveDate)>,
+    buckets: Vec<Bucket>,
+    cursor: usize,
+    /// Other end of the range being selected ('v')
+    anchor: Option<usize>,
+    /// Files without the chosen timestamp
+    undated: usize,
+    zoom_stack: Vec<Level>,
+}
+
+impl Timeline {
+    /// Yearly histogram of all files by modified date
+    pub fn new(entries: &[FileEntry], tz: DisplayTz) -> Self {
+        let mut timeline = Self {
+            tz,
+            field: TimelineField::Modified,
+            resolution: Resolution::Year,
+            span: None,
+            buckets: Vec::new(),
+            cursor: 0,
+            anchor: None,
+            undated: 0,
+            zoom_stack: Vec::new(),
+        };
+        timeline.rebuild(entries);
+        timeline.cursor = timeline.buckets.len().saturating_sub(1);
+        timeline
+    }
+
+    pub fn buckets(&self) -> &[Bucket] {
+        &self.buckets
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn field(&self) -> TimelineField {
+        self.field
+    }
+
+    pub fn resolution(&self) -> Resolution {
+        self.resolution
+    }
+
+    pub fn undated(&self) -> usize {
+        self.undated
+    }
+
+    /// Label of the span being shown, e.g. "all time" or "2023"
+    pub fn span_label(&self) -> String {
+        match (self.span, self.zoom_stack.last()) {
+            (Some((start, _)), Some(level)) => level.resolution.label_of(start),
+            _ => "all time".to_string(),
+        }
+    }
+
+    /// Bucket indices of the selected range (the cursor bucket when no
+    /// range is being selected)
+    pub fn selected(&self) -> std::ops::RangeInclusive<usize> {
+        let anchor = self.anchor.unwrap_or(self.cursor);
+        anchor.min(self.cursor)..=anchor.max(self.cursor)
+    }
+
+    pub fn is_ranging(&self) -> bool {
+        self.anchor.is_some()
+    }
+
+    /// Label of the selected range, e.g. "Mar 2023" or "Mar 2023 – May 2023"
+    pub fn selected_label(&self) -> String {
+        let range = self.selected();
+        let (Some(first), Some(last)) = (
+            self.buckets.get(*range.start()),
+            self.buckets.get(*range.end()),
+        ) else {
+            return String::new();
+        };
+        let (from, to) = (
+            self.resolution.label_of(first.start),
+            self.resolution.label_of(last.start),
+        );
+        if from == to {
+            from
+        } else {
+            format!("{} – {}", from, to)
+        }
+    }
+
+    /// Files dated within the selected range
+    pub fn selected_files<'a>(&self, entries: &'a [FileEntry]) -> Vec<&'a FileEntry> {
+        let range = self.selected();
+        let (Some(first), Some(last)) = (
+            self.buckets.get(*range.start()),
+            self.buckets.get(*range.end()),
+        ) else {
+            return Vec::new();
+        };
+        let (start, end) = (first.start, last.end);
+        entries
+            .iter()
+            .filter(|e| {
+                self.date_of(e)
+                    .is_some_and(|date| date >= start && date < end)
+            })
+            .collect()
+    }
+
+    pub fn move_cursor(&mut self, delta: isize) {
+        let last = self.buckets.len().saturating_sub(1);
+        self.cursor = self.cursor.saturating_add_signed(delta).min(last);
+    }
+
+    pub fn jump_to(&mut self, end: bool) {
+        self.cursor = if end {
+            self.buckets.len().saturating_sub(1)
+        } else {
+            0
+        };
+    }
+
+    /// Start or drop a range at the cursor
+    pub fn toggle_anchor(&mut self) {
+        self.anchor = match self.anchor {
+            Some(_) => None,
+            None => Some(self.cursor),
+        };
+    }
+
+    /// Show the cursor bucket at the next finer resolution. Returns false
+    /// when already showing days.
+    pub fn zoom_in(&mut self, entries: &[FileEntry]) -> bool {
+        let (Some(finer), Some(bucket)) = (self.resolution.finer(), self.buckets.get(self.cursor))
+        else {
+            return false;
+        };
+        self.zoom_stack.push(Level {
+            span: self.span,
+            resolution: self.resolution,
+            cursor: self.cursor,
+        });
+        self.span = Some((bucket.start, bucket.end));
+        self.resolution = finer;
+        self.anchor = None;
+        self.rebuild(entries);
+        self.cursor = self.buckets.iter().position(|b| b.count > 0).unwrap_or(0);
+        true
+    }
+
+    /// Go back to the previous zoom level. Returns false at the top.
+    pub fn zoom_out(&mut self, entries: &[FileEntry]) -> bool {
+        let Some(level) = self.zoom_stack.pop() else {
+            return false;
+        };
+        self.span = level.span;
+        self.resolution = level.resolution;
+        self.anchor = None;
+        self.rebuild(entries);
+        self.cursor = level.cursor.min(self.buckets.len().saturating_sub(1));
+        true
+    }
+
+    /// Switch between modified and created dates, keeping the zoom
+    pub fn toggle_field(&mut self, entries: &[FileEntry]) {
+        self.field = self.field.toggle();
+        self.rebuild(entries);
+    }
+
+    fn date_of(&self, entry: &FileEntry) -> Option<NaiveDate> {
+        self.field.of(entry).map(|dt| self.tz.date(&dt))
+    }
+
+    /// Re-bucket `entries` for the current field, span and resolution
+    fn rebuild(&mut self, entries: &[FileEntry]) {
+        let dated: Vec<(NaiveDate, u64)> = entries
+            .iter()
+            .filter_map(|e| Some((self.date_of(e)?, e.size)))
+            .collect();
+        self.undated = entries.len() - dated.len();
+        self.buckets.clear();
+
+        let res = self.resolution;
+        let (first, end) = match self.span {
+            Some(span) => span,
+            None => {
+                let (Some(min), Some(max)) = (
+                    dated.iter().map(|d| d.0).min(),
+                    dated.iter().map(|d| d.0).max(),
+                ) else {
+                    self.cursor = 0;
+                    return;
+                };
+                (res.floor(min), res.next(res.floor(max)))
+            }
+        };
+
+        let mut start = first;
+        while start < end {
+            let next = res.next(start);
+            self.buckets.push(Bucket {
+                start,
+                end: next,
+                label: res.short_label(start),
+                count: 0,
+                bytes: 0,
+            });
+            start = next;
+        }
+        for (date, size) in dated {
+            if date < first || date >= end {
+                continue;
+            }
+            let i = self.buckets.partition_point(|b| b.end <= date);
+            self.buckets[i].count += 1;
+            self.buckets[i].bytes += size;
+        }
+        self.cursor = self.cursor.min(self.buckets.len().saturating_sub(1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use std::path::PathBuf;
+
+    fn entry(name: &str, modified: (i32, u32, u32), created: Option<(i32, u32, u32)>) -> FileEntry {
+        let at = |(y, m, d)| Utc.with_ymd_and_hms(y, m, d, 12, 0, 0).single();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(name);
+        std::fs::write(&path, b"x").unwrap();
+        let mut e = FileEntry::new(PathBuf::from(name), &std::fs::metadata(&path).unwrap());
+        e.size = 100;
+        e.modified = at(modified);
+        e.created = created.and_then(at);
+        e
+    }
+
+    #[test]
+    fn test_zoom_and_range_selection() {
+        let entries = vec![
+            entry("a.jpg", (2021, 6, 1), None),
+            entry("b.jpg", (2023, 3, 2), Some((2020, 1, 1))),
+            entry("c.jpg", (2023, 3, 30), None),
+            entry("d.jpg", (2023, 5, 9), None),
+        ];
+        let mut timeline = Timeline::new(&entries, DisplayTz::Utc);
+        let counts: Vec<_> = timeline.buckets().iter().map(|b| b.count).collect();
+        assert_eq!(counts, [1, 0, 1 + 2]);
+        assert_eq!(timeline.cursor(), 2);
+
+        // 2023 by month, cursor on the first month with files
+        assert!(timeline.zoom_in(&entries));
+        assert_eq!(timeline.buckets().len(), 12);
+        assert_eq!(timeline.selected_label(), "Mar 2023");
+        assert_eq!(timeline.span_label(), "2023");
+        let march: Vec<_> = timeline.selected_files(&entries);
+        assert_eq!(march.len(), 2);
+
+        // Mar – May
+        timeline.toggle_anchor();
+        timeline.move_cursor(2);
+        assert_eq!(timeline.selected_label(), "Mar 2023 – May 2023");
+        assert_eq!(timeline.selected_files(&entries).len(), 3);
+
+        assert!(timeline.zoom_in(&entries));
+        assert_eq!(timeline.buckets().len(), 31);
+        assert!(
+            !timeline.zoom_in(&entries),
+            "days are the finest resolution"
+        );
+
+        assert!(timeline.zoom_out(&entries));
+        assert!(timeline.zoom_out(&entries));
+        assert!(!timeline.zoom_out(&entries));
+        assert_eq!(timeline.resolution(), Resolution::Year);
+
+        timeline.toggle_field(&entries);
+        assert_eq!(timeline.undated(), 3);
+        assert_eq!(timeline.buckets().len(), 1);
+        assert_eq!(timeline.selected_label(), "2020");
+    }
+}
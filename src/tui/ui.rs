@@ -6,11 +6,20 @@ use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Clear, Gauge, List, ListItem, Padding, Paragraph, Tabs},
+    widgets::{
+        Bar, BarChart, BarGroup, Block, Borders, Clear, Gauge, LineGauge, List, ListItem, Padding,
+        Paragraph, Tabs,
+    },
     Frame,
 };
 
 use super::app::{App, AppState, Tab};
+use super::carved::CarveBrowser;
+use super::graphics::{block_lines, GraphicsProtocol};
+use super::jobs::JobStatus;
+use super::throughput::sparkline;
+use crate::analyze::RegionClass;
+use crate::carve::BoundaryMethod;
 use crate::core::FileType;
 
 // ── Color palette ───────────────────────────────────────────────────
@@ -79,6 +88,8 @@ fn fmt_size(bytes: u64) -> String {
 
 pub fn draw(frame: &mut Frame, app: &App) {
     let area = frame.area();
+    // Set again by the preview pane when it is on screen
+    app.preview_area.set(None);
 
     // Main layout: header + tabs + content + status
     let chunks = Layout::default()
@@ -94,15 +105,34 @@ pub fn draw(frame: &mut Frame, app: &App) {
     draw_header(frame, chunks[0], app);
     draw_tabs(frame, chunks[1], app);
 
+    // Background jobs get a pane under the content while they run
+    let jobs = app.jobs.jobs();
+    let content = if jobs.is_empty() {
+        chunks[2]
+    } else {
+        let split = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(4),
+                Constraint::Length(jobs.len() as u16 + 2),
+            ])
+            .split(chunks[2]);
+        draw_jobs(frame, split[1], app);
+        split[0]
+    };
+
     match app.state {
-        AppState::Indexing => draw_indexing(frame, chunks[2], app),
-        AppState::Init => draw_init(frame, chunks[2]),
-        _ => draw_content(frame, chunks[2], app),
+        AppState::Indexing => draw_indexing(frame, content, app),
+        AppState::Init => draw_init(frame, content),
+        AppState::Timeline => draw_timeline(frame, content, app),
+        _ => draw_content(frame, content, app),
     }
 
     draw_status_bar(frame, chunks[3], app);
 
     if app.show_help {
+        // Protocol images would cover the overlay
+        app.preview_area.set(None);
         draw_help_overlay(frame, area);
     }
 }
@@ -456,7 +486,7 @@ fn draw_file_list(frame: &mut Frame, area: Rect, app: &App) {
     frame.render_widget(list, area);
 }
 
-/// Draw the right panel: details + distribution + summary
+/// Draw the right panel: details + distribution (or image preview) + summary
 fn draw_right_panel(frame: &mut Frame, area: Rect, app: &App) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
@@ -468,10 +498,43 @@ fn draw_right_panel(frame: &mut Frame, area: Rect, app: &App) {
         .split(area);
 
     draw_file_details(frame, chunks[0], app);
-    draw_distribution(frame, chunks[1], app);
+    if app.preview.is_some() {
+        draw_preview(frame, chunks[1], app);
+    } else {
+        draw_distribution(frame, chunks[1], app);
+    }
     draw_summary_panel(frame, chunks[2], app);
 }
 
+/// Draw the thumbnail of the image under the cursor. Graphics protocols
+/// draw after the frame, into the area left blank here.
+fn draw_preview(frame: &mut Frame, area: Rect, app: &App) {
+    let Some(ref preview) = app.preview else {
+        return;
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(C_BORDER))
+        .title(Span::styled(
+            format!(" Preview ({}) ", app.graphics.label()),
+            Style::default().fg(C_BRAND),
+        ));
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    if app.graphics.is_inline() {
+        app.preview_area.set(Some(inner));
+    } else if app.graphics == GraphicsProtocol::Blocks {
+        let source = if inner.width as u32 > preview.small.width() {
+            &preview.large
+        } else {
+            &preview.small
+        };
+        let lines = block_lines(source, inner.width, inner.height);
+        frame.render_widget(Paragraph::new(lines).alignment(Alignment::Center), inner);
+    }
+}
+
 /// Draw file details panel
 fn draw_file_details(frame: &mut Frame, area: Rect, app: &App) {
     let text = if let Some(path) = app.file_tree.selected_path() {
@@ -525,7 +588,7 @@ fn draw_file_details(frame: &mut Frame, area: Rect, app: &App) {
                 lines.push(Line::from(vec![
                     Span::styled("  Date   ", Style::default().fg(C_DIM)),
                     Span::styled(
-                        modified.format("%Y-%m-%d %H:%M").to_string(),
+                        crate::core::format_timestamp(modified, "%Y-%m-%d %H:%M"),
                         Style::default().fg(C_TEXT),
                     ),
                 ]));
@@ -771,10 +834,24 @@ fn draw_export_tab(frame: &mut Frame, area: Rect, app: &App) {
             ))
         } else {
             Line::from(Span::styled(
-                "  Use CLI to export:  diamond-drill export <source> <dest>",
+                format!(
+                    "  Press x to export in the background to {}",
+                    app.out_dir.join("export").display()
+                ),
                 Style::default().fg(C_ACCENT),
             ))
         },
+        if app.selected_files.is_empty() {
+            Line::from("")
+        } else {
+            Line::from(Span::styled(
+                format!(
+                    "  or w to save, then:  diamond-drill export <source> <dest> --selection {}",
+                    app.selection_path.display()
+                ),
+                Style::default().fg(C_DIM),
+            ))
+        },
     ];
 
     let block = Block::default()
@@ -833,7 +910,31 @@ fn draw_export_tab(frame: &mut Frame, area: Rect, app: &App) {
 // ═══════════════════════════════════════════════════════════════════
 
 fn draw_carve_tab(frame: &mut Frame, area: Rect, app: &App) {
-    let text = vec![
+    if let Some(browser) = app
+        .carve_browser
+        .as_ref()
+        .filter(|b| !b.listing.files.is_empty())
+    {
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+            .split(area);
+        draw_carved_list(frame, chunks[0], browser);
+        let right = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(11), Constraint::Min(4)])
+            .split(chunks[1]);
+        draw_carved_details(frame, right[0], browser);
+        if app.preview.is_some() {
+            draw_preview(frame, right[1], app);
+        } else {
+            let lines = entropy_lines(app, right[1].width.saturating_sub(6) as usize);
+            frame.render_widget(Paragraph::new(lines), right[1]);
+        }
+        return;
+    }
+
+    let mut text = vec![
         Line::from(""),
         Line::from(Span::styled(
             "  File Carving Engine",
@@ -869,7 +970,10 @@ fn draw_carve_tab(frame: &mut Frame, area: Rect, app: &App) {
         ]),
         Line::from(""),
         Line::from(Span::styled(
-            "  To carve, use CLI:",
+            format!(
+                "  Press 'c' to carve in the background into {}, or use the CLI:",
+                app.out_dir.join("carved").display()
+            ),
             Style::default().fg(C_DIM),
         )),
         Line::from(Span::styled(
@@ -877,7 +981,31 @@ fn draw_carve_tab(frame: &mut Frame, area: Rect, app: &App) {
                 app.source.as_ref().map(|p| p.display().to_string()).unwrap_or_else(|| "<source>".into())),
             Style::default().fg(C_ACCENT),
         )),
+        Line::from(""),
     ];
+    if let Some(result) = app.carve_browser.as_ref().map(|b| &b.listing.result) {
+        let color = if result.files_failed > 0 {
+            C_WARN
+        } else {
+            C_OK
+        };
+        text.push(Line::from(vec![
+            Span::styled("  Last carve: ", Style::default().fg(C_DIM)),
+            Span::styled(
+                format!(
+                    "{} extracted of {} found ({}), {} verified, {} failed",
+                    result.files_extracted,
+                    result.files_found,
+                    fmt_size(result.total_bytes_extracted),
+                    result.files_verified,
+                    result.files_failed
+                ),
+                Style::default().fg(color),
+            ),
+        ]));
+        text.push(Line::from(""));
+    }
+    text.extend(entropy_lines(app, area.width.saturating_sub(6) as usize));
 
     let block = Block::default()
         .borders(Borders::ALL)
@@ -887,6 +1015,248 @@ fn draw_carve_tab(frame: &mut Frame, area: Rect, app: &App) {
     frame.render_widget(Paragraph::new(text).block(block), area);
 }
 
+fn boundary_color(method: BoundaryMethod) -> Color {
+    match method {
+        BoundaryMethod::InternalSize | BoundaryMethod::FooterScan => C_OK,
+        BoundaryMethod::NextHeader | BoundaryMethod::TextRun => C_WARN,
+        BoundaryMethod::MaxSizeCap => C_ERR,
+    }
+}
+
+/// Carved files by offset, with marks for re-extraction
+fn draw_carved_list(frame: &mut Frame, area: Rect, browser: &CarveBrowser) {
+    let inner_height = area.height.saturating_sub(2) as usize;
+    let start = browser
+        .cursor
+        .saturating_sub(inner_height.saturating_sub(1));
+    let items: Vec<ListItem> = browser
+        .listing
+        .files
+        .iter()
+        .enumerate()
+        .skip(start)
+        .take(inner_height)
+        .map(|(i, file)| {
+            let is_cursor = i == browser.cursor;
+            let bg = if is_cursor { C_BG_SELECT } else { Color::Reset };
+            let mark = if browser.marked.contains(&i) {
+                Span::styled(
+                    "\u{25cf} ",
+                    Style::default().fg(C_OK).add_modifier(Modifier::BOLD),
+                )
+            } else {
+                Span::styled("\u{25cb} ", Style::default().fg(C_DIM))
+            };
+            let bad = if file.bad_region_bytes > 0 { " !" } else { "" };
+            ListItem::new(Line::from(vec![
+                mark,
+                Span::styled(
+                    format!("{:#012x} ", file.offset),
+                    Style::default().fg(C_TEXT).bg(bg),
+                ),
+                Span::styled(
+                    format!("{:>9} ", fmt_size(file.size)),
+                    Style::default().fg(C_DIM).bg(bg),
+                ),
+                Span::styled(
+                    format!("{:<5} ", file.extension),
+                    Style::default().fg(ft_color(&file.file_type)).bg(bg),
+                ),
+                Span::styled(
+                    format!("{:?}", file.boundary_method),
+                    Style::default()
+                        .fg(boundary_color(file.boundary_method))
+                        .bg(bg),
+                ),
+                Span::styled(bad, Style::default().fg(C_ERR).bg(bg)),
+            ]))
+        })
+        .collect();
+
+    let title = format!(
+        " Carved ({}/{}) [Space mark] [B boundary: {}] [R re-extract] ",
+        browser.cursor + 1,
+        browser.listing.files.len(),
+        browser.boundary.label()
+    );
+    let list = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(C_BORDER_ACTIVE))
+            .title(Span::styled(title, Style::default().fg(C_BRAND))),
+    );
+    frame.render_widget(list, area);
+}
+
+/// The carved file under the cursor and the carve it came from
+fn draw_carved_details(frame: &mut Frame, area: Rect, browser: &CarveBrowser) {
+    let result = &browser.listing.result;
+    let field = |label: &str, value: String, color: Color| {
+        Line::from(vec![
+            Span::styled(format!("  {:<10}", label), Style::default().fg(C_DIM)),
+            Span::styled(value, Style::default().fg(color)),
+        ])
+    };
+    let mut text = vec![field(
+        "Source",
+        browser.listing.source.display().to_string(),
+        C_TEXT,
+    )];
+    text.push(field(
+        "Carve",
+        format!(
+            "{} extracted of {} found ({}), {} failed",
+            result.files_extracted,
+            result.files_found,
+            fmt_size(result.total_bytes_extracted),
+            result.files_failed
+        ),
+        if result.files_failed > 0 {
+            C_WARN
+        } else {
+            C_OK
+        },
+    ));
+    if let Some(file) = browser.selected() {
+        text.push(Line::from(""));
+        text.push(field(
+            "Offset",
+            format!("{:#x} ({})", file.offset, file.offset),
+            C_TEXT,
+        ));
+        text.push(field(
+            "Type",
+            format!("{} (.{})", file.signature_name, file.extension),
+            ft_color(&file.file_type),
+        ));
+        text.push(field("Size", fmt_size(file.size), C_TEXT));
+        text.push(field(
+            "Boundary",
+            format!("{:?}", file.boundary_method),
+            boundary_color(file.boundary_method),
+        ));
+        if file.bad_region_bytes > 0 {
+            text.push(field("Unreadable", fmt_size(file.bad_region_bytes), C_ERR));
+        }
+        text.push(field(
+            "BLAKE3",
+            file.hash
+                .as_deref()
+                .map(|h| h[..16.min(h.len())].to_string())
+                .unwrap_or_else(|| "-".into()),
+            C_DIM,
+        ));
+        text.push(field(
+            "Path",
+            file.path
+                .as_ref()
+                .map(|p| p.display().to_string())
+                .unwrap_or_else(|| "(not extracted)".into()),
+            C_ACCENT,
+        ));
+    }
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(C_BORDER))
+        .title(Span::styled(" Carved file ", Style::default().fg(C_BRAND)));
+    frame.render_widget(Paragraph::new(text).block(block), area);
+}
+
+fn entropy_color(class: RegionClass) -> Color {
+    match class {
+        RegionClass::Blank => C_DIM,
+        RegionClass::Data => C_OK,
+        RegionClass::Compressed => C_WARN,
+        RegionClass::Encrypted => C_ERR,
+    }
+}
+
+/// Entropy heat strip, legend, high-entropy regions and volume headers
+fn entropy_lines(app: &App, width: usize) -> Vec<Line<'static>> {
+    let Some(report) = &app.entropy_report else {
+        return vec![Line::from(Span::styled(
+            "  Press 'e' to map entropy (finds encrypted regions carving can't see into)",
+            Style::default().fg(C_DIM),
+        ))];
+    };
+
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!(
+                "  Entropy map{}",
+                if report.sampled { " (sampled)" } else { "" }
+            ),
+            Style::default().fg(C_BRAND).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(
+            std::iter::once(Span::raw("  "))
+                .chain(report.heat_strip(width).into_iter().map(|cell| {
+                    Span::styled("\u{2588}", Style::default().fg(entropy_color(cell.class)))
+                }))
+                .collect::<Vec<_>>(),
+        ),
+        Line::from(
+            std::iter::once(Span::raw("  "))
+                .chain(RegionClass::ALL.into_iter().flat_map(|class| {
+                    [
+                        Span::styled("\u{2588} ", Style::default().fg(entropy_color(class))),
+                        Span::styled(
+                            format!(
+                                "{} {}   ",
+                                class.label(),
+                                humansize::format_size(
+                                    report.class_bytes(class),
+                                    humansize::BINARY
+                                )
+                            ),
+                            Style::default().fg(C_DIM),
+                        ),
+                    ]
+                }))
+                .collect::<Vec<_>>(),
+        ),
+    ];
+
+    for header in &report.headers {
+        lines.push(Line::from(vec![
+            Span::styled("  \u{1f512} ", Style::default().fg(C_ERR)),
+            Span::styled(
+                header.kind.label(),
+                Style::default().fg(C_ERR).add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(
+                format!(" at offset {}  {}", header.offset, header.detail),
+                Style::default().fg(C_TEXT),
+            ),
+        ]));
+    }
+    for region in report
+        .regions
+        .iter()
+        .filter(|r| matches!(r.class, RegionClass::Compressed | RegionClass::Encrypted))
+        .take(8)
+    {
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("  {:<10} ", region.class.label()),
+                Style::default().fg(entropy_color(region.class)),
+            ),
+            Span::styled(
+                format!(
+                    "{} \u{2013} {}  ({}, {:.2} bits/byte)",
+                    region.start,
+                    region.end,
+                    humansize::format_size(region.len(), humansize::BINARY),
+                    region.mean_entropy
+                ),
+                Style::default().fg(C_TEXT),
+            ),
+        ]));
+    }
+    lines
+}
+
 // ═══════════════════════════════════════════════════════════════════
 //  DEDUP TAB
 // ═══════════════════════════════════════════════════════════════════
@@ -929,6 +1299,7 @@ fn draw_dedup_tab(frame: &mut Frame, area: Rect, app: &App) {
         .split(area);
 
     // Summary with colored stats
+    let (purge_files, purge_bytes) = app.dedup_purge_totals();
     let summary = vec![
         Line::from(vec![
             Span::styled("  Scanned  ", Style::default().fg(C_DIM)),
@@ -971,6 +1342,19 @@ fn draw_dedup_tab(frame: &mut Frame, area: Rect, app: &App) {
                 Style::default().fg(C_DIM),
             ),
         ]),
+        Line::from(vec![
+            Span::styled("  Purge    ", Style::default().fg(C_DIM)),
+            Span::styled(
+                format!("{} files, {}", purge_files, fmt_size(purge_bytes)),
+                Style::default().fg(C_ERR),
+            ),
+            Span::styled("  \u{2502}  ", Style::default().fg(C_BORDER)),
+            Span::styled("Kept  ", Style::default().fg(C_DIM)),
+            Span::styled(
+                format!("{}", app.dedup_keep.len()),
+                Style::default().fg(C_OK),
+            ),
+        ]),
     ];
 
     let summary_block = Block::default()
@@ -982,9 +1366,22 @@ fn draw_dedup_tab(frame: &mut Frame, area: Rect, app: &App) {
         ));
     frame.render_widget(Paragraph::new(summary).block(summary_block), chunks[0]);
 
+    // Group list on the left, text diff of the selected group on the right
+    let list_area = if app.dedup_diff.is_some() {
+        let panes = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+            .split(chunks[1]);
+        draw_dedup_diff(frame, panes[1], app);
+        panes[0]
+    } else {
+        chunks[1]
+    };
+
     // Group list with visual distinction
-    let inner_height = chunks[1].height.saturating_sub(2) as usize;
+    let inner_height = list_area.height.saturating_sub(2) as usize;
     let mut lines: Vec<Line> = Vec::new();
+    let mut selected_line = 0;
 
     for (i, group) in report.groups.iter().enumerate() {
         let kind = if group.similarity == 100 {
@@ -1005,10 +1402,18 @@ fn draw_dedup_tab(frame: &mut Frame, area: Rect, app: &App) {
             )
         };
 
+        let is_selected = i == app.dedup_selected;
+        if is_selected {
+            selected_line = lines.len();
+        }
         lines.push(Line::from(vec![
             Span::styled(
-                format!("  #{:<3} ", i + 1),
-                Style::default().fg(C_DIM),
+                format!("{}#{:<3} ", if is_selected { "\u{25B6} " } else { "  " }, i + 1),
+                if is_selected {
+                    Style::default().fg(C_BRAND).add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().fg(C_DIM)
+                },
             ),
             kind,
             Span::styled("  ", Style::default()),
@@ -1019,41 +1424,60 @@ fn draw_dedup_tab(frame: &mut Frame, area: Rect, app: &App) {
             Span::styled(" wasted", Style::default().fg(C_DIM)),
         ]));
 
-        let master_name = group
-            .master
-            .file_name()
-            .map(|n| n.to_string_lossy().to_string())
-            .unwrap_or_else(|| group.master.display().to_string());
-        lines.push(Line::from(vec![
-            Span::styled(
-                "    KEEP ",
-                Style::default().fg(C_OK).add_modifier(Modifier::BOLD),
-            ),
-            Span::styled(master_name, Style::default().fg(C_TEXT)),
-        ]));
-
-        for dup in group.duplicates.iter().take(5) {
-            let dup_name = dup
+        // The selected group lists every file so the review cursor can reach it
+        let shown = if is_selected { usize::MAX } else { 5 };
+        let files = std::iter::once(&group.master).chain(group.duplicates.iter().take(shown));
+        for (j, path) in files.enumerate() {
+            let name = path
                 .file_name()
                 .map(|n| n.to_string_lossy().to_string())
-                .unwrap_or_else(|| dup.display().to_string());
+                .unwrap_or_else(|| path.display().to_string());
+            let (tag, tag_style, name_color) = if j == 0 {
+                (
+                    "KEEP ",
+                    Style::default().fg(C_OK).add_modifier(Modifier::BOLD),
+                    C_TEXT,
+                )
+            } else if app.dedup_keep.contains(path) {
+                ("KEEP ", Style::default().fg(C_OK), C_TEXT)
+            } else {
+                ("DEL  ", Style::default().fg(C_ERR), C_DIM)
+            };
+            let under_cursor = is_selected && j == app.dedup_file;
+            let mut name_style = Style::default().fg(name_color);
+            if under_cursor {
+                name_style = name_style.bg(C_BG_SELECT);
+            }
             lines.push(Line::from(vec![
-                Span::styled("    DEL  ", Style::default().fg(C_ERR)),
-                Span::styled(dup_name, Style::default().fg(C_DIM)),
+                Span::styled(
+                    if under_cursor { "  \u{25B8} " } else { "    " },
+                    Style::default().fg(C_BRAND),
+                ),
+                Span::styled(tag, tag_style),
+                Span::styled(name, name_style),
             ]));
         }
-        if group.duplicates.len() > 5 {
+        if group.duplicates.len() > shown {
             lines.push(Line::from(Span::styled(
-                format!("    \u{2026} +{} more", group.duplicates.len() - 5),
+                format!("    \u{2026} +{} more", group.duplicates.len() - shown),
                 Style::default().fg(C_DIM),
             )));
         }
         lines.push(Line::from(""));
     }
 
+    // Keep the selected group's header in view
+    let scroll = if selected_line < app.dedup_scroll {
+        selected_line
+    } else if inner_height > 0 && selected_line >= app.dedup_scroll + inner_height {
+        selected_line + 1 - inner_height
+    } else {
+        app.dedup_scroll
+    };
+
     let visible_lines: Vec<Line> = lines
         .into_iter()
-        .skip(app.dedup_scroll)
+        .skip(scroll)
         .take(inner_height)
         .collect();
 
@@ -1061,10 +1485,93 @@ fn draw_dedup_tab(frame: &mut Frame, area: Rect, app: &App) {
         .borders(Borders::ALL)
         .border_style(Style::default().fg(C_WARN))
         .title(Span::styled(
-            " Groups [\u{2191}/\u{2193} scroll] [d refresh] ",
+            " Groups [\u{2191}/\u{2193}] [J/K file] [m master] [Space keep] [P purge] [x export] [v diff] ",
             Style::default().fg(C_DIM),
         ));
-    frame.render_widget(Paragraph::new(visible_lines).block(groups_block), chunks[1]);
+    frame.render_widget(Paragraph::new(visible_lines).block(groups_block), list_area);
+}
+
+fn draw_dedup_diff(frame: &mut Frame, area: Rect, app: &App) {
+    use crate::dedup::diff::DiffLineKind;
+
+    let Some(ref diffs) = app.dedup_diff else {
+        return;
+    };
+
+    let mut lines: Vec<Line> = Vec::new();
+    for (path, result) in diffs {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.display().to_string());
+
+        match result {
+            Err(e) => {
+                lines.push(Line::from(vec![
+                    Span::styled(" \u{2717} ", Style::default().fg(C_ERR)),
+                    Span::styled(name, Style::default().fg(C_TEXT)),
+                ]));
+                lines.push(Line::from(Span::styled(
+                    format!("   {}", e),
+                    Style::default().fg(C_DIM),
+                )));
+            }
+            Ok(diff) => {
+                lines.push(Line::from(vec![
+                    Span::styled(
+                        format!(" {} ", name),
+                        Style::default().fg(C_TEXT).add_modifier(Modifier::BOLD),
+                    ),
+                    Span::styled(
+                        format!("{}% ", diff.similarity),
+                        Style::default().fg(C_ACCENT),
+                    ),
+                    Span::styled(format!("+{} ", diff.added), Style::default().fg(C_OK)),
+                    Span::styled(format!("-{}", diff.removed), Style::default().fg(C_ERR)),
+                ]));
+                if diff.is_identical() {
+                    lines.push(Line::from(Span::styled(
+                        "   Text content is identical",
+                        Style::default().fg(C_DIM),
+                    )));
+                }
+                for hunk in &diff.hunks {
+                    lines.push(Line::from(Span::styled(
+                        hunk.header.clone(),
+                        Style::default().fg(C_ACCENT),
+                    )));
+                    for line in &hunk.lines {
+                        let (sign, color) = match line.kind {
+                            DiffLineKind::Context => (' ', C_DIM),
+                            DiffLineKind::Added => ('+', C_OK),
+                            DiffLineKind::Removed => ('-', C_ERR),
+                        };
+                        lines.push(Line::from(Span::styled(
+                            format!("{}{}", sign, line.text),
+                            Style::default().fg(color),
+                        )));
+                    }
+                }
+            }
+        }
+        lines.push(Line::from(""));
+    }
+
+    let inner_height = area.height.saturating_sub(2) as usize;
+    let visible_lines: Vec<Line> = lines
+        .into_iter()
+        .skip(app.dedup_diff_scroll)
+        .take(inner_height)
+        .collect();
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(C_ACCENT))
+        .title(Span::styled(
+            format!(" Diff #{} [[/] scroll] [Esc close] ", app.dedup_selected + 1),
+            Style::default().fg(C_DIM),
+        ));
+    frame.render_widget(Paragraph::new(visible_lines).block(block), area);
 }
 
 // ═══════════════════════════════════════════════════════════════════
@@ -1229,6 +1736,118 @@ fn draw_badsector_tab(frame: &mut Frame, area: Rect, app: &App) {
     );
 }
 
+// ═══════════════════════════════════════════════════════════════════
+//  TIMELINE — histogram of file dates, zoomable year → month → day
+// ═══════════════════════════════════════════════════════════════════
+
+fn draw_timeline(frame: &mut Frame, area: Rect, app: &App) {
+    let Some(ref timeline) = app.timeline else {
+        return;
+    };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(6), Constraint::Length(5)])
+        .split(area);
+
+    // Fit as many bars as the width allows, keeping the cursor in view
+    let buckets = timeline.buckets();
+    let inner = chunks[0].width.saturating_sub(2) as usize;
+    let bar_width = (inner / buckets.len().max(1)).saturating_sub(1).clamp(1, 8);
+    let fit = (inner / (bar_width + 1)).max(1);
+    let start = timeline
+        .cursor()
+        .saturating_sub(fit / 2)
+        .min(buckets.len().saturating_sub(fit));
+    let selected = timeline.selected();
+
+    let bars: Vec<Bar> = buckets
+        .iter()
+        .enumerate()
+        .skip(start)
+        .take(fit)
+        .map(|(i, bucket)| {
+            let color = if i == timeline.cursor() {
+                C_ACCENT
+            } else if selected.contains(&i) {
+                C_WARN
+            } else if bucket.count == 0 {
+                C_DIM
+            } else {
+                C_BRAND
+            };
+            Bar::default()
+                .value(bucket.count as u64)
+                .text_value(if bucket.count > 0 {
+                    bucket.count.to_string()
+                } else {
+                    String::new()
+                })
+                .label(Line::from(bucket.label.clone()))
+                .style(Style::default().fg(color))
+                .value_style(Style::default().fg(Color::Black).bg(color))
+        })
+        .collect();
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(C_BORDER_ACTIVE))
+        .title(Span::styled(
+            format!(
+                " Timeline \u{2014} {} by {} ({}) ",
+                timeline.span_label(),
+                timeline.resolution().label(),
+                timeline.field().label()
+            ),
+            Style::default().fg(C_BRAND).add_modifier(Modifier::BOLD),
+        ));
+    let chart = BarChart::default()
+        .block(block)
+        .data(BarGroup::default().bars(&bars))
+        .bar_width(bar_width as u16)
+        .bar_gap(1);
+    frame.render_widget(chart, chunks[0]);
+
+    let files = timeline.selected_files(&app.cached_entries);
+    let bytes: u64 = files.iter().map(|e| e.size).sum();
+    let mut lines = vec![Line::from(vec![
+        Span::styled(
+            if timeline.is_ranging() {
+                "  Range    "
+            } else {
+                "  Bucket   "
+            },
+            Style::default().fg(C_DIM),
+        ),
+        Span::styled(
+            timeline.selected_label(),
+            Style::default().fg(C_ACCENT).add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(
+            format!("   {} files  {}", files.len(), fmt_size(bytes)),
+            Style::default().fg(C_TEXT),
+        ),
+    ])];
+    if timeline.undated() > 0 {
+        lines.push(Line::from(Span::styled(
+            format!(
+                "  {} files have no {} date",
+                timeline.undated(),
+                timeline.field().label()
+            ),
+            Style::default().fg(C_WARN),
+        )));
+    }
+    lines.push(Line::from(Span::styled(
+        "  h/l:Move  Enter:Zoom in  Bksp:Zoom out  v:Range  Space:Select  c:Created/Modified  t:Back",
+        Style::default().fg(C_DIM),
+    )));
+
+    let info = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(C_BORDER));
+    frame.render_widget(Paragraph::new(lines).block(info), chunks[1]);
+}
+
 // ═══════════════════════════════════════════════════════════════════
 //  STATUS BAR — dense single line
 // ═══════════════════════════════════════════════════════════════════
@@ -1243,7 +1862,25 @@ fn draw_status_bar(frame: &mut Frame, area: Rect, app: &App) {
 
     // Calculate right-align padding
     let left_len = app.status_message.len() + 1;
-    let right_len = right_text.len();
+    let mut right_spans = throughput_spans(app);
+    if app.unreadable_regions > 0 {
+        right_spans.push(Span::styled(
+            format!(" \u{26a0} {} unreadable ", app.unreadable_regions),
+            Style::default().fg(C_ERR),
+        ));
+    }
+    if let Some(limit) = app.throttle.limit() {
+        right_spans.push(Span::styled(
+            format!(" \u{23f1} {}/s ", fmt_size(limit)),
+            Style::default().fg(C_WARN),
+        ));
+    }
+    let spark_len: usize = right_spans.iter().map(|s| s.width()).sum();
+    // Key hints give way to the sparkline on narrow terminals
+    if left_len + spark_len + right_text.len() <= area.width as usize {
+        right_spans.push(Span::styled(right_text, Style::default().fg(C_DIM)));
+    }
+    let right_len: usize = right_spans.iter().map(|s| s.width()).sum();
     let padding = (area.width as usize)
         .saturating_sub(left_len)
         .saturating_sub(right_len);
@@ -1253,20 +1890,135 @@ fn draw_status_bar(frame: &mut Frame, area: Rect, app: &App) {
         " ".repeat(padding),
         Style::default(),
     ));
-    spans.push(Span::styled(right_text, Style::default().fg(C_DIM)));
+    spans.extend(right_spans);
 
     let bar = Paragraph::new(Line::from(spans))
         .style(Style::default().bg(Color::Rgb(20, 20, 30)));
     frame.render_widget(bar, area);
 }
 
+/// Read/write/error sparklines for the last `SPARK_BUCKETS` seconds, empty
+/// when nothing has happened recently
+fn throughput_spans(app: &App) -> Vec<Span<'static>> {
+    const SPARK_BUCKETS: usize = 16;
+    if !app.throughput.is_active() {
+        return Vec::new();
+    }
+    let recent = app.throughput.recent(SPARK_BUCKETS);
+    let latest = app.throughput.latest();
+    let series = [
+        (
+            "R",
+            recent.iter().map(|s| s.read).collect::<Vec<_>>(),
+            format!("{}/s", fmt_size(latest.read)),
+            C_OK,
+        ),
+        (
+            "W",
+            recent.iter().map(|s| s.written).collect(),
+            format!("{}/s", fmt_size(latest.written)),
+            C_ACCENT,
+        ),
+        (
+            "E",
+            recent.iter().map(|s| s.errors).collect(),
+            format!("{}/s", latest.errors),
+            C_ERR,
+        ),
+    ];
+
+    let mut spans = Vec::new();
+    for (label, values, rate, color) in series {
+        spans.push(Span::styled(
+            format!(" {} ", label),
+            Style::default().fg(C_DIM),
+        ));
+        spans.push(Span::styled(
+            format!("{:>width$}", sparkline(&values), width = SPARK_BUCKETS),
+            Style::default().fg(color),
+        ));
+        spans.push(Span::styled(
+            format!(" {:<9}", rate),
+            Style::default().fg(C_TEXT),
+        ));
+    }
+    spans
+}
+
+// ═══════════════════════════════════════════════════════════════════
+//  JOBS PANE — one progress row per background job
+// ═══════════════════════════════════════════════════════════════════
+
+fn draw_jobs(frame: &mut Frame, area: Rect, app: &App) {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(C_BORDER))
+        .title(Span::styled(" Jobs ", Style::default().fg(C_BRAND)));
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let jobs = app.jobs.jobs();
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(vec![Constraint::Length(1); jobs.len()])
+        .split(inner);
+
+    for (job, row) in jobs.iter().zip(rows.iter()) {
+        let cols = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Length(16),
+                Constraint::Length(24),
+                Constraint::Min(1),
+            ])
+            .split(*row);
+
+        let (color, state) = match &job.status {
+            JobStatus::Running => (C_BRAND, format!("{:>5.0}s", job.elapsed().as_secs_f64())),
+            JobStatus::Done => (C_OK, "  done".to_string()),
+            JobStatus::Failed(_) => (C_ERR, "failed".to_string()),
+        };
+        let head = Line::from(vec![
+            Span::styled(
+                format!(" {:<7}", job.kind.label()),
+                Style::default().fg(color).add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(state, Style::default().fg(C_DIM)),
+        ]);
+        frame.render_widget(Paragraph::new(head), cols[0]);
+
+        match job.ratio() {
+            Some(ratio) => frame.render_widget(
+                LineGauge::default()
+                    .filled_style(Style::default().fg(color))
+                    .unfilled_style(Style::default().fg(C_BORDER))
+                    .ratio(ratio),
+                cols[1],
+            ),
+            None => frame.render_widget(
+                Paragraph::new(Span::styled(
+                    format!("{} so far", job.done),
+                    Style::default().fg(C_ACCENT),
+                )),
+                cols[1],
+            ),
+        }
+
+        let detail = match &job.status {
+            JobStatus::Failed(e) => Span::styled(format!(" {}", e), Style::default().fg(C_ERR)),
+            _ => Span::styled(format!(" {}", job.detail), Style::default().fg(C_DIM)),
+        };
+        frame.render_widget(Paragraph::new(detail), cols[2]);
+    }
+}
+
 // ═══════════════════════════════════════════════════════════════════
 //  HELP OVERLAY
 // ═══════════════════════════════════════════════════════════════════
 
 fn draw_help_overlay(frame: &mut Frame, area: Rect) {
     let popup_width = 58.min(area.width.saturating_sub(4));
-    let popup_height = 26.min(area.height.saturating_sub(4));
+    let popup_height = 36.min(area.height.saturating_sub(4));
     let popup_x = (area.width.saturating_sub(popup_width)) / 2;
     let popup_y = (area.height.saturating_sub(popup_height)) / 2;
     let popup_area = Rect::new(popup_x, popup_y, popup_width, popup_height);
@@ -1305,6 +2057,10 @@ fn draw_help_overlay(frame: &mut Frame, area: Rect) {
             Span::styled("    a  n  i    ", Style::default().fg(C_ACCENT)),
             Span::styled("All / None / Invert", Style::default().fg(C_TEXT)),
         ]),
+        Line::from(vec![
+            Span::styled("    w          ", Style::default().fg(C_ACCENT)),
+            Span::styled("Save selection (.ddsel)", Style::default().fg(C_TEXT)),
+        ]),
         Line::from(""),
         Line::from(Span::styled("  Actions", Style::default().fg(C_WARN))),
         Line::from(vec![
@@ -1319,10 +2075,46 @@ fn draw_help_overlay(frame: &mut Frame, area: Rect) {
             Span::styled("    d          ", Style::default().fg(C_ACCENT)),
             Span::styled("Run dedup analysis (Dedup tab)", Style::default().fg(C_TEXT)),
         ]),
+        Line::from(vec![
+            Span::styled("    v          ", Style::default().fg(C_ACCENT)),
+            Span::styled("Diff selected dedup group", Style::default().fg(C_TEXT)),
+        ]),
+        Line::from(vec![
+            Span::styled("    J K m Spc  ", Style::default().fg(C_ACCENT)),
+            Span::styled("Pick file, make master, keep", Style::default().fg(C_TEXT)),
+        ]),
+        Line::from(vec![
+            Span::styled("    P  x       ", Style::default().fg(C_ACCENT)),
+            Span::styled("Purge dupes / export masters", Style::default().fg(C_TEXT)),
+        ]),
         Line::from(vec![
             Span::styled("    b          ", Style::default().fg(C_ACCENT)),
             Span::styled("Scan bad sectors (BadSector tab)", Style::default().fg(C_TEXT)),
         ]),
+        Line::from(vec![
+            Span::styled("    e          ", Style::default().fg(C_ACCENT)),
+            Span::styled("Map entropy of source (Carve tab)", Style::default().fg(C_TEXT)),
+        ]),
+        Line::from(vec![
+            Span::styled("    c          ", Style::default().fg(C_ACCENT)),
+            Span::styled("Carve source (Carve tab)", Style::default().fg(C_TEXT)),
+        ]),
+        Line::from(vec![
+            Span::styled("    B / R      ", Style::default().fg(C_ACCENT)),
+            Span::styled("Pick boundary / re-extract", Style::default().fg(C_TEXT)),
+        ]),
+        Line::from(vec![
+            Span::styled("    x          ", Style::default().fg(C_ACCENT)),
+            Span::styled("Export selection (Export tab)", Style::default().fg(C_TEXT)),
+        ]),
+        Line::from(vec![
+            Span::styled("    t          ", Style::default().fg(C_ACCENT)),
+            Span::styled("Timeline of file dates", Style::default().fg(C_TEXT)),
+        ]),
+        Line::from(vec![
+            Span::styled("    +  -       ", Style::default().fg(C_ACCENT)),
+            Span::styled("Raise / lower I/O rate limit", Style::default().fg(C_TEXT)),
+        ]),
         Line::from(""),
         Line::from(Span::styled("  Tabs & Search", Style::default().fg(C_WARN))),
         Line::from(vec![
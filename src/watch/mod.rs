@@ -0,0 +1,361 @@
+//! Watch a staging directory while another tool is still writing to it
+//!
+//! New and changed files are indexed into the directory's index once they
+//! have stopped changing for a settle period (imaging tools write files in
+//! pieces), with thumbnails for images and a check against everything
+//! indexed so far for identical content. Files already in the directory
+//! when watching starts are picked up the same way if the index doesn't
+//! have them yet.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use notify::{EventKind, RecursiveMode, Watcher};
+use serde::Serialize;
+
+use crate::core::{DrillEngine, FileEntry, FileType};
+use crate::dedup::hash_file;
+
+/// Quiet time before a file counts as completely written
+pub const DEFAULT_SETTLE: Duration = Duration::from_secs(5);
+
+/// How often settled files are collected
+const TICK: Duration = Duration::from_millis(500);
+
+/// What to do with files that appear in the watched directory
+#[derive(Debug, Clone)]
+pub struct WatchOptions {
+    /// Directory to watch (recursively)
+    pub dir: PathBuf,
+    /// Quiet time before a file is indexed
+    pub settle: Duration,
+    /// Generate thumbnails for new images
+    pub thumbnails: bool,
+    /// Look for indexed files with the same content
+    pub dedup: bool,
+    /// Ignore files and directories whose name starts with a dot
+    pub skip_hidden: bool,
+}
+
+/// A file indexed while watching
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchedFile {
+    pub path: PathBuf,
+    pub size: u64,
+    pub file_type: FileType,
+    /// Blake3 of the contents; absent if the file could not be read
+    pub hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thumbnail: Option<PathBuf>,
+    /// An indexed file with identical content
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duplicate_of: Option<PathBuf>,
+    pub indexed_at: DateTime<Utc>,
+}
+
+/// Totals for a watch session
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct WatchSummary {
+    pub files_indexed: usize,
+    pub bytes_indexed: u64,
+    pub duplicates: usize,
+    pub duplicate_bytes: u64,
+    pub unreadable: usize,
+    /// Files still being written when watching stopped
+    pub pending: usize,
+}
+
+/// Watch `options.dir` until Ctrl-C, indexing settled files into `engine`
+/// (which must be the engine for that directory) and passing each to
+/// `on_file`.
+pub async fn watch(
+    engine: &DrillEngine,
+    options: &WatchOptions,
+    mut on_file: impl FnMut(&WatchedFile),
+) -> Result<WatchSummary> {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |event| {
+        let _ = tx.send(event);
+    })
+    .context("Failed to start file system watcher")?;
+    watcher
+        .watch(&options.dir, RecursiveMode::Recursive)
+        .with_context(|| format!("Failed to watch {}", options.dir.display()))?;
+
+    let existing = engine.get_all_entries().await;
+    let mut settler = Settler::new(options.settle);
+    let now = Instant::now();
+    for path in unindexed_files(&options.dir, &existing, options.skip_hidden) {
+        settler.touch(path, now);
+    }
+    let mut duplicates = Duplicates::new(&existing);
+    drop(existing);
+
+    let mut summary = WatchSummary::default();
+    let mut ticker = tokio::time::interval(TICK);
+    let stop = tokio::signal::ctrl_c();
+    tokio::pin!(stop);
+    loop {
+        tokio::select! {
+            _ = &mut stop => break,
+            _ = ticker.tick() => {}
+        }
+
+        let now = Instant::now();
+        while let Ok(event) = rx.try_recv() {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    tracing::warn!("Watch error: {}", e);
+                    continue;
+                }
+            };
+            for path in event.paths {
+                if options.skip_hidden && is_hidden(&options.dir, &path) {
+                    continue;
+                }
+                match event.kind {
+                    EventKind::Create(_) | EventKind::Modify(_) => settler.touch(path, now),
+                    EventKind::Remove(_) => settler.forget(&path),
+                    _ => {}
+                }
+            }
+        }
+
+        let ready = settler.ready(now);
+        if ready.is_empty() {
+            continue;
+        }
+        for entry in engine.index_files(&ready, options.thumbnails).await? {
+            let duplicate_of = if options.dedup {
+                duplicates.check(&entry)
+            } else {
+                None
+            };
+            summary.files_indexed += 1;
+            summary.bytes_indexed += entry.size;
+            if entry.has_bad_sectors {
+                summary.unreadable += 1;
+            }
+            if duplicate_of.is_some() {
+                summary.duplicates += 1;
+                summary.duplicate_bytes += entry.size;
+            }
+            on_file(&WatchedFile {
+                path: entry.path,
+                size: entry.size,
+                file_type: entry.file_type,
+                hash: entry.hash,
+                thumbnail: entry.thumbnail,
+                duplicate_of,
+                indexed_at: Utc::now(),
+            });
+        }
+    }
+
+    summary.pending = settler.len();
+    Ok(summary)
+}
+
+/// Files under `dir` the index doesn't have in their current version
+fn unindexed_files(dir: &Path, existing: &[FileEntry], skip_hidden: bool) -> Vec<PathBuf> {
+    let known: HashMap<&Path, &FileEntry> =
+        existing.iter().map(|e| (e.path.as_path(), e)).collect();
+    walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| !(skip_hidden && is_hidden(dir, e.path())))
+        .filter(|e| {
+            let Some(indexed) = known.get(e.path()) else {
+                return true;
+            };
+            e.metadata().map_or(true, |m| {
+                m.len() != indexed.size
+                    || m.modified().ok().map(DateTime::<Utc>::from) != indexed.modified
+            })
+        })
+        .map(|e| e.into_path())
+        .collect()
+}
+
+fn is_hidden(dir: &Path, path: &Path) -> bool {
+    path.strip_prefix(dir)
+        .unwrap_or(path)
+        .components()
+        .any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
+}
+
+/// Files seen changing, each held back until it has been quiet for the
+/// settle period and its size and mtime match what they were then
+pub struct Settler {
+    settle: Duration,
+    pending: HashMap<PathBuf, Pending>,
+}
+
+struct Pending {
+    last_change: Instant,
+    snapshot: Option<(u64, Option<SystemTime>)>,
+}
+
+impl Settler {
+    pub fn new(settle: Duration) -> Self {
+        Self {
+            settle,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Record that `path` changed at `now`
+    pub fn touch(&mut self, path: PathBuf, now: Instant) {
+        let snapshot = snapshot(&path);
+        self.pending.insert(
+            path,
+            Pending {
+                last_change: now,
+                snapshot,
+            },
+        );
+    }
+
+    /// Stop waiting for a file that was removed
+    pub fn forget(&mut self, path: &Path) {
+        self.pending.retain(|p, _| !p.starts_with(path));
+    }
+
+    /// Take the files that have been quiet for the settle period. One that
+    /// changed without an event (size or mtime differ) waits another period.
+    pub fn ready(&mut self, now: Instant) -> Vec<PathBuf> {
+        let mut ready = Vec::new();
+        self.pending.retain(|path, pending| {
+            if now.duration_since(pending.last_change) < self.settle {
+                return true;
+            }
+            let current = snapshot(path);
+            if current.is_none() {
+                // Gone, or a directory
+                return false;
+            }
+            if current == pending.snapshot {
+                ready.push(path.clone());
+                return false;
+            }
+            pending.snapshot = current;
+            pending.last_change = now;
+            true
+        });
+        ready.sort();
+        ready
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+fn snapshot(path: &Path) -> Option<(u64, Option<SystemTime>)> {
+    let metadata = std::fs::metadata(path).ok().filter(|m| m.is_file())?;
+    Some((metadata.len(), metadata.modified().ok()))
+}
+
+/// Indexed files by size, hashed on demand, to find identical content
+pub struct Duplicates {
+    by_size: HashMap<u64, Vec<(PathBuf, Option<String>)>>,
+}
+
+impl Duplicates {
+    pub fn new(entries: &[FileEntry]) -> Self {
+        let mut by_size: HashMap<u64, Vec<(PathBuf, Option<String>)>> = HashMap::new();
+        for entry in entries.iter().filter(|e| e.size > 0) {
+            by_size
+                .entry(entry.size)
+                .or_default()
+                .push((entry.path.clone(), entry.hash.clone()));
+        }
+        Self { by_size }
+    }
+
+    /// An earlier file with the same content as `entry`; `entry` is
+    /// remembered for later checks either way
+    pub fn check(&mut self, entry: &FileEntry) -> Option<PathBuf> {
+        let hash = entry.hash.as_deref().filter(|_| entry.size > 0)?;
+        let candidates = self.by_size.entry(entry.size).or_default();
+        candidates.retain(|(path, _)| path != &entry.path);
+        let mut found = None;
+        for (path, known) in candidates.iter_mut() {
+            if known.is_none() {
+                *known = hash_file(path)
+                    .map_err(|e| tracing::debug!("Failed to hash {}: {}", path.display(), e))
+                    .ok();
+            }
+            if known.as_deref() == Some(hash) {
+                found = Some(path.clone());
+                break;
+            }
+        }
+        candidates.push((entry.path.clone(), Some(hash.to_string())));
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settler_waits_for_writes_to_stop() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("disk.img");
+        std::fs::write(&file, b"first chunk").unwrap();
+
+        let settle = Duration::from_secs(5);
+        let start = Instant::now();
+        let mut settler = Settler::new(settle);
+        settler.touch(file.clone(), start);
+        assert!(settler.ready(start + Duration::from_secs(1)).is_empty());
+
+        // Grew without an event reaching us: wait another period
+        std::fs::write(&file, b"first chunk, second chunk").unwrap();
+        assert!(settler.ready(start + settle).is_empty());
+        assert_eq!(settler.ready(start + settle * 2), vec![file.clone()]);
+        assert!(settler.is_empty());
+
+        settler.touch(file.clone(), start);
+        settler.forget(dir.path());
+        assert!(settler.is_empty());
+    }
+
+    #[test]
+    fn test_duplicates_hash_indexed_files_on_demand() {
+        let dir = tempfile::tempdir().unwrap();
+        let entry = |name: &str, data: &[u8], hashed: bool| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, data).unwrap();
+            let mut entry = FileEntry::new(path.clone(), &std::fs::metadata(&path).unwrap());
+            if hashed {
+                entry.hash = Some(hash_file(&path).unwrap());
+            }
+            entry
+        };
+        let indexed = vec![
+            entry("a.jpg", b"photo", false),
+            entry("b.jpg", b"other", false),
+        ];
+        let mut duplicates = Duplicates::new(&indexed);
+
+        let copy = entry("copy.jpg", b"photo", true);
+        assert_eq!(duplicates.check(&copy), Some(indexed[0].path.clone()));
+        let new = entry("new.jpg", b"fresh", true);
+        assert_eq!(duplicates.check(&new), None);
+        let again = entry("again.jpg", b"fresh", true);
+        assert_eq!(duplicates.check(&again), Some(new.path.clone()));
+    }
+}
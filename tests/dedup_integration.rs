@@ -2,11 +2,14 @@
 //!
 //! Verifies the full deduplication workflow using the DrillEngine.
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tempfile::tempdir;
 
-use diamond_drill::cli::{DedupArgs, DedupKeepStrategy, DedupReportFormat};
-use diamond_drill::core::DrillEngine;
+use diamond_drill::cli::{
+    DedupAction, DedupArgs, DedupDiffArgs, DedupKeepStrategy, DedupReportFormat, ExtraHashAlgorithm,
+};
+use diamond_drill::core::{DrillEngine, HashAlgorithm};
+use diamond_drill::DedupOptions;
 
 /// Create a test environment with duplicate files
 async fn create_dedup_test_structure(base: &PathBuf) -> std::io::Result<()> {
@@ -31,6 +34,34 @@ async fn create_dedup_test_structure(base: &PathBuf) -> std::io::Result<()> {
     Ok(())
 }
 
+fn index_args(source: &Path) -> diamond_drill::cli::IndexArgs {
+    diamond_drill::cli::IndexArgs {
+        source: source.to_path_buf(),
+        resume: false,
+        index_file: None,
+        skip_hidden: false,
+        depth: None,
+        extensions: None,
+        thumbnails: false,
+        workers: None,
+        checkpoint_interval: 1000,
+        bad_sector_report: None,
+        block_size: 4096,
+        sqlite: None,
+        incremental: false,
+        verify_hash: false,
+        metadata: false,
+        report_format: None,
+        report_file: None,
+        hashset: Vec::new(),
+        repair_names: None,
+        links: Default::default(),
+        all_hardlinks: false,
+        archives: false,
+        snapshots: false,
+    }
+}
+
 #[tokio::test]
 async fn test_engine_dedup_integration() {
     let source_dir = tempdir().unwrap();
@@ -56,6 +87,18 @@ async fn test_engine_dedup_integration() {
         checkpoint_interval: 1000,
         bad_sector_report: None,
         block_size: 4096,
+        sqlite: None,
+        incremental: false,
+        verify_hash: false,
+        metadata: false,
+        report_format: None,
+        report_file: None,
+        hashset: Vec::new(),
+        repair_names: None,
+        links: Default::default(),
+        all_hardlinks: false,
+        archives: false,
+        snapshots: false,
     };
     engine.index_with_progress(&index_args).await.unwrap();
 
@@ -65,9 +108,16 @@ async fn test_engine_dedup_integration() {
         keep: DedupKeepStrategy::Oldest, // consistent strategy
         fuzzy: false,
         threshold: 85,
+        media: false,
         min_size: 1,
         purge: false, // Dry run
+        merge: None,
+        sources: Vec::new(),
+        baseline: Vec::new(),
+        missing: None,
         report: DedupReportFormat::Json,
+        hash: Vec::new(),
+        action: None,
     };
 
     // We can't easily capture stdout here to verify report content without capturing implementation,
@@ -82,9 +132,16 @@ async fn test_engine_dedup_integration() {
         keep: DedupKeepStrategy::Cleanest,
         fuzzy: true,
         threshold: 80,
+        media: false,
         min_size: 1,
         purge: false,
+        merge: None,
+        sources: Vec::new(),
+        baseline: Vec::new(),
+        missing: None,
         report: DedupReportFormat::Human,
+        hash: Vec::new(),
+        action: None,
     };
 
     engine.run_dedup(&fuzzy_args).await.unwrap();
@@ -103,9 +160,16 @@ async fn test_engine_dedup_integration() {
         keep: DedupKeepStrategy::Cleanest, // Should keep "orig.txt" (shortest/cleanest name)
         fuzzy: false,
         threshold: 85,
+        media: false,
         min_size: 1,
         purge: true, // ACTUAL DELETE
+        merge: None,
+        sources: Vec::new(),
+        baseline: Vec::new(),
+        missing: None,
         report: DedupReportFormat::Json,
+        hash: Vec::new(),
+        action: None,
     };
 
     engine.run_dedup(&purge_args).await.unwrap();
@@ -138,3 +202,104 @@ async fn test_engine_dedup_integration() {
         "Unique file should stick around"
     );
 }
+
+#[tokio::test]
+async fn test_engine_dedup_extra_hashes() {
+    let source_dir = tempdir().unwrap();
+    let source_path = source_dir.path().to_path_buf();
+    create_dedup_test_structure(&source_path).await.unwrap();
+
+    let engine = DrillEngine::new(source_path.clone()).await.unwrap();
+    engine
+        .index_with_progress(&index_args(&source_path))
+        .await
+        .unwrap();
+
+    // --hash md5 --hash sha256 goes through the engine
+    let dedup_args = DedupArgs {
+        source: source_path.clone(),
+        keep: DedupKeepStrategy::Oldest,
+        fuzzy: false,
+        threshold: 85,
+        media: false,
+        min_size: 1,
+        purge: false,
+        merge: None,
+        sources: Vec::new(),
+        baseline: Vec::new(),
+        missing: None,
+        report: DedupReportFormat::Json,
+        hash: vec![ExtraHashAlgorithm::Md5, ExtraHashAlgorithm::Sha256],
+        action: None,
+    };
+    engine.run_dedup(&dedup_args).await.unwrap();
+
+    // The exact group of three carries digests of the shared content
+    let options = DedupOptions {
+        hash_algorithms: vec![HashAlgorithm::Md5, HashAlgorithm::Sha256],
+        ..Default::default()
+    };
+    let report = diamond_drill::analyze(&engine.get_all_entries().await, &options).unwrap();
+    let group = report
+        .groups
+        .iter()
+        .find(|g| g.duplicates.len() == 2)
+        .expect("group of three identical files");
+    assert_eq!(
+        group.digests[&HashAlgorithm::Md5],
+        "e7faa48ad4fcab277902b749a7a91353"
+    );
+    assert_eq!(
+        group.digests[&HashAlgorithm::Sha256],
+        "b79f8c07798dcc75d6f288e6a620644a88a9c67e74019a57b88a5bfd918e4b0f"
+    );
+}
+
+#[tokio::test]
+async fn test_engine_dedup_diff() {
+    let source_dir = tempdir().unwrap();
+    let source_path = source_dir.path().to_path_buf();
+    create_dedup_test_structure(&source_path).await.unwrap();
+
+    let engine = DrillEngine::new(source_path.clone()).await.unwrap();
+    engine
+        .index_with_progress(&index_args(&source_path))
+        .await
+        .unwrap();
+
+    let fuzzy_args = DedupArgs {
+        source: source_path.clone(),
+        keep: DedupKeepStrategy::Cleanest,
+        fuzzy: true,
+        threshold: 80,
+        media: false,
+        min_size: 1,
+        purge: false,
+        merge: None,
+        sources: Vec::new(),
+        baseline: Vec::new(),
+        missing: None,
+        report: DedupReportFormat::Human,
+        hash: Vec::new(),
+        action: None,
+    };
+
+    // Diff a group, and reject a group number that doesn't exist
+    let diff_args = DedupArgs {
+        action: Some(DedupAction::Diff(DedupDiffArgs {
+            group: 1,
+            context: 3,
+        })),
+        ..fuzzy_args.clone()
+    };
+    engine.run_dedup(&diff_args).await.unwrap();
+
+    let missing_group_args = DedupArgs {
+        action: Some(DedupAction::Diff(DedupDiffArgs {
+            group: 99,
+            context: 3,
+        })),
+        ..fuzzy_args
+    };
+    assert!(engine.run_dedup(&missing_group_args).await.is_err());
+}
@@ -43,6 +43,18 @@ fn make_index_args(source: std::path::PathBuf) -> diamond_drill::cli::IndexArgs
         checkpoint_interval: 0,
         bad_sector_report: None,
         block_size: 4096,
+        sqlite: None,
+        incremental: false,
+        verify_hash: false,
+        metadata: false,
+        report_format: None,
+        report_file: None,
+        hashset: Vec::new(),
+        repair_names: None,
+        links: Default::default(),
+        all_hardlinks: false,
+        archives: false,
+        snapshots: false,
     }
 }
 
@@ -65,6 +77,102 @@ async fn test_engine_index_and_count() {
     assert_eq!(count, 5, "5 visible files (hidden skipped)");
 }
 
+#[tokio::test]
+async fn test_engine_incremental_index() {
+    let dir = tempdir().unwrap();
+    let source = dir.path().join("src");
+    create_test_structure(&source).await.unwrap();
+
+    let mut args = make_index_args(source.clone());
+    args.index_file = Some(dir.path().join("test.idx"));
+
+    let engine = DrillEngine::new(source.clone()).await.unwrap();
+    engine.index_with_progress(&args).await.unwrap();
+    assert!(engine.last_changes().await.is_none());
+
+    fs::write(
+        source.join("documents/notes.txt"),
+        "Notes, now a bit longer",
+    )
+    .await
+    .unwrap();
+    fs::write(source.join("photos/new.gif"), b"fake gif")
+        .await
+        .unwrap();
+    fs::remove_file(source.join("code/main.rs")).await.unwrap();
+
+    args.incremental = true;
+    let engine = DrillEngine::new(source.clone()).await.unwrap();
+    engine.index_with_progress(&args).await.unwrap();
+
+    let changes = engine.last_changes().await.expect("incremental run");
+    assert_eq!(changes.added, 1);
+    assert_eq!(changes.modified, 1);
+    assert_eq!(changes.removed, 1);
+    assert_eq!(changes.unchanged, 3);
+    assert_eq!(engine.file_count().await, 5);
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_engine_sqlite_index_streams() {
+    use diamond_drill::core::SqliteIndex;
+
+    // More files than one batch, so entries are written while scanning
+    let dir = tempdir().unwrap();
+    let source = dir.path().join("src");
+    fs::create_dir_all(&source).await.unwrap();
+    for i in 0..9000 {
+        fs::write(source.join(format!("{}.txt", i)), b"x")
+            .await
+            .unwrap();
+    }
+
+    let mut args = make_index_args(source.clone());
+    args.index_file = Some(dir.path().join("test.idx"));
+    args.sqlite = Some(dir.path().join("index.db"));
+
+    let engine = DrillEngine::new(source.clone()).await.unwrap();
+    engine.index_with_progress(&args).await.unwrap();
+
+    assert_eq!(engine.file_count().await, 0, "nothing kept in memory");
+    assert_eq!(engine.stats().await.total_files, 9000);
+    assert_eq!(engine.stats().await.total_bytes, 9000);
+    assert!(!dir.path().join("test.idx").exists());
+
+    let db = SqliteIndex::open(&dir.path().join("index.db")).unwrap();
+    assert_eq!(db.len().unwrap(), 9000);
+    assert_eq!(db.source().unwrap(), Some(source));
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_engine_sqlite_reindex_drops_removed_files() {
+    use diamond_drill::core::SqliteIndex;
+
+    let dir = tempdir().unwrap();
+    let source = dir.path().join("src");
+    create_test_structure(&source).await.unwrap();
+    let db_path = dir.path().join("index.db");
+
+    let mut args = make_index_args(source.clone());
+    args.sqlite = Some(db_path.clone());
+    let engine = DrillEngine::new(source.clone()).await.unwrap();
+    engine.index_with_progress(&args).await.unwrap();
+    assert_eq!(SqliteIndex::open(&db_path).unwrap().len().unwrap(), 5);
+
+    fs::remove_file(source.join("code/main.rs")).await.unwrap();
+    let engine = DrillEngine::new(source.clone()).await.unwrap();
+    engine.index_with_progress(&args).await.unwrap();
+
+    let db = SqliteIndex::open(&db_path).unwrap();
+    assert_eq!(db.len().unwrap(), 4);
+    assert!(db
+        .entry(&source.join("code/main.rs").to_string_lossy())
+        .unwrap()
+        .is_none());
+}
+
 // ═══════════════════════════════════════════════════════════════════
 // DrillEngine: get_all_files + get_files_by_type
 // ═══════════════════════════════════════════════════════════════════
@@ -125,6 +233,94 @@ async fn test_engine_search_fuzzy() {
     assert!(results.is_empty(), "garbage query should return empty");
 }
 
+#[tokio::test]
+async fn test_engine_search_fuzzy_translit() {
+    let dir = tempdir().unwrap();
+    create_test_structure(dir.path()).await.unwrap();
+    fs::write(dir.path().join("documents/документ.txt"), "cyrillic name")
+        .await
+        .unwrap();
+
+    let engine = DrillEngine::new(dir.path().to_path_buf()).await.unwrap();
+    engine
+        .index_with_progress(&make_index_args(dir.path().to_path_buf()))
+        .await
+        .unwrap();
+
+    let results = engine.search_fuzzy_with("dokument", false).await.unwrap();
+    assert!(results.is_empty(), "plain fuzzy should not cross scripts");
+
+    let results = engine.search_fuzzy_with("dokument", true).await.unwrap();
+    assert_eq!(results.len(), 1);
+    assert!(results[0].contains("документ"));
+}
+
+#[tokio::test]
+async fn test_engine_search_records() {
+    use clap::Parser;
+    use diamond_drill::cli::{output, OutputFormat, SearchArgs};
+
+    let dir = tempdir().unwrap();
+    create_test_structure(dir.path()).await.unwrap();
+
+    let engine = DrillEngine::new(dir.path().to_path_buf()).await.unwrap();
+    engine
+        .index_with_progress(&make_index_args(dir.path().to_path_buf()))
+        .await
+        .unwrap();
+
+    let source = dir.path().to_string_lossy().to_string();
+    let args = SearchArgs::parse_from([
+        "search",
+        source.as_str(),
+        "*.*",
+        "--search-type",
+        "glob",
+        "--file-type",
+        "image",
+    ]);
+    let entries = engine.search(&args).await.unwrap();
+    assert_eq!(entries.len(), 2, "vacation.jpg + family.png");
+    assert!(entries.iter().all(|e| e.file_type == FileType::Image));
+
+    let mut out = Vec::new();
+    output::write_entries(&mut out, &entries, OutputFormat::Json).unwrap();
+    let lines: Vec<serde_json::Value> = String::from_utf8(out)
+        .unwrap()
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines.iter().all(|v| v["file_type"] == "Image"));
+}
+
+#[tokio::test]
+async fn test_engine_search_content() {
+    use clap::Parser;
+    use diamond_drill::cli::SearchArgs;
+
+    let dir = tempdir().unwrap();
+    create_test_structure(dir.path()).await.unwrap();
+
+    let engine = DrillEngine::new(dir.path().to_path_buf()).await.unwrap();
+    engine
+        .index_with_progress(&make_index_args(dir.path().to_path_buf()))
+        .await
+        .unwrap();
+
+    let source = dir.path().to_string_lossy().to_string();
+    let args = SearchArgs::parse_from(["search", source.as_str(), "IMPORTANT notes", "--content"]);
+    let matches = engine.search_content(&args).await.unwrap();
+    assert_eq!(matches.len(), 1);
+    assert!(matches[0].path.ends_with("documents/notes.txt"));
+    assert_eq!(matches[0].offset, 5);
+    assert!(matches[0].snippet.contains("important notes"));
+
+    // Name-only hits don't count: "vacation" is a file name, not content
+    let args = SearchArgs::parse_from(["search", source.as_str(), "vacation", "--content"]);
+    assert!(engine.search_content(&args).await.unwrap().is_empty());
+}
+
 // ═══════════════════════════════════════════════════════════════════
 // DrillEngine: search_glob
 // ═══════════════════════════════════════════════════════════════════
@@ -271,6 +467,7 @@ async fn test_export_full_pipeline() {
         continue_on_error: false,
         create_manifest: true,
         dry_run: false,
+        ..Default::default()
     };
 
     let result = engine
@@ -318,6 +515,7 @@ async fn test_export_dry_run() {
         continue_on_error: false,
         create_manifest: false,
         dry_run: true,
+        ..Default::default()
     };
 
     let exporter = Exporter::new(options);
@@ -354,6 +552,14 @@ async fn test_carve_jpeg_in_synthetic_image() {
         workers: 1,
         dry_run: true,
         verify: false,
+        mapfile: None,
+        max_bytes_per_sec: None,
+        unlock: None,
+        extra_signatures: Vec::new(),
+        faststart: false,
+        text_min_len: None,
+        min_confidence: 0,
+        io: None,
     };
 
     let carver = Carver::new(opts);
@@ -392,6 +598,14 @@ async fn test_carve_mp4_sector_aligned() {
         workers: 1,
         dry_run: true,
         verify: false,
+        mapfile: None,
+        max_bytes_per_sec: None,
+        unlock: None,
+        extra_signatures: Vec::new(),
+        faststart: false,
+        text_min_len: None,
+        min_confidence: 0,
+        io: None,
     };
 
     let carver = Carver::new(opts);